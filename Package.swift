@@ -21,6 +21,10 @@ let package = Package(
         .executableTarget(
             name: "infinitty-mcp"
         ),
+        .executableTarget(
+            name: "infinitty-ptyd",
+            dependencies: ["CPty"]
+        ),
         .testTarget(
             name: "InfinittyKitTests",
             dependencies: ["InfinittyKit"]
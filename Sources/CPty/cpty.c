@@ -1,9 +1,13 @@
 #include "cpty.h"
 
+#include <IOKit/serial/ioss.h>
 #include <crt_externs.h>
+#include <errno.h>
+#include <fcntl.h>
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
+#include <termios.h>
 #include <unistd.h>
 #include <util.h>
 
@@ -90,8 +94,88 @@ pid_t cpty_spawn_shell(int *amaster, const struct winsize *ws,
     return pid;
 }
 
+pid_t cpty_spawn(int *amaster, const struct winsize *ws, const char *path,
+                 char *const argv[], char *const envp[], const char *cwd) {
+    struct winsize wsz = *ws;
+    pid_t pid = forkpty(amaster, NULL, NULL, &wsz);
+    if (pid == 0) {
+        if (cwd && *cwd) {
+            (void)chdir(cwd);
+        }
+        execve(path, argv, envp);
+        _exit(127);
+    }
+    return pid;
+}
+
 int cpty_set_winsize(int fd, unsigned short rows, unsigned short cols,
                      unsigned short xpixel, unsigned short ypixel) {
     struct winsize ws = {rows, cols, xpixel, ypixel};
     return ioctl(fd, TIOCSWINSZ, &ws);
 }
+
+static int standard_rate(unsigned long baud) {
+    switch (baud) {
+    case 300: case 600: case 1200: case 2400: case 4800: case 9600:
+    case 19200: case 38400: case 57600: case 115200: case 230400:
+        return 1;
+    default:
+        return 0;
+    }
+}
+
+int cpty_open_serial(const char *path, unsigned long baud, int data_bits,
+                     char parity, int stop_bits, int flow) {
+    int fd = open(path, O_RDWR | O_NOCTTY | O_NONBLOCK | O_CLOEXEC);
+    if (fd < 0) {
+        return -1;
+    }
+    struct termios t;
+    /* TIOCEXCL keeps a second screen/minicom from opening it under us. */
+    if (ioctl(fd, TIOCEXCL) < 0 || tcgetattr(fd, &t) < 0) {
+        goto fail;
+    }
+    cfmakeraw(&t);
+    t.c_cflag &= ~(CSIZE | PARENB | PARODD | CSTOPB | CRTSCTS);
+    t.c_cflag |= CREAD | CLOCAL;
+    switch (data_bits) {
+    case 5: t.c_cflag |= CS5; break;
+    case 6: t.c_cflag |= CS6; break;
+    case 7: t.c_cflag |= CS7; break;
+    default: t.c_cflag |= CS8; break;
+    }
+    if (parity == 'e') {
+        t.c_cflag |= PARENB;
+    } else if (parity == 'o') {
+        t.c_cflag |= PARENB | PARODD;
+    }
+    if (stop_bits == 2) {
+        t.c_cflag |= CSTOPB;
+    }
+    t.c_iflag &= ~(IXON | IXOFF | IXANY);
+    if (flow == 1) {
+        t.c_cflag |= CRTSCTS;
+    } else if (flow == 2) {
+        t.c_iflag |= IXON | IXOFF;
+    }
+    t.c_cc[VMIN] = 1;
+    t.c_cc[VTIME] = 0;
+    cfsetspeed(&t, standard_rate(baud) ? (speed_t)baud : B9600);
+    if (tcsetattr(fd, TCSANOW, &t) < 0) {
+        goto fail;
+    }
+    if (!standard_rate(baud)) {
+        speed_t speed = (speed_t)baud;
+        if (ioctl(fd, IOSSIOSPEED, &speed) < 0) {
+            goto fail;
+        }
+    }
+    tcflush(fd, TCIOFLUSH);
+    return fd;
+
+fail:;
+    int saved = errno;
+    close(fd);
+    errno = saved;
+    return -1;
+}
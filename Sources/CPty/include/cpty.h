@@ -12,8 +12,20 @@
 pid_t cpty_spawn_shell(int *amaster, const struct winsize *ws,
                        const char *socket_path, const char *cwd);
 
+/// Fork `path` on a fresh pty with exactly `argv` and `envp` (both NULL
+/// terminated, built by the caller); otherwise as cpty_spawn_shell.
+pid_t cpty_spawn(int *amaster, const struct winsize *ws, const char *path,
+                 char *const argv[], char *const envp[], const char *cwd);
+
 /// Update the pty's window size (drives SIGWINCH in the child).
 int cpty_set_winsize(int fd, unsigned short rows, unsigned short cols,
                      unsigned short xpixel, unsigned short ypixel);
 
+/// Open a serial device raw and exclusively at the given settings: parity
+/// 'n', 'e' or 'o', flow 0 (none), 1 (RTS/CTS) or 2 (XON/XOFF). Rates
+/// termios doesn't know are set with IOSSIOSPEED. Returns a non-blocking,
+/// close-on-exec fd, or -1 with errno set.
+int cpty_open_serial(const char *path, unsigned long baud, int data_bits,
+                     char parity, int stop_bits, int flow);
+
 #endif
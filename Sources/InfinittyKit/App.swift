@@ -1,4 +1,5 @@
 import AppKit
+import Carbon
 
 private enum TerminalWindowRole {
     case standard
@@ -30,12 +31,19 @@ struct PaneDividerKeyframe {
 
 /// `NSSplitView.setPosition` is not implicitly animatable. Drive the divider
 /// model values directly so panes physically displace one another every frame.
+/// Ticks ride the window's display link when one is available, so each split
+/// (and every embedded web view inside it) relays out at most once per frame
+/// with only the latest geometry, rather than on a free-running 120 Hz timer.
 final class PaneDividerAnimation {
     private let keyframes: [PaneDividerKeyframe]
     private let duration: TimeInterval
     private let completion: () -> Void
     private var timer: Timer?
+    private var displayLink: CADisplayLink?
     private var startedAt: TimeInterval = 0
+    /// Last positions written per split. A frame that would not move a split
+    /// skips `setPosition` entirely, so no redundant layout pass runs.
+    private var applied: [ObjectIdentifier: [CGFloat]] = [:]
 
     init(
         keyframes: [PaneDividerKeyframe], duration: TimeInterval,
@@ -50,6 +58,13 @@ final class PaneDividerAnimation {
         cancel()
         startedAt = ProcessInfo.processInfo.systemUptime
         apply(progress: 0)
+        if let host = keyframes.lazy.compactMap({ $0.split.window?.contentView }).first {
+            let proxy = PaneDividerFrameProxy { [weak self] in self?.tick() }
+            let link = host.displayLink(target: proxy, selector: #selector(PaneDividerFrameProxy.frame(_:)))
+            link.add(to: .main, forMode: .common)
+            displayLink = link
+            return
+        }
         let timer = Timer(timeInterval: 1.0 / 120.0, repeats: true) { [weak self] _ in
             self?.tick()
         }
@@ -60,6 +75,8 @@ final class PaneDividerAnimation {
     func cancel() {
         timer?.invalidate()
         timer = nil
+        displayLink?.invalidate()
+        displayLink = nil
     }
 
     private func tick() {
@@ -79,9 +96,14 @@ final class PaneDividerAnimation {
         for keyframe in keyframes
         where keyframe.split.superview != nil
             && keyframe.start.count == keyframe.end.count {
-            for index in keyframe.start.indices {
+            let positions = keyframe.start.indices.map { index -> CGFloat in
                 let start = keyframe.start[index]
-                let position = start + (keyframe.end[index] - start) * progress
+                return start + (keyframe.end[index] - start) * progress
+            }
+            let key = ObjectIdentifier(keyframe.split)
+            guard applied[key] != positions else { continue }
+            applied[key] = positions
+            for (index, position) in positions.enumerated() {
                 keyframe.split.setPosition(position, ofDividerAt: index)
             }
         }
@@ -90,6 +112,18 @@ final class PaneDividerAnimation {
     deinit { cancel() }
 }
 
+/// Display-link target. CADisplayLink retains its target, so the animation
+/// sits behind this weak trampoline and `cancel()` breaks the link.
+private final class PaneDividerFrameProxy: NSObject {
+    private let onFrame: () -> Void
+
+    init(_ onFrame: @escaping () -> Void) {
+        self.onFrame = onFrame
+    }
+
+    @objc func frame(_ link: CADisplayLink) { onFrame() }
+}
+
 private final class UtilityPanelRecord {
     /// Files and Chat use the shared code/chat controller. Browser is a
     /// native WebKit surface with its own controller, but both remain pane
@@ -97,6 +131,7 @@ private final class UtilityPanelRecord {
     let controller: CodeViewController?
     let browser: BrowserPaneController?
     let surface: SurfacePaneController?
+    let notes: WorkspaceNotesViewController?
     let pane: UtilityPaneView
     /// Stable pane-ledger identity. Files and Chat are per-window singletons
     /// and keep their kind name; each Browser instance gets a unique
@@ -112,6 +147,7 @@ private final class UtilityPanelRecord {
         self.controller = controller
         self.browser = nil
         self.surface = nil
+        self.notes = nil
         self.pane = pane
         self.ledgerID = ledgerID
     }
@@ -120,6 +156,7 @@ private final class UtilityPanelRecord {
         self.controller = nil
         self.browser = browser
         self.surface = nil
+        self.notes = nil
         self.pane = pane
         self.ledgerID = ledgerID
     }
@@ -128,6 +165,16 @@ private final class UtilityPanelRecord {
         self.controller = nil
         self.browser = nil
         self.surface = surface
+        self.notes = nil
+        self.pane = pane
+        self.ledgerID = ledgerID
+    }
+
+    init(notes: WorkspaceNotesViewController, pane: UtilityPaneView, ledgerID: String) {
+        self.controller = nil
+        self.browser = nil
+        self.surface = nil
+        self.notes = notes
         self.pane = pane
         self.ledgerID = ledgerID
     }
@@ -137,28 +184,21 @@ private final class UtilityPanelRecord {
 /// sheet. This token makes completion/cancellation one-way, so a late Allow
 /// cannot execute an abandoned browser operation.
 private final class BrowserControlOperation {
-    private let lock = NSLock()
-    private var ended = false
+    private let ended = LockedState(false)
 
     func claimCompletion() -> Bool {
-        lock.lock()
-        defer { lock.unlock() }
-        guard !ended else { return false }
-        ended = true
-        return true
+        ended.withLock { ended in
+            guard !ended else { return false }
+            ended = true
+            return true
+        }
     }
 
     func cancel() {
-        lock.lock()
-        ended = true
-        lock.unlock()
+        ended.withLock { $0 = true }
     }
 
-    var isCancelled: Bool {
-        lock.lock()
-        defer { lock.unlock() }
-        return ended
-    }
+    var isCancelled: Bool { ended.snapshot }
 }
 
 /// Manages windows, native tabs, and split panes. Every pane is a
@@ -169,7 +209,11 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         installTitlebarDoubleClickMonitor()
         installModifierHintMonitor()
         installForegroundProcessMonitor()
+        installPaneDirectoryMonitor()
+        installGitStatusMonitor()
+        installGitCredentialPrompts()
         installRepoTipMonitor()
+        installProjectAutomationMonitor()
     }
 
     /// Install a local mouse monitor that turns a native-tab double-click (or
@@ -200,6 +244,10 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         paneShortcutKeyMonitor = NSEvent.addLocalMonitorForEvents(matching: .keyDown) {
             [weak self] event in
             guard let self else { return event }
+            if let pin = self.pinnedCommand(for: event), let s = self.focusedSession() {
+                s.pty.write(Array(pin.command.utf8) + [0x0D])
+                return nil
+            }
             if let offset = TabNavigation.cycleOffset(
                 keyCode: event.keyCode,
                 modifiers: event.modifierFlags),
@@ -223,6 +271,18 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
+    /// The focused pane's workspace pin bound to this key press. Only
+    /// chords with cmd, ctrl or opt are looked up, and only while some pin
+    /// has a hotkey at all.
+    private func pinnedCommand(for event: NSEvent) -> PinnedCommand? {
+        guard !event.modifierFlags.intersection([.command, .control, .option]).isEmpty,
+              PinnedCommands.shared.hasHotkeys,
+              let directory = focusedSession()?.currentDirectory()
+        else { return nil }
+        return PinnedCommands.shared.pin(
+            keyCode: event.keyCode, modifiers: event.modifierFlags, in: WorkspaceNotes.workspace(for: directory))
+    }
+
     /// True when the mouse event landed in the titlebar/tab strip of `win`,
     /// including the bare-titlebar case where there's no visible strip but
     /// the top inset of the content view occupies the same role.
@@ -249,13 +309,52 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     private var settings: SettingsWindowController?
     private let notch = NotchActivityController()
     private let appControl = AppControlServer()
+    private let launchReadiness = LaunchReadiness()
     private struct RunItem {
         let id: UUID
         let command: String
         let completion: (Int) -> Void
     }
     private var runQueues: [Int: [RunItem]] = [:] // session id -> request queue
-    private var pendingLaunchCommands: [Int: String] = [:]
+    /// Command running in each pane since its OSC 133 C marker, recorded to
+    /// `CommandHistory` (and checked for "command not found") when the D
+    /// marker brings its exit code.
+    private var runningCommands: [Int: (command: String, directory: String?, started: Date)] = [:]
+    /// Terminals taken out of every window by `detach` (or started that way
+    /// by `new-detached`), with when. They stay in `sessions` — shell,
+    /// scrollback and pane socket alive — until `attach` or the shell exits.
+    private var detachedSessions: [Int: Date] = [:]
+    /// Panes whose output `record-start` is writing to an asciicast file.
+    private var recordings: [Int: AsciicastRecorder] = [:]
+    private var broadcastGroups = InputBroadcastGroups()
+    /// Panes whose output is being scanned for triggers or links.
+    private var scannedPanes = Set<Int>()
+    /// `detect-links`: publish the links in each finished row of output.
+    private let detectingLinks = LockedState(false)
+    /// Schemes set over the socket (`link-policy`), laid over the config's
+    /// `link-schemes` until quit.
+    private var linkSchemes: [String: LinkAction] = [:]
+    /// Triggers with a `run` command still going; touched from PTY threads.
+    private let runningTriggers = LockedState(Set<String>())
+    private var timeTrackingTimer: Timer?
+    private var idleTimer: Timer?
+    private var scrollbackBudgetTimer: Timer?
+    /// Panes `scrollback-budget` has capped below their `scrollback-lines`.
+    private var scrollbackCapped = Set<Int>()
+    /// A workspace save waiting out the burst of changes that asked for it;
+    /// none once the app is quitting, so closing windows can't shrink it.
+    private var pendingWorkspaceSave: DispatchWorkItem?
+    private var savesWorkspace = true
+    /// Per pane, the activity time the idle policy last acted on, so an
+    /// idle stretch gets one action however many checks it spans.
+    private var idleHandled: [Int: Date] = [:]
+    /// A command waiting for a new pane's first prompt, and optionally who
+    /// wants its exit code (delivered through `runQueues` once typed).
+    private struct PendingLaunch {
+        let command: String
+        let completion: ((Int) -> Void)?
+    }
+    private var pendingLaunchCommands: [Int: PendingLaunch] = [:]
     private let updater = Updater()
     private var updateIndicators: [ObjectIdentifier: UpdateIndicatorView] = [:]
     private var sidebarToggleAccessories: [ObjectIdentifier: SidebarToggleAccessory] = [:]
@@ -297,7 +396,34 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     private var modifierHintMonitor: Any?
     private var paneShortcutKeyMonitor: Any?
     private var foregroundProcessObserver: NSObjectProtocol?
+    private var paneDirectoryObserver: NSObjectProtocol?
+    private var gitStatusObserver: NSObjectProtocol?
+    /// The sheets asking for git credentials, by prompt id.
+    private var gitCredentialAlerts: [String: NSAlert] = [:]
+    /// Macros being recorded, by the pane whose input they capture.
+    private var macroRecordings: [Int: LockedState<MacroRecorder>] = [:]
+    /// The directory last published as a `cwd` event, per pane.
+    private var publishedDirectories: [Int: String] = [:]
     private var repoTipObserver: NSObjectProtocol?
+    private var projectAutomationObserver: NSObjectProtocol?
+    /// The `.infinitty.toml` each pane's shell is inside, so moving around
+    /// within a project doesn't apply it again.
+    private var projectAutomationFiles: [Int: String] = [:]
+    /// Files with a trust prompt on screen.
+    private var projectAutomationPrompts: Set<String> = []
+    /// Attached `tmux -C` clients by id, and which tmux pane each native
+    /// pane shows.
+    private var tmuxClients: [String: TmuxControlClient] = [:]
+    private var nextTmuxClient = 1
+    private var tmuxBindings: [Int: (client: String, window: Int, pane: Int)] = [:]
+    /// Panes whose far end is a serial device rather than a shell.
+    private var serialConnections: [Int: SerialConnection] = [:]
+    /// Panes whose shell lives in infinitty-ptyd, by pane id.
+    private var daemonPanes: [Int: PTYDaemonPane] = [:]
+    /// Whether the app has secure event input turned on for a focused
+    /// password prompt; Enable and Disable calls must pair up.
+    private var holdsSecureEventInput = false
+    private var workspaceNotesObserver: NSObjectProtocol?
     /// Repo roots the pet has already tipped about, per session id.
     private var petTipShownRoots: [Int: Set<String>] = [:]
     /// Rotation index so revisits of a root surface a different tip.
@@ -322,14 +448,104 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             self?.handleAppRequest(request) ?? "error: shutting down"
         }
         appControl.start()
+        BackgroundTaskRegistry.shared.onEvent = { [weak self] event in
+            self?.appControl.broadcast(event)
+        }
+        launchReadiness.onReady = { [weak self] subsystem, error in
+            self?.appControl.broadcast(.subsystemReady(subsystem, error: error))
+        }
+        LogViewerRegistry.shared.onLines = { [weak self] session, lines in
+            // Chunked so one burst (a file's initial tail) can't produce a
+            // single multi-megabyte event line.
+            let rendered = session.filter.render(lines)
+            for start in stride(from: 0, to: rendered.count, by: 200) {
+                let chunk = Array(rendered[start..<min(start + 200, rendered.count)])
+                self?.appControl.broadcast(.logLines(log: session.id, lines: chunk))
+            }
+        }
+        LogViewerRegistry.shared.onEnded = { [weak self] session, reason in
+            self?.appControl.broadcast(.logEnded(log: session.id, reason: reason))
+        }
+        HostMonitor.shared.onChange = { [weak self] status in
+            self?.appControl.broadcast(.hostStatus(status))
+        }
+        HostMonitor.shared.start()
+        CommandWatcher.shared.onChange = { [weak self] change in
+            DispatchQueue.main.async {
+                let win = change.watch.pane.flatMap { self?.session(withID: $0)?.view.window }
+                self?.publish(.commandWatch(change), in: win)
+            }
+        }
+        SpeechInput.shared.onTranscript = { [weak self] transcript in
+            self?.appControl.broadcast(.speech(transcript))
+        }
+        DoNotDisturb.shared.onChange = { [weak self] snapshot in
+            self?.appControl.broadcast(.doNotDisturb(snapshot))
+        }
+        DoNotDisturb.shared.start(mode: config.doNotDisturb)
+        CommandNotifier.shared.configure(LongCommandPolicy(
+            enabled: config.notifyLongCommands, threshold: config.notifyLongCommandsAfter))
+        setLinkDetection(config.detectLinks)
+        DispatchQueue.global(qos: .utility).async { ScrollbackSpill.purgeStale() }
+        CommandNotifier.shared.onActivate = { [weak self] pane in
+            guard let self, let s = self.sessions.first(where: { $0.id == pane }) else { return }
+            NSApp.activate(ignoringOtherApps: true)
+            self.focusSession(s)
+        }
+        TimeTracker.shared.onPomodoroFinished = { [weak self] pomodoro in
+            guard let self else { return }
+            self.appControl.broadcast(.pomodoro(pomodoro))
+            if !DoNotDisturb.shared.isActive {
+                self.notch.showCustom(text: "Pomodoro done: \(pomodoro.minutes) min")
+            }
+        }
+        configureTimeTracking()
+        configureIdlePolicy()
+        configureScrollbackBudget()
+        workspaceNotesObserver = NotificationCenter.default.addObserver(
+            forName: WorkspaceNotes.didChangeNotification, object: nil, queue: .main
+        ) { [weak self] note in
+            guard let workspace = note.userInfo?[WorkspaceNotes.workspaceKey] as? String else { return }
+            self?.appControl.broadcast(.workspaceNotes(workspace: workspace))
+        }
+        SessionShareRegistry.shared.onPresence = { [weak self] host, participant, joined in
+            DispatchQueue.main.async {
+                guard let self else { return }
+                let win = self.sessions.first { $0.id == host.pane }?.view.window
+                self.publish(.shareParticipant(
+                    pane: host.pane, participant: participant.id, name: participant.name,
+                    joined: joined), in: win)
+            }
+        }
+        SessionShareRegistry.shared.onEnded = { [weak self] host, reason in
+            DispatchQueue.main.async {
+                guard let self else { return }
+                let s = self.sessions.first { $0.id == host.pane }
+                s?.setOutputTap("share", nil)
+                self.publish(.shareEnded(pane: host.pane, reason: reason), in: s?.view.window)
+            }
+        }
+        ProcessRunner.defaultTimeout = config.processTimeout
+        SearchIndexes.shared.limits = config.searchIndexLimits
+        // Nothing is live yet: everything stored is an orphan of the last
+        // run, kept (unless stale) for the workspace restore below.
+        browserSessions.reconcile(live: [])
         CodePalette.apply(config)
-        openWindow(cwd: initialWorkingDirectory)
+        // The windows, tabs and splits open at the last quit — unless the
+        // daemon kept the shells themselves, which join a fresh window as
+        // tabs once it answers.
+        var restored: [TerminalSession] = []
+        if !config.daemon, config.restoreWorkspace, initialWorkingDirectory == nil,
+           let saved = WorkspaceStore.shared.load() {
+            restored = restoreWorkspace(saved)
+        }
+        if restored.isEmpty { openWindow(cwd: initialWorkingDirectory) }
+        if config.daemon { reattachDaemonShellsAtLaunch() }
         launchCompleted = true
         watchConfigFile()
         configureQuickTerminalHotKey()
-        if config.mcpAutoRegister { _ = MCPConfiguration.registerIfNeeded() }
         configureSessionNotch()
-        if config.notch { notch.show(display: config.notchDisplay) }
+        deferLaunchWork()
         if ProcessInfo.processInfo.environment["INFINITTY_SHOW_SETTINGS"] != nil {
             openSettings(nil) // UI testing hook
         }
@@ -364,6 +580,31 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
+    /// Everything the first window can live without. CLI resolution for MCP
+    /// registration and the /tmp sweep touch the disk (and may spawn a
+    /// login shell), and the notch builds a window per screen; none of it
+    /// should hold up the first terminal.
+    private func deferLaunchWork() {
+        launchReadiness.runInBackground(.socketSweep) {
+            AppControlServer.sweepStaleSockets()
+        }
+        if config.mcpAutoRegister {
+            launchReadiness.runInBackground(.mcpRegistration) {
+                _ = MCPConfiguration.registerIfNeeded()
+            }
+        } else {
+            launchReadiness.markReady(.mcpRegistration)
+        }
+        if config.notch {
+            launchReadiness.runAfterFirstPaint(.notch) { [weak self] in
+                guard let self, self.config.notch else { return }
+                self.notch.show(display: self.config.notchDisplay)
+            }
+        } else {
+            launchReadiness.markReady(.notch)
+        }
+    }
+
     @objc func checkForUpdates(_ sender: Any?) {
         updater.check(userInitiated: true)
     }
@@ -467,6 +708,9 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     }
 
     public func applicationWillTerminate(_ notification: Notification) {
+        pendingWorkspaceSave?.cancel()
+        if savesWorkspace { WorkspaceStore.shared.save(currentWorkspace()) }
+        savesWorkspace = false
         quickTerminalHotKey = nil
         pendingTabHint?.cancel()
         pendingPaneHint?.cancel()
@@ -476,10 +720,25 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         if let repoTipObserver {
             NotificationCenter.default.removeObserver(repoTipObserver)
         }
+        if let projectAutomationObserver {
+            NotificationCenter.default.removeObserver(projectAutomationObserver)
+        }
+        if let workspaceNotesObserver {
+            NotificationCenter.default.removeObserver(workspaceNotesObserver)
+        }
+        if let paneDirectoryObserver {
+            NotificationCenter.default.removeObserver(paneDirectoryObserver)
+        }
+        if let gitStatusObserver {
+            NotificationCenter.default.removeObserver(gitStatusObserver)
+        }
         if let foregroundProcessObserver {
             NotificationCenter.default.removeObserver(foregroundProcessObserver)
         }
         appControl.stop()
+        LogViewerRegistry.shared.closeAll()
+        SessionShareRegistry.shared.stopAll()
+        PaneSnapshotServer.shared.stop()
         // `shutdown()` stops PTYs without necessarily calling `onExited`, so
         // close registered main tabs explicitly before emitting the clean end
         // marker. Otherwise a normal quit would resemble a crash in the log.
@@ -487,12 +746,19 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             closePaneLedgerTab(
                 for: win, reason: "application-terminate", origin: "application-terminate")
         }
+        // Let go of daemon shells before shutdown so they keep running.
+        daemonPanes.values.forEach { $0.detach() }
         for s in sessions { s.shutdown() }
         paneLifecycleLedger.finish()
     }
 
     public func applicationWillResignActive(_ notification: Notification) {
         setShortcutHintModifiers([])
+        updateSecureEventInput(active: false)
+    }
+
+    public func applicationDidBecomeActive(_ notification: Notification) {
+        updateSecureEventInput()
     }
 
     // MARK: - session plumbing
@@ -502,6 +768,11 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         usesSharedWindowSurface: Bool = false
     ) -> TerminalSession {
         let s = TerminalSession(config: config, scale: scale)
+        s.profile = config.defaultProfile.flatMap(TerminalProfiles.shared.profile(named:))
+        s.view.confirmsPaste = config.pasteConfirmation
+        s.view.confirmsDangerousCommands = config.confirmDangerousCommands
+        s.view.copiesOnSelect = config.copyOnSelect
+        s.view.copyTransforms = config.copyTransforms
         if usesSharedWindowSurface {
             s.renderer.setUsesSharedWindowSurface(true)
         }
@@ -510,12 +781,16 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             DispatchQueue.main.async { self?.reloadConfig() }
         }
         s.onExited = { [weak self] session in self?.sessionDidExit(session) }
+        s.launchRemotely = { [weak self] session in self?.launchInDaemon(session) }
         s.onTitleChanged = { [weak self] session in
             guard let win = session.view.window else { return }
             session.view.paneTitle = self?.paneHeaderTitle(for: session) ?? session.title
             self?.quickTerminal.setTitle(session.title, for: session)
             self?.updateTitle(for: win)
-            self?.appControl.broadcast(["event": "title", "pane": session.id, "title": session.title])
+            self?.publish(.title(pane: session.id, title: session.title), in: win)
+        }
+        s.onOutputFlood = { [weak self] session, flood in
+            self?.publish(.outputFlood(pane: session.id, flood), in: session.view.window)
         }
         s.view.onFocus = { [weak self, weak s] in
             guard let self, let s, let win = s.view.window else { return }
@@ -523,6 +798,27 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             self.quickTerminal.setFocusedSession(s)
             self.updateTitle(for: win)
             self.rebindUtilityPanels(to: s, in: win)
+            self.updateDockProgress()
+            self.updateSecureEventInput()
+        }
+        s.view.onUserInput = { [weak self, weak s] bytes in
+            guard let self, let s else { return }
+            if s.isHibernated { self.resumePane(s) }
+            let peers = self.broadcastGroups.peers(of: s.id)
+            // A password only goes to peers that are asking for one too;
+            // anywhere else it would be echoed, and maybe run.
+            let secret = s.secureInput.isActive
+            for peer in self.sessions where peers.contains(peer.id) && (!secret || peer.secureInput.isActive) {
+                peer.pty.write(bytes)
+            }
+        }
+        s.view.onSecureInput = { [weak self, weak s] in
+            guard let self, let s else { return }
+            self.showSecureFillMenu(for: s)
+        }
+        s.view.onUnlockInput = { [weak self, weak s] in
+            guard let self, let s else { return }
+            self.setInputLocked(s, false)
         }
         s.view.onPetClick = { [weak self, weak s] in
             guard let self, let s else { return }
@@ -565,6 +861,24 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             guard let self, let s else { return }
             self.togglePaneZoom(for: s)
         }
+        s.view.onPasteHeld = { [weak self, weak s] check in
+            guard let self, let s else { return }
+            self.publish(.pasteHeld(pane: s.id, check), in: s.view.window)
+        }
+        s.view.onCommandHeld = { [weak self, weak s] analysis in
+            guard let self, let s else { return }
+            self.publish(.commandHeld(pane: s.id, analysis), in: s.view.window)
+        }
+        s.view.commandDirectory = { [weak s] in s?.currentDirectory() }
+        s.view.onOpenLink = { [weak self, weak s] url in
+            guard let self, let s else { return }
+            self.openLink(url, from: s)
+        }
+        s.view.onRevealPath = { [weak self, weak s] text, target in
+            guard let self, let s else { return }
+            guard let path = RevealPath.resolve(text, cwd: s.currentDirectory()),
+                  self.reveal(path, in: target, from: s) else { NSSound.beep(); return }
+        }
         s.view.onClosePane = { [weak self, weak s] in
             guard let self, let s else { return }
             let running = ForegroundProcessTracker.runningProcesses(in: [s])
@@ -601,9 +915,24 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 if kind == UInt8(ascii: "C") {
                     s.petAnimator?.commandStarted()
                     s.processTracker?.poke()
+                    if self.config.commandHistory || self.config.commandSuggestions || self.config.timeTracking,
+                       let command, !command.isEmpty {
+                        self.runningCommands[s.id] = (command, s.currentDirectory(), Date())
+                    }
                 }
                 if kind == UInt8(ascii: "D") {
                     s.petAnimator?.commandEnded(exitCode: exit)
+                    if let running = self.runningCommands.removeValue(forKey: s.id) {
+                        if self.config.commandHistory {
+                            CommandHistory.shared.record(CommandRecord(
+                                command: running.command, directory: running.directory, exitCode: exit,
+                                started: running.started, duration: Date().timeIntervalSince(running.started),
+                                pane: s.id))
+                        }
+                        if self.config.commandSuggestions, (1...127).contains(exit) {
+                            self.suggestCorrection(running.command, exitCode: exit, in: s)
+                        }
+                    }
                     if var queue = self.runQueues[s.id], !queue.isEmpty {
                         let finishedItem = queue.removeFirst()
                         self.runQueues[s.id] = queue.isEmpty ? nil : queue
@@ -615,24 +944,521 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                     }
                     s.processTracker?.poke()
                 }
-                self.appControl.broadcast([
-                    "event": "marker", "pane": s.id,
-                    "kind": String(UnicodeScalar(kind)), "exit": exit,
-                ])
+                self.publish(.marker(pane: s.id, kind: kind, exit: exit), in: s.view.window)
+            }
+        }
+        syncOutputScan(s)
+        s.setOutputTap("progress") { [weak self, weak s] data in
+            guard let s, s.progress.ingest(data) else { return }
+            DispatchQueue.main.async { self?.progressChanged(s) }
+        }
+        s.setOutputTap("secure-input") { [weak self, weak s] data in
+            guard let s, s.secureInput.ingest(data, echo: s.pty.isRemote ? nil : TTYEcho.of(s.pty.fd)) else { return }
+            DispatchQueue.main.async { self?.secureInputChanged(s) }
+        }
+        s.terminal.onCommand = { [weak self, weak s] event in
+            DispatchQueue.main.async {
+                guard let self, let s else { return }
+                self.publish(.command(pane: s.id, event), in: s.view.window)
+                if s.progress.reset() { self.progressChanged(s) }
+                if case .finished = event, s.secureInput.reset() { self.secureInputChanged(s) }
+                if case .finished(let command, let exitCode, _, _) = event,
+                   let startup = s.startup?.finished(command: command, exitCode: exitCode), startup.failed {
+                    self.startupCommandFailed(s, startup)
+                }
+                if case .finished(let command, let exitCode, let duration, _) = event {
+                    let focused = NSApp.isActive && s.view.window?.isKeyWindow == true
+                    if CommandNotifier.shared.policy.shouldNotify(
+                        duration: duration, windowFocused: focused, quiet: DoNotDisturb.shared.isActive) {
+                        CommandNotifier.shared.post(
+                            pane: s.id, command: command, exitCode: exitCode, duration: duration)
+                    }
+                }
             }
         }
         s.onTodosChanged = { [weak self] session in
-            self?.appControl.broadcast([
-                "event": "todos", "pane": session.id,
-                "total": session.todos.count,
-                "done": session.todos.filter(\.done).count,
-            ])
+            self?.publish(.todos(
+                pane: session.id, total: session.todos.count,
+                done: session.todos.filter(\.done).count
+            ), in: session.view.window)
         }
         sessions.append(s)
-        appControl.broadcast(["event": "pane-opened", "pane": s.id])
+        publish(.paneOpened(pane: s.id), in: s.view.window)
         return s
     }
 
+    /// Tells subscribers a pane's startup command failed, and the user with
+    /// a notification: the pane may have opened in the background.
+    private func startupCommandFailed(_ s: TerminalSession, _ startup: StartupCommand) {
+        publish(.startupFailed(pane: s.id, startup), in: s.view.window)
+        guard !DoNotDisturb.shared.isActive else { return }
+        CommandNotifier.shared.post(
+            pane: s.id, title: "Startup command failed in \(s.title)",
+            body: "\(startup.command) exited with \(startup.exitCode ?? 0)")
+    }
+
+    /// Shows a pane's new progress in its header and the dock badge, and
+    /// tells subscribers. A program reporting an error while you're away
+    /// gets a notification.
+    private func progressChanged(_ s: TerminalSession) {
+        guard sessions.contains(where: { $0 === s }) else { return }
+        let progress = s.progress.current
+        if let progress, progress.state == .error, s.view.paneHeader.progress?.state != .error,
+           !(NSApp.isActive && s.view.window?.isKeyWindow == true), !DoNotDisturb.shared.isActive {
+            CommandNotifier.shared.post(
+                pane: s.id, title: "\(s.title) reported an error", body: "Stopped at \(progress.badge)")
+        }
+        s.view.paneHeader.setProgress(progress)
+        publish(.progress(pane: s.id, progress), in: s.view.window)
+        updateDockProgress()
+    }
+
+    /// Shows or hides the pane's lock and tells subscribers when it starts
+    /// or stops asking for a password.
+    private func secureInputChanged(_ s: TerminalSession) {
+        guard sessions.contains(where: { $0 === s }) else { return }
+        s.view.paneHeader.setSecureInput(s.secureInput.current)
+        publish(.secureInput(pane: s.id, s.secureInput.current), in: s.view.window)
+        updateSecureEventInput()
+    }
+
+    /// Makes the pane read-only, or writable again, and shows it in the
+    /// pane's header.
+    private func setInputLocked(_ s: TerminalSession, _ locked: Bool) {
+        guard s.pty.isInputLocked != locked else { return }
+        s.pty.isInputLocked = locked
+        s.view.paneHeader.setInputLocked(locked)
+        publish(.inputLock(pane: s.id, locked: locked), in: s.view.window)
+    }
+
+    /// While the focused pane waits for a password, macOS secure event
+    /// input keeps other processes from seeing the keystrokes, as
+    /// Terminal's Secure Keyboard Entry does.
+    private func updateSecureEventInput(active: Bool = NSApp.isActive) {
+        let wanted = active && focusedSession()?.secureInput.isActive == true
+        guard wanted != holdsSecureEventInput else { return }
+        holdsSecureEventInput = wanted
+        if wanted {
+            EnableSecureEventInput()
+        } else {
+            DisableSecureEventInput()
+        }
+    }
+
+    /// The lock's menu: the Keychain entries offered at this prompt.
+    private func showSecureFillMenu(for s: TerminalSession) {
+        guard let prompt = s.secureInput.current else { return }
+        let menu = NSMenu(title: "Secure Input")
+        let entries = PromptPasswords.entries(matching: prompt.prompt)
+        for entry in entries {
+            let item = NSMenuItem(
+                title: "Fill “\(entry.account)” from Keychain", action: #selector(secureFillSelected(_:)),
+                keyEquivalent: "")
+            item.target = self
+            item.representedObject = (pane: s.id, account: entry.account)
+            menu.addItem(item)
+        }
+        if entries.isEmpty {
+            let item = NSMenuItem(title: "No Keychain entries for this prompt", action: nil, keyEquivalent: "")
+            item.isEnabled = false
+            menu.addItem(item)
+        }
+        let anchor = s.view.paneHeader.secureAnchorView
+        menu.popUp(positioning: nil, at: NSPoint(x: 0, y: anchor.bounds.maxY + 2), in: anchor)
+    }
+
+    @objc private func secureFillSelected(_ sender: NSMenuItem) {
+        guard let target = sender.representedObject as? (pane: Int, account: String),
+              let s = session(withID: target.pane) else { return }
+        if fillPromptPassword(in: s, account: target.account) != nil { NSSound.beep() }
+    }
+
+    /// Types a Keychain secret and Return into `s`, which must be at a
+    /// password prompt; `account` defaults to the first entry offered
+    /// there. Returns why not, if it couldn't.
+    private func fillPromptPassword(in s: TerminalSession, account: String?) -> String? {
+        guard let prompt = s.secureInput.current else { return "pane \(s.id) isn't asking for a password" }
+        guard let account = account ?? PromptPasswords.entries(matching: prompt.prompt).first?.account else {
+            return "no Keychain entry for \"\(prompt.prompt)\""
+        }
+        guard let password = PromptPasswords.password(for: account) else {
+            return "no Keychain password for \(account)"
+        }
+        s.pty.write(Array(password.utf8) + [0x0D])
+        return nil
+    }
+
+    /// The dock badge follows the focused pane's progress, or failing that
+    /// the pane whose progress moved last.
+    private func updateDockProgress() {
+        let reporting = sessions.filter { $0.progress.current != nil }
+        let shown = focusedSession().flatMap { focused in reporting.first { $0 === focused } }
+            ?? reporting.max { ($0.progress.updated ?? .distantPast) < ($1.progress.updated ?? .distantPast) }
+        NSApp.dockTile.badgeLabel = shown?.progress.current?.badge
+    }
+
+    /// Look up fixes for a command that just failed, off the main thread
+    /// (history and the Homebrew index are files), and publish them as a
+    /// `pane-suggestion` event when there are any: for exit 127 with the
+    /// shell's "not found", a near-miss program or an install; for other
+    /// failures, a mistyped subcommand.
+    private func suggestCorrection(_ command: String, exitCode: Int, in s: TerminalSession) {
+        DispatchQueue.global(qos: .utility).async { [weak self, weak s] in
+            guard let s else { return }
+            let history = CommandHistory.shared.records()
+            let program: String
+            let suggestions: [CommandSuggestion]
+            if exitCode == 127 {
+                guard let missing = MissingCommand.program(
+                    in: command, exitCode: exitCode, output: s.terminal.lastCommandOutput() ?? "")
+                else { return }
+                program = missing
+                suggestions = MissingCommand.suggestions(
+                    for: missing, in: command, known: Self.knownPrograms(history: history),
+                    formulae: Self.formulae(providing: missing))
+            } else {
+                program = CommandRecord.program(of: command)
+                suggestions = MissingCommand.subcommandCorrections(in: command, history: history)
+                    .map { CommandSuggestion(kind: .didYouMean, command: $0) }
+            }
+            guard !suggestions.isEmpty else { return }
+            DispatchQueue.main.async {
+                self?.publish(.paneSuggestion(
+                    pane: s.id, command: command, program: program, suggestions: suggestions
+                ), in: s.view.window)
+            }
+        }
+    }
+
+    /// Programs a correction may suggest: history, executables on disk and
+    /// the shell's aliases. Reads files; keep off the main thread.
+    private static func knownPrograms(history: [CommandRecord]) -> [String: Int] {
+        MissingCommand.knownPrograms(
+            history: history, directories: MissingCommand.searchDirectories, aliases: MissingCommand.shellAliases())
+    }
+
+    private static func formulae(providing program: String) -> [String] {
+        PackageManager.detect()?.manager == .homebrew ? HomebrewIndex.shared.formulae(providing: program) : []
+    }
+
+    /// Ticks `TimeTracker` every 30s while `time-tracking` is on.
+    private func configureTimeTracking() {
+        TimeTracker.shared.idleThreshold = config.timeTrackingIdle
+        guard config.timeTracking else {
+            timeTrackingTimer?.invalidate()
+            timeTrackingTimer = nil
+            return
+        }
+        guard timeTrackingTimer == nil else { return }
+        let timer = Timer(timeInterval: TimeTracker.tickInterval, repeats: true) { [weak self] _ in
+            self?.trackTime()
+        }
+        timer.tolerance = 5
+        RunLoop.main.add(timer, forMode: .common)
+        timeTrackingTimer = timer
+    }
+
+    /// Time counts toward the focused pane's repo while infinitty is
+    /// frontmost; commands still running keep their workspace's clock going
+    /// through keyboard idleness.
+    private func trackTime() {
+        for running in runningCommands.values {
+            guard let directory = running.directory else { continue }
+            TimeTracker.shared.noteCommand(in: TimeTracker.workspace(for: directory))
+        }
+        let session = NSApp.keyWindow.flatMap { focusedSession(in: $0) ?? activeSessions(in: $0).first }
+        let directory = NSApp.isActive ? session?.currentDirectory() : nil
+        let anyInput = CGEventType(rawValue: ~0) ?? .null
+        let idle = CGEventSource.secondsSinceLastEventType(.combinedSessionState, eventType: anyInput)
+        TimeTracker.shared.record(workspace: directory.map { TimeTracker.workspace(for: $0) }, idle: idle)
+    }
+
+    /// Checks panes against `idle-timeout` every minute while it's set.
+    private func configureIdlePolicy() {
+        guard config.idlePolicy.isEnabled else {
+            idleTimer?.invalidate()
+            idleTimer = nil
+            return
+        }
+        guard idleTimer == nil else { return }
+        let timer = Timer(timeInterval: 60, repeats: true) { [weak self] _ in
+            self?.checkIdlePanes()
+        }
+        timer.tolerance = 10
+        RunLoop.main.add(timer, forMode: .common)
+        idleTimer = timer
+    }
+
+    /// No OSC 133 command running and nothing but the shell in the
+    /// foreground.
+    private func isAtPrompt(_ s: TerminalSession) -> Bool {
+        guard runningCommands[s.id] == nil else { return false }
+        guard let process = s.processTracker?.current else { return true }
+        return process.pid == s.pty.pid
+    }
+
+    /// The pane in front of the user is never idle, however long it sat.
+    private func checkIdlePanes(now: Date = Date()) {
+        let policy = config.idlePolicy
+        let inFront = NSApp.isActive ? NSApp.keyWindow.flatMap { focusedSession(in: $0) } : nil
+        for s in sessions where s !== inFront && !s.isHibernated {
+            let last = s.pty.lastActivity
+            guard policy.isDue(lastActivity: last, atPrompt: isAtPrompt(s), handled: idleHandled[s.id], now: now)
+            else { continue }
+            idleHandled[s.id] = last
+            actOnIdlePane(s, action: policy.action, idleSeconds: now.timeIntervalSince(last))
+        }
+    }
+
+    /// Panes whose shell isn't this process's own (the PTY daemon's, tmux's)
+    /// can't hibernate; they're only notified about.
+    private func actOnIdlePane(_ s: TerminalSession, action: IdleAction, idleSeconds: TimeInterval) {
+        let idle = idleSeconds >= 3600 ? "\(Int(idleSeconds / 3600))h" : "\(Int(idleSeconds / 60))m"
+        let notify = { (title: String, body: String) in
+            guard !DoNotDisturb.shared.isActive else { return }
+            CommandNotifier.shared.post(pane: s.id, title: title, body: body)
+        }
+        switch action {
+        case .hibernate where hibernatePane(s, idleSeconds: idleSeconds):
+            notify("Hibernated \(s.title)", "Idle for \(idle); its shell was ended. Press a key in it for a new one.")
+        case .close:
+            // Rows are copied here; the HTML is built and written off the
+            // main thread, then the pane closes.
+            let rows = s.terminal.historyRows(lines: 100_000)
+            let theme = Theme.dark.applying(config)
+            let title = s.paneTitleOverride ?? s.title
+            DispatchQueue.global(qos: .utility).async { [weak self] in
+                let html = PaneSnapshot.html(rows: rows, theme: theme, title: title)
+                let saved = IdleSnapshots.save(html, pane: s.id)
+                DispatchQueue.main.async {
+                    guard let self, self.sessions.contains(where: { $0 === s }) else { return }
+                    self.publish(.paneIdle(
+                        pane: s.id, action: .close, idleSeconds: idleSeconds, snapshot: saved?.path
+                    ), in: s.view.window)
+                    notify("Closed \(title)", "Idle for \(idle)." + (saved.map { " Snapshot: \($0.path)" } ?? ""))
+                    if let win = s.view.window {
+                        self.recordPaneLedgerNote(
+                            in: win, paneID: self.paneLedgerTerminalID(s), reason: "idle-close",
+                            origin: "idle-policy")
+                    }
+                    s.terminate()
+                }
+            }
+        default:
+            publish(.paneIdle(pane: s.id, action: .notify, idleSeconds: idleSeconds, snapshot: nil), in: s.view.window)
+            notify("\(s.title) is idle", "Nothing has happened in it for \(idle).")
+        }
+    }
+
+    @discardableResult
+    private func hibernatePane(_ s: TerminalSession, idleSeconds: TimeInterval) -> Bool {
+        guard s.hibernate() else { return false }
+        publish(.paneIdle(pane: s.id, action: .hibernate, idleSeconds: idleSeconds, snapshot: nil), in: s.view.window)
+        return true
+    }
+
+    @discardableResult
+    private func resumePane(_ s: TerminalSession) -> Bool {
+        guard s.resume() else { return false }
+        idleHandled[s.id] = nil
+        publish(.paneResumed(pane: s.id), in: s.view.window)
+        return true
+    }
+
+    /// Holds panes' scrollback to `scrollback-budget`, checked every 15s
+    /// while one is set.
+    private func configureScrollbackBudget() {
+        guard config.scrollbackBudget > 0 else {
+            scrollbackBudgetTimer?.invalidate()
+            scrollbackBudgetTimer = nil
+            releaseScrollbackCaps()
+            return
+        }
+        enforceScrollbackBudget()
+        guard scrollbackBudgetTimer == nil else { return }
+        let timer = Timer(timeInterval: 15, repeats: true) { [weak self] _ in
+            self?.enforceScrollbackBudget()
+        }
+        timer.tolerance = 5
+        RunLoop.main.add(timer, forMode: .common)
+        scrollbackBudgetTimer = timer
+    }
+
+    /// Over budget, the panes holding the most are capped to their share
+    /// and start spilling; well under it, the caps come off again.
+    private func enforceScrollbackBudget() {
+        let budget = config.scrollbackBudget
+        let usage = Dictionary(uniqueKeysWithValues: sessions.map { ($0.id, $0.terminal.scrollbackUsage) })
+        guard let caps = ScrollbackBudget.caps(usage: usage.mapValues { $0.bytes }, budget: budget) else {
+            let total = usage.values.reduce(0) { $0 + $1.bytes }
+            if Double(total) < Double(budget) * ScrollbackBudget.releaseRatio { releaseScrollbackCaps() }
+            return
+        }
+        for s in sessions {
+            guard let cap = caps[s.id], let held = usage[s.id] else { continue }
+            let lines = ScrollbackBudget.lines(forCap: cap, bytes: held.bytes, lines: held.lines)
+            guard lines < held.lines else { continue }
+            s.setScrollbackSpill(true)
+            s.terminal.capScrollback(lines: lines)
+            scrollbackCapped.insert(s.id)
+        }
+    }
+
+    private func releaseScrollbackCaps() {
+        for s in sessions where scrollbackCapped.contains(s.id) { s.terminal.capScrollback(lines: nil) }
+        scrollbackCapped.removeAll()
+    }
+
+    // MARK: - workspace
+
+    /// Every standard window's tabs, splits and terminal panes as they are
+    /// now, back to front so a restore leaves the front window in front.
+    private func currentWorkspace() -> WorkspaceLayout {
+        var seen = Set<ObjectIdentifier>()
+        var windows: [WorkspaceWindow] = []
+        for win in NSApp.orderedWindows.reversed()
+        where win.tabbingIdentifier == "infinitty" && win !== quickTerminal.window {
+            guard !seen.contains(ObjectIdentifier(win)) else { continue }
+            let group = win.tabbedWindows ?? [win]
+            group.forEach { seen.insert(ObjectIdentifier($0)) }
+            var tabs: [WorkspaceTab] = []
+            var selectedTab: Int?
+            for tab in group {
+                var leaves: [NSView] = []
+                guard let root = terminalRoot(of: tab),
+                      let shape = LayoutShape.capture(root, leaves: &leaves, isPane: { $0 is TerminalView })
+                else { continue }
+                let panes = leaves.map { view -> WorkspacePane in
+                    let s = sessions.first { $0.view === view }
+                    let display = s.flatMap { $0.display.isDefault ? nil : $0.display }
+                    return WorkspacePane(cwd: s?.currentDirectory(), profile: s?.profile?.name, display: display)
+                }
+                let focused = focusedPaneLeaf(in: tab).flatMap { leaf in leaves.firstIndex { $0 === leaf } }
+                if tab === (win.tabGroup?.selectedWindow ?? win) { selectedTab = tabs.count }
+                let browsers = utilityRecords(in: tab).compactMap { workspaceBrowser($0, terminals: leaves) }
+                tabs.append(WorkspaceTab(
+                    shape: shape, panes: panes, focused: focused, browsers: browsers.isEmpty ? nil : browsers))
+            }
+            guard !tabs.isEmpty else { continue }
+            let frame = win.frame
+            windows.append(WorkspaceWindow(
+                frame: [frame.origin.x, frame.origin.y, frame.width, frame.height].map { Double($0) },
+                tabs: tabs, selectedTab: selectedTab))
+        }
+        return WorkspaceLayout(windows: windows)
+    }
+
+    /// A browser pane as the workspace keeps it: its id and page, and the
+    /// split it shares with one of `terminals` — the nearest before it, else
+    /// after. Nil for a pane that isn't split off a terminal.
+    private func workspaceBrowser(_ record: UtilityPanelRecord, terminals: [NSView]) -> WorkspaceBrowser? {
+        guard let browser = record.browser,
+              let split = record.pane.superview as? NSSplitView,
+              let index = split.arrangedSubviews.firstIndex(of: record.pane) else { return nil }
+        let siblings = split.arrangedSubviews
+        let nearest = Array(siblings.indices[..<index].reversed()) + Array(siblings.indices[(index + 1)...])
+        func terminal(in view: NSView) -> Int? { terminals.firstIndex { $0.isDescendant(of: view) } }
+        guard let position = nearest.first(where: { terminal(in: siblings[$0]) != nil }),
+              let beside = terminal(in: siblings[position]) else { return nil }
+        let url = (browser.controlState()["url"] as? String).flatMap { $0.isEmpty ? nil : $0 }
+            ?? browserSessions.record(browser.browserID)?.url
+        guard let url else { return nil }
+        let length = { (view: NSView) in Double(split.isVertical ? view.frame.width : view.frame.height) }
+        let total = siblings.map(length).reduce(0, +)
+        return WorkspaceBrowser(
+            id: browser.browserID, url: url, beside: beside, vertical: split.isVertical,
+            leading: position > index ? true : nil,
+            share: total > 0 ? length(record.pane) / total : nil)
+    }
+
+    /// Reopens a saved browser pane beside `terminal` under its old id, on
+    /// the page it was last on and at its old share of the split.
+    private func restoreBrowser(_ saved: WorkspaceBrowser, beside terminal: NSView, in win: NSWindow) {
+        guard let record = openUtilityPanel(
+                  .browser, in: win, relativeTo: terminal, vertical: saved.vertical, forceNewInstance: true,
+                  browserID: saved.id, newFirst: saved.leading ?? false),
+              let browser = record.browser else { return }
+        let location = browserSessions.record(saved.id) ?? BrowserPaneSessionStore.Record(
+            browserID: browser.browserID, url: saved.url, title: "", viewport: .desktop,
+            updatedAt: Date().timeIntervalSince1970)
+        if BrowserPaneController.normalizedURL(location.url) != nil { browser.restore(location) }
+        if let share = saved.share { setShare(of: record.pane, to: share, publishes: false) }
+    }
+
+    /// Saves the workspace a couple of seconds after the change that asked,
+    /// once for a burst of them.
+    private func scheduleWorkspaceSave() {
+        guard savesWorkspace, launchCompleted, pendingWorkspaceSave == nil else { return }
+        let work = DispatchWorkItem { [weak self] in
+            guard let self else { return }
+            self.pendingWorkspaceSave = nil
+            if self.savesWorkspace { WorkspaceStore.shared.save(self.currentWorkspace()) }
+        }
+        pendingWorkspaceSave = work
+        DispatchQueue.main.asyncAfter(deadline: .now() + 2, execute: work)
+    }
+
+    /// Rebuilds a saved workspace's windows alongside any already open:
+    /// each tab's first pane opens it in its directory with its profile and
+    /// `applyLayout` adds the rest in reading order. Returns the panes
+    /// started.
+    @discardableResult
+    private func restoreWorkspace(_ layout: WorkspaceLayout) -> [TerminalSession] {
+        let profiles = TerminalProfiles.shared
+        var started: [TerminalSession] = []
+        var front: NSWindow?
+        for saved in layout.restorable.windows {
+            var host: NSWindow?
+            var selected: NSWindow?
+            for (index, tab) in saved.tabs.enumerated() {
+                let first = tab.panes[0]
+                let (win, s) = makeTerminalWindow(
+                    cwd: first.cwd, profile: first.profile.flatMap(profiles.profile(named:)))
+                if let host {
+                    host.addTabbedWindow(win, ordered: .above)
+                } else if let frame = saved.frame, frame.count == 4 {
+                    win.setFrame(NSRect(x: frame[0], y: frame[1], width: frame[2], height: frame[3]), display: false)
+                }
+                recordPaneLedgerNote(
+                    in: win, reason: host == nil ? "tab-presented" : "tab-joined", origin: "workspace-restore")
+                host = host ?? win
+                if index == (saved.selectedTab ?? 0) { selected = win }
+                win.orderFront(nil)
+                if let display = first.display { setDisplay(display, for: s) }
+                s.launch()
+                started.append(s)
+                var added: [TerminalSession] = []
+                if tab.panes.count > 1 {
+                    _ = applyLayout(tab.shape, in: win) { session, position in
+                        let pane = tab.panes[position + 1]
+                        if let cwd = pane.cwd { session.workingDirectory = cwd }
+                        if let profile = pane.profile.flatMap(profiles.profile(named:)) { session.profile = profile }
+                        if let display = pane.display { self.setDisplay(display, for: session) }
+                        added.append(session)
+                    }
+                }
+                started += added
+                let panes = [s] + added
+                for browser in tab.browsers ?? [] where panes.indices.contains(browser.beside) {
+                    restoreBrowser(browser, beside: panes[browser.beside].view, in: win)
+                }
+                if let focused = tab.focused, panes.indices.contains(focused) {
+                    DispatchQueue.main.async { win.makeFirstResponder(panes[focused].view) }
+                }
+            }
+            if let selected { selected.tabGroup?.selectedWindow = selected }
+            front = selected ?? host
+            if let host { DispatchQueue.main.async { self.refreshTabStrips(in: host) } }
+        }
+        front?.makeKeyAndOrderFront(nil)
+        DispatchQueue.main.async {
+            self.refreshPets()
+            self.refreshShortcutHints()
+            NSApp.windows.filter { $0.tabbingIdentifier == "infinitty" }.forEach { self.updateTitle(for: $0) }
+        }
+        return started
+    }
+
     private func installForegroundProcessMonitor() {
         foregroundProcessObserver = NotificationCenter.default.addObserver(
             forName: ForegroundProcessTracker.didChangeNotification,
@@ -654,11 +1480,108 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             // name means the pane returned to its shell prompt.
             let process = session.processTracker?.current
             let isShell = process == nil || process?.pid == session.pty.pid
-            self.appControl.broadcast([
-                "event": "process", "pane": session.id,
-                "name": isShell ? "" : (process?.rawName ?? ""),
-                "displayName": isShell ? "" : (process?.displayName ?? ""),
-            ])
+            self.publish(.process(
+                pane: session.id,
+                name: isShell ? "" : (process?.rawName ?? ""),
+                displayName: isShell ? "" : (process?.displayName ?? "")
+            ), in: session.view.window)
+        }
+    }
+
+    /// Publishes a `cwd` event whenever a pane's working directory moves:
+    /// the shell's OSC 7 report, or a tracker poll that finds the shell or
+    /// its foreground tool somewhere new.
+    private func installPaneDirectoryMonitor() {
+        paneDirectoryObserver = NotificationCenter.default.addObserver(
+            forName: ForegroundProcessTracker.cwdDidChangeNotification,
+            object: nil,
+            queue: .main
+        ) { [weak self] notification in
+            guard let self else { return }
+            let session: TerminalSession?
+            if let tracker = notification.object as? ForegroundProcessTracker {
+                session = self.sessions.first { $0.processTracker === tracker }
+            } else {
+                session = notification.object as? TerminalSession
+            }
+            guard let session, let (cwd, source) = session.directoryAndSource(),
+                  self.publishedDirectories[session.id] != cwd
+            else { return }
+            self.publishedDirectories[session.id] = cwd
+            self.releaseGitWatchers()
+            self.publish(
+                .directory(pane: session.id, cwd: cwd, source: source.rawValue), in: session.view.window)
+        }
+    }
+
+    /// Drops the git status watchers of repos no pane is in any more.
+    private func releaseGitWatchers() {
+        GitStatusCache.shared.release(keeping: sessions.compactMap {
+            publishedDirectories[$0.id] ?? $0.workingDirectory
+        })
+    }
+
+    /// Publishes `git-status-changed` when a repo whose status was read
+    /// (the Changes page, `git-status`) moves on disk, with the status the
+    /// cache re-read once the repo settled.
+    private func installGitStatusMonitor() {
+        gitStatusObserver = NotificationCenter.default.addObserver(
+            forName: GitStatusCache.didChangeNotification,
+            object: GitStatusCache.shared,
+            queue: .main
+        ) { [weak self] notification in
+            guard let repo = notification.userInfo?[GitStatusCache.repoKey] as? String,
+                  let status = notification.userInfo?[GitStatusCache.statusKey] as? CodeGit.RepoStatus
+            else { return }
+            self?.publish(.gitStatus(repo: repo, status))
+        }
+    }
+
+    /// Git commands the app runs ask for HTTPS credentials here: each
+    /// prompt goes out as a `git-credential-prompt` event and up as a sheet
+    /// on the key window, and `git-credential-answer` can answer it instead.
+    private func installGitCredentialPrompts() {
+        GitAskpass.shared.socketPath = appControl.path
+        GitAskpass.shared.presenter = { [weak self] prompt in
+            DispatchQueue.main.async { self?.presentGitCredentialPrompt(prompt) }
+        }
+        GitAskpass.shared.onResolved = { [weak self] prompt, answered in
+            DispatchQueue.main.async {
+                guard let self else { return }
+                // Answered over the socket or timed out: the sheet goes too.
+                if let alert = self.gitCredentialAlerts.removeValue(forKey: prompt.id),
+                   let parent = alert.window.sheetParent {
+                    parent.endSheet(alert.window, returnCode: .abort)
+                }
+                self.publish(.gitCredentialResolved(id: prompt.id, answered: answered))
+            }
+        }
+    }
+
+    private func presentGitCredentialPrompt(_ prompt: GitCredentialPrompt) {
+        publish(.gitCredentialPrompt(prompt))
+        guard let win = NSApp.keyWindow ?? NSApp.windows.first(where: { $0.tabbingIdentifier == "infinitty" })
+        else { return }
+        let alert = NSAlert()
+        alert.messageText = prompt.isSecret ? "Git needs a password" : "Git needs a username"
+        alert.informativeText = prompt.prompt.trimmingCharacters(in: .whitespaces)
+        let field = prompt.isSecret ? NSSecureTextField(string: "") : NSTextField(string: prompt.username ?? "")
+        field.frame = NSRect(x: 0, y: 28, width: 280, height: 24)
+        let remember = NSButton(checkboxWithTitle: "Remember in Keychain", target: nil, action: nil)
+        remember.frame = NSRect(x: 0, y: 0, width: 280, height: 20)
+        let accessory = NSView(frame: NSRect(x: 0, y: 0, width: 280, height: 52))
+        accessory.addSubview(field)
+        accessory.addSubview(remember)
+        alert.accessoryView = accessory
+        alert.addButton(withTitle: "OK")
+        alert.addButton(withTitle: "Cancel")
+        alert.window.initialFirstResponder = field
+        gitCredentialAlerts[prompt.id] = alert
+        alert.beginSheetModal(for: win) { [weak self] response in
+            guard self?.gitCredentialAlerts.removeValue(forKey: prompt.id) != nil else { return }
+            GitAskpass.shared.answer(
+                prompt.id, with: response == .alertFirstButtonReturn ? field.stringValue : nil,
+                remember: remember.state == .on)
         }
     }
 
@@ -729,6 +1652,20 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         return sessions.filter { $0.view.window === win }
     }
 
+    /// Send `event` to control-socket subscribers, targeted at `win`'s tab
+    /// so `subscribe <tab-id>` clients only see their own window. Windows
+    /// without a tab id (quick terminal, surface windows, one already torn
+    /// down) make the event app-wide.
+    private func publish(_ event: AppEvent, in win: NSWindow? = nil) {
+        appControl.broadcast(event, tab: win.flatMap { paneLedgerTabID(for: $0) })
+        switch event {
+        case .paneOpened, .paneClosed, .paneDetached, .paneAttached, .layoutChanged, .directory:
+            scheduleWorkspaceSave()
+        default:
+            break
+        }
+    }
+
     // MARK: - pane lifecycle ledger
 
     /// Each standard NSWindow is one native main tab. Keep an app-assigned ID
@@ -980,7 +1917,10 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     private func rebindUtilityPanels(to session: TerminalSession, in win: NSWindow) {
         let panels = utilityRecords(in: win)
         guard !panels.isEmpty else { return }
-        for record in panels { record.controller?.track(session: session) }
+        for record in panels {
+            record.controller?.track(session: session)
+            record.notes?.track(session: session)
+        }
         guard let chat = panels.last(where: { $0.kind == .chat }) else { return }
         let assistant = chat.assistant ?? petAssistant(for: session)
         rehomeAssistant(assistant, to: session)
@@ -1388,11 +2328,29 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             : []
         let win = s.view.window
         s.shutdown()
-        pendingLaunchCommands.removeValue(forKey: s.id)
+        SessionShareRegistry.shared.stop(pane: s.id)
+        pendingLaunchCommands.removeValue(forKey: s.id)?.completion?(-1)
         sessions.removeAll { $0 === s }
+        detachedSessions.removeValue(forKey: s.id)
+        _ = recordings.removeValue(forKey: s.id)?.stop()
+        broadcastGroups.forget(pane: s.id)
+        TriggerEngine.shared.forget(pane: s.id)
+        CommandWatcher.shared.forget(pane: s.id)
+        projectAutomationFiles.removeValue(forKey: s.id)
+        tmuxBindings.removeValue(forKey: s.id)
+        serialConnections.removeValue(forKey: s.id)?.close()
+        daemonPanes.removeValue(forKey: s.id)
+        publishedDirectories.removeValue(forKey: s.id)
+        releaseGitWatchers()
+        scannedPanes.remove(s.id)
+        if s.progress.current != nil { updateDockProgress() }
+        if s.secureInput.isActive { updateSecureEventInput() }
         let exitingAssistant = petAssistants.removeValue(forKey: s.id)
-        appControl.broadcast(["event": "pane-closed", "pane": s.id])
+        publish(.paneClosed(pane: s.id), in: win)
         runQueues.removeValue(forKey: s.id)?.forEach { $0.completion(-1) }
+        runningCommands.removeValue(forKey: s.id)
+        idleHandled.removeValue(forKey: s.id)
+        scrollbackCapped.remove(s.id)
         let v = s.view
         guard let win else {
             exitingAssistant?.detach()
@@ -1495,16 +2453,57 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
+    /// The theme's background color, sampled the way the renderer clears
+    /// the grid.
+    private func sampledBackground() -> NSColor {
+        let bg = Theme.dark.applying(config).background
+        return NSColor(
+            srgbRed: CGFloat(bg.x), green: CGFloat(bg.y), blue: CGFloat(bg.z),
+            alpha: CGFloat(bg.w))
+    }
+
+    /// Paints everything in `win` that shows before content arrives — the
+    /// window backing, the chrome behind the traffic lights and tab strip,
+    /// smart-pane surfaces and browser backdrops — from a terminal's clear
+    /// color, so they agree with the grid.
+    private func syncBackground(in win: NSWindow) {
+        guard let s = sessions.first(where: { $0.view.window === win }) else { return }
+        applyWindowBacking(to: win, renderer: s.renderer)
+        let bg = s.renderer.backgroundColor
+        let color = NSColor(
+            srgbRed: CGFloat(bg.x), green: CGFloat(bg.y), blue: CGFloat(bg.z),
+            alpha: CGFloat(bg.w))
+        terminalChromes[ObjectIdentifier(win)]?.setBacking(color: color, blur: config.backgroundBlur)
+        utilityPanels[ObjectIdentifier(win)]?.forEach {
+            $0.pane.updateSurface(
+                background: color.withAlphaComponent(config.backgroundOpacity),
+                blurred: config.backgroundBlur)
+            $0.browser?.backdrop = color.withAlphaComponent(config.backgroundOpacity)
+        }
+    }
+
+    /// Where a tab or split opened from `source` starts: by the new pane's
+    /// profile's `newPaneDirectory`, else the config's.
+    private func startDirectory(for session: TerminalSession, from source: TerminalSession?) -> String? {
+        let rule = session.profile?.newPaneDirectory ?? config.newPaneDirectory
+        return rule == .home ? nil : rule.resolve(from: source?.currentDirectory())
+    }
+
     @discardableResult
     private func makeTerminalWindow(
         cwd: String? = nil,
-        role: TerminalWindowRole = .standard
+        profile: TerminalProfile? = nil,
+        role: TerminalWindowRole = .standard,
+        reusing existing: TerminalSession? = nil
     ) -> (NSWindow, TerminalSession) {
         let scale = NSScreen.main?.backingScaleFactor ?? 2
-        let session = createSession(
+        let session = existing ?? createSession(
             scale: scale,
             usesSharedWindowSurface: role == .standard)
-        session.workingDirectory = cwd
+        if existing == nil {
+            session.workingDirectory = cwd
+            if let profile { session.profile = profile }
+        }
 
         let cell = session.renderer.cellSizePoints
         let inset = session.renderer.insetPoints
@@ -1612,8 +2611,8 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         // New quick-terminal tabs land in the current tab's live folder.
         let focused = (window.firstResponder as? TerminalView)
             .flatMap { view in sessions.first { $0.view === view } }
-        session.workingDirectory = (focused ?? quickTerminal.activeSessions.first)?
-            .currentDirectory()
+        session.workingDirectory = startDirectory(
+            for: session, from: focused ?? quickTerminal.activeSessions.first)
         let size = quickTerminal.activeRootView?.bounds.size
             ?? window.contentView?.bounds.size
             ?? window.contentLayoutRect.size
@@ -1714,13 +2713,13 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             newWindow(sender)
             return
         }
-        // Inherit the live cwd of the current pane so the new tab lands where
+        // By default the new tab lands in the current pane's live cwd, where
         // the user is working, not back in $HOME.
         let t0 = CFAbsoluteTimeGetCurrent()
         let source = focusedSession() ?? activeSessions(in: key).first
-        let cwd = source?.currentDirectory()
         let t1 = CFAbsoluteTimeGetCurrent()
-        let (window, session) = makeTerminalWindow(cwd: cwd)
+        let (window, session) = makeTerminalWindow()
+        session.workingDirectory = startDirectory(for: session, from: source)
         let t2 = CFAbsoluteTimeGetCurrent()
         key.addTabbedWindow(window, ordered: .above)
         let t3 = CFAbsoluteTimeGetCurrent()
@@ -1746,9 +2745,228 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         newTab(sender)
     }
 
-    // MARK: - splits
+    // MARK: - tmux
+
+    /// Starts a control-mode client; the caller lists its windows. Each
+    /// tmux window becomes a tab and its panes native splits, kept in step
+    /// by the client's notifications.
+    private func attachTmux(socket: String?, session: String?) -> Result<TmuxControlClient, TmuxError> {
+        let client = TmuxControlClient(id: "tmux-\(nextTmuxClient)", socket: socket, session: session)
+        nextTmuxClient += 1
+        client.onMessage = { [weak self] client, message in
+            DispatchQueue.main.async { self?.handleTmuxMessage(message, from: client) }
+        }
+        client.onEnded = { [weak self] client, _ in
+            DispatchQueue.main.async { self?.tmuxClients.removeValue(forKey: client.id) }
+        }
+        if case .failure(let error) = client.start() { return .failure(error) }
+        tmuxClients[client.id] = client
+        // tmux sizes its windows to the client; start from the pane in use.
+        let size = focusedSession()?.terminal
+        client.send("refresh-client -C \(size?.cols ?? 120)x\(size?.rows ?? 32)")
+        return .success(client)
+    }
+
+    private func handleTmuxMessage(_ message: TmuxMessage, from client: TmuxControlClient) {
+        guard tmuxClients[client.id] === client else { return }
+        switch message {
+        case .layoutChange(let window, let text):
+            guard let layout = TmuxLayout.parse(text) else { return }
+            applyTmuxLayout(layout, window: window, client: client)
+        case .windowAdd(let window):
+            client.send("display-message -p -t @\(window) '#{window_layout}'") { [weak self] result in
+                guard case .success(let lines) = result, let layout = lines.first.flatMap(TmuxLayout.parse) else {
+                    return
+                }
+                DispatchQueue.main.async { self?.applyTmuxLayout(layout, window: window, client: client) }
+            }
+        case .windowClose(let window):
+            for binding in tmuxBindings.values where binding.client == client.id && binding.window == window {
+                client.unbind(pane: binding.pane)
+            }
+        default:
+            break
+        }
+    }
 
-    private func showSplitChooser(sourceView: NSView, vertical: Bool) {
+    /// Brings a tmux window's tab in line with its layout: a new tab for a
+    /// window not shown yet, a split beside its layout neighbour for each
+    /// new pane, and gone panes closed.
+    private func applyTmuxLayout(_ layout: TmuxLayout, window: Int, client: TmuxControlClient) {
+        let order = layout.panes
+        var shown: [Int: TerminalSession] = [:]
+        for (id, binding) in tmuxBindings where binding.client == client.id && binding.window == window {
+            guard let s = session(withID: id) else { continue }
+            if order.contains(binding.pane) {
+                shown[binding.pane] = s
+            } else {
+                client.unbind(pane: binding.pane)
+            }
+        }
+        var changed: NSWindow?
+        if shown.isEmpty, let first = order.first {
+            let s = createSession(scale: NSScreen.main?.backingScaleFactor ?? 2, usesSharedWindowSurface: true)
+            let (win, _) = makeTerminalWindow(reusing: s)
+            if let host = NSApp.windows.first(where: { $0 !== win && $0.tabbingIdentifier == "infinitty" }) {
+                host.addTabbedWindow(win, ordered: .above)
+            } else {
+                win.orderFront(nil)
+            }
+            recordPaneLedgerNote(in: win, reason: "tab-joined", origin: "tmux")
+            bindTmuxSession(s, pane: first, window: window, client: client)
+            s.launch()
+            shown[first] = s
+            changed = win
+        }
+        for (index, pane) in order.enumerated() where shown[pane] == nil {
+            let before = order[..<index].last { shown[$0] != nil }
+            let after = order[(index + 1)...].first { shown[$0] != nil }
+            guard let anchor = (before ?? after).flatMap({ shown[$0] }), let win = anchor.view.window else { continue }
+            let s = createSession(
+                scale: win.backingScaleFactor,
+                usesSharedWindowSurface: terminalChromes[ObjectIdentifier(win)] != nil)
+            let vertical = layout.splitsSideBySide(around: pane) ?? true
+            guard insertPaneView(s.view, relativeTo: anchor.view, vertical: vertical, newFirst: before == nil) else {
+                s.shutdown()
+                sessions.removeAll { $0 === s }
+                continue
+            }
+            recordPaneLedgerTerminalAdded(
+                s, in: win, reason: "tmux-split", origin: "tmux", sourceView: anchor.view, vertical: vertical)
+            bindTmuxSession(s, pane: pane, window: window, client: client)
+            s.launch()
+            shown[pane] = s
+            changed = win
+        }
+        guard let win = changed else { return }
+        DispatchQueue.main.async {
+            self.refreshPets()
+            self.updateTitle(for: win)
+            self.refreshShortcutHints()
+            self.refreshTabStrips(in: win)
+        }
+    }
+
+    private func bindTmuxSession(_ s: TerminalSession, pane: Int, window: Int, client: TmuxControlClient) {
+        s.paneTitleOverride = "tmux %\(pane)"
+        s.view.paneTitle = paneHeaderTitle(for: s)
+        client.bind(s.pty, to: pane)
+        tmuxBindings[s.id] = (client.id, window, pane)
+    }
+
+    /// Splitting a tmux pane asks tmux to split it; the layout change that
+    /// follows adds the native pane.
+    private func splitTmuxPane(_ s: TerminalSession, vertical: Bool, newFirst: Bool = false) -> Bool {
+        guard let binding = tmuxBindings[s.id], let client = tmuxClients[binding.client] else { return false }
+        client.send("split-window \(vertical ? "-h" : "-v")\(newFirst ? " -b" : "") -t %\(binding.pane)")
+        return true
+    }
+
+    // MARK: - serial
+
+    /// Opens a serial device as a new tab, in place of a shell: the pane
+    /// reads and writes the device until it closes.
+    private func openSerial(_ settings: SerialSettings) -> Result<TerminalSession, SerialError> {
+        SerialConnection.open(settings).map { connection in
+            let s = createSession(scale: NSScreen.main?.backingScaleFactor ?? 2, usesSharedWindowSurface: true)
+            connection.bind(s.pty)
+            serialConnections[s.id] = connection
+            s.paneTitleOverride = "\(SerialPort.name(of: settings.port)) · \(settings.summary)"
+            s.view.paneTitle = paneHeaderTitle(for: s)
+            openPreparedTab(s, origin: "serial")
+            return s
+        }
+    }
+
+    /// Shows a session made up front (its far end chosen already) as a tab
+    /// of the first terminal window, or as a window when there is none,
+    /// and launches it.
+    private func openPreparedTab(_ s: TerminalSession, origin: String) {
+        let (win, _) = makeTerminalWindow(reusing: s)
+        let host = NSApp.windows.first { $0 !== win && $0.tabbingIdentifier == "infinitty" }
+        host?.addTabbedWindow(win, ordered: .above)
+        recordPaneLedgerNote(in: win, reason: host == nil ? "tab-presented" : "tab-joined", origin: origin)
+        win.makeKeyAndOrderFront(nil)
+        s.launch()
+        DispatchQueue.main.async {
+            self.refreshPets()
+            self.updateTitle(for: win)
+            self.refreshShortcutHints()
+            self.refreshTabStrips(in: win)
+        }
+    }
+
+    // MARK: - daemon
+
+    /// Every pane's `launchRemotely`: with `daemon = true` its shell is
+    /// spawned in infinitty-ptyd. If the daemon isn't up the pane gets a
+    /// local shell as usual and the daemon is started for the next one.
+    private func launchInDaemon(_ s: TerminalSession) -> pid_t? {
+        guard config.daemon else { return nil }
+        let spawned = PTYDaemonPane.spawn(
+            cols: s.terminal.cols, rows: s.terminal.rows, cwd: s.workingDirectory, launch: s.shellLaunch)
+        switch spawned {
+        case .success(let pane):
+            pane.bind(s.pty)
+            daemonPanes[s.id] = pane
+            return pane.pid
+        case .failure(.notRunning):
+            DispatchQueue.global(qos: .utility).async { _ = PTYDaemon.start() }
+            return nil
+        case .failure(let error):
+            FileHandle.standardError.write(Data("infinitty: \(error); using a local shell\n".utf8))
+            return nil
+        }
+    }
+
+    /// Starts the daemon off main — `PTYDaemon.start()` can wait two
+    /// seconds for its socket — then reopens its detached shells as tabs
+    /// beside the window launch already opened.
+    private func reattachDaemonShellsAtLaunch() {
+        DispatchQueue.global(qos: .userInitiated).async {
+            let started = PTYDaemon.start()
+            DispatchQueue.main.async { [weak self] in
+                guard let self, case .success = started else { return }
+                if case .failure(let error) = self.reattachDaemonShells() {
+                    FileHandle.standardError.write(Data("infinitty: daemon shells: \(error)\n".utf8))
+                }
+            }
+        }
+    }
+
+    /// Reopens daemon shells as tabs: the given ones, or every shell no app
+    /// is attached to. Each replays its recent output, then is redrawn at
+    /// its new size.
+    private func reattachDaemonShells(_ ids: [Int]? = nil) -> Result<[TerminalSession], PTYDaemonError> {
+        PTYDaemon.list().flatMap { listing in
+            let owned = Set(daemonPanes.values.map(\.id))
+            let shells: [PTYDaemonSession]
+            if let ids {
+                if let missing = ids.first(where: { id in !listing.sessions.contains { $0.id == id } }) {
+                    return .failure(.failed("no shell \(missing)"))
+                }
+                shells = listing.sessions.filter { ids.contains($0.id) && !owned.contains($0.id) }
+            } else {
+                shells = listing.sessions.filter { !$0.attached && !owned.contains($0.id) }
+            }
+            return .success(shells.compactMap { shell in
+                guard case .success(let pane) = PTYDaemonPane.attach(shell.id) else { return nil }
+                let s = createSession(scale: NSScreen.main?.backingScaleFactor ?? 2, usesSharedWindowSurface: true)
+                s.launchRemotely = { [weak self] session in
+                    pane.bind(session.pty)
+                    self?.daemonPanes[session.id] = pane
+                    return pane.pid
+                }
+                openPreparedTab(s, origin: "daemon")
+                s.pty.setSize(cols: s.terminal.cols, rows: s.terminal.rows)
+                return s
+            })
+        }
+    }
+
+    // MARK: - splits
+
+    private func showSplitChooser(sourceView: NSView, vertical: Bool) {
         guard sourceView.window != nil else { return }
         restorePaneZoom(containing: sourceView, refocus: false)
         let menu = NSMenu(title: vertical ? "Split Right" : "Split Down")
@@ -1794,13 +3012,17 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     }
 
     private func splitTerminal(relativeTo sourceView: NSView, vertical: Bool) {
+        if let tmux = sessions.first(where: { $0.view === sourceView }), splitTmuxPane(tmux, vertical: vertical) {
+            return
+        }
         restorePaneZoom(containing: sourceView, refocus: false)
         guard let win = sourceView.window else { return }
         let session = createSession(
             scale: win.backingScaleFactor,
             usesSharedWindowSurface: terminalChromes[ObjectIdentifier(win)] != nil)
-        session.workingDirectory = focusedSession(in: win)?.currentDirectory()
-            ?? activeSessions(in: win).first?.currentDirectory()
+        let source = focusedSession(in: win) ?? activeSessions(in: win).first
+        if let source { session.profile = source.profile }
+        session.workingDirectory = startDirectory(for: session, from: source)
         guard insertPaneView(session.view, relativeTo: sourceView, vertical: vertical) else {
             recordPaneLedgerFailure(
                 in: win, paneID: paneLedgerTerminalID(session), reason: "split-insert-failed",
@@ -1856,6 +3078,11 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         return true
     }
 
+    @objc func toggleInputLock(_ sender: Any?) {
+        guard let s = focusedSession() else { return }
+        setInputLocked(s, !s.pty.isInputLocked)
+    }
+
     @objc func togglePaneZoom(_ sender: Any?) {
         guard let win = NSApp.keyWindow, let pane = focusedPaneLeaf(in: win) else { return }
         togglePaneZoom(for: pane)
@@ -1924,6 +3151,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             maximizedRatios: PaneLayoutController.ratios(for: orderedTargets),
             collapsedViews: collapsedViews)
         win.makeFirstResponder(pane)
+        publishLayoutChange("zoom", pane: pane, in: win)
         let keyframes = orderedTargets.map { split, positions in
             PaneDividerKeyframe(
                 split: split,
@@ -1938,6 +3166,19 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
+    private func isPaneZoomed(_ pane: NSView) -> Bool {
+        paneZoomStates.values.contains { $0.pane === pane }
+    }
+
+    private func publishLayoutChange(_ reason: String, pane: NSView? = nil, in win: NSWindow) {
+        let record = pane.flatMap { utilityRecord(forPane: $0) }
+        publish(.layoutChanged(
+            reason: reason,
+            pane: pane.flatMap { view in sessions.first { $0.view === view }?.id },
+            surface: record.map { $0.browser?.browserID ?? $0.ledgerID }
+        ), in: win)
+    }
+
     private func paneSplitPath(
         from pane: NSView, to root: NSView
     ) -> [(split: NSSplitView, selectedIndex: Int)] {
@@ -1986,6 +3227,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     ) {
         guard let state = paneZoomStates.removeValue(forKey: key) else { return }
         paneDividerAnimations.removeValue(forKey: key)?.cancel()
+        if let win = state.root.window { publishLayoutChange("unzoom", pane: state.pane, in: win) }
         if animated {
             paneZoomRestoreStates[key] = state
             let wasFullyMaximized = state.collapsedViews.contains(where: \.isHidden)
@@ -2153,7 +3395,9 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
-    private func movePaneView(_ source: NSView, relativeTo target: NSView, zone: PaneDropZone) {
+    private func movePaneView(
+        _ source: NSView, relativeTo target: NSView, zone: PaneDropZone, origin: String = "pane-drag"
+    ) {
         guard source !== target, let win = source.window, target.window === win else { return }
         let beforePanes = paneLeafViews(in: win)
         let beforeIDs = Set(beforePanes.map(ObjectIdentifier.init))
@@ -2171,7 +3415,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 + "source=\(ObjectIdentifier(source)) target=\(ObjectIdentifier(target))")
             recordPaneLedgerFailure(
                 in: win, paneID: paneLedgerPaneID(for: source), reason: "pane-move-unchanged",
-                origin: "pane-drag")
+                origin: origin)
             return
         }
         if let split = result.insertedSplit {
@@ -2188,7 +3432,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             + "tree=\(root.map(PaneLog.describe) ?? "nil")"
         PaneLog.log(beforeIDs == afterIDs ? summary : "ERROR \(summary)")
         recordPaneLedgerNote(
-            in: win, paneID: paneLedgerPaneID(for: source), reason: "pane-moved", origin: "pane-drag",
+            in: win, paneID: paneLedgerPaneID(for: source), reason: "pane-moved", origin: origin,
             sourcePaneID: paneLedgerPaneID(for: target), axis: String(describing: zone))
         animatePaneReflow(from: oldGeometry)
         DispatchQueue.main.asyncAfter(deadline: .now() + 0.35) { [weak self, weak win] in
@@ -2202,7 +3446,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 + "tree=\(settledRoot.map(PaneLog.describe) ?? "nil")"
             PaneLog.log(beforeIDs == settledIDs ? settledSummary : "ERROR \(settledSummary)")
             self.recordPaneLedgerNote(
-                in: win, reason: "pane-move-settled", origin: "pane-drag")
+                in: win, reason: "pane-move-settled", origin: origin)
         }
         win.makeFirstResponder(source)
         refreshPets()
@@ -2238,6 +3482,163 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
+    // MARK: - pane arrangement
+
+    /// Swap, move-to-tab, equalize and the resize presets, shared by the
+    /// File menu and the pane's context menu. Resize tags are percentages.
+    static func paneArrangementMenuItems() -> [NSMenuItem] {
+        let resize = NSMenuItem(title: "Resize Pane", action: nil, keyEquivalent: "")
+        let presets = NSMenu(title: "Resize Pane")
+        for (title, percent) in [("Quarter", 25), ("Third", 33), ("Half", 50), ("Two Thirds", 67),
+                                 ("Three Quarters", 75)] {
+            let item = presets.addItem(
+                withTitle: title, action: #selector(AppDelegate.setPaneShare(_:)), keyEquivalent: "")
+            item.tag = percent
+        }
+        resize.submenu = presets
+        return [
+            NSMenuItem(
+                title: "Swap With Next Pane", action: #selector(AppDelegate.swapWithNextPane(_:)), keyEquivalent: ""),
+            NSMenuItem(
+                title: "Move Pane to New Tab", action: #selector(AppDelegate.movePaneToNewTab(_:)), keyEquivalent: ""),
+            NSMenuItem(title: "Equalize Splits", action: #selector(AppDelegate.equalizeSplits(_:)), keyEquivalent: ""),
+            resize,
+        ]
+    }
+
+    @objc func swapWithNextPane(_ sender: Any?) {
+        guard let win = NSApp.keyWindow, let pane = focusedPaneLeaf(in: win) else { return }
+        swapPane(pane)
+    }
+
+    @objc func movePaneToNewTab(_ sender: Any?) {
+        guard let session = focusedSession() else { return }
+        moveToNewTab(session)
+    }
+
+    @objc func equalizeSplits(_ sender: Any?) {
+        guard let win = NSApp.keyWindow else { return }
+        equalizeSplits(in: win)
+    }
+
+    @objc func setPaneShare(_ sender: Any?) {
+        guard let item = sender as? NSMenuItem, let win = NSApp.keyWindow,
+              let pane = focusedPaneLeaf(in: win) else { return }
+        setShare(of: pane, to: Double(item.tag) / 100)
+    }
+
+    /// Exchanges `pane` with `other`, by default the next pane of its tab in
+    /// reading order (the first after the last).
+    @discardableResult
+    private func swapPane(_ pane: NSView, with other: NSView? = nil) -> Bool {
+        guard let win = pane.window else { return false }
+        let leaves = paneLeafViews(in: win)
+        guard let index = leaves.firstIndex(of: pane), leaves.count > 1 else { return false }
+        let target = other ?? leaves[(index + 1) % leaves.count]
+        guard target !== pane, target.window === win else { return false }
+        restorePaneZoom(revealing: pane)
+        movePaneView(pane, relativeTo: target, zone: .center, origin: "pane-command")
+        publishLayoutChange("swap", pane: pane, in: win)
+        return true
+    }
+
+    /// Gives terminal `s` a tab of its own next to its current one, shell
+    /// and scrollback intact. False when it's alone in its tab already.
+    @discardableResult
+    private func moveToNewTab(_ s: TerminalSession) -> Bool {
+        guard detachedSessions[s.id] == nil, !quickTerminal.contains(s), let win = s.view.window,
+              paneLeafViews(in: win).count > 1, let split = s.view.superview as? NSSplitView else { return false }
+        restorePaneZoom(containing: s, refocus: false)
+        s.view.removeFromSuperview()
+        collapse(split, in: win)
+        recordPaneLedgerTerminalRemoved(s, in: win, reason: "pane-moved-to-tab", origin: "pane-command")
+        if let next = activeSessions(in: win).first { rebindUtilityPanels(to: next, in: win) }
+        publishLayoutChange("move-to-tab", in: win)
+
+        let (window, _) = makeTerminalWindow(reusing: s)
+        win.addTabbedWindow(window, ordered: .above)
+        window.makeKeyAndOrderFront(nil)
+        window.makeFirstResponder(s.view)
+        recordPaneLedgerNote(
+            in: window, paneID: paneLedgerTerminalID(s), reason: "tab-joined", origin: "pane-command")
+        DispatchQueue.main.async {
+            self.refreshPets()
+            self.updateTitle(for: win)
+            self.updateTitle(for: window)
+            self.refreshShortcutHints()
+            self.refreshTabStrips(in: window)
+        }
+        return true
+    }
+
+    /// Evens out every split in `win`'s tab, outer splits first so the
+    /// inner ones are sized against their final bounds.
+    @discardableResult
+    private func equalizeSplits(in win: NSWindow) -> Bool {
+        guard win !== quickTerminal.window, let root = terminalRoot(of: win) else { return false }
+        if let entry = paneZoomStates.first(where: { $0.value.root === root }) {
+            restorePaneZoom(key: entry.key, refocus: false, animated: false)
+        }
+        var splits: [NSSplitView] = []
+        func collect(_ view: NSView) {
+            if let split = view as? NSSplitView {
+                splits.append(split)
+                split.arrangedSubviews.forEach(collect)
+            } else if !(view is TerminalView || view is UtilityPaneView) {
+                view.subviews.forEach(collect)
+            }
+        }
+        collect(root)
+        guard !splits.isEmpty else { return false }
+        for split in splits {
+            split.superview?.layoutSubtreeIfNeeded()
+            let count = split.arrangedSubviews.count
+            let sizes = Array(repeating: 1 / Double(count), count: count)
+            PaneLayoutController.restoreDividerRatios([(split, LayoutShape.dividerRatios(for: sizes))])
+        }
+        recordPaneLedgerNote(in: win, reason: "splits-equalized", origin: "pane-command")
+        publishLayoutChange("equalize", in: win)
+        return true
+    }
+
+    /// Sizes `pane` to `share` of the split it sits in, its siblings
+    /// sharing the rest evenly. False for a pane alone in its tab.
+    /// `publishes` false leaves the `layout-changed` event to the caller,
+    /// for a batch that should announce itself once.
+    @discardableResult
+    private func setShare(of pane: NSView, to share: Double, publishes: Bool = true) -> Bool {
+        guard let win = pane.window else { return false }
+        restorePaneZoom(revealing: pane)
+        guard let split = pane.superview as? NSSplitView,
+              let index = split.arrangedSubviews.firstIndex(of: pane) else { return false }
+        split.superview?.layoutSubtreeIfNeeded()
+        let sizes = LayoutShape.sizes(count: split.arrangedSubviews.count, giving: index, share: share)
+        PaneLayoutController.restoreDividerRatios([(split, LayoutShape.dividerRatios(for: sizes))])
+        if publishes { publishLayoutChange("resize", pane: pane, in: win) }
+        return true
+    }
+
+    /// A `pane-resize` batch: every pane is looked up before any moves, so
+    /// a bad id changes nothing, and each window it touched gets one
+    /// `layout-changed`.
+    private func resizePanes(_ batch: [(pane: String, share: Double)]) -> String {
+        var panes: [(view: NSView, share: Double)] = []
+        for (word, share) in batch {
+            let view = Int(word).flatMap { id in sessions.first { $0.id == id }?.view }
+                ?? browserRecord(withID: word)?.pane
+            guard let view, view.window != nil else { return "error: no pane \(word) in a window" }
+            panes.append((view, share))
+        }
+        var resized: [(window: NSWindow, pane: NSView)] = []
+        for (view, share) in panes {
+            guard setShare(of: view, to: share, publishes: false), let win = view.window else { continue }
+            resized.removeAll { $0.window === win }
+            resized.append((win, view))
+        }
+        for (win, pane) in resized { publishLayoutChange("resize", pane: pane, in: win) }
+        return resized.isEmpty ? "error: no pane in the batch shares a split" : "ok"
+    }
+
     @discardableResult
     private func replaceNode(_ old: NSView, with new: NSView, in parent: NSView) -> Bool {
         PaneLayoutController.replace(old, with: new, in: parent)
@@ -2268,14 +3669,24 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         split(session: session, vertical: vertical, newFirst: newFirst)
     }
 
-    private func split(session: TerminalSession, vertical: Bool, newFirst: Bool) {
+    /// `prepare` sees the new session before its shell starts; the new
+    /// session comes back unless tmux made the split.
+    @discardableResult
+    private func split(
+        session: TerminalSession, vertical: Bool, newFirst: Bool,
+        prepare: ((TerminalSession) -> Void)? = nil
+    ) -> TerminalSession? {
+        if splitTmuxPane(session, vertical: vertical, newFirst: newFirst) { return nil }
         restorePaneZoom(containing: session, refocus: false)
-        guard let win = session.view.window else { return }
+        guard let win = session.view.window else { return nil }
         let newSession = createSession(
             scale: win.backingScaleFactor,
             usesSharedWindowSurface: terminalChromes[ObjectIdentifier(win)] != nil)
-        // Splits land in the source pane's live folder, same as new tabs.
-        newSession.workingDirectory = session.currentDirectory()
+        // Splits start the way the source pane did, and in the folder
+        // `new-pane-directory` picks from its live one, same as new tabs.
+        newSession.profile = session.profile
+        newSession.workingDirectory = startDirectory(for: newSession, from: session)
+        prepare?(newSession)
 
         let old = session.view
         let container = old.superview
@@ -2302,7 +3713,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 origin: "split-command")
             newSession.shutdown()
             sessions.removeAll { $0 === newSession }
-            return
+            return nil
         }
         old.autoresizingMask = []
         newSession.view.autoresizingMask = []
@@ -2330,6 +3741,250 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             self.refreshShortcutHints()
         }
         newSession.launch()
+        return newSession
+    }
+
+    /// Another shell exactly where `s` is: split beside it in its live
+    /// directory with its profile and environment, including a trusted
+    /// project's exports without running the project's commands again.
+    /// With `replay`, the pane's last command is typed at the first prompt.
+    @discardableResult
+    private func duplicatePane(
+        of s: TerminalSession, vertical: Bool = true, replay: Bool = false
+    ) -> TerminalSession? {
+        let command = replay ? s.terminal.lastCommandLine().flatMap { $0.isEmpty ? nil : $0 } : nil
+        let project = projectAutomationFiles[s.id]
+        var environment = s.environment
+        if let project, case .success(let automation) = ProjectAutomation.load(path: project),
+           ProjectTrust.shared.decision(for: automation) == true {
+            environment.merge(automation.env) { _, new in new }
+        }
+        let copy = split(session: s, vertical: vertical, newFirst: false) { copy in
+            copy.environment = environment
+            if let project { self.projectAutomationFiles[copy.id] = project }
+            if !s.display.isDefault { self.setDisplay(s.display, for: copy) }
+        }
+        if let copy, let command { queueLaunchCommand(command, for: copy) }
+        return copy
+    }
+
+    @objc func duplicatePane(_ sender: Any?) {
+        guard let s = focusedSession() else { return }
+        duplicatePane(of: s)
+    }
+
+    /// Gives `s` its own text size and line height and re-lays it out at
+    /// the new cell size; neighbouring panes keep the config's.
+    private func setDisplay(_ display: PaneDisplay, for s: TerminalSession) {
+        s.display = display
+        let scale = s.view.window?.backingScaleFactor ?? NSScreen.main?.backingScaleFactor ?? 2
+        s.renderer.applyConfig(display.applied(to: config), scale: scale)
+        s.view.needsLayout = true
+        s.terminal.touch()
+        if let win = s.view.window, focusedSession(in: win) === s {
+            win.contentResizeIncrements = s.renderer.cellSizePoints
+        }
+        scheduleWorkspaceSave()
+    }
+
+    @objc func biggerText(_ sender: Any?) {
+        guard let s = focusedSession() else { return }
+        setDisplay(s.display.stepped(1, from: config), for: s)
+    }
+
+    @objc func smallerText(_ sender: Any?) {
+        guard let s = focusedSession() else { return }
+        setDisplay(s.display.stepped(-1, from: config), for: s)
+    }
+
+    @objc func defaultTextSize(_ sender: Any?) {
+        guard let s = focusedSession(), !s.display.isDefault else { return }
+        setDisplay(PaneDisplay(), for: s)
+    }
+
+    /// Takes a terminal out of its window without ending its shell. The
+    /// layout closes around it as if it had exited (the tab too, when it was
+    /// the last pane), but output keeps landing in its scrollback.
+    private func detachSession(_ s: TerminalSession) -> Bool {
+        guard detachedSessions[s.id] == nil, !quickTerminal.contains(s), let win = s.view.window else {
+            return false
+        }
+        restorePaneZoom(containing: s, refocus: false)
+        let v = s.view
+        if let split = v.superview as? NSSplitView {
+            v.removeFromSuperview()
+            collapse(split, in: win)
+        } else {
+            v.removeFromSuperview()
+        }
+        recordPaneLedgerTerminalRemoved(s, in: win, reason: "terminal-detached", origin: "app-control-detach")
+        detachedSessions[s.id] = Date()
+        publish(.paneDetached(pane: s.id), in: win)
+
+        if let next = activeSessions(in: win).first {
+            rebindUtilityPanels(to: next, in: win)
+            win.makeFirstResponder(next.view)
+        } else if let remainingPane = paneLeafViews(in: win).first {
+            win.makeFirstResponder(remainingPane)
+            updatePaneSelection(in: win, focused: remainingPane)
+        }
+        if PaneLifecyclePolicy.shouldCloseTab(remainingPaneCount: paneLeafViews(in: win).count) {
+            win.close()
+        } else {
+            updateTitle(for: win)
+        }
+        refreshPets()
+        refreshShortcutHints()
+        return true
+    }
+
+    /// Puts a detached terminal back: split beside `target` in `direction`,
+    /// or as a new tab of the key window when there is no target. Like an
+    /// agent-created pane it does not take keyboard focus.
+    private func attachSession(_ s: TerminalSession, beside target: TerminalSession?, direction: String) -> Bool {
+        guard detachedSessions[s.id] != nil else { return false }
+        let win: NSWindow
+        if let target {
+            guard let host = target.view.window, !quickTerminal.contains(target) else { return false }
+            restorePaneZoom(containing: target, refocus: false)
+            let vertical = direction == "right" || direction == "left"
+            guard insertPaneView(
+                s.view, relativeTo: target.view, vertical: vertical,
+                newFirst: direction == "left" || direction == "up")
+            else { return false }
+            recordPaneLedgerTerminalAdded(
+                s, in: host, reason: "terminal-attached", origin: "app-control-attach",
+                sourceView: target.view, vertical: vertical)
+            win = host
+        } else {
+            let (window, _) = makeTerminalWindow(reusing: s)
+            let key = NSApp.keyWindow.flatMap { $0.tabbingIdentifier == "infinitty" ? $0 : nil }
+            if let host = key ?? NSApp.windows.first(where: { $0.tabbingIdentifier == "infinitty" && $0 !== window }) {
+                host.addTabbedWindow(window, ordered: .above)
+            } else {
+                window.orderFront(nil)
+            }
+            recordPaneLedgerNote(in: window, reason: "tab-joined", origin: "app-control-attach")
+            win = window
+        }
+        detachedSessions.removeValue(forKey: s.id)
+        publish(.paneAttached(pane: s.id), in: win)
+        DispatchQueue.main.async {
+            self.refreshPets()
+            self.updateTitle(for: win)
+            self.refreshShortcutHints()
+            self.refreshTabStrips(in: win)
+        }
+        return true
+    }
+
+    /// Rebuilds `win`'s split tree as `shape`. The tab's panes fill its slots
+    /// in reading order, empty slots get new shells in the focused pane's
+    /// folder (then `prepare`d, with their order among the new ones), and
+    /// panes left over once the slots run out stack in rows inside the last
+    /// one, so nothing is closed.
+    private func applyLayout(
+        _ shape: LayoutShape, in win: NSWindow, prepare: (TerminalSession, Int) -> Void = { _, _ in }
+    ) -> [String: Any]? {
+        guard win !== quickTerminal.window, let root = terminalRoot(of: win) else { return nil }
+        if let entry = paneZoomStates.first(where: { $0.value.root === root }) {
+            restorePaneZoom(key: entry.key, refocus: false, animated: false)
+        }
+        let leaves = paneLeafViews(in: win)
+        let isTree: (NSView) -> Bool = { $0 is NSSplitView || $0 is TerminalView || $0 is UtilityPaneView }
+        guard let top = isTree(root) ? root : root.subviews.first(where: isTree) else { return nil }
+        let focused = focusedPaneLeaf(in: win)
+        let cwd = focusedSession(in: win)?.currentDirectory()
+        let chrome = terminalChromes[ObjectIdentifier(win)] != nil
+        let frame = top.frame
+        let autoresizingMask = top.autoresizingMask
+        let container = top.superview
+        let isContentView = win.contentView === top
+        // A stand-in holds the tree's place among the container's subviews
+        // while the panes are lifted out of their old splits.
+        let placeholder = NSView(frame: frame)
+        if isContentView {
+            win.contentView = placeholder
+        } else {
+            container?.replaceSubview(top, with: placeholder)
+        }
+        leaves.forEach { $0.removeFromSuperview() }
+
+        var queue = leaves
+        var added: [TerminalSession] = []
+        var ratios: PaneLayoutController.DividerRatios = []
+        func makeSplit(vertical: Bool, sizes: [Double]) -> PaneSplitView {
+            let split = PaneSplitView(frame: frame)
+            split.isVertical = vertical
+            split.dividerStyle = .thin
+            // Outer splits come first so their children have settled bounds
+            // by the time their own dividers are placed.
+            ratios.append((split, LayoutShape.dividerRatios(for: sizes)))
+            return split
+        }
+        func build(_ node: LayoutShape, last: Bool) -> NSView {
+            switch node {
+            case .pane:
+                let pane: NSView
+                if queue.isEmpty {
+                    let s = createSession(scale: win.backingScaleFactor, usesSharedWindowSurface: chrome)
+                    s.workingDirectory = cwd
+                    prepare(s, added.count)
+                    added.append(s)
+                    pane = s.view
+                } else {
+                    pane = queue.removeFirst()
+                }
+                guard last, !queue.isEmpty else { return pane }
+                let overflow = [pane] + queue
+                queue.removeAll()
+                let stack = makeSplit(
+                    vertical: false, sizes: Array(repeating: 1 / Double(overflow.count), count: overflow.count))
+                for view in overflow {
+                    view.autoresizingMask = []
+                    stack.addArrangedSubview(view)
+                }
+                return stack
+            case .split(let vertical, let children, let sizes):
+                let split = makeSplit(vertical: vertical, sizes: sizes)
+                for (index, child) in children.enumerated() {
+                    let view = build(child, last: last && index == children.count - 1)
+                    view.autoresizingMask = []
+                    split.addArrangedSubview(view)
+                }
+                return split
+            }
+        }
+        let tree = build(shape.normalized, last: true)
+        tree.frame = frame
+        tree.autoresizingMask = autoresizingMask
+        if isContentView {
+            win.contentView = tree
+        } else {
+            container?.replaceSubview(placeholder, with: tree)
+        }
+        applyTabTint(to: win)
+        let source = focused ?? leaves.first
+        for s in added {
+            recordPaneLedgerTerminalAdded(
+                s, in: win, reason: "layout-preset", origin: "app-control-layout",
+                sourceView: source ?? s.view, vertical: true)
+        }
+        recordPaneLedgerNote(in: win, reason: "layout-applied", origin: "app-control-layout")
+        publishLayoutChange("preset", in: win)
+
+        DispatchQueue.main.async {
+            for entry in ratios {
+                entry.split.superview?.layoutSubtreeIfNeeded()
+                PaneLayoutController.restoreDividerRatios([entry])
+            }
+            if let pane = source ?? added.first?.view { win.makeFirstResponder(pane) }
+            self.refreshPets()
+            self.updateTitle(for: win)
+            self.refreshShortcutHints()
+        }
+        added.forEach { $0.launch() }
+        return ["panes": paneLeafViews(in: win).count, "added": added.map(\.id), "layout": shape.normalized.wire]
     }
 
     @objc func closePane(_ sender: Any?) {
@@ -2370,6 +4025,8 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     private var utilityPanels: [ObjectIdentifier: [UtilityPanelRecord]] = [:]
     /// Monotonic counter behind the per-instance "browser-N" ledger IDs.
     private var nextBrowserLedgerID = 1
+    /// Persisted browser locations; see `BrowserPaneSessionStore`.
+    private let browserSessions = BrowserPaneSessionStore()
     private var nextSurfaceLedgerID = 1
     /// Agent surfaces opened as standalone windows: the controller must stay
     /// alive (it is the WKWebView's script-message handler) until close.
@@ -2408,6 +4065,25 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         return nil
     }
 
+    private func liveBrowserIDs() -> Set<String> {
+        Set(utilityPanels.values.flatMap { $0.compactMap { $0.browser?.browserID } })
+    }
+
+    /// Stop a browser or surface web view whose pane is going away and tell
+    /// subscribers. A closed pane's saved location is forgotten; only panes
+    /// still open at quit are offered back on the next launch.
+    private func tearDownWebContent(of record: UtilityPanelRecord, in win: NSWindow) {
+        if let browser = record.browser {
+            browser.cancelPendingAutomation()
+            browserSessions.remove(browser.browserID)
+            publish(.browserClosed(browserId: browser.browserID), in: win)
+        }
+        if let surface = record.surface {
+            surface.teardown()
+            publish(.surfaceClosed(surface: record.ledgerID), in: win)
+        }
+    }
+
     private func removeUtilityRecord(_ record: UtilityPanelRecord, windowKey id: ObjectIdentifier) {
         utilityPanels[id]?.removeAll { $0 === record }
         if utilityPanels[id]?.isEmpty == true { utilityPanels.removeValue(forKey: id) }
@@ -2451,6 +4127,12 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         _ = openUtilityPanel(.browser, in: win)
     }
 
+    /// Open (or focus) the workspace Notes pane in the key window.
+    @objc func openNotesPane(_ sender: Any?) {
+        guard let win = standardKeyWindow() else { return }
+        _ = openUtilityPanel(.notes, in: win)
+    }
+
     /// Always create an additional Browser pane, even when one already exists.
     @objc func newBrowserPane(_ sender: Any?) {
         guard let win = standardKeyWindow() else { return }
@@ -2649,6 +4331,31 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             tabs[index].performClose(nil)
         }
         chrome.strip.onNewTab = { [weak self] in self?.newTab(nil) }
+        chrome.strip.onUpgradePackages = { [weak self] in
+            guard case .failure(let error) = self?.startPackageUpgrade([]) else { return }
+            let alert = NSAlert()
+            alert.messageText = "Can't upgrade packages"
+            alert.informativeText = "\(error)."
+            alert.runModal()
+        }
+        chrome.strip.onTransformSelection = { [weak self, weak win] transform in
+            guard let self, let win else { return }
+            let selection = self.activeSessions(in: win).lazy.compactMap { $0.terminal.selectedText() }.first
+            self.showTransform(transform, of: selection)
+        }
+        chrome.strip.pinnedCommands = { [weak self, weak win] in
+            guard let self, let win,
+                  let directory = (self.focusedSession(in: win) ?? self.activeSessions(in: win).first)?
+                    .currentDirectory()
+            else { return [] }
+            return PinnedCommands.shared.pins(for: WorkspaceNotes.workspace(for: directory))
+        }
+        chrome.strip.onRunPinned = { [weak self, weak win] pin in
+            guard let self, let win,
+                  let s = self.focusedSession(in: win) ?? self.activeSessions(in: win).first else { return }
+            s.pty.write(Array(pin.command.utf8) + [0x0D])
+            self.refocusTerminal(in: win)
+        }
         chrome.strip.onRenameCommit = { [weak self, weak win] name in
             guard let self, let win else { return }
             let trimmed = name.trimmingCharacters(in: .whitespacesAndNewlines)
@@ -2807,7 +4514,9 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         in win: NSWindow,
         relativeTo requestedSource: NSView? = nil,
         vertical: Bool = true,
-        forceNewInstance: Bool = false
+        forceNewInstance: Bool = false,
+        browserID: String? = nil,
+        newFirst: Bool = false
     ) -> UtilityPanelRecord? {
         let id = ObjectIdentifier(win)
         // Files and Chat stay one-per-window; only Browser supports extra
@@ -2827,23 +4536,35 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 ?? terminalRoot(of: win),
               anchorView.window === win else { return nil }
 
-        let bg = Theme.dark.applying(config).background
-        let background = NSColor(
-            srgbRed: CGFloat(bg.x), green: CGFloat(bg.y), blue: CGFloat(bg.z),
-            alpha: CGFloat(bg.w))
+        let background = sampledBackground()
         let codeController: CodeViewController?
         let browserController: BrowserPaneController?
+        let notesController: WorkspaceNotesViewController?
         let contentView: NSView
         switch kind {
         case .files, .chat:
             let controller = CodeViewController(config: config, panelKind: kind)
             codeController = controller
             browserController = nil
+            notesController = nil
             contentView = controller.view
         case .browser:
-            let controller = BrowserPaneController()
+            // A restored pane keeps its id unless a live pane already has it.
+            let live = liveBrowserIDs()
+            let taken = live.union(browserSessions.records().map(\.browserID))
+            let controller = BrowserPaneController(
+                browserID: browserID.flatMap { live.contains($0) ? nil : $0 }
+                    ?? BrowserPaneController.makeBrowserID(avoiding: taken))
+            controller.backdrop = background.withAlphaComponent(config.backgroundOpacity)
             codeController = nil
             browserController = controller
+            notesController = nil
+            contentView = controller.view
+        case .notes:
+            let controller = WorkspaceNotesViewController()
+            codeController = nil
+            browserController = nil
+            notesController = controller
             contentView = controller.view
         case .surface:
             return nil // surfaces are agent-created via openSurfacePanel
@@ -2865,6 +4586,8 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             record = UtilityPanelRecord(controller: controller, pane: pane, ledgerID: ledgerID)
         } else if let controller = browserController {
             record = UtilityPanelRecord(browser: controller, pane: pane, ledgerID: ledgerID)
+        } else if let controller = notesController {
+            record = UtilityPanelRecord(notes: controller, pane: pane, ledgerID: ledgerID)
         } else {
             return nil
         }
@@ -2879,23 +4602,33 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             }
         }
         if let browser = browserController {
-            browser.onEvent = { [weak self, weak win] event in
-                guard let self, let win else { return }
-                self.appControl.broadcast(event)
-                if let name = event["event"] as? String {
-                    self.recordPaneLedgerNote(
-                        in: win, paneID: ledgerID, reason: name, origin: "browser-pane")
-                }
+            browser.onEvent = { [weak self, weak win, weak browser] event in
+                guard let self, let win, let browser else { return }
+                self.publish(.browser(browserId: browser.browserID, event), in: win)
+                // Requests come too often to be worth a ledger line each.
+                if case .networkRequest = event { return }
+                self.recordPaneLedgerNote(
+                    in: win, paneID: ledgerID, reason: event.name, origin: "browser-pane")
             }
             browser.onAnnotationsSubmitted = { [weak self, weak win] annotations in
                 guard let self, let win else { return }
                 self.submitBrowserAnnotations(annotations, in: win)
             }
+            browser.onLocationChange = { [weak self] location in
+                self?.browserSessions.save(location)
+            }
+            browser.bookmarkWorkspace = { [weak self, weak win] in
+                guard let self, let win,
+                      let session = self.focusedSession(in: win) ?? self.activeSessions(in: win).first,
+                      let directory = session.currentDirectory() else { return nil }
+                return WorkspaceNotes.workspace(for: directory)
+            }
         }
 
         wireUtilityPane(pane, record: record, in: win)
 
         let sourceSession = focusedSession(in: win) ?? activeSessions(in: win).first
+        if let controller = notesController, let sourceSession { controller.track(session: sourceSession) }
         if let controller = codeController {
             if let sourceSession { controller.track(session: sourceSession) }
             if kind == .chat {
@@ -2916,7 +4649,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 }
             }
         }
-        guard insertPaneView(pane, relativeTo: anchorView, vertical: vertical)
+        guard insertPaneView(pane, relativeTo: anchorView, vertical: vertical, newFirst: newFirst)
         else {
             recordPaneLedgerFailure(
                 in: win, paneID: ledgerID, reason: "utility-insert-failed",
@@ -2990,10 +4723,8 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         let controller = SurfacePaneController(request: request)
         let ledgerID = "surface-\(nextSurfaceLedgerID)"
         nextSurfaceLedgerID += 1
-        controller.onUIEvent = { [weak self] payload in
-            self?.appControl.broadcast([
-                "event": "ui", "surface": ledgerID, "payload": payload,
-            ])
+        controller.onUIEvent = { [weak self, weak session] payload in
+            self?.publish(.ui(surface: ledgerID, payload: payload), in: session?.view.window)
         }
 
         if request.target == .window {
@@ -3019,9 +4750,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 self?.surfaceWindowControllers[key]?.teardown()
                 self?.surfaceWindowControllers.removeValue(forKey: key)
                 self?.surfaceWindows.removeValue(forKey: ledgerID)
-                self?.appControl.broadcast([
-                    "event": "surface-closed", "surface": ledgerID,
-                ])
+                self?.publish(.surfaceClosed(surface: ledgerID))
             }
             return ledgerID
         }
@@ -3067,14 +4796,9 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         } else {
             record.pane.removeFromSuperview()
         }
-        if let browser = record.browser {
-            browser.cancelPendingAutomation()
-            appControl.broadcast(["event": "browser-closed", "browserId": browser.browserID])
-        }
-        if let surface = record.surface {
-            surface.teardown()
-            appControl.broadcast(["event": "surface-closed", "surface": record.ledgerID])
-        }
+        record.controller?.cancelBackgroundWork()
+        if record.kind == .chat { record.assistant?.cancelPendingRequests() }
+        tearDownWebContent(of: record, in: win)
         removeUtilityRecord(record, windowKey: id)
         recordPaneLedgerUtilityRemoved(
             paneID: record.ledgerID, in: win, reason: "utility-close", origin: "utility-pane")
@@ -3105,6 +4829,33 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     }
 
     /// Keep keyboard input in the terminal after sidebar toggles.
+    /// Runs a palette text transform on the selection and shows the result
+    /// with a button that copies it.
+    private func showTransform(_ transform: TextTransform, of selection: String?) {
+        let alert = NSAlert()
+        guard let selection, !selection.isEmpty else {
+            alert.messageText = "Nothing selected"
+            alert.informativeText = "Select text in a terminal pane first."
+            alert.runModal()
+            return
+        }
+        switch TextUtils.apply(transform, to: selection) {
+        case .success(let result):
+            alert.messageText = transform.title
+            alert.informativeText = result.count > 4000 ? String(result.prefix(4000)) + "…" : result
+            alert.addButton(withTitle: "Copy")
+            alert.addButton(withTitle: "Close")
+            if alert.runModal() == .alertFirstButtonReturn {
+                NSPasteboard.general.clearContents()
+                NSPasteboard.general.setString(result, forType: .string)
+            }
+        case .failure(let error):
+            alert.messageText = "Can't \(transform.title.lowercased())"
+            alert.informativeText = "\(error)."
+            alert.runModal()
+        }
+    }
+
     private func refocusTerminal(in win: NSWindow) {
         if win.firstResponder is TerminalView { return }
         let activeRoot = terminalRoot(of: win)
@@ -3189,8 +4940,10 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         return true
     }
 
-    private func queueLaunchCommand(_ command: String, for session: TerminalSession) {
-        pendingLaunchCommands[session.id] = command
+    private func queueLaunchCommand(
+        _ command: String, for session: TerminalSession, completion: ((Int) -> Void)? = nil
+    ) {
+        pendingLaunchCommands[session.id] = PendingLaunch(command: command, completion: completion)
         // OSC 133 A/B normally arrives first. This fallback supports shells
         // without Infinitty's integration and is cancelled by the first marker.
         DispatchQueue.main.asyncAfter(deadline: .now() + 1.2) { [weak self, weak session] in
@@ -3200,9 +4953,15 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
     }
 
     private func flushPendingLaunchCommand(for session: TerminalSession) {
-        guard let command = pendingLaunchCommands.removeValue(forKey: session.id) else { return }
+        guard let pending = pendingLaunchCommands.removeValue(forKey: session.id) else { return }
+        if let completion = pending.completion {
+            // Queued as it is typed, so the D marker that completes it is
+            // this command's and not the new shell's first prompt.
+            runQueues[session.id, default: []].append(
+                RunItem(id: UUID(), command: pending.command, completion: completion))
+        }
         session.view.showAgentGlow()
-        session.pty.write(Array(command.utf8) + [0x0D])
+        session.pty.write(Array(pending.command.utf8) + [0x0D])
     }
 
     private func recoverDetectedSessionInChat(_ detected: AgentSession) {
@@ -3259,7 +5018,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             }
             assistant.submitBrowserAnnotations(annotations)
             win.makeFirstResponder(record.pane)
-            broadcastBrowserAnnotationSubmission(annotations)
+            publishBrowserAnnotationSubmission(annotations, in: win)
             return
         }
         guard let record = openUtilityPanel(.chat, in: win) else { return }
@@ -3270,17 +5029,18 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         record.controller?.attachAssistant(assistant)
         assistant.submitBrowserAnnotations(annotations)
         win.makeFirstResponder(record.pane)
-        broadcastBrowserAnnotationSubmission(annotations)
+        publishBrowserAnnotationSubmission(annotations, in: win)
     }
 
-    private func broadcastBrowserAnnotationSubmission(_ annotations: [BrowserAnnotation]) {
+    private func publishBrowserAnnotationSubmission(
+        _ annotations: [BrowserAnnotation], in win: NSWindow
+    ) {
         guard let first = annotations.first else { return }
-        appControl.broadcast([
-            "event": "browser-annotation-submitted",
-            "origin": URL(string: first.url)?.host ?? "",
-            "documentId": first.documentID,
-            "annotationCount": annotations.count,
-        ])
+        publish(.browserAnnotationSubmitted(
+            origin: URL(string: first.url)?.host ?? "",
+            documentId: first.documentID,
+            annotationCount: annotations.count
+        ), in: win)
     }
 
     private func petAssistant(for session: TerminalSession) -> PetAssistant {
@@ -3353,7 +5113,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         _ message: String, for session: TerminalSession,
         timeout: TimeInterval = 8, onClick: (() -> Void)? = nil
     ) {
-        guard config.pet != nil, !message.isEmpty,
+        guard config.pet != nil, !message.isEmpty, !DoNotDisturb.shared.isActive,
               !session.view.isHiddenOrHasHiddenAncestor else { return }
         if petHiddenUntilNeeded {
             temporarilyRevealedPetSessionID = session.id
@@ -3386,19 +5146,101 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
     }
 
-    private func maybeShowRepoTip(for session: TerminalSession, cwd: String) {
-        guard config.pet != nil,
-              Date().timeIntervalSince(lastPetTipAt) > 90 else { return }
-        let sessionID = session.id
-        DispatchQueue.global(qos: .utility).async { [weak self, weak session] in
-            guard let root = PetTipScanner.repoRoot(for: cwd) else { return }
-            let tips = PetTipScanner.scan(directory: root)
-            guard !tips.isEmpty else { return }
-            DispatchQueue.main.async {
-                guard let self, let session,
-                      !(self.petTipShownRoots[sessionID]?.contains(root) ?? false),
-                      Date().timeIntervalSince(self.lastPetTipAt) > 90 else { return }
-                self.petTipShownRoots[sessionID, default: []].insert(root)
+    /// Follows each shell's directory (its OSC 7 reports, or the process
+    /// poll while the shell itself is in the foreground, so nothing is typed
+    /// into a running program) for `.infinitty.toml` files.
+    private func installProjectAutomationMonitor() {
+        projectAutomationObserver = NotificationCenter.default.addObserver(
+            forName: ForegroundProcessTracker.cwdDidChangeNotification,
+            object: nil,
+            queue: .main
+        ) { [weak self] notification in
+            guard let self, self.config.projectAutomation,
+                  let cwd = notification.userInfo?[ForegroundProcessTracker.cwdKey] as? String
+            else { return }
+            let session: TerminalSession?
+            if let tracker = notification.object as? ForegroundProcessTracker {
+                guard tracker.current?.pid == tracker.shellPid else { return }
+                session = self.sessions.first { $0.processTracker === tracker }
+            } else {
+                session = notification.object as? TerminalSession
+            }
+            guard let session else { return }
+            let sessionID = session.id
+            DispatchQueue.global(qos: .utility).async {
+                let path = ProjectAutomation.find(from: cwd)
+                DispatchQueue.main.async { [weak self, weak session] in
+                    guard let self, let session, self.projectAutomationFiles[sessionID] != path else { return }
+                    self.projectAutomationFiles[sessionID] = path
+                    guard let path, case .success(let automation) = ProjectAutomation.load(path: path) else { return }
+                    self.enterProject(automation, in: session)
+                }
+            }
+        }
+    }
+
+    /// Applies a trusted `.infinitty.toml`, asks about one not seen before
+    /// (or edited since), and leaves an ignored one alone.
+    private func enterProject(_ automation: ProjectAutomation, in session: TerminalSession) {
+        switch ProjectTrust.shared.decision(for: automation) {
+        case true?:
+            applyProjectAutomation(automation, in: session)
+        case false?:
+            return
+        case nil:
+            guard let win = session.view.window, !projectAutomationPrompts.contains(automation.path) else { return }
+            projectAutomationPrompts.insert(automation.path)
+            var summary: [String] = []
+            if !automation.env.isEmpty {
+                summary.append("Sets " + automation.env.keys.sorted().joined(separator: ", "))
+            }
+            if !automation.run.isEmpty {
+                summary.append("Runs:\n" + automation.run.map { "  " + $0 }.joined(separator: "\n"))
+            }
+            if let layout = automation.layout { summary.append("Applies the \(layout) layout") }
+            let alert = NSAlert()
+            alert.messageText = "Run \((automation.path as NSString).abbreviatingWithTildeInPath)?"
+            alert.informativeText = (summary.isEmpty ? ["It asks for nothing."] : summary).joined(separator: "\n")
+                + "\n\nYou'll be asked again if the file changes."
+            alert.addButton(withTitle: "Trust and Run")
+            alert.addButton(withTitle: "Ignore")
+            alert.beginSheetModal(for: win) { [weak self, weak session] response in
+                guard let self else { return }
+                self.projectAutomationPrompts.remove(automation.path)
+                let trusted = response == .alertFirstButtonReturn
+                ProjectTrust.shared.record(automation, trusted: trusted)
+                if trusted, let session { self.applyProjectAutomation(automation, in: session) }
+            }
+        }
+    }
+
+    /// Types the exports and commands at the prompt, and applies the layout
+    /// when the pane is alone in its tab, so a tab already split by hand
+    /// keeps its arrangement. Panes the layout adds count as already in the
+    /// project rather than running it again.
+    private func applyProjectAutomation(_ automation: ProjectAutomation, in session: TerminalSession) {
+        let input = automation.shellInput
+        if !input.isEmpty { session.pty.write(Array(input.utf8)) }
+        guard let name = automation.layout, let preset = LayoutPresetLibrary.shared.preset(named: name),
+              let win = session.view.window, paneLeafViews(in: win).count == 1,
+              let added = applyLayout(preset.shape, in: win)?["added"] as? [Int]
+        else { return }
+        for id in added { projectAutomationFiles[id] = automation.path }
+    }
+
+    private func maybeShowRepoTip(for session: TerminalSession, cwd: String) {
+        guard config.pet != nil,
+              Date().timeIntervalSince(lastPetTipAt) > 90 else { return }
+        let sessionID = session.id
+        DispatchQueue.global(qos: .utility).async { [weak self, weak session] in
+            guard let root = PetTipScanner.repoRoot(for: cwd) else { return }
+            let tips = PetTipScanner.scan(directory: root)
+            guard !tips.isEmpty else { return }
+            DispatchQueue.main.async {
+                guard let self, let session,
+                      !(self.petTipShownRoots[sessionID]?.contains(root) ?? false),
+                      Date().timeIntervalSince(self.lastPetTipAt) > 90 else { return }
+                self.petTipShownRoots[sessionID, default: []].insert(root)
                 let index = self.petTipRotation[root, default: 0]
                 self.petTipRotation[root] = index + 1
                 self.lastPetTipAt = Date()
@@ -3439,6 +5281,25 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         controller.showSearchResults(paths, query: query)
     }
 
+    /// Shows `path` selected in the Files pane of `session`'s window, or in
+    /// Finder. False when there is no Files pane to use or the tree has no
+    /// row for it.
+    @discardableResult
+    private func reveal(_ path: String, in target: RevealTarget, from session: TerminalSession) -> Bool {
+        switch target {
+        case .finder:
+            NSWorkspace.shared.activateFileViewerSelecting([URL(fileURLWithPath: path)])
+            return true
+        case .files:
+            guard let win = session.view.window,
+                  win.tabbingIdentifier == "infinitty",
+                  win !== quickTerminal.window,
+                  let controller = openCodeView(in: win) else { return false }
+            controller.track(session: session)
+            return controller.reveal(path: path)
+        }
+    }
+
     public func validateMenuItem(_ item: NSMenuItem) -> Bool {
         if item.action == #selector(toggleCodeView(_:)) {
             let standard = standardKeyWindow()
@@ -3447,163 +5308,3348 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             } != nil ? .on : .off
             return standard != nil
         }
-        if item.action == #selector(openBrowserPane(_:))
-            || item.action == #selector(newBrowserPane(_:)) {
-            return standardKeyWindow() != nil
+        if item.action == #selector(toggleInputLock(_:)) {
+            let focused = focusedSession()
+            item.state = focused?.pty.isInputLocked == true ? .on : .off
+            return focused != nil
+        }
+        if item.action == #selector(swapWithNextPane(_:))
+            || item.action == #selector(movePaneToNewTab(_:))
+            || item.action == #selector(equalizeSplits(_:)) {
+            guard let win = standardKeyWindow(), paneLeafViews(in: win).count > 1 else { return false }
+            return item.action != #selector(movePaneToNewTab(_:)) || focusedSession(in: win) != nil
+        }
+        if item.action == #selector(setPaneShare(_:)) {
+            return standardKeyWindow().flatMap { focusedPaneLeaf(in: $0) }?.superview is NSSplitView
+        }
+        if item.action == #selector(openBrowserPane(_:))
+            || item.action == #selector(newBrowserPane(_:))
+            || item.action == #selector(openNotesPane(_:)) {
+            return standardKeyWindow() != nil
+        }
+        return true
+    }
+
+    // MARK: - app control API (external apps / MCP)
+
+    /// Run work on the main thread from a socket thread, with a deadline.
+    private func onMain<T>(_ work: @escaping () -> T) -> T? {
+        if Thread.isMainThread { return work() }
+        var result: T?
+        let sem = DispatchSemaphore(value: 0)
+        CFRunLoopPerformBlock(CFRunLoopGetMain(), CFRunLoopMode.commonModes.rawValue) {
+            result = work()
+            sem.signal()
+        }
+        CFRunLoopWakeUp(CFRunLoopGetMain())
+        return sem.wait(timeout: .now() + 3) == .success ? result : nil
+    }
+
+    private func session(withID id: Int) -> TerminalSession? {
+        onMain { self.sessions.first { $0.id == id } } ?? nil
+    }
+
+    /// Browser actions are asynchronous WebKit work.  The app-control socket
+    /// runs on a worker thread, schedules the UI mutation on AppKit's main
+    /// thread, then waits only on that worker for the browser completion.
+    /// This deliberately avoids blocking the main run loop during navigation.
+    private func handleBrowserControl(_ encoded: String) -> String {
+        let request: [String: Any]
+        switch BrowserControlCodec.decode(encoded) {
+        case let .success(value): request = value
+        case let .failure(error):
+            return BrowserControlCodec.response(
+                error: "invalid_request", message: error.localizedDescription)
+        }
+        if let version = request["v"] as? Int, version != 1 {
+            return BrowserControlCodec.response(
+                error: "unsupported_version", message: "Browser request version \(version) is unsupported.")
+        }
+
+        let done = DispatchSemaphore(value: 0)
+        let operationState = BrowserControlOperation()
+        var response = BrowserControlCodec.response(
+            error: "browser_unavailable", message: "Browser control did not start.")
+        // An optional caller-chosen requestId makes the operation reachable
+        // by `cancel-operation` while this thread waits on it.
+        let requestID = (request["requestId"] as? String).flatMap { $0.isEmpty ? nil : $0 }
+        let cancellation = CancellationToken()
+        if let requestID { CancellationRegistry.shared.register(requestID, cancellation) }
+        defer { if let requestID { CancellationRegistry.shared.unregister(requestID) } }
+        cancellation.onCancel {
+            guard operationState.claimCompletion() else { return }
+            response = BrowserControlCodec.response(
+                error: "cancelled", message: "Browser operation was cancelled.")
+            done.signal()
+        }
+        let started = onMain { () -> Bool in
+            guard !operationState.isCancelled else { return false }
+            let operation = request["op"] as? String ?? ""
+            let finish: (String) -> Void = { value in
+                guard operationState.claimCompletion() else { return }
+                response = value
+                done.signal()
+            }
+
+            let resolved = BrowserControlOwnership.owner(
+                anchorPane: request["anchorPane"] as? Int,
+                windowOfPane: { pane in self.sessions.first { $0.id == pane }?.view.window },
+                keyWindow: NSApp.keyWindow, orderedWindows: NSApp.orderedWindows,
+                isMainWindow: { $0.tabbingIdentifier == "infinitty" && $0 !== self.quickTerminal.window })
+            func fail(_ failure: BrowserControlOwnership.Failure) {
+                finish(BrowserControlCodec.response(error: failure.code, message: failure.message))
+            }
+            let owner: NSWindow?
+            switch resolved {
+            case .success(let window): owner = window
+            case .failure(let failure):
+                fail(failure)
+                return true
+            }
+            func owned(_ record: UtilityPanelRecord) -> Bool {
+                owner != nil && record.pane.window === owner
+            }
+            func requireOwner() -> Bool {
+                guard owner == nil else { return true }
+                fail(.noOwner)
+                return false
+            }
+            func ownedBrowser(_ browserID: String) -> UtilityPanelRecord? {
+                guard let record = self.browserRecord(withID: browserID),
+                      record.browser != nil else {
+                    finish(BrowserControlCodec.response(
+                        error: "unknown_browser", message: "No live browser has id \(browserID)."))
+                    return nil
+                }
+                if case .failure(let failure) = BrowserControlOwnership.check(
+                    browserID, in: record.pane.window, owner: owner) {
+                    fail(failure)
+                    return nil
+                }
+                return record
+            }
+
+            if operation == "list" {
+                guard requireOwner() else { return true }
+                let browsers = self.utilityPanels.values
+                    .flatMap { $0 }
+                    .filter(owned)
+                    .compactMap { record -> [String: Any]? in
+                        guard let browser = record.browser else { return nil }
+                        var state = browser.controlState()
+                        if let window = record.pane.window,
+                           let tab = self.paneLedgerTabID(for: window) {
+                            state["tab"] = tab
+                        }
+                        return state
+                    }
+                finish(BrowserControlCodec.response(result: ["browsers": browsers]))
+                return true
+            }
+
+            if operation == "open" {
+                var browserRequest = request
+                if let url = request["url"] as? String, !url.isEmpty {
+                    browserRequest["op"] = "navigate"
+                } else {
+                    browserRequest["op"] = "state"
+                }
+
+                // Optional target: focus (and optionally navigate) a specific
+                // existing instance instead of the most recent one.
+                if let browserID = request["browserId"] as? String, !browserID.isEmpty {
+                    guard let record = ownedBrowser(browserID),
+                          let browser = record.browser else { return true }
+                    self.restorePaneZoom(revealing: record.pane)
+                    record.pane.window?.makeFirstResponder(record.pane)
+                    browser.performAutomation(
+                        browserRequest, isCancelled: { operationState.isCancelled },
+                        completion: finish)
+                    return true
+                }
+
+                let host = owner ?? NSApp.windows.first(where: {
+                    $0.tabbingIdentifier == "infinitty" && $0 !== self.quickTerminal.window
+                })
+
+                let window: NSWindow
+                if let host {
+                    window = host
+                } else {
+                    // A browser needs a real native main-tab host.  Keep the
+                    // initial terminal visible instead of creating a hidden
+                    // process; it also supplies the local AI/chat context.
+                    let created = self.makeTerminalWindow()
+                    window = created.0
+                    window.orderFront(nil)
+                    created.1.launch()
+                }
+                let wantsNewInstance = request["newPane"] as? Bool ?? false
+                guard let record = self.openUtilityPanel(
+                          .browser, in: window, forceNewInstance: wantsNewInstance),
+                      let browser = record.browser else {
+                    finish(BrowserControlCodec.response(
+                        error: "open_failed", message: "Could not create a browser pane."))
+                    return true
+                }
+                browser.performAutomation(
+                    browserRequest, isCancelled: { operationState.isCancelled }, completion: finish)
+                self.publish(.browserOpened(browserId: browser.browserID), in: window)
+                return true
+            }
+
+            guard let browserID = request["browserId"] as? String, !browserID.isEmpty else {
+                finish(BrowserControlCodec.response(
+                    error: "missing_browser", message: "browserId is required."))
+                return true
+            }
+            guard let browser = ownedBrowser(browserID)?.browser else { return true }
+            browser.performAutomation(
+                request, isCancelled: { operationState.isCancelled }, completion: finish)
+            return true
+        } ?? false
+
+        guard started else {
+            operationState.cancel()
+            return BrowserControlCodec.response(
+                error: "main_thread_timeout", message: "Could not schedule browser control on the main thread.")
+        }
+        guard done.wait(timeout: .now() + 40) == .success else {
+            operationState.cancel()
+            return BrowserControlCodec.response(
+                error: "timeout", message: "Browser operation timed out.")
+        }
+        return response
+    }
+
+    /// fs-copy / fs-move / fs-delete. Runs as a background task so progress
+    /// and `task-cancel` work like every other long job; the reply waits up
+    /// to two minutes and then returns the task snapshot, whose `result` is
+    /// the structured `FileOperationResult` once the task has finished.
+    private func handleFileOperation(_ cmd: String, _ arg: String) -> String {
+        let usage = cmd == "fs-delete" || cmd == "fs-hash"
+            ? "error: \(cmd) {\"path\": …}"
+            : "error: \(cmd) {\"from\": …, \"to\": …, \"conflict\": skip|overwrite|rename}"
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+        else { return usage }
+        func path(_ key: String) -> URL? {
+            guard let raw = request[key] as? String else { return nil }
+            let expanded = (raw as NSString).expandingTildeInPath
+            return expanded.hasPrefix("/") ? URL(fileURLWithPath: expanded) : nil
+        }
+        let conflictName = request["conflict"] as? String ?? FileConflictPolicy.skip.rawValue
+        guard let conflict = FileConflictPolicy(rawValue: conflictName) else {
+            return "error: conflict must be skip, overwrite or rename"
+        }
+
+        let task: BackgroundTask
+        if cmd == "fs-delete" {
+            guard let target = path("path") else { return usage }
+            task = BackgroundTaskRegistry.shared.start(kind: "delete", title: target.path) { task in
+                FileOperations.delete(target, isCancelled: { task.isCancelled }).jsonObject
+            }
+        } else if cmd == "fs-hash" {
+            guard let target = path("path") else { return usage }
+            task = BackgroundTaskRegistry.shared.start(kind: "hash", title: target.path) { task in
+                let digest = FileContents.sha256(
+                    of: target, isCancelled: { task.isCancelled },
+                    progress: { task.report(completed: $0) })
+                switch digest {
+                case .success(let hex): return ["sha256": hex]
+                case .failure(.cancelled): return nil
+                case .failure(let error): throw error
+                }
+            }
+        } else {
+            guard let from = path("from"), let to = path("to") else { return usage }
+            let title = "\(from.path) → \(to.path)"
+            task = BackgroundTaskRegistry.shared.start(
+                kind: cmd == "fs-copy" ? "copy" : "move", title: title
+            ) { task in
+                let result = cmd == "fs-copy"
+                    ? FileOperations.copy(from, to: to, conflict: conflict, isCancelled: { task.isCancelled })
+                    : FileOperations.move(from, to: to, conflict: conflict, isCancelled: { task.isCancelled })
+                return result.jsonObject
+            }
+        }
+        task.wait(timeout: 120)
+        let data = (try? JSONSerialization.data(withJSONObject: task.snapshot())) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// fs-read {"path", "limit"?}: capped UTF-8 text of a file. Larger
+    /// files come back truncated rather than read whole.
+    private func handleFileRead(_ arg: String) -> String {
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let raw = request["path"] as? String
+        else { return "error: fs-read {\"path\": …, \"limit\": bytes}" }
+        let expanded = (raw as NSString).expandingTildeInPath
+        guard expanded.hasPrefix("/") else { return "error: fs-read needs an absolute path" }
+        let limit = min(max(request["limit"] as? Int ?? 65_536, 1), Self.maxFileReadBytes)
+        switch FileContents.text(of: URL(fileURLWithPath: expanded), limit: limit, truncate: true) {
+        case .success(let contents):
+            let object: [String: Any] = [
+                "path": expanded, "size": contents.size,
+                "truncated": contents.truncated, "text": contents.text,
+            ]
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(expanded): \(error)"
+        }
+    }
+
+    /// Upper bound for fs-read, leaving room under the socket's 256 KB
+    /// response cap for JSON escaping.
+    private static let maxFileReadBytes = 200_000
+
+    /// `log-open {"path" | "command", "cwd", "filter", "level"}`: follow a
+    /// file or a command's output. Replies with the session id.
+    private func handleLogOpen(_ arg: String) -> String {
+        let usage = "error: log-open {\"path\" | \"command\": …, \"filter\": regex, \"level\": warn}"
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+        else { return usage }
+        let source: LogSession.Source
+        if let raw = request["path"] as? String, !raw.isEmpty {
+            let expanded = (raw as NSString).expandingTildeInPath
+            guard expanded.hasPrefix("/") else { return "error: log-open needs an absolute path" }
+            source = .file(expanded)
+        } else if let command = request["command"] as? String, !command.isEmpty {
+            let cwd = (request["cwd"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            source = .command(command, directory: cwd)
+        } else {
+            return usage
+        }
+        let filter: LogFilter
+        switch LogFilter.parse(pattern: request["filter"] as? String, level: request["level"] as? String) {
+        case .success(let parsed): filter = parsed
+        case .failure(let error): return "error: \(error)"
+        }
+        switch LogViewerRegistry.shared.open(source, filter: filter) {
+        case .success(let session):
+            let data = (try? JSONSerialization.data(withJSONObject: ["id": session.id])) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// The relay `share-start` and `share-join` go through, or the reason
+    /// sharing is unavailable.
+    private func shareRelay() -> Result<String, SessionShareError> {
+        let settings = onMain { (self.config.sessionSharing, self.config.shareRelay) }
+        guard settings?.0 == true else { return .failure(.disabled) }
+        guard let relay = settings?.1 else { return .failure(.noRelay) }
+        return .success(relay)
+    }
+
+    /// `share-start <id>`: stream a pane to guests through the configured
+    /// relay. Replies with the join code; `shares` never repeats it, so
+    /// whoever started the share decides who gets it. Guests watch
+    /// read-only until `share-mode` grants one of them input.
+    private func handleShareStart(_ arg: String) -> String {
+        guard let id = Int(arg.trimmingCharacters(in: .whitespaces)) else { return "error: share-start <id>" }
+        let relay: String
+        switch shareRelay() {
+        case .success(let configured): relay = configured
+        case .failure(let error): return "error: \(error)"
+        }
+        guard let s = session(withID: id) else { return "error: no pane \(id)" }
+        let host: SessionShareHost
+        switch SessionShareRegistry.shared.open(pane: id, relay: relay) {
+        case .success(let opened): host = opened
+        case .failure(let error): return "error: \(error)"
+        }
+        host.screen = { [weak s] in
+            guard let s else { return (0, 0, "") }
+            return (s.terminal.cols, s.terminal.rows, s.terminal.screenText())
+        }
+        host.onInput = { [weak s] data in s?.pty.write([UInt8](data)) }
+        s.setOutputTap("share") { [weak host] data in host?.publishOutput(data) }
+        host.start()
+        let reply: [String: Any] = ["pane": id, "code": host.code.string, "relay": relay]
+        let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `share-join <code> [name]`: watch someone else's share as a guest,
+    /// under `name` (the account's full name by default). Replies with
+    /// the joined share's `share-read` state; its id drives `share-send`
+    /// and `share-leave`.
+    private func handleShareJoin(_ arg: String) -> String {
+        let parts = arg.trimmingCharacters(in: .whitespaces).split(separator: " ", maxSplits: 1).map(String.init)
+        guard let code = parts.first else { return "error: share-join <code> [name]" }
+        let relay: String
+        switch shareRelay() {
+        case .success(let configured): relay = configured
+        case .failure(let error): return "error: \(error)"
+        }
+        let name = parts.count == 2 ? parts[1] : (NSFullUserName().isEmpty ? NSUserName() : NSFullUserName())
+        switch SessionShareRegistry.shared.join(code: code, name: name, relay: relay) {
+        case .success(let share):
+            let data = (try? JSONSerialization.data(withJSONObject: share.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `share-snapshot <id> [lines]`: the pane's scrollback as styled HTML,
+    /// uploaded to `snapshot-paste-url` when one is set, else served from
+    /// the loopback snapshot server.
+    private func handleShareSnapshot(_ arg: String) -> String {
+        let words = arg.split(separator: " ")
+        guard let first = words.first, let id = Int(first), words.count <= 2,
+              words.count == 1 || Int(words[1]).map({ $0 > 0 }) == true
+        else { return "error: share-snapshot <id> [lines]" }
+        let lines = words.count == 2 ? min(Int(words[1]) ?? 0, 100_000) : 100_000
+        let captured = onMain { () -> (session: TerminalSession, theme: Theme, paste: String?)? in
+            guard let s = self.sessions.first(where: { $0.id == id }) else { return nil }
+            return (s, Theme.dark.applying(self.config), self.config.snapshotPasteURL)
+        } ?? nil
+        guard let captured else { return "error: no pane \(id)" }
+        // Rows are copied under the terminal's own lock; the HTML is built
+        // here on the socket thread so a long scrollback never stalls AppKit.
+        let rows = captured.session.terminal.historyRows(lines: lines)
+        let title = onMain { captured.session.paneTitleOverride ?? captured.session.title } ?? "infinitty"
+        let html = PaneSnapshot.html(rows: rows, theme: captured.theme, title: title)
+        let result: Result<String, PaneSnapshotError>
+        let via: String
+        if let paste = captured.paste {
+            guard let endpoint = URL(string: paste), endpoint.scheme?.hasPrefix("http") == true else {
+                return "error: snapshot-paste-url must be an http(s) URL"
+            }
+            result = PaneSnapshot.upload(html, to: endpoint)
+            via = "paste"
+        } else {
+            result = PaneSnapshotServer.shared.publish(html)
+            via = "local"
+        }
+        switch result {
+        case .success(let url):
+            let reply: [String: Any] = ["url": url, "via": via, "lines": rows.count, "bytes": html.utf8.count]
+            let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// Upgrade `packages` (all outdated ones when empty) in a new tab, so
+    /// password prompts and build output stay in front of the user, while a
+    /// `packages-upgrade` background task reports which package is being
+    /// upgraded. Cancelling the task interrupts the pane. Main thread only.
+    private func startPackageUpgrade(
+        _ packages: [String]
+    ) -> Result<(task: BackgroundTask, session: TerminalSession), PackageQueryError> {
+        guard let detected = PackageManager.detect() else { return .failure(.noManager) }
+        let (manager, path) = (detected.manager, detected.path)
+        let command = manager.upgradeCommand(path: path, packages: packages)
+        let session = openTab(cwd: nil)
+        let progress = PackageUpgradeProgress(manager: manager, expected: packages)
+        let exitCode = LockedState<Int?>(nil)
+        let done = DispatchSemaphore(value: 0)
+        let title = ([manager.rawValue, "upgrade"] + packages).joined(separator: " ")
+        let task = BackgroundTaskRegistry.shared.start(
+            kind: "packages-upgrade", title: title
+        ) { [weak session] task in
+            defer { session?.setOutputTap("packages", nil) }
+            if packages.isEmpty,
+               case .success(let outdated) = PackageListing.query(manager, path: path, outdated: true) {
+                task.report(completed: 0, total: Int64(outdated.count))
+            } else if !packages.isEmpty {
+                task.report(completed: 0, total: Int64(packages.count))
+            }
+            var interrupted = false
+            while done.wait(timeout: .now() + 0.5) == .timedOut {
+                guard task.isCancelled, !interrupted else { continue }
+                interrupted = true
+                DispatchQueue.main.async { session?.pty.write([0x03]) }
+            }
+            task.report(completed: Int64(progress.started.count))
+            let code = exitCode.snapshot ?? -1
+            guard code == 0 else {
+                throw PackageQueryError.failed(
+                    code < 0 ? "the upgrade pane closed" : "upgrade exited with status \(code)")
+            }
+            return ["manager": manager.rawValue, "upgraded": progress.started, "pane": session?.id ?? 0]
+        }
+        session.setOutputTap("packages") { [weak task] data in
+            let begun = progress.ingest(data)
+            guard let task, let current = begun.last else { return }
+            task.report(completed: Int64(progress.started.count - 1), detail: "upgrading \(current)")
+        }
+        queueLaunchCommand(command, for: session) { code in
+            exitCode.withLock { $0 = code }
+            done.signal()
+        }
+        return .success((task, session))
+    }
+
+    /// `packages [outdated|installed]` lists through the detected manager;
+    /// `packages-upgrade [names…]` starts an upgrade and replies at once
+    /// with its task snapshot, since upgrades outlast any socket timeout.
+    private func handlePackages(_ cmd: String, _ arg: String) -> String {
+        let words = arg.split(separator: " ").map(String.init)
+        if cmd == "packages-upgrade" {
+            guard words.allSatisfy(PackageManager.isValidName) else {
+                return "error: packages-upgrade [name …]"
+            }
+            let started = onMain { self.startPackageUpgrade(words) }
+            switch started {
+            case .success(let upgrade)?:
+                var object = upgrade.task.snapshot()
+                object["pane"] = upgrade.session.id
+                let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+                return String(decoding: data, as: UTF8.self)
+            case .failure(let error)?:
+                return "error: \(error)"
+            case nil:
+                return "error: timed out opening the upgrade tab"
+            }
+        }
+        guard words.count <= 1, ["", "outdated", "installed"].contains(words.first ?? "") else {
+            return "error: packages [outdated | installed]"
+        }
+        guard let detected = PackageManager.detect() else { return "error: \(PackageQueryError.noManager)" }
+        switch PackageListing.query(detected.manager, path: detected.path, outdated: words.first != "installed") {
+        case .success(let packages):
+            let object: [String: Any] = [
+                "manager": detected.manager.rawValue, "path": detected.path,
+                "packages": packages.map(\.wire),
+            ]
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `cloud-set-profile <id> aws|gcp|azure [profile]`: export the profile's
+    /// variables into the pane's shell, or unset them when no profile is
+    /// named, and badge the pane header with it. Refused while a program
+    /// holds the foreground, since the line would be typed into it.
+    private func handleCloudSetProfile(_ arg: String) -> String {
+        let words = arg.split(separator: " ", maxSplits: 2).map(String.init)
+        guard words.count >= 2, let id = Int(words[0]),
+              let provider = CloudProvider(rawValue: words[1].lowercased())
+        else { return "error: cloud-set-profile <id> aws|gcp|azure [profile]" }
+        let name = words.count > 2 ? words[2].trimmingCharacters(in: .whitespaces) : ""
+        var profile: CloudProfile?
+        if !name.isEmpty {
+            let known = CloudProfiles.discover().filter { $0.provider == provider }
+            // Azure subscriptions can be picked by name or by id.
+            guard let match = known.first(where: { $0.name == name }) ?? known.first(where: { $0.value == name })
+            else { return "error: no \(provider.rawValue) profile '\(name)'" }
+            profile = match
+        }
+        return onMain { () -> String in
+            guard let s = self.sessions.first(where: { $0.id == id }) else { return "error: no pane \(id)" }
+            if let process = s.processTracker?.current, process.pid != s.pty.pid {
+                return "error: pane \(id) is running \(process.displayName); retry at its prompt"
+            }
+            s.pty.write(Array(CloudProfiles.exportCommand(provider, profile).utf8) + [0x0D])
+            s.setCloudProfile(profile, for: provider)
+            self.publish(.cloudProfile(pane: id, provider: provider, profile: profile), in: s.view.window)
+            return "ok"
+        } ?? "error: timed out"
+    }
+
+    /// `ssh-keys` audits `~/.ssh` and the agent; `ssh-keygen {"type",
+    /// "bits", "path", "comment", "passphrase"}` creates a key;
+    /// `ssh-copy-id {"host", "key", "port"}` opens a tab running ssh-copy-id
+    /// so the remote password prompt has somewhere to go.
+    private func handleSSHKeys(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        if cmd == "ssh-keys" {
+            // ssh-add exits 1 for an empty agent and 2 when there is none.
+            let agent = ProcessRunner.run("/usr/bin/ssh-add", ["-l", "-E", "sha256"], timeout: 5)
+            let output = try? agent.get()
+            let available = output.map { $0.status == 0 || $0.status == 1 } ?? false
+            let keys = SSHKeys.list(agentOutput: output?.status == 0 ? output?.stdoutText : nil)
+            return reply(["agent": available, "keys": keys.map(\.wire)] as [String: Any])
+        }
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+        else {
+            return cmd == "ssh-keygen"
+                ? "error: ssh-keygen {\"type\": ed25519|ecdsa|rsa, \"path\": …, \"comment\": …}"
+                : "error: ssh-copy-id {\"host\": \"user@host\", \"key\": \"~/.ssh/id_ed25519.pub\"}"
+        }
+        if cmd == "ssh-keygen" {
+            let spec: SSHKeys.KeygenSpec
+            do {
+                spec = try SSHKeys.KeygenSpec(json: request)
+            } catch {
+                return "error: \(error)"
+            }
+            switch SSHKeys.generate(spec) {
+            case .success(let key): return reply(key.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        guard let host = request["host"] as? String, SSHKeys.isValidHost(host) else {
+            return "error: ssh-copy-id needs a host like user@example.com"
+        }
+        let port = request["port"] as? Int
+        if let port, !(1...65_535).contains(port) { return "error: port must be 1-65535" }
+        var key = ((request["key"] as? String ?? "\(SSHKeys.directory)/id_ed25519.pub") as NSString)
+            .expandingTildeInPath
+        if !key.hasSuffix(".pub") { key += ".pub" }
+        guard key.hasPrefix("/"), FileManager.default.fileExists(atPath: key) else {
+            return "error: no public key at \(key)"
+        }
+        let command = SSHKeys.copyIDCommand(host: host, port: port, publicKey: key)
+        guard let pane = onMain({ self.openTab(cwd: nil, launchCommand: command).id }) else {
+            return "error: timed out opening a tab"
+        }
+        return reply(["pane": pane, "command": command] as [String: Any])
+    }
+
+    /// `net-dns <host> [type] [@server]` answers directly. `net-ping <host>
+    /// [count]` and `net-traceroute <host> [max-hops]` start a task and
+    /// reply with its snapshot; each reply or hop is pushed as a
+    /// `net-ping`/`net-traceroute` event and the summary is the task result.
+    private func handleNetwork(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        var words = arg.split(separator: " ").map(String.init)
+        guard !words.isEmpty else {
+            switch cmd {
+            case "net-dns": return "error: net-dns <host> [A|AAAA|CNAME|MX|NS|TXT|SOA|PTR|SRV|CAA] [@server]"
+            case "net-ping": return "error: net-ping <host> [count]"
+            default: return "error: net-traceroute <host> [max-hops]"
+            }
+        }
+        let host = words.removeFirst()
+        guard NetworkDiagnostics.isValidHost(host) else { return "error: \(NetworkToolError.invalidHost(host))" }
+
+        if cmd == "net-dns" {
+            let server = words.last.flatMap { $0.hasPrefix("@") ? String($0.dropFirst()) : nil }
+            if server != nil { words.removeLast() }
+            guard let type = DNSRecordType(rawValue: (words.first ?? "A").uppercased()) else {
+                return "error: record type must be one of "
+                    + DNSRecordType.allCases.map(\.rawValue).joined(separator: ", ")
+            }
+            switch NetworkDiagnostics.lookup(host: host, type: type, server: server) {
+            case .success(let object): return reply(object)
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+
+        let tool = cmd == "net-ping" ? "ping" : "traceroute"
+        let (name, limit, fallback) = tool == "ping" ? ("count", 1...100, 5) : ("max-hops", 1...64, 30)
+        guard let count = words.isEmpty ? fallback : Int(words[0]), limit.contains(count) else {
+            return "error: \(name) must be \(limit.lowerBound)-\(limit.upperBound)"
+        }
+        let task = BackgroundTaskRegistry.shared.start(kind: cmd, title: "\(tool) \(host)") { [weak self] task in
+            let result: Result<[String: Any], NetworkToolError>
+            if tool == "ping" {
+                var seen: Int64 = 0
+                result = NetworkDiagnostics.ping(host: host, count: count, cancellation: task.cancellation) { echo in
+                    seen += 1
+                    task.report(
+                        completed: seen, total: Int64(count),
+                        detail: echo.rtt.map { "seq \(echo.sequence): \($0) ms" } ?? "seq \(echo.sequence): timeout")
+                    self?.appControl.broadcast(.netSample(task: task.id, tool: tool, sample: echo.wire))
+                }
+            } else {
+                result = NetworkDiagnostics.traceroute(
+                    host: host, maxHops: count, cancellation: task.cancellation
+                ) { hop in
+                    let detail = hop.probes.lazy.compactMap(\.address).first ?? "*"
+                    task.report(completed: Int64(hop.hop), total: Int64(count), detail: "\(hop.hop): \(detail)")
+                    self?.appControl.broadcast(.netSample(task: task.id, tool: tool, sample: hop.wire))
+                }
+            }
+            if task.isCancelled { return nil }
+            return try result.get()
+        }
+        return reply(task.snapshot())
+    }
+
+    /// `hosts` lists the monitored hosts; `host-watch {"host", "name",
+    /// "mac", "interval"}` adds or updates one and `host-unwatch <host>`
+    /// drops it. `net-wol <mac | watched host> [broadcast]` sends a magic
+    /// packet, taking the MAC from the watch list when given a host.
+    private func handleHosts(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        switch cmd {
+        case "hosts":
+            return reply(HostMonitor.shared.list().map(\.wire))
+        case "host-unwatch":
+            return HostMonitor.shared.unwatch(arg) ? "ok" : "error: not watching \(arg)"
+        case "host-watch":
+            guard let data = arg.data(using: .utf8),
+                  let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+                  let host = request["host"] as? String
+            else { return "error: host-watch {\"host\": …, \"name\"?, \"mac\"?, \"interval\"?: seconds}" }
+            guard NetworkDiagnostics.isValidHost(host) else { return "error: \(NetworkToolError.invalidHost(host))" }
+            let mac = request["mac"] as? String
+            if let mac, WakeOnLAN.parseMAC(mac) == nil { return "error: not a MAC address: \(mac)" }
+            let interval = (request["interval"] as? NSNumber)?.doubleValue ?? WatchedHost.defaultInterval
+            guard WatchedHost.intervals.contains(interval) else {
+                return "error: interval must be \(Int(WatchedHost.intervals.lowerBound))-"
+                    + "\(Int(WatchedHost.intervals.upperBound)) seconds"
+            }
+            let watched = WatchedHost(host: host, name: request["name"] as? String, mac: mac, interval: interval)
+            return reply(HostMonitor.shared.watch(watched).wire)
+        default:
+            let words = arg.split(separator: " ").map(String.init)
+            guard let target = words.first else { return "error: net-wol <mac | watched host> [broadcast-address]" }
+            let saved = HostMonitor.shared.watched(target)?.mac
+            guard let mac = WakeOnLAN.parseMAC(target) ?? saved.flatMap(WakeOnLAN.parseMAC) else {
+                return "error: \(target) is neither a MAC address nor a watched host with one"
+            }
+            let address = words.count > 1 ? words[1] : "255.255.255.255"
+            switch WakeOnLAN.send(mac: mac, address: address) {
+            case .success:
+                let hex = mac.map { String(format: "%02x", $0) }.joined(separator: ":")
+                return reply(["mac": hex, "broadcast": address] as [String: Any])
+            case .failure(let error):
+                return "error: \(error)"
+            }
+        }
+    }
+
+    /// `text-util {"op", "text" | "pane" | "path", "key"}`: an encoding,
+    /// decoding or hash (`TextTransform`) of inline text, a pane's selection,
+    /// or — for hashes — a file; `jwt-verify` checks a token's signature
+    /// against `key`.
+    private func handleTextUtil(_ arg: String) -> String {
+        let operations = TextTransform.allCases.map(\.rawValue) + ["jwt-verify"]
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let op = request["op"] as? String
+        else {
+            return "error: text-util {\"op\": \(operations.joined(separator: "|")), "
+                + "\"text\" | \"pane\" | \"path\": …}"
+        }
+        if let raw = request["path"] as? String {
+            guard let algorithm = HashAlgorithm(rawValue: op) else { return "error: only hashes take a path" }
+            let expanded = (raw as NSString).expandingTildeInPath
+            guard expanded.hasPrefix("/") else { return "error: path must be absolute" }
+            switch TextUtils.hashFile(URL(fileURLWithPath: expanded), algorithm: algorithm) {
+            case .success(let hex): return hex
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        let text: String
+        if let inline = request["text"] as? String {
+            text = inline
+        } else if let id = request["pane"] as? Int {
+            guard let s = session(withID: id) else { return "error: no pane \(id)" }
+            guard let selection = s.terminal.selectedText() else { return "error: nothing selected in pane \(id)" }
+            text = selection
+        } else {
+            return "error: text-util needs text, pane or path"
+        }
+        if op == "jwt-verify" {
+            guard let key = request["key"] as? String else { return "error: jwt-verify needs the key" }
+            switch TextUtils.verifyJWT(text, key: key) {
+            case .success(let valid):
+                let algorithm = (try? TextUtils.decodeJWT(text).get())?.algorithm ?? ""
+                let object: [String: Any] = ["valid": valid, "algorithm": algorithm]
+                let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+                return String(decoding: data, as: UTF8.self)
+            case .failure(let error):
+                return "error: \(error)"
+            }
+        }
+        guard let transform = TextTransform(rawValue: op) else {
+            return "error: op must be one of \(operations.joined(separator: ", "))"
+        }
+        switch TextUtils.apply(transform, to: text) {
+        case .success(let result): return result
+        case .failure(let error): return "error: \(error)"
+        }
+    }
+
+    /// `regex-test {"pattern", "flags", "sample", "replace"}` reports
+    /// matches and groups, or where the pattern is broken;
+    /// `regex-patterns`, `regex-save {"name", "pattern", "flags",
+    /// "sample"}` and `regex-delete <name>` manage the saved library.
+    private func handleRegex(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        switch cmd {
+        case "regex-patterns":
+            return reply(RegexLibrary.shared.all().map(\.wire))
+        case "regex-delete":
+            return RegexLibrary.shared.delete(arg) ? "ok" : "error: no saved pattern named \(arg)"
+        default:
+            break
+        }
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let pattern = request["pattern"] as? String
+        else {
+            return cmd == "regex-save"
+                ? "error: regex-save {\"name\": …, \"pattern\": …, \"flags\": \"i\", \"sample\": …}"
+                : "error: regex-test {\"pattern\": …, \"flags\": \"im\", \"sample\": …, \"replace\": \"$1\"}"
+        }
+        let flags = request["flags"] as? String ?? ""
+        if cmd == "regex-save" {
+            let entry = SavedPattern(
+                name: request["name"] as? String ?? "", pattern: pattern, flags: flags,
+                sample: request["sample"] as? String)
+            switch RegexLibrary.shared.save(entry) {
+            case .success(let saved): return reply(saved.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        let result = RegexScratchpad.test(
+            pattern: pattern, flags: flags, sample: request["sample"] as? String ?? "",
+            template: request["replace"] as? String)
+        switch result {
+        case .success(let object):
+            return reply(object.merging(["valid": true]) { a, _ in a })
+        case .failure(.invalidPattern(let reason, let offset)):
+            var object: [String: Any] = ["valid": false, "error": reason]
+            if let offset { object["offset"] = offset }
+            return reply(object)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `eval <expression>`: the palette's calculator — arithmetic, unit
+    /// conversion and currency at cached ECB rates (`rates` is their date).
+    private func handleEval(_ arg: String) -> String {
+        switch Calculator.evaluate(arg) {
+        case .success(let result):
+            var object = result.wire
+            if result.usesCurrency, let table = CurrencyRates.shared.table { object["rates"] = table.date }
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(.noRate(let code)):
+            return "error: no exchange rate cached for \(code) yet; fetching, try again in a moment"
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `speech-start [locale]` starts dictation once access is granted (the
+    /// first time that means answering the system prompts); transcripts
+    /// stream as `speech` events. `speech-stop` returns the text so far.
+    private func handleSpeech(_ cmd: String, _ arg: String) -> String {
+        if cmd == "speech-stop" {
+            guard let text = onMain({ SpeechInput.shared.stop() }) else { return "error: timed out" }
+            let data = (try? JSONSerialization.data(withJSONObject: ["text": text])) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let locale = arg.trimmingCharacters(in: .whitespaces)
+        let done = DispatchSemaphore(value: 0)
+        var outcome: Result<Void, SpeechInputError>?
+        DispatchQueue.main.async {
+            SpeechInput.shared.start(locale: locale.isEmpty ? nil : locale) { result in
+                outcome = result
+                done.signal()
+            }
+        }
+        // Long enough for someone to answer the permission prompts.
+        guard done.wait(timeout: .now() + 60) == .success, let outcome else {
+            return "error: timed out waiting for speech access"
+        }
+        switch outcome {
+        case .success: return "ok"
+        case .failure(let error): return "error: \(error)"
+        }
+    }
+
+    /// `timetrack-report [range]` sums tracked time per workspace (today by
+    /// default). `pomodoro <minutes>` starts a timer for the focused pane's
+    /// workspace, `pomodoro stop` ends it early, and bare `pomodoro` reports it.
+    private func handleTimeTracking(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: [String: Any]) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let value = arg.trimmingCharacters(in: .whitespaces)
+        if cmd == "timetrack-report" {
+            guard config.timeTracking || !TimeTracker.shared.spans().isEmpty else {
+                return "error: time tracking is off (set time-tracking = true)"
+            }
+            switch TimeTracker.shared.report(range: value.isEmpty ? "today" : value) {
+            case .success(let report): return reply(report)
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        switch value.lowercased() {
+        case "":
+            return reply(TimeTracker.shared.pomodoro?.wire() ?? [:])
+        case "stop":
+            guard let stopped = TimeTracker.shared.stopPomodoro() else { return "error: no pomodoro running" }
+            return reply(stopped.wire())
+        default:
+            guard let minutes = Int(value), (1...240).contains(minutes) else {
+                return "error: pomodoro [minutes (1-240) | stop]"
+            }
+            let directory = onMain { self.focusedSession()?.currentDirectory() } ?? nil
+            let pomodoro = TimeTracker.shared.startPomodoro(
+                minutes: minutes, workspace: directory.map { TimeTracker.workspace(for: $0) })
+            return reply(pomodoro.wire())
+        }
+    }
+
+    /// The workspace notes store. Each command takes a JSON object ({"dir",
+    /// "text", "id", "done", "scan"}) or plain text for its main field;
+    /// `dir` defaults to the focused pane's directory and resolves to its
+    /// repo root. `todos-list` with "scan" adds the TODO/FIXME comments.
+    private func handleWorkspaceNotes(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: [String: Any]) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: \(cmd) expects a JSON object"
+            }
+            request = object
+        } else if !trimmed.isEmpty {
+            let field = ["notes-get": "dir", "todos-list": "dir", "todos-toggle": "id"][cmd] ?? "text"
+            request[field] = trimmed
+        }
+        let directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            ?? onMain { self.focusedSession()?.currentDirectory() } ?? nil
+        guard let directory else { return "error: no focused pane; pass \"dir\"" }
+        let workspace = WorkspaceNotes.workspace(for: directory)
+        let store = WorkspaceNotes.shared
+        switch cmd {
+        case "notes-get":
+            return reply(store.wire(for: workspace))
+        case "notes-set":
+            guard let text = request["text"] as? String else { return "error: notes-set {\"text\", \"dir\"?}" }
+            store.setNotes(text, for: workspace)
+            return "ok"
+        case "todos-add":
+            switch store.addTodo(request["text"] as? String ?? "", to: workspace) {
+            case .success(let todo): return reply(todo.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        case "todos-toggle":
+            guard let id = (request["id"] as? String) ?? (request["id"] as? Int).map(String.init) else {
+                return "error: todos-toggle <id>"
+            }
+            switch store.toggleTodo(id, in: workspace, done: request["done"] as? Bool) {
+            case .success(let todo): return reply(todo.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        default:
+            var object: [String: Any] = ["workspace": workspace, "todos": store.todos(for: workspace).map(\.wire)]
+            if request["scan"] as? Bool == true {
+                object["comments"] = WorkspaceNotes.scanComments(in: workspace).map(\.wire)
+            }
+            return reply(object)
+        }
+    }
+
+    /// `git-askpass` is the helper a git command runs when it needs a
+    /// credential, holding the connection until someone answers; the rest
+    /// let a client see and answer those prompts, or forget what the
+    /// keychain remembers for them.
+    private func handleGitCredentials(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        switch cmd {
+        case "git-askpass":
+            let parts = arg.split(separator: " ", maxSplits: 1, omittingEmptySubsequences: false)
+            guard parts.count == 2 else { return "error: git-askpass <token> <prompt>" }
+            guard let answer = GitAskpass.shared.ask(token: String(parts[0]), prompt: String(parts[1])) else {
+                return "error: no answer"
+            }
+            return "answer:" + answer
+        case "git-credentials":
+            return reply(GitAskpass.shared.pending.map(\.wire))
+        case "git-credential-answer":
+            let usage = "error: git-credential-answer {\"id\", \"answer\" | \"cancel\", \"remember\"}"
+            let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let id = (request["id"] as? String) ?? (request["id"] as? Int).map(String.init)
+            else { return usage }
+            let answer = request["answer"] as? String
+            guard answer != nil || request["cancel"] as? Bool == true else { return usage }
+            guard GitAskpass.shared.answer(id, with: answer, remember: request["remember"] as? Bool == true) else {
+                return "error: no prompt \(id) is waiting"
+            }
+            return "ok"
+        default:
+            let url = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+            let prompts = GitAskpass.rememberedPrompts(for: url.isEmpty ? nil : url)
+            return reply(["forgotten": prompts.filter(GitAskpass.forget(prompt:))])
+        }
+    }
+
+    /// The pinned-commands store, like the notes commands: a JSON object
+    /// ({"dir", "command", "title", "hotkey", "id", "index", "pane"}) or
+    /// plain text for the main field, `dir` defaulting to the focused
+    /// pane's workspace. `pins-run` types the pin into `pane` (the focused
+    /// one by default) and presses Return.
+    private func handlePinnedCommands(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        func result<T>(_ result: Result<T, PinnedCommandError>, _ wire: (T) -> Any) -> String {
+            switch result {
+            case .success(let value): return reply(wire(value))
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: \(cmd) expects a JSON object"
+            }
+            request = object
+        } else if !trimmed.isEmpty {
+            request[cmd == "pins-add" ? "command" : cmd == "pins-list" ? "dir" : "id"] = trimmed
+        }
+        let pane: TerminalSession?
+        if let id = request["pane"] as? Int {
+            guard let found = onMain({ self.session(withID: id) }) ?? nil else { return "error: no pane \(id)" }
+            pane = found
+        } else {
+            pane = onMain { self.focusedSession() } ?? nil
+        }
+        let directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            ?? onMain { pane?.currentDirectory() } ?? nil
+        guard let directory else { return "error: no focused pane; pass \"dir\"" }
+        let workspace = WorkspaceNotes.workspace(for: directory)
+        let store = PinnedCommands.shared
+        let id = (request["id"] as? String) ?? (request["id"] as? Int).map(String.init)
+        switch cmd {
+        case "pins-add":
+            return result(store.add(
+                request["command"] as? String ?? "", title: request["title"] as? String,
+                hotkey: request["hotkey"] as? String, to: workspace)) { $0.wire }
+        case "pins-edit":
+            guard let id else { return "error: pins-edit {\"id\", \"command\"?, \"title\"?, \"hotkey\"?}" }
+            return result(store.edit(
+                id, in: workspace, command: request["command"] as? String, title: request["title"] as? String,
+                hotkey: request["hotkey"] as? String)) { $0.wire }
+        case "pins-remove":
+            guard let id else { return "error: pins-remove <id>" }
+            return result(store.remove(id, in: workspace)) { $0.wire }
+        case "pins-move":
+            guard let id, let index = request["index"] as? Int else { return "error: pins-move {\"id\", \"index\"}" }
+            return result(store.move(id, to: index, in: workspace)) { ["workspace": workspace, "pins": $0.map(\.wire)] }
+        case "pins-run":
+            guard let id else { return "error: pins-run <id>" }
+            guard let pin = store.pins(for: workspace).first(where: { $0.id == id }) else {
+                return "error: \(PinnedCommandError.notFound(id))"
+            }
+            guard let pane else { return "error: no pane to run in" }
+            pane.pty.write(Array(pin.command.utf8) + [0x0D])
+            return reply(pin.wire.merging(["pane": pane.id]) { a, _ in a })
+        default:
+            return reply(["workspace": workspace, "pins": store.pins(for: workspace).map(\.wire)])
+        }
+    }
+
+    /// A workspace's startup commands, and a pane's run of them: the
+    /// profile's and the workspace's lines with their exit codes so far.
+    private func handleStartupCommands(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: \(cmd) expects a JSON object"
+            }
+            request = object
+        } else if let id = Int(trimmed) {
+            request["pane"] = id
+        } else if !trimmed.isEmpty {
+            request["dir"] = trimmed
+        }
+        let pane: TerminalSession?
+        if let id = request["pane"] as? Int {
+            guard let found = onMain({ self.session(withID: id) }) ?? nil else { return "error: no pane \(id)" }
+            pane = found
+        } else {
+            pane = request["dir"] == nil ? onMain { self.focusedSession() } ?? nil : nil
+        }
+        let directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            ?? onMain { pane?.currentDirectory() } ?? nil
+        guard let directory else { return "error: no focused pane; pass \"dir\"" }
+        let workspace = WorkspaceNotes.workspace(for: directory)
+        if cmd == "startup-commands-set" {
+            guard let commands = request["commands"] as? [String] else {
+                return "error: startup-commands-set {\"commands\": [...], \"dir\"? | \"pane\"?}"
+            }
+            let kept = WorkspaceStartup.shared.setCommands(commands, for: workspace)
+            return reply(["workspace": workspace, "commands": kept])
+        }
+        var object: [String: Any] = [
+            "workspace": workspace, "commands": WorkspaceStartup.shared.commands(for: workspace),
+        ]
+        if let pane {
+            object["pane"] = pane.id
+            if let profile = onMain({ pane.profile }) ?? nil { object["profile"] = profile.startup }
+            if let run = onMain({ pane.startup }) ?? nil { object["run"] = run.wire }
+        }
+        return reply(object)
+    }
+
+    /// Keyboard macros: a pane's input recorded with its timing, kept by
+    /// name and typed back into any pane.
+    private func handleMacros(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: \(cmd) expects a JSON object"
+            }
+            request = object
+        } else if !trimmed.isEmpty {
+            request["name"] = trimmed
+        }
+        let store = KeyboardMacros.shared
+        let paneID = request["pane"] as? Int
+        let target: TerminalSession?
+        if let paneID {
+            guard let found = onMain({ self.session(withID: paneID) }) ?? nil else { return "error: no pane \(paneID)" }
+            target = found
+        } else {
+            target = onMain { self.focusedSession() } ?? nil
+        }
+        switch cmd {
+        case "macro-record-start":
+            let name: String
+            switch KeyboardMacros.name(request["name"] as? String) {
+            case .success(let value): name = value
+            case .failure(let error): return "error: \(error)"
+            }
+            guard let target else { return "error: no focused pane; pass \"pane\"" }
+            return onMain {
+                if let recording = self.macroRecordings[target.id] {
+                    return "error: pane \(target.id) is already recording '\(recording.snapshot.name)'"
+                }
+                let recording = LockedState(MacroRecorder(name: name, pane: target.id))
+                target.pty.inputTap = { [weak target] bytes in
+                    // A password typed at a prompt stays out of the macro.
+                    guard target?.secureInput.current == nil else { return }
+                    let now = ProcessInfo.processInfo.systemUptime
+                    recording.withLock { $0.record(bytes, at: now) }
+                }
+                self.macroRecordings[target.id] = recording
+                return reply(["name": name, "pane": target.id, "recording": true])
+            } ?? "error: app is shutting down"
+        case "macro-record-stop":
+            let name = request["name"] as? String
+            let focused = paneID == nil && name == nil ? target?.id : nil
+            let stopped = onMain { () -> MacroRecorder? in
+                let recordings = self.macroRecordings
+                let key = paneID ?? recordings.first(where: { $0.value.snapshot.name == name })?.key
+                    ?? focused.flatMap { recordings[$0] == nil ? nil : $0 }
+                    ?? (name == nil && recordings.count == 1 ? recordings.keys.first : nil)
+                guard let key, let recording = self.macroRecordings.removeValue(forKey: key) else { return nil }
+                self.session(withID: key)?.pty.inputTap = nil
+                return recording.snapshot
+            } ?? nil
+            guard let stopped else { return "error: nothing is recording" + (name.map { " as '\($0)'" } ?? "") }
+            switch stopped.macro() {
+            case .success(let macro):
+                store.save(macro)
+                return reply(macro.wire.merging(["pane": stopped.pane]) { a, _ in a })
+            case .failure(let error):
+                return "error: \(error)"
+            }
+        case "macro-play":
+            guard let name = request["name"] as? String else {
+                return "error: macro-play <name> | {\"name\", \"pane\"?, \"speed\"?}"
+            }
+            let speed = request["speed"] as? Double ?? 1
+            guard speed > 0 else { return "error: \(KeyboardMacroError.invalidSpeed(speed))" }
+            let macro: KeyboardMacro
+            switch store.macro(named: name) {
+            case .success(let found): macro = found
+            case .failure(let error): return "error: \(error)"
+            }
+            guard let target else { return "error: no focused pane; pass \"pane\"" }
+            let schedule = macro.schedule(speed: speed)
+            DispatchQueue.global(qos: .userInitiated).async { [weak target] in
+                let start = ProcessInfo.processInfo.systemUptime
+                for step in schedule {
+                    let wait = step.at - (ProcessInfo.processInfo.systemUptime - start)
+                    if wait > 0 { Thread.sleep(forTimeInterval: wait) }
+                    guard let target else { return }
+                    target.pty.write(step.bytes)
+                }
+            }
+            return reply(macro.wire.merging([
+                "pane": target.id, "speed": speed, "seconds": ((schedule.last?.at ?? 0) * 1000).rounded() / 1000,
+            ]) { a, _ in a })
+        case "macro-remove":
+            guard let name = request["name"] as? String else { return "error: macro-remove <name>" }
+            switch store.remove(named: name) {
+            case .success(let macro): return reply(macro.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        default:
+            let recording = onMain { self.macroRecordings.values.map(\.snapshot) } ?? []
+            return reply([
+                "macros": store.macros.map(\.wire),
+                "recording": recording.map { ["name": $0.name, "pane": $0.pane, "steps": $0.steps.count] },
+            ])
+        }
+    }
+
+    /// Browser bookmarks, in the shape of the pins commands: a JSON object
+    /// ({"dir", "url", "title", "folder", "id", "index", "from", "to",
+    /// "pane"}) or plain text for the main field, `dir` defaulting to the
+    /// focused pane's workspace. `bookmarks-open` focuses a browser pane
+    /// already showing the page, else opens a new one on it.
+    private func handleBrowserBookmarks(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        func result<T>(_ result: Result<T, BrowserBookmarkError>, _ wire: (T) -> Any) -> String {
+            switch result {
+            case .success(let value): return reply(wire(value))
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: \(cmd) expects a JSON object"
+            }
+            request = object
+        } else if !trimmed.isEmpty {
+            request[cmd == "bookmarks-add" ? "url" : cmd == "bookmarks-list" ? "dir" : "id"] = trimmed
+        }
+        let pane: TerminalSession?
+        if let id = request["pane"] as? Int {
+            guard let found = onMain({ self.session(withID: id) }) ?? nil else { return "error: no pane \(id)" }
+            pane = found
+        } else {
+            pane = onMain { self.focusedSession() } ?? nil
+        }
+        let directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            ?? onMain { pane?.currentDirectory() } ?? nil
+        guard let directory else { return "error: no focused pane; pass \"dir\"" }
+        let workspace = WorkspaceNotes.workspace(for: directory)
+        let store = BrowserBookmarks.shared
+        let id = (request["id"] as? String) ?? (request["id"] as? Int).map(String.init)
+        func list(_ bookmarks: [BrowserBookmark]) -> [String: Any] {
+            ["workspace": workspace, "bookmarks": bookmarks.map(\.wire), "folders": store.folders(for: workspace)]
+        }
+        switch cmd {
+        case "bookmarks-add":
+            return result(store.add(
+                request["url"] as? String ?? "", title: request["title"] as? String,
+                folder: request["folder"] as? String, to: workspace)) { $0.wire }
+        case "bookmarks-edit":
+            guard let id else { return "error: bookmarks-edit {\"id\", \"url\"?, \"title\"?, \"folder\"?}" }
+            return result(store.edit(
+                id, in: workspace, url: request["url"] as? String, title: request["title"] as? String,
+                folder: request["folder"] as? String)) { $0.wire }
+        case "bookmarks-remove":
+            if let folder = request["folder"] as? String {
+                return result(store.removeFolder(folder, in: workspace)) { ["removed": $0.map(\.wire)] }
+            }
+            guard let id else { return "error: bookmarks-remove <id> | {\"folder\"}" }
+            return result(store.remove(id, in: workspace)) { $0.wire }
+        case "bookmarks-move":
+            guard let id, let index = request["index"] as? Int else {
+                return "error: bookmarks-move {\"id\", \"index\"}"
+            }
+            return result(store.move(id, to: index, in: workspace)) { list($0) }
+        case "bookmarks-folder-rename":
+            guard let from = request["from"] as? String, let to = request["to"] as? String else {
+                return "error: bookmarks-folder-rename {\"from\", \"to\"}"
+            }
+            return result(store.renameFolder(from, to: to, in: workspace)) { list($0) }
+        case "bookmarks-open":
+            guard let id else { return "error: bookmarks-open <id>" }
+            guard let bookmark = store.bookmarks(for: workspace).first(where: { $0.id == id }) else {
+                return "error: \(BrowserBookmarkError.notFound(id))"
+            }
+            return openBookmark(bookmark)
+        default:
+            return reply(list(store.bookmarks(for: workspace)))
+        }
+    }
+
+    /// Focuses the browser pane already on `bookmark`'s page (ignoring a
+    /// trailing slash or fragment), or opens a new pane there, through the
+    /// browser `open` op. Its `{bookmark, browser}` or the op's error.
+    private func openBookmark(_ bookmark: BrowserBookmark) -> String {
+        func page(_ url: String) -> String {
+            var page = url.split(separator: "#", maxSplits: 1).first.map(String.init) ?? url
+            if page.hasSuffix("/") { page.removeLast() }
+            return page
+        }
+        let showing = onMain { () -> String? in
+            self.utilityPanels.values.flatMap { $0 }.compactMap(\.browser).first { browser in
+                (browser.controlState()["url"] as? String).map(page) == page(bookmark.url)
+            }?.browserID
+        } ?? nil
+        var open: [String: Any] = ["v": 1, "op": "open"]
+        if let showing {
+            open["browserId"] = showing
+        } else {
+            open["url"] = bookmark.url
+            open["newPane"] = true
+        }
+        guard let encoded = BrowserControlCodec.encode(open),
+              let data = handleBrowserControl(encoded).data(using: .utf8),
+              let response = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any] else {
+            return "error: could not open a browser pane"
+        }
+        guard response["ok"] as? Bool == true else {
+            let message = (response["error"] as? [String: Any])?["message"] as? String
+            return "error: \(message ?? "could not open a browser pane")"
+        }
+        let object: [String: Any] = ["bookmark": bookmark.wire, "browser": response["result"] ?? [:]]
+        let json = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: json, as: UTF8.self)
+    }
+
+    /// `project-automation [dir]` shows the `.infinitty.toml` that applies
+    /// at `dir` (the focused pane's directory by default) with its trust
+    /// state; `project-trust` and `project-untrust` decide for its current
+    /// content without waiting for the prompt.
+    private func handleProjectAutomation(_ cmd: String, _ arg: String) -> String {
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        let directory = trimmed.isEmpty
+            ? onMain { self.focusedSession()?.currentDirectory() } ?? nil
+            : (trimmed as NSString).expandingTildeInPath
+        guard let directory else { return "error: no focused pane; pass a directory" }
+        guard let path = ProjectAutomation.find(from: directory) else {
+            guard cmd == "project-automation" else {
+                return "error: no \(ProjectAutomation.fileName) at or above \(directory)"
+            }
+            return "{}"
+        }
+        let automation: ProjectAutomation
+        switch ProjectAutomation.load(path: path) {
+        case .success(let loaded): automation = loaded
+        case .failure(let error): return "error: \(path): \(error)"
+        }
+        if cmd != "project-automation" { ProjectTrust.shared.record(automation, trusted: cmd == "project-trust") }
+        let trust = ProjectTrust.shared.decision(for: automation).map { $0 ? "trusted" : "ignored" } ?? "ask"
+        let object = automation.wire.merging(["trust": trust]) { a, _ in a }
+        let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `output-diff <id> [<line> <line>]` diffs the output of two finished
+    /// commands in a pane, named by the line their output began on (as
+    /// `fold` lists them). Without lines it compares the latest command
+    /// with the last earlier run of the same command line, or else with
+    /// the command before it. `{"a": {"pane", "line"}, "b": {…},
+    /// "context"}` compares across panes.
+    private func handleOutputDiff(_ arg: String) -> String {
+        let usage = "error: output-diff <id> [<line> <line>] | {\"a\": {\"pane\", \"line\"}, \"b\": {…}}"
+        typealias Side = (pane: Int, line: Int?)
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var sides: (a: Side, b: Side)?
+        var context = 3
+        if trimmed.hasPrefix("{") {
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let a = request["a"] as? [String: Any], let b = request["b"] as? [String: Any],
+                  let paneA = (a["pane"] as? NSNumber)?.intValue
+            else { return usage }
+            let paneB = (b["pane"] as? NSNumber)?.intValue ?? paneA
+            sides = ((paneA, (a["line"] as? NSNumber)?.intValue), (paneB, (b["line"] as? NSNumber)?.intValue))
+            context = min(max((request["context"] as? NSNumber)?.intValue ?? context, 0), 100)
+        } else {
+            let words = trimmed.split(separator: " ").compactMap { Int($0) }
+            guard let pane = words.first, words.count == 1 || words.count == 3 else { return usage }
+            if words.count == 3 { sides = ((pane, words[1]), (pane, words[2])) }
+            if words.count == 1 {
+                guard let s = onMain({ self.session(withID: pane) }) ?? nil else { return "error: no pane \(pane)" }
+                let blocks = s.terminal.commandOutputBlocks()
+                guard let latest = blocks.last, blocks.count > 1 else {
+                    return "error: pane \(pane) needs two finished commands with output (enable OSC 133)"
+                }
+                let earlier = blocks.dropLast()
+                let previous = earlier.last { latest.command != nil && $0.command == latest.command } ?? earlier.last
+                sides = ((pane, previous?.line), (pane, latest.line))
+            }
+        }
+        guard let (a, b) = sides else { return usage }
+        var outputs: [(pane: Int, block: CommandBlock, lines: [String])] = []
+        for side in [a, b] {
+            guard let s = onMain({ self.session(withID: side.pane) }) ?? nil else {
+                return "error: no pane \(side.pane)"
+            }
+            switch s.terminal.commandOutput(line: side.line) {
+            case .success(let output): outputs.append((side.pane, output.block, output.lines))
+            case .failure(let error): return "error: pane \(side.pane): \(error)"
+            }
+        }
+        let rows = CodeDiff.compare(outputs[0].lines, outputs[1].lines, context: context)
+        func described(_ output: (pane: Int, block: CommandBlock, lines: [String])) -> [String: Any] {
+            output.block.wire.merging(["pane": output.pane]) { a, _ in a }
+        }
+        let reply: [String: Any] = [
+            "a": described(outputs[0]), "b": described(outputs[1]), "identical": rows.isEmpty,
+            "added": rows.filter { $0.kind == .add }.count, "removed": rows.filter { $0.kind == .del }.count,
+            "lines": rows.map(\.wire),
+        ]
+        let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `watch-start {"command", "interval"?, "paths"?, "pane"?, "dir"?}`
+    /// re-runs a command every `interval` seconds, when files under `paths`
+    /// change, or both (every 2s when neither is given), and publishes a
+    /// `watch` event for each run whose output moved. A pane lends its
+    /// directory and ties the watch to its lifetime; without one the watch
+    /// runs in `dir` (or the home directory) until `watch-stop <id>`.
+    /// `watches` lists them and `watch-output <id>` returns the last run's
+    /// lines with the ones it changed marked.
+    private func handleCommandWatches(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let id = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        switch cmd {
+        case "watches":
+            return reply(CommandWatcher.shared.list().map(\.wire))
+        case "watch-stop":
+            return CommandWatcher.shared.stop(id) ? "ok" : "error: \(CommandWatchError.notFound(id))"
+        case "watch-output":
+            guard let output = CommandWatcher.shared.output(id) else {
+                return "error: \(CommandWatchError.notFound(id))"
+            }
+            return reply(output)
+        default:
+            break
+        }
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let command = request["command"] as? String
+        else {
+            return "error: watch-start {\"command\": …, \"interval\"?: seconds, \"paths\"?: […], "
+                + "\"pane\"?: id, \"dir\"?}"
+        }
+        let pane = (request["pane"] as? NSNumber)?.intValue
+        var directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+        if let pane {
+            let found = onMain { () -> (Bool, String?) in
+                guard let s = self.session(withID: pane) else { return (false, nil) }
+                return (true, s.currentDirectory())
+            } ?? (false, nil)
+            guard found.0 else { return "error: no pane \(pane)" }
+            directory = directory ?? found.1
+        }
+        let paths = request["paths"] as? [String] ?? (request["paths"] as? String).map { [$0] } ?? []
+        switch CommandWatcher.shared.start(
+            command: command, directory: directory ?? NSHomeDirectory(), pane: pane,
+            interval: (request["interval"] as? NSNumber)?.doubleValue, paths: paths)
+        {
+        case .success(let watch): return reply(watch.wire)
+        case .failure(let error): return "error: \(error)"
+        }
+    }
+
+    /// `tmux-attach [session]` or `tmux-attach {"socket", "session"}`
+    /// attaches to a running tmux server in control mode and shows its
+    /// windows as tabs of native panes; `socket` is a `-L` name or a `-S`
+    /// path. `tmux-detach <id>` leaves the session running and closes the
+    /// tabs, `tmux-clients` lists attachments, and `tmux-command <id>
+    /// <command>` runs any tmux command and returns its output lines.
+    private func handleTmux(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        func request(_ client: TmuxControlClient, _ command: String) -> Result<[String], TmuxError> {
+            let done = DispatchSemaphore(value: 0)
+            let result = LockedState<Result<[String], TmuxError>>(.failure(.failed("no reply")))
+            client.send(command) { answer in
+                result.withLock { $0 = answer }
+                done.signal()
+            }
+            _ = done.wait(timeout: .now() + 10)
+            return result.snapshot
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        switch cmd {
+        case "tmux-clients":
+            let clients = onMain {
+                self.tmuxClients.values.sorted { $0.id.localizedStandardCompare($1.id) == .orderedAscending }
+            } ?? []
+            return reply(clients.map { $0.wire.merging(["panes": $0.boundPanes]) { a, _ in a } })
+        case "tmux-detach":
+            guard let client = onMain({ self.tmuxClients[trimmed] }) ?? nil else {
+                return "error: \(TmuxError.notFound(trimmed))"
+            }
+            client.detach()
+            return "ok"
+        case "tmux-command":
+            let parts = trimmed.split(separator: " ", maxSplits: 1).map(String.init)
+            guard parts.count == 2 else { return "error: tmux-command <id> <tmux command>" }
+            guard let client = onMain({ self.tmuxClients[parts[0]] }) ?? nil else {
+                return "error: \(TmuxError.notFound(parts[0]))"
+            }
+            switch request(client, parts[1]) {
+            case .success(let lines): return reply(["lines": lines])
+            case .failure(let error): return "error: \(error)"
+            }
+        default:
+            var socket: String?
+            var session: String? = trimmed.isEmpty ? nil : trimmed
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return "error: tmux-attach [session] | {\"socket\", \"session\"}" }
+                socket = object["socket"] as? String
+                session = object["session"] as? String
+            }
+            let attached = onMain { self.attachTmux(socket: socket, session: session) }
+                ?? .failure(.launchFailed("app is shutting down"))
+            let client: TmuxControlClient
+            switch attached {
+            case .success(let started): client = started
+            case .failure(let error): return "error: \(error)"
+            }
+            // A server with no such session exits here instead of answering.
+            let windows: [(id: Int, layout: TmuxLayout)]
+            switch request(client, "list-windows -F '#{window_id} #{window_layout}'") {
+            case .success(let lines):
+                windows = lines.compactMap { line in
+                    let fields = line.split(separator: " ", maxSplits: 1)
+                    guard fields.count == 2, let id = Int(fields[0].dropFirst()),
+                          let layout = TmuxLayout.parse(String(fields[1]))
+                    else { return nil }
+                    return (id, layout)
+                }
+            case .failure(let error):
+                client.detach()
+                return "error: \(error)"
+            }
+            _ = onMain {
+                for window in windows { self.applyTmuxLayout(window.layout, window: window.id, client: client) }
+            }
+            return reply(client.wire.merging(["windows": windows.map(\.id), "panes": client.boundPanes]) { a, _ in a })
+        }
+    }
+
+    /// `serial-ports` lists the devices a `serial-open <port> [baud]` or
+    /// `serial-open {port, baud, dataBits, parity, stopBits, flowControl}`
+    /// can open as a pane, and which pane has each open.
+    private func handleSerial(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard cmd == "serial-open" else {
+            let open = onMain { self.serialConnections.mapValues(\.settings.port) } ?? [:]
+            return reply(SerialPort.list().map { path -> [String: Any] in
+                var object: [String: Any] = ["path": path, "name": SerialPort.name(of: path)]
+                if let pane = open.first(where: { $0.value == path })?.key { object["pane"] = pane }
+                return object
+            })
+        }
+        let parsed: Result<SerialSettings, SerialError>
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: serial-open <port> [baud] | {\"port\", \"baud\", ...}"
+            }
+            parsed = SerialSettings.parse(object)
+        } else {
+            let parts = trimmed.split(separator: " ").map(String.init)
+            guard let port = parts.first else { return "error: serial-open <port> [baud] | {\"port\", \"baud\", ...}" }
+            var object: [String: Any] = ["port": port]
+            if parts.count > 1 { object["baud"] = Int(parts[1]) ?? -1 }
+            parsed = SerialSettings.parse(object)
+        }
+        let settings: SerialSettings
+        switch parsed {
+        case .success(let value): settings = value
+        case .failure(let error): return "error: \(error)"
+        }
+        switch onMain({ self.openSerial(settings) }) {
+        case .success(let s)?: return reply(settings.wire.merging(["pane": s.id]) { a, _ in a })
+        case .failure(let error)?: return "error: \(error)"
+        case nil: return "error: timed out"
+        }
+    }
+
+    /// `daemon-status` says whether new shells go to infinitty-ptyd and
+    /// lists the shells it holds, with the pane showing each. `daemon-enable
+    /// [on|off]` sets `daemon` in settings.conf (starting the daemon when
+    /// on) and answers with the status; `daemon-attach [id]` reopens one
+    /// shell, or every detached one, as tabs.
+    private func handleDaemon(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        switch cmd {
+        case "daemon-attach":
+            var ids: [Int]?
+            if !trimmed.isEmpty {
+                guard let id = Int(trimmed) else { return "error: daemon-attach [id]" }
+                ids = [id]
+            }
+            let attached = onMain { () -> Result<[[String: Any]], PTYDaemonError> in
+                self.reattachDaemonShells(ids).map { sessions in
+                    sessions.map { ["pane": $0.id, "id": self.daemonPanes[$0.id]?.id ?? 0] }
+                }
+            }
+            switch attached {
+            case .success(let panes)?: return reply(["panes": panes])
+            case .failure(let error)?: return "error: \(error)"
+            case nil: return "error: timed out"
+            }
+        case "daemon-enable":
+            let enable: Bool
+            switch trimmed.lowercased() {
+            case "", "on", "true": enable = true
+            case "off", "false": enable = false
+            default: return "error: daemon-enable [on|off]"
+            }
+            let saved = onMain { () -> String? in
+                self.config.daemon = enable
+                do {
+                    try self.config.saveAll()
+                    return nil
+                } catch {
+                    return "\(error)"
+                }
+            }
+            guard let saved else { return "error: timed out" }
+            if let saved { return "error: could not save settings: \(saved)" }
+            if enable, case .failure(let error) = PTYDaemon.start() { return "error: \(error)" }
+        default:
+            break
+        }
+        let (enabled, owned) = onMain { (self.config.daemon, self.daemonPanes.mapValues(\.id)) } ?? (false, [:])
+        var status: [String: Any] = ["enabled": enabled, "socket": PTYDaemon.socketPath]
+        switch PTYDaemon.list() {
+        case .success(let listing):
+            status["running"] = true
+            status["pid"] = Int(listing.pid)
+            status["sessions"] = listing.sessions.map { shell -> [String: Any] in
+                var object = shell.wire
+                if let pane = owned.first(where: { $0.value == shell.id })?.key { object["pane"] = pane }
+                return object
+            }
+        case .failure:
+            status["running"] = false
+            status["sessions"] = [[String: Any]]()
+        }
+        return reply(status)
+    }
+
+    /// `secure-input [id]` says whether a pane (or which panes) are at a
+    /// password prompt and which Keychain entries are offered there;
+    /// `secure-fill <id> [account]` types one in. `secure-store {account,
+    /// password, prompt?}` and `secure-forget <account>` manage the entries.
+    private func handleSecureInput(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        func state(of s: TerminalSession) -> [String: Any] {
+            var object: [String: Any] = ["pane": s.id, "active": false]
+            if let prompt = s.secureInput.current {
+                object.merge(prompt.wire) { a, _ in a }
+                object["active"] = true
+                object["fills"] = PromptPasswords.entries(matching: prompt.prompt).map(\.account)
+            }
+            return object
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        switch cmd {
+        case "secure-fill":
+            let parts = trimmed.split(separator: " ", maxSplits: 1).map(String.init)
+            guard let id = parts.first.flatMap(Int.init), let s = session(withID: id) else {
+                return "error: secure-fill <id> [account]"
+            }
+            return fillPromptPassword(in: s, account: parts.count > 1 ? parts[1] : nil).map { "error: \($0)" } ?? "ok"
+        case "secure-store":
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let account = object["account"] as? String, !account.isEmpty,
+                  let password = object["password"] as? String else {
+                return "error: secure-store {\"account\", \"password\", \"prompt\"}"
+            }
+            let pattern = (object["prompt"] as? String).flatMap { $0.isEmpty ? nil : $0 }
+            return PromptPasswords.store(account: account, password: password, pattern: pattern)
+                .map { "error: \($0)" } ?? "ok"
+        case "secure-forget":
+            guard !trimmed.isEmpty else { return "error: secure-forget <account>" }
+            return PromptPasswords.remove(account: trimmed) ? "ok" : "error: no entry \(trimmed)"
+        default:
+            if trimmed.isEmpty {
+                return reply(onMain { self.sessions.filter(\.secureInput.isActive) }?.map(state(of:)) ?? [])
+            }
+            guard let id = Int(trimmed), let s = session(withID: id) else { return "error: secure-input [id]" }
+            return reply(state(of: s))
+        }
+    }
+
+    /// The argument of `new-window`, `new-tab` and `new-detached`: a
+    /// directory, or {"cwd", "profile"}. `failure` is the reply to send back
+    /// when it doesn't resolve.
+    private func newPaneOptions(
+        _ cmd: String, _ arg: String
+    ) -> (cwd: String?, profile: TerminalProfile?, failure: String?) {
+        let trimmed = arg.trimmingCharacters(in: .whitespaces)
+        var directory: String? = trimmed.isEmpty ? nil : trimmed
+        var profile: TerminalProfile?
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return (nil, nil, "error: \(cmd) [dir] or {\"cwd\", \"profile\"}")
+            }
+            directory = (object["cwd"] as? String).flatMap { $0.isEmpty ? nil : $0 }
+            if let name = object["profile"] as? String {
+                guard let named = TerminalProfiles.shared.profile(named: name) else {
+                    return (nil, nil, "error: \(ProfileError.notFound(name))")
+                }
+                profile = named
+            }
+        }
+        guard let directory else { return (nil, profile, nil) }
+        guard let cwd = LaunchOptions.workingDirectory(from: [directory]) else {
+            return (nil, nil, "error: no such directory: \(directory)")
+        }
+        return (cwd, profile, nil)
+    }
+
+    /// `profiles [name]` lists saved profiles (or shows one) with which is
+    /// the default; `profile-save {"name", "shell"?, "args"?, "env"?,
+    /// "startup"?, "cwd"?}` adds or replaces one and `profile-remove <name>`
+    /// deletes it. Profiles apply to panes opened afterwards.
+    private func handleProfiles(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        let store = TerminalProfiles.shared
+        switch cmd {
+        case "profile-save":
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: profile-save {\"name\", \"shell\", \"args\", \"env\", \"startup\", \"cwd\"}"
+            }
+            switch TerminalProfile.parse(object) {
+            case .success(let profile):
+                store.save(profile)
+                return reply(profile.wire)
+            case .failure(let error):
+                return "error: \(error)"
+            }
+        case "profile-remove":
+            guard !trimmed.isEmpty else { return "error: profile-remove <name>" }
+            switch store.remove(trimmed) {
+            case .success: return "ok"
+            case .failure(let error): return "error: \(error)"
+            }
+        default:
+            let defaultName = onMain { self.config.defaultProfile } ?? nil
+            func wire(_ profile: TerminalProfile) -> [String: Any] {
+                var object = profile.wire
+                object["default"] = profile.name == defaultName
+                return object
+            }
+            if trimmed.isEmpty { return reply(store.all.map(wire)) }
+            guard let profile = store.profile(named: trimmed) else { return "error: \(ProfileError.notFound(trimmed))" }
+            return reply(wire(profile))
+        }
+    }
+
+    /// `idle-panes` lists how long each pane has been idle against the
+    /// policy; `pane-hibernate <id>` and `pane-resume <id>` do by hand what
+    /// the `hibernate` action and a key press do.
+    private func handleIdlePanes(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        guard cmd != "idle-panes" else {
+            let listing = onMain { () -> [String: Any] in
+                let now = Date()
+                let panes = self.sessions.map { s -> [String: Any] in
+                    [
+                        "pane": s.id, "idleSeconds": now.timeIntervalSince(s.pty.lastActivity).rounded(),
+                        "atPrompt": self.isAtPrompt(s), "hibernated": s.isHibernated,
+                    ]
+                }
+                return ["policy": self.config.idlePolicy.wire, "panes": panes]
+            }
+            guard let listing else { return "error: timed out" }
+            return reply(listing)
+        }
+        guard let id = Int(arg.trimmingCharacters(in: .whitespaces)), let s = session(withID: id) else {
+            return "error: \(cmd) <id>"
+        }
+        let done = onMain { () -> Bool in
+            if cmd == "pane-resume" { return self.resumePane(s) }
+            return self.hibernatePane(s, idleSeconds: Date().timeIntervalSince(s.pty.lastActivity))
+        }
+        switch (cmd, done) {
+        case (_, nil): return "error: timed out"
+        case ("pane-resume", false): return "error: pane \(id) is not hibernated, or its shell is still ending"
+        case (_, false): return "error: pane \(id) can't hibernate (already is, or its shell isn't local)"
+        default: return reply(["pane": id, "hibernated": cmd == "pane-hibernate"])
+        }
+    }
+
+    /// `pane-processes <id>` lists the pane's shell and everything under it;
+    /// `pane-signal <id> <signal> [pid]` signals one of those processes, or
+    /// without a pid the foreground job's process group.
+    private func handlePaneProcesses(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let words = arg.split(separator: " ").map(String.init)
+        let usage = cmd == "pane-processes" ? "error: pane-processes <id>" : "error: pane-signal <id> <signal> [pid]"
+        guard let id = words.first.flatMap(Int.init), let s = session(withID: id),
+              cmd == "pane-processes" ? words.count == 1 : (2...3).contains(words.count)
+        else { return usage }
+        guard let shell = onMain({ s.shellPID }) ?? nil else { return "error: pane \(id) has no local shell" }
+        guard cmd == "pane-signal" else {
+            return reply(["pane": id, "shellPid": Int(shell),
+                          "processes": ProcessTree.processes(under: shell).map(\.wire)])
+        }
+        guard let signal = PaneSignal(name: words[1]) else {
+            return "error: \(ProcessSignalError.unknownSignal(words[1]))"
+        }
+        var pid: pid_t?
+        if words.count == 3 {
+            guard let given = pid_t(words[2]) else { return usage }
+            pid = given
+        }
+        switch ProcessTree.send(signal, to: pid, under: shell) {
+        case .success(let targets):
+            return reply(["pane": id, "signal": signal.rawValue, "pids": targets.map { Int($0.pid) }])
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `paste <id> <text>` or `paste <id> {"text", "confirm"}` pastes as the
+    /// keyboard would, through `PasteCheck`: control characters stripped,
+    /// bracketed when the program asked. A paste with concerns comes back
+    /// unsent, listing them, unless `confirm` is true or
+    /// `paste-confirmation` is off.
+    private func handlePaste(_ arg: String) -> String {
+        let usage = "error: paste <id> <text> | <id> {\"text\", \"confirm\"}"
+        let parts = arg.split(separator: " ", maxSplits: 1)
+        guard parts.count == 2, let id = Int(parts[0]), let s = session(withID: id) else { return usage }
+        let rest = String(parts[1])
+        var text = rest
+        var confirmed = false
+        if rest.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(rest.utf8))) as? [String: Any],
+                  let given = object["text"] as? String, !given.isEmpty else { return usage }
+            text = given
+            confirmed = object["confirm"] as? Bool ?? false
+        }
+        guard !s.pty.isInputLocked else { return "error: pane \(s.id) is input-locked" }
+        let check = PasteCheck(text, bracketed: s.terminal.bracketedPasteEnabled)
+        var reply = check.wire
+        reply["pane"] = s.id
+        let asks = onMain { self.config.pasteConfirmation } ?? true
+        if asks, !confirmed, !check.concerns.isEmpty {
+            reply["pasted"] = false
+            reply["needsConfirmation"] = true
+            _ = onMain { self.publish(.pasteHeld(pane: s.id, check), in: s.view.window) }
+        } else {
+            _ = onMain { s.view.showAgentGlow() }
+            s.pty.write(check.bytes)
+            reply["pasted"] = true
+        }
+        let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `reveal-in-explorer <id> <path>` selects a path in the Files pane;
+    /// `reveal-in-finder <id> <path>` shows it in Finder. A relative path is
+    /// read from the pane's directory and a `:line:column` suffix is allowed,
+    /// so paths can be passed as the pane printed them.
+    private func handleReveal(_ cmd: String, _ arg: String) -> String {
+        let usage = "error: \(cmd) <id> <path>"
+        let parts = arg.split(separator: " ", maxSplits: 1)
+        guard parts.count == 2, let id = Int(parts[0]), let s = session(withID: id) else { return usage }
+        let text = String(parts[1])
+        guard let path = RevealPath.resolve(text, cwd: s.currentDirectory()) else {
+            return "error: no such file: \(text)"
+        }
+        let target: RevealTarget = cmd == "reveal-in-finder" ? .finder : .files
+        guard onMain({ self.reveal(path, in: target, from: s) }) == true else {
+            return "error: can't show \(path) in the Files pane of pane \(s.id)"
+        }
+        let data = (try? JSONSerialization.data(withJSONObject: ["pane": s.id, "path": path, "in": target.rawValue]))
+            ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `workspace` reads the open windows, tabs and splits as they would be
+    /// saved, `workspace-save` writes them now, and `workspace-restore`
+    /// reopens the saved ones beside whatever is open.
+    private func handleWorkspace(_ cmd: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        switch cmd {
+        case "workspace-save":
+            let saved = onMain { () -> WorkspaceLayout? in
+                let layout = self.currentWorkspace()
+                return WorkspaceStore.shared.save(layout) ? layout : nil
+            } ?? nil
+            guard let saved else { return "error: could not write \(WorkspaceStore.shared.url.path)" }
+            var object = saved.wire
+            object["path"] = WorkspaceStore.shared.url.path
+            return reply(object)
+        case "workspace-restore":
+            guard let saved = WorkspaceStore.shared.load() else { return "error: no saved workspace" }
+            let started = onMain { self.restoreWorkspace(saved).map(\.id) } ?? []
+            return started.isEmpty ? "error: the saved workspace has no panes to open" : reply(["panes": started])
+        default:
+            return reply(onMain { self.currentWorkspace().wire } ?? [:])
+        }
+    }
+
+    /// `clipboard-write <text> | {"text", "transforms"}` puts text on the
+    /// clipboard through the same clean-up as a copy from a pane (the
+    /// configured `copy-transforms` unless given; `"none"` for raw), and
+    /// `clipboard-copy <id>` copies a pane's selection.
+    private func handleClipboard(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        var text = arg
+        var transforms = onMain { self.config.copyTransforms } ?? CopyTransform.defaults
+        var object: [String: Any] = [:]
+        if cmd == "clipboard-copy" {
+            let usage = "error: clipboard-copy <id>"
+            guard let id = Int(arg.trimmingCharacters(in: .whitespaces)), let s = session(withID: id) else {
+                return usage
+            }
+            guard let selected = s.terminal.selectedText() else { return "error: nothing selected in pane \(id)" }
+            text = selected
+            object["pane"] = id
+        } else if arg.hasPrefix("{") {
+            let usage = "error: clipboard-write <text> | {\"text\", \"transforms\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(arg.utf8))) as? [String: Any],
+                  let given = request["text"] as? String else { return usage }
+            text = given
+            if let names = request["transforms"] {
+                let list = (names as? [String])?.joined(separator: ",") ?? names as? String
+                guard let parsed = list.flatMap(CopyTransform.parse) else {
+                    let known = CopyTransform.allCases.map(\.rawValue).joined(separator: ", ")
+                    return "error: transforms are \(known), all or none"
+                }
+                transforms = parsed
+            }
+        }
+        guard !text.isEmpty else { return "error: clipboard-write <text> | {\"text\", \"transforms\"}" }
+        let written = onMain { Clipboard.write(text, transforms: transforms) } ?? text
+        object["text"] = written
+        object["transforms"] = transforms.map(\.rawValue).sorted()
+        return reply(object)
+    }
+
+    /// `git-status [id | dir]` is the status of the repo holding a pane's
+    /// directory (the focused pane by default) or `dir`, from the cache
+    /// the Changes page shares. Reading a repo is what arms its
+    /// `git-status-changed` event. `git-diff-stats` takes the same target
+    /// and counts lines per file, staged and unstaged; `git-diff <id> <file>`
+    /// or `git-diff {"pane" | "dir", "file", "staged"}` splits one file's
+    /// diff into hunks. `git-stage-hunk {…, "hunk"}` (its index or header)
+    /// and `git-stage-lines {…, "old", "new"}` (line ranges on each side)
+    /// stage part of that diff, or unstage part of the staged one with
+    /// `"unstage": true`. `git-log` pages through history, with lanes for
+    /// drawing the graph on request, and `git-show` details one commit.
+    /// `git-blame` says who last changed each line of a file, or a range.
+    /// `git-branches` lists local and remote-tracking branches; creating,
+    /// deleting, renaming and `git-checkout` answer with the new status, as
+    /// do `git-discard` and `git-clean`, whose dry run (the default) only
+    /// lists what would be deleted. `git-commit` commits what's staged,
+    /// amending, signing or as someone else on request, and says
+    /// "signing failed" when that is why it didn't.
+    /// `git-merge` and `git-rebase` (with their `-abort` and `-continue`)
+    /// answer the same way, plus the operation and conflicted paths when
+    /// one stops; `git-conflicts` hands over base, ours and theirs for a
+    /// resolver, and `git-resolve-conflict` writes and stages the result.
+    private func handleGit(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        /// The repo for a pane id, a directory, or the focused pane.
+        func repo(pane: Int?, dir: String?) -> (repo: String?, error: String?) {
+            let directory: String?
+            if let pane {
+                guard let s = session(withID: pane) else { return (nil, "error: no pane \(pane)") }
+                directory = onMain { s.currentDirectory() } ?? nil
+            } else if let dir, !dir.isEmpty {
+                directory = (dir as NSString).expandingTildeInPath
+            } else {
+                directory = onMain { self.focusedSession()?.currentDirectory() } ?? nil
+            }
+            guard let directory else { return (nil, "error: no focused pane; pass a pane id or directory") }
+            guard let root = CodeGit.repoRoot(of: directory) else {
+                return (nil, "error: not a git repository: \(directory)")
+            }
+            return (root, nil)
+        }
+
+        let trimmed = arg.trimmingCharacters(in: .whitespaces)
+        switch cmd {
+        case "git-diff":
+            let usage = "error: git-diff <id> <file> | {\"pane\" | \"dir\", \"file\", \"staged\"}"
+            var pane: Int?
+            var dir: String?
+            let file: String
+            var staged = false
+            if trimmed.hasPrefix("{") {
+                guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                      let given = request["file"] as? String, !given.isEmpty else { return usage }
+                pane = request["pane"] as? Int
+                dir = request["dir"] as? String
+                file = given
+                staged = request["staged"] as? Bool == true
+            } else {
+                let parts = trimmed.split(separator: " ", maxSplits: 1)
+                guard parts.count == 2, let id = Int(parts[0]) else { return usage }
+                pane = id
+                file = String(parts[1])
+            }
+            let target = repo(pane: pane, dir: dir)
+            guard let root = target.repo else { return target.error ?? usage }
+            let diff = CodeGit.fileDiff(in: root, path: file, staged: staged)
+            if let error = diff.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            let hunks = CodeDiff.hunks(diff.text)
+            return reply([
+                "repo": root, "file": file, "staged": staged, "hunks": hunks.map(\.wire),
+                "added": hunks.reduce(0) { $0 + $1.added }, "removed": hunks.reduce(0) { $0 + $1.removed },
+                "binary": hunks.isEmpty && diff.text.contains("Binary files "),
+            ])
+        case "git-stage-hunk", "git-stage-lines":
+            let usage = cmd == "git-stage-hunk"
+                ? "error: git-stage-hunk {\"pane\" | \"dir\", \"file\", \"hunk\", \"unstage\"}"
+                : "error: git-stage-lines {\"pane\" | \"dir\", \"file\", \"old\": [[from, to]], "
+                    + "\"new\": [[from, to]], \"unstage\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let file = request["file"] as? String, !file.isEmpty else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let unstage = request["unstage"] as? Bool == true
+            let diff = CodeGit.fileDiff(in: root, path: file, staged: unstage)
+            if let error = diff.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            let hunks = CodeDiff.hunks(diff.text)
+            guard !hunks.isEmpty else { return "error: no \(unstage ? "staged" : "unstaged") changes in \(file)" }
+            let patch: String?
+            if cmd == "git-stage-hunk" {
+                let index = (request["hunk"] as? Int)
+                    ?? (request["hunk"] as? String).flatMap { header in
+                        hunks.firstIndex { $0.header == header || $0.header.hasPrefix(header + " ") }
+                    }
+                guard let index, hunks.indices.contains(index) else {
+                    return "error: no such hunk; \(file) has \(hunks.count): "
+                        + hunks.map(\.header).joined(separator: ", ")
+                }
+                patch = CodeDiff.partialPatch(diff.text, reverse: unstage) { hunk, _ in hunk == index }
+            } else {
+                func ranges(_ key: String) -> [ClosedRange<Int>]? {
+                    guard let value = request[key] else { return [] }
+                    guard let pairs = value as? [[Int]] else { return nil }
+                    var ranges: [ClosedRange<Int>] = []
+                    for pair in pairs {
+                        guard let from = pair.first, let to = pair.last, from <= to else { return nil }
+                        ranges.append(from...to)
+                    }
+                    return ranges
+                }
+                guard let old = ranges("old"), let new = ranges("new"), !(old.isEmpty && new.isEmpty) else {
+                    return usage
+                }
+                patch = CodeDiff.partialPatch(diff.text, reverse: unstage) { _, line in
+                    if let number = line.oldLine { return old.contains { $0.contains(number) } }
+                    if let number = line.newLine { return new.contains { $0.contains(number) } }
+                    return false
+                }
+            }
+            guard let patch else { return "error: the selection has no changed lines" }
+            if let error = CodeGit.applyToIndex(in: root, patch: patch, reverse: unstage) {
+                return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+            }
+            let remaining = CodeDiff.hunks(CodeGit.fileDiff(in: root, path: file, staged: unstage).text)
+            return reply([
+                "repo": root, "file": file, "unstaged": unstage, "remainingHunks": remaining.count,
+            ])
+        case "git-log":
+            let usage = "error: git-log [id | dir] | {\"pane\" | \"dir\", \"limit\", \"skip\", \"ref\", "
+                + "\"path\", \"graph\"}"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else if let id = Int(trimmed) {
+                request["pane"] = id
+            } else if !trimmed.isEmpty {
+                request["dir"] = trimmed
+            }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let ref = request["ref"] as? String
+            if let ref, ref.hasPrefix("-") || ref.isEmpty { return "error: not a ref: \(ref)" }
+            let limit = min(max(request["limit"] as? Int ?? 50, 1), 1000)
+            let skip = max(request["skip"] as? Int ?? 0, 0)
+            let log = CodeGit.log(
+                in: root, limit: limit, skip: skip, ref: ref, path: request["path"] as? String)
+            if let error = log.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            var commits = log.commits.map(\.wire)
+            if request["graph"] as? Bool == true {
+                for (index, row) in CommitGraph.rows(log.commits).enumerated() { commits[index]["graph"] = row.wire }
+            }
+            // A full page means there may be more; the next one starts here.
+            var object: [String: Any] = ["repo": root, "commits": commits]
+            if log.commits.count == limit { object["nextSkip"] = skip + limit }
+            return reply(object)
+        case "git-show":
+            let usage = "error: git-show <id> <hash> | {\"pane\" | \"dir\", \"hash\"}"
+            var pane: Int?
+            var dir: String?
+            let hash: String
+            if trimmed.hasPrefix("{") {
+                guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                      let given = request["hash"] as? String else { return usage }
+                pane = request["pane"] as? Int
+                dir = request["dir"] as? String
+                hash = given
+            } else {
+                let parts = trimmed.split(separator: " ", maxSplits: 1)
+                guard parts.count == 2, let id = Int(parts[0]) else { return usage }
+                pane = id
+                hash = String(parts[1])
+            }
+            guard !hash.isEmpty, !hash.hasPrefix("-") else { return usage }
+            let target = repo(pane: pane, dir: dir)
+            guard let root = target.repo else { return target.error ?? usage }
+            let shown = CodeGit.show(in: root, hash: hash)
+            guard let commit = shown.commit else {
+                return "error: \((shown.error ?? "").trimmingCharacters(in: .whitespacesAndNewlines))"
+            }
+            var object = commit.wire
+            object["repo"] = root
+            object["body"] = shown.body
+            object["files"] = shown.files.map(\.wire)
+            return reply(object)
+        case "git-blame":
+            let usage = "error: git-blame <id> <file> | {\"pane\" | \"dir\", \"file\", \"from\", \"to\", \"rev\"}"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else {
+                let parts = trimmed.split(separator: " ", maxSplits: 1)
+                guard parts.count == 2, let id = Int(parts[0]) else { return usage }
+                request = ["pane": id, "file": String(parts[1])]
+            }
+            guard let file = request["file"] as? String, !file.isEmpty else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let rev = request["rev"] as? String
+            if let rev, rev.hasPrefix("-") || rev.isEmpty { return "error: not a revision: \(rev)" }
+            var lines: ClosedRange<Int>?
+            if let from = request["from"] as? Int {
+                let to = request["to"] as? Int ?? from
+                guard from >= 1, to >= from else { return "error: from and to are lines, 1-based, from <= to" }
+                lines = from...to
+            }
+            let blame = CodeGit.blame(in: root, path: file, lines: lines, rev: rev)
+            if let error = blame.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            return reply(["repo": root, "file": file, "lines": blame.lines.map(\.wire)])
+        case "git-fetch", "git-pull":
+            let usage = cmd == "git-fetch"
+                ? "error: git-fetch [id | dir] | {\"pane\" | \"dir\", \"remote\"}"
+                : "error: git-pull [id | dir] | {\"pane\" | \"dir\", \"rebase\"}"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else if let id = Int(trimmed) {
+                request["pane"] = id
+            } else if !trimmed.isEmpty {
+                request["dir"] = trimmed
+            }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            var output: String?
+            if cmd == "git-fetch" {
+                let remote = request["remote"] as? String
+                if let remote, remote.hasPrefix("-") || remote.isEmpty { return "error: not a remote: \(remote)" }
+                if let error = CodeGit.fetch(in: root, remote: remote) {
+                    return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+                }
+            } else {
+                let pulled = CodeGit.pull(in: root, rebase: request["rebase"] as? Bool)
+                if let error = pulled.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+                output = pulled.output
+            }
+            // The counts the reply carries are the ones this fetch or pull left.
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            if let output { object["output"] = output }
+            return reply(object)
+        case "git-push":
+            let usage = "error: git-push [id | dir] | {\"pane\" | \"dir\", \"remote\", \"branch\", "
+                + "\"forceWithLease\", \"setUpstream\", \"tags\"}"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else if let id = Int(trimmed) {
+                request["pane"] = id
+            } else if !trimmed.isEmpty {
+                request["dir"] = trimmed
+            }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let options = CodeGit.PushOptions(
+                remote: request["remote"] as? String, branch: request["branch"] as? String,
+                forceWithLease: request["forceWithLease"] as? Bool == true,
+                setUpstream: request["setUpstream"] as? Bool, tags: request["tags"] as? Bool == true)
+            let pushed = CodeGit.push(in: root, options: options)
+            if let error = pushed.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            object["output"] = pushed.output
+            return reply(object)
+        case "git-remotes":
+            let target = repo(pane: Int(trimmed), dir: Int(trimmed) == nil ? trimmed : nil)
+            guard let root = target.repo else { return target.error ?? "error: git-remotes [id | dir]" }
+            return reply(["repo": root, "remotes": CodeGit.remotes(in: root).map(\.wire)])
+        case "git-remote-add", "git-remote-remove":
+            let usage = cmd == "git-remote-add"
+                ? "error: git-remote-add {\"pane\" | \"dir\", \"name\", \"url\"}"
+                : "error: git-remote-remove {\"pane\" | \"dir\", \"name\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let name = request["name"] as? String else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let error: String?
+            if cmd == "git-remote-add" {
+                guard let url = request["url"] as? String else { return usage }
+                error = CodeGit.addRemote(in: root, name: name, url: url)
+            } else {
+                error = CodeGit.removeRemote(in: root, name: name)
+            }
+            if let error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            return reply(["repo": root, "remotes": CodeGit.remotes(in: root).map(\.wire)])
+        case "git-branches":
+            let target = repo(pane: Int(trimmed), dir: Int(trimmed) == nil ? trimmed : nil)
+            guard let root = target.repo else { return target.error ?? "error: git-branches [id | dir]" }
+            var object: [String: Any] = [
+                "repo": root, "local": CodeGit.branches(in: root), "remote": CodeGit.remoteBranches(in: root),
+            ]
+            object["current"] = GitStatusCache.shared.status(in: root).branch
+            return reply(object)
+        case "git-branch-create", "git-branch-delete", "git-branch-rename", "git-checkout":
+            let usages = [
+                "git-branch-create": "{\"pane\" | \"dir\", \"name\", \"from\", \"checkout\"}",
+                "git-branch-delete": "{\"pane\" | \"dir\", \"name\", \"force\"}",
+                "git-branch-rename": "{\"pane\" | \"dir\", \"from\", \"to\"}",
+                "git-checkout": "{\"pane\" | \"dir\", \"branch\"}",
+            ]
+            let usage = "error: \(cmd) \(usages[cmd] ?? "")"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let error: String?
+            switch cmd {
+            case "git-branch-create":
+                guard let name = request["name"] as? String else { return usage }
+                error = CodeGit.createBranch(
+                    in: root, name: name, from: request["from"] as? String,
+                    checkout: request["checkout"] as? Bool == true)
+            case "git-branch-delete":
+                guard let name = request["name"] as? String else { return usage }
+                error = CodeGit.deleteBranch(in: root, name: name, force: request["force"] as? Bool == true)
+            case "git-branch-rename":
+                guard let new = request["to"] as? String,
+                      let old = request["from"] as? String ?? GitStatusCache.shared.status(in: root).branch
+                else { return usage }
+                error = CodeGit.renameBranch(in: root, from: old, to: new)
+            default:
+                // A remote-tracking name with no local branch of its own is
+                // checked out through a new local branch that tracks it.
+                guard let branch = request["branch"] as? String, !branch.isEmpty, !branch.hasPrefix("-")
+                else { return usage }
+                if !CodeGit.branches(in: root).contains(branch), CodeGit.remoteBranches(in: root).contains(branch) {
+                    error = CodeGit.checkoutRemote(in: root, remoteBranch: branch).error
+                } else {
+                    error = CodeGit.checkout(in: root, branch: branch)
+                }
+            }
+            if let error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            return reply(object)
+        case "git-commit":
+            let usage = "error: git-commit {\"pane\" | \"dir\", \"message\", \"amend\", \"sign\", \"signingKey\", "
+                + "\"author\", \"committer\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            let message = (request["message"] as? String ?? "").trimmingCharacters(in: .whitespacesAndNewlines)
+            let options = CodeGit.CommitOptions(
+                amend: request["amend"] as? Bool == true, sign: request["sign"] as? Bool,
+                signingKey: (request["signingKey"] as? String).flatMap { $0.isEmpty ? nil : $0 },
+                author: request["author"] as? String, committer: request["committer"] as? String)
+            guard !message.isEmpty || options.amend else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            if let error = CodeGit.commit(in: root, message: message, options: options) {
+                let trimmedError = error.trimmingCharacters(in: .whitespacesAndNewlines)
+                // Told apart so a caller can say "check your key or agent"
+                // rather than "commit failed".
+                return CodeGit.isSigningError(error)
+                    ? "error: signing failed: \(trimmedError)"
+                    : "error: \(trimmedError)"
+            }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            if let head = CodeGit.head(in: root) { object["commit"] = head }
+            return reply(object)
+        case "git-discard":
+            let usage = "error: git-discard {\"pane\" | \"dir\", \"file\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let file = request["file"] as? String, !file.isEmpty else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            if let error = CodeGit.discard(in: root, path: file) {
+                return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+            }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            return reply(object)
+        case "git-clean":
+            // Dry run unless told otherwise: the list is what a confirmation shows.
+            let usage = "error: git-clean {\"pane\" | \"dir\", \"paths\", \"dryRun\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            var paths: [String] = []
+            if let given = request["paths"] {
+                guard let list = given as? [String] ?? (given as? String).map({ [$0] }) else { return usage }
+                paths = list.filter { !$0.isEmpty }
+            }
+            let dryRun = request["dryRun"] as? Bool ?? true
+            let cleaned = CodeGit.clean(in: root, paths: paths, dryRun: dryRun)
+            if let error = cleaned.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            var object: [String: Any] = dryRun ? [:] : GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            object["dryRun"] = dryRun
+            object[dryRun ? "wouldRemove" : "removed"] = cleaned.paths
+            return reply(object)
+        case "git-merge", "git-rebase", "git-merge-abort", "git-rebase-abort", "git-merge-continue",
+             "git-rebase-continue":
+            let usages = [
+                "git-merge": "{\"pane\" | \"dir\", \"branch\"}", "git-rebase": "{\"pane\" | \"dir\", \"onto\"}",
+            ]
+            let usage = "error: \(cmd) \(usages[cmd] ?? "[id | dir] | {\"pane\" | \"dir\"}")"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else if usages[cmd] != nil {
+                return usage
+            } else if let id = Int(trimmed) {
+                request["pane"] = id
+            } else if !trimmed.isEmpty {
+                request["dir"] = trimmed
+            }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let operation: CodeGit.Operation = cmd.hasPrefix("git-merge") ? .merge : .rebase
+            let result: (output: String, error: String?)
+            switch cmd {
+            case "git-merge":
+                guard let branch = request["branch"] as? String, !branch.isEmpty else { return usage }
+                result = CodeGit.merge(in: root, branch: branch)
+            case "git-rebase":
+                guard let onto = request["onto"] as? String, !onto.isEmpty else { return usage }
+                result = CodeGit.rebase(in: root, onto: onto)
+            default:
+                guard CodeGit.operation(in: root) == operation else {
+                    return "error: no \(operation.rawValue) in progress"
+                }
+                result = cmd.hasSuffix("-abort")
+                    ? ("", CodeGit.abort(in: root, operation: operation))
+                    : CodeGit.resume(in: root, operation: operation)
+            }
+            // Stopping on conflicts is an answer, not a failure: the reply
+            // says what's in progress and which paths need resolving.
+            let stopped = CodeGit.operation(in: root)
+            if let error = result.error, stopped == nil || cmd.hasSuffix("-abort") {
+                return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+            }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            let output = result.error ?? result.output
+            if !output.isEmpty { object["output"] = output.trimmingCharacters(in: .whitespacesAndNewlines) }
+            if let stopped {
+                object["operation"] = stopped.rawValue
+                object["conflicts"] = CodeGit.conflicts(in: root).conflicts.map(\.path)
+            }
+            return reply(object)
+        case "git-conflicts":
+            let usage = "error: git-conflicts [id | dir] | {\"pane\" | \"dir\", \"path\"}"
+            var request: [String: Any] = [:]
+            if trimmed.hasPrefix("{") {
+                guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+                else { return usage }
+                request = object
+            } else if let id = Int(trimmed) {
+                request["pane"] = id
+            } else if !trimmed.isEmpty {
+                request["dir"] = trimmed
+            }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            let found = CodeGit.conflicts(in: root, path: request["path"] as? String)
+            if let error = found.error { return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))" }
+            var object: [String: Any] = ["repo": root, "conflicts": found.conflicts.map(\.wire)]
+            if let operation = CodeGit.operation(in: root) { object["operation"] = operation.rawValue }
+            return reply(object)
+        case "git-resolve-conflict":
+            let usage = "error: git-resolve-conflict {\"pane\" | \"dir\", \"file\", \"content\"}"
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any],
+                  let file = request["file"] as? String, !file.isEmpty,
+                  let content = request["content"] as? String else { return usage }
+            let target = repo(pane: request["pane"] as? Int, dir: request["dir"] as? String)
+            guard let root = target.repo else { return target.error ?? usage }
+            if let error = CodeGit.resolveConflict(in: root, path: file, content: content) {
+                return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+            }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            object["conflicts"] = CodeGit.conflicts(in: root).conflicts.map(\.path)
+            if let operation = CodeGit.operation(in: root) { object["operation"] = operation.rawValue }
+            return reply(object)
+        case "git-diff-stats":
+            let target = repo(pane: Int(trimmed), dir: Int(trimmed) == nil ? trimmed : nil)
+            guard let root = target.repo else { return target.error ?? "error: git-diff-stats [id | dir]" }
+            var object: [String: Any] = ["repo": root]
+            for staged in [false, true] {
+                let result = CodeGit.diffStats(in: root, staged: staged)
+                if let error = result.error {
+                    return "error: \(error.trimmingCharacters(in: .whitespacesAndNewlines))"
+                }
+                object[staged ? "staged" : "unstaged"] = [
+                    "files": result.stats.map(\.wire),
+                    "added": result.stats.reduce(0) { $0 + $1.added },
+                    "removed": result.stats.reduce(0) { $0 + $1.removed },
+                ]
+            }
+            return reply(object)
+        default:
+            let target = repo(pane: Int(trimmed), dir: Int(trimmed) == nil ? trimmed : nil)
+            guard let root = target.repo else { return target.error ?? "error: git-status [id | dir]" }
+            var object = GitStatusCache.shared.status(in: root).wire
+            object["repo"] = root
+            return reply(object)
+        }
+    }
+
+    /// `pane-display <id>` reads a pane's text size and line height;
+    /// `pane-display <id> {"fontSize", "lineSpacing"}` gives it its own (null
+    /// goes back to the config's), leaving the other panes as they are.
+    private func handlePaneDisplay(_ arg: String) -> String {
+        let usage = "error: pane-display <id> [{\"fontSize\", \"lineSpacing\"}]"
+        let parts = arg.trimmingCharacters(in: .whitespaces).split(separator: " ", maxSplits: 1)
+        guard let first = parts.first, let id = Int(first), let s = session(withID: id) else { return usage }
+        if parts.count == 2 {
+            guard let request = (try? JSONSerialization.jsonObject(with: Data(parts[1].utf8))) as? [String: Any]
+            else { return usage }
+            guard let display = s.display.updated(with: request) else {
+                return "error: fontSize is 6-72 points and lineSpacing 0.7-3"
+            }
+            _ = onMain { self.setDisplay(display, for: s) }
+        }
+        var object = onMain { s.display.wire(over: self.config) } ?? [:]
+        object["pane"] = s.id
+        let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `link-policy` lists what each scheme's links do. `{"scheme",
+    /// "action"}` sets one until quit (open, ask, deny or event — the last
+    /// leaves the link to this client's `link-activated` subscription),
+    /// null drops it back to the config; `{"url"}` says what that one would do.
+    private func handleLinkPolicy(_ arg: String) -> String {
+        let usage = "error: link-policy [{\"scheme\", \"action\": open|ask|deny|event|null} | {\"url\"}]"
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if !trimmed.isEmpty {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            request = object
+        }
+        if let name = request["scheme"] as? String {
+            guard let scheme = LinkPolicy.scheme(name) else { return "error: \(name) isn't a URL scheme" }
+            guard let given = request["action"] else { return usage }
+            switch given {
+            case is NSNull:
+                _ = onMain { self.linkSchemes[scheme] = nil }
+            case let value as String:
+                guard let action = LinkAction(rawValue: value.lowercased()) else { return usage }
+                _ = onMain { self.linkSchemes[scheme] = action }
+            default:
+                return usage
+            }
+        }
+        guard let policy = onMain({ self.linkPolicy }) else { return usage }
+        var reply: [String: Any] = ["rules": policy.wire]
+        if let text = request["url"] as? String {
+            guard let url = URL(string: text) else { return "error: \(text) isn't a URL" }
+            reply["url"] = text
+            reply["action"] = policy.action(for: url).rawValue
+        }
+        let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// The workspace content index (see `SearchIndex`). `index-status` with
+    /// no argument lists the live ones; given a pane or directory it starts
+    /// that workspace's index if needed and reports it. `index-rebuild`
+    /// reads the tree again from scratch. `index-search {"pane" | "dir",
+    /// "query", "limit"}` returns {root, indexed, matches}; while the first
+    /// pass is running `indexed` is false and the lines come from grep.
+    private func handleSearchIndex(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let usage = cmd == "index-search"
+            ? "error: index-search {\"pane\" | \"dir\", \"query\", \"limit\"}"
+            : "error: \(cmd) [id | dir]"
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        if cmd == "index-status", trimmed.isEmpty {
+            return reply(["indexes": SearchIndexes.shared.all.map(\.status.wire)])
+        }
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            request = object
+        } else if let id = Int(trimmed) {
+            request["pane"] = id
+        } else if !trimmed.isEmpty {
+            request["dir"] = trimmed
+        }
+        let directory: String?
+        if let pane = request["pane"] as? Int {
+            guard let s = session(withID: pane) else { return "error: no pane \(pane)" }
+            directory = onMain { s.currentDirectory() } ?? nil
+        } else if let dir = request["dir"] as? String, !dir.isEmpty {
+            directory = (dir as NSString).expandingTildeInPath
+        } else {
+            directory = onMain { self.focusedSession()?.currentDirectory() } ?? nil
+        }
+        guard let directory else { return "error: no focused pane; pass a pane id or directory" }
+        var isDirectory: ObjCBool = false
+        guard FileManager.default.fileExists(atPath: directory, isDirectory: &isDirectory), isDirectory.boolValue
+        else { return "error: not a directory: \(directory)" }
+        let index = SearchIndexes.shared.index(for: SearchIndexes.workspaceRoot(of: directory))
+        switch cmd {
+        case "index-rebuild":
+            index.rebuild()
+            return reply(index.status.wire)
+        case "index-search":
+            guard let query = request["query"] as? String, !query.isEmpty else { return usage }
+            let limit = min(max(request["limit"] as? Int ?? 200, 1), 5_000)
+            let indexed = index.search(query, limit: limit)
+            let matches = indexed ?? CodeSearch.grep(
+                pattern: query, root: index.root, limit: limit, literal: true, ignoreCase: true)
+            return reply([
+                "root": index.root, "indexed": indexed != nil,
+                "matches": matches.map { ["path": $0.path, "line": $0.line, "text": $0.text] },
+            ])
+        default:
+            return reply(index.status.wire)
+        }
+    }
+
+    /// `analyze-command <command line>` or `{"command", "pane" | "dir"}` says
+    /// what `confirm-dangerous-commands` would make of it: {risky} alone
+    /// when nothing, else the analysis with globs expanded from the pane's
+    /// directory (the focused one's by default) and paths counted. Nothing
+    /// runs.
+    private func handleAnalyzeCommand(_ arg: String) -> String {
+        let usage = "error: analyze-command <command line> | {\"command\", \"pane\" | \"dir\"}"
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = ["command": trimmed]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            request = object
+        }
+        guard let command = request["command"] as? String, !command.isEmpty else { return usage }
+        let directory: String?
+        if let pane = request["pane"] as? Int {
+            guard let s = session(withID: pane) else { return "error: no pane \(pane)" }
+            directory = onMain { s.currentDirectory() } ?? nil
+        } else if let dir = request["dir"] as? String, !dir.isEmpty {
+            directory = (dir as NSString).expandingTildeInPath
+        } else {
+            directory = onMain { self.focusedSession()?.currentDirectory() } ?? nil
+        }
+        var object: [String: Any] = ["command": command, "risky": false]
+        if let analysis = CommandGuard.analyze(command, in: directory ?? NSHomeDirectory()) {
+            object = analysis.wire
+            object["risky"] = true
+        }
+        let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `expand-globs <pattern>` or `{"pattern", "pane" | "dir", "limit"}`
+    /// lists what a shell pattern matches, braces first, from the pane's
+    /// directory (the focused one's by default). Takes the pattern as the
+    /// shell would see it after quoting: a backslash escapes.
+    private func handleExpandGlobs(_ arg: String) -> String {
+        let usage = "error: expand-globs <pattern> | {\"pattern\", \"pane\" | \"dir\", \"limit\"}"
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = ["pattern": trimmed]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any]
+            else { return usage }
+            request = object
+        }
+        guard let pattern = request["pattern"] as? String, !pattern.isEmpty else { return usage }
+        let directory: String?
+        if let pane = request["pane"] as? Int {
+            guard let s = session(withID: pane) else { return "error: no pane \(pane)" }
+            directory = onMain { s.currentDirectory() } ?? nil
+        } else if let dir = request["dir"] as? String, !dir.isEmpty {
+            directory = (dir as NSString).expandingTildeInPath
+        } else {
+            directory = onMain { self.focusedSession()?.currentDirectory() } ?? nil
+        }
+        let base = directory ?? NSHomeDirectory()
+        let limit = min(max(request["limit"] as? Int ?? 10_000, 1), 100_000)
+        var object = GlobExpansion.expand(pattern, in: base, limit: limit).wire
+        object["pattern"] = pattern
+        object["directory"] = base
+        let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `deps [dir]` or `deps {"dir", "offline"}` starts a task that reads
+    /// the project's manifests (found at or above `dir`, the focused pane's
+    /// directory by default), resolves versions and licenses locally and,
+    /// unless offline, checks them against OSV. The report is the task
+    /// result: {root, dependencies, summary}, plus `advisoriesError` when
+    /// OSV could not be reached and only cached answers were used.
+    private func handleDependencies(_ arg: String) -> String {
+        let trimmed = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+                return "error: deps expects a directory or {\"dir\", \"offline\"}"
+            }
+            request = object
+        } else if !trimmed.isEmpty {
+            request["dir"] = trimmed
+        }
+        let directory = (request["dir"] as? String).map { ($0 as NSString).expandingTildeInPath }
+            ?? onMain { self.focusedSession()?.currentDirectory() } ?? nil
+        guard let directory else { return "error: no focused pane; pass a directory" }
+        guard let root = ProjectDependencies.projectRoot(for: directory) else {
+            return "error: \(DependencyScanError.noManifest(directory))"
+        }
+        let offline = request["offline"] as? Bool == true
+        let title = "dependencies of \((root as NSString).lastPathComponent)"
+        let task = BackgroundTaskRegistry.shared.start(kind: "deps", title: title) { task in
+            task.report(completed: 0, total: 2, detail: "reading manifests")
+            var dependencies = ProjectDependencies.scan(root: root)
+            var result: [String: Any] = ["root": root]
+            if !offline, !dependencies.isEmpty {
+                task.report(completed: 1, total: 2, detail: "checking \(dependencies.count) against OSV")
+                let lookup = OSVAdvisories.shared.advisories(for: dependencies, cancellation: task.cancellation)
+                for index in dependencies.indices {
+                    dependencies[index].advisories = lookup.advisories[dependencies[index].key] ?? []
+                }
+                if let error = lookup.error { result["advisoriesError"] = error.description }
+            }
+            if task.isCancelled { return nil }
+            task.report(completed: 2, total: 2, detail: nil)
+            result["dependencies"] = dependencies.map(\.wire)
+            result["summary"] = ProjectDependencies.summary(dependencies)
+            return result
+        }
+        let data = (try? JSONSerialization.data(withJSONObject: task.snapshot())) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `services` maps every dev endpoint — local TCP listeners, published
+    /// Docker ports and Kubernetes services — to where it is reachable:
+    /// {endpoints, sources}, each source "ok" or why it is missing.
+    /// `service-open <id | port>` opens one in a Browser pane.
+    private func handleServices(_ cmd: String, _ arg: String) -> String {
+        let map = ServiceMap.collect()
+        if cmd == "services" {
+            let object: [String: Any] = ["endpoints": map.endpoints.map(\.wire), "sources": map.sources]
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let key = arg.trimmingCharacters(in: .whitespaces)
+        guard !key.isEmpty else { return "error: service-open <id | port>" }
+        guard let url = map.endpoints.first(where: { $0.id == key || $0.port.map(String.init) == key })?.url else {
+            return "error: \(ServiceMapError.noSuchEndpoint(key))"
+        }
+        let opened = onMain { () -> Bool in
+            guard let win = NSApp.keyWindow
+                ?? NSApp.windows.first(where: { $0.tabbingIdentifier == "infinitty" }),
+                  let browser = self.openUtilityPanel(.browser, in: win)?.browser
+            else { return false }
+            browser.load(address: url)
+            return true
+        } ?? false
+        return opened ? url : "error: no window to open a Browser pane in"
+    }
+
+    /// `layouts`, `layout [<pane>]` (the shape of that pane's tab, or the
+    /// focused one), `layout-save <name> | {"name", "pane", "shape"}`,
+    /// `layout-apply <name> | {"name" | "shape", "pane"}` and
+    /// `layout-delete <name>`: named split arrangements.
+    private func handleLayout(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let trimmed = arg.trimmingCharacters(in: .whitespaces)
+        var request: [String: Any] = [:]
+        if trimmed.hasPrefix("{") {
+            guard let data = trimmed.data(using: .utf8),
+                  let object = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+            else { return "error: \(cmd) takes a name or a JSON object" }
+            request = object
+        } else if cmd == "layout" {
+            if !trimmed.isEmpty {
+                guard let id = Int(trimmed) else { return "error: layout [<pane>]" }
+                request["pane"] = id
+            }
+        } else if !trimmed.isEmpty {
+            request["name"] = trimmed
+        }
+        let name = request["name"] as? String ?? ""
+        let paneID = request["pane"] as? Int
+        if let paneID, session(withID: paneID) == nil { return "error: no pane \(paneID)" }
+        let library = LayoutPresetLibrary.shared
+        func window() -> NSWindow? {
+            if let paneID { return self.session(withID: paneID)?.view.window }
+            return self.focusedSession()?.view.window
+                ?? NSApp.keyWindow ?? NSApp.windows.first(where: { $0.tabbingIdentifier == "infinitty" })
+        }
+        func currentShape() -> LayoutShape? {
+            onMain { window().flatMap { self.terminalRoot(of: $0) }.flatMap(LayoutShape.capture) } ?? nil
+        }
+
+        switch cmd {
+        case "layouts":
+            return reply(library.all().map(\.wire))
+        case "layout-delete":
+            return library.delete(name) ? "ok" : "error: no saved layout named \(name)"
+        case "layout":
+            guard let shape = currentShape() else { return "error: no tab to read a layout from" }
+            return reply(["shape": shape.wire, "panes": shape.paneCount])
+        case "layout-save":
+            var shape: LayoutShape?
+            if let object = request["shape"] {
+                guard let parsed = LayoutShape(wire: object) else { return "error: \(LayoutPresetError.invalidShape)" }
+                shape = parsed
+            } else {
+                shape = currentShape()
+            }
+            guard let shape else { return "error: no tab to read a layout from" }
+            switch library.save(name: name, shape: shape) {
+            case .success(let preset): return reply(preset.wire)
+            case .failure(let error): return "error: \(error)"
+            }
+        default:
+            let shape: LayoutShape
+            if let object = request["shape"] {
+                guard let parsed = LayoutShape(wire: object) else { return "error: \(LayoutPresetError.invalidShape)" }
+                shape = parsed
+            } else {
+                guard let preset = library.preset(named: name) else {
+                    return "error: \(LayoutPresetError.notFound(name))"
+                }
+                shape = preset.shape
+            }
+            guard shape.paneCount <= LayoutShape.maxPanes else { return "error: \(LayoutPresetError.invalidShape)" }
+            let result = onMain { window().flatMap { self.applyLayout(shape, in: $0) } } ?? nil
+            return result.map { reply($0) } ?? "error: no main window to lay out"
+        }
+    }
+
+    /// `record-start <id> [path]` writes a pane's output to an asciicast v2
+    /// file (default: Application Support/infinitty/recordings) until
+    /// `record-stop <id>` or the shell exits; `recordings` lists them.
+    /// `replay <path> | {"path", "pane", "speed", "maxIdle"}` plays one back
+    /// as a task, each event a `replay-frame` with the recorded timing, and
+    /// also drawn into `pane` when given.
+    private func handleRecording(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let words = arg.split(separator: " ", maxSplits: 1, omittingEmptySubsequences: false)
+        let pane = words.first.flatMap { Int($0) }.flatMap { session(withID: $0) }
+        let rest = words.count > 1 ? String(words[1]).trimmingCharacters(in: .whitespaces) : ""
+        switch cmd {
+        case "recordings":
+            let active = onMain {
+                self.recordings.sorted { $0.key < $1.key }.map { id, recorder -> [String: Any] in
+                    var object = recorder.summary
+                    object["pane"] = id
+                    return object
+                }
+            }
+            return reply(active ?? [])
+        case "record-start":
+            guard let s = pane else { return "error: record-start <id> [path]" }
+            let stamp = DateFormatter()
+            stamp.dateFormat = "yyyyMMdd-HHmmss"
+            let name = "pane-\(s.id)-\(stamp.string(from: Date())).cast"
+            let path = rest.isEmpty
+                ? AsciicastRecorder.defaultDirectory.appendingPathComponent(name).path
+                : (rest as NSString).expandingTildeInPath
+            let result = onMain { () -> Result<AsciicastRecorder, SessionRecordingError> in
+                guard self.recordings[s.id] == nil else { return .failure(.alreadyRecording) }
+                var environment = ProcessInfo.processInfo.environment
+                environment["TERM"] = environment["TERM"] ?? "xterm-256color"
+                let recorder: AsciicastRecorder
+                do {
+                    recorder = try AsciicastRecorder(
+                        path: path, cols: s.terminal.cols, rows: s.terminal.rows, title: s.title,
+                        environment: environment)
+                } catch {
+                    return .failure(error as? SessionRecordingError ?? .unwritable(path))
+                }
+                self.recordings[s.id] = recorder
+                s.setOutputTap("recording") { [weak s] data in
+                    guard let terminal = s?.terminal else { return }
+                    recorder.record(data, cols: terminal.cols, rows: terminal.rows)
+                }
+                return .success(recorder)
+            } ?? .failure(.unwritable(path))
+            switch result {
+            case .success(let recorder): return reply(recorder.summary)
+            case .failure(let error): return "error: \(error)"
+            }
+        case "record-stop":
+            guard let s = pane else { return "error: record-stop <id>" }
+            let summary = onMain { () -> [String: Any]? in
+                s.setOutputTap("recording", nil)
+                return self.recordings.removeValue(forKey: s.id)?.stop()
+            } ?? nil
+            return summary.map { reply($0) } ?? "error: \(SessionRecordingError.notRecording)"
+        default:
+            break
+        }
+
+        let trimmed = arg.trimmingCharacters(in: .whitespaces)
+        var request: [String: Any] = ["path": trimmed]
+        if trimmed.hasPrefix("{") {
+            guard let data = trimmed.data(using: .utf8),
+                  let object = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+            else { return "error: replay <path> | {\"path\", \"pane\", \"speed\", \"maxIdle\"}" }
+            request = object
+        }
+        let path = ((request["path"] as? String ?? "") as NSString).expandingTildeInPath
+        guard !path.isEmpty else { return "error: replay <path> | {\"path\", \"pane\", \"speed\", \"maxIdle\"}" }
+        var target: TerminalSession?
+        if let id = request["pane"] as? Int {
+            guard let found = session(withID: id) else { return "error: no pane \(id)" }
+            target = found
+        }
+        guard let text = try? String(contentsOfFile: path, encoding: .utf8) else {
+            return "error: can't read \(path)"
+        }
+        let cast: Asciicast
+        switch Asciicast.parse(text) {
+        case .success(let parsed): cast = parsed
+        case .failure(let error): return "error: \(error)"
+        }
+        let speed = (request["speed"] as? NSNumber)?.doubleValue ?? 1
+        let maxIdle = (request["maxIdle"] as? NSNumber)?.doubleValue
+        let times = Asciicast.schedule(cast.events, speed: speed, maxIdle: maxIdle)
+        let title = "Replay \((path as NSString).lastPathComponent)"
+        let registry = BackgroundTaskRegistry.shared
+        let task = registry.start(kind: "replay", title: title) { [weak self, weak target] task in
+            let wake = DispatchSemaphore(value: 0)
+            let handler = task.cancellation.onCancel { wake.signal() }
+            defer { task.cancellation.removeHandler(handler) }
+            let start = DispatchTime.now()
+            for (index, event) in cast.events.enumerated() {
+                if wake.wait(timeout: start + times[index]) == .success { return nil }
+                self?.appControl.broadcast(
+                    .replayFrame(task: task.id, time: event.time, kind: event.kind, data: event.data))
+                if event.kind == "o", let terminal = target?.terminal {
+                    let bytes = Array(event.data.utf8)
+                    bytes.withUnsafeBufferPointer { terminal.feed($0.baseAddress!, $0.count) }
+                }
+                task.report(completed: Int64(index + 1), total: Int64(cast.events.count))
+            }
+            return [
+                "events": cast.events.count, "duration": cast.duration, "width": cast.width, "height": cast.height,
+            ]
+        }
+        return reply(task.snapshot())
+    }
+
+    /// `broadcast-create <id …>` groups panes so keystrokes typed in one go
+    /// to all; `broadcast-add`/`broadcast-remove <group> <id …>` change the
+    /// members and `broadcast-send[-line] <group> <text>` writes to each.
+    private func handleBroadcast(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let usage: String
+        switch cmd {
+        case "broadcast-create": usage = "broadcast-create <id> [id …]"
+        case "broadcast-send", "broadcast-send-line": usage = "\(cmd) <group> <text>"
+        case "broadcast-delete": usage = "broadcast-delete <group>"
+        default: usage = "\(cmd) <group> <id> [id …]"
+        }
+        if cmd == "broadcast-groups" {
+            return reply(onMain { self.broadcastGroups.groups.map(\.wire) } ?? [])
+        }
+        if cmd == "broadcast-send" || cmd == "broadcast-send-line" {
+            let parts = arg.split(separator: " ", maxSplits: 1, omittingEmptySubsequences: false)
+            guard parts.count == 2, !parts[0].isEmpty else { return "error: \(usage)" }
+            let id = String(parts[0])
+            let bytes = Array(parts[1].utf8) + (cmd == "broadcast-send-line" ? [0x0D] : [])
+            let sent = onMain { () -> [Int]? in
+                guard let group = self.broadcastGroups.group(id) else { return nil }
+                let members = self.sessions.filter { group.panes.contains($0.id) }
+                for s in members {
+                    s.view.showAgentGlow()
+                    s.pty.write(bytes)
+                }
+                return members.map(\.id)
+            } ?? nil
+            guard let sent else { return "error: \(InputBroadcastError.notFound(id))" }
+            return reply(["group": id, "panes": sent])
+        }
+
+        var words = arg.split(separator: " ").map(String.init)
+        let group = cmd == "broadcast-create" || words.isEmpty ? nil : words.removeFirst()
+        let panes = words.compactMap(Int.init)
+        guard panes.count == words.count, cmd == "broadcast-create" || group != nil else { return "error: \(usage)" }
+        if cmd == "broadcast-delete" {
+            guard let group, panes.isEmpty else { return "error: \(usage)" }
+            let deleted = onMain { self.broadcastGroups.delete(group) } ?? false
+            return deleted ? "ok" : "error: \(InputBroadcastError.notFound(group))"
+        }
+        if let missing = panes.first(where: { session(withID: $0) == nil }) { return "error: no pane \(missing)" }
+        let result = onMain { () -> Result<InputBroadcastGroup, InputBroadcastError> in
+            switch cmd {
+            case "broadcast-create": return self.broadcastGroups.create(panes)
+            case "broadcast-add": return self.broadcastGroups.add(panes, to: group ?? "")
+            default: return self.broadcastGroups.remove(panes, from: group ?? "")
+            }
+        }
+        switch result {
+        case .success(let group)?: return reply(group.wire)
+        case .failure(let error)?: return "error: \(error)"
+        case nil: return "error: timed out"
         }
-        return true
     }
 
-    // MARK: - app control API (external apps / MCP)
-
-    /// Run work on the main thread from a socket thread, with a deadline.
-    private func onMain<T>(_ work: @escaping () -> T) -> T? {
-        if Thread.isMainThread { return work() }
-        var result: T?
-        let sem = DispatchSemaphore(value: 0)
-        CFRunLoopPerformBlock(CFRunLoopGetMain(), CFRunLoopMode.commonModes.rawValue) {
-            result = work()
-            sem.signal()
+    /// Scans `s`'s finished rows while triggers apply to it or links are
+    /// being detected; other panes skip the tap, and its copy of every
+    /// read, entirely.
+    private func syncOutputScan(_ s: TerminalSession) {
+        let wanted = detectingLinks.snapshot || !TriggerEngine.shared.triggers(for: s.id).isEmpty
+        guard wanted != scannedPanes.contains(s.id) else { return }
+        guard wanted else {
+            scannedPanes.remove(s.id)
+            s.setOutputTap("scan", nil)
+            return
+        }
+        scannedPanes.insert(s.id)
+        var next: Int?
+        s.setOutputTap("scan") { [weak self, weak s] _ in
+            guard let self, let s else { return }
+            // Rows a flood scrolls past aren't scanned; the scan picks up
+            // where the output is once it calms down.
+            let (rows, after) = s.terminal.finishedRows(after: s.isFlooding ? nil : next)
+            next = after
+            let detecting = self.detectingLinks.snapshot
+            var links: [DetectedLink] = []
+            for row in rows {
+                for match in TriggerEngine.shared.matches(pane: s.id, line: row.line, chars: row.chars) {
+                    self.fireTrigger(match, in: s)
+                }
+                if detecting { links += SmartToken.links(in: row.chars, line: row.line) }
+            }
+            guard !links.isEmpty else { return }
+            DispatchQueue.main.async { [weak self, weak s] in
+                guard let self, let s else { return }
+                self.publish(.detectedLinks(pane: s.id, links: links), in: s.view.window)
+            }
         }
-        CFRunLoopWakeUp(CFRunLoopGetMain())
-        return sem.wait(timeout: .now() + 3) == .success ? result : nil
     }
 
-    private func session(withID id: Int) -> TerminalSession? {
-        onMain { self.sessions.first { $0.id == id } } ?? nil
+    /// The config's link rules with the socket's on top.
+    private var linkPolicy: LinkPolicy {
+        var policy = config.linkPolicy
+        policy.rules.merge(linkSchemes) { _, new in new }
+        return policy
     }
 
-    /// Browser actions are asynchronous WebKit work.  The app-control socket
-    /// runs on a worker thread, schedules the UI mutation on AppKit's main
-    /// thread, then waits only on that worker for the browser completion.
-    /// This deliberately avoids blocking the main run loop during navigation.
-    private func handleBrowserControl(_ encoded: String) -> String {
-        let request: [String: Any]
-        switch BrowserControlCodec.decode(encoded) {
-        case let .success(value): request = value
-        case let .failure(error):
-            return BrowserControlCodec.response(
-                error: "invalid_request", message: error.localizedDescription)
+    /// A cmd-clicked link, opened, asked about, refused or handed to the
+    /// socket as `linkPolicy` says, and published either way.
+    private func openLink(_ url: URL, from s: TerminalSession) {
+        var action = linkPolicy.action(for: url)
+        if action == .ask {
+            let alert = NSAlert()
+            alert.messageText = "Open this link?"
+            alert.informativeText = url.absoluteString
+            alert.addButton(withTitle: "Open")
+            alert.addButton(withTitle: "Cancel")
+            action = alert.runModal() == .alertFirstButtonReturn ? .open : .deny
         }
-        if let version = request["v"] as? Int, version != 1 {
-            return BrowserControlCodec.response(
-                error: "unsupported_version", message: "Browser request version \(version) is unsupported.")
+        switch action {
+        case .open: NSWorkspace.shared.open(url)
+        case .deny: NSSound.beep()
+        case .ask, .event: break
         }
+        publish(.linkActivated(pane: s.id, url: url.absoluteString, action: action), in: s.view.window)
+    }
 
-        let done = DispatchSemaphore(value: 0)
-        let operationState = BrowserControlOperation()
-        var response = BrowserControlCodec.response(
-            error: "browser_unavailable", message: "Browser control did not start.")
-        let started = onMain { () -> Bool in
-            guard !operationState.isCancelled else { return false }
-            let operation = request["op"] as? String ?? ""
-            let finish: (String) -> Void = { value in
-                guard operationState.claimCompletion() else { return }
-                response = value
-                done.signal()
-            }
+    /// Turns `detected-links` events on or off for every pane.
+    private func setLinkDetection(_ enabled: Bool) {
+        detectingLinks.withLock { $0 = enabled }
+        sessions.forEach(syncOutputScan)
+    }
 
-            if operation == "list" {
-                let browsers = self.utilityPanels.values
-                    .flatMap { $0 }
-                    .compactMap(\.browser)
-                    .map { $0.controlState() }
-                finish(BrowserControlCodec.response(result: ["browsers": browsers]))
-                return true
+    /// Carries out one trigger match, on the pane's PTY thread.
+    private func fireTrigger(_ match: TriggerMatch, in s: TerminalSession) {
+        switch match.trigger.action {
+        case .event:
+            DispatchQueue.main.async { [weak self, weak s] in
+                self?.publish(.trigger(match), in: s?.view.window)
             }
-
-            if operation == "open" {
-                var browserRequest = request
-                if let url = request["url"] as? String, !url.isEmpty {
-                    browserRequest["op"] = "navigate"
-                } else {
-                    browserRequest["op"] = "state"
-                }
-
-                // Optional target: focus (and optionally navigate) a specific
-                // existing instance instead of the most recent one.
-                if let browserID = request["browserId"] as? String, !browserID.isEmpty {
-                    guard let record = self.browserRecord(withID: browserID),
-                          let browser = record.browser else {
-                        finish(BrowserControlCodec.response(
-                            error: "unknown_browser",
-                            message: "No live browser has id \(browserID)."))
-                        return true
-                    }
-                    self.restorePaneZoom(revealing: record.pane)
-                    record.pane.window?.makeFirstResponder(record.pane)
-                    browser.performAutomation(
-                        browserRequest, isCancelled: { operationState.isCancelled },
-                        completion: finish)
-                    return true
-                }
-
-                let host: NSWindow?
-                if let anchor = request["anchorPane"] as? Int,
-                   let session = self.sessions.first(where: { $0.id == anchor }) {
-                    host = session.view.window
-                } else {
-                    host = NSApp.keyWindow.flatMap {
-                        $0.tabbingIdentifier == "infinitty" && $0 !== self.quickTerminal.window ? $0 : nil
-                    } ?? NSApp.windows.first(where: {
-                        $0.tabbingIdentifier == "infinitty" && $0 !== self.quickTerminal.window
-                    })
-                }
-
-                let window: NSWindow
-                if let host {
-                    window = host
-                } else {
-                    // A browser needs a real native main-tab host.  Keep the
-                    // initial terminal visible instead of creating a hidden
-                    // process; it also supplies the local AI/chat context.
-                    let created = self.makeTerminalWindow()
-                    window = created.0
-                    window.orderFront(nil)
-                    created.1.launch()
+        case .highlight:
+            s.terminal.highlight(line: match.line, lo: match.start, hi: match.end)
+        case .notify:
+            guard !DoNotDisturb.shared.isActive else { return }
+            CommandNotifier.shared.post(
+                pane: match.pane, title: "Output matched \(match.trigger.pattern)", body: match.text)
+        case .run(let command):
+            // One run per trigger at a time; matches while it runs are dropped.
+            guard runningTriggers.withLock({ $0.insert(match.trigger.id).inserted }) else { return }
+            let directory = s.currentDirectory()
+            DispatchQueue.global(qos: .utility).async { [runningTriggers] in
+                var environment = ProcessInfo.processInfo.environment
+                environment["INFINITTY_PANE"] = String(match.pane)
+                environment["INFINITTY_TRIGGER"] = match.trigger.id
+                environment["INFINITTY_TRIGGER_MATCH"] = match.text
+                environment["INFINITTY_TRIGGER_LINE"] = String(match.line)
+                for (index, group) in match.groups.enumerated() {
+                    environment["INFINITTY_TRIGGER_GROUP_\(index + 1)"] = group
                 }
-                let wantsNewInstance = request["newPane"] as? Bool ?? false
-                guard let record = self.openUtilityPanel(
-                          .browser, in: window, forceNewInstance: wantsNewInstance),
-                      let browser = record.browser else {
-                    finish(BrowserControlCodec.response(
-                        error: "open_failed", message: "Could not create a browser pane."))
-                    return true
-                }
-                browser.performAutomation(
-                    browserRequest, isCancelled: { operationState.isCancelled }, completion: finish)
-                self.appControl.broadcast([
-                    "event": "browser-opened", "browserId": browser.browserID,
-                ])
-                return true
+                _ = ProcessRunner.run("/bin/sh", ["-c", command], environment: environment, currentDirectory: directory)
+                runningTriggers.withLock { $0.remove(match.trigger.id) }
             }
+        }
+    }
 
-            guard let browserID = request["browserId"] as? String, !browserID.isEmpty else {
-                finish(BrowserControlCodec.response(
-                    error: "missing_browser", message: "browserId is required."))
-                return true
+    /// `trigger-add {pattern, action, command?, pane?}` watches output for a
+    /// regex; `trigger-remove <id>` and `triggers [pane]` manage them.
+    private func handleTrigger(_ cmd: String, _ arg: String) -> String {
+        func reply(_ object: Any) -> String {
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        }
+        let text = arg.trimmingCharacters(in: .whitespaces)
+        switch cmd {
+        case "triggers":
+            if text.isEmpty { return reply(TriggerEngine.shared.triggers.map(\.wire)) }
+            guard let pane = Int(text) else { return "error: triggers [pane]" }
+            return reply(TriggerEngine.shared.triggers(for: pane).map(\.wire))
+        case "trigger-remove":
+            guard TriggerEngine.shared.remove(text) else { return "error: \(TriggerError.notFound(text))" }
+            _ = onMain { self.sessions.forEach(self.syncOutputScan) }
+            return "ok"
+        default:
+            break
+        }
+        var request: [String: Any] = ["pattern": text]
+        if text.hasPrefix("{") {
+            guard let object = try? JSONSerialization.jsonObject(with: Data(text.utf8)) as? [String: Any] else {
+                return "error: trigger-add <pattern> | {\"pattern\", \"action\", \"command\", \"pane\"}"
             }
-            guard let browser = self.browserRecord(withID: browserID)?.browser else {
-                finish(BrowserControlCodec.response(
-                    error: "unknown_browser", message: "No live browser has id \(browserID)."))
-                return true
+            request = object
+        }
+        let name = request["action"] as? String ?? "event"
+        let command = request["command"] as? String
+        guard let action = TriggerAction(name: name, command: command) else {
+            return "error: \(name.hasPrefix("run") ? TriggerError.missingCommand : .unknownAction(name))"
+        }
+        let pane = request["pane"] as? Int
+        if let pane, session(withID: pane) == nil { return "error: no pane \(pane)" }
+        switch TriggerEngine.shared.add(pattern: request["pattern"] as? String ?? "", action: action, pane: pane) {
+        case .success(let trigger):
+            _ = onMain { self.sessions.forEach(self.syncOutputScan) }
+            return reply(trigger.wire)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `rerun <id> [<line> | last | last-failed] [edit]`: types a finished
+    /// command back into its pane over whatever is at the prompt and runs
+    /// it, or with `edit` leaves it there to change first. Commands are
+    /// found by the line their output began on, as command events give it;
+    /// a pane without shell integration falls back on the command history.
+    private func handleRerun(_ arg: String) -> String {
+        let usage = "error: rerun <id> [<line> | last | last-failed] [edit]"
+        var words = arg.split(separator: " ").map { $0.lowercased() }
+        guard let first = words.first, let id = Int(first), let s = session(withID: id) else { return usage }
+        words.removeFirst()
+        let edit = words.last == "edit"
+        if edit { words.removeLast() }
+        let target = words.first ?? "last"
+        let line = Int(target)
+        guard words.count <= 1, line != nil || target == "last" || target == "last-failed" else { return usage }
+        let failedOnly = target == "last-failed"
+
+        var found: [String: Any] = [:]
+        let tracked = s.terminal.finishedCommands().filter { !($0.command ?? "").isEmpty }
+        let wanted: (CommandBlock) -> Bool = { block in
+            if let line { return block.line == line }
+            return !failedOnly || block.exitCode != 0
+        }
+        if let block = tracked.last(where: wanted), let command = block.command {
+            found = ["command": command, "exitCode": block.exitCode, "line": block.line, "source": "pane"]
+        } else if tracked.isEmpty, line == nil,
+                  let record = CommandHistory.shared.records().last(where: { !failedOnly || $0.exitCode != 0 }) {
+            found = ["command": record.command, "exitCode": record.exitCode, "source": "history"]
+        }
+        guard let command = found["command"] as? String else {
+            if let line { return "error: no finished command at line \(line)" }
+            return failedOnly ? "error: no failed command to re-run" : "error: no command to re-run"
+        }
+        guard !s.terminal.isRunningCommand else { return "error: pane \(id) is still running a command" }
+        _ = onMain { s.view.showAgentGlow() }
+        // Ctrl-U first, so a half-typed line doesn't run along with it.
+        s.pty.write([0x15] + Array(command.utf8) + (edit ? [] : [0x0D]))
+        found["edit"] = edit
+        let data = (try? JSONSerialization.data(withJSONObject: found)) ?? Data("{}".utf8)
+        return String(decoding: data, as: UTF8.self)
+    }
+
+    /// `copy-mode <id> [action …]`: keyboard selection over a pane's
+    /// scrollback. The terminal owns the cursor and selection and draws
+    /// them; each action replies with where the cursor ended up.
+    private func handleCopyMode(_ arg: String) -> String {
+        let usage = "error: copy-mode <id> [enter | exit | move <motion> [n] | select [mode] | jump <line> [column]"
+            + " | search <text> | search-back <text> | copy]"
+        var words = arg.split(separator: " ", maxSplits: 2).map(String.init)
+        guard let first = words.first, let id = Int(first), let s = session(withID: id) else { return usage }
+        words.removeFirst()
+        let action = words.first ?? ""
+        let terminal = s.terminal
+        let result: Result<CopyModeStatus, CopyModeError>
+        switch action {
+        case "", "enter":
+            result = .success(terminal.copyModeEnter())
+        case "exit":
+            terminal.copyModeExit()
+            return "ok"
+        case "move":
+            let parts = words.dropFirst().joined(separator: " ").split(separator: " ")
+            guard let name = parts.first, let motion = CopyModeMotion(rawValue: String(name)) else {
+                return "error: motion must be one of " + CopyModeMotion.allCases.map(\.rawValue).joined(separator: ", ")
             }
-            browser.performAutomation(
-                request, isCancelled: { operationState.isCancelled }, completion: finish)
-            return true
-        } ?? false
-
-        guard started else {
-            operationState.cancel()
-            return BrowserControlCodec.response(
-                error: "main_thread_timeout", message: "Could not schedule browser control on the main thread.")
+            guard let count = parts.count > 1 ? Int(parts[1]) : 1, (1...10_000).contains(count) else { return usage }
+            result = terminal.copyModeMove(motion, count: count)
+        case "select":
+            let modes: [String: Terminal.SelectionMode?] = [
+                "": .character, "character": .character, "word": .word, "line": .line, "block": .block, "none": nil,
+            ]
+            guard let mode = modes[words.count > 1 ? words[1] : ""] else { return usage }
+            result = terminal.copyModeSelect(mode)
+        case "jump":
+            let numbers = (words.count > 1 ? words[1] : "").split(separator: " ").compactMap { Int($0) }
+            guard let line = numbers.first else { return usage }
+            result = terminal.copyModeJump(line: line, column: numbers.count > 1 ? numbers[1] : 0)
+        case "search", "search-back":
+            guard words.count > 1, !words[1].isEmpty else { return usage }
+            result = terminal.copyModeSearch(words[1], backward: action == "search-back")
+        case "copy":
+            guard terminal.copyModeStatus != nil else { return "error: \(CopyModeError.inactive)" }
+            guard let selected = terminal.selectedText() else { return "error: nothing selected in pane \(id)" }
+            let text = onMain { Clipboard.write(selected, transforms: self.config.copyTransforms) } ?? selected
+            terminal.copyModeExit()
+            let data = (try? JSONSerialization.data(withJSONObject: ["text": text])) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        default:
+            return usage
         }
-        guard done.wait(timeout: .now() + 40) == .success else {
-            operationState.cancel()
-            return BrowserControlCodec.response(
-                error: "timeout", message: "Browser operation timed out.")
+        switch result {
+        case .success(let status):
+            let data = (try? JSONSerialization.data(withJSONObject: status.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `scrollback-extract <id> block | column <n> | {...}`: a rectangle or
+    /// the Nth field of each selected line, or of lines `from`...`to`.
+    private func handleScrollbackExtract(_ arg: String) -> String {
+        let usage = "error: scrollback-extract <id> block | column <n> [separator]"
+            + " | {\"mode\": \"block\" | \"column\", \"from\", \"to\", \"columns\": [lo, hi],"
+            + " \"column\", \"separator\"}"
+        let parts = arg.split(separator: " ", maxSplits: 1).map(String.init)
+        guard let first = parts.first, let id = Int(first), let s = session(withID: id) else { return usage }
+        let rest = parts.count > 1 ? parts[1].trimmingCharacters(in: .whitespaces) : ""
+        var request: [String: Any] = [:]
+        if rest.hasPrefix("{") {
+            guard let object = try? JSONSerialization.jsonObject(with: Data(rest.utf8)) as? [String: Any] else {
+                return usage
+            }
+            request = object
+        } else {
+            let words = rest.split(separator: " ", maxSplits: 2).map(String.init)
+            request["mode"] = words.first ?? "block"
+            if words.count > 1 { request["column"] = Int(words[1]) }
+            if words.count > 2 { request["separator"] = words[2] }
+        }
+        var lines: ClosedRange<Int>?
+        if let from = request["from"] as? Int {
+            let to = request["to"] as? Int ?? from
+            lines = min(from, to)...max(from, to)
+        }
+        let mode: ScrollbackExtractMode
+        switch request["mode"] as? String ?? (request["column"] != nil ? "column" : "block") {
+        case "block":
+            if let bounds = request["columns"] as? [Int] {
+                guard bounds.count == 2 else { return usage }
+                mode = .block(min(bounds[0], bounds[1])...max(bounds[0], bounds[1]))
+            } else {
+                mode = .block(nil)
+            }
+        case "column", "field":
+            guard let n = request["column"] as? Int else { return usage }
+            mode = .field(n, separator: request["separator"] as? String)
+        default:
+            return usage
         }
-        return response
+        switch s.terminal.scrollbackExtract(lines: lines, mode: mode) {
+        case .success(let extraction):
+            let data = (try? JSONSerialization.data(withJSONObject: extraction.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
+        }
+    }
+
+    /// `format {"text" | "pane", "input", "output", "query"}`: pretty-print
+    /// JSON or YAML — given inline, or a pane's last command output — and
+    /// optionally narrow it with a jq-style path.
+    private func handleFormat(_ arg: String) -> String {
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+        else { return "error: format {\"text\" | \"pane\": …, \"query\": \".items[0]\", \"output\": yaml}" }
+        let text: String
+        if let inline = request["text"] as? String {
+            text = inline
+        } else if let id = request["pane"] as? Int {
+            guard let s = session(withID: id) else { return "error: no pane \(id)" }
+            guard let output = s.terminal.lastCommandOutput() else {
+                return "error: no completed command (enable OSC 133)"
+            }
+            text = output
+        } else {
+            return "error: format needs text or pane"
+        }
+        var formats: [StructuredFormat?] = []
+        for key in ["input", "output"] {
+            guard let name = request[key] as? String else {
+                formats.append(nil)
+                continue
+            }
+            guard let format = StructuredFormat(rawValue: name.lowercased()) else {
+                return "error: \(key) must be json or yaml"
+            }
+            formats.append(format)
+        }
+        switch StructuredText.format(
+            text, input: formats[0], output: formats[1], query: request["query"] as? String) {
+        case .success(let formatted): return formatted
+        case .failure(let error): return "error: \(error)"
+        }
+    }
+
+    /// `log-read {"id", "since", "limit", "filter", "level"}`: buffered lines
+    /// after `since`. A filter given here replaces the session's for this
+    /// read only, so a client can re-filter history without reopening.
+    private func handleLogRead(_ arg: String) -> String {
+        guard let data = arg.data(using: .utf8),
+              let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let id = request["id"] as? String
+        else { return "error: log-read {\"id\": …, \"since\": seq, \"limit\": n}" }
+        guard let session = LogViewerRegistry.shared.session(id) else { return "error: no log session \(id)" }
+        var filter = session.filter
+        if request["filter"] != nil || request["level"] != nil {
+            switch LogFilter.parse(pattern: request["filter"] as? String, level: request["level"] as? String) {
+            case .success(let parsed): filter = parsed
+            case .failure(let error): return "error: \(error)"
+            }
+        }
+        let limit = min(max(request["limit"] as? Int ?? 200, 1), 1000)
+        let object = session.read(since: request["since"] as? Int ?? 0, limit: limit, filter: filter)
+        let encoded = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+        return String(decoding: encoded, as: UTF8.self)
     }
 
     private func handleAppRequest(_ request: String) -> String {
@@ -3637,22 +8683,19 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                         "cols": s.terminal.cols,
                         "rows": s.terminal.rows,
                         "socket": s.control.path,
+                        "cwd": s.currentDirectory() ?? "",
+                        "tab": s.view.window.flatMap { self.paneLedgerTabID(for: $0) } ?? "",
+                        "detached": self.detachedSessions[s.id] != nil,
                     ]
                 }
             } ?? []
             let data = (try? JSONSerialization.data(withJSONObject: panes)) ?? Data("[]".utf8)
             return String(decoding: data, as: UTF8.self)
         case "new-window":
-            let trimmed = arg.trimmingCharacters(in: .whitespaces)
-            var cwd: String?
-            if !trimmed.isEmpty {
-                guard let dir = LaunchOptions.workingDirectory(from: [trimmed]) else {
-                    return "error: no such directory: \(trimmed)"
-                }
-                cwd = dir
-            }
+            let (cwd, profile, failure) = newPaneOptions(cmd, arg)
+            if let failure { return failure }
             let id = onMain { () -> Int in
-                let (window, session) = self.makeTerminalWindow(cwd: cwd)
+                let (window, session) = self.makeTerminalWindow(cwd: cwd, profile: profile)
                 // orderFront, NOT makeKey: an agent creating a pane must never
                 // steal keyboard focus from whatever the user is typing in.
                 window.orderFront(nil)
@@ -3668,14 +8711,8 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             }
             return id.map(String.init) ?? "error: could not create window"
         case "new-tab":
-            let trimmed = arg.trimmingCharacters(in: .whitespaces)
-            var cwd: String?
-            if !trimmed.isEmpty {
-                guard let dir = LaunchOptions.workingDirectory(from: [trimmed]) else {
-                    return "error: no such directory: \(trimmed)"
-                }
-                cwd = dir
-            }
+            let (cwd, profile, failure) = newPaneOptions(cmd, arg)
+            if let failure { return failure }
             let id = onMain { () -> Int? in
                 let key = NSApp.keyWindow.flatMap {
                     $0.tabbingIdentifier == "infinitty" ? $0 : nil
@@ -3684,7 +8721,11 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                     return nil
                 }
                 let t0 = CFAbsoluteTimeGetCurrent()
-                let (window, session) = self.makeTerminalWindow(cwd: cwd)
+                let (window, session) = self.makeTerminalWindow(cwd: cwd, profile: profile)
+                if cwd == nil {
+                    let source = self.focusedSession(in: host) ?? self.activeSessions(in: host).first
+                    session.workingDirectory = self.startDirectory(for: session, from: source)
+                }
                 let t1 = CFAbsoluteTimeGetCurrent()
                 host.addTabbedWindow(window, ordered: .above)
                 let t2 = CFAbsoluteTimeGetCurrent()
@@ -3723,12 +8764,107 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             let after = onMain { self.sessions.map(\.id) } ?? []
             if let newID = after.first(where: { !before.contains($0) }) { return String(newID) }
             return "error: split failed"
+        case "clipboard-write", "clipboard-copy":
+            return handleClipboard(cmd, arg)
+        case "reveal-in-explorer", "reveal-in-finder":
+            return handleReveal(cmd, arg)
+        case "pane-display":
+            return handlePaneDisplay(arg)
+        case "git-status", "git-diff", "git-diff-stats", "git-stage-hunk", "git-stage-lines", "git-log", "git-show",
+             "git-fetch", "git-pull", "git-push", "git-remotes", "git-remote-add", "git-remote-remove",
+             "git-branches", "git-branch-create", "git-branch-delete", "git-branch-rename",
+             "git-checkout", "git-blame", "git-commit", "git-discard", "git-clean", "git-merge", "git-rebase",
+             "git-merge-abort", "git-rebase-abort", "git-merge-continue", "git-rebase-continue", "git-conflicts",
+             "git-resolve-conflict":
+            return handleGit(cmd, arg)
+        case "git-askpass", "git-credentials", "git-credential-answer", "git-credential-forget":
+            return handleGitCredentials(cmd, arg)
+        case "pane-duplicate":
+            let usage = "error: pane-duplicate <id> [right|down] [replay]"
+            guard let (s, text) = paneAndText(arg) else { return usage }
+            let words = text.split(separator: " ").map { $0.lowercased() }
+            guard words.allSatisfy({ ["right", "down", "replay"].contains($0) }),
+                  !(words.contains("right") && words.contains("down")) else { return usage }
+            let copy = onMain {
+                self.duplicatePane(of: s, vertical: !words.contains("down"), replay: words.contains("replay"))
+            } ?? nil
+            return copy.map { String($0.id) } ?? "error: pane \(s.id) can't be duplicated"
         case "focus":
             guard let (s, _) = paneAndText(arg) else { return "error: focus <id>" }
             _ = onMain {
                 self.focusSession(s)
             }
             return "ok"
+        case "zoom":
+            let usage = "error: zoom <id | browser-id> [on|off]"
+            let words = arg.split(separator: " ").map(String.init)
+            guard let target = words.first, words.count <= 2 else { return usage }
+            var wanted: Bool?
+            if words.count == 2 {
+                guard ["on", "off"].contains(words[1]) else { return usage }
+                wanted = words[1] == "on"
+            }
+            let zoomed = onMain { () -> Bool? in
+                guard let pane = Int(target).flatMap({ self.session(withID: $0)?.view })
+                        ?? self.browserRecord(withID: target)?.pane,
+                      pane.window != nil
+                else { return nil }
+                if wanted.map({ $0 != self.isPaneZoomed(pane) }) ?? true { self.togglePaneZoom(for: pane) }
+                return self.isPaneZoomed(pane)
+            } ?? nil
+            guard let zoomed else { return "error: no pane \(target) in a window" }
+            if wanted == true, !zoomed { return "error: pane \(target) is the only one in its tab" }
+            return zoomed ? "zoomed" : "restored"
+        case "pane-swap", "pane-resize":
+            let usage = cmd == "pane-swap"
+                ? "error: pane-swap <id | browser-id> [<other id | browser-id>]"
+                : "error: pane-resize <id | browser-id> <percent 10-90> | [{\"pane\", \"percent\"}, …]"
+            if cmd == "pane-resize", arg.trimmingCharacters(in: .whitespaces).hasPrefix("[") {
+                guard let batch = PaneResizeBatch.parse(arg) else { return usage }
+                return onMain { self.resizePanes(batch) } ?? "error: timed out"
+            }
+            let words = arg.split(separator: " ").map(String.init)
+            guard let first = words.first, words.count <= 2 else { return usage }
+            var percent: Int?
+            if cmd == "pane-resize" {
+                guard words.count == 2, let value = Int(words[1].trimmingCharacters(in: ["%"])),
+                      (10...90).contains(value) else { return usage }
+                percent = value
+            }
+            let outcome = onMain { () -> String in
+                func pane(_ word: String) -> NSView? {
+                    let view = Int(word).flatMap({ self.session(withID: $0)?.view })
+                        ?? self.browserRecord(withID: word)?.pane
+                    return view?.window == nil ? nil : view
+                }
+                guard let source = pane(first) else { return "error: no pane \(first) in a window" }
+                if let percent {
+                    return self.setShare(of: source, to: Double(percent) / 100)
+                        ? "ok" : "error: pane \(first) is the only one in its tab"
+                }
+                var other: NSView?
+                if words.count == 2 {
+                    guard let found = pane(words[1]), found.window === source.window else {
+                        return "error: no pane \(words[1]) in the same tab"
+                    }
+                    other = found
+                }
+                return self.swapPane(source, with: other) ? "ok" : "error: pane \(first) is the only one in its tab"
+            } ?? "error: timed out"
+            return outcome
+        case "pane-to-tab":
+            guard let (s, _) = paneAndText(arg) else { return "error: pane-to-tab <id>" }
+            let ok = onMain { self.moveToNewTab(s) } ?? false
+            return ok ? "ok" : "error: pane \(s.id) is alone in its tab or not in a main window"
+        case "pane-equalize":
+            let target = arg.trimmingCharacters(in: .whitespaces)
+            let ok = onMain { () -> Bool in
+                let window = target.isEmpty
+                    ? self.standardKeyWindow()
+                    : Int(target).flatMap { self.session(withID: $0)?.view.window }
+                return window.map { self.equalizeSplits(in: $0) } ?? false
+            } ?? false
+            return ok ? "ok" : "error: no split tab for \(target.isEmpty ? "the key window" : "pane \(target)")"
         case "surface":
             guard let (s, json) = paneAndText(arg),
                   !json.trimmingCharacters(in: .whitespaces).isEmpty else {
@@ -3783,8 +8919,97 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
                 s.terminate()
             }
             return "ok"
+        case "detach":
+            guard let (s, _) = paneAndText(arg) else { return "error: detach <id>" }
+            let ok = onMain { self.detachSession(s) } ?? false
+            return ok ? "ok" : "error: pane \(s.id) is already detached or not in a main window"
+        case "attach":
+            let usage = "error: attach <id> [<target-id> right|left|down|up]"
+            guard let (s, rest) = paneAndText(arg) else { return usage }
+            let words = rest.split(separator: " ").map(String.init)
+            var target: TerminalSession?
+            var direction = "right"
+            if !words.isEmpty {
+                guard let id = Int(words[0]), let found = session(withID: id), found !== s else { return usage }
+                target = found
+                if words.count > 1 { direction = words[1].lowercased() }
+                guard ["right", "left", "down", "up"].contains(direction) else { return usage }
+            }
+            let ok = onMain { self.attachSession(s, beside: target, direction: direction) } ?? false
+            return ok ? "ok" : "error: pane \(s.id) is not detached, or the target is not in a main window"
+        case "detached":
+            let panes = onMain { () -> [[String: Any]] in
+                self.sessions.compactMap { s in
+                    guard let since = self.detachedSessions[s.id] else { return nil }
+                    return [
+                        "id": s.id, "title": s.title, "cwd": s.currentDirectory() ?? "",
+                        "detachedAt": since.timeIntervalSince1970, "socket": s.control.path,
+                    ]
+                }
+            } ?? []
+            let data = (try? JSONSerialization.data(withJSONObject: panes)) ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "new-detached":
+            let (cwd, profile, failure) = newPaneOptions(cmd, arg)
+            if let failure { return failure }
+            // A shell with no window yet: it renders once `attach` gives it one.
+            let id = onMain { () -> Int in
+                let session = self.createSession(
+                    scale: NSScreen.main?.backingScaleFactor ?? 2, usesSharedWindowSurface: true)
+                session.workingDirectory = cwd
+                if let profile { session.profile = profile }
+                self.detachedSessions[session.id] = Date()
+                session.launch()
+                return session.id
+            }
+            return id.map(String.init) ?? "error: could not create a session"
+        case "workspace", "workspace-save", "workspace-restore":
+            return handleWorkspace(cmd)
+        case "layout", "layouts", "layout-save", "layout-apply", "layout-delete":
+            return handleLayout(cmd, arg)
+        case "broadcast-create", "broadcast-add", "broadcast-remove", "broadcast-delete", "broadcast-groups",
+             "broadcast-send", "broadcast-send-line":
+            return handleBroadcast(cmd, arg)
+        case "record-start", "record-stop", "recordings", "replay":
+            return handleRecording(cmd, arg)
+        case "trigger-add", "trigger-remove", "triggers":
+            return handleTrigger(cmd, arg)
+        case "sync-background":
+            let sampled = onMain { () -> [String: Any] in
+                let windows = Set(self.sessions.compactMap { $0.view.window })
+                windows.forEach(self.syncBackground)
+                let color = self.sampledBackground()
+                return [
+                    "background": String(
+                        format: "#%02X%02X%02X", Int(round(color.redComponent * 255)),
+                        Int(round(color.greenComponent * 255)), Int(round(color.blueComponent * 255))),
+                    "opacity": self.config.backgroundOpacity, "blur": self.config.backgroundBlur,
+                    "windows": windows.count,
+                ]
+            } ?? [:]
+            let data = (try? JSONSerialization.data(withJSONObject: sampled)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "pane-lock":
+            let parts = arg.split(separator: " ").map(String.init)
+            guard let id = parts.first.flatMap(Int.init), let s = session(withID: id),
+                  parts.count == 1 || (parts.count == 2 && ["on", "off"].contains(parts[1])) else {
+                return "error: pane-lock <id> [on|off]"
+            }
+            let locked = onMain { () -> Bool in
+                self.setInputLocked(s, parts.count == 2 ? parts[1] == "on" : !s.pty.isInputLocked)
+                return s.pty.isInputLocked
+            }
+            guard let locked else { return "error: timed out" }
+            return "{\"pane\":\(s.id),\"locked\":\(locked)}"
+        case "idle-panes", "pane-hibernate", "pane-resume":
+            return handleIdlePanes(cmd, arg)
+        case "pane-processes", "pane-signal":
+            return handlePaneProcesses(cmd, arg)
+        case "paste":
+            return handlePaste(arg)
         case "send", "send-line":
             guard let (s, text) = paneAndText(arg) else { return "error: \(cmd) <id> <text>" }
+            guard !s.pty.isInputLocked else { return "error: pane \(s.id) is input-locked" }
             _ = onMain { s.view.showAgentGlow() }
             s.pty.write(Array(text.utf8) + (cmd == "send-line" ? [0x0D] : []))
             return "ok"
@@ -3793,14 +9018,196 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             return s.terminal.screenText()
         case "history":
             guard let (s, text) = paneAndText(arg) else { return "error: history <id> <n>" }
-            let n = min(max(Int(text.trimmingCharacters(in: .whitespaces)) ?? 100, 1), Terminal.maxScrollback)
+            let n = min(max(Int(text.trimmingCharacters(in: .whitespaces)) ?? 100, 1), s.terminal.scrollbackLimit)
             return s.terminal.historyText(lines: n)
+        case "search":
+            guard let (s, text) = paneAndText(arg), !text.isEmpty else {
+                return "error: search <id> <query> | {\"query\", \"regex\", \"caseSensitive\", \"limit\"}"
+            }
+            return ControlServer.searchScrollback(s.terminal, text)
         case "last-output":
             guard let (s, _) = paneAndText(arg) else { return "error: last-output <id>" }
             return s.terminal.lastCommandOutput() ?? "error: no completed command (enable OSC 133)"
         case "last-command":
             guard let (s, _) = paneAndText(arg) else { return "error: last-command <id>" }
             return s.terminal.lastCommandLine() ?? "error: no command markers (enable OSC 133)"
+        case "cwd":
+            // The pane's own working directory (OSC 7 / process probe), not
+            // the app's — each pane answers for itself.
+            guard let (s, _) = paneAndText(arg) else { return "error: cwd <id>" }
+            return s.currentDirectory() ?? "error: pane \(s.id) has no known directory"
+        case "copy-mode":
+            return handleCopyMode(arg)
+        case "scrollback-extract":
+            return handleScrollbackExtract(arg)
+        case "links":
+            guard let (s, text) = paneAndText(arg) else { return "error: links <id> [n]" }
+            let count = min(max(Int(text.trimmingCharacters(in: .whitespaces)) ?? s.terminal.rows, 1), 10_000)
+            let links = s.terminal.recentRows(count).flatMap { SmartToken.links(in: $0.chars, line: $0.line) }
+            let data = (try? JSONSerialization.data(withJSONObject: links.map(\.wire))) ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "hyperlinks":
+            guard let (s, text) = paneAndText(arg) else { return "error: hyperlinks <id> [n]" }
+            let count = min(max(Int(text.trimmingCharacters(in: .whitespaces)) ?? s.terminal.rows, 1), 10_000)
+            let links = s.terminal.hyperlinkRanges(count)
+            let data = (try? JSONSerialization.data(withJSONObject: links.map(\.wire))) ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "link-policy":
+            return handleLinkPolicy(arg)
+        case "analyze-command":
+            return handleAnalyzeCommand(arg)
+        case "expand-globs":
+            return handleExpandGlobs(arg)
+        case "index-status", "index-rebuild", "index-search":
+            return handleSearchIndex(cmd, arg)
+        case "detect-links":
+            switch arg.trimmingCharacters(in: .whitespaces).lowercased() {
+            case "": break
+            case "on": _ = onMain { self.setLinkDetection(true) }
+            case "off": _ = onMain { self.setLinkDetection(false) }
+            default: return "error: detect-links [on | off]"
+            }
+            let data = (try? JSONSerialization.data(withJSONObject: ["enabled": detectingLinks.snapshot]))
+                ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "smart-select":
+            let usage = "error: smart-select <id> <line> <column>"
+            guard let (s, text) = paneAndText(arg) else { return usage }
+            let numbers = text.split(separator: " ").compactMap { Int($0) }
+            guard numbers.count == 2 else { return usage }
+            guard let token = s.terminal.smartSelect(line: numbers[0], column: numbers[1]) else {
+                return "error: nothing to select at line \(numbers[0]), column \(numbers[1])"
+            }
+            let data = (try? JSONSerialization.data(withJSONObject: token.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "rerun":
+            return handleRerun(arg)
+        case "scrollback-load":
+            let usage = "error: scrollback-load <id> <line> [n]"
+            guard let (s, text) = paneAndText(arg) else { return usage }
+            let numbers = text.split(separator: " ").compactMap { Int($0) }
+            guard (1...2).contains(numbers.count) else { return usage }
+            let count = min(max(numbers.count > 1 ? numbers[1] : 1000, 1), 10_000)
+            let loaded = s.terminal.scrollbackLines(from: numbers[0], count: count)
+            let reply: [String: Any] = [
+                "firstLine": loaded.firstLine, "lines": loaded.lines, "spilling": s.terminal.spillsScrollback,
+            ]
+            let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "scrollback-usage":
+            // Memory each pane's scrollback holds against `scrollback-budget`.
+            let report = onMain { () -> [String: Any] in
+                var total = 0
+                let panes = self.sessions.map { s -> [String: Any] in
+                    let usage = s.terminal.scrollbackUsage
+                    total += usage.bytes
+                    return [
+                        "pane": s.id, "lines": usage.lines, "bytes": usage.bytes, "capacity": usage.capacity,
+                        "capped": self.scrollbackCapped.contains(s.id), "spilledLines": usage.spilled,
+                    ]
+                }
+                return ["budget": self.config.scrollbackBudget, "bytes": total, "panes": panes]
+            }
+            guard let report else { return "error: timed out" }
+            let data = (try? JSONSerialization.data(withJSONObject: report)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "fold":
+            let usage = "error: fold <id> [<line> | last] [on | off | toggle]"
+            guard let (s, text) = paneAndText(arg) else { return usage }
+            let words = text.split(separator: " ").map { $0.lowercased() }
+            guard let target = words.first else {
+                let blocks = s.terminal.commandOutputBlocks().map(\.wire)
+                let data = (try? JSONSerialization.data(withJSONObject: blocks)) ?? Data("[]".utf8)
+                return String(decoding: data, as: UTF8.self)
+            }
+            let line = Int(target)
+            guard line != nil || target == "last", words.count <= 2 else { return usage }
+            let folded: Bool?
+            switch words.count > 1 ? words[1] : "toggle" {
+            case "on": folded = true
+            case "off": folded = false
+            case "toggle": folded = nil
+            default: return usage
+            }
+            switch s.terminal.foldCommand(line: line, folded: folded) {
+            case .success(let block):
+                let data = (try? JSONSerialization.data(withJSONObject: block.wire)) ?? Data("{}".utf8)
+                return String(decoding: data, as: UTF8.self)
+            case .failure(let error):
+                return "error: \(error)"
+            }
+        case "output-diff":
+            return handleOutputDiff(arg)
+        case "pane-info":
+            guard let (s, _) = paneAndText(arg) else { return "error: pane-info <id>" }
+            let info = onMain { () -> [String: Any] in
+                var object: [String: Any] = [
+                    "pane": s.id, "title": self.paneHeaderTitle(for: s), "shellPid": s.pty.pid,
+                    "running": s.terminal.isRunningCommand,
+                ]
+                if let (cwd, source) = s.directoryAndSource() {
+                    object["cwd"] = cwd
+                    object["cwdSource"] = source.rawValue
+                }
+                if let process = s.processTracker?.current, process.pid != s.pty.pid {
+                    var foreground: [String: Any] = [
+                        "pid": process.pid, "name": process.rawName, "displayName": process.displayName,
+                    ]
+                    if let path = process.executablePath { foreground["path"] = path }
+                    object["process"] = foreground
+                }
+                if let progress = s.progress.current { object["progress"] = progress.wire }
+                if let prompt = s.secureInput.current { object["secureInput"] = prompt.wire }
+                if let profile = s.profile { object["profile"] = profile.name }
+                if s.pty.isInputLocked { object["inputLocked"] = true }
+                if s.isHibernated { object["hibernated"] = true }
+                return object
+            } ?? [:]
+            let data = (try? JSONSerialization.data(withJSONObject: info)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "progress":
+            // One pane's progress, or every pane's that has any.
+            let object: Any
+            if arg.trimmingCharacters(in: .whitespaces).isEmpty {
+                object = onMain {
+                    self.sessions.compactMap { s -> [String: Any]? in
+                        guard var wire = s.progress.current?.wire else { return nil }
+                        wire["pane"] = s.id
+                        return wire
+                    }
+                } ?? []
+            } else {
+                guard let (s, _) = paneAndText(arg) else { return "error: progress [id]" }
+                var wire = s.progress.current?.wire ?? ["state": "none"]
+                wire["pane"] = s.id
+                object = wire
+            }
+            let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "pane-title":
+            guard let (s, _) = paneAndText(arg) else { return "error: pane-title <id>" }
+            let info = onMain { () -> [String: Any] in
+                let cwd = s.currentDirectory()
+                var object: [String: Any] = ["pane": s.id, "title": self.paneHeaderTitle(for: s)]
+                if let override = s.paneTitleOverride, !override.isEmpty {
+                    object["source"] = "override"
+                } else if s.agentSessionName != nil {
+                    object["source"] = "agent"
+                } else if s.reportedTitle != nil {
+                    object["source"] = "reported"
+                } else {
+                    object["source"] = (s.reportedDirectory ?? s.workingDirectory) == nil ? "default" : "directory"
+                }
+                if let reported = s.reportedTitle { object["reported"] = reported }
+                if let icon = s.iconName { object["icon"] = icon }
+                if let process = s.processTracker?.current, process.pid != s.pty.pid {
+                    object["process"] = process.displayName
+                }
+                if let cwd { object["cwd"] = cwd }
+                return object
+            } ?? [:]
+            let data = (try? JSONSerialization.data(withJSONObject: info)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
         case "exit-code":
             guard let (s, _) = paneAndText(arg) else { return "error: exit-code <id>" }
             if let code = s.terminal.lastExitCode() { return String(code) }
@@ -3842,9 +9249,206 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             ]
             let data = (try? JSONSerialization.data(withJSONObject: payload)) ?? Data("{}".utf8)
             return String(decoding: data, as: UTF8.self)
+        case "fs-copy", "fs-move", "fs-delete", "fs-hash":
+            return handleFileOperation(cmd, arg)
+        case "fs-read":
+            return handleFileRead(arg)
+        case "format":
+            return handleFormat(arg)
+        case "log-open":
+            return handleLogOpen(arg)
+        case "log-read":
+            return handleLogRead(arg)
+        case "log-pause", "log-follow":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            guard let session = LogViewerRegistry.shared.session(id) else {
+                return "error: \(cmd) <log-id>"
+            }
+            session.setPaused(cmd == "log-pause")
+            return "ok"
+        case "log-close":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            return LogViewerRegistry.shared.close(id) ? "ok" : "error: no log session \(id)"
+        case "share-start":
+            return handleShareStart(arg)
+        case "share-mode":
+            let sub = arg.split(separator: " ").map(String.init)
+            guard sub.count == 3, let id = Int(sub[0]), sub[2] == "ro" || sub[2] == "rw" else {
+                return "error: share-mode <id> <participant> ro|rw"
+            }
+            guard let host = SessionShareRegistry.shared.host(pane: id) else { return "error: pane \(id) is not shared" }
+            guard host.setReadWrite(sub[2] == "rw", for: sub[1]) else {
+                return "error: \(SessionShareError.noParticipant(pane: id, sub[1]))"
+            }
+            return "ok"
+        case "share-stop":
+            guard let id = Int(arg.trimmingCharacters(in: .whitespaces)) else { return "error: share-stop <id>" }
+            guard SessionShareRegistry.shared.stop(pane: id) else { return "error: pane \(id) is not shared" }
+            session(withID: id)?.setOutputTap("share", nil)
+            return "ok"
+        case "share-snapshot":
+            return handleShareSnapshot(arg)
+        case "share-join":
+            return handleShareJoin(arg)
+        case "share-read":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            guard let share = SessionShareRegistry.shared.joined(id) else {
+                return "error: \(SessionShareError.notJoined(id))"
+            }
+            let data = (try? JSONSerialization.data(withJSONObject: share.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "share-send", "share-send-line":
+            let parts = arg.split(separator: " ", maxSplits: 1, omittingEmptySubsequences: false).map(String.init)
+            guard parts.count == 2, !parts[0].isEmpty else { return "error: \(cmd) <share-id> <text>" }
+            guard let share = SessionShareRegistry.shared.joined(parts[0]) else {
+                return "error: \(SessionShareError.notJoined(parts[0]))"
+            }
+            switch share.send(parts[1] + (cmd == "share-send-line" ? "\r" : "")) {
+            case .success: return "ok"
+            case .failure(let error): return "error: \(error)"
+            }
+        case "share-leave":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            return SessionShareRegistry.shared.leave(id) ? "ok" : "error: \(SessionShareError.notJoined(id))"
+        case "history-stats":
+            guard let range = HistoryStats.range(arg) else {
+                return "error: history-stats [today | all | <days>d]"
+            }
+            let stats = HistoryStats(CommandHistory.shared.records(), range: range)
+            let data = (try? JSONSerialization.data(withJSONObject: stats.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "history-search":
+            switch HistoryQuery.parse(arg) {
+            case .success(let query):
+                let hits = query.search(CommandHistory.shared.records()).map { hit -> [String: Any] in
+                    var object = hit.record.wire
+                    object["score"] = hit.score
+                    return object
+                }
+                let data = (try? JSONSerialization.data(withJSONObject: hits)) ?? Data("[]".utf8)
+                return String(decoding: data, as: UTF8.self)
+            case .failure(let error):
+                return "error: history-search: \(error)"
+            }
+        case "suggest-correction":
+            // Runs on the socket thread: history, PATH and rc files are all
+            // local reads, so this answers at once.
+            let command = arg.trimmingCharacters(in: .whitespacesAndNewlines)
+            guard !command.isEmpty else { return "error: suggest-correction <command line>" }
+            let history = CommandHistory.shared.records()
+            let suggestions = MissingCommand.suggestCorrection(
+                for: command, known: Self.knownPrograms(history: history), history: history,
+                formulae: Self.formulae(providing:))
+            let reply: [String: Any] = [
+                "command": command, "program": CommandRecord.program(of: command),
+                "suggestions": suggestions.map(\.wire),
+            ]
+            let data = (try? JSONSerialization.data(withJSONObject: reply)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "packages", "packages-upgrade":
+            return handlePackages(cmd, arg)
+        case "cloud-profiles":
+            let data = (try? JSONSerialization.data(withJSONObject: CloudProfiles.discover().map(\.wire)))
+                ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "cloud-set-profile":
+            return handleCloudSetProfile(arg)
+        case "ssh-keys", "ssh-keygen", "ssh-copy-id":
+            return handleSSHKeys(cmd, arg)
+        case "net-dns", "net-ping", "net-traceroute":
+            return handleNetwork(cmd, arg)
+        case "net-wol", "hosts", "host-watch", "host-unwatch":
+            return handleHosts(cmd, arg)
+        case "text-util":
+            return handleTextUtil(arg)
+        case "regex-test", "regex-patterns", "regex-save", "regex-delete":
+            return handleRegex(cmd, arg)
+        case "eval":
+            return handleEval(arg)
+        case "speech-start", "speech-stop":
+            return handleSpeech(cmd, arg)
+        case "timetrack-report", "pomodoro":
+            return handleTimeTracking(cmd, arg)
+        case "project-automation", "project-trust", "project-untrust":
+            return handleProjectAutomation(cmd, arg)
+        case "watch-start", "watch-stop", "watches", "watch-output":
+            return handleCommandWatches(cmd, arg)
+        case "tmux-attach", "tmux-detach", "tmux-clients", "tmux-command":
+            return handleTmux(cmd, arg)
+        case "serial-ports", "serial-open":
+            return handleSerial(cmd, arg)
+        case "daemon-status", "daemon-enable", "daemon-attach":
+            return handleDaemon(cmd, arg)
+        case "secure-input", "secure-fill", "secure-store", "secure-forget":
+            return handleSecureInput(cmd, arg)
+        case "profiles", "profile-save", "profile-remove":
+            return handleProfiles(cmd, arg)
+        case "pins-list", "pins-add", "pins-edit", "pins-remove", "pins-move", "pins-run":
+            return handlePinnedCommands(cmd, arg)
+        case "startup-commands", "startup-commands-set":
+            return handleStartupCommands(cmd, arg)
+        case "macros", "macro-record-start", "macro-record-stop", "macro-play", "macro-remove":
+            return handleMacros(cmd, arg)
+        case "bookmarks-list", "bookmarks-add", "bookmarks-edit", "bookmarks-remove", "bookmarks-move",
+             "bookmarks-folder-rename", "bookmarks-open":
+            return handleBrowserBookmarks(cmd, arg)
+        case "notes-get", "notes-set", "todos-add", "todos-toggle", "todos-list":
+            return handleWorkspaceNotes(cmd, arg)
+        case "deps":
+            return handleDependencies(arg)
+        case "services", "service-open":
+            return handleServices(cmd, arg)
+        case "shares":
+            let data = (try? JSONSerialization.data(withJSONObject: SessionShareRegistry.shared.list()))
+                ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "ready":
+            let data = (try? JSONSerialization.data(withJSONObject: launchReadiness.snapshot()))
+                ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "tasks":
+            let list = BackgroundTaskRegistry.shared.list()
+            let data = (try? JSONSerialization.data(withJSONObject: list)) ?? Data("[]".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "cancel-operation":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            guard !id.isEmpty else { return "error: cancel-operation <request-id>" }
+            return CancellationRegistry.shared.cancel(id)
+                ? "ok" : "error: no running operation \(id)"
+        case "task-cancel":
+            let id = arg.trimmingCharacters(in: .whitespaces)
+            guard !id.isEmpty else { return "error: task-cancel <task-id>" }
+            return BackgroundTaskRegistry.shared.cancel(id)
+                ? "ok" : "error: no running task \(id)"
         case "activity":
+            guard !DoNotDisturb.shared.isActive else { return "ok: held back by do not disturb" }
             _ = onMain { self.notch.showCustom(text: arg) }
             return "ok"
+        case "dnd":
+            let value = arg.trimmingCharacters(in: .whitespaces).lowercased()
+            if !value.isEmpty {
+                guard let mode = DoNotDisturb.Mode(rawValue: value) else { return "error: dnd [auto | on | off]" }
+                DoNotDisturb.shared.setMode(mode)
+            } else {
+                DoNotDisturb.shared.refresh()
+            }
+            let data = (try? JSONSerialization.data(withJSONObject: DoNotDisturb.shared.snapshot.wire))
+                ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case "notify-long-commands":
+            var policy = CommandNotifier.shared.policy
+            for word in arg.split(separator: " ") {
+                if let seconds = Double(word), seconds >= 0 {
+                    policy.threshold = seconds
+                } else if ["on", "off"].contains(word.lowercased()) {
+                    policy.enabled = word.lowercased() == "on"
+                } else {
+                    return "error: notify-long-commands [on | off] [seconds]"
+                }
+            }
+            CommandNotifier.shared.configure(policy)
+            let data = (try? JSONSerialization.data(withJSONObject: policy.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
         case "toggle-quick-terminal":
             _ = onMain { self.quickTerminal.toggle() }
             return "ok"
@@ -3874,17 +9478,18 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             }
             return "ok"
         case "sidebar-tab":
-            // Compatibility command: Files/Changes share one pane; Chat owns
-            // its own independent pane.
+            // Compatibility command: Files/Changes share one pane; Chat and
+            // Notes own their own independent panes.
             let name = arg.trimmingCharacters(in: .whitespaces).lowercased()
-            guard ["files", "changes", "git", "chat"].contains(name) else {
-                return "error: sidebar-tab files|changes|chat"
+            guard ["files", "changes", "git", "chat", "notes"].contains(name) else {
+                return "error: sidebar-tab files|changes|chat|notes"
             }
             let ok = onMain { () -> Bool in
                 guard let win = NSApp.keyWindow
                     ?? NSApp.windows.first(where: { $0.tabbingIdentifier == "infinitty" })
                 else { return false }
                 if name == "chat" { return self.openUtilityPanel(.chat, in: win) != nil }
+                if name == "notes" { return self.openUtilityPanel(.notes, in: win) != nil }
                 guard let controller = self.openCodeView(in: win) else { return false }
                 return controller.selectPage(named: name)
             } ?? false
@@ -3907,8 +9512,55 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             return ok ? "ok" : "error: no match for \(cmd) '\(value)'"
         default:
             return "error: unknown command '\(cmd)' (ping | version | list | new-window | new-tab | "
-                + "split | focus | close | send | send-line | screen | history | last-output | "
-                + "last-command | exit-code | run | activity | toggle-quick-terminal | toggle-sidebar | "
+                + "split | focus | zoom | pane-swap | pane-to-tab | pane-equalize | pane-resize | "
+                + "close | detach | attach | detached | new-detached | "
+                + "layout | layouts | layout-save | layout-apply | layout-delete | "
+                + "workspace | workspace-save | workspace-restore | sync-background | "
+                + "git-status | git-diff | git-diff-stats | git-stage-hunk | git-stage-lines | git-log | git-show | "
+                + "git-fetch | git-pull | git-push | git-remotes | git-remote-add | git-remote-remove | "
+                + "git-branches | git-branch-create | git-branch-delete | git-branch-rename | "
+                + "git-checkout | git-blame | git-commit | git-discard | git-clean | git-merge | git-rebase | "
+                + "git-merge-abort | git-rebase-abort | git-merge-continue | git-rebase-continue | git-conflicts | "
+                + "git-resolve-conflict | git-credentials | git-credential-answer | git-credential-forget | "
+                + "record-start | record-stop | recordings | replay | trigger-add | trigger-remove | triggers | "
+                + "broadcast-create | broadcast-add | broadcast-remove | broadcast-delete | broadcast-groups | "
+                + "broadcast-send | broadcast-send-line | "
+                + "send | send-line | pane-lock | screen | history | search | last-output | pane-title | pane-info | "
+                + "paste | clipboard-write | clipboard-copy | pane-duplicate | reveal-in-explorer | reveal-in-finder | "
+                + "pane-display | "
+                + "idle-panes | pane-hibernate | pane-resume | "
+                + "pane-processes | pane-signal | "
+                + "progress | copy-mode | "
+                + "scrollback-extract | smart-select | links | hyperlinks | link-policy | detect-links | "
+                + "analyze-command | expand-globs | "
+                + "fold | output-diff | rerun | "
+                + "scrollback-load | scrollback-usage | index-status | index-rebuild | index-search | "
+                + "last-command | exit-code | cwd | run | fs-copy | fs-move | fs-delete | fs-read | "
+                + "fs-hash | format | log-open | log-read | log-pause | log-follow | log-close | "
+                + "share-start | share-mode | share-stop | shares | share-snapshot | "
+                + "share-join | share-read | share-send | share-send-line | share-leave | "
+                + "history-stats | history-search | suggest-correction | packages | packages-upgrade | "
+                + "cloud-profiles | "
+                + "cloud-set-profile | "
+                + "ssh-keys | ssh-keygen | ssh-copy-id | net-dns | net-ping | net-traceroute | "
+                + "net-wol | hosts | host-watch | host-unwatch | text-util | "
+                + "regex-test | regex-patterns | regex-save | regex-delete | eval | "
+                + "speech-start | speech-stop | dnd | notify-long-commands | timetrack-report | pomodoro | "
+                + "notes-get | notes-set | todos-add | todos-toggle | todos-list | deps | "
+                + "pins-list | pins-add | pins-edit | pins-remove | pins-move | pins-run | "
+                + "startup-commands | startup-commands-set | "
+                + "macros | macro-record-start | macro-record-stop | macro-play | macro-remove | "
+                + "bookmarks-list | bookmarks-add | bookmarks-edit | bookmarks-remove | bookmarks-move | "
+                + "bookmarks-folder-rename | bookmarks-open | "
+                + "project-automation | project-trust | project-untrust | "
+                + "watch-start | watch-stop | watches | watch-output | "
+                + "tmux-attach | tmux-detach | tmux-clients | tmux-command | serial-ports | serial-open | "
+                + "daemon-status | daemon-enable | daemon-attach | "
+                + "secure-input | secure-fill | secure-store | secure-forget | "
+                + "profiles | profile-save | profile-remove | "
+                + "services | service-open | ready | tasks | "
+                + "task-cancel | cancel-operation | activity | "
+                + "toggle-quick-terminal | toggle-sidebar | "
                 + "sidebar | sidebar-tab | chat-model | chat-effort | browser | subscribe)"
         }
     }
@@ -3945,6 +9597,24 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
 
     private func reloadConfig() {
         config = AppConfig.load()
+        ProcessRunner.defaultTimeout = config.processTimeout
+        SearchIndexes.shared.limits = config.searchIndexLimits
+        DoNotDisturb.shared.setMode(config.doNotDisturb)
+        CommandNotifier.shared.configure(LongCommandPolicy(
+            enabled: config.notifyLongCommands, threshold: config.notifyLongCommandsAfter))
+        setLinkDetection(config.detectLinks)
+        // A pane the budget capped keeps spilling: its oldest rows are
+        // only on disk.
+        sessions.forEach { $0.setScrollbackSpill(config.scrollbackSpill || scrollbackCapped.contains($0.id)) }
+        for s in sessions {
+            s.view.confirmsPaste = config.pasteConfirmation
+            s.view.confirmsDangerousCommands = config.confirmDangerousCommands
+            s.view.copiesOnSelect = config.copyOnSelect
+            s.view.copyTransforms = config.copyTransforms
+        }
+        configureTimeTracking()
+        configureIdlePolicy()
+        configureScrollbackBudget()
         CodePalette.apply(config)
         configureSessionNotch()
         quickTerminal.applyConfig(config)
@@ -3953,7 +9623,7 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         for s in sessions {
             let scale = s.view.window?.backingScaleFactor
                 ?? NSScreen.main?.backingScaleFactor ?? 2
-            s.renderer.applyConfig(config, scale: scale)
+            s.renderer.applyConfig(s.display.applied(to: config), scale: scale)
             s.applyMarkdownConfig(config)
             s.view.needsLayout = true // re-derives cols/rows from new metrics
             s.terminal.touch()
@@ -3961,20 +9631,9 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         }
         for win in windows {
             if let s = sessions.first(where: { $0.view.window === win }) {
-                applyWindowBacking(to: win, renderer: s.renderer)
                 win.contentResizeIncrements = s.renderer.cellSizePoints
-                let bg = s.renderer.backgroundColor
-                let color = NSColor(
-                    srgbRed: CGFloat(bg.x), green: CGFloat(bg.y), blue: CGFloat(bg.z),
-                    alpha: CGFloat(bg.w))
-                terminalChromes[ObjectIdentifier(win)]?.setBacking(
-                    color: color, blur: config.backgroundBlur)
-                utilityPanels[ObjectIdentifier(win)]?.forEach {
-                    $0.pane.updateSurface(
-                        background: color.withAlphaComponent(config.backgroundOpacity),
-                        blurred: config.backgroundBlur)
-                }
             }
+            syncBackground(in: win)
             applyTabTint(to: win)
             refreshTabStrips(in: win)
         }
@@ -4056,7 +9715,11 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         titleOverrides.removeValue(forKey: ObjectIdentifier(win))
         tabPins.removeValue(forKey: ObjectIdentifier(win))
         tabTints.removeValue(forKey: ObjectIdentifier(win))
-        utilityPanels.removeValue(forKey: ObjectIdentifier(win))
+        // Web views would otherwise outlive their window with automation
+        // still pending and no browser-closed event for subscribers.
+        utilityPanels.removeValue(forKey: ObjectIdentifier(win))?.forEach {
+            tearDownWebContent(of: $0, in: win)
+        }
         sidebarToggleAccessories.removeValue(forKey: ObjectIdentifier(win))?.detach()
         terminalChromes.removeValue(forKey: ObjectIdentifier(win))
         updateIndicators.removeValue(forKey: ObjectIdentifier(win))
@@ -4068,9 +9731,10 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         // a child that keeps the pty open (nohup) would otherwise pin the
         // whole session graph forever.
         for s in closing {
-            pendingLaunchCommands.removeValue(forKey: s.id)
+            pendingLaunchCommands.removeValue(forKey: s.id)?.completion?(-1)
             petAssistants.removeValue(forKey: s.id)?.detach()
             runQueues.removeValue(forKey: s.id)?.forEach { $0.completion(-1) }
+            daemonPanes.removeValue(forKey: s.id)?.close()
         }
         // Repaint the surviving siblings' strips on the next runloop (after
         // AppKit drops this window from the tab group); without this a closed
@@ -4138,11 +9802,23 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
         fileMenu.addItem(withTitle: "Split Down", action: #selector(AppDelegate.splitDown(_:)), keyEquivalent: "D")
         fileMenu.addItem(withTitle: "Split Left", action: #selector(AppDelegate.splitLeft(_:)), keyEquivalent: "")
         fileMenu.addItem(withTitle: "Split Up", action: #selector(AppDelegate.splitUp(_:)), keyEquivalent: "")
+        fileMenu.addItem(
+            withTitle: "Duplicate Pane", action: #selector(AppDelegate.duplicatePane(_:)), keyEquivalent: "")
         let zoomPane = fileMenu.addItem(
             withTitle: "Toggle Pane Zoom",
             action: #selector(AppDelegate.togglePaneZoom(_:)),
             keyEquivalent: "\r")
         zoomPane.keyEquivalentModifierMask = [.command, .shift]
+        AppDelegate.paneArrangementMenuItems().forEach(fileMenu.addItem)
+        fileMenu.addItem(
+            withTitle: "Lock Pane Input", action: #selector(AppDelegate.toggleInputLock(_:)), keyEquivalent: "")
+        fileMenu.addItem(
+            withTitle: "Bigger Text in Pane", action: #selector(AppDelegate.biggerText(_:)), keyEquivalent: "=")
+        fileMenu.addItem(
+            withTitle: "Smaller Text in Pane", action: #selector(AppDelegate.smallerText(_:)), keyEquivalent: "-")
+        fileMenu.addItem(
+            withTitle: "Default Text Size in Pane", action: #selector(AppDelegate.defaultTextSize(_:)),
+            keyEquivalent: "0")
         let renameTab = fileMenu.addItem(
             withTitle: "Rename Tab…",
             action: #selector(AppDelegate.renameTab(_:)),
@@ -4216,6 +9892,10 @@ public final class AppDelegate: NSObject, NSApplicationDelegate, NSWindowDelegat
             action: #selector(AppDelegate.newBrowserPane(_:)),
             keyEquivalent: "b")
         newBrowserItem.keyEquivalentModifierMask = [.command, .option]
+        windowMenu.addItem(
+            withTitle: "Notes",
+            action: #selector(AppDelegate.openNotesPane(_:)),
+            keyEquivalent: "")
 
         let focusPaneItem = NSMenuItem(title: "Focus Pane", action: nil, keyEquivalent: "")
         let focusPaneMenu = NSMenu(title: "Focus Pane")
@@ -12,17 +12,396 @@ import Foundation
 ///   list                     -> JSON array of panes (id, title, focused, …)
 ///   new-window [dir]         -> pane id of the new window's session
 ///   new-tab [dir]            -> pane id (tab of the key window); optional
-///                               dir = shell starting directory
+///                               dir = shell starting directory, else where
+///                               new-pane-directory says. new-window,
+///                               new-tab and new-detached also take JSON
+///                               {"cwd"?, "profile"?} to start a profile
 ///   split <id> right|left|down|up -> pane id of the new split
+///   pane-duplicate <id> [right|down] [replay]
+///                            -> pane id of a split with the pane's live
+///                               directory, profile and environment (a
+///                               trusted project's exports included); with
+///                               replay its last command runs again there
+///   reveal-in-explorer <id> <path>
+///                            -> {pane, path, in}: selects the path in the
+///                               Files pane (opened if needed); relative
+///                               paths are read from the pane's directory
+///                               and a :line:column suffix is ignored
+///   reveal-in-finder <id> <path> -> {pane, path, in}: the same, in Finder
 ///   focus <id>               -> ok (raises + focuses the pane)
+///   zoom <id|browser-id> [on|off] -> zoomed | restored; toggles (or sets)
+///                               the pane filling its tab, siblings hidden
+///   pane-swap <id|browser-id> [<other>] -> ok; trades places with other,
+///                               default the tab's next pane in reading order
+///   pane-to-tab <id>         -> ok; the terminal leaves its split for a new
+///                               tab beside it, shell and scrollback intact
+///   pane-equalize [id]       -> ok; every split in that pane's tab (default
+///                               the key window's) gets even shares
+///   pane-resize <id|browser-id> <percent> -> ok; the pane takes percent
+///                               (10-90) of its split, siblings split the rest
+///   pane-resize [{"pane", "percent"}, …]
+///                            -> ok; the same for several panes in one pass,
+///                               a pane named twice taking its last percent,
+///                               with one layout-changed event per window
 ///   close <id>               -> ok (terminates the pane's shell)
+///   detach <id>              -> ok; the pane leaves its window (closing the
+///                               tab if it was the last) but its shell and
+///                               scrollback live on; pane commands still work
+///   attach <id> [<target> right|left|down|up] -> ok; back beside the
+///                               target pane, or as a new tab without one
+///   detached                 -> JSON array of detached panes (id, title,
+///                               cwd, detachedAt, socket)
+///   new-detached [dir]       -> pane id of a new shell with no window
+///   layout [<id>]            -> JSON {shape, panes}: the split geometry of
+///                               that pane's tab (default: the focused one)
+///   layouts                  -> JSON array of layout presets, built-ins first
+///   layout-save <name> | {"name", "pane", "shape"} -> the saved preset;
+///                               captures the tab's geometry unless a shape
+///                               ("pane" | {"columns"|"rows": […], "sizes"})
+///                               is given
+///   layout-apply <name> | {"name" | "shape", "pane"} -> JSON {panes, added,
+///                               layout}; rebuilds the tab's splits to match,
+///                               adding shells for empty slots
+///   layout-delete <name>     -> ok
+///   workspace                -> JSON {windows: [{frame, selectedTab, tabs:
+///                               [{shape, panes: [{cwd, profile}], focused,
+///                               browsers?: [{id, url, beside, vertical,
+///                               share}]}]}], panes}: what a launch would
+///                               reopen
+///   workspace-save           -> the same plus path, written now (it is also
+///                               saved a moment after each layout change)
+///   workspace-restore        -> {panes}: reopens the saved windows
+///   git-status [id | dir]    -> {repo, branch, changes: [{path, status,
+///                               staged}], upstream?, ahead?, behind?,
+///                               upstreamGone?, error?}: the repo holding
+///                               the pane's (default focused) directory or
+///                               dir; a git-status-changed event follows
+///                               when it next moves on disk
+///   git-fetch [id | dir] | {"pane" | "dir", "remote"}
+///                            -> git-status's reply after git fetch --prune
+///                               (the branch's remote by default), so ahead
+///                               and behind are current
+///   git-pull [id | dir] | {"pane" | "dir", "rebase"}
+///                            -> the same plus git's output: pulls into the
+///                               current branch, rebasing or merging as
+///                               rebase says (pull.rebase when absent).
+///                               An HTTPS remote that wants credentials
+///                               raises git-credential-prompt and waits;
+///                               ssh never prompts
+///   git-push [id | dir] | {"pane" | "dir", "remote", "branch",
+///              "forceWithLease", "setUpstream", "tags"}
+///                            -> the same after git push: the current
+///                               branch (or branch, a refspec) to its remote,
+///                               origin for one without. setUpstream
+///                               defaults to on for a branch with no
+///                               upstream yet; tags pushes every tag, and
+///                               only the tags without a branch
+///   git-credentials          -> [{id, prompt, url?, username?, secret}]:
+///                               credential prompts git commands the app
+///                               runs are waiting on (90 seconds each)
+///   git-credential-answer {"id", "answer" | "cancel", "remember"}
+///                            -> ok; the answer goes to git (cancel fails
+///                               it), and remember keeps it in the Keychain
+///                               for the same prompt until git rejects it
+///   git-credential-forget [url]
+///                            -> {forgotten: [prompt]}: remembered answers
+///                               for url (https://host), or all of them
+///   git-remotes [id | dir]   -> {repo, remotes: [{name, fetch, push}]}
+///   git-remote-add {"pane" | "dir", "name", "url"}
+///                            -> the same after adding it
+///   git-remote-remove {"pane" | "dir", "name"}
+///                            -> the same after removing it and its
+///                               remote-tracking branches
+///   git-branches [id | dir]  -> {repo, current?, local, remote}: branch
+///                               names, remote ones as origin/name
+///   git-branch-create {"pane" | "dir", "name", "from", "checkout"}
+///                            -> git-status's reply after creating name at
+///                               from (default HEAD), switching to it when
+///                               checkout; from a remote branch it tracks it
+///   git-branch-delete {"pane" | "dir", "name", "force"}
+///                            -> the same; force deletes unmerged commits
+///   git-branch-rename {"pane" | "dir", "from", "to"}
+///                            -> the same; from defaults to the current one
+///   git-checkout {"pane" | "dir", "branch"}
+///                            -> the same after switching; a remote branch
+///                               (origin/topic) without a local one gets a
+///                               local topic that tracks it
+///   git-commit {"pane" | "dir", "message", "amend", "sign", "signingKey",
+///               "author", "committer"}
+///                            -> the same plus commit (the new HEAD). amend
+///                               rewrites the last commit, keeping its
+///                               message when none is given; sign true or
+///                               false overrides commit.gpgsign (GPG or SSH
+///                               per gpg.format); author and committer are
+///                               "Name <email>". A commit that couldn't be
+///                               signed fails with "signing failed: …"
+///   git-discard {"pane" | "dir", "file"}
+///                            -> the same after putting file back as staged
+///                               (or committed); its unstaged edits are lost
+///   git-clean {"pane" | "dir", "paths", "dryRun"}
+///                            -> {repo, dryRun, wouldRemove | removed: [path]}
+///                               untracked files and folders (ignored ones
+///                               stay) under paths (default the whole repo);
+///                               dryRun defaults to true and deletes nothing,
+///                               a real clean also carries the new status
+///   git-merge {"pane" | "dir", "branch"}
+///   git-rebase {"pane" | "dir", "onto"}
+///                            -> the same plus git's output; when it stops
+///                               on conflicts, also operation (merge or
+///                               rebase) and conflicts: [path]
+///   git-merge-abort | git-rebase-abort | git-merge-continue |
+///   git-rebase-continue [id | dir] | {"pane" | "dir"}
+///                            -> the same, for the operation in progress;
+///                               continuing needs every conflict staged
+///   git-conflicts [id | dir] | {"pane" | "dir", "path"}
+///                            -> {repo, operation?, conflicts: [{path, kind,
+///                               base?, ours?, theirs?, binary}]}: each
+///                               unmerged path's three versions (absent
+///                               when that side has none, all of them when
+///                               binary); in a rebase ours is the upstream
+///   git-resolve-conflict {"pane" | "dir", "file", "content"}
+///                            -> git-status's reply plus conflicts and
+///                               operation, after writing content over the
+///                               conflicted file and staging it
+///   git-diff <id> <file> | {"pane" | "dir", "file", "staged"}
+///                            -> {repo, file, staged, added, removed, binary,
+///                               hunks: [{header, oldStart, oldLines,
+///                               newStart, newLines, added, removed, lines:
+///                               [{kind, old?, new?, text}]}]}: the work
+///                               tree against the index (an untracked file
+///                               is all additions), or the index against
+///                               HEAD when staged
+///   git-stage-hunk {"pane" | "dir", "file", "hunk", "unstage"}
+///                            -> {repo, file, unstaged, remainingHunks}:
+///                               stages one hunk of git-diff's (its index or
+///                               header), or unstages one of the staged
+///                               diff's, as git add -p would
+///   git-stage-lines {"pane" | "dir", "file", "old": [[from, to]],
+///                    "new": [[from, to]], "unstage"}
+///                            -> the same, for the removed lines numbered
+///                               in old and added lines numbered in new
+///   git-log [id | dir] | {"pane" | "dir", "limit", "skip", "ref", "path",
+///            "graph"}        -> {repo, commits: [{hash, parents, author,
+///                               email, date, subject, refs, graph?: {column,
+///                               joining, parents, passing}}], nextSkip?}:
+///                               50 commits from HEAD by default (ref "all"
+///                               for every branch); graph adds lanes
+///   git-show <id> <hash> | {"pane" | "dir", "hash"}
+///                            -> the commit as git-log lists it plus body
+///                               and files [{path, added, removed}]
+///   git-blame <id> <file> | {"pane" | "dir", "file", "from", "to", "rev"}
+///                            -> {repo, file, lines: [{line, hash, author,
+///                               email, date, summary, text, committed}]}:
+///                               the last change to each line of the
+///                               worktree file (at rev when given), only
+///                               from...to when given; uncommitted lines
+///                               have a zero hash
+///   git-diff-stats [id | dir]
+///                            -> {repo, unstaged, staged}, each {added,
+///                               removed, files: [{path, added, removed,
+///                               binary?}]}
+///   sync-background          -> JSON {background, opacity, blur, windows};
+///                               repaints window backings, chrome, smart
+///                               panes and browser backdrops from the theme
+///   record-start <id> [path] -> JSON {path, events, startedAt, recording};
+///                               writes the pane's output to an asciicast v2
+///                               file (default under Application Support/
+///                               infinitty/recordings) until record-stop
+///   record-stop <id>         -> JSON {path, events, duration}
+///   recordings               -> JSON array of active recordings, with pane
+///   replay <path> | {"path", "pane", "speed", "maxIdle"}
+///                            -> starts a replay task and replies with its
+///                               snapshot; each event arrives as replay-frame
+///                               at its recorded time, and is drawn into pane
+///   trigger-add <pattern> | {"pattern", "action", "command", "pane"}
+///                            -> JSON {id, pattern, action, command?, pane?,
+///                               fired}: a regex matched against each row of
+///                               output once it is finished, in one pane or
+///                               all. action: event (default; a trigger
+///                               event), run (sh -c command with the match
+///                               in INFINITTY_TRIGGER_MATCH, groups in
+///                               INFINITTY_TRIGGER_GROUP_n), highlight, notify
+///   trigger-remove <id>      -> ok
+///   triggers [pane]          -> JSON array of triggers (those applying to pane)
+///   broadcast-create <id> [id …] -> JSON {id, panes}; keystrokes typed in
+///                               any member are mirrored to the others
+///   broadcast-add <group> <id …> / broadcast-remove <group> <id …>
+///                            -> the group's {id, panes}; removing the last
+///                               member deletes it
+///   broadcast-delete <group> -> ok
+///   broadcast-groups         -> JSON array of {id, panes}
+///   broadcast-send <group> <text> / broadcast-send-line <group> <text>
+///                            -> JSON {group, panes}: the text (plus return)
+///                               written to every member
 ///   send <id> <text>         -> ok (type into pane; triggers agent glow)
-///   send-line <id> <text>    -> ok (type + return)
+///   send-line <id> <text>    -> ok (type + return); both refused while
+///                               the pane is input-locked
+///   paste <id> <text> | <id> {"text", "confirm"}
+///                            -> {pane, pasted, needsConfirmation?, lines,
+///                               removed, bracketed, concerns}: pastes as
+///                               the clipboard does, control characters
+///                               stripped and bracketed when asked for; with
+///                               concerns (multiline, privileged,
+///                               pipe-to-shell, destructive,
+///                               hidden-characters) it is only sent with
+///                               confirm, or paste-confirmation off, and a
+///                               paste-held event goes out
+///   clipboard-write <text> | {"text", "transforms"}
+///                            -> {text, transforms}: copies the text cleaned
+///                               by copy-transforms, or by the given ones
+///                               (trim, strip-ansi, strip-prompts; all, none)
+///   clipboard-copy <id>      -> {pane, text, transforms}: the pane's
+///                               selection, cleaned the same way
+///   pane-display <id> [{"fontSize", "lineSpacing"}]
+///                            -> {pane, fontSize, lineSpacing, overrides}:
+///                               what the pane draws with; the JSON gives it
+///                               its own (null returns one to the config's)
+///   pane-lock <id> [on|off]  -> {pane, locked}: toggles (or sets) the
+///                               pane read-only; keys, paste, sends and
+///                               broadcasts are dropped until unlocked
+///   idle-panes               -> {policy: {timeout, action}, panes: [{pane,
+///                               idleSeconds, atPrompt, hibernated}]}
+///   pane-hibernate <id>      -> {pane, hibernated}: ends the shell, keeps
+///                               the pane and scrollback (local shells only)
+///   pane-resume <id>         -> {pane, hibernated}: a new shell where the
+///                               old one was; a key press does the same
+///   pane-processes <id>      -> {pane, shellPid, processes: [{pid, ppid,
+///                               pgid, name, path?, foreground, depth}]}:
+///                               the shell and everything under it
+///   pane-signal <id> <int|term|kill|hup|quit|stop|cont> [pid]
+///                            -> {pane, signal, pids}: signals a process
+///                               in the pane, or without a pid the
+///                               foreground job's process group
 ///   screen <id>              -> pane's visible screen
 ///   history <id> <n>         -> last n lines
+///   search <id> <query> | <id> {"query", "regex", "caseSensitive", "limit"}
+///                            -> JSON {matches: [{line, column, length, text}],
+///                               truncated, firstLine, lastLine}
 ///   last-output <id>         -> last command's output (OSC 133)
 ///   last-command <id>        -> last command line (OSC 133)
 ///   exit-code <id>           -> last exit code (OSC 133)
+///   cwd <id>                 -> the pane's working directory (foreground
+///                               tool's cwd, else the shell's OSC 7 report,
+///                               else the shell's probed cwd)
+///   copy-mode <id> [enter | exit | move <motion> [n] | select [character|word|
+///                   line|block|none] | jump <line> [column] | search <text> |
+///                   search-back <text> | copy]
+///                            -> JSON {line, column, firstLine, lastLine,
+///                               viewOffset, selection?, text?}: keyboard
+///                               selection over scrollback, drawn in the pane.
+///                               Motions: left, right, up, down, word-next,
+///                               word-prev, line-start, line-end, page-up,
+///                               page-down, top, bottom. copy puts the
+///                               selection on the clipboard, replies {text}
+///                               and leaves copy mode
+///   scrollback-extract <id> block | column <n> [separator] | {mode, from, to,
+///                   columns: [lo, hi], column, separator}
+///                            -> JSON {firstLine, lastLine, values, text}:
+///                               per line of the selection (or of absolute
+///                               lines from...to), the cells in columns
+///                               lo...hi (default the block selection's) or
+///                               whitespace/separator field n, 1-based,
+///                               negative from the end
+///   links <id> [n]           -> JSON array of {kind (url, path, git-hash),
+///                               line, start, end, text, path?, fileLine?,
+///                               fileColumn?} in the last n rows (default a
+///                               screenful); columns are cells
+///   hyperlinks <id> [n]      -> JSON array of {line, start, end, url, id?}:
+///                               OSC 8 links printed in the last n rows,
+///                               one per run of cells
+///   link-policy [{"scheme", "action"} | {"url"}]
+///                            -> {rules, url?, action?}: what cmd-clicking
+///                               a link of each scheme does (open, ask,
+///                               deny, or event: publish link-activated
+///                               and open nothing); sets one scheme until
+///                               quit, null restores the config's
+///                               link-schemes; url says what it would do
+///   analyze-command <command line> | {"command", "pane" | "dir"}
+///                            -> {command, risky}, and when risky {directory,
+///                               risks, targets: [{argument, matches,
+///                               matchCount, count}], affected, truncated}:
+///                               what confirm-dangerous-commands would hold
+///                               Enter for, with globs expanded from the
+///                               pane's directory and files counted; runs
+///                               nothing
+///   expand-globs <pattern> | {"pattern", "pane" | "dir", "limit"}
+///                            -> {pattern, directory, alternatives, matches,
+///                               count, truncated}: the absolute paths a
+///                               shell pattern names, after brace expansion
+///                               ({a,b}, {1..5}), ~ and * ? [...] globs, from
+///                               the pane's directory; at most limit (10000)
+///   detect-links [on|off]    -> {enabled}: while on, each read that
+///                               finishes rows with links publishes a
+///                               detected-links event (config detect-links)
+///   smart-select <id> <line> <column>
+///                            -> JSON {kind, line, start, end, text}: selects
+///                               what a double-click at that cell would — a
+///                               url, path, uuid, ip, git-hash, json object
+///                               (from one of its braces) or word
+///   fold <id> [<line>|last] [on|off|toggle]
+///                            -> JSON {line, lastLine, lines, exitCode,
+///                               folded, summary, command?}: folds a finished
+///                               command's output (by the line its command
+///                               events give; default the latest, toggling)
+///                               to one summary row in the viewport; with
+///                               no line, the array of foldable commands
+///   output-diff <id> [<line> <line>]
+///                            -> JSON {a, b (each as fold lists it, plus
+///                               pane), identical, added, removed, lines:
+///                               [{kind (hunk|context|add|del), old?, new?,
+///                               text}]}: the second command's output
+///                               against the first's, as unified hunks.
+///                               Default: the latest command against the
+///                               last earlier run of the same command line.
+///                               JSON {"a": {"pane", "line"}, "b": {...},
+///                               "context"} compares across panes
+///   scrollback-load <id> <line> [n]
+///                            -> JSON {firstLine, lines, spilling}: up to n
+///                               (default 1000) lines of text from absolute
+///                               line on, reading rows past scrollback-lines
+///                               back from disk when scrollback-spill is on
+///   scrollback-usage         -> JSON {budget, bytes, panes: [{pane, lines,
+///                               bytes, capacity, capped, spilledLines}]}:
+///                               scrollback memory against scrollback-budget
+///   index-status [id|dir]    -> JSON {root, state (building|ready), files,
+///                               bytes, skipped, truncated, maxBytes,
+///                               maxFiles, updated?} for the workspace (repo
+///                               root, else the directory) holding the pane's
+///                               or dir's files, starting its index; with no
+///                               argument, {indexes: [...]} for every live one
+///   index-rebuild [id|dir]   -> the same, after dropping the index and
+///                               starting a fresh pass over the tree
+///   index-search {"pane" | "dir", "query", "limit"}
+///                            -> JSON {root, indexed, matches: [{path, line,
+///                               text}]}: lines containing query (ASCII case
+///                               folded), from the trigram index, or from
+///                               grep (indexed false) while it builds
+///   rerun <id> [<line>|last|last-failed] [edit]
+///                            -> JSON {command, exitCode, line?, source,
+///                               edit}: types a finished command (by line,
+///                               or the latest, or latest failing) back in
+///                               over the prompt's input and runs it; edit
+///                               leaves it unrun. source is pane, or history
+///                               for a pane without shell integration
+///   pane-title <id>          -> JSON {pane, title, source, reported?, icon?,
+///                               process?, cwd?}; title is the pane header's,
+///                               source says what it came from (override,
+///                               agent, reported OSC 0/2, directory, default)
+///   pane-info <id>           -> JSON {pane, title, shellPid, running, cwd?,
+///                               cwdSource?, process?}; cwdSource is process
+///                               (a foreground tool's cwd), osc7, probe (the
+///                               shell's cwd) or launch; process is the
+///                               foreground program {pid, name, displayName,
+///                               path?}, absent at the prompt; progress as
+///                               below while a command shows any, and
+///                               secureInput {prompt, source} at a password
+///                               prompt
+///   progress [id]            -> JSON {pane, state, source, percent?}: how far
+///                               the pane's command has got, read from OSC
+///                               9;4 (source osc) or from percentages and
+///                               progress bars in its output (output); state
+///                               is normal, error, paused, indeterminate or
+///                               none. Without an id, every pane with any
 ///   todos <id> [json]        -> set (or read, with no json) the pane's agent
 ///                               todo list; array of strings or of
 ///                               {text|content, done|status} objects
@@ -35,14 +414,382 @@ import Foundation
 ///                               surface id; MCP-UI/json-render messages
 ///                               stream as "ui" events
 ///   surface-close <sid>      -> close an agent surface (split or window)
+///   fs-copy <json>           -> {from, to, conflict: skip|overwrite|rename};
+///   fs-move <json>              runs as a background task and replies with
+///   fs-delete <json>            its snapshot once done (or after 120s);
+///                               result = {entries, bytes, skipped,
+///                               warnings, failures, cancelled}
+///   fs-read <json>           -> {path, limit?}: UTF-8 text of a file, capped
+///                               (default 64 KB, max 200 KB) and memory-mapped,
+///                               so huge files are never read whole; replies
+///                               {path, size, truncated, text}
+///   fs-hash <json>           -> {path}: SHA-256 as a background task,
+///                               result = {sha256}
+///   format <json>            -> {text | pane, input?, output?, query?}:
+///                               pretty-print JSON or YAML (given inline, or
+///                               the pane's last command output) as json or
+///                               yaml, optionally through a jq-style path
+///                               like .items[].name
+///   log-open <json>          -> {path | command, cwd?, filter?, level?}:
+///                               follow a file (tail -F) or a command's
+///                               output; filter is a regex, level a minimum
+///                               severity. Replies {id}; new matching lines
+///                               stream as "log-lines" events
+///   log-read <json>          -> {id, since?, limit?, filter?, level?}: lines
+///                               after seq `since` as {seq, text, level?,
+///                               highlights?: [[utf16 offset, length]]},
+///                               plus latestSeq / paused / ended
+///   log-pause <log-id>       -> ok; stop reading the source (no lines lost)
+///   log-follow <log-id>      -> ok; resume following
+///   log-close <log-id>       -> ok; stop the tail or command
+///   share-start <id>         -> {pane, code, relay}: stream the pane,
+///                               end-to-end encrypted, through share-relay.
+///                               Guests watch read-only. Refused unless
+///                               session-sharing = true in the config
+///   share-mode <id> <participant> ro|rw
+///                            -> ok; grant or revoke that guest's input
+///   share-stop <id>          -> ok; disconnect the relay and every guest
+///   shares                   -> JSON array of live shares (pane, relay,
+///                               participants: [{id, name, mode}])
+///   share-join <code> [name] -> {id, relay, participant, mode, cols, rows,
+///                               screen}: watch another app's share as a
+///                               guest through the same relay
+///   share-read <share-id>    -> the same, with the host's current screen
+///                               and ended (the reason) once it is over;
+///                               an ended share's id is gone after that
+///   share-send <share-id> <text>
+///                            -> ok; type into the host's pane, once it has
+///                               granted this guest rw (share-send-line adds
+///                               Enter)
+///   share-leave <share-id>   -> ok; say goodbye and disconnect
+///   share-snapshot <id> [n]  -> {url, via, lines, bytes}: the last n lines
+///                               (default all) as styled HTML, uploaded to
+///                               snapshot-paste-url if set (via "paste"),
+///                               else served on 127.0.0.1 for an hour
+///                               (via "local")
+///   history-stats [range]    -> usage over the local command history
+///                               (today | all | <n>d, default 30d): total,
+///                               failureRate, per-day and per-hour counts,
+///                               topCommands and directories with their
+///                               failure rates
+///   history-search <query>   -> [{command, exitCode, started, duration,
+///                               cwd?, pane?, score}]: fuzzy search of every
+///                               pane's logged commands, best match first
+///                               (newest first without a query). JSON
+///                               {"query", "cwd", "pane", "failed": bool,
+///                               "since": today|<n>d, "limit" (default
+///                               50), "unique" (default true)} filters
+///   suggest-correction <command line>
+///                            -> {command, program, suggestions:[{kind
+///                               (did-you-mean, install), command}]}: for
+///                               an unknown program, near misses among
+///                               PATH, aliases and history, and installs;
+///                               else a mistyped subcommand fixed from
+///                               history (git psuh -> git push)
+///   packages [outdated|installed]
+///                            -> {manager, path, packages:[{name, kind,
+///                               installed, latest?}]} from Homebrew or
+///                               MacPorts (default outdated)
+///   packages-upgrade [name …] -> opens a tab running the upgrade (all
+///                               outdated when no names) and replies with
+///                               its packages-upgrade task snapshot plus pane
+///   cloud-profiles           -> JSON array of AWS profiles, gcloud
+///                               configurations and Azure subscriptions
+///                               (provider, name, id?, detail?, default,
+///                               production) from the CLIs' config files
+///   cloud-set-profile <id> aws|gcp|azure [profile]
+///                            -> ok; types an export (or, with no profile,
+///                               an unset) of the provider's variables into
+///                               the pane's shell and badges its header.
+///                               Refused while a program is in the foreground
+///   ssh-keys                 -> {agent, keys:[{fingerprint, type, bits,
+///                               comment, publicKey?, privateKey?,
+///                               encrypted?, permissionsTooOpen, inAgent}]}
+///                               from ~/.ssh and `ssh-add -l`
+///   ssh-keygen <json>        -> {"type": ed25519|ecdsa|rsa, "bits"?,
+///                               "path"?, "comment"?, "passphrase"?}; the
+///                               new key's entry. Never overwrites a key
+///   ssh-copy-id <json>       -> {"host", "key"?, "port"?}: opens a tab
+///                               running ssh-copy-id; {pane, command}
+///   net-dns <host> [type] [@server]
+///                            -> {host, type, status, answers:[{name, type,
+///                               ttl, value}], elapsed} via dig (default A)
+///   net-ping <host> [count]  -> starts a net-ping task (default 5 echoes)
+///                               and replies with its snapshot; the result
+///                               is {address, sent, received, loss, replies,
+///                               min, avg, max, stddev}
+///   net-traceroute <host> [max-hops]
+///                            -> starts a net-traceroute task (default 30
+///                               hops); the result is {hops:[{hop,
+///                               addresses, rtts}], complete}
+///   net-wol <mac|host> [broadcast]
+///                            -> {mac, broadcast}; sends a Wake-on-LAN
+///                               packet (a watched host's saved MAC by name)
+///   hosts                    -> JSON array of watched hosts: {host, name?,
+///                               mac?, interval, up?, rtt?, checked?, since?}
+///   host-watch <json>        -> {"host", "name"?, "mac"?, "interval"?}:
+///                               pings the host every interval seconds
+///                               (default 30); its status entry
+///   host-unwatch <host>      -> ok
+///   text-util <json>         -> {"op", "text" | "pane" | "path", "key"?}:
+///                               base64-encode/-decode, url-encode/-decode,
+///                               jwt-decode (no verification), or an
+///                               md5/sha1/sha256/sha384/sha512/blake3 hex
+///                               digest of text, a pane's selection or a
+///                               file; jwt-verify -> {valid, algorithm}
+///   regex-test <json>        -> {"pattern", "flags"? (imsxw), "sample",
+///                               "replace"?}: {valid, matches:[{start, end,
+///                               text, groups}], count, groupCount,
+///                               groupNames, replaced?} in UTF-16 offsets,
+///                               or {valid: false, error, offset?}
+///   regex-patterns           -> JSON array of built-in and saved patterns
+///   regex-save <json>        -> {"name", "pattern", "flags"?, "sample"?};
+///                               the saved entry (must compile)
+///   regex-delete <name>      -> ok
+///   eval <expression>        -> {value, text, unit?, kind?, rates?}:
+///                               arithmetic, unit conversion ("1536 MiB in
+///                               GB") and currency at cached ECB rates
+///   speech-start [locale]    -> ok once listening (asks for microphone and
+///                               speech access the first time); transcripts
+///                               arrive as speech events
+///   speech-stop              -> {text}: the transcript so far
+///   dnd [auto|on|off]        -> {active, mode, focus, focusName?}; auto
+///                               follows macOS Focus. While active the
+///                               bell is silent and pet bubbles and
+///                               `activity` are held back
+///   notify-long-commands [on|off] [seconds]
+///                            -> {enabled, threshold}: post a macOS
+///                               notification when a command (OSC 133)
+///                               that ran at least threshold seconds
+///                               finishes while its window is in the
+///                               background. Lasts until config reload
+///   timetrack-report [range] -> {from, to, total, workspaces: [{workspace,
+///                               seconds, hours, days}]}: active time per
+///                               repo (needs time-tracking = true); range
+///                               is today, yesterday, week, last-week,
+///                               month, last-month, 7d or YYYY-MM-DD[..]
+///   pomodoro [minutes|stop]  -> {minutes, started, ends, remaining,
+///                               workspace?}; no argument reports the one
+///                               running ({} when none)
+///   notes-get [dir]          -> {workspace, notes, todos}; dir (default the
+///                               focused pane's) resolves to its repo root.
+///                               These commands also take JSON {"dir", ...}
+///   notes-set <json>         -> {"text", "dir"?}; ok
+///   todos-add <text>         -> the todo {id, text, done, created}
+///   todos-toggle <id>        -> the todo; JSON {"id", "done"?} sets it
+///   todos-list [dir]         -> {workspace, todos, comments?}; JSON with
+///                               "scan": true adds the TODO/FIXME comments
+///                               found by rg [{path, line, tag, text}]
+///   pins-list [dir]          -> {workspace, pins: [{id, command, title?,
+///                               hotkey?}]}, in palette order. The pins-*
+///                               commands also take JSON {"dir", ...}
+///   pins-add <command>       -> the pin; JSON {"command", "title"?,
+///                               "hotkey"?} (e.g. "cmd+shift+b")
+///   pins-edit <json>         -> {"id", "command"?, "title"?, "hotkey"?};
+///                               the pin. An empty title or hotkey clears it
+///   pins-remove <id>         -> the removed pin
+///   pins-move <json>         -> {"id", "index"}; {workspace, pins}
+///   pins-run <id>            -> the pin plus pane; types it into "pane"
+///                               (default the focused one) and runs it
+///   startup-commands [pane | dir] -> {workspace, commands, pane?,
+///                               profile?, run?: {commands: [{command,
+///                               source, exitCode?}], finished, failed}}:
+///                               the lines every new pane in the workspace
+///                               types after its profile's startup, and
+///                               how a pane's own run went. Exit codes need
+///                               shell integration (OSC 133)
+///   startup-commands-set <json> -> {"commands": [...], "dir"? | "pane"?};
+///                               {workspace, commands}. [] removes them
+///   macros                   -> {macros: [{name, steps, bytes, duration,
+///                               created}], recording: [{name, pane,
+///                               steps}]}. The macro-* commands take a name
+///                               or JSON {"name", "pane"?, "speed"?}
+///   macro-record-start <name> -> starts recording what is written to
+///                               "pane" (default the focused one): keys,
+///                               pastes and sends, with their timing, but
+///                               not what's typed at a password prompt
+///   macro-record-stop [name] -> the saved macro plus pane; stops the
+///                               named recording, else the pane's, else
+///                               the only one. Saving replaces a macro
+///                               with the same name
+///   macro-play <name>        -> the macro plus pane, speed and seconds;
+///                               types it into "pane" at its recorded pace
+///                               divided by "speed" (default 1)
+///   macro-remove <name>      -> the removed macro
+///   bookmarks-list [dir]     -> {workspace, bookmarks: [{id, url, title?,
+///                               folder?}], folders}, browser bookmarks in
+///                               order. The bookmarks-* commands also take
+///                               JSON {"dir", ...}
+///   bookmarks-add <url>      -> the bookmark; JSON {"url", "title"?,
+///                               "folder"?} (e.g. "Docs/API")
+///   bookmarks-edit <json>    -> {"id", "url"?, "title"?, "folder"?}; the
+///                               bookmark. An empty title or folder clears it
+///   bookmarks-remove <id>    -> the removed bookmark; JSON {"folder"}
+///                               removes that folder with everything in it
+///   bookmarks-move <json>    -> {"id", "index"}; {workspace, bookmarks,
+///                               folders}
+///   bookmarks-folder-rename <json>
+///                            -> {"from", "to"}; the same. Subfolders move
+///                               along; an empty "to" lifts them to the top
+///   bookmarks-open <id>      -> {bookmark, browser}: focuses a browser pane
+///                               already on the page, else opens a new one
+///   project-automation [dir] -> {path, directory, hash, env, run, layout?,
+///                               trust (trusted|ignored|ask)} for the
+///                               .infinitty.toml at or above dir (default
+///                               the focused pane's); {} when there is none
+///   project-trust [dir]      -> the same, after trusting its current
+///                               content; project-untrust ignores it
+///   watch-start <json>       -> {"command", "interval"?, "paths"?,
+///                               "pane"?, "dir"?}; the watch {id, command,
+///                               directory?, pane?, interval?, paths, runs}.
+///                               Re-runs on the interval and/or when files
+///                               under paths change; each run whose output
+///                               or exit code moved sends a watch event
+///   watch-stop <id>          -> ok
+///   watches                  -> JSON array of watches, with exitCode and
+///                               lastRun once they have run
+///   watch-output <id>        -> the watch plus lines [{line, text,
+///                               changed}] of its last run, and removed
+///   tmux-attach [session]    -> {id, socket?, session?, windows, panes}:
+///                               attaches to a tmux server in control mode
+///                               (tmux -C); each tmux window opens as a tab
+///                               and its panes as native splits that type
+///                               into, resize, split and close the tmux
+///                               panes. JSON {"socket", "session"}; socket
+///                               is a -L name or a -S path
+///   tmux-detach <id>         -> ok; tmux keeps running, the tabs close
+///   tmux-clients             -> JSON array of {id, socket?, session?, panes}
+///   tmux-command <id> <cmd>  -> {lines}: any tmux command's output
+///   serial-ports             -> JSON array of {path, name, pane?}: the
+///                               /dev/cu.* devices, and the pane with each
+///                               open
+///   serial-open <port> [baud] -> {pane, port, baud, dataBits, parity,
+///                               stopBits, flowControl}: opens a serial
+///                               device as a new tab in place of a shell
+///                               (115200 8N1 by default); pane commands,
+///                               events and triggers work on it as on any
+///                               pane. JSON {"port", "baud", "dataBits",
+///                               "parity": none|even|odd, "stopBits",
+///                               "flowControl": none|hardware|software}
+///   secure-input [id]        -> {pane, active, prompt?, source?, fills?}:
+///                               whether the pane is asking for a password
+///                               (source echo: its tty reads with echo off;
+///                               prompt: its output ends in one) and the
+///                               Keychain entries offered there. Without an
+///                               id, an array of the panes that are
+///   secure-fill <id> [account] -> ok: types that Keychain entry (default
+///                               the first offered) and Return at the
+///                               pane's password prompt; refused elsewhere
+///   secure-store {"account", "password", "prompt"?} -> ok: saves an entry
+///                               in the login keychain (service
+///                               infinitty.prompt); prompt is a regex for
+///                               the prompts it's offered at
+///   secure-forget <account>  -> ok: deletes the entry
+///   daemon-status            -> {enabled, running, socket, pid?, sessions:
+///                               [{id, pid, attached, started, pane?}]}:
+///                               the shells infinitty-ptyd holds; pane is
+///                               the one showing it here
+///   daemon-enable [on|off]   -> the status after setting `daemon` in
+///                               settings.conf; on starts the daemon, and
+///                               new shells run there from then on
+///   daemon-attach [id]       -> {panes: [{pane, id}]}: reopens that shell,
+///                               or every detached one, as a tab
+///   profiles [name]          -> [{name, shell?, args?, env, startup, cwd?,
+///                               newPaneDirectory?, default}]: saved shell
+///                               profiles, or one
+///   profile-save {"name", "shell"?, "args"?, "env"?, "startup"?, "cwd"?,
+///                "newPaneDirectory"?}
+///                            -> the profile; adds or replaces it. No args
+///                               means a login shell; env values expand
+///                               $VAR; startup lines are typed at launch;
+///                               newPaneDirectory (home | inherit |
+///                               workspace) overrides new-pane-directory
+///   profile-remove <name>    -> ok
+///   deps [dir]               -> task snapshot; the result is {root,
+///                               dependencies: [{name, ecosystem,
+///                               requirement, version?, license?, dev,
+///                               advisories}], summary} for the nearest
+///                               Cargo/npm/pip/Go/Bundler manifests, with
+///                               OSV advisories (cached a day). JSON
+///                               {"dir", "offline": true} skips OSV
+///   services                 -> {endpoints: [{id, source (local|docker|
+///                               kubernetes), name, host?, port?, url?,
+///                               targetPort?, pid?, detail?, forward?}],
+///                               sources: {local|docker|kubernetes: "ok" or
+///                               why it is missing}}
+///   service-open <id|port>   -> the URL, opened in a Browser pane
+///   ready                    -> JSON launch readiness per deferred subsystem
+///                               (socket-sweep, mcp-registration, notch):
+///                               {ready, error?}
+///   tasks                    -> JSON array of background tasks (id, kind,
+///                               state, completed/total, result|error)
+///   task-cancel <task-id>    -> ok (cooperative; the task ends "cancelled")
+///   cancel-operation <id>    -> ok; cancels a background task by task id or
+///                               a browser request by the `requestId` it
+///                               was sent with
 ///   activity <text>          -> show text in the notch live-activity widget
 ///   toggle-quick-terminal    -> show or hide the persistent quick terminal
 ///   browser <base64url-json> -> native browser automation request/reply JSON
 ///                               (use the infinitty_browser MCP tools rather
-///                               than constructing this framing by hand)
-///   subscribe                -> connection stays open; JSON events stream in:
-///                               pane-opened, pane-closed, title, marker,
-///                               process (foreground process changed)
+///                               than constructing this framing by hand);
+///                               a request only reaches browsers in the tab
+///                               of its anchorPane, else of the key window;
+///                               ops network-capture {enabled} and
+///                               network-log {after, clear} keep a pane's
+///                               page loads, fetch and XHR calls, each also
+///                               a browser-network-request event
+///   subscribe [tab-id]       -> connection stays open; JSON events stream in:
+///                               pane-opened, pane-closed, pane-detached,
+///                               pane-attached, layout-changed (zoom,
+///                               unzoom, preset), title, marker,
+///                               command-started (pane, command?, line),
+///                               command-finished (… exitCode, duration),
+///                               process (foreground process changed),
+///                               cwd (pane, cwd, source: a pane's working
+///                               directory moved),
+///                               task-progress (background task updates),
+///                               subsystem-ready (deferred launch work done),
+///                               browser-*, surface-closed, ui, log-lines,
+///                               log-ended, share-participant, share-ended,
+///                               pane-suggestion (fixes for a command not
+///                               found or a mistyped subcommand:
+///                               did-you-mean or install),
+///                               cloud-profile, net-ping / net-traceroute
+///                               (one reply or hop, with its "task"),
+///                               replay-frame (task, time, kind, data),
+///                               trigger (an event trigger matched: trigger,
+///                               pane, line, start, end, text, groups),
+///                               detected-links (pane, links, as `links`
+///                               reports them; needs detect-links on),
+///                               host-status (a watched host went up/down),
+///                               speech (dictation: text so far, final),
+///                               dnd (Do Not Disturb turned on or off),
+///                               pomodoro (a pomodoro finished),
+///                               notes (a workspace's notes or todos changed),
+///                               output-flood (pane, flooding; the end adds
+///                               bytes and seconds),
+///                               watch (watch, pane?, run, exitCode, lines,
+///                               changed and removed [{line, text}]),
+///                               progress (pane, state, source?, percent?;
+///                               state none once the command is done),
+///                               secure-input (pane, active, prompt?,
+///                               source?), input-lock (pane, locked),
+///                               pane-idle (pane, action, idleSeconds,
+///                               snapshot?), pane-resumed (pane),
+///                               paste-held (pane, lines, removed, hidden,
+///                               bracketed, concerns), command-held (pane,
+///                               command, risks, targets, affected),
+///                               git-status-changed
+///                               (repo, branch, changes, error?),
+///                               link-activated (pane, url, action),
+///                               git-credential-prompt (id, prompt, url?,
+///                               username?, secret), git-credential-resolved
+///                               (id, answered), startup-failed (pane,
+///                               command, source, exitCode).
+///                               Events from
+///                               a window carry its "tab" id (see `list`);
+///                               with a tab id only that tab's events and
+///                               app-wide ones are sent
 final class AppControlServer {
     let path: String
     static let currentLink = "/tmp/infinitty-current.sock"
@@ -51,8 +798,13 @@ final class AppControlServer {
     var handler: ((String) -> String)?
 
     private var listenFD: Int32 = -1
-    private var subscribers: [Int32] = []
-    private let subscriberLock = NSLock()
+    private let subscribers = LockedState([Subscriber]())
+
+    /// A `subscribe` connection and the tab it filters on (nil = all).
+    private struct Subscriber {
+        let fd: Int32
+        let tab: String?
+    }
 
     init() {
         path = AppControlServer.ownSocketPath
@@ -101,8 +853,9 @@ final class AppControlServer {
         return String(cString: buf)
     }
 
+    /// Bind and listen. The stale-socket sweep is separate
+    /// (`sweepStaleSockets`) so launch can run it after the first window.
     func start() {
-        AppControlServer.sweepStaleSockets()
         unlink(path)
         let fd = socket(AF_UNIX, SOCK_STREAM, 0)
         guard fd >= 0 else { return }
@@ -154,48 +907,50 @@ final class AppControlServer {
         if AppControlServer.readlinkString(AppControlServer.currentLink) == path {
             unlink(AppControlServer.currentLink)
         }
-        subscriberLock.lock()
-        for fd in subscribers { close(fd) }
-        subscribers.removeAll()
-        subscriberLock.unlock()
+        subscribers.withLock { subscribers in
+            for subscriber in subscribers { close(subscriber.fd) }
+            subscribers.removeAll()
+        }
     }
 
-    /// Push a JSON event line to every subscriber (called from main).
+    /// Push `event` as a JSON line to every subscriber whose filter admits
+    /// `tab`, the id of the tab it came from (nil = app-wide). Called from
+    /// main, or from the emitting queue for app-wide events.
     /// Subscriber sockets carry a short SO_SNDTIMEO (set at subscribe time),
     /// so each write returns within milliseconds; slow or dead readers are
     /// pruned here. A stalled subscriber can never park AppKit behind
     /// write(2). Removal and close happen under the lock, so teardown in the
     /// subscribe handler never double-closes a pruned (and possibly reused)
     /// descriptor.
-    func broadcast(_ object: [String: Any]) {
+    func broadcast(_ event: AppEvent, tab: String? = nil) {
+        let object = event.payload(tab: tab)
         guard let data = try? JSONSerialization.data(withJSONObject: object) else { return }
         let line = Array(data) + [0x0A]
         DispatchQueue.global(qos: .utility).async { [weak self] in
             guard let self else { return }
-            self.subscriberLock.lock()
-            let targets = self.subscribers
-            self.subscriberLock.unlock()
+            let targets = self.subscribers.snapshot
+                .filter { AppEvent.delivers(tab: tab, to: $0.tab) }
+                .map(\.fd)
             guard !targets.isEmpty else { return }
 
             var dead: [Int32] = []
             for fd in targets {
-                self.subscriberLock.lock()
-                let isStillSubscriber = self.subscribers.contains(fd)
-                self.subscriberLock.unlock()
-                guard isStillSubscriber else { continue }
-
-                let n = line.withUnsafeBufferPointer { write(fd, $0.baseAddress!, $0.count) }
+                guard self.subscribers.withLock({ $0.contains { $0.fd == fd } }) else { continue }
+                let n = line.withUnsafeBufferPointer { p -> Int in
+                    guard let base = p.baseAddress else { return 0 }
+                    return write(fd, base, p.count)
+                }
                 if n != line.count { dead.append(fd) }
             }
             if !dead.isEmpty {
-                self.subscriberLock.lock()
-                for fd in dead {
-                    if let i = self.subscribers.firstIndex(of: fd) {
-                        self.subscribers.remove(at: i)
-                        close(fd)
+                self.subscribers.withLock { subscribers in
+                    for fd in dead {
+                        if let i = subscribers.firstIndex(where: { $0.fd == fd }) {
+                            subscribers.remove(at: i)
+                            close(fd)
+                        }
                     }
                 }
-                self.subscriberLock.unlock()
             }
         }
     }
@@ -257,7 +1012,8 @@ final class AppControlServer {
         if line.last == 0x0D { line.removeLast() }
         let request = String(decoding: line, as: UTF8.self)
 
-        if request == "subscribe" {
+        if request == "subscribe" || request.hasPrefix("subscribe ") {
+            let filter = request.dropFirst("subscribe".count).trimmingCharacters(in: .whitespaces)
             // Long-lived: millisecond send deadline so fanout on the main
             // thread stays bounded and a stalled reader gets pruned; no
             // receive deadline (we only write). Hold until client EOF.
@@ -266,20 +1022,20 @@ final class AppControlServer {
             var noTv = timeval(tv_sec: 0, tv_usec: 0)
             setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &noTv, socklen_t(MemoryLayout<timeval>.size))
             _ = "ok\n".withCString { write(fd, $0, strlen($0)) }
-            subscriberLock.lock()
-            subscribers.append(fd)
-            subscriberLock.unlock()
+            subscribers.withLock {
+                $0.append(Subscriber(fd: fd, tab: filter.isEmpty ? nil : filter))
+            }
             var drain = [UInt8](repeating: 0, count: 256)
             while read(fd, &drain, drain.count) > 0 {}
             // Close only if we still own the fd: broadcast()/stop() prune
             // (remove AND close) subscribers under the same lock, so an
             // unconditional close here could hit a reused descriptor.
-            subscriberLock.lock()
-            if let i = subscribers.firstIndex(of: fd) {
-                subscribers.remove(at: i)
-                close(fd)
+            subscribers.withLock { subscribers in
+                if let i = subscribers.firstIndex(where: { $0.fd == fd }) {
+                    subscribers.remove(at: i)
+                    close(fd)
+                }
             }
-            subscriberLock.unlock()
             return
         }
 
@@ -291,9 +1047,10 @@ final class AppControlServer {
         }
         if out.last != 0x0A { out.append(0x0A) }
         out.withUnsafeBufferPointer { p in
+            guard let base = p.baseAddress else { return }
             var off = 0
             while off < p.count {
-                let n = write(fd, p.baseAddress! + off, p.count - off)
+                let n = write(fd, base + off, p.count - off)
                 if n > 0 { off += n } else if errno == EINTR { continue } else { break }
             }
         }
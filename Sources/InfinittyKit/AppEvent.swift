@@ -0,0 +1,379 @@
+import Foundation
+
+/// Every event pushed to `subscribe` clients. Emit sites build a case with
+/// typed fields; `payload` is the only place those become JSON keys, so an
+/// event's name and field spellings are defined once here instead of at
+/// each `broadcast` call.
+enum AppEvent {
+    case paneOpened(pane: Int)
+    case paneClosed(pane: Int)
+    /// `detach` took a pane out of its window; its shell keeps running.
+    case paneDetached(pane: Int)
+    case paneAttached(pane: Int)
+    /// A tab's split tree changed shape as a whole: `reason` is `zoom`,
+    /// `unzoom` or `preset`. Zooms name the pane, by terminal id or, for a
+    /// smart pane, its browser id or kind.
+    case layoutChanged(reason: String, pane: Int?, surface: String?)
+    case title(pane: Int, title: String)
+    /// OSC 133 prompt/command marker; `kind` is the marker letter (A–D).
+    case marker(pane: Int, kind: UInt8, exit: Int)
+    /// A command started or finished, from the same markers: the command
+    /// line and, once done, its exit code and how long it ran.
+    case command(pane: Int, CommandEvent)
+    case todos(pane: Int, total: Int, done: Int)
+    /// Foreground process changed; empty names mean back at the shell.
+    case process(pane: Int, name: String, displayName: String)
+    /// The pane's working directory changed (`cwd`'s answer).
+    case directory(pane: Int, cwd: String, source: String)
+    case browserOpened(browserId: String)
+    case browserClosed(browserId: String)
+    case browser(browserId: String, BrowserPaneEvent)
+    case browserAnnotationSubmitted(origin: String, documentId: Int, annotationCount: Int)
+    case surfaceClosed(surface: String)
+    /// MCP-UI / json-render message from a `ui` surface, passed through as-is.
+    case ui(surface: String, payload: Any)
+    /// A background task's snapshot (`BackgroundTask.snapshot()`).
+    case taskProgress([String: Any])
+    case subsystemReady(LaunchReadiness.Subsystem, error: String?)
+    /// New lines of a `log-open` session that pass its filter, already in
+    /// wire form (`LogFilter.render`).
+    case logLines(log: String, lines: [[String: Any]])
+    case logEnded(log: String, reason: String)
+    /// Someone joined (`joined`) or left a pane's live share.
+    case shareParticipant(pane: Int, participant: String, name: String, joined: Bool)
+    case shareEnded(pane: Int, reason: String)
+    /// Fixes for a command the shell could not find (`MissingCommand`).
+    case paneSuggestion(pane: Int, command: String, program: String, suggestions: [CommandSuggestion])
+    /// `cloud-set-profile` exported (or, with nil, cleared) a profile.
+    case cloudProfile(pane: Int, provider: CloudProvider, profile: CloudProfile?)
+    /// One ping reply or traceroute hop from a `net-ping`/`net-traceroute`
+    /// task, as it arrives; the task's result has them all.
+    case netSample(task: String, tool: String, sample: [String: Any])
+    /// One asciicast event played back by a `replay` task, at its recorded
+    /// offset `time`.
+    case replayFrame(task: String, time: Double, kind: String, data: String)
+    /// An `event` trigger matched a finished row of output.
+    case trigger(TriggerMatch)
+    /// URLs, paths and git hashes in rows a pane just finished printing
+    /// (`detect-links`), one event per read.
+    case detectedLinks(pane: Int, links: [DetectedLink])
+    /// A watched host went up or down (`HostMonitor`).
+    case hostStatus(HostStatus)
+    /// Dictation progress: the transcript so far, then the final one.
+    case speech(SpeechTranscript)
+    /// Do Not Disturb turned on or off, by hand or by a macOS Focus.
+    case doNotDisturb(DoNotDisturb.Snapshot)
+    /// A pomodoro started with `pomodoro` ran its course.
+    case pomodoro(Pomodoro)
+    /// A workspace's notes or todos changed (`WorkspaceNotes`).
+    case workspaceNotes(workspace: String)
+    /// A pane's output started or stopped flooding; the end carries how
+    /// much it printed and for how long.
+    case outputFlood(pane: Int, OutputFlood)
+    /// A `watch-start` run whose output or exit status moved.
+    case commandWatch(CommandWatchChange)
+    /// A pane's progress moved (`ProgressDetector`); nil once its command
+    /// is done.
+    case progress(pane: Int, PaneProgress?)
+    /// A pane started or stopped asking for a password
+    /// (`SecureInputDetector`); nil once it stopped.
+    case secureInput(pane: Int, SecurePrompt?)
+    /// A pane was made read-only or writable again (`pane-lock`).
+    case inputLock(pane: Int, locked: Bool)
+    /// The idle policy acted on a pane; a closed pane's snapshot path
+    /// comes with it.
+    case paneIdle(pane: Int, action: IdleAction, idleSeconds: TimeInterval, snapshot: String?)
+    /// A hibernated pane started a new shell.
+    case paneResumed(pane: Int)
+    /// A paste with concerns is waiting for a yes, in the pane's sheet or
+    /// from the `paste` caller.
+    case pasteHeld(pane: Int, PasteCheck)
+    /// Enter on a risky command line is held for a yes, with what it would
+    /// touch (`confirm-dangerous-commands`).
+    case commandHeld(pane: Int, CommandAnalysis)
+    /// A repo whose status was read changed on disk (`GitStatusCache`'s
+    /// watcher), with its status re-read.
+    case gitStatus(repo: String, CodeGit.RepoStatus)
+    /// A link was cmd-clicked, with what `LinkPolicy` made of it: opened,
+    /// refused (or declined when asked), or left to the socket (`event`).
+    case linkActivated(pane: Int, url: String, action: LinkAction)
+    /// A git command the app ran is waiting on a credential; answer it with
+    /// `git-credential-answer`.
+    case gitCredentialPrompt(GitCredentialPrompt)
+    /// That prompt was answered, cancelled or timed out.
+    case gitCredentialResolved(id: String, answered: Bool)
+    /// One of a new shell's startup commands (its profile's or its
+    /// workspace's) exited non-zero.
+    case startupFailed(pane: Int, StartupCommand)
+
+    var name: String {
+        switch self {
+        case .paneOpened: return "pane-opened"
+        case .paneClosed: return "pane-closed"
+        case .paneDetached: return "pane-detached"
+        case .paneAttached: return "pane-attached"
+        case .layoutChanged: return "layout-changed"
+        case .title: return "title"
+        case .marker: return "marker"
+        case .command(_, .started): return "command-started"
+        case .command(_, .finished): return "command-finished"
+        case .todos: return "todos"
+        case .process: return "process"
+        case .directory: return "cwd"
+        case .browserOpened: return "browser-opened"
+        case .browserClosed: return "browser-closed"
+        case .browser(_, let event): return event.name
+        case .browserAnnotationSubmitted: return "browser-annotation-submitted"
+        case .surfaceClosed: return "surface-closed"
+        case .ui: return "ui"
+        case .taskProgress: return "task-progress"
+        case .subsystemReady: return "subsystem-ready"
+        case .logLines: return "log-lines"
+        case .logEnded: return "log-ended"
+        case .shareParticipant: return "share-participant"
+        case .shareEnded: return "share-ended"
+        case .paneSuggestion: return "pane-suggestion"
+        case .cloudProfile: return "cloud-profile"
+        case .netSample(_, let tool, _): return "net-\(tool)"
+        case .replayFrame: return "replay-frame"
+        case .trigger: return "trigger"
+        case .detectedLinks: return "detected-links"
+        case .hostStatus: return "host-status"
+        case .speech: return "speech"
+        case .doNotDisturb: return "dnd"
+        case .pomodoro: return "pomodoro"
+        case .workspaceNotes: return "notes"
+        case .outputFlood: return "output-flood"
+        case .commandWatch: return "watch"
+        case .progress: return "progress"
+        case .secureInput: return "secure-input"
+        case .inputLock: return "input-lock"
+        case .paneIdle: return "pane-idle"
+        case .paneResumed: return "pane-resumed"
+        case .pasteHeld: return "paste-held"
+        case .commandHeld: return "command-held"
+        case .gitStatus: return "git-status-changed"
+        case .linkActivated: return "link-activated"
+        case .gitCredentialPrompt: return "git-credential-prompt"
+        case .gitCredentialResolved: return "git-credential-resolved"
+        case .startupFailed: return "startup-failed"
+        }
+    }
+
+    /// The JSON object sent on the wire. Events aimed at one tab carry its
+    /// id as `tab` so unfiltered subscribers can still tell windows apart.
+    func payload(tab: String? = nil) -> [String: Any] {
+        var object: [String: Any]
+        switch self {
+        case .paneOpened(let pane), .paneClosed(let pane), .paneDetached(let pane), .paneAttached(let pane):
+            object = ["pane": pane]
+        case .layoutChanged(let reason, let pane, let surface):
+            object = ["reason": reason]
+            if let pane { object["pane"] = pane }
+            if let surface { object["surface"] = surface }
+        case .title(let pane, let title):
+            object = ["pane": pane, "title": title]
+        case .marker(let pane, let kind, let exit):
+            object = ["pane": pane, "kind": String(UnicodeScalar(kind)), "exit": exit]
+        case .command(let pane, .started(let command, let line)):
+            object = ["pane": pane, "line": line]
+            if let command { object["command"] = command }
+        case .command(let pane, .finished(let command, let exitCode, let duration, let line)):
+            object = ["pane": pane, "line": line, "exitCode": exitCode, "duration": duration]
+            if let command { object["command"] = command }
+        case .todos(let pane, let total, let done):
+            object = ["pane": pane, "total": total, "done": done]
+        case .process(let pane, let name, let displayName):
+            object = ["pane": pane, "name": name, "displayName": displayName]
+        case .directory(let pane, let cwd, let source):
+            object = ["pane": pane, "cwd": cwd, "source": source]
+        case .browserOpened(let browserId), .browserClosed(let browserId):
+            object = ["browserId": browserId]
+        case .browser(let browserId, let event):
+            object = event.fields
+            object["browserId"] = browserId
+        case .browserAnnotationSubmitted(let origin, let documentId, let annotationCount):
+            object = ["origin": origin, "documentId": documentId, "annotationCount": annotationCount]
+        case .surfaceClosed(let surface):
+            object = ["surface": surface]
+        case .ui(let surface, let payload):
+            object = ["surface": surface, "payload": payload]
+        case .taskProgress(let snapshot):
+            object = snapshot
+            object["task"] = object.removeValue(forKey: "id")
+        case .subsystemReady(let subsystem, let error):
+            object = ["subsystem": subsystem.rawValue]
+            if let error { object["error"] = error }
+        case .logLines(let log, let lines):
+            object = ["log": log, "lines": lines]
+        case .logEnded(let log, let reason):
+            object = ["log": log, "reason": reason]
+        case .shareParticipant(let pane, let participant, let name, let joined):
+            object = ["pane": pane, "participant": participant, "name": name, "joined": joined]
+        case .shareEnded(let pane, let reason):
+            object = ["pane": pane, "reason": reason]
+        case .paneSuggestion(let pane, let command, let program, let suggestions):
+            object = [
+                "pane": pane, "command": command, "program": program,
+                "suggestions": suggestions.map(\.wire),
+            ]
+        case .cloudProfile(let pane, let provider, let profile):
+            object = ["pane": pane, "provider": provider.rawValue]
+            if let profile {
+                object["profile"] = profile.name
+                object["production"] = profile.looksLikeProduction
+            }
+        case .netSample(let task, _, let sample):
+            object = sample
+            object["task"] = task
+        case .replayFrame(let task, let time, let kind, let data):
+            object = ["task": task, "time": time, "kind": kind, "data": data]
+        case .trigger(let match):
+            object = match.wire
+        case .detectedLinks(let pane, let links):
+            object = ["pane": pane, "links": links.map(\.wire)]
+        case .hostStatus(let status):
+            object = status.wire
+        case .speech(let transcript):
+            object = transcript.wire
+        case .doNotDisturb(let snapshot):
+            object = snapshot.wire
+        case .pomodoro(let pomodoro):
+            object = pomodoro.wire()
+        case .workspaceNotes(let workspace):
+            object = ["workspace": workspace]
+        case .outputFlood(let pane, .started):
+            object = ["pane": pane, "flooding": true]
+        case .outputFlood(let pane, .ended(let bytes, let duration)):
+            object = ["pane": pane, "flooding": false, "bytes": bytes, "seconds": duration]
+        case .commandWatch(let change):
+            object = change.wire
+        case .progress(let pane, let progress):
+            object = progress?.wire ?? ["state": "none"]
+            object["pane"] = pane
+        case .secureInput(let pane, let prompt):
+            object = prompt?.wire ?? [:]
+            object["pane"] = pane
+            object["active"] = prompt != nil
+        case .inputLock(let pane, let locked):
+            object = ["pane": pane, "locked": locked]
+        case .paneIdle(let pane, let action, let idleSeconds, let snapshot):
+            object = ["pane": pane, "action": action.rawValue, "idleSeconds": idleSeconds.rounded()]
+            if let snapshot { object["snapshot"] = snapshot }
+        case .paneResumed(let pane):
+            object = ["pane": pane]
+        case .pasteHeld(let pane, let check):
+            object = check.wire
+            object["pane"] = pane
+        case .commandHeld(let pane, let analysis):
+            object = analysis.wire
+            object["pane"] = pane
+        case .gitStatus(let repo, let status):
+            object = status.wire
+            object["repo"] = repo
+        case .linkActivated(let pane, let url, let action):
+            object = ["pane": pane, "url": url, "action": action.rawValue]
+        case .gitCredentialPrompt(let prompt):
+            object = prompt.wire
+        case .gitCredentialResolved(let id, let answered):
+            object = ["id": id, "answered": answered]
+        case .startupFailed(let pane, let startup):
+            object = startup.wire
+            object["pane"] = pane
+        }
+        object["event"] = name
+        if let tab { object["tab"] = tab }
+        return object
+    }
+
+    /// Whether an event targeted at `tab` (nil = app-wide) reaches a
+    /// subscriber filtering on `filter` (nil = everything).
+    static func delivers(tab: String?, to filter: String?) -> Bool {
+        guard let filter, let tab else { return true }
+        return tab == filter
+    }
+}
+
+/// Events raised by a browser pane's chrome, inspector and annotation
+/// tools. The app wraps them in `AppEvent.browser`, which adds the pane's
+/// `browserId`.
+enum BrowserPaneEvent: Equatable {
+    case profileImported(source: String, bookmarks: Int)
+    case siteSettingsChanged(agentAccess: String, blockPopups: Bool)
+    case siteDataCleared
+    case inspectorArmed(Bool)
+    case inspectorRearmed
+    case inspectorWaiting(retry: Int)
+    case inspectorReady
+    case inspectorCancelled
+    case annotationAdded(annotationId: String, count: Int)
+    case annotationUpdated(annotationId: String)
+    case annotationDeleted(annotationId: String, count: Int)
+    case annotationMarkersShown(Bool, count: Int)
+    case annotationMarkerSyncWaiting
+    case annotationsCleared(count: Int)
+    case annotationsClearedForNavigation(count: Int)
+    case annotationsSubmitted(count: Int)
+    case loadStarted
+    case loadFailed(error: String)
+    case navigated(documentId: Int)
+    case popupBlocked
+    /// A request finished while `network-capture` was on.
+    case networkRequest(BrowserNetworkEntry)
+
+    var name: String {
+        switch self {
+        case .profileImported: return "browser-profile-imported"
+        case .siteSettingsChanged: return "browser-site-settings-changed"
+        case .siteDataCleared: return "browser-site-data-cleared"
+        case .inspectorArmed(let armed): return armed ? "browser-inspector-armed" : "browser-inspector-disarmed"
+        case .inspectorRearmed: return "browser-inspector-rearmed"
+        case .inspectorWaiting: return "browser-inspector-waiting"
+        case .inspectorReady: return "browser-inspector-ready"
+        case .inspectorCancelled: return "browser-inspector-cancelled"
+        case .annotationAdded: return "browser-annotation-added"
+        case .annotationUpdated: return "browser-annotation-updated"
+        case .annotationDeleted: return "browser-annotation-deleted"
+        case .annotationMarkersShown(let shown, _):
+            return shown ? "browser-annotation-markers-shown" : "browser-annotation-markers-hidden"
+        case .annotationMarkerSyncWaiting: return "browser-annotation-marker-sync-waiting"
+        case .annotationsCleared: return "browser-annotations-cleared"
+        case .annotationsClearedForNavigation: return "browser-annotations-cleared-for-navigation"
+        case .annotationsSubmitted: return "browser-annotations-submitted"
+        case .loadStarted, .loadFailed: return "browser-load-state"
+        case .navigated: return "browser-navigated"
+        case .popupBlocked: return "browser-popup-blocked"
+        case .networkRequest: return "browser-network-request"
+        }
+    }
+
+    /// Event-specific keys, without `event` and `browserId`.
+    var fields: [String: Any] {
+        switch self {
+        case .profileImported(let source, let bookmarks):
+            return ["source": source, "bookmarks": bookmarks]
+        case .siteSettingsChanged(let agentAccess, let blockPopups):
+            return ["agentAccess": agentAccess, "blockPopups": blockPopups]
+        case .inspectorWaiting(let retry):
+            return ["retry": retry]
+        case .annotationAdded(let id, let count), .annotationDeleted(let id, let count):
+            return ["annotationId": id, "count": count]
+        case .annotationUpdated(let id):
+            return ["annotationId": id]
+        case .annotationMarkersShown(_, let count), .annotationsCleared(let count),
+             .annotationsClearedForNavigation(let count), .annotationsSubmitted(let count):
+            return ["count": count]
+        case .loadStarted:
+            return ["loading": true]
+        case .loadFailed(let error):
+            return ["loading": false, "error": error]
+        case .navigated(let documentId):
+            return ["documentId": documentId]
+        case .networkRequest(let entry):
+            return entry.wire
+        case .siteDataCleared, .inspectorArmed, .inspectorRearmed, .inspectorReady,
+             .inspectorCancelled, .annotationMarkerSyncWaiting, .popupBlocked:
+            return [:]
+        }
+    }
+}
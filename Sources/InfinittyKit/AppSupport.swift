@@ -0,0 +1,65 @@
+import Foundation
+
+/// `~/Library/Application Support/infinitty`, where the app keeps what it
+/// remembers between launches: stores, caches and recordings.
+enum AppSupport {
+    static var directory: URL {
+        let support = FileManager.default.urls(for: .applicationSupportDirectory, in: .userDomainMask).first
+            ?? URL(fileURLWithPath: NSHomeDirectory()).appendingPathComponent("Library/Application Support")
+        return support.appendingPathComponent("infinitty", isDirectory: true)
+    }
+
+    /// A file, or with `isDirectory` a folder, inside `directory`.
+    static func url(_ name: String, isDirectory: Bool = false) -> URL {
+        directory.appendingPathComponent(name, isDirectory: isDirectory)
+    }
+
+    /// Replaces the file at `url` in one step, creating its folder first.
+    /// `permissions` (0o600 for anything private) are set once it's
+    /// written. False when it couldn't be.
+    @discardableResult
+    static func write(_ data: Data, to url: URL, permissions: Int? = nil) -> Bool {
+        let fm = FileManager.default
+        try? fm.createDirectory(at: url.deletingLastPathComponent(), withIntermediateDirectories: true)
+        guard (try? data.write(to: url, options: .atomic)) != nil else { return false }
+        if let permissions { try? fm.setAttributes([.posixPermissions: permissions], ofItemAtPath: url.path) }
+        return true
+    }
+}
+
+/// A Codable value kept whole in one JSON file, as the app's small stores
+/// keep theirs: read back in full, rewritten in full on every change.
+struct JSONFileStore<Value: Codable> {
+    enum Dates {
+        case deferred, secondsSince1970, iso8601
+    }
+
+    let url: URL
+    var dates: Dates = .deferred
+    var formatting: JSONEncoder.OutputFormatting = []
+    var permissions: Int?
+
+    /// The saved value; nil when there is none or it no longer decodes.
+    func load() -> Value? {
+        let decoder = JSONDecoder()
+        switch dates {
+        case .deferred: break
+        case .secondsSince1970: decoder.dateDecodingStrategy = .secondsSince1970
+        case .iso8601: decoder.dateDecodingStrategy = .iso8601
+        }
+        return (try? Data(contentsOf: url)).flatMap { try? decoder.decode(Value.self, from: $0) }
+    }
+
+    @discardableResult
+    func save(_ value: Value) -> Bool {
+        let encoder = JSONEncoder()
+        encoder.outputFormatting = formatting
+        switch dates {
+        case .deferred: break
+        case .secondsSince1970: encoder.dateEncodingStrategy = .secondsSince1970
+        case .iso8601: encoder.dateEncodingStrategy = .iso8601
+        }
+        guard let data = try? encoder.encode(value) else { return false }
+        return AppSupport.write(data, to: url, permissions: permissions)
+    }
+}
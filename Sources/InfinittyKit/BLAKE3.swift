@@ -0,0 +1,148 @@
+import Foundation
+
+/// BLAKE3 in its default hashing mode with a 32-byte digest, after the
+/// reference implementation: 1 KiB chunks compressed block by block, their
+/// chaining values merged into a binary tree on a stack. Single-threaded
+/// and unkeyed — enough for checksums, which is all it is used for.
+struct BLAKE3 {
+    private static let iv: [UInt32] = [
+        0x6A09_E667, 0xBB67_AE85, 0x3C6E_F372, 0xA54F_F53A, 0x510E_527F, 0x9B05_688C, 0x1F83_D9AB, 0x5BE0_CD19,
+    ]
+    private static let permutation = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8]
+    private static let chunkStart: UInt32 = 1, chunkEnd: UInt32 = 2, parent: UInt32 = 4, root: UInt32 = 8
+    private static let blockLength = 64, chunkLength = 1024
+
+    /// The inputs of one compression, kept unevaluated so the root can be
+    /// compressed with the ROOT flag.
+    private struct Output {
+        let chainingValue: [UInt32]
+        let block: [UInt32]
+        let counter: UInt64
+        let length: UInt32
+        let flags: UInt32
+
+        var next: [UInt32] {
+            Array(BLAKE3.compress(chainingValue, block, counter: counter, length: length, flags: flags)[0..<8])
+        }
+
+        var digest: [UInt8] {
+            BLAKE3.compress(chainingValue, block, counter: 0, length: length, flags: flags | BLAKE3.root)[0..<8]
+                .flatMap { word in (0..<4).map { UInt8(truncatingIfNeeded: word >> ($0 * 8)) } }
+        }
+    }
+
+    private var chainingValue = BLAKE3.iv
+    private var chunkCounter: UInt64 = 0
+    private var block: [UInt8] = []
+    private var blocksCompressed = 0
+    private var stack: [[UInt32]] = []
+
+    init() {}
+
+    mutating func update<Bytes: Collection>(data: Bytes) where Bytes.Element == UInt8 {
+        var rest = data[...]
+        while !rest.isEmpty {
+            if blocksCompressed * Self.blockLength + block.count == Self.chunkLength {
+                // The chunk is full and more input follows, so it is not the root.
+                push(chunkOutput.next)
+                chainingValue = Self.iv
+                chunkCounter += 1
+                block = []
+                blocksCompressed = 0
+            }
+            if block.count == Self.blockLength {
+                chainingValue = Array(Self.compress(
+                    chainingValue, Self.words(block), counter: chunkCounter, length: UInt32(Self.blockLength),
+                    flags: startFlag)[0..<8])
+                blocksCompressed += 1
+                block = []
+            }
+            let take = min(Self.blockLength - block.count, rest.count)
+            block += rest.prefix(take)
+            rest = rest.dropFirst(take)
+        }
+    }
+
+    func finalize() -> [UInt8] {
+        var output = chunkOutput
+        for left in stack.reversed() {
+            output = Output(
+                chainingValue: Self.iv, block: left + output.next, counter: 0,
+                length: UInt32(Self.blockLength), flags: Self.parent)
+        }
+        return output.digest
+    }
+
+    static func hash<Bytes: Collection>(data: Bytes) -> [UInt8] where Bytes.Element == UInt8 {
+        var hasher = BLAKE3()
+        hasher.update(data: data)
+        return hasher.finalize()
+    }
+
+    private var startFlag: UInt32 { blocksCompressed == 0 ? Self.chunkStart : 0 }
+
+    private var chunkOutput: Output {
+        Output(
+            chainingValue: chainingValue, block: Self.words(block), counter: chunkCounter,
+            length: UInt32(block.count), flags: startFlag | Self.chunkEnd)
+    }
+
+    /// Adds a finished chunk's chaining value, merging completed subtrees:
+    /// one merge per trailing zero bit of the chunk count.
+    private mutating func push(_ value: [UInt32]) {
+        var value = value
+        var total = chunkCounter + 1
+        while total & 1 == 0, let left = stack.popLast() {
+            value = Output(
+                chainingValue: Self.iv, block: left + value, counter: 0,
+                length: UInt32(Self.blockLength), flags: Self.parent).next
+            total >>= 1
+        }
+        stack.append(value)
+    }
+
+    private static func words(_ bytes: [UInt8]) -> [UInt32] {
+        let padded = bytes + [UInt8](repeating: 0, count: blockLength - bytes.count)
+        return stride(from: 0, to: blockLength, by: 4).map { i in
+            (0..<4).reduce(UInt32(0)) { $0 | UInt32(padded[i + $1]) << ($1 * 8) }
+        }
+    }
+
+    private static func compress(
+        _ chainingValue: [UInt32], _ block: [UInt32], counter: UInt64, length: UInt32, flags: UInt32
+    ) -> [UInt32] {
+        var s = chainingValue + iv[0..<4]
+            + [UInt32(truncatingIfNeeded: counter), UInt32(truncatingIfNeeded: counter >> 32), length, flags]
+        var m = block
+        func g(_ a: Int, _ b: Int, _ c: Int, _ d: Int, _ x: UInt32, _ y: UInt32) {
+            s[a] = s[a] &+ s[b] &+ x
+            s[d] = (s[d] ^ s[a]).rotatedRight(16)
+            s[c] = s[c] &+ s[d]
+            s[b] = (s[b] ^ s[c]).rotatedRight(12)
+            s[a] = s[a] &+ s[b] &+ y
+            s[d] = (s[d] ^ s[a]).rotatedRight(8)
+            s[c] = s[c] &+ s[d]
+            s[b] = (s[b] ^ s[c]).rotatedRight(7)
+        }
+        for _ in 0..<7 {
+            g(0, 4, 8, 12, m[0], m[1])
+            g(1, 5, 9, 13, m[2], m[3])
+            g(2, 6, 10, 14, m[4], m[5])
+            g(3, 7, 11, 15, m[6], m[7])
+            g(0, 5, 10, 15, m[8], m[9])
+            g(1, 6, 11, 12, m[10], m[11])
+            g(2, 7, 8, 13, m[12], m[13])
+            g(3, 4, 9, 14, m[14], m[15])
+            m = permutation.map { m[$0] }
+        }
+        for i in 0..<8 {
+            s[i] ^= s[i + 8]
+            s[i + 8] ^= chainingValue[i]
+        }
+        return s
+    }
+}
+
+private extension UInt32 {
+    func rotatedRight(_ n: UInt32) -> UInt32 { self >> n | self << (32 - n) }
+}
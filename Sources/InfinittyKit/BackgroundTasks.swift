@@ -0,0 +1,185 @@
+import Foundation
+
+/// Lifecycle of a tracked job. Everything but `running` is terminal.
+enum BackgroundTaskState: String {
+    case running, succeeded, failed, cancelled
+}
+
+/// One long-running job (recursive copy, search, clone, archive, ...).
+/// The work closure receives its handle to report progress and to poll
+/// `isCancelled`; cancellation is cooperative, so work must check it
+/// between units, or hand `cancellation` to blocking calls such as
+/// `ProcessRunner.run`. All members are safe to touch from any queue.
+final class BackgroundTask {
+    let id: String
+    let kind: String
+    let title: String
+    let startedAt = Date()
+
+    private struct State {
+        var phase = BackgroundTaskState.running
+        var completed: Int64 = 0
+        var total: Int64?
+        var detail: String?
+        var error: String?
+        var result: [String: Any]?
+        var lastProgressEvent: TimeInterval = 0
+    }
+
+    private let state = LockedState(State())
+    /// Also registered with `CancellationRegistry` under `id` while running.
+    let cancellation = CancellationToken()
+    private let finished = DispatchGroup()
+    fileprivate var onChange: ((BackgroundTask) -> Void)?
+
+    /// Progress events are rate-limited to this interval; state changes
+    /// always go out immediately.
+    static let progressInterval: TimeInterval = 0.1
+
+    fileprivate init(id: String, kind: String, title: String) {
+        self.id = id
+        self.kind = kind
+        self.title = title
+        finished.enter()
+    }
+
+    var isCancelled: Bool { cancellation.isCancelled }
+
+    var currentState: BackgroundTaskState { state.withLock { $0.phase } }
+
+    /// Record progress. `total` stays at its previous value when nil, so
+    /// jobs that learn their size late can report it once.
+    func report(completed: Int64, total: Int64? = nil, detail: String? = nil) {
+        let due = state.withLock { state -> Bool in
+            guard state.phase == .running else { return false }
+            state.completed = completed
+            if let total { state.total = total }
+            if let detail { state.detail = detail }
+            let now = ProcessInfo.processInfo.systemUptime
+            guard now - state.lastProgressEvent >= Self.progressInterval else { return false }
+            state.lastProgressEvent = now
+            return true
+        }
+        if due { onChange?(self) }
+    }
+
+    /// Returns false when the task had already finished.
+    fileprivate func requestCancel() -> Bool {
+        guard currentState == .running, !cancellation.isCancelled else { return false }
+        cancellation.cancel()
+        return true
+    }
+
+    fileprivate func finish(result: [String: Any]?, error: String?) {
+        let ended = state.withLock { state -> Bool in
+            guard state.phase == .running else { return false }
+            if cancellation.isCancelled {
+                state.phase = .cancelled
+            } else if error != nil {
+                state.phase = .failed
+            } else {
+                state.phase = .succeeded
+            }
+            state.result = result
+            state.error = error
+            return true
+        }
+        guard ended else { return }
+        finished.leave()
+        onChange?(self)
+    }
+
+    /// Block until the task reaches a terminal state. False on timeout.
+    @discardableResult
+    func wait(timeout: TimeInterval) -> Bool {
+        finished.wait(timeout: .now() + timeout) == .success
+    }
+
+    /// JSON-ready description, also the payload of `task-progress` events.
+    func snapshot() -> [String: Any] {
+        let state = self.state.snapshot
+        var object: [String: Any] = [
+            "id": id, "kind": kind, "title": title, "state": state.phase.rawValue,
+            "completed": state.completed,
+            "elapsed": (Date().timeIntervalSince(startedAt) * 1000).rounded() / 1000,
+        ]
+        if let total = state.total { object["total"] = total }
+        if let detail = state.detail { object["detail"] = detail }
+        if let error = state.error { object["error"] = error }
+        if let result = state.result { object["result"] = result }
+        return object
+    }
+}
+
+/// App-wide registry of tracked jobs, so every long operation shares one
+/// id space, one progress event (`task-progress`) and one cancel command
+/// instead of inventing its own. Finished tasks stay listed for a while so
+/// a client that polls `tasks` late still sees the outcome.
+final class BackgroundTaskRegistry {
+    static let shared = BackgroundTaskRegistry()
+
+    /// Receives every `task-progress` event; the app forwards these to
+    /// control-socket subscribers. Called on the job's queue.
+    var onEvent: ((AppEvent) -> Void)?
+
+    private let tasks = LockedState((list: [BackgroundTask](), nextID: 1))
+    private let queue = DispatchQueue(
+        label: "infinitty.background-tasks", qos: .utility, attributes: .concurrent)
+    static let retainedFinished = 50
+
+    /// Start `work` on a background queue. Its return value becomes the
+    /// task's `result`; a thrown error marks it failed. Returns at once.
+    @discardableResult
+    func start(
+        kind: String, title: String,
+        work: @escaping (BackgroundTask) throws -> [String: Any]?
+    ) -> BackgroundTask {
+        let task = tasks.withLock { tasks -> BackgroundTask in
+            let task = BackgroundTask(id: "task-\(tasks.nextID)", kind: kind, title: title)
+            tasks.nextID += 1
+            tasks.list.append(task)
+            return task
+        }
+
+        task.onChange = { [weak self] task in
+            self?.onEvent?(.taskProgress(task.snapshot()))
+            if task.currentState != .running { self?.prune() }
+        }
+        task.onChange?(task)
+        CancellationRegistry.shared.register(task.id, task.cancellation)
+        queue.async {
+            defer { CancellationRegistry.shared.unregister(task.id) }
+            do {
+                let result = try work(task)
+                task.finish(result: result, error: nil)
+            } catch {
+                task.finish(result: nil, error: String(describing: error))
+            }
+        }
+        return task
+    }
+
+    func task(withID id: String) -> BackgroundTask? {
+        tasks.withLock { $0.list.first { $0.id == id } }
+    }
+
+    /// Ask a running task to stop. False for unknown or finished ids.
+    func cancel(_ id: String) -> Bool {
+        task(withID: id)?.requestCancel() ?? false
+    }
+
+    /// Snapshots of every running task plus recently finished ones, oldest
+    /// first.
+    func list() -> [[String: Any]] {
+        tasks.snapshot.list.map { $0.snapshot() }
+    }
+
+    private func prune() {
+        tasks.withLock { tasks in
+            let finished = tasks.list.filter { $0.currentState != .running }
+            guard finished.count > Self.retainedFinished else { return }
+            let drop = Set(finished.prefix(finished.count - Self.retainedFinished).map(\.id))
+            tasks.list.removeAll { drop.contains($0.id) }
+        }
+    }
+}
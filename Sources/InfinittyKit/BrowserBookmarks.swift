@@ -0,0 +1,216 @@
+import Foundation
+
+enum BrowserBookmarkError: Error, Equatable, CustomStringConvertible {
+    case invalidURL(String)
+    case notFound(String)
+    case folderNotFound(String)
+
+    var description: String {
+        switch self {
+        case .invalidURL(let url): return "can't bookmark '\(url)' (an http or https address)"
+        case .notFound(let id): return "no bookmark \(id)"
+        case .folderNotFound(let folder): return "no bookmark folder \(folder)"
+        }
+    }
+}
+
+/// A page kept for one workspace, opened in a browser pane from the
+/// pane's menu or `bookmarks-open`.
+struct BrowserBookmark: Codable, Equatable {
+    let id: String
+    var url: String
+    var title: String?
+    /// Slash-separated (`Docs/API`); nil at the top level.
+    var folder: String?
+
+    /// What menus show: the title, or else the host and path.
+    var label: String {
+        if let title { return title }
+        guard let components = URLComponents(string: url), let host = components.host else { return url }
+        return host + (components.path == "/" ? "" : components.path)
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "url": url]
+        if let title { object["title"] = title }
+        if let folder { object["folder"] = folder }
+        return object
+    }
+}
+
+/// Browser bookmarks per workspace (a repo root, or the directory when
+/// there is no repo), in their chosen order, kept in Application Support.
+/// Folders exist while a bookmark is in them; ids count up per workspace.
+final class BrowserBookmarks {
+    static let shared = BrowserBookmarks(url: BrowserBookmarks.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("browser-bookmarks.json") }
+
+    private typealias Store = [String: [BrowserBookmark]]
+
+    let url: URL
+    private let file: JSONFileStore<Store>
+    private let entries = LockedState<Store?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, formatting: [.sortedKeys])
+    }
+
+    func bookmarks(for workspace: String) -> [BrowserBookmark] {
+        read { $0[workspace] ?? [] }
+    }
+
+    /// The workspace's folders in first-use order.
+    func folders(for workspace: String) -> [String] {
+        var seen = Set<String>()
+        return bookmarks(for: workspace).compactMap(\.folder).filter { seen.insert($0).inserted }
+    }
+
+    func add(
+        _ url: String, title: String? = nil, folder: String? = nil, to workspace: String
+    ) -> Result<BrowserBookmark, BrowserBookmarkError> {
+        guard let url = Self.normalizedURL(url) else { return .failure(.invalidURL(url)) }
+        return update(workspace) { bookmarks in
+            let next = (bookmarks.compactMap { Int($0.id) }.max() ?? 0) + 1
+            let bookmark = BrowserBookmark(
+                id: String(next), url: url, title: Self.nonEmpty(title), folder: Self.folderPath(folder))
+            bookmarks.append(bookmark)
+            return .success(bookmark)
+        }
+    }
+
+    /// Changes the fields given; an empty title or folder clears it.
+    func edit(
+        _ id: String, in workspace: String, url: String? = nil, title: String? = nil, folder: String? = nil
+    ) -> Result<BrowserBookmark, BrowserBookmarkError> {
+        update(workspace) { bookmarks in
+            guard let index = bookmarks.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            if let url {
+                guard let normalized = Self.normalizedURL(url) else { return .failure(.invalidURL(url)) }
+                bookmarks[index].url = normalized
+            }
+            if let title { bookmarks[index].title = Self.nonEmpty(title) }
+            if let folder { bookmarks[index].folder = Self.folderPath(folder) }
+            return .success(bookmarks[index])
+        }
+    }
+
+    func remove(_ id: String, in workspace: String) -> Result<BrowserBookmark, BrowserBookmarkError> {
+        update(workspace) { bookmarks in
+            guard let index = bookmarks.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            return .success(bookmarks.remove(at: index))
+        }
+    }
+
+    /// Moves a bookmark to `index` in the workspace's order, clamped to the
+    /// list.
+    func move(_ id: String, to index: Int, in workspace: String) -> Result<[BrowserBookmark], BrowserBookmarkError> {
+        update(workspace) { bookmarks in
+            guard let from = bookmarks.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            let bookmark = bookmarks.remove(at: from)
+            bookmarks.insert(bookmark, at: min(max(index, 0), bookmarks.count))
+            return .success(bookmarks)
+        }
+    }
+
+    /// Renames `folder` and the folders under it; an empty `to` moves its
+    /// bookmarks to the top level.
+    func renameFolder(
+        _ folder: String, to: String, in workspace: String
+    ) -> Result<[BrowserBookmark], BrowserBookmarkError> {
+        guard let from = Self.folderPath(folder) else { return .failure(.folderNotFound(folder)) }
+        let to = Self.folderPath(to)
+        return update(workspace) { bookmarks in
+            var found = false
+            for index in bookmarks.indices {
+                guard let current = bookmarks[index].folder, let rest = Self.suffix(of: current, under: from) else {
+                    continue
+                }
+                found = true
+                bookmarks[index].folder = Self.folderPath([to ?? "", rest].joined(separator: "/"))
+            }
+            return found ? .success(bookmarks) : .failure(.folderNotFound(from))
+        }
+    }
+
+    /// Deletes `folder`, the folders under it and their bookmarks; the
+    /// removed bookmarks.
+    func removeFolder(_ folder: String, in workspace: String) -> Result<[BrowserBookmark], BrowserBookmarkError> {
+        guard let path = Self.folderPath(folder) else { return .failure(.folderNotFound(folder)) }
+        return update(workspace) { bookmarks in
+            let removed = bookmarks.filter { $0.folder.flatMap { Self.suffix(of: $0, under: path) } != nil }
+            guard !removed.isEmpty else { return .failure(.folderNotFound(path)) }
+            bookmarks.removeAll { bookmark in removed.contains { $0.id == bookmark.id } }
+            return .success(removed)
+        }
+    }
+
+    /// An http(s) address with a host, `https://` added when no scheme
+    /// was typed; nil for anything else.
+    static func normalizedURL(_ text: String) -> String? {
+        var text = text.trimmingCharacters(in: .whitespacesAndNewlines)
+        if !text.isEmpty, !text.contains("://") { text = "https://" + text }
+        guard let url = URL(string: text), let scheme = url.scheme?.lowercased(),
+              scheme == "http" || scheme == "https", url.host?.isEmpty == false else { return nil }
+        return text
+    }
+
+    /// `folder` with empty and surrounding-space components dropped; nil
+    /// for the top level.
+    static func folderPath(_ folder: String?) -> String? {
+        let parts = (folder ?? "").split(separator: "/")
+            .map { $0.trimmingCharacters(in: .whitespaces) }
+            .filter { !$0.isEmpty }
+        return parts.isEmpty ? nil : parts.joined(separator: "/")
+    }
+
+    /// What follows `folder` in `path` (empty for the folder itself); nil
+    /// when `path` isn't in it.
+    private static func suffix(of path: String, under folder: String) -> String? {
+        if path == folder { return "" }
+        return path.hasPrefix(folder + "/") ? String(path.dropFirst(folder.count + 1)) : nil
+    }
+
+    private static func nonEmpty(_ text: String?) -> String? {
+        let trimmed = text?.trimmingCharacters(in: .whitespacesAndNewlines)
+        return trimmed?.isEmpty == false ? trimmed : nil
+    }
+
+    // MARK: - storage
+
+    private func read<R>(_ body: (Store) -> R) -> R {
+        entries.withLock { entries in
+            load(&entries)
+            return body(entries ?? [:])
+        }
+    }
+
+    /// Applies `body` to the workspace's bookmarks and saves them when it
+    /// succeeds.
+    private func update<R>(
+        _ workspace: String, _ body: (inout [BrowserBookmark]) -> Result<R, BrowserBookmarkError>
+    ) -> Result<R, BrowserBookmarkError> {
+        let (result, snapshot) = entries.withLock { entries -> (Result<R, BrowserBookmarkError>, Store?) in
+            load(&entries)
+            var all = entries ?? [:]
+            var bookmarks = all[workspace] ?? []
+            let result = body(&bookmarks)
+            guard case .success = result else { return (result, nil) }
+            all[workspace] = bookmarks.isEmpty ? nil : bookmarks
+            entries = all
+            return (result, all)
+        }
+        if let snapshot { save(snapshot) }
+        return result
+    }
+
+    private func load(_ entries: inout Store?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+
+    private func save(_ entries: Store) {
+        file.save(entries)
+    }
+}
@@ -0,0 +1,140 @@
+import Foundation
+
+/// One request a browser pane's page made while capture was on.
+struct BrowserNetworkEntry: Equatable {
+    /// Increasing per pane; `network-log`'s `after` pages by it.
+    let id: Int
+    /// `document` for the page itself, else `fetch` or `xhr`.
+    let kind: String
+    let method: String
+    let url: String
+    /// 0 when no response came back (blocked, offline, aborted).
+    let status: Int
+    /// Milliseconds since 1970.
+    let started: Double
+    let duration: Double
+    let error: String?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "id": id, "kind": kind, "method": method, "url": url, "status": status,
+            "started": started, "duration": duration,
+        ]
+        if let error { object["error"] = error }
+        return object
+    }
+}
+
+/// The requests a browser pane has seen, newest last, for `network-log`.
+/// Fetch and XHR calls come from a script in the page's own world (it has
+/// to wrap the page's `fetch`), so a page can add entries of its own: this
+/// is a developer's view of their app's API calls, not an audit trail.
+struct BrowserNetworkLog {
+    static let maxEntries = 500
+    static let maxURLBytes = 2_048
+
+    private(set) var entries: [BrowserNetworkEntry] = []
+    /// Entries pushed out by `maxEntries` since the last `clear`.
+    private(set) var dropped = 0
+    private var nextID = 1
+
+    var lastID: Int { nextID - 1 }
+
+    @discardableResult
+    mutating func record(
+        kind: String, method: String, url: String, status: Int, started: Double, duration: Double,
+        error: String? = nil
+    ) -> BrowserNetworkEntry {
+        var trimmedURL = url
+        if trimmedURL.utf8.count > Self.maxURLBytes {
+            trimmedURL = String(decoding: Array(url.utf8.prefix(Self.maxURLBytes)), as: UTF8.self) + "…"
+        }
+        let entry = BrowserNetworkEntry(
+            id: nextID, kind: kind, method: String(method.uppercased().prefix(16)), url: trimmedURL,
+            status: status, started: started, duration: max(0, duration),
+            error: error.map { String($0.prefix(200)) })
+        nextID += 1
+        entries.append(entry)
+        if entries.count > Self.maxEntries {
+            dropped += entries.count - Self.maxEntries
+            entries.removeFirst(entries.count - Self.maxEntries)
+        }
+        return entry
+    }
+
+    /// Records a message from the page script; nil when it isn't one.
+    @discardableResult
+    mutating func record(message body: Any) -> BrowserNetworkEntry? {
+        guard let body = body as? [String: Any],
+              let kind = body["kind"] as? String, kind == "fetch" || kind == "xhr",
+              let url = body["url"] as? String, !url.isEmpty else { return nil }
+        return record(
+            kind: kind, method: body["method"] as? String ?? "GET", url: url,
+            status: (body["status"] as? NSNumber)?.intValue ?? 0,
+            started: (body["started"] as? NSNumber)?.doubleValue ?? Date().timeIntervalSince1970 * 1_000,
+            duration: (body["duration"] as? NSNumber)?.doubleValue ?? 0,
+            error: body["error"] as? String)
+    }
+
+    /// Entries with ids above `id`, oldest first.
+    func entries(after id: Int) -> [BrowserNetworkEntry] {
+        entries.filter { $0.id > id }
+    }
+
+    /// Drops every entry; ids keep counting so a caller's `after` stays valid.
+    mutating func clear() {
+        entries.removeAll()
+        dropped = 0
+    }
+
+    /// Wraps `fetch` and `XMLHttpRequest` in the page world and reports
+    /// each finished call to the `infinittyNetwork` handler. Runs once per
+    /// document; a handler that's gone (capture off) just swallows the post.
+    static let pageScript = """
+    (() => {
+      if (window.__infinittyNetworkLog) return;
+      window.__infinittyNetworkLog = true;
+      const post = (entry) => {
+        try { window.webkit.messageHandlers.infinittyNetwork.postMessage(entry); } catch (_) {}
+      };
+      const now = () => performance.timeOrigin + performance.now();
+      const absolute = (url) => { try { return new URL(url, location.href).href; } catch (_) { return String(url); } };
+      const originalFetch = window.fetch;
+      if (originalFetch) {
+        window.fetch = function (input, init) {
+          const isRequest = typeof Request !== 'undefined' && input instanceof Request;
+          const url = absolute(isRequest ? input.url : input);
+          const method = String((init && init.method) || (isRequest ? input.method : 'GET'));
+          const started = now();
+          return originalFetch.apply(this, arguments).then((response) => {
+            post({ kind: 'fetch', method, url, status: response.status, started, duration: now() - started });
+            return response;
+          }, (error) => {
+            const message = String((error && error.message) || error);
+            post({ kind: 'fetch', method, url, status: 0, started, duration: now() - started, error: message });
+            throw error;
+          });
+        };
+      }
+      const open = XMLHttpRequest.prototype.open;
+      const send = XMLHttpRequest.prototype.send;
+      XMLHttpRequest.prototype.open = function (method, url) {
+        this.__infinittyRequest = { method: String(method), url: absolute(url) };
+        return open.apply(this, arguments);
+      };
+      XMLHttpRequest.prototype.send = function () {
+        const request = this.__infinittyRequest;
+        if (request) {
+          const started = now();
+          this.addEventListener('loadend', () => {
+            const entry = { kind: 'xhr', method: request.method, url: request.url, status: this.status, started,
+              duration: now() - started };
+            if (this.status === 0) entry.error = 'request failed';
+            post(entry);
+          }, { once: true });
+        }
+        return send.apply(this, arguments);
+      };
+    })();
+    """
+}
@@ -59,6 +59,58 @@ enum BrowserControlCodec {
     }
 }
 
+/// Browsers are scoped to the native tab that hosts them. Every request lists
+/// and drives only the browsers in its owner's tab, so an agent working in
+/// one window cannot steer (or close) another window's pane by id. Generic
+/// over the window so the rules are testable without AppKit.
+enum BrowserControlOwnership {
+    enum Failure: Error, Equatable {
+        case unknownPane
+        case noOwner
+        case notOwned(browserID: String)
+
+        var code: String {
+            switch self {
+            case .unknownPane: return "unknown_pane"
+            case .noOwner: return "no_owner"
+            case .notOwned: return "browser_not_owned"
+            }
+        }
+
+        var message: String {
+            switch self {
+            case .unknownPane: return "anchorPane is not a live terminal pane."
+            case .noOwner: return "Pass anchorPane, or open an infinitty window."
+            case .notOwned(let id): return "Browser \(id) belongs to another tab than the caller's."
+            }
+        }
+    }
+
+    /// anchorPane's window when the request names one. Otherwise the key
+    /// main window, the tab `open` would use — or, while infinitty isn't
+    /// the active app and so has no key window, its frontmost main window.
+    /// Nil only when no main window is open.
+    static func owner<Window: AnyObject>(
+        anchorPane: Int?, windowOfPane: (Int) -> Window?,
+        keyWindow: Window?, orderedWindows: [Window], isMainWindow: (Window) -> Bool
+    ) -> Result<Window?, Failure> {
+        if let anchorPane {
+            guard let window = windowOfPane(anchorPane) else { return .failure(.unknownPane) }
+            return .success(window)
+        }
+        if let keyWindow, isMainWindow(keyWindow) { return .success(keyWindow) }
+        return .success(orderedWindows.first(where: isMainWindow))
+    }
+
+    /// Whether a browser hosted in `browserWindow` may be driven for `owner`.
+    static func check<Window: AnyObject>(
+        _ browserID: String, in browserWindow: Window?, owner: Window?
+    ) -> Result<Void, Failure> {
+        guard let owner else { return .failure(.noOwner) }
+        return browserWindow === owner ? .success(()) : .failure(.notOwned(browserID: browserID))
+    }
+}
+
 /// `WKUserContentController` retains script message handlers. Forward through
 /// a weak wrapper so closing a Browser pane actually releases its web process
 /// and its persistent UI controller.
@@ -452,6 +504,85 @@ private enum BrowserSiteSettingsStore {
     }
 }
 
+/// Last-known location of each browser pane, so workspace restore reopens
+/// a pane where the previous run left it. A record belongs to its live pane
+/// until that pane (or its window) closes; anything still stored at launch
+/// is an orphan from a quit or crash, kept for the saved workspace that
+/// names it and pruned by `reconcile`. A new pane never takes one over.
+struct BrowserPaneSessionStore {
+    struct Record: Equatable {
+        let browserID: String
+        var url: String
+        var title: String
+        var viewport: BrowserViewportMode
+        var updatedAt: TimeInterval
+    }
+
+    static let key = "infinitty.browser.pane-sessions.v1"
+    static let maxRecords = 20
+    static let maxAge: TimeInterval = 30 * 24 * 60 * 60
+
+    let defaults: UserDefaults
+
+    init(defaults: UserDefaults = .standard) {
+        self.defaults = defaults
+    }
+
+    /// Stored records, newest first.
+    func records() -> [Record] {
+        let raw = defaults.array(forKey: Self.key) as? [[String: Any]] ?? []
+        return raw.compactMap { entry -> Record? in
+            guard let id = entry["id"] as? String, let url = entry["url"] as? String,
+                  BrowserPaneController.normalizedURL(url) != nil else { return nil }
+            return Record(
+                browserID: id, url: url, title: entry["title"] as? String ?? "",
+                viewport: BrowserViewportMode(rawValue: entry["viewport"] as? String ?? "") ?? .desktop,
+                updatedAt: entry["updatedAt"] as? TimeInterval ?? 0)
+        }
+        .sorted { $0.updatedAt > $1.updatedAt }
+    }
+
+    func save(_ record: Record) {
+        var all = records().filter { $0.browserID != record.browserID }
+        all.insert(record, at: 0)
+        write(all)
+    }
+
+    func remove(_ browserID: String) {
+        let all = records()
+        guard all.contains(where: { $0.browserID == browserID }) else { return }
+        write(all.filter { $0.browserID != browserID })
+    }
+
+    /// Drop orphans older than `maxAge` and beyond `maxRecords`; records of
+    /// `live` panes are always kept. Returns the surviving orphans.
+    @discardableResult
+    func reconcile(live: Set<String>, now: Date = Date()) -> [Record] {
+        let all = records()
+        var orphans = all.filter {
+            !live.contains($0.browserID) && now.timeIntervalSince1970 - $0.updatedAt < Self.maxAge
+        }
+        orphans = Array(orphans.prefix(max(Self.maxRecords - live.count, 0)))
+        let kept = all.filter { live.contains($0.browserID) } + orphans
+        if kept.count != all.count { write(kept.sorted { $0.updatedAt > $1.updatedAt }) }
+        return orphans
+    }
+
+    /// The stored location of `browserID`, for restoring that pane.
+    func record(_ browserID: String) -> Record? {
+        records().first { $0.browserID == browserID }
+    }
+
+    private func write(_ records: [Record]) {
+        defaults.set(records.prefix(Self.maxRecords).map { record -> [String: Any] in
+            [
+                "id": record.browserID, "url": record.url, "title": record.title,
+                "viewport": record.viewport.rawValue, "updatedAt": record.updatedAt,
+            ]
+        }, forKey: Self.key)
+    }
+}
+
 /// A purpose-built, anchored site-settings surface.  `NSAlert` is fine for a
 /// confirmation, but its accessory layout is not a reliable form container:
 /// it can squeeze segmented controls and leave the save action visually
@@ -679,12 +810,17 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
 
     typealias AutomationCompletion = (String) -> Void
 
-    let browserID = "browser-\(UUID().uuidString.prefix(8).lowercased())"
+    let browserID: String
     var onAnnotation: ((BrowserAnnotation) -> Void)?
     /// Invoked only when the user explicitly presses the annotation toolbar's
     /// Send button. Adding a marker remains a local edit operation.
     var onAnnotationsSubmitted: (([BrowserAnnotation]) -> Void)?
-    var onEvent: (([String: Any]) -> Void)?
+    var onEvent: ((BrowserPaneEvent) -> Void)?
+    /// Committed URL or title changed; the app persists it for restore.
+    var onLocationChange: ((BrowserPaneSessionStore.Record) -> Void)?
+    /// The workspace whose bookmarks the menu shows: the host window's
+    /// terminal's, set by the app. Nil hides them.
+    var bookmarkWorkspace: (() -> String?)?
 
     private let dataStore: WKWebsiteDataStore
     private var viewportMode: BrowserViewportMode = .desktop
@@ -727,12 +863,43 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
     private var rearmInspectorWhenAnnotationEditorCloses = false
     private var annotations: [BrowserAnnotation] = []
     private var markersVisible = true
-
-    init(dataStore: WKWebsiteDataStore = BrowserProfileStore.persistentStore()) {
+    /// Request logging is opt-in per pane (the lock menu, or the
+    /// `network-capture` operation); it hooks the page's own world.
+    private(set) var capturesNetwork = false
+    private var networkLog = BrowserNetworkLog()
+    /// The main-frame load waiting for its response, for the log.
+    private var pendingDocument: (method: String, url: String, started: Date)?
+
+    /// The theme background the pane paints until a page has drawn itself.
+    /// A fresh WKWebView is opaque white before its first paint, which
+    /// flashes in a dark window, so it stays transparent over this until
+    /// its first load finishes.
+    var backdrop: NSColor = .windowBackgroundColor {
+        didSet { applyBackdrop() }
+    }
+
+    init(
+        browserID: String = BrowserPaneController.makeBrowserID(avoiding: []),
+        dataStore: WKWebsiteDataStore = BrowserProfileStore.persistentStore()
+    ) {
+        self.browserID = browserID
         self.dataStore = dataStore
         super.init(nibName: nil, bundle: nil)
     }
 
+    /// A fresh "browser-xxxxxxxx" id not in `taken`. Eight hex digits make a
+    /// clash unlikely, not impossible, and ids outlive panes in the session
+    /// store, so the app passes every id it knows about.
+    static func makeBrowserID(
+        avoiding taken: Set<String>,
+        random: () -> String = { UUID().uuidString }
+    ) -> String {
+        while true {
+            let id = "browser-\(random().replacingOccurrences(of: "-", with: "").prefix(8).lowercased())"
+            if !taken.contains(id) { return id }
+        }
+    }
+
     required init?(coder: NSCoder) { fatalError("init(coder:) is not supported") }
 
     deinit {
@@ -748,12 +915,16 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             webView?.configuration.userContentController.removeScriptMessageHandler(
                 forName: name, contentWorld: inspectorContentWorld)
         }
+        if capturesNetwork {
+            webView?.configuration.userContentController.removeScriptMessageHandler(
+                forName: "infinittyNetwork", contentWorld: .page)
+        }
     }
 
     override func loadView() {
         let root = NSView()
         root.wantsLayer = true
-        root.layer?.backgroundColor = NSColor.windowBackgroundColor.cgColor
+        root.layer?.backgroundColor = backdrop.cgColor
         view = root
 
         // The web view is inserted below this sibling.  Add the toolbar first
@@ -945,11 +1116,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         configuration.websiteDataStore = dataStore
         configuration.defaultWebpagePreferences.preferredContentMode = viewportMode.preferredContentMode
         let controller = WKUserContentController()
-        controller.addUserScript(WKUserScript(
-            source: Self.inspectorScript,
-            injectionTime: .atDocumentEnd,
-            forMainFrameOnly: true,
-            in: inspectorContentWorld))
+        controller.addUserScript(inspectorUserScript)
         controller.add(
             WeakBrowserScriptMessageHandler(self), contentWorld: inspectorContentWorld,
             name: "infinittyInspector")
@@ -962,10 +1129,57 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         controller.add(
             WeakBrowserScriptMessageHandler(self), contentWorld: inspectorContentWorld,
             name: "infinittyAnnotationMarker")
+        if capturesNetwork { installNetworkCapture(in: controller) }
         configuration.userContentController = controller
         return configuration
     }
 
+    private var inspectorUserScript: WKUserScript {
+        WKUserScript(
+            source: Self.inspectorScript,
+            injectionTime: .atDocumentEnd,
+            forMainFrameOnly: true,
+            in: inspectorContentWorld)
+    }
+
+    private func installNetworkCapture(in controller: WKUserContentController) {
+        controller.addUserScript(WKUserScript(
+            source: BrowserNetworkLog.pageScript,
+            injectionTime: .atDocumentStart,
+            forMainFrameOnly: true,
+            in: .page))
+        controller.add(WeakBrowserScriptMessageHandler(self), contentWorld: .page, name: "infinittyNetwork")
+    }
+
+    /// Turns request logging on or off. Switching on also hooks the page
+    /// already loaded, so calls it makes from now on show up without a
+    /// reload; its document load itself was before capture began.
+    func setNetworkCapture(_ enabled: Bool) {
+        guard enabled != capturesNetwork else { return }
+        capturesNetwork = enabled
+        pendingDocument = nil
+        guard let controller = webView?.configuration.userContentController else { return }
+        if enabled {
+            installNetworkCapture(in: controller)
+            webView.evaluateJavaScript(BrowserNetworkLog.pageScript, in: nil, in: .page)
+        } else {
+            controller.removeScriptMessageHandler(forName: "infinittyNetwork", contentWorld: .page)
+            // The inspector's script is the only other one; put it back.
+            controller.removeAllUserScripts()
+            controller.addUserScript(inspectorUserScript)
+        }
+    }
+
+    private func recordDocumentRequest(status: Int, url: URL?, error: String? = nil) {
+        guard capturesNetwork, let pending = pendingDocument else { return }
+        pendingDocument = nil
+        let entry = networkLog.record(
+            kind: "document", method: pending.method, url: url?.absoluteString ?? pending.url, status: status,
+            started: pending.started.timeIntervalSince1970 * 1_000,
+            duration: Date().timeIntervalSince(pending.started) * 1_000, error: error)
+        onEvent?(.networkRequest(entry))
+    }
+
     private func rebuildWebView(reloading url: URL?) {
         cancelPendingNavigations(
             code: "navigation_replaced", message: "The browser view was rebuilt; retry the navigation.")
@@ -978,6 +1192,8 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         next.navigationDelegate = self
         next.uiDelegate = self
         next.allowsBackForwardNavigationGestures = true
+        next.setValue(false, forKey: "drawsBackground")
+        next.underPageBackgroundColor = backdrop
         next.translatesAutoresizingMaskIntoConstraints = false
         view.addSubview(next, positioned: .below, relativeTo: toolbar)
         NSLayoutConstraint.activate([
@@ -993,12 +1209,24 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             self.progress.doubleValue = value
             self.progress.isHidden = value <= 0 || value >= 1
         }
-        titleObservation = next.observe(\.title, options: [.new]) { [weak self] _, _ in self?.refreshChrome() }
-        urlObservation = next.observe(\.url, options: [.new]) { [weak self] _, _ in self?.refreshChrome() }
+        titleObservation = next.observe(\.title, options: [.new]) { [weak self] _, _ in
+            self?.refreshChrome()
+            self?.reportLocation()
+        }
+        urlObservation = next.observe(\.url, options: [.new]) { [weak self] _, _ in
+            self?.refreshChrome()
+            self?.reportLocation()
+        }
         if let url { next.load(URLRequest(url: url)) }
         refreshChrome()
     }
 
+    private func applyBackdrop() {
+        guard isViewLoaded else { return }
+        view.layer?.backgroundColor = backdrop.cgColor
+        webView?.underPageBackgroundColor = backdrop
+    }
+
     @objc private func goBack() { if webView.canGoBack { webView.goBack() } }
     @objc private func goForward() { if webView.canGoForward { webView.goForward() } }
     @objc private func reloadOrStop() {
@@ -1012,6 +1240,24 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         let mode = modes[sender.selectedSegment]
         viewportMode = mode
         webView.reload()
+        reportLocation()
+    }
+
+    /// Resume a persisted pane: viewport first, so the page loads with the
+    /// content mode it was last viewed in.
+    func restore(_ record: BrowserPaneSessionStore.Record) {
+        loadViewIfNeeded()
+        viewportMode = record.viewport
+        viewportControl.selectedSegment = BrowserViewportMode.allCases.firstIndex(of: viewportMode) ?? 0
+        load(address: record.url)
+    }
+
+    private func reportLocation() {
+        guard let url = webView?.url, BrowserPaneController.normalizedURL(url.absoluteString) != nil
+        else { return }
+        onLocationChange?(BrowserPaneSessionStore.Record(
+            browserID: browserID, url: url.absoluteString, title: webView?.title ?? "",
+            viewport: viewportMode, updatedAt: Date().timeIntervalSince1970))
     }
 
     func controlTextDidEndEditing(_ obj: Notification) {
@@ -1157,7 +1403,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         }
         BrowserProfileStore.recordImport(source: source, location: location, bookmarks: bookmarks)
         UserDefaults.standard.set(true, forKey: BrowserProfileStore.onboardingKey)
-        onEvent?(["event": "browser-profile-imported", "source": source, "bookmarks": bookmarks.count])
+        onEvent?(.profileImported(source: source, bookmarks: bookmarks.count))
     }
 
     private func presentBrowserAlert(title: String, information: String) {
@@ -1223,9 +1469,56 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             withTitle: "Show imported bookmarks (\(BrowserProfileStore.importedBookmarks.count))",
             action: #selector(showImportedBookmarks), keyEquivalent: "")
         bookmarks.target = self
+        if let workspace = bookmarkWorkspace?() {
+            menu.addItem(.separator())
+            addBookmarkItems(to: menu, workspace: workspace)
+        }
+        menu.addItem(.separator())
+        let network = menu.addItem(
+            withTitle: "Log network requests", action: #selector(toggleNetworkCapture), keyEquivalent: "")
+        network.target = self
+        network.state = capturesNetwork ? .on : .off
         menu.popUp(positioning: nil, at: NSPoint(x: 0, y: securityButton.bounds.height), in: securityButton)
     }
 
+    @objc private func toggleNetworkCapture() { setNetworkCapture(!capturesNetwork) }
+
+    /// "Bookmark This Page" and the workspace's bookmarks, each folder a
+    /// submenu.
+    private func addBookmarkItems(to menu: NSMenu, workspace: String) {
+        let add = menu.addItem(withTitle: "Bookmark This Page", action: #selector(bookmarkPage(_:)), keyEquivalent: "")
+        add.target = self
+        add.representedObject = workspace
+        add.isEnabled = webView?.url.flatMap { BrowserBookmarks.normalizedURL($0.absoluteString) } != nil
+        for bookmark in BrowserBookmarks.shared.bookmarks(for: workspace) {
+            var parent = menu
+            for name in (bookmark.folder ?? "").split(separator: "/").map(String.init) {
+                if let existing = parent.items.first(where: { $0.submenu != nil && $0.title == name })?.submenu {
+                    parent = existing
+                } else {
+                    let submenu = NSMenu(title: name)
+                    parent.addItem(withTitle: name, action: nil, keyEquivalent: "").submenu = submenu
+                    parent = submenu
+                }
+            }
+            let item = parent.addItem(withTitle: bookmark.label, action: #selector(openBookmark(_:)), keyEquivalent: "")
+            item.target = self
+            item.representedObject = bookmark.url
+            item.toolTip = bookmark.url
+        }
+    }
+
+    @objc private func bookmarkPage(_ sender: NSMenuItem) {
+        guard let workspace = sender.representedObject as? String,
+              let url = webView?.url?.absoluteString else { return }
+        _ = BrowserBookmarks.shared.add(url, title: webView?.title, to: workspace)
+    }
+
+    @objc private func openBookmark(_ sender: NSMenuItem) {
+        guard let url = sender.representedObject as? String else { return }
+        load(address: url)
+    }
+
     @objc private func importInstalledBrowserBookmarks() { presentChromeProfilePicker() }
     @objc private func importSafariBookmarksAction() { importSafariBookmarks() }
 
@@ -1266,12 +1559,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             guard let self, let popover else { return }
             BrowserSiteSettingsStore.setAgentAccess(access, for: siteOrigin)
             BrowserSiteSettingsStore.setBlocksPopups(blockPopups, for: siteOrigin)
-            self.onEvent?([
-                "event": "browser-site-settings-changed",
-                "browserId": self.browserID,
-                "agentAccess": access.rawValue,
-                "blockPopups": blockPopups,
-            ])
+            self.onEvent?(.siteSettingsChanged(agentAccess: access.rawValue, blockPopups: blockPopups))
             if clearData { self.clearSiteData(forHost: host) }
             popover.performClose(nil)
             if self.siteSettingsPopover === popover { self.siteSettingsPopover = nil }
@@ -1295,10 +1583,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             }
             self?.dataStore.removeData(ofTypes: types, for: matches) { [weak self] in
                 guard let self else { return }
-                self.onEvent?([
-                    "event": "browser-site-data-cleared",
-                    "browserId": self.browserID,
-                ])
+                self.onEvent?(.siteDataCleared)
             }
         }
     }
@@ -1322,10 +1607,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         inspectButton.toolTip = inspectorEnabled
             ? (inspectorScriptReady ? "Inspector armed — click a page element" : "Inspector is waiting for the page")
             : "Select page element"
-        onEvent?([
-            "event": inspectorEnabled ? "browser-inspector-armed" : "browser-inspector-disarmed",
-            "browserId": browserID,
-        ])
+        onEvent?(.inspectorArmed(inspectorEnabled))
         updateInspectorScriptState()
     }
 
@@ -1370,11 +1652,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
                 self.inspectorScriptReady = false
                 self.inspectButton.contentTintColor = .systemOrange
                 self.inspectButton.toolTip = "Inspector is waiting for the page"
-                self.onEvent?([
-                    "event": "browser-inspector-waiting",
-                    "browserId": self.browserID,
-                    "retry": retryCount + 1,
-                ])
+                self.onEvent?(.inspectorWaiting(retry: retryCount + 1))
                 self.scheduleInspectorRetry(nonce: nonce, retryCount: retryCount + 1)
             }
     }
@@ -1397,9 +1675,14 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
     func userContentController(_ userContentController: WKUserContentController,
                                didReceive message: WKScriptMessage) {
         guard message.frameInfo.isMainFrame else { return }
+        if message.name == "infinittyNetwork" {
+            guard capturesNetwork, let entry = networkLog.record(message: message.body) else { return }
+            onEvent?(.networkRequest(entry))
+            return
+        }
         if message.name == "infinittyInspectorReady" {
             inspectorScriptReady = true
-            onEvent?(["event": "browser-inspector-ready", "browserId": browserID])
+            onEvent?(.inspectorReady)
             syncAnnotationMarkers()
             if inspectorEnabled {
                 inspectButton.contentTintColor = .systemBlue
@@ -1413,7 +1696,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             inspectorNonce = nil
             inspectButton.contentTintColor = .secondaryLabelColor
             inspectButton.toolTip = "Select page element"
-            onEvent?(["event": "browser-inspector-cancelled", "browserId": browserID])
+            onEvent?(.inspectorCancelled)
             return
         }
         if message.name == "infinittyAnnotationMarker" {
@@ -1527,12 +1810,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         annotations.append(annotation)
         syncAnnotationMarkers()
         updateAnnotationToolbar()
-        onEvent?([
-            "event": "browser-annotation-added",
-            "browserId": browserID,
-            "annotationId": annotation.id,
-            "count": annotations.count,
-        ])
+        onEvent?(.annotationAdded(annotationId: annotation.id, count: annotations.count))
 
         // Do not delay the numbered marker on screenshot encoding. Update the
         // local record only if it still belongs to this document.
@@ -1580,11 +1858,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         annotations[index].comment = trimmed
         syncAnnotationMarkers()
         updateAnnotationToolbar()
-        onEvent?([
-            "event": "browser-annotation-updated",
-            "browserId": browserID,
-            "annotationId": id,
-        ])
+        onEvent?(.annotationUpdated(annotationId: id))
     }
 
     private func deleteAnnotation(id: String) {
@@ -1592,12 +1866,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         annotations.remove(at: index)
         syncAnnotationMarkers()
         updateAnnotationToolbar()
-        onEvent?([
-            "event": "browser-annotation-deleted",
-            "browserId": browserID,
-            "annotationId": id,
-            "count": annotations.count,
-        ])
+        onEvent?(.annotationDeleted(annotationId: id, count: annotations.count))
     }
 
     @objc private func toggleAnnotationMarkers() {
@@ -1605,11 +1874,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         markersVisible.toggle()
         syncAnnotationMarkers()
         updateAnnotationToolbar()
-        onEvent?([
-            "event": markersVisible ? "browser-annotation-markers-shown" : "browser-annotation-markers-hidden",
-            "browserId": browserID,
-            "count": annotations.count,
-        ])
+        onEvent?(.annotationMarkersShown(markersVisible, count: annotations.count))
     }
 
     @objc private func clearAnnotations() {
@@ -1619,11 +1884,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         markersVisible = true
         syncAnnotationMarkers()
         updateAnnotationToolbar()
-        onEvent?([
-            "event": "browser-annotations-cleared",
-            "browserId": browserID,
-            "count": count,
-        ])
+        onEvent?(.annotationsCleared(count: count))
     }
 
     @objc private func sendAnnotationsToAI() {
@@ -1640,11 +1901,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         DispatchQueue.main.asyncAfter(deadline: .now() + 0.9) { [weak self] in
             self?.annotationSendButton.contentTintColor = .labelColor
         }
-        onEvent?([
-            "event": "browser-annotations-submitted",
-            "browserId": browserID,
-            "count": batch.count,
-        ])
+        onEvent?(.annotationsSubmitted(count: batch.count))
     }
 
     private func syncAnnotationMarkers() {
@@ -1665,10 +1922,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
                     applied = false
                 }
                 guard !applied, !self.annotations.isEmpty else { return }
-                self.onEvent?([
-                    "event": "browser-annotation-marker-sync-waiting",
-                    "browserId": self.browserID,
-                ])
+                self.onEvent?(.annotationMarkerSyncWaiting)
             }
     }
 
@@ -1692,10 +1946,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             ? "Inspector armed — click another page element"
             : "Inspector is waiting for the page"
         updateInspectorScriptState()
-        onEvent?([
-            "event": "browser-inspector-rearmed",
-            "browserId": browserID,
-        ])
+        onEvent?(.inspectorRearmed)
     }
 
     // MARK: DOM-first automation
@@ -1770,6 +2021,22 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             press(key: key, ref: ref, completion: completion)
         case "scroll":
             scroll(x: request["deltaX"] as? Double ?? 0, y: request["deltaY"] as? Double ?? 500, completion: completion)
+        case "network-capture":
+            guard let enabled = request["enabled"] as? Bool else {
+                completion(response(error: "missing_argument", message: "enabled is required.")); return
+            }
+            setNetworkCapture(enabled)
+            completion(response(result: controlState()))
+        case "network-log":
+            // `after` is the last id a caller has seen; `next` is the one to pass back.
+            let after = request["after"] as? Int ?? 0
+            let result: [String: Any] = [
+                "browserId": browserID, "capturing": capturesNetwork,
+                "entries": networkLog.entries(after: after).map(\.wire),
+                "next": networkLog.lastID, "dropped": networkLog.dropped,
+            ]
+            if request["clear"] as? Bool == true { networkLog.clear() }
+            completion(response(result: result))
         case "screenshot":
             takeScreenshot { [weak self] path in
                 guard let self else { return }
@@ -1962,6 +2229,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             "documentId": documentID,
             "loading": webView?.isLoading ?? false,
             "viewport": viewportMode.rawValue,
+            "networkCapture": capturesNetwork,
         ]
     }
 
@@ -2060,7 +2328,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         inspectorRetryWorkItem = nil
         inspectorScriptReady = false
         refreshChrome()
-        onEvent?(["event": "browser-load-state", "browserId": browserID, "loading": true])
+        onEvent?(.loadStarted)
     }
 
     func webView(
@@ -2070,9 +2338,28 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         decisionHandler: @escaping (WKNavigationActionPolicy, WKWebpagePreferences) -> Void
     ) {
         preferences.preferredContentMode = viewportMode.preferredContentMode
+        if capturesNetwork, navigationAction.targetFrame?.isMainFrame == true {
+            pendingDocument = (
+                navigationAction.request.httpMethod ?? "GET", navigationAction.request.url?.absoluteString ?? "",
+                Date())
+        }
         decisionHandler(.allow, preferences)
     }
 
+    func webView(
+        _ webView: WKWebView,
+        decidePolicyFor navigationResponse: WKNavigationResponse,
+        decisionHandler: @escaping (WKNavigationResponsePolicy) -> Void
+    ) {
+        if navigationResponse.isForMainFrame {
+            recordDocumentRequest(
+                status: (navigationResponse.response as? HTTPURLResponse)?.statusCode ?? 0,
+                url: navigationResponse.response.url)
+        }
+        // What WebKit does without this method: show what it can, drop the rest.
+        decisionHandler(navigationResponse.canShowMIMEType ? .allow : .cancel)
+    }
+
     func webView(_ webView: WKWebView, didCommit navigation: WKNavigation!) {
         documentID += 1
         snapshotSerial = 0
@@ -2085,11 +2372,7 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
             annotations.removeAll()
             markersVisible = true
             updateAnnotationToolbar()
-            onEvent?([
-                "event": "browser-annotations-cleared-for-navigation",
-                "browserId": browserID,
-                "count": count,
-            ])
+            onEvent?(.annotationsClearedForNavigation(count: count))
         }
         rearmInspectorWhenAnnotationEditorCloses = false
         annotationEditorPopover?.performClose(nil)
@@ -2104,9 +2387,13 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
     }
 
     func webView(_ webView: WKWebView, didFinish navigation: WKNavigation!) {
+        // Pages that set no background of their own expect white.
+        if webView.value(forKey: "drawsBackground") as? Bool == false {
+            webView.setValue(true, forKey: "drawsBackground")
+        }
         refreshChrome()
         completeNavigation(navigation, response: response(result: controlState()))
-        onEvent?(["event": "browser-navigated", "browserId": browserID, "documentId": documentID])
+        onEvent?(.navigated(documentId: documentID))
     }
 
     func webView(_ webView: WKWebView, didFail navigation: WKNavigation!, withError error: Error) {
@@ -2124,14 +2411,15 @@ final class BrowserPaneController: NSViewController, WKNavigationDelegate, WKUID
         } else {
             cancelPendingNavigations(code: "navigation_failed", message: error.localizedDescription)
         }
-        onEvent?(["event": "browser-load-state", "browserId": browserID, "loading": false, "error": error.localizedDescription])
+        recordDocumentRequest(status: 0, url: nil, error: error.localizedDescription)
+        onEvent?(.loadFailed(error: error.localizedDescription))
     }
 
     func webView(_ webView: WKWebView, createWebViewWith configuration: WKWebViewConfiguration,
                  for navigationAction: WKNavigationAction, windowFeatures: WKWindowFeatures) -> WKWebView? {
         if navigationAction.targetFrame == nil, let url = navigationAction.request.url {
             if BrowserSiteSettingsStore.blocksPopups(for: origin) {
-                onEvent?(["event": "browser-popup-blocked", "browserId": browserID])
+                onEvent?(.popupBlocked)
                 return nil
             }
             // This browser intentionally owns popup navigation rather than
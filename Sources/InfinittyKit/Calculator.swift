@@ -0,0 +1,669 @@
+import Foundation
+
+enum CalculatorError: Error, Equatable, CustomStringConvertible {
+    case syntax(String)
+    case unknownUnit(String)
+    case incompatible(String, String)
+    case cannotConvert(String, String)
+    case noRate(String)
+    case domain(String)
+
+    var description: String {
+        switch self {
+        case .syntax(let reason): return reason
+        case .unknownUnit(let name): return "unknown unit \(name)"
+        case .incompatible(let lhs, let rhs): return "can't combine \(lhs) with \(rhs)"
+        case .cannotConvert(let from, let to): return "can't convert \(from) to \(to)"
+        case .noRate(let code): return "no exchange rate cached for \(code) yet"
+        case .domain(let reason): return reason
+        }
+    }
+}
+
+/// A unit as a scale, plus an offset for temperatures, onto the base unit
+/// of its kind (bytes, metres, kelvin, euros, …).
+struct MeasureUnit: Equatable {
+    enum Kind: String {
+        case data, dataRate = "data-rate", length, area, volume, mass, time, speed, temperature, currency
+    }
+
+    let symbol: String
+    let kind: Kind
+    let scale: Double
+    var offset: Double = 0
+
+    func toBase(_ value: Double) -> Double { value * scale + offset }
+    func fromBase(_ value: Double) -> Double { (value - offset) / scale }
+}
+
+struct CalculatorResult: Equatable {
+    let value: Double
+    let unit: MeasureUnit?
+    /// The answer as the palette shows it: `1.610612736 GB`, `0x1f`.
+    let text: String
+    /// Whether an exchange rate went into the answer.
+    let usesCurrency: Bool
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["value": value, "text": text]
+        if let unit {
+            object["unit"] = unit.symbol
+            object["kind"] = unit.kind.rawValue
+        }
+        return object
+    }
+}
+
+/// Spotlight-style answers for the palette and `eval`: arithmetic with
+/// `+ - * / ^ mod`, percentages (`15% of 80`), functions and constants,
+/// hex/binary/octal literals, and quantities converted with `in`/`to`/`as`
+/// — `1536 MiB in GB`, `100 km/h to mph`, `20 usd in eur`. Units combine
+/// only where the result stays a unit of the same kind; currencies use the
+/// cached `CurrencyRates` and never wait on the network.
+enum Calculator {
+    /// Evaluates against the cached exchange rates, starting a background
+    /// refresh when a currency was involved and they are missing or stale.
+    static func evaluate(_ text: String) -> Result<CalculatorResult, CalculatorError> {
+        let rates = CurrencyRates.shared
+        let result = evaluate(text, rates: rates.table?.rates ?? [:])
+        switch result {
+        case .success(let answer) where answer.usesCurrency: rates.refreshIfStale()
+        case .failure(.noRate): rates.refreshIfStale()
+        default: break
+        }
+        return result
+    }
+
+    /// `rates` are units of each currency per one of a common base.
+    static func evaluate(_ text: String, rates: [String: Double]) -> Result<CalculatorResult, CalculatorError> {
+        do {
+            var parser = Parser(tokens: try tokenize(text), rates: rates)
+            guard !parser.tokens.isEmpty else { throw CalculatorError.syntax("empty expression") }
+            return .success(try parser.parse())
+        } catch let error as CalculatorError {
+            return .failure(error)
+        } catch {
+            return .failure(.syntax("\(error)"))
+        }
+    }
+
+    /// Plain integers print without a fraction; everything else gets ten
+    /// significant digits, which hides binary rounding noise.
+    static func format(_ value: Double) -> String {
+        if value == value.rounded(), abs(value) < 1e15 { return String(Int64(value)) }
+        return String(format: "%.10g", value)
+    }
+
+    // MARK: - Units
+
+    private static let conversionWords: Set<String> = ["in", "to", "as", "into"]
+    /// Words that end a quantity rather than name its unit.
+    private static let keywords: Set<String> = conversionWords.union(["of", "mod"])
+
+    private static let constants: [String: Double] = ["pi": .pi, "π": .pi, "e": M_E, "tau": 2 * .pi]
+
+    private static let functions: [String: (Double) -> Double] = [
+        "sqrt": { $0.squareRoot() }, "cbrt": cbrt, "abs": { Swift.abs($0) },
+        "round": { $0.rounded() }, "floor": { $0.rounded(.down) }, "ceil": { $0.rounded(.up) },
+        "exp": exp, "ln": log, "log": log10, "log2": log2,
+        "sin": sin, "cos": cos, "tan": tan, "asin": asin, "acos": acos, "atan": atan,
+    ]
+    /// Functions that make sense on a quantity and keep its unit.
+    private static let unitPreserving: Set<String> = ["abs", "round", "floor", "ceil"]
+
+    /// Names and scales per kind; the first name is the display symbol.
+    private static let catalog: [(MeasureUnit.Kind, [([String], Double)])] = [
+        (.data, [
+            (["B", "byte"], 1), (["bit", "b"], 0.125),
+            (["kB", "KB", "kilobyte"], 1e3), (["MB", "megabyte"], 1e6), (["GB", "gigabyte"], 1e9),
+            (["TB", "terabyte"], 1e12), (["PB", "petabyte"], 1e15),
+            (["KiB", "kibibyte"], 1024), (["MiB", "mebibyte"], 1_048_576), (["GiB", "gibibyte"], 1_073_741_824),
+            (["TiB", "tebibyte"], 1_099_511_627_776), (["PiB", "pebibyte"], 1_125_899_906_842_624),
+            (["kbit", "Kb", "kilobit"], 125), (["Mbit", "Mb", "megabit"], 125_000),
+            (["Gbit", "Gb", "gigabit"], 1.25e8), (["Tbit", "Tb", "terabit"], 1.25e11),
+        ]),
+        (.dataRate, [(["bps"], 0.125), (["kbps"], 125), (["Mbps"], 125_000), (["Gbps"], 1.25e8)]),
+        (.length, [
+            (["m", "meter", "metre"], 1), (["mm", "millimeter", "millimetre"], 0.001),
+            (["cm", "centimeter", "centimetre"], 0.01), (["km", "kilometer", "kilometre"], 1000),
+            (["µm", "um", "micrometer", "micron"], 1e-6), (["nm", "nanometer"], 1e-9),
+            (["in", "inch", "inches"], 0.0254), (["ft", "foot", "feet"], 0.3048), (["yd", "yard"], 0.9144),
+            (["mi", "mile"], 1609.344), (["nmi"], 1852),
+        ]),
+        (.area, [
+            (["m²", "m2", "sqm"], 1), (["cm²", "cm2"], 1e-4), (["km²", "km2"], 1e6),
+            (["ft²", "ft2", "sqft"], 0.09290304), (["in²", "in2", "sqin"], 0.00064516),
+            (["ha", "hectare"], 1e4), (["acre"], 4046.8564224),
+        ]),
+        (.volume, [
+            (["L", "l", "liter", "litre"], 1), (["mL", "ml", "milliliter", "millilitre"], 0.001),
+            (["cL", "cl"], 0.01), (["dL", "dl"], 0.1), (["m³", "m3"], 1000),
+            (["gal", "gallon"], 3.785411784), (["qt", "quart"], 0.946352946), (["pt", "pint"], 0.473176473),
+            (["cup"], 0.2365882365), (["floz"], 0.0295735295625),
+            (["tbsp", "tablespoon"], 0.01478676478125), (["tsp", "teaspoon"], 0.00492892159375),
+        ]),
+        (.mass, [
+            (["kg", "kilogram", "kilo"], 1), (["g", "gram", "gramme"], 0.001), (["mg", "milligram"], 1e-6),
+            (["µg", "ug", "microgram"], 1e-9), (["t", "tonne"], 1000), (["oz", "ounce"], 0.028349523125),
+            (["lb", "lbs", "pound"], 0.45359237), (["st", "stone"], 6.35029318),
+        ]),
+        (.time, [
+            (["s", "sec", "second"], 1), (["ms", "millisecond"], 1e-3), (["µs", "us", "microsecond"], 1e-6),
+            (["ns", "nanosecond"], 1e-9), (["min", "minute"], 60), (["h", "hr", "hour"], 3600),
+            (["d", "day"], 86400), (["wk", "week"], 604_800), (["month"], 2_629_800), (["yr", "year"], 31_557_600),
+        ]),
+        (.speed, [
+            (["m/s", "mps"], 1), (["km/h", "kph", "kmh"], 1 / 3.6), (["mph"], 0.44704),
+            (["kn", "knot", "kt"], 1852.0 / 3600), (["ft/s", "fps"], 0.3048),
+        ]),
+    ]
+
+    private static let units: [MeasureUnit] = {
+        var units: [MeasureUnit] = catalog.flatMap { kind, entries in
+            entries.map { MeasureUnit(symbol: $0.0[0], kind: kind, scale: $0.1) }
+        }
+        // Every data size also reads as a rate per second: `MB/s`, `Mbit/s`.
+        units += units.filter { $0.kind == .data }.map {
+            MeasureUnit(symbol: "\($0.symbol)/s", kind: .dataRate, scale: $0.scale)
+        }
+        units += [
+            MeasureUnit(symbol: "K", kind: .temperature, scale: 1),
+            MeasureUnit(symbol: "°C", kind: .temperature, scale: 1, offset: 273.15),
+            MeasureUnit(symbol: "°F", kind: .temperature, scale: 5.0 / 9, offset: 273.15 - 32 * 5.0 / 9),
+        ]
+        return units
+    }()
+
+    /// Exact names first; lower-cased lookups go to the first unit that
+    /// claims the spelling, which is why bytes are listed before bits.
+    private static let names: (exact: [String: MeasureUnit], folded: [String: MeasureUnit]) = {
+        var aliases: [String: [String]] = [
+            "K": ["kelvin"], "°C": ["C", "celsius", "degC"], "°F": ["F", "fahrenheit", "degF"],
+        ]
+        for (kind, entries) in catalog {
+            for (spellings, _) in entries {
+                aliases[spellings[0], default: []] += spellings.dropFirst()
+                guard kind == .data else { continue }
+                aliases["\(spellings[0])/s", default: []] += spellings.dropFirst().map { "\($0)/s" }
+            }
+        }
+        var exact: [String: MeasureUnit] = [:]
+        var folded: [String: MeasureUnit] = [:]
+        for unit in units {
+            for name in [unit.symbol] + aliases[unit.symbol, default: []] {
+                exact[name] = exact[name] ?? unit
+                folded[name.lowercased()] = folded[name.lowercased()] ?? unit
+            }
+        }
+        return (exact, folded)
+    }()
+
+    /// Looks a unit up by symbol or name, case-insensitively when the exact
+    /// spelling is unknown and with a plural `s` dropped.
+    static func unit(named name: String) -> MeasureUnit? {
+        if let unit = names.exact[name] { return unit }
+        let lower = name.lowercased()
+        if let unit = names.folded[lower] { return unit }
+        guard lower.count > 2, lower.hasSuffix("s") else { return nil }
+        return names.folded[String(lower.dropLast())]
+    }
+
+    // MARK: - Arithmetic on quantities
+
+    private struct Quantity {
+        var value: Double
+        var unit: MeasureUnit?
+
+        var name: String { unit?.symbol ?? "a plain number" }
+
+        func converted(to target: MeasureUnit) throws -> Quantity {
+            guard let unit, unit.kind == target.kind else {
+                throw CalculatorError.cannotConvert(name, target.symbol)
+            }
+            return Quantity(value: target.fromBase(unit.toBase(value)), unit: target)
+        }
+    }
+
+    /// Like quantities add in the left-hand unit: `1 GB + 512 MB` is GB.
+    private static func add(_ lhs: Quantity, _ rhs: Quantity) throws -> Quantity {
+        switch (lhs.unit, rhs.unit) {
+        case (nil, nil):
+            return Quantity(value: lhs.value + rhs.value)
+        case (let unit?, _?) where unit.kind == rhs.unit?.kind:
+            return Quantity(value: lhs.value + (try rhs.converted(to: unit)).value, unit: unit)
+        default:
+            throw CalculatorError.incompatible(lhs.name, rhs.name)
+        }
+    }
+
+    private static func multiply(_ lhs: Quantity, _ rhs: Quantity) throws -> Quantity {
+        guard lhs.unit == nil || rhs.unit == nil else { throw CalculatorError.incompatible(lhs.name, rhs.name) }
+        return Quantity(value: lhs.value * rhs.value, unit: lhs.unit ?? rhs.unit)
+    }
+
+    /// A quantity over a number keeps its unit; over a like quantity it is
+    /// a plain ratio (`1 GiB / 1 GB`).
+    private static func divide(_ lhs: Quantity, _ rhs: Quantity) throws -> Quantity {
+        guard rhs.value != 0 else { throw CalculatorError.domain("division by zero") }
+        switch (lhs.unit, rhs.unit) {
+        case (_, nil):
+            return Quantity(value: lhs.value / rhs.value, unit: lhs.unit)
+        case (let unit?, let other?) where unit.kind == other.kind:
+            return Quantity(value: unit.toBase(lhs.value) / other.toBase(rhs.value))
+        default:
+            throw CalculatorError.incompatible(lhs.name, rhs.name)
+        }
+    }
+
+    // MARK: - Parsing
+
+    private enum Token: Equatable {
+        case number(Double)
+        case word(String)
+        /// `$`, `€`, `£` or `¥` in front of an amount.
+        case currency(String)
+        case symbol(Character)
+
+        var text: String {
+            switch self {
+            case .number(let value): return Calculator.format(value)
+            case .word(let word): return word
+            case .currency(let code): return code
+            case .symbol(let symbol): return String(symbol)
+            }
+        }
+    }
+
+    private static let currencySymbols: [Character: String] = ["$": "USD", "€": "EUR", "£": "GBP", "¥": "JPY"]
+    private static let operators: [Character: Character] = ["×": "*", "÷": "/", "−": "-"]
+
+    private static func tokenize(_ text: String) throws -> [Token] {
+        let chars = Array(text)
+        var tokens: [Token] = []
+        var i = 0
+        func isDigit(_ index: Int) -> Bool { index < chars.count && chars[index].isASCII && chars[index].isNumber }
+        while i < chars.count {
+            let c = chars[i]
+            if c.isWhitespace {
+                i += 1
+            } else if isDigit(i) || (c == "." && isDigit(i + 1)) {
+                let (value, end) = try number(chars, from: i)
+                tokens.append(.number(value))
+                i = end
+            } else if c.isLetter || c == "°" {
+                var end = i + 1
+                while end < chars.count, chars[end].isLetter || chars[end].isNumber { end += 1 }
+                tokens.append(.word(String(chars[i..<end])))
+                i = end
+            } else if let code = currencySymbols[c] {
+                tokens.append(.currency(code))
+                i += 1
+            } else if c == "*", i + 1 < chars.count, chars[i + 1] == "*" {
+                tokens.append(.symbol("^"))
+                i += 2
+            } else if "+-*/^()%".contains(c) || operators[c] != nil {
+                tokens.append(.symbol(operators[c] ?? c))
+                i += 1
+            } else {
+                throw CalculatorError.syntax("unexpected \(c)")
+            }
+        }
+        return tokens
+    }
+
+    /// Decimal with `_` or thousands-comma grouping and an optional
+    /// exponent, or an integer with a `0x`, `0b` or `0o` prefix.
+    private static func number(_ chars: [Character], from start: Int) throws -> (Double, Int) {
+        func isDigit(_ index: Int, radix: Int = 10) -> Bool {
+            index < chars.count && chars[index].isASCII && chars[index].hexDigitValue.map { $0 < radix } == true
+        }
+        func char(_ index: Int) -> Character? { index < chars.count ? chars[index] : nil }
+        let radixes: [Character: Int] = ["x": 16, "b": 2, "o": 8]
+        if chars[start] == "0", start + 1 < chars.count, let radix = radixes[chars[start + 1]],
+           isDigit(start + 2, radix: radix) {
+            var end = start + 2
+            while isDigit(end, radix: radix) || (char(end) == "_" && isDigit(end + 1, radix: radix)) {
+                end += 1
+            }
+            let digits = String(chars[(start + 2)..<end]).replacingOccurrences(of: "_", with: "")
+            guard let value = UInt64(digits, radix: radix) else { throw CalculatorError.domain("number too large") }
+            return (Double(value), end)
+        }
+        var literal = ""
+        var end = start
+        while end < chars.count {
+            let c = chars[end]
+            let plain = !literal.contains(".") && !literal.contains("e")
+            if isDigit(end) {
+                literal.append(c)
+            } else if c == "_", isDigit(end + 1) {
+                // Digit separator.
+            } else if c == ",", plain, (1...3).allSatisfy({ isDigit(end + $0) }), !isDigit(end + 4) {
+                // Thousands separator: exactly three digits follow.
+            } else if c == ".", plain, isDigit(end + 1) {
+                literal.append(c)
+            } else if c == "e" || c == "E", !literal.contains("e"),
+                      isDigit(end + 1) || (char(end + 1).map { "+-".contains($0) } == true && isDigit(end + 2)) {
+                literal += "e" + (isDigit(end + 1) ? "" : String(chars[end + 1]))
+                end += isDigit(end + 1) ? 0 : 1
+            } else {
+                break
+            }
+            end += 1
+        }
+        guard let value = Double(literal) else { throw CalculatorError.syntax("bad number \(literal)") }
+        return (value, end)
+    }
+
+    private enum Target {
+        case unit(MeasureUnit)
+        case radix(Int)
+    }
+
+    /// Recursive descent over `input := expr (in target)?`, with `^` right
+    /// associative and binding tighter than unary minus (`-2^2` is -4).
+    private struct Parser {
+        let tokens: [Token]
+        let rates: [String: Double]
+        var index = 0
+        var usesCurrency = false
+
+        init(tokens: [Token], rates: [String: Double]) {
+            self.tokens = tokens
+            self.rates = rates
+        }
+
+        mutating func parse() throws -> CalculatorResult {
+            var quantity = try expression()
+            var radix = 10
+            if case .word(let word)? = peek(), Calculator.conversionWords.contains(word.lowercased()) {
+                index += 1
+                switch try target() {
+                case .unit(let unit): quantity = try quantity.converted(to: unit)
+                case .radix(let base): radix = base
+                }
+            }
+            if index < tokens.count { throw CalculatorError.syntax("unexpected \(tokens[index].text)") }
+            guard quantity.value.isFinite else { throw CalculatorError.domain("the result is not a finite number") }
+            return CalculatorResult(
+                value: quantity.value, unit: quantity.unit, text: try render(quantity, radix: radix),
+                usesCurrency: usesCurrency)
+        }
+
+        private func render(_ quantity: Quantity, radix: Int) throws -> String {
+            guard let unit = quantity.unit else {
+                guard radix != 10 else { return Calculator.format(quantity.value) }
+                guard quantity.value == quantity.value.rounded(), abs(quantity.value) < 9.2e18 else {
+                    throw CalculatorError.domain("only whole numbers convert to base \(radix)")
+                }
+                let prefix = [16: "0x", 2: "0b", 8: "0o"][radix] ?? ""
+                let digits = String(Int64(abs(quantity.value)), radix: radix)
+                return (quantity.value < 0 ? "-" : "") + prefix + digits
+            }
+            if radix != 10 { throw CalculatorError.cannotConvert(unit.symbol, "base \(radix)") }
+            if unit.kind == .currency { return String(format: "%.2f %@", quantity.value, unit.symbol) }
+            return "\(Calculator.format(quantity.value)) \(unit.symbol)"
+        }
+
+        private func peek(_ ahead: Int = 0) -> Token? {
+            tokens.indices.contains(index + ahead) ? tokens[index + ahead] : nil
+        }
+
+        private mutating func accept(_ symbol: Character) -> Bool {
+            guard peek() == .symbol(symbol) else { return false }
+            index += 1
+            return true
+        }
+
+        private mutating func acceptWord(_ word: String) -> Bool {
+            guard case .word(let next)? = peek(), next.lowercased() == word else { return false }
+            index += 1
+            return true
+        }
+
+        private mutating func expression() throws -> Quantity {
+            var lhs = try term()
+            while true {
+                if accept("+") {
+                    lhs = try Calculator.add(lhs, term())
+                } else if accept("-") {
+                    var rhs = try term()
+                    rhs.value.negate()
+                    lhs = try Calculator.add(lhs, rhs)
+                } else {
+                    return lhs
+                }
+            }
+        }
+
+        private mutating func term() throws -> Quantity {
+            var lhs = try unary()
+            while true {
+                if accept("*") || acceptWord("of") {
+                    lhs = try Calculator.multiply(lhs, unary())
+                } else if accept("/") {
+                    lhs = try Calculator.divide(lhs, unary())
+                } else if acceptWord("mod") {
+                    let rhs = try unary()
+                    guard lhs.unit == nil, rhs.unit == nil else {
+                        throw CalculatorError.incompatible(lhs.name, rhs.name)
+                    }
+                    guard rhs.value != 0 else { throw CalculatorError.domain("division by zero") }
+                    lhs.value = fmod(lhs.value, rhs.value)
+                } else {
+                    return lhs
+                }
+            }
+        }
+
+        private mutating func unary() throws -> Quantity {
+            if accept("-") {
+                var operand = try unary()
+                operand.value.negate()
+                return operand
+            }
+            if accept("+") { return try unary() }
+            let base = try postfix()
+            guard accept("^") else { return base }
+            let exponent = try unary()
+            guard base.unit == nil, exponent.unit == nil else {
+                throw CalculatorError.incompatible(base.name, exponent.name)
+            }
+            return Quantity(value: pow(base.value, exponent.value))
+        }
+
+        /// `15%` is 0.15, so `15% of 80` and `80 * 15%` both read naturally.
+        private mutating func postfix() throws -> Quantity {
+            var operand = try primary()
+            while accept("%") {
+                guard operand.unit == nil else { throw CalculatorError.incompatible(operand.name, "%") }
+                operand.value /= 100
+            }
+            return operand
+        }
+
+        private mutating func primary() throws -> Quantity {
+            guard let token = peek() else { throw CalculatorError.syntax("expression ends early") }
+            index += 1
+            switch token {
+            case .number(let value):
+                return Quantity(value: value, unit: try unitSuffix())
+            case .currency(let code):
+                let amount = try primary()
+                guard amount.unit == nil else { throw CalculatorError.incompatible(code, amount.name) }
+                return Quantity(value: amount.value, unit: try currency(code))
+            case .symbol("("):
+                let inner = try expression()
+                guard accept(")") else { throw CalculatorError.syntax("missing )") }
+                guard inner.unit == nil, let unit = try unitSuffix() else { return inner }
+                return Quantity(value: inner.value, unit: unit)
+            case .word(let word):
+                let name = word.lowercased()
+                if let constant = Calculator.constants[name] { return Quantity(value: constant) }
+                guard let function = Calculator.functions[name] else {
+                    throw CalculatorError.syntax("unexpected \(word)")
+                }
+                guard accept("(") else { throw CalculatorError.syntax("\(name) needs parentheses") }
+                let argument = try expression()
+                guard accept(")") else { throw CalculatorError.syntax("missing )") }
+                guard argument.unit == nil || Calculator.unitPreserving.contains(name) else {
+                    throw CalculatorError.incompatible(name, argument.name)
+                }
+                return Quantity(value: function(argument.value), unit: argument.unit)
+            case .symbol(let symbol):
+                throw CalculatorError.syntax("unexpected \(symbol)")
+            }
+        }
+
+        /// The unit written after an amount, if a word follows that is not
+        /// a keyword like `in` or `of`.
+        private mutating func unitSuffix() throws -> MeasureUnit? {
+            guard case .word(let word)? = peek(), !Calculator.keywords.contains(word.lowercased()) else {
+                return nil
+            }
+            index += 1
+            return try unit(startingWith: word)
+        }
+
+        private mutating func target() throws -> Target {
+            guard case .word(let word)? = peek() else { throw CalculatorError.syntax("convert to what?") }
+            index += 1
+            switch word.lowercased() {
+            case "hex", "hexadecimal": return .radix(16)
+            case "bin", "binary": return .radix(2)
+            case "oct", "octal": return .radix(8)
+            case "dec", "decimal": return .radix(10)
+            default: return .unit(try unit(startingWith: word))
+            }
+        }
+
+        /// `km/h` and `MB/s` are one unit when the compound is known;
+        /// otherwise the slash stays a division.
+        private mutating func unit(startingWith word: String) throws -> MeasureUnit {
+            if peek() == .symbol("/"), case .word(let per)? = peek(1),
+               let compound = Calculator.unit(named: "\(word)/\(per)") {
+                index += 2
+                return compound
+            }
+            if let unit = Calculator.unit(named: word) { return unit }
+            let code = word.uppercased()
+            guard code.count == 3, rates[code] != nil || CurrencyRates.codes.contains(code) else {
+                throw CalculatorError.unknownUnit(word)
+            }
+            return try currency(code)
+        }
+
+        private mutating func currency(_ code: String) throws -> MeasureUnit {
+            usesCurrency = true
+            guard let rate = rates[code], rate > 0 else { throw CalculatorError.noRate(code) }
+            return MeasureUnit(symbol: code, kind: .currency, scale: 1 / rate)
+        }
+    }
+}
+
+/// Euro reference rates from the European Central Bank, cached in
+/// Application Support and refreshed in the background at most twice a
+/// day, and only once a currency has actually been asked for.
+final class CurrencyRates {
+    struct Table: Codable, Equatable {
+        /// The business day the rates were published for.
+        let date: String
+        let fetched: Date
+        /// Units of each currency per euro, EUR included.
+        let rates: [String: Double]
+    }
+
+    static let shared = CurrencyRates(url: CurrencyRates.defaultURL)
+    static let source = URL(string: "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml")!
+    static let maxAge: TimeInterval = 12 * 60 * 60
+    /// Failed fetches are retried no sooner than this.
+    static let retryDelay: TimeInterval = 5 * 60
+    /// The currencies the ECB publishes; these read as currencies before
+    /// any rates are cached.
+    static let codes: Set<String> = [
+        "EUR", "USD", "JPY", "BGN", "CZK", "DKK", "GBP", "HUF", "PLN", "RON", "SEK", "CHF", "ISK", "NOK",
+        "TRY", "AUD", "BRL", "CAD", "CNY", "HKD", "IDR", "ILS", "INR", "KRW", "MXN", "MYR", "NZD", "PHP",
+        "SGD", "THB", "ZAR",
+    ]
+
+    static var defaultURL: URL { AppSupport.url("currency-rates.json") }
+
+    private struct State {
+        var table: Table?
+        var loaded = false
+        var attempted: Date?
+    }
+
+    let url: URL
+    private let file: JSONFileStore<Table>
+    private let state = LockedState(State())
+    private let session = URLSession(configuration: .ephemeral)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url)
+    }
+
+    var table: Table? {
+        state.withLock { state in
+            load(&state)
+            return state.table
+        }
+    }
+
+    func refreshIfStale(now: Date = Date()) {
+        let due = state.withLock { state -> Bool in
+            load(&state)
+            if let table = state.table, now.timeIntervalSince(table.fetched) < Self.maxAge { return false }
+            if let attempted = state.attempted, now.timeIntervalSince(attempted) < Self.retryDelay { return false }
+            state.attempted = now
+            return true
+        }
+        if due { refresh() }
+    }
+
+    /// Fetches today's rates; `completion` gets the new table, or nil when
+    /// the fetch or the parse failed and the cached one stays.
+    func refresh(completion: ((Table?) -> Void)? = nil) {
+        session.dataTask(with: Self.source) { [weak self] data, _, _ in
+            let table = data.flatMap { Self.parseECB(String(decoding: $0, as: UTF8.self), fetched: Date()) }
+            if let table { self?.store(table) }
+            completion?(table)
+        }.resume()
+    }
+
+    func store(_ table: Table) {
+        state.withLock {
+            $0.table = table
+            $0.loaded = true
+        }
+        file.save(table)
+    }
+
+    /// Reads `<Cube time='…'>` and its `<Cube currency='USD' rate='1.08'/>`
+    /// children out of the daily reference-rate feed.
+    static func parseECB(_ xml: String, fetched: Date) -> Table? {
+        let range = NSRange(xml.startIndex..., in: xml)
+        let cubePattern = #"currency=['"]([A-Z]{3})['"]\s+rate=['"]([0-9.]+)['"]"#
+        guard let day = try? NSRegularExpression(pattern: #"time=['"](\d{4}-\d{2}-\d{2})['"]"#),
+              let cube = try? NSRegularExpression(pattern: cubePattern),
+              let dayMatch = day.firstMatch(in: xml, range: range),
+              let dateRange = Range(dayMatch.range(at: 1), in: xml)
+        else { return nil }
+        var rates = ["EUR": 1.0]
+        for match in cube.matches(in: xml, range: range) {
+            guard let code = Range(match.range(at: 1), in: xml), let rate = Range(match.range(at: 2), in: xml),
+                  let value = Double(xml[rate]), value > 0
+            else { continue }
+            rates[String(xml[code])] = value
+        }
+        guard rates.count > 1 else { return nil }
+        return Table(date: String(xml[dateRange]), fetched: fetched, rates: rates)
+    }
+
+    private func load(_ state: inout State) {
+        guard !state.loaded else { return }
+        state.loaded = true
+        state.table = file.load()
+    }
+}
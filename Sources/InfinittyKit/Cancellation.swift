@@ -0,0 +1,78 @@
+import Foundation
+
+/// One-way cancellation flag shared between whoever owns a piece of work
+/// and the code doing it. Pollers read `isCancelled` between units; work
+/// that blocks (a child process, a URL request, a Swift `Task`) registers
+/// an `onCancel` handler that interrupts it. Safe from any queue.
+final class CancellationToken {
+    private struct State {
+        var cancelled = false
+        var nextHandlerID = 0
+        var handlers: [Int: () -> Void] = [:]
+    }
+
+    private let state = LockedState(State())
+
+    init() {}
+
+    var isCancelled: Bool { state.withLock { $0.cancelled } }
+
+    /// Cancel and run every registered handler once. Later calls are no-ops.
+    func cancel() {
+        let handlers = state.withLock { state -> [() -> Void] in
+            guard !state.cancelled else { return [] }
+            state.cancelled = true
+            defer { state.handlers.removeAll() }
+            return state.handlers.sorted { $0.key < $1.key }.map(\.value)
+        }
+        for handler in handlers { handler() }
+    }
+
+    /// Run `handler` on cancellation, or right away when already cancelled.
+    /// Returns a key for `removeHandler` so short-lived work doesn't pile
+    /// handlers onto a long-lived token.
+    @discardableResult
+    func onCancel(_ handler: @escaping () -> Void) -> Int? {
+        let id = state.withLock { state -> Int? in
+            guard !state.cancelled else { return nil }
+            let id = state.nextHandlerID
+            state.nextHandlerID += 1
+            state.handlers[id] = handler
+            return id
+        }
+        if id == nil { handler() }
+        return id
+    }
+
+    func removeHandler(_ id: Int?) {
+        guard let id else { return }
+        state.withLock { _ = $0.handlers.removeValue(forKey: id) }
+    }
+}
+
+/// Cancellable work addressable by a request id from the control socket
+/// (`cancel-operation <id>`): background tasks register under their task
+/// id, browser automation under the caller's `requestId`. Work must
+/// unregister when it ends so a stale id can't cancel a reused one.
+final class CancellationRegistry {
+    static let shared = CancellationRegistry()
+
+    private let tokens = LockedState([String: CancellationToken]())
+
+    func register(_ id: String, _ token: CancellationToken) {
+        tokens.withLock { $0[id] = token }
+    }
+
+    func unregister(_ id: String) {
+        tokens.withLock { _ = $0.removeValue(forKey: id) }
+    }
+
+    /// False when nothing running is registered under `id`.
+    func cancel(_ id: String) -> Bool {
+        guard let token = tokens.withLock({ $0.removeValue(forKey: id) }) else { return false }
+        token.cancel()
+        return true
+    }
+
+    var activeIDs: [String] { tokens.withLock { $0.keys.sorted() } }
+}
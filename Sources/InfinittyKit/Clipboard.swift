@@ -0,0 +1,69 @@
+import AppKit
+
+/// A clean-up applied to text on its way to the clipboard (`copy-transforms`).
+enum CopyTransform: String, CaseIterable {
+    /// Line breaks at the end, so pasting a command doesn't also run it.
+    case trim
+    /// Colour and other escape sequences.
+    case stripANSI = "strip-ansi"
+    /// A shell prompt's `$ `, `% ` or `❯ ` at the start of a line, and
+    /// lines that were nothing but a prompt.
+    case stripPrompts = "strip-prompts"
+
+    static let defaults: Set<CopyTransform> = [.trim, .stripANSI]
+
+    /// `trim, strip-ansi` or `all`; `none` is no clean-up. Nil when a name
+    /// isn't one.
+    static func parse(_ text: String) -> Set<CopyTransform>? {
+        let names = text.lowercased().split(whereSeparator: { $0 == "," || $0 == " " }).map(String.init)
+        if names == ["none"] { return [] }
+        if names == ["all"] { return Set(allCases) }
+        var transforms = Set<CopyTransform>()
+        for name in names {
+            guard let transform = CopyTransform(rawValue: name) else { return nil }
+            transforms.insert(transform)
+        }
+        return transforms
+    }
+}
+
+/// Every copy out of a pane goes through here — the Copy command, copy on
+/// select, copy mode and `clipboard-write` — so what lands on the clipboard
+/// is cleaned the same way whichever one made it.
+enum Clipboard {
+    private static let escapes = try! NSRegularExpression(
+        pattern: #"\x1B\[[0-?]*[ -/]*[@-~]|\x1B\][^\x07\x1B]*(?:\x07|\x1B\\)|\x1B[@-Z\\-_]"#)
+    private static let prompt = try! NSRegularExpression(
+        pattern: #"^[ \t]*[$%❯➜»][ \t]+(?=\S)"#, options: .anchorsMatchLines)
+    private static let barePrompt = try! NSRegularExpression(
+        pattern: #"^[ \t]*[$%❯➜»][ \t]*(?:\r?\n|$)"#, options: .anchorsMatchLines)
+
+    /// `text` with `transforms` applied: escapes go first so a coloured
+    /// prompt is still recognised, and trimming last.
+    static func clean(_ text: String, _ transforms: Set<CopyTransform>) -> String {
+        var text = text
+        func remove(_ regex: NSRegularExpression) {
+            text = regex.stringByReplacingMatches(
+                in: text, range: NSRange(text.startIndex..., in: text), withTemplate: "")
+        }
+        if transforms.contains(.stripANSI) { remove(escapes) }
+        if transforms.contains(.stripPrompts) {
+            remove(barePrompt)
+            remove(prompt)
+        }
+        if transforms.contains(.trim) {
+            while let last = text.last, last.isNewline { text.removeLast() }
+        }
+        return text
+    }
+
+    /// Cleans `text` and puts it on the general pasteboard, returning what
+    /// was written. Main thread only.
+    @discardableResult
+    static func write(_ text: String, transforms: Set<CopyTransform>) -> String {
+        let cleaned = clean(text, transforms)
+        NSPasteboard.general.clearContents()
+        NSPasteboard.general.setString(cleaned, forType: .string)
+        return cleaned
+    }
+}
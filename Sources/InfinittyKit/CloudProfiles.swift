@@ -0,0 +1,169 @@
+import Foundation
+
+/// A cloud CLI whose credentials a shell selects through environment
+/// variables.
+enum CloudProvider: String, CaseIterable {
+    case aws
+    case gcp
+    case azure
+
+    /// Variables that pick the profile for commands run after they are
+    /// exported. `az` itself keeps one global subscription, but Azure SDKs
+    /// and Terraform read these.
+    var environmentKeys: [String] {
+        switch self {
+        case .aws: return ["AWS_PROFILE"]
+        case .gcp: return ["CLOUDSDK_ACTIVE_CONFIG_NAME"]
+        case .azure: return ["AZURE_SUBSCRIPTION_ID", "ARM_SUBSCRIPTION_ID"]
+        }
+    }
+}
+
+/// One selectable identity: an AWS profile, a gcloud configuration, or an
+/// Azure subscription.
+struct CloudProfile: Equatable {
+    let provider: CloudProvider
+    let name: String
+    /// What the environment variable is set to: the name, except for Azure,
+    /// which selects subscriptions by id.
+    let value: String
+    /// Region, project or tenant, when the config names one.
+    var detail: String?
+    /// The CLI's own default when nothing is exported.
+    var isDefault = false
+
+    /// Names that read like production get a warning-colored badge.
+    var looksLikeProduction: Bool {
+        let words = name.lowercased().split { !$0.isLetter && !$0.isNumber }
+        return words.contains { ["prod", "prd", "production", "live"].contains($0) }
+    }
+
+    /// Short badge text for the pane header.
+    var badge: String { "\(provider.rawValue):\(name)" }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "provider": provider.rawValue, "name": name, "default": isDefault,
+            "production": looksLikeProduction,
+        ]
+        if value != name { object["id"] = value }
+        if let detail { object["detail"] = detail }
+        return object
+    }
+}
+
+/// Reads the AWS, gcloud and Azure CLI config files, honoring the same
+/// override variables the CLIs do. Parsers take file contents so they can
+/// be checked without a home directory full of credentials; nothing secret
+/// is kept, only names and the region/project/tenant beside them.
+enum CloudProfiles {
+    static func discover(
+        home: String = NSHomeDirectory(),
+        environment: [String: String] = ProcessInfo.processInfo.environment
+    ) -> [CloudProfile] {
+        func read(_ path: String) -> String? { try? String(contentsOfFile: path, encoding: .utf8) }
+        let awsConfig = read(environment["AWS_CONFIG_FILE"] ?? "\(home)/.aws/config")
+        let awsCredentials = read(environment["AWS_SHARED_CREDENTIALS_FILE"] ?? "\(home)/.aws/credentials")
+
+        let gcloud = environment["CLOUDSDK_CONFIG"] ?? "\(home)/.config/gcloud"
+        let names = (try? FileManager.default.contentsOfDirectory(atPath: "\(gcloud)/configurations")) ?? []
+        let configurations = names.sorted().compactMap { file -> (String, String)? in
+            guard file.hasPrefix("config_"), let text = read("\(gcloud)/configurations/\(file)") else { return nil }
+            return (String(file.dropFirst("config_".count)), text)
+        }
+        let active = read("\(gcloud)/active_config")?.trimmingCharacters(in: .whitespacesAndNewlines)
+
+        let azure = environment["AZURE_CONFIG_DIR"] ?? "\(home)/.azure"
+        let azureProfile = FileManager.default.contents(atPath: "\(azure)/azureProfile.json")
+
+        return aws(config: awsConfig, credentials: awsCredentials, selected: environment["AWS_PROFILE"])
+            + gcp(configurations: configurations, active: active)
+            + (azureProfile.map(azureSubscriptions) ?? [])
+    }
+
+    /// `[profile name]` sections of the config (plain `[default]`) merged
+    /// with the credentials file's `[name]` sections. `selected` is an
+    /// inherited `AWS_PROFILE`, which outranks `default`.
+    static func aws(config: String?, credentials: String?, selected: String? = nil) -> [CloudProfile] {
+        var names: [String] = []
+        var regions: [String: String] = [:]
+        func add(_ name: String, region: String?) {
+            if !names.contains(name) { names.append(name) }
+            if regions[name] == nil { regions[name] = region }
+        }
+        for (section, values) in ini(config ?? "") {
+            let name: String?
+            if section == "default" {
+                name = section
+            } else if section.hasPrefix("profile ") {
+                name = section.dropFirst("profile ".count).trimmingCharacters(in: .whitespaces)
+            } else {
+                name = nil // sso-session and services sections are not profiles
+            }
+            if let name { add(name, region: values["region"]) }
+        }
+        for (section, _) in ini(credentials ?? "") { add(section, region: nil) }
+        let chosen = selected.flatMap { names.contains($0) ? $0 : nil } ?? "default"
+        return names.map { name in
+            CloudProfile(provider: .aws, name: name, value: name, detail: regions[name], isDefault: name == chosen)
+        }
+    }
+
+    /// gcloud configurations (`configurations/config_<name>`), with the
+    /// `[core] project` each one points at.
+    static func gcp(configurations: [(name: String, text: String)], active: String?) -> [CloudProfile] {
+        configurations.map { configuration in
+            let core = ini(configuration.text).first { $0.section == "core" }?.values
+            return CloudProfile(
+                provider: .gcp, name: configuration.name, value: configuration.name,
+                detail: core?["project"], isDefault: configuration.name == (active ?? "default"))
+        }
+    }
+
+    /// `azureProfile.json`, written by `az login` with a UTF-8 byte order
+    /// mark that JSONSerialization rejects.
+    static func azureSubscriptions(_ data: Data) -> [CloudProfile] {
+        let body = data.starts(with: [0xEF, 0xBB, 0xBF]) ? data.dropFirst(3) : data
+        guard let object = try? JSONSerialization.jsonObject(with: Data(body)) as? [String: Any],
+              let subscriptions = object["subscriptions"] as? [[String: Any]]
+        else { return [] }
+        return subscriptions.compactMap { subscription in
+            guard let id = subscription["id"] as? String else { return nil }
+            return CloudProfile(
+                provider: .azure, name: subscription["name"] as? String ?? id, value: id,
+                detail: subscription["tenantId"] as? String,
+                isDefault: subscription["isDefault"] as? Bool ?? false)
+        }
+    }
+
+    /// Shell line that selects `profile` (or with nil, clears `provider`)
+    /// for the commands after it. The leading space keeps it out of
+    /// history in shells that ignore space-prefixed lines.
+    static func exportCommand(_ provider: CloudProvider, _ profile: CloudProfile?) -> String {
+        guard let profile else { return " unset " + provider.environmentKeys.joined(separator: " ") }
+        let assignments = provider.environmentKeys.map { "\($0)=\(shellQuoted(profile.value))" }
+        return " export " + assignments.joined(separator: " ")
+    }
+
+    private static func shellQuoted(_ text: String) -> String {
+        "'" + text.replacingOccurrences(of: "'", with: "'\\''") + "'"
+    }
+
+    /// Minimal INI: `[section]` headers and `key = value` lines; `#` and
+    /// `;` start comments.
+    static func ini(_ text: String) -> [(section: String, values: [String: String])] {
+        var sections: [(section: String, values: [String: String])] = []
+        for raw in text.split(whereSeparator: \.isNewline) {
+            let line = raw.trimmingCharacters(in: .whitespaces)
+            if line.isEmpty || line.hasPrefix("#") || line.hasPrefix(";") { continue }
+            if line.hasPrefix("["), line.hasSuffix("]") {
+                sections.append((String(line.dropFirst().dropLast()).trimmingCharacters(in: .whitespaces), [:]))
+            } else if let equals = line.firstIndex(of: "="), !sections.isEmpty {
+                let key = line[..<equals].trimmingCharacters(in: .whitespaces)
+                let value = line[line.index(after: equals)...].trimmingCharacters(in: .whitespaces)
+                sections[sections.count - 1].values[key] = value
+            }
+        }
+        return sections
+    }
+}
@@ -10,6 +10,49 @@ struct DiffLine: Equatable {
     let oldLine: Int?
     let newLine: Int?
     let text: String
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["kind": "\(kind)", "text": text]
+        if let oldLine { object["old"] = oldLine }
+        if let newLine { object["new"] = newLine }
+        return object
+    }
+}
+
+/// One `@@` section of a file's diff: where it sits on each side and its
+/// lines, for `git-diff`.
+struct DiffHunk {
+    let header: String
+    let oldStart: Int
+    let newStart: Int
+    let lines: [DiffLine]
+
+    var oldCount: Int { lines.filter { $0.kind != .add }.count }
+    var newCount: Int { lines.filter { $0.kind != .del }.count }
+    var added: Int { lines.filter { $0.kind == .add }.count }
+    var removed: Int { lines.filter { $0.kind == .del }.count }
+
+    var wire: [String: Any] {
+        [
+            "header": header, "oldStart": oldStart, "oldLines": oldCount, "newStart": newStart,
+            "newLines": newCount, "added": added, "removed": removed, "lines": lines.map(\.wire),
+        ]
+    }
+}
+
+/// Lines added and removed in one file, from `git diff --numstat`. Binary
+/// files have no line counts.
+struct DiffStat: Equatable {
+    let path: String
+    let added: Int
+    let removed: Int
+    let isBinary: Bool
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["path": path, "added": added, "removed": removed]
+        if isBinary { object["binary"] = true }
+        return object
+    }
 }
 
 /// One row of a side-by-side diff: an old-side cell and a new-side cell.
@@ -74,6 +117,155 @@ enum CodeDiff {
         return lines
     }
 
+    /// `parse`'s rows grouped under their hunk headers. Rows before the
+    /// first header, and separators that aren't hunks, are dropped.
+    static func hunks(_ diff: String) -> [DiffHunk] {
+        var hunks: [DiffHunk] = []
+        var current: (header: String, old: Int, new: Int, lines: [DiffLine])?
+        func close() {
+            if let current {
+                hunks.append(DiffHunk(
+                    header: current.header, oldStart: current.old, newStart: current.new, lines: current.lines))
+            }
+            current = nil
+        }
+        for line in parse(diff) {
+            if line.kind == .hunk {
+                close()
+                if let (old, new) = parseHunkHeader(line.text) { current = (line.text, old, new, []) }
+            } else {
+                current?.lines.append(line)
+            }
+        }
+        close()
+        return hunks
+    }
+
+    /// Which hunk a diff row sits in, counted within its half of
+    /// `CodeGit.diff`'s output: `headers` holds each row's hunk header (nil
+    /// for other rows), the "--- unstaged ---" separator included. The
+    /// half before the separator is staged when `stagedFirst`.
+    static func hunk(at row: Int, headers: [String?], stagedFirst: Bool) -> (index: Int, staged: Bool)? {
+        guard headers.indices.contains(row) else { return nil }
+        var staged = stagedFirst
+        var index = -1
+        var current: Int?
+        for header in headers[...row] {
+            guard let header else { continue }
+            if header == "--- unstaged ---" {
+                (staged, index, current) = (false, -1, nil)
+            } else {
+                index += 1
+                current = index
+            }
+        }
+        return current.map { ($0, staged) }
+    }
+
+    /// A patch holding only the changes `selecting` picks from one file's
+    /// diff, for `git apply --cached` — what `git add -p` does with the
+    /// answers. `hunk` is the index into `hunks(diff)`. Forward (staging),
+    /// an unpicked addition is left out and an unpicked deletion stays as
+    /// context; `reverse` (unstaging a staged diff) is the mirror image,
+    /// since the index holds the new side. Nil when nothing is picked.
+    static func partialPatch(
+        _ diff: String, reverse: Bool = false, selecting: (_ hunk: Int, _ line: DiffLine) -> Bool
+    ) -> String? {
+        var header: [String] = []
+        var body: [String] = []
+        var hunk = -1
+        var oldNo = 0
+        var newNo = 0
+        var rows: [String] = []
+        var (oldStart, newStart, section) = (0, 0, "")
+        var picked = false
+        var lastKept = false
+        // How far the computed side has moved from the fixed one so far.
+        var offset = 0
+        func close() {
+            guard hunk >= 0, picked else { return }
+            let oldCount = rows.filter { $0.first == " " || $0.first == "-" }.count
+            let newCount = rows.filter { $0.first == " " || $0.first == "+" }.count
+            var (old, new) = (oldStart, newStart)
+            if reverse {
+                old = max(newStart - offset + (newCount == 0 ? 1 : 0) - (oldCount == 0 ? 1 : 0), 0)
+                offset += newCount - oldCount
+            } else {
+                new = max(oldStart + offset + (oldCount == 0 ? 1 : 0) - (newCount == 0 ? 1 : 0), 0)
+                offset += newCount - oldCount
+            }
+            body.append("@@ -\(old),\(oldCount) +\(new),\(newCount) @@\(section)")
+            body += rows
+        }
+        for raw in diff.split(separator: "\n", omittingEmptySubsequences: false) {
+            let line = String(raw)
+            if line.hasPrefix("@@") {
+                close()
+                guard let (old, new) = parseHunkHeader(line) else { return nil }
+                hunk += 1
+                (oldStart, newStart, oldNo, newNo) = (old, new, old, new)
+                let closing = line.dropFirst(2).range(of: "@@")
+                section = closing.map { String(line[$0.upperBound...]) } ?? ""
+                rows = []
+                picked = false
+                continue
+            }
+            if hunk < 0 {
+                if line.hasPrefix("diff --git"), !header.isEmpty { break }
+                header.append(line)
+                continue
+            }
+            if line.hasPrefix("diff --git") { break }
+            guard let first = line.first else { continue }
+            let text = String(line.dropFirst())
+            switch first {
+            case " ":
+                rows.append(line)
+                lastKept = true
+                oldNo += 1
+                newNo += 1
+            case "-":
+                let chosen = selecting(hunk, DiffLine(kind: .del, oldLine: oldNo, newLine: nil, text: text))
+                oldNo += 1
+                picked = picked || chosen
+                if chosen { rows.append(line) } else if !reverse { rows.append(" " + text) }
+                lastKept = chosen || !reverse
+            case "+":
+                let chosen = selecting(hunk, DiffLine(kind: .add, oldLine: nil, newLine: newNo, text: text))
+                newNo += 1
+                picked = picked || chosen
+                if chosen { rows.append(line) } else if reverse { rows.append(" " + text) }
+                lastKept = chosen || reverse
+            case "\\":
+                if lastKept { rows.append(line) }
+            default:
+                continue
+            }
+        }
+        close()
+        guard !body.isEmpty else { return nil }
+        return (header + body).joined(separator: "\n") + "\n"
+    }
+
+    /// `git diff --numstat` output: "12\t3\tpath", with "-\t-" for a binary
+    /// file and "old => new" (or "dir/{old => new}") for a rename, which is
+    /// reported under its new path.
+    static func parseNumstat(_ output: String) -> [DiffStat] {
+        output.split(separator: "\n").compactMap { line in
+            let fields = line.split(separator: "\t", maxSplits: 2, omittingEmptySubsequences: false)
+            guard fields.count == 3 else { return nil }
+            var path = String(fields[2])
+            if let open = path.firstIndex(of: "{"), let close = path.lastIndex(of: "}"), open < close,
+               let arrow = path[open...close].range(of: " => ") {
+                path = String(path[..<open]) + path[arrow.upperBound..<close] + path[path.index(after: close)...]
+            } else if let arrow = path.range(of: " => ") {
+                path = String(path[arrow.upperBound...])
+            }
+            let binary = fields[0] == "-" && fields[1] == "-"
+            return DiffStat(path: path, added: Int(fields[0]) ?? 0, removed: Int(fields[1]) ?? 0, isBinary: binary)
+        }
+    }
+
     /// "@@ -3,7 +3,8 @@" → (3, 3); counts default to 1 when omitted.
     static func parseHunkHeader(_ line: String) -> (old: Int, new: Int)? {
         guard let rx = try? NSRegularExpression(
@@ -86,6 +278,63 @@ enum CodeDiff {
         return (old, new)
     }
 
+    /// Diff two texts line by line into the same rows `parse` makes from
+    /// git's output: hunks of changes with up to `context` unchanged lines
+    /// around them, deletions before the additions that replace them.
+    /// Identical texts give no rows.
+    static func compare(_ old: [String], _ new: [String], context: Int = 3) -> [DiffLine] {
+        let difference = new.difference(from: old)
+        var removed = Set<Int>()
+        var inserted = Set<Int>()
+        for change in difference {
+            switch change {
+            case .remove(let offset, _, _): removed.insert(offset)
+            case .insert(let offset, _, _): inserted.insert(offset)
+            }
+        }
+        var script: [DiffLine] = []
+        var (i, j) = (0, 0)
+        while i < old.count || j < new.count {
+            if removed.contains(i) {
+                script.append(DiffLine(kind: .del, oldLine: i + 1, newLine: nil, text: old[i]))
+                i += 1
+            } else if inserted.contains(j) {
+                script.append(DiffLine(kind: .add, oldLine: nil, newLine: j + 1, text: new[j]))
+                j += 1
+            } else {
+                script.append(DiffLine(kind: .context, oldLine: i + 1, newLine: j + 1, text: old[i]))
+                i += 1
+                j += 1
+            }
+        }
+        let changes = script.indices.filter { script[$0].kind != .context }
+        guard !changes.isEmpty else { return [] }
+        // Group changes whose context would touch, then widen each group.
+        var groups: [ClosedRange<Int>] = []
+        for index in changes {
+            if let last = groups.last, index - last.upperBound <= 2 * context + 1 {
+                groups[groups.count - 1] = last.lowerBound...index
+            } else {
+                groups.append(index...index)
+            }
+        }
+        var lines: [DiffLine] = []
+        for group in groups {
+            let start = max(group.lowerBound - context, 0)
+            let rows = script[start...min(group.upperBound + context, script.count - 1)]
+            let oldBefore = script[..<start].filter { $0.kind != .add }.count
+            let newBefore = script[..<start].filter { $0.kind != .del }.count
+            let oldCount = rows.filter { $0.kind != .add }.count
+            let newCount = rows.filter { $0.kind != .del }.count
+            // An empty side is numbered by the line before it, as git does.
+            let header = "@@ -\(oldBefore + (oldCount == 0 ? 0 : 1)),\(oldCount) "
+                + "+\(newBefore + (newCount == 0 ? 0 : 1)),\(newCount) @@"
+            lines.append(DiffLine(kind: .hunk, oldLine: nil, newLine: nil, text: header))
+            lines += rows
+        }
+        return lines
+    }
+
     /// Pair parsed lines into side-by-side rows. Context lines appear on both
     /// sides; each del*/add* run is paired index-wise (git emits deletions
     /// first), with blanks padding the shorter side.
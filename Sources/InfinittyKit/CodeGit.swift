@@ -21,7 +21,9 @@ struct CodeChange: Hashable {
 }
 
 /// Thin wrapper over the git CLI for the code-view Changes tab. All calls
-/// are synchronous — invoke from a background queue.
+/// are synchronous — invoke from a background queue. Mutations invalidate
+/// `GitStatusCache` whether or not they succeed (a failed commit hook can
+/// still have touched the index).
 enum CodeGit {
 
     /// Repo root containing `dir`, or nil when not inside a work tree.
@@ -38,17 +40,211 @@ enum CodeGit {
         let branch: String?
         let changes: [CodeChange]
         let error: String?
+        /// The branch's upstream and how far apart they are; nil without one.
+        var tracking: Tracking?
+
+        /// For `git-status` and `git-status-changed`.
+        var wire: [String: Any] {
+            var object: [String: Any] = [
+                "changes": changes.map { ["path": $0.path, "status": $0.label, "staged": $0.isStaged] },
+            ]
+            if let branch { object["branch"] = branch }
+            if let tracking {
+                object["upstream"] = tracking.upstream
+                object["ahead"] = tracking.ahead
+                object["behind"] = tracking.behind
+                if tracking.gone { object["upstreamGone"] = true }
+            }
+            if let error { object["error"] = error }
+            return object
+        }
+    }
+
+    /// Where the branch stands against its upstream, as of the last fetch:
+    /// commits only it has (`ahead`, to push) and only the upstream has
+    /// (`behind`, to pull).
+    struct Tracking: Equatable {
+        let upstream: String
+        var ahead = 0
+        var behind = 0
+        /// The upstream branch is gone from the remote.
+        var gone = false
+    }
+
+    /// A merge or rebase that stopped partway (on conflicts, or an `edit`
+    /// step) and is waiting to be continued or aborted.
+    enum Operation: String {
+        case merge
+        case rebase
+    }
+
+    /// One unmerged path with the three versions a resolver works from.
+    /// In a rebase git's sides swap: `ours` is the branch being rebased
+    /// onto and `theirs` the commit being replayed.
+    struct Conflict: Equatable {
+        let path: String
+        /// The common ancestor's version; nil when both sides added the file.
+        let base: String?
+        /// HEAD's version; nil when this side deleted the file.
+        let ours: String?
+        /// The incoming version; nil when that side deleted the file.
+        let theirs: String?
+        /// A version isn't UTF-8 text, so none of them are included.
+        var binary = false
+
+        /// `both-modified`, `both-added`, `deleted-by-us`, `deleted-by-them`,
+        /// `added-by-us` or `added-by-them`, as `git status` words it.
+        let kind: String
+
+        var wire: [String: Any] {
+            var object: [String: Any] = ["path": path, "kind": kind, "binary": binary]
+            if let base { object["base"] = base }
+            if let ours { object["ours"] = ours }
+            if let theirs { object["theirs"] = theirs }
+            return object
+        }
     }
 
     /// Branch name + changes for `repo` (a root from `repoRoot`). Never nil:
     /// failures arrive in `error` instead of masquerading as a clean tree.
+    /// `--no-optional-locks` keeps the probe from rewriting the index,
+    /// which would otherwise trip `GitStatusCache`'s watcher on every read.
     static func status(in repo: String) -> RepoStatus {
-        let result = runDetailed(["-C", repo, "status", "--porcelain=v1", "-b"])
+        let result = runDetailed(["--no-optional-locks", "-C", repo, "status", "--porcelain=v1", "-b"])
         guard result.status == 0 else {
             return RepoStatus(branch: nil, changes: [], error: result.stderr)
         }
         let (branch, changes) = parseStatus(result.stdout)
-        return RepoStatus(branch: branch, changes: changes, error: nil)
+        return RepoStatus(branch: branch, changes: changes, error: nil, tracking: parseTracking(result.stdout))
+    }
+
+    /// `git fetch --prune` from `remote`, or from the current branch's
+    /// remote (origin when it has none) when nil. nil on success; git's
+    /// stderr on failure.
+    static func fetch(in repo: String, remote: String? = nil) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        var args = ["-C", repo, "fetch", "--prune"]
+        if let remote { args.append(remote) }
+        let result = runDetailed(args, remote: true)
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git fetch failed" : result.stderr)
+    }
+
+    /// `git pull` into the current branch: rebasing, merging, or as the
+    /// repo's `pull.rebase` says when `rebase` is nil. git's summary, or
+    /// its stderr on failure (conflicts, diverged without a strategy,
+    /// local changes in the way).
+    static func pull(in repo: String, rebase: Bool? = nil) -> (output: String, error: String?) {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        var args = ["-C", repo, "pull"]
+        if let rebase { args.append(rebase ? "--rebase" : "--no-rebase") }
+        let result = runDetailed(args, remote: true)
+        let output = result.stdout.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard result.status == 0 else {
+            let error = [result.stderr, result.stdout].first { !$0.isEmpty } ?? "git pull failed"
+            return (output, error)
+        }
+        return (output, nil)
+    }
+
+    /// A remote and where it fetches from and pushes to, which differ once
+    /// `remote set-url --push` has been used.
+    struct Remote: Equatable {
+        let name: String
+        var fetchURL: String
+        var pushURL: String
+
+        var wire: [String: Any] { ["name": name, "fetch": fetchURL, "push": pushURL] }
+    }
+
+    static func remotes(in repo: String) -> [Remote] {
+        parseRemotes(run(["-C", repo, "remote", "-v"]) ?? "")
+    }
+
+    /// `git remote add name url`. nil on success; git's stderr on failure
+    /// (the name is taken or isn't valid).
+    static func addRemote(in repo: String, name: String, url: String) -> String? {
+        guard !name.isEmpty, !name.hasPrefix("-") else { return "not a remote name: \(name)" }
+        guard !url.isEmpty, !url.hasPrefix("-") else { return "not a remote URL: \(url)" }
+        let result = runDetailed(["-C", repo, "remote", "add", name, url])
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git remote add failed" : result.stderr)
+    }
+
+    /// `git remote remove name`, dropping its remote-tracking branches and
+    /// the upstream settings that pointed at it.
+    static func removeRemote(in repo: String, name: String) -> String? {
+        guard !name.isEmpty, !name.hasPrefix("-") else { return "not a remote name: \(name)" }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(["-C", repo, "remote", "remove", name])
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git remote remove failed" : result.stderr)
+    }
+
+    /// How `push` deviates from a plain `git push`.
+    struct PushOptions: Equatable {
+        /// nil pushes to the branch's remote, or `origin` (else the only
+        /// remote) when it has none yet.
+        var remote: String?
+        /// A branch or `local:remote` refspec; nil is the current branch.
+        var branch: String?
+        /// Overwrite the remote branch, but only if it is still where the
+        /// last fetch saw it.
+        var forceWithLease = false
+        /// true records the pushed branch as the upstream; nil does when
+        /// the current branch doesn't have one yet, so a new branch can
+        /// be published at all.
+        var setUpstream: Bool?
+        /// Push every tag too; with no branch, only the tags.
+        var tags = false
+    }
+
+    /// `git push` as `options` say. git's summary (it prints to stderr),
+    /// or its error: rejected as non-fast-forward, a stale lease, no
+    /// remote, or credentials it wasn't allowed to ask for.
+    static func push(in repo: String, options: PushOptions = PushOptions()) -> (output: String, error: String?) {
+        if let remote = options.remote, remote.isEmpty || remote.hasPrefix("-") {
+            return ("", "not a remote: \(remote)")
+        }
+        if let branch = options.branch, branch.isEmpty || branch.hasPrefix("-") {
+            return ("", "not a branch: \(branch)")
+        }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        var remote = options.remote
+        var branch = options.branch
+        let setUpstream = options.setUpstream
+            ?? (branch == nil && !options.tags
+                && run(["-C", repo, "rev-parse", "--abbrev-ref", "--symbolic-full-name", "@{u}"]) == nil)
+        if setUpstream, branch == nil {
+            guard let current = currentBranch(in: repo) else { return ("", "HEAD is not on a branch") }
+            branch = current
+        }
+        // git reads a lone refspec as the remote, so one needs a remote first.
+        if let branch, remote == nil {
+            let local = branch.split(separator: ":", omittingEmptySubsequences: false).first.map(String.init) ?? ""
+            let configured = local.isEmpty ? nil : run(["-C", repo, "config", "--get", "branch.\(local).remote"])?
+                .trimmingCharacters(in: .whitespacesAndNewlines).nilIfEmpty
+            let names = remotes(in: repo).map(\.name)
+            remote = configured ?? (names.contains("origin") ? "origin" : names.count == 1 ? names.first : nil)
+            guard remote != nil else {
+                return ("", names.isEmpty ? "no remote to push to" : "more than one remote: say which")
+            }
+        }
+        var args = ["-C", repo, "push"]
+        if options.forceWithLease { args.append("--force-with-lease") }
+        if setUpstream { args.append("--set-upstream") }
+        if options.tags { args.append("--tags") }
+        args += [remote, branch].compactMap { $0 }
+        let result = runDetailed(args, remote: true)
+        let output = [result.stdout, result.stderr]
+            .map { $0.trimmingCharacters(in: .whitespacesAndNewlines) }
+            .filter { !$0.isEmpty }
+            .joined(separator: "\n")
+        guard result.status == 0 else { return (output, output.isEmpty ? "git push failed" : output) }
+        return (output, nil)
+    }
+
+    /// The branch HEAD is on; nil when detached.
+    static func currentBranch(in repo: String) -> String? {
+        run(["-C", repo, "symbolic-ref", "--short", "-q", "HEAD"])?
+            .trimmingCharacters(in: .whitespacesAndNewlines).nilIfEmpty
     }
 
     /// The diff to preview for a change. Staged and unstaged sections are
@@ -75,6 +271,111 @@ enum CodeGit {
         return (parts.isEmpty ? nil : parts.joined(), parts.isEmpty ? firstError : nil)
     }
 
+    /// One side of one file's diff for `git-diff`: the index against HEAD
+    /// when `staged`, else the work tree against the index. An untracked
+    /// file diffs against nothing, so it reads as all additions. `path` is
+    /// repo-relative or absolute. Empty text when nothing changed.
+    static func fileDiff(in repo: String, path: String, staged: Bool) -> (text: String, error: String?) {
+        let untracked = !staged
+            && run(["-C", repo, "ls-files", "--others", "--exclude-standard", "--", path])?.isEmpty == false
+        if untracked {
+            // --no-index exits 1 when the files differ, which they always do.
+            let result = runDetailed(["-C", repo, "diff", "--no-color", "--no-index", "--", "/dev/null", path])
+            return result.status <= 1 ? (result.stdout, nil) : ("", result.stderr)
+        }
+        var args = ["--no-optional-locks", "-C", repo, "diff", "--no-color"]
+        if staged { args.append("--cached") }
+        let result = runDetailed(args + ["--", path])
+        return result.status == 0 ? (result.stdout, nil) : ("", result.stderr)
+    }
+
+    /// Per-file line counts of the staged or unstaged changes in `repo`.
+    /// Untracked files aren't counted until they are staged.
+    static func diffStats(in repo: String, staged: Bool) -> (stats: [DiffStat], error: String?) {
+        var args = ["--no-optional-locks", "-C", repo, "diff", "--numstat"]
+        if staged { args.append("--cached") }
+        let result = runDetailed(args)
+        return result.status == 0 ? (CodeDiff.parseNumstat(result.stdout), nil) : ([], result.stderr)
+    }
+
+    /// The last change to each line of `path` (repo-relative) as it is in
+    /// the worktree, or at `rev` when given; only `lines` (1-based) when
+    /// given. Lines not committed yet have a zero hash.
+    static func blame(
+        in repo: String, path: String, lines: ClosedRange<Int>? = nil, rev: String? = nil
+    ) -> (lines: [BlameLine], error: String?) {
+        var args = ["--no-optional-locks", "-C", repo, "blame", "--porcelain"]
+        if let lines { args += ["-L", "\(lines.lowerBound),\(lines.upperBound)"] }
+        if let rev { args.append(rev) }
+        args += ["--", path]
+        let result = runDetailed(args)
+        guard result.status == 0 else { return ([], result.stderr.isEmpty ? "git blame failed" : result.stderr) }
+        return (parseBlame(result.stdout), nil)
+    }
+
+    /// Apply `patch` (from `CodeDiff.partialPatch`) to the index only:
+    /// stages part of a file, or with `reverse` unstages part of one. nil
+    /// on success; git's stderr when the patch no longer fits.
+    static func applyToIndex(in repo: String, patch: String, reverse: Bool) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        var args = ["-C", repo, "apply", "--cached", "--whitespace=nowarn"]
+        if reverse { args.append("--reverse") }
+        args.append("-")
+        switch ProcessRunner.run("/usr/bin/git", args, input: Data(patch.utf8)) {
+        case .success(let output):
+            return output.status == 0 ? nil : (output.stderrText.isEmpty ? "git apply failed" : output.stderrText)
+        case .failure(let error):
+            return "git \(error)"
+        }
+    }
+
+    /// Fields of one `git log` record, unit- and record-separated so a
+    /// subject can hold anything.
+    private static let logFormat = "--format=%H%x1F%P%x1F%an%x1F%ae%x1F%at%x1F%D%x1F%s%x1E"
+
+    /// A page of history: `limit` commits after skipping `skip`, from `ref`
+    /// (HEAD when nil, `--all` when "all"), only those touching `path` when
+    /// given. Newest first, children always before their parents.
+    static func log(
+        in repo: String, limit: Int, skip: Int = 0, ref: String? = nil, path: String? = nil
+    ) -> (commits: [CodeCommit], error: String?) {
+        var args = [
+            "--no-optional-locks", "-C", repo, "log", "--topo-order", logFormat,
+            "--max-count=\(limit)", "--skip=\(skip)",
+        ]
+        switch ref {
+        case nil: break
+        case "all": args.append("--all")
+        case let ref?: args.append(ref)
+        }
+        args.append("--")
+        if let path { args.append(path) }
+        let result = runDetailed(args)
+        // A repo with no commits yet has no HEAD to log.
+        if result.status != 0, result.stderr.contains("does not have any commits") { return ([], nil) }
+        return result.status == 0 ? (parseLog(result.stdout), nil) : ([], result.stderr)
+    }
+
+    /// One commit's message and the files it changed, against its first
+    /// parent for a merge. Nil commit with git's stderr when `hash` isn't
+    /// one.
+    static func show(
+        in repo: String, hash: String
+    ) -> (commit: CodeCommit?, body: String, files: [DiffStat], error: String?) {
+        let header = runDetailed(["-C", repo, "show", "-s", logFormat, hash, "--"])
+        guard header.status == 0, let commit = parseLog(header.stdout).first else {
+            return (nil, "", [], header.stderr.isEmpty ? "no commit \(hash)" : header.stderr)
+        }
+        let body = run(["-C", repo, "show", "-s", "--format=%b", hash, "--"]) ?? ""
+        let files = runDetailed([
+            "-C", repo, "show", "--format=", "--numstat", "-M", "--diff-merges=first-parent", hash, "--",
+        ])
+        return (
+            commit, body.trimmingCharacters(in: .whitespacesAndNewlines),
+            files.status == 0 ? CodeDiff.parseNumstat(files.stdout) : [], files.status == 0 ? nil : files.stderr
+        )
+    }
+
     /// Local branch names for `repo`.
     static func branches(in repo: String) -> [String] {
         (run(["-C", repo, "branch", "--format=%(refname:short)"]) ?? "")
@@ -84,15 +385,73 @@ enum CodeGit {
     /// Switch `repo` to `branch`. nil on success; git's stderr on failure
     /// (e.g. uncommitted changes that would be overwritten).
     static func checkout(in repo: String, branch: String) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
         let result = runDetailed(["-C", repo, "checkout", branch])
         return result.status == 0
             ? nil
             : (result.stderr.isEmpty ? "git checkout failed" : result.stderr)
     }
 
+    /// Remote-tracking branches (`origin/main`), without each remote's HEAD
+    /// alias.
+    static func remoteBranches(in repo: String) -> [String] {
+        parseRemoteBranches(run(["-C", repo, "branch", "-r", "--format=%(refname:short)%09%(symref)"]) ?? "")
+    }
+
+    /// Create branch `name` at `from` (HEAD when nil), switching to it when
+    /// `checkout`. Branching from a remote-tracking branch sets it as the
+    /// upstream, as git does by default. nil on success; git's stderr on
+    /// failure (the name is taken, `from` doesn't resolve).
+    static func createBranch(in repo: String, name: String, from: String? = nil, checkout: Bool = false) -> String? {
+        guard isValidBranchName(name) else { return "not a valid branch name: \(name)" }
+        if let from, from.isEmpty || from.hasPrefix("-") { return "not a commit: \(from)" }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let command = checkout ? ["checkout", "-b", name] : ["branch", name]
+        let result = runDetailed(["-C", repo] + command + (from.map { [$0] } ?? []))
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git branch failed" : result.stderr)
+    }
+
+    /// Delete local branch `name`; without `force`, git refuses one whose
+    /// commits aren't merged anywhere. nil on success; git's stderr on failure.
+    static func deleteBranch(in repo: String, name: String, force: Bool = false) -> String? {
+        guard isValidBranchName(name) else { return "not a valid branch name: \(name)" }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(["-C", repo, "branch", force ? "-D" : "-d", name])
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git branch failed" : result.stderr)
+    }
+
+    /// Rename local branch `old` to `new`, keeping its upstream and reflog.
+    /// nil on success; git's stderr on failure.
+    static func renameBranch(in repo: String, from old: String, to new: String) -> String? {
+        guard isValidBranchName(old) else { return "not a valid branch name: \(old)" }
+        guard isValidBranchName(new) else { return "not a valid branch name: \(new)" }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(["-C", repo, "branch", "-m", old, new])
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git branch failed" : result.stderr)
+    }
+
+    /// Switch to remote-tracking branch `remoteBranch` (`origin/topic`)
+    /// through a local branch of the same name (`topic`): the existing one
+    /// if there is one, otherwise a new one tracking it. Returns the local
+    /// name, or git's stderr.
+    static func checkoutRemote(in repo: String, remoteBranch: String) -> (branch: String?, error: String?) {
+        guard let local = localName(ofRemote: remoteBranch), isValidBranchName(local) else {
+            return (nil, "not a remote-tracking branch: \(remoteBranch)")
+        }
+        if branches(in: repo).contains(local) {
+            let error = checkout(in: repo, branch: local)
+            return (error == nil ? local : nil, error)
+        }
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(["-C", repo, "checkout", "-b", local, "--track", remoteBranch])
+        guard result.status == 0 else { return (nil, result.stderr.isEmpty ? "git checkout failed" : result.stderr) }
+        return (local, nil)
+    }
+
     /// Stage one path (untracked, modified or deleted). nil on success;
     /// git's stderr on failure (e.g. a stale index.lock).
     static func stage(in repo: String, path: String) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
         let result = runDetailed(["-C", repo, "add", "--", path])
         return result.status == 0 ? nil : result.stderr
     }
@@ -100,6 +459,7 @@ enum CodeGit {
     /// Unstage one path (`git restore --staged`, falling back to `git reset`).
     /// nil on success; git's stderr on failure.
     static func unstage(in repo: String, path: String) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
         let restored = runDetailed(["-C", repo, "restore", "--staged", "--", path])
         if restored.status == 0 { return nil }
         let reset = runDetailed(["-C", repo, "reset", "-q", "HEAD", "--", path])
@@ -108,19 +468,188 @@ enum CodeGit {
 
     /// Stage every change in the worktree. nil on success; stderr on failure.
     static func stageAll(in repo: String) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
         let result = runDetailed(["-C", repo, "add", "-A"])
         return result.status == 0 ? nil : result.stderr
     }
 
+    /// How `commit` deviates from a plain `git commit`.
+    struct CommitOptions: Equatable {
+        /// Rewrite the last commit instead; an empty message keeps its one.
+        var amend = false
+        /// true signs (`-S`), false never does; nil leaves it to
+        /// `commit.gpgsign`. `gpg.format` picks GPG or SSH either way.
+        var sign: Bool?
+        /// A key for `-S<key>` other than `user.signingkey`.
+        var signingKey: String?
+        /// `Name <email>`; nil keeps the configured identity.
+        var author: String?
+        var committer: String?
+    }
+
     /// Commit the staged changes with `message`. nil on success; git's
-    /// stderr on failure (nothing staged, no identity configured, hook).
-    static func commit(in repo: String, message: String) -> String? {
-        let result = runDetailed(["-C", repo, "commit", "-m", message])
-        return result.status == 0 ? nil : result.stderr
+    /// stderr on failure (nothing staged, no identity configured, hook,
+    /// signing — see `isSigningError`).
+    static func commit(in repo: String, message: String, options: CommitOptions = CommitOptions()) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        var args = ["-C", repo, "commit"]
+        if options.amend { args.append("--amend") }
+        args += options.amend && message.isEmpty ? ["--no-edit"] : ["-m", message]
+        switch options.sign {
+        case true?: args.append("-S" + (options.signingKey ?? ""))
+        case false?: args.append("--no-gpg-sign")
+        case nil: break
+        }
+        var environment: [String: String] = [:]
+        for (role, identity) in [("AUTHOR", options.author), ("COMMITTER", options.committer)] {
+            guard let identity else { continue }
+            guard let parsed = parseIdentity(identity) else { return "not a Name <email> identity: \(identity)" }
+            environment["GIT_\(role)_NAME"] = parsed.name
+            environment["GIT_\(role)_EMAIL"] = parsed.email
+        }
+        let result = runDetailed(args, hooks: true, environment: environment)
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git commit failed" : result.stderr)
+    }
+
+    /// True when a failed commit failed to sign: no key, no agent, or a
+    /// gpg that wanted a pinentry it couldn't show. Worth saying so, since
+    /// git's own message ends with an unhelpful "failed to write commit
+    /// object".
+    static func isSigningError(_ error: String) -> Bool {
+        let lowered = error.lowercased()
+        return [
+            "failed to sign the data", "signing failed", "no secret key", "pinentry",
+            "inappropriate ioctl for device", "couldn't load public key", "load key",
+        ].contains { lowered.contains($0) }
+    }
+
+    /// The hash HEAD points at, nil before the first commit.
+    static func head(in repo: String) -> String? {
+        run(["-C", repo, "rev-parse", "--verify", "-q", "HEAD"])?
+            .trimmingCharacters(in: .whitespacesAndNewlines)
+            .nilIfEmpty
+    }
+
+    /// Throw away the worktree changes to `path` (`git checkout --`): it
+    /// goes back to what's staged, or to HEAD when nothing is. nil on
+    /// success; git's stderr on failure (an untracked path among them).
+    static func discard(in repo: String, path: String) -> String? {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(["-C", repo, "checkout", "--", path])
+        return result.status == 0 ? nil : (result.stderr.isEmpty ? "git checkout failed" : result.stderr)
+    }
+
+    /// Delete untracked files and folders (`git clean -d`), only under
+    /// `paths` when any are given; ignored files stay. With `dryRun`
+    /// nothing is touched and the list says what would go. Repo-relative
+    /// paths, or git's stderr.
+    static func clean(in repo: String, paths: [String] = [], dryRun: Bool) -> (paths: [String], error: String?) {
+        defer { if !dryRun { GitStatusCache.shared.invalidate(repo) } }
+        // Unescaped names, so non-ASCII paths come back as they are on disk.
+        let args = ["-C", repo, "-c", "core.quotePath=false", "clean", "-d", dryRun ? "-n" : "-f", "--"]
+        let result = runDetailed(args + paths)
+        guard result.status == 0 else { return ([], result.stderr.isEmpty ? "git clean failed" : result.stderr) }
+        return (parseClean(result.stdout), nil)
+    }
+
+    /// Merge `branch` into the current one, committing without an editor
+    /// when it goes through. git's summary, or its output on failure; a
+    /// merge that stops on conflicts fails and leaves them for `conflicts`.
+    static func merge(in repo: String, branch: String) -> (output: String, error: String?) {
+        guard !branch.hasPrefix("-") else { return ("", "not a branch: \(branch)") }
+        return runOperation(["-C", repo, "merge", "--no-edit", branch], name: "merge", in: repo)
+    }
+
+    /// Rebase the current branch onto `onto`. Like `merge`, conflicts
+    /// stop it and come back as the error.
+    static func rebase(in repo: String, onto: String) -> (output: String, error: String?) {
+        guard !onto.hasPrefix("-") else { return ("", "not a branch: \(onto)") }
+        return runOperation(["-C", repo, "rebase", onto], name: "rebase", in: repo)
+    }
+
+    /// `git merge --abort` or `git rebase --abort`: back to where the
+    /// branch was before. nil on success; git's stderr on failure.
+    static func abort(in repo: String, operation: Operation) -> String? {
+        runOperation(["-C", repo, operation.rawValue, "--abort"], name: operation.rawValue, in: repo).error
+    }
+
+    /// `git merge --continue` or `git rebase --continue` once every
+    /// conflict is staged, keeping git's messages (`core.editor=true`).
+    /// A rebase may stop again on the next commit.
+    static func resume(in repo: String, operation: Operation) -> (output: String, error: String?) {
+        runOperation(
+            ["-C", repo, "-c", "core.editor=true", operation.rawValue, "--continue"],
+            name: operation.rawValue, in: repo)
+    }
+
+    /// The merge or rebase in progress, from the files git keeps while
+    /// one is stopped; nil when there's none.
+    static func operation(in repo: String) -> Operation? {
+        func exists(_ name: String) -> Bool {
+            guard let path = run(["-C", repo, "rev-parse", "--git-path", name])?
+                .trimmingCharacters(in: .whitespacesAndNewlines), !path.isEmpty else { return false }
+            let absolute = path.hasPrefix("/") ? path : (repo as NSString).appendingPathComponent(path)
+            return FileManager.default.fileExists(atPath: absolute)
+        }
+        if exists("rebase-merge") || exists("rebase-apply") { return .rebase }
+        return exists("MERGE_HEAD") ? .merge : nil
+    }
+
+    /// Unmerged paths (only `path` when given) with their base, ours and
+    /// theirs from the index, sorted by path. `error` is git's stderr.
+    static func conflicts(in repo: String, path: String? = nil) -> (conflicts: [Conflict], error: String?) {
+        var args = ["-C", repo, "ls-files", "-u", "-z"]
+        if let path { args += ["--", path] }
+        let result = runDetailed(args)
+        guard result.status == 0 else { return ([], result.stderr.isEmpty ? "git ls-files failed" : result.stderr) }
+        let conflicts = parseUnmerged(result.stdout).map { entry -> Conflict in
+            var texts: [Int: String] = [:]
+            var binary = false
+            for (stage, object) in entry.stages {
+                guard let data = blob(in: repo, object: object), !data.prefix(8000).contains(0),
+                      let text = String(data: data, encoding: .utf8) else {
+                    binary = true
+                    continue
+                }
+                texts[stage] = text
+            }
+            if binary { texts = [:] }
+            return Conflict(
+                path: entry.path, base: texts[1], ours: texts[2], theirs: texts[3], binary: binary,
+                kind: conflictKind(stages: Set(entry.stages.keys)))
+        }
+        return (conflicts, nil)
+    }
+
+    /// Settle a conflicted `path` (repo-relative) with `content`: written
+    /// over the file, then staged. nil on success; the reason otherwise.
+    static func resolveConflict(in repo: String, path: String, content: String) -> String? {
+        let file = ((repo as NSString).appendingPathComponent(path) as NSString).standardizingPath
+        let root = (repo as NSString).standardizingPath
+        guard file.hasPrefix(root.hasSuffix("/") ? root : root + "/") else { return "outside the repository: \(path)" }
+        let listed = runDetailed(["-C", repo, "ls-files", "-u", "--", path])
+        guard listed.status == 0, !listed.stdout.isEmpty else { return "not in conflict: \(path)" }
+        do {
+            try Data(content.utf8).write(to: URL(fileURLWithPath: file), options: .atomic)
+        } catch {
+            return error.localizedDescription
+        }
+        return stage(in: repo, path: path)
     }
 
     // MARK: - parsing (pure, unit-tested)
 
+    /// `Name <email>` split in two; nil when either is missing.
+    static func parseIdentity(_ text: String) -> (name: String, email: String)? {
+        let trimmed = text.trimmingCharacters(in: .whitespaces)
+        guard trimmed.hasSuffix(">"), let open = trimmed.lastIndex(of: "<") else { return nil }
+        let name = trimmed[..<open].trimmingCharacters(in: .whitespaces)
+        let email = trimmed[trimmed.index(after: open)..<trimmed.index(before: trimmed.endIndex)]
+            .trimmingCharacters(in: .whitespaces)
+        guard !name.isEmpty, !email.isEmpty, !name.contains("<"), !email.contains(">") else { return nil }
+        return (name, email)
+    }
+
     static func parseStatus(_ output: String) -> (branch: String?, changes: [CodeChange]) {
         var branch: String?
         var changes: [CodeChange] = []
@@ -146,6 +675,177 @@ enum CodeGit {
         return (branch, changes)
     }
 
+    static func parseLog(_ output: String) -> [CodeCommit] {
+        output.split(separator: "\u{1E}").compactMap { record in
+            let fields = record.trimmingCharacters(in: .newlines)
+                .split(separator: "\u{1F}", omittingEmptySubsequences: false).map(String.init)
+            guard fields.count == 7, !fields[0].isEmpty else { return nil }
+            return CodeCommit(
+                hash: fields[0], parents: fields[1].split(separator: " ").map(String.init),
+                author: fields[2], email: fields[3], date: Int(fields[4]) ?? 0, subject: fields[6],
+                refs: fields[5].components(separatedBy: ", ").filter { !$0.isEmpty })
+        }
+    }
+
+    /// The upstream half of the `## main...origin/main [ahead 1, behind 2]`
+    /// header; nil when the branch has no upstream or HEAD is detached.
+    static func parseTracking(_ output: String) -> Tracking? {
+        guard let header = output.split(separator: "\n").first(where: { $0.hasPrefix("## ") }),
+              let dots = header.range(of: "...") else { return nil }
+        var rest = header[dots.upperBound...]
+        var counts: Substring = ""
+        if let open = rest.range(of: " ["), rest.hasSuffix("]") {
+            counts = rest[open.upperBound..<rest.index(before: rest.endIndex)]
+            rest = rest[..<open.lowerBound]
+        }
+        guard !rest.isEmpty else { return nil }
+        var tracking = Tracking(upstream: String(rest))
+        for part in counts.components(separatedBy: ", ") {
+            let words = part.split(separator: " ")
+            if words.first == "gone" { tracking.gone = true }
+            guard words.count == 2, let count = Int(words[1]) else { continue }
+            if words[0] == "ahead" { tracking.ahead = count }
+            if words[0] == "behind" { tracking.behind = count }
+        }
+        return tracking
+    }
+
+    /// `branch -r` lines as `refname:short<TAB>symref`; the symbolic ones
+    /// are `origin/HEAD` pointing at the default branch.
+    static func parseRemoteBranches(_ output: String) -> [String] {
+        output.split(separator: "\n").compactMap { line in
+            let fields = line.split(separator: "\t", omittingEmptySubsequences: false)
+            guard let name = fields.first, !name.isEmpty, fields.count < 2 || fields[1].isEmpty else { return nil }
+            return String(name)
+        }
+    }
+
+    /// `remote -v` lines, `name<TAB>url (fetch|push)`, in git's order.
+    static func parseRemotes(_ output: String) -> [Remote] {
+        var remotes: [Remote] = []
+        for line in output.split(separator: "\n") {
+            let fields = line.split(separator: "\t", maxSplits: 1)
+            guard fields.count == 2, let space = fields[1].lastIndex(of: " ") else { continue }
+            let name = String(fields[0])
+            let url = String(fields[1][..<space])
+            let isPush = fields[1][space...].contains("(push)")
+            if let index = remotes.firstIndex(where: { $0.name == name }) {
+                if isPush { remotes[index].pushURL = url } else { remotes[index].fetchURL = url }
+            } else {
+                remotes.append(Remote(name: name, fetchURL: url, pushURL: url))
+            }
+        }
+        return remotes
+    }
+
+    /// `origin/feature/x` → `feature/x`: the name after the remote's.
+    static func localName(ofRemote remoteBranch: String) -> String? {
+        guard let slash = remoteBranch.firstIndex(of: "/"), slash != remoteBranch.startIndex else { return nil }
+        return String(remoteBranch[remoteBranch.index(after: slash)...]).nilIfEmpty
+    }
+
+    /// What `git check-ref-format --branch` accepts, less the `@{-1}`
+    /// shorthand, and never with a leading dash that git would read as a flag.
+    static func isValidBranchName(_ name: String) -> Bool {
+        let forbidden = Set(" ~^:?*[\\".unicodeScalars)
+        guard !name.isEmpty, name != "@", !name.hasPrefix("-"), !name.hasPrefix("/"), !name.hasSuffix("/"),
+              !name.hasSuffix("."), !name.contains(".."), !name.contains("@{"), !name.contains("//"),
+              !name.unicodeScalars.contains(where: { $0.value < 0x20 || $0.value == 0x7f || forbidden.contains($0) })
+        else { return false }
+        return !name.split(separator: "/").contains { $0.hasPrefix(".") || $0.hasSuffix(".lock") }
+    }
+
+    /// `blame --porcelain` output: a `hash orig final [count]` header per
+    /// line, the commit's details only the first time it appears, then
+    /// the line itself after a tab.
+    static func parseBlame(_ output: String) -> [BlameLine] {
+        struct Details {
+            var author = ""
+            var email = ""
+            var date = 0
+            var summary = ""
+        }
+        var details: [String: Details] = [:]
+        var lines: [BlameLine] = []
+        var hash: String?
+        var number = 0
+        for line in output.split(separator: "\n", omittingEmptySubsequences: false) {
+            if line.hasPrefix("\t") {
+                guard let current = hash else { continue }
+                let info = details[current] ?? Details()
+                lines.append(BlameLine(
+                    line: number, hash: current, author: info.author, email: info.email, date: info.date,
+                    summary: info.summary, text: String(line.dropFirst())))
+                hash = nil
+                continue
+            }
+            guard let space = line.firstIndex(of: " ") else { continue }
+            let key = String(line[..<space])
+            let value = String(line[line.index(after: space)...])
+            if hash == nil {
+                // A header: the hash, then original and final line numbers.
+                let fields = value.split(separator: " ")
+                guard key.count >= 40, key.allSatisfy(\.isHexDigit), fields.count >= 2,
+                      let final = Int(fields[1]) else { continue }
+                hash = key
+                number = final
+                continue
+            }
+            guard let current = hash else { continue }
+            switch key {
+            case "author": details[current, default: Details()].author = value
+            case "author-mail":
+                let email = value.trimmingCharacters(in: CharacterSet(charactersIn: "<>"))
+                details[current, default: Details()].email = email
+            case "author-time": details[current, default: Details()].date = Int(value) ?? 0
+            case "summary": details[current, default: Details()].summary = value
+            default: break
+            }
+        }
+        return lines
+    }
+
+    /// `Would remove x` (dry run) or `Removing x` lines as paths; a
+    /// folder keeps its trailing slash.
+    static func parseClean(_ output: String) -> [String] {
+        output.split(separator: "\n").compactMap { line in
+            for prefix in ["Would remove ", "Removing "] where line.hasPrefix(prefix) {
+                let path = line.dropFirst(prefix.count)
+                guard path.hasPrefix("\""), path.hasSuffix("\""), path.count > 1 else { return String(path) }
+                return String(path.dropFirst().dropLast())
+            }
+            return nil
+        }
+    }
+
+    /// `ls-files -u -z` records (`mode object stage<TAB>path`, NUL-ended)
+    /// grouped by path: each path's object per stage (1 base, 2 ours,
+    /// 3 theirs), sorted by path.
+    static func parseUnmerged(_ output: String) -> [(path: String, stages: [Int: String])] {
+        var stages: [String: [Int: String]] = [:]
+        for record in output.split(separator: "\0") {
+            guard let tab = record.firstIndex(of: "\t") else { continue }
+            let fields = record[..<tab].split(separator: " ")
+            guard fields.count == 3, let stage = Int(fields[2]), (1...3).contains(stage) else { continue }
+            let path = String(record[record.index(after: tab)...])
+            stages[path, default: [:]][stage] = String(fields[1])
+        }
+        return stages.keys.sorted().map { ($0, stages[$0] ?? [:]) }
+    }
+
+    /// How `git status` names a conflict from the stages present.
+    static func conflictKind(stages: Set<Int>) -> String {
+        switch (stages.contains(1), stages.contains(2), stages.contains(3)) {
+        case (true, true, true): return "both-modified"
+        case (false, true, true): return "both-added"
+        case (true, false, true): return "deleted-by-us"
+        case (true, true, false): return "deleted-by-them"
+        case (false, true, false): return "added-by-us"
+        case (false, false, true): return "added-by-them"
+        default: return "both-deleted"
+        }
+    }
+
     /// "## main...origin/main" → "main"; "## No commits yet on main" → "main";
     /// detached → nil.
     private static func parseBranch(_ s: String) -> String? {
@@ -167,26 +867,69 @@ enum CodeGit {
         return result.stdout
     }
 
+    /// Runs a merge or rebase step: its output when it went through,
+    /// otherwise the error with the CONFLICT lines git prints to stdout.
+    private static func runOperation(
+        _ args: [String], name: String, in repo: String
+    ) -> (output: String, error: String?) {
+        defer { GitStatusCache.shared.invalidate(repo) }
+        let result = runDetailed(args, hooks: true)
+        let output = result.stdout.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard result.status == 0 else {
+            let error = [result.stderr, output].filter { !$0.isEmpty }.joined(separator: "\n")
+            return (output, error.isEmpty ? "git \(name) failed" : error)
+        }
+        return (output.isEmpty ? result.stderr : output, nil)
+    }
+
+    /// The raw bytes of `object`, which may not be text.
+    private static func blob(in repo: String, object: String) -> Data? {
+        guard case .success(let output) = ProcessRunner.run("/usr/bin/git", ["-C", repo, "cat-file", "blob", object]),
+              output.status == 0 else { return nil }
+        return output.stdout
+    }
+
+    /// `remote` is for commands that talk to a remote: nothing can type
+    /// at a terminal prompt from here, so git asks through `GitAskpass`
+    /// (failing as before when the app hasn't set it up) and ssh fails
+    /// instead of asking, and a slow network gets two minutes rather than
+    /// the usual limit, plus time for the user to answer a username and a
+    /// password prompt when there's someone to ask. `hooks` is for
+    /// commands that run hooks or an editor (commit, merge, rebase and
+    /// their continue), which may wait on the user, a pinentry or a test
+    /// suite: those get no limit at all.
     private static func runDetailed(
-        _ args: [String]
+        _ args: [String], remote: Bool = false, hooks: Bool = false, environment extra: [String: String] = [:]
     ) -> (status: Int32, stdout: String, stderr: String) {
-        let p = Process()
-        p.executableURL = URL(fileURLWithPath: "/usr/bin/git")
-        p.arguments = args
-        let out = Pipe()
-        let err = Pipe()
-        p.standardOutput = out
-        p.standardError = err
-        guard let _ = try? p.run() else { return (-1, "", "could not launch git") }
-        let outData = out.fileHandleForReading.readDataToEndOfFile()
-        let errData = err.fileHandleForReading.readDataToEndOfFile()
-        p.waitUntilExit()
-        return (
-            p.terminationStatus,
-            String(data: outData, encoding: .utf8) ?? "",
-            String(data: errData, encoding: .utf8) ?? ""
-                .trimmingCharacters(in: .whitespacesAndNewlines)
-        )
+        if remote {
+            return GitAskpass.shared.session { askpass in
+                let timeout = 120 + (askpass.isEmpty ? 0 : 2 * GitAskpass.timeout)
+                let environment = extra.merging(askpass) { _, new in new }
+                return runProcess(args, remote: true, timeout: timeout, environment: environment)
+            }
+        }
+        return runProcess(args, remote: false, timeout: hooks ? ProcessRunner.noTimeout : nil, environment: extra)
+    }
+
+    private static func runProcess(
+        _ args: [String], remote: Bool, timeout: TimeInterval?, environment extra: [String: String]
+    ) -> (status: Int32, stdout: String, stderr: String) {
+        var environment: [String: String]?
+        if remote || !extra.isEmpty {
+            var env = ProcessInfo.processInfo.environment
+            if remote {
+                env["GIT_TERMINAL_PROMPT"] = "0"
+                env["GIT_SSH_COMMAND"] = env["GIT_SSH_COMMAND"] ?? "ssh -o BatchMode=yes"
+            }
+            env.merge(extra) { _, new in new }
+            environment = env
+        }
+        switch ProcessRunner.run("/usr/bin/git", args, environment: environment, timeout: timeout) {
+        case .success(let output):
+            return (output.status, output.stdoutText, output.stderrText)
+        case .failure(let error):
+            return (-1, "", "git \(error)")
+        }
     }
 }
 
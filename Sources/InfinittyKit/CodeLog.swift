@@ -0,0 +1,101 @@
+import Foundation
+
+/// One commit from `git log`, for `git-log` and `git-show`.
+struct CodeCommit: Equatable {
+    let hash: String
+    let parents: [String]
+    let author: String
+    let email: String
+    /// Author date, seconds since 1970.
+    let date: Int
+    let subject: String
+    /// Branch and tag names pointing here, `HEAD -> main` style, as `%D`
+    /// prints them.
+    let refs: [String]
+
+    var wire: [String: Any] {
+        [
+            "hash": hash, "parents": parents, "author": author, "email": email, "date": date,
+            "subject": subject, "refs": refs,
+        ]
+    }
+}
+
+/// Who last changed one line, from `git blame`, for `git-blame`.
+struct BlameLine: Equatable {
+    /// 1-based, in the file as it is now.
+    let line: Int
+    /// All zeros for a line changed since the last commit.
+    let hash: String
+    let author: String
+    let email: String
+    /// Author date, seconds since 1970.
+    let date: Int
+    let summary: String
+    let text: String
+
+    var isCommitted: Bool { hash.contains { $0 != "0" } }
+
+    var wire: [String: Any] {
+        [
+            "line": line, "hash": hash, "author": author, "email": email, "date": date, "summary": summary,
+            "text": text, "committed": isCommitted,
+        ]
+    }
+}
+
+/// Where one commit sits in a drawn graph. Lanes are columns, numbered from
+/// the left; a lane carries one line of history down the page.
+struct CommitGraphRow: Equatable {
+    /// The commit's lane.
+    let column: Int
+    /// Lanes other than `column` that end here: branches merged in from
+    /// below, as git draws them, whose tip was this commit.
+    let joining: [Int]
+    /// The lane each parent continues in, first parent first.
+    let parents: [Int]
+    /// Lanes that pass this row untouched.
+    let passing: [Int]
+
+    var wire: [String: Any] {
+        ["column": column, "joining": joining, "parents": parents, "passing": passing]
+    }
+}
+
+/// Lane assignment for a page of commits in `git log` order (children
+/// before parents), the way `git log --graph` lays them out: a commit
+/// takes the lane that was waiting for it, its first parent inherits that
+/// lane, and other parents get the leftmost free one. A parent outside the
+/// page keeps its lane open to the bottom.
+enum CommitGraph {
+    static func rows(_ commits: [CodeCommit]) -> [CommitGraphRow] {
+        // The hash each lane is waiting for; nil is a free column.
+        var lanes: [String?] = []
+        var rows: [CommitGraphRow] = []
+        func freeLane() -> Int {
+            if let free = lanes.firstIndex(where: { $0 == nil }) { return free }
+            lanes.append(nil)
+            return lanes.count - 1
+        }
+        for commit in commits {
+            let waiting = lanes.indices.filter { lanes[$0] == commit.hash }
+            let column = waiting.first ?? freeLane()
+            for lane in waiting { lanes[lane] = nil }
+            let passing = lanes.indices.filter { lanes[$0] != nil }
+            var parents: [Int] = []
+            for (index, parent) in commit.parents.enumerated() {
+                if index > 0, let existing = lanes.firstIndex(where: { $0 == parent }) {
+                    parents.append(existing)
+                    continue
+                }
+                let lane = index == 0 ? column : freeLane()
+                lanes[lane] = parent
+                parents.append(lane)
+            }
+            rows.append(CommitGraphRow(
+                column: column, joining: Array(waiting.dropFirst()), parents: parents, passing: passing))
+            while let last = lanes.last, last == nil { lanes.removeLast() }
+        }
+        return rows
+    }
+}
@@ -14,17 +14,26 @@ enum CodeSearch {
     }
 
     /// Relative paths of candidate files under `root`. Calls back on the
-    /// main queue. Falls back to a recursive FileManager walk when rg is
-    /// unavailable (no .gitignore support in that case).
-    static func listFiles(root: String, completion: @escaping ([String]) -> Void) {
+    /// main queue, or not at all once `cancellation` fires. Falls back to a
+    /// recursive FileManager walk when rg is unavailable (no .gitignore
+    /// support in that case).
+    static func listFiles(
+        root: String, cancellation: CancellationToken? = nil,
+        completion: @escaping ([String]) -> Void
+    ) {
         DispatchQueue.global(qos: .userInitiated).async {
-            let files = listFilesSync(root: root)
-            DispatchQueue.main.async { completion(files) }
+            let files = listFilesSync(root: root, cancellation: cancellation)
+            guard cancellation?.isCancelled != true else { return }
+            DispatchQueue.main.async {
+                guard cancellation?.isCancelled != true else { return }
+                completion(files)
+            }
         }
     }
 
-    static func listFilesSync(root: String) -> [String] {
-        if let rg = ripgrepPath(), let out = runRg(rg, root: root) {
+    static func listFilesSync(root: String, cancellation: CancellationToken? = nil) -> [String] {
+        if cancellation?.isCancelled == true { return [] }
+        if let rg = ripgrepPath(), let out = runRg(rg, root: root, cancellation: cancellation) {
             // rg prints absolute paths when given an absolute root; relativize.
             let prefix = root.hasSuffix("/") ? root : root + "/"
             return out.split(separator: "\n").map { line in
@@ -33,7 +42,7 @@ enum CodeSearch {
                 return s
             }
         }
-        return walk(root: root)
+        return walk(root: root, cancellation: cancellation)
     }
 
     /// Case-insensitive substring match. Filename hits rank above
@@ -55,28 +64,83 @@ enum CodeSearch {
         return Array((nameHits + pathHits).prefix(limit))
     }
 
+    /// One line of a content search, `path` relative to the root.
+    struct Match: Equatable {
+        let path: String
+        let line: Int
+        let text: String
+    }
+
+    /// Lines under `root` matching the regex `pattern`, at most `limit`;
+    /// `literal` takes the pattern as plain text. rg when available (so
+    /// .gitignore holds); otherwise the file list is read directly, skipping
+    /// large and non-UTF-8 files. Blocking.
+    static func grep(
+        pattern: String, root: String, limit: Int = 500, literal: Bool = false, ignoreCase: Bool = false,
+        cancellation: CancellationToken? = nil
+    ) -> [Match] {
+        let prefix = root.hasSuffix("/") ? root : root + "/"
+        let flags = (literal ? ["--fixed-strings"] : []) + (ignoreCase ? ["--ignore-case"] : [])
+        if let rg = ripgrepPath(), case .success(let output) = ProcessRunner.run(
+            rg, ["--line-number", "--no-heading", "--null", "--color", "never", "--no-require-git",
+                 "--max-columns", "400"] + flags + ["-e", pattern, root],
+            cancellation: cancellation),
+           output.status == 0 || output.status == 1 {
+            // --null ends the path with NUL, so colons in names can't confuse the split.
+            var matches: [Match] = []
+            for record in String(decoding: output.stdout, as: UTF8.self).split(separator: "\n") {
+                guard let nul = record.firstIndex(of: "\0") else { continue }
+                let rest = record[record.index(after: nul)...]
+                guard let colon = rest.firstIndex(of: ":"), let line = Int(rest[..<colon]) else { continue }
+                var path = String(record[..<nul])
+                if path.hasPrefix(prefix) { path = String(path.dropFirst(prefix.count)) }
+                matches.append(Match(path: path, line: line, text: String(rest[rest.index(after: colon)...])))
+                if matches.count >= limit { break }
+            }
+            return matches
+        }
+        guard let regex = try? NSRegularExpression(
+            pattern: literal ? NSRegularExpression.escapedPattern(for: pattern) : pattern,
+            options: ignoreCase ? [.caseInsensitive] : []) else { return [] }
+        var matches: [Match] = []
+        for path in walk(root: root, limit: 20_000, cancellation: cancellation) {
+            if cancellation?.isCancelled == true { return [] }
+            let url = URL(fileURLWithPath: prefix + path)
+            guard let size = try? url.resourceValues(forKeys: [.fileSizeKey]).fileSize, size < 1 << 20,
+                  let data = try? Data(contentsOf: url), let text = String(data: data, encoding: .utf8)
+            else { continue }
+            for (index, line) in text.split(separator: "\n", omittingEmptySubsequences: false).enumerated() {
+                let line = String(line)
+                guard regex.firstMatch(in: line, range: NSRange(line.startIndex..., in: line)) != nil else { continue }
+                matches.append(Match(path: path, line: index + 1, text: line))
+                if matches.count >= limit { return matches }
+            }
+        }
+        return matches
+    }
+
     // MARK: - internals
 
-    private static func runRg(_ rg: String, root: String) -> String? {
-        let p = Process()
-        p.executableURL = URL(fileURLWithPath: rg)
-        p.arguments = ["--files", "--color", "never", "--no-require-git", root]
-        let out = Pipe()
-        p.standardOutput = out
-        p.standardError = Pipe()
-        guard let _ = try? p.run() else { return nil }
-        let data = out.fileHandleForReading.readDataToEndOfFile()
-        p.waitUntilExit()
-        guard p.terminationStatus == 0 || p.terminationStatus == 1 else { return nil }
-        return String(data: data, encoding: .utf8)
+    /// nil on failure or timeout; the caller falls back to a bounded walk.
+    private static func runRg(
+        _ rg: String, root: String, cancellation: CancellationToken?
+    ) -> String? {
+        guard case .success(let output) = ProcessRunner.run(
+            rg, ["--files", "--color", "never", "--no-require-git", root],
+            cancellation: cancellation),
+            output.status == 0 || output.status == 1 else { return nil }
+        return String(data: output.stdout, encoding: .utf8)
     }
 
-    private static func walk(root: String, limit: Int = 50_000) -> [String] {
+    private static func walk(
+        root: String, limit: Int = 50_000, cancellation: CancellationToken?
+    ) -> [String] {
         guard let enumerator = FileManager.default.enumerator(
             atPath: root) else { return [] }
         var out: [String] = []
         let rootURL = URL(fileURLWithPath: root)
         for case let path as String in enumerator {
+            if cancellation?.isCancelled == true { return [] }
             var isDir: ObjCBool = false
             FileManager.default.fileExists(
                 atPath: rootURL.appendingPathComponent(path).path, isDirectory: &isDir)
@@ -35,6 +35,11 @@ final class CodeFileNode {
         return loadedChildren!
     }
 
+    /// Drops the cached listing so the next `children` reads the disk.
+    func forgetChildren() {
+        loadedChildren = nil
+    }
+
     /// Directories first, then files; alphabetical within each group. Pure so
     /// it can be unit-tested without touching the disk.
     static func sort(_ nodes: [CodeFileNode]) -> [CodeFileNode] {
@@ -267,7 +272,7 @@ final class DiffCellView: NSTableCellView {
 /// changes preview as diffs and can be staged, committed and switched
 /// between branches in place. Follows the tracked session's live cwd
 /// (2s tracker poll, debounced).
-final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOutlineViewDelegate, NSSearchFieldDelegate, NSTableViewDataSource, NSTableViewDelegate {
+final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOutlineViewDelegate, NSSearchFieldDelegate, NSTableViewDataSource, NSTableViewDelegate, NSMenuDelegate {
 
     private enum Page: Int { case files = 0, changes = 1, chat = 2 }
     private enum DiffMode { case combined, split }
@@ -276,6 +281,7 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     private let panelKind: UtilityPanelKind?
     private weak var session: TerminalSession?
     private var cwdObserver: NSObjectProtocol?
+    private var gitObserver: NSObjectProtocol?
 
     // UI
     private let pageControl: CodeSegmentedBar
@@ -321,6 +327,10 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     private let fontSizeLabel = NSTextField(labelWithString: "")
     private let diffScroll = NSScrollView()
     private let diffTable = NSTableView()
+    /// Stage Hunk / Unstage Hunk for the right-clicked diff row.
+    private let diffMenu = NSMenu()
+    /// The hunk `diffMenu` was built for.
+    private var clickedHunk: (index: Int, staged: Bool)?
 
     // Files state
     private var page: Page = .files
@@ -329,12 +339,17 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     private var pendingReRoot: DispatchWorkItem?
     private var searchResults: [CodeFileNode]?
     private var fileListCache: [String]?
+    /// The rg listing behind `fileListCache` while it runs.
+    private var fileListing: CancellationToken?
     private var pendingSearch: DispatchWorkItem?
 
     // Changes state
     private var changes: [CodeChange] = []
     private var changesRepo: String?
     private var changesBranch: String?
+    private var changesTracking: CodeGit.Tracking?
+    /// A merge or rebase stopped partway in the repo.
+    private var changesOperation: CodeGit.Operation?
     private var notARepo = false
     private var statusError: String?
 
@@ -368,13 +383,13 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
             self.pageControl = CodeSegmentedBar(
                 labels: ["CHAT"], icons: ["bubble.left.and.bubble.right"],
                 fontSize: 10, fontWeight: .medium, squared: true, neutralSelection: true)
-        case .browser, .surface:
-            // Browser and agent-surface panes use their own controllers
-            // directly. Keep this fallback exhaustive so a future caller
-            // cannot crash while constructing the shared controller.
+        case .browser, .surface, .notes:
+            // Browser, agent-surface and Notes panes use their own
+            // controllers directly. Keep this fallback exhaustive so a future
+            // caller cannot crash while constructing the shared controller.
             self.pageControl = CodeSegmentedBar(
-                labels: [panelKind == .surface ? "SURFACE" : "BROWSER"],
-                icons: [panelKind == .surface ? "sparkles.rectangle.stack" : "globe"],
+                labels: [panelKind?.title.uppercased() ?? "BROWSER"],
+                icons: [panelKind?.symbol ?? "globe"],
                 fontSize: 10, fontWeight: .medium, squared: true, neutralSelection: true)
         case nil:
             self.pageControl = CodeSegmentedBar(
@@ -383,14 +398,36 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
                 fontSize: 10, fontWeight: .medium, squared: true, neutralSelection: true)
         }
         super.init(nibName: nil, bundle: nil)
+        // The status cache's watcher saw the work tree or .git move and
+        // settle: a commit, checkout or edit from a shell pane shows up
+        // without a page switch.
+        gitObserver = NotificationCenter.default.addObserver(
+            forName: GitStatusCache.didChangeNotification, object: GitStatusCache.shared, queue: .main
+        ) { [weak self] note in
+            guard let self, let repo = note.userInfo?[GitStatusCache.repoKey] as? String,
+                  repo == self.changesRepo
+            else { return }
+            self.refreshChanges()
+        }
     }
 
     required init?(coder: NSCoder) { fatalError("init(coder:) is not supported") }
 
     deinit {
         if let cwdObserver { NotificationCenter.default.removeObserver(cwdObserver) }
+        if let gitObserver { NotificationCenter.default.removeObserver(gitObserver) }
         pendingReRoot?.cancel()
         pendingSearch?.cancel()
+        fileListing?.cancel()
+    }
+
+    /// Stop listing work for a pane that is going away; a later search
+    /// starts a fresh listing.
+    func cancelBackgroundWork() {
+        pendingReRoot?.cancel()
+        pendingSearch?.cancel()
+        fileListing?.cancel()
+        fileListing = nil
     }
 
     // MARK: - layout
@@ -429,6 +466,14 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         commitButton.target = self
         commitButton.action = #selector(commitTapped(_:))
         commitButton.isEnabled = false
+        // Right-click amends; on the row too, since a disabled button
+        // (nothing staged) is exactly when amending a message is wanted.
+        let amendMenu = NSMenu()
+        let amend = amendMenu.addItem(
+            withTitle: "Amend Last Commit", action: #selector(amendTapped(_:)), keyEquivalent: "")
+        amend.target = self
+        commitButton.menu = amendMenu
+        commitRow.menu = amendMenu
         commitRow.addSubview(commitField)
         commitRow.addSubview(commitButton)
         commitRow.isHidden = true
@@ -561,6 +606,8 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         diffTable.backgroundColor = .clear
         diffTable.dataSource = self
         diffTable.delegate = self
+        diffMenu.delegate = self
+        diffTable.menu = diffMenu
         diffScroll.documentView = diffTable
         diffScroll.isHidden = true
 
@@ -827,7 +874,16 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     private func updateBranchFooter() {
         let inRepo = changesRepo != nil && page != .chat
         branchFooter.isHidden = !inRepo
-        branchButton.title = changesBranch ?? "detached HEAD"
+        var title = changesBranch ?? "detached HEAD"
+        if let tracking = changesTracking {
+            if tracking.ahead > 0 { title += " ↑\(tracking.ahead)" }
+            if tracking.behind > 0 { title += " ↓\(tracking.behind)" }
+        }
+        if let operation = changesOperation { title += operation == .merge ? " (merging)" : " (rebasing)" }
+        branchButton.title = title
+        branchButton.toolTip = changesTracking.map { tracking in
+            tracking.gone ? "\(tracking.upstream) is gone from the remote" : "Tracking \(tracking.upstream)"
+        }
         splitBottomToFooter?.isActive = inRepo
         splitBottomToContainer?.isActive = !inRepo
     }
@@ -864,6 +920,19 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         }
     }
 
+    /// Folds what's staged into the last commit, rewording it when the
+    /// field has a message.
+    @objc private func amendTapped(_ sender: Any?) {
+        let message = commitField.stringValue.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard let repo = changesRepo else { return }
+        let options = CodeGit.CommitOptions(amend: true)
+        runGitMutation("Could not amend the last commit", {
+            CodeGit.commit(in: repo, message: message, options: options)
+        }) { [weak self] in
+            self?.commitField.stringValue = ""
+        }
+    }
+
     private func updateCommitControls() {
         let hasStaged = changes.contains { $0.isStaged }
         let hasMessage = !commitField.stringValue
@@ -917,6 +986,12 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         }
         alert.messageText = message
         alert.informativeText = error
+        if CodeGit.isSigningError(error) {
+            alert.messageText = "Could not sign the commit"
+            alert.informativeText = error
+                + "\n\nCheck that your signing key is loaded. gpg needs a graphical pinentry "
+                + "(such as pinentry-mac) when it isn't run from a terminal."
+        }
         alert.addButton(withTitle: "OK")
         alert.runModal()
     }
@@ -934,12 +1009,272 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
             item.state = branch == changesBranch ? .on : .off
             menu.addItem(item)
         }
+        // Remote branches without a local one yet; picking one creates it,
+        // tracking the remote.
+        let remote = CodeGit.remoteBranches(in: repo).filter { name in
+            CodeGit.localName(ofRemote: name).map { !branches.contains($0) } ?? false
+        }
+        if !remote.isEmpty {
+            let submenu = NSMenu()
+            for name in remote {
+                let item = NSMenuItem(title: name, action: #selector(remoteBranchPicked(_:)), keyEquivalent: "")
+                item.target = self
+                item.representedObject = name
+                submenu.addItem(item)
+            }
+            let item = NSMenuItem(title: "Remote Branches", action: nil, keyEquivalent: "")
+            item.submenu = submenu
+            menu.addItem(item)
+        }
+        menu.addItem(.separator())
+        let create = NSMenuItem(title: "New Branch…", action: #selector(newBranchTapped(_:)), keyEquivalent: "")
+        create.target = self
+        menu.addItem(create)
+        if let current = changesBranch {
+            let rename = NSMenuItem(
+                title: "Rename \(current)…", action: #selector(renameBranchTapped(_:)), keyEquivalent: "")
+            rename.target = self
+            menu.addItem(rename)
+        }
+        let others = branches.filter { $0 != changesBranch }
+        if !others.isEmpty {
+            let submenu = NSMenu()
+            for name in others {
+                let item = NSMenuItem(title: name, action: #selector(deleteBranchPicked(_:)), keyEquivalent: "")
+                item.target = self
+                item.representedObject = name
+                submenu.addItem(item)
+            }
+            let item = NSMenuItem(title: "Delete Branch", action: nil, keyEquivalent: "")
+            item.submenu = submenu
+            menu.addItem(item)
+        }
+        if let operation = changesOperation {
+            menu.addItem(.separator())
+            let name = operation == .merge ? "Merge" : "Rebase"
+            for (title, action) in [
+                ("Continue \(name)", #selector(continueOperationTapped(_:))),
+                ("Abort \(name)", #selector(abortOperationTapped(_:))),
+            ] {
+                let item = NSMenuItem(title: title, action: action, keyEquivalent: "")
+                item.target = self
+                menu.addItem(item)
+            }
+        } else if !others.isEmpty, let current = changesBranch {
+            menu.addItem(.separator())
+            for (title, action) in [
+                ("Merge into \(current)", #selector(mergePicked(_:))),
+                ("Rebase \(current) onto", #selector(rebasePicked(_:))),
+            ] {
+                let submenu = NSMenu()
+                for name in others {
+                    let item = NSMenuItem(title: name, action: action, keyEquivalent: "")
+                    item.target = self
+                    item.representedObject = name
+                    submenu.addItem(item)
+                }
+                let item = NSMenuItem(title: title, action: nil, keyEquivalent: "")
+                item.submenu = submenu
+                menu.addItem(item)
+            }
+        }
+        menu.addItem(.separator())
+        let fetch = NSMenuItem(title: "Fetch", action: #selector(fetchTapped(_:)), keyEquivalent: "")
+        fetch.target = self
+        menu.addItem(fetch)
+        let behind = changesTracking?.behind ?? 0
+        let pull = NSMenuItem(
+            title: behind > 0 ? "Pull \(behind) Commit\(behind == 1 ? "" : "s")" : "Pull",
+            action: changesTracking == nil ? nil : #selector(pullTapped(_:)), keyEquivalent: "")
+        pull.target = self
+        menu.addItem(pull)
+        // A branch without an upstream is published: pushed and tracked.
+        let ahead = changesTracking?.ahead ?? 0
+        let push = NSMenuItem(
+            title: changesTracking == nil ? "Publish Branch"
+                : ahead > 0 ? "Push \(ahead) Commit\(ahead == 1 ? "" : "s")" : "Push",
+            action: changesBranch == nil ? nil : #selector(pushTapped(_:)), keyEquivalent: "")
+        push.target = self
+        menu.addItem(push)
+        if changesTracking != nil {
+            let force = NSMenuItem(
+                title: "Force Push (With Lease)", action: #selector(forcePushTapped(_:)), keyEquivalent: "")
+            force.target = self
+            force.keyEquivalentModifierMask = .option
+            force.isAlternate = true
+            menu.addItem(force)
+        }
         menu.popUp(
             positioning: nil,
             at: NSPoint(x: 0, y: branchButton.bounds.height + 4),
             in: branchButton)
     }
 
+    @objc private func fetchTapped(_ sender: Any?) {
+        guard let repo = changesRepo else { return }
+        runGitMutation("Could not fetch") { CodeGit.fetch(in: repo) }
+    }
+
+    /// Pulls the way the repo's `pull.rebase` says; a diverged branch
+    /// without that set comes back as git's error asking for one.
+    @objc private func pullTapped(_ sender: Any?) {
+        guard let repo = changesRepo else { return }
+        runGitMutation("Could not pull") { CodeGit.pull(in: repo).error } onSuccess: { [weak self] in
+            // Pulled files may be anywhere in the tree.
+            guard let self else { return }
+            let path = self.rootPath
+            self.rootPath = nil
+            self.reRoot(path)
+        }
+    }
+
+    @objc private func pushTapped(_ sender: Any?) {
+        guard let repo = changesRepo else { return }
+        runGitMutation("Could not push") { CodeGit.push(in: repo).error }
+    }
+
+    /// Overwrites the upstream only while it's where the last fetch left
+    /// it, so commits someone else pushed since aren't dropped unseen.
+    @objc private func forcePushTapped(_ sender: Any?) {
+        guard let repo = changesRepo else { return }
+        runGitMutation("Could not force push") {
+            CodeGit.push(in: repo, options: CodeGit.PushOptions(forceWithLease: true)).error
+        }
+    }
+
+    @objc private func mergePicked(_ sender: NSMenuItem) {
+        guard let branch = sender.representedObject as? String, let repo = changesRepo else { return }
+        runMergeStep("Could not merge \(branch)", in: repo) { CodeGit.merge(in: repo, branch: branch).error }
+    }
+
+    @objc private func rebasePicked(_ sender: NSMenuItem) {
+        guard let onto = sender.representedObject as? String, let repo = changesRepo else { return }
+        runMergeStep("Could not rebase onto \(onto)", in: repo) { CodeGit.rebase(in: repo, onto: onto).error }
+    }
+
+    @objc private func continueOperationTapped(_ sender: Any?) {
+        guard let repo = changesRepo, let operation = changesOperation else { return }
+        runGitMutation("Could not continue the \(operation.rawValue)") {
+            // Conflicts still unstaged are the usual reason; git says so.
+            CodeGit.resume(in: repo, operation: operation).error
+        } onSuccess: { [weak self] in
+            self?.reloadTreeAfterMerge()
+        }
+    }
+
+    @objc private func abortOperationTapped(_ sender: Any?) {
+        guard let repo = changesRepo, let operation = changesOperation else { return }
+        runGitMutation("Could not abort the \(operation.rawValue)") {
+            CodeGit.abort(in: repo, operation: operation)
+        } onSuccess: { [weak self] in
+            self?.reloadTreeAfterMerge()
+        }
+    }
+
+    /// A merge or rebase that stops on conflicts isn't a failure: the
+    /// footer shows it in progress and the Changes page lists the files.
+    private func runMergeStep(_ message: String, in repo: String, _ step: @escaping () -> String?) {
+        runGitMutation(message) {
+            let error = step()
+            return CodeGit.operation(in: repo) == nil ? error : nil
+        } onSuccess: { [weak self] in
+            self?.reloadTreeAfterMerge()
+        }
+    }
+
+    /// Merged or rewound files may be anywhere in the tree.
+    private func reloadTreeAfterMerge() {
+        let path = rootPath
+        rootPath = nil
+        reRoot(path)
+    }
+
+    /// A branch name from a small prompt, or nil when cancelled or left
+    /// empty.
+    private func promptBranchName(_ message: String, button: String, initial: String = "") -> String? {
+        let alert = NSAlert()
+        alert.messageText = message
+        let field = NSTextField(string: initial)
+        field.frame = NSRect(x: 0, y: 0, width: 260, height: 24)
+        alert.accessoryView = field
+        alert.addButton(withTitle: button)
+        alert.addButton(withTitle: "Cancel")
+        alert.window.initialFirstResponder = field
+        guard alert.runModal() == .alertFirstButtonReturn else { return nil }
+        let name = field.stringValue.trimmingCharacters(in: .whitespaces)
+        return name.isEmpty ? nil : name
+    }
+
+    /// Creates the branch at HEAD and switches to it.
+    @objc private func newBranchTapped(_ sender: Any?) {
+        guard let repo = changesRepo, let name = promptBranchName("New branch from HEAD", button: "Create")
+        else { return }
+        runGitMutation("Could not create \(name)") {
+            CodeGit.createBranch(in: repo, name: name, checkout: true)
+        }
+    }
+
+    @objc private func renameBranchTapped(_ sender: Any?) {
+        guard let repo = changesRepo, let current = changesBranch,
+              let name = promptBranchName("Rename \(current)", button: "Rename", initial: current),
+              name != current else { return }
+        runGitMutation("Could not rename \(current)") {
+            CodeGit.renameBranch(in: repo, from: current, to: name)
+        }
+    }
+
+    /// Asks first; a branch with unmerged commits gets a second chance to
+    /// go anyway rather than git's error alone.
+    @objc private func deleteBranchPicked(_ sender: NSMenuItem) {
+        guard let name = sender.representedObject as? String, let repo = changesRepo else { return }
+        let alert = NSAlert()
+        alert.messageText = "Delete branch \(name)?"
+        alert.alertStyle = .warning
+        alert.addButton(withTitle: "Delete")
+        alert.addButton(withTitle: "Cancel")
+        guard alert.runModal() == .alertFirstButtonReturn else { return }
+        DispatchQueue.global(qos: .userInitiated).async { [weak self] in
+            let error = CodeGit.deleteBranch(in: repo, name: name)
+            DispatchQueue.main.async {
+                guard let self else { return }
+                guard let error, error.contains("not fully merged") else {
+                    if let error {
+                        self.showGitError(error, message: "Could not delete \(name)") {
+                            self.runGitMutation("Could not delete \(name)") {
+                                CodeGit.deleteBranch(in: repo, name: name)
+                            }
+                        }
+                    } else {
+                        self.refreshChanges()
+                    }
+                    return
+                }
+                let force = NSAlert()
+                force.messageText = "\(name) has commits that aren't merged anywhere"
+                force.informativeText = "Deleting it loses them unless another ref points at them."
+                force.alertStyle = .critical
+                force.addButton(withTitle: "Delete Anyway")
+                force.addButton(withTitle: "Cancel")
+                guard force.runModal() == .alertFirstButtonReturn else { return }
+                self.runGitMutation("Could not delete \(name)") {
+                    CodeGit.deleteBranch(in: repo, name: name, force: true)
+                }
+            }
+        }
+    }
+
+    @objc private func remoteBranchPicked(_ sender: NSMenuItem) {
+        guard let remote = sender.representedObject as? String, let repo = changesRepo else { return }
+        runGitMutation("Could not check out \(remote)") {
+            CodeGit.checkoutRemote(in: repo, remoteBranch: remote).error
+        } onSuccess: { [weak self] in
+            guard let self else { return }
+            let path = self.rootPath
+            self.rootPath = nil
+            self.reRoot(path)
+        }
+    }
+
     @objc private func branchPicked(_ sender: NSMenuItem) {
         guard let branch = sender.representedObject as? String,
               branch != changesBranch, let repo = changesRepo else { return }
@@ -971,16 +1306,18 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         guard session !== self.session else { return }
         if let cwdObserver { NotificationCenter.default.removeObserver(cwdObserver) }
         self.session = session
-        if let tracker = session.processTracker {
-            cwdObserver = NotificationCenter.default.addObserver(
-                forName: ForegroundProcessTracker.cwdDidChangeNotification,
-                object: tracker, queue: .main
-            ) { [weak self] note in
-                guard let self,
-                      let path = note.userInfo?[ForegroundProcessTracker.cwdKey] as? String
-                else { return }
-                self.reRootDebounced(path)
-            }
+        // Posted by the session's process tracker (polled) and by the
+        // session itself when the shell reports OSC 7.
+        let tracker = session.processTracker
+        cwdObserver = NotificationCenter.default.addObserver(
+            forName: ForegroundProcessTracker.cwdDidChangeNotification,
+            object: nil, queue: .main
+        ) { [weak self, weak session, weak tracker] note in
+            guard let self, let sender = note.object as AnyObject?,
+                  sender === session || sender === tracker,
+                  let path = note.userInfo?[ForegroundProcessTracker.cwdKey] as? String
+            else { return }
+            self.reRootDebounced(path)
         }
         reRoot(session.currentDirectory())
     }
@@ -1019,6 +1356,8 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         rootPath = resolved
         root = CodeFileNode(url: URL(fileURLWithPath: resolved), isDirectory: true)
         fileListCache = nil
+        fileListing?.cancel()
+        fileListing = nil
         searchResults = nil
         searchField.stringValue = ""
         changes = []
@@ -1103,6 +1442,44 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         outlineView.reloadData()
     }
 
+    /// Selects `path` on the Files page, expanding the folders above it. A
+    /// path outside the tree re-roots it at the path's folder first.
+    @discardableResult
+    func reveal(path: String) -> Bool {
+        setPage(.files)
+        if searchResults != nil {
+            searchResults = nil
+            searchField.stringValue = ""
+            outlineView.reloadData()
+        }
+        var names = rootPath.flatMap { RevealPath.components(of: path, under: $0) }
+        if names == nil {
+            reRoot((path as NSString).deletingLastPathComponent)
+            names = [(path as NSString).lastPathComponent]
+        }
+        guard var node = root, let names else { return false }
+        var item: CodeFileNode?
+        for name in names {
+            var child = node.children.first { $0.url.lastPathComponent == name }
+            if child == nil {
+                // Created since the folder was listed.
+                node.forgetChildren()
+                outlineView.reloadItem(node === root ? nil : node, reloadChildren: true)
+                child = node.children.first { $0.url.lastPathComponent == name }
+            }
+            guard let child else { return false }
+            if let item { outlineView.expandItem(item) }
+            item = child
+            node = child
+        }
+        guard let item else { return names.isEmpty }
+        let row = outlineView.row(forItem: item)
+        guard row >= 0 else { return false }
+        outlineView.selectRowIndexes(IndexSet(integer: row), byExtendingSelection: false)
+        outlineView.scrollRowToVisible(row)
+        return true
+    }
+
     private func updateHeader() {
         switch page {
         case .files:
@@ -1152,10 +1529,15 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
             outlineView.reloadData()
             return
         }
-        guard let rootPath else { return }
+        // A listing already running for this root re-reads the field when
+        // it lands.
+        guard let rootPath, fileListing == nil else { return }
         let expected = rootPath
-        CodeSearch.listFiles(root: expected) { [weak self] files in
+        let listing = CancellationToken()
+        fileListing = listing
+        CodeSearch.listFiles(root: expected, cancellation: listing) { [weak self] files in
             guard let self, self.rootPath == expected else { return }
+            self.fileListing = nil
             self.fileListCache = files
             // Re-read the field: the user may have typed more while rg ran.
             let q = self.searchField.stringValue.trimmingCharacters(in: .whitespaces)
@@ -1188,16 +1570,17 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
                 }
                 return
             }
-            let status = CodeGit.status(in: repo)
+            let status = GitStatusCache.shared.status(in: repo)
+            let operation = CodeGit.operation(in: repo)
             DispatchQueue.main.async {
-                self?.applyChanges(status, repo: repo, root: expected)
+                self?.applyChanges(status, repo: repo, root: expected, operation: operation)
             }
         }
     }
 
     private func applyChanges(
         _ status: CodeGit.RepoStatus?,
-        repo: String?, root: String
+        repo: String?, root: String, operation: CodeGit.Operation? = nil
     ) {
         guard root == rootPath else { return } // user cd'd while git ran
         // Keep the selected row across the reload so staging feedback is
@@ -1206,6 +1589,8 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         changesRepo = repo
         changes = status?.changes ?? []
         changesBranch = status?.branch ?? nil
+        changesTracking = status?.tracking
+        changesOperation = operation
         notARepo = repo == nil
         statusError = status?.error
         selectedChange = nil
@@ -1369,6 +1754,108 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
         }
     }
 
+    /// The hunk under `row` of the table as shown, and whether it belongs
+    /// to the staged half of a change's diff.
+    private func hunk(atDiffRow row: Int) -> (index: Int, staged: Bool)? {
+        guard let change = selectedChange else { return nil }
+        let headers = diffMode == .combined
+            ? parsedDiff.map { $0.kind == .hunk ? $0.text : nil }
+            : splitDiff.map { $0.isHunk ? $0.oldText : nil }
+        return CodeDiff.hunk(at: row, headers: headers, stagedFirst: change.isStaged)
+    }
+
+    func menuNeedsUpdate(_ menu: NSMenu) {
+        menu.removeAllItems()
+        clickedHunk = showingDiff ? hunk(atDiffRow: diffTable.clickedRow) : nil
+        if let clickedHunk {
+            let item = NSMenuItem(
+                title: clickedHunk.staged ? "Unstage Hunk" : "Stage Hunk",
+                action: #selector(stageHunkTapped(_:)), keyEquivalent: "")
+            item.target = self
+            menu.addItem(item)
+        }
+        guard let change = selectedChange else { return }
+        var destructive: [NSMenuItem] = []
+        if change.y != " ", !change.isUntracked {
+            destructive.append(NSMenuItem(
+                title: "Discard Changes to \((change.path as NSString).lastPathComponent)…",
+                action: #selector(discardTapped(_:)), keyEquivalent: ""))
+        }
+        if changes.contains(where: \.isUntracked) {
+            destructive.append(NSMenuItem(
+                title: "Delete Untracked Files…", action: #selector(cleanTapped(_:)), keyEquivalent: ""))
+        }
+        if !destructive.isEmpty, !menu.items.isEmpty { menu.addItem(.separator()) }
+        for item in destructive {
+            item.target = self
+            menu.addItem(item)
+        }
+    }
+
+    /// Puts the selected file back as staged (or committed); asks first,
+    /// since the edits are gone for good.
+    @objc private func discardTapped(_ sender: Any?) {
+        guard let change = selectedChange, let repo = changesRepo else { return }
+        let alert = NSAlert()
+        alert.messageText = "Discard changes to \(change.path)?"
+        alert.informativeText = change.isStaged
+            ? "Unstaged edits are lost; what's staged stays."
+            : "The edits are lost; this can't be undone."
+        alert.alertStyle = .warning
+        alert.addButton(withTitle: "Discard")
+        alert.addButton(withTitle: "Cancel")
+        guard alert.runModal() == .alertFirstButtonReturn else { return }
+        runGitMutation("Could not discard \(change.path)") { CodeGit.discard(in: repo, path: change.path) }
+    }
+
+    /// Lists what `git clean` would delete (a dry run) and deletes it only
+    /// once that list is confirmed.
+    @objc private func cleanTapped(_ sender: Any?) {
+        guard let repo = changesRepo else { return }
+        DispatchQueue.global(qos: .userInitiated).async { [weak self] in
+            let preview = CodeGit.clean(in: repo, dryRun: true)
+            DispatchQueue.main.async {
+                guard let self else { return }
+                if let error = preview.error {
+                    self.showGitError(error, message: "Could not list untracked files") {}
+                    return
+                }
+                guard !preview.paths.isEmpty else { return }
+                let alert = NSAlert()
+                let count = preview.paths.count
+                alert.messageText = "Delete \(count) untracked item\(count == 1 ? "" : "s")?"
+                let shown = preview.paths.prefix(15).joined(separator: "\n")
+                alert.informativeText = shown + (count > 15 ? "\n…and \(count - 15) more" : "")
+                    + "\n\nIgnored files are kept. This can't be undone."
+                alert.alertStyle = .critical
+                alert.addButton(withTitle: "Delete")
+                alert.addButton(withTitle: "Cancel")
+                guard alert.runModal() == .alertFirstButtonReturn else { return }
+                // Only what was listed, even if more appeared since.
+                self.runGitMutation("Could not delete untracked files") {
+                    CodeGit.clean(in: repo, paths: preview.paths, dryRun: false).error
+                } onSuccess: { [weak self] in
+                    self?.reloadTreeAfterMerge()
+                }
+            }
+        }
+    }
+
+    /// Stages (or unstages) one hunk the way `git add -p` would: the
+    /// file's diff is read again so a change made since the preview
+    /// can't be staged blind.
+    @objc private func stageHunkTapped(_ sender: Any?) {
+        guard let (index, staged) = clickedHunk, let change = selectedChange, let repo = changesRepo else { return }
+        runGitMutation("Could not \(staged ? "unstage" : "stage") the hunk in \(change.path)") {
+            let diff = CodeGit.fileDiff(in: repo, path: change.path, staged: staged)
+            if let error = diff.error { return error }
+            guard let patch = CodeDiff.partialPatch(diff.text, reverse: staged, selecting: { hunk, _ in
+                hunk == index
+            }) else { return "The hunk is no longer in the diff." }
+            return CodeGit.applyToIndex(in: repo, patch: patch, reverse: staged)
+        }
+    }
+
     private func diffTint(for kind: DiffLine.Kind?) -> NSColor? {
         switch kind {
         case .add: return Self.diffAddColor.withAlphaComponent(0.13)
@@ -1486,33 +1973,30 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     }
 
     private func loadPreview(for url: URL) {
-        let path = url.path
-        guard let handle = FileHandle(forReadingAtPath: path) else {
-            showPlaceholder("Cannot read \(url.lastPathComponent)")
+        let name = url.lastPathComponent
+        let contents: FileText
+        switch FileContents.text(of: url, limit: Self.maxPreviewBytes) {
+        case .success(let text):
+            contents = text
+        case .failure(.tooLarge(let size, _)):
+            showPlaceholder("\(name) is too large to preview (\(size / 1_000_000) MB)")
             return
-        }
-        defer { try? handle.close() }
-        guard let size = try? handle.seekToEnd(), size > 0 else {
-            clearPreview()
+        case .failure(.binary):
+            showPlaceholder("\(name): binary file")
             return
-        }
-        guard size <= Self.maxPreviewBytes else {
-            showPlaceholder("\(url.lastPathComponent) is too large to preview (\(size / 1_000_000) MB)")
+        case .failure(.notUTF8):
+            showPlaceholder("\(name): not UTF-8 text")
             return
-        }
-        try? handle.seek(toOffset: 0)
-        let data = handle.readDataToEndOfFile()
-        // NUL in the first chunk ⇒ treat as binary.
-        if data.prefix(8192).contains(0) {
-            showPlaceholder("\(url.lastPathComponent): binary file")
+        case .failure:
+            showPlaceholder("Cannot read \(name)")
             return
         }
-        guard let string = String(data: data, encoding: .utf8) else {
-            showPlaceholder("\(url.lastPathComponent): not UTF-8 text")
+        guard contents.size > 0 else {
+            clearPreview()
             return
         }
         previewedURL = url
-        previewRaw = string
+        previewRaw = contents.text
         showingDiff = false
         updateDiffChrome()
         previewTitle.stringValue = url.lastPathComponent
@@ -1754,6 +2238,9 @@ final class CodeViewController: NSViewController, NSOutlineViewDataSource, NSOut
     func selectRowForTesting(_ row: Int) {
         outlineView.selectRowIndexes(IndexSet(integer: row), byExtendingSelection: false)
     }
+    var selectedPathForTesting: String? {
+        (outlineView.item(atRow: outlineView.selectedRow) as? CodeFileNode)?.url.path
+    }
     func stageSelectedForTesting() { stageTapped(nil) }
     func stageAllForTesting() { stageAllTapped(nil) }
     var commitButtonEnabledForTesting: Bool { commitButton.isEnabled }
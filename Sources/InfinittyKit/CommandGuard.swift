@@ -0,0 +1,300 @@
+import Foundation
+
+/// Something a typed command line does that's hard to take back.
+enum CommandRisk: String, CaseIterable {
+    /// `rm -r`, or `find … -delete`.
+    case recursiveDelete = "recursive-delete"
+    /// `chmod`, `chown` or `chgrp` with `-R`.
+    case recursivePermissions = "recursive-permissions"
+    /// `git push` with `--force`, `-f` or a `+refspec`.
+    case forcePush = "force-push"
+    /// SQL `DROP TABLE`, `DROP DATABASE`, `DROP SCHEMA` or `TRUNCATE TABLE`.
+    case dropTable = "drop-table"
+    /// `mkfs`, or `dd` writing to a device.
+    case diskWrite = "disk-write"
+
+    var explanation: String {
+        switch self {
+        case .recursiveDelete: return "It deletes files and folders recursively."
+        case .recursivePermissions: return "It changes permissions or owners through whole folders."
+        case .forcePush: return "It force-pushes, which can drop commits others have."
+        case .dropTable: return "It drops or empties database tables."
+        case .diskWrite: return "It writes straight to a disk."
+        }
+    }
+}
+
+/// One path operand of a risky command, as the shell would see it.
+struct CommandTarget: Equatable {
+    /// The word as typed.
+    let argument: String
+    /// What it names once `~` and globs are expanded, absolute; empty when
+    /// nothing matches.
+    let matches: [String]
+    /// Files and folders at and under `matches`.
+    let count: Int
+
+    var wire: [String: Any] {
+        ["argument": argument, "matches": Array(matches.prefix(50)), "matchCount": matches.count, "count": count]
+    }
+}
+
+/// What a command line would touch, for the sheet that holds it before
+/// Enter (`confirm-dangerous-commands`) and for `analyze-command`.
+struct CommandAnalysis: Equatable {
+    let command: String
+    let directory: String
+    let risks: [CommandRisk]
+    let targets: [CommandTarget]
+    /// Counting stopped at `CommandGuard.countLimit`.
+    let truncated: Bool
+
+    var affected: Int { targets.reduce(0) { $0 + $1.count } }
+
+    var wire: [String: Any] {
+        [
+            "command": command, "directory": directory, "risks": risks.map(\.rawValue),
+            "targets": targets.map(\.wire), "affected": affected, "truncated": truncated,
+        ]
+    }
+}
+
+/// Reads a command line the way a POSIX shell splits it — quotes,
+/// backslashes, `;`, `&&`, `|` — and says which of `CommandRisk` it runs,
+/// with its path operands expanded and counted. Only what's on the line is
+/// judged: an alias or function that hides `rm -rf` gets through.
+enum CommandGuard {
+    /// Files counted under all targets before giving up.
+    static let countLimit = 100_000
+
+    /// One shell word. `pattern` is set when it has unquoted glob or brace
+    /// characters: the word with its quoted parts escaped, for
+    /// `GlobExpansion`.
+    struct Word: Equatable {
+        var text = ""
+        var pattern: String?
+    }
+
+    private static let sql = try! NSRegularExpression(
+        pattern: #"\b(drop\s+(table|database|schema)|truncate\s+table)\b"#, options: .caseInsensitive)
+
+    /// Programs that run the command after them.
+    private static let wrappers: Set<String> = ["sudo", "doas", "command", "nohup", "time", "exec", "nice", "env"]
+
+    static func risks(in command: String) -> [CommandRisk] {
+        var found = Set<CommandRisk>()
+        for segment in words(command) {
+            found.formUnion(check(segment).risks)
+        }
+        if sql.firstMatch(in: command, range: NSRange(command.startIndex..., in: command)) != nil {
+            found.insert(.dropTable)
+        }
+        return CommandRisk.allCases.filter(found.contains)
+    }
+
+    /// Nil when the line has none of `CommandRisk`. Walks the file system
+    /// under each target, so call it off the main thread.
+    static func analyze(_ command: String, in directory: String, limit: Int = countLimit) -> CommandAnalysis? {
+        let risks = risks(in: command)
+        guard !risks.isEmpty else { return nil }
+        var targets: [CommandTarget] = []
+        var budget = limit
+        for segment in words(command) {
+            for operand in check(segment).operands {
+                let matches = expand(operand, in: directory)
+                var count = 0
+                for path in matches where budget > 0 {
+                    let counted = countItems(at: path, limit: budget)
+                    count += counted
+                    budget -= counted
+                }
+                targets.append(CommandTarget(argument: operand.text, matches: matches, count: count))
+            }
+        }
+        return CommandAnalysis(
+            command: command, directory: directory, risks: risks, targets: targets, truncated: budget <= 0)
+    }
+
+    /// The line's simple commands, each as its words. Separators (`;`,
+    /// `&`, `|`, their doubles, newlines, parentheses) end a command;
+    /// `#` at the start of a word ends the line.
+    static func words(_ line: String) -> [[Word]] {
+        var segments: [[Word]] = []
+        var current: [Word] = []
+        var word = Word()
+        var started = false
+        var quote: Character?
+        var escaped = false
+        var escapedInDoubleQuotes = false
+        var globbing = false
+        var leadingTilde = false
+        var escapedPattern = ""
+
+        func finishWord() {
+            guard started else { return }
+            if globbing { word.pattern = escapedPattern }
+            if leadingTilde, word.text == "~" || word.text.hasPrefix("~/") {
+                word.text = NSHomeDirectory() + word.text.dropFirst()
+                word.pattern = word.pattern.map { NSHomeDirectory() + $0.dropFirst() }
+            }
+            current.append(word)
+            word = Word()
+            started = false
+            globbing = false
+            leadingTilde = false
+            escapedPattern = ""
+        }
+        func finishSegment() {
+            finishWord()
+            if !current.isEmpty { segments.append(current) }
+            current = []
+        }
+        func append(_ c: Character, quoted: Bool) {
+            if word.text.isEmpty, !quoted, c == "~" { leadingTilde = true }
+            started = true
+            word.text.append(c)
+            if quoted, "*?[]{},\\".contains(c) { escapedPattern.append("\\") }
+            escapedPattern.append(c)
+            if !quoted, "*?[{".contains(c) { globbing = true }
+        }
+
+        for c in line {
+            if escaped {
+                escaped = false
+                // Inside double quotes a backslash only escapes $ ` " \ and newlines.
+                if escapedInDoubleQuotes, !"$`\"\\\n".contains(c) { append("\\", quoted: true) }
+                if c != "\n" { append(c, quoted: true) }
+                continue
+            }
+            switch quote {
+            case "'":
+                if c == "'" { quote = nil } else { append(c, quoted: true) }
+                continue
+            case "\"":
+                if c == "\"" {
+                    quote = nil
+                } else if c == "\\" {
+                    escaped = true
+                    escapedInDoubleQuotes = true
+                } else {
+                    append(c, quoted: true)
+                }
+                continue
+            default:
+                break
+            }
+            switch c {
+            case "\\":
+                escaped = true
+                escapedInDoubleQuotes = false
+            case "'", "\"":
+                quote = c
+                started = true
+            case " ", "\t": finishWord()
+            case ";", "&", "|", "\n", "\r", "(", ")": finishSegment()
+            case "#" where !started:
+                finishSegment()
+                return segments
+            default: append(c, quoted: false)
+            }
+        }
+        finishSegment()
+        return segments
+    }
+
+    /// Absolute paths `word` names from `directory`: what its braces and
+    /// globs expand to, or itself when it exists. Quoted glob characters
+    /// match literally.
+    static func expand(_ word: Word, in directory: String) -> [String] {
+        guard let pattern = word.pattern else {
+            let path = word.text.hasPrefix("/") ? word.text : (directory as NSString).appendingPathComponent(word.text)
+            let standardized = (path as NSString).standardizingPath
+            return FileManager.default.fileExists(atPath: standardized) ? [standardized] : []
+        }
+        return GlobExpansion.expand(pattern, in: directory).matches
+    }
+
+    // MARK: - internals
+
+    /// The risks of one simple command and the words it'd act on.
+    private static func check(_ segment: [Word]) -> (risks: Set<CommandRisk>, operands: [Word]) {
+        var words = segment[...]
+        // Leading assignments and wrappers (with their flags) aren't the command.
+        while let first = words.first {
+            let text = first.text
+            if let equals = text.firstIndex(of: "="), equals != text.startIndex,
+               text[..<equals].allSatisfy({ $0 == "_" || $0.isLetter || $0.isNumber }) {
+                words = words.dropFirst()
+            } else if wrappers.contains(text) {
+                words = words.dropFirst()
+                while let flag = words.first, flag.text.hasPrefix("-") { words = words.dropFirst() }
+            } else {
+                break
+            }
+        }
+        guard let program = words.first.map({ ($0.text as NSString).lastPathComponent }) else { return ([], []) }
+        let args = Array(words.dropFirst())
+        let (flags, operands) = split(args)
+        func shortFlag(_ letter: Character) -> Bool {
+            flags.contains { !$0.hasPrefix("--") && $0.dropFirst().contains(letter) }
+        }
+
+        switch program {
+        case "rm":
+            guard shortFlag("r") || shortFlag("R") || flags.contains("--recursive") else { return ([], []) }
+            return ([.recursiveDelete], operands)
+        case "chmod", "chown", "chgrp":
+            guard shortFlag("R") || flags.contains("--recursive") else { return ([], []) }
+            return ([.recursivePermissions], Array(operands.dropFirst()))
+        case "find":
+            guard args.contains(where: { $0.text == "-delete" }) else { return ([], []) }
+            let roots = args.prefix { !$0.text.hasPrefix("-") && $0.text != "!" }
+            return ([.recursiveDelete], roots.isEmpty ? [Word(text: ".")] : Array(roots))
+        case "git":
+            var rest = args[...]
+            while let first = rest.first, first.text.hasPrefix("-") {
+                rest = rest.dropFirst(["-C", "-c"].contains(first.text) ? 2 : 1)
+            }
+            guard rest.first?.text == "push" else { return ([], []) }
+            let pushArgs = rest.dropFirst().map(\.text)
+            let forced = pushArgs.contains { arg in
+                arg == "--force" || arg.hasPrefix("--force-with-lease") || arg.hasPrefix("+")
+                    || (arg.hasPrefix("-") && !arg.hasPrefix("--") && arg.contains("f"))
+            }
+            return (forced ? [.forcePush] : [], [])
+        case "dd":
+            return (args.contains { $0.text.hasPrefix("of=/dev/") } ? [.diskWrite] : [], [])
+        default:
+            return (program == "mkfs" || program.hasPrefix("mkfs.") ? [.diskWrite] : [], [])
+        }
+    }
+
+    /// Flags (up to `--`) and the other words.
+    private static func split(_ args: [Word]) -> (flags: [String], operands: [Word]) {
+        var flags: [String] = []
+        var operands: [Word] = []
+        var ended = false
+        for arg in args {
+            if !ended, arg.text == "--" {
+                ended = true
+            } else if !ended, arg.text.hasPrefix("-"), arg.text.count > 1 {
+                flags.append(arg.text)
+            } else {
+                operands.append(arg)
+            }
+        }
+        return (flags, operands)
+    }
+
+    /// `path` itself plus everything under it, not following symlinks,
+    /// at most `limit`.
+    private static func countItems(at path: String, limit: Int) -> Int {
+        guard limit > 0 else { return 0 }
+        let attributes = try? FileManager.default.attributesOfItem(atPath: path)
+        guard attributes?[.type] as? FileAttributeType == .typeDirectory,
+              let enumerator = FileManager.default.enumerator(atPath: path) else { return 1 }
+        var count = 1
+        while count < limit, enumerator.nextObject() != nil { count += 1 }
+        return count
+    }
+}
@@ -0,0 +1,318 @@
+import Foundation
+
+/// One finished shell command, as seen through OSC 133 markers.
+struct CommandRecord: Codable, Equatable {
+    let command: String
+    let directory: String?
+    let exitCode: Int
+    let started: Date
+    let duration: TimeInterval
+    /// The pane it ran in; ids restart with the app, so this tells
+    /// sessions of one run apart. Absent in records from older versions.
+    var pane: Int?
+
+    /// The program a command line runs: leading `VAR=value` assignments and
+    /// wrappers like `sudo` or `time` skipped, path stripped. `git push -f`
+    /// and `/usr/bin/git log` both count as `git`.
+    var program: String { Self.program(of: command) }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "command": command, "exitCode": exitCode,
+            "started": ISO8601DateFormatter().string(from: started),
+            "duration": (duration * 1000).rounded() / 1000,
+        ]
+        if let directory { object["cwd"] = directory }
+        if let pane { object["pane"] = pane }
+        return object
+    }
+
+    static func program(of command: String) -> String {
+        let wrappers: Set<Substring> = ["sudo", "time", "env", "command", "nohup", "exec", "nice", "caffeinate"]
+        let words = command.split(whereSeparator: \.isWhitespace)
+        let first = words.first { word in
+            !wrappers.contains(word) && !word.hasPrefix("-") && !word.contains("=")
+        }
+        guard let first else { return words.first.map(String.init) ?? "" }
+        return first.split(separator: "/").last.map(String.init) ?? String(first)
+    }
+}
+
+/// Local, append-only log of finished commands: one JSON object per line
+/// under Application Support. Nothing here leaves the machine; the file is
+/// owner-only and trimmed to the newest `maxRecords` once it grows past
+/// that by a margin, so it never needs a database.
+final class CommandHistory {
+    static let shared = CommandHistory(url: CommandHistory.defaultURL)
+    static let maxRecords = 50_000
+
+    static var defaultURL: URL { AppSupport.url("command-history.jsonl") }
+
+    let url: URL
+    private let queue = DispatchQueue(label: "infinitty.command-history", qos: .utility)
+    /// Lines in the file, counted on the first append.
+    private var lineCount: Int?
+
+    init(url: URL) {
+        self.url = url
+    }
+
+    func record(_ record: CommandRecord) {
+        queue.async { self.append(record) }
+    }
+
+    /// Every stored record, oldest first. Waits for pending appends.
+    func records() -> [CommandRecord] {
+        queue.sync { readAll() }
+    }
+
+    private func append(_ record: CommandRecord) {
+        let encoder = JSONEncoder()
+        encoder.dateEncodingStrategy = .secondsSince1970
+        guard let data = try? encoder.encode(record) else { return }
+        let fm = FileManager.default
+        if !fm.fileExists(atPath: url.path) {
+            try? fm.createDirectory(at: url.deletingLastPathComponent(), withIntermediateDirectories: true)
+            fm.createFile(atPath: url.path, contents: nil, attributes: [.posixPermissions: 0o600])
+            lineCount = 0
+        }
+        guard let handle = try? FileHandle(forWritingTo: url) else { return }
+        defer { try? handle.close() }
+        _ = try? handle.seekToEnd()
+        try? handle.write(contentsOf: data + [0x0A])
+
+        let count = (lineCount ?? readAll().count) + 1
+        lineCount = count
+        if count > Self.maxRecords + Self.maxRecords / 10 {
+            let kept = readAll().suffix(Self.maxRecords)
+            let lines = kept.compactMap { try? encoder.encode($0) }
+            let body = lines.reduce(into: Data()) { $0 += $1 + [0x0A] }
+            AppSupport.write(body, to: url, permissions: 0o600)
+            lineCount = lines.count
+        }
+    }
+
+    private func readAll() -> [CommandRecord] {
+        guard let data = try? Data(contentsOf: url) else { return [] }
+        let decoder = JSONDecoder()
+        decoder.dateDecodingStrategy = .secondsSince1970
+        return data.split(separator: 0x0A).compactMap { try? decoder.decode(CommandRecord.self, from: $0) }
+    }
+}
+
+/// A Ctrl-R style search over every pane's history: a fuzzy query plus
+/// optional filters. Without a query, newest first.
+struct HistoryQuery: Equatable {
+    var text = ""
+    /// Only commands run in this directory or below it.
+    var directory: String?
+    var pane: Int?
+    /// true for failures only, false for successes only.
+    var failed: Bool?
+    var since: Date?
+    var limit = 50
+    /// Each command line once, at its latest run.
+    var unique = true
+
+    /// `history-search`'s argument: the query itself, or JSON {"query",
+    /// "cwd", "pane", "failed", "since" (as history-stats' range), "limit",
+    /// "unique"}.
+    static func parse(_ text: String, now: Date = Date()) -> Result<HistoryQuery, HistorySearchError> {
+        let trimmed = text.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard trimmed.hasPrefix("{") else { return .success(HistoryQuery(text: trimmed)) }
+        guard let object = (try? JSONSerialization.jsonObject(with: Data(trimmed.utf8))) as? [String: Any] else {
+            return .failure(.invalid("expected a query or a JSON object"))
+        }
+        var query = HistoryQuery(text: (object["query"] as? String ?? "").trimmingCharacters(in: .whitespaces))
+        if let cwd = object["cwd"] as? String, !cwd.isEmpty {
+            query.directory = (cwd as NSString).expandingTildeInPath
+        }
+        query.pane = object["pane"] as? Int
+        query.failed = object["failed"] as? Bool
+        if let since = object["since"] as? String {
+            guard let range = HistoryStats.range(since, now: now) else {
+                return .failure(.invalid("since must be today, all or <days>d"))
+            }
+            query.since = range.start == .distantPast ? nil : range.start
+        }
+        if let limit = object["limit"] {
+            guard let limit = limit as? Int, (1...1000).contains(limit) else {
+                return .failure(.invalid("limit must be 1-1000"))
+            }
+            query.limit = limit
+        }
+        if let unique = object["unique"] as? Bool { query.unique = unique }
+        return .success(query)
+    }
+
+    /// Matching records, best first: by fuzzy score, then the most recent.
+    func search(_ records: [CommandRecord]) -> [(record: CommandRecord, score: Int)] {
+        let directoryPrefix = directory.map { $0.hasSuffix("/") ? $0 : $0 + "/" }
+        var seen = Set<String>()
+        var hits: [(record: CommandRecord, score: Int)] = []
+        for record in records.reversed() {
+            if let pane, record.pane != pane { continue }
+            if let failed, (record.exitCode != 0) != failed { continue }
+            if let since, record.started < since { continue }
+            if let directory, let directoryPrefix {
+                guard let ran = record.directory, ran == directory || ran.hasPrefix(directoryPrefix) else { continue }
+            }
+            if unique, !seen.insert(record.command).inserted { continue }
+            guard let score = Self.score(text, in: record.command) else { continue }
+            hits.append((record, score))
+        }
+        // Stable: equal scores keep newest first.
+        let ranked = text.isEmpty ? hits : hits.enumerated()
+            .sorted { a, b in
+                a.element.score != b.element.score ? a.element.score > b.element.score : a.offset < b.offset
+            }
+            .map(\.element)
+        return Array(ranked.prefix(limit))
+    }
+
+    /// Fuzzy match of `query`'s characters, in order and ignoring case, in
+    /// `text`; nil when they aren't all there. An exact substring beats a
+    /// scattered match, and characters at word starts or next to each
+    /// other score more. Words of the query may match in any order.
+    static func score(_ query: String, in text: String) -> Int? {
+        let words = query.lowercased().split(whereSeparator: \.isWhitespace)
+        guard !words.isEmpty else { return 0 }
+        let haystack = Array(text.lowercased())
+        var total = 0
+        for word in words {
+            guard let score = wordScore(Array(word), in: haystack) else { return nil }
+            total += score
+        }
+        // Shorter lines are likelier what was meant.
+        return total * 4 - min(haystack.count, 200) / 10
+    }
+
+    private static func wordScore(_ word: [Character], in text: [Character]) -> Int? {
+        if let at = firstIndex(of: word, in: text) {
+            let boundary = at == 0 || !(text[at - 1].isLetter || text[at - 1].isNumber)
+            return word.count * 10 + (boundary ? 15 : 0) + (at == 0 ? 10 : 0)
+        }
+        var score = 0
+        var next = 0
+        var previous: Int?
+        for character in word {
+            guard let at = text[next...].firstIndex(of: character) else { return nil }
+            let boundary = at == 0 || !(text[at - 1].isLetter || text[at - 1].isNumber)
+            score += 2 + (boundary ? 4 : 0) + (previous == at - 1 ? 3 : 0)
+            previous = at
+            next = at + 1
+        }
+        return score
+    }
+
+    private static func firstIndex(of word: [Character], in text: [Character]) -> Int? {
+        guard word.count <= text.count else { return nil }
+        return (0...(text.count - word.count)).first { start in
+            text[start..<(start + word.count)].elementsEqual(word)
+        }
+    }
+}
+
+enum HistorySearchError: Error, Equatable, CustomStringConvertible {
+    case invalid(String)
+
+    var description: String {
+        switch self {
+        case .invalid(let message): return message
+        }
+    }
+}
+
+/// Usage numbers over a slice of `CommandHistory`: commands per day and per
+/// hour (for a heatmap), top programs with their failure rates, and the
+/// directories most commands ran in.
+struct HistoryStats {
+    struct Count {
+        let key: String
+        let count: Int
+        let failures: Int
+    }
+
+    let range: DateInterval
+    let total: Int
+    let failures: Int
+    let days: [Count]
+    let hours: [Int]
+    let topCommands: [Count]
+    let directories: [Count]
+
+    /// Parses `today`, `all`, or a day count like `7d` / `30`, ending now.
+    static func range(_ text: String, now: Date = Date(), calendar: Calendar = .current) -> DateInterval? {
+        let trimmed = text.trimmingCharacters(in: .whitespaces).lowercased()
+        switch trimmed {
+        case "", "30d": return range("30", now: now, calendar: calendar)
+        case "today": return DateInterval(start: calendar.startOfDay(for: now), end: now)
+        case "all": return DateInterval(start: .distantPast, end: now)
+        default:
+            let digits = trimmed.hasSuffix("d") ? String(trimmed.dropLast()) : trimmed
+            guard let days = Int(digits), (1...3660).contains(days),
+                  let start = calendar.date(byAdding: .day, value: 1 - days, to: calendar.startOfDay(for: now))
+            else { return nil }
+            return DateInterval(start: start, end: now)
+        }
+    }
+
+    init(_ records: [CommandRecord], range: DateInterval, top: Int = 10, calendar: Calendar = .current) {
+        let inRange = records.filter { range.contains($0.started) }
+        self.range = range
+        total = inRange.count
+        failures = inRange.filter { $0.exitCode != 0 }.count
+
+        let dayFormat = DateFormatter()
+        dayFormat.calendar = calendar
+        dayFormat.timeZone = calendar.timeZone
+        dayFormat.locale = Locale(identifier: "en_US_POSIX")
+        dayFormat.dateFormat = "yyyy-MM-dd"
+        days = Self.counts(inRange, top: nil) { dayFormat.string(from: $0.started) }
+            .sorted { $0.key < $1.key }
+
+        var hours = [Int](repeating: 0, count: 24)
+        for record in inRange { hours[calendar.component(.hour, from: record.started)] += 1 }
+        self.hours = hours
+
+        topCommands = Self.counts(inRange, top: top) { $0.program.isEmpty ? nil : $0.program }
+        directories = Self.counts(inRange, top: top) { $0.directory }
+    }
+
+    private static func counts(
+        _ records: [CommandRecord], top: Int?, key: (CommandRecord) -> String?
+    ) -> [Count] {
+        var tally: [String: (count: Int, failures: Int)] = [:]
+        for record in records {
+            guard let key = key(record) else { continue }
+            tally[key, default: (0, 0)].count += 1
+            if record.exitCode != 0 { tally[key, default: (0, 0)].failures += 1 }
+        }
+        let sorted = tally
+            .map { Count(key: $0.key, count: $0.value.count, failures: $0.value.failures) }
+            .sorted { $0.count != $1.count ? $0.count > $1.count : $0.key < $1.key }
+        return top.map { Array(sorted.prefix($0)) } ?? sorted
+    }
+
+    private static func rate(_ failures: Int, of count: Int) -> Double {
+        count == 0 ? 0 : (Double(failures) / Double(count) * 1000).rounded() / 1000
+    }
+
+    var wire: [String: Any] {
+        func entries(_ counts: [Count], as name: String) -> [[String: Any]] {
+            counts.map { [name: $0.key, "count": $0.count, "failureRate": Self.rate($0.failures, of: $0.count)] }
+        }
+        var object: [String: Any] = [
+            "total": total,
+            "failures": failures,
+            "failureRate": Self.rate(failures, of: total),
+            "days": days.map { ["date": $0.key, "count": $0.count, "failures": $0.failures] },
+            "hours": hours,
+            "topCommands": entries(topCommands, as: "command"),
+            "directories": entries(directories, as: "path"),
+            "until": ISO8601DateFormatter().string(from: range.end),
+        ]
+        if range.start != .distantPast { object["since"] = ISO8601DateFormatter().string(from: range.start) }
+        return object
+    }
+}
@@ -0,0 +1,257 @@
+import Foundation
+
+enum CommandWatchError: Error, Equatable, CustomStringConvertible {
+    case emptyCommand
+    case invalidInterval
+    case unwatchablePaths
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .emptyCommand: return "the command is empty"
+        case .invalidInterval:
+            let range = CommandWatch.intervals
+            return "interval must be \(range.lowerBound)-\(Int(range.upperBound)) seconds"
+        case .unwatchablePaths: return "none of the paths can be watched"
+        case .notFound(let id): return "no watch \(id)"
+        }
+    }
+}
+
+/// How one run's output differs from the last: the new output's lines that
+/// are new or changed, and the old lines that are gone. Line numbers start
+/// at 0.
+struct OutputDiff: Equatable {
+    struct Line: Equatable {
+        let line: Int
+        let text: String
+
+        var wire: [String: Any] { ["line": line, "text": text] }
+    }
+
+    var changed: [Line] = []
+    var removed: [Line] = []
+
+    var isEmpty: Bool { changed.isEmpty && removed.isEmpty }
+
+    static func between(_ old: [String], _ new: [String]) -> OutputDiff {
+        var diff = OutputDiff()
+        for change in new.difference(from: old) {
+            switch change {
+            case .insert(let offset, let text, _): diff.changed.append(Line(line: offset, text: text))
+            case .remove(let offset, let text, _): diff.removed.append(Line(line: offset, text: text))
+            }
+        }
+        diff.changed.sort { $0.line < $1.line }
+        diff.removed.sort { $0.line < $1.line }
+        return diff
+    }
+}
+
+/// A command re-run on an interval, when files under `paths` change, or
+/// both, like `watch` or `entr`. `pane` lends it a working directory and
+/// routes its events to that pane's window; without one it is virtual.
+struct CommandWatch {
+    static let intervals: ClosedRange<TimeInterval> = 0.5...3600
+    static let defaultInterval: TimeInterval = 2
+    /// Lines kept per run; a watch is for output that fits on a screen.
+    static let maxLines = 2000
+
+    let id: String
+    let command: String
+    let directory: String?
+    let pane: Int?
+    let interval: TimeInterval?
+    let paths: [String]
+    var runs = 0
+    var exitCode: Int32?
+    var lines: [String] = []
+    var lastRun: Date?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "command": command, "paths": paths, "runs": runs]
+        if let directory { object["directory"] = directory }
+        if let pane { object["pane"] = pane }
+        if let interval { object["interval"] = interval }
+        if let exitCode { object["exitCode"] = Int(exitCode) }
+        if let lastRun { object["lastRun"] = lastRun.timeIntervalSince1970 }
+        return object
+    }
+}
+
+/// One run that changed something: its exit status and how its output
+/// moved, for the `watch` event. The first run reports every line.
+struct CommandWatchChange {
+    let watch: CommandWatch
+    let diff: OutputDiff
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "watch": watch.id, "command": watch.command, "run": watch.runs,
+            "exitCode": watch.exitCode.map(Int.init) ?? -1, "lines": watch.lines.count,
+            "changed": diff.changed.map(\.wire), "removed": diff.removed.map(\.wire),
+        ]
+        if let pane = watch.pane { object["pane"] = pane }
+        return object
+    }
+}
+
+/// The app's command watches. Each runs its command with `/bin/sh -c` on a
+/// utility queue, one run at a time: a trigger that arrives mid-run queues
+/// one more. Only runs whose output or exit status moved are reported.
+final class CommandWatcher {
+    static let shared = CommandWatcher()
+
+    /// A run changed something, on the watcher's queue.
+    var onChange: ((CommandWatchChange) -> Void)?
+
+    private struct Entry {
+        var watch: CommandWatch
+        var timer: DispatchSourceTimer?
+        var files: DirectoryWatcher?
+        /// The last run's changes against the one before.
+        var diff = OutputDiff()
+        var running = false
+        var again = false
+    }
+
+    private struct State {
+        var entries: [String: Entry] = [:]
+        var nextID = 1
+    }
+
+    private let state = LockedState(State())
+    private let queue = DispatchQueue(label: "infinitty.command-watch", qos: .utility, attributes: .concurrent)
+
+    /// Starts a watch and its first run. `paths` are resolved against
+    /// `directory`; with neither an interval nor paths it runs every
+    /// `defaultInterval` seconds.
+    func start(
+        command: String, directory: String?, pane: Int? = nil, interval: TimeInterval? = nil, paths: [String] = []
+    ) -> Result<CommandWatch, CommandWatchError> {
+        let command = command.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard !command.isEmpty else { return .failure(.emptyCommand) }
+        if let interval, !CommandWatch.intervals.contains(interval) { return .failure(.invalidInterval) }
+        let resolved = paths.map { path -> String in
+            let expanded = (path as NSString).expandingTildeInPath
+            guard !expanded.hasPrefix("/"), let directory else { return expanded }
+            return (directory as NSString).appendingPathComponent(expanded)
+        }
+        let id = state.withLock { state -> String in
+            defer { state.nextID += 1 }
+            return "watch-\(state.nextID)"
+        }
+        var files: DirectoryWatcher?
+        if !resolved.isEmpty {
+            files = DirectoryWatcher(paths: resolved) { [weak self] in self?.run(id) }
+            guard files != nil else { return .failure(.unwatchablePaths) }
+        }
+        let every = interval ?? (resolved.isEmpty ? CommandWatch.defaultInterval : nil)
+        let watch = CommandWatch(
+            id: id, command: command, directory: directory, pane: pane, interval: every, paths: resolved)
+        var timer: DispatchSourceTimer?
+        if let every {
+            let source = DispatchSource.makeTimerSource(queue: queue)
+            source.schedule(deadline: .now() + every, repeating: every, leeway: .milliseconds(100))
+            source.setEventHandler { [weak self] in self?.run(id) }
+            timer = source
+        }
+        state.withLock { $0.entries[id] = Entry(watch: watch, timer: timer, files: files) }
+        timer?.resume()
+        queue.async { [weak self] in self?.run(id) }
+        return .success(watch)
+    }
+
+    func stop(_ id: String) -> Bool {
+        let removed = state.withLock { $0.entries.removeValue(forKey: id) }
+        removed?.timer?.cancel()
+        return removed != nil
+    }
+
+    /// Stops the watches borrowing a closed pane.
+    func forget(pane: Int) {
+        let removed = state.withLock { state -> [Entry] in
+            let ids = state.entries.filter { $0.value.watch.pane == pane }.map(\.key)
+            return ids.compactMap { state.entries.removeValue(forKey: $0) }
+        }
+        removed.forEach { $0.timer?.cancel() }
+    }
+
+    func list() -> [CommandWatch] {
+        state.snapshot.entries.values.map(\.watch).sorted { $0.id.localizedStandardCompare($1.id) == .orderedAscending }
+    }
+
+    func watch(_ id: String) -> CommandWatch? {
+        state.withLock { $0.entries[id]?.watch }
+    }
+
+    /// The watch with its last run's lines, each marked `changed` when that
+    /// run added or altered it, and the lines it dropped.
+    func output(_ id: String) -> [String: Any]? {
+        guard let entry = state.withLock({ $0.entries[id] }) else { return nil }
+        let changed = Set(entry.diff.changed.map(\.line))
+        var object = entry.watch.wire
+        object["lines"] = entry.watch.lines.enumerated().map { line, text -> [String: Any] in
+            ["line": line, "text": text, "changed": changed.contains(line)]
+        }
+        object["removed"] = entry.diff.removed.map(\.wire)
+        return object
+    }
+
+    private func run(_ id: String) {
+        let claimed = state.withLock { state -> CommandWatch? in
+            guard var entry = state.entries[id] else { return nil }
+            defer { state.entries[id] = entry }
+            guard !entry.running else {
+                entry.again = true
+                return nil
+            }
+            entry.running = true
+            return entry.watch
+        }
+        guard let watch = claimed else { return }
+        let (status, lines) = Self.execute(watch)
+        let (change, again) = state.withLock { state -> (CommandWatchChange?, Bool) in
+            guard var entry = state.entries[id] else { return (nil, false) }
+            defer { state.entries[id] = entry }
+            let first = entry.watch.runs == 0
+            let diff = OutputDiff.between(entry.watch.lines, lines)
+            let moved = first || !diff.isEmpty || status != entry.watch.exitCode
+            entry.watch.runs += 1
+            entry.watch.exitCode = status
+            entry.watch.lines = lines
+            entry.watch.lastRun = Date()
+            if moved { entry.diff = diff }
+            entry.running = false
+            let again = entry.again
+            entry.again = false
+            return (moved ? CommandWatchChange(watch: entry.watch, diff: diff) : nil, again)
+        }
+        if let change { onChange?(change) }
+        if again { queue.async { [weak self] in self?.run(id) } }
+    }
+
+    /// Runs the command once; stdout then stderr, as lines. A command that
+    /// can't start or times out reports status -1 and the reason.
+    static func execute(_ watch: CommandWatch) -> (status: Int32, lines: [String]) {
+        var environment = ProcessInfo.processInfo.environment
+        environment["INFINITTY_WATCH"] = watch.id
+        if let pane = watch.pane { environment["INFINITTY_PANE"] = String(pane) }
+        let timeout = max(watch.interval ?? 0, ProcessRunner.defaultTimeout)
+        switch ProcessRunner.run(
+            "/bin/sh", ["-c", watch.command], environment: environment, currentDirectory: watch.directory,
+            timeout: timeout)
+        {
+        case .success(let output):
+            var text = output.stdoutText
+            if !output.stderr.isEmpty {
+                text += String(decoding: output.stderr, as: UTF8.self)
+            }
+            var lines = text.split(separator: "\n", omittingEmptySubsequences: false).map(String.init)
+            if lines.last == "" { lines.removeLast() }
+            return (output.status, Array(lines.suffix(CommandWatch.maxLines)))
+        case .failure(let error):
+            return (-1, ["\(error)"])
+        }
+    }
+}
@@ -48,6 +48,9 @@ struct AppConfig {
     var petScale: CGFloat = 0.5
     var petMode = "window" // window = one pet (follows focus) | pane = every split
     var backgroundOpacity: CGFloat = 1.0
+    /// Lowest WCAG contrast ratio palette text may have against the
+    /// background; entries below it are lightened or darkened. 1 is off.
+    var minimumContrast = 1.0
     var backgroundBlur = false // frosted behind-window blur
     var notch = false // live-activity widget beside the MacBook notch
     var notchDisplay = "builtin" // builtin | external | primary | all
@@ -77,6 +80,75 @@ struct AppConfig {
     var mcpAutoRegister = false
     var agentGlow = true // pulsing inner glow while an agent drives the pane
     var sideTabs = false // tabs as a left column instead of a top row
+    var processTimeout: TimeInterval = 30 // seconds before a git/rg/hook helper is killed
+    var scrollbackLines = Terminal.defaultScrollback // rows each pane keeps above its screen
+    /// Write rows pushed out of `scrollbackLines` to compressed files for
+    /// `scrollback-load`, instead of dropping them.
+    var scrollbackSpill = false
+    /// Bytes all panes' scrollback may hold in memory together (0 = no
+    /// limit); over it the biggest are capped and spill to disk.
+    var scrollbackBudget = 0
+    /// Caps on each workspace's content index (`search-index-max` bytes of
+    /// text, `search-index-max-files` files).
+    var searchIndexLimits = SearchIndexLimits()
+    /// Ask before pasting line breaks into a program without bracketed
+    /// paste, or a command that uses sudo, pipes curl into sh or rm -rf.
+    var pasteConfirmation = true
+    /// Hold Enter on a typed command that deletes or chmods recursively,
+    /// force-pushes, drops tables or writes to a disk, and show what it
+    /// would touch first. Needs shell integration to see the command line.
+    var confirmDangerousCommands = false
+    /// Copy a mouse selection as soon as it is made.
+    var copyOnSelect = false
+    /// What copying out of a pane cleans up: trailing line breaks and
+    /// escape sequences unless told otherwise.
+    var copyTransforms = CopyTransform.defaults
+    /// What cmd-clicking a link does, by scheme: web, mail and file links
+    /// open, anything else asks (`link-schemes = vscode:open, ssh:ask`).
+    var linkPolicy = LinkPolicy()
+    /// Apply a project's `.infinitty.toml` (env, commands, layout) when a
+    /// pane's shell enters it, once the user has trusted the file.
+    var projectAutomation = true
+    /// Allow `share-start` to stream panes through `shareRelay`. Off by
+    /// default: a share sends terminal contents off the machine.
+    var sessionSharing = false
+    /// Run new shells in `infinitty-ptyd` so they outlive the app: quitting
+    /// detaches them, and the next launch reopens them as tabs.
+    var daemon = false
+    /// Reopen the windows, tabs and splits of the last run at launch, each
+    /// shell in its directory with its profile.
+    var restoreWorkspace = true
+    /// Where new tabs and splits start: the focused pane's directory by
+    /// default, its repo root, or home. A profile can set its own.
+    var newPaneDirectory: NewPaneDirectory = .inherit
+    /// The profile (see `profiles`) new panes start with; nil runs the
+    /// login shell from `$SHELL`.
+    var defaultProfile: String?
+    var shareRelay: String? // ws:// or wss:// relay that pairs hosts with guests
+    /// Keep a local log of finished commands (OSC 133) for `history-stats`.
+    var commandHistory = true
+    /// Suggest a correction or a Homebrew install when a command is not
+    /// found, and the intended subcommand when one is mistyped.
+    var commandSuggestions = true
+    /// Keep the bell, pet bubbles and notch activity quiet: `auto` follows
+    /// macOS Focus, `on`/`off` decide outright.
+    var doNotDisturb: DoNotDisturb.Mode = .auto
+    /// Attribute active time to workspaces for `timetrack-report`. Off by
+    /// default; idle seconds before the clock stops (0 never stops it).
+    var timeTracking = false
+    var timeTrackingIdle: TimeInterval = 300
+    /// Post a macOS notification when a command that ran at least this
+    /// many seconds finishes while its window is in the background.
+    var notifyLongCommands = true
+    var notifyLongCommandsAfter: TimeInterval = LongCommandPolicy.defaultThreshold
+    /// Panes idle at the prompt this long (`8h`, `90m`) get `idle-action`:
+    /// notify, close with a snapshot, or hibernate. Off by default.
+    var idlePolicy = IdlePolicy()
+    /// Publish `detected-links` events for the URLs, paths and git hashes
+    /// in output as it is printed.
+    var detectLinks = false
+    /// `share-snapshot` uploads here instead of serving on localhost.
+    var snapshotPasteURL: String?
     var sourcePath: String? // config file in use (for live reload)
 
     var atlasKey: String {
@@ -221,6 +293,8 @@ struct AppConfig {
                 }
             case "background-opacity", "opacity", "transparency":
                 if let v = Double(value) { backgroundOpacity = CGFloat(v) }
+            case "minimum-contrast":
+                if let v = Double(value) { minimumContrast = v }
             case "background-blur", "background-blur-radius", "blur":
                 if let v = Double(value) {
                     backgroundBlur = v > 0
@@ -274,6 +348,67 @@ struct AppConfig {
                 agentGlow = AppConfig.parseBool(value)
             case "side-tabs":
                 sideTabs = AppConfig.parseBool(value)
+            case "process-timeout", "command-timeout":
+                if let seconds = Double(value) { processTimeout = seconds }
+            case "scrollback-lines", "scrollback":
+                if let lines = Int(value.replacingOccurrences(of: "_", with: "")) { scrollbackLines = lines }
+            case "scrollback-spill":
+                scrollbackSpill = AppConfig.parseBool(value)
+            case "paste-confirmation", "confirm-paste":
+                pasteConfirmation = AppConfig.parseBool(value)
+            case "confirm-dangerous-commands":
+                confirmDangerousCommands = AppConfig.parseBool(value)
+            case "copy-on-select":
+                copyOnSelect = AppConfig.parseBool(value)
+            case "copy-transforms":
+                if let transforms = CopyTransform.parse(value) { copyTransforms = transforms }
+            case "link-schemes":
+                if let rules = LinkPolicy.parse(value) { linkPolicy.rules.merge(rules) { _, new in new } }
+            case "scrollback-budget":
+                if let bytes = ScrollbackBudget.size(value) { scrollbackBudget = bytes }
+            case "search-index-max":
+                if let bytes = ScrollbackBudget.size(value), bytes > 0 { searchIndexLimits.maxBytes = bytes }
+            case "search-index-max-files":
+                if let files = Int(value.replacingOccurrences(of: "_", with: "")), files > 0 {
+                    searchIndexLimits.maxFiles = files
+                }
+            case "project-automation":
+                projectAutomation = AppConfig.parseBool(value)
+            case "session-sharing":
+                sessionSharing = AppConfig.parseBool(value)
+            case "daemon":
+                daemon = AppConfig.parseBool(value)
+            case "restore-workspace":
+                restoreWorkspace = AppConfig.parseBool(value)
+            case "new-pane-directory":
+                if let rule = NewPaneDirectory(rawValue: value.lowercased()) { newPaneDirectory = rule }
+            case "default-profile", "profile":
+                defaultProfile = value.isEmpty ? nil : value
+            case "share-relay":
+                shareRelay = value.isEmpty ? nil : value
+            case "command-history":
+                commandHistory = AppConfig.parseBool(value)
+            case "command-suggestions":
+                commandSuggestions = AppConfig.parseBool(value)
+            case "do-not-disturb", "dnd":
+                doNotDisturb = DoNotDisturb.Mode(rawValue: value.lowercased())
+                    ?? (AppConfig.parseBool(value) ? .on : .off)
+            case "time-tracking":
+                timeTracking = AppConfig.parseBool(value)
+            case "time-tracking-idle":
+                if let seconds = Double(value), seconds >= 0 { timeTrackingIdle = seconds }
+            case "notify-long-commands":
+                notifyLongCommands = AppConfig.parseBool(value)
+            case "notify-long-commands-after":
+                if let seconds = Double(value), seconds >= 0 { notifyLongCommandsAfter = seconds }
+            case "idle-timeout":
+                if let seconds = IdlePolicy.duration(value) { idlePolicy.timeout = seconds }
+            case "idle-action":
+                if let action = IdleAction(rawValue: value.lowercased()) { idlePolicy.action = action }
+            case "detect-links":
+                detectLinks = AppConfig.parseBool(value)
+            case "snapshot-paste-url":
+                snapshotPasteURL = value.isEmpty ? nil : value
             default:
                 break // unknown keys (themes, cursor styles, ...) ignored
             }
@@ -347,6 +482,7 @@ struct AppConfig {
         out += "line-spacing = \(Double(lineSpacing))\n"
         out += "kerning = \(Double(kerning))\n"
         if backgroundOpacity < 1 { out += "background-opacity = \(Double(backgroundOpacity))\n" }
+        if minimumContrast > 1 { out += "minimum-contrast = \(minimumContrast)\n" }
         if backgroundBlur { out += "background-blur = true\n" }
         out += "traffic-lights = \(trafficLights)\n"
         if let c = foreground { out += "foreground = \(Self.hex(c))\n" }
@@ -394,6 +530,12 @@ struct AppConfig {
         if let v = claudeModel, !v.isEmpty { out += "claude-model = \(v)\n" }
         if let v = codexModel, !v.isEmpty { out += "codex-model = \(v)\n" }
         if mcpAutoRegister { out += "mcp-auto-register = true\n" }
+        if daemon { out += "daemon = true\n" }
+        if !restoreWorkspace { out += "restore-workspace = false\n" }
+        if newPaneDirectory != .inherit { out += "new-pane-directory = \(newPaneDirectory.rawValue)\n" }
+        if let v = defaultProfile, !v.isEmpty { out += "default-profile = \(v)\n" }
+        if processTimeout != 30 { out += "process-timeout = \(processTimeout)\n" }
+        if scrollbackLines != Terminal.defaultScrollback { out += "scrollback-lines = \(scrollbackLines)\n" }
         if markdownCommand != "glow -p" { out += "markdown-command = \(markdownCommand)\n" }
         if markdownRender != "off" { out += "markdown-render = \(markdownRender)\n" }
         if notch {
@@ -437,6 +579,9 @@ struct AppConfig {
         cellHeightExtra = min(max(cellHeightExtra, -10), 40)
         petScale = min(max(petScale, 0.1), 2)
         backgroundOpacity = min(max(backgroundOpacity, 0.15), 1)
+        minimumContrast = min(max(minimumContrast, 1), 21)
         quickTerminalAnimationDuration = min(max(quickTerminalAnimationDuration, 0), 2)
+        processTimeout = min(max(processTimeout, 1), 600)
+        scrollbackLines = min(max(scrollbackLines, 100), Terminal.maxScrollback)
     }
 }
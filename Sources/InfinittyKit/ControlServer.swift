@@ -8,6 +8,9 @@ import Foundation
 ///
 ///   screen              -> visible screen as text
 ///   history <n>         -> last n lines (scrollback + screen)
+///   search <query> | {"query", "regex", "caseSensitive", "limit"}
+///                       -> JSON {matches: [{line, column, length, text}],
+///                          truncated, firstLine, lastLine}
 ///   last-output         -> output of last completed command (OSC 133)
 ///   last-command        -> last command line as typed (OSC 133)
 ///   exit-code           -> exit code of last completed command (OSC 133)
@@ -172,8 +175,10 @@ final class ControlServer {
         case "screen":
             return terminal.screenText()
         case "history":
-            let n = min(max(Int(arg.trimmingCharacters(in: .whitespaces)) ?? 100, 1), Terminal.maxScrollback)
+            let n = min(max(Int(arg.trimmingCharacters(in: .whitespaces)) ?? 100, 1), terminal.scrollbackLimit)
             return terminal.historyText(lines: n)
+        case "search":
+            return ControlServer.searchScrollback(terminal, arg)
         case "last-output":
             return terminal.lastCommandOutput()
                 ?? "error: no completed command (enable OSC 133 shell integration)"
@@ -184,10 +189,12 @@ final class ControlServer {
             if let code = terminal.lastExitCode() { return String(code) }
             return "error: no completed command (enable OSC 133 shell integration)"
         case "send":
+            guard !pty.isInputLocked else { return "error: input is locked" }
             activityHandler?()
             pty.write(Array(arg.utf8))
             return "ok"
         case "send-line":
+            guard !pty.isInputLocked else { return "error: input is locked" }
             activityHandler?()
             pty.write(Array(arg.utf8) + [0x0D])
             return "ok"
@@ -203,7 +210,31 @@ final class ControlServer {
         case "ping":
             return "pong"
         default:
-            return "error: unknown command '\(cmd)' (screen | history N | last-output | last-command | exit-code | send TEXT | send-line TEXT | ping)"
+            return "error: unknown command '\(cmd)' (screen | history N | search QUERY | last-output | last-command | exit-code | send TEXT | send-line TEXT | ping)"
+        }
+    }
+
+    /// `search`'s argument is a literal, case-insensitive query, or JSON to
+    /// ask for a regex, exact case or a different match cap.
+    static func searchScrollback(_ terminal: Terminal, _ arg: String) -> String {
+        var query = arg
+        var regex = false
+        var caseSensitive = false
+        var limit = 1000
+        if arg.hasPrefix("{"),
+           let data = arg.data(using: .utf8),
+           let request = try? JSONSerialization.jsonObject(with: data) as? [String: Any] {
+            query = request["query"] as? String ?? ""
+            regex = request["regex"] as? Bool ?? false
+            caseSensitive = request["caseSensitive"] as? Bool ?? false
+            limit = min(max(request["limit"] as? Int ?? limit, 1), 100_000)
+        }
+        switch terminal.searchScrollback(query, regex: regex, caseSensitive: caseSensitive, limit: limit) {
+        case .success(let search):
+            let data = (try? JSONSerialization.data(withJSONObject: search.wire)) ?? Data("{}".utf8)
+            return String(decoding: data, as: UTF8.self)
+        case .failure(let error):
+            return "error: \(error)"
         }
     }
 }
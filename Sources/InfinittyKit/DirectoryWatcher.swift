@@ -0,0 +1,47 @@
+import CoreServices
+import Foundation
+
+/// Recursive FSEvents stream over one or more paths, coalesced to one
+/// callback per quarter second. Git status watches a whole work tree
+/// (including `.git`, so index, HEAD and ref moves made by other git
+/// processes count too); `watch-start` watches what its command reads.
+final class DirectoryWatcher {
+    private var stream: FSEventStreamRef?
+    private let onChange: () -> Void
+    private static let queue = DispatchQueue(label: "infinitty.directory-watch", qos: .utility)
+
+    convenience init?(path: String, onChange: @escaping () -> Void) {
+        self.init(paths: [path], onChange: onChange)
+    }
+
+    init?(paths: [String], onChange: @escaping () -> Void) {
+        self.onChange = onChange
+        guard !paths.isEmpty else { return nil }
+        var context = FSEventStreamContext(
+            version: 0, info: Unmanaged.passUnretained(self).toOpaque(),
+            retain: nil, release: nil, copyDescription: nil)
+        let callback: FSEventStreamCallback = { _, info, _, _, _, _ in
+            guard let info else { return }
+            Unmanaged<DirectoryWatcher>.fromOpaque(info).takeUnretainedValue().onChange()
+        }
+        guard let stream = FSEventStreamCreate(
+            kCFAllocatorDefault, callback, &context, paths as CFArray,
+            FSEventStreamEventId(kFSEventStreamEventIdSinceNow), 0.25,
+            FSEventStreamCreateFlags(kFSEventStreamCreateFlagNone))
+        else { return nil }
+        FSEventStreamSetDispatchQueue(stream, Self.queue)
+        guard FSEventStreamStart(stream) else {
+            FSEventStreamInvalidate(stream)
+            FSEventStreamRelease(stream)
+            return nil
+        }
+        self.stream = stream
+    }
+
+    deinit {
+        guard let stream else { return }
+        FSEventStreamStop(stream)
+        FSEventStreamInvalidate(stream)
+        FSEventStreamRelease(stream)
+    }
+}
@@ -0,0 +1,121 @@
+import Foundation
+
+/// macOS Focus as the Do Not Disturb database records it.
+struct FocusStatus: Equatable {
+    let isFocused: Bool
+    /// The Focus's name ("Work", "Do Not Disturb") when it can be resolved.
+    var name: String?
+
+    static var directory: URL {
+        URL(fileURLWithPath: NSHomeDirectory()).appendingPathComponent("Library/DoNotDisturb/DB", isDirectory: true)
+    }
+
+    /// Reads the active assertions — Focus modes turned on by hand, from
+    /// Control Center or by a Shortcut — and names them from the mode
+    /// configurations. nil when the database can't be read.
+    static func current(in directory: URL = FocusStatus.directory) -> FocusStatus? {
+        guard let assertions = try? Data(contentsOf: directory.appendingPathComponent("Assertions.json")) else {
+            return nil
+        }
+        let configurations = try? Data(contentsOf: directory.appendingPathComponent("ModeConfigurations.json"))
+        return parse(assertions: assertions, configurations: configurations)
+    }
+
+    static func parse(assertions: Data, configurations: Data?) -> FocusStatus? {
+        func firstRecord(_ data: Data?) -> [String: Any]? {
+            guard let data, let root = try? JSONSerialization.jsonObject(with: data) as? [String: Any] else {
+                return nil
+            }
+            return (root["data"] as? [[String: Any]])?.first ?? [:]
+        }
+        guard let store = firstRecord(assertions) else { return nil }
+        let records = store["storeAssertionRecords"] as? [[String: Any]] ?? []
+        guard let latest = records.last else { return FocusStatus(isFocused: false) }
+        let mode = (latest["assertionDetails"] as? [String: Any])?["assertionDetailsModeIdentifier"] as? String
+        let modes = firstRecord(configurations)?["modeConfigurations"] as? [String: Any]
+        let name = mode
+            .flatMap { (modes?[$0] as? [String: Any])?["mode"] as? [String: Any] }
+            .flatMap { $0["name"] as? String }
+        return FocusStatus(isFocused: true, name: name ?? (mode == nil ? nil : "Focus"))
+    }
+}
+
+/// Whether the app should keep quiet: set by hand (`on`/`off`) or, in
+/// `auto`, following macOS Focus. The bell, pet bubbles and the notch
+/// activity widget check it before making themselves noticed.
+final class DoNotDisturb {
+    enum Mode: String, CaseIterable {
+        case auto, on, off
+    }
+
+    struct Snapshot: Equatable {
+        let mode: Mode
+        let focus: FocusStatus?
+
+        var isActive: Bool { mode == .on || (mode == .auto && focus?.isFocused == true) }
+
+        var wire: [String: Any] {
+            var object: [String: Any] = [
+                "active": isActive, "mode": mode.rawValue, "focus": focus?.isFocused ?? false,
+            ]
+            if let name = focus?.name { object["focusName"] = name }
+            if focus == nil { object["focusReadable"] = false }
+            return object
+        }
+    }
+
+    static let shared = DoNotDisturb()
+    static let pollInterval: TimeInterval = 10
+
+    /// Called, on any thread, when `isActive` or the Focus name changes.
+    var onChange: ((Snapshot) -> Void)?
+
+    private let readFocus: () -> FocusStatus?
+    private let state: LockedState<Snapshot>
+    private let queue = DispatchQueue(label: "infinitty.do-not-disturb", qos: .utility)
+    private var timer: DispatchSourceTimer?
+
+    init(readFocus: @escaping () -> FocusStatus? = { FocusStatus.current() }) {
+        self.readFocus = readFocus
+        state = LockedState(Snapshot(mode: .auto, focus: nil))
+    }
+
+    var snapshot: Snapshot { state.snapshot }
+    var isActive: Bool { snapshot.isActive }
+
+    /// Applies the configured mode and polls Focus while in `auto`.
+    func start(mode: Mode) {
+        setMode(mode)
+        queue.async { [weak self] in
+            guard let self, self.timer == nil else { return }
+            let timer = DispatchSource.makeTimerSource(queue: self.queue)
+            timer.schedule(deadline: .now() + Self.pollInterval, repeating: Self.pollInterval, leeway: .seconds(2))
+            timer.setEventHandler { [weak self] in
+                guard let self, self.snapshot.mode == .auto else { return }
+                self.refresh()
+            }
+            self.timer = timer
+            timer.resume()
+        }
+    }
+
+    func setMode(_ mode: Mode) {
+        update { Snapshot(mode: mode, focus: mode == .auto ? self.readFocus() : $0.focus) }
+    }
+
+    /// Re-reads Focus now rather than at the next poll.
+    func refresh() {
+        update { Snapshot(mode: $0.mode, focus: self.readFocus()) }
+    }
+
+    private func update(_ next: (Snapshot) -> Snapshot) {
+        let (old, new) = state.withLock { current -> (Snapshot, Snapshot) in
+            let old = current
+            current = next(current)
+            return (old, current)
+        }
+        if old.isActive != new.isActive || old.focus?.name != new.focus?.name || old.mode != new.mode {
+            onChange?(new)
+        }
+    }
+}
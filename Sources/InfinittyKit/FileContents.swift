@@ -0,0 +1,122 @@
+import CryptoKit
+import Foundation
+
+/// Why `FileContents` refused or failed to read a file.
+enum FileContentsError: Error, Equatable, CustomStringConvertible {
+    case unreadable(String)
+    case notRegularFile
+    case tooLarge(size: Int64, limit: Int)
+    case binary
+    case notUTF8
+    case cancelled
+
+    var description: String {
+        switch self {
+        case .unreadable(let reason): return reason
+        case .notRegularFile: return "not a regular file"
+        case .tooLarge(let size, let limit): return "\(size) bytes exceeds the \(limit)-byte limit"
+        case .binary: return "binary file"
+        case .notUTF8: return "not UTF-8 text"
+        case .cancelled: return "cancelled"
+        }
+    }
+}
+
+/// Text decoded from at most `limit` bytes of a file.
+struct FileText: Equatable {
+    let text: String
+    /// Size of the whole file on disk.
+    let size: Int64
+    /// True when `text` covers only the first part of the file.
+    let truncated: Bool
+}
+
+/// Size-capped access to file contents for previews, hashing and the
+/// control socket. Files are memory-mapped rather than read, so only the
+/// pages actually touched (the capped prefix, or one hashing chunk at a
+/// time) are resident and a multi-GB log never lands on the heap. Binary
+/// detection uses git's heuristic: a NUL in the first 8 KB.
+enum FileContents {
+    static let sniffLength = 8192
+
+    /// Map `url` read-only. Empty files map to empty `Data`.
+    static func map(_ url: URL) -> Result<Data, FileContentsError> {
+        var info = stat()
+        guard stat(url.path, &info) == 0 else {
+            return .failure(.unreadable(String(cString: strerror(errno))))
+        }
+        guard info.st_mode & S_IFMT == S_IFREG else { return .failure(.notRegularFile) }
+        do {
+            return .success(try Data(contentsOf: url, options: .alwaysMapped))
+        } catch {
+            return .failure(.unreadable(error.localizedDescription))
+        }
+    }
+
+    static func looksBinary(_ data: Data) -> Bool {
+        data.prefix(sniffLength).contains(0)
+    }
+
+    /// UTF-8 text of `url`. A file over `limit` bytes fails with
+    /// `.tooLarge`, or with `truncate` yields its first `limit` bytes cut
+    /// back to a whole character.
+    static func text(
+        of url: URL, limit: Int, truncate: Bool = false
+    ) -> Result<FileText, FileContentsError> {
+        let data: Data
+        switch map(url) {
+        case .success(let mapped): data = mapped
+        case .failure(let error): return .failure(error)
+        }
+        let size = Int64(data.count)
+        if data.count > limit, !truncate {
+            return .failure(.tooLarge(size: size, limit: limit))
+        }
+        if looksBinary(data) { return .failure(.binary) }
+        var slice = data.prefix(limit)
+        let truncated = slice.count < data.count
+        // A cut can split a multi-byte character; drop its leading bytes.
+        if truncated, let lead = slice.lastIndex(where: { $0 & 0xC0 != 0x80 }),
+           slice[lead] >= 0xC0,
+           slice.distance(from: lead, to: slice.endIndex) < utf8Length(lead: slice[lead]) {
+            slice = slice[..<lead]
+        }
+        // Copy just the capped prefix out of the mapping.
+        guard let text = String(data: Data(slice), encoding: .utf8) else {
+            return .failure(.notUTF8)
+        }
+        return .success(FileText(text: text, size: size, truncated: truncated))
+    }
+
+    /// Hex SHA-256 of `url`, fed to the hasher one chunk of the mapping at
+    /// a time. `progress` receives the bytes hashed so far after each chunk.
+    static func sha256(
+        of url: URL, chunkSize: Int = 4 << 20,
+        isCancelled: () -> Bool = { false },
+        progress: (Int64) -> Void = { _ in }
+    ) -> Result<String, FileContentsError> {
+        let data: Data
+        switch map(url) {
+        case .success(let mapped): data = mapped
+        case .failure(let error): return .failure(error)
+        }
+        var hasher = SHA256()
+        var offset = data.startIndex
+        while offset < data.endIndex {
+            if isCancelled() { return .failure(.cancelled) }
+            let end = data.index(offset, offsetBy: chunkSize, limitedBy: data.endIndex) ?? data.endIndex
+            hasher.update(data: data[offset..<end])
+            offset = end
+            progress(Int64(data.distance(from: data.startIndex, to: end)))
+        }
+        return .success(hasher.finalize().map { String(format: "%02x", $0) }.joined())
+    }
+
+    private static func utf8Length(lead: UInt8) -> Int {
+        switch lead {
+        case 0xF0...: return 4
+        case 0xE0...: return 3
+        default: return 2
+        }
+    }
+}
@@ -0,0 +1,322 @@
+import Darwin
+import Foundation
+
+/// What a copy does when its destination already exists.
+enum FileConflictPolicy: String {
+    /// Keep the existing entry and record the source as skipped.
+    case skip
+    /// Replace the existing entry.
+    case overwrite
+    /// Copy alongside under a Finder-style unique name ("a 2.txt").
+    case rename
+}
+
+/// One entry that could not be processed. Operations record it and carry
+/// on with the remaining entries instead of aborting the whole tree.
+struct FileOperationFailure: Equatable {
+    let path: String
+    let message: String
+}
+
+/// Outcome of a filesystem operation, entry by entry.
+struct FileOperationResult {
+    /// Files, links and directories created (copy), moved, or removed
+    /// (delete).
+    var entries = 0
+    /// Bytes of regular-file data written.
+    var bytes: Int64 = 0
+    /// Destination paths left untouched by `FileConflictPolicy.skip`.
+    var skipped: [String] = []
+    /// Things that worked, but not quite as asked (a rename to dodge a
+    /// clash, a cross-volume move done as copy + delete).
+    var warnings: [String] = []
+    var failures: [FileOperationFailure] = []
+    /// True when `isCancelled` stopped the walk early.
+    var cancelled = false
+
+    var succeeded: Bool { failures.isEmpty && !cancelled }
+
+    /// Control-socket / task-result encoding.
+    var jsonObject: [String: Any] {
+        [
+            "entries": entries, "bytes": bytes, "skipped": skipped,
+            "warnings": warnings, "cancelled": cancelled,
+            "failures": failures.map { ["path": $0.path, "message": $0.message] },
+        ]
+    }
+
+    mutating func absorb(_ other: FileOperationResult) {
+        entries += other.entries
+        bytes += other.bytes
+        skipped += other.skipped
+        warnings += other.warnings
+        failures += other.failures
+        cancelled = cancelled || other.cancelled
+    }
+}
+
+/// Recursive filesystem operations for the Files pane and the control
+/// socket. Symlinks are copied as links (never followed), permissions,
+/// extended attributes and timestamps travel with each entry, and failures
+/// are collected per entry. Synchronous — run on a background queue.
+enum FileOperations {
+
+    /// Copy `source` to exactly `destination` (not into it). Directories
+    /// merge into an existing directory, applying `conflict` to each entry
+    /// that already exists; with `.rename` a clashing top-level item is
+    /// copied whole under a unique name instead.
+    static func copy(
+        _ source: URL, to destination: URL,
+        conflict: FileConflictPolicy = .skip,
+        isCancelled: () -> Bool = { false }
+    ) -> FileOperationResult {
+        var result = FileOperationResult()
+        let src = source.standardizedFileURL.path
+        var dst = destination.standardizedFileURL.path
+        if dst == src || dst.hasPrefix(src + "/") {
+            result.failures.append(FileOperationFailure(
+                path: src, message: "cannot copy an item into itself"))
+            return result
+        }
+        if conflict == .rename, entryExists(dst) {
+            dst = uniqueDestination(for: dst)
+            result.warnings.append("\(destination.path) exists; copied to \(dst)")
+        }
+        copyEntry(src, to: dst, conflict: conflict, result: &result, isCancelled: isCancelled)
+        return result
+    }
+
+    /// Move `source` to exactly `destination`. A same-volume move is one
+    /// rename(2) and counts as a single entry with no bytes written; across
+    /// volumes it becomes copy + delete, and the source is only removed
+    /// when every entry copied.
+    static func move(
+        _ source: URL, to destination: URL,
+        conflict: FileConflictPolicy = .skip,
+        isCancelled: () -> Bool = { false }
+    ) -> FileOperationResult {
+        var result = FileOperationResult()
+        let src = source.standardizedFileURL.path
+        var dst = destination.standardizedFileURL.path
+        guard entryExists(src) else {
+            result.failures.append(FileOperationFailure(path: src, message: "no such file or directory"))
+            return result
+        }
+        if dst == src { return result }
+        if dst.hasPrefix(src + "/") {
+            result.failures.append(FileOperationFailure(
+                path: src, message: "cannot move an item into itself"))
+            return result
+        }
+        if entryExists(dst) {
+            guard resolve(conflict: conflict, at: &dst, result: &result) else { return result }
+        }
+        if Darwin.rename(src, dst) == 0 {
+            result.entries = 1
+            return result
+        }
+        guard errno == EXDEV else {
+            result.failures.append(failure(src))
+            return result
+        }
+        result.warnings.append("\(dst) is on another volume; copied, then removed the original")
+        let copied = copy(
+            URL(fileURLWithPath: src), to: URL(fileURLWithPath: dst),
+            conflict: .overwrite, isCancelled: isCancelled)
+        result.absorb(copied)
+        guard copied.succeeded else {
+            result.warnings.append("kept \(src) because the copy did not complete")
+            return result
+        }
+        let removed = delete(URL(fileURLWithPath: src), isCancelled: isCancelled)
+        result.failures += removed.failures
+        result.cancelled = result.cancelled || removed.cancelled
+        return result
+    }
+
+    /// Delete `target` recursively without following symlinks. Counts every
+    /// removed entry and the bytes of removed regular files; an entry that
+    /// cannot be removed keeps its parent directories too.
+    static func delete(_ target: URL, isCancelled: () -> Bool = { false }) -> FileOperationResult {
+        var result = FileOperationResult()
+        let path = target.standardizedFileURL.path
+        guard path != "/", path != NSHomeDirectory() else {
+            result.failures.append(FileOperationFailure(path: path, message: "refusing to delete \(path)"))
+            return result
+        }
+        deleteEntry(path, result: &result, isCancelled: isCancelled)
+        return result
+    }
+
+    /// First free "name N.ext" sibling of `path`, counting from 2. Dotfiles
+    /// and extensionless names get the suffix at the end.
+    static func uniqueDestination(
+        for path: String, exists: (String) -> Bool = FileOperations.entryExists
+    ) -> String {
+        let url = URL(fileURLWithPath: path)
+        let dir = url.deletingLastPathComponent()
+        let name = url.lastPathComponent
+        let ext = url.pathExtension
+        let stem = ext.isEmpty || name.hasPrefix(".") && name.dropFirst().firstIndex(of: ".") == nil
+            ? name
+            : String(name.dropLast(ext.count + 1))
+        let suffix = stem == name ? "" : "." + ext
+        var n = 2
+        while true {
+            let candidate = dir.appendingPathComponent("\(stem) \(n)\(suffix)").path
+            if !exists(candidate) { return candidate }
+            n += 1
+        }
+    }
+
+    /// lstat-based existence: a dangling symlink still occupies its name.
+    static func entryExists(_ path: String) -> Bool {
+        var info = stat()
+        return lstat(path, &info) == 0
+    }
+
+    // MARK: - walk
+
+    private static func copyEntry(
+        _ src: String, to dst: String, conflict: FileConflictPolicy,
+        result: inout FileOperationResult, isCancelled: () -> Bool
+    ) {
+        if isCancelled() {
+            result.cancelled = true
+            return
+        }
+        var info = stat()
+        guard lstat(src, &info) == 0 else {
+            result.failures.append(failure(src))
+            return
+        }
+        var dst = dst
+        var existing = stat()
+        let exists = lstat(dst, &existing) == 0
+        let existingIsDirectory = exists && existing.st_mode & S_IFMT == S_IFDIR
+
+        switch info.st_mode & S_IFMT {
+        case S_IFDIR:
+            if exists && !existingIsDirectory {
+                guard resolve(conflict: conflict, at: &dst, result: &result) else { return }
+            }
+            if !entryExists(dst) {
+                // Owner-writable while children land; real mode applied after.
+                guard mkdir(dst, 0o700) == 0 else {
+                    result.failures.append(failure(dst))
+                    return
+                }
+                result.entries += 1
+            }
+            let children: [String]
+            do {
+                children = try FileManager.default.contentsOfDirectory(atPath: src)
+            } catch {
+                result.failures.append(FileOperationFailure(
+                    path: src, message: error.localizedDescription))
+                return
+            }
+            for child in children.sorted() {
+                copyEntry(
+                    src + "/" + child, to: dst + "/" + child, conflict: conflict,
+                    result: &result, isCancelled: isCancelled)
+                if result.cancelled { return }
+            }
+            // Metadata last: a read-only mode would block the children, and
+            // writing them would bump the directory's mtime.
+            if copyfile(src, dst, nil, copyfile_flags_t(COPYFILE_METADATA | COPYFILE_NOFOLLOW)) != 0 {
+                result.failures.append(failure(dst))
+            }
+
+        case S_IFREG, S_IFLNK:
+            if exists {
+                guard resolve(conflict: conflict, at: &dst, result: &result) else { return }
+            }
+            let flags = copyfile_flags_t(COPYFILE_ALL | COPYFILE_NOFOLLOW | COPYFILE_EXCL)
+            guard copyfile(src, dst, nil, flags) == 0 else {
+                result.failures.append(failure(src))
+                return
+            }
+            result.entries += 1
+            if info.st_mode & S_IFMT == S_IFREG { result.bytes += Int64(info.st_size) }
+
+        default:
+            result.failures.append(FileOperationFailure(
+                path: src, message: "unsupported file type (fifo, socket or device)"))
+        }
+    }
+
+    /// Apply `conflict` to an occupied `dst`. False when the entry should
+    /// not be copied (skipped, or the old entry could not be removed).
+    private static func resolve(
+        conflict: FileConflictPolicy, at dst: inout String,
+        result: inout FileOperationResult
+    ) -> Bool {
+        switch conflict {
+        case .skip:
+            result.skipped.append(dst)
+            return false
+        case .overwrite:
+            do {
+                try FileManager.default.removeItem(atPath: dst)
+                return true
+            } catch {
+                result.failures.append(FileOperationFailure(
+                    path: dst, message: error.localizedDescription))
+                return false
+            }
+        case .rename:
+            let original = dst
+            dst = uniqueDestination(for: dst)
+            result.warnings.append("\(original) exists; used \(dst)")
+            return true
+        }
+    }
+
+    private static func deleteEntry(
+        _ path: String, result: inout FileOperationResult, isCancelled: () -> Bool
+    ) {
+        if isCancelled() {
+            result.cancelled = true
+            return
+        }
+        var info = stat()
+        guard lstat(path, &info) == 0 else {
+            result.failures.append(failure(path))
+            return
+        }
+        if info.st_mode & S_IFMT == S_IFDIR {
+            let children: [String]
+            do {
+                children = try FileManager.default.contentsOfDirectory(atPath: path)
+            } catch {
+                result.failures.append(FileOperationFailure(
+                    path: path, message: error.localizedDescription))
+                return
+            }
+            let failuresBefore = result.failures.count
+            for child in children {
+                deleteEntry(path + "/" + child, result: &result, isCancelled: isCancelled)
+                if result.cancelled { return }
+            }
+            // A child that stayed behind already explains the non-empty dir.
+            guard result.failures.count == failuresBefore else { return }
+            guard rmdir(path) == 0 else {
+                result.failures.append(failure(path))
+                return
+            }
+            result.entries += 1
+            return
+        }
+        guard unlink(path) == 0 else {
+            result.failures.append(failure(path))
+            return
+        }
+        result.entries += 1
+        if info.st_mode & S_IFMT == S_IFREG { result.bytes += Int64(info.st_size) }
+    }
+
+    private static func failure(_ path: String) -> FileOperationFailure {
+        FileOperationFailure(path: path, message: String(cString: strerror(errno)))
+    }
+}
@@ -0,0 +1,253 @@
+import Foundation
+import Security
+
+/// A question git asked through `GIT_ASKPASS` while the app ran it: a
+/// username or password for an HTTPS remote, usually.
+struct GitCredentialPrompt: Equatable {
+    let id: String
+    /// As git printed it: `Password for 'https://me@github.com': `.
+    let prompt: String
+    /// The remote's scheme and host, when the prompt names one.
+    let url: String?
+    let username: String?
+    /// Typed into a secure field: anything but a username.
+    let isSecret: Bool
+
+    init(id: String, prompt: String) {
+        self.id = id
+        self.prompt = prompt
+        var url: String?
+        var username: String?
+        if let open = prompt.firstIndex(of: "'"),
+           let close = prompt[prompt.index(after: open)...].firstIndex(of: "'"),
+           let components = URLComponents(string: String(prompt[prompt.index(after: open)..<close])),
+           let scheme = components.scheme, let host = components.host {
+            url = "\(scheme)://\(host)" + (components.port.map { ":\($0)" } ?? "")
+            username = components.user?.removingPercentEncoding
+        }
+        self.url = url
+        self.username = username
+        isSecret = !prompt.lowercased().hasPrefix("username")
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "prompt": prompt, "secret": isSecret]
+        if let url { object["url"] = url }
+        if let username { object["username"] = username }
+        return object
+    }
+}
+
+/// Answers git's credential prompts from the user instead of failing them.
+/// While the app runs a git command that talks to a remote, `GIT_ASKPASS`
+/// points at a small script that hands the prompt to the app socket
+/// (`git-askpass`) with a token only that command has; the app raises a
+/// `git-credential-prompt` event and a sheet, and whichever answers first
+/// (the sheet, or `git-credential-answer`) goes back to git. Answers the
+/// user chose to remember are kept in the login keychain under
+/// `infinitty.git`, keyed by the prompt, and dropped again when git
+/// rejects them. ssh keeps running in batch mode: this covers HTTPS.
+final class GitAskpass {
+    static let shared = GitAskpass(scriptURL: GitAskpass.defaultScriptURL)
+    static let keychainService = "infinitty.git"
+    /// How long a prompt waits for an answer before git is told no.
+    static let timeout: TimeInterval = 90
+
+    /// Shows a prompt to the user; set by the app. Until it is, remote
+    /// commands run without a helper and fail on any prompt, as before.
+    var presenter: ((GitCredentialPrompt) -> Void)? {
+        get { state.withLock { $0.presenter } }
+        set { state.withLock { $0.presenter = newValue } }
+    }
+    /// A prompt was answered, cancelled or timed out, however it happened.
+    var onResolved: ((GitCredentialPrompt, _ answered: Bool) -> Void)? {
+        get { state.withLock { $0.onResolved } }
+        set { state.withLock { $0.onResolved = newValue } }
+    }
+    /// Where the helper reaches the app.
+    var socketPath: String {
+        get { state.withLock { $0.socketPath } }
+        set { state.withLock { $0.socketPath = newValue } }
+    }
+
+    private final class Pending {
+        let prompt: GitCredentialPrompt
+        let done = DispatchSemaphore(value: 0)
+        var answer: String?
+        var remember = false
+
+        init(prompt: GitCredentialPrompt) {
+            self.prompt = prompt
+        }
+    }
+
+    private struct State {
+        /// Live commands' tokens, each with the keychain entries it was
+        /// answered from.
+        var sessions: [String: [String]] = [:]
+        var pending: [String: Pending] = [:]
+        var nextID = 1
+        /// Set on main, read from the commands' queues.
+        var socketPath = AppControlServer.ownSocketPath
+        var presenter: ((GitCredentialPrompt) -> Void)?
+        var onResolved: ((GitCredentialPrompt, _ answered: Bool) -> Void)?
+    }
+
+    let scriptURL: URL
+    private let state = LockedState(State())
+
+    init(scriptURL: URL) {
+        self.scriptURL = scriptURL
+    }
+
+    var pending: [GitCredentialPrompt] {
+        state.withLock { $0.pending.values.map(\.prompt) }.sorted { (Int($0.id) ?? 0) < (Int($1.id) ?? 0) }
+    }
+
+    /// Runs one remote git command with the helper in its environment.
+    /// When it fails on authentication, the keychain answers it was given
+    /// are forgotten so the next try asks again.
+    func session(
+        _ body: (_ environment: [String: String]) -> (status: Int32, stdout: String, stderr: String)
+    ) -> (status: Int32, stdout: String, stderr: String) {
+        guard presenter != nil, let script = installScript() else { return body([:]) }
+        let token = UUID().uuidString
+        state.withLock { $0.sessions[token] = [] }
+        let result = body([
+            "GIT_ASKPASS": script, "INFINITTY_ASKPASS_SOCKET": socketPath, "INFINITTY_ASKPASS_TOKEN": token,
+        ])
+        let used = state.withLock { $0.sessions.removeValue(forKey: token) } ?? []
+        if result.status != 0, Self.isAuthenticationFailure(result.stderr) {
+            used.forEach { _ = Self.forget(prompt: $0) }
+        }
+        return result
+    }
+
+    /// The helper's request: a remembered answer, else the user's, else
+    /// nil when nobody answered in time or the token isn't a live command.
+    func ask(token: String, prompt text: String) -> String? {
+        guard state.withLock({ $0.sessions[token] != nil }) else { return nil }
+        if let cached = Self.remembered(prompt: text) {
+            state.withLock { $0.sessions[token]?.append(text) }
+            return cached
+        }
+        let pending = state.withLock { state -> Pending in
+            let pending = Pending(prompt: GitCredentialPrompt(id: String(state.nextID), prompt: text))
+            state.nextID += 1
+            state.pending[pending.prompt.id] = pending
+            return pending
+        }
+        presenter?(pending.prompt)
+        let answered = pending.done.wait(timeout: .now() + Self.timeout) == .success
+        state.withLock { _ = $0.pending.removeValue(forKey: pending.prompt.id) }
+        guard answered, let answer = pending.answer else {
+            onResolved?(pending.prompt, false)
+            return nil
+        }
+        if pending.remember { _ = Self.remember(answer, for: text) }
+        onResolved?(pending.prompt, true)
+        return answer
+    }
+
+    /// Answers prompt `id`; nil cancels it. False when it isn't waiting.
+    @discardableResult
+    func answer(_ id: String, with answer: String?, remember: Bool = false) -> Bool {
+        guard let pending = state.withLock({ $0.pending.removeValue(forKey: id) }) else { return false }
+        pending.answer = answer
+        pending.remember = remember
+        pending.done.signal()
+        return true
+    }
+
+    static func isAuthenticationFailure(_ stderr: String) -> Bool {
+        let text = stderr.lowercased()
+        return ["authentication failed", "invalid username or password", "http basic: access denied",
+                "the requested url returned error: 401", "the requested url returned error: 403"]
+            .contains { text.contains($0) }
+    }
+
+    // MARK: - helper
+
+    /// The script git runs with its prompt as `$1`. It prints the answer
+    /// after the `answer:` the app puts in front, or fails.
+    static let script = """
+    #!/bin/sh
+    prompt=$(printf '%s' "$1" | tr '\\n' ' ')
+    request=$(printf 'git-askpass %s %s' "$INFINITTY_ASKPASS_TOKEN" "$prompt")
+    reply=$(printf '%s\\n' "$request" | /usr/bin/nc -U "$INFINITTY_ASKPASS_SOCKET") || exit 1
+    case "$reply" in
+    answer:*) printf '%s\\n' "${reply#answer:}" ;;
+    *) exit 1 ;;
+    esac
+
+    """
+
+    static var defaultScriptURL: URL { AppSupport.url("git-askpass.sh") }
+
+    /// Writes the script when it's missing or out of date; its path, or
+    /// nil when it can't be written.
+    private func installScript() -> String? {
+        let url = scriptURL
+        if (try? String(contentsOf: url, encoding: .utf8)) != Self.script {
+            guard AppSupport.write(Data(Self.script.utf8), to: url, permissions: 0o700) else { return nil }
+        }
+        return url.path
+    }
+
+    // MARK: - keychain
+
+    static func remembered(prompt: String) -> String? {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: keychainService,
+            kSecAttrAccount as String: prompt,
+            kSecMatchLimit as String: kSecMatchLimitOne,
+            kSecReturnData as String: true,
+        ]
+        var result: CFTypeRef?
+        guard SecItemCopyMatching(query as CFDictionary, &result) == errSecSuccess,
+              let data = result as? Data else { return nil }
+        return String(data: data, encoding: .utf8)
+    }
+
+    /// Adds or replaces the answer to `prompt`. An error message on failure.
+    static func remember(_ answer: String, for prompt: String) -> String? {
+        _ = forget(prompt: prompt)
+        let item: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: keychainService,
+            kSecAttrAccount as String: prompt,
+            kSecAttrLabel as String: "infinitty git: \(prompt.trimmingCharacters(in: .whitespaces))",
+            kSecValueData as String: Data(answer.utf8),
+        ]
+        let status = SecItemAdd(item as CFDictionary, nil)
+        return status == errSecSuccess ? nil : SecCopyErrorMessageString(status, nil) as String? ?? "error \(status)"
+    }
+
+    /// True when there was an entry to remove.
+    static func forget(prompt: String) -> Bool {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: keychainService,
+            kSecAttrAccount as String: prompt,
+        ]
+        return SecItemDelete(query as CFDictionary) == errSecSuccess
+    }
+
+    /// The prompts with a remembered answer, for `url` (a prompt's `url`)
+    /// or all of them.
+    static func rememberedPrompts(for url: String? = nil) -> [String] {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: keychainService,
+            kSecMatchLimit as String: kSecMatchLimitAll,
+            kSecReturnAttributes as String: true,
+        ]
+        var result: CFTypeRef?
+        guard SecItemCopyMatching(query as CFDictionary, &result) == errSecSuccess,
+              let items = result as? [[String: Any]] else { return [] }
+        return items.compactMap { $0[kSecAttrAccount as String] as? String }
+            .filter { url == nil || GitCredentialPrompt(id: "", prompt: $0).url == url }
+            .sorted()
+    }
+}
@@ -0,0 +1,140 @@
+import CoreServices
+import Foundation
+
+/// Per-repo memo of `CodeGit.status`. The Changes page re-probes on every
+/// page switch, cwd change and mutation; without a cache each of those
+/// spawns git even when nothing on disk moved. Entries are dropped by our
+/// own mutations (CodeGit calls `invalidate`) and by an FSEvents watcher on
+/// the work tree, so edits made from a shell pane show up on the next read.
+final class GitStatusCache {
+    static let shared = GitStatusCache()
+
+    /// Posted (object: the cache, on a background queue) once a repo
+    /// someone has read settles after being invalidated, with its status
+    /// re-read, so the Changes page and `git-status-changed` subscribers
+    /// follow the disk without polling or debouncing on their own. A
+    /// burst of invalidations (a rebase, a checkout) posts once.
+    static let didChangeNotification = Notification.Name("InfinittyGitStatusDidChange")
+    static let repoKey = "repo"
+    /// The re-read `CodeGit.RepoStatus`.
+    static let statusKey = "status"
+
+    private struct State {
+        var entries: [String: CodeGit.RepoStatus] = [:]
+        /// Bumped on every invalidation. A probe that started before a bump
+        /// may have read the pre-change tree, so its result is returned but
+        /// not stored.
+        var generations: [String: Int] = [:]
+        var watchers: [String: DirectoryWatcher] = [:]
+        /// Repos waiting out a burst of invalidations before their re-read,
+        /// with the generation of the latest; earlier timers find it moved
+        /// on and do nothing.
+        var pending: [String: Int] = [:]
+    }
+
+    private let state = LockedState(State())
+    private let watchesFileSystem: Bool
+    private let settle: TimeInterval
+    private let probe: (String) -> CodeGit.RepoStatus
+    private static let queue = DispatchQueue(label: "infinitty.git-status", qos: .utility)
+
+    /// `settle` is how long a repo has to stay quiet after an invalidation
+    /// before it is re-read and `didChangeNotification` goes out.
+    init(
+        watchesFileSystem: Bool = true,
+        settle: TimeInterval = 1,
+        probe: @escaping (String) -> CodeGit.RepoStatus = { CodeGit.status(in: $0) }
+    ) {
+        self.watchesFileSystem = watchesFileSystem
+        self.settle = settle
+        self.probe = probe
+    }
+
+    /// Cached status for `repo` (a root from `CodeGit.repoRoot`), probing
+    /// git on a miss. Failed probes are never cached. Synchronous on a
+    /// miss — call from a background queue.
+    func status(in repo: String) -> CodeGit.RepoStatus {
+        let lookup = state.withLock { state -> (cached: CodeGit.RepoStatus?, generation: Int) in
+            if let cached = state.entries[repo] { return (cached, 0) }
+            if watchesFileSystem, state.watchers[repo] == nil {
+                state.watchers[repo] = DirectoryWatcher(path: repo) { [weak self] in
+                    self?.invalidate(repo)
+                }
+            }
+            return (nil, state.generations[repo, default: 0])
+        }
+        if let cached = lookup.cached { return cached }
+
+        let status = probe(repo)
+        state.withLock { state in
+            if status.error == nil, state.generations[repo, default: 0] == lookup.generation {
+                state.entries[repo] = status
+            }
+        }
+        return status
+    }
+
+    /// Drops `repo`'s entry. When someone had read it, a re-read (and its
+    /// post) follows once the repo has been quiet for `settle`.
+    func invalidate(_ repo: String) {
+        let scheduled = state.withLock { state -> Int? in
+            state.generations[repo, default: 0] += 1
+            let wasCached = state.entries.removeValue(forKey: repo) != nil
+            guard wasCached || state.pending[repo] != nil else { return nil }
+            state.pending[repo] = state.generations[repo]
+            return state.generations[repo]
+        }
+        guard let scheduled else { return }
+        Self.queue.asyncAfter(deadline: .now() + settle) { [weak self] in
+            self?.publish(repo, scheduled: scheduled)
+        }
+    }
+
+    private func publish(_ repo: String, scheduled: Int) {
+        let latest = state.withLock { state -> Bool in
+            guard state.pending[repo] == scheduled else { return false }
+            state.pending[repo] = nil
+            return true
+        }
+        guard latest else { return }
+        let status = self.status(in: repo)
+        NotificationCenter.default.post(
+            name: Self.didChangeNotification, object: self,
+            userInfo: [Self.repoKey: repo, Self.statusKey: status])
+    }
+
+    /// Repos with a live FSEvents stream.
+    var watchedRepos: [String] {
+        state.withLock { $0.watchers.keys.sorted() }
+    }
+
+    /// Stops watching, and forgets, every repo none of `directories` (the
+    /// panes' working directories) is inside, so a repo a pane only passed
+    /// through doesn't keep its stream open for the life of the app. A
+    /// later read of it watches it again.
+    func release(keeping directories: [String]) {
+        let released = state.withLock { state -> [DirectoryWatcher] in
+            let unused = state.watchers.keys.filter { repo in
+                let prefix = repo.hasSuffix("/") ? repo : repo + "/"
+                return !directories.contains { $0 == repo || $0.hasPrefix(prefix) }
+            }
+            for repo in unused {
+                state.entries[repo] = nil
+                state.generations[repo, default: 0] += 1
+                state.pending[repo] = nil
+            }
+            return unused.compactMap { state.watchers.removeValue(forKey: $0) }
+        }
+        // Stopping a stream waits out its callback, which takes the lock.
+        withExtendedLifetime(released) {}
+    }
+
+    func invalidateAll() {
+        state.withLock { state in
+            for repo in Set(state.entries.keys).union(state.generations.keys) {
+                state.generations[repo, default: 0] += 1
+            }
+            state.entries.removeAll()
+        }
+    }
+}
@@ -0,0 +1,162 @@
+import Darwin
+import Foundation
+
+/// What a shell pattern would match, worked out without running anything:
+/// brace expansion (`{a,b}`, `{1..10}`, `{a..e..2}`) as bash does it, then
+/// `~` and glob(3) for `*`, `?` and `[…]`, from a given directory. Backs
+/// `expand-globs`, the paths `CommandGuard` counts, and the tooltip over a
+/// pattern at the prompt. A backslash quotes the next character for both.
+enum GlobExpansion {
+    /// Alternatives a pattern may brace-expand to before the rest is cut.
+    static let maxAlternatives = 1_000
+
+    struct Result: Equatable {
+        /// The pattern after brace expansion, in order.
+        let alternatives: [String]
+        /// Absolute paths, each alternative's sorted and in alternative
+        /// order, duplicates dropped.
+        let matches: [String]
+        /// `limit` (or `maxAlternatives`) cut the list short.
+        let truncated: Bool
+
+        var wire: [String: Any] {
+            ["alternatives": alternatives, "matches": matches, "count": matches.count, "truncated": truncated]
+        }
+    }
+
+    /// Paths `pattern` matches from `directory`, at most `limit`. A
+    /// pattern without wildcards matches itself when it exists.
+    static func expand(_ pattern: String, in directory: String, limit: Int = 10_000) -> Result {
+        let alternatives = braces(pattern)
+        var truncated = alternatives.count >= maxAlternatives
+        var seen = Set<String>()
+        var matches: [String] = []
+        for alternative in alternatives {
+            var path = alternative
+            if path == "~" || path.hasPrefix("~/") { path = NSHomeDirectory() + path.dropFirst() }
+            if !path.hasPrefix("/") { path = (directory as NSString).appendingPathComponent(path) }
+            for match in glob(path) where seen.insert(match).inserted {
+                guard matches.count < limit else {
+                    truncated = true
+                    break
+                }
+                matches.append(match)
+            }
+        }
+        return Result(alternatives: alternatives, matches: matches, truncated: truncated)
+    }
+
+    /// Bash brace expansion, left to right and nested. A brace with
+    /// neither a top-level comma nor a valid `x..y` range stays literal.
+    static func braces(_ pattern: String) -> [String] {
+        var out: [String] = []
+        expandBraces(Array(pattern), into: &out)
+        return out
+    }
+
+    // MARK: - internals
+
+    private static func expandBraces(_ chars: [Character], into out: inout [String]) {
+        var i = 0
+        while i < chars.count, out.count < maxAlternatives {
+            if chars[i] == "\\" {
+                i += 2
+                continue
+            }
+            if chars[i] == "{", let close = matchingBrace(chars, from: i),
+               let parts = alternatives(Array(chars[(i + 1)..<close])) {
+                let prefix = Array(chars[..<i])
+                let suffix = Array(chars[(close + 1)...])
+                for part in parts {
+                    guard out.count < maxAlternatives else { return }
+                    expandBraces(prefix + part + suffix, into: &out)
+                }
+                return
+            }
+            i += 1
+        }
+        if out.count < maxAlternatives { out.append(String(chars)) }
+    }
+
+    private static func matchingBrace(_ chars: [Character], from open: Int) -> Int? {
+        var depth = 0
+        var i = open
+        while i < chars.count {
+            switch chars[i] {
+            case "\\": i += 1
+            case "{": depth += 1
+            case "}":
+                depth -= 1
+                if depth == 0 { return i }
+            default: break
+            }
+            i += 1
+        }
+        return nil
+    }
+
+    /// The comma-separated parts of a brace body, or the values of its
+    /// range; nil when it's neither.
+    private static func alternatives(_ body: [Character]) -> [[Character]]? {
+        var parts: [[Character]] = []
+        var current: [Character] = []
+        var depth = 0
+        var i = 0
+        while i < body.count {
+            let c = body[i]
+            if c == "\\", i + 1 < body.count {
+                current += [c, body[i + 1]]
+                i += 2
+                continue
+            }
+            if c == "{" { depth += 1 }
+            if c == "}" { depth -= 1 }
+            if c == ",", depth == 0 {
+                parts.append(current)
+                current = []
+            } else {
+                current.append(c)
+            }
+            i += 1
+        }
+        parts.append(current)
+        if parts.count > 1 { return parts }
+        return range(String(body)).map { $0.map(Array.init) }
+    }
+
+    /// `1..5`, `05..10`, `10..1..3`, `a..e`: bash's sequence expressions.
+    private static func range(_ body: String) -> [String]? {
+        let fields = body.components(separatedBy: "..")
+        guard fields.count == 2 || fields.count == 3 else { return nil }
+        var step = 1
+        if fields.count == 3 {
+            guard let given = Int(fields[2]) else { return nil }
+            step = given == 0 ? 1 : abs(given)
+        }
+        if let from = Int(fields[0]), let to = Int(fields[1]) {
+            // A leading zero on either end pads every value to the wider one.
+            let padded = [fields[0], fields[1]].contains { $0.hasPrefix("0") || $0.hasPrefix("-0") }
+            let width = padded ? max(fields[0].count, fields[1].count) : 0
+            let values = stride(from: from, through: to, by: from <= to ? step : -step).prefix(maxAlternatives)
+            return values.map { value -> String in
+                let digits = String(abs(value))
+                let pad = String(repeating: "0", count: max(0, width - digits.count - (value < 0 ? 1 : 0)))
+                return (value < 0 ? "-" : "") + pad + digits
+            }
+        }
+        guard fields[0].count == 1, fields[1].count == 1,
+              let from = fields[0].unicodeScalars.first, let to = fields[1].unicodeScalars.first,
+              from.isASCII, to.isASCII, Character(from).isLetter, Character(to).isLetter else { return nil }
+        return stride(from: Int(from.value), through: Int(to.value), by: from.value <= to.value ? step : -step)
+            .compactMap { Unicode.Scalar(UInt32($0)).map { String(Character($0)) } }
+    }
+
+    private static func glob(_ pattern: String) -> [String] {
+        var result = glob_t()
+        defer { globfree(&result) }
+        guard Darwin.glob(pattern, 0, nil, &result) == 0 else { return [] }
+        return (0..<Int(result.gl_pathc)).compactMap { index in
+            result.gl_pathv[index].map { (String(cString: $0) as NSString).standardizingPath }
+        }
+    }
+}
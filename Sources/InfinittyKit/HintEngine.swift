@@ -254,23 +254,14 @@ final class HintEngine {
     private static func runHintCommand(_ command: String, input: String) -> String? {
         let parts = command.split(separator: " ").map(String.init)
         guard let exe = parts.first else { return nil }
-        let proc = Process()
-        proc.executableURL = URL(fileURLWithPath: "/usr/bin/env")
-        proc.arguments = [exe] + parts.dropFirst()
         var env = ProcessInfo.processInfo.environment
         env["PATH"] = "/opt/homebrew/bin:/usr/local/bin:" + (env["PATH"] ?? "")
-        proc.environment = env
-        let stdin = Pipe(), stdout = Pipe()
-        proc.standardInput = stdin
-        proc.standardOutput = stdout
-        proc.standardError = FileHandle.nullDevice
-        do { try proc.run() } catch { return nil }
-        stdin.fileHandleForWriting.write(Data(input.utf8))
-        stdin.fileHandleForWriting.closeFile()
-        let data = stdout.fileHandleForReading.readDataToEndOfFile()
-        proc.waitUntilExit()
-        guard proc.terminationStatus == 0 else { return nil }
-        let out = String(decoding: data, as: UTF8.self)
+        // A hint that arrives after a few seconds is stale anyway.
+        guard case .success(let result) = ProcessRunner.run(
+            "/usr/bin/env", [exe] + parts.dropFirst(),
+            input: Data(input.utf8), environment: env, timeout: 5),
+            result.status == 0 else { return nil }
+        let out = result.stdoutText
             .trimmingCharacters(in: .whitespacesAndNewlines)
         return out.isEmpty ? nil : out
     }
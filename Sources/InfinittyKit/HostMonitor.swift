@@ -0,0 +1,222 @@
+import Darwin
+import Foundation
+
+/// Wake-on-LAN magic packets: six 0xFF bytes, then the target's MAC sixteen
+/// times, sent as a UDP broadcast. Nothing answers, so success only means
+/// the packet left this machine.
+enum WakeOnLAN {
+    /// `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff` or `aabbccddeeff`.
+    static func parseMAC(_ text: String) -> [UInt8]? {
+        let hex = text.filter { $0 != ":" && $0 != "-" }
+        guard hex.count == 12, hex.allSatisfy(\.isHexDigit) else { return nil }
+        var bytes: [UInt8] = []
+        var index = hex.startIndex
+        while index < hex.endIndex {
+            let next = hex.index(index, offsetBy: 2)
+            guard let byte = UInt8(hex[index..<next], radix: 16) else { return nil }
+            bytes.append(byte)
+            index = next
+        }
+        return bytes
+    }
+
+    static func magicPacket(_ mac: [UInt8]) -> Data {
+        Data(repeating: 0xFF, count: 6) + Data((0..<16).flatMap { _ in mac })
+    }
+
+    /// Broadcasts the packet for `mac` to `address` (the limited broadcast
+    /// by default; a subnet's directed broadcast reaches through routers
+    /// that forward it) on the discard port.
+    static func send(
+        mac: [UInt8], address: String = "255.255.255.255", port: UInt16 = 9
+    ) -> Result<Void, NetworkToolError> {
+        var target = sockaddr_in()
+        target.sin_len = UInt8(MemoryLayout<sockaddr_in>.size)
+        target.sin_family = sa_family_t(AF_INET)
+        target.sin_port = port.bigEndian
+        guard inet_pton(AF_INET, address, &target.sin_addr) == 1 else {
+            return .failure(.invalidHost(address))
+        }
+        let fd = socket(AF_INET, SOCK_DGRAM, IPPROTO_UDP)
+        guard fd >= 0 else { return .failure(.failed("socket: \(String(cString: strerror(errno)))")) }
+        defer { close(fd) }
+        var on: Int32 = 1
+        setsockopt(fd, SOL_SOCKET, SO_BROADCAST, &on, socklen_t(MemoryLayout<Int32>.size))
+        let packet = magicPacket(mac)
+        let sent = packet.withUnsafeBytes { bytes in
+            withUnsafePointer(to: &target) { pointer in
+                pointer.withMemoryRebound(to: sockaddr.self, capacity: 1) {
+                    sendto(fd, bytes.baseAddress, bytes.count, 0, $0, socklen_t(MemoryLayout<sockaddr_in>.size))
+                }
+            }
+        }
+        guard sent == packet.count else {
+            return .failure(.failed("sendto: \(String(cString: strerror(errno)))"))
+        }
+        return .success(())
+    }
+}
+
+/// A host the monitor pings; `mac` lets `net-wol` wake it by name.
+struct WatchedHost: Codable, Equatable {
+    let host: String
+    var name: String?
+    var mac: String?
+    var interval: TimeInterval
+
+    static let defaultInterval: TimeInterval = 30
+    static let intervals: ClosedRange<TimeInterval> = 5...3600
+}
+
+/// What the monitor last saw of a host. `up` is nil until the first probe
+/// finishes.
+struct HostStatus {
+    var watched: WatchedHost
+    var up: Bool?
+    var rtt: Double?
+    var checked: Date?
+    /// When `up` last changed.
+    var since: Date?
+    /// Probes lost in a row.
+    var misses = 0
+
+    /// Folds in one probe (`rtt` nil when it was lost) and says whether
+    /// `up` changed. A host goes down only after `HostMonitor.missesBeforeDown`
+    /// lost probes in a row, or on the first probe ever.
+    mutating func record(rtt: Double?, at date: Date) -> Bool {
+        checked = date
+        self.rtt = rtt
+        misses = rtt == nil ? misses + 1 : 0
+        let now: Bool?
+        if rtt != nil {
+            now = true
+        } else if up == nil || misses >= HostMonitor.missesBeforeDown {
+            now = false
+        } else {
+            now = up
+        }
+        guard now != up else { return false }
+        up = now
+        since = date
+        return true
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["host": watched.host, "interval": watched.interval]
+        if let name = watched.name { object["name"] = name }
+        if let mac = watched.mac { object["mac"] = mac }
+        if let up { object["up"] = up }
+        if let rtt { object["rtt"] = rtt }
+        if let checked { object["checked"] = checked.timeIntervalSince1970 }
+        if let since { object["since"] = since.timeIntervalSince1970 }
+        return object
+    }
+}
+
+/// Pings registered hosts on their own intervals and reports up/down
+/// transitions through `onChange`; one dropped packet on Wi-Fi does not
+/// flap a host (see `HostStatus.record`). The list is kept in Application
+/// Support and picked up again at launch.
+final class HostMonitor {
+    static let shared = HostMonitor(url: HostMonitor.defaultURL)
+    static let missesBeforeDown = 2
+
+    static var defaultURL: URL { AppSupport.url("watched-hosts.json") }
+
+    /// A host changed state (including its first result), on the
+    /// monitor's queue.
+    var onChange: ((HostStatus) -> Void)?
+
+    private struct Entry {
+        var status: HostStatus
+        var probing = false
+        var timer: DispatchSourceTimer?
+    }
+
+    let url: URL
+    private let file: JSONFileStore<[WatchedHost]>
+    private let state = LockedState([String: Entry]())
+    private let queue = DispatchQueue(label: "infinitty.host-monitor", qos: .utility, attributes: .concurrent)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url)
+    }
+
+    /// Loads the saved list and starts its timers.
+    func start() {
+        file.load()?.forEach { schedule($0) }
+    }
+
+    /// Adds `host`, or replaces the settings of one already watched, and
+    /// probes it at once.
+    @discardableResult
+    func watch(_ host: WatchedHost) -> HostStatus {
+        let status = schedule(host)
+        save()
+        return status
+    }
+
+    func unwatch(_ host: String) -> Bool {
+        let removed = state.withLock { $0.removeValue(forKey: host) }
+        removed?.timer?.cancel()
+        if removed != nil { save() }
+        return removed != nil
+    }
+
+    func list() -> [HostStatus] {
+        state.snapshot.values.map(\.status).sorted { $0.watched.host < $1.watched.host }
+    }
+
+    func watched(_ host: String) -> WatchedHost? {
+        state.withLock { $0[host]?.status.watched }
+    }
+
+    @discardableResult
+    private func schedule(_ host: WatchedHost) -> HostStatus {
+        let timer = DispatchSource.makeTimerSource(queue: queue)
+        timer.schedule(deadline: .now(), repeating: host.interval, leeway: .seconds(1))
+        timer.setEventHandler { [weak self] in self?.run(host.host) }
+        let (previous, status) = state.withLock { entries -> (DispatchSourceTimer?, HostStatus) in
+            var entry = entries[host.host] ?? Entry(status: HostStatus(watched: host))
+            let previous = entry.timer
+            entry.status.watched = host
+            entry.timer = timer
+            entries[host.host] = entry
+            return (previous, entry.status)
+        }
+        previous?.cancel()
+        timer.resume()
+        return status
+    }
+
+    private func run(_ host: String) {
+        let claimed = state.withLock { entries -> Bool in
+            guard entries[host]?.probing == false else { return false }
+            entries[host]?.probing = true
+            return true
+        }
+        guard claimed else { return }
+        let rtt = Self.ping(host)
+        let changed = state.withLock { entries -> HostStatus? in
+            guard var entry = entries[host] else { return nil }
+            defer { entries[host] = entry }
+            entry.probing = false
+            return entry.status.record(rtt: rtt, at: Date()) ? entry.status : nil
+        }
+        if let changed { onChange?(changed) }
+    }
+
+    private func save() {
+        file.save(list().map(\.watched))
+    }
+
+    /// One echo with a two-second wait.
+    static func ping(_ host: String) -> Double? {
+        guard case .success(let output) = ProcessRunner.run(
+            "/sbin/ping", ["-c", "1", "-t", "2", host], timeout: 5), output.status == 0
+        else { return nil }
+        return output.stdoutText.split(whereSeparator: \.isNewline)
+            .lazy.compactMap { NetworkDiagnostics.parsePingLine(String($0))?.rtt }.first
+    }
+}
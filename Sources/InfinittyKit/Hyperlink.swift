@@ -0,0 +1,93 @@
+import Foundation
+
+/// A link a program printed with OSC 8 (`ESC ] 8 ; params ; URI ST`). The
+/// text on screen is whatever the program wrote between the opening and
+/// closing sequence, so it needn't look anything like `url`.
+struct TerminalHyperlink: Hashable {
+    let url: String
+    /// The `id=` parameter, which ties pieces of one link together across
+    /// lines or redraws.
+    let id: String?
+}
+
+/// One run of cells carrying the same OSC 8 link, for `hyperlinks`.
+struct HyperlinkRange: Equatable {
+    let line: Int
+    let start: Int
+    let end: Int
+    let link: TerminalHyperlink
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["line": line, "start": start, "end": end, "url": link.url]
+        if let id = link.id { object["id"] = id }
+        return object
+    }
+}
+
+/// What opening a link with a given scheme does.
+enum LinkAction: String, CaseIterable {
+    /// Straight to the system handler.
+    case open
+    /// Show the full URL and ask first.
+    case ask
+    /// Refuse with a beep.
+    case deny
+    /// Open nothing; a `link-activated` event hands the URL to whoever
+    /// registered the scheme over the socket.
+    case event
+}
+
+/// Decides what a cmd-click on a link does, by scheme, so a program that
+/// prints an OSC 8 link to `x-man-page:` or a custom app scheme can't have
+/// it launched without the user seeing where it goes (`link-schemes`,
+/// `link-policy`).
+struct LinkPolicy: Equatable {
+    static let defaults: [String: LinkAction] = [
+        "http": .open, "https": .open, "mailto": .open, "file": .open,
+    ]
+
+    var rules = LinkPolicy.defaults
+
+    /// `vscode:open, ssh:ask, javascript:deny`. Nil when a pair doesn't
+    /// parse.
+    static func parse(_ text: String) -> [String: LinkAction]? {
+        var rules: [String: LinkAction] = [:]
+        for pair in text.split(whereSeparator: { $0 == "," || $0 == " " }) {
+            let parts = pair.split(separator: ":", maxSplits: 1)
+            guard parts.count == 2, let scheme = scheme(String(parts[0])),
+                  let action = LinkAction(rawValue: parts[1].lowercased()) else { return nil }
+            rules[scheme] = action
+        }
+        return rules
+    }
+
+    /// `text` lowercased if it's a URL scheme: a letter, then letters,
+    /// digits, `+`, `-` or `.`.
+    static func scheme(_ text: String) -> String? {
+        let scheme = text.lowercased()
+        guard let first = scheme.unicodeScalars.first, CharacterSet.lowercaseLetters.contains(first),
+              scheme.allSatisfy({ $0.isASCII && ($0.isLetter || $0.isNumber || "+-.".contains($0)) })
+        else { return nil }
+        return scheme
+    }
+
+    /// Schemes without a rule ask. A `file:` link that would launch
+    /// something — an app bundle or an executable — asks even when file
+    /// links open.
+    func action(for url: URL) -> LinkAction {
+        guard let scheme = url.scheme?.lowercased() else { return .deny }
+        let action = rules[scheme] ?? .ask
+        if scheme == "file", action == .open, Self.launches(url.path) { return .ask }
+        return action
+    }
+
+    private static func launches(_ path: String) -> Bool {
+        let bundles: Set<String> = ["app", "command", "tool", "terminal", "workflow", "pkg", "scpt"]
+        if bundles.contains((path as NSString).pathExtension.lowercased()) { return true }
+        var isDirectory: ObjCBool = false
+        return FileManager.default.fileExists(atPath: path, isDirectory: &isDirectory)
+            && !isDirectory.boolValue && FileManager.default.isExecutableFile(atPath: path)
+    }
+
+    var wire: [String: Any] { rules.mapValues(\.rawValue) }
+}
@@ -0,0 +1,58 @@
+import Foundation
+
+/// What happens to a pane left idle past `idle-timeout`.
+enum IdleAction: String, CaseIterable {
+    /// A notification, once per idle stretch.
+    case notify
+    /// Its scrollback saved as an HTML snapshot, then the pane closed.
+    case close
+    /// Its shell ended but the pane and scrollback kept; a key press
+    /// starts a new shell in the same directory.
+    case hibernate
+}
+
+/// `idle-timeout` and `idle-action`: when a pane counts as forgotten and
+/// what to do about it. A pane is idle while nothing has been written to or
+/// read from its PTY and its shell is at the prompt, not running anything.
+struct IdlePolicy: Equatable {
+    /// Seconds; 0 turns the policy off.
+    var timeout: TimeInterval = 0
+    var action: IdleAction = .notify
+
+    var isEnabled: Bool { timeout > 0 }
+
+    /// `8h`, `90m`, `2d`, `3600s`, or a bare number of hours; `off` is 0.
+    static func duration(_ text: String) -> TimeInterval? {
+        let trimmed = text.trimmingCharacters(in: .whitespaces).lowercased()
+        if ["off", "never", "0"].contains(trimmed) { return 0 }
+        let units: [Character: TimeInterval] = ["s": 1, "m": 60, "h": 3600, "d": 86_400]
+        let unit = trimmed.last.flatMap { units[$0] }
+        guard let amount = Double(unit == nil ? trimmed : String(trimmed.dropLast())), amount >= 0 else {
+            return nil
+        }
+        return amount * (unit ?? 3600)
+    }
+
+    /// Whether a pane last active at `lastActivity` is due for the action
+    /// at `now`. `handled` is the activity time it was last acted on for,
+    /// so each idle stretch is acted on once.
+    func isDue(lastActivity: Date, atPrompt: Bool, handled: Date?, now: Date) -> Bool {
+        isEnabled && atPrompt && handled != lastActivity && now.timeIntervalSince(lastActivity) >= timeout
+    }
+
+    var wire: [String: Any] { ["timeout": timeout, "action": action.rawValue] }
+}
+
+/// Where `close` keeps the snapshots of panes it closed.
+enum IdleSnapshots {
+    static var directory: URL { AppSupport.url("idle-snapshots", isDirectory: true) }
+
+    /// Writes `html` as `pane-<id>-<yyyyMMdd-HHmmss>.html`, owner-only.
+    static func save(_ html: String, pane: Int, at date: Date = Date(), in directory: URL = directory) -> URL? {
+        let format = DateFormatter()
+        format.locale = Locale(identifier: "en_US_POSIX")
+        format.dateFormat = "yyyyMMdd-HHmmss"
+        let url = directory.appendingPathComponent("pane-\(pane)-\(format.string(from: date)).html")
+        return AppSupport.write(Data(html.utf8), to: url, permissions: 0o600) ? url : nil
+    }
+}
@@ -0,0 +1,80 @@
+import Foundation
+
+enum InputBroadcastError: Error, Equatable, CustomStringConvertible {
+    case noPanes
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .noPanes: return "a broadcast group needs at least one pane"
+        case .notFound(let id): return "no broadcast group \(id)"
+        }
+    }
+}
+
+/// Panes that receive the same input: text sent to the group goes to each
+/// member's PTY, and keystrokes typed into one member are mirrored to the
+/// rest.
+struct InputBroadcastGroup: Equatable {
+    let id: String
+    var panes: [Int]
+
+    var wire: [String: Any] { ["id": id, "panes": panes] }
+}
+
+/// The app's broadcast groups. A pane may sit in several groups; closing a
+/// pane drops it from all of them, and a group left empty goes with it.
+struct InputBroadcastGroups {
+    private(set) var groups: [InputBroadcastGroup] = []
+    private var nextID = 1
+
+    func group(_ id: String) -> InputBroadcastGroup? {
+        groups.first { $0.id == id }
+    }
+
+    mutating func create(_ panes: [Int]) -> Result<InputBroadcastGroup, InputBroadcastError> {
+        let members = Self.unique(panes)
+        guard !members.isEmpty else { return .failure(.noPanes) }
+        let group = InputBroadcastGroup(id: "group-\(nextID)", panes: members)
+        nextID += 1
+        groups.append(group)
+        return .success(group)
+    }
+
+    mutating func add(_ panes: [Int], to id: String) -> Result<InputBroadcastGroup, InputBroadcastError> {
+        guard let index = groups.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+        groups[index].panes = Self.unique(groups[index].panes + panes)
+        return .success(groups[index])
+    }
+
+    /// Removing the last member deletes the group; the reply is then empty.
+    mutating func remove(_ panes: [Int], from id: String) -> Result<InputBroadcastGroup, InputBroadcastError> {
+        guard let index = groups.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+        groups[index].panes.removeAll { panes.contains($0) }
+        let group = groups[index]
+        if group.panes.isEmpty { groups.remove(at: index) }
+        return .success(group)
+    }
+
+    mutating func delete(_ id: String) -> Bool {
+        let before = groups.count
+        groups.removeAll { $0.id == id }
+        return groups.count != before
+    }
+
+    mutating func forget(pane: Int) {
+        for index in groups.indices { groups[index].panes.removeAll { $0 == pane } }
+        groups.removeAll { $0.panes.isEmpty }
+    }
+
+    /// Every other pane sharing a group with `pane`, in the order they
+    /// joined.
+    func peers(of pane: Int) -> [Int] {
+        Self.unique(groups.filter { $0.panes.contains(pane) }.flatMap(\.panes)).filter { $0 != pane }
+    }
+
+    private static func unique(_ panes: [Int]) -> [Int] {
+        var seen = Set<Int>()
+        return panes.filter { seen.insert($0).inserted }
+    }
+}
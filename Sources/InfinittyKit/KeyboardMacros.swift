@@ -0,0 +1,150 @@
+import Foundation
+
+enum KeyboardMacroError: Error, Equatable, CustomStringConvertible {
+    case emptyName
+    case notFound(String)
+    case nothingRecorded(String)
+    case invalidSpeed(Double)
+
+    var description: String {
+        switch self {
+        case .emptyName: return "the macro needs a name"
+        case .notFound(let name): return "no macro '\(name)'"
+        case .nothingRecorded(let name): return "nothing was typed while recording '\(name)'"
+        case .invalidSpeed(let speed): return "speed must be more than 0, not \(speed)"
+        }
+    }
+}
+
+/// Input written to a pane, as it was typed: each write with the pause
+/// before it, so a sequence that waits on a program (a REPL, a TUI, a
+/// prompt) plays back at the pace it needs.
+struct KeyboardMacro: Codable, Equatable {
+    struct Step: Codable, Equatable {
+        /// Seconds since the step before; the first step's is zero.
+        var delay: TimeInterval
+        var bytes: Data
+    }
+
+    let name: String
+    var steps: [Step]
+    var created: Date
+
+    var duration: TimeInterval { steps.reduce(0) { $0 + $1.delay } }
+
+    /// When each step is written at `speed` (2 is twice as fast), in
+    /// seconds from the start.
+    func schedule(speed: Double = 1) -> [(at: TimeInterval, bytes: [UInt8])] {
+        var at: TimeInterval = 0
+        return steps.map { step in
+            at += step.delay / speed
+            return (at, Array(step.bytes))
+        }
+    }
+
+    var wire: [String: Any] {
+        [
+            "name": name, "steps": steps.count, "bytes": steps.reduce(0) { $0 + $1.bytes.count },
+            "duration": (duration * 1000).rounded() / 1000, "created": created.timeIntervalSince1970,
+        ]
+    }
+}
+
+/// Collects one pane's input into a macro while it records. Fed from the
+/// PTY's input tap, so keys, pastes and socket sends all count; replies to
+/// the program's own queries don't go through it.
+struct MacroRecorder {
+    let name: String
+    let pane: Int
+    private(set) var steps: [KeyboardMacro.Step] = []
+    private var last: TimeInterval?
+
+    init(name: String, pane: Int) {
+        self.name = name
+        self.pane = pane
+    }
+
+    mutating func record(_ bytes: [UInt8], at now: TimeInterval) {
+        guard !bytes.isEmpty else { return }
+        steps.append(KeyboardMacro.Step(delay: last.map { max(0, now - $0) } ?? 0, bytes: Data(bytes)))
+        last = now
+    }
+
+    func macro(created: Date = Date()) -> Result<KeyboardMacro, KeyboardMacroError> {
+        guard !steps.isEmpty else { return .failure(.nothingRecorded(name)) }
+        return .success(KeyboardMacro(name: name, steps: steps, created: created))
+    }
+}
+
+/// Recorded macros by name, kept in Application Support beside the other
+/// stores. They aren't tied to a workspace: the sequences they replay
+/// (a debugger session, a TUI's menus) tend to follow the program.
+final class KeyboardMacros {
+    static let shared = KeyboardMacros(url: KeyboardMacros.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("macros.json") }
+
+    private typealias Store = [String: KeyboardMacro]
+
+    let url: URL
+    private let file: JSONFileStore<Store>
+    private let entries = LockedState<Store?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, dates: .iso8601, formatting: [.sortedKeys])
+    }
+
+    /// Trimmed, or an error when nothing is left.
+    static func name(_ text: String?) -> Result<String, KeyboardMacroError> {
+        let name = text?.trimmingCharacters(in: .whitespacesAndNewlines) ?? ""
+        return name.isEmpty ? .failure(.emptyName) : .success(name)
+    }
+
+    /// Every macro, by name.
+    var macros: [KeyboardMacro] {
+        read { $0.values.sorted { $0.name.localizedStandardCompare($1.name) == .orderedAscending } }
+    }
+
+    func macro(named name: String) -> Result<KeyboardMacro, KeyboardMacroError> {
+        guard let macro = read({ $0[name] }) else { return .failure(.notFound(name)) }
+        return .success(macro)
+    }
+
+    /// Adds the macro, replacing one with its name.
+    func save(_ macro: KeyboardMacro) {
+        update { $0[macro.name] = macro }
+    }
+
+    func remove(named name: String) -> Result<KeyboardMacro, KeyboardMacroError> {
+        var removed: KeyboardMacro?
+        update { removed = $0.removeValue(forKey: name) }
+        guard let removed else { return .failure(.notFound(name)) }
+        return .success(removed)
+    }
+
+    // MARK: - storage
+
+    private func read<R>(_ body: (Store) -> R) -> R {
+        entries.withLock { entries in
+            load(&entries)
+            return body(entries ?? [:])
+        }
+    }
+
+    private func update(_ body: (inout Store) -> Void) {
+        let snapshot = entries.withLock { entries -> Store in
+            load(&entries)
+            var all = entries ?? [:]
+            body(&all)
+            entries = all
+            return all
+        }
+        file.save(snapshot)
+    }
+
+    private func load(_ entries: inout Store?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+}
@@ -0,0 +1,77 @@
+import Foundation
+
+/// Launch work that the first window doesn't need: it runs after the window
+/// is up, each piece reporting when it is done. Off-main work runs as a
+/// background task (so it shows in `tasks`); main-thread work waits one run
+/// loop turn so the window paints first. Clients that depend on a subsystem
+/// check `ready` or wait for its `subsystem-ready` event instead of racing
+/// the launch.
+final class LaunchReadiness {
+    enum Subsystem: String, CaseIterable {
+        /// Stale pane/app socket files from dead instances removed.
+        case socketSweep = "socket-sweep"
+        /// infinitty-mcp registered with the Codex / Claude CLIs.
+        case mcpRegistration = "mcp-registration"
+        /// Notch live-activity windows on screen.
+        case notch
+    }
+
+    /// Fired once per subsystem, on the queue that finished it.
+    var onReady: ((Subsystem, _ error: String?) -> Void)?
+
+    private let finished = LockedState([Subsystem: String?]())
+    private let registry: BackgroundTaskRegistry
+
+    init(registry: BackgroundTaskRegistry = .shared) {
+        self.registry = registry
+    }
+
+    /// Run `work` as a background task after launch.
+    func runInBackground(_ subsystem: Subsystem, _ work: @escaping () throws -> Void) {
+        registry.start(kind: "launch", title: subsystem.rawValue) { [weak self] _ in
+            do {
+                try work()
+                self?.markReady(subsystem, error: nil)
+            } catch {
+                self?.markReady(subsystem, error: String(describing: error))
+                throw error
+            }
+            return nil
+        }
+    }
+
+    /// Run main-thread `work` on the next run loop turn.
+    func runAfterFirstPaint(_ subsystem: Subsystem, _ work: @escaping () -> Void) {
+        DispatchQueue.main.async { [weak self] in
+            work()
+            self?.markReady(subsystem, error: nil)
+        }
+    }
+
+    /// Mark a subsystem that needs no deferred work (disabled by config).
+    func markReady(_ subsystem: Subsystem, error: String? = nil) {
+        let first = finished.withLock { finished -> Bool in
+            guard finished[subsystem] == nil else { return false }
+            finished[subsystem] = .some(error)
+            return true
+        }
+        if first { onReady?(subsystem, error) }
+    }
+
+    func isReady(_ subsystem: Subsystem) -> Bool {
+        finished.withLock { $0[subsystem] != nil }
+    }
+
+    /// `ready` command payload: every subsystem with ready / pending, plus
+    /// the error of any that failed.
+    func snapshot() -> [String: Any] {
+        let finished = self.finished.snapshot
+        var object: [String: Any] = [:]
+        for subsystem in Subsystem.allCases {
+            var entry: [String: Any] = ["ready": finished[subsystem] != nil]
+            if case .some(.some(let error)) = finished[subsystem] { entry["error"] = error }
+            object[subsystem.rawValue] = entry
+        }
+        return object
+    }
+}
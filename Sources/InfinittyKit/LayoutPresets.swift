@@ -0,0 +1,283 @@
+import AppKit
+
+enum LayoutPresetError: Error, Equatable, CustomStringConvertible {
+    case invalidName
+    case invalidShape
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .invalidName: return "layout names must be 1-64 characters and not a built-in's"
+        case .invalidShape: return "a layout needs 1-\(LayoutShape.maxPanes) panes"
+        case .notFound(let name): return "no layout named \(name)"
+        }
+    }
+}
+
+/// The geometry of a tab's split tree with the panes left out, so an
+/// arrangement captured in one tab can be rebuilt around another's panes.
+/// JSON spells a leaf `"pane"` and a split `{"columns": […], "sizes": […]}`
+/// (side by side) or `{"rows": […], "sizes": […]}` (stacked).
+indirect enum LayoutShape: Equatable {
+    static let maxPanes = 16
+
+    case pane
+    /// `sizes` are the children's shares of the split's length.
+    case split(vertical: Bool, children: [LayoutShape], sizes: [Double])
+
+    var paneCount: Int {
+        switch self {
+        case .pane: return 1
+        case .split(_, let children, _): return children.reduce(0) { $0 + $1.paneCount }
+        }
+    }
+
+    /// Single-child splits fold into their child and sizes are scaled to
+    /// sum to 1; sizes that don't fit the children become even shares.
+    var normalized: LayoutShape {
+        guard case .split(let vertical, let children, let sizes) = self else { return self }
+        let folded = children.map(\.normalized)
+        guard folded.count > 1 else { return folded.first ?? .pane }
+        let total = sizes.reduce(0, +)
+        let usable = sizes.count == folded.count && sizes.allSatisfy { $0.isFinite && $0 > 0 }
+        let shares = usable
+            ? sizes.map { $0 / total }
+            : Array(repeating: 1 / Double(folded.count), count: folded.count)
+        return .split(vertical: vertical, children: folded, sizes: shares)
+    }
+
+    /// `JSONSerialization`'s form of the shape, for socket replies.
+    var wire: Any {
+        guard let data = try? JSONEncoder().encode(self),
+              let object = try? JSONSerialization.jsonObject(with: data, options: .fragmentsAllowed)
+        else { return "pane" }
+        return object
+    }
+
+    init?(wire: Any) {
+        guard let data = try? JSONSerialization.data(withJSONObject: wire, options: .fragmentsAllowed),
+              let shape = try? JSONDecoder().decode(LayoutShape.self, from: data)
+        else { return nil }
+        self = shape
+    }
+
+    /// `count` shares with `index`'s set to `share` (kept to 10–90%) and the
+    /// rest split evenly between the others.
+    static func sizes(count: Int, giving index: Int, share: Double) -> [Double] {
+        guard count > 1, (0..<count).contains(index) else { return count == 1 ? [1] : [] }
+        let share = min(max(share, 0.1), 0.9)
+        let rest = (1 - share) / Double(count - 1)
+        return (0..<count).map { $0 == index ? share : rest }
+    }
+
+    /// Divider positions as fractions of the split's length, the form
+    /// `PaneLayoutController.restoreDividerRatios` takes.
+    static func dividerRatios(for sizes: [Double]) -> [CGFloat] {
+        var position = 0.0
+        return sizes.dropLast().map { size in
+            position += size
+            return CGFloat(position)
+        }
+    }
+
+    /// The shape of the split tree under `view`, each split's sizes read
+    /// from its arranged subviews' current frames.
+    static func capture(_ view: NSView) -> LayoutShape? {
+        var leaves: [NSView] = []
+        return capture(view, leaves: &leaves) { _ in true }
+    }
+
+    /// `capture` counting only the panes `isPane` accepts, which are added
+    /// to `leaves` in reading order, one per `.pane` in the shape.
+    static func capture(_ view: NSView, leaves: inout [NSView], isPane: (NSView) -> Bool) -> LayoutShape? {
+        if view is TerminalView || view is UtilityPaneView {
+            guard isPane(view) else { return nil }
+            leaves.append(view)
+            return .pane
+        }
+        if let split = view as? NSSplitView {
+            var children: [LayoutShape] = []
+            var sizes: [Double] = []
+            for child in split.arrangedSubviews {
+                guard let shape = capture(child, leaves: &leaves, isPane: isPane) else { continue }
+                children.append(shape)
+                sizes.append(Double(split.isVertical ? child.frame.width : child.frame.height))
+            }
+            guard !children.isEmpty else { return nil }
+            return LayoutShape.split(vertical: split.isVertical, children: children, sizes: sizes).normalized
+        }
+        for child in view.subviews {
+            if let shape = capture(child, leaves: &leaves, isPane: isPane) { return shape }
+        }
+        return nil
+    }
+}
+
+/// A `pane-resize` batch, `[{"pane": id | browser-id, "percent": 10-90}, …]`:
+/// one call for a drag or an animation instead of one per frame. A pane named
+/// more than once keeps only its last percent, in the place it was first
+/// named, so the app lays out each pane once with its latest size.
+enum PaneResizeBatch {
+    static func parse(_ json: String) -> [(pane: String, share: Double)]? {
+        guard let entries = try? JSONSerialization.jsonObject(with: Data(json.utf8)) as? [[String: Any]],
+              !entries.isEmpty
+        else { return nil }
+        var order: [String] = []
+        var shares: [String: Double] = [:]
+        for entry in entries {
+            let pane = (entry["pane"] as? String) ?? (entry["pane"] as? Int).map(String.init)
+            guard let pane, !pane.isEmpty, let percent = entry["percent"] as? Double,
+                  (10...90).contains(percent)
+            else { return nil }
+            if shares[pane] == nil { order.append(pane) }
+            shares[pane] = percent / 100
+        }
+        return order.map { ($0, shares[$0] ?? 0) }
+    }
+}
+
+extension LayoutShape: Codable {
+    private enum CodingKeys: String, CodingKey {
+        case columns, rows, sizes
+    }
+
+    init(from decoder: Decoder) throws {
+        if let single = try? decoder.singleValueContainer(), let word = try? single.decode(String.self) {
+            guard word == "pane" else {
+                throw DecodingError.dataCorruptedError(in: single, debugDescription: "expected \"pane\"")
+            }
+            self = .pane
+            return
+        }
+        let container = try decoder.container(keyedBy: CodingKeys.self)
+        let vertical = container.contains(.columns)
+        let children = try container.decode([LayoutShape].self, forKey: vertical ? .columns : .rows)
+        let sizes = try container.decodeIfPresent([Double].self, forKey: .sizes) ?? []
+        self = .split(vertical: vertical, children: children, sizes: sizes)
+    }
+
+    func encode(to encoder: Encoder) throws {
+        switch self {
+        case .pane:
+            var single = encoder.singleValueContainer()
+            try single.encode("pane")
+        case .split(let vertical, let children, let sizes):
+            var container = encoder.container(keyedBy: CodingKeys.self)
+            try container.encode(children, forKey: vertical ? .columns : .rows)
+            try container.encode(sizes, forKey: .sizes)
+        }
+    }
+}
+
+struct LayoutPreset: Codable, Equatable {
+    let name: String
+    let shape: LayoutShape
+    /// Built-ins ship with the app and can't be replaced or deleted.
+    var builtIn = false
+
+    var wire: [String: Any] {
+        ["name": name, "shape": shape.wire, "panes": shape.paneCount, "builtIn": builtIn]
+    }
+}
+
+/// Named split arrangements: a few built-ins plus the ones the user saved,
+/// kept as JSON under Application Support.
+final class LayoutPresetLibrary {
+    static let shared = LayoutPresetLibrary(url: LayoutPresetLibrary.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("layout-presets.json") }
+
+    static let builtIns: [LayoutPreset] = [
+        LayoutPreset(
+            name: "two-columns", shape: .split(vertical: true, children: [.pane, .pane], sizes: [0.5, 0.5]),
+            builtIn: true),
+        LayoutPreset(
+            name: "three-columns",
+            shape: .split(vertical: true, children: [.pane, .pane, .pane], sizes: [1 / 3.0, 1 / 3.0, 1 / 3.0]),
+            builtIn: true),
+        LayoutPreset(
+            name: "two-rows", shape: .split(vertical: false, children: [.pane, .pane], sizes: [0.5, 0.5]),
+            builtIn: true),
+        LayoutPreset(
+            name: "grid",
+            shape: .split(vertical: false, children: [
+                .split(vertical: true, children: [.pane, .pane], sizes: [0.5, 0.5]),
+                .split(vertical: true, children: [.pane, .pane], sizes: [0.5, 0.5]),
+            ], sizes: [0.5, 0.5]),
+            builtIn: true),
+        LayoutPreset(
+            name: "main-and-stack",
+            shape: .split(vertical: true, children: [
+                .pane,
+                .split(vertical: false, children: [.pane, .pane], sizes: [0.5, 0.5]),
+            ], sizes: [0.6, 0.4]),
+            builtIn: true),
+        LayoutPreset(
+            name: "three-columns-wide-bottom",
+            shape: .split(vertical: false, children: [
+                .split(vertical: true, children: [.pane, .pane, .pane], sizes: [1 / 3.0, 1 / 3.0, 1 / 3.0]),
+                .pane,
+            ], sizes: [0.65, 0.35]),
+            builtIn: true),
+    ]
+
+    let url: URL
+    private let file: JSONFileStore<[LayoutPreset]>
+    private let state = LockedState<[LayoutPreset]?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url)
+    }
+
+    /// Built-ins first, then saved layouts by name.
+    func all() -> [LayoutPreset] {
+        Self.builtIns + saved().sorted { $0.name.localizedCaseInsensitiveCompare($1.name) == .orderedAscending }
+    }
+
+    func preset(named name: String) -> LayoutPreset? {
+        all().first { $0.name == name }
+    }
+
+    /// Saves `shape` under `name`, replacing a saved layout of that name.
+    func save(name: String, shape: LayoutShape) -> Result<LayoutPreset, LayoutPresetError> {
+        let name = name.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard (1...64).contains(name.count), !Self.builtIns.contains(where: { $0.name == name }) else {
+            return .failure(.invalidName)
+        }
+        let shape = shape.normalized
+        guard shape.paneCount <= LayoutShape.maxPanes else { return .failure(.invalidShape) }
+        let entry = LayoutPreset(name: name, shape: shape)
+        update { presets in
+            presets.removeAll { $0.name == name }
+            presets.append(entry)
+        }
+        return .success(entry)
+    }
+
+    func delete(_ name: String) -> Bool {
+        var removed = false
+        update { presets in
+            let before = presets.count
+            presets.removeAll { $0.name == name }
+            removed = presets.count != before
+        }
+        return removed
+    }
+
+    private func saved() -> [LayoutPreset] {
+        state.withLock { cached in
+            if let cached { return cached }
+            let loaded = file.load() ?? []
+            cached = loaded
+            return loaded
+        }
+    }
+
+    private func update(_ change: (inout [LayoutPreset]) -> Void) {
+        var presets = saved()
+        change(&presets)
+        state.withLock { $0 = presets }
+        file.save(presets)
+    }
+}
@@ -0,0 +1,26 @@
+import Foundation
+
+/// Mutable state shared across queues, reachable only through `withLock`.
+/// The scope-bound accessor releases the lock on every exit path, a thrown
+/// error included, so one failed request can't leave the lock held and wedge
+/// every later caller — the failure mode hand-paired lock()/unlock() invites
+/// once an early return or `try` slips between them. Keep the closures
+/// short, never re-enter the same state from inside one, and never wait on
+/// the main thread while holding it.
+final class LockedState<Value> {
+    private let lock = NSLock()
+    private var value: Value
+
+    init(_ value: Value) {
+        self.value = value
+    }
+
+    func withLock<R>(_ body: (inout Value) throws -> R) rethrows -> R {
+        lock.lock()
+        defer { lock.unlock() }
+        return try body(&value)
+    }
+
+    /// Copy of the current value.
+    var snapshot: Value { withLock { $0 } }
+}
@@ -0,0 +1,459 @@
+import Foundation
+
+/// Severity of a log line, ordered so a minimum-level filter is one
+/// comparison.
+enum LogLevel: Int, Comparable, CaseIterable {
+    case trace, debug, info, warn, error, fatal
+
+    var name: String {
+        switch self {
+        case .trace: return "trace"
+        case .debug: return "debug"
+        case .info: return "info"
+        case .warn: return "warn"
+        case .error: return "error"
+        case .fatal: return "fatal"
+        }
+    }
+
+    static func < (a: LogLevel, b: LogLevel) -> Bool { a.rawValue < b.rawValue }
+
+    /// Accepts the spellings common loggers use (`WARNING`, `err`, `crit`…).
+    init?(name: String) {
+        switch name.lowercased() {
+        case "trace", "verbose": self = .trace
+        case "debug", "dbg": self = .debug
+        case "info", "notice", "information": self = .info
+        case "warn", "warning": self = .warn
+        case "error", "err": self = .error
+        case "fatal", "critical", "crit", "panic", "emerg", "alert": self = .fatal
+        default: return nil
+        }
+    }
+
+    /// pino / bunyan numeric levels (10 trace … 60 fatal).
+    init?(numeric: Int) {
+        guard numeric >= 10 else { return nil }
+        self.init(rawValue: min(numeric / 10 - 1, LogLevel.fatal.rawValue))
+    }
+
+    /// Level stated by a line: a JSON `level`/`severity` field, a logfmt
+    /// `level=` pair, or an upper-case (or bracketed) level word near the
+    /// start. Nil when the line names none.
+    static func detect(in line: String) -> LogLevel? {
+        if line.drop(while: { $0 == " " }).first == "{" {
+            if let object = try? JSONSerialization.jsonObject(with: Data(line.utf8)) as? [String: Any] {
+                for key in jsonKeys {
+                    if let name = object[key] as? String, let level = LogLevel(name: name) { return level }
+                    if let number = object[key] as? Int, let level = LogLevel(numeric: number) { return level }
+                }
+            }
+        }
+        let head = String(line.prefix(160))
+        let range = NSRange(head.startIndex..., in: head)
+        for pattern in [logfmtPattern, wordPattern] {
+            guard let match = pattern.firstMatch(in: head, range: range) else { continue }
+            // wordPattern's bare and bracketed spellings are separate groups.
+            for group in 1..<match.numberOfRanges {
+                if let word = Range(match.range(at: group), in: head),
+                   let level = LogLevel(name: String(head[word])) {
+                    return level
+                }
+            }
+        }
+        return nil
+    }
+
+    private static let jsonKeys = ["level", "severity", "lvl", "loglevel", "log.level"]
+    private static let logfmtPattern = try! NSRegularExpression(
+        pattern: #"\b(?:level|lvl|severity)="?([A-Za-z]+)"#, options: .caseInsensitive)
+    private static let wordPattern = try! NSRegularExpression(
+        pattern: #"(?<![A-Za-z])(TRACE|DEBUG|INFO|NOTICE|WARN|WARNING|ERROR|ERR|FATAL|CRITICAL|CRIT|PANIC)(?![A-Za-z])|\[(?i:(trace|debug|info|warn|warning|error|fatal))\]"#)
+}
+
+/// One line held by a log session. `level` is the line's own level, or
+/// the previous line's for continuation lines such as stack frames.
+struct LogLine: Equatable {
+    let seq: Int
+    let text: String
+    let level: LogLevel?
+}
+
+/// Backend-side line filter: an optional regex (whose matches are also the
+/// highlight ranges) and an optional minimum level.
+struct LogFilter {
+    let pattern: NSRegularExpression?
+    let minimumLevel: LogLevel?
+
+    init(pattern: NSRegularExpression? = nil, minimumLevel: LogLevel? = nil) {
+        self.pattern = pattern
+        self.minimumLevel = minimumLevel
+    }
+
+    /// Build from socket arguments. Fails with a message on a bad regex or
+    /// an unknown level name.
+    static func parse(pattern: String?, level: String?) -> Result<LogFilter, LogViewerError> {
+        var regex: NSRegularExpression?
+        if let pattern, !pattern.isEmpty {
+            do {
+                regex = try NSRegularExpression(pattern: pattern)
+            } catch {
+                return .failure(.badFilter("invalid regex: \(pattern)"))
+            }
+        }
+        var minimum: LogLevel?
+        if let level, !level.isEmpty {
+            guard let parsed = LogLevel(name: level) else {
+                return .failure(.badFilter("unknown level: \(level)"))
+            }
+            minimum = parsed
+        }
+        return .success(LogFilter(pattern: regex, minimumLevel: minimum))
+    }
+
+    /// Match ranges (UTF-16, as JavaScript indexes strings) of `line`, or
+    /// nil when the filter drops it. Empty when there is no pattern.
+    func highlights(in line: LogLine) -> [NSRange]? {
+        if let minimumLevel {
+            guard let level = line.level, level >= minimumLevel else { return nil }
+        }
+        guard let pattern else { return [] }
+        let ranges = pattern
+            .matches(in: line.text, range: NSRange(line.text.startIndex..., in: line.text))
+            .map(\.range)
+            .filter { $0.length > 0 }
+        return ranges.isEmpty ? nil : ranges
+    }
+
+    /// Wire form of the lines that pass, for `log-read` and `log-lines`.
+    func render(_ lines: [LogLine]) -> [[String: Any]] {
+        lines.compactMap { line in
+            guard let ranges = highlights(in: line) else { return nil }
+            var object: [String: Any] = ["seq": line.seq, "text": line.text]
+            if let level = line.level { object["level"] = level.name }
+            if !ranges.isEmpty { object["highlights"] = ranges.map { [$0.location, $0.length] } }
+            return object
+        }
+    }
+}
+
+enum LogViewerError: Error, Equatable, CustomStringConvertible {
+    case badFilter(String)
+    case unreadable(String)
+    case launchFailed(String)
+    case tooManySessions(Int)
+
+    var description: String {
+        switch self {
+        case .badFilter(let reason): return reason
+        case .unreadable(let reason): return reason
+        case .launchFailed(let reason): return "could not launch: \(reason)"
+        case .tooManySessions(let limit): return "at most \(limit) log sessions; close one first"
+        }
+    }
+}
+
+/// A followed log: a file tailed like `tail -F` (rotation and truncation
+/// survive) or a command's merged stdout/stderr. Lines are split, levelled
+/// and kept in a bounded ring; `onLines` receives each new batch. Pausing
+/// stops reading the source — a file keeps its offset and a command blocks
+/// on its full pipe — so following again resumes without gaps.
+final class LogSession {
+    enum Source {
+        case file(String)
+        case command(String, directory: String?)
+
+        var description: String {
+            switch self {
+            case .file(let path): return path
+            case .command(let command, _): return command
+            }
+        }
+    }
+
+    static let capacity = 5000
+    /// Longer lines are cut; a minified bundle on one line would otherwise
+    /// fill every response.
+    static let maxLineBytes = 8192
+    /// How much of an existing file is shown when following starts.
+    static let initialTailBytes: UInt64 = 64 << 10
+    static let pollInterval: TimeInterval = 0.25
+
+    let id: String
+    let source: Source
+    let filter: LogFilter
+
+    /// New lines, unfiltered, on the session's queue.
+    var onLines: ((LogSession, [LogLine]) -> Void)?
+    /// A command source exited; files are followed until closed.
+    var onEnded: ((LogSession, String) -> Void)?
+
+    private struct State {
+        var lines: [LogLine] = []
+        var nextSeq = 1
+        var paused = false
+        var ended: String?
+        var partial = Data()
+        var lastLevel: LogLevel?
+    }
+
+    private let state = LockedState(State())
+    private let queue: DispatchQueue
+    private var timer: DispatchSourceTimer?
+    private var file: (handle: FileHandle, inode: UInt64, offset: UInt64)?
+    private var process: Process?
+    private var output: FileHandle?
+
+    init(id: String, source: Source, filter: LogFilter) {
+        self.id = id
+        self.source = source
+        self.filter = filter
+        queue = DispatchQueue(label: "infinitty.log.\(id)", qos: .utility)
+    }
+
+    var isPaused: Bool { state.withLock { $0.paused } }
+
+    func start() throws {
+        switch source {
+        case .file(let path):
+            guard FileManager.default.isReadableFile(atPath: path) else {
+                throw LogViewerError.unreadable("\(path): not readable")
+            }
+            let timer = DispatchSource.makeTimerSource(queue: queue)
+            timer.schedule(deadline: .now(), repeating: Self.pollInterval)
+            timer.setEventHandler { [weak self] in self?.pollFile(path) }
+            self.timer = timer
+            timer.resume()
+        case .command(let command, let directory):
+            let p = Process()
+            p.executableURL = URL(fileURLWithPath: "/bin/sh")
+            p.arguments = ["-c", command]
+            if let directory { p.currentDirectoryURL = URL(fileURLWithPath: directory) }
+            let pipe = Pipe()
+            p.standardOutput = pipe
+            p.standardError = pipe
+            p.standardInput = FileHandle.nullDevice
+            p.terminationHandler = { [weak self] p in
+                // Let the reader drain what the child wrote before exiting.
+                self?.queue.async { self?.finish("exited with status \(p.terminationStatus)") }
+            }
+            do {
+                try p.run()
+            } catch {
+                throw LogViewerError.launchFailed(error.localizedDescription)
+            }
+            process = p
+            output = pipe.fileHandleForReading
+            installReader()
+        }
+    }
+
+    func setPaused(_ paused: Bool) {
+        state.withLock { $0.paused = paused }
+        queue.async { [weak self] in
+            guard let self else { return }
+            if case .command = self.source {
+                if paused { self.output?.readabilityHandler = nil } else { self.installReader() }
+            }
+        }
+    }
+
+    func close() {
+        state.withLock { $0.paused = true }
+        queue.async { [weak self] in
+            guard let self else { return }
+            self.timer?.cancel()
+            self.timer = nil
+            self.output?.readabilityHandler = nil
+            if let process = self.process, process.isRunning { process.terminate() }
+            try? self.file?.handle.close()
+            self.file = nil
+        }
+    }
+
+    /// Lines after `since` that pass `filter`, at most `limit` of them and
+    /// roughly `byteBudget` of text. `latestSeq` is where to resume; `more`
+    /// says lines were left out by the caps.
+    func read(since: Int, limit: Int, filter: LogFilter, byteBudget: Int = 180_000) -> [String: Any] {
+        let snapshot = state.snapshot
+        var picked: [[String: Any]] = []
+        var bytes = 0
+        var resume = since
+        var more = false
+        for line in snapshot.lines where line.seq > since {
+            if picked.count >= limit || bytes >= byteBudget {
+                more = true
+                break
+            }
+            resume = line.seq
+            guard let rendered = filter.render([line]).first else { continue }
+            picked.append(rendered)
+            bytes += line.text.utf8.count
+        }
+        if !more, let last = snapshot.lines.last { resume = max(resume, last.seq) }
+        var object: [String: Any] = [
+            "id": id, "source": source.description, "paused": snapshot.paused,
+            "latestSeq": resume, "more": more, "lines": picked,
+        ]
+        if let first = snapshot.lines.first, since > 0, first.seq > since + 1 {
+            // The ring dropped lines the caller never saw.
+            object["skipped"] = first.seq - since - 1
+        }
+        if let ended = snapshot.ended { object["ended"] = ended }
+        return object
+    }
+
+    /// Split `data` into lines and append them; exposed for tests.
+    func ingest(_ data: Data, flush: Bool = false) {
+        let added = state.withLock { state -> [LogLine] in
+            state.partial.append(data)
+            var added: [LogLine] = []
+            while let newline = state.partial.firstIndex(of: 0x0A) {
+                let raw = state.partial[state.partial.startIndex..<newline]
+                state.partial.removeSubrange(state.partial.startIndex...newline)
+                added.append(Self.makeLine(raw, in: &state))
+            }
+            if state.partial.count > Self.maxLineBytes || (flush && !state.partial.isEmpty) {
+                added.append(Self.makeLine(state.partial, in: &state))
+                state.partial.removeAll()
+            }
+            state.lines.append(contentsOf: added)
+            if state.lines.count > Self.capacity {
+                state.lines.removeFirst(state.lines.count - Self.capacity)
+            }
+            return added
+        }
+        if !added.isEmpty { onLines?(self, added) }
+    }
+
+    private static func makeLine(_ raw: Data, in state: inout State) -> LogLine {
+        var bytes = raw.prefix(maxLineBytes)
+        if bytes.last == 0x0D { bytes.removeLast() }
+        let text = String(decoding: bytes, as: UTF8.self)
+        let level = LogLevel.detect(in: text) ?? state.lastLevel
+        state.lastLevel = level
+        defer { state.nextSeq += 1 }
+        return LogLine(seq: state.nextSeq, text: text, level: level)
+    }
+
+    private func installReader() {
+        output?.readabilityHandler = { [weak self] handle in
+            let data = handle.availableData
+            guard let self else { return }
+            if data.isEmpty {
+                handle.readabilityHandler = nil
+                return
+            }
+            self.queue.async { self.ingest(data) }
+        }
+    }
+
+    private func finish(_ reason: String) {
+        let first = state.withLock { state -> Bool in
+            guard state.ended == nil else { return false }
+            state.ended = reason
+            return true
+        }
+        guard first else { return }
+        output?.readabilityHandler = nil
+        if let rest = try? output?.readToEnd(), !rest.isEmpty { ingest(rest) }
+        ingest(Data(), flush: true)
+        timer?.cancel()
+        timer = nil
+        onEnded?(self, reason)
+    }
+
+    /// One `tail -F` step: reopen on rotation, rewind on truncation, then
+    /// read whatever was appended.
+    private func pollFile(_ path: String) {
+        guard !isPaused else { return }
+        guard let attributes = try? FileManager.default.attributesOfItem(atPath: path),
+              let size = (attributes[.size] as? NSNumber)?.uint64Value,
+              let inode = (attributes[.systemFileNumber] as? NSNumber)?.uint64Value
+        else {
+            // Mid-rotation the path can briefly not exist; keep waiting.
+            return
+        }
+        if file == nil || file?.inode != inode {
+            try? file?.handle.close()
+            guard let handle = FileHandle(forReadingAtPath: path) else { return }
+            // First open shows the end of the file; a rotated-in file is new
+            // and read from the start.
+            let start = file == nil && size > Self.initialTailBytes ? size - Self.initialTailBytes : 0
+            file = (handle, inode, start)
+            if start > 0 {
+                // Skip the partial line the tail window starts inside.
+                try? handle.seek(toOffset: start)
+                if let chunk = try? handle.read(upToCount: 4096),
+                   let newline = chunk.firstIndex(of: 0x0A) {
+                    file?.offset = start + UInt64(chunk.distance(from: chunk.startIndex, to: newline) + 1)
+                }
+            }
+        }
+        guard var current = file else { return }
+        if size < current.offset {
+            current.offset = 0
+            state.withLock { $0.partial.removeAll() }
+        }
+        guard size > current.offset else {
+            file = current
+            return
+        }
+        try? current.handle.seek(toOffset: current.offset)
+        let data = (try? current.handle.read(upToCount: 1 << 20)) ?? Data()
+        current.offset += UInt64(data.count)
+        file = current
+        ingest(data)
+    }
+}
+
+/// Open log sessions, addressed as `log-N` from the control socket.
+final class LogViewerRegistry {
+    static let shared = LogViewerRegistry()
+    static let maxSessions = 16
+
+    /// Forwarded from every session; the app turns these into events.
+    var onLines: ((LogSession, [LogLine]) -> Void)?
+    var onEnded: ((LogSession, String) -> Void)?
+
+    private let sessions = LockedState((byID: [String: LogSession](), nextID: 1))
+
+    func open(_ source: LogSession.Source, filter: LogFilter) -> Result<LogSession, LogViewerError> {
+        let session: LogSession? = sessions.withLock { sessions in
+            guard sessions.byID.count < Self.maxSessions else { return nil }
+            let session = LogSession(id: "log-\(sessions.nextID)", source: source, filter: filter)
+            sessions.nextID += 1
+            sessions.byID[session.id] = session
+            return session
+        }
+        guard let session else { return .failure(.tooManySessions(Self.maxSessions)) }
+        session.onLines = { [weak self] in self?.onLines?($0, $1) }
+        session.onEnded = { [weak self] in self?.onEnded?($0, $1) }
+        do {
+            try session.start()
+        } catch {
+            sessions.withLock { _ = $0.byID.removeValue(forKey: session.id) }
+            return .failure(error as? LogViewerError ?? .unreadable(String(describing: error)))
+        }
+        return .success(session)
+    }
+
+    func session(_ id: String) -> LogSession? {
+        sessions.withLock { $0.byID[id] }
+    }
+
+    @discardableResult
+    func close(_ id: String) -> Bool {
+        guard let session = sessions.withLock({ $0.byID.removeValue(forKey: id) }) else { return false }
+        session.close()
+        return true
+    }
+
+    func closeAll() {
+        let all = sessions.withLock { sessions -> [LogSession] in
+            defer { sessions.byID.removeAll() }
+            return Array(sessions.byID.values)
+        }
+        for session in all { session.close() }
+    }
+}
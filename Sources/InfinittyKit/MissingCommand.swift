@@ -0,0 +1,281 @@
+import Foundation
+
+/// A fix offered after a command was not found: the same line with a known
+/// program swapped in, or the install that would provide the missing one.
+struct CommandSuggestion: Equatable {
+    enum Kind: String {
+        case didYouMean = "did-you-mean"
+        case install
+    }
+
+    let kind: Kind
+    /// Ready to type: the corrected command line or the install command.
+    let command: String
+
+    var wire: [String: Any] { ["kind": kind.rawValue, "command": command] }
+}
+
+/// Resolves "command not found" failures, and mistyped subcommands, into
+/// suggestions. Everything is local: the command history, executables on
+/// disk, shell aliases and Homebrew's cached indices — nothing is fetched.
+enum MissingCommand {
+    /// The program a failed command line could not run, when the shell
+    /// said so: exit status 127 plus the shell's own message (bash and zsh
+    /// "command not found", fish "Unknown command"). Paths like `./build`
+    /// are left alone, since no other program is what they meant.
+    static func program(in command: String, exitCode: Int, output: String) -> String? {
+        guard exitCode == 127 else { return nil }
+        let program = CommandRecord.program(of: command)
+        guard !program.isEmpty, token(of: program, in: command)?.contains("/") == false else { return nil }
+        let reported = output.split(whereSeparator: \.isNewline).contains { line in
+            line.contains(program)
+                && (line.localizedCaseInsensitiveContains("command not found")
+                    || line.localizedCaseInsensitiveContains("unknown command"))
+        }
+        return reported ? program : nil
+    }
+
+    /// Suggestions for `program`, best first: up to three near misses from
+    /// `known` (program name → how often it ran), then the Homebrew
+    /// formulae that ship it.
+    static func suggestions(
+        for program: String, in command: String, known: [String: Int], formulae: [String]
+    ) -> [CommandSuggestion] {
+        let corrected = corrections(for: program, among: known).compactMap { name -> CommandSuggestion? in
+            guard let token = token(of: program, in: command) else { return nil }
+            var line = command
+            line.replaceSubrange(token.startIndex..<token.endIndex, with: name)
+            return CommandSuggestion(kind: .didYouMean, command: line)
+        }
+        let installs = formulae.prefix(3).map { CommandSuggestion(kind: .install, command: "brew install \($0)") }
+        return corrected + installs
+    }
+
+    /// What `command` most likely meant, for a failure or on request
+    /// (`suggest-correction`): when its program isn't in `known`, near
+    /// misses and installs as `suggestions(for:in:known:formulae:)` gives
+    /// them; when it is, the same line with a mistyped subcommand fixed.
+    static func suggestCorrection(
+        for command: String, known: [String: Int], history: [CommandRecord], formulae: (String) -> [String]
+    ) -> [CommandSuggestion] {
+        let program = CommandRecord.program(of: command)
+        guard !program.isEmpty else { return [] }
+        if known[program] == nil, token(of: program, in: command)?.contains("/") == false {
+            return suggestions(for: program, in: command, known: known, formulae: formulae(program))
+        }
+        return subcommandCorrections(in: command, history: history)
+            .map { CommandSuggestion(kind: .didYouMean, command: $0) }
+    }
+
+    /// `command` with its subcommand (the word after the program, as in
+    /// `git psuh`) swapped for a near miss that ran successfully with the
+    /// same program before. Nothing when the subcommand itself has.
+    static func subcommandCorrections(in command: String, history: [CommandRecord], limit: Int = 3) -> [String] {
+        let program = CommandRecord.program(of: command)
+        guard let typed = subcommand(of: command, program: program) else { return [] }
+        var known: [String: Int] = [:]
+        for record in history where record.exitCode == 0 && record.program == program {
+            if let word = subcommand(of: record.command, program: program) { known[String(word), default: 0] += 1 }
+        }
+        guard known[String(typed)] == nil else { return [] }
+        return corrections(for: String(typed), among: known, limit: limit).map { name in
+            var line = command
+            line.replaceSubrange(typed.startIndex..<typed.endIndex, with: name)
+            return line
+        }
+    }
+
+    /// The word after `program` in `command` when it reads like a
+    /// subcommand: letters, digits and dashes, not an option.
+    private static func subcommand(of command: String, program: String) -> Substring? {
+        let words = command.split(whereSeparator: \.isWhitespace)
+        guard let at = words.firstIndex(where: { $0 == program || $0.hasSuffix("/" + program) }),
+              at + 1 < words.count else { return nil }
+        let word = words[at + 1]
+        guard word.first?.isLetter == true, word.allSatisfy({ $0.isLetter || $0.isNumber || $0 == "-" })
+        else { return nil }
+        return word
+    }
+
+    /// Known programs within a small edit distance (one edit for names of
+    /// three letters or fewer, two otherwise), nearest and most used first.
+    static func corrections(for program: String, among known: [String: Int], limit: Int = 3) -> [String] {
+        let allowed = program.count <= 3 ? 1 : 2
+        return known
+            .compactMap { name, uses -> (name: String, distance: Int, uses: Int)? in
+                guard name != program, abs(name.count - program.count) <= allowed else { return nil }
+                let distance = editDistance(program, name)
+                return distance <= allowed ? (name, distance, uses) : nil
+            }
+            .sorted {
+                ($0.distance, -$0.uses, $0.name) < ($1.distance, -$1.uses, $1.name)
+            }
+            .prefix(limit)
+            .map(\.name)
+    }
+
+    /// Optimal string alignment distance: insertions, deletions,
+    /// substitutions and adjacent transpositions (`gti` → `git` is one).
+    static func editDistance(_ a: String, _ b: String) -> Int {
+        let a = Array(a), b = Array(b)
+        guard !a.isEmpty, !b.isEmpty else { return max(a.count, b.count) }
+        var rows = [[Int]](repeating: [Int](repeating: 0, count: b.count + 1), count: a.count + 1)
+        for i in 0...a.count { rows[i][0] = i }
+        for j in 0...b.count { rows[0][j] = j }
+        for i in 1...a.count {
+            for j in 1...b.count {
+                let cost = a[i - 1] == b[j - 1] ? 0 : 1
+                rows[i][j] = min(rows[i - 1][j] + 1, rows[i][j - 1] + 1, rows[i - 1][j - 1] + cost)
+                if i > 1, j > 1, a[i - 1] == b[j - 2], a[i - 2] == b[j - 1] {
+                    rows[i][j] = min(rows[i][j], rows[i - 2][j - 2] + 1)
+                }
+            }
+        }
+        return rows[a.count][b.count]
+    }
+
+    /// Programs worth suggesting: ones that succeeded in the command
+    /// history, weighted by use, plus the shell's `aliases` and every
+    /// executable in `directories` (counted once, so history breaks ties).
+    static func knownPrograms(
+        history: [CommandRecord], directories: [String], aliases: [String] = []
+    ) -> [String: Int] {
+        var known: [String: Int] = [:]
+        for record in history where record.exitCode == 0 {
+            let program = record.program
+            if !program.isEmpty { known[program, default: 0] += 1 }
+        }
+        for alias in aliases where known[alias] == nil { known[alias] = 0 }
+        let fm = FileManager.default
+        for directory in directories {
+            for name in (try? fm.contentsOfDirectory(atPath: directory)) ?? [] where !name.hasPrefix(".") {
+                if known[name] == nil, fm.isExecutableFile(atPath: "\(directory)/\(name)") { known[name] = 0 }
+            }
+        }
+        return known
+    }
+
+    /// Where to look for executables: the app's PATH, which misses
+    /// Homebrew when launched from the Dock, plus the usual places.
+    static var searchDirectories: [String] {
+        let path = (ProcessInfo.processInfo.environment["PATH"] ?? "").split(separator: ":").map(String.init)
+        return (path + ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin", "/usr/bin", "/bin"])
+            .reduce(into: [String]()) { if !$0.contains($1) { $0.append($1) } }
+    }
+
+    /// Startup files aliases are usually defined in, under `home`.
+    static func aliasFiles(home: String = NSHomeDirectory()) -> [String] {
+        [".zshrc", ".zsh_aliases", ".bashrc", ".bash_aliases", ".bash_profile", ".aliases",
+         ".config/fish/config.fish"].map { "\(home)/\($0)" }
+    }
+
+    /// Alias names defined in shell startup text: `alias ll='ls -l'` (bash,
+    /// zsh, with `-g` and friends) and fish's `alias ll 'ls -l'` and
+    /// `abbr -a gco git checkout`. The shell's own list isn't reachable
+    /// from here, so this reads what the files say.
+    static func aliases(in text: String) -> [String] {
+        var names: [String] = []
+        for line in text.split(whereSeparator: \.isNewline) {
+            var words = line.split(whereSeparator: \.isWhitespace)[...]
+            guard let first = words.first, first == "alias" || first == "abbr" else { continue }
+            words = words.dropFirst().drop(while: { $0.hasPrefix("-") })
+            guard let word = words.first else { continue }
+            let name = word.split(separator: "=", maxSplits: 1, omittingEmptySubsequences: false)[0]
+            guard !name.isEmpty, !name.contains("'"), !name.contains("\""), !names.contains(String(name))
+            else { continue }
+            names.append(String(name))
+        }
+        return names
+    }
+
+    /// Aliases from every file in `aliasFiles`.
+    static func shellAliases(home: String = NSHomeDirectory()) -> [String] {
+        aliasFiles(home: home).flatMap { path in
+            (try? String(contentsOfFile: path, encoding: .utf8)).map(aliases(in:)) ?? []
+        }
+    }
+
+    /// The first whole word of `command` whose last path component is
+    /// `program` — the token `CommandRecord.program(of:)` picked.
+    private static func token(of program: String, in command: String) -> Substring? {
+        command.split(whereSeparator: \.isWhitespace).first { $0 == program || $0.hasSuffix("/" + program) }
+    }
+}
+
+/// Which Homebrew formulae provide an executable, from files Homebrew keeps
+/// on disk: the executables database of `brew which-formula` (or the
+/// command-not-found tap), falling back to the cached formula list, where
+/// only a formula named after the program matches. Loaded once, lazily.
+final class HomebrewIndex {
+    static let shared = HomebrewIndex()
+
+    private typealias Index = (executables: [String: [String]], names: Set<String>)
+    private let state = LockedState<Index?>(nil)
+
+    private static var apiCache: String {
+        let caches = FileManager.default.urls(for: .cachesDirectory, in: .userDomainMask).first?.path
+            ?? NSHomeDirectory() + "/Library/Caches"
+        return caches + "/Homebrew/api"
+    }
+
+    static var executableDatabases: [String] {
+        [
+            "\(apiCache)/internal/executables.txt",
+            "/opt/homebrew/Library/Taps/homebrew/homebrew-command-not-found/executables.txt",
+            "/usr/local/Homebrew/Library/Taps/homebrew/homebrew-command-not-found/executables.txt",
+        ]
+    }
+
+    static var formulaLists: [String] {
+        ["\(apiCache)/formula_names.txt", "\(apiCache)/formula.jws.json"]
+    }
+
+    func formulae(providing program: String) -> [String] {
+        let index = state.withLock { index -> Index in
+            if let index { return index }
+            let loaded = Self.load()
+            index = loaded
+            return loaded
+        }
+        if let providers = index.executables[program], !providers.isEmpty { return providers }
+        return index.names.contains(program) ? [program] : []
+    }
+
+    private static func load() -> Index {
+        let executables = executableDatabases.lazy
+            .compactMap { try? String(contentsOfFile: $0, encoding: .utf8) }
+            .first
+            .map(parseExecutables) ?? [:]
+        var names = Set<String>()
+        for path in formulaLists {
+            guard let data = FileManager.default.contents(atPath: path) else { continue }
+            names = path.hasSuffix(".json") ? parseFormulaJSON(data) : Set(
+                String(decoding: data, as: UTF8.self).split(whereSeparator: \.isNewline).map(String.init))
+            if !names.isEmpty { break }
+        }
+        return (executables, names)
+    }
+
+    /// `formula(version):exe exe …` per line; the version is optional.
+    static func parseExecutables(_ text: String) -> [String: [String]] {
+        var providers: [String: [String]] = [:]
+        for line in text.split(whereSeparator: \.isNewline) {
+            guard let colon = line.firstIndex(of: ":") else { continue }
+            let formula = line[..<colon].split(separator: "(").first.map(String.init) ?? ""
+            guard !formula.isEmpty else { continue }
+            for executable in line[line.index(after: colon)...].split(separator: " ") {
+                providers[String(executable), default: []].append(formula)
+            }
+        }
+        return providers
+    }
+
+    /// The signed API cache: `{"payload": "<JSON array of formulae>"}`.
+    static func parseFormulaJSON(_ data: Data) -> Set<String> {
+        guard let object = try? JSONSerialization.jsonObject(with: data) as? [String: Any],
+              let payload = (object["payload"] as? String)?.data(using: .utf8),
+              let formulae = try? JSONSerialization.jsonObject(with: payload) as? [[String: Any]]
+        else { return [] }
+        return Set(formulae.compactMap { $0["name"] as? String })
+    }
+}
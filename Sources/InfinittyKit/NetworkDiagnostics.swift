@@ -0,0 +1,281 @@
+import Foundation
+
+enum NetworkToolError: Error, Equatable, CustomStringConvertible {
+    case invalidHost(String)
+    case failed(String)
+
+    var description: String {
+        switch self {
+        case .invalidHost(let host): return "not a host name or address: \(host)"
+        case .failed(let reason): return reason
+        }
+    }
+}
+
+enum DNSRecordType: String, CaseIterable {
+    case a = "A", aaaa = "AAAA", cname = "CNAME", mx = "MX", ns = "NS"
+    case txt = "TXT", soa = "SOA", ptr = "PTR", srv = "SRV", caa = "CAA"
+}
+
+struct DNSAnswer: Equatable {
+    let name: String
+    let type: String
+    let ttl: Int
+    let value: String
+
+    var wire: [String: Any] { ["name": name, "type": type, "ttl": ttl, "value": value] }
+}
+
+/// One ping echo: a reply with its round trip, or a timeout (`rtt` nil).
+struct PingReply: Equatable {
+    let sequence: Int
+    let rtt: Double?
+    var ttl: Int?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["seq": sequence]
+        if let rtt { object["rtt"] = rtt }
+        if let ttl { object["ttl"] = ttl }
+        return object
+    }
+}
+
+/// One traceroute hop: each probe's responder and round trip; a probe
+/// that got no answer has neither.
+struct TracerouteHop: Equatable {
+    struct Probe: Equatable {
+        var address: String?
+        var rtt: Double?
+    }
+
+    let hop: Int
+    let probes: [Probe]
+
+    var wire: [String: Any] {
+        var addresses: [String] = []
+        for case let address? in probes.map(\.address) where !addresses.contains(address) {
+            addresses.append(address)
+        }
+        return [
+            "hop": hop, "addresses": addresses,
+            "rtts": probes.map { $0.rtt.map { $0 as Any } ?? NSNull() },
+        ]
+    }
+}
+
+/// DNS lookup, ping and traceroute by wrapping macOS's `dig`, `ping` and
+/// `traceroute` and parsing what they print, so clients get numbers rather
+/// than text. Ping and traceroute report each reply or hop as it arrives.
+enum NetworkDiagnostics {
+    /// Host names and IPv4/IPv6 literals; nothing that starts like an
+    /// option.
+    static func isValidHost(_ host: String) -> Bool {
+        !host.isEmpty && host.count <= 253 && !host.hasPrefix("-") && host.unicodeScalars.allSatisfy {
+            CharacterSet.alphanumerics.contains($0) || ".-_:".unicodeScalars.contains($0)
+        }
+    }
+
+    // MARK: - dns
+
+    static func digArguments(host: String, type: DNSRecordType, server: String?) -> [String] {
+        var arguments = ["+noall", "+answer", "+comments", "+time=3", "+tries=2"]
+        if let server { arguments.append("@" + server) }
+        // PTR lookups of an address go through dig's reverse-name helper.
+        if type == .ptr, host.contains(where: { $0 == ":" }) || host.allSatisfy({ $0.isNumber || $0 == "." }) {
+            return arguments + ["-x", host]
+        }
+        return arguments + [host, type.rawValue]
+    }
+
+    /// `dig +noall +answer +comments` output: the header's `status:` and
+    /// the answer records (`name TTL class type data…`).
+    static func parseDig(_ text: String) -> (status: String?, answers: [DNSAnswer]) {
+        var status: String?
+        var answers: [DNSAnswer] = []
+        for line in text.split(whereSeparator: \.isNewline) {
+            if line.hasPrefix(";") {
+                if let range = line.range(of: "status: ") {
+                    status = line[range.upperBound...].prefix { $0.isLetter }.description
+                }
+                continue
+            }
+            let fields = line.split(whereSeparator: { $0 == " " || $0 == "\t" })
+            guard fields.count >= 5, let ttl = Int(fields[1]) else { continue }
+            answers.append(DNSAnswer(
+                name: String(fields[0]), type: String(fields[3]), ttl: ttl,
+                value: fields[4...].joined(separator: " ")))
+        }
+        return (status, answers)
+    }
+
+    static func lookup(
+        host: String, type: DNSRecordType, server: String? = nil
+    ) -> Result<[String: Any], NetworkToolError> {
+        guard isValidHost(host) else { return .failure(.invalidHost(host)) }
+        if let server, !isValidHost(server) { return .failure(.invalidHost(server)) }
+        let started = Date()
+        switch ProcessRunner.run("/usr/bin/dig", digArguments(host: host, type: type, server: server), timeout: 15) {
+        case .success(let output) where output.status == 0:
+            let parsed = parseDig(output.stdoutText)
+            var object: [String: Any] = [
+                "host": host, "type": type.rawValue, "status": parsed.status ?? "NOERROR",
+                "answers": parsed.answers.map(\.wire),
+                "elapsed": (Date().timeIntervalSince(started) * 1000).rounded() / 1000,
+            ]
+            if let server { object["server"] = server }
+            return .success(object)
+        case .success(let output):
+            // dig exits 9 when no server answered.
+            let reason = output.status == 9 ? "no DNS server answered" : output.stdoutText + output.stderrText
+            return .failure(.failed("dig: \(reason.trimmingCharacters(in: .whitespacesAndNewlines))"))
+        case .failure(let error):
+            return .failure(.failed("dig: \(error)"))
+        }
+    }
+
+    // MARK: - ping
+
+    /// `64 bytes from 1.1.1.1: icmp_seq=0 ttl=57 time=12.345 ms`, or
+    /// `Request timeout for icmp_seq 1`.
+    static func parsePingLine(_ line: String) -> PingReply? {
+        func number(after key: String) -> Substring? {
+            guard let range = line.range(of: key) else { return nil }
+            return line[range.upperBound...].prefix { $0.isNumber || $0 == "." }
+        }
+        if line.hasPrefix("Request timeout for icmp_seq") {
+            return line.split(separator: " ").last.flatMap { Int($0) }.map { PingReply(sequence: $0, rtt: nil) }
+        }
+        guard line.contains(" bytes from "), let sequence = number(after: "icmp_seq=").flatMap({ Int($0) }),
+              let rtt = number(after: "time=").flatMap({ Double($0) })
+        else { return nil }
+        return PingReply(sequence: sequence, rtt: rtt, ttl: number(after: "ttl=").flatMap { Int($0) })
+    }
+
+    /// `PING example.com (93.184.216.34): 56 data bytes` → the address.
+    static func parsePingAddress(_ line: String) -> String? {
+        guard line.hasPrefix("PING "), let open = line.firstIndex(of: "("),
+              let close = line[open...].firstIndex(of: ")")
+        else { return nil }
+        return String(line[line.index(after: open)..<close])
+    }
+
+    /// Summary over the replies seen: loss and min/avg/max/stddev, in ms.
+    static func pingSummary(host: String, address: String?, sent: Int, replies: [PingReply]) -> [String: Any] {
+        let rtts = replies.compactMap(\.rtt)
+        var object: [String: Any] = [
+            "host": host, "sent": sent, "received": rtts.count,
+            "loss": sent == 0 ? 0 : (Double(sent - rtts.count) / Double(sent) * 1000).rounded() / 1000,
+            "replies": replies.map(\.wire),
+        ]
+        if let address { object["address"] = address }
+        if !rtts.isEmpty {
+            let mean = rtts.reduce(0, +) / Double(rtts.count)
+            let variance = rtts.map { ($0 - mean) * ($0 - mean) }.reduce(0, +) / Double(rtts.count)
+            func ms(_ value: Double) -> Double { (value * 1000).rounded() / 1000 }
+            object["min"] = ms(rtts.min() ?? 0)
+            object["avg"] = ms(mean)
+            object["max"] = ms(rtts.max() ?? 0)
+            object["stddev"] = ms(variance.squareRoot())
+        }
+        return object
+    }
+
+    /// Sends `count` echoes a second apart, calling `onReply` for each
+    /// reply or timeout as ping prints it.
+    static func ping(
+        host: String, count: Int, cancellation: CancellationToken? = nil,
+        onReply: @escaping (PingReply) -> Void
+    ) -> Result<[String: Any], NetworkToolError> {
+        guard isValidHost(host) else { return .failure(.invalidHost(host)) }
+        let state = LockedState((address: String?.none, replies: [PingReply]()))
+        let lines = lineSplitter { line in
+            if let address = parsePingAddress(line) { state.withLock { $0.address = address } }
+            guard let reply = parsePingLine(line) else { return }
+            state.withLock { $0.replies.append(reply) }
+            onReply(reply)
+        }
+        // -t ends the run even when the host never answers.
+        let arguments = ["-c", String(count), "-t", String(count + 5), host]
+        let run = ProcessRunner.run(
+            "/sbin/ping", arguments, timeout: TimeInterval(count + 15), cancellation: cancellation,
+            onOutput: lines)
+        let seen = state.snapshot
+        switch run {
+        case .success(let output) where seen.address == nil:
+            // ping exits 68 for an unknown host, before printing anything.
+            return .failure(.failed(output.stderrText.isEmpty ? "ping exited \(output.status)" : output.stderrText))
+        case .success:
+            return .success(pingSummary(host: host, address: seen.address, sent: count, replies: seen.replies))
+        case .failure(let error):
+            return .failure(.failed("ping: \(error)"))
+        }
+    }
+
+    // MARK: - traceroute
+
+    /// ` 3  10.0.0.1  5.12 ms  10.0.0.2  6.01 ms *` (from `traceroute -n`).
+    /// Each `ms` value is one probe, answered by the address before it;
+    /// `*` is a probe that got nothing. `!H`-style annotations are skipped.
+    static func parseTracerouteLine(_ line: String) -> TracerouteHop? {
+        let tokens = line.split(separator: " ")
+        guard let first = tokens.first, let hop = Int(first) else { return nil }
+        var probes: [TracerouteHop.Probe] = []
+        var address: String?
+        var index = 1
+        while index < tokens.count {
+            let token = tokens[index]
+            if token == "*" {
+                probes.append(TracerouteHop.Probe())
+            } else if let rtt = Double(token), index + 1 < tokens.count, tokens[index + 1] == "ms" {
+                probes.append(TracerouteHop.Probe(address: address, rtt: rtt))
+                index += 1
+            } else if !token.hasPrefix("!") {
+                address = String(token)
+            }
+            index += 1
+        }
+        return TracerouteHop(hop: hop, probes: probes)
+    }
+
+    /// Numeric traceroute with three probes per hop, two seconds each,
+    /// calling `onHop` as each hop line arrives.
+    static func traceroute(
+        host: String, maxHops: Int, cancellation: CancellationToken? = nil,
+        onHop: @escaping (TracerouteHop) -> Void
+    ) -> Result<[String: Any], NetworkToolError> {
+        guard isValidHost(host) else { return .failure(.invalidHost(host)) }
+        let hops = LockedState([TracerouteHop]())
+        let lines = lineSplitter { line in
+            guard let hop = parseTracerouteLine(line) else { return }
+            hops.withLock { $0.append(hop) }
+            onHop(hop)
+        }
+        let arguments = ["-n", "-q", "3", "-w", "2", "-m", String(maxHops), host]
+        let run = ProcessRunner.run(
+            "/usr/sbin/traceroute", arguments, timeout: TimeInterval(maxHops * 6 + 10),
+            cancellation: cancellation, onOutput: lines)
+        switch run {
+        case .success(let output) where output.status != 0 && hops.snapshot.isEmpty:
+            let reason = output.stderrText.isEmpty ? "traceroute exited \(output.status)" : output.stderrText
+            return .failure(.failed(reason))
+        case .success:
+            let all = hops.snapshot
+            let reached = all.last?.probes.contains { $0.address != nil } ?? false
+            return .success(["host": host, "hops": all.map(\.wire), "complete": reached && all.count < maxHops])
+        case .failure(let error):
+            return .failure(.failed("traceroute: \(error)"))
+        }
+    }
+
+    /// Turns streamed chunks into whole lines. Chunks come from one reader
+    /// at a time, so the carried-over partial line needs no lock.
+    private static func lineSplitter(_ onLine: @escaping (String) -> Void) -> (Data) -> Void {
+        var partial = ""
+        return { chunk in
+            partial += String(decoding: chunk, as: UTF8.self)
+            var lines = partial.components(separatedBy: "\n")
+            partial = lines.removeLast()
+            lines.forEach(onLine)
+        }
+    }
+}
@@ -0,0 +1,87 @@
+import AppKit
+import UserNotifications
+
+/// When a finished command earns a notification: it ran for at least
+/// `threshold` seconds while its window wasn't the one in front.
+struct LongCommandPolicy: Equatable {
+    static let defaultThreshold: TimeInterval = 10
+
+    var enabled = true
+    var threshold = LongCommandPolicy.defaultThreshold
+
+    func shouldNotify(duration: TimeInterval, windowFocused: Bool, quiet: Bool) -> Bool {
+        enabled && !quiet && !windowFocused && duration >= threshold
+    }
+
+    var wire: [String: Any] { ["enabled": enabled, "threshold": threshold] }
+}
+
+/// Posts a macOS notification when a long command finishes in the
+/// background (`notify-long-commands`), or for a `notify` trigger.
+/// Clicking one brings its pane forward through `onActivate`.
+final class CommandNotifier: NSObject, UNUserNotificationCenterDelegate {
+    static let shared = CommandNotifier()
+
+    /// Called on the main thread with the pane id of a clicked notification.
+    var onActivate: ((Int) -> Void)?
+
+    private let state = LockedState(LongCommandPolicy())
+    private let authorized = LockedState<Bool?>(nil)
+
+    var policy: LongCommandPolicy { state.snapshot }
+
+    func configure(_ policy: LongCommandPolicy) {
+        state.withLock { $0 = policy }
+    }
+
+    /// The notification's title and body: the outcome, then the command
+    /// and how long it took.
+    static func content(command: String?, exitCode: Int, duration: TimeInterval) -> (title: String, body: String) {
+        let formatter = DateComponentsFormatter()
+        formatter.allowedUnits = duration >= 3600 ? [.hour, .minute] : [.minute, .second]
+        formatter.unitsStyle = .abbreviated
+        let took = formatter.string(from: duration.rounded()) ?? "\(Int(duration))s"
+        let title = exitCode == 0 ? "Command finished" : "Command failed (exit \(exitCode))"
+        guard let command, !command.isEmpty else { return (title, "after \(took)") }
+        let shown = command.count > 80 ? String(command.prefix(79)) + "…" : command
+        return (title, "\(shown) — \(took)")
+    }
+
+    func post(pane: Int, command: String?, exitCode: Int, duration: TimeInterval) {
+        let (title, body) = Self.content(command: command, exitCode: exitCode, duration: duration)
+        post(pane: pane, title: title, body: body)
+    }
+
+    /// Any notification about a pane; clicking it focuses that pane.
+    func post(pane: Int, title: String, body: String) {
+        // Unbundled runs (`swift run`) have no notification center to post to.
+        guard Bundle.main.bundleIdentifier != nil else { return }
+        let center = UNUserNotificationCenter.current()
+        center.delegate = self
+        let content = UNMutableNotificationContent()
+        content.title = title
+        content.body = body
+        content.sound = .default
+        content.userInfo = ["pane": pane]
+        let request = UNNotificationRequest(
+            identifier: "pane-\(pane)-\(UUID().uuidString)", content: content, trigger: nil)
+        if authorized.snapshot == true {
+            center.add(request)
+            return
+        }
+        center.requestAuthorization(options: [.alert, .sound]) { [weak self] granted, _ in
+            self?.authorized.withLock { $0 = granted }
+            if granted { center.add(request) }
+        }
+    }
+
+    func userNotificationCenter(
+        _ center: UNUserNotificationCenter, didReceive response: UNNotificationResponse,
+        withCompletionHandler completionHandler: @escaping () -> Void
+    ) {
+        if let pane = response.notification.request.content.userInfo["pane"] as? Int {
+            DispatchQueue.main.async { self.onActivate?(pane) }
+        }
+        completionHandler()
+    }
+}
@@ -0,0 +1,148 @@
+import Foundation
+
+/// How far a pane's running command has got, as best the app can tell.
+struct PaneProgress: Equatable {
+    enum State: String {
+        case normal, error, paused, indeterminate
+    }
+
+    /// Where the guess came from: the program's own OSC 9;4 report, or a
+    /// percentage or progress bar read off its output.
+    enum Source: String {
+        case osc, output
+    }
+
+    /// 0–100; nil while indeterminate, or for an error or pause reported
+    /// before any value.
+    let percent: Double?
+    let state: State
+    let source: Source
+
+    /// Short form for the dock badge: `45%`, or `…` with no number.
+    var badge: String { percent.map { "\(Int($0))%" } ?? "…" }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["state": state.rawValue, "source": source.rawValue]
+        if let percent { object["percent"] = (percent * 10).rounded() / 10 }
+        return object
+    }
+}
+
+/// Reads progress out of one pane's raw PTY output. A program that speaks
+/// OSC 9;4 (`ESC ] 9 ; 4 ; state ; percent BEL`) is taken at its word;
+/// otherwise every line or carriage-return redraw is checked for a
+/// percentage (`45%`, curl's `#### 45.2%`) or a bar with a count beside it
+/// (cargo's `[===>  ] 120/300`, pip's `━━━━ 3.2/10.0 MB`, docker's
+/// `[==>  ] 12MB/45MB`). Fed on the PTY thread, read on main.
+final class ProgressDetector {
+    private struct State {
+        /// Output since the last line break or carriage return.
+        var partial = ""
+        var reported: PaneProgress?
+        var read: PaneProgress?
+        var updated: Date?
+    }
+
+    private let state = LockedState(State())
+
+    /// The program's own report wins over what its output looks like.
+    var current: PaneProgress? {
+        let state = state.snapshot
+        return state.reported ?? state.read
+    }
+
+    var updated: Date? { state.snapshot.updated }
+
+    /// Feed raw PTY bytes; true when the pane's progress changed.
+    func ingest(_ data: Data) -> Bool {
+        state.withLock { state in
+            let before = state.reported ?? state.read
+            var text = state.partial + String(decoding: data, as: UTF8.self)
+            let range = NSRange(text.startIndex..., in: text)
+            let reports = Self.oscPattern.matches(in: text, range: range)
+            for match in reports {
+                state.reported = Self.report(match, in: text, previous: state.reported)
+            }
+            if !reports.isEmpty {
+                text = Self.oscPattern.stringByReplacingMatches(in: text, range: range, withTemplate: "")
+            }
+            var segments = text.components(separatedBy: .newlines)
+            state.partial = String(segments.removeLast().suffix(4096))
+            for segment in segments {
+                guard let percent = Self.percent(in: PackageUpgradeProgress.stripEscapes(segment)) else { continue }
+                state.read = PaneProgress(percent: percent, state: .normal, source: .output)
+            }
+            let after = state.reported ?? state.read
+            guard after != before else { return false }
+            state.updated = Date()
+            return true
+        }
+    }
+
+    /// Forgets the last command's progress when one starts or finishes;
+    /// true when there was any.
+    func reset() -> Bool {
+        state.withLock { state in
+            guard state.reported != nil || state.read != nil else { return false }
+            state.reported = nil
+            state.read = nil
+            state.updated = Date()
+            return true
+        }
+    }
+
+    /// The progress one line of output shows, 0–100, or nil. Stated
+    /// percentages win; a count only counts next to a bar, so dates,
+    /// paths and `3/4 tests` in a log stay out of it.
+    static func percent(in line: String) -> Double? {
+        let range = NSRange(line.startIndex..., in: line)
+        if let match = percentPattern.matches(in: line, range: range).last,
+           let number = Range(match.range(at: 1), in: line),
+           let value = Double(line[number]), value <= 100 {
+            return value
+        }
+        guard barPattern.firstMatch(in: line, range: range) != nil,
+              let match = countPattern.firstMatch(in: line, range: range)
+        else { return nil }
+        func group(_ index: Int) -> String? {
+            Range(match.range(at: index), in: line).map { String(line[$0]) }
+        }
+        // pip writes the unit once, after the total.
+        let totalUnit = group(4)
+        guard let done = group(1).flatMap(Double.init), let total = group(3).flatMap(Double.init) else { return nil }
+        let doneBytes = done * scale(group(2) ?? totalUnit)
+        let totalBytes = total * scale(totalUnit)
+        guard totalBytes > 0, doneBytes <= totalBytes else { return nil }
+        return doneBytes / totalBytes * 100
+    }
+
+    private static func scale(_ unit: String?) -> Double {
+        guard let prefix = unit?.uppercased().first, prefix != "B" else { return 1 }
+        let binary = unit?.contains("i") == true
+        let power: Double = ["K": 1, "M": 2, "G": 3, "T": 4][prefix] ?? 0
+        return pow(binary ? 1024 : 1000, power)
+    }
+
+    private static func report(
+        _ match: NSTextCheckingResult, in text: String, previous: PaneProgress?
+    ) -> PaneProgress? {
+        let value = Range(match.range(at: 2), in: text).flatMap { Double(text[$0]) }.map { min($0, 100) }
+        let state: PaneProgress.State
+        switch Range(match.range(at: 1), in: text).map({ String(text[$0]) }) ?? "" {
+        case "1": state = .normal
+        case "2": state = .error
+        case "3": return PaneProgress(percent: nil, state: .indeterminate, source: .osc)
+        case "4": state = .paused
+        default: return nil
+        }
+        // Error and pause may leave the value out to keep the last one.
+        return PaneProgress(percent: value ?? previous?.percent, state: state, source: .osc)
+    }
+
+    private static let oscPattern = try! NSRegularExpression(
+        pattern: "\u{1B}\\]9;4;([0-4])(?:;(\\d{0,3}))?(?:\u{07}|\u{1B}\\\\)")
+    private static let percentPattern = try! NSRegularExpression(pattern: "(?<![\\d.])(\\d{1,3}(?:\\.\\d+)?) ?%")
+    private static let barPattern = try! NSRegularExpression(pattern: "\\[[=#>. *-]{3,}\\]|[━╸╺#█▉▊▋▌▍▎▏]{3,}")
+    private static let countPattern = try! NSRegularExpression(
+        pattern: "(\\d+(?:\\.\\d+)?) ?([kKMGT]i?B|B)? ?/ ?(\\d+(?:\\.\\d+)?) ?([kKMGT]i?B|B)?")
+}
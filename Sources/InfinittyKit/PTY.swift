@@ -2,12 +2,81 @@ import CPty
 import Darwin
 import Foundation
 
+/// A pane's output crossing into or out of flood territory.
+enum OutputFlood: Equatable {
+    case started
+    case ended(bytes: Int, duration: TimeInterval)
+}
+
+/// Measures a pane's output rate over windows of `window` seconds. A window
+/// at `floodRate` bytes a second or more starts a flood; one under a
+/// quarter of it ends the flood, so a rate hovering at the line doesn't
+/// flap.
+struct OutputFloodMeter {
+    static let window: TimeInterval = 0.25
+    static let floodRate = 4.0 * 1024 * 1024
+
+    private(set) var isFlooding = false
+    private var windowStart: TimeInterval?
+    private var windowBytes = 0
+    private var floodStart: TimeInterval = 0
+    private var floodBytes = 0
+
+    /// Counts `bytes` read at `now`; returns a change when a window closes
+    /// across the threshold.
+    mutating func record(_ bytes: Int, at now: TimeInterval) -> OutputFlood? {
+        if isFlooding { floodBytes += bytes }
+        guard let start = windowStart else {
+            windowStart = now
+            windowBytes = bytes
+            return nil
+        }
+        windowBytes += bytes
+        guard now - start >= Self.window else { return nil }
+        let rate = Double(windowBytes) / (now - start)
+        windowStart = now
+        windowBytes = 0
+        if !isFlooding, rate >= Self.floodRate {
+            isFlooding = true
+            floodStart = start
+            floodBytes = 0
+            return .started
+        }
+        if isFlooding, rate < Self.floodRate / 4 {
+            isFlooding = false
+            return .ended(bytes: floodBytes, duration: now - floodStart)
+        }
+        return nil
+    }
+}
+
 /// Pseudo-terminal plumbing. Reads happen on a dedicated high-QoS thread in
 /// 256 KB batches (one wakeup per kernel buffer, not per byte); writes go
 /// through a serial queue so a slow child can never block the UI.
+///
+/// Output that is already waiting when a read returns joins the same
+/// delivery, and while the child floods (`OutputFloodMeter`) the loop holds
+/// each delivery open for a frame, so the terminal and every output tap see
+/// ~60 batches a second rather than one per kernel buffer. Outside a flood
+/// nothing waits: a keystroke's echo goes straight through.
 final class PTY {
+    static let frameInterval: TimeInterval = 1.0 / 60
+
+    /// The far end of a pane with no local child, such as a tmux pane
+    /// driven in control mode: input and size changes go to it, and its
+    /// output arrives through `deliver`.
+    struct Remote {
+        let write: ([UInt8]) -> Void
+        let resize: (_ cols: Int, _ rows: Int) -> Void
+        let close: () -> Void
+    }
+
     private let lock = NSLock()
     private var _fd: Int32 = -1
+    private var _remote: Remote?
+    private var _inputLocked = false
+    private var _lastActivity = Date()
+    private var _inputTap: (([UInt8]) -> Void)?
     private(set) var pid: pid_t = -1
 
     var fd: Int32 {
@@ -18,20 +87,106 @@ final class PTY {
 
     var onData: ((UnsafePointer<UInt8>, Int) -> Void)?
     var onEOF: (() -> Void)?
+    /// Called on the read thread when output starts or stops flooding.
+    var onFlood: ((OutputFlood) -> Void)?
 
     private let writeQueue = DispatchQueue(label: "infinitty.pty.write", qos: .userInitiated)
     private var readThread: Thread?
 
-    /// Spawn the login shell. Returns false on forkpty failure (process limit,
-    /// etc.) instead of crashing the whole app.
+    private var remote: Remote? {
+        lock.lock()
+        defer { lock.unlock() }
+        return _remote
+    }
+
+    var isRemote: Bool { remote != nil }
+
+    /// Read-only pane: `write` drops everything (keys, paste, socket sends,
+    /// broadcasts) while set. Replies to the program's own queries still
+    /// go through `reply`.
+    var isInputLocked: Bool {
+        get {
+            lock.lock()
+            defer { lock.unlock() }
+            return _inputLocked
+        }
+        set {
+            lock.lock()
+            _inputLocked = newValue
+            lock.unlock()
+        }
+    }
+
+    /// When input was last written or output last read, for the idle
+    /// policy. Replies to the program's queries don't count.
+    var lastActivity: Date {
+        lock.lock()
+        defer { lock.unlock() }
+        return _lastActivity
+    }
+
+    /// Sees every write that gets past the input lock, on the writer's
+    /// thread; a recording macro sets it. Replies don't pass through.
+    var inputTap: (([UInt8]) -> Void)? {
+        get {
+            lock.lock()
+            defer { lock.unlock() }
+            return _inputTap
+        }
+        set {
+            lock.lock()
+            _inputTap = newValue
+            lock.unlock()
+        }
+    }
+
+    private func markActivity() {
+        lock.lock()
+        _lastActivity = Date()
+        lock.unlock()
+    }
+
+    /// Connects this PTY to `remote` instead of spawning a shell.
+    func attach(remote: Remote) {
+        lock.lock()
+        _remote = remote
+        lock.unlock()
+    }
+
+    /// Output from the remote end, handled like a read.
+    func deliver(_ bytes: [UInt8]) {
+        guard !bytes.isEmpty else { return }
+        markActivity()
+        bytes.withUnsafeBufferPointer { onData?($0.baseAddress!, $0.count) }
+    }
+
+    /// The remote end went away: ends the pane as EOF ends a local one.
+    func disconnectRemote() {
+        lock.lock()
+        let wasRemote = _remote != nil
+        _remote = nil
+        lock.unlock()
+        if wasRemote { onEOF?() }
+    }
+
+    /// Asks the remote end to close; `disconnectRemote` follows once it has.
+    func closeRemote() {
+        remote?.close()
+    }
+
+    /// Spawn the shell `launch` describes (by default the login shell).
+    /// Returns false on forkpty failure (process limit, etc.) instead of
+    /// crashing the whole app.
     @discardableResult
-    func spawn(cols: Int, rows: Int, socketPath: String? = nil, cwd: String? = nil) -> Bool {
+    func spawn(
+        cols: Int, rows: Int, launch: ShellLaunch = ShellLaunch(profile: nil, socketPath: nil), cwd: String? = nil
+    ) -> Bool {
         var ws = winsize(
             ws_row: UInt16(rows), ws_col: UInt16(cols),
             ws_xpixel: 0, ws_ypixel: 0
         )
         var master: Int32 = -1
-        let child = cpty_spawn_shell(&master, &ws, socketPath, cwd)
+        let child = launch.withCArrays { argv, envp in cpty_spawn(&master, &ws, launch.path, argv, envp, cwd) }
         guard child > 0, master >= 0 else {
             FileHandle.standardError.write(
                 Data("infinitty: failed to spawn shell (forkpty)\n".utf8))
@@ -58,10 +213,27 @@ final class PTY {
         let buf = UnsafeMutablePointer<UInt8>.allocate(capacity: bufSize)
         defer { buf.deallocate() }
         let currentFD = self.fd
+        var meter = OutputFloodMeter()
         while currentFD >= 0 {
+            // A flood that goes quiet has to end without another read.
+            if meter.isFlooding, !Self.readable(currentFD, within: OutputFloodMeter.window) {
+                if let change = meter.record(0, at: ProcessInfo.processInfo.systemUptime) { onFlood?(change) }
+                continue
+            }
             let n = read(currentFD, buf, bufSize)
             if n > 0 {
-                onData?(buf, n)
+                var filled = n
+                let deadline = ProcessInfo.processInfo.systemUptime + Self.frameInterval
+                while filled < bufSize {
+                    let wait = meter.isFlooding ? deadline - ProcessInfo.processInfo.systemUptime : 0
+                    guard wait >= 0, Self.readable(currentFD, within: wait) else { break }
+                    let more = read(currentFD, buf + filled, bufSize - filled)
+                    guard more > 0 else { break }
+                    filled += more
+                }
+                markActivity()
+                onData?(buf, filled)
+                if let change = meter.record(filled, at: ProcessInfo.processInfo.systemUptime) { onFlood?(change) }
             } else if n == 0 {
                 break
             } else if errno == EINTR {
@@ -87,8 +259,28 @@ final class PTY {
         onEOF?()
     }
 
+    /// Whether `fd` has input within `seconds` (zero just checks).
+    private static func readable(_ fd: Int32, within seconds: TimeInterval) -> Bool {
+        var descriptor = pollfd(fd: fd, events: Int16(POLLIN), revents: 0)
+        let ready = poll(&descriptor, 1, Int32((seconds * 1000).rounded(.up)))
+        return ready > 0 && descriptor.revents & Int16(POLLIN) != 0
+    }
+
     func write(_ bytes: [UInt8]) {
+        guard !isInputLocked else { return }
+        if !bytes.isEmpty { markActivity() }
+        inputTap?(bytes)
+        reply(bytes)
+    }
+
+    /// Terminal responses (DSR, DA, OSC queries) the program is waiting
+    /// for; unlike input these pass an input lock.
+    func reply(_ bytes: [UInt8]) {
         guard !bytes.isEmpty else { return }
+        if let remote {
+            remote.write(bytes)
+            return
+        }
         let targetFD = self.fd
         guard targetFD >= 0 else { return }
         writeQueue.async { [weak self] in
@@ -110,6 +302,10 @@ final class PTY {
     }
 
     func setSize(cols: Int, rows: Int, pixelWidth: Int = 0, pixelHeight: Int = 0) {
+        if let remote {
+            remote.resize(cols, rows)
+            return
+        }
         let targetFD = self.fd
         guard targetFD >= 0 else { return }
         _ = cpty_set_winsize(targetFD, UInt16(rows), UInt16(cols), UInt16(pixelWidth), UInt16(pixelHeight))
@@ -0,0 +1,341 @@
+import Darwin
+import Foundation
+
+enum PTYDaemonError: Error, Equatable, CustomStringConvertible {
+    case notInstalled
+    case notRunning
+    case startFailed(String)
+    case failed(String)
+
+    var description: String {
+        switch self {
+        case .notInstalled: return "infinitty-ptyd isn't next to the app"
+        case .notRunning: return "the PTY daemon isn't running"
+        case .startFailed(let reason): return "could not start the PTY daemon: \(reason)"
+        case .failed(let message): return "PTY daemon: \(message)"
+        }
+    }
+}
+
+/// One unit of the daemon's stream protocol (see infinitty-ptyd): a kind
+/// byte, a big-endian UInt32 length, then the payload.
+struct PTYDaemonFrame: Equatable {
+    enum Kind: UInt8 {
+        case input = 0x69 // i
+        case resize = 0x72 // r
+        case hangUp = 0x6B // k
+        case output = 0x6F // o
+        case exit = 0x78 // x
+    }
+
+    let kind: Kind
+    var payload: [UInt8] = []
+
+    static func resize(cols: Int, rows: Int) -> PTYDaemonFrame {
+        let cols = UInt16(clamping: cols), rows = UInt16(clamping: rows)
+        return PTYDaemonFrame(
+            kind: .resize, payload: [UInt8(cols >> 8), UInt8(cols & 0xFF), UInt8(rows >> 8), UInt8(rows & 0xFF)])
+    }
+
+    var encoded: [UInt8] {
+        let length = UInt32(payload.count)
+        return [kind.rawValue] + [24, 16, 8, 0].map { UInt8(length >> $0 & 0xFF) } + payload
+    }
+
+    /// Reassembles frames from a byte stream; unknown kinds are skipped.
+    struct Reader {
+        private var buffer: [UInt8] = []
+
+        mutating func feed(_ bytes: [UInt8]) -> [PTYDaemonFrame] {
+            buffer += bytes
+            var frames: [PTYDaemonFrame] = []
+            var start = 0
+            while buffer.count - start >= 5 {
+                let length = buffer[(start + 1)...(start + 4)].reduce(0) { $0 << 8 | Int($1) }
+                guard buffer.count - start - 5 >= length else { break }
+                let payload = Array(buffer[(start + 5)..<(start + 5 + length)])
+                if let kind = Kind(rawValue: buffer[start]) {
+                    frames.append(PTYDaemonFrame(kind: kind, payload: payload))
+                }
+                start += 5 + length
+            }
+            buffer.removeFirst(start)
+            return frames
+        }
+    }
+}
+
+/// A shell the daemon keeps, as its `list` reports it.
+struct PTYDaemonSession: Equatable {
+    let id: Int
+    let pid: pid_t
+    /// Whether an app connection is streaming it right now.
+    let attached: Bool
+    let started: Date
+
+    init?(_ object: [String: Any]) {
+        guard let id = object["id"] as? Int, let pid = object["pid"] as? Int else { return nil }
+        self.id = id
+        self.pid = pid_t(pid)
+        attached = object["attached"] as? Bool ?? false
+        started = Date(timeIntervalSince1970: object["started"] as? Double ?? 0)
+    }
+
+    var wire: [String: Any] {
+        ["id": id, "pid": Int(pid), "attached": attached, "started": started.timeIntervalSince1970]
+    }
+}
+
+/// The optional helper that owns shells on the app's behalf, so quitting
+/// infinitty leaves them running and the next launch picks them up again.
+enum PTYDaemon {
+    /// Made 0700 by the daemon; the socket is only trusted inside it.
+    static var socketDirectory: String { "/tmp/infinitty-ptyd-\(getuid())" }
+    static var socketPath: String { "\(socketDirectory)/ptyd.sock" }
+
+    /// `infinitty-ptyd`, shipped beside the app's executable.
+    static func executablePath(fileManager: FileManager = .default, bundle: Bundle = .main) -> String? {
+        [
+            bundle.executableURL?.deletingLastPathComponent().appendingPathComponent("infinitty-ptyd").path,
+            "\(bundle.bundlePath)/Contents/MacOS/infinitty-ptyd",
+        ].compactMap { $0 }.first { fileManager.isExecutableFile(atPath: $0) }
+    }
+
+    /// The daemon's pid and shells.
+    static func list() -> Result<(pid: pid_t, sessions: [PTYDaemonSession]), PTYDaemonError> {
+        request(["op": "list"]).map { answer in
+            let sessions = (answer.reply["sessions"] as? [[String: Any]] ?? []).compactMap(PTYDaemonSession.init)
+            close(answer.fd)
+            return (pid_t(answer.reply["pid"] as? Int ?? 0), sessions)
+        }
+    }
+
+    /// Starts the daemon unless it's already up; waits up to two seconds
+    /// for its socket. Call off the main thread. Callers racing at launch
+    /// take turns, so only one of them spawns a daemon.
+    static func start() -> Result<pid_t, PTYDaemonError> {
+        startLock.lock()
+        defer { startLock.unlock() }
+        if case .success(let running) = list() { return .success(running.pid) }
+        guard let path = executablePath() else { return .failure(.notInstalled) }
+        let process = Process()
+        process.executableURL = URL(fileURLWithPath: path)
+        process.standardInput = FileHandle.nullDevice
+        process.standardOutput = FileHandle.nullDevice
+        process.standardError = FileHandle.nullDevice
+        do {
+            try process.run()
+        } catch {
+            return .failure(.startFailed(error.localizedDescription))
+        }
+        for _ in 0..<20 {
+            Thread.sleep(forTimeInterval: 0.1)
+            if case .success(let running) = list() { return .success(running.pid) }
+        }
+        return .failure(.startFailed("it didn't open \(socketPath)"))
+    }
+    private static let startLock = NSLock()
+
+    /// Sends one handshake line and reads the one-line reply. Bytes that
+    /// arrived after it (the start of a stream) come back as `rest`.
+    fileprivate static func request(
+        _ object: [String: Any]
+    ) -> Result<(fd: Int32, reply: [String: Any], rest: [UInt8]), PTYDaemonError> {
+        let fd = connect()
+        guard fd >= 0 else { return .failure(.notRunning) }
+        var timeout = timeval(tv_sec: 3, tv_usec: 0)
+        setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, socklen_t(MemoryLayout<timeval>.size))
+        setsockopt(fd, SOL_SOCKET, SO_SNDTIMEO, &timeout, socklen_t(MemoryLayout<timeval>.size))
+        let line = ((try? JSONSerialization.data(withJSONObject: object)) ?? Data()) + Data([0x0A])
+        guard write(fd, Array(line), line.count) == line.count else {
+            close(fd)
+            return .failure(.notRunning)
+        }
+        var received: [UInt8] = []
+        var buffer = [UInt8](repeating: 0, count: 4096)
+        while !received.contains(0x0A) {
+            let n = read(fd, &buffer, buffer.count)
+            if n < 0, errno == EINTR { continue }
+            guard n > 0 else {
+                close(fd)
+                return .failure(.failed("no reply"))
+            }
+            received += buffer[..<n]
+        }
+        let end = received.firstIndex(of: 0x0A)!
+        guard let reply = (try? JSONSerialization.jsonObject(with: Data(received[..<end]))) as? [String: Any] else {
+            close(fd)
+            return .failure(.failed("unreadable reply"))
+        }
+        if let error = reply["error"] as? String {
+            close(fd)
+            return .failure(.failed(error))
+        }
+        // Streams block on reads for as long as the shell is quiet.
+        var none = timeval(tv_sec: 0, tv_usec: 0)
+        setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &none, socklen_t(MemoryLayout<timeval>.size))
+        return .success((fd, reply, Array(received[(end + 1)...])))
+    }
+
+    /// Connects only to a daemon of this user's: the directory must be ours
+    /// and private, and the peer's uid ours too, before any shell
+    /// environment or keystroke goes down the socket.
+    private static func connect() -> Int32 {
+        var info = stat()
+        guard lstat(socketDirectory, &info) == 0, (info.st_mode & S_IFMT) == S_IFDIR,
+              info.st_uid == getuid(), info.st_mode & 0o077 == 0 else { return -1 }
+        let fd = socket(AF_UNIX, SOCK_STREAM, 0)
+        guard fd >= 0 else { return -1 }
+        var addr = sockaddr_un()
+        addr.sun_family = sa_family_t(AF_UNIX)
+        let capacity = MemoryLayout.size(ofValue: addr.sun_path)
+        let ok = withUnsafeMutablePointer(to: &addr.sun_path) { tuple -> Bool in
+            tuple.withMemoryRebound(to: CChar.self, capacity: capacity) { dst in
+                let bytes = Array(socketPath.utf8)
+                guard bytes.count < capacity else { return false }
+                for (i, b) in bytes.enumerated() { dst[i] = CChar(bitPattern: b) }
+                dst[bytes.count] = 0
+                return true
+            }
+        }
+        let len = socklen_t(MemoryLayout<sockaddr_un>.size)
+        guard ok, withUnsafePointer(to: &addr, {
+            $0.withMemoryRebound(to: sockaddr.self, capacity: 1) { Darwin.connect(fd, $0, len) }
+        }) == 0 else {
+            close(fd)
+            return -1
+        }
+        var peerUID: uid_t = 0
+        var peerGID: gid_t = 0
+        guard getpeereid(fd, &peerUID, &peerGID) == 0, peerUID == getuid() else {
+            close(fd)
+            return -1
+        }
+        _ = fcntl(fd, F_SETFD, FD_CLOEXEC)
+        return fd
+    }
+}
+
+/// A pane's stream to a daemon-owned shell, standing in for its PTY's
+/// child. Closing the pane hangs the shell up; `detach` (the app quitting)
+/// just drops the stream and leaves the shell to the next attach.
+final class PTYDaemonPane {
+    let id: Int
+    let pid: pid_t
+    private let fd: Int32
+    private var pending: [UInt8]
+    private weak var pty: PTY?
+    private let writeQueue = DispatchQueue(label: "infinitty.ptyd.write", qos: .userInitiated)
+    private let ended = LockedState(false)
+
+    private init(id: Int, pid: pid_t, fd: Int32, pending: [UInt8]) {
+        self.id = id
+        self.pid = pid
+        self.fd = fd
+        self.pending = pending
+    }
+
+    /// A new shell in the daemon, sized, placed and started as a local one
+    /// would be.
+    static func spawn(
+        cols: Int, rows: Int, cwd: String?, launch: ShellLaunch
+    ) -> Result<PTYDaemonPane, PTYDaemonError> {
+        var request: [String: Any] = [
+            "op": "spawn", "cols": cols, "rows": rows,
+            "path": launch.path, "argv": launch.arguments, "env": launch.envp,
+        ]
+        if let cwd { request["cwd"] = cwd }
+        return open(request)
+    }
+
+    /// Reattaches to shell `id`; its recent output is replayed first.
+    static func attach(_ id: Int) -> Result<PTYDaemonPane, PTYDaemonError> {
+        open(["op": "attach", "id": id])
+    }
+
+    private static func open(_ request: [String: Any]) -> Result<PTYDaemonPane, PTYDaemonError> {
+        PTYDaemon.request(request).flatMap { answer in
+            guard let id = answer.reply["id"] as? Int, let pid = answer.reply["pid"] as? Int else {
+                close(answer.fd)
+                return .failure(.failed("unreadable reply"))
+            }
+            return .success(PTYDaemonPane(id: id, pid: pid_t(pid), fd: answer.fd, pending: answer.rest))
+        }
+    }
+
+    /// Makes the shell `pty`'s far end and starts streaming its output.
+    func bind(_ pty: PTY) {
+        self.pty = pty
+        pty.attach(remote: PTY.Remote(
+            write: { [weak self] bytes in self?.send(PTYDaemonFrame(kind: .input, payload: bytes)) },
+            resize: { [weak self] cols, rows in self?.send(.resize(cols: cols, rows: rows)) },
+            close: { [weak self] in self?.close() }))
+        let thread = Thread { [weak self] in self?.readLoop() }
+        thread.name = "infinitty-ptyd-read"
+        thread.qualityOfService = .userInitiated
+        thread.start()
+    }
+
+    /// Hangs the shell up; the pane ends when the daemon reports its exit.
+    func close() {
+        send(PTYDaemonFrame(kind: .hangUp))
+    }
+
+    /// Drops the stream without touching the shell.
+    func detach() {
+        guard ended.withLock({ ended -> Bool in
+            defer { ended = true }
+            return !ended
+        }) else { return }
+        shutdown(fd, SHUT_RDWR)
+    }
+
+    private func send(_ frame: PTYDaemonFrame) {
+        let bytes = frame.encoded
+        writeQueue.async { [fd, ended] in
+            guard !ended.snapshot else { return }
+            var offset = 0
+            while offset < bytes.count {
+                let n = bytes[offset...].withUnsafeBufferPointer { write(fd, $0.baseAddress, $0.count) }
+                if n > 0 {
+                    offset += n
+                } else if n < 0, errno == EINTR {
+                    continue
+                } else {
+                    return
+                }
+            }
+        }
+    }
+
+    /// Until the shell exits or the stream drops. Either way the pane ends
+    /// as a local one does at EOF, unless the app let go of it first.
+    private func readLoop() {
+        var reader = PTYDaemonFrame.Reader()
+        var incoming = pending
+        pending = []
+        var buffer = [UInt8](repeating: 0, count: 1 << 16)
+        streaming: while true {
+            for frame in reader.feed(incoming) {
+                switch frame.kind {
+                case .output: pty?.deliver(frame.payload)
+                case .exit: break streaming
+                default: break
+                }
+            }
+            let n = read(fd, &buffer, buffer.count)
+            if n < 0, errno == EINTR {
+                incoming = []
+                continue
+            }
+            guard n > 0 else { break }
+            incoming = Array(buffer[..<n])
+        }
+        let detached = !ended.withLock { ended -> Bool in
+            defer { ended = true }
+            return !ended
+        }
+        writeQueue.async { [fd] in close(fd) }
+        if !detached { pty?.disconnectRemote() }
+    }
+}
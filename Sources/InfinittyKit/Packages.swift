@@ -0,0 +1,285 @@
+import Foundation
+
+/// A system package manager infinitty knows how to query and drive.
+/// Homebrew and MacPorts are the ones a Mac has; apt and winget have no
+/// macOS install to find.
+enum PackageManager: String, CaseIterable {
+    case homebrew = "brew"
+    case macports = "port"
+
+    /// Standard install locations, checked before `$PATH` because GUI apps
+    /// launched from the Dock inherit a PATH without /opt/homebrew/bin.
+    var knownPaths: [String] {
+        switch self {
+        case .homebrew: return ["/opt/homebrew/bin/brew", "/usr/local/bin/brew"]
+        case .macports: return ["/opt/local/bin/port"]
+        }
+    }
+
+    /// The first installed manager, Homebrew preferred, with its path.
+    static func detect(
+        environment: [String: String] = ProcessInfo.processInfo.environment,
+        isExecutable: (String) -> Bool = { FileManager.default.isExecutableFile(atPath: $0) }
+    ) -> (manager: PackageManager, path: String)? {
+        let searchPath = (environment["PATH"] ?? "").split(separator: ":").map(String.init)
+        for manager in allCases {
+            let candidates = manager.knownPaths + searchPath.map { "\($0)/\(manager.rawValue)" }
+            if let path = candidates.first(where: isExecutable) { return (manager, path) }
+        }
+        return nil
+    }
+
+    var installedArguments: [[String]] {
+        switch self {
+        case .homebrew: return [["list", "--formula", "--versions"], ["list", "--cask", "--versions"]]
+        case .macports: return [["-q", "installed", "active"]]
+        }
+    }
+
+    var outdatedArguments: [String] {
+        switch self {
+        case .homebrew: return ["outdated", "--json=v2"]
+        case .macports: return ["-q", "outdated"]
+        }
+    }
+
+    /// Shell command typed into the upgrade pane. MacPorts writes to
+    /// /opt/local, so it goes through sudo; the prompt is the user's to
+    /// answer in that pane.
+    func upgradeCommand(path: String, packages: [String]) -> String {
+        let names = packages.map(Self.shellQuoted).joined(separator: " ")
+        switch self {
+        case .homebrew:
+            return ([Self.shellQuoted(path), "upgrade"] + (names.isEmpty ? [] : [names])).joined(separator: " ")
+        case .macports:
+            return "sudo \(Self.shellQuoted(path)) upgrade " + (names.isEmpty ? "outdated" : names)
+        }
+    }
+
+    /// Package names as managers print them; anything else is refused
+    /// before it reaches a shell.
+    static func isValidName(_ name: String) -> Bool {
+        !name.isEmpty && !name.hasPrefix("-") && name.unicodeScalars.allSatisfy {
+            CharacterSet.alphanumerics.contains($0) || "@+._/-".unicodeScalars.contains($0)
+        }
+    }
+
+    /// Quoted only when needed, so the typed command reads like one a
+    /// person would type.
+    private static func shellQuoted(_ text: String) -> String {
+        guard !text.isEmpty, !text.unicodeScalars.allSatisfy({
+            CharacterSet.alphanumerics.contains($0) || "@+._/-=:".unicodeScalars.contains($0)
+        }) else { return text }
+        return "'" + text.replacingOccurrences(of: "'", with: "'\\''") + "'"
+    }
+}
+
+struct InstalledPackage: Equatable {
+    enum Kind: String { case formula, cask, port }
+
+    let name: String
+    let kind: Kind
+    let installed: String
+    /// Newest available version, when outdated.
+    var latest: String?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["name": name, "kind": kind.rawValue, "installed": installed]
+        if let latest { object["latest"] = latest }
+        return object
+    }
+}
+
+enum PackageQueryError: Error, Equatable, CustomStringConvertible {
+    case noManager
+    case failed(String)
+
+    var description: String {
+        switch self {
+        case .noManager: return "no package manager found (Homebrew or MacPorts)"
+        case .failed(let reason): return reason
+        }
+    }
+}
+
+/// Parsers for each manager's listing output, kept separate from running
+/// the tools so they can be checked against captured output.
+enum PackageListing {
+    /// `brew list --versions`: `name 1.0 1.1` (the last version is linked).
+    static func brewInstalled(_ text: String, kind: InstalledPackage.Kind) -> [InstalledPackage] {
+        text.split(separator: "\n").compactMap { line in
+            let words = line.split(separator: " ")
+            guard words.count >= 2 else { return nil }
+            return InstalledPackage(name: String(words[0]), kind: kind, installed: String(words[words.count - 1]))
+        }
+    }
+
+    /// `brew outdated --json=v2`: formulae and casks with installed and
+    /// current versions. Casks report `installed_versions` as either a
+    /// string or an array depending on the Homebrew release.
+    static func brewOutdated(_ data: Data) -> [InstalledPackage]? {
+        guard let object = try? JSONSerialization.jsonObject(with: data) as? [String: Any] else { return nil }
+        var packages: [InstalledPackage] = []
+        for (key, kind) in [("formulae", InstalledPackage.Kind.formula), ("casks", .cask)] {
+            for entry in object[key] as? [[String: Any]] ?? [] {
+                guard let name = entry["name"] as? String else { continue }
+                let installed = (entry["installed_versions"] as? [String])?.last
+                    ?? entry["installed_versions"] as? String ?? ""
+                packages.append(InstalledPackage(
+                    name: name, kind: kind, installed: installed,
+                    latest: entry["current_version"] as? String))
+            }
+        }
+        return packages
+    }
+
+    /// `port -q installed active`: `  name @1.2.3_0+variant (active)`.
+    static func portInstalled(_ text: String) -> [InstalledPackage] {
+        text.split(separator: "\n").compactMap { line in
+            let words = line.split(separator: " ")
+            guard words.count >= 2, words[1].hasPrefix("@") else { return nil }
+            return InstalledPackage(name: String(words[0]), kind: .port, installed: String(words[1].dropFirst()))
+        }
+    }
+
+    /// `port -q outdated`: `name  1.2.3_0 < 1.2.4_0`.
+    static func portOutdated(_ text: String) -> [InstalledPackage] {
+        text.split(separator: "\n").compactMap { line in
+            let words = line.split(separator: " ")
+            guard words.count >= 4, words[2] == "<" else { return nil }
+            return InstalledPackage(
+                name: String(words[0]), kind: .port, installed: String(words[1]), latest: String(words[3]))
+        }
+    }
+
+    /// Runs the manager's query. Homebrew's auto-update is suppressed so a
+    /// listing never turns into a network fetch.
+    static func query(
+        _ manager: PackageManager, path: String, outdated: Bool
+    ) -> Result<[InstalledPackage], PackageQueryError> {
+        var environment = ProcessInfo.processInfo.environment
+        environment["HOMEBREW_NO_AUTO_UPDATE"] = "1"
+        environment["HOMEBREW_NO_ENV_HINTS"] = "1"
+        // `outdated` may exit non-zero just because something is outdated;
+        // its output still counts when there is some.
+        func run(_ arguments: [String]) -> Result<ProcessOutput, PackageQueryError> {
+            switch ProcessRunner.run(path, arguments, environment: environment, timeout: 120) {
+            case .success(let output) where output.status == 0 || (outdated && !output.stdout.isEmpty):
+                return .success(output)
+            case .success(let output):
+                let reason = output.stderrText.isEmpty ? "exit \(output.status)" : output.stderrText
+                return .failure(.failed("\(manager.rawValue) \(arguments.joined(separator: " ")): \(reason)"))
+            case .failure(let error):
+                return .failure(.failed("\(manager.rawValue): \(error)"))
+            }
+        }
+        if outdated {
+            return run(manager.outdatedArguments).flatMap { output in
+                switch manager {
+                case .homebrew:
+                    guard let packages = brewOutdated(output.stdout) else {
+                        return .failure(.failed("brew outdated: unreadable JSON"))
+                    }
+                    return .success(packages)
+                case .macports:
+                    return .success(portOutdated(output.stdoutText))
+                }
+            }
+        }
+        var packages: [InstalledPackage] = []
+        for (index, arguments) in manager.installedArguments.enumerated() {
+            switch run(arguments) {
+            case .success(let output):
+                switch manager {
+                case .homebrew:
+                    packages += brewInstalled(output.stdoutText, kind: index == 0 ? .formula : .cask)
+                case .macports:
+                    packages += portInstalled(output.stdoutText)
+                }
+            case .failure(let error):
+                return .failure(error)
+            }
+        }
+        return .success(packages)
+    }
+}
+
+/// Turns an upgrade pane's raw output into per-package progress. Only the
+/// manager's own step headers count, so the shell prompt, build logs and
+/// download bars in between are ignored.
+final class PackageUpgradeProgress {
+    let manager: PackageManager
+    /// Packages being upgraded; empty when upgrading "everything outdated".
+    let expected: [String]
+    /// Fed on the PTY thread, read by the task waiting on the upgrade.
+    private let state = LockedState((started: [String](), partial: ""))
+
+    init(manager: PackageManager, expected: [String]) {
+        self.manager = manager
+        self.expected = expected
+    }
+
+    /// Packages whose upgrade has begun, in order.
+    var started: [String] { state.snapshot.started }
+
+    /// Feed raw PTY bytes; returns the packages whose upgrade began in them.
+    func ingest(_ data: Data) -> [String] {
+        state.withLock { state in
+            state.partial += String(decoding: data, as: UTF8.self)
+            var lines = state.partial.components(separatedBy: .newlines)
+            state.partial = String(lines.removeLast().suffix(4096))
+            var begun: [String] = []
+            for line in lines {
+                guard let name = packageName(in: Self.stripEscapes(line)),
+                      !state.started.contains(name),
+                      expected.isEmpty || expected.contains(name)
+                else { continue }
+                state.started.append(name)
+                begun.append(name)
+            }
+            return begun
+        }
+    }
+
+    private func packageName(in line: String) -> String? {
+        let trimmed = line.trimmingCharacters(in: .whitespaces)
+        let prefix: String
+        switch manager {
+        case .homebrew: prefix = "==> Upgrading "
+        case .macports: prefix = "--->  Fetching archive for "
+        }
+        guard trimmed.hasPrefix(prefix) else { return nil }
+        let rest = trimmed.dropFirst(prefix.count)
+        // brew's "==> Upgrading 3 outdated packages:" summary is not a step.
+        guard let name = rest.split(separator: " ").first, !rest.hasSuffix(":") else { return nil }
+        return String(name)
+    }
+
+    /// Drops CSI and OSC sequences (brew colors its `==>` headers).
+    static func stripEscapes(_ line: String) -> String {
+        var out = String.UnicodeScalarView()
+        var scalars = line.unicodeScalars.makeIterator()
+        while let scalar = scalars.next() {
+            guard scalar == "\u{1B}" else {
+                if scalar != "\r" { out.append(scalar) }
+                continue
+            }
+            switch scalars.next() {
+            case "["?:
+                while let next = scalars.next(), !(0x40...0x7E).contains(next.value) {}
+            case "]"?:
+                // Ends at BEL or at ST (ESC \\).
+                while let next = scalars.next() {
+                    if next == "\u{07}" { break }
+                    if next == "\u{1B}" {
+                        _ = scalars.next()
+                        break
+                    }
+                }
+            default:
+                break
+            }
+        }
+        return String(out)
+    }
+}
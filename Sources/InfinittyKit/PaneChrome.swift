@@ -473,6 +473,8 @@ final class PaneHeaderView: NSView {
     var onChooseSplitRight: (() -> Void)?
     var onChooseSplitDown: (() -> Void)?
     var onToggleTodos: (() -> Void)?
+    var onSecureInput: (() -> Void)?
+    var onUnlockInput: (() -> Void)?
     var onToggleZoom: (() -> Void)?
     var onFocus: (() -> Void)?
     var onRenameCommit: ((String) -> Void)?
@@ -490,7 +492,12 @@ final class PaneHeaderView: NSView {
     private let splitDownButton = PaneSplitButton()
     private let todoButton = PaneSplitButton()
     private var todoTotal = 0
+    private let secureButton = PaneSplitButton()
+    private let inputLockButton = PaneSplitButton()
+    private let cloudBadge = NSTextField(labelWithString: "")
     private let bottomHairline = NSView()
+    private let progressBar = NSView()
+    private(set) var progress: PaneProgress?
     private var closeHoverActive = false
     private var iconTrackingArea: NSTrackingArea?
     private weak var renameEditor: TabRenameTextView?
@@ -521,6 +528,10 @@ final class PaneHeaderView: NSView {
     var isRenamingForTesting: Bool { renameEditor != nil }
     var todoButtonIsVisibleForTesting: Bool { !todoButton.isHidden }
     var todoTooltipForTesting: String { todoButton.toolTip ?? "" }
+    var secureTooltipForTesting: String? { secureButton.isHidden ? nil : secureButton.toolTip }
+    var inputLockIsVisibleForTesting: Bool { !inputLockButton.isHidden }
+    var cloudBadgeForTesting: String? { cloudBadge.isHidden ? nil : cloudBadge.stringValue }
+    var progressWidthForTesting: CGFloat? { progressBar.isHidden ? nil : progressBar.frame.width }
 
     /// Anchor for the todo popover.
     var todoAnchorView: NSView { todoButton }
@@ -539,10 +550,74 @@ final class PaneHeaderView: NSView {
         needsLayout = true
     }
 
+    /// Show the cloud profiles exported in this pane (`aws:staging ·
+    /// gcp:web`), in red when any of them looks like production. Empty
+    /// hides the badge.
+    func setCloudProfiles(_ profiles: [CloudProfile]) {
+        cloudBadge.isHidden = profiles.isEmpty
+        cloudBadge.stringValue = profiles.map(\.badge).joined(separator: " · ")
+        let production = profiles.contains(where: \.looksLikeProduction)
+        cloudBadge.textColor = production
+            ? NSColor.systemRed : NSColor.secondaryLabelColor.withAlphaComponent(0.85)
+        cloudBadge.toolTip = profiles.map { profile in
+            [profile.badge, profile.detail].compactMap { $0 }.joined(separator: " — ")
+        }.joined(separator: "\n")
+        cloudBadge.setAccessibilityLabel(
+            (production ? "Production cloud profile: " : "Cloud profile: ") + cloudBadge.stringValue)
+        needsLayout = true
+    }
+
+    /// Show how far the pane's command has got as a bar along the header's
+    /// bottom edge: full width and faint while indeterminate, red on error,
+    /// yellow while paused. Nil hides it.
+    func setProgress(_ progress: PaneProgress?) {
+        self.progress = progress
+        progressBar.isHidden = progress == nil
+        toolTip = progress.map { "Progress: \($0.badge)" }
+        guard let progress else { return }
+        let color: NSColor
+        switch progress.state {
+        case .normal: color = .controlAccentColor
+        case .error: color = .systemRed
+        case .paused: color = .systemYellow
+        case .indeterminate: color = NSColor.controlAccentColor.withAlphaComponent(0.35)
+        }
+        progressBar.layer?.backgroundColor = color.cgColor
+        progressBar.setAccessibilityValue(progress.percent.map { "\(Int($0)) percent" })
+        needsLayout = true
+    }
+
+    /// Show a lock while the pane waits for a password; pressing it offers
+    /// Keychain entries for the prompt. Nil hides it.
+    func setSecureInput(_ prompt: SecurePrompt?) {
+        secureButton.isHidden = prompt == nil
+        let tip = prompt.map { $0.prompt.isEmpty ? "Secure input" : "Secure input: \($0.prompt)" }
+        secureButton.toolTip = tip
+        secureButton.setAccessibilityLabel(tip ?? "Secure input")
+        needsLayout = true
+    }
+
+    /// Anchor for the Keychain fill menu.
+    var secureAnchorView: NSView { secureButton }
+
+    /// Show that the pane takes no input; pressing the badge unlocks it.
+    func setInputLocked(_ locked: Bool) {
+        inputLockButton.isHidden = !locked
+        needsLayout = true
+    }
+
     @objc private func todoPressed(_ sender: Any?) {
         onToggleTodos?()
     }
 
+    @objc private func securePressed(_ sender: Any?) {
+        onSecureInput?()
+    }
+
+    @objc private func inputLockPressed(_ sender: Any?) {
+        onUnlockInput?()
+    }
+
     override init(frame frameRect: NSRect) {
         super.init(frame: frameRect)
         wantsLayer = true
@@ -571,11 +646,33 @@ final class PaneHeaderView: NSView {
             todoButton, symbol: "checklist",
             label: "Agent todo list", action: #selector(todoPressed))
         todoButton.isHidden = true
+        configure(
+            secureButton, symbol: "lock.fill",
+            label: "Secure input", action: #selector(securePressed))
+        secureButton.contentTintColor = NSColor.systemOrange.withAlphaComponent(0.9)
+        secureButton.isHidden = true
+        configure(
+            inputLockButton, symbol: "pencil.slash",
+            label: "Input locked (click to unlock)", action: #selector(inputLockPressed))
+        inputLockButton.contentTintColor = NSColor.systemRed.withAlphaComponent(0.8)
+        inputLockButton.isHidden = true
+
+        cloudBadge.font = .monospacedSystemFont(ofSize: 10.5, weight: .semibold)
+        cloudBadge.alignment = .right
+        cloudBadge.lineBreakMode = .byTruncatingHead
+        cloudBadge.isHidden = true
+        addSubview(cloudBadge)
 
         bottomHairline.wantsLayer = true
         bottomHairline.layer?.backgroundColor = NSColor.clear.cgColor
         addSubview(bottomHairline)
 
+        progressBar.wantsLayer = true
+        progressBar.isHidden = true
+        progressBar.setAccessibilityRole(.progressIndicator)
+        progressBar.setAccessibilityLabel("Command progress")
+        addSubview(progressBar)
+
         setAccessibilityRole(.group)
         setAccessibilityLabel("Terminal pane")
     }
@@ -652,9 +749,21 @@ final class PaneHeaderView: NSView {
         todoButton.frame = NSRect(
             x: splitRightButton.frame.minX - (todoButton.isHidden ? 0 : buttonSize), y: 1,
             width: buttonSize, height: buttonSize)
+        secureButton.frame = NSRect(
+            x: todoButton.frame.minX - (secureButton.isHidden ? 0 : buttonSize), y: 1,
+            width: buttonSize, height: buttonSize)
+        inputLockButton.frame = NSRect(
+            x: secureButton.frame.minX - (inputLockButton.isHidden ? 0 : buttonSize), y: 1,
+            width: buttonSize, height: buttonSize)
         iconView.frame = NSRect(x: 10, y: 6, width: 16, height: 16)
-        let titleLimit = todoButton.isHidden
-            ? splitRightButton.frame.minX : todoButton.frame.minX
+        var titleLimit = inputLockButton.frame.minX
+        if !cloudBadge.isHidden {
+            // Never squeezed out by a long title: the badge keeps up to
+            // half the header and the title truncates first.
+            let width = min(ceil(cloudBadge.intrinsicContentSize.width), max(titleLimit - 32, 0) / 2)
+            cloudBadge.frame = NSRect(x: titleLimit - width - 6, y: 4, width: width, height: 16)
+            titleLimit = cloudBadge.frame.minX - 4
+        }
         titleLabel.frame = NSRect(
             x: 32, y: 1,
             width: max(titleLimit - 39, 0), height: 20)
@@ -662,6 +771,8 @@ final class PaneHeaderView: NSView {
             renameEditor.frame = renameFrame
         }
         bottomHairline.frame = NSRect(x: 0, y: 0, width: bounds.width, height: 1)
+        let fraction = progress.map { $0.state == .indeterminate ? 1 : ($0.percent ?? 0) / 100 } ?? 0
+        progressBar.frame = NSRect(x: 0, y: 0, width: (bounds.width * fraction).rounded(), height: 2)
     }
 
     private var renameFrame: NSRect {
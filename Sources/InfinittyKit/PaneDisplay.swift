@@ -0,0 +1,69 @@
+import Foundation
+
+/// A pane's own text size and line height, laid over the config's so
+/// zooming one split leaves its neighbours alone (`pane-display`, ⌘= / ⌘-
+/// / ⌘0). A nil field follows the config, including later edits to it.
+struct PaneDisplay: Codable, Equatable {
+    var fontSize: Double?
+    var lineSpacing: Double?
+
+    /// The bounds `AppConfig` clamps the same settings to.
+    static let fontSizes: ClosedRange<Double> = 6...72
+    static let lineSpacings: ClosedRange<Double> = 0.7...3
+
+    var isDefault: Bool { fontSize == nil && lineSpacing == nil }
+
+    /// `config` as this pane draws it.
+    func applied(to config: AppConfig) -> AppConfig {
+        var config = config
+        if let fontSize { config.fontSize = CGFloat(Self.fontSizes.clamp(fontSize)) }
+        if let lineSpacing { config.lineSpacing = CGFloat(Self.lineSpacings.clamp(lineSpacing)) }
+        return config
+    }
+
+    /// `steps` points bigger (or smaller) than the pane shows now. Landing
+    /// back on the config's size clears the override.
+    func stepped(_ steps: Int, from config: AppConfig) -> PaneDisplay {
+        var display = self
+        let size = Self.fontSizes.clamp((fontSize ?? Double(config.fontSize)) + Double(steps))
+        display.fontSize = size == Double(config.fontSize) ? nil : size
+        return display
+    }
+
+    /// This display with `{"fontSize", "lineSpacing"}` laid on; null clears
+    /// one. Nil when a value isn't a number in range.
+    func updated(with request: [String: Any]) -> PaneDisplay? {
+        var display = self
+        for (key, range) in [("fontSize", Self.fontSizes), ("lineSpacing", Self.lineSpacings)] {
+            guard let value = request[key] else { continue }
+            let number: Double?
+            if value is NSNull {
+                number = nil
+            } else {
+                guard let given = (value as? NSNumber)?.doubleValue, range.contains(given) else { return nil }
+                number = given
+            }
+            if key == "fontSize" { display.fontSize = number } else { display.lineSpacing = number }
+        }
+        return display
+    }
+
+    /// The overrides alone.
+    var wire: [String: Any] {
+        var object: [String: Any] = [:]
+        if let fontSize { object["fontSize"] = fontSize }
+        if let lineSpacing { object["lineSpacing"] = lineSpacing }
+        return object
+    }
+
+    /// What the pane shows with `config` underneath, and which of it is
+    /// the pane's own.
+    func wire(over config: AppConfig) -> [String: Any] {
+        let shown = applied(to: config)
+        return ["fontSize": Double(shown.fontSize), "lineSpacing": Double(shown.lineSpacing), "overrides": wire]
+    }
+}
+
+private extension ClosedRange where Bound == Double {
+    func clamp(_ value: Double) -> Double { Swift.min(Swift.max(value, lowerBound), upperBound) }
+}
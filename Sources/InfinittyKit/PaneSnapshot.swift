@@ -0,0 +1,289 @@
+import Darwin
+import Foundation
+
+enum PaneSnapshotError: Error, Equatable, CustomStringConvertible {
+    case serverUnavailable(String)
+    case uploadFailed(String)
+
+    var description: String {
+        switch self {
+        case .serverUnavailable(let reason): return "snapshot server unavailable: \(reason)"
+        case .uploadFailed(let reason): return "snapshot upload failed: \(reason)"
+        }
+    }
+}
+
+/// A pane's scrollback as a standalone HTML page: one `<pre>`, runs of
+/// identically styled cells merged into a `<span>`, colors resolved through
+/// the same theme the renderer draws with.
+enum PaneSnapshot {
+    static func html(rows: [[Cell]], theme: Theme, title: String) -> String {
+        var lines = rows.map { line(of: $0, theme: theme) }
+        while lines.last?.isEmpty == true { lines.removeLast() }
+        let background = css(theme.background)
+        let foreground = css(theme.foreground)
+        return """
+            <!doctype html>
+            <html><head><meta charset="utf-8"><title>\(escape(title))</title>
+            <style>
+            body { margin: 0; background: \(background); }
+            pre { margin: 0; padding: 16px; color: \(foreground); font: 13px/1.35 ui-monospace, \
+            "SF Mono", Menlo, monospace; white-space: pre; }
+            </style></head>
+            <body><pre>\(lines.joined(separator: "\n"))</pre></body></html>
+
+            """
+    }
+
+    private struct Style: Equatable {
+        var color: String?
+        var background: String?
+        var flags: UInt16
+    }
+
+    private static let styledFlags = CellFlags.bold | CellFlags.italic | CellFlags.underline
+        | CellFlags.strikethrough | CellFlags.invisible
+
+    /// One row as escaped HTML, trailing blank cells dropped.
+    private static func line(of row: [Cell], theme: Theme) -> String {
+        var end = row.count
+        while end > 0, row[end - 1].glyph == 0 || row[end - 1].glyph == 0x20,
+              row[end - 1].bg == ColorCode.defaultBG, row[end - 1].flags & CellFlags.inverse == 0 {
+            end -= 1
+        }
+        var out = ""
+        var run = ""
+        var runStyle: Style?
+        func flush() {
+            guard !run.isEmpty else { return }
+            out += open(runStyle) + run + (runStyle == nil ? "" : "</span>")
+            run = ""
+        }
+        for cell in row[..<end] where cell.flags & CellFlags.wideContinuation == 0 {
+            let style = style(of: cell, theme: theme)
+            if style != runStyle {
+                flush()
+                runStyle = style
+            }
+            if cell.glyph == 0 {
+                run += " "
+            } else if let scalar = Unicode.Scalar(cell.glyph) {
+                run += escape(String(Character(scalar)))
+            }
+        }
+        flush()
+        return out
+    }
+
+    /// nil for cells drawn in the page's default colors with no attributes.
+    private static func style(of cell: Cell, theme: Theme) -> Style? {
+        let inverse = cell.flags & CellFlags.inverse != 0
+        let fgCode = inverse ? cell.bg : cell.fg
+        let bgCode = inverse ? cell.fg : cell.bg
+        let flags = cell.flags & styledFlags
+        let color = fgCode == ColorCode.defaultFG && cell.flags & CellFlags.faint == 0
+            ? nil : css(theme.resolve(fgCode, isFG: true, flags: cell.flags))
+        let background = bgCode == ColorCode.defaultBG
+            ? nil : css(theme.resolve(bgCode, isFG: false, flags: cell.flags))
+        guard color != nil || background != nil || flags != 0 else { return nil }
+        return Style(color: color, background: background, flags: flags)
+    }
+
+    private static func open(_ style: Style?) -> String {
+        guard let style else { return "" }
+        var rules: [String] = []
+        if let color = style.color { rules.append("color:\(color)") }
+        if let background = style.background { rules.append("background:\(background)") }
+        if style.flags & CellFlags.bold != 0 { rules.append("font-weight:bold") }
+        if style.flags & CellFlags.italic != 0 { rules.append("font-style:italic") }
+        var decorations: [String] = []
+        if style.flags & CellFlags.underline != 0 { decorations.append("underline") }
+        if style.flags & CellFlags.strikethrough != 0 { decorations.append("line-through") }
+        if !decorations.isEmpty { rules.append("text-decoration:" + decorations.joined(separator: " ")) }
+        if style.flags & CellFlags.invisible != 0 { rules.append("visibility:hidden") }
+        return "<span style=\"\(rules.joined(separator: ";"))\">"
+    }
+
+    /// `#rrggbb`; alpha is dropped since a shared page has nothing to
+    /// show through.
+    private static func css(_ color: SIMD4<Float>) -> String {
+        func byte(_ v: Float) -> Int { Int((min(max(v, 0), 1) * 255).rounded()) }
+        return String(format: "#%02x%02x%02x", byte(color.x), byte(color.y), byte(color.z))
+    }
+
+    static func escape(_ text: String) -> String {
+        var out = ""
+        out.reserveCapacity(text.count)
+        for ch in text {
+            switch ch {
+            case "&": out += "&amp;"
+            case "<": out += "&lt;"
+            case ">": out += "&gt;"
+            case "\"": out += "&quot;"
+            default: out.append(ch)
+            }
+        }
+        return out
+    }
+
+    /// POST the page to a paste endpoint and return the URL it answers
+    /// with: a `Location` header, or else a response body that is a URL.
+    /// Blocks the calling (socket) thread for at most `timeout`.
+    static func upload(
+        _ html: String, to endpoint: URL, timeout: TimeInterval = 20
+    ) -> Result<String, PaneSnapshotError> {
+        var request = URLRequest(url: endpoint, timeoutInterval: timeout)
+        request.httpMethod = "POST"
+        request.setValue("text/html; charset=utf-8", forHTTPHeaderField: "Content-Type")
+        request.httpBody = Data(html.utf8)
+        let outcome = LockedState<Result<String, PaneSnapshotError>>(.failure(.uploadFailed("timed out")))
+        let done = DispatchSemaphore(value: 0)
+        URLSession.shared.dataTask(with: request) { data, response, error in
+            defer { done.signal() }
+            let result: Result<String, PaneSnapshotError>
+            if let error {
+                result = .failure(.uploadFailed(error.localizedDescription))
+            } else if let http = response as? HTTPURLResponse, !(200..<300).contains(http.statusCode) {
+                result = .failure(.uploadFailed("HTTP \(http.statusCode)"))
+            } else if let location = (response as? HTTPURLResponse)?.value(forHTTPHeaderField: "Location"),
+                      let url = URL(string: location, relativeTo: endpoint) {
+                result = .success(url.absoluteString)
+            } else {
+                let body = String(decoding: data ?? Data(), as: UTF8.self)
+                    .trimmingCharacters(in: .whitespacesAndNewlines)
+                result = body.hasPrefix("http://") || body.hasPrefix("https://")
+                    ? .success(body) : .failure(.uploadFailed("no URL in the response"))
+            }
+            outcome.withLock { $0 = result }
+        }.resume()
+        _ = done.wait(timeout: .now() + timeout + 1)
+        return outcome.snapshot
+    }
+}
+
+/// Serves exported snapshots at unguessable paths on a loopback port, so a
+/// teammate on the same machine (or behind an SSH tunnel) can open them in
+/// a browser. Started on first use; pages expire after `lifetime`.
+final class PaneSnapshotServer {
+    static let shared = PaneSnapshotServer()
+    static let lifetime: TimeInterval = 3600
+    static let maxPages = 32
+
+    private struct Page {
+        let html: Data
+        let expires: Date
+    }
+
+    private let state = LockedState((fd: Int32(-1), port: 0, pages: [String: Page]()))
+
+    /// Store `html` and return its URL, starting the listener if needed.
+    func publish(_ html: String, now: Date = Date()) -> Result<String, PaneSnapshotError> {
+        let port: Int
+        switch start() {
+        case .success(let bound): port = bound
+        case .failure(let error): return .failure(error)
+        }
+        let token = UUID().uuidString.replacingOccurrences(of: "-", with: "").lowercased()
+        state.withLock { state in
+            state.pages = state.pages.filter { $0.value.expires > now }
+            if state.pages.count >= Self.maxPages,
+               let oldest = state.pages.min(by: { $0.value.expires < $1.value.expires })?.key {
+                state.pages.removeValue(forKey: oldest)
+            }
+            state.pages[token] = Page(html: Data(html.utf8), expires: now.addingTimeInterval(Self.lifetime))
+        }
+        return .success("http://127.0.0.1:\(port)/\(token).html")
+    }
+
+    func stop() {
+        state.withLock { state in
+            if state.fd >= 0 { close(state.fd) }
+            state = (fd: -1, port: 0, pages: [:])
+        }
+    }
+
+    private func start() -> Result<Int, PaneSnapshotError> {
+        state.withLock { state -> Result<Int, PaneSnapshotError> in
+            if state.fd >= 0 { return .success(state.port) }
+            let fd = socket(AF_INET, SOCK_STREAM, 0)
+            guard fd >= 0 else { return .failure(.serverUnavailable(String(cString: strerror(errno)))) }
+            var addr = sockaddr_in()
+            addr.sin_len = UInt8(MemoryLayout<sockaddr_in>.size)
+            addr.sin_family = sa_family_t(AF_INET)
+            addr.sin_addr.s_addr = inet_addr("127.0.0.1")
+            addr.sin_port = 0
+            var len = socklen_t(MemoryLayout<sockaddr_in>.size)
+            let bound = withUnsafePointer(to: &addr) {
+                $0.withMemoryRebound(to: sockaddr.self, capacity: 1) { bind(fd, $0, len) }
+            }
+            let named = withUnsafeMutablePointer(to: &addr) {
+                $0.withMemoryRebound(to: sockaddr.self, capacity: 1) { getsockname(fd, $0, &len) }
+            }
+            guard bound == 0, named == 0, listen(fd, 16) == 0 else {
+                let reason = String(cString: strerror(errno))
+                close(fd)
+                return .failure(.serverUnavailable(reason))
+            }
+            _ = fcntl(fd, F_SETFD, FD_CLOEXEC)
+            state.fd = fd
+            state.port = Int(UInt16(bigEndian: addr.sin_port))
+            let thread = Thread { [weak self] in self?.acceptLoop(fd) }
+            thread.name = "infinitty-snapshot-server"
+            thread.qualityOfService = .utility
+            thread.start()
+            return .success(state.port)
+        }
+    }
+
+    private func acceptLoop(_ listenFD: Int32) {
+        while true {
+            let client = accept(listenFD, nil, nil)
+            if client < 0 {
+                if errno == EINTR { continue }
+                break
+            }
+            _ = fcntl(client, F_SETFD, FD_CLOEXEC)
+            DispatchQueue.global(qos: .utility).async { [weak self] in
+                self?.serve(client)
+                close(client)
+            }
+        }
+    }
+
+    private func serve(_ fd: Int32) {
+        var tv = timeval(tv_sec: 5, tv_usec: 0)
+        setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &tv, socklen_t(MemoryLayout<timeval>.size))
+        setsockopt(fd, SOL_SOCKET, SO_SNDTIMEO, &tv, socklen_t(MemoryLayout<timeval>.size))
+        var buf = [UInt8](repeating: 0, count: 4096)
+        var head: [UInt8] = []
+        while head.count < 16384, !head.suffix(4).elementsEqual([13, 10, 13, 10]) {
+            let n = read(fd, &buf, buf.count)
+            guard n > 0 else { return }
+            head += buf[..<n]
+        }
+        let (status, body) = response(to: String(decoding: head, as: UTF8.self))
+        let header = "HTTP/1.1 \(status)\r\nContent-Type: text/html; charset=utf-8\r\n"
+            + "Content-Length: \(body.count)\r\nCache-Control: no-store\r\n"
+            + "X-Content-Type-Options: nosniff\r\nConnection: close\r\n\r\n"
+        let bytes = Array(header.utf8) + body
+        var sent = 0
+        while sent < bytes.count {
+            let n = bytes[sent...].withUnsafeBufferPointer { write(fd, $0.baseAddress, $0.count) }
+            guard n > 0 else { return }
+            sent += n
+        }
+    }
+
+    private func response(to request: String) -> (String, [UInt8]) {
+        let words = request.prefix { $0 != "\r" && $0 != "\n" }.split(separator: " ")
+        guard words.count >= 2, words[0] == "GET" else {
+            return ("405 Method Not Allowed", Array("method not allowed\n".utf8))
+        }
+        let token = words[1].dropFirst().replacingOccurrences(of: ".html", with: "")
+        let page = state.withLock { $0.pages[token] }
+        guard let page, page.expires > Date() else {
+            return ("404 Not Found", Array("snapshot not found or expired\n".utf8))
+        }
+        return ("200 OK", Array(page.html))
+    }
+}
@@ -0,0 +1,115 @@
+import Foundation
+
+/// Something about a paste worth a second look before it reaches the shell.
+enum PasteConcern: String, CaseIterable {
+    /// Has line breaks and the program didn't ask for bracketed paste, so
+    /// each line runs as it lands.
+    case multiline
+    /// Runs something as root: sudo, su, doas.
+    case privileged
+    /// Pipes a download straight into a shell.
+    case pipeToShell = "pipe-to-shell"
+    /// Deletes recursively or writes to a disk.
+    case destructive
+    /// Has characters that don't show but change what runs or how it
+    /// reads: direction overrides, zero-width spaces, line separators.
+    case hidden = "hidden-characters"
+
+    var explanation: String {
+        switch self {
+        case .multiline: return "Each line runs as soon as it is pasted."
+        case .privileged: return "It runs a command as root."
+        case .pipeToShell: return "It pipes a download into a shell."
+        case .destructive: return "It deletes files recursively or writes to a disk."
+        case .hidden: return "It has invisible characters that can make it look like something else."
+        }
+    }
+}
+
+/// A paste made safe to type: control characters a clipboard could use to
+/// break out of bracketed paste, or to act without a key press, are gone,
+/// line breaks are carriage returns, and `concerns` says whether to ask
+/// first. Every paste goes through here, from the keyboard, a drop or the
+/// socket.
+struct PasteCheck: Equatable {
+    let text: String
+    /// Control characters dropped.
+    let removed: Int
+    /// Invisible characters kept but flagged (`PasteConcern.hidden`).
+    let hidden: Int
+    let concerns: [PasteConcern]
+    let bracketed: Bool
+
+    var lineCount: Int { text.split(separator: "\r", omittingEmptySubsequences: false).count }
+
+    /// `text` for showing before it's pasted, each invisible character
+    /// spelled out as `<U+202E>`.
+    var visibleText: String {
+        guard hidden > 0 else { return text }
+        var shown = ""
+        for scalar in text.unicodeScalars {
+            if Self.isHidden(scalar) {
+                shown += String(format: "<U+%04X>", scalar.value)
+            } else {
+                shown.unicodeScalars.append(scalar)
+            }
+        }
+        return shown
+    }
+
+    private static func isHidden(_ scalar: Unicode.Scalar) -> Bool {
+        switch scalar.value {
+        case 0x200B...0x200F, 0x2028...0x202E, 0x2060, 0x2066...0x2069, 0xFEFF: return true
+        default: return false
+        }
+    }
+
+    /// What the PTY is sent, wrapped in ESC[200~ … ESC[201~ when the
+    /// program asked for bracketed paste.
+    var bytes: [UInt8] {
+        let body = Array(text.utf8)
+        return bracketed ? Array("\u{1B}[200~".utf8) + body + Array("\u{1B}[201~".utf8) : body
+    }
+
+    private static let privileged = try! NSRegularExpression(
+        pattern: #"(^|[\s;&|(`$])(sudo|su|doas|pkexec)(\s|$)"#, options: .anchorsMatchLines)
+    private static let pipeToShell = try! NSRegularExpression(
+        pattern: #"\b(curl|wget|fetch)\b[^|\r]*\|\s*(sudo\s+)?(ba|z|fi|k|da)?sh\b"#)
+    private static let destructive = try! NSRegularExpression(
+        pattern: #"\brm\s+(-[A-Za-z]*[rR][A-Za-z]*|--recursive)\b|\bmkfs(\.\w+)?\b|\bdd\b[^\r]*\bof=/dev/"#)
+
+    init(_ pasted: String, bracketed: Bool) {
+        var text = ""
+        var removed = 0
+        var hidden = 0
+        for scalar in pasted.replacingOccurrences(of: "\r\n", with: "\r").unicodeScalars {
+            switch scalar.value {
+            case 0x0A, 0x0D: text.unicodeScalars.append("\r")
+            case 0x09: text.unicodeScalars.append(scalar)
+            case 0x00...0x1F, 0x7F...0x9F: removed += 1
+            default:
+                if Self.isHidden(scalar) { hidden += 1 }
+                text.unicodeScalars.append(scalar)
+            }
+        }
+        self.text = text
+        self.removed = removed
+        self.hidden = hidden
+        self.bracketed = bracketed
+        let range = NSRange(text.startIndex..., in: text)
+        var concerns: [PasteConcern] = []
+        if !bracketed, text.contains("\r") { concerns.append(.multiline) }
+        if Self.privileged.firstMatch(in: text, range: range) != nil { concerns.append(.privileged) }
+        if Self.pipeToShell.firstMatch(in: text, range: range) != nil { concerns.append(.pipeToShell) }
+        if Self.destructive.firstMatch(in: text, range: range) != nil { concerns.append(.destructive) }
+        if hidden > 0 { concerns.append(.hidden) }
+        self.concerns = concerns
+    }
+
+    var wire: [String: Any] {
+        [
+            "lines": lineCount, "removed": removed, "hidden": hidden, "bracketed": bracketed,
+            "concerns": concerns.map(\.rawValue),
+        ]
+    }
+}
@@ -327,6 +327,9 @@ final class PetAssistantPanelView: NSView {
     private let inputScroll = NSScrollView()
     private let input = TabRenameTextView()
     private let attachmentButton = NSButton()
+    private let micButton = NSButton()
+    /// What was typed before dictation started; transcripts follow it.
+    private var dictationDraft: String?
     private let sendButton = NSButton()
     private let sendWrap = NSView()
     private let showFilesButton = NSButton(title: "Show Files", target: nil, action: nil)
@@ -506,6 +509,13 @@ final class PetAssistantPanelView: NSView {
         attachmentButton.target = self
         attachmentButton.action = #selector(attachmentTapped)
 
+        micButton.image = NSImage(systemSymbolName: "mic", accessibilityDescription: "Dictate")
+        micButton.isBordered = false
+        micButton.contentTintColor = .secondaryLabelColor
+        micButton.toolTip = "Dictate"
+        micButton.target = self
+        micButton.action = #selector(micTapped)
+
         input.font = .systemFont(ofSize: NSFont.systemFontSize)
         input.textColor = .labelColor
         input.insertionPointColor = .labelColor
@@ -664,13 +674,14 @@ final class PetAssistantPanelView: NSView {
 
         inputContainer.addSubview(attachmentButton)
         inputContainer.addSubview(inputScroll)
+        inputContainer.addSubview(micButton)
         sendWrap.addSubview(sendButton)
         inputContainer.addSubview(sendWrap)
         let views = [
             newChatButton, closeButton,
             separator, transcriptScroll, emptyStateLabel, showFilesButton, queueScroll,
             modelPicker, modelButton, effortButton, inputContainer, attachmentButton,
-            inputScroll, sendButton, sendWrap,
+            inputScroll, micButton, sendButton, sendWrap,
         ]
         for view in views { view.translatesAutoresizingMaskIntoConstraints = false }
         for view in [
@@ -742,9 +753,12 @@ final class PetAssistantPanelView: NSView {
             attachmentButton.centerYAnchor.constraint(equalTo: inputContainer.centerYAnchor),
             attachmentButton.widthAnchor.constraint(equalToConstant: 24),
             inputScroll.leadingAnchor.constraint(equalTo: attachmentButton.trailingAnchor, constant: 4),
-            inputScroll.trailingAnchor.constraint(equalTo: sendWrap.leadingAnchor, constant: -6),
+            inputScroll.trailingAnchor.constraint(equalTo: micButton.leadingAnchor, constant: -4),
             inputScroll.centerYAnchor.constraint(equalTo: inputContainer.centerYAnchor),
             inputScroll.heightAnchor.constraint(equalToConstant: 32),
+            micButton.trailingAnchor.constraint(equalTo: sendWrap.leadingAnchor, constant: -4),
+            micButton.centerYAnchor.constraint(equalTo: inputContainer.centerYAnchor),
+            micButton.widthAnchor.constraint(equalToConstant: 24),
             sendWrap.trailingAnchor.constraint(equalTo: inputContainer.trailingAnchor, constant: -7),
             sendWrap.bottomAnchor.constraint(equalTo: inputContainer.bottomAnchor, constant: -7),
             sendWrap.widthAnchor.constraint(equalToConstant: 30),
@@ -834,11 +848,50 @@ final class PetAssistantPanelView: NSView {
     private func submit() {
         let request = input.string.trimmingCharacters(in: .whitespacesAndNewlines)
         guard !request.isEmpty else { return }
+        if dictationDraft != nil { stopDictation(keepText: false) }
         input.string = ""
         onSubmit?(request, selectedChoice.displayName, selectedEffort)
     }
 
     @objc private func sendTapped(_ sender: Any?) { submit() }
+
+    /// Toggles dictation into the composer. Transcripts replace each other
+    /// after whatever was already typed, the final one arriving just after
+    /// the stop; the result is left to edit and send.
+    @objc private func micTapped(_ sender: Any?) {
+        if dictationDraft != nil { return stopDictation(keepText: true) }
+        let draft = input.string
+        SpeechInput.shared.start(onUpdate: { [weak self] transcript in
+            guard let self else { return }
+            self.input.string = SpeechInput.composed(draft: draft, transcript: transcript.text)
+            if transcript.isFinal { self.setDictating(false) }
+        }, completion: { [weak self] result in
+            guard let self else { return }
+            switch result {
+            case .success:
+                self.dictationDraft = draft
+                self.setDictating(true)
+                self.focusInput()
+            case .failure(let error):
+                self.micButton.toolTip = "Dictation unavailable: \(error)"
+                NSSound.beep()
+            }
+        })
+    }
+
+    private func stopDictation(keepText: Bool) {
+        if keepText { SpeechInput.shared.stop() } else { SpeechInput.shared.cancel() }
+        setDictating(false)
+    }
+
+    private func setDictating(_ dictating: Bool) {
+        if !dictating { dictationDraft = nil }
+        micButton.image = NSImage(
+            systemSymbolName: dictating ? "mic.fill" : "mic",
+            accessibilityDescription: dictating ? "Stop dictating" : "Dictate")
+        micButton.contentTintColor = dictating ? .systemRed : .secondaryLabelColor
+        micButton.toolTip = dictating ? "Stop dictating" : "Dictate"
+    }
     @objc private func showFilesTapped(_ sender: Any?) { onShowFiles?() }
     @objc private func newChatTapped(_ sender: Any?) { onNewChat?() }
     @objc private func closeTapped(_ sender: Any?) { onClose?() }
@@ -1042,6 +1095,8 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     private var sidebarMessages: [AssistantChatMessage] = []
     private var pendingRequests: [PendingRequest] = []
     private var requestInFlight = false
+    /// Cancels the backend call behind `requestInFlight`.
+    private var inFlightCancellation: CancellationToken?
     private var conversationGeneration = 0
     private var recoveryContext: String?
     private weak var sidebarPanel: PetAssistantPanelView?
@@ -1135,6 +1190,7 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     private func resetConversation() {
         conversationGeneration += 1
         pendingRequests.removeAll()
+        inFlightCancellation?.cancel()
         sidebarMessages.removeAll()
         lastFiles.removeAll()
         lastQuery = nil
@@ -1257,9 +1313,11 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
         if let requestRunner {
             requestRunner(backendRequest, request.model, request.effort, completion)
         } else {
+            let cancellation = CancellationToken()
+            inFlightCancellation = cancellation
             ask(
                 backendRequest, model: request.model, effort: request.effort,
-                completion: completion)
+                cancellation: cancellation, completion: completion)
         }
     }
 
@@ -1334,7 +1392,9 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
         files: [String], query: String?
     ) {
         requestInFlight = false
-        if request.generation == conversationGeneration {
+        let cancelled = inFlightCancellation?.isCancelled == true
+        inFlightCancellation = nil
+        if request.generation == conversationGeneration, !cancelled {
             lastFiles = files
             lastQuery = query
             sidebarMessages.append(AssistantChatMessage(
@@ -1350,6 +1410,15 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
         session = nil
     }
 
+    /// Drop queued requests and stop the one in flight (its CLI turn, HTTP
+    /// request or command hook). Used when the Chat pane closes, so a
+    /// backend doesn't keep working on an answer nobody will see.
+    func cancelPendingRequests() {
+        pendingRequests.removeAll()
+        inFlightCancellation?.cancel()
+        updatePanels()
+    }
+
     // MARK: - input bubble
 
     func presentInput(anchorRect: NSRect, in view: NSView) {
@@ -1434,13 +1503,14 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     For plain questions that need no terminal action, answer concisely in a few \
     sentences of plain text (no markdown). If answering requires finding files \
     in the project, reply with EXACTLY one line "SEARCH: <filename or path \
-    keywords>" and nothing else; you will receive the matching files to compose \
-    the final answer.
+    keywords>" and nothing else; you will receive the matching files, and lines \
+    containing the keywords, to compose the final answer.
     """
 
     private func ask(
         _ request: String, model: String = "Auto · Best available",
         effort: String = "Auto",
+        cancellation: CancellationToken? = nil,
         completion: AskCompletion? = nil
     ) {
         // A Chat/Browser tab is allowed to outlive its final terminal pane.
@@ -1481,24 +1551,33 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
                 + (effortNote.isEmpty ? "" : "\n" + effortNote)
             let runCwd = cwd ?? NSHomeDirectory()
 
-            Self.askAI(backend: backend, system: system, user: user, cwd: runCwd) { outcome in
+            Self.askAI(
+                backend: backend, system: system, user: user, cwd: runCwd,
+                cancellation: cancellation
+            ) { outcome in
                 if let query = Self.parseSearchDirective(Self.replyText(for: outcome)), let cwd {
-                    let all = CodeSearch.listFilesSync(root: cwd)
+                    let all = CodeSearch.listFilesSync(root: cwd, cancellation: cancellation)
                     let matches = CodeSearch.filter(all, query: query, limit: 50)
                     let fileBlock = matches.isEmpty
                         ? "(no files matched)" : matches.joined(separator: "\n")
+                    let lines = Self.indexedLines(containing: query, in: cwd)
                     let followUp = context
                         + "\n--- files matching \"\(query)\" ---\n" + fileBlock
+                        + (lines.isEmpty ? "" : "\n--- lines containing \"\(query)\" ---\n"
+                            + lines.joined(separator: "\n"))
                         + "\n--- user request ---\n" + request
-                    Self.askAI(backend: backend, system: system, user: followUp, cwd: runCwd) { final in
+                    Self.askAI(
+                        backend: backend, system: system, user: followUp, cwd: runCwd,
+                        cancellation: cancellation
+                    ) { final in
                         self.finish(
                             answer: Self.displayText(for: final), files: matches, query: query,
-                            completion: completion)
+                            cancellation: cancellation, completion: completion)
                     }
                 } else {
                     self.finish(
                         answer: Self.displayText(for: outcome),
-                        files: [], query: nil, completion: completion)
+                        files: [], query: nil, cancellation: cancellation, completion: completion)
                 }
             }
         }
@@ -1678,6 +1757,18 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
         return .none
     }
 
+    /// `path:line: text` for lines under `cwd` containing `query`, from the
+    /// workspace's search index. Empty until the index has finished its
+    /// first pass, so a cold project never waits on a full read.
+    static func indexedLines(containing query: String, in cwd: String, limit: Int = 40) -> [String] {
+        let index = SearchIndexes.shared.index(for: SearchIndexes.workspaceRoot(of: cwd))
+        let root = index.root.hasSuffix("/") ? index.root : index.root + "/"
+        let directory = (cwd as NSString).standardizingPath + "/"
+        let prefix = directory.hasPrefix(root) ? String(directory.dropFirst(root.count)) : ""
+        let matches = index.search(query, limit: limit, under: prefix.isEmpty ? nil : prefix) ?? []
+        return matches.map { "\($0.path.dropFirst(prefix.count)):\($0.line): \($0.text)" }
+    }
+
     /// "SEARCH: keywords" as the entire reply → keywords, else nil.
     static func parseSearchDirective(_ reply: String?) -> String? {
         guard let line = reply?
@@ -1713,11 +1804,12 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
 
     private func finish(
         answer: String, files: [String], query: String?,
-        completion: AskCompletion?
+        cancellation: CancellationToken?, completion: AskCompletion?
     ) {
         DispatchQueue.main.async {
             self.session?.petAnimator?.stopThinking()
             completion?(answer, files, query)
+            guard cancellation?.isCancelled != true else { return }
             self.onPetMessage?(answer)
         }
     }
@@ -1728,43 +1820,54 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     // MARK: - AI backends (mirrors HintEngine's smart-source resolution)
 
     /// Calls `done` on whatever thread the backend completes on; callers hop
-    /// to main as needed.
+    /// to main as needed. Cancelling `cancellation` interrupts the backend,
+    /// which still calls `done` (with a failure).
     static func askAI(
         backend: Backend,
         system: String, user: String, cwd: String,
+        cancellation: CancellationToken? = nil,
         done: @escaping (AIOutcome) -> Void
     ) {
+        if cancellation?.isCancelled == true {
+            done(.failure("Cancelled."))
+            return
+        }
         switch backend {
         case .none:
             done(.unconfigured)
         case .command(let cmd):
-            let proc = Process()
-            proc.executableURL = URL(fileURLWithPath: "/bin/zsh")
-            proc.arguments = ["-c", cmd]
-            let stdin = Pipe(), stdout = Pipe()
-            proc.standardInput = stdin
-            proc.standardOutput = stdout
-            proc.standardError = Pipe()
-            guard (try? proc.run()) != nil else {
+            let result = ProcessRunner.run(
+                "/bin/zsh", ["-c", cmd],
+                input: Data((system + "\n\n" + user).utf8), timeout: 120,
+                cancellation: cancellation)
+            let output: ProcessOutput
+            switch result {
+            case .failure(.launchFailed):
                 done(.failure("Custom AI command failed to launch: \(cmd)")); return
+            case .failure(let error):
+                done(.failure("Custom AI command \(error).")); return
+            case .success(let finished):
+                output = finished
             }
-            stdin.fileHandleForWriting.write(Data((system + "\n\n" + user).utf8))
-            try? stdin.fileHandleForWriting.close()
-            let data = stdout.fileHandleForReading.readDataToEndOfFile()
-            proc.waitUntilExit()
-            guard proc.terminationStatus == 0 else {
-                done(.failure("Custom AI command exited \(proc.terminationStatus).")); return
+            guard output.status == 0 else {
+                done(.failure("Custom AI command exited \(output.status).")); return
             }
-            let text = String(data: data, encoding: .utf8)?
+            let text = String(data: output.stdout, encoding: .utf8)?
                 .trimmingCharacters(in: .whitespacesAndNewlines)
             if let text, !text.isEmpty { done(.text(text)) }
             else { done(.failure("Custom AI command produced no output.")) }
         case .openai(let base, let key, let model):
-            askOpenAI(base: base, key: key, model: model, system: system, user: user, done: done)
+            askOpenAI(
+                base: base, key: key, model: model, system: system, user: user,
+                cancellation: cancellation, done: done)
         case .codex(let model):
-            askCodex(model: model, cwd: cwd, system: system, user: user, done: done)
+            askCodex(
+                model: model, cwd: cwd, system: system, user: user,
+                cancellation: cancellation, done: done)
         case .claude(let model):
-            askClaude(model: model, system: system, user: user, done: done)
+            askClaude(
+                model: model, system: system, user: user,
+                cancellation: cancellation, done: done)
         case .foundation:
             #if canImport(FoundationModels)
             if #available(macOS 26.0, *) {
@@ -1785,11 +1888,12 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     private static func askCodex(
         model: String?, cwd: String,
         system: String, user: String,
+        cancellation: CancellationToken?,
         done: @escaping (AIOutcome) -> Void
     ) {
         let prompt = system + "\n\n" + user
         DispatchQueue.global(qos: .userInitiated).async {
-            Task {
+            let turn = Task {
                 do {
                     let reply = try await CodexAppServer.shared.turn(
                         prompt: prompt, cwd: cwd, model: model ?? "gpt-5.4")
@@ -1799,6 +1903,7 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
                     done(.failure("Codex: \(error.localizedDescription)"))
                 }
             }
+            cancellation?.onCancel { turn.cancel() }
         }
     }
 
@@ -1807,10 +1912,11 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
     private static func askClaude(
         model: String?,
         system: String, user: String,
+        cancellation: CancellationToken?,
         done: @escaping (AIOutcome) -> Void
     ) {
         DispatchQueue.global(qos: .userInitiated).async {
-            Task {
+            let turn = Task {
                 do {
                     let reply = try await ClaudeBridge.shared.turn(
                         prompt: user, system: system, model: model)
@@ -1820,12 +1926,14 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
                     done(.failure("Claude: \(error.localizedDescription)"))
                 }
             }
+            cancellation?.onCancel { turn.cancel() }
         }
     }
 
     private static func askOpenAI(
         base: String, key: String, model: String,
         system: String, user: String,
+        cancellation: CancellationToken?,
         done: @escaping (AIOutcome) -> Void
     ) {
         let urlStr = base.hasSuffix("/chat/completions") ? base
@@ -1848,7 +1956,7 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
             "max_tokens": 400,
         ]
         req.httpBody = try? JSONSerialization.data(withJSONObject: payload)
-        URLSession(configuration: .ephemeral).dataTask(with: req) { data, _, err in
+        let request = URLSession(configuration: .ephemeral).dataTask(with: req) { data, _, err in
             if let err {
                 done(.failure("OpenAI request failed: \(err.localizedDescription)")); return
             }
@@ -1863,7 +1971,9 @@ final class PetAssistant: NSObject, NSPopoverDelegate {
                 done(.failure("OpenAI: \(apiErr ?? "no choices in response").")); return
             }
             done(.text(content.trimmingCharacters(in: .whitespacesAndNewlines)))
-        }.resume()
+        }
+        cancellation?.onCancel { request.cancel() }
+        request.resume()
     }
 }
 
@@ -0,0 +1,182 @@
+import AppKit
+import Carbon
+
+enum PinnedCommandError: Error, Equatable, CustomStringConvertible {
+    case emptyCommand
+    case invalidHotkey(String)
+    case hotkeyTaken(String, by: String)
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .emptyCommand: return "the command is empty"
+        case .invalidHotkey(let spec): return "can't use '\(spec)' as a hotkey (cmd, ctrl or opt plus a key)"
+        case .hotkeyTaken(let spec, let id): return "\(spec) already runs pin \(id)"
+        case .notFound(let id): return "no pinned command \(id)"
+        }
+    }
+}
+
+/// A command kept at the top of the palette for one workspace, optionally
+/// run by a hotkey while one of that workspace's panes has focus.
+struct PinnedCommand: Codable, Equatable {
+    let id: String
+    var command: String
+    var title: String?
+    var hotkey: String?
+
+    /// What the palette shows: the title, or else the command itself.
+    var label: String { title ?? command }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "command": command]
+        if let title { object["title"] = title }
+        if let hotkey { object["hotkey"] = hotkey }
+        return object
+    }
+}
+
+/// Pinned commands per workspace (a repo root, or the directory when there
+/// is no repo), in their chosen order, kept in Application Support. Ids
+/// count up per workspace, as todo ids do.
+final class PinnedCommands {
+    static let shared = PinnedCommands(url: PinnedCommands.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("pinned-commands.json") }
+
+    private typealias Store = [String: [PinnedCommand]]
+
+    let url: URL
+    private let file: JSONFileStore<Store>
+    private let entries = LockedState<Store?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, formatting: [.sortedKeys])
+    }
+
+    func pins(for workspace: String) -> [PinnedCommand] {
+        read { $0[workspace] ?? [] }
+    }
+
+    /// Whether any workspace binds a hotkey, so key handling can skip the
+    /// workspace lookup when none does.
+    var hasHotkeys: Bool {
+        read { $0.values.contains { $0.contains { $0.hotkey != nil } } }
+    }
+
+    /// The pin in `workspace` whose hotkey is this key press.
+    func pin(keyCode: UInt16, modifiers: NSEvent.ModifierFlags, in workspace: String) -> PinnedCommand? {
+        let pressed = GlobalHotKeySpec(keyCode: UInt32(keyCode), modifiers: GlobalHotKeySpec.carbonModifiers(modifiers))
+        return pins(for: workspace).first { pin in pin.hotkey.flatMap(GlobalHotKeySpec.parse) == pressed }
+    }
+
+    func add(
+        _ command: String, title: String? = nil, hotkey: String? = nil, to workspace: String
+    ) -> Result<PinnedCommand, PinnedCommandError> {
+        let command = command.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard !command.isEmpty else { return .failure(.emptyCommand) }
+        let hotkey = Self.nonEmpty(hotkey)
+        return update(workspace) { pins in
+            if let error = Self.hotkeyConflict(hotkey, in: pins, except: nil) { return .failure(error) }
+            let next = (pins.compactMap { Int($0.id) }.max() ?? 0) + 1
+            let pin = PinnedCommand(id: String(next), command: command, title: Self.nonEmpty(title), hotkey: hotkey)
+            pins.append(pin)
+            return .success(pin)
+        }
+    }
+
+    /// Changes the fields given; an empty title or hotkey clears it.
+    func edit(
+        _ id: String, in workspace: String, command: String? = nil, title: String? = nil, hotkey: String? = nil
+    ) -> Result<PinnedCommand, PinnedCommandError> {
+        update(workspace) { pins in
+            guard let index = pins.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            if let command {
+                let command = command.trimmingCharacters(in: .whitespacesAndNewlines)
+                guard !command.isEmpty else { return .failure(.emptyCommand) }
+                pins[index].command = command
+            }
+            if let title { pins[index].title = Self.nonEmpty(title) }
+            if let hotkey {
+                let hotkey = Self.nonEmpty(hotkey)
+                if let error = Self.hotkeyConflict(hotkey, in: pins, except: id) { return .failure(error) }
+                pins[index].hotkey = hotkey
+            }
+            return .success(pins[index])
+        }
+    }
+
+    func remove(_ id: String, in workspace: String) -> Result<PinnedCommand, PinnedCommandError> {
+        update(workspace) { pins in
+            guard let index = pins.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            return .success(pins.remove(at: index))
+        }
+    }
+
+    /// Moves a pin to `index` in the workspace's order, clamped to the list.
+    func move(_ id: String, to index: Int, in workspace: String) -> Result<[PinnedCommand], PinnedCommandError> {
+        update(workspace) { pins in
+            guard let from = pins.firstIndex(where: { $0.id == id }) else { return .failure(.notFound(id)) }
+            let pin = pins.remove(at: from)
+            pins.insert(pin, at: min(max(index, 0), pins.count))
+            return .success(pins)
+        }
+    }
+
+    /// Hotkeys need cmd, ctrl or opt: shift alone would swallow capitals.
+    private static func hotkeyConflict(
+        _ hotkey: String?, in pins: [PinnedCommand], except id: String?
+    ) -> PinnedCommandError? {
+        guard let hotkey else { return nil }
+        guard let spec = GlobalHotKeySpec.parse(hotkey), spec.modifiers & ~UInt32(shiftKey) != 0 else {
+            return .invalidHotkey(hotkey)
+        }
+        if let other = pins.first(where: { $0.id != id && $0.hotkey.flatMap(GlobalHotKeySpec.parse) == spec }) {
+            return .hotkeyTaken(hotkey, by: other.id)
+        }
+        return nil
+    }
+
+    private static func nonEmpty(_ text: String?) -> String? {
+        let trimmed = text?.trimmingCharacters(in: .whitespacesAndNewlines)
+        return trimmed?.isEmpty == false ? trimmed : nil
+    }
+
+    // MARK: - storage
+
+    private func read<R>(_ body: (Store) -> R) -> R {
+        entries.withLock { entries in
+            load(&entries)
+            return body(entries ?? [:])
+        }
+    }
+
+    /// Applies `body` to the workspace's pins and saves them when it
+    /// succeeds.
+    private func update<R>(
+        _ workspace: String, _ body: (inout [PinnedCommand]) -> Result<R, PinnedCommandError>
+    ) -> Result<R, PinnedCommandError> {
+        let (result, snapshot) = entries.withLock { entries -> (Result<R, PinnedCommandError>, Store?) in
+            load(&entries)
+            var all = entries ?? [:]
+            var pins = all[workspace] ?? []
+            let result = body(&pins)
+            guard case .success = result else { return (result, nil) }
+            all[workspace] = pins.isEmpty ? nil : pins
+            entries = all
+            return (result, all)
+        }
+        if let snapshot { save(snapshot) }
+        return result
+    }
+
+    private func load(_ entries: inout Store?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+
+    private func save(_ entries: Store) {
+        file.save(entries)
+    }
+}
@@ -0,0 +1,134 @@
+import Foundation
+
+/// Why a helper process produced no usable result. A non-zero exit is not
+/// an error here — callers read `ProcessOutput.status` for that.
+enum ProcessRunError: Error, Equatable, CustomStringConvertible {
+    case launchFailed(String)
+    case timedOut(after: TimeInterval)
+    case cancelled
+
+    var description: String {
+        switch self {
+        case .launchFailed(let reason): return "could not launch: \(reason)"
+        case .timedOut(let seconds): return "timed out after \(Int(seconds.rounded()))s"
+        case .cancelled: return "cancelled"
+        }
+    }
+}
+
+/// Everything a finished helper wrote, plus its exit status.
+struct ProcessOutput {
+    let status: Int32
+    let stdout: Data
+    let stderr: Data
+
+    var stdoutText: String { String(decoding: stdout, as: UTF8.self) }
+    var stderrText: String {
+        String(decoding: stderr, as: UTF8.self).trimmingCharacters(in: .whitespacesAndNewlines)
+    }
+}
+
+/// Watchdog wrapper for short-lived helpers (git, rg, hint and AI command
+/// hooks). Output pipes are drained concurrently so a chatty child can't
+/// deadlock on a full pipe buffer, and a child that outlives its deadline is
+/// sent SIGTERM, then SIGKILL, and reported as `.timedOut` — a `git` blocked
+/// on a dead network mount must not wedge the queue that called it. A
+/// child that waits on the user (a commit's hooks, a pinentry) passes
+/// `noTimeout` instead.
+/// Cancelling the optional token stops the child the same way, SIGKILL
+/// following `killGrace` after the SIGTERM it ignored.
+/// `onOutput`, when given, sees stdout as it arrives (for tools that report
+/// as they go, like ping); the full output is still returned at the end.
+/// Synchronous — call from a background queue.
+enum ProcessRunner {
+    /// Applied when a call passes no timeout (`process-timeout` config key).
+    /// Set on main at launch and reload, read from every caller's queue.
+    static var defaultTimeout: TimeInterval {
+        get { configuredTimeout.snapshot }
+        set { configuredTimeout.withLock { $0 = newValue } }
+    }
+    private static let configuredTimeout = LockedState<TimeInterval>(30)
+    /// Between SIGTERM and SIGKILL.
+    static let killGrace: TimeInterval = 1
+    /// Waits as long as the child runs; cancelling still stops it.
+    static let noTimeout: TimeInterval = .infinity
+
+    static func run(
+        _ executable: String, _ arguments: [String],
+        input: Data? = nil,
+        environment: [String: String]? = nil,
+        currentDirectory: String? = nil,
+        timeout: TimeInterval? = nil,
+        cancellation: CancellationToken? = nil,
+        onOutput: ((Data) -> Void)? = nil
+    ) -> Result<ProcessOutput, ProcessRunError> {
+        let limit = timeout ?? defaultTimeout
+        let p = Process()
+        p.executableURL = URL(fileURLWithPath: executable)
+        p.arguments = arguments
+        if let environment { p.environment = environment }
+        if let currentDirectory { p.currentDirectoryURL = URL(fileURLWithPath: currentDirectory) }
+        let out = Pipe()
+        let err = Pipe()
+        let stdin = input.map { _ in Pipe() }
+        p.standardOutput = out
+        p.standardError = err
+        p.standardInput = stdin ?? FileHandle.nullDevice
+        do {
+            try p.run()
+        } catch {
+            log(executable, arguments, "launch failed: \(error.localizedDescription)")
+            return .failure(.launchFailed(error.localizedDescription))
+        }
+
+        let cancelHandler = cancellation?.onCancel { [p] in
+            guard p.isRunning else { return }
+            p.terminate()
+            DispatchQueue.global(qos: .utility).asyncAfter(deadline: .now() + Self.killGrace) {
+                if p.isRunning { kill(p.processIdentifier, SIGKILL) }
+            }
+        }
+        defer { cancellation?.removeHandler(cancelHandler) }
+
+        let group = DispatchGroup()
+        var outData = Data()
+        var errData = Data()
+        let queue = DispatchQueue.global(qos: .utility)
+        queue.async(group: group) {
+            guard let onOutput else {
+                outData = out.fileHandleForReading.readDataToEndOfFile()
+                return
+            }
+            while case let chunk = out.fileHandleForReading.availableData, !chunk.isEmpty {
+                outData += chunk
+                onOutput(chunk)
+            }
+        }
+        queue.async(group: group) { errData = err.fileHandleForReading.readDataToEndOfFile() }
+        if let input, let stdin {
+            queue.async {
+                // Throws (rather than raising) when the child already exited.
+                try? stdin.fileHandleForWriting.write(contentsOf: input)
+                try? stdin.fileHandleForWriting.close()
+            }
+        }
+
+        let deadline: DispatchTime = limit.isFinite ? .now() + limit : .distantFuture
+        guard group.wait(timeout: deadline) == .success else {
+            p.terminate()
+            if group.wait(timeout: .now() + killGrace) != .success, p.isRunning {
+                kill(p.processIdentifier, SIGKILL)
+            }
+            log(executable, arguments, "killed after \(limit)s timeout")
+            return .failure(.timedOut(after: limit))
+        }
+        p.waitUntilExit()
+        if cancellation?.isCancelled == true { return .failure(.cancelled) }
+        return .success(ProcessOutput(status: p.terminationStatus, stdout: outData, stderr: errData))
+    }
+
+    private static func log(_ executable: String, _ arguments: [String], _ outcome: String) {
+        let line = ([executable] + arguments).joined(separator: " ")
+        FileHandle.standardError.write(Data("infinitty: \(line): \(outcome)\n".utf8))
+    }
+}
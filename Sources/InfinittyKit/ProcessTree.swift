@@ -0,0 +1,149 @@
+import Darwin
+import Foundation
+
+enum ProcessSignalError: Error, Equatable, CustomStringConvertible {
+    case unknownSignal(String)
+    case notInPane(pid_t)
+    case nothingRunning
+    case failed(pid_t, String)
+
+    var description: String {
+        switch self {
+        case .unknownSignal(let name): return "unknown signal \(name); use int, term, kill, hup, quit, stop or cont"
+        case .notInPane(let pid): return "process \(pid) is not running in this pane"
+        case .nothingRunning: return "nothing is running in the foreground"
+        case .failed(let pid, let reason): return "could not signal \(pid): \(reason)"
+        }
+    }
+}
+
+/// The signals `pane-signal` sends: enough to stop a program that ignores
+/// Ctrl-C, pause and continue it, or hang it up.
+enum PaneSignal: String, CaseIterable {
+    case int, term, kill, hup, quit, stop, cont
+
+    var number: Int32 {
+        switch self {
+        case .int: return SIGINT
+        case .term: return SIGTERM
+        case .kill: return SIGKILL
+        case .hup: return SIGHUP
+        case .quit: return SIGQUIT
+        case .stop: return SIGSTOP
+        case .cont: return SIGCONT
+        }
+    }
+
+    /// `int`, `INT`, `SIGINT` or `2`.
+    init?(name: String) {
+        let upper = name.trimmingCharacters(in: .whitespaces).uppercased()
+        if let number = Int32(upper) {
+            guard let match = Self.allCases.first(where: { $0.number == number }) else { return nil }
+            self = match
+            return
+        }
+        self.init(rawValue: (upper.hasPrefix("SIG") ? String(upper.dropFirst(3)) : upper).lowercased())
+    }
+}
+
+/// One process in a pane: its shell or something under it.
+struct PaneProcess: Equatable {
+    let pid: pid_t
+    let parent: pid_t
+    /// Process group; the terminal's foreground group is what Ctrl-C hits.
+    let group: pid_t
+    let name: String
+    var path: String?
+    var isForeground: Bool
+    /// 0 for the shell, 1 for what it started, and so on.
+    let depth: Int
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "pid": Int(pid), "ppid": Int(parent), "pgid": Int(group), "name": name,
+            "foreground": isForeground, "depth": depth,
+        ]
+        if let path { object["path"] = path }
+        return object
+    }
+}
+
+/// A pane's processes, read from the kernel's process table, and signals
+/// sent to them. Only the shell and its descendants can be signalled, so a
+/// stale or mistyped pid can't reach anything outside the pane.
+enum ProcessTree {
+    /// Enough for any build or test run; a fork bomb stops here.
+    static let limit = 512
+
+    /// `root` and everything under it, depth-first, children by pid.
+    static func processes(under root: pid_t) -> [PaneProcess] {
+        guard let top = process(root, depth: 0) else { return [] }
+        var found: [PaneProcess] = []
+        var visited = Set<pid_t>()
+        func walk(_ process: PaneProcess) {
+            guard found.count < limit, visited.insert(process.pid).inserted else { return }
+            found.append(process)
+            for child in children(of: process.pid) {
+                if let next = Self.process(child, depth: process.depth + 1) { walk(next) }
+            }
+        }
+        walk(top)
+        return found
+    }
+
+    /// Sends `signal` to `pid`, which must be `root` or under it. Without a
+    /// pid it goes to the foreground job's whole process group, as Ctrl-C
+    /// would, but never to the shell's own group.
+    static func send(
+        _ signal: PaneSignal, to pid: pid_t?, under root: pid_t
+    ) -> Result<[PaneProcess], ProcessSignalError> {
+        let all = processes(under: root)
+        let targets: [PaneProcess]
+        let result: Int32
+        if let pid {
+            guard let target = all.first(where: { $0.pid == pid }) else { return .failure(.notInPane(pid)) }
+            targets = [target]
+            result = kill(pid, signal.number)
+        } else {
+            let shellGroup = all.first?.group
+            guard let group = all.first(where: { $0.isForeground && $0.group != shellGroup })?.group else {
+                return .failure(.nothingRunning)
+            }
+            targets = all.filter { $0.group == group }
+            result = killpg(group, signal.number)
+        }
+        guard result == 0 else {
+            return .failure(.failed(pid ?? targets[0].group, String(cString: strerror(errno))))
+        }
+        return .success(targets)
+    }
+
+    static func children(of pid: pid_t) -> [pid_t] {
+        guard pid > 1 else { return [] }
+        var buffer = [pid_t](repeating: 0, count: 256)
+        // Like the foreground tracker: the result is a count of pids.
+        let count = buffer.withUnsafeMutableBufferPointer { pointer -> Int32 in
+            proc_listchildpids(pid, pointer.baseAddress, Int32(MemoryLayout<pid_t>.stride * pointer.count))
+        }
+        guard count > 0 else { return [] }
+        return buffer.prefix(min(Int(count), buffer.count)).filter { $0 > 1 }.sorted()
+    }
+
+    private static func process(_ pid: pid_t, depth: Int) -> PaneProcess? {
+        guard pid > 1 else { return nil }
+        var info = proc_bsdinfo()
+        let size = Int32(MemoryLayout<proc_bsdinfo>.size)
+        guard proc_pidinfo(pid, PROC_PIDTBSDINFO, 0, &info, size) == size else { return nil }
+        var name = [CChar](repeating: 0, count: 256)
+        let named = name.withUnsafeMutableBufferPointer { proc_name(pid, $0.baseAddress, UInt32($0.count)) }
+        var path = [CChar](repeating: 0, count: Int(MAXPATHLEN))
+        let pathLength = path.withUnsafeMutableBufferPointer { proc_pidpath(pid, $0.baseAddress, UInt32($0.count)) }
+        let group = pid_t(info.pbi_pgid)
+        return PaneProcess(
+            pid: pid, parent: pid_t(info.pbi_ppid), group: group,
+            name: named > 0 ? String(cString: name) : "",
+            path: pathLength > 0 ? String(cString: path) : nil,
+            isForeground: info.e_tpgid != 0 && pid_t(info.e_tpgid) == group,
+            depth: depth)
+    }
+}
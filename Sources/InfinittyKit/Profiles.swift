@@ -0,0 +1,313 @@
+import Darwin
+import Foundation
+
+enum ProfileError: Error, Equatable, CustomStringConvertible {
+    case invalid(String)
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .invalid(let message): return message
+        case .notFound(let name): return "no profile \(name)"
+        }
+    }
+}
+
+/// Where a new tab or split's shell starts (`new-pane-directory`, or a
+/// profile's own `newPaneDirectory`).
+enum NewPaneDirectory: String, Codable, CaseIterable {
+    /// The home directory, or the profile's `cwd` when it has one.
+    case home
+    /// The directory the pane it was opened from is in.
+    case inherit
+    /// The repo root above that directory, or the directory itself
+    /// outside a repo.
+    case workspace
+
+    /// The start directory given the source pane's; nil leaves it to the
+    /// profile's `cwd` and then the home directory.
+    func resolve(from directory: String?) -> String? {
+        switch self {
+        case .home: return nil
+        case .inherit: return directory
+        case .workspace: return directory.map(WorkspaceNotes.workspace(for:))
+        }
+    }
+}
+
+/// How a pane's shell is started: which program, with what arguments and
+/// environment, where, and what it's given to run once it's up.
+struct TerminalProfile: Codable, Equatable {
+    let name: String
+    /// Absolute path; nil keeps `$SHELL`.
+    var shell: String?
+    /// Arguments after argv[0]; nil starts the shell as a login shell.
+    var args: [String]?
+    /// Set on top of the app's environment. Values may use `$VAR`,
+    /// `${VAR}` and a leading `~`.
+    var env: [String: String]
+    /// Typed into the shell, one line each, as soon as it starts.
+    var startup: [String]
+    var cwd: String?
+    /// Overrides `new-pane-directory` for tabs and splits with this profile.
+    var newPaneDirectory: NewPaneDirectory?
+
+    init(
+        name: String, shell: String? = nil, args: [String]? = nil, env: [String: String] = [:],
+        startup: [String] = [], cwd: String? = nil, newPaneDirectory: NewPaneDirectory? = nil
+    ) {
+        self.name = name
+        self.shell = shell
+        self.args = args
+        self.env = env
+        self.startup = startup
+        self.cwd = cwd
+        self.newPaneDirectory = newPaneDirectory
+    }
+
+    /// What the shell reads first, before the user types anything.
+    var startupInput: [UInt8]? {
+        startup.isEmpty ? nil : startup.flatMap { Array($0.utf8) + [0x0D] }
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["name": name, "env": env, "startup": startup]
+        if let shell { object["shell"] = shell }
+        if let args { object["args"] = args }
+        if let cwd { object["cwd"] = cwd }
+        if let newPaneDirectory { object["newPaneDirectory"] = newPaneDirectory.rawValue }
+        return object
+    }
+
+    private static let namePattern = try! NSRegularExpression(pattern: #"^[A-Za-z0-9._-]{1,64}$"#)
+    private static let variablePattern = try! NSRegularExpression(pattern: #"^[A-Za-z_][A-Za-z0-9_]*$"#)
+
+    /// A profile from `profile-save`'s JSON: `name` plus any of `shell`,
+    /// `args`, `env`, `startup` (a line or a list of them), `cwd` and
+    /// `newPaneDirectory` (`home`, `inherit` or `workspace`).
+    static func parse(
+        _ object: [String: Any], fileManager: FileManager = .default
+    ) -> Result<TerminalProfile, ProfileError> {
+        guard let name = object["name"] as? String, matches(namePattern, name) else {
+            return .failure(.invalid("name is required: letters, digits, '.', '_' or '-'"))
+        }
+        var profile = TerminalProfile(name: name)
+        if let shell = object["shell"] {
+            guard let shell = shell as? String else { return .failure(.invalid("shell must be a path")) }
+            let path = (shell as NSString).expandingTildeInPath
+            guard path.hasPrefix("/"), fileManager.isExecutableFile(atPath: path) else {
+                return .failure(.invalid("\(shell) is not an executable"))
+            }
+            profile.shell = path
+        }
+        if let args = object["args"] {
+            guard let args = args as? [String] else { return .failure(.invalid("args must be a list of strings")) }
+            profile.args = args
+        }
+        if let env = object["env"] {
+            guard let env = env as? [String: String] else {
+                return .failure(.invalid("env must map names to strings"))
+            }
+            if let bad = env.keys.sorted().first(where: { !matches(variablePattern, $0) }) {
+                return .failure(.invalid("\(bad) is not a variable name"))
+            }
+            profile.env = env
+        }
+        switch object["startup"] {
+        case nil:
+            break
+        case let line as String:
+            profile.startup = line.isEmpty ? [] : [line]
+        case let lines as [String]:
+            profile.startup = lines.filter { !$0.isEmpty }
+        default:
+            return .failure(.invalid("startup must be a command or a list of them"))
+        }
+        if let cwd = object["cwd"] {
+            guard let cwd = cwd as? String else { return .failure(.invalid("cwd must be a path")) }
+            profile.cwd = cwd.isEmpty ? nil : (cwd as NSString).expandingTildeInPath
+        }
+        if let rule = object["newPaneDirectory"] {
+            guard let rule = rule as? String, rule.isEmpty || NewPaneDirectory(rawValue: rule) != nil else {
+                return .failure(.invalid("newPaneDirectory must be home, inherit or workspace"))
+            }
+            profile.newPaneDirectory = NewPaneDirectory(rawValue: rule)
+        }
+        return .success(profile)
+    }
+
+    private static func matches(_ pattern: NSRegularExpression, _ text: String) -> Bool {
+        pattern.firstMatch(in: text, range: NSRange(text.startIndex..., in: text)) != nil
+    }
+}
+
+/// Everything `execve` needs for one pane's shell, resolved from its
+/// profile against the app's own environment.
+struct ShellLaunch: Equatable {
+    let path: String
+    /// Including argv[0].
+    let arguments: [String]
+    let environment: [String: String]
+
+    /// Variables the terminal sets itself, whatever the app inherited.
+    static let terminalVariables = [
+        "TERM": "xterm-256color", "COLORTERM": "truecolor", "TERM_PROGRAM": "infinitty",
+    ]
+
+    /// `overrides` go in last and as given, unexpanded: the values a pane
+    /// being duplicated was already running with. `pane` is exported as
+    /// INFINITTY_PANE, so tools run in the shell can name their own pane.
+    init(
+        profile: TerminalProfile?, overrides: [String: String] = [:], socketPath: String?, pane: Int? = nil,
+        inherited: [String: String] = ProcessInfo.processInfo.environment,
+        systemPaths: [String] = ShellLaunch.systemPaths()
+    ) {
+        let shell = profile?.shell ?? inherited["SHELL"].flatMap { $0.isEmpty ? nil : $0 } ?? "/bin/zsh"
+        let base = (shell as NSString).lastPathComponent
+        path = shell
+        arguments = profile?.args.map { [base] + $0 } ?? ["-" + base]
+
+        var environment = inherited
+        environment["TERM_PROGRAM_VERSION"] = nil
+        environment["PATH"] = Self.fixedPath(inherited["PATH"], adding: systemPaths)
+        environment.merge(Self.terminalVariables) { _, new in new }
+        // References see the environment before the profile's own entries,
+        // so `PATH = "$HOME/bin:$PATH"` extends the fixed-up PATH.
+        let before = environment
+        for (key, value) in profile?.env ?? [:] {
+            environment[key] = Self.expand(value, in: before)
+        }
+        environment.merge(overrides) { _, new in new }
+        environment["INFINITTY_SOCKET"] = socketPath
+        environment["TITERM_SOCKET"] = socketPath
+        environment["INFINITTY_PANE"] = pane.map(String.init)
+        self.environment = environment
+    }
+
+    /// `KEY=value` strings for `envp`, sorted.
+    var envp: [String] {
+        environment.sorted { $0.key < $1.key }.map { "\($0.key)=\($0.value)" }
+    }
+
+    /// Runs `body` with NULL-terminated C copies of argv and envp.
+    func withCArrays<R>(
+        _ body: (_ argv: [UnsafeMutablePointer<CChar>?], _ envp: [UnsafeMutablePointer<CChar>?]) -> R
+    ) -> R {
+        let argv = arguments.map { strdup($0) } + [nil]
+        let environment = envp.map { strdup($0) } + [nil]
+        defer { (argv + environment).forEach { free($0) } }
+        return body(argv, environment)
+    }
+
+    /// An app opened from Finder or the Dock gets launchd's bare PATH,
+    /// without what a login shell would add. Appends the entries
+    /// `path_helper` reads, then Homebrew's, keeping what was there first.
+    static func fixedPath(_ current: String?, adding system: [String]) -> String {
+        var entries = (current ?? "").split(separator: ":").map(String.init).filter { !$0.isEmpty }
+        for entry in system where !entries.contains(entry) { entries.append(entry) }
+        return entries.joined(separator: ":")
+    }
+
+    /// /etc/paths, then /etc/paths.d in name order, then Homebrew's bin
+    /// directories.
+    static func systemPaths(root: String = "/etc") -> [String] {
+        let files = ["\(root)/paths"]
+            + ((try? FileManager.default.contentsOfDirectory(atPath: "\(root)/paths.d")) ?? [])
+                .sorted().map { "\(root)/paths.d/\($0)" }
+        let listed = files.flatMap { file in
+            ((try? String(contentsOfFile: file, encoding: .utf8)) ?? "")
+                .split(whereSeparator: \.isNewline)
+                .map { $0.trimmingCharacters(in: .whitespaces) }
+                .filter { !$0.isEmpty && !$0.hasPrefix("#") }
+        }
+        return listed + ["/opt/homebrew/bin", "/opt/homebrew/sbin", "/usr/local/bin"]
+    }
+
+    private static let referencePattern = try! NSRegularExpression(
+        pattern: #"\$\{([A-Za-z_][A-Za-z0-9_]*)\}|\$([A-Za-z_][A-Za-z0-9_]*)"#)
+
+    /// `$VAR` and `${VAR}` from `environment` (empty when unset), and a
+    /// leading `~` as the home directory.
+    static func expand(_ value: String, in environment: [String: String]) -> String {
+        var text = value
+        if text == "~" || text.hasPrefix("~/") {
+            text = (environment["HOME"] ?? NSHomeDirectory()) + text.dropFirst()
+        }
+        let ns = text as NSString
+        var out = ""
+        var last = 0
+        for match in referencePattern.matches(in: text, range: NSRange(location: 0, length: ns.length)) {
+            out += ns.substring(with: NSRange(location: last, length: match.range.location - last))
+            let group = match.range(at: 1).location != NSNotFound ? 1 : 2
+            out += environment[ns.substring(with: match.range(at: group))] ?? ""
+            last = match.range.location + match.range.length
+        }
+        return out + ns.substring(from: last)
+    }
+}
+
+/// Saved profiles, by name, kept in Application Support.
+final class TerminalProfiles {
+    static let shared = TerminalProfiles(url: TerminalProfiles.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("profiles.json") }
+
+    private typealias Store = [String: TerminalProfile]
+
+    let url: URL
+    private let file: JSONFileStore<Store>
+    private let entries = LockedState<Store?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, formatting: [.sortedKeys, .prettyPrinted])
+    }
+
+    var all: [TerminalProfile] {
+        read { $0.values.sorted { $0.name < $1.name } }
+    }
+
+    func profile(named name: String) -> TerminalProfile? {
+        read { $0[name] }
+    }
+
+    /// Adds `profile`, or replaces the one with its name.
+    func save(_ profile: TerminalProfile) {
+        update { $0[profile.name] = profile }
+    }
+
+    func remove(_ name: String) -> Result<TerminalProfile, ProfileError> {
+        var removed: TerminalProfile?
+        update { removed = $0.removeValue(forKey: name) }
+        return removed.map { .success($0) } ?? .failure(.notFound(name))
+    }
+
+    // MARK: - storage
+
+    private func read<R>(_ body: (Store) -> R) -> R {
+        entries.withLock { entries in
+            load(&entries)
+            return body(entries ?? [:])
+        }
+    }
+
+    private func update(_ body: (inout Store) -> Void) {
+        let snapshot = entries.withLock { entries -> Store in
+            load(&entries)
+            var all = entries ?? [:]
+            body(&all)
+            entries = all
+            return all
+        }
+        save(snapshot)
+    }
+
+    private func load(_ entries: inout Store?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+
+    private func save(_ entries: Store) {
+        file.save(entries)
+    }
+}
@@ -0,0 +1,279 @@
+import CryptoKit
+import Foundation
+
+enum ProjectAutomationError: Error, Equatable, CustomStringConvertible {
+    case unreadable(String)
+    case invalidLine(Int, String)
+    case unknownKey(Int, String)
+
+    var description: String {
+        switch self {
+        case .unreadable(let path): return "can't read \(path)"
+        case .invalidLine(let line, let reason): return "line \(line): \(reason)"
+        case .unknownKey(let line, let key): return "line \(line): unknown key '\(key)' (use run, layout or [env])"
+        }
+    }
+}
+
+/// A project's `.infinitty.toml`: environment to export, commands to run
+/// and a saved layout to apply when a pane's shell enters the directory
+/// (or one below it).
+///
+///     layout = "main-and-stack"
+///     run = ["nvm use", "docker compose up -d"]
+///
+///     [env]
+///     RUST_LOG = "debug"
+struct ProjectAutomation: Equatable {
+    static let fileName = ".infinitty.toml"
+
+    let path: String
+    /// SHA-256 of the file, hex; trust is pinned to it.
+    let hash: String
+    var env: [String: String] = [:]
+    var run: [String] = []
+    var layout: String?
+
+    var directory: String { (path as NSString).deletingLastPathComponent }
+
+    /// The nearest `.infinitty.toml` at or above `directory`.
+    static func find(from directory: String, fileManager fm: FileManager = .default) -> String? {
+        var url = URL(fileURLWithPath: directory).standardizedFileURL
+        while true {
+            let candidate = url.appendingPathComponent(fileName).path
+            var isDirectory: ObjCBool = false
+            if fm.fileExists(atPath: candidate, isDirectory: &isDirectory), !isDirectory.boolValue { return candidate }
+            guard url.path != "/" else { return nil }
+            url.deleteLastPathComponent()
+        }
+    }
+
+    static func load(path: String) -> Result<ProjectAutomation, ProjectAutomationError> {
+        guard let data = FileManager.default.contents(atPath: path) else { return .failure(.unreadable(path)) }
+        return parse(String(decoding: data, as: UTF8.self), path: path, hash: hash(of: data))
+    }
+
+    static func hash(of data: Data) -> String {
+        SHA256.hash(data: data).map { String(format: "%02x", $0) }.joined()
+    }
+
+    /// The TOML subset the file needs: top-level `run` (a string or an
+    /// array of them, which may span lines) and `layout`, and an `[env]`
+    /// table of strings, numbers or booleans. Anything else is an error, so
+    /// a typo doesn't quietly do nothing.
+    static func parse(_ text: String, path: String, hash: String) -> Result<ProjectAutomation, ProjectAutomationError> {
+        var automation = ProjectAutomation(path: path, hash: hash)
+        var section = ""
+        var pending: (line: Int, text: String)?
+        for (offset, raw) in text.components(separatedBy: .newlines).enumerated() {
+            var line = stripComment(raw)
+            var number = offset + 1
+            if let open = pending {
+                line = open.text + " " + line
+                number = open.line
+                pending = nil
+            }
+            guard !line.isEmpty else { continue }
+            if line.hasPrefix("[") {
+                guard line.hasSuffix("]") else { return .failure(.invalidLine(number, "unclosed table header")) }
+                section = line.dropFirst().dropLast().trimmingCharacters(in: .whitespaces)
+                guard section == "env" else { return .failure(.unknownKey(number, section)) }
+                continue
+            }
+            guard let equals = line.firstIndex(of: "=") else {
+                return .failure(.invalidLine(number, "expected key = value"))
+            }
+            let bare = line[..<equals].trimmingCharacters(in: .whitespaces)
+            let key = unquote(bare) ?? bare
+            let value = line[line.index(after: equals)...].trimmingCharacters(in: .whitespaces)
+            if value.hasPrefix("["), !bracketsClosed(value) {
+                pending = (number, line)
+                continue
+            }
+            switch (section, key) {
+            case ("env", _):
+                guard key.range(of: #"^[A-Za-z_][A-Za-z0-9_]*$"#, options: .regularExpression) != nil else {
+                    return .failure(.invalidLine(number, "'\(key)' isn't a variable name"))
+                }
+                guard let text = unquote(value) ?? scalar(value) else {
+                    return .failure(.invalidLine(number, "\(key) needs a string value"))
+                }
+                automation.env[key] = text
+            case ("", "run"):
+                guard let commands = value.hasPrefix("[") ? array(value) : unquote(value).map({ [$0] }) else {
+                    return .failure(.invalidLine(number, "run takes a string or an array of strings"))
+                }
+                automation.run = commands.filter { !$0.trimmingCharacters(in: .whitespaces).isEmpty }
+            case ("", "layout"):
+                guard let name = unquote(value), !name.isEmpty else {
+                    return .failure(.invalidLine(number, "layout takes a saved layout's name"))
+                }
+                automation.layout = name
+            default:
+                return .failure(.unknownKey(number, key))
+            }
+        }
+        if let open = pending { return .failure(.invalidLine(open.line, "unclosed array")) }
+        return .success(automation)
+    }
+
+    /// What gets typed at the prompt: one `export` line (with a leading
+    /// space, so shells set to ignore such lines keep it out of history),
+    /// then each command.
+    var shellInput: String {
+        var lines: [String] = []
+        if !env.isEmpty {
+            let assignments = env.sorted { $0.key < $1.key }.map { "\($0.key)=\(Self.shellQuoted($0.value))" }
+            lines.append(" export " + assignments.joined(separator: " "))
+        }
+        lines += run
+        return lines.map { $0 + "\r" }.joined()
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["path": path, "directory": directory, "hash": hash, "env": env, "run": run]
+        if let layout { object["layout"] = layout }
+        return object
+    }
+
+    static func shellQuoted(_ value: String) -> String {
+        "'" + value.replacingOccurrences(of: "'", with: #"'\''"#) + "'"
+    }
+
+    // MARK: - parsing
+
+    private static func stripComment(_ line: String) -> String {
+        var quote: Character?
+        var escaped = false
+        for index in line.indices {
+            let character = line[index]
+            if let open = quote {
+                if escaped {
+                    escaped = false
+                } else if character == "\\", open == "\"" {
+                    escaped = true
+                } else if character == open {
+                    quote = nil
+                }
+            } else if character == "\"" || character == "'" {
+                quote = character
+            } else if character == "#" {
+                return line[..<index].trimmingCharacters(in: .whitespaces)
+            }
+        }
+        return line.trimmingCharacters(in: .whitespaces)
+    }
+
+    private static func bracketsClosed(_ value: String) -> Bool {
+        strings(in: value).rest.contains("]")
+    }
+
+    /// A basic ("…", with escapes) or literal ('…') string, nil otherwise.
+    private static func unquote(_ value: String) -> String? {
+        let (found, rest) = strings(in: value)
+        guard found.count == 1, rest.trimmingCharacters(in: .whitespaces).isEmpty else { return nil }
+        return found[0]
+    }
+
+    private static func array(_ value: String) -> [String]? {
+        let (found, rest) = strings(in: value)
+        let punctuation = rest.filter { !$0.isWhitespace }
+        guard punctuation.hasPrefix("["), punctuation.hasSuffix("]"),
+              punctuation.dropFirst().dropLast().allSatisfy({ $0 == "," })
+        else { return nil }
+        return found
+    }
+
+    /// Bare numbers and booleans, kept as written.
+    private static func scalar(_ value: String) -> String? {
+        if value == "true" || value == "false" || Double(value.replacingOccurrences(of: "_", with: "")) != nil {
+            return value
+        }
+        return nil
+    }
+
+    /// The quoted strings in `value`, decoded, and what lies outside them.
+    private static func strings(in value: String) -> (found: [String], rest: String) {
+        var found: [String] = []
+        var rest = ""
+        var current: String?
+        var quote: Character = "\""
+        var escaped = false
+        for character in value {
+            guard var text = current else {
+                if character == "\"" || character == "'" {
+                    quote = character
+                    current = ""
+                } else {
+                    rest.append(character)
+                }
+                continue
+            }
+            if escaped {
+                let escapes: [Character: Character] = ["n": "\n", "t": "\t", "\"": "\"", "\\": "\\"]
+                text.append(escapes[character] ?? character)
+                escaped = false
+            } else if character == "\\", quote == "\"" {
+                escaped = true
+            } else if character == quote {
+                found.append(text)
+                current = nil
+                continue
+            } else {
+                text.append(character)
+            }
+            current = text
+        }
+        if current != nil { rest.append("\"") }
+        return (found, rest)
+    }
+}
+
+/// Which `.infinitty.toml` files the user has said yes or no to, each
+/// pinned to the hash of the content they saw: an edited file is asked
+/// about again. Kept in Application Support.
+final class ProjectTrust {
+    static let shared = ProjectTrust(url: ProjectTrust.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("trusted-projects.json") }
+
+    private struct Entry: Codable {
+        let hash: String
+        let trusted: Bool
+    }
+
+    let url: URL
+    private let file: JSONFileStore<[String: Entry]>
+    private let entries = LockedState<[String: Entry]?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, formatting: [.sortedKeys])
+    }
+
+    /// True or false once decided for this content; nil means ask.
+    func decision(for automation: ProjectAutomation) -> Bool? {
+        let entry = entries.withLock { entries -> Entry? in
+            load(&entries)
+            return entries?[automation.path]
+        }
+        guard let entry, entry.hash == automation.hash else { return nil }
+        return entry.trusted
+    }
+
+    func record(_ automation: ProjectAutomation, trusted: Bool) {
+        let snapshot = entries.withLock { entries -> [String: Entry] in
+            load(&entries)
+            var all = entries ?? [:]
+            all[automation.path] = Entry(hash: automation.hash, trusted: trusted)
+            entries = all
+            return all
+        }
+        file.save(snapshot)
+    }
+
+    private func load(_ entries: inout [String: Entry]?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+}
@@ -0,0 +1,754 @@
+import Foundation
+
+enum DependencyScanError: Error, Equatable, CustomStringConvertible {
+    case noManifest(String)
+    case advisoriesUnavailable(String)
+
+    var description: String {
+        switch self {
+        case .noManifest(let directory):
+            return "no Cargo.toml, package.json, requirements.txt, pyproject.toml, go.mod or Gemfile.lock "
+                + "in \(directory) or above it"
+        case .advisoriesUnavailable(let reason):
+            return "could not check OSV: \(reason)"
+        }
+    }
+}
+
+enum DependencyEcosystem: String, CaseIterable {
+    case cargo, npm, pypi, go, rubygems
+
+    /// The ecosystem name OSV files advisories under.
+    var osvName: String {
+        switch self {
+        case .cargo: return "crates.io"
+        case .npm: return "npm"
+        case .pypi: return "PyPI"
+        case .go: return "Go"
+        case .rubygems: return "RubyGems"
+        }
+    }
+}
+
+/// A published vulnerability affecting a dependency's resolved version.
+struct DependencyAdvisory: Codable, Equatable {
+    let id: String
+    let summary: String
+    var aliases: [String] = []
+    /// GitHub's rating (LOW, MODERATE, HIGH, CRITICAL) when OSV has one.
+    var severity: String?
+    /// Versions that fix it, for this package.
+    var fixed: [String] = []
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "summary": summary, "aliases": aliases, "fixed": fixed]
+        if let severity { object["severity"] = severity }
+        return object
+    }
+}
+
+/// A dependency as a manifest declares it, with what the lock file or the
+/// local install says about it.
+struct ProjectDependency: Equatable {
+    let name: String
+    let ecosystem: DependencyEcosystem
+    /// The declared constraint ("^1.2", ">=2,<3"); empty when there is none.
+    var requirement: String
+    let manifest: String
+    var dev = false
+    /// The version the lock file pins or that is installed, when known.
+    var version: String?
+    var license: String?
+    var advisories: [DependencyAdvisory] = []
+
+    /// Identity for the advisory cache.
+    var key: String { "\(ecosystem.osvName)/\(name)@\(version ?? "")" }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "name": name, "ecosystem": ecosystem.rawValue, "requirement": requirement, "manifest": manifest,
+            "dev": dev, "advisories": advisories.map(\.wire),
+        ]
+        if let version { object["version"] = version }
+        if let license { object["license"] = license }
+        return object
+    }
+}
+
+/// Reads a project's dependency manifests — Cargo, npm, pip/pyproject, Go
+/// modules and Bundler — resolves versions from lock files or the local
+/// install, and finds licenses in installed packages, all offline. OSV
+/// advisories are layered on by `OSVAdvisories`.
+enum ProjectDependencies {
+
+    /// The nearest directory at or above `directory`, up to its repo root,
+    /// that has a manifest.
+    static func projectRoot(for directory: String, fileManager fm: FileManager = .default) -> String? {
+        let stop = PetTipScanner.repoRoot(for: directory, fileManager: fm)
+        var url = URL(fileURLWithPath: directory)
+        for _ in 0..<12 {
+            if let names = try? fm.contentsOfDirectory(atPath: url.path), names.contains(where: isManifest) {
+                return url.path
+            }
+            let parent = url.deletingLastPathComponent()
+            if url.path == stop || parent.path == url.path { return nil }
+            url = parent
+        }
+        return nil
+    }
+
+    static func isManifest(_ name: String) -> Bool {
+        ["Cargo.toml", "package.json", "pyproject.toml", "go.mod", "Gemfile.lock"].contains(name)
+            || (name.hasPrefix("requirements") && name.hasSuffix(".txt"))
+    }
+
+    /// Every dependency declared in `root`'s manifests, versions and
+    /// licenses filled in where the project's lock files or installs say.
+    static func scan(root: String, fileManager fm: FileManager = .default) -> [ProjectDependency] {
+        let rootURL = URL(fileURLWithPath: root)
+        func text(_ name: String) -> String? {
+            try? String(contentsOf: rootURL.appendingPathComponent(name), encoding: .utf8)
+        }
+        var found: [ProjectDependency] = []
+
+        if let manifest = text("Cargo.toml") {
+            let locked = lockFile("Cargo.lock", from: rootURL, fileManager: fm).map(parseCargoLock) ?? [:]
+            found += parseCargoManifest(manifest, manifest: "Cargo.toml").map { dependency in
+                var dependency = dependency
+                dependency.version = locked[dependency.name]
+                dependency.license = dependency.version.flatMap { cargoLicense(dependency.name, $0, fileManager: fm) }
+                return dependency
+            }
+        }
+        if let data = text("package.json").map({ Data($0.utf8) }) {
+            let locked = text("package-lock.json").map { parsePackageLock(Data($0.utf8)) } ?? [:]
+            found += parsePackageJSON(data, manifest: "package.json").map { dependency in
+                var dependency = dependency
+                let installed = rootURL.appendingPathComponent("node_modules/\(dependency.name)/package.json")
+                let package = (try? Data(contentsOf: installed))
+                    .flatMap { try? JSONSerialization.jsonObject(with: $0) as? [String: Any] }
+                dependency.version = locked[dependency.name]?.version ?? package?["version"] as? String
+                dependency.license = locked[dependency.name]?.license ?? package.flatMap(npmLicense)
+                return dependency
+            }
+        }
+        var python: [ProjectDependency] = []
+        let names = ((try? fm.contentsOfDirectory(atPath: root)) ?? []).sorted()
+        for name in names where name.hasPrefix("requirements") && name.hasSuffix(".txt") {
+            python += text(name).map { parseRequirements($0, manifest: name) } ?? []
+        }
+        python += text("pyproject.toml").map { parsePyproject($0, manifest: "pyproject.toml") } ?? []
+        if !python.isEmpty {
+            let installed = sitePackages(in: rootURL, fileManager: fm)
+            found += python.map { dependency in
+                var dependency = dependency
+                let metadata = installed[normalizedPythonName(dependency.name)]
+                dependency.version = dependency.version ?? metadata?.version
+                dependency.license = metadata?.license
+                return dependency
+            }
+        }
+        if let module = text("go.mod") {
+            found += parseGoMod(module, manifest: "go.mod").map { dependency in
+                var dependency = dependency
+                dependency.license = dependency.version.flatMap { goLicense(dependency.name, $0, fileManager: fm) }
+                return dependency
+            }
+        }
+        if let lock = text("Gemfile.lock") {
+            found += parseGemfileLock(lock, manifest: "Gemfile.lock")
+        }
+        return found
+    }
+
+    /// Counts for the health panel: how many are vulnerable, how many have
+    /// no license on disk, each license's tally and the copyleft ones.
+    static func summary(_ dependencies: [ProjectDependency]) -> [String: Any] {
+        var licenses: [String: Int] = [:]
+        for license in dependencies.compactMap(\.license) { licenses[license, default: 0] += 1 }
+        let copyleft = dependencies.filter { dependency in
+            guard let license = dependency.license?.uppercased() else { return false }
+            return ["GPL", "AGPL", "LGPL", "SSPL", "EUPL", "OSL"].contains { license.contains($0) }
+        }
+        return [
+            "total": dependencies.count,
+            "vulnerable": dependencies.filter { !$0.advisories.isEmpty }.count,
+            "unresolved": dependencies.filter { $0.version == nil }.count,
+            "unknownLicense": dependencies.filter { $0.license == nil }.count,
+            "licenses": licenses,
+            "copyleft": copyleft.map(\.name),
+        ]
+    }
+
+    // MARK: - Cargo
+
+    static func parseCargoManifest(_ text: String, manifest: String) -> [ProjectDependency] {
+        var section = ""
+        var found: [ProjectDependency] = []
+        /// `[dependencies.serde]` style tables fill their entry in line by line.
+        var tableEntry: Int?
+        for raw in text.components(separatedBy: .newlines) {
+            let line = stripTOMLComment(raw)
+            if line.hasPrefix("[") {
+                section = line.trimmingCharacters(in: CharacterSet(charactersIn: "[] "))
+                tableEntry = nil
+                if let range = section.range(of: "dependencies.", options: .backwards) {
+                    let name = unquote(String(section[range.upperBound...]))
+                    found.append(ProjectDependency(
+                        name: name, ecosystem: .cargo, requirement: "", manifest: manifest,
+                        dev: section.contains("dev-dependencies")))
+                    tableEntry = found.count - 1
+                }
+                continue
+            }
+            guard let (key, value) = tomlPair(line) else { continue }
+            if let index = tableEntry {
+                if key == "version" { found[index].requirement = unquote(value) }
+                continue
+            }
+            guard section.hasSuffix("dependencies") else { continue }
+            var name = key
+            var requirement = unquote(value)
+            if value.hasPrefix("{") {
+                let fields = inlineTable(value)
+                name = fields["package"] ?? key
+                requirement = fields["version"] ?? (fields["workspace"] == "true" ? "workspace" : "")
+            }
+            found.append(ProjectDependency(
+                name: name, ecosystem: .cargo, requirement: requirement, manifest: manifest,
+                dev: section.hasSuffix("dev-dependencies")))
+        }
+        return found
+    }
+
+    /// Package name to locked version; the first when several are locked.
+    static func parseCargoLock(_ text: String) -> [String: String] {
+        var versions: [String: String] = [:]
+        var name: String?
+        for raw in text.components(separatedBy: .newlines) {
+            let line = raw.trimmingCharacters(in: .whitespaces)
+            if line == "[[package]]" { name = nil }
+            guard let (key, value) = tomlPair(line) else { continue }
+            if key == "name" { name = unquote(value) }
+            if key == "version", let name, versions[name] == nil { versions[name] = unquote(value) }
+        }
+        return versions
+    }
+
+    // MARK: - npm
+
+    static func parsePackageJSON(_ data: Data, manifest: String) -> [ProjectDependency] {
+        guard let package = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any] else { return [] }
+        var found: [ProjectDependency] = []
+        for (field, dev) in [("dependencies", false), ("optionalDependencies", false), ("devDependencies", true)] {
+            let entries = (package[field] as? [String: Any] ?? [:]).sorted { $0.key < $1.key }
+            for (name, requirement) in entries where !found.contains(where: { $0.name == name }) {
+                found.append(ProjectDependency(
+                    name: name, ecosystem: .npm, requirement: requirement as? String ?? "", manifest: manifest,
+                    dev: dev))
+            }
+        }
+        return found
+    }
+
+    /// Top-level packages in a package-lock.json (v1, or v2/v3 `packages`).
+    static func parsePackageLock(_ data: Data) -> [String: (version: String, license: String?)] {
+        guard let lock = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any] else { return [:] }
+        var found: [String: (version: String, license: String?)] = [:]
+        if let packages = lock["packages"] as? [String: [String: Any]] {
+            for (path, package) in packages where path.hasPrefix("node_modules/") {
+                let name = String(path.dropFirst("node_modules/".count))
+                guard !name.contains("/node_modules/"), let version = package["version"] as? String else { continue }
+                found[name] = (version, npmLicense(package))
+            }
+        } else if let dependencies = lock["dependencies"] as? [String: [String: Any]] {
+            for (name, package) in dependencies {
+                if let version = package["version"] as? String { found[name] = (version, nil) }
+            }
+        }
+        return found
+    }
+
+    private static func npmLicense(_ package: [String: Any]) -> String? {
+        if let license = package["license"] as? String { return license }
+        return (package["license"] as? [String: Any])?["type"] as? String
+    }
+
+    // MARK: - Python
+
+    /// One PEP 508 requirement: `name[extras] <spec> ; markers`. An exact
+    /// `==` pin doubles as the version.
+    static func parseRequirement(_ line: String, manifest: String, dev: Bool = false) -> ProjectDependency? {
+        let spec = (line.components(separatedBy: ";").first ?? "").trimmingCharacters(in: .whitespaces)
+        let nameEnd = spec.firstIndex { !($0.isLetter || $0.isNumber || "._-".contains($0)) } ?? spec.endIndex
+        let name = String(spec[..<nameEnd])
+        guard let first = name.first, first.isLetter || first.isNumber else { return nil }
+        var rest = spec[nameEnd...].trimmingCharacters(in: .whitespaces)
+        if rest.hasPrefix("["), let close = rest.firstIndex(of: "]") {
+            rest = rest[rest.index(after: close)...].trimmingCharacters(in: .whitespaces)
+        }
+        var dependency = ProjectDependency(
+            name: name, ecosystem: .pypi, requirement: rest, manifest: manifest, dev: dev)
+        if rest.hasPrefix("=="), !rest.contains(","), !rest.contains("*") {
+            dependency.version = rest.drop { $0 == "=" }.trimmingCharacters(in: .whitespaces)
+        }
+        return dependency
+    }
+
+    static func parseRequirements(_ text: String, manifest: String) -> [ProjectDependency] {
+        let dev = manifest.lowercased().contains("dev") || manifest.lowercased().contains("test")
+        return text.components(separatedBy: .newlines).compactMap { raw in
+            let line = (raw.range(of: " #").map { String(raw[..<$0.lowerBound]) } ?? raw)
+                .trimmingCharacters(in: .whitespaces)
+            // Options (-r, -e, --index-url) and direct URLs aren't index packages.
+            guard !line.isEmpty, !line.hasPrefix("#"), !line.hasPrefix("-"), !line.contains("://") else {
+                return nil
+            }
+            return parseRequirement(line, manifest: manifest, dev: dev)
+        }
+    }
+
+    /// PEP 621 `[project] dependencies` and Poetry's dependency tables.
+    static func parsePyproject(_ text: String, manifest: String) -> [ProjectDependency] {
+        var section = ""
+        var found: [ProjectDependency] = []
+        var collecting = false
+        for raw in text.components(separatedBy: .newlines) {
+            let line = stripTOMLComment(raw)
+            if collecting {
+                found += quotedStrings(line).compactMap { parseRequirement($0, manifest: manifest) }
+                if line.contains("]") { collecting = false }
+                continue
+            }
+            if line.hasPrefix("[") {
+                section = line.trimmingCharacters(in: CharacterSet(charactersIn: "[] "))
+                continue
+            }
+            guard let (key, value) = tomlPair(line) else { continue }
+            if section == "project", key == "dependencies" {
+                found += quotedStrings(value).compactMap { parseRequirement($0, manifest: manifest) }
+                collecting = !value.contains("]")
+            } else if section.hasPrefix("tool.poetry"), section.hasSuffix("dependencies"), key != "python" {
+                let requirement = value.hasPrefix("{") ? inlineTable(value)["version"] ?? "" : unquote(value)
+                found.append(ProjectDependency(
+                    name: key, ecosystem: .pypi, requirement: requirement, manifest: manifest,
+                    dev: section.contains("dev")))
+            }
+        }
+        return found
+    }
+
+    /// PEP 503 normalization, as dist-info directory names are matched.
+    static func normalizedPythonName(_ name: String) -> String {
+        name.lowercased().replacingOccurrences(of: "_", with: "-").replacingOccurrences(of: ".", with: "-")
+    }
+
+    /// Installed distributions in the project's virtualenv (`.venv`,
+    /// `venv` or `env`), by normalized name.
+    private static func sitePackages(
+        in root: URL, fileManager fm: FileManager
+    ) -> [String: (version: String, license: String?)] {
+        var found: [String: (version: String, license: String?)] = [:]
+        for venv in [".venv", "venv", "env"] {
+            let lib = root.appendingPathComponent("\(venv)/lib")
+            for python in (try? fm.contentsOfDirectory(atPath: lib.path)) ?? [] where python.hasPrefix("python") {
+                let site = lib.appendingPathComponent("\(python)/site-packages")
+                let entries = (try? fm.contentsOfDirectory(atPath: site.path)) ?? []
+                for entry in entries where entry.hasSuffix(".dist-info") {
+                    let metadata = (try? String(
+                        contentsOf: site.appendingPathComponent("\(entry)/METADATA"), encoding: .utf8)) ?? ""
+                    let fields = pythonMetadata(metadata)
+                    guard let name = fields.name, let version = fields.version else { continue }
+                    found[normalizedPythonName(name)] = (version, fields.license)
+                }
+            }
+            if !found.isEmpty { break }
+        }
+        return found
+    }
+
+    /// Name, version and license from a dist-info METADATA header: the
+    /// SPDX `License-Expression`, a short `License`, or the classifier.
+    static func pythonMetadata(_ text: String) -> (name: String?, version: String?, license: String?) {
+        var name: String?, version: String?, expression: String?, field: String?, classifier: String?
+        for line in text.components(separatedBy: .newlines) {
+            if line.isEmpty { break } // the body starts after the headers
+            func value(_ header: String) -> String? {
+                line.hasPrefix(header + ": ") ? String(line.dropFirst(header.count + 2)) : nil
+            }
+            name = name ?? value("Name")
+            version = version ?? value("Version")
+            expression = expression ?? value("License-Expression")
+            if let license = value("License"), license.count <= 40, license != "UNKNOWN" { field = field ?? license }
+            if let classified = value("Classifier"), classified.hasPrefix("License :: ") {
+                classifier = classifier ?? classified.components(separatedBy: " :: ").last
+            }
+        }
+        return (name, version, expression ?? field ?? classifier)
+    }
+
+    // MARK: - Go and Ruby
+
+    /// Direct requirements from go.mod; `// indirect` ones are skipped.
+    static func parseGoMod(_ text: String, manifest: String) -> [ProjectDependency] {
+        var inBlock = false
+        var found: [ProjectDependency] = []
+        for raw in text.components(separatedBy: .newlines) {
+            var line = raw.trimmingCharacters(in: .whitespaces)
+            if line.hasPrefix("require (") || line == "require(" {
+                inBlock = true
+                continue
+            }
+            if inBlock, line == ")" {
+                inBlock = false
+                continue
+            }
+            if line.hasPrefix("require ") {
+                line = String(line.dropFirst("require ".count))
+            } else if !inBlock {
+                continue
+            }
+            guard !line.contains("// indirect") else { continue }
+            let words = line.split(separator: " ").map(String.init)
+            guard words.count >= 2, words[1].hasPrefix("v") else { continue }
+            var dependency = ProjectDependency(
+                name: words[0], ecosystem: .go, requirement: words[1], manifest: manifest)
+            dependency.version = words[1]
+            found.append(dependency)
+        }
+        return found
+    }
+
+    /// Gems under DEPENDENCIES, with the versions the specs lock.
+    static func parseGemfileLock(_ text: String, manifest: String) -> [ProjectDependency] {
+        var specs: [String: String] = [:]
+        var direct: [(name: String, requirement: String)] = []
+        var section = ""
+        for line in text.components(separatedBy: .newlines) {
+            if let first = line.first, !first.isWhitespace {
+                section = line
+                continue
+            }
+            let indent = line.prefix { $0 == " " }.count
+            let entry = line.trimmingCharacters(in: .whitespaces)
+            let name = String(entry.prefix { $0 != " " && $0 != "!" && $0 != "(" })
+            let detail = entry.firstIndex(of: "(").map { open in
+                entry[entry.index(after: open)...].prefix { $0 != ")" }
+            }.map { String($0) } ?? ""
+            if indent == 4, !name.isEmpty, section != "DEPENDENCIES" {
+                specs[name] = specs[name] ?? detail.components(separatedBy: "-").first
+            } else if indent == 2, section == "DEPENDENCIES", !name.isEmpty {
+                direct.append((name, detail))
+            }
+        }
+        return direct.map { gem in
+            var dependency = ProjectDependency(
+                name: gem.name, ecosystem: .rubygems, requirement: gem.requirement, manifest: manifest)
+            dependency.version = specs[gem.name]
+            return dependency
+        }
+    }
+
+    // MARK: - licenses on disk
+
+    private static func cargoLicense(_ name: String, _ version: String, fileManager fm: FileManager) -> String? {
+        let home = ProcessInfo.processInfo.environment["CARGO_HOME"] ?? NSHomeDirectory() + "/.cargo"
+        let registries = home + "/registry/src"
+        for registry in (try? fm.contentsOfDirectory(atPath: registries)) ?? [] {
+            let crate = "\(registries)/\(registry)/\(name)-\(version)"
+            guard let manifest = try? String(contentsOfFile: crate + "/Cargo.toml", encoding: .utf8) else { continue }
+            var inPackage = false
+            for raw in manifest.components(separatedBy: .newlines) {
+                let line = stripTOMLComment(raw)
+                if line.hasPrefix("[") { inPackage = line == "[package]" }
+                if inPackage, let (key, value) = tomlPair(line), key == "license" { return unquote(value) }
+            }
+            return licenseFile(in: crate, fileManager: fm)
+        }
+        return nil
+    }
+
+    private static func goLicense(_ module: String, _ version: String, fileManager fm: FileManager) -> String? {
+        let cache = ProcessInfo.processInfo.environment["GOMODCACHE"]
+            ?? (ProcessInfo.processInfo.environment["GOPATH"] ?? NSHomeDirectory() + "/go") + "/pkg/mod"
+        // The module cache spells capitals as "!" plus the lowercase letter.
+        let escaped = module.map { $0.isUppercase ? "!" + $0.lowercased() : String($0) }.joined()
+        return licenseFile(in: "\(cache)/\(escaped)@\(version)", fileManager: fm)
+    }
+
+    private static func licenseFile(in directory: String, fileManager fm: FileManager) -> String? {
+        let names = (try? fm.contentsOfDirectory(atPath: directory)) ?? []
+        let licenses = names.sorted().filter { name in
+            ["LICENSE", "LICENCE", "COPYING"].contains { name.uppercased().hasPrefix($0) }
+        }
+        for name in licenses {
+            if let text = try? String(contentsOfFile: "\(directory)/\(name)", encoding: .utf8),
+               let license = licenseName(fromText: text) {
+                return license
+            }
+        }
+        return nil
+    }
+
+    /// Recognizes the common license texts by their opening wording.
+    static func licenseName(fromText text: String) -> String? {
+        let head = text.prefix(3000).lowercased()
+            .components(separatedBy: .whitespacesAndNewlines).filter { !$0.isEmpty }.joined(separator: " ")
+        if head.contains("apache license") { return "Apache-2.0" }
+        if head.contains("mozilla public license") { return "MPL-2.0" }
+        if head.contains("gnu affero general public license") { return "AGPL-3.0" }
+        if head.contains("gnu lesser general public license") {
+            return head.contains("version 2.1") ? "LGPL-2.1" : "LGPL-3.0"
+        }
+        if head.contains("gnu general public license") {
+            return head.contains("version 3") ? "GPL-3.0" : "GPL-2.0"
+        }
+        if head.contains("permission is hereby granted, free of charge") { return "MIT" }
+        if head.contains("permission to use, copy, modify, and/or distribute") { return "ISC" }
+        if head.contains("redistribution and use in source and binary forms") {
+            return head.contains("neither the name") || head.contains("names of its contributors")
+                ? "BSD-3-Clause" : "BSD-2-Clause"
+        }
+        if head.contains("this is free and unencumbered software") { return "Unlicense" }
+        return nil
+    }
+
+    // MARK: - TOML fragments
+
+    private static func stripTOMLComment(_ line: String) -> String {
+        var inString: Character?
+        for index in line.indices {
+            let character = line[index]
+            if let quote = inString {
+                if character == quote { inString = nil }
+            } else if character == "\"" || character == "'" {
+                inString = character
+            } else if character == "#" {
+                return line[..<index].trimmingCharacters(in: .whitespaces)
+            }
+        }
+        return line.trimmingCharacters(in: .whitespaces)
+    }
+
+    private static func tomlPair(_ line: String) -> (String, String)? {
+        guard let equals = line.firstIndex(of: "="), !line.hasPrefix("[") else { return nil }
+        let key = unquote(line[..<equals].trimmingCharacters(in: .whitespaces))
+        let value = line[line.index(after: equals)...].trimmingCharacters(in: .whitespaces)
+        return key.isEmpty ? nil : (key, value)
+    }
+
+    /// `{ version = "1", features = ["x"] }` → the scalar fields.
+    private static func inlineTable(_ value: String) -> [String: String] {
+        var fields: [String: String] = [:]
+        let body = value.trimmingCharacters(in: CharacterSet(charactersIn: "{} "))
+        var depth = 0
+        var current = ""
+        for character in body + "," {
+            if character == "[" { depth += 1 }
+            if character == "]" { depth -= 1 }
+            if character == ",", depth == 0 {
+                if let (key, value) = tomlPair(current.trimmingCharacters(in: .whitespaces)),
+                   !value.hasPrefix("[") {
+                    fields[key] = unquote(value)
+                }
+                current = ""
+            } else {
+                current.append(character)
+            }
+        }
+        return fields
+    }
+
+    private static func unquote(_ text: String) -> String {
+        let trimmed = text.trimmingCharacters(in: .whitespaces)
+        guard trimmed.count >= 2, let first = trimmed.first, first == "\"" || first == "'",
+              trimmed.last == first else { return trimmed }
+        return String(trimmed.dropFirst().dropLast())
+    }
+
+    private static func quotedStrings(_ text: String) -> [String] {
+        guard let regex = try? NSRegularExpression(pattern: #""([^"]*)"|'([^']*)'"#) else { return [] }
+        return regex.matches(in: text, range: NSRange(text.startIndex..., in: text)).compactMap { match in
+            (Range(match.range(at: 1), in: text) ?? Range(match.range(at: 2), in: text)).map { String(text[$0]) }
+        }
+    }
+
+    private static func lockFile(_ name: String, from root: URL, fileManager fm: FileManager) -> String? {
+        // Cargo workspaces keep one lock file at the workspace root.
+        var url = root
+        for _ in 0..<4 {
+            if let text = try? String(contentsOf: url.appendingPathComponent(name), encoding: .utf8) { return text }
+            if fm.fileExists(atPath: url.appendingPathComponent(".git").path) { break }
+            url.deleteLastPathComponent()
+        }
+        return nil
+    }
+}
+
+/// Known vulnerabilities from the OSV database (osv.dev) for resolved
+/// dependency versions. Answers are cached in Application Support for a
+/// day, so rescanning a project only asks about what changed. Blocking;
+/// run it from a background task.
+final class OSVAdvisories {
+    static let shared = OSVAdvisories(url: OSVAdvisories.defaultURL)
+    static let batchEndpoint = URL(string: "https://api.osv.dev/v1/querybatch")!
+    static let vulnerabilityEndpoint = URL(string: "https://api.osv.dev/v1/vulns/")!
+    static let maxAge: TimeInterval = 24 * 3600
+    /// Advisory details fetched per scan at most; the rest wait for the next.
+    static let detailLimit = 200
+
+    static var defaultURL: URL { AppSupport.url("osv-cache.json") }
+
+    private struct Cached: Codable {
+        let fetched: Date
+        let advisories: [DependencyAdvisory]
+    }
+
+    let url: URL
+    private let file: JSONFileStore<[String: Cached]>
+    private let cache = LockedState<[String: Cached]?>(nil)
+    private let session = URLSession(configuration: .ephemeral)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, dates: .secondsSince1970)
+    }
+
+    /// Advisories by `ProjectDependency.key` for every dependency with a
+    /// version. On a network failure the cached answers are still returned,
+    /// with the error.
+    func advisories(
+        for dependencies: [ProjectDependency], now: Date = Date(), cancellation: CancellationToken? = nil
+    ) -> (advisories: [String: [DependencyAdvisory]], error: DependencyScanError?) {
+        var unique: [String: ProjectDependency] = [:]
+        for dependency in dependencies where dependency.version != nil { unique[dependency.key] = dependency }
+        var known: [String: [DependencyAdvisory]] = [:]
+        var stale: [ProjectDependency] = []
+        cache.withLock { cache in
+            load(&cache)
+            for (key, dependency) in unique.sorted(by: { $0.key < $1.key }) {
+                if let cached = cache?[key], now.timeIntervalSince(cached.fetched) < Self.maxAge {
+                    known[key] = cached.advisories
+                } else {
+                    stale.append(dependency)
+                }
+            }
+        }
+        guard !stale.isEmpty else { return (known, nil) }
+
+        var fresh: [String: Cached] = [:]
+        var failure: DependencyScanError?
+        var details: [String: Data] = [:]
+        batches: for start in stride(from: 0, to: stale.count, by: 500) {
+            let batch = Array(stale[start..<min(start + 500, stale.count)])
+            let queries = batch.map { dependency -> [String: Any] in
+                var version = dependency.version ?? ""
+                if dependency.ecosystem == .go, version.hasPrefix("v") { version.removeFirst() }
+                return ["package": ["name": dependency.name, "ecosystem": dependency.ecosystem.osvName],
+                        "version": version]
+            }
+            guard cancellation?.isCancelled != true,
+                  let body = try? JSONSerialization.data(withJSONObject: ["queries": queries])
+            else { break }
+            var request = URLRequest(url: Self.batchEndpoint, timeoutInterval: 30)
+            request.httpMethod = "POST"
+            request.setValue("application/json", forHTTPHeaderField: "Content-Type")
+            request.httpBody = body
+            let ids: [[String]]
+            switch fetch(request) {
+            case .success(let data):
+                guard let parsed = Self.parseBatch(data), parsed.count == batch.count else {
+                    failure = .advisoriesUnavailable("unexpected reply from OSV")
+                    break batches
+                }
+                ids = parsed
+            case .failure(let error):
+                failure = error
+                break batches
+            }
+            for (dependency, vulnerabilities) in zip(batch, ids) {
+                var advisories: [DependencyAdvisory] = []
+                for id in vulnerabilities {
+                    let url = Self.vulnerabilityEndpoint.appendingPathComponent(id)
+                    if details[id] == nil, details.count < Self.detailLimit, cancellation?.isCancelled != true,
+                       case .success(let data) = fetch(URLRequest(url: url, timeoutInterval: 20)) {
+                        details[id] = data
+                    }
+                    advisories.append(details[id].flatMap { Self.parseVulnerability($0, package: dependency.name) }
+                        ?? DependencyAdvisory(id: id, summary: ""))
+                }
+                known[dependency.key] = advisories
+                // Only complete answers are cached; missing details are retried.
+                if vulnerabilities.allSatisfy({ details[$0] != nil }) {
+                    fresh[dependency.key] = Cached(fetched: now, advisories: advisories)
+                }
+            }
+        }
+        if !fresh.isEmpty { store(fresh) }
+        return (known, failure)
+    }
+
+    /// Vulnerability ids per query from a `querybatch` reply, in order.
+    static func parseBatch(_ data: Data) -> [[String]]? {
+        guard let root = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any],
+              let results = root["results"] as? [[String: Any]] else { return nil }
+        return results.map { result in
+            (result["vulns"] as? [[String: Any]] ?? []).compactMap { $0["id"] as? String }
+        }
+    }
+
+    /// An OSV vulnerability record, with the fixed versions for `package`.
+    static func parseVulnerability(_ data: Data, package: String) -> DependencyAdvisory? {
+        guard let record = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any],
+              let id = record["id"] as? String else { return nil }
+        var fixed: [String] = []
+        for affected in record["affected"] as? [[String: Any]] ?? [] {
+            guard (affected["package"] as? [String: Any])?["name"] as? String == package else { continue }
+            for range in affected["ranges"] as? [[String: Any]] ?? [] {
+                for event in range["events"] as? [[String: Any]] ?? [] {
+                    if let version = event["fixed"] as? String, !fixed.contains(version) { fixed.append(version) }
+                }
+            }
+        }
+        let summary = record["summary"] as? String
+            ?? (record["details"] as? String).map { String($0.prefix(200)) } ?? id
+        return DependencyAdvisory(
+            id: id, summary: summary, aliases: record["aliases"] as? [String] ?? [],
+            severity: (record["database_specific"] as? [String: Any])?["severity"] as? String, fixed: fixed)
+    }
+
+    private func fetch(_ request: URLRequest) -> Result<Data, DependencyScanError> {
+        let outcome = LockedState<Result<Data, DependencyScanError>>(.failure(.advisoriesUnavailable("timed out")))
+        let done = DispatchSemaphore(value: 0)
+        session.dataTask(with: request) { data, response, error in
+            defer { done.signal() }
+            let result: Result<Data, DependencyScanError>
+            if let error {
+                result = .failure(.advisoriesUnavailable(error.localizedDescription))
+            } else if let http = response as? HTTPURLResponse, !(200..<300).contains(http.statusCode) {
+                result = .failure(.advisoriesUnavailable("HTTP \(http.statusCode)"))
+            } else {
+                result = .success(data ?? Data())
+            }
+            outcome.withLock { $0 = result }
+        }.resume()
+        _ = done.wait(timeout: .now() + request.timeoutInterval + 1)
+        return outcome.snapshot
+    }
+
+    private func load(_ cache: inout [String: Cached]?) {
+        guard cache == nil else { return }
+        cache = file.load() ?? [:]
+    }
+
+    private func store(_ fresh: [String: Cached]) {
+        let snapshot = cache.withLock { cache -> [String: Cached] in
+            load(&cache)
+            var all = cache ?? [:]
+            all.merge(fresh) { _, new in new }
+            cache = all
+            return all
+        }
+        file.save(snapshot)
+    }
+}
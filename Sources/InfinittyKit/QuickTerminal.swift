@@ -33,6 +33,17 @@ struct GlobalHotKeySpec: Equatable {
         return GlobalHotKeySpec(keyCode: keyCode, modifiers: modifiers)
     }
 
+    /// Carbon modifier bits for an AppKit key event's flags, to compare a
+    /// key press against a parsed spec.
+    static func carbonModifiers(_ flags: NSEvent.ModifierFlags) -> UInt32 {
+        var modifiers: UInt32 = 0
+        if flags.contains(.command) { modifiers |= UInt32(cmdKey) }
+        if flags.contains(.control) { modifiers |= UInt32(controlKey) }
+        if flags.contains(.option) { modifiers |= UInt32(optionKey) }
+        if flags.contains(.shift) { modifiers |= UInt32(shiftKey) }
+        return modifiers
+    }
+
     private static let keyCodes: [String: UInt32] = [
         "a": 0, "s": 1, "d": 2, "f": 3, "h": 4, "g": 5,
         "z": 6, "x": 7, "c": 8, "v": 9, "b": 11,
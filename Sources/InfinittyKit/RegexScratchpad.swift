@@ -0,0 +1,338 @@
+import Foundation
+
+enum RegexScratchpadError: Error, Equatable, CustomStringConvertible {
+    /// `offset` is a UTF-16 index into the pattern, when the mistake could
+    /// be pinned down.
+    case invalidPattern(String, offset: Int?)
+    case unknownFlag(Character)
+    case invalidName
+
+    var description: String {
+        switch self {
+        case .invalidPattern(let reason, let offset):
+            return offset.map { "\(reason) at offset \($0)" } ?? reason
+        case .unknownFlag(let flag): return "unknown flag '\(flag)' (use i, m, s, x, w)"
+        case .invalidName: return "pattern names must be 1-64 characters"
+        }
+    }
+}
+
+/// A pattern kept for reuse in the regex tester.
+struct SavedPattern: Codable, Equatable {
+    let name: String
+    let pattern: String
+    var flags: String = ""
+    var sample: String?
+    /// Built-ins ship with the app and can't be deleted.
+    var builtIn = false
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["name": name, "pattern": pattern, "flags": flags, "builtIn": builtIn]
+        if let sample { object["sample"] = sample }
+        return object
+    }
+}
+
+/// Backend of the regex tester: compiles with the engine log filters use
+/// (`NSRegularExpression`, ICU syntax), and reports every match with its
+/// groups as UTF-16 offsets, which is what text views index by. ICU's
+/// errors carry no position, so `errorOffset` finds the usual culprits —
+/// unbalanced groups and classes, stray quantifiers, a trailing backslash —
+/// itself.
+enum RegexScratchpad {
+    static let maxMatches = 1000
+
+    /// `i` case-insensitive, `m` anchors match at lines, `s` dot matches
+    /// newlines, `x` whitespace and comments ignored, `w` Unicode word
+    /// boundaries.
+    static func options(_ flags: String) -> Result<NSRegularExpression.Options, RegexScratchpadError> {
+        var options: NSRegularExpression.Options = []
+        for flag in flags {
+            switch flag {
+            case "i": options.insert(.caseInsensitive)
+            case "m": options.insert(.anchorsMatchLines)
+            case "s": options.insert(.dotMatchesLineSeparators)
+            case "x": options.insert(.allowCommentsAndWhitespace)
+            case "w": options.insert(.useUnicodeWordBoundaries)
+            case "g": continue // every match is reported anyway
+            default: return .failure(.unknownFlag(flag))
+            }
+        }
+        return .success(options)
+    }
+
+    static func compile(_ pattern: String, flags: String) -> Result<NSRegularExpression, RegexScratchpadError> {
+        let options: NSRegularExpression.Options
+        switch self.options(flags) {
+        case .success(let parsed): options = parsed
+        case .failure(let error): return .failure(error)
+        }
+        do {
+            return .success(try NSRegularExpression(pattern: pattern, options: options))
+        } catch {
+            let offset = errorOffset(in: pattern)
+            let message = offset.map { reason(at: $0, in: pattern) } ?? "invalid pattern"
+            return .failure(.invalidPattern(message, offset: offset))
+        }
+    }
+
+    /// Matches of `pattern` in `sample`, each with its capture groups (nil
+    /// entries for groups that did not take part), plus `replaced` when a
+    /// `$1`-style template is given.
+    static func test(
+        pattern: String, flags: String, sample: String, template: String? = nil
+    ) -> Result<[String: Any], RegexScratchpadError> {
+        let regex: NSRegularExpression
+        switch compile(pattern, flags: flags) {
+        case .success(let compiled): regex = compiled
+        case .failure(let error): return .failure(error)
+        }
+        let names = groupNames(in: pattern)
+        let text = sample as NSString
+        let all = regex.matches(in: sample, range: NSRange(location: 0, length: text.length))
+        let matches = all.prefix(maxMatches).map { match -> [String: Any] in
+            let groups = (1..<max(match.numberOfRanges, 1)).map { index -> Any in
+                let range = match.range(at: index)
+                guard range.location != NSNotFound else { return NSNull() }
+                var group: [String: Any] = [
+                    "start": range.location, "end": NSMaxRange(range), "text": text.substring(with: range),
+                ]
+                if let name = names[index] { group["name"] = name }
+                return group
+            }
+            return [
+                "start": match.range.location, "end": NSMaxRange(match.range),
+                "text": text.substring(with: match.range), "groups": groups,
+            ]
+        }
+        var object: [String: Any] = [
+            "matches": matches, "count": all.count, "truncated": all.count > maxMatches,
+            "groupCount": regex.numberOfCaptureGroups,
+            "groupNames": names.sorted { $0.key < $1.key }.map(\.value),
+        ]
+        if let template {
+            object["replaced"] = regex.stringByReplacingMatches(
+                in: sample, range: NSRange(location: 0, length: text.length), withTemplate: template)
+        }
+        return .success(object)
+    }
+
+    /// Capture group number → name for `(?<name>…)` groups, counting
+    /// groups the way ICU numbers them.
+    static func groupNames(in pattern: String) -> [Int: String] {
+        var names: [Int: String] = [:]
+        var group = 0
+        scan(pattern) { index, chars, isAtom in
+            guard !isAtom, chars[index] == UInt16(ascii: "(") else { return }
+            let next = chars.dropFirst(index + 1)
+            guard next.first == UInt16(ascii: "?") else {
+                group += 1
+                return
+            }
+            // `(?<name>` captures; `(?<=` and `(?<!` are lookbehinds.
+            let after = next.dropFirst()
+            guard after.first == UInt16(ascii: "<"), let first = after.dropFirst().first,
+                  first != UInt16(ascii: "="), first != UInt16(ascii: "!")
+            else { return }
+            group += 1
+            let name = after.dropFirst().prefix { $0 != UInt16(ascii: ">") }
+            names[group] = String(decoding: name, as: UTF16.self)
+        }
+        return names
+    }
+
+    /// Where a pattern that failed to compile most likely went wrong, as a
+    /// UTF-16 offset; nil when none of the checks applies.
+    static func errorOffset(in pattern: String) -> Int? {
+        let chars = Array(pattern.utf16)
+        let backslashes = chars.reversed().prefix { $0 == UInt16(ascii: "\\") }.count
+        if backslashes % 2 == 1 { return chars.count - 1 }
+        var opens: [Int] = []
+        var found: Int?
+        var previous: UInt16?
+        scan(pattern) { index, chars, isAtom in
+            guard found == nil else { return }
+            defer { previous = isAtom ? 0 : chars[index] }
+            guard !isAtom else { return }
+            let char = chars[index]
+            switch char {
+            case UInt16(ascii: "("):
+                opens.append(index)
+            case UInt16(ascii: ")"):
+                if opens.popLast() == nil { found = index }
+            case UInt16(ascii: "*"), UInt16(ascii: "+"), UInt16(ascii: "?"):
+                // `(?` opens group syntax; `*?` and `++` are lazy and
+                // possessive forms. Otherwise there must be something to
+                // repeat: not the start, `(` or `|`.
+                if char == UInt16(ascii: "?"), previous == UInt16(ascii: "(") { break }
+                if char != UInt16(ascii: "*"), let previous, isQuantifier(previous) { break }
+                if previous == nil || previous == UInt16(ascii: "(") || previous == UInt16(ascii: "|") {
+                    found = index
+                }
+            default:
+                break
+            }
+        }
+        if let found { return found }
+        if let open = unclosedClass(in: chars) { return open }
+        return opens.last
+    }
+
+    private static func isQuantifier(_ char: UInt16) -> Bool {
+        char == UInt16(ascii: "*") || char == UInt16(ascii: "+") || char == UInt16(ascii: "?")
+            || char == UInt16(ascii: "}")
+    }
+
+    private static func reason(at offset: Int, in pattern: String) -> String {
+        let chars = Array(pattern.utf16)
+        switch chars[offset] {
+        case UInt16(ascii: "\\"): return "trailing backslash"
+        case UInt16(ascii: ")"): return "unmatched ')'"
+        case UInt16(ascii: "("): return "unclosed group"
+        case UInt16(ascii: "["): return "unclosed character class"
+        default: return "nothing to repeat"
+        }
+    }
+
+    /// Walks the pattern's code units, calling `visit` with each plain one
+    /// and once (`isAtom`) for each escape or character class, so `\\(` and
+    /// `[(]` are not mistaken for groups.
+    private static func scan(_ pattern: String, _ visit: (Int, [UInt16], _ isAtom: Bool) -> Void) {
+        let chars = Array(pattern.utf16)
+        var index = 0
+        while index < chars.count {
+            switch chars[index] {
+            case UInt16(ascii: "\\"):
+                visit(index, chars, true)
+                index += 2
+                continue
+            case UInt16(ascii: "["):
+                visit(index, chars, true)
+                index = classEnd(chars, from: index) ?? chars.count
+            default:
+                visit(index, chars, false)
+            }
+            index += 1
+        }
+    }
+
+    /// Index of the `]` closing the class opened at `start`; a `]` right
+    /// after `[` or `[^` is literal, and classes nest in ICU.
+    private static func classEnd(_ chars: [UInt16], from start: Int) -> Int? {
+        var depth = 0
+        var index = start
+        while index < chars.count {
+            let char = chars[index]
+            if char == UInt16(ascii: "\\") {
+                index += 2
+                continue
+            }
+            if char == UInt16(ascii: "[") {
+                depth += 1
+                if index + 1 < chars.count, chars[index + 1] == UInt16(ascii: "^") { index += 1 }
+                if index + 1 < chars.count, chars[index + 1] == UInt16(ascii: "]") { index += 1 }
+            } else if char == UInt16(ascii: "]") {
+                depth -= 1
+                if depth == 0 { return index }
+            }
+            index += 1
+        }
+        return nil
+    }
+
+    private static func unclosedClass(in chars: [UInt16]) -> Int? {
+        var index = 0
+        while index < chars.count {
+            if chars[index] == UInt16(ascii: "\\") {
+                index += 2
+                continue
+            }
+            if chars[index] == UInt16(ascii: "[") {
+                guard let end = classEnd(chars, from: index) else { return index }
+                index = end
+            }
+            index += 1
+        }
+        return nil
+    }
+}
+
+/// The tester's pattern library: a few built-ins plus the user's own,
+/// saved as JSON under Application Support.
+final class RegexLibrary {
+    static let shared = RegexLibrary(url: RegexLibrary.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("regex-patterns.json") }
+
+    static let builtIns: [SavedPattern] = [
+        SavedPattern(name: "IPv4 address", pattern: #"\b(?:\d{1,3}\.){3}\d{1,3}\b"#, builtIn: true),
+        SavedPattern(
+            name: "ISO 8601 date", pattern: #"\b(?<year>\d{4})-(?<month>\d{2})-(?<day>\d{2})\b"#, builtIn: true),
+        SavedPattern(
+            name: "UUID", pattern: #"\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b"#,
+            flags: "i", builtIn: true),
+        SavedPattern(
+            name: "Semantic version",
+            pattern: #"\bv?(?<major>\d+)\.(?<minor>\d+)\.(?<patch>\d+)(?:-[0-9A-Za-z.-]+)?\b"#, builtIn: true),
+        SavedPattern(name: "Email address", pattern: #"[\w.+-]+@[\w-]+(?:\.[\w-]+)+"#, builtIn: true),
+        SavedPattern(name: "URL", pattern: #"https?://[^\s"'<>]+"#, builtIn: true),
+        SavedPattern(name: "Hex color", pattern: #"#(?:[0-9a-f]{3}){1,2}\b"#, flags: "i", builtIn: true),
+    ]
+
+    let url: URL
+    private let file: JSONFileStore<[SavedPattern]>
+    private let state = LockedState<[SavedPattern]?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url)
+    }
+
+    /// Built-ins first, then saved patterns by name.
+    func all() -> [SavedPattern] {
+        Self.builtIns + saved().sorted { $0.name.localizedCaseInsensitiveCompare($1.name) == .orderedAscending }
+    }
+
+    /// Saves `pattern`, replacing one of the same name. It must compile.
+    func save(_ pattern: SavedPattern) -> Result<SavedPattern, RegexScratchpadError> {
+        let name = pattern.name.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard (1...64).contains(name.count), !Self.builtIns.contains(where: { $0.name == name }) else {
+            return .failure(.invalidName)
+        }
+        if case .failure(let error) = RegexScratchpad.compile(pattern.pattern, flags: pattern.flags) {
+            return .failure(error)
+        }
+        let entry = SavedPattern(name: name, pattern: pattern.pattern, flags: pattern.flags, sample: pattern.sample)
+        update { patterns in
+            patterns.removeAll { $0.name == name }
+            patterns.append(entry)
+        }
+        return .success(entry)
+    }
+
+    func delete(_ name: String) -> Bool {
+        var removed = false
+        update { patterns in
+            let before = patterns.count
+            patterns.removeAll { $0.name == name }
+            removed = patterns.count != before
+        }
+        return removed
+    }
+
+    private func saved() -> [SavedPattern] {
+        state.withLock { cached in
+            if let cached { return cached }
+            let loaded = file.load() ?? []
+            cached = loaded
+            return loaded
+        }
+    }
+
+    private func update(_ change: (inout [SavedPattern]) -> Void) {
+        var patterns = saved()
+        change(&patterns)
+        state.withLock { $0 = patterns }
+        file.save(patterns)
+    }
+}
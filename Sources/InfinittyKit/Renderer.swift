@@ -565,26 +565,7 @@ final class Renderer: NSObject {
     private func resolve(
         _ code: UInt32, isFG: Bool, flags: UInt16, theme: Theme
     ) -> SIMD4<Float> {
-        var color: SIMD4<Float>
-        if code & 0x4000_0000 != 0 {
-            color = code == ColorCode.defaultFG ? theme.foreground : theme.background
-        } else if code & 0x8000_0000 != 0 {
-            color = SIMD4<Float>(
-                Float((code >> 16) & 0xFF) / 255,
-                Float((code >> 8) & 0xFF) / 255,
-                Float(code & 0xFF) / 255,
-                1
-            )
-        } else {
-            var idx = Int(code & 0xFF)
-            if isFG && idx < 8 && flags & CellFlags.bold != 0 { idx += 8 }
-            color = theme.palette[idx]
-        }
-        if isFG && flags & CellFlags.faint != 0 {
-            color = simd_mix(theme.background, color, SIMD4<Float>(repeating: 0.55))
-            color.w = 1
-        }
-        return color
+        theme.resolve(code, isFG: isFG, flags: flags)
     }
 
     /// Build instance lists from `snap`. Uses the captured `atlas`/`theme`
@@ -0,0 +1,46 @@
+import Foundation
+
+/// Where a path printed in a pane is shown: selected in the Files pane's
+/// tree, or in a Finder window.
+enum RevealTarget: String {
+    case files
+    case finder
+}
+
+/// Turns a path as a pane printed it into one that exists on disk, for
+/// `reveal-in-explorer`, `reveal-in-finder` and the context menu.
+enum RevealPath {
+    /// `text` made absolute: `~` expanded and a relative path read from
+    /// `cwd`. A `:line[:column]` suffix is dropped when the path with it
+    /// doesn't exist. Nil when neither does.
+    static func resolve(
+        _ text: String, cwd: String?,
+        exists: (String) -> Bool = { FileManager.default.fileExists(atPath: $0) }
+    ) -> String? {
+        let trimmed = text.trimmingCharacters(in: .whitespaces)
+        guard !trimmed.isEmpty else { return nil }
+        var candidates = [trimmed]
+        if let bare = DetectedLink(kind: .path, line: 0, columns: 0...0, text: trimmed).path, bare != trimmed {
+            candidates.append(bare)
+        }
+        for candidate in candidates {
+            var path = (candidate as NSString).expandingTildeInPath
+            if !path.hasPrefix("/") {
+                guard let cwd, !cwd.isEmpty else { continue }
+                path = (cwd as NSString).appendingPathComponent(path)
+            }
+            path = (path as NSString).standardizingPath
+            if exists(path) { return path }
+        }
+        return nil
+    }
+
+    /// The names leading from `root` down to `path`, or nil when `path`
+    /// is not under it. Empty when they are the same.
+    static func components(of path: String, under root: String) -> [String]? {
+        let target = URL(fileURLWithPath: path).standardizedFileURL.pathComponents
+        let base = URL(fileURLWithPath: root).standardizedFileURL.pathComponents
+        guard target.count >= base.count, Array(target.prefix(base.count)) == base else { return nil }
+        return Array(target.dropFirst(base.count))
+    }
+}
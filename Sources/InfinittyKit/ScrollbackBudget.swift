@@ -0,0 +1,54 @@
+import Foundation
+
+/// `scrollback-budget`: one ceiling on the memory every pane's scrollback
+/// holds together, on top of each pane's own `scrollback-lines`. Over it,
+/// the panes holding the most are capped to a fair share and their oldest
+/// rows spill to disk, where search, export and `scrollback-load` still
+/// find them.
+enum ScrollbackBudget {
+    /// However tight the budget, a pane keeps this many rows in memory.
+    static let minimumLines = 1000
+    /// Caps come off once usage falls under this much of the budget, so
+    /// panes near the line don't flap between capped and not.
+    static let releaseRatio = 0.75
+
+    /// `512MB`, `2G`, `300k` or plain bytes; `off` and `0` are no budget.
+    static func size(_ text: String) -> Int? {
+        let trimmed = text.trimmingCharacters(in: .whitespaces).uppercased()
+        if ["OFF", "NONE", "0"].contains(trimmed) { return 0 }
+        let units: [(String, Double)] = [
+            ("GB", 1_073_741_824), ("G", 1_073_741_824), ("MB", 1_048_576), ("M", 1_048_576),
+            ("KB", 1024), ("K", 1024), ("B", 1),
+        ]
+        let unit = units.first { trimmed.hasSuffix($0.0) }
+        let number = trimmed.dropLast(unit?.0.count ?? 0).trimmingCharacters(in: .whitespaces)
+        guard let amount = Double(number), amount >= 0 else { return nil }
+        return Int(amount * (unit?.1 ?? 1))
+    }
+
+    /// Byte caps that bring `usage` (bytes per pane) within `budget`, for
+    /// the panes that have to shrink; nil when everything already fits.
+    /// Water-filling: panes under an even share keep all they have and the
+    /// rest of the budget is shared evenly by the larger ones.
+    static func caps(usage: [Int: Int], budget: Int) -> [Int: Int]? {
+        guard budget > 0, usage.values.reduce(0, +) > budget else { return nil }
+        let ascending = usage.sorted { $0.value != $1.value ? $0.value < $1.value : $0.key < $1.key }
+        var remaining = budget
+        for (index, entry) in ascending.enumerated() {
+            let share = remaining / (ascending.count - index)
+            guard entry.value <= share else {
+                return Dictionary(uniqueKeysWithValues: ascending[index...].map { ($0.key, share) })
+            }
+            remaining -= entry.value
+        }
+        return nil
+    }
+
+    /// Rows a pane holding `bytes` in `lines` rows can keep under a byte
+    /// `cap`, never fewer than `minimumLines`.
+    static func lines(forCap cap: Int, bytes: Int, lines: Int) -> Int {
+        guard bytes > 0, lines > 0 else { return max(lines, minimumLines) }
+        let perLine = max(Double(bytes) / Double(lines), 1)
+        return max(Int(Double(cap) / perLine), minimumLines)
+    }
+}
@@ -0,0 +1,139 @@
+import Foundation
+
+/// Scrollback a pane's in-memory ring has pushed out, kept on disk as text
+/// (`scrollback-spill`) so a long build's history is still there to read
+/// back. Lines keep their absolute numbers and are written in
+/// zlib-compressed chunks of `chunkLines` under Application Support; the
+/// files belong to one pane and go when it closes.
+final class ScrollbackSpill {
+    static let chunkLines = 2000
+
+    static var rootURL: URL { AppSupport.url("scrollback", isDirectory: true) }
+
+    let directory: URL
+    private let queue = DispatchQueue(label: "infinitty.scrollback-spill", qos: .utility)
+
+    private struct State {
+        var firstLine = 0
+        var count = 0
+        /// The newest lines, not yet a whole chunk.
+        var pending: [String] = []
+    }
+
+    private let state = LockedState(State())
+
+    init(directory: URL) {
+        self.directory = directory
+    }
+
+    /// A fresh directory for `pane`, named after this process so a later
+    /// launch can tell leftovers from a crash (`purgeStale`) from a
+    /// running instance's.
+    convenience init(pane: Int) {
+        self.init(directory: Self.rootURL.appendingPathComponent(
+            "\(getpid())-pane-\(pane)-\(UUID().uuidString.prefix(8))", isDirectory: true))
+    }
+
+    /// The absolute lines held, oldest first.
+    var lines: Range<Int> {
+        let state = state.snapshot
+        return state.firstLine..<(state.firstLine + state.count)
+    }
+
+    /// Takes the row evicted from memory as absolute `line`. Called on the
+    /// PTY thread, so a full chunk is compressed and written off it. A gap
+    /// (scrollback was cleared) starts the spill over.
+    func append(line: Int, text: String) {
+        state.withLock { state in
+            if line != state.firstLine + state.count {
+                if state.count > 0 { removeFiles() }
+                state = State(firstLine: line)
+            }
+            state.pending.append(text)
+            state.count += 1
+            guard state.pending.count == Self.chunkLines else { return }
+            // Queued while the lock is held, so a reader that no longer
+            // sees these lines as pending finds the write ahead of it.
+            let lines = state.pending
+            let url = Self.chunkURL(state.count / Self.chunkLines - 1, in: directory)
+            state.pending.removeAll(keepingCapacity: true)
+            queue.async { Self.write(lines, to: url) }
+        }
+    }
+
+    /// Up to `count` lines from absolute `from`, clamped to what is held.
+    func read(from: Int, count: Int) -> (firstLine: Int, lines: [String]) {
+        let snapshot = state.snapshot
+        let first = max(from, snapshot.firstLine)
+        let end = min(from + count, snapshot.firstLine + snapshot.count)
+        guard first < end else { return (first, []) }
+        let written = snapshot.count - snapshot.pending.count
+        var lines: [String] = []
+        var offset = first - snapshot.firstLine
+        let stop = end - snapshot.firstLine
+        if offset < written {
+            // Waits for chunk writes already queued.
+            queue.sync {
+                while offset < min(stop, written) {
+                    let index = offset / Self.chunkLines
+                    let chunk = Self.readChunk(Self.chunkURL(index, in: directory))
+                    let upper = min(stop, written, (index + 1) * Self.chunkLines)
+                    for position in offset..<upper {
+                        let inChunk = position - index * Self.chunkLines
+                        lines.append(inChunk < chunk.count ? chunk[inChunk] : "")
+                    }
+                    offset = upper
+                }
+            }
+        }
+        if offset < stop {
+            lines.append(contentsOf: snapshot.pending[(offset - written)..<(stop - written)])
+        }
+        return (first, lines)
+    }
+
+    /// Forgets every line and deletes the files.
+    func removeAll() {
+        state.withLock { state in
+            state = State()
+            removeFiles()
+        }
+    }
+
+    private func removeFiles() {
+        queue.async { [directory] in try? FileManager.default.removeItem(at: directory) }
+    }
+
+    /// Deletes spill directories whose process is gone.
+    static func purgeStale() {
+        let fm = FileManager.default
+        guard let names = try? fm.contentsOfDirectory(atPath: rootURL.path) else { return }
+        for name in names {
+            guard let pid = name.split(separator: "-").first.flatMap({ pid_t($0) }) else { continue }
+            if pid == getpid() || kill(pid, 0) == 0 { continue }
+            try? fm.removeItem(at: rootURL.appendingPathComponent(name))
+        }
+    }
+
+    private static func chunkURL(_ index: Int, in directory: URL) -> URL {
+        directory.appendingPathComponent("chunk-\(index).zlib")
+    }
+
+    private static func write(_ lines: [String], to url: URL) {
+        let text = Data(lines.joined(separator: "\n").utf8)
+        guard let compressed = try? (text as NSData).compressed(using: .zlib) else { return }
+        let fm = FileManager.default
+        try? fm.createDirectory(
+            at: url.deletingLastPathComponent(), withIntermediateDirectories: true,
+            attributes: [.posixPermissions: 0o700])
+        fm.createFile(atPath: url.path, contents: compressed as Data, attributes: [.posixPermissions: 0o600])
+    }
+
+    private static func readChunk(_ url: URL) -> [String] {
+        guard let data = try? Data(contentsOf: url),
+              let text = try? (data as NSData).decompressed(using: .zlib)
+        else { return [] }
+        return String(decoding: text as Data, as: UTF8.self)
+            .split(separator: "\n", omittingEmptySubsequences: false).map(String.init)
+    }
+}
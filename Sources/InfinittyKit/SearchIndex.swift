@@ -0,0 +1,334 @@
+import CryptoKit
+import Foundation
+
+/// How much of one workspace `SearchIndex` takes in (`search-index-max`,
+/// `search-index-max-files`). Files past either cap are left out and the
+/// index reports itself truncated; searches still cover what fits.
+struct SearchIndexLimits: Equatable {
+    var maxBytes = 128 * 1_048_576
+    var maxFiles = 50_000
+    /// Bigger files are generated or data, not something to search by line.
+    static let maxFileSize = 1 << 20
+}
+
+/// One indexed file as it was read.
+struct IndexedFile: Codable, Equatable {
+    var modified: Double
+    var size: Int
+    /// Its distinct trigrams, sorted, as little-endian UInt32s.
+    var trigrams: Data
+
+    func contains(_ trigram: UInt32) -> Bool {
+        trigrams.withUnsafeBytes { raw in
+            var lo = 0
+            var hi = raw.count / 4 - 1
+            while lo <= hi {
+                let mid = (lo + hi) / 2
+                let value = UInt32(littleEndian: raw.loadUnaligned(fromByteOffset: mid * 4, as: UInt32.self))
+                if value == trigram { return true }
+                if value < trigram { lo = mid + 1 } else { hi = mid - 1 }
+            }
+            return false
+        }
+    }
+}
+
+/// A trigram index over the text files of one workspace (a repo root, or
+/// the directory itself outside git), so a content search only reads the
+/// files that can match. Files come from `CodeSearch.listFilesSync`, so
+/// .gitignore holds; an FSEvents watcher re-checks sizes and dates a
+/// couple of seconds after the tree moves and re-reads only what changed.
+/// Saved under Application Support, so the next launch starts from what
+/// it knew. Matching is by line, case-insensitive for ASCII.
+final class SearchIndex {
+    enum Phase: String {
+        /// The first pass over the tree is still running; searches fall
+        /// back to grep.
+        case building
+        case ready
+    }
+
+    struct Status {
+        let root: String
+        let phase: Phase
+        let files: Int
+        let bytes: Int
+        /// Binary, oversized or unreadable files passed over.
+        let skipped: Int
+        /// A cap stopped the last pass before every file was in.
+        let truncated: Bool
+        let limits: SearchIndexLimits
+        let updated: Date?
+
+        var wire: [String: Any] {
+            var object: [String: Any] = [
+                "root": root, "state": phase.rawValue, "files": files, "bytes": bytes, "skipped": skipped,
+                "truncated": truncated, "maxBytes": limits.maxBytes, "maxFiles": limits.maxFiles,
+            ]
+            if let updated { object["updated"] = ISO8601DateFormatter().string(from: updated) }
+            return object
+        }
+    }
+
+    private struct Snapshot: Codable {
+        static let version = 1
+        var version = Snapshot.version
+        var root: String
+        var files: [String: IndexedFile]
+    }
+
+    private struct Contents {
+        var phase = Phase.building
+        var files: [String: IndexedFile] = [:]
+        var bytes = 0
+        var skipped = 0
+        var truncated = false
+        var updated: Date?
+        var limits: SearchIndexLimits
+    }
+
+    let root: String
+    private let storeURL: URL?
+    private let contents: LockedState<Contents>
+    private let queue: DispatchQueue
+    private var watcher: DirectoryWatcher?
+    private let pendingRefresh = LockedState<DispatchWorkItem?>(nil)
+
+    /// `storeURL` nil keeps the index in memory only.
+    init(root: String, limits: SearchIndexLimits, storeURL: URL?, watches: Bool = true) {
+        self.root = root
+        self.storeURL = storeURL
+        contents = LockedState(Contents(limits: limits))
+        queue = DispatchQueue(label: "infinitty.search-index", qos: .utility)
+        if watches {
+            watcher = DirectoryWatcher(path: root) { [weak self] in self?.scheduleRefresh() }
+        }
+        queue.async { [weak self] in self?.refresh(loadingSaved: true) }
+    }
+
+    var status: Status {
+        contents.withLock {
+            Status(
+                root: root, phase: $0.phase, files: $0.files.count, bytes: $0.bytes, skipped: $0.skipped,
+                truncated: $0.truncated, limits: $0.limits, updated: $0.updated)
+        }
+    }
+
+    /// New caps take effect on the next pass, which starts now.
+    func setLimits(_ limits: SearchIndexLimits) {
+        let changed = contents.withLock { contents -> Bool in
+            defer { contents.limits = limits }
+            return contents.limits != limits
+        }
+        if changed { queue.async { [weak self] in self?.refresh(loadingSaved: false) } }
+    }
+
+    /// Forgets everything and reads the tree again.
+    func rebuild() {
+        contents.withLock {
+            $0.files = [:]
+            $0.bytes = 0
+            $0.phase = .building
+        }
+        queue.async { [weak self] in self?.refresh(loadingSaved: false) }
+    }
+
+    /// Blocks until the passes queued so far are done.
+    func waitForIdle() {
+        queue.sync {}
+    }
+
+    /// Lines containing `query`, at most `limit`, paths relative to the
+    /// root; only files under `prefix` when given. Nil until the first
+    /// pass is done.
+    func search(_ query: String, limit: Int = 200, under prefix: String? = nil) -> [CodeSearch.Match]? {
+        let needle = Array(query.utf8)
+        guard !needle.isEmpty else { return [] }
+        let wanted = Self.trigrams(of: needle)
+        let candidates = contents.withLock { contents -> [String]? in
+            guard contents.phase == .ready else { return nil }
+            return contents.files
+                .filter { path, file in
+                    (prefix.map { path.hasPrefix($0) } ?? true) && wanted.allSatisfy(file.contains)
+                }
+                .keys.sorted()
+        }
+        guard let candidates else { return nil }
+        var matches: [CodeSearch.Match] = []
+        for path in candidates {
+            guard let data = FileManager.default.contents(atPath: root + "/" + path),
+                  let text = String(data: data, encoding: .utf8) else { continue }
+            for (index, line) in text.split(separator: "\n", omittingEmptySubsequences: false).enumerated()
+            where line.range(of: query, options: .caseInsensitive) != nil {
+                matches.append(CodeSearch.Match(path: path, line: index + 1, text: String(line.prefix(400))))
+                if matches.count >= limit { return matches }
+            }
+        }
+        return matches
+    }
+
+    /// Distinct trigrams of `bytes`, ASCII letters folded to lowercase.
+    static func trigrams(of bytes: [UInt8]) -> Set<UInt32> {
+        guard bytes.count >= 3 else { return [] }
+        func fold(_ byte: UInt8) -> UInt32 { UInt32(byte >= 65 && byte <= 90 ? byte + 32 : byte) }
+        var set = Set<UInt32>()
+        set.reserveCapacity(min(bytes.count, 1 << 16))
+        for i in 0..<(bytes.count - 2) {
+            set.insert(fold(bytes[i]) << 16 | fold(bytes[i + 1]) << 8 | fold(bytes[i + 2]))
+        }
+        return set
+    }
+
+    static func packed(_ trigrams: Set<UInt32>) -> Data {
+        var data = Data(capacity: trigrams.count * 4)
+        for trigram in trigrams.sorted() {
+            withUnsafeBytes(of: trigram.littleEndian) { data.append(contentsOf: $0) }
+        }
+        return data
+    }
+
+    // MARK: - passes
+
+    private func scheduleRefresh() {
+        let work = DispatchWorkItem { [weak self] in self?.refresh(loadingSaved: false) }
+        pendingRefresh.withLock { pending in
+            pending?.cancel()
+            pending = work
+        }
+        queue.asyncAfter(deadline: .now() + 2, execute: work)
+    }
+
+    /// Lists the tree and brings the index in line with it: unchanged files
+    /// (same size and date) keep their entry, changed and new ones are
+    /// read, vanished ones dropped. On `queue` only.
+    private func refresh(loadingSaved: Bool) {
+        let current = contents.withLock { (files: $0.files, limits: $0.limits) }
+        var files = current.files
+        let limits = current.limits
+        if loadingSaved, files.isEmpty, let saved = load() { files = saved }
+        let prefix = root.hasSuffix("/") ? root : root + "/"
+        var kept: [String: IndexedFile] = [:]
+        var bytes = 0
+        var skipped = 0
+        var truncated = false
+        for path in CodeSearch.listFilesSync(root: root) {
+            guard kept.count < limits.maxFiles else {
+                truncated = true
+                break
+            }
+            let url = URL(fileURLWithPath: prefix + path)
+            guard let values = try? url.resourceValues(forKeys: [.fileSizeKey, .contentModificationDateKey]),
+                  let size = values.fileSize, size <= SearchIndexLimits.maxFileSize else {
+                skipped += 1
+                continue
+            }
+            guard bytes + size <= limits.maxBytes else {
+                truncated = true
+                continue
+            }
+            let modified = values.contentModificationDate?.timeIntervalSince1970 ?? 0
+            if let known = files[path], known.size == size, known.modified == modified {
+                kept[path] = known
+                bytes += size
+                continue
+            }
+            guard let data = try? Data(contentsOf: url), !data.prefix(8000).contains(0) else {
+                skipped += 1
+                continue
+            }
+            kept[path] = IndexedFile(
+                modified: modified, size: size, trigrams: Self.packed(Self.trigrams(of: Array(data))))
+            bytes += size
+        }
+        let changed = kept != files
+        contents.withLock {
+            $0.files = kept
+            $0.bytes = bytes
+            $0.skipped = skipped
+            $0.truncated = truncated
+            $0.phase = .ready
+            $0.updated = Date()
+        }
+        if changed || loadingSaved { save(kept) }
+    }
+
+    private func load() -> [String: IndexedFile]? {
+        guard let storeURL, let data = try? Data(contentsOf: storeURL),
+              let snapshot = try? PropertyListDecoder().decode(Snapshot.self, from: data),
+              snapshot.version == Snapshot.version, snapshot.root == root else { return nil }
+        return snapshot.files
+    }
+
+    private func save(_ files: [String: IndexedFile]) {
+        guard let storeURL else { return }
+        let encoder = PropertyListEncoder()
+        encoder.outputFormat = .binary
+        guard let data = try? encoder.encode(Snapshot(root: root, files: files)) else { return }
+        AppSupport.write(data, to: storeURL)
+    }
+}
+
+/// The live `SearchIndex` per workspace. Indexes start on first use and
+/// the least recently used is dropped (its saved copy stays on disk) once
+/// more than `maxLive` are open, since each holds its trigrams in memory.
+final class SearchIndexes {
+    static let shared = SearchIndexes(directory: SearchIndexes.defaultDirectory)
+    static let maxLive = 6
+
+    static var defaultDirectory: URL { AppSupport.url("search-index", isDirectory: true) }
+
+    private struct State {
+        var live: [String: SearchIndex] = [:]
+        var used: [String] = []
+        var limits = SearchIndexLimits()
+    }
+
+    let directory: URL?
+    private let state = LockedState(State())
+
+    init(directory: URL?) {
+        self.directory = directory
+    }
+
+    /// The workspace `path` belongs to: its repo root, else itself.
+    static func workspaceRoot(of path: String) -> String {
+        CodeGit.repoRoot(of: path) ?? (path as NSString).standardizingPath
+    }
+
+    var limits: SearchIndexLimits {
+        get { state.withLock { $0.limits } }
+        set {
+            let live = state.withLock { state -> [SearchIndex] in
+                state.limits = newValue
+                return Array(state.live.values)
+            }
+            live.forEach { $0.setLimits(newValue) }
+        }
+    }
+
+    /// The index for `root`, started when there isn't one yet.
+    func index(for root: String) -> SearchIndex {
+        state.withLock { state in
+            state.used.removeAll { $0 == root }
+            state.used.append(root)
+            if let index = state.live[root] { return index }
+            let index = SearchIndex(root: root, limits: state.limits, storeURL: storeURL(for: root))
+            state.live[root] = index
+            while state.live.count > Self.maxLive, let oldest = state.used.first {
+                state.used.removeFirst()
+                state.live[oldest] = nil
+            }
+            return index
+        }
+    }
+
+    /// Live indexes, most recently used first.
+    var all: [SearchIndex] {
+        state.withLock { state in state.used.reversed().compactMap { state.live[$0] } }
+    }
+
+    private func storeURL(for root: String) -> URL? {
+        let name = SHA256.hash(data: Data(root.utf8)).prefix(12).map { String(format: "%02x", $0) }.joined()
+        return directory?.appendingPathComponent(name + ".plist")
+    }
+}
@@ -0,0 +1,187 @@
+import Darwin
+import Foundation
+import Security
+
+/// A pane waiting for a secret: sudo, ssh, gpg, `read -s` and the like.
+struct SecurePrompt: Equatable {
+    /// How it was spotted: the pane's tty took a line with echo off, or
+    /// (over ssh, in tmux, or anywhere the local tty is raw) the output
+    /// ends in something that reads like a password prompt.
+    enum Source: String {
+        case echo, prompt
+    }
+
+    /// The unfinished last line, e.g. `[sudo] password for ada:`.
+    let prompt: String
+    let source: Source
+
+    var wire: [String: Any] { ["prompt": prompt, "source": source.rawValue] }
+}
+
+/// The echo and line-discipline flags of a pane's tty, read through the
+/// master side of its pty.
+struct TTYEcho: Equatable {
+    var echo: Bool
+    var canonical: Bool
+
+    static func of(_ fd: Int32) -> TTYEcho? {
+        guard fd >= 0 else { return nil }
+        var attributes = termios()
+        guard tcgetattr(fd, &attributes) == 0 else { return nil }
+        return TTYEcho(
+            echo: attributes.c_lflag & tcflag_t(ECHO) != 0,
+            canonical: attributes.c_lflag & tcflag_t(ICANON) != 0)
+    }
+}
+
+/// Notices when one pane is asking for a password. Echo switched off on a
+/// line-buffered tty is what sudo, ssh and `read -s` all do, so that alone
+/// counts; a raw tty (a line editor, or ssh passing keys through) needs the
+/// output to end in a prompt too. Fed on the PTY thread, read on main.
+final class SecureInputDetector {
+    private struct State {
+        /// Output since the last line break.
+        var partial = ""
+        var current: SecurePrompt?
+    }
+
+    private let state = LockedState(State())
+
+    var current: SecurePrompt? { state.snapshot.current }
+    var isActive: Bool { current != nil }
+
+    /// Feed raw PTY bytes with the tty's flags as they are now (nil for a
+    /// pane with no local tty); true when the pane went in or out of a
+    /// prompt, or the prompt changed.
+    func ingest(_ data: Data, echo: TTYEcho?) -> Bool {
+        state.withLock { state in
+            let text = state.partial + String(decoding: data, as: UTF8.self)
+            let lines = text.components(separatedBy: .newlines)
+            state.partial = String(lines[lines.count - 1].suffix(512))
+            let line = PackageUpgradeProgress.stripEscapes(state.partial).trimmingCharacters(in: .whitespaces)
+            let next: SecurePrompt?
+            if let echo, !echo.echo, echo.canonical {
+                next = SecurePrompt(prompt: line, source: .echo)
+            } else if echo?.echo != true, Self.looksLikePrompt(line) {
+                next = SecurePrompt(prompt: line, source: .prompt)
+            } else {
+                next = nil
+            }
+            // Echo stays off for the whole read; later output doesn't make
+            // it a new prompt.
+            if next?.source == .echo, state.current?.source == .echo { return false }
+            guard next != state.current else { return false }
+            state.current = next
+            return true
+        }
+    }
+
+    /// Forgets the prompt once the command that asked has finished; true
+    /// if there was one.
+    func reset() -> Bool {
+        state.withLock { state in
+            state.partial = ""
+            guard state.current != nil else { return false }
+            state.current = nil
+            return true
+        }
+    }
+
+    private static let promptPattern = try! NSRegularExpression(
+        pattern: #"(?i)(pass(word|phrase|code)|\bpin\b|one-time code|verification code)[^:?]*[:?]$"#)
+
+    /// `Password:`, `[sudo] password for ada:`, `Enter passphrase for key
+    /// '/Users/ada/.ssh/id_ed25519':`, `Enter PIN for 'YubiKey':`.
+    static func looksLikePrompt(_ line: String) -> Bool {
+        guard !line.isEmpty, line.count <= 200 else { return false }
+        return promptPattern.firstMatch(in: line, range: NSRange(line.startIndex..., in: line)) != nil
+    }
+}
+
+/// Secrets the app can type at a prompt for you, kept in the login
+/// keychain as generic passwords under the service `infinitty.prompt`. An
+/// entry's comment, when set, is a regular expression naming the prompts
+/// it's offered at; without one it's offered at every prompt.
+enum PromptPasswords {
+    static let service = "infinitty.prompt"
+
+    struct Entry: Equatable {
+        let account: String
+        let pattern: String?
+
+        func matches(_ prompt: String) -> Bool {
+            guard let pattern, !pattern.isEmpty else { return true }
+            return prompt.range(of: pattern, options: [.regularExpression, .caseInsensitive]) != nil
+        }
+
+        var wire: [String: Any] {
+            var object: [String: Any] = ["account": account]
+            if let pattern { object["prompt"] = pattern }
+            return object
+        }
+    }
+
+    /// Names and patterns only; reading these never asks for the keychain.
+    static func entries() -> [Entry] {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: service,
+            kSecMatchLimit as String: kSecMatchLimitAll,
+            kSecReturnAttributes as String: true,
+        ]
+        var result: CFTypeRef?
+        guard SecItemCopyMatching(query as CFDictionary, &result) == errSecSuccess,
+              let items = result as? [[String: Any]] else { return [] }
+        return items.compactMap { item in
+            guard let account = item[kSecAttrAccount as String] as? String else { return nil }
+            return Entry(account: account, pattern: item[kSecAttrComment as String] as? String)
+        }.sorted { $0.account < $1.account }
+    }
+
+    static func entries(matching prompt: String) -> [Entry] {
+        entries().filter { $0.matches(prompt) }
+    }
+
+    /// The secret itself; macOS may ask the user to allow this.
+    static func password(for account: String) -> String? {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: service,
+            kSecAttrAccount as String: account,
+            kSecMatchLimit as String: kSecMatchLimitOne,
+            kSecReturnData as String: true,
+        ]
+        var result: CFTypeRef?
+        guard SecItemCopyMatching(query as CFDictionary, &result) == errSecSuccess,
+              let data = result as? Data else { return nil }
+        return String(data: data, encoding: .utf8)
+    }
+
+    /// Adds or replaces `account`. Returns an error message on failure.
+    static func store(account: String, password: String, pattern: String?) -> String? {
+        if let pattern, (try? NSRegularExpression(pattern: pattern)) == nil {
+            return "prompt is not a valid regular expression"
+        }
+        _ = remove(account: account)
+        var item: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: service,
+            kSecAttrAccount as String: account,
+            kSecAttrLabel as String: "infinitty prompt: \(account)",
+            kSecValueData as String: Data(password.utf8),
+        ]
+        if let pattern { item[kSecAttrComment as String] = pattern }
+        let status = SecItemAdd(item as CFDictionary, nil)
+        return status == errSecSuccess ? nil : SecCopyErrorMessageString(status, nil) as String? ?? "error \(status)"
+    }
+
+    /// True when there was an entry to remove.
+    static func remove(account: String) -> Bool {
+        let query: [String: Any] = [
+            kSecClass as String: kSecClassGenericPassword,
+            kSecAttrService as String: service,
+            kSecAttrAccount as String: account,
+        ]
+        return SecItemDelete(query as CFDictionary) == errSecSuccess
+    }
+}
@@ -0,0 +1,237 @@
+import CPty
+import Darwin
+import Foundation
+
+enum SerialError: Error, Equatable, CustomStringConvertible {
+    case invalidSetting(String)
+    case noSuchPort(String)
+    case openFailed(port: String, reason: String)
+
+    var description: String {
+        switch self {
+        case .invalidSetting(let message): return message
+        case .noSuchPort(let port): return "no serial port \(port)"
+        case .openFailed(let port, let reason): return "could not open \(port): \(reason)"
+        }
+    }
+}
+
+/// How to talk to a serial device: 115200 baud 8N1 without flow control
+/// unless told otherwise, as screen and minicom default to.
+struct SerialSettings: Equatable {
+    enum Parity: String, CaseIterable {
+        case none, even, odd
+    }
+
+    enum FlowControl: String, CaseIterable {
+        case none, hardware, software
+    }
+
+    static let baudRates: ClosedRange<Int> = 50...12_000_000
+
+    /// The device path, resolved from whatever `serial-open` was given.
+    var port: String
+    var baud = 115_200
+    var dataBits = 8
+    var parity = Parity.none
+    var stopBits = 1
+    var flowControl = FlowControl.none
+
+    init(port: String) {
+        self.port = SerialPort.path(for: port)
+    }
+
+    /// Settings from `serial-open`'s JSON: `port` plus any of `baud`,
+    /// `dataBits` (5-8), `parity`, `stopBits` (1 or 2) and `flowControl`.
+    static func parse(_ object: [String: Any]) -> Result<SerialSettings, SerialError> {
+        guard let port = object["port"] as? String, !port.isEmpty else {
+            return .failure(.invalidSetting("port is required"))
+        }
+        var settings = SerialSettings(port: port)
+        if let baud = object["baud"] {
+            guard let baud = baud as? Int, baudRates.contains(baud) else {
+                return .failure(.invalidSetting("baud must be \(baudRates.lowerBound)-\(baudRates.upperBound)"))
+            }
+            settings.baud = baud
+        }
+        if let bits = object["dataBits"] {
+            guard let bits = bits as? Int, (5...8).contains(bits) else {
+                return .failure(.invalidSetting("dataBits must be 5-8"))
+            }
+            settings.dataBits = bits
+        }
+        if let parity = object["parity"] {
+            guard let parity = (parity as? String).flatMap({ Parity(rawValue: $0.lowercased()) }) else {
+                return .failure(.invalidSetting("parity must be none, even or odd"))
+            }
+            settings.parity = parity
+        }
+        if let stop = object["stopBits"] {
+            guard let stop = stop as? Int, stop == 1 || stop == 2 else {
+                return .failure(.invalidSetting("stopBits must be 1 or 2"))
+            }
+            settings.stopBits = stop
+        }
+        if let flow = object["flowControl"] {
+            guard let flow = (flow as? String).flatMap({ FlowControl(rawValue: $0.lowercased()) }) else {
+                return .failure(.invalidSetting("flowControl must be none, hardware or software"))
+            }
+            settings.flowControl = flow
+        }
+        return .success(settings)
+    }
+
+    /// `115200 8N1`, as the pane title shows it.
+    var summary: String {
+        let parity = parity == .none ? "N" : parity == .even ? "E" : "O"
+        return "\(baud) \(dataBits)\(parity)\(stopBits)"
+    }
+
+    var wire: [String: Any] {
+        [
+            "port": port, "baud": baud, "dataBits": dataBits, "parity": parity.rawValue,
+            "stopBits": stopBits, "flowControl": flowControl.rawValue,
+        ]
+    }
+}
+
+/// The serial devices macOS exposes under `/dev`. Only the `cu.` (call-out)
+/// nodes are listed: their `tty.` twins block on open until carrier detect,
+/// which USB adapters rarely raise.
+enum SerialPort {
+    static func list(in directory: String = "/dev") -> [String] {
+        let names = (try? FileManager.default.contentsOfDirectory(atPath: directory)) ?? []
+        return names.filter { $0.hasPrefix("cu.") }
+            .sorted { $0.localizedStandardCompare($1) == .orderedAscending }
+            .map { (directory as NSString).appendingPathComponent($0) }
+    }
+
+    /// A port as given to `serial-open`: a path, `cu.usbserial-1410`, or
+    /// just `usbserial-1410`.
+    static func path(for port: String, in directory: String = "/dev") -> String {
+        if port.contains("/") { return port }
+        let name = port.hasPrefix("cu.") || port.hasPrefix("tty.") ? port : "cu.\(port)"
+        return (directory as NSString).appendingPathComponent(name)
+    }
+
+    /// `usbserial-1410` for `/dev/cu.usbserial-1410`.
+    static func name(of path: String) -> String {
+        let file = (path as NSString).lastPathComponent
+        for prefix in ["cu.", "tty."] where file.hasPrefix(prefix) { return String(file.dropFirst(prefix.count)) }
+        return file
+    }
+}
+
+/// An open serial device standing in for a pane's shell: what the device
+/// sends is delivered to the pane's PTY, and keys go to the device. When
+/// the device goes away (unplugged, reset into a bootloader) the pane says
+/// so and keeps its scrollback until it's closed.
+final class SerialConnection {
+    let settings: SerialSettings
+    private let fd: Int32
+    private let queue = DispatchQueue(label: "infinitty.serial", qos: .userInitiated)
+    private var source: DispatchSourceRead?
+    private weak var pty: PTY?
+    /// Set once the device stops answering; writes are dropped from then on.
+    private let lost = LockedState(false)
+    private let closed = LockedState(false)
+
+    private init(settings: SerialSettings, fd: Int32) {
+        self.settings = settings
+        self.fd = fd
+    }
+
+    static func open(_ settings: SerialSettings) -> Result<SerialConnection, SerialError> {
+        guard FileManager.default.fileExists(atPath: settings.port) else {
+            return .failure(.noSuchPort(settings.port))
+        }
+        let parity: CChar
+        switch settings.parity {
+        case .none: parity = CChar(UInt8(ascii: "n"))
+        case .even: parity = CChar(UInt8(ascii: "e"))
+        case .odd: parity = CChar(UInt8(ascii: "o"))
+        }
+        let flow: Int32
+        switch settings.flowControl {
+        case .none: flow = 0
+        case .hardware: flow = 1
+        case .software: flow = 2
+        }
+        let fd = cpty_open_serial(
+            settings.port, UInt(settings.baud), Int32(settings.dataBits), parity, Int32(settings.stopBits), flow)
+        guard fd >= 0 else {
+            // EBUSY is TIOCEXCL: screen, minicom or another pane has it.
+            let reason = errno == EBUSY ? "it's in use" : String(cString: strerror(errno))
+            return .failure(.openFailed(port: settings.port, reason: reason))
+        }
+        return .success(SerialConnection(settings: settings, fd: fd))
+    }
+
+    /// Makes the device `pty`'s far end and starts reading from it.
+    func bind(_ pty: PTY) {
+        self.pty = pty
+        pty.attach(remote: PTY.Remote(
+            write: { [weak self] bytes in self?.write(bytes) },
+            resize: { _, _ in },
+            close: { [weak self] in self?.close() }))
+        let source = DispatchSource.makeReadSource(fileDescriptor: fd, queue: queue)
+        source.setEventHandler { [weak self] in self?.readAvailable() }
+        self.source = source
+        source.resume()
+    }
+
+    /// Closes the device; the pane ends with it. Idempotent.
+    func close() {
+        guard closed.withLock({ closed -> Bool in
+            defer { closed = true }
+            return !closed
+        }) else { return }
+        source?.cancel()
+        // Behind any read or write already queued.
+        queue.async { [fd] in Darwin.close(fd) }
+        pty?.disconnectRemote()
+    }
+
+    private func readAvailable() {
+        var buffer = [UInt8](repeating: 0, count: 1 << 16)
+        let count = read(fd, &buffer, buffer.count)
+        if count > 0 {
+            pty?.deliver(Array(buffer[..<count]))
+            return
+        }
+        if count < 0, errno == EAGAIN || errno == EINTR { return }
+        // EOF or ENXIO: the device is gone. Stop reading; the fd stays open
+        // until the pane closes so `close` has one thing to undo.
+        guard lost.withLock({ lost -> Bool in
+            defer { lost = true }
+            return !lost
+        }) else { return }
+        source?.cancel()
+        let reason = count < 0 ? String(cString: strerror(errno)) : "disconnected"
+        pty?.deliver(Array("\r\n\u{1B}[2m[\(SerialPort.name(of: settings.port)): \(reason)]\u{1B}[0m\r\n".utf8))
+    }
+
+    /// Runs on the read queue, so it never interleaves with a read; a
+    /// device that can't take more right now gets up to a second.
+    private func write(_ bytes: [UInt8]) {
+        queue.async { [weak self] in
+            guard let self, !self.lost.snapshot, !self.closed.snapshot else { return }
+            var offset = 0
+            while offset < bytes.count {
+                let written = bytes[offset...].withUnsafeBufferPointer { chunk in
+                    Darwin.write(self.fd, chunk.baseAddress, chunk.count)
+                }
+                if written > 0 {
+                    offset += written
+                } else if written < 0, errno == EAGAIN {
+                    var descriptor = pollfd(fd: self.fd, events: Int16(POLLOUT), revents: 0)
+                    guard poll(&descriptor, 1, 1000) > 0 else { return }
+                } else if written < 0, errno == EINTR {
+                    continue
+                } else {
+                    return
+                }
+            }
+        }
+    }
+}
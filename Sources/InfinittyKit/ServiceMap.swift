@@ -0,0 +1,325 @@
+import Foundation
+
+enum ServiceMapError: Error, Equatable, CustomStringConvertible {
+    case notInstalled(String)
+    case failed(String, String)
+    case noSuchEndpoint(String)
+
+    var description: String {
+        switch self {
+        case .notInstalled(let tool): return "\(tool) not installed"
+        case .failed(let tool, let reason): return "\(tool): \(reason)"
+        case .noSuchEndpoint(let id): return "no reachable endpoint \(id)"
+        }
+    }
+}
+
+/// Where an endpoint was found.
+enum ServiceSource: String, CaseIterable {
+    case local, docker, kubernetes
+}
+
+/// One dev endpoint: something listening, and where it can be reached from
+/// this Mac. `port` is nil when it can't be reached directly — a container
+/// port nobody published, or a ClusterIP service, which gets a
+/// `kubectl port-forward` line instead.
+struct ServiceEndpoint: Equatable {
+    let source: ServiceSource
+    /// Process, container, or `namespace/service`.
+    let name: String
+    var host = "localhost"
+    var port: Int?
+    /// The port inside the container or the service's own port.
+    var targetPort: Int?
+    var proto = "tcp"
+    var pid: Int32?
+    /// Image for containers, service type for Kubernetes, the bind address
+    /// for local listeners.
+    var detail: String?
+    var forward: String?
+
+    /// Stable across scans: `source:name:port/targetPort`, "-" for either
+    /// port when there is none.
+    var id: String {
+        "\(source.rawValue):\(name):\(port.map(String.init) ?? "-")/\(targetPort.map(String.init) ?? "-")"
+    }
+
+    /// What the browser pane opens; TCP only.
+    var url: String? {
+        guard let port, proto == "tcp" else { return nil }
+        let scheme = [443, 8443].contains(port) ? "https" : "http"
+        return "\(scheme)://\(host.contains(":") ? "[\(host)]" : host):\(port)"
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "source": source.rawValue, "name": name, "proto": proto]
+        if let port {
+            object["host"] = host
+            object["port"] = port
+        }
+        if let targetPort { object["targetPort"] = targetPort }
+        if let pid { object["pid"] = Int(pid) }
+        if let detail { object["detail"] = detail }
+        if let forward { object["forward"] = forward }
+        if let url { object["url"] = url }
+        return object
+    }
+}
+
+/// "What's reachable where": local TCP listeners from lsof, published
+/// Docker ports and Kubernetes services in one list. Tools that aren't
+/// installed or daemons that aren't running just leave their source out,
+/// with the reason in `sources`. Synchronous; each tool gets `timeout`.
+enum ServiceMap {
+    static let timeout: TimeInterval = 6
+
+    /// lsof truncates command names to nine characters; these are the
+    /// port proxies container runtimes leave on the host.
+    static let proxyCommands = ["com.docke", "vpnkit", "docker-pr", "OrbStack", "limactl", "gvproxy", "rancher-d"]
+
+    static func collect(
+        environment: [String: String] = ProcessInfo.processInfo.environment,
+        cancellation: CancellationToken? = nil
+    ) -> (endpoints: [ServiceEndpoint], sources: [String: String]) {
+        let results = LockedState<[ServiceSource: Result<[ServiceEndpoint], ServiceMapError>]>([:])
+        DispatchQueue.concurrentPerform(iterations: ServiceSource.allCases.count) { index in
+            let source = ServiceSource.allCases[index]
+            let result = query(source, environment: environment, cancellation: cancellation)
+            results.withLock { $0[source] = result }
+        }
+        let found = results.snapshot
+        func endpoints(_ source: ServiceSource) -> [ServiceEndpoint] {
+            (try? found[source]?.get()) ?? []
+        }
+        var sources: [String: String] = [:]
+        for (source, result) in found {
+            if case .failure(let error) = result {
+                sources[source.rawValue] = error.description
+            } else {
+                sources[source.rawValue] = "ok"
+            }
+        }
+        let merged = merge(
+            local: endpoints(.local), docker: endpoints(.docker), kubernetes: endpoints(.kubernetes))
+        return (merged, sources)
+    }
+
+    private static func query(
+        _ source: ServiceSource, environment: [String: String], cancellation: CancellationToken?
+    ) -> Result<[ServiceEndpoint], ServiceMapError> {
+        let tool: String
+        let arguments: [String]
+        switch source {
+        case .local:
+            tool = "lsof"
+            arguments = ["-nP", "-iTCP", "-sTCP:LISTEN", "-F", "pcn"]
+        case .docker:
+            tool = "docker"
+            arguments = ["ps", "--format", "{{json .}}"]
+        case .kubernetes:
+            tool = "kubectl"
+            arguments = ["get", "services", "--all-namespaces", "-o", "json", "--request-timeout=\(Int(timeout) - 1)s"]
+        }
+        guard let path = executable(tool, environment: environment) else { return .failure(.notInstalled(tool)) }
+        switch ProcessRunner.run(path, arguments, timeout: timeout, cancellation: cancellation) {
+        case .failure(let error):
+            return .failure(.failed(tool, error.description))
+        case .success(let output):
+            // lsof exits 1 when nothing is listening.
+            guard output.status == 0 || (source == .local && output.stderrText.isEmpty) else {
+                let reason = output.stderrText.components(separatedBy: .newlines).first ?? "exit \(output.status)"
+                return .failure(.failed(tool, reason))
+            }
+            switch source {
+            case .local: return .success(parseLsof(output.stdoutText))
+            case .docker: return .success(parseDockerPS(output.stdoutText))
+            case .kubernetes:
+                guard let services = parseKubernetesServices(output.stdout) else {
+                    return .failure(.failed(tool, "unexpected output"))
+                }
+                return .success(services)
+            }
+        }
+    }
+
+    /// Installed helpers: the usual install locations first, since GUI apps
+    /// launched from the Dock inherit a minimal PATH.
+    static func executable(
+        _ tool: String,
+        environment: [String: String] = ProcessInfo.processInfo.environment,
+        isExecutable: (String) -> Bool = { FileManager.default.isExecutableFile(atPath: $0) }
+    ) -> String? {
+        let home = environment["HOME"] ?? NSHomeDirectory()
+        var candidates = ["/usr/sbin/\(tool)", "/opt/homebrew/bin/\(tool)", "/usr/local/bin/\(tool)"]
+        if tool == "docker" || tool == "kubectl" {
+            candidates += [
+                "/Applications/Docker.app/Contents/Resources/bin/\(tool)",
+                "\(home)/.orbstack/bin/\(tool)", "\(home)/.rd/bin/\(tool)",
+            ]
+        }
+        candidates += (environment["PATH"] ?? "").split(separator: ":").map { "\($0)/\(tool)" }
+        return candidates.first(where: isExecutable)
+    }
+
+    // MARK: - Parsing
+
+    struct PortMapping: Equatable {
+        let host: String?
+        let hostPort: Int?
+        let containerPort: Int
+        let proto: String
+    }
+
+    /// `lsof -F pcn` output: a `p` line per process, then `c` and one `n`
+    /// per listening socket (`f` lines are ignored). IPv4 and IPv6
+    /// listeners on the same port become one endpoint.
+    static func parseLsof(_ text: String) -> [ServiceEndpoint] {
+        var found: [ServiceEndpoint] = []
+        var pid: Int32?
+        var command = ""
+        for line in text.components(separatedBy: .newlines) {
+            guard let field = line.first else { continue }
+            let value = String(line.dropFirst())
+            switch field {
+            case "p":
+                pid = Int32(value)
+                command = ""
+            case "c":
+                command = value
+            case "n":
+                guard let colon = value.lastIndex(of: ":"), let port = Int(value[value.index(after: colon)...])
+                else { continue }
+                let address = String(value[..<colon]).trimmingCharacters(in: CharacterSet(charactersIn: "[]"))
+                let loopback = ["127.0.0.1", "::1", "localhost"].contains(address)
+                if let index = found.firstIndex(where: { $0.pid == pid && $0.port == port }) {
+                    // Bound to every interface on either family wins over loopback.
+                    if address == "*" { found[index].detail = "*" }
+                    continue
+                }
+                var endpoint = ServiceEndpoint(source: .local, name: command, port: port, pid: pid)
+                endpoint.detail = address
+                if !loopback, address != "*" { endpoint.host = address }
+                found.append(endpoint)
+            default:
+                continue
+            }
+        }
+        return found.sorted { ($0.port ?? 0, $0.name) < ($1.port ?? 0, $1.name) }
+    }
+
+    /// `docker ps --format '{{json .}}'`: one JSON object per container.
+    static func parseDockerPS(_ text: String) -> [ServiceEndpoint] {
+        var found: [ServiceEndpoint] = []
+        for line in text.components(separatedBy: .newlines) where line.hasPrefix("{") {
+            guard let container = (try? JSONSerialization.jsonObject(with: Data(line.utf8))) as? [String: Any],
+                  let name = container["Names"] as? String
+            else { continue }
+            for mapping in parseDockerPorts(container["Ports"] as? String ?? "") {
+                if found.contains(where: {
+                    $0.name == name && $0.port == mapping.hostPort && $0.targetPort == mapping.containerPort
+                        && $0.proto == mapping.proto
+                }) { continue } // the IPv6 twin of a 0.0.0.0 binding
+                var endpoint = ServiceEndpoint(
+                    source: .docker, name: name, port: mapping.hostPort, targetPort: mapping.containerPort,
+                    proto: mapping.proto)
+                if let host = mapping.host, !["0.0.0.0", "::", "127.0.0.1", "::1"].contains(host) {
+                    endpoint.host = host
+                }
+                endpoint.detail = container["Image"] as? String
+                found.append(endpoint)
+            }
+        }
+        return found
+    }
+
+    /// Docker's port column: `0.0.0.0:8080->80/tcp, :::8080->80/tcp,
+    /// 6379/tcp`, ranges like `0.0.0.0:8000-8001->8000-8001/tcp` expanded.
+    /// Unpublished ports have no host port.
+    static func parseDockerPorts(_ text: String) -> [PortMapping] {
+        var found: [PortMapping] = []
+        func range(_ text: Substring) -> ClosedRange<Int>? {
+            let bounds = text.split(separator: "-").compactMap { Int($0) }
+            guard let first = bounds.first, let last = bounds.last, first <= last, last - first < 100 else {
+                return nil
+            }
+            return first...last
+        }
+        for entry in text.components(separatedBy: ",") {
+            let entry = entry.trimmingCharacters(in: .whitespaces)
+            let parts = entry.split(separator: "/", maxSplits: 1)
+            guard let target = parts.first else { continue }
+            let proto = parts.count > 1 ? String(parts[1]) : "tcp"
+            let sides = target.components(separatedBy: "->")
+            if sides.count == 2 {
+                guard let colon = sides[0].lastIndex(of: ":"),
+                      let published = range(sides[0][sides[0].index(after: colon)...]),
+                      let inside = range(Substring(sides[1])), published.count == inside.count
+                else { continue }
+                let host = String(sides[0][..<colon])
+                for (hostPort, containerPort) in zip(published, inside) {
+                    found.append(PortMapping(
+                        host: host.isEmpty ? nil : host, hostPort: hostPort, containerPort: containerPort,
+                        proto: proto))
+                }
+            } else if let inside = range(Substring(target)) {
+                for containerPort in inside {
+                    found.append(PortMapping(host: nil, hostPort: nil, containerPort: containerPort, proto: proto))
+                }
+            }
+        }
+        return found
+    }
+
+    /// `kubectl get services -o json`. LoadBalancer services are reachable
+    /// at their ingress, NodePorts on localhost (Docker Desktop, OrbStack
+    /// and Rancher Desktop route them there), ClusterIPs only through a
+    /// port-forward.
+    static func parseKubernetesServices(_ data: Data) -> [ServiceEndpoint]? {
+        guard let root = (try? JSONSerialization.jsonObject(with: data)) as? [String: Any],
+              let items = root["items"] as? [[String: Any]]
+        else { return nil }
+        var found: [ServiceEndpoint] = []
+        for item in items {
+            let metadata = item["metadata"] as? [String: Any] ?? [:]
+            let spec = item["spec"] as? [String: Any] ?? [:]
+            guard let service = metadata["name"] as? String else { continue }
+            let namespace = metadata["namespace"] as? String ?? "default"
+            let type = spec["type"] as? String ?? "ClusterIP"
+            let ingress = ((item["status"] as? [String: Any])?["loadBalancer"] as? [String: Any])?["ingress"]
+                as? [[String: Any]] ?? []
+            let ingressHost = ingress.first.flatMap { ($0["hostname"] as? String) ?? ($0["ip"] as? String) }
+            for port in spec["ports"] as? [[String: Any]] ?? [] {
+                guard let servicePort = port["port"] as? Int else { continue }
+                var endpoint = ServiceEndpoint(
+                    source: .kubernetes, name: "\(namespace)/\(service)", targetPort: servicePort,
+                    proto: (port["protocol"] as? String ?? "TCP").lowercased())
+                endpoint.detail = type
+                if type == "LoadBalancer", let ingressHost {
+                    endpoint.host = ingressHost
+                    endpoint.port = servicePort
+                } else if let nodePort = port["nodePort"] as? Int {
+                    endpoint.port = nodePort
+                } else if type != "ExternalName" {
+                    endpoint.forward = "kubectl port-forward -n \(namespace) svc/\(service) "
+                        + "\(servicePort):\(servicePort)"
+                }
+                found.append(endpoint)
+            }
+        }
+        return found
+    }
+
+    /// One list: containers and services first, then local listeners
+    /// minus the runtimes' port proxies for ports already shown as
+    /// published.
+    static func merge(
+        local: [ServiceEndpoint], docker: [ServiceEndpoint], kubernetes: [ServiceEndpoint]
+    ) -> [ServiceEndpoint] {
+        let forwarded = Set((docker + kubernetes).filter { $0.host == "localhost" }.compactMap(\.port))
+        let listeners = local.filter { endpoint in
+            guard let port = endpoint.port, forwarded.contains(port) else { return true }
+            return !proxyCommands.contains { endpoint.name.hasPrefix($0) }
+        }
+        return docker + kubernetes + listeners
+    }
+}
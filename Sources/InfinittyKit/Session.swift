@@ -11,7 +11,16 @@ final class TerminalSession: NSObject {
     let view: TerminalView
     let control: ControlServer
 
-    private(set) var title = "infinitty"
+    /// What a program last set with OSC 0/2, else the working directory's
+    /// name, so a shell that never reports a title still reads as where it
+    /// is.
+    var title: String {
+        reportedTitle ?? Self.titleName(forDirectory: reportedDirectory ?? workingDirectory) ?? "infinitty"
+    }
+    /// The OSC 0/2 title; nil until one is set, or after it is set empty.
+    private(set) var reportedTitle: String?
+    /// The OSC 0/1 icon name, which shells usually set alongside the title.
+    private(set) var iconName: String?
     var paneTitleOverride: String?
     /// Live agent-session name ("claude · titerm", later the real session
     /// title) while a recognized agent CLI runs in this pane. Cleared when the
@@ -21,6 +30,15 @@ final class TerminalSession: NSObject {
     /// icon. Set via the pane socket (`todos <json>`), the app socket
     /// (`todos <id> <json>`), or the infinitty_todos MCP tool.
     private(set) var todos: [PaneTodo] = []
+    /// Cloud profiles exported into this pane's shell by `cloud-set-profile`,
+    /// shown in the pane header. Main thread only.
+    private(set) var cloudProfiles: [CloudProvider: CloudProfile] = [:]
+
+    func setCloudProfile(_ profile: CloudProfile?, for provider: CloudProvider) {
+        cloudProfiles[provider] = profile
+        view.paneHeader.setCloudProfiles(CloudProvider.allCases.compactMap { cloudProfiles[$0] })
+    }
+
     /// Broadcast hook for todo changes (wired by the app delegate).
     var onTodosChanged: ((TerminalSession) -> Void)?
 
@@ -37,19 +55,55 @@ final class TerminalSession: NSObject {
     /// Shell starting directory; set before launch() (folder launches, socket
     /// new-tab/new-window with a path).
     var workingDirectory: String?
+    /// How the shell starts; set before `launch()`. Nil is the login shell.
+    var profile: TerminalProfile?
+    /// This shell's startup commands and which have finished; nil when it
+    /// had none. Main thread.
+    var startup: StartupRun?
+    /// Set on top of the profile's environment, for a duplicated pane.
+    var environment: [String: String] = [:]
+    /// The pane's own text size and line height; the app re-derives the
+    /// renderer when it changes.
+    var display = PaneDisplay()
+    /// Last cwd the shell announced via OSC 7. Locked: `currentDirectory()`
+    /// is also called from background queues.
+    private let reported = LockedState<String?>(nil)
+    var reportedDirectory: String? { reported.snapshot }
+    private let flooding = LockedState(false)
+    /// Whether the child is flooding output right now (`OutputFloodMeter`),
+    /// so per-row work can skip ahead instead of falling behind.
+    var isFlooding: Bool { flooding.snapshot }
+    /// Observers of raw PTY output (a live share, an upgrade's progress
+    /// parser), by key. Read on the PTY thread for every chunk, so a pane
+    /// nobody watches pays one lock and no copy.
+    private let outputTaps = LockedState([String: (Data) -> Void]())
+    /// Best guess at how far the running command has got, read off its
+    /// output by the app's `progress` tap.
+    let progress = ProgressDetector()
+    /// Set while the pane is asking for a password, by the app's
+    /// `secure-input` tap.
+    let secureInput = SecureInputDetector()
     var petAnimator: PetAnimator?
     private(set) var processTracker: ForegroundProcessTracker?
     private var lastForegroundPokeMs: Int64 = 0
     private var launched = false
     private var torndown = false
+    /// The idle policy ended this pane's shell; the scrollback stays and
+    /// `resume()` starts a new one. Main thread.
+    private(set) var isHibernated = false
 
     var onExited: ((TerminalSession) -> Void)?
+    /// Offered the shell before it's spawned here, for the PTY daemon to
+    /// run instead; returns the shell's pid when it took it.
+    var launchRemotely: ((TerminalSession) -> pid_t?)?
     var onTitleChanged: ((TerminalSession) -> Void)?
+    var onOutputFlood: ((TerminalSession, OutputFlood) -> Void)?
 
     init(config: AppConfig, scale: CGFloat) {
         TerminalSession.nextID += 1
         id = TerminalSession.nextID
-        terminal = Terminal(cols: 120, rows: 32)
+        terminal = Terminal(cols: 120, rows: 32, scrollback: config.scrollbackLines)
+        if config.scrollbackSpill { terminal.spillScrollback(to: ScrollbackSpill(pane: id)) }
         pty = PTY()
         renderer = Renderer(config: config, scale: scale)
         renderer.debugLabel = "pane-\(id)"
@@ -69,26 +123,66 @@ final class TerminalSession: NSObject {
         // whole life, so these callbacks never need to keep each other alive.
         // Strong captures here form pty<->terminal and terminal->renderer
         // cycles that leak the entire engine on every pane close.
-        pty.onData = { [weak terminal] buf, count in terminal?.feed(buf, count) }
+        pty.onData = { [weak terminal, weak self] buf, count in
+            terminal?.feed(buf, count)
+            guard let taps = self?.outputTaps.snapshot, !taps.isEmpty else { return }
+            let data = Data(bytes: buf, count: count)
+            for tap in taps.values { tap(data) }
+        }
         pty.onEOF = { [weak self] in
             DispatchQueue.main.async {
                 guard let self else { return }
+                guard !self.isHibernated else {
+                    self.pty.deliver(Array(Self.hibernatedNote.utf8))
+                    return
+                }
                 self.onExited?(self)
             }
         }
-        terminal.onOutput = { [weak pty] bytes in pty?.write(bytes) }
+        pty.onFlood = { [weak self] flood in
+            self?.flooding.withLock { $0 = flood == .started }
+            DispatchQueue.main.async {
+                guard let self else { return }
+                self.onOutputFlood?(self, flood)
+            }
+        }
+        terminal.onOutput = { [weak pty] bytes in pty?.reply(bytes) }
         terminal.onChange = { [weak renderer] in renderer?.poke() }
         terminal.onTitle = { [weak self] t in
             DispatchQueue.main.async {
                 guard let self else { return }
-                self.title = t.isEmpty ? "infinitty" : t
+                self.reportedTitle = t.isEmpty ? nil : t
                 self.onTitleChanged?(self)
             }
         }
+        terminal.onIconName = { [weak self] name in
+            DispatchQueue.main.async {
+                guard let self else { return }
+                self.iconName = name.isEmpty ? nil : name
+            }
+        }
+        terminal.onDirectory = { [weak self] dir in
+            DispatchQueue.main.async {
+                guard let self else { return }
+                let changed = self.reported.withLock { current -> Bool in
+                    guard current != dir else { return false }
+                    current = dir
+                    return true
+                }
+                guard changed else { return }
+                if self.reportedTitle == nil { self.onTitleChanged?(self) }
+                // Same notification the 2s process poll posts, so followers
+                // (the Files pane) move on the prompt instead of the poll.
+                NotificationCenter.default.post(
+                    name: ForegroundProcessTracker.cwdDidChangeNotification,
+                    object: self, userInfo: [ForegroundProcessTracker.cwdKey: dir])
+            }
+        }
         terminal.onBell = { [weak self] in
             // AppKit audio + pet animator must run on main — never the PTY thread.
             DispatchQueue.main.async {
-                NSSound.beep()
+                // Do Not Disturb mutes the beep; the pet's silent jolt stays.
+                if !DoNotDisturb.shared.isActive { NSSound.beep() }
                 self?.petAnimator?.bell()
             }
         }
@@ -140,7 +234,10 @@ final class TerminalSession: NSObject {
             self?.terminal.refreshHint()
         }
         hintEngine = engine
-        terminal.setHintProvider { [weak engine] input in engine?.suggest(input) }
+        // No suggestions, local or smart, while a password is being typed.
+        terminal.setHintProvider { [weak engine, secureInput] input in
+            secureInput.isActive ? nil : engine?.suggest(input)
+        }
     }
 
     /// Call once the view is inside a window (display link needs it).
@@ -149,9 +246,18 @@ final class TerminalSession: NSObject {
         launched = true
         renderer.attach(view: view, layer: view.metalLayer, terminal: terminal)
         view.window?.layoutIfNeeded()
+        // A remote pane (tmux) already has its far end; there is no shell.
+        guard !pty.isRemote else { return }
+        if workingDirectory == nil { workingDirectory = profile?.cwd }
+        if let pid = launchRemotely?(self) {
+            let tracker = ForegroundProcessTracker(shellPid: pid)
+            tracker.start()
+            processTracker = tracker
+            sendStartupCommands()
+            return
+        }
         let ok = pty.spawn(
-            cols: terminal.cols, rows: terminal.rows,
-            socketPath: control.path, cwd: workingDirectory)
+            cols: terminal.cols, rows: terminal.rows, launch: shellLaunch, cwd: workingDirectory)
         guard ok else {
             // Don't crash the whole app on process-table exhaustion; surface
             // a modal and tear the pane down cleanly.
@@ -178,22 +284,130 @@ final class TerminalSession: NSObject {
             tracker.start()
             processTracker = tracker
         }
+        sendStartupCommands()
+    }
+
+    /// The shell's pid, whether this process or the PTY daemon spawned it;
+    /// nil for a tmux pane or while hibernated.
+    var shellPID: pid_t? {
+        guard !isHibernated else { return nil }
+        if let tracker = processTracker { return tracker.shellPid }
+        return pty.pid > 0 && !pty.isRemote ? pty.pid : nil
+    }
+
+    /// The program, arguments and environment this pane's shell starts with.
+    var shellLaunch: ShellLaunch {
+        ShellLaunch(profile: profile, overrides: environment, socketPath: control.path, pane: id)
+    }
+
+    /// The profile's startup lines, then the workspace's, go in as
+    /// typeahead: the shell reads them once its rc files have run, as if
+    /// typed at the first prompt. A dim line marks where they start.
+    private func sendStartupCommands() {
+        let workspace = WorkspaceNotes.workspace(for: workingDirectory ?? NSHomeDirectory())
+        let run = StartupRun(
+            profile: profile?.startup ?? [], workspace: WorkspaceStartup.shared.commands(for: workspace))
+        startup = run.isEmpty ? nil : run
+        guard !run.isEmpty else { return }
+        pty.deliver(Array(run.marker.utf8))
+        pty.write(run.input)
+    }
+
+    private static let hibernatedNote =
+        "\r\n\u{1B}[2m[shell ended while idle \u{2014} press a key to start a new one]\u{1B}[0m\r\n"
+
+    /// Ends the shell but keeps the pane, for the idle policy. Only a shell
+    /// this process spawned can be hibernated: a daemon's or tmux's pane
+    /// isn't ours to end. Returns false when it can't be.
+    @discardableResult
+    func hibernate() -> Bool {
+        guard launched, !torndown, !isHibernated, !pty.isRemote, pty.fd >= 0, pty.pid > 0 else { return false }
+        workingDirectory = currentDirectory() ?? workingDirectory
+        isHibernated = true
+        processTracker?.stop()
+        processTracker = nil
+        kill(pty.pid, SIGHUP)
+        return true
+    }
+
+    /// Starts a new shell in a hibernated pane, where the old one was.
+    /// Waits for the old shell's EOF; until then this does nothing.
+    @discardableResult
+    func resume() -> Bool {
+        guard isHibernated, !torndown, pty.fd < 0 else { return false }
+        isHibernated = false
+        reported.withLock { $0 = nil }
+        guard pty.spawn(cols: terminal.cols, rows: terminal.rows, launch: shellLaunch, cwd: workingDirectory) else {
+            onExited?(self)
+            return false
+        }
+        if pty.pid > 0 {
+            let tracker = ForegroundProcessTracker(shellPid: pty.pid)
+            tracker.start()
+            processTracker = tracker
+        }
+        sendStartupCommands()
+        return true
     }
 
     /// Ask the shell to exit; the EOF path fires onExited for teardown.
     func terminate() {
+        if isHibernated {
+            // With the old shell's EOF still to come, that EOF ends the pane.
+            isHibernated = false
+            if pty.fd < 0 {
+                onExited?(self)
+                return
+            }
+        }
+        if pty.isRemote { pty.closeRemote() }
         if pty.pid > 0 { kill(pty.pid, SIGHUP) }
     }
 
-    /// The pane's live working directory: the foreground process's cwd (the
-    /// shell itself at a prompt), probed on demand so it's fresh even between
-    /// the tracker's 2s polls. Falls back to the launch directory.
+    /// The pane's live working directory. A foreground tool's own cwd wins
+    /// while one runs; at the prompt the shell's OSC 7 report is used when
+    /// it sends one, else the shell's cwd is probed. Probes happen on demand
+    /// so the answer is fresh between the tracker's 2s polls. Falls back to
+    /// the launch directory.
     func currentDirectory() -> String? {
-        let pid = processTracker?.current?.pid ?? pty.pid
-        if pid > 1, let dir = ForegroundProcessTracker.directory(of: pid) {
-            return dir
+        directoryAndSource()?.path
+    }
+
+    /// Which of `currentDirectory()`'s sources answered.
+    enum DirectorySource: String {
+        case process, osc7, probe, launch
+    }
+
+    func directoryAndSource() -> (path: String, source: DirectorySource)? {
+        if let pid = processTracker?.current?.pid, pid != pty.pid, pid > 1,
+           let dir = ForegroundProcessTracker.directory(of: pid) {
+            return (dir, .process)
         }
-        return workingDirectory
+        if let reportedDirectory { return (reportedDirectory, .osc7) }
+        if pty.pid > 1, let dir = ForegroundProcessTracker.directory(of: pty.pid) {
+            return (dir, .probe)
+        }
+        return workingDirectory.map { ($0, .launch) }
+    }
+
+    /// A directory as a title: its last component, or `~` for home.
+    static func titleName(forDirectory path: String?, home: String = NSHomeDirectory()) -> String? {
+        guard let path, !path.isEmpty else { return nil }
+        let trimmed = path.count > 1 && path.hasSuffix("/") ? String(path.dropLast()) : path
+        if trimmed == home { return "~" }
+        return (trimmed as NSString).lastPathComponent
+    }
+
+    /// Start (or with nil, stop) copying PTY output to the tap under `key`,
+    /// called on the PTY thread after the terminal has parsed each chunk.
+    func setOutputTap(_ key: String, _ tap: ((Data) -> Void)?) {
+        outputTaps.withLock { $0[key] = tap }
+    }
+
+    /// Starts or stops writing this pane's evicted scrollback to disk.
+    func setScrollbackSpill(_ enabled: Bool) {
+        guard enabled != terminal.spillsScrollback else { return }
+        terminal.spillScrollback(to: enabled ? ScrollbackSpill(pane: id) : nil)
     }
 
     /// Release threads and the socket. Idempotent.
@@ -205,7 +419,9 @@ final class TerminalSession: NSObject {
         processTracker?.stop()
         processTracker = nil
         control.stop()
+        outputTaps.withLock { $0.removeAll() }
         terminal.setHintProvider(nil)
+        terminal.spillScrollback(to: nil)
         renderer.shutdown()
         if pty.pid > 0 { kill(pty.pid, SIGHUP) }
     }
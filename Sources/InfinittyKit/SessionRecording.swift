@@ -0,0 +1,186 @@
+import Foundation
+
+enum SessionRecordingError: Error, Equatable, CustomStringConvertible {
+    case alreadyRecording
+    case notRecording
+    case unwritable(String)
+    case invalidCast(String)
+
+    var description: String {
+        switch self {
+        case .alreadyRecording: return "the pane is already being recorded"
+        case .notRecording: return "the pane is not being recorded"
+        case .unwritable(let path): return "can't write \(path)"
+        case .invalidCast(let reason): return "not an asciicast v2 file: \(reason)"
+        }
+    }
+}
+
+/// One asciicast v2 event: seconds since the recording began, its kind
+/// (`o` output, `i` input, `r` resize as "COLSxROWS", `m` marker) and data.
+struct AsciicastEvent: Equatable {
+    let time: Double
+    let kind: String
+    let data: String
+}
+
+/// An asciicast v2 recording: a JSON header line followed by one JSON
+/// array per event (https://docs.asciinema.org/manual/asciicast/v2/).
+struct Asciicast: Equatable {
+    var width: Int
+    var height: Int
+    var timestamp: Int?
+    var title: String?
+    var events: [AsciicastEvent] = []
+
+    var duration: Double { events.last?.time ?? 0 }
+
+    static func parse(_ text: String) -> Result<Asciicast, SessionRecordingError> {
+        var lines = text.split(whereSeparator: \.isNewline).makeIterator()
+        guard let first = lines.next(),
+              let header = try? JSONSerialization.jsonObject(with: Data(first.utf8)) as? [String: Any]
+        else { return .failure(.invalidCast("no header line")) }
+        guard header["version"] as? Int == 2 else { return .failure(.invalidCast("version is not 2")) }
+        guard let width = header["width"] as? Int, let height = header["height"] as? Int else {
+            return .failure(.invalidCast("header has no width and height"))
+        }
+        var cast = Asciicast(
+            width: width, height: height, timestamp: header["timestamp"] as? Int, title: header["title"] as? String)
+        var number = 1
+        while let line = lines.next() {
+            number += 1
+            guard let array = try? JSONSerialization.jsonObject(with: Data(line.utf8)) as? [Any], array.count == 3,
+                  let time = (array[0] as? NSNumber)?.doubleValue,
+                  let kind = array[1] as? String, let data = array[2] as? String
+            else { return .failure(.invalidCast("line \(number) is not [time, kind, data]")) }
+            cast.events.append(AsciicastEvent(time: time, kind: kind, data: data))
+        }
+        return .success(cast)
+    }
+
+    /// When each event plays, in seconds from the start: recorded times
+    /// divided by `speed`, with pauses longer than `maxIdle` cut to it.
+    static func schedule(_ events: [AsciicastEvent], speed: Double = 1, maxIdle: Double? = nil) -> [Double] {
+        var played = 0.0
+        var previous = 0.0
+        return events.map { event in
+            var gap = max(event.time - previous, 0)
+            if let maxIdle { gap = min(gap, maxIdle) }
+            previous = event.time
+            played += gap / max(speed, 0.01)
+            return played
+        }
+    }
+}
+
+/// Appends a pane's output to an asciicast v2 file as it arrives. Fed from
+/// the PTY thread; output that ends inside a UTF-8 sequence is held back
+/// until the rest comes, since each event must be a whole string.
+final class AsciicastRecorder {
+    static var defaultDirectory: URL { AppSupport.url("recordings", isDirectory: true) }
+
+    let path: String
+    let startedAt: Date
+
+    private struct State {
+        var handle: FileHandle?
+        var pending = Data()
+        var cols: Int
+        var rows: Int
+        var events = 0
+        var lastTime = 0.0
+    }
+
+    private let state: LockedState<State>
+
+    init(
+        path: String, cols: Int, rows: Int, title: String? = nil,
+        environment: [String: String] = [:], now: Date = Date()
+    ) throws {
+        let url = URL(fileURLWithPath: path)
+        try? FileManager.default.createDirectory(
+            at: url.deletingLastPathComponent(), withIntermediateDirectories: true)
+        var header: [String: Any] = [
+            "version": 2, "width": cols, "height": rows, "timestamp": Int(now.timeIntervalSince1970),
+            "env": environment.filter { ["SHELL", "TERM"].contains($0.key) },
+        ]
+        if let title { header["title"] = title }
+        guard let line = try? JSONSerialization.data(withJSONObject: header, options: [.sortedKeys]),
+              FileManager.default.createFile(atPath: path, contents: line + Data("\n".utf8)),
+              let handle = FileHandle(forWritingAtPath: path)
+        else { throw SessionRecordingError.unwritable(path) }
+        handle.seekToEndOfFile()
+        self.path = path
+        self.startedAt = now
+        self.state = LockedState(State(handle: handle, cols: cols, rows: rows))
+    }
+
+    /// Records `data` as output, preceded by a resize event when the grid
+    /// is no longer `cols` x `rows` as last seen.
+    func record(_ data: Data, cols: Int, rows: Int, at now: Date = Date()) {
+        let time = (now.timeIntervalSince(startedAt) * 1_000_000).rounded() / 1_000_000
+        state.withLock { state in
+            guard let handle = state.handle else { return }
+            var out = Data()
+            if cols != state.cols || rows != state.rows {
+                state.cols = cols
+                state.rows = rows
+                out += Self.line(AsciicastEvent(time: time, kind: "r", data: "\(cols)x\(rows)"))
+                state.events += 1
+            }
+            state.pending += data
+            let complete = Self.completeUTF8Length(state.pending)
+            if complete > 0 {
+                let text = String(decoding: state.pending.prefix(complete), as: UTF8.self)
+                state.pending.removeFirst(complete)
+                out += Self.line(AsciicastEvent(time: time, kind: "o", data: text))
+                state.events += 1
+            }
+            state.lastTime = time
+            if !out.isEmpty { handle.write(out) }
+        }
+    }
+
+    /// Closes the file; later output is dropped.
+    func stop() -> [String: Any] {
+        state.withLock { state in
+            if let handle = state.handle {
+                if !state.pending.isEmpty {
+                    let text = String(decoding: state.pending, as: UTF8.self)
+                    handle.write(Self.line(AsciicastEvent(time: state.lastTime, kind: "o", data: text)))
+                    state.events += 1
+                }
+                handle.closeFile()
+                state.handle = nil
+            }
+            return ["path": path, "events": state.events, "duration": state.lastTime]
+        }
+    }
+
+    var summary: [String: Any] {
+        let state = self.state.snapshot
+        return [
+            "path": path, "events": state.events, "startedAt": startedAt.timeIntervalSince1970,
+            "recording": state.handle != nil,
+        ]
+    }
+
+    static func line(_ event: AsciicastEvent) -> Data {
+        let data = (try? JSONSerialization.data(
+            withJSONObject: [event.time, event.kind, event.data], options: [.withoutEscapingSlashes])) ?? Data()
+        return data + Data("\n".utf8)
+    }
+
+    /// How many leading bytes of `data` end on a UTF-8 sequence boundary.
+    static func completeUTF8Length(_ data: Data) -> Int {
+        let bytes = [UInt8](data.suffix(4))
+        let base = data.count - bytes.count
+        for index in bytes.indices.reversed() {
+            let byte = bytes[index]
+            if byte & 0xC0 == 0x80 { continue } // continuation: keep looking for its lead
+            let needed = byte >= 0xF0 ? 4 : byte >= 0xE0 ? 3 : byte >= 0xC0 ? 2 : 1
+            return bytes.count - index >= needed ? data.count : base + index
+        }
+        return data.count
+    }
+}
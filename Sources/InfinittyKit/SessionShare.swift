@@ -0,0 +1,743 @@
+import CryptoKit
+import Foundation
+
+/// The secret behind a join code: 32 random bytes, written as unpadded
+/// base64url. The relay only learns `room`, a hash it uses to pair the host
+/// with its guests; the message key is derived separately and never leaves
+/// either end, so the relay forwards frames it cannot read or forge.
+struct ShareCode: Equatable {
+    let secret: Data
+
+    static func generate() -> ShareCode {
+        ShareCode(secret: SymmetricKey(size: .bits256).withUnsafeBytes { Data($0) })
+    }
+
+    private init(secret: Data) {
+        self.secret = secret
+    }
+
+    /// Parses a code as typed or pasted; surrounding whitespace is ignored.
+    init?(_ string: String) {
+        var base64 = string.trimmingCharacters(in: .whitespacesAndNewlines)
+            .replacingOccurrences(of: "-", with: "+")
+            .replacingOccurrences(of: "_", with: "/")
+        base64 += String(repeating: "=", count: (4 - base64.count % 4) % 4)
+        guard let secret = Data(base64Encoded: base64), secret.count == 32 else { return nil }
+        self.secret = secret
+    }
+
+    var string: String {
+        secret.base64EncodedString()
+            .replacingOccurrences(of: "+", with: "-")
+            .replacingOccurrences(of: "/", with: "_")
+            .replacingOccurrences(of: "=", with: "")
+    }
+
+    var room: String {
+        let digest = SHA256.hash(data: Data("infinitty-share-room".utf8) + secret)
+        return digest.prefix(16).map { String(format: "%02x", $0) }.joined()
+    }
+
+    var key: SymmetricKey {
+        HKDF<SHA256>.deriveKey(
+            inputKeyMaterial: SymmetricKey(data: secret),
+            info: Data("infinitty-share-key".utf8),
+            outputByteCount: 32)
+    }
+
+    /// The key the host and one guest share and no other holder of the code
+    /// can derive: their key agreement, salted with the secret.
+    func participantKey(
+        _ privateKey: Curve25519.KeyAgreement.PrivateKey, _ publicKey: Curve25519.KeyAgreement.PublicKey
+    ) -> SymmetricKey? {
+        guard let shared = try? privateKey.sharedSecretFromKeyAgreement(with: publicKey) else { return nil }
+        return shared.hkdfDerivedSymmetricKey(
+            using: SHA256.self, salt: secret, sharedInfo: Data("infinitty-share-participant".utf8),
+            outputByteCount: 32)
+    }
+}
+
+enum SessionShareError: Error, Equatable, CustomStringConvertible {
+    case disabled
+    case noRelay
+    case badRelay(String)
+    case alreadyShared(pane: Int)
+    case badCode
+    case noParticipant(pane: Int, String)
+    case notJoined(String)
+    case readOnly(String)
+
+    var description: String {
+        switch self {
+        case .disabled: return "session sharing is off (set session-sharing = true)"
+        case .noRelay: return "no share-relay configured"
+        case .badRelay(let relay): return "share-relay must be a ws:// or wss:// URL, got \(relay)"
+        case .alreadyShared(let pane): return "pane \(pane) is already shared"
+        case .badCode: return "not a share code"
+        case .noParticipant(let pane, let id): return "no participant \(id) in pane \(pane)'s share"
+        case .notJoined(let id): return "no joined share \(id)"
+        case .readOnly(let id): return "\(id) is read-only until the host grants input"
+        }
+    }
+}
+
+/// What host and guests say to each other inside the encrypted channel.
+/// `join`, `welcome` and `output` travel under the code's key; the rest
+/// under the key the host and that one guest agreed on.
+enum ShareMessage: Equatable {
+    /// A guest introducing itself with its public key, whose hash is its id.
+    case join(name: String, key: Data)
+    /// The host's public key, for the guest (`to`) that joined.
+    case welcome(to: String, key: Data)
+    /// A guest's heartbeat.
+    case hello(name: String)
+    case bye
+    /// Keystrokes from a guest; the host drops them unless it granted
+    /// that guest read-write.
+    case input(Data)
+    /// Raw PTY output, exactly as the host's terminal parsed it.
+    case output(Data)
+    /// The visible screen for a guest that just joined (`to`), so it starts
+    /// from the current picture instead of a blank one.
+    case snapshot(to: String, cols: Int, rows: Int, text: String)
+    /// The guest's grant.
+    case mode(to: String, readWrite: Bool)
+
+    fileprivate var fields: [String: Any] {
+        switch self {
+        case .join(let name, let key): return ["type": "join", "name": name, "key": key.base64EncodedString()]
+        case .welcome(let to, let key): return ["type": "welcome", "to": to, "key": key.base64EncodedString()]
+        case .hello(let name): return ["type": "hello", "name": name]
+        case .bye: return ["type": "bye"]
+        case .input(let data): return ["type": "input", "data": data.base64EncodedString()]
+        case .output(let data): return ["type": "output", "data": data.base64EncodedString()]
+        case .snapshot(let to, let cols, let rows, let text):
+            return ["type": "snapshot", "to": to, "cols": cols, "rows": rows, "text": text]
+        case .mode(let to, let readWrite): return ["type": "mode", "to": to, "rw": readWrite]
+        }
+    }
+
+    fileprivate init?(fields: [String: Any]) {
+        switch fields["type"] as? String {
+        case "join":
+            guard let name = fields["name"] as? String, let encoded = fields["key"] as? String,
+                  let key = Data(base64Encoded: encoded) else { return nil }
+            self = .join(name: name, key: key)
+        case "welcome":
+            guard let to = fields["to"] as? String, let encoded = fields["key"] as? String,
+                  let key = Data(base64Encoded: encoded) else { return nil }
+            self = .welcome(to: to, key: key)
+        case "hello":
+            guard let name = fields["name"] as? String else { return nil }
+            self = .hello(name: name)
+        case "bye":
+            self = .bye
+        case "input", "output":
+            guard let encoded = fields["data"] as? String,
+                  let data = Data(base64Encoded: encoded) else { return nil }
+            self = fields["type"] as? String == "input" ? .input(data) : .output(data)
+        case "snapshot":
+            guard let to = fields["to"] as? String, let cols = fields["cols"] as? Int,
+                  let rows = fields["rows"] as? Int, let text = fields["text"] as? String
+            else { return nil }
+            self = .snapshot(to: to, cols: cols, rows: rows, text: text)
+        case "mode":
+            guard let to = fields["to"] as? String, let readWrite = fields["rw"] as? Bool else { return nil }
+            self = .mode(to: to, readWrite: readWrite)
+        default:
+            return nil
+        }
+    }
+}
+
+/// Seals and opens frames for one end of a share, under the code's key or
+/// one participant's. Every frame names its sender and carries a per-sender
+/// sequence number inside the ciphertext; frames that go backwards are
+/// dropped, so a relay replaying captured input can't type into a
+/// read-write pane a second time.
+final class ShareChannel {
+    static let hostID = "host"
+
+    let sender: String
+    private let key: SymmetricKey
+    private let counters = LockedState((sent: 0, seen: [String: Int]()))
+
+    init(key: SymmetricKey, sender: String) {
+        self.key = key
+        self.sender = sender
+    }
+
+    convenience init(code: ShareCode, sender: String) {
+        self.init(key: code.key, sender: sender)
+    }
+
+    /// A guest's id: a hash of its public key, so claiming an id means
+    /// holding the private key behind it.
+    static func participantID(_ key: Curve25519.KeyAgreement.PublicKey) -> String {
+        SHA256.hash(data: key.rawRepresentation).prefix(16).map { String(format: "%02x", $0) }.joined()
+    }
+
+    func seal(_ message: ShareMessage) -> Data? {
+        var fields = message.fields
+        fields["from"] = sender
+        fields["seq"] = counters.withLock { counters -> Int in
+            counters.sent += 1
+            return counters.sent
+        }
+        guard let plain = try? JSONSerialization.data(withJSONObject: fields) else { return nil }
+        return try? ChaChaPoly.seal(plain, using: key).combined
+    }
+
+    /// The sender and message of a frame from someone else, or nil for a
+    /// frame that fails to authenticate, echoes our own, or is a replay.
+    func open(_ frame: Data) -> (from: String, message: ShareMessage)? {
+        guard let box = try? ChaChaPoly.SealedBox(combined: frame),
+              let plain = try? ChaChaPoly.open(box, using: key),
+              let fields = try? JSONSerialization.jsonObject(with: plain) as? [String: Any],
+              let from = fields["from"] as? String, from != sender,
+              let seq = fields["seq"] as? Int,
+              let message = ShareMessage(fields: fields)
+        else { return nil }
+        let fresh = counters.withLock { counters -> Bool in
+            guard seq > counters.seen[from, default: 0] else { return false }
+            counters.seen[from] = seq
+            return true
+        }
+        return fresh ? (from, message) : nil
+    }
+}
+
+/// A connection to the relay. The relay is deliberately dumb: it forwards
+/// every binary frame from the host to all guests in the room and every
+/// guest frame to the host.
+protocol ShareTransport: AnyObject {
+    var onReceive: ((Data) -> Void)? { get set }
+    /// The connection dropped; not called after `close()`.
+    var onClose: ((String) -> Void)? { get set }
+    func connect()
+    func send(_ frame: Data)
+    func close()
+}
+
+final class WebSocketShareTransport: ShareTransport {
+    var onReceive: ((Data) -> Void)?
+    var onClose: ((String) -> Void)?
+    private let task: URLSessionWebSocketTask
+    private let closed = LockedState(false)
+
+    init(url: URL) {
+        task = URLSession.shared.webSocketTask(with: url)
+    }
+
+    /// `<relay>/<room>?role=host|guest`, or nil when `relay` isn't a
+    /// WebSocket URL.
+    static func url(relay: String, room: String, role: String) -> URL? {
+        guard var components = URLComponents(string: relay),
+              let scheme = components.scheme?.lowercased(), scheme == "ws" || scheme == "wss",
+              components.host != nil
+        else { return nil }
+        components.path = (components.path.hasSuffix("/") ? components.path : components.path + "/") + room
+        components.queryItems = [URLQueryItem(name: "role", value: role)]
+        return components.url
+    }
+
+    func connect() {
+        task.resume()
+        receive()
+    }
+
+    func send(_ frame: Data) {
+        task.send(.data(frame)) { [weak self] error in
+            if let error { self?.finish(error.localizedDescription) }
+        }
+    }
+
+    func close() {
+        guard markClosed() else { return }
+        task.cancel(with: .goingAway, reason: nil)
+    }
+
+    private func receive() {
+        task.receive { [weak self] result in
+            guard let self else { return }
+            switch result {
+            case .success(let message):
+                // Text frames are relay chatter; everything we send is binary.
+                if case .data(let frame) = message { self.onReceive?(frame) }
+                self.receive()
+            case .failure(let error):
+                self.finish(error.localizedDescription)
+            }
+        }
+    }
+
+    private func finish(_ reason: String) {
+        guard markClosed() else { return }
+        task.cancel()
+        onClose?(reason)
+    }
+
+    private func markClosed() -> Bool {
+        closed.withLock { closed in
+            guard !closed else { return false }
+            closed = true
+            return true
+        }
+    }
+}
+
+/// The sharing end of one pane. Output streams to guests only while someone
+/// is watching; a guest that joins gets a snapshot of the screen first and
+/// watches read-only until the host grants it input. Everything but the
+/// join and the output goes under that guest's own key, so one guest can
+/// neither read another's grant nor speak for it. Guests re-send `hello`
+/// as a heartbeat, and one silent for `participantTimeout` is dropped as if
+/// it had said `bye`, grant and all.
+final class SessionShareHost {
+    struct Participant: Equatable {
+        let id: String
+        let name: String
+        fileprivate(set) var lastSeen: Date
+        fileprivate(set) var readWrite = false
+    }
+
+    static let maxParticipants = 8
+    static let participantTimeout: TimeInterval = 60
+
+    let pane: Int
+    let code: ShareCode
+    let relay: String
+    /// Keystrokes from guests granted read-write.
+    var onInput: ((Data) -> Void)?
+    /// A participant joined (`true`) or left (`false`).
+    var onPresence: ((Participant, Bool) -> Void)?
+    /// The relay connection dropped; the share is over.
+    var onEnded: ((String) -> Void)?
+    /// The current screen, for a joining guest's snapshot.
+    var screen: (() -> (cols: Int, rows: Int, text: String))?
+
+    private let transport: ShareTransport
+    private let channel: ShareChannel
+    private let privateKey = Curve25519.KeyAgreement.PrivateKey()
+    private let state = LockedState((participants: [Participant](), channels: [String: ShareChannel](), stopped: false))
+    private var sweep: DispatchSourceTimer?
+
+    init(pane: Int, code: ShareCode, relay: String, transport: ShareTransport) {
+        self.pane = pane
+        self.code = code
+        self.relay = relay
+        self.transport = transport
+        channel = ShareChannel(code: code, sender: ShareChannel.hostID)
+    }
+
+    var participants: [Participant] { state.snapshot.participants }
+
+    func start() {
+        transport.onReceive = { [weak self] frame in self?.receive(frame) }
+        transport.onClose = { [weak self] reason in
+            guard let self, self.markStopped() else { return }
+            self.sweep?.cancel()
+            self.onEnded?(reason)
+        }
+        let timer = DispatchSource.makeTimerSource(queue: .global(qos: .utility))
+        timer.schedule(deadline: .now() + 15, repeating: 15)
+        timer.setEventHandler { [weak self] in self?.expireSilent(now: Date()) }
+        timer.resume()
+        sweep = timer
+        transport.connect()
+    }
+
+    func publishOutput(_ data: Data) {
+        let watched = state.withLock { !$0.stopped && !$0.participants.isEmpty }
+        guard watched, let frame = channel.seal(.output(data)) else { return }
+        transport.send(frame)
+    }
+
+    /// Grants or revokes one participant's input; false when no such
+    /// participant is watching.
+    @discardableResult
+    func setReadWrite(_ readWrite: Bool, for participant: String) -> Bool {
+        let found = state.withLock { state -> Bool in
+            guard let index = state.participants.firstIndex(where: { $0.id == participant }) else { return false }
+            state.participants[index].readWrite = readWrite
+            return true
+        }
+        if found { send(.mode(to: participant, readWrite: readWrite), to: participant) }
+        return found
+    }
+
+    func stop() {
+        guard markStopped() else { return }
+        sweep?.cancel()
+        transport.close()
+    }
+
+    func expireSilent(now: Date) {
+        let expired = state.withLock { state -> [Participant] in
+            let cutoff = now.addingTimeInterval(-Self.participantTimeout)
+            let gone = state.participants.filter { $0.lastSeen < cutoff }
+            state.participants.removeAll { $0.lastSeen < cutoff }
+            for participant in gone { state.channels.removeValue(forKey: participant.id) }
+            return gone
+        }
+        for participant in expired { onPresence?(participant, false) }
+    }
+
+    private func markStopped() -> Bool {
+        state.withLock { state in
+            guard !state.stopped else { return false }
+            state.stopped = true
+            return true
+        }
+    }
+
+    /// Under the code's key (nil `participant`) or that guest's.
+    private func send(_ message: ShareMessage, to participant: String? = nil) {
+        let channel: ShareChannel?
+        if let participant {
+            channel = state.withLock { $0.channels[participant] }
+        } else {
+            channel = self.channel
+        }
+        guard !state.snapshot.stopped, let frame = channel?.seal(message) else { return }
+        transport.send(frame)
+    }
+
+    private func receive(_ frame: Data) {
+        if let opened = channel.open(frame) {
+            // Under the code's key a guest may only introduce itself.
+            if case .join(let name, let key) = opened.message { admit(opened.from, name: name, key: key) }
+            return
+        }
+        for (id, channel) in state.snapshot.channels {
+            guard let opened = channel.open(frame) else { continue }
+            if opened.from == id { handle(opened.message, from: id) }
+            return
+        }
+    }
+
+    /// Takes in a guest whose id is its key's hash, agrees a key with it
+    /// and answers with ours. A repeated join (the welcome went missing)
+    /// gets the welcome again and nothing else.
+    private func admit(_ from: String, name: String, key: Data) {
+        guard let publicKey = try? Curve25519.KeyAgreement.PublicKey(rawRepresentation: key),
+              ShareChannel.participantID(publicKey) == from,
+              let shared = code.participantKey(privateKey, publicKey) else { return }
+        let admitted = state.withLock { state -> (new: Participant?, known: Bool) in
+            if state.channels[from] != nil { return (nil, true) }
+            guard state.participants.count < Self.maxParticipants else { return (nil, false) }
+            let participant = Participant(id: from, name: String(name.prefix(64)), lastSeen: Date())
+            state.participants.append(participant)
+            state.channels[from] = ShareChannel(key: shared, sender: ShareChannel.hostID)
+            return (participant, true)
+        }
+        guard admitted.known else { return }
+        send(.welcome(to: from, key: privateKey.publicKey.rawRepresentation))
+        guard let joined = admitted.new else { return }
+        if let screen = screen?() {
+            send(.snapshot(to: from, cols: screen.cols, rows: screen.rows, text: screen.text), to: from)
+        }
+        send(.mode(to: from, readWrite: false), to: from)
+        onPresence?(joined, true)
+    }
+
+    private func handle(_ message: ShareMessage, from: String) {
+        switch message {
+        case .hello:
+            state.withLock { state in
+                if let index = state.participants.firstIndex(where: { $0.id == from }) {
+                    state.participants[index].lastSeen = Date()
+                }
+            }
+        case .bye:
+            let left = state.withLock { state -> Participant? in
+                state.channels.removeValue(forKey: from)
+                guard let index = state.participants.firstIndex(where: { $0.id == from }) else { return nil }
+                return state.participants.remove(at: index)
+            }
+            if let left { onPresence?(left, false) }
+        case .input(let data):
+            let allowed = state.withLock { state -> Bool in
+                !state.stopped && state.participants.contains { $0.id == from && $0.readWrite }
+            }
+            if allowed { onInput?(data) }
+        case .join, .welcome, .output, .snapshot, .mode:
+            break // joins come under the code's key; the rest is host-to-guest
+        }
+    }
+}
+
+/// The joining end: decrypts the host's stream and, when the host has
+/// granted this guest read-write, sends keystrokes back under the key it
+/// agreed with the host on joining.
+final class SessionShareGuest {
+    static let heartbeatInterval: TimeInterval = 20
+
+    let participant: String
+    var onOutput: ((Data) -> Void)?
+    var onSnapshot: ((_ cols: Int, _ rows: Int, _ text: String) -> Void)?
+    var onMode: ((Bool) -> Void)?
+    var onEnded: ((String) -> Void)?
+
+    private let name: String
+    private let code: ShareCode
+    private let transport: ShareTransport
+    private let channel: ShareChannel
+    private let privateKey: Curve25519.KeyAgreement.PrivateKey
+    /// Ours and the host's, once it has welcomed us.
+    private let hostChannel = LockedState<ShareChannel?>(nil)
+    private let readWriteState = LockedState(false)
+    private var heartbeat: DispatchSourceTimer?
+
+    init(code: ShareCode, name: String, transport: ShareTransport) {
+        let privateKey = Curve25519.KeyAgreement.PrivateKey()
+        self.privateKey = privateKey
+        participant = ShareChannel.participantID(privateKey.publicKey)
+        self.name = name
+        self.code = code
+        self.transport = transport
+        channel = ShareChannel(code: code, sender: participant)
+    }
+
+    var readWrite: Bool { readWriteState.snapshot }
+
+    func start() {
+        transport.onReceive = { [weak self] frame in self?.receive(frame) }
+        transport.onClose = { [weak self] reason in
+            self?.heartbeat?.cancel()
+            self?.onEnded?(reason)
+        }
+        let timer = DispatchSource.makeTimerSource(queue: .global(qos: .utility))
+        timer.schedule(deadline: .now() + Self.heartbeatInterval, repeating: Self.heartbeatInterval)
+        timer.setEventHandler { [weak self] in
+            guard let self else { return }
+            self.send(.hello(name: self.name))
+        }
+        timer.resume()
+        heartbeat = timer
+        transport.connect()
+        send(.join(name: name, key: privateKey.publicKey.rawRepresentation))
+    }
+
+    /// Sends keystrokes; false (nothing sent) while the share is read-only.
+    @discardableResult
+    func send(input: Data) -> Bool {
+        guard readWrite else { return false }
+        send(.input(input))
+        return true
+    }
+
+    func leave() {
+        heartbeat?.cancel()
+        send(.bye)
+        transport.close()
+    }
+
+    /// A join goes under the code's key, the rest under ours and the
+    /// host's; until the host has welcomed us a heartbeat joins again, and
+    /// anything else waits.
+    private func send(_ message: ShareMessage) {
+        var frame: Data?
+        if case .join = message {
+            frame = channel.seal(message)
+        } else if let hostChannel = hostChannel.snapshot {
+            frame = hostChannel.seal(message)
+        } else if case .hello = message {
+            frame = channel.seal(.join(name: name, key: privateKey.publicKey.rawRepresentation))
+        }
+        if let frame { transport.send(frame) }
+    }
+
+    private func receive(_ frame: Data) {
+        if let opened = channel.open(frame) {
+            guard opened.from == ShareChannel.hostID else { return }
+            switch opened.message {
+            case .output(let data):
+                onOutput?(data)
+            case .welcome(let to, let key) where to == participant:
+                guard let publicKey = try? Curve25519.KeyAgreement.PublicKey(rawRepresentation: key),
+                      let shared = code.participantKey(privateKey, publicKey) else { return }
+                hostChannel.withLock { $0 = $0 ?? ShareChannel(key: shared, sender: participant) }
+            default:
+                break
+            }
+            return
+        }
+        guard let opened = hostChannel.snapshot?.open(frame), opened.from == ShareChannel.hostID else { return }
+        switch opened.message {
+        case .snapshot(let to, let cols, let rows, let text) where to == participant:
+            onSnapshot?(cols, rows, text)
+        case .mode(let to, let readWrite) where to == participant:
+            readWriteState.withLock { $0 = readWrite }
+            onMode?(readWrite)
+        default:
+            break
+        }
+    }
+}
+
+/// A share this app joined as a guest. The host's stream plays into a
+/// headless terminal, so `share-read` answers with the screen the host
+/// sees rather than raw escape sequences.
+final class JoinedShare {
+    let id: String
+    let relay: String
+    let guest: SessionShareGuest
+    private let terminal = Terminal(cols: 80, rows: 24, scrollback: 1000)
+    private let endReason = LockedState<String?>(nil)
+
+    init(id: String, relay: String, guest: SessionShareGuest) {
+        self.id = id
+        self.relay = relay
+        self.guest = guest
+        guest.onSnapshot = { [weak self] cols, rows, text in self?.show(cols: cols, rows: rows, text: text) }
+        guest.onOutput = { [weak self] data in self?.feed(data) }
+        guest.onEnded = { [weak self] reason in self?.endReason.withLock { $0 = reason } }
+    }
+
+    /// Why the relay connection dropped, once it has.
+    var ended: String? { endReason.snapshot }
+
+    /// Types into the host's pane; refused until the host grants input
+    /// and once the share has ended.
+    func send(_ text: String) -> Result<Void, SessionShareError> {
+        guard ended == nil else { return .failure(.notJoined(id)) }
+        return guest.send(input: Data(text.utf8)) ? .success(()) : .failure(.readOnly(id))
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "id": id, "relay": relay, "participant": guest.participant, "mode": guest.readWrite ? "rw" : "ro",
+            "cols": terminal.cols, "rows": terminal.rows, "screen": terminal.screenText(),
+        ]
+        if let ended { object["ended"] = ended }
+        return object
+    }
+
+    private func show(cols: Int, rows: Int, text: String) {
+        terminal.resize(cols: max(cols, 1), rows: max(rows, 1))
+        let lines = text.split(separator: "\n", omittingEmptySubsequences: false).joined(separator: "\r\n")
+        feed(Data(("\u{1B}[H\u{1B}[2J" + lines).utf8))
+    }
+
+    private func feed(_ data: Data) {
+        data.withUnsafeBytes { raw in
+            guard let base = raw.bindMemory(to: UInt8.self).baseAddress else { return }
+            terminal.feed(base, raw.count)
+        }
+    }
+}
+
+/// Live shares: at most one hosted per pane, and the ones joined as a guest.
+final class SessionShareRegistry {
+    static let shared = SessionShareRegistry()
+
+    /// Forwarded from every host; the app turns these into events.
+    var onPresence: ((SessionShareHost, SessionShareHost.Participant, Bool) -> Void)?
+    var onEnded: ((SessionShareHost, String) -> Void)?
+
+    private let hosts = LockedState([Int: SessionShareHost]())
+    private let guests = LockedState((byID: [String: JoinedShare](), nextID: 1))
+
+    /// Registers a share for `pane` with a fresh code. The caller wires
+    /// `screen` and `onInput` and then calls `start()`.
+    func open(pane: Int, relay: String) -> Result<SessionShareHost, SessionShareError> {
+        let code = ShareCode.generate()
+        guard let url = WebSocketShareTransport.url(relay: relay, room: code.room, role: "host") else {
+            return .failure(.badRelay(relay))
+        }
+        let host = SessionShareHost(
+            pane: pane, code: code, relay: relay, transport: WebSocketShareTransport(url: url))
+        let added = hosts.withLock { hosts -> Bool in
+            guard hosts[pane] == nil else { return false }
+            hosts[pane] = host
+            return true
+        }
+        guard added else { return .failure(.alreadyShared(pane: pane)) }
+        host.onPresence = { [weak self, weak host] participant, joined in
+            guard let host else { return }
+            self?.onPresence?(host, participant, joined)
+        }
+        host.onEnded = { [weak self, weak host] reason in
+            guard let self, let host else { return }
+            self.hosts.withLock { hosts in
+                if hosts[host.pane] === host { hosts.removeValue(forKey: host.pane) }
+            }
+            self.onEnded?(host, reason)
+        }
+        return .success(host)
+    }
+
+    func host(pane: Int) -> SessionShareHost? {
+        hosts.withLock { $0[pane] }
+    }
+
+    @discardableResult
+    func stop(pane: Int) -> Bool {
+        guard let host = hosts.withLock({ $0.removeValue(forKey: pane) }) else { return false }
+        host.stop()
+        return true
+    }
+
+    /// Joins someone else's share through `relay` under `name`, which the
+    /// host sees in its participant list.
+    func join(code text: String, name: String, relay: String) -> Result<JoinedShare, SessionShareError> {
+        guard let code = ShareCode(text) else { return .failure(.badCode) }
+        guard let url = WebSocketShareTransport.url(relay: relay, room: code.room, role: "guest") else {
+            return .failure(.badRelay(relay))
+        }
+        let guest = SessionShareGuest(code: code, name: name, transport: WebSocketShareTransport(url: url))
+        let share = guests.withLock { guests -> JoinedShare in
+            guests.byID = guests.byID.filter { $0.value.ended == nil }
+            let share = JoinedShare(id: "share-\(guests.nextID)", relay: relay, guest: guest)
+            guests.nextID += 1
+            guests.byID[share.id] = share
+            return share
+        }
+        guest.start()
+        return .success(share)
+    }
+
+    /// The joined share `id`. One that has ended is handed out this last
+    /// time, for its reason, and then forgotten; joining again also clears
+    /// any ended ones nobody read.
+    func joined(_ id: String) -> JoinedShare? {
+        guests.withLock { guests in
+            guard let share = guests.byID[id] else { return nil }
+            if share.ended != nil { guests.byID.removeValue(forKey: id) }
+            return share
+        }
+    }
+
+    @discardableResult
+    func leave(_ id: String) -> Bool {
+        guard let share = guests.withLock({ $0.byID.removeValue(forKey: id) }) else { return false }
+        share.guest.leave()
+        return true
+    }
+
+    func stopAll() {
+        let all = hosts.withLock { hosts -> [SessionShareHost] in
+            defer { hosts.removeAll() }
+            return Array(hosts.values)
+        }
+        for host in all { host.stop() }
+        let left = guests.withLock { guests -> [JoinedShare] in
+            defer { guests.byID.removeAll() }
+            return Array(guests.byID.values)
+        }
+        for share in left { share.guest.leave() }
+    }
+
+    func list() -> [[String: Any]] {
+        hosts.snapshot.values.sorted { $0.pane < $1.pane }.map { host in
+            [
+                "pane": host.pane,
+                "relay": host.relay,
+                "participants": host.participants.map {
+                    ["id": $0.id, "name": $0.name, "mode": $0.readWrite ? "rw" : "ro"]
+                },
+            ]
+        }
+    }
+}
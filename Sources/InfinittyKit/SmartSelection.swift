@@ -0,0 +1,171 @@
+import Foundation
+
+/// A URL, path or git hash found in output (`detected-links`). A path's
+/// `:line[:column]` suffix is split off into `path`, `fileLine` and
+/// `fileColumn`; `line` is where it sits in the terminal.
+struct DetectedLink: Equatable {
+    let kind: SmartToken.Kind
+    let line: Int
+    let start: Int
+    let end: Int
+    let text: String
+    var path: String?
+    var fileLine: Int?
+    var fileColumn: Int?
+
+    init(kind: SmartToken.Kind, line: Int, columns: ClosedRange<Int>, text: String) {
+        self.kind = kind
+        self.line = line
+        self.start = columns.lowerBound
+        self.end = columns.upperBound
+        self.text = text
+        guard kind == .path else { return }
+        var parts = text.split(separator: ":", omittingEmptySubsequences: false)
+        var numbers: [Int] = []
+        while parts.count > 1, numbers.count < 2, let number = Int(parts[parts.count - 1]) {
+            numbers.insert(number, at: 0)
+            parts.removeLast()
+        }
+        path = parts.joined(separator: ":")
+        fileLine = numbers.first
+        fileColumn = numbers.count > 1 ? numbers[1] : nil
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "kind": kind.rawValue, "line": line, "start": start, "end": end, "text": text,
+        ]
+        if let path { object["path"] = path }
+        if let fileLine { object["fileLine"] = fileLine }
+        if let fileColumn { object["fileColumn"] = fileColumn }
+        return object
+    }
+}
+
+/// What a double-click selects when there is something better than a word
+/// under it: a URL, a path (with any `:line:column` suffix), a UUID, an IP
+/// address, a git hash, or — clicking one of its braces — a JSON object.
+/// Lines are absolute and columns count cells, as in search results.
+struct SmartToken: Equatable {
+    enum Kind: String {
+        case url, path, uuid, ip
+        case gitHash = "git-hash"
+        case json, word
+    }
+
+    let kind: Kind
+    let line: Int
+    let start: Int
+    let end: Int
+    let text: String
+
+    var wire: [String: Any] {
+        ["kind": kind.rawValue, "line": line, "start": start, "end": end, "text": text]
+    }
+
+    /// Tried in order; the first whose match covers the click wins, so a
+    /// URL beats the path inside it.
+    private static let patterns: [(Kind, NSRegularExpression)] = ([
+        (.url, #"(?:https?|ftp|file)://[^\s"'`<>]+|www\.[^\s"'`<>]+"#),
+        (.uuid, #"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b"#),
+        (.ip, #"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)(?:/\d{1,2}|:\d{1,5})?\b"#),
+        (.ip, #"(?<![\w:])(?:[0-9A-Fa-f]{0,4}:){2,7}[0-9A-Fa-f]{0,4}(?![\w:])"#),
+        (.path, #"(?<![\w.@+~-])(?:~|\.{1,2})?(?:/[^\s"'`<>|;,()\[\]{}:]+)+/?(?::\d+){0,2}"#),
+        (.path, #"(?<![\w/.-])[\w.@+-]+(?:/[\w.@+-]+)+/?(?::\d+){0,2}"#),
+        (.gitHash, #"\b[0-9a-f]{7,40}\b"#),
+    ] as [(Kind, String)]).map { ($0.0, try! NSRegularExpression(pattern: $0.1)) }
+
+    /// The token covering `column` of a row's characters (one per cell),
+    /// or nil when only a plain word is there.
+    static func find(in chars: [Character], at column: Int) -> (kind: Kind, columns: ClosedRange<Int>)? {
+        guard chars.indices.contains(column) else { return nil }
+        if "{}".contains(chars[column]) { return jsonObject(in: chars, at: column).map { (.json, $0) } }
+        return candidates(in: chars).first { $0.columns.contains(column) }
+    }
+
+    private static let linkKinds: Set<Kind> = [.url, .path, .gitHash]
+
+    /// The URLs, paths and git hashes in a row, left to right. Where two
+    /// overlap the earlier pattern keeps its span, as with `find`.
+    static func links(in chars: [Character], line: Int) -> [DetectedLink] {
+        var taken: [ClosedRange<Int>] = []
+        var links: [DetectedLink] = []
+        for (kind, columns) in candidates(in: chars) where linkKinds.contains(kind) {
+            guard !taken.contains(where: { $0.overlaps(columns) }) else { continue }
+            taken.append(columns)
+            links.append(DetectedLink(kind: kind, line: line, columns: columns, text: String(chars[columns])))
+        }
+        return links.sorted { $0.start < $1.start }
+    }
+
+    /// Every match of every pattern, in pattern order, trimmed of trailing
+    /// punctuation and checked.
+    private static func candidates(in chars: [Character]) -> [(kind: Kind, columns: ClosedRange<Int>)] {
+        // One UTF-16 unit per cell, so match ranges are columns.
+        let line = String(chars.map { $0.utf16.count == 1 ? $0 : "\u{FFFD}" })
+        let range = NSRange(location: 0, length: (line as NSString).length)
+        var found: [(kind: Kind, columns: ClosedRange<Int>)] = []
+        for (kind, regex) in patterns {
+            for match in regex.matches(in: line, range: range) where match.range.length > 0 {
+                let lo = match.range.location
+                var hi = lo + match.range.length - 1
+                if kind == .url || kind == .path {
+                    while hi > lo, ").,;:!?'\"".contains(chars[hi]) { hi -= 1 }
+                }
+                guard accepts(kind, String(chars[lo...hi])) else { continue }
+                found.append((kind, lo...hi))
+            }
+        }
+        return found
+    }
+
+    private static func accepts(_ kind: Kind, _ text: String) -> Bool {
+        switch kind {
+        case .ip where !text.contains("."):
+            var address = in6_addr()
+            return inet_pton(AF_INET6, text, &address) == 1
+        case .gitHash:
+            // A run of digits is a number and a run of letters a word.
+            return text.contains(where: \.isNumber) && text.contains(where: \.isLetter)
+        case .path:
+            return text.count > 1
+        default:
+            return true
+        }
+    }
+
+    /// The braces pairing with the one at `column`, when what they enclose
+    /// parses as JSON.
+    private static func jsonObject(in chars: [Character], at column: Int) -> ClosedRange<Int>? {
+        var opens: [Int] = []
+        var inString = false
+        var escaped = false
+        for (index, char) in chars.enumerated() {
+            if inString {
+                if escaped {
+                    escaped = false
+                } else if char == "\\" {
+                    escaped = true
+                } else if char == "\"" {
+                    inString = false
+                }
+                continue
+            }
+            switch char {
+            case "\"" where !opens.isEmpty:
+                inString = true
+            case "{":
+                opens.append(index)
+            case "}":
+                guard let open = opens.popLast() else { continue }
+                guard open == column || index == column else { continue }
+                let text = String(chars[open...index])
+                guard (try? JSONSerialization.jsonObject(with: Data(text.utf8))) != nil else { return nil }
+                return open...index
+            default:
+                continue
+            }
+        }
+        return nil
+    }
+}
@@ -0,0 +1,166 @@
+import AVFoundation
+import Foundation
+import Speech
+
+enum SpeechInputError: Error, Equatable, CustomStringConvertible {
+    case notAuthorized(String)
+    case unavailable(String)
+    case busy
+    case failed(String)
+
+    var description: String {
+        switch self {
+        case .notAuthorized(let what):
+            return "\(what) access was denied (System Settings → Privacy & Security)"
+        case .unavailable(let reason): return reason
+        case .busy: return "already listening"
+        case .failed(let reason): return reason
+        }
+    }
+}
+
+/// One recognition update: the whole utterance so far, revised as more
+/// audio arrives, until the final one.
+struct SpeechTranscript: Equatable {
+    let text: String
+    let isFinal: Bool
+
+    var wire: [String: Any] { ["text": text, "final": isFinal] }
+}
+
+/// Dictation for the assistant: the microphone through `SFSpeechRecognizer`,
+/// on-device whenever the language supports it so audio stays on the Mac.
+/// One capture at a time, app-wide; main thread only.
+final class SpeechInput {
+    static let shared = SpeechInput()
+
+    /// Every update from every capture, for the `speech` socket event.
+    var onTranscript: ((SpeechTranscript) -> Void)?
+
+    private let engine = AVAudioEngine()
+    private var request: SFSpeechAudioBufferRecognitionRequest?
+    private var task: SFSpeechRecognitionTask?
+    private var onUpdate: ((SpeechTranscript) -> Void)?
+    /// Bumped per capture so a late callback from an old task is dropped.
+    private var generation = 0
+    private var starting = false
+    private(set) var transcript = ""
+
+    var isListening: Bool { request != nil }
+
+    /// Asks for speech and microphone access the first time, then starts
+    /// listening. `onUpdate` sees this capture's transcripts on the main
+    /// thread; `locale` is a BCP 47 identifier, the system language if nil.
+    func start(
+        locale: String? = nil, onUpdate: ((SpeechTranscript) -> Void)? = nil,
+        completion: @escaping (Result<Void, SpeechInputError>) -> Void
+    ) {
+        guard !isListening, !starting else { return completion(.failure(.busy)) }
+        starting = true
+        Self.authorize { [weak self] error in
+            guard let self else { return }
+            self.starting = false
+            if let error { return completion(.failure(error)) }
+            completion(self.begin(locale: locale, onUpdate: onUpdate))
+        }
+    }
+
+    /// Stops listening and returns the transcript so far. The recognizer
+    /// may still revise it once in a final update.
+    @discardableResult
+    func stop() -> String {
+        guard let request else { return transcript }
+        request.endAudio()
+        stopAudio()
+        self.request = nil
+        return transcript
+    }
+
+    /// Stops and discards the capture; no final update follows.
+    func cancel() {
+        generation += 1
+        task?.cancel()
+        task = nil
+        if request != nil { stopAudio() }
+        request = nil
+        onUpdate = nil
+    }
+
+    /// What the composer shows while dictating: the text typed before the
+    /// capture started, then the transcript.
+    static func composed(draft: String, transcript: String) -> String {
+        let typed = draft.trimmingCharacters(in: .whitespaces)
+        guard !typed.isEmpty else { return transcript }
+        return transcript.isEmpty ? typed : "\(typed) \(transcript)"
+    }
+
+    private static func authorize(_ done: @escaping (SpeechInputError?) -> Void) {
+        SFSpeechRecognizer.requestAuthorization { status in
+            guard status == .authorized else {
+                DispatchQueue.main.async { done(.notAuthorized("Speech recognition")) }
+                return
+            }
+            AVCaptureDevice.requestAccess(for: .audio) { granted in
+                DispatchQueue.main.async { done(granted ? nil : .notAuthorized("Microphone")) }
+            }
+        }
+    }
+
+    private func begin(
+        locale: String?, onUpdate: ((SpeechTranscript) -> Void)?
+    ) -> Result<Void, SpeechInputError> {
+        let recognizer = locale.map { SFSpeechRecognizer(locale: Locale(identifier: $0)) } ?? SFSpeechRecognizer()
+        guard let recognizer else { return .failure(.unavailable("no speech recognizer for \(locale ?? "")")) }
+        guard recognizer.isAvailable else { return .failure(.unavailable("speech recognition is unavailable")) }
+
+        let request = SFSpeechAudioBufferRecognitionRequest()
+        request.shouldReportPartialResults = true
+        request.addsPunctuation = true
+        if recognizer.supportsOnDeviceRecognition { request.requiresOnDeviceRecognition = true }
+
+        let input = engine.inputNode
+        let format = input.outputFormat(forBus: 0)
+        guard format.channelCount > 0 else { return .failure(.unavailable("no audio input device")) }
+        input.installTap(onBus: 0, bufferSize: 1024, format: format) { buffer, _ in request.append(buffer) }
+        engine.prepare()
+        do {
+            try engine.start()
+        } catch {
+            input.removeTap(onBus: 0)
+            return .failure(.failed("could not start the microphone: \(error.localizedDescription)"))
+        }
+
+        generation += 1
+        let current = generation
+        self.request = request
+        self.onUpdate = onUpdate
+        transcript = ""
+        task = recognizer.recognitionTask(with: request) { [weak self] result, error in
+            let text = result?.bestTranscription.formattedString
+            let isFinal = result?.isFinal ?? false
+            DispatchQueue.main.async {
+                self?.receive(text, isFinal: isFinal || error != nil, generation: current)
+            }
+        }
+        return .success(())
+    }
+
+    private func receive(_ text: String?, isFinal: Bool, generation current: Int) {
+        guard current == generation else { return }
+        if let text { transcript = text }
+        let update = SpeechTranscript(text: transcript, isFinal: isFinal)
+        onUpdate?(update)
+        onTranscript?(update)
+        guard isFinal else { return }
+        // The recognizer gave up (silence, an error) or `stop` ran its course.
+        if request != nil { stopAudio() }
+        request = nil
+        task = nil
+        onUpdate = nil
+    }
+
+    private func stopAudio() {
+        engine.stop()
+        engine.inputNode.removeTap(onBus: 0)
+    }
+}
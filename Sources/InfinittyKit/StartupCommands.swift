@@ -0,0 +1,120 @@
+import Foundation
+
+/// One line a pane typed into its new shell by itself, and how it went.
+struct StartupCommand: Equatable {
+    enum Source: String {
+        case profile, workspace
+    }
+
+    let command: String
+    let source: Source
+    /// Set once the shell reports it finished (OSC 133); a shell without
+    /// the integration never does.
+    var exitCode: Int?
+
+    var failed: Bool { (exitCode ?? 0) != 0 }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["command": command, "source": source.rawValue]
+        if let exitCode { object["exitCode"] = exitCode }
+        return object
+    }
+}
+
+/// A new shell's startup commands: its profile's `startup` lines, then its
+/// workspace's. They go in as typeahead behind a dim marker line, so the
+/// scrollback shows where they end and the user's own typing begins, and
+/// the shell's command-finished reports are matched back to them in order
+/// to tell which failed.
+struct StartupRun: Equatable {
+    private(set) var commands: [StartupCommand]
+    /// The first command not reported finished yet.
+    private(set) var next = 0
+
+    init(profile: [String], workspace: [String]) {
+        commands = profile.map { StartupCommand(command: $0, source: .profile) }
+            + workspace.map { StartupCommand(command: $0, source: .workspace) }
+    }
+
+    var isEmpty: Bool { commands.isEmpty }
+    var isFinished: Bool { next >= commands.count }
+    var pending: [StartupCommand] { Array(commands[min(next, commands.count)...]) }
+
+    /// What the shell reads, one line each.
+    var input: [UInt8] {
+        commands.flatMap { Array($0.command.utf8) + [0x0D] }
+    }
+
+    /// Shown locally before the commands go in; never sent to the shell.
+    var marker: String {
+        let count = commands.count == 1 ? "1 startup command" : "\(commands.count) startup commands"
+        return "\u{1B}[2m\u{2500}\u{2500} \(count) \u{2500}\u{2500}\u{1B}[0m\r\n"
+    }
+
+    /// Records a finished command. Returns the startup command it was, or
+    /// nil for one the user typed: a command whose text the shell reported
+    /// has to match the next one, and one it didn't is taken to be it.
+    mutating func finished(command: String?, exitCode: Int) -> StartupCommand? {
+        guard !isFinished else { return nil }
+        if let command, command.trimmingCharacters(in: .whitespaces) != commands[next].command {
+            return nil
+        }
+        commands[next].exitCode = exitCode
+        next += 1
+        return commands[next - 1]
+    }
+
+    var wire: [String: Any] {
+        ["commands": commands.map(\.wire), "finished": isFinished, "failed": commands.filter(\.failed).count]
+    }
+}
+
+/// Commands every new pane in a workspace (a repo root, or the directory
+/// when there is no repo) runs once its shell is up, after its profile's.
+/// Kept in Application Support, unlike a project's `.infinitty.toml`,
+/// which runs on entering the project and only once it's trusted.
+final class WorkspaceStartup {
+    static let shared = WorkspaceStartup(url: WorkspaceStartup.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("workspace-startup.json") }
+
+    let url: URL
+    private let file: JSONFileStore<[String: [String]]>
+    private let entries = LockedState<[String: [String]]?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, formatting: [.sortedKeys])
+    }
+
+    func commands(for workspace: String) -> [String] {
+        entries.withLock { entries in
+            load(&entries)
+            return entries?[workspace] ?? []
+        }
+    }
+
+    /// Replaces the workspace's commands, a line each; blank lines are
+    /// dropped and an empty list removes them. Returns what was kept.
+    @discardableResult
+    func setCommands(_ commands: [String], for workspace: String) -> [String] {
+        let lines = commands
+            .flatMap { $0.split(whereSeparator: \.isNewline) }
+            .map { $0.trimmingCharacters(in: .whitespaces) }
+            .filter { !$0.isEmpty }
+        let snapshot = entries.withLock { entries -> [String: [String]] in
+            load(&entries)
+            var all = entries ?? [:]
+            all[workspace] = lines.isEmpty ? nil : lines
+            entries = all
+            return all
+        }
+        file.save(snapshot)
+        return lines
+    }
+
+    private func load(_ entries: inout [String: [String]]?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+}
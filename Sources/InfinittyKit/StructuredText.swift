@@ -0,0 +1,807 @@
+import Foundation
+
+/// A parsed JSON or YAML document. Objects keep their source key order and
+/// numbers keep their source spelling, so pretty-printing never reorders
+/// keys or turns `1.10` into `1.1`.
+indirect enum StructuredValue: Equatable {
+    struct Field: Equatable {
+        let key: String
+        let value: StructuredValue
+    }
+
+    case null
+    case bool(Bool)
+    case number(String)
+    case string(String)
+    case array([StructuredValue])
+    case object([Field])
+
+    var isCollection: Bool {
+        switch self {
+        case .array(let items): return !items.isEmpty
+        case .object(let fields): return !fields.isEmpty
+        default: return false
+        }
+    }
+}
+
+enum StructuredFormat: String, CaseIterable {
+    case json, yaml
+}
+
+enum StructuredTextError: Error, Equatable, CustomStringConvertible {
+    case syntax(String, line: Int)
+    case badQuery(String)
+    case query(String)
+
+    var description: String {
+        switch self {
+        case .syntax(let message, let line): return "line \(line): \(message)"
+        case .badQuery(let message): return "bad query: \(message)"
+        case .query(let message): return message
+        }
+    }
+}
+
+/// Pretty-printing and querying for JSON and YAML command output, behind
+/// the `format` socket command. YAML support covers what configs and CLI
+/// output actually use — block mappings and sequences, flow collections,
+/// quoted and plain scalars, `|` / `>` block text, comments — not anchors,
+/// tags or multiple documents.
+enum StructuredText {
+    /// Parse `text` (detecting the format when `input` is nil), apply the
+    /// optional jq-style path, and render each result in `output` (default:
+    /// the input's format), one per line group as jq does.
+    static func format(
+        _ text: String, input: StructuredFormat? = nil,
+        output: StructuredFormat? = nil, query: String? = nil
+    ) -> Result<String, StructuredTextError> {
+        let parsed: (value: StructuredValue, format: StructuredFormat)
+        switch parse(text, as: input) {
+        case .success(let result): parsed = result
+        case .failure(let error): return .failure(error)
+        }
+        var results = [parsed.value]
+        if let query, !query.trimmingCharacters(in: .whitespaces).isEmpty {
+            do {
+                results = try StructuredQuery(query).apply(to: parsed.value)
+            } catch let error as StructuredTextError {
+                return .failure(error)
+            } catch {
+                return .failure(.query(String(describing: error)))
+            }
+        }
+        let style = output ?? parsed.format
+        return .success(results.map { render($0, as: style) }.joined(separator: "\n"))
+    }
+
+    /// JSON when asked for, or when detection finds valid JSON; YAML
+    /// otherwise. A detected parse that fails both ways reports the error of
+    /// the format the text looked like.
+    static func parse(
+        _ text: String, as format: StructuredFormat? = nil
+    ) -> Result<(value: StructuredValue, format: StructuredFormat), StructuredTextError> {
+        do {
+            switch format {
+            case .json:
+                return .success((try JSONReader.document(text), .json))
+            case .yaml:
+                return .success((try YAMLReader.document(text), .yaml))
+            case nil:
+                do {
+                    return .success((try JSONReader.document(text), .json))
+                } catch let jsonError as StructuredTextError {
+                    let head = text.drop(while: { $0.isWhitespace }).first
+                    do {
+                        return .success((try YAMLReader.document(text), .yaml))
+                    } catch {
+                        if head == "{" || head == "[" { throw jsonError }
+                        throw error
+                    }
+                }
+            }
+        } catch let error as StructuredTextError {
+            return .failure(error)
+        } catch {
+            return .failure(.syntax(String(describing: error), line: 1))
+        }
+    }
+
+    static func render(_ value: StructuredValue, as format: StructuredFormat) -> String {
+        switch format {
+        case .json: return renderJSON(value, indent: 0)
+        case .yaml: return renderYAML(value, indent: 0)
+        }
+    }
+
+    // MARK: JSON output
+
+    private static func renderJSON(_ value: StructuredValue, indent: Int) -> String {
+        let pad = String(repeating: " ", count: indent + 2)
+        let close = String(repeating: " ", count: indent)
+        switch value {
+        case .null: return "null"
+        case .bool(let flag): return flag ? "true" : "false"
+        case .number(let literal): return literal
+        case .string(let string): return quoted(string)
+        case .array(let items):
+            guard !items.isEmpty else { return "[]" }
+            let body = items.map { pad + renderJSON($0, indent: indent + 2) }
+            return "[\n" + body.joined(separator: ",\n") + "\n\(close)]"
+        case .object(let fields):
+            guard !fields.isEmpty else { return "{}" }
+            let body = fields.map { pad + quoted($0.key) + ": " + renderJSON($0.value, indent: indent + 2) }
+            return "{\n" + body.joined(separator: ",\n") + "\n\(close)}"
+        }
+    }
+
+    /// JSON string literal; also valid YAML double-quoted scalar.
+    static func quoted(_ string: String) -> String {
+        var out = "\""
+        for scalar in string.unicodeScalars {
+            switch scalar {
+            case "\"": out += "\\\""
+            case "\\": out += "\\\\"
+            case "\n": out += "\\n"
+            case "\r": out += "\\r"
+            case "\t": out += "\\t"
+            case _ where scalar.value < 0x20 || scalar.value == 0x7F:
+                out += String(format: "\\u%04x", scalar.value)
+            default: out.unicodeScalars.append(scalar)
+            }
+        }
+        return out + "\""
+    }
+
+    // MARK: YAML output
+
+    private static func renderYAML(_ value: StructuredValue, indent: Int) -> String {
+        let pad = String(repeating: " ", count: indent)
+        switch value {
+        case .array(let items) where !items.isEmpty:
+            return items.map { item in
+                guard item.isCollection else { return pad + "- " + yamlScalar(item) }
+                // Render the child one level in, then hang its first line
+                // off the dash: "- key: value" / "- - item".
+                let child = renderYAML(item, indent: indent + 2)
+                return pad + "- " + String(child.dropFirst(indent + 2))
+            }.joined(separator: "\n")
+        case .object(let fields) where !fields.isEmpty:
+            return fields.map { field in
+                let key = pad + yamlString(field.key) + ":"
+                guard field.value.isCollection else { return key + " " + yamlScalar(field.value) }
+                return key + "\n" + renderYAML(field.value, indent: indent + 2)
+            }.joined(separator: "\n")
+        default:
+            return pad + yamlScalar(value)
+        }
+    }
+
+    private static func yamlScalar(_ value: StructuredValue) -> String {
+        switch value {
+        case .null: return "null"
+        case .bool(let flag): return flag ? "true" : "false"
+        case .number(let literal): return literal
+        case .string(let string): return yamlString(string)
+        case .array: return "[]"
+        case .object: return "{}"
+        }
+    }
+
+    /// Plain when the text would read back as the same string, else quoted.
+    private static func yamlString(_ string: String) -> String {
+        guard let first = string.first, let last = string.last,
+              !first.isWhitespace, !last.isWhitespace,
+              !"-?:,[]{}#&*!|>'\"%@`".contains(first),
+              !string.contains(": "), !string.contains(" #"), !string.hasSuffix(":"),
+              !string.unicodeScalars.contains(where: { $0.value < 0x20 || $0.value == 0x7F }),
+              YAMLReader.plainScalar(string) == .string(string)
+        else { return quoted(string) }
+        return string
+    }
+}
+
+// MARK: - JSON reader
+
+/// Recursive-descent JSON parser that keeps key order and number text.
+/// `flow` mode also accepts YAML flow collections: unquoted keys and plain
+/// scalars inside `[...]` / `{...}`.
+struct JSONReader {
+    private let bytes: [UInt8]
+    private var index = 0
+    private let flow: Bool
+
+    init(_ text: String, flow: Bool = false) {
+        bytes = Array(text.utf8)
+        self.flow = flow
+    }
+
+    static func document(_ text: String, flow: Bool = false) throws -> StructuredValue {
+        var reader = JSONReader(text, flow: flow)
+        return try reader.readDocument()
+    }
+
+    mutating func readDocument() throws -> StructuredValue {
+        let value = try readValue()
+        skipWhitespace()
+        guard index == bytes.count else { throw fail("unexpected trailing text") }
+        return value
+    }
+
+    private var line: Int { bytes[..<min(index, bytes.count)].filter { $0 == 0x0A }.count + 1 }
+
+    private func fail(_ message: String) -> StructuredTextError {
+        .syntax(message, line: line)
+    }
+
+    private mutating func skipWhitespace() {
+        while index < bytes.count, [0x20, 0x09, 0x0A, 0x0D].contains(bytes[index]) { index += 1 }
+    }
+
+    private mutating func readValue() throws -> StructuredValue {
+        skipWhitespace()
+        guard index < bytes.count else { throw fail("unexpected end of input") }
+        switch bytes[index] {
+        case UInt8(ascii: "{"): return try readObject()
+        case UInt8(ascii: "["): return try readArray()
+        case UInt8(ascii: "\""): return .string(try readString())
+        case UInt8(ascii: "'") where flow: return .string(try readSingleQuoted())
+        default:
+            if flow { return YAMLReader.plainScalar(readPlain()) }
+            return try readLiteral()
+        }
+    }
+
+    private mutating func readObject() throws -> StructuredValue {
+        index += 1
+        var fields: [StructuredValue.Field] = []
+        skipWhitespace()
+        if index < bytes.count, bytes[index] == UInt8(ascii: "}") {
+            index += 1
+            return .object(fields)
+        }
+        while true {
+            skipWhitespace()
+            let key: String
+            if index < bytes.count, bytes[index] == UInt8(ascii: "\"") {
+                key = try readString()
+            } else if flow, index < bytes.count, bytes[index] == UInt8(ascii: "'") {
+                key = try readSingleQuoted()
+            } else if flow {
+                key = readPlain()
+            } else {
+                throw fail("expected a quoted key")
+            }
+            skipWhitespace()
+            guard index < bytes.count, bytes[index] == UInt8(ascii: ":") else { throw fail("expected ':'") }
+            index += 1
+            fields.append(.init(key: key, value: try readValue()))
+            skipWhitespace()
+            guard index < bytes.count else { throw fail("unterminated object") }
+            if bytes[index] == UInt8(ascii: ",") { index += 1; continue }
+            if bytes[index] == UInt8(ascii: "}") { index += 1; return .object(fields) }
+            throw fail("expected ',' or '}'")
+        }
+    }
+
+    private mutating func readArray() throws -> StructuredValue {
+        index += 1
+        var items: [StructuredValue] = []
+        skipWhitespace()
+        if index < bytes.count, bytes[index] == UInt8(ascii: "]") {
+            index += 1
+            return .array(items)
+        }
+        while true {
+            items.append(try readValue())
+            skipWhitespace()
+            guard index < bytes.count else { throw fail("unterminated array") }
+            if bytes[index] == UInt8(ascii: ",") { index += 1; continue }
+            if bytes[index] == UInt8(ascii: "]") { index += 1; return .array(items) }
+            throw fail("expected ',' or ']'")
+        }
+    }
+
+    private mutating func readString() throws -> String {
+        index += 1
+        var scalars = String.UnicodeScalarView()
+        var raw: [UInt8] = []
+        func flushRaw() {
+            scalars.append(contentsOf: String(decoding: raw, as: UTF8.self).unicodeScalars)
+            raw.removeAll()
+        }
+        while index < bytes.count {
+            let byte = bytes[index]
+            index += 1
+            switch byte {
+            case UInt8(ascii: "\""):
+                flushRaw()
+                return String(scalars)
+            case UInt8(ascii: "\\"):
+                flushRaw()
+                guard index < bytes.count else { break }
+                let escape = bytes[index]
+                index += 1
+                switch escape {
+                case UInt8(ascii: "n"): scalars.append("\n")
+                case UInt8(ascii: "t"): scalars.append("\t")
+                case UInt8(ascii: "r"): scalars.append("\r")
+                case UInt8(ascii: "b"): scalars.append("\u{8}")
+                case UInt8(ascii: "f"): scalars.append("\u{C}")
+                case UInt8(ascii: "0") where flow: scalars.append("\0")
+                case UInt8(ascii: "u"):
+                    var code = try readHex4()
+                    if (0xD800..<0xDC00).contains(code), index + 1 < bytes.count,
+                       bytes[index] == UInt8(ascii: "\\"), bytes[index + 1] == UInt8(ascii: "u") {
+                        index += 2
+                        let low = try readHex4()
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    scalars.append(Unicode.Scalar(code) ?? "\u{FFFD}")
+                default: scalars.append(Unicode.Scalar(escape))
+                }
+            case 0x00..<0x20 where !flow:
+                throw fail("control character in string")
+            default:
+                raw.append(byte)
+            }
+        }
+        throw fail("unterminated string")
+    }
+
+    private mutating func readHex4() throws -> UInt32 {
+        guard index + 4 <= bytes.count,
+              let code = UInt32(String(decoding: bytes[index..<index + 4], as: UTF8.self), radix: 16)
+        else { throw fail("bad \\u escape") }
+        index += 4
+        return code
+    }
+
+    private mutating func readSingleQuoted() throws -> String {
+        index += 1
+        var raw: [UInt8] = []
+        while index < bytes.count {
+            let byte = bytes[index]
+            index += 1
+            if byte == UInt8(ascii: "'") {
+                if index < bytes.count, bytes[index] == UInt8(ascii: "'") {
+                    raw.append(byte)
+                    index += 1
+                    continue
+                }
+                return String(decoding: raw, as: UTF8.self)
+            }
+            raw.append(byte)
+        }
+        throw fail("unterminated string")
+    }
+
+    /// A flow-mode plain scalar: up to `,` `]` `}` or a `: ` key separator.
+    private mutating func readPlain() -> String {
+        let start = index
+        while index < bytes.count {
+            let byte = bytes[index]
+            if byte == UInt8(ascii: ",") || byte == UInt8(ascii: "]") || byte == UInt8(ascii: "}") { break }
+            if byte == UInt8(ascii: ":"),
+               index + 1 >= bytes.count || [0x20, 0x0A, 0x09].contains(bytes[index + 1]) { break }
+            index += 1
+        }
+        return String(decoding: bytes[start..<index], as: UTF8.self).trimmingCharacters(in: .whitespaces)
+    }
+
+    private mutating func readLiteral() throws -> StructuredValue {
+        let start = index
+        let delimiters: Set<UInt8> = [UInt8(ascii: ","), UInt8(ascii: "]"), UInt8(ascii: "}"), UInt8(ascii: ":")]
+        while index < bytes.count, bytes[index] >= 0x21, !delimiters.contains(bytes[index]) {
+            index += 1
+        }
+        let word = String(decoding: bytes[start..<index], as: UTF8.self)
+        switch word {
+        case "null": return .null
+        case "true": return .bool(true)
+        case "false": return .bool(false)
+        default:
+            let pattern = #"^-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][-+]?[0-9]+)?$"#
+            guard word.range(of: pattern, options: .regularExpression) != nil else {
+                index = start
+                throw fail(word.isEmpty ? "unexpected character" : "unexpected '\(word)'")
+            }
+            return .number(word)
+        }
+    }
+}
+
+// MARK: - YAML reader
+
+/// Indentation-driven reader for the block YAML subset `StructuredText`
+/// documents. Flow collections on a line are handed to `JSONReader` in
+/// flow mode.
+struct YAMLReader {
+    private struct Line {
+        var indent: Int
+        var text: String
+        let number: Int
+    }
+
+    private var lines: [Line] = []
+    /// Raw lines, for block scalars whose content comments must not touch.
+    private let raw: [String]
+    private var index = 0
+
+    init(_ text: String) {
+        raw = text.components(separatedBy: "\n").map {
+            $0.hasSuffix("\r") ? String($0.dropLast()) : $0
+        }
+        for (offset, line) in raw.enumerated() {
+            let content = Self.stripComment(line)
+            let trimmed = content.trimmingCharacters(in: .whitespaces)
+            if trimmed.isEmpty || trimmed == "---" || trimmed == "..." || trimmed.hasPrefix("%") { continue }
+            let indent = content.prefix(while: { $0 == " " }).count
+            lines.append(Line(indent: indent, text: trimmed, number: offset + 1))
+        }
+    }
+
+    static func document(_ text: String) throws -> StructuredValue {
+        var reader = YAMLReader(text)
+        return try reader.readDocument()
+    }
+
+    mutating func readDocument() throws -> StructuredValue {
+        guard !lines.isEmpty else { return .null }
+        if raw.filter({ $0.trimmingCharacters(in: .whitespaces) == "---" }).count > 1 {
+            throw StructuredTextError.syntax("multiple documents are not supported", line: 1)
+        }
+        let value = try readBlock(indent: lines[0].indent)
+        if index < lines.count {
+            throw StructuredTextError.syntax("unexpected indentation", line: lines[index].number)
+        }
+        return value
+    }
+
+    private mutating func readBlock(indent: Int) throws -> StructuredValue {
+        let line = lines[index]
+        if line.text == "-" || line.text.hasPrefix("- ") { return try readSequence(indent: indent) }
+        if Self.keySplit(line.text) != nil { return try readMapping(indent: indent) }
+        // A bare scalar, possibly folded over several more-indented lines.
+        var parts = [line.text]
+        index += 1
+        while index < lines.count, lines[index].indent > indent {
+            parts.append(lines[index].text)
+            index += 1
+        }
+        return try Self.scalar(parts.joined(separator: " "), line: line.number)
+    }
+
+    private mutating func readSequence(indent: Int) throws -> StructuredValue {
+        var items: [StructuredValue] = []
+        while index < lines.count, lines[index].indent == indent,
+              lines[index].text == "-" || lines[index].text.hasPrefix("- ") {
+            let line = lines[index]
+            let rest = String(line.text.dropFirst()).trimmingCharacters(in: .whitespaces)
+            if rest.isEmpty {
+                index += 1
+                if index < lines.count, lines[index].indent > indent {
+                    items.append(try readBlock(indent: lines[index].indent))
+                } else {
+                    items.append(.null)
+                }
+                continue
+            }
+            // "- key: v" / "- - x": the rest is a nested block starting at
+            // the column after the dash.
+            let column = indent + (line.text.count - rest.count)
+            lines[index] = Line(indent: column, text: rest, number: line.number)
+            if rest == "-" || rest.hasPrefix("- ") || Self.keySplit(rest) != nil {
+                items.append(try readBlock(indent: column))
+            } else {
+                index += 1
+                items.append(try Self.scalar(continued(rest, deeperThan: indent), line: line.number))
+            }
+        }
+        return .array(items)
+    }
+
+    private mutating func readMapping(indent: Int) throws -> StructuredValue {
+        var fields: [StructuredValue.Field] = []
+        while index < lines.count, lines[index].indent == indent {
+            let line = lines[index]
+            guard let split = Self.keySplit(line.text) else {
+                throw StructuredTextError.syntax("expected 'key: value'", line: line.number)
+            }
+            let (rawKey, rest) = split
+            let key = try Self.keyText(rawKey, line: line.number)
+            index += 1
+            let value: StructuredValue
+            if rest.isEmpty {
+                if index < lines.count, lines[index].indent > indent {
+                    value = try readBlock(indent: lines[index].indent)
+                } else if index < lines.count, lines[index].indent == indent,
+                          lines[index].text == "-" || lines[index].text.hasPrefix("- ") {
+                    // Sequences may sit at their key's own indentation.
+                    value = try readSequence(indent: indent)
+                } else {
+                    value = .null
+                }
+            } else if let style = rest.first, style == "|" || style == ">",
+                      rest.dropFirst().allSatisfy({ "+-0123456789".contains($0) }) {
+                value = .string(readBlockScalar(after: line, parentIndent: indent, header: rest))
+            } else {
+                value = try Self.scalar(continued(rest, deeperThan: indent), line: line.number)
+            }
+            fields.append(.init(key: key, value: value))
+        }
+        return .object(fields)
+    }
+
+    /// `|` keeps newlines, `>` folds them into spaces; `-` drops the final
+    /// newline and `+` keeps trailing blank lines.
+    private mutating func readBlockScalar(after line: Line, parentIndent: Int, header: String) -> String {
+        var body: [String] = []
+        var rawIndex = line.number
+        var contentIndent: Int?
+        while rawIndex < raw.count {
+            let text = raw[rawIndex]
+            let trimmed = text.trimmingCharacters(in: .whitespaces)
+            let indent = text.prefix(while: { $0 == " " }).count
+            if !trimmed.isEmpty {
+                let base = contentIndent ?? indent
+                if indent <= parentIndent || indent < base { break }
+                contentIndent = base
+            }
+            body.append(trimmed.isEmpty ? "" : String(text.dropFirst(contentIndent ?? 0)))
+            rawIndex += 1
+        }
+        // Skip the structural lines the block consumed.
+        while index < lines.count, lines[index].number <= rawIndex { index += 1 }
+
+        var trailing = 0
+        while body.last == "" {
+            body.removeLast()
+            trailing += 1
+        }
+        let joined = header.hasPrefix("|")
+            ? body.joined(separator: "\n")
+            : body.split(separator: "", omittingEmptySubsequences: false)
+                .map { $0.joined(separator: " ") }.joined(separator: "\n")
+        if header.contains("-") || body.isEmpty { return joined }
+        if header.contains("+") { return joined + String(repeating: "\n", count: trailing + 1) }
+        return joined + "\n"
+    }
+
+    /// `text` plus any more-indented lines after it: a plain scalar or flow
+    /// collection wrapped over several lines.
+    private mutating func continued(_ text: String, deeperThan indent: Int) -> String {
+        var parts = [text]
+        while index < lines.count, lines[index].indent > indent {
+            parts.append(lines[index].text)
+            index += 1
+        }
+        return parts.joined(separator: " ")
+    }
+
+    private static func scalar(_ text: String, line: Int) throws -> StructuredValue {
+        guard let first = text.first else { return .null }
+        if first == "[" || first == "{" || first == "\"" || first == "'" {
+            do {
+                return try JSONReader.document(text, flow: true)
+            } catch StructuredTextError.syntax(let message, _) {
+                throw StructuredTextError.syntax(message, line: line)
+            }
+        }
+        return plainScalar(text)
+    }
+
+    private static func keyText(_ raw: String, line: Int) throws -> String {
+        guard let first = raw.first, first == "\"" || first == "'" else { return raw }
+        guard case .string(let key) = try scalar(raw, line: line) else { return raw }
+        return key
+    }
+
+    /// Core-schema resolution of an unquoted scalar.
+    static func plainScalar(_ text: String) -> StructuredValue {
+        switch text {
+        case "", "~", "null", "Null", "NULL": return .null
+        case "true", "True", "TRUE": return .bool(true)
+        case "false", "False", "FALSE": return .bool(false)
+        default:
+            // Leading-zero integers ("007", zip codes) stay strings.
+            if text.range(of: #"^[-+]?((0|[1-9][0-9]*)(\.[0-9]*)?|\.[0-9]+)([eE][-+]?[0-9]+)?$"#,
+                          options: .regularExpression) != nil {
+                return .number(normalizedNumber(text))
+            }
+            return .string(text)
+        }
+    }
+
+    /// YAML numbers like `+1`, `.5` or `1.` aren't valid JSON; spell them so
+    /// JSON output stays parseable.
+    private static func normalizedNumber(_ text: String) -> String {
+        var number = text.hasPrefix("+") ? String(text.dropFirst()) : text
+        let sign = number.hasPrefix("-") ? "-" : ""
+        if !sign.isEmpty { number.removeFirst() }
+        if number.hasPrefix(".") { number = "0" + number }
+        number = number.replacingOccurrences(of: ".e", with: ".0e").replacingOccurrences(of: ".E", with: ".0E")
+        if number.hasSuffix(".") { number += "0" }
+        return sign + number
+    }
+
+    /// Split `key: value` at the first `:` that ends a key, ignoring colons
+    /// inside quotes or flow brackets and in URLs like `http://`.
+    static func keySplit(_ text: String) -> (String, String)? {
+        guard let first = text.first, first != "[", first != "{" else { return nil }
+        var quote: Character?
+        var position = text.startIndex
+        while position < text.endIndex {
+            let character = text[position]
+            if let open = quote {
+                if character == open { quote = nil }
+            } else if (character == "\"" || character == "'") && position == text.startIndex {
+                quote = character
+            } else if character == ":" {
+                let next = text.index(after: position)
+                if next == text.endIndex || text[next] == " " || text[next] == "\t" {
+                    let key = text[..<position].trimmingCharacters(in: .whitespaces)
+                    let value = text[next...].trimmingCharacters(in: .whitespaces)
+                    return key.isEmpty ? nil : (key, value)
+                }
+            }
+            position = text.index(after: position)
+        }
+        return nil
+    }
+
+    /// Drop a `#` comment: one at the start of the line or after a space,
+    /// outside quotes.
+    private static func stripComment(_ line: String) -> String {
+        var quote: Character?
+        var previous: Character = " "
+        for (offset, character) in line.enumerated() {
+            if let open = quote {
+                if character == open { quote = nil }
+            } else if character == "\"" || character == "'" {
+                if previous == " " || previous == ":" || previous == "[" || previous == "{" || previous == "," {
+                    quote = character
+                }
+            } else if character == "#", previous == " " || previous == "\t" {
+                return String(line.prefix(offset))
+            }
+            previous = character
+        }
+        return line
+    }
+}
+
+// MARK: - queries
+
+/// A jq path expression: `.`, `.key`, `."odd key"`, `["key"]`, `[2]`,
+/// `[-1]`, `[1:3]` and `[]` (iterate), chained. Missing keys and indexes
+/// yield null like jq; indexing the wrong kind of value is an error.
+struct StructuredQuery {
+    private enum Step: Equatable {
+        case key(String)
+        case index(Int)
+        case slice(Int?, Int?)
+        case iterate
+    }
+
+    private let steps: [Step]
+
+    init(_ text: String) throws {
+        var steps: [Step] = []
+        let chars = Array(text.trimmingCharacters(in: .whitespaces))
+        var i = 0
+        guard chars.first == "." || chars.first == "[" else {
+            throw StructuredTextError.badQuery("a path starts with '.'")
+        }
+        func readQuoted() throws -> String {
+            var out = ""
+            i += 1
+            while i < chars.count, chars[i] != "\"" {
+                if chars[i] == "\\", i + 1 < chars.count { i += 1 }
+                out.append(chars[i])
+                i += 1
+            }
+            guard i < chars.count else { throw StructuredTextError.badQuery("unterminated string") }
+            i += 1
+            return out
+        }
+        while i < chars.count {
+            switch chars[i] {
+            case ".":
+                i += 1
+                if i < chars.count, chars[i] == "\"" {
+                    steps.append(.key(try readQuoted()))
+                } else {
+                    let start = i
+                    while i < chars.count, chars[i].isLetter || chars[i].isNumber || chars[i] == "_" { i += 1 }
+                    if i > start {
+                        steps.append(.key(String(chars[start..<i])))
+                    } else if i < chars.count, chars[i] != "[" {
+                        throw StructuredTextError.badQuery("unexpected '\(chars[i])'")
+                    }
+                }
+            case "[":
+                i += 1
+                if i < chars.count, chars[i] == "\"" {
+                    steps.append(.key(try readQuoted()))
+                } else {
+                    let start = i
+                    while i < chars.count, chars[i] != "]" { i += 1 }
+                    let inner = String(chars[start..<min(i, chars.count)]).trimmingCharacters(in: .whitespaces)
+                    if inner.isEmpty {
+                        steps.append(.iterate)
+                    } else if inner.contains(":") {
+                        let bounds = inner.split(separator: ":", omittingEmptySubsequences: false)
+                        let lower = bounds[0].isEmpty ? nil : Int(bounds[0])
+                        let upper = bounds.count > 1 && !bounds[1].isEmpty ? Int(bounds[1]) : nil
+                        guard bounds.count == 2,
+                              bounds[0].isEmpty || lower != nil,
+                              bounds[1].isEmpty || upper != nil
+                        else { throw StructuredTextError.badQuery("bad slice [\(inner)]") }
+                        steps.append(.slice(lower, upper))
+                    } else if let index = Int(inner) {
+                        steps.append(.index(index))
+                    } else {
+                        throw StructuredTextError.badQuery("bad index [\(inner)]")
+                    }
+                }
+                guard i < chars.count, chars[i] == "]" else {
+                    throw StructuredTextError.badQuery("missing ']'")
+                }
+                i += 1
+            default:
+                throw StructuredTextError.badQuery("unexpected '\(chars[i])'")
+            }
+        }
+        self.steps = steps
+    }
+
+    func apply(to value: StructuredValue) throws -> [StructuredValue] {
+        try steps.reduce([value]) { values, step in
+            try values.flatMap { try Self.apply(step, to: $0) }
+        }
+    }
+
+    private static func apply(_ step: Step, to value: StructuredValue) throws -> [StructuredValue] {
+        switch (step, value) {
+        case (.iterate, .array(let items)): return items
+        case (.iterate, .object(let fields)): return fields.map(\.value)
+        case (_, .null) where step != .iterate: return [.null]
+        case (.key(let key), .object(let fields)):
+            return [fields.last(where: { $0.key == key })?.value ?? .null]
+        case (.index(let index), .array(let items)):
+            let resolved = index < 0 ? items.count + index : index
+            return [items.indices.contains(resolved) ? items[resolved] : .null]
+        case (.slice(let lower, let upper), .array(let items)):
+            func clamp(_ bound: Int?, _ fallback: Int) -> Int {
+                guard let bound else { return fallback }
+                return min(max(bound < 0 ? items.count + bound : bound, 0), items.count)
+            }
+            let start = clamp(lower, 0)
+            let end = max(clamp(upper, items.count), start)
+            return [.array(Array(items[start..<end]))]
+        default:
+            throw StructuredTextError.query("cannot apply \(describe(step)) to \(kind(of: value))")
+        }
+    }
+
+    private static func describe(_ step: Step) -> String {
+        switch step {
+        case .key(let key): return "key \"\(key)\""
+        case .index(let index): return "index [\(index)]"
+        case .slice: return "slice"
+        case .iterate: return "[]"
+        }
+    }
+
+    private static func kind(of value: StructuredValue) -> String {
+        switch value {
+        case .null: return "null"
+        case .bool: return "a boolean"
+        case .number: return "a number"
+        case .string: return "a string"
+        case .array: return "an array"
+        case .object: return "an object"
+        }
+    }
+}
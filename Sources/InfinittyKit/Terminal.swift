@@ -30,25 +30,60 @@ struct SnapImage {
 private struct RowRing {
     private var buf: [[Cell]] = []
     private var start = 0
-    let capacity: Int
+    private(set) var capacity: Int
+    /// Cell storage held, for the scrollback budget.
+    private(set) var bytes = 0
 
     init(capacity: Int) { self.capacity = capacity }
     var count: Int { buf.count }
 
-    mutating func append(_ row: [Cell]) {
+    /// Returns the oldest row when the ring was full and dropped it.
+    @discardableResult
+    mutating func append(_ row: [Cell]) -> [Cell]? {
+        bytes += Self.size(row)
         if buf.count < capacity {
             buf.append(row)
-        } else {
-            buf[start] = row
-            start = (start + 1) % capacity
+            return nil
+        }
+        let evicted = buf[start]
+        bytes -= Self.size(evicted)
+        buf[start] = row
+        start = (start + 1) % capacity
+        return evicted
+    }
+
+    subscript(i: Int) -> [Cell] {
+        get { buf[(start + i) % buf.count] }
+        set {
+            let index = (start + i) % buf.count
+            bytes += Self.size(newValue) - Self.size(buf[index])
+            buf[index] = newValue
         }
     }
 
-    subscript(i: Int) -> [Cell] { buf[(start + i) % buf.count] }
+    /// Changes how many rows the ring keeps; returns the oldest rows a
+    /// smaller capacity drops, oldest first.
+    mutating func setCapacity(_ newCapacity: Int) -> [[Cell]] {
+        if start > 0 {
+            buf = Array(buf[start...] + buf[..<start])
+            start = 0
+        }
+        capacity = max(newCapacity, 1)
+        guard buf.count > capacity else { return [] }
+        let dropped = Array(buf.prefix(buf.count - capacity))
+        buf.removeFirst(dropped.count)
+        bytes -= dropped.reduce(0) { $0 + Self.size($1) }
+        return dropped
+    }
 
     mutating func removeAll() {
         buf.removeAll(keepingCapacity: false)
         start = 0
+        bytes = 0
+    }
+
+    private static func size(_ row: [Cell]) -> Int {
+        row.count * MemoryLayout<Cell>.stride
     }
 }
 
@@ -67,17 +102,201 @@ private struct SavedCursor {
     var charsets: [Bool] = [false, false]
 }
 
+struct ScrollbackMatch: Equatable {
+    let line: Int
+    let column: Int
+    /// In cells, like `column`.
+    let length: Int
+    /// The whole line the match sits on.
+    let text: String
+}
+
+struct ScrollbackSearch {
+    var matches: [ScrollbackMatch] = []
+    var truncated = false
+    /// The oldest line still held and the screen's bottom line.
+    let firstLine: Int
+    let lastLine: Int
+
+    init(firstLine: Int, lastLine: Int) {
+        self.firstLine = firstLine
+        self.lastLine = lastLine
+    }
+
+    var wire: [String: Any] {
+        [
+            "matches": matches.map {
+                ["line": $0.line, "column": $0.column, "length": $0.length, "text": $0.text] as [String: Any]
+            },
+            "truncated": truncated, "firstLine": firstLine, "lastLine": lastLine,
+        ]
+    }
+}
+
+/// A command's run as shell integration reports it: OSC 133 (or VS Code's
+/// 633) C when it starts, D with its exit status when it ends. Lines are
+/// absolute, where the command's output began.
+enum CommandEvent: Equatable {
+    case started(command: String?, line: Int)
+    case finished(command: String?, exitCode: Int, duration: TimeInterval, line: Int)
+}
+
+/// Copy-mode cursor moves, named as the control socket spells them.
+enum CopyModeMotion: String, CaseIterable {
+    case left, right, up, down
+    case wordNext = "word-next"
+    case wordPrevious = "word-prev"
+    case lineStart = "line-start"
+    case lineEnd = "line-end"
+    case pageUp = "page-up"
+    case pageDown = "page-down"
+    case top, bottom
+}
+
+enum CopyModeError: Error, Equatable, CustomStringConvertible {
+    case inactive
+    case noMatch
+    case invalidPattern(String)
+
+    var description: String {
+        switch self {
+        case .inactive: return "the pane is not in copy mode"
+        case .noMatch: return "no match"
+        case .invalidPattern(let reason): return reason
+        }
+    }
+}
+
+/// Copy mode's cursor (absolute line, cell column), the viewport it keeps
+/// in view, and the selection it has made.
+struct CopyModeStatus: Equatable {
+    let line: Int
+    let column: Int
+    let firstLine: Int
+    let lastLine: Int
+    let viewOffset: Int
+    let selection: Terminal.SelectionMode?
+    let text: String?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "line": line, "column": column, "firstLine": firstLine, "lastLine": lastLine,
+            "viewOffset": viewOffset,
+        ]
+        if let selection { object["selection"] = "\(selection)" }
+        if let text { object["text"] = text }
+        return object
+    }
+}
+
+/// What `scrollbackExtract` takes from each line: a rectangle of cell
+/// columns (the block selection's, or whole lines, when nil) or the Nth
+/// field, split on runs of whitespace or on `separator`. Fields count
+/// from 1; negative ones count back from the last.
+enum ScrollbackExtractMode: Equatable {
+    case block(ClosedRange<Int>?)
+    case field(Int, separator: String?)
+
+    /// Field `n` of `line`, or nil when it has too few.
+    static func field(_ n: Int, of line: String, separator: String? = nil) -> String? {
+        let fields: [String]
+        if let separator, !separator.isEmpty {
+            fields = line.components(separatedBy: separator).map { $0.trimmingCharacters(in: .whitespaces) }
+        } else {
+            fields = line.split(whereSeparator: \.isWhitespace).map(String.init)
+        }
+        let index = n > 0 ? n - 1 : fields.count + n
+        return n != 0 && fields.indices.contains(index) ? fields[index] : nil
+    }
+}
+
+enum ScrollbackExtractError: Error, Equatable, CustomStringConvertible {
+    case noSelection
+    case outOfRange
+    case invalidField
+
+    var description: String {
+        switch self {
+        case .noSelection: return "nothing selected; give from and to lines"
+        case .outOfRange: return "those lines are no longer in scrollback"
+        case .invalidField: return "fields count from 1 (or back from -1)"
+        }
+    }
+}
+
+/// The lines an extraction read (absolute, as in search results) and what
+/// it took from them.
+struct ScrollbackExtraction: Equatable {
+    let firstLine: Int
+    let lastLine: Int
+    let values: [String]
+
+    var wire: [String: Any] {
+        [
+            "firstLine": firstLine, "lastLine": lastLine, "values": values,
+            "text": values.joined(separator: "\n"),
+        ]
+    }
+}
+
+/// A finished command's output, absolute lines `line...lastLine`, where
+/// `line` is the one its command events give. Folded, the part of it in
+/// scrollback shows as a single summary row.
+struct CommandBlock: Equatable {
+    let command: String?
+    let exitCode: Int
+    let line: Int
+    let lastLine: Int
+    var folded = false
+
+    var lineCount: Int { lastLine - line + 1 }
+
+    /// The row a folded block shows in place of its output.
+    var summary: String {
+        let count = lineCount == 1 ? "1 line" : "\(lineCount) lines"
+        let status = exitCode == 0 ? "" : " (exit \(exitCode))"
+        guard let command, !command.isEmpty else { return "▸ \(count) folded\(status)" }
+        return "▸ \(command) — \(count) folded\(status)"
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [
+            "line": line, "lastLine": lastLine, "lines": lineCount, "exitCode": exitCode,
+            "folded": folded, "summary": summary,
+        ]
+        if let command { object["command"] = command }
+        return object
+    }
+}
+
+enum CommandFoldError: Error, Equatable, CustomStringConvertible {
+    case notFound(Int?)
+
+    var description: String {
+        switch self {
+        case .notFound(let line?): return "no finished command with output at line \(line)"
+        case .notFound(nil): return "no finished command with output yet"
+        }
+    }
+}
+
 /// The terminal engine: grid, scrollback, and a single-pass VT parser.
 /// `feed` is called from the PTY read thread with whole kernel-sized batches;
 /// the renderer takes snapshots. One unfair lock, held briefly by both sides.
 final class Terminal {
-    static let maxScrollback = 10_000
+    static let defaultScrollback = 10_000
+    /// Ceiling for the `scrollback-lines` setting; a full ring of 120-column
+    /// rows at this size is a few hundred megabytes.
+    static let maxScrollback = 1_000_000
 
     var onOutput: (([UInt8]) -> Void)? // parser responses (DSR etc.) -> pty
-    var onTitle: ((String) -> Void)?
+    var onTitle: ((String) -> Void)? // OSC 0/2 window title
+    var onIconName: ((String) -> Void)? // OSC 0/1 icon name
+    var onDirectory: ((String) -> Void)? // OSC 7 cwd reports from the shell
     var onBell: (() -> Void)?
     var onChange: (() -> Void)? // fired after every mutating batch, outside the lock
     var onMarker: ((UInt8, Int) -> Void)? // OSC 133 events: (kind, exitCode)
+    var onCommand: ((CommandEvent) -> Void)? // OSC 133/633 command start and finish
 
     private let lock = OSAllocatedUnfairLock()
     private var generation: UInt64 = 1
@@ -89,11 +308,18 @@ final class Terminal {
     private var inactiveScreen: [[Cell]]
     private var usingAlt = false
     private var scrollback: RowRing
+    let scrollbackLimit: Int
     private var viewOffset = 0 // rows scrolled back into history
 
     private var cx = 0
     private var cy = 0
     private var pen = Pen()
+    // OSC 8 links, numbered so a cell can carry one in `Cell.link`. The
+    // open link isn't part of the pen: SGR 0 and DECRC leave it alone.
+    private var hyperlinks: [UInt16: TerminalHyperlink] = [:]
+    private var hyperlinkNumbers: [TerminalHyperlink: UInt16] = [:]
+    private var lastHyperlink: UInt16 = 0
+    private var openHyperlink: UInt16 = 0
     private var savedMain = SavedCursor()
     private var savedAlt = SavedCursor()
 
@@ -138,8 +364,11 @@ final class Terminal {
 
     private var pendingOutput: [UInt8] = []
     private var pendingTitle: String?
+    private var pendingIconName: String?
+    private var pendingDirectory: String?
     private var pendingBell = false
     private var pendingMarkers: [(UInt8, Int)] = []
+    private var pendingCommands: [CommandEvent] = []
 
     // MARK: semantic command markers (OSC 133, for agents/tooling)
 
@@ -151,6 +380,15 @@ final class Terminal {
     }
 
     private var markers: [Marker] = []
+    /// The command between its C and D markers, timed from the C.
+    private var runningCommand: (command: String?, line: Int, started: Date)?
+    /// The command line an OSC 633 E reported for the coming C; exact where
+    /// reading it off the screen may catch a wrapped or redrawn prompt.
+    private var reportedCommandLine: String?
+    /// Where rows evicted from `scrollback` go, when spilling is on.
+    private var spill: ScrollbackSpill?
+    /// Finished commands, oldest first, for folding and re-running.
+    private var commandBlocks: [CommandBlock] = []
     private var sbAppended = 0 // total rows ever pushed to scrollback
 
     // MARK: inline hint (ghost text) state
@@ -166,12 +404,14 @@ final class Terminal {
 
     // MARK: selection & link highlight (absolute content coordinates)
 
-    enum SelectionMode { case character, word, line }
+    enum SelectionMode { case character, word, line, block }
 
     private var selAnchor: (line: Int, col: Int)?
     private var selHead: (line: Int, col: Int)?
     private var selMode = SelectionMode.character
     private var linkRange: (line: Int, lo: Int, hi: Int)?
+    /// Copy mode's cursor; nil outside copy mode.
+    private var copyCursor: (line: Int, col: Int)?
 
     // MARK: inline images (iTerm2 OSC 1337 File=)
 
@@ -198,11 +438,12 @@ final class Terminal {
     private var kittyStoreOrder: [UInt32] = []
     private var kittyChunks: (controls: [String: String], data: [UInt8])?
 
-    init(cols: Int, rows: Int) {
+    init(cols: Int, rows: Int, scrollback: Int = Terminal.defaultScrollback) {
         self.cols = max(2, cols)
         self.rows = max(2, rows)
         self.bottom = self.rows - 1
-        self.scrollback = RowRing(capacity: Terminal.maxScrollback)
+        self.scrollbackLimit = min(max(scrollback, 1), Terminal.maxScrollback)
+        self.scrollback = RowRing(capacity: scrollbackLimit)
         let blank = [Cell](repeating: Cell(), count: self.cols)
         self.screen = [[Cell]](repeating: blank, count: self.rows)
         self.inactiveScreen = self.screen
@@ -246,6 +487,13 @@ final class Terminal {
         return (mouseMode, mouseSGR)
     }
 
+    /// Whether shell integration has a command running (a C with no D yet).
+    var isRunningCommand: Bool {
+        lock.lock()
+        defer { lock.unlock() }
+        return runningCommand != nil
+    }
+
     /// Full reset (context-menu Reset Terminal).
     func hardReset() {
         lock.lock()
@@ -267,10 +515,11 @@ final class Terminal {
     /// and drop any selection.
     func userDidInput() {
         lock.lock()
-        let changed = viewOffset != 0 || selAnchor != nil
+        let changed = viewOffset != 0 || selAnchor != nil || copyCursor != nil
         viewOffset = 0
         selAnchor = nil
         selHead = nil
+        copyCursor = nil
         if changed { generation &+= 1 }
         lock.unlock()
         if changed { onChange?() }
@@ -280,7 +529,7 @@ final class Terminal {
     func scrollViewport(by lines: Int) {
         lock.lock()
         let old = viewOffset
-        viewOffset = min(max(0, viewOffset + lines), usingAlt ? 0 : scrollback.count)
+        viewOffset = min(max(0, viewOffset + lines), usingAlt ? 0 : scrollbackRowsLocked())
         let changed = viewOffset != old
         if changed { generation &+= 1 }
         lock.unlock()
@@ -349,10 +598,16 @@ final class Terminal {
         pendingOutput.removeAll(keepingCapacity: true)
         let title = pendingTitle
         pendingTitle = nil
+        let iconName = pendingIconName
+        pendingIconName = nil
+        let directory = pendingDirectory
+        pendingDirectory = nil
         let bell = pendingBell
         pendingBell = false
         let markerEvents = pendingMarkers
         pendingMarkers.removeAll(keepingCapacity: true)
+        let commandEvents = pendingCommands
+        pendingCommands.removeAll(keepingCapacity: true)
         let imageJobs = pendingImageWork
         pendingImageWork.removeAll(keepingCapacity: true)
         let wantMarkdown = pendingMarkdownRender && !markdownRenderInFlight
@@ -361,8 +616,11 @@ final class Terminal {
 
         if !out.isEmpty { onOutput?(out) }
         if let t = title { onTitle?(t) }
+        if let iconName { onIconName?(iconName) }
+        if let directory { onDirectory?(directory) }
         if bell { onBell?() }
         for (kind, exit) in markerEvents { onMarker?(kind, exit) }
+        for event in commandEvents { onCommand?(event) }
         onChange?()
 
         for job in imageJobs {
@@ -387,12 +645,16 @@ final class Terminal {
             snap.cells = [Cell](repeating: Cell(), count: needed)
         }
         let offset = viewOffset
-        let sbCount = scrollback.count
+        let dropped = sbAppended - scrollback.count
+        let folds = foldSpansLocked()
         let selection = normalizedSelection
         for r in 0..<rows {
+            let (abs, fold) = viewLineLocked(forViewRow: r, folds: folds)
             let srcRow: [Cell]
-            if r < offset {
-                srcRow = scrollback[sbCount - offset + r]
+            if let fold {
+                srcRow = summaryRow(fold.block)
+            } else if r < offset {
+                srcRow = scrollback[abs - dropped]
             } else {
                 srcRow = screen[r - offset]
             }
@@ -403,13 +665,8 @@ final class Terminal {
                 for c in n..<cols { snap.cells[base + c] = Cell() }
             }
 
-            let abs = sbAppended - offset + r
-            if let (s, e) = selection, abs >= s.line, abs <= e.line {
-                let lo = abs == s.line ? min(s.col, cols - 1) : 0
-                let hi = abs == e.line ? min(e.col, cols - 1) : cols - 1
-                if lo <= hi {
-                    for c in lo...hi { snap.cells[base + c].flags |= CellFlags.selected }
-                }
+            if let selection, let (lo, hi) = selectedColumns(selection, line: abs), lo <= hi {
+                for c in lo...hi { snap.cells[base + c].flags |= CellFlags.selected }
             }
             if let link = linkRange, link.line == abs {
                 let lo = min(max(link.lo, 0), cols - 1)
@@ -421,15 +678,19 @@ final class Terminal {
         let viewY = cy + offset
         snap.cursorY = viewY
         snap.cursorVisible = cursorVisible && viewY < rows
+        if let copy = copyCursor {
+            snap.cursorX = min(copy.col, cols - 1)
+            snap.cursorY = viewRowLocked(forLine: copy.line, folds: folds)
+            snap.cursorVisible = snap.cursorY >= 0 && snap.cursorY < rows
+        }
         snap.scrolledBack = offset > 0
         // Ghost text renders after the cursor, only on the live screen.
-        snap.ghost = (offset == 0 && !usingAlt) ? ghostText : ""
+        snap.ghost = (offset == 0 && !usingAlt && copyCursor == nil) ? ghostText : ""
 
         snap.images.removeAll(keepingCapacity: true)
         if !images.isEmpty && !usingAlt {
-            let viewTopAbs = sbAppended - offset
-            for img in images {
-                let viewRow = img.absLine - viewTopAbs
+            for img in images where !folds.contains(where: { ($0.start...$0.end).contains(img.absLine) }) {
+                let viewRow = viewRowLocked(forLine: img.absLine, folds: folds)
                 guard viewRow + img.cellRows > 0, viewRow < rows else { continue }
                 snap.images.append(SnapImage(
                     id: img.id, viewRow: viewRow, col: img.col,
@@ -467,10 +728,7 @@ final class Terminal {
                 if cy >= nr {
                     for _ in 0..<excess {
                         let row = grid.removeFirst()
-                        if keepHistory {
-                            scrollback.append(row)
-                            sbAppended += 1
-                        }
+                        if keepHistory { pushScrollback(row) }
                     }
                 } else {
                     grid.removeLast(excess)
@@ -492,7 +750,7 @@ final class Terminal {
         cx = min(cx, nc - 1)
         wrapPending = false
         tabs = Terminal.defaultTabs(cols: nc)
-        viewOffset = min(viewOffset, scrollback.count)
+        viewOffset = min(viewOffset, scrollbackRowsLocked())
     }
 
     // MARK: - byte processing (lock held)
@@ -773,11 +1031,11 @@ final class Terminal {
         }
 
         normalizeWideBoundaries(row: cy, lo: cx, hi: min(cx + w - 1, cols - 1))
-        var cell = Cell(glyph: ch, fg: pen.fg, bg: pen.bg, flags: pen.flags)
+        var cell = Cell(glyph: ch, fg: pen.fg, bg: pen.bg, flags: pen.flags, link: openHyperlink)
         if w == 2 { cell.flags |= CellFlags.wide }
         screen[cy][cx] = cell
         if w == 2 && cx + 1 < cols {
-            var cont = Cell(glyph: 0, fg: pen.fg, bg: pen.bg, flags: pen.flags)
+            var cont = Cell(glyph: 0, fg: pen.fg, bg: pen.bg, flags: pen.flags, link: openHyperlink)
             cont.flags |= CellFlags.wideContinuation
             screen[cy][cx + 1] = cont
         }
@@ -800,6 +1058,7 @@ final class Terminal {
         let fg = pen.fg
         let bg = pen.bg
         let flags = pen.flags
+        let link = openHyperlink
         var k = 0
         while k < n {
             if wrapPending {
@@ -814,7 +1073,7 @@ final class Terminal {
             normalizeWideBoundaries(row: cy, lo: x, hi: x + take - 1)
             screen[cy].withUnsafeMutableBufferPointer { row in
                 for j in 0..<take {
-                    row[x + j] = Cell(glyph: UInt32(p[k + j]), fg: fg, bg: bg, flags: flags)
+                    row[x + j] = Cell(glyph: UInt32(p[k + j]), fg: fg, bg: bg, flags: flags, link: link)
                 }
             }
             cx += take
@@ -869,15 +1128,23 @@ final class Terminal {
         }
     }
 
+    /// Adds a row to scrollback, handing the one it evicts to the spill.
+    private func pushScrollback(_ row: [Cell]) {
+        let oldest = sbAppended - scrollback.count
+        if let evicted = scrollback.append(row), let spill {
+            spill.append(line: oldest, text: rowToString(evicted))
+        }
+        sbAppended += 1
+    }
+
     private func scrollUp(_ n: Int) {
         let count = min(n, bottom - top + 1)
         for _ in 0..<count {
             let row = screen.remove(at: top)
             if top == 0 && !usingAlt {
-                scrollback.append(row)
-                sbAppended += 1
+                pushScrollback(row)
                 if viewOffset > 0 {
-                    viewOffset = min(viewOffset + 1, scrollback.count)
+                    viewOffset = min(viewOffset + 1, scrollbackRowsLocked())
                 }
             }
             screen.insert(blankRow(), at: bottom)
@@ -906,6 +1173,7 @@ final class Terminal {
             for y in 0..<rows { screen[y] = blankRow() }
         case 3:
             scrollback.removeAll()
+            spill?.removeAll()
             viewOffset = 0
             images.removeAll()
         default:
@@ -997,6 +1265,7 @@ final class Terminal {
 
     private func fullReset() {
         pen = Pen()
+        openHyperlink = 0
         cx = 0
         cy = 0
         top = 0
@@ -1310,8 +1579,16 @@ final class Terminal {
         guard code.count <= 4, let n = Int(String(decoding: code, as: UTF8.self)) else { return }
         switch n {
         case 0, 1, 2:
-            pendingTitle = String(decoding: oscBuf[(sep + 1)...], as: UTF8.self)
-        case 133:
+            let text = String(decoding: oscBuf[(sep + 1)...], as: UTF8.self)
+            if n != 1 { pendingTitle = text }
+            if n != 2 { pendingIconName = text }
+        case 7:
+            if let dir = Self.directory(fromOSC7: String(decoding: oscBuf[(sep + 1)...], as: UTF8.self)) {
+                pendingDirectory = dir
+            }
+        case 8:
+            handleHyperlink(oscBuf[(sep + 1)...])
+        case 133, 633:
             handleSemanticMarker(Array(oscBuf[(sep + 1)...]))
         case 1337:
             handleITerm2Payload(Array(oscBuf[(sep + 1)...]))
@@ -1320,6 +1597,62 @@ final class Terminal {
         }
     }
 
+    /// Local path from an OSC 7 `file://host/path` report. Nil for other
+    /// hosts — a shell inside ssh reports its remote cwd, which means
+    /// nothing on this Mac.
+    static func directory(fromOSC7 payload: String, localHosts: Set<String> = localHostNames) -> String? {
+        guard let url = URL(string: payload), url.scheme == "file" else { return nil }
+        let host = url.host?.lowercased() ?? ""
+        guard host.isEmpty || localHosts.contains(host) else { return nil }
+        let path = url.path
+        return path.hasPrefix("/") ? path : nil
+    }
+
+    /// gethostname(3) is local and instant, unlike ProcessInfo.hostName,
+    /// which may wait on DNS.
+    static let localHostNames: Set<String> = {
+        var names: Set<String> = ["localhost"]
+        var buf = [CChar](repeating: 0, count: Int(MAXHOSTNAMELEN) + 1)
+        if gethostname(&buf, buf.count - 1) == 0 {
+            let name = String(cString: buf).lowercased()
+            names.insert(name)
+            if let short = name.split(separator: ".").first { names.insert(String(short)) }
+        }
+        return names
+    }()
+
+    // MARK: - OSC 8 hyperlinks
+
+    /// Longest URI kept; a longer one closes the open link instead.
+    static let maxHyperlinkLength = 2048
+
+    /// `params;URI` opens a link for the cells printed next, reusing the
+    /// number of one already seen; an empty URI closes it. Numbers wrap
+    /// after 65535, so a link that old may read as a newer one.
+    private func handleHyperlink(_ payload: ArraySlice<UInt8>) {
+        guard let sep = payload.firstIndex(of: UInt8(ascii: ";")) else { return }
+        let uri = payload[(sep + 1)...]
+        guard !uri.isEmpty, uri.count <= Self.maxHyperlinkLength else {
+            openHyperlink = 0
+            return
+        }
+        var id: String?
+        for param in String(decoding: payload[..<sep], as: UTF8.self).split(separator: ":") {
+            let kv = param.split(separator: "=", maxSplits: 1)
+            if kv.count == 2, kv[0] == "id" { id = String(kv[1]) }
+        }
+        let link = TerminalHyperlink(url: String(decoding: uri, as: UTF8.self), id: id)
+        if let number = hyperlinkNumbers[link] {
+            openHyperlink = number
+            return
+        }
+        lastHyperlink = lastHyperlink == .max ? 1 : lastHyperlink + 1
+        if let old = hyperlinks[lastHyperlink] { hyperlinkNumbers[old] = nil }
+        hyperlinks[lastHyperlink] = link
+        hyperlinkNumbers[link] = lastHyperlink
+        openHyperlink = lastHyperlink
+    }
+
     // MARK: - OSC 1337 inline images
 
     /// Under the terminal lock: parse cheap metadata and queue the heavy
@@ -1788,13 +2121,20 @@ final class Terminal {
 
     // OSC 133 semantic prompts: A = prompt start, B = input start,
     // C = command output start, D;<exit> = command finished.
+    /// OSC 133 and VS Code's OSC 633, which shares its A–D and adds E, the
+    /// command line about to run.
     private func handleSemanticMarker(_ payload: [UInt8]) {
         guard !usingAlt, let kind = payload.first else { return }
+        let params = payload.split(separator: UInt8(ascii: ";"), omittingEmptySubsequences: false).dropFirst()
+        if kind == UInt8(ascii: "E") {
+            reportedCommandLine = params.first.map { Self.unescapeOSC633(Array($0)) }
+            return
+        }
         guard kind == UInt8(ascii: "A") || kind == UInt8(ascii: "B")
             || kind == UInt8(ascii: "C") || kind == UInt8(ascii: "D") else { return }
         var exitCode = 0
-        if kind == UInt8(ascii: "D"), payload.count > 2, payload[1] == UInt8(ascii: ";") {
-            exitCode = Int(String(decoding: payload[2...], as: UTF8.self)) ?? 0
+        if kind == UInt8(ascii: "D"), let status = params.first {
+            exitCode = Int(String(decoding: status, as: UTF8.self)) ?? 0
         }
         markers.append(Marker(kind: kind, line: sbAppended + cy, col: cx, exitCode: exitCode))
         pendingMarkers.append((kind, exitCode))
@@ -1802,6 +2142,31 @@ final class Terminal {
             markers.removeFirst(markers.count - 256)
         }
 
+        if kind == UInt8(ascii: "A") {
+            reportedCommandLine = nil
+        } else if kind == UInt8(ascii: "C") {
+            let command = reportedCommandLine ?? typedCommandLocked()
+            reportedCommandLine = nil
+            runningCommand = (command, sbAppended + cy, Date())
+            pendingCommands.append(.started(command: command, line: sbAppended + cy))
+        } else if kind == UInt8(ascii: "D"), let running = runningCommand {
+            // A D with no C is an empty prompt line, not a command.
+            runningCommand = nil
+            // Output ends on the row above a D at the start of a line.
+            let lastLine = cx == 0 ? sbAppended + cy - 1 : sbAppended + cy
+            commandBlocks.append(CommandBlock(
+                command: running.command, exitCode: exitCode, line: running.line,
+                lastLine: max(lastLine, running.line - 1)))
+            let first = sbAppended - scrollback.count
+            commandBlocks.removeAll { $0.lastLine < first }
+            if commandBlocks.count > 512 {
+                commandBlocks.removeFirst(commandBlocks.count - 256)
+            }
+            pendingCommands.append(.finished(
+                command: running.command, exitCode: exitCode,
+                duration: Date().timeIntervalSince(running.started), line: running.line))
+        }
+
         // Input tracking for inline hints: B = input begins, C = command runs.
         if kind == UInt8(ascii: "B") {
             inputActive = true
@@ -1999,6 +2364,44 @@ final class Terminal {
         return textBetween(startLine: promptLine, startCol: promptCol, endLine: curAbs, endCol: cx)
     }
 
+    /// Everything typed at the prompt (OSC 133 B) through the end of the
+    /// cursor's row, wherever the cursor is in it: what Enter would run.
+    /// Rows of a long line that wrapped are joined with a space. Nil
+    /// without shell integration or once the command has started.
+    func pendingInput() -> String? {
+        lock.lock()
+        defer { lock.unlock() }
+        guard inputActive else { return nil }
+        let curAbs = sbAppended + cy
+        guard curAbs >= promptLine else { return nil }
+        let text = textBetween(startLine: promptLine, startCol: promptCol, endLine: curAbs, endCol: cols)
+            .replacingOccurrences(of: "\n", with: " ")
+        return text.trimmingCharacters(in: .whitespaces).isEmpty ? nil : text
+    }
+
+    /// The space-delimited word of the pending input under a view cell, nil
+    /// when the cell is blank or outside what's typed at the prompt.
+    func inputWord(viewRow: Int, col: Int) -> String? {
+        lock.lock()
+        defer { lock.unlock() }
+        guard inputActive else { return nil }
+        let line = absLineLocked(forViewRow: viewRow)
+        guard line >= promptLine, line <= sbAppended + cy, line > promptLine || col >= promptCol,
+              let row = rowAtAbsoluteLine(line), col >= 0, col < row.count else { return nil }
+        let start = line == promptLine ? promptCol : 0
+        func filled(_ c: Int) -> Bool { row[c].glyph > 32 || row[c].flags & CellFlags.wideContinuation != 0 }
+        guard filled(col) else { return nil }
+        var lo = col
+        var hi = col
+        while lo > start, filled(lo - 1) { lo -= 1 }
+        while hi + 1 < row.count, filled(hi + 1) { hi += 1 }
+        var word = ""
+        for c in lo...hi where row[c].flags & CellFlags.wideContinuation == 0 {
+            if let scalar = Unicode.Scalar(row[c].glyph) { word.unicodeScalars.append(scalar) }
+        }
+        return word
+    }
+
     /// Recompute the ghost suggestion after input changes. Fast path: skip when
     /// the input is unchanged. Only suggests when the cursor is at the end of a
     /// non-trivial input line (avoids mid-edit noise).
@@ -2098,6 +2501,174 @@ final class Terminal {
         return textForLines(from: from, to: dropped + total)
     }
 
+    /// The last `count` rows of scrollback + screen with their cells, for
+    /// exports that keep colors and attributes. Lines only the spill still
+    /// has come first, as plain text in the default colors.
+    func historyRows(lines count: Int) -> [[Cell]] {
+        lock.lock()
+        let spill = self.spill
+        let dropped = sbAppended - scrollback.count
+        let wanted = sbAppended + rows - count
+        let memory = (max(dropped, wanted)..<(sbAppended + rows)).compactMap(rowAtAbsoluteLine)
+        lock.unlock()
+        guard let spill, wanted < dropped else { return memory }
+        let disk = spill.read(from: wanted, count: dropped - wanted).lines
+        return disk.map(plainRow) + memory
+    }
+
+    /// A spilled line back as cells: one per character, two for a wide
+    /// one. Combining marks don't survive the trip.
+    private func plainRow(_ text: String) -> [Cell] {
+        var row: [Cell] = []
+        for scalar in text.unicodeScalars {
+            let width = charWidth(scalar.value)
+            guard width > 0 else { continue }
+            row.append(Cell(glyph: scalar.value))
+            if width == 2 {
+                row[row.count - 1].flags |= CellFlags.wide
+                row.append(Cell(flags: CellFlags.wideContinuation))
+            }
+        }
+        return row
+    }
+
+    /// Scrollback's rows and bytes of cells in memory (the screen aside),
+    /// the rows memory may hold (`scrollbackLimit` unless the budget capped
+    /// it), and the rows only the spill still has.
+    var scrollbackUsage: (lines: Int, bytes: Int, capacity: Int, spilled: Int) {
+        lock.lock()
+        defer { lock.unlock() }
+        return (scrollback.count, scrollback.bytes, scrollback.capacity, spill?.lines.count ?? 0)
+    }
+
+    /// Keeps at most `lines` rows of scrollback in memory (nil lifts the
+    /// cap back to `scrollbackLimit`). Rows over a lower cap go to the
+    /// spill, when there is one, as if they had scrolled out.
+    func capScrollback(lines: Int?) {
+        lock.lock()
+        let oldest = sbAppended - scrollback.count
+        let dropped = scrollback.setCapacity(min(lines ?? scrollbackLimit, scrollbackLimit))
+        if let spill {
+            for (offset, row) in dropped.enumerated() { spill.append(line: oldest + offset, text: rowToString(row)) }
+        }
+        viewOffset = min(viewOffset, scrollbackRowsLocked())
+        if !dropped.isEmpty { generation &+= 1 }
+        lock.unlock()
+        if !dropped.isEmpty { onChange?() }
+    }
+
+    /// Hands rows that scroll out of memory to `spill` from now on; nil
+    /// stops spilling and deletes what was written.
+    func spillScrollback(to spill: ScrollbackSpill?) {
+        lock.lock()
+        let old = self.spill
+        self.spill = spill
+        lock.unlock()
+        if old !== spill { old?.removeAll() }
+    }
+
+    var spillsScrollback: Bool {
+        lock.lock()
+        defer { lock.unlock() }
+        return spill != nil
+    }
+
+    /// Up to `count` lines of plain text from absolute `line` on: from the
+    /// spill for lines memory no longer holds, then scrollback and screen.
+    /// `firstLine` is where the lines actually start, past any that are
+    /// gone for good.
+    func scrollbackLines(from line: Int, count: Int) -> (firstLine: Int, lines: [String]) {
+        lock.lock()
+        let spill = self.spill
+        let dropped = sbAppended - scrollback.count
+        let end = min(line + max(count, 0), sbAppended + rows)
+        let memoryFrom = max(line, dropped)
+        let memory = memoryFrom < end ? (memoryFrom..<end).map { rowAtAbsoluteLine($0).map(rowToString) ?? "" } : []
+        lock.unlock()
+        guard let spill, line < dropped, line < end else { return (memoryFrom, memory) }
+        let disk = spill.read(from: line, count: min(end, dropped) - line)
+        guard !disk.lines.isEmpty else { return (memoryFrom, memory) }
+        return (disk.firstLine, disk.lines + memory)
+    }
+
+    /// Every match of `query` (a regex when `regex`) in scrollback + screen,
+    /// spilled lines included, oldest first, stopping at `limit`. Lines are
+    /// absolute, the numbering OSC 133 markers use, and columns count cells,
+    /// so a wide glyph spans two. Rows are copied under the lock and
+    /// searched outside it; a match can't cross a soft wrap.
+    func searchScrollback(
+        _ query: String, regex: Bool, caseSensitive: Bool = false, limit: Int = 1000
+    ) -> Result<ScrollbackSearch, RegexScratchpadError> {
+        guard !query.isEmpty else { return .failure(.invalidPattern("empty query", offset: nil)) }
+        let expression: NSRegularExpression
+        switch RegexScratchpad.compile(
+            regex ? query : NSRegularExpression.escapedPattern(for: query), flags: caseSensitive ? "" : "i") {
+        case .success(let compiled): expression = compiled
+        case .failure(let error): return .failure(error)
+        }
+        lock.lock()
+        let spill = self.spill
+        let firstLine = sbAppended - scrollback.count
+        let copied = (firstLine..<(sbAppended + rows)).compactMap(rowAtAbsoluteLine)
+        lock.unlock()
+        // Lines memory no longer holds are searched from the spill, as text.
+        var spilled: (firstLine: Int, lines: [String]) = (firstLine, [])
+        if let held = spill?.lines, held.lowerBound < firstLine, let spill {
+            spilled = spill.read(from: held.lowerBound, count: firstLine - held.lowerBound)
+        }
+
+        var search = ScrollbackSearch(
+            firstLine: spilled.lines.isEmpty ? firstLine : spilled.firstLine, lastLine: firstLine + copied.count - 1)
+        // `columns` is the cell each UTF-16 unit of `text` came from; false
+        // once `limit` is reached.
+        func scan(_ text: String, columns: [Int], end: Int, line: Int) -> Bool {
+            let range = NSRange(location: 0, length: columns.count)
+            for match in expression.matches(in: text, range: range) where match.range.length > 0 {
+                guard search.matches.count < limit else {
+                    search.truncated = true
+                    return false
+                }
+                let column = columns[match.range.location]
+                let upper = match.range.upperBound
+                search.matches.append(ScrollbackMatch(
+                    line: line, column: column,
+                    length: (upper < columns.count ? columns[upper] : end) - column, text: text))
+            }
+            return true
+        }
+        for (offset, line) in spilled.lines.enumerated() {
+            var text = ""
+            var columns: [Int] = []
+            var column = 0
+            for scalar in line.unicodeScalars {
+                let width = charWidth(scalar.value)
+                guard width > 0 else { continue }
+                text.unicodeScalars.append(scalar)
+                columns.append(contentsOf: repeatElement(column, count: scalar.utf16.count))
+                column += width
+            }
+            guard column > 0 else { continue }
+            guard scan(text, columns: columns, end: column, line: spilled.firstLine + offset) else {
+                return .success(search)
+            }
+        }
+        for (offset, row) in copied.enumerated() {
+            var end = row.count
+            while end > 0 && (row[end - 1].glyph == 0 || row[end - 1].glyph == 0x20) { end -= 1 }
+            guard end > 0 else { continue }
+            var text = ""
+            var columns: [Int] = []
+            for column in 0..<end where row[column].flags & CellFlags.wideContinuation == 0 {
+                let glyph = row[column].glyph
+                let scalar: Unicode.Scalar = glyph == 0 ? " " : Unicode.Scalar(glyph) ?? " "
+                text.unicodeScalars.append(scalar)
+                columns.append(contentsOf: repeatElement(column, count: scalar.utf16.count))
+            }
+            guard scan(text, columns: columns, end: end, line: firstLine + offset) else { return .success(search) }
+        }
+        return .success(search)
+    }
+
     /// Text between two marker positions (line + column), inclusive of the
     /// start position, exclusive of the end position.
     private func textBetween(
@@ -2165,6 +2736,42 @@ final class Terminal {
         return markers.last(where: { $0.kind == UInt8(ascii: "D") })?.exitCode
     }
 
+    /// What was typed between the last B (input start) and the C that just
+    /// ran it, without the prompt. Nil without a B or when nothing was typed.
+    private func typedCommandLocked() -> String? {
+        guard markers.count >= 2, markers[markers.count - 1].kind == UInt8(ascii: "C"),
+              markers[markers.count - 2].kind == UInt8(ascii: "B")
+        else { return nil }
+        let (b, c) = (markers[markers.count - 2], markers[markers.count - 1])
+        let text = textBetween(startLine: b.line, startCol: b.col, endLine: c.line, endCol: c.col)
+            .trimmingCharacters(in: .whitespacesAndNewlines)
+        return text.isEmpty ? nil : text
+    }
+
+    /// OSC 633 escapes `\` as `\\` and `;` and control bytes as `\xNN`.
+    static func unescapeOSC633(_ bytes: [UInt8]) -> String {
+        var out: [UInt8] = []
+        var i = 0
+        while i < bytes.count {
+            if bytes[i] == UInt8(ascii: "\\"), i + 1 < bytes.count {
+                if bytes[i + 1] == UInt8(ascii: "\\") {
+                    out.append(UInt8(ascii: "\\"))
+                    i += 2
+                    continue
+                }
+                if bytes[i + 1] == UInt8(ascii: "x"), i + 3 < bytes.count,
+                   let value = UInt8(String(decoding: bytes[(i + 2)...(i + 3)], as: UTF8.self), radix: 16) {
+                    out.append(value)
+                    i += 4
+                    continue
+                }
+            }
+            out.append(bytes[i])
+            i += 1
+        }
+        return String(decoding: out, as: UTF8.self)
+    }
+
     /// The most recent command line(s) as typed (between input start and
     /// output start markers). Includes the prompt text.
     func lastCommandLine() -> String? {
@@ -2186,7 +2793,7 @@ final class Terminal {
 
     @inline(__always)
     private func absLineLocked(forViewRow r: Int) -> Int {
-        sbAppended - viewOffset + r
+        viewLineLocked(forViewRow: r, folds: foldSpansLocked()).line
     }
 
     private static let wordChars = CharacterSet.alphanumerics
@@ -2219,28 +2826,57 @@ final class Terminal {
             selAnchor = (line, c)
             selHead = (line, c)
         case .word:
-            if let chars = rowChars(absLine: line) {
-                var lo = c
-                var hi = c
-                func isWord(_ ch: Character) -> Bool {
-                    ch.unicodeScalars.allSatisfy { Terminal.wordChars.contains($0) }
-                }
-                guard isWord(chars[c]) else {
-                    selAnchor = (line, c)
-                    selHead = (line, c)
-                    return
-                }
-                while lo > 0 && isWord(chars[lo - 1]) { lo -= 1 }
-                while hi < cols - 1 && isWord(chars[hi + 1]) { hi += 1 }
-                selAnchor = (line, lo)
-                selHead = (line, hi)
+            if let token = smartTokenLocked(line: line, column: c) {
+                selAnchor = (line, token.start)
+                selHead = (line, token.end)
+            } else {
+                selAnchor = (line, c)
+                selHead = (line, c)
             }
         case .line:
             selAnchor = (line, 0)
             selHead = (line, cols - 1)
+        case .block:
+            selAnchor = (line, c)
+            selHead = (line, c)
         }
     }
 
+    /// Selects the smart token (or word) at absolute `line`, `column`, as a
+    /// double-click there would, and reports it.
+    func smartSelect(line: Int, column: Int) -> SmartToken? {
+        lock.lock()
+        defer { lock.unlock() }
+        guard let token = smartTokenLocked(line: line, column: min(max(column, 0), cols - 1)) else { return nil }
+        selMode = .word
+        selAnchor = (line, token.start)
+        selHead = (line, token.end)
+        generation &+= 1
+        return token
+    }
+
+    /// The URL, path, hash or other `SmartToken` under the cell, falling
+    /// back to the run of word characters around it.
+    private func smartTokenLocked(line: Int, column c: Int) -> SmartToken? {
+        guard let chars = rowChars(absLine: line), let row = rowAtAbsoluteLine(line) else { return nil }
+        var found = SmartToken.find(in: chars, at: c)
+        if found == nil {
+            func isWord(_ ch: Character) -> Bool {
+                ch.unicodeScalars.allSatisfy { Terminal.wordChars.contains($0) }
+            }
+            guard isWord(chars[c]) else { return nil }
+            var lo = c
+            var hi = c
+            while lo > 0 && isWord(chars[lo - 1]) { lo -= 1 }
+            while hi < cols - 1 && isWord(chars[hi + 1]) { hi += 1 }
+            found = (.word, lo...hi)
+        }
+        guard let (kind, columns) = found else { return nil }
+        return SmartToken(
+            kind: kind, line: line, start: columns.lowerBound, end: columns.upperBound,
+            text: cellText(row, from: columns.lowerBound, through: columns.upperBound))
+    }
+
     func selectionExtend(viewRow: Int, col: Int) {
         lock.lock()
         defer {
@@ -2270,32 +2906,36 @@ final class Terminal {
         return (a, h)
     }
 
+    /// The columns `selection` covers on absolute `line`, clamped to the
+    /// grid. A block selection spans the same columns on every line.
+    private func selectedColumns(
+        _ selection: (start: (line: Int, col: Int), end: (line: Int, col: Int)), line: Int
+    ) -> (lo: Int, hi: Int)? {
+        let (s, e) = selection
+        guard line >= s.line, line <= e.line else { return nil }
+        if selMode == .block, let a = selAnchor, let h = selHead {
+            return (min(a.col, h.col, cols - 1), min(max(a.col, h.col), cols - 1))
+        }
+        return (line == s.line ? min(s.col, cols - 1) : 0, line == e.line ? min(e.col, cols - 1) : cols - 1)
+    }
+
     func selectedText() -> String? {
         lock.lock()
         defer { lock.unlock() }
+        return selectedTextLocked()
+    }
+
+    private func selectedTextLocked() -> String? {
         guard let (s, e) = normalizedSelection else { return nil }
         var lines: [String] = []
         for line in s.line...e.line {
             guard let row = rowAtAbsoluteLine(line) else { continue }
-            let lo = line == s.line ? s.col : 0
-            var hi = line == e.line ? e.col : cols - 1
-            hi = min(hi, row.count - 1)
-            guard lo <= hi else {
-                lines.append("")
-                continue
-            }
-            var text = ""
-            for c in lo...hi {
-                let cell = row[c]
-                if cell.flags & CellFlags.wideContinuation != 0 { continue }
-                if cell.glyph != 0, let us = Unicode.Scalar(cell.glyph) {
-                    text.unicodeScalars.append(us)
-                } else {
-                    text.append(" ")
-                }
-            }
+            let block = selMode == .block ? selectedColumns((s, e), line: line) : nil
+            var text = cellText(
+                row, from: block?.lo ?? (line == s.line ? s.col : 0),
+                through: block?.hi ?? (line == e.line ? e.col : cols - 1))
             // Trim trailing padding except for a mid-line selection end.
-            if line != e.line || e.col >= cols - 1 {
+            if block != nil || line != e.line || e.col >= cols - 1 {
                 while text.hasSuffix(" ") { text.removeLast() }
             }
             lines.append(text)
@@ -2304,6 +2944,432 @@ final class Terminal {
         return joined.isEmpty ? nil : joined
     }
 
+    /// Cells `lo...hi` of `row` as text: blanks become spaces and a wide
+    /// glyph is read once.
+    private func cellText(_ row: [Cell], from lo: Int, through hi: Int) -> String {
+        let hi = min(hi, row.count - 1)
+        guard lo <= hi else { return "" }
+        var text = ""
+        for c in lo...hi {
+            let cell = row[c]
+            if cell.flags & CellFlags.wideContinuation != 0 { continue }
+            if cell.glyph != 0, let us = Unicode.Scalar(cell.glyph) {
+                text.unicodeScalars.append(us)
+            } else {
+                text.append(" ")
+            }
+        }
+        return text
+    }
+
+    /// Pulls `mode`'s rectangle or field out of absolute `lines`, or out of
+    /// the current selection's lines when nil. A block keeps one value per
+    /// line, blank where the rectangle is empty, so rows stay aligned; a
+    /// field skips the lines that don't have it.
+    func scrollbackExtract(
+        lines: ClosedRange<Int>? = nil, mode: ScrollbackExtractMode
+    ) -> Result<ScrollbackExtraction, ScrollbackExtractError> {
+        if case .field(0, _) = mode { return .failure(.invalidField) }
+        lock.lock()
+        defer { lock.unlock() }
+        let selection = normalizedSelection
+        guard let requested = lines ?? selection.map({ $0.start.line...$0.end.line }) else {
+            return .failure(.noSelection)
+        }
+        let first = max(requested.lowerBound, sbAppended - scrollback.count)
+        let last = min(requested.upperBound, sbAppended + rows - 1)
+        guard first <= last else { return .failure(.outOfRange) }
+        var values: [String] = []
+        for line in first...last {
+            guard let row = rowAtAbsoluteLine(line) else { continue }
+            switch mode {
+            case .block(let columns):
+                var span = (lo: 0, hi: cols - 1)
+                if let columns {
+                    span = (max(columns.lowerBound, 0), columns.upperBound)
+                } else if lines == nil, selMode == .block, let selection,
+                          let selected = selectedColumns(selection, line: line) {
+                    span = selected
+                }
+                var text = cellText(row, from: span.lo, through: span.hi)
+                while text.hasSuffix(" ") { text.removeLast() }
+                values.append(text)
+            case .field(let n, let separator):
+                if let value = ScrollbackExtractMode.field(n, of: rowToString(row), separator: separator) {
+                    values.append(value)
+                }
+            }
+        }
+        return .success(ScrollbackExtraction(firstLine: first, lastLine: last, values: values))
+    }
+
+    // MARK: - copy mode (keyboard selection, driven over the control socket)
+
+    /// Starts copy mode at the shell's cursor, or reports where it is.
+    func copyModeEnter() -> CopyModeStatus {
+        lock.lock()
+        if copyCursor == nil {
+            copyCursor = (sbAppended + cy, min(cx, cols - 1))
+            generation &+= 1
+        }
+        let status = copyModeStatusLocked()!
+        lock.unlock()
+        onChange?()
+        return status
+    }
+
+    /// Leaves copy mode, dropping its selection and scrolling back to the
+    /// live screen.
+    func copyModeExit() {
+        lock.lock()
+        let active = copyCursor != nil
+        if active {
+            copyCursor = nil
+            selAnchor = nil
+            selHead = nil
+            viewOffset = 0
+            generation &+= 1
+        }
+        lock.unlock()
+        if active { onChange?() }
+    }
+
+    var copyModeStatus: CopyModeStatus? {
+        lock.lock()
+        defer { lock.unlock() }
+        return copyModeStatusLocked()
+    }
+
+    func copyModeMove(_ motion: CopyModeMotion, count: Int = 1) -> Result<CopyModeStatus, CopyModeError> {
+        copyModeUpdate { line, col in
+            let (first, last) = self.copyModeBoundsLocked()
+            for _ in 0..<max(count, 1) {
+                switch motion {
+                case .left: col = max(col - 1, 0)
+                case .right: col = min(col + 1, self.cols - 1)
+                case .up: line = max(line - 1, first)
+                case .down: line = min(line + 1, last)
+                case .pageUp: line = max(line - self.rows, first)
+                case .pageDown: line = min(line + self.rows, last)
+                case .lineStart: col = 0
+                case .lineEnd:
+                    let chars = self.rowChars(absLine: line) ?? []
+                    col = chars.lastIndex { $0 != " " } ?? 0
+                case .top: (line, col) = (first, 0)
+                case .bottom: (line, col) = (last, 0)
+                case .wordNext: (line, col) = self.nextWordLocked(from: (line, col), last: last)
+                case .wordPrevious: (line, col) = self.previousWordLocked(from: (line, col), first: first)
+                }
+            }
+        }
+    }
+
+    /// Moves the cursor to (`line`, `column`), clamped to what is held.
+    func copyModeJump(line: Int, column: Int) -> Result<CopyModeStatus, CopyModeError> {
+        copyModeUpdate { current, col in
+            let (first, last) = self.copyModeBoundsLocked()
+            current = min(max(line, first), last)
+            col = min(max(column, 0), self.cols - 1)
+        }
+    }
+
+    /// Starts a selection of `mode` at the cursor — `word` and `line`
+    /// select the cursor's word or line at once — which later moves
+    /// extend. Nil clears the selection.
+    func copyModeSelect(_ mode: SelectionMode?) -> Result<CopyModeStatus, CopyModeError> {
+        lock.lock()
+        guard let cursor = copyCursor else {
+            lock.unlock()
+            return .failure(.inactive)
+        }
+        selMode = mode ?? .character
+        selAnchor = nil
+        selHead = nil
+        if let mode {
+            var (lo, hi) = (cursor.col, cursor.col)
+            if mode == .word, let chars = rowChars(absLine: cursor.line), cursor.col < chars.count,
+               Self.isWordCharacter(chars[cursor.col]) {
+                while lo > 0 && Self.isWordCharacter(chars[lo - 1]) { lo -= 1 }
+                while hi < cols - 1 && Self.isWordCharacter(chars[hi + 1]) { hi += 1 }
+            } else if mode == .line {
+                (lo, hi) = (0, cols - 1)
+            }
+            selAnchor = (cursor.line, lo)
+            selHead = (cursor.line, hi)
+            if mode == .word { copyCursor = (cursor.line, hi) }
+        }
+        generation &+= 1
+        let status = copyModeStatusLocked()!
+        lock.unlock()
+        onChange?()
+        return .success(status)
+    }
+
+    /// Moves the cursor to the next match of `query` after it (before it
+    /// when `backward`), wrapping around the buffer.
+    func copyModeSearch(
+        _ query: String, backward: Bool = false, regex: Bool = false, caseSensitive: Bool = false
+    ) -> Result<CopyModeStatus, CopyModeError> {
+        guard let cursor = copyModeStatus else { return .failure(.inactive) }
+        let matches: [ScrollbackMatch]
+        switch searchScrollback(query, regex: regex, caseSensitive: caseSensitive, limit: .max) {
+        case .success(let search): matches = search.matches
+        case .failure(let error): return .failure(.invalidPattern("\(error)"))
+        }
+        let here = (cursor.line, cursor.column)
+        let target = backward
+            ? matches.last { ($0.line, $0.column) < here } ?? matches.last
+            : matches.first { ($0.line, $0.column) > here } ?? matches.first
+        guard let target else { return .failure(.noMatch) }
+        return copyModeJump(line: target.line, column: target.column)
+    }
+
+    /// Applies `move` to the cursor, extends any selection to it and
+    /// scrolls it into view.
+    private func copyModeUpdate(
+        _ move: (inout Int, inout Int) -> Void
+    ) -> Result<CopyModeStatus, CopyModeError> {
+        lock.lock()
+        guard var cursor = copyCursor else {
+            lock.unlock()
+            return .failure(.inactive)
+        }
+        move(&cursor.line, &cursor.col)
+        copyCursor = cursor
+        if let anchor = selAnchor {
+            if selMode == .line {
+                let down = cursor.line >= anchor.line
+                selAnchor = (anchor.line, down ? 0 : cols - 1)
+                selHead = (cursor.line, down ? cols - 1 : 0)
+            } else {
+                selHead = cursor
+            }
+        }
+        let viewRow = viewRowLocked(forLine: cursor.line, folds: foldSpansLocked())
+        if viewRow < 0 {
+            viewOffset -= viewRow
+        } else if viewRow >= rows {
+            viewOffset -= viewRow - rows + 1
+        }
+        viewOffset = min(max(viewOffset, 0), usingAlt ? 0 : scrollbackRowsLocked())
+        generation &+= 1
+        let status = copyModeStatusLocked()!
+        lock.unlock()
+        onChange?()
+        return .success(status)
+    }
+
+    /// The oldest line copy mode can reach and the screen's bottom line.
+    private func copyModeBoundsLocked() -> (first: Int, last: Int) {
+        (usingAlt ? sbAppended : sbAppended - scrollback.count, sbAppended + rows - 1)
+    }
+
+    private func copyModeStatusLocked() -> CopyModeStatus? {
+        guard let cursor = copyCursor else { return nil }
+        let (first, last) = copyModeBoundsLocked()
+        let selecting = selAnchor != nil
+        return CopyModeStatus(
+            line: cursor.line, column: cursor.col, firstLine: first, lastLine: last,
+            viewOffset: viewOffset, selection: selecting ? selMode : nil,
+            text: selecting ? selectedTextLocked() : nil)
+    }
+
+    private static func isWordCharacter(_ ch: Character) -> Bool {
+        ch.unicodeScalars.allSatisfy { wordChars.contains($0) }
+    }
+
+    /// The start of the next word after `position`; a line break ends a word.
+    private func nextWordLocked(from position: (line: Int, col: Int), last: Int) -> (Int, Int) {
+        var (line, col) = position
+        var chars = rowChars(absLine: line) ?? []
+        var inWord = col < chars.count && Self.isWordCharacter(chars[col])
+        while true {
+            col += 1
+            if col >= cols {
+                guard line < last else { return position }
+                line += 1
+                col = 0
+                chars = rowChars(absLine: line) ?? []
+                inWord = false
+            }
+            let word = col < chars.count && Self.isWordCharacter(chars[col])
+            if word && !inWord { return (line, col) }
+            inWord = word
+        }
+    }
+
+    /// The start of the word before `position` (or of the one it is in).
+    private func previousWordLocked(from position: (line: Int, col: Int), first: Int) -> (Int, Int) {
+        var (line, col) = position
+        var chars = rowChars(absLine: line) ?? []
+        func isWord(_ col: Int) -> Bool { col < chars.count && Self.isWordCharacter(chars[col]) }
+        repeat {
+            if col == 0 {
+                guard line > first else { return (line, 0) }
+                line -= 1
+                chars = rowChars(absLine: line) ?? []
+                col = cols - 1
+            } else {
+                col -= 1
+            }
+        } while !isWord(col)
+        while col > 0 && isWord(col - 1) { col -= 1 }
+        return (line, col)
+    }
+
+    // MARK: - command output folding
+
+    private typealias FoldSpan = (start: Int, end: Int, block: CommandBlock)
+
+    /// Finished commands that printed something, oldest first.
+    func commandOutputBlocks() -> [CommandBlock] {
+        finishedCommands().filter { $0.lineCount > 0 }
+    }
+
+    /// Every finished command still in scrollback, oldest first, whether
+    /// or not it printed anything.
+    func finishedCommands() -> [CommandBlock] {
+        lock.lock()
+        defer { lock.unlock() }
+        return commandBlocks
+    }
+
+    /// The output of the finished command whose output began at absolute
+    /// `line` (the latest when nil), as plain lines; lines that left memory
+    /// are read back from the spill, and ones gone for good are skipped.
+    func commandOutput(line: Int?) -> Result<(block: CommandBlock, lines: [String]), CommandFoldError> {
+        guard let block = commandOutputBlocks().last(where: { line == nil || $0.line == line }) else {
+            return .failure(.notFound(line))
+        }
+        return .success((block, scrollbackLines(from: block.line, count: block.lineCount).lines))
+    }
+
+    /// Folds or unfolds the output of the command whose output began at
+    /// absolute `line` (the latest command when nil); a nil `folded`
+    /// toggles. The line at the top of the viewport stays put.
+    func foldCommand(line: Int?, folded: Bool?) -> Result<CommandBlock, CommandFoldError> {
+        lock.lock()
+        let index = commandBlocks.lastIndex { $0.lineCount > 0 && (line == nil || $0.line == line) }
+        guard let index else {
+            lock.unlock()
+            return .failure(.notFound(line))
+        }
+        let top = viewLineLocked(forViewRow: 0, folds: foldSpansLocked()).line
+        commandBlocks[index].folded = folded ?? !commandBlocks[index].folded
+        if viewOffset > 0 {
+            viewOffset -= viewRowLocked(forLine: top, folds: foldSpansLocked())
+            viewOffset = min(max(viewOffset, 0), scrollbackRowsLocked())
+        }
+        let block = commandBlocks[index]
+        generation &+= 1
+        lock.unlock()
+        onChange?()
+        return .success(block)
+    }
+
+    /// The folded blocks as the viewport sees them: absolute lines
+    /// `start...end`, drawn as one row. Only scrollback folds; a block's
+    /// rows still on the screen stay, and the alt screen has no history.
+    private func foldSpansLocked() -> [FoldSpan] {
+        guard !usingAlt, commandBlocks.contains(where: \.folded) else { return [] }
+        let first = sbAppended - scrollback.count
+        return commandBlocks.compactMap { block in
+            guard block.folded else { return nil }
+            let start = max(block.line, first)
+            let end = min(block.lastLine, sbAppended - 1)
+            return start < end ? (start, end, block) : nil
+        }
+    }
+
+    /// Rows the viewport can scroll back through; a fold counts as one.
+    private func scrollbackRowsLocked() -> Int {
+        scrollback.count - foldSpansLocked().reduce(0) { $0 + $1.end - $1.start }
+    }
+
+    /// The absolute line view row `r` shows, and the fold when it is one's
+    /// summary (the line is then the fold's first).
+    private func viewLineLocked(forViewRow r: Int, folds: [FoldSpan]) -> (line: Int, fold: FoldSpan?) {
+        var line = sbAppended - folds.reduce(0) { $0 + $1.end - $1.start } - viewOffset + r
+        for fold in folds {
+            if line < fold.start { break }
+            if line == fold.start { return (line, fold) }
+            line += fold.end - fold.start
+        }
+        return (line, nil)
+    }
+
+    /// The view row showing absolute `line`; a folded line maps to its
+    /// fold's summary. Off-screen lines give rows outside `0..<rows`.
+    private func viewRowLocked(forLine line: Int, folds: [FoldSpan]) -> Int {
+        let hidden = folds.reduce(0) { $0 + max(0, min(line, $1.end) - $1.start) }
+        let total = folds.reduce(0) { $0 + $1.end - $1.start }
+        return line - hidden - (sbAppended - total) + viewOffset
+    }
+
+    private func summaryRow(_ block: CommandBlock) -> [Cell] {
+        var row = [Cell](repeating: Cell(), count: cols)
+        for (c, scalar) in block.summary.unicodeScalars.prefix(cols).enumerated() {
+            row[c].glyph = scalar.value
+            row[c].flags = CellFlags.faint
+        }
+        return row
+    }
+
+    // MARK: - output triggers
+
+    /// Rows finished since absolute `line` — the ones the cursor has moved
+    /// below — each as one character per cell, and where the next scan
+    /// starts. A nil `line` starts at the cursor. The alternate screen is
+    /// skipped (next is nil): full-screen programs redraw rows in place.
+    func finishedRows(after line: Int?, limit: Int = 1000) -> (rows: [(line: Int, chars: [Character])], next: Int?) {
+        lock.lock()
+        defer { lock.unlock() }
+        guard !usingAlt else { return ([], nil) }
+        let cursorLine = sbAppended + cy
+        guard let line else { return ([], cursorLine) }
+        let from = max(line, cursorLine - limit, sbAppended - scrollback.count)
+        guard from < cursorLine else { return ([], cursorLine) }
+        let rows = (from..<cursorLine).compactMap { l in rowChars(absLine: l).map { (line: l, chars: $0) } }
+        return (rows, cursorLine)
+    }
+
+    /// The last `count` rows of scrollback + screen, one character per
+    /// cell, with their absolute lines.
+    func recentRows(_ count: Int) -> [(line: Int, chars: [Character])] {
+        lock.lock()
+        defer { lock.unlock() }
+        let end = sbAppended + rows
+        let from = max(sbAppended - scrollback.count, end - count)
+        return (from..<end).compactMap { l in rowChars(absLine: l).map { (line: l, chars: $0) } }
+    }
+
+    /// Paints cells `lo...hi` of absolute `line` black on yellow, in the
+    /// grid itself, so the mark scrolls with the text.
+    func highlight(line: Int, lo: Int, hi: Int) {
+        func paint(_ row: inout [Cell]) {
+            let lo = max(lo, 0)
+            let hi = min(hi, row.count - 1)
+            guard lo <= hi else { return }
+            for c in lo...hi {
+                row[c].fg = ColorCode.indexed(0)
+                row[c].bg = ColorCode.indexed(11)
+            }
+        }
+        lock.lock()
+        let index = line - (sbAppended - scrollback.count)
+        let inRange = index >= 0 && index < scrollback.count + rows
+        if inRange {
+            if index < scrollback.count {
+                paint(&scrollback[index])
+            } else {
+                paint(&screen[index - scrollback.count])
+            }
+            generation &+= 1
+        }
+        lock.unlock()
+        if inRange { onChange?() }
+    }
+
     // MARK: - link detection support
 
     /// Per-column characters of a view row (columns map 1:1).
@@ -2314,6 +3380,45 @@ final class Terminal {
         return rowChars(absLine: absLineLocked(forViewRow: viewRow))
     }
 
+    /// The OSC 8 link under a cell of a view row, with the run of cells
+    /// around it that carry the same one.
+    func hyperlink(viewRow: Int, col: Int) -> (link: TerminalHyperlink, lo: Int, hi: Int)? {
+        lock.lock()
+        defer { lock.unlock() }
+        guard viewRow >= 0, viewRow < rows,
+              let row = rowAtAbsoluteLine(absLineLocked(forViewRow: viewRow)),
+              col >= 0, col < row.count, let link = hyperlinks[row[col].link] else { return nil }
+        let number = row[col].link
+        var lo = col
+        var hi = col
+        while lo > 0, row[lo - 1].link == number { lo -= 1 }
+        while hi < row.count - 1, row[hi + 1].link == number { hi += 1 }
+        return (link, lo, hi)
+    }
+
+    /// Every OSC 8 link in the last `count` rows of scrollback + screen,
+    /// one range per run of cells.
+    func hyperlinkRanges(_ count: Int) -> [HyperlinkRange] {
+        lock.lock()
+        defer { lock.unlock() }
+        let end = sbAppended + rows
+        var ranges: [HyperlinkRange] = []
+        for line in max(sbAppended - scrollback.count, end - count)..<end {
+            guard let row = rowAtAbsoluteLine(line) else { continue }
+            var c = 0
+            while c < row.count {
+                let number = row[c].link
+                var hi = c
+                while hi < row.count - 1, row[hi + 1].link == number { hi += 1 }
+                if let link = hyperlinks[number] {
+                    ranges.append(HyperlinkRange(line: line, start: c, end: hi, link: link))
+                }
+                c = hi + 1
+            }
+        }
+        return ranges
+    }
+
     func setLinkHighlight(viewRow: Int, lo: Int, hi: Int) {
         lock.lock()
         defer { lock.unlock() }
@@ -7,8 +7,13 @@ final class TabCommandPaletteViewController: NSViewController,
     NSTableViewDataSource, NSTableViewDelegate, NSSearchFieldDelegate
 {
     private enum Action {
+        case runPinned(PinnedCommand)
         case select(Int)
         case newTab
+        case upgradePackages
+        case transformSelection(TextTransform)
+        case copyAnswer(String)
+        case setDoNotDisturb(DoNotDisturb.Mode)
     }
 
     private struct Item {
@@ -20,6 +25,9 @@ final class TabCommandPaletteViewController: NSViewController,
 
     var onSelect: ((Int) -> Void)?
     var onNewTab: (() -> Void)?
+    var onUpgradePackages: (() -> Void)?
+    var onTransformSelection: ((TextTransform) -> Void)?
+    var onRunPinned: ((PinnedCommand) -> Void)?
     var onDismiss: (() -> Void)?
 
     private let searchField = NSSearchField()
@@ -28,10 +36,17 @@ final class TabCommandPaletteViewController: NSViewController,
     private let allItems: [Item]
     private var filteredItems: [Item]
 
-    init(titles: [String], selectedIndex: Int) {
-        var items = [Item(
+    /// `pins` are the focused workspace's pinned commands, listed first.
+    init(titles: [String], selectedIndex: Int, pins: [PinnedCommand] = []) {
+        var items = pins.map { pin in
+            let detail = pin.title == nil ? "Pinned command" : pin.command
+            return Item(
+                title: pin.label, detail: pin.hotkey.map { "\(detail) · \($0)" } ?? detail,
+                symbol: "pin.fill", action: .runPinned(pin))
+        }
+        items.append(Item(
             title: "New terminal tab", detail: "Create a new main tab",
-            symbol: "plus", action: .newTab)]
+            symbol: "plus", action: .newTab))
         items += titles.enumerated().map { index, title in
             Item(
                 title: title,
@@ -39,6 +54,20 @@ final class TabCommandPaletteViewController: NSViewController,
                 symbol: index == selectedIndex ? "checkmark.circle.fill" : "terminal",
                 action: .select(index))
         }
+        items.append(Item(
+            title: "Upgrade outdated packages", detail: "Homebrew or MacPorts, with progress",
+            symbol: "shippingbox", action: .upgradePackages))
+        items += TextTransform.allCases.map { transform in
+            Item(
+                title: "\(transform.title) selection", detail: "Result opens with a copy button",
+                symbol: transform.hash == nil ? "textformat.abc" : "number", action: .transformSelection(transform))
+        }
+        let quiet = DoNotDisturb.shared.snapshot
+        let focusDetail = quiet.focus?.isFocused == true && quiet.mode == .auto
+            ? "On for the \(quiet.focus?.name ?? "current") Focus" : "Quiets the bell, pet bubbles and notch activity"
+        items.append(Item(
+            title: quiet.isActive ? "Turn off Do Not Disturb" : "Turn on Do Not Disturb", detail: focusDetail,
+            symbol: quiet.isActive ? "moon.fill" : "moon", action: .setDoNotDisturb(quiet.isActive ? .off : .on)))
         allItems = items
         filteredItems = items
         super.init(nibName: nil, bundle: nil)
@@ -175,10 +204,22 @@ final class TabCommandPaletteViewController: NSViewController,
             $0.title.localizedCaseInsensitiveContains(needle)
                 || $0.detail.localizedCaseInsensitiveContains(needle)
         }
+        if let answer = Self.answer(for: needle) { filteredItems.insert(answer, at: 0) }
         table.reloadData()
         selectFirstResult()
     }
 
+    /// A query that evaluates (`1536 MiB in GB`, `2^16`) gets its answer as
+    /// the first row, the way Spotlight does. Bare numbers are left alone.
+    private static func answer(for query: String) -> Item? {
+        guard query.contains(where: \.isNumber),
+              case .success(let result) = Calculator.evaluate(query), result.text != query
+        else { return nil }
+        return Item(
+            title: "= \(result.text)", detail: "Copy the answer", symbol: "equal.circle",
+            action: .copyAnswer(result.text))
+    }
+
     private func selectFirstResult() {
         if filteredItems.isEmpty {
             table.deselectAll(nil)
@@ -200,8 +241,15 @@ final class TabCommandPaletteViewController: NSViewController,
         let row = table.selectedRow >= 0 ? table.selectedRow : 0
         guard filteredItems.indices.contains(row) else { return }
         switch filteredItems[row].action {
+        case .runPinned(let pin): onRunPinned?(pin)
         case .select(let index): onSelect?(index)
         case .newTab: onNewTab?()
+        case .upgradePackages: onUpgradePackages?()
+        case .transformSelection(let transform): onTransformSelection?(transform)
+        case .copyAnswer(let text):
+            NSPasteboard.general.clearContents()
+            NSPasteboard.general.setString(text, forType: .string)
+        case .setDoNotDisturb(let mode): DoNotDisturb.shared.setMode(mode)
         }
         onDismiss?()
     }
@@ -232,6 +280,14 @@ final class TerminalTabStripView: NSView, NSPopoverDelegate {
     var onClose: ((Int) -> Void)?
     /// Create a new tab (the trailing +).
     var onNewTab: (() -> Void)?
+    /// Upgrade outdated system packages (from the search palette).
+    var onUpgradePackages: (() -> Void)?
+    /// Encode, decode or hash the selected terminal text (palette).
+    var onTransformSelection: ((TextTransform) -> Void)?
+    /// The focused workspace's pinned commands, read when the palette opens,
+    /// and running one of them.
+    var pinnedCommands: (() -> [PinnedCommand])?
+    var onRunPinned: ((PinnedCommand) -> Void)?
     var onRenameCommit: ((String) -> Void)?
     var onRenameCancel: (() -> Void)?
     /// Reorder the tab from `from` to `to` (drag within the strip).
@@ -671,7 +727,7 @@ final class TerminalTabStripView: NSView, NSPopoverDelegate {
             return
         }
         let palette = TabCommandPaletteViewController(
-            titles: titles, selectedIndex: selectedIndex)
+            titles: titles, selectedIndex: selectedIndex, pins: pinnedCommands?() ?? [])
         let popover = NSPopover()
         popover.contentViewController = palette
         popover.contentSize = palette.preferredContentSize
@@ -680,6 +736,9 @@ final class TerminalTabStripView: NSView, NSPopoverDelegate {
         popover.delegate = self
         palette.onSelect = { [weak self] index in self?.onSelect?(index) }
         palette.onNewTab = { [weak self] in self?.onNewTab?() }
+        palette.onUpgradePackages = { [weak self] in self?.onUpgradePackages?() }
+        palette.onTransformSelection = { [weak self] in self?.onTransformSelection?($0) }
+        palette.onRunPinned = { [weak self] in self?.onRunPinned?($0) }
         palette.onDismiss = { [weak popover] in popover?.close() }
         searchPopover = popover
         popover.show(relativeTo: searchButton.bounds, of: searchButton, preferredEdge: .minY)
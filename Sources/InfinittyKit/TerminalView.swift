@@ -9,6 +9,32 @@ final class TerminalView: NSView {
     var pty: PTY!
     var renderer: Renderer!
     var onFocus: (() -> Void)?
+    /// Bytes the user typed or pasted, after they reach this pane's PTY.
+    var onUserInput: (([UInt8]) -> Void)?
+    /// Ask before a paste `PasteCheck` has concerns about
+    /// (`paste-confirmation`).
+    var confirmsPaste = true
+    /// A paste is waiting on the user's answer to `confirmsPaste`.
+    var onPasteHeld: ((PasteCheck) -> Void)?
+    /// Hold Enter on a command line `CommandGuard` finds risky until the
+    /// user has seen what it touches (`confirm-dangerous-commands`).
+    var confirmsDangerousCommands = false
+    /// The directory a held command's paths are relative to.
+    var commandDirectory: (() -> String?)?
+    /// A risky command is waiting on the user's answer, analyzed.
+    var onCommandHeld: ((CommandAnalysis) -> Void)?
+    /// Directory and pattern of the prompt glob under the mouse.
+    private var hoveredGlob: String?
+    /// Clean-up for text copied out of the pane (`copy-transforms`).
+    var copyTransforms = CopyTransform.defaults
+    /// Copy a selection as soon as the mouse lets go of it (`copy-on-select`).
+    var copiesOnSelect = false
+    /// "Reveal in Files" / "Reveal in Finder" on a path in the output, as
+    /// printed; the app resolves it against the pane's directory.
+    var onRevealPath: ((String, RevealTarget) -> Void)?
+    /// A cmd-clicked link, for the app's `LinkPolicy` to decide on; opened
+    /// directly when unset.
+    var onOpenLink: ((URL) -> Void)?
     /// Click landed on the pet sprite (pet assistant entry point).
     var onPetClick: (() -> Void)?
     var onPetScaleChange: ((CGFloat) -> Void)?
@@ -27,6 +53,8 @@ final class TerminalView: NSView {
     }
     var onTogglePaneZoom: (() -> Void)? { didSet { paneHeader.onToggleZoom = onTogglePaneZoom } }
     var onClosePane: (() -> Void)? { didSet { paneHeader.onClose = onClosePane } }
+    var onSecureInput: (() -> Void)? { didSet { paneHeader.onSecureInput = onSecureInput } }
+    var onUnlockInput: (() -> Void)? { didSet { paneHeader.onUnlockInput = onUnlockInput } }
     var onPaneDragBegan: ((NSPoint) -> Void)? { didSet { paneHeader.onDragBegan = onPaneDragBegan } }
     var onPaneDragMoved: ((NSPoint) -> Void)? { didSet { paneHeader.onDragMoved = onPaneDragMoved } }
     var onPaneDragEnded: ((NSPoint, Bool) -> Void)? { didSet { paneHeader.onDragEnded = onPaneDragEnded } }
@@ -62,6 +90,8 @@ final class TerminalView: NSView {
 
     private enum DragMode { case none, report, select }
     private var dragMode = DragMode.none
+    /// The selection being made grew past the cell first clicked.
+    private var selectionMoved = false
 
     // Live-resize winsize coalescing: our grid reflows and repaints every
     // frame, but the child only gets SIGWINCH at ~12 Hz plus a final one.
@@ -411,17 +441,28 @@ final class TerminalView: NSView {
     // MARK: - keyboard
 
     override func keyDown(with event: NSEvent) {
+        // A read-only pane swallows typing; the beep says why nothing happened.
+        if pty.isInputLocked {
+            NSSound.beep()
+            return
+        }
         // Accept an inline hint with Tab or Right-arrow (when one is showing).
         if event.keyCode == 48 || event.keyCode == 124, // Tab or →
            !event.modifierFlags.contains(.shift),
            let accepted = terminal.acceptHint(), !accepted.isEmpty {
             pty.write(accepted)
+            onUserInput?(accepted)
             renderer.poke()
             return
         }
         guard let bytes = encodeKey(event), !bytes.isEmpty else { return }
+        if bytes == [0x0D], confirmsDangerousCommands, let command = terminal.pendingInput(),
+           !CommandGuard.risks(in: command).isEmpty {
+            return holdCommand(command)
+        }
         terminal.userDidInput()
         pty.write(bytes)
+        onUserInput?(bytes)
     }
 
     private func encodeKey(_ event: NSEvent) -> [UInt8]? {
@@ -491,18 +532,95 @@ final class TerminalView: NSView {
         return nil
     }
 
+    // MARK: - risky commands
+
+    /// Counts what `command` touches off the main thread, then asks in a
+    /// sheet. Enter goes through only if the prompt still holds the same
+    /// line when the answer comes.
+    private func holdCommand(_ command: String) {
+        let directory = commandDirectory?() ?? NSHomeDirectory()
+        DispatchQueue.global(qos: .userInitiated).async { [weak self] in
+            guard let analysis = CommandGuard.analyze(command, in: directory) else { return }
+            DispatchQueue.main.async { self?.confirm(analysis) }
+        }
+    }
+
+    private func confirm(_ analysis: CommandAnalysis) {
+        onCommandHeld?(analysis)
+        let alert = NSAlert()
+        alert.messageText = "Run this command?"
+        var details = analysis.risks.map(\.explanation).joined(separator: " ")
+        let command = analysis.command.count > 300 ? String(analysis.command.prefix(300)) + "…" : analysis.command
+        details += "\n\n" + command
+        for target in analysis.targets.prefix(8) {
+            let what = target.matches.isEmpty
+                ? "matches nothing"
+                : "\(target.matches.count) match\(target.matches.count == 1 ? "" : "es"), "
+                    + "\(target.count) item\(target.count == 1 ? "" : "s")"
+            details += "\n• \(target.argument): \(what)"
+        }
+        if !analysis.targets.isEmpty {
+            details += "\n\n\(analysis.affected)\(analysis.truncated ? "+" : "") files and folders in all."
+        }
+        alert.informativeText = details
+        alert.alertStyle = .critical
+        alert.addButton(withTitle: "Run")
+        alert.addButton(withTitle: "Cancel")
+        let finish = { [weak self] (response: NSApplication.ModalResponse) in
+            guard response == .alertFirstButtonReturn, let self, !self.pty.isInputLocked,
+                  self.terminal.pendingInput() == analysis.command else { return }
+            self.terminal.userDidInput()
+            self.pty.write([0x0D])
+            self.onUserInput?([0x0D])
+        }
+        if let window {
+            alert.beginSheetModal(for: window, completionHandler: finish)
+        } else {
+            finish(alert.runModal())
+        }
+    }
+
     // MARK: - paste
 
     @objc func paste(_ sender: Any?) {
-        guard var s = NSPasteboard.general.string(forType: .string) else { return }
-        s = s.replacingOccurrences(of: "\r\n", with: "\r")
-            .replacingOccurrences(of: "\n", with: "\r")
-        var bytes = Array(s.utf8)
-        if terminal.bracketedPasteEnabled {
-            bytes = Array("\u{1B}[200~".utf8) + bytes + Array("\u{1B}[201~".utf8)
+        guard let s = NSPasteboard.general.string(forType: .string) else { return }
+        if pty.isInputLocked {
+            NSSound.beep()
+            return
         }
+        paste(text: s)
+    }
+
+    /// Pastes `text` through `PasteCheck`, asking first when it has
+    /// concerns.
+    func paste(text: String) {
+        let check = PasteCheck(text, bracketed: terminal.bracketedPasteEnabled)
+        guard confirmsPaste, !check.concerns.isEmpty else { return send(check) }
+        onPasteHeld?(check)
+        let alert = NSAlert()
+        alert.messageText = check.lineCount > 1 ? "Paste \(check.lineCount) lines?" : "Paste this command?"
+        let preview = check.visibleText.split(separator: "\r", omittingEmptySubsequences: false).prefix(6)
+            .map { $0.count > 100 ? $0.prefix(100) + "…" : $0 }.joined(separator: "\n")
+        alert.informativeText = check.concerns.map(\.explanation).joined(separator: " ") + "\n\n" + preview
+        alert.alertStyle = .warning
+        alert.addButton(withTitle: "Paste")
+        alert.addButton(withTitle: "Cancel")
+        let finish = { [weak self] (response: NSApplication.ModalResponse) in
+            guard response == .alertFirstButtonReturn, let self, !self.pty.isInputLocked else { return }
+            self.send(check)
+        }
+        if let window {
+            alert.beginSheetModal(for: window, completionHandler: finish)
+        } else {
+            finish(alert.runModal())
+        }
+    }
+
+    private func send(_ check: PasteCheck) {
+        let bytes = check.bytes
         terminal.userDidInput()
         pty.write(bytes)
+        onUserInput?(bytes)
     }
 
     // MARK: - mouse reporting (xterm protocol)
@@ -614,6 +732,7 @@ final class TerminalView: NSView {
         }
 
         dragMode = .select
+        selectionMoved = event.clickCount > 1
         terminal.clearSelection()
         let (col, row) = clampedCell(event)
         switch event.clickCount {
@@ -631,6 +750,9 @@ final class TerminalView: NSView {
         if dragMode == .report {
             reportMouse(event, button: 0, pressed: false, motion: false)
         }
+        // A plain click leaves a one-cell selection; only a drag or a
+        // double- or triple-click is worth copying.
+        if dragMode == .select, selectionMoved, copiesOnSelect { copy(nil) }
         dragMode = .none
     }
 
@@ -641,6 +763,7 @@ final class TerminalView: NSView {
         case .select:
             let (col, row) = clampedCell(event)
             terminal.selectionExtend(viewRow: row, col: col)
+            selectionMoved = true
             renderer.poke()
         case .none:
             break
@@ -651,17 +774,19 @@ final class TerminalView: NSView {
 
     @objc func copy(_ sender: Any?) {
         guard let text = terminal.selectedText() else { return }
-        let pb = NSPasteboard.general
-        pb.clearContents()
-        pb.setString(text, forType: .string)
+        Clipboard.write(text, transforms: copyTransforms)
     }
 
     private static let urlRegex = try! NSRegularExpression(
         pattern: "(https?://|file:///|www\\.)[^\\s\"'`<>]+"
     )
 
-    /// URL under a cell, with its column range on that row.
+    /// URL under a cell, with its column range on that row: an OSC 8 link
+    /// the program printed, else one that reads like a URL.
     private func link(atCol col: Int, viewRow row: Int) -> (URL, Int, Int)? {
+        if let (link, lo, hi) = terminal.hyperlink(viewRow: row, col: col) {
+            return URL(string: link.url).map { ($0, lo, hi) }
+        }
         guard let chars = terminal.lineChars(viewRow: row) else { return nil }
         // Force one UTF-16 unit per column so regex ranges map to columns.
         let line = String(chars.map { $0.isASCII ? $0 : " " })
@@ -685,7 +810,11 @@ final class TerminalView: NSView {
     private func openLink(at event: NSEvent) {
         guard let (col, row) = mouseCell(event) else { return }
         if let (url, _, _) = link(atCol: col, viewRow: row) {
-            NSWorkspace.shared.open(url)
+            if let onOpenLink {
+                onOpenLink(url)
+            } else {
+                NSWorkspace.shared.open(url)
+            }
             return
         }
         // Markdown files open through the configured viewer (default glow).
@@ -726,6 +855,40 @@ final class TerminalView: NSView {
         NSCursor.pointingHand.set()
     }
 
+    /// A glob or brace pattern typed at the prompt gets a tooltip with the
+    /// files it matches, expanded off the main thread and kept for the
+    /// last pattern so resting on it doesn't re-read the directory.
+    private func updateGlobHover(_ event: NSEvent) {
+        guard let (col, row) = mouseCell(event), let word = terminal.inputWord(viewRow: row, col: col),
+              let pattern = CommandGuard.words(word).first?.first?.pattern else {
+            hoveredGlob = nil
+            if toolTip != nil { toolTip = nil }
+            return
+        }
+        let directory = commandDirectory?() ?? NSHomeDirectory()
+        let key = directory + "\0" + pattern
+        guard key != hoveredGlob else { return }
+        hoveredGlob = key
+        toolTip = nil
+        DispatchQueue.global(qos: .userInitiated).async { [weak self] in
+            let result = GlobExpansion.expand(pattern, in: directory, limit: 1_000)
+            let tip = Self.globTooltip(result, directory: directory)
+            DispatchQueue.main.async {
+                guard let self, self.hoveredGlob == key else { return }
+                self.toolTip = tip
+            }
+        }
+    }
+
+    static func globTooltip(_ result: GlobExpansion.Result, directory: String) -> String {
+        guard !result.matches.isEmpty else { return "Matches nothing" }
+        let prefix = directory.hasSuffix("/") ? directory : directory + "/"
+        let shown = result.matches.prefix(20).map { $0.hasPrefix(prefix) ? String($0.dropFirst(prefix.count)) : $0 }
+        let count = result.matches.count
+        let heading = "\(count)\(result.truncated ? "+" : "") match\(count == 1 ? "" : "es")"
+        return heading + "\n" + shown.joined(separator: "\n") + (count > shown.count ? "\n…" : "")
+    }
+
     override func flagsChanged(with event: NSEvent) {
         super.flagsChanged(with: event)
         if !event.modifierFlags.contains(.command) {
@@ -742,6 +905,7 @@ final class TerminalView: NSView {
     }
 
     override func performDragOperation(_ sender: NSDraggingInfo) -> Bool {
+        guard !pty.isInputLocked else { return false }
         let pb = sender.draggingPasteboard
         if let urls = pb.readObjects(
             forClasses: [NSURL.self],
@@ -753,12 +917,7 @@ final class TerminalView: NSView {
             return true
         }
         if let s = pb.string(forType: .string), !s.isEmpty {
-            var bytes = Array(s.replacingOccurrences(of: "\n", with: "\r").utf8)
-            if terminal.bracketedPasteEnabled {
-                bytes = Array("\u{1B}[200~".utf8) + bytes + Array("\u{1B}[201~".utf8)
-            }
-            terminal.userDidInput()
-            pty.write(bytes)
+            paste(text: s)
             return true
         }
         return false
@@ -791,6 +950,14 @@ final class TerminalView: NSView {
         menu.addItem(withTitle: "Copy", action: #selector(copy(_:)), keyEquivalent: "").target = self
         menu.addItem(withTitle: "Paste", action: #selector(paste(_:)), keyEquivalent: "").target = self
         menu.addItem(.separator())
+        if let path = revealablePath(at: event) {
+            for (title, target) in [("Reveal in Files", RevealTarget.files), ("Reveal in Finder", .finder)] {
+                let item = menu.addItem(withTitle: title, action: #selector(revealPath(_:)), keyEquivalent: "")
+                item.target = self
+                item.representedObject = [path, target.rawValue]
+            }
+            menu.addItem(.separator())
+        }
         menu.addItem(
             withTitle: "New Chat",
             action: #selector(AppDelegate.newChatPane(_:)), keyEquivalent: "")
@@ -808,6 +975,7 @@ final class TerminalView: NSView {
         menu.addItem(
             withTitle: "Toggle Pane Zoom",
             action: #selector(AppDelegate.togglePaneZoom(_:)), keyEquivalent: "")
+        AppDelegate.paneArrangementMenuItems().forEach(menu.addItem)
         menu.addItem(.separator())
         menu.addItem(withTitle: "Rename Tab…", action: #selector(AppDelegate.renameTab(_:)), keyEquivalent: "")
         let renamePanel = menu.addItem(
@@ -882,6 +1050,20 @@ final class TerminalView: NSView {
         paneHeader.beginRename()
     }
 
+    /// The path token under a right-click, if that is what's there.
+    private func revealablePath(at event: NSEvent) -> String? {
+        guard onRevealPath != nil, let (col, row) = mouseCell(event),
+              let chars = terminal.lineChars(viewRow: row),
+              let (kind, columns) = SmartToken.find(in: chars, at: col), kind == .path else { return nil }
+        return String(chars[columns])
+    }
+
+    @objc private func revealPath(_ sender: NSMenuItem) {
+        guard let pair = sender.representedObject as? [String], pair.count == 2,
+              let target = RevealTarget(rawValue: pair[1]) else { return }
+        onRevealPath?(pair[0], target)
+    }
+
     @objc func resetTerminal(_ sender: Any?) {
         terminal.hardReset()
         pty.write([0x0C]) // ^L so the shell repaints its prompt
@@ -916,6 +1098,7 @@ final class TerminalView: NSView {
             updateLinkHover(event)
             return
         }
+        updateGlobHover(event)
         let (mode, _) = terminal.mouseReporting
         if mode == 1003 {
             reportMouse(event, button: 3, pressed: true, motion: true)
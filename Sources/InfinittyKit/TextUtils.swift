@@ -0,0 +1,246 @@
+import CryptoKit
+import Foundation
+import Security
+
+enum TextUtilError: Error, Equatable, CustomStringConvertible {
+    case invalidInput(String)
+    case unsupported(String)
+
+    var description: String {
+        switch self {
+        case .invalidInput(let reason): return reason
+        case .unsupported(let reason): return reason
+        }
+    }
+}
+
+enum HashAlgorithm: String, CaseIterable {
+    case md5, sha1, sha256, sha384, sha512, blake3
+
+    /// Feeds data in pieces and hands back the digest once. MD5 and SHA-1
+    /// are here for matching published checksums, not for security.
+    func hasher() -> (update: (Data) -> Void, finish: () -> [UInt8]) {
+        func wrap<H: HashFunction>(_ hasher: H) -> (update: (Data) -> Void, finish: () -> [UInt8]) {
+            var hasher = hasher
+            return ({ hasher.update(data: $0) }, { Array(hasher.finalize()) })
+        }
+        switch self {
+        case .md5: return wrap(Insecure.MD5())
+        case .sha1: return wrap(Insecure.SHA1())
+        case .sha256: return wrap(SHA256())
+        case .sha384: return wrap(SHA384())
+        case .sha512: return wrap(SHA512())
+        case .blake3:
+            var hasher = BLAKE3()
+            return ({ hasher.update(data: $0) }, { hasher.finalize() })
+        }
+    }
+
+    func hex(of data: Data) -> String {
+        let digest = hasher()
+        digest.update(data)
+        return TextUtils.hex(digest.finish())
+    }
+}
+
+/// What the palette and `text-util` can do to a piece of text.
+enum TextTransform: String, CaseIterable {
+    case base64Encode = "base64-encode"
+    case base64Decode = "base64-decode"
+    case urlEncode = "url-encode"
+    case urlDecode = "url-decode"
+    case jwtDecode = "jwt-decode"
+    case md5, sha1, sha256, sha384, sha512, blake3
+
+    var title: String {
+        switch self {
+        case .base64Encode: return "Base64 encode"
+        case .base64Decode: return "Base64 decode"
+        case .urlEncode: return "URL encode"
+        case .urlDecode: return "URL decode"
+        case .jwtDecode: return "Decode JWT"
+        case .md5: return "MD5 hash"
+        case .sha1: return "SHA-1 hash"
+        case .sha256: return "SHA-256 hash"
+        case .sha384: return "SHA-384 hash"
+        case .sha512: return "SHA-512 hash"
+        case .blake3: return "BLAKE3 hash"
+        }
+    }
+
+    var hash: HashAlgorithm? { HashAlgorithm(rawValue: rawValue) }
+}
+
+/// Encoding, decoding and hashing for text picked out of a terminal:
+/// base64 (standard or URL-safe), percent-encoding, JWTs and digests. JWT
+/// decoding never checks the signature; `verifyJWT` does, given the key.
+enum TextUtils {
+    static func apply(_ transform: TextTransform, to text: String) -> Result<String, TextUtilError> {
+        if let hash = transform.hash { return .success(hash.hex(of: Data(text.utf8))) }
+        switch transform {
+        case .base64Encode:
+            return .success(Data(text.utf8).base64EncodedString())
+        case .base64Decode:
+            guard let data = base64Decode(text) else { return .failure(.invalidInput("not base64")) }
+            guard let decoded = String(data: data, encoding: .utf8) else {
+                return .failure(.invalidInput("decodes to \(data.count) bytes of binary data"))
+            }
+            return .success(decoded)
+        case .urlEncode:
+            return .success(urlEncode(text))
+        case .urlDecode:
+            // Form encoding writes spaces as `+`.
+            guard let decoded = text.replacingOccurrences(of: "+", with: " ").removingPercentEncoding else {
+                return .failure(.invalidInput("malformed percent-encoding"))
+            }
+            return .success(decoded)
+        case .jwtDecode:
+            return decodeJWT(text).map { jwt in
+                let data = try? JSONSerialization.data(
+                    withJSONObject: jwt.wire(), options: [.prettyPrinted, .sortedKeys])
+                return data.map { String(decoding: $0, as: UTF8.self) } ?? "{}"
+            }
+        default:
+            return .failure(.unsupported(transform.rawValue))
+        }
+    }
+
+    /// Standard or URL-safe alphabet, padding optional, whitespace and line
+    /// breaks ignored.
+    static func base64Decode(_ text: String) -> Data? {
+        var normalized = String(text.filter { !$0.isWhitespace })
+            .replacingOccurrences(of: "-", with: "+")
+            .replacingOccurrences(of: "_", with: "/")
+        if normalized.count % 4 != 0 { normalized += String(repeating: "=", count: 4 - normalized.count % 4) }
+        return Data(base64Encoded: normalized)
+    }
+
+    /// Percent-encodes everything but RFC 3986's unreserved characters, so
+    /// the result is safe as a query value or a path segment.
+    static func urlEncode(_ text: String) -> String {
+        let unreserved = CharacterSet(
+            charactersIn: "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~")
+        return text.addingPercentEncoding(withAllowedCharacters: unreserved) ?? text
+    }
+
+    static func hex(_ bytes: [UInt8]) -> String {
+        bytes.map { String(format: "%02x", $0) }.joined()
+    }
+
+    /// Hex digest of `url`, mapped and hashed a chunk at a time like
+    /// `FileContents.sha256`.
+    static func hashFile(
+        _ url: URL, algorithm: HashAlgorithm, chunkSize: Int = 4 << 20, isCancelled: () -> Bool = { false }
+    ) -> Result<String, FileContentsError> {
+        let data: Data
+        switch FileContents.map(url) {
+        case .success(let mapped): data = mapped
+        case .failure(let error): return .failure(error)
+        }
+        let hasher = algorithm.hasher()
+        var offset = data.startIndex
+        while offset < data.endIndex {
+            if isCancelled() { return .failure(.cancelled) }
+            let end = data.index(offset, offsetBy: chunkSize, limitedBy: data.endIndex) ?? data.endIndex
+            hasher.update(data[offset..<end])
+            offset = end
+        }
+        return .success(hex(hasher.finish()))
+    }
+
+    // MARK: - JWT
+
+    struct JWT {
+        let header: [String: Any]
+        let payload: [String: Any]
+        let signature: Data
+        /// `header.payload` as it appeared, which is what was signed.
+        let signingInput: String
+
+        var algorithm: String { header["alg"] as? String ?? "none" }
+
+        /// Header and payload, plus `expired` when the token has an `exp`.
+        func wire(now: Date = Date()) -> [String: Any] {
+            var object: [String: Any] = ["header": header, "payload": payload]
+            if let exp = payload["exp"] as? NSNumber {
+                object["expired"] = exp.doubleValue < now.timeIntervalSince1970
+            }
+            return object
+        }
+    }
+
+    /// Splits and decodes a compact JWS without looking at the signature.
+    /// A leading `Bearer ` is dropped, since that is how tokens get copied.
+    static func decodeJWT(_ text: String) -> Result<JWT, TextUtilError> {
+        var token = text.trimmingCharacters(in: .whitespacesAndNewlines)
+        if token.lowercased().hasPrefix("bearer ") { token = String(token.dropFirst("bearer ".count)) }
+        let parts = token.split(separator: ".", omittingEmptySubsequences: false)
+        guard parts.count == 3 else {
+            return .failure(.invalidInput("a JWT has three dot-separated parts, not \(parts.count)"))
+        }
+        func object(_ part: Substring) -> [String: Any]? {
+            base64Decode(String(part)).flatMap { try? JSONSerialization.jsonObject(with: $0) as? [String: Any] }
+        }
+        guard let header = object(parts[0]) else { return .failure(.invalidInput("header is not JSON")) }
+        guard let payload = object(parts[1]) else { return .failure(.invalidInput("payload is not JSON")) }
+        guard let signature = base64Decode(String(parts[2])) else {
+            return .failure(.invalidInput("signature is not base64url"))
+        }
+        return .success(JWT(
+            header: header, payload: payload, signature: signature, signingInput: "\(parts[0]).\(parts[1])"))
+    }
+
+    /// Checks the signature of `token` against `key`: the shared secret for
+    /// HS256/384/512, or a PEM public key for RS256/384/512 and ES256/384.
+    static func verifyJWT(_ token: String, key: String) -> Result<Bool, TextUtilError> {
+        let jwt: JWT
+        switch decodeJWT(token) {
+        case .success(let decoded): jwt = decoded
+        case .failure(let error): return .failure(error)
+        }
+        let input = Data(jwt.signingInput.utf8)
+        func hmac<H: HashFunction>(_: H.Type) -> Bool {
+            HMAC<H>.isValidAuthenticationCode(
+                jwt.signature, authenticating: input, using: SymmetricKey(data: Data(key.utf8)))
+        }
+        switch jwt.algorithm {
+        case "HS256": return .success(hmac(SHA256.self))
+        case "HS384": return .success(hmac(SHA384.self))
+        case "HS512": return .success(hmac(SHA512.self))
+        case "ES256":
+            guard let publicKey = try? P256.Signing.PublicKey(pemRepresentation: key) else {
+                return .failure(.invalidInput("ES256 needs a P-256 public key in PEM"))
+            }
+            let signature = try? P256.Signing.ECDSASignature(rawRepresentation: jwt.signature)
+            return .success(signature.map { publicKey.isValidSignature($0, for: input) } ?? false)
+        case "ES384":
+            guard let publicKey = try? P384.Signing.PublicKey(pemRepresentation: key) else {
+                return .failure(.invalidInput("ES384 needs a P-384 public key in PEM"))
+            }
+            let signature = try? P384.Signing.ECDSASignature(rawRepresentation: jwt.signature)
+            return .success(signature.map { publicKey.isValidSignature($0, for: input) } ?? false)
+        case "RS256", "RS384", "RS512":
+            return verifyRSA(jwt, pem: key)
+        case let other:
+            return .failure(.unsupported("can't verify \(other) tokens"))
+        }
+    }
+
+    private static func verifyRSA(_ jwt: JWT, pem: String) -> Result<Bool, TextUtilError> {
+        let body = pem.split(whereSeparator: \.isNewline).filter { !$0.hasPrefix("-----") }.joined()
+        let attributes: [CFString: Any] = [
+            kSecAttrKeyType: kSecAttrKeyTypeRSA, kSecAttrKeyClass: kSecAttrKeyClassPublic,
+        ]
+        guard let der = Data(base64Encoded: body),
+              let publicKey = SecKeyCreateWithData(der as CFData, attributes as CFDictionary, nil)
+        else { return .failure(.invalidInput("\(jwt.algorithm) needs an RSA public key in PEM")) }
+        let algorithm: SecKeyAlgorithm
+        switch jwt.algorithm {
+        case "RS384": algorithm = .rsaSignatureMessagePKCS1v15SHA384
+        case "RS512": algorithm = .rsaSignatureMessagePKCS1v15SHA512
+        default: algorithm = .rsaSignatureMessagePKCS1v15SHA256
+        }
+        return .success(SecKeyVerifySignature(
+            publicKey, algorithm, Data(jwt.signingInput.utf8) as CFData, jwt.signature as CFData, nil))
+    }
+}
@@ -1,3 +1,4 @@
+import Foundation
 import simd
 
 // One terminal cell: 16 bytes, cache-friendly, trivially memcpy-able.
@@ -6,7 +7,8 @@ struct Cell {
     var fg: UInt32 = ColorCode.defaultFG
     var bg: UInt32 = ColorCode.defaultBG
     var flags: UInt16 = 0
-    var _pad: UInt16 = 0
+    /// OSC 8 hyperlink number (`Terminal.hyperlink`); 0 = none.
+    var link: UInt16 = 0
 }
 
 enum CellFlags {
@@ -40,6 +42,10 @@ struct Theme {
     var cursor: SIMD4<Float>
     var selection: SIMD4<Float>
     var palette: [SIMD4<Float>] // 256 entries
+    /// `palette` with each entry pushed to `minimum-contrast` against the
+    /// background, used for text only so a blue background stays blue.
+    /// Nil when the setting is off.
+    var foregroundPalette: [SIMD4<Float>]?
 
     func applying(_ config: AppConfig) -> Theme {
         var t = self
@@ -51,9 +57,41 @@ struct Theme {
             t.palette[index] = Theme.rgba(color)
         }
         t.background.w = Float(config.backgroundOpacity)
+        if config.minimumContrast > 1 {
+            let minimum = Float(config.minimumContrast)
+            t.foregroundPalette = t.palette.map { ColorContrast.ensure($0, against: t.background, minimum: minimum) }
+        }
         return t
     }
 
+    /// A cell's `ColorCode` as RGBA: palette lookup, bold-brightening of
+    /// the base eight colors, and faint text mixed toward the background.
+    /// Shared by the renderer and the HTML snapshot export so both show the
+    /// same colors.
+    @inline(__always)
+    func resolve(_ code: UInt32, isFG: Bool, flags: UInt16) -> SIMD4<Float> {
+        var color: SIMD4<Float>
+        if code & 0x4000_0000 != 0 {
+            color = code == ColorCode.defaultFG ? foreground : background
+        } else if code & 0x8000_0000 != 0 {
+            color = SIMD4<Float>(
+                Float((code >> 16) & 0xFF) / 255,
+                Float((code >> 8) & 0xFF) / 255,
+                Float(code & 0xFF) / 255,
+                1
+            )
+        } else {
+            var idx = Int(code & 0xFF)
+            if isFG && idx < 8 && flags & CellFlags.bold != 0 { idx += 8 }
+            color = isFG ? foregroundPalette?[idx] ?? palette[idx] : palette[idx]
+        }
+        if isFG && flags & CellFlags.faint != 0 {
+            color = simd_mix(background, color, SIMD4<Float>(repeating: 0.55))
+            color.w = 1
+        }
+        return color
+    }
+
     static func rgba(_ hex: UInt32) -> SIMD4<Float> {
         SIMD4<Float>(
             Float((hex >> 16) & 0xFF) / 255.0,
@@ -95,3 +133,40 @@ struct Theme {
         )
     }()
 }
+
+/// WCAG contrast between colours, for `minimum-contrast`: a palette entry
+/// too close to the background (dark blue on black) is moved toward white
+/// or black, whichever the background leaves more room for, just far
+/// enough to reach the ratio. Hue is kept as far as the mix allows.
+enum ColorContrast {
+    /// Relative luminance, 0 for black to 1 for white.
+    static func luminance(_ color: SIMD4<Float>) -> Float {
+        func linear(_ c: Float) -> Float { c <= 0.03928 ? c / 12.92 : powf((c + 0.055) / 1.055, 2.4) }
+        return 0.2126 * linear(color.x) + 0.7152 * linear(color.y) + 0.0722 * linear(color.z)
+    }
+
+    /// 1 (identical) to 21 (black on white), in either order.
+    static func ratio(_ a: SIMD4<Float>, _ b: SIMD4<Float>) -> Float {
+        let (la, lb) = (luminance(a), luminance(b))
+        return (max(la, lb) + 0.05) / (min(la, lb) + 0.05)
+    }
+
+    static func ensure(_ color: SIMD4<Float>, against background: SIMD4<Float>, minimum: Float) -> SIMD4<Float> {
+        guard ratio(color, background) < minimum else { return color }
+        let white = SIMD4<Float>(1, 1, 1, color.w)
+        let black = SIMD4<Float>(0, 0, 0, color.w)
+        let target = ratio(white, background) >= ratio(black, background) ? white : black
+        guard ratio(target, background) > minimum else { return target }
+        // The least mix toward `target` that gets there.
+        var (low, high): (Float, Float) = (0, 1)
+        for _ in 0..<12 {
+            let mid = (low + high) / 2
+            if ratio(simd_mix(color, target, SIMD4<Float>(repeating: mid)), background) >= minimum {
+                high = mid
+            } else {
+                low = mid
+            }
+        }
+        return simd_mix(color, target, SIMD4<Float>(repeating: high))
+    }
+}
@@ -0,0 +1,244 @@
+import Foundation
+
+enum TimeTrackerError: Error, Equatable, CustomStringConvertible {
+    case invalidRange(String)
+
+    var description: String {
+        switch self {
+        case .invalidRange(let range):
+            return "unknown range \(range) (today | yesterday | week | last-week | month | last-month | 7d | "
+                + "YYYY-MM-DD | YYYY-MM-DD..YYYY-MM-DD)"
+        }
+    }
+}
+
+/// A stretch of active time spent in one workspace.
+struct TimeSpan: Codable, Equatable {
+    let workspace: String
+    let start: Date
+    var end: Date
+}
+
+struct Pomodoro: Equatable {
+    let workspace: String?
+    let started: Date
+    let minutes: Int
+
+    var ends: Date { started.addingTimeInterval(TimeInterval(minutes * 60)) }
+
+    func wire(now: Date = Date()) -> [String: Any] {
+        var object: [String: Any] = [
+            "minutes": minutes, "started": started.timeIntervalSince1970, "ends": ends.timeIntervalSince1970,
+            "remaining": max(0, Int(ends.timeIntervalSince(now).rounded())),
+        ]
+        if let workspace { object["workspace"] = workspace }
+        return object
+    }
+}
+
+/// Attributes active time to workspaces — the git repo, or else the
+/// directory, of the focused pane — so hours can be billed by project.
+/// The app ticks it every `tickInterval` while it is frontmost; keyboard
+/// and mouse idleness past `idleThreshold` stops the clock unless a
+/// command has been running or finishing in that workspace meanwhile.
+/// Spans are kept in Application Support and never leave the Mac.
+final class TimeTracker {
+    static let shared = TimeTracker(url: TimeTracker.defaultURL)
+    static let tickInterval: TimeInterval = 30
+
+    static var defaultURL: URL { AppSupport.url("time-tracking.json") }
+
+    /// 0 turns idle detection off.
+    var idleThreshold: TimeInterval = 300
+    /// Called on the main thread when a pomodoro runs its course.
+    var onPomodoroFinished: ((Pomodoro) -> Void)?
+
+    private struct State {
+        var spans: [TimeSpan]?
+        var lastTick: Date?
+        var lastCommand: [String: Date] = [:]
+        var pomodoro: Pomodoro?
+    }
+
+    let url: URL
+    private let file: JSONFileStore<[TimeSpan]>
+    private let state = LockedState(State())
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, dates: .secondsSince1970)
+    }
+
+    static func workspace(for directory: String) -> String {
+        PetTipScanner.repoRoot(for: directory) ?? directory
+    }
+
+    /// One tick: `workspace` is where the user is looking, nil when the app
+    /// is in the background. A new span is backdated to the previous tick
+    /// so switching workspaces loses nothing.
+    func record(workspace: String?, idle: TimeInterval, now: Date = Date()) {
+        let changed = state.withLock { state -> [TimeSpan]? in
+            load(&state)
+            let previous = state.lastTick
+            state.lastTick = nil
+            guard let workspace else { return nil }
+            let commandRecently = state.lastCommand[workspace].map { now.timeIntervalSince($0) < idleThreshold }
+            guard idleThreshold <= 0 || idle < idleThreshold || commandRecently == true else { return nil }
+            state.lastTick = now
+
+            var spans = state.spans ?? []
+            if let last = spans.last, last.workspace == workspace, let previous, last.end == previous {
+                spans[spans.count - 1].end = now
+            } else {
+                let start = previous.map { max($0, now.addingTimeInterval(-2 * Self.tickInterval)) } ?? now
+                spans.append(TimeSpan(workspace: workspace, start: start, end: now))
+            }
+            state.spans = spans
+            return spans
+        }
+        if let changed { save(changed) }
+    }
+
+    /// A command started or finished in `workspace`; a long build or test
+    /// run keeps the clock going while the keyboard is idle.
+    func noteCommand(in workspace: String, at now: Date = Date()) {
+        state.withLock { $0.lastCommand[workspace] = now }
+    }
+
+    func spans() -> [TimeSpan] {
+        state.withLock { state in
+            load(&state)
+            return state.spans ?? []
+        }
+    }
+
+    func report(
+        range: String, now: Date = Date(), calendar: Calendar = .current
+    ) -> Result<[String: Any], TimeTrackerError> {
+        guard let interval = Self.interval(for: range, now: now, calendar: calendar) else {
+            return .failure(.invalidRange(range))
+        }
+        return .success(Self.summarize(spans(), in: interval, calendar: calendar))
+    }
+
+    // MARK: - Pomodoro
+
+    var pomodoro: Pomodoro? { state.snapshot.pomodoro }
+
+    /// Starts (or restarts) a pomodoro; `onPomodoroFinished` fires when it
+    /// ends unless it was stopped or replaced first.
+    func startPomodoro(minutes: Int, workspace: String?, now: Date = Date()) -> Pomodoro {
+        let pomodoro = Pomodoro(workspace: workspace, started: now, minutes: minutes)
+        state.withLock { $0.pomodoro = pomodoro }
+        DispatchQueue.main.asyncAfter(deadline: .now() + TimeInterval(minutes * 60)) { [weak self] in
+            guard let self else { return }
+            let finished = self.state.withLock { state -> Bool in
+                guard state.pomodoro == pomodoro else { return false }
+                state.pomodoro = nil
+                return true
+            }
+            if finished { self.onPomodoroFinished?(pomodoro) }
+        }
+        return pomodoro
+    }
+
+    func stopPomodoro() -> Pomodoro? {
+        state.withLock { state in
+            defer { state.pomodoro = nil }
+            return state.pomodoro
+        }
+    }
+
+    // MARK: - Reports
+
+    /// `today`, `yesterday`, `week`, `last-week`, `month`, `last-month`,
+    /// `<n>d` (the last n days, today included), a `YYYY-MM-DD` day, or an
+    /// inclusive `YYYY-MM-DD..YYYY-MM-DD`.
+    static func interval(for range: String, now: Date, calendar: Calendar) -> DateInterval? {
+        let today = calendar.startOfDay(for: now)
+        func day(_ offset: Int, from date: Date = today) -> Date? {
+            calendar.date(byAdding: .day, value: offset, to: date)
+        }
+        func parse(_ text: Substring) -> Date? {
+            let parts = text.split(separator: "-").compactMap { Int($0) }
+            guard parts.count == 3 else { return nil }
+            return calendar.date(from: DateComponents(year: parts[0], month: parts[1], day: parts[2]))
+        }
+        let key = range.trimmingCharacters(in: .whitespaces).lowercased()
+        switch key {
+        case "", "today":
+            return day(1).map { DateInterval(start: today, end: $0) }
+        case "yesterday":
+            return day(-1).map { DateInterval(start: $0, end: today) }
+        case "week", "last-week", "month", "last-month":
+            let unit: Calendar.Component = key.hasSuffix("week") ? .weekOfYear : .month
+            guard var current = calendar.dateInterval(of: unit, for: now) else { return nil }
+            if key.hasPrefix("last-") {
+                guard let previous = calendar.date(byAdding: unit, value: -1, to: current.start),
+                      let interval = calendar.dateInterval(of: unit, for: previous)
+                else { return nil }
+                current = interval
+            }
+            return current
+        default:
+            if key.hasSuffix("d"), let days = Int(key.dropLast()), (1...3660).contains(days) {
+                guard let start = day(1 - days), let end = day(1) else { return nil }
+                return DateInterval(start: start, end: end)
+            }
+            let bounds = key.components(separatedBy: "..")
+            guard (1...2).contains(bounds.count), let first = parse(Substring(bounds[0])),
+                  let last = parse(Substring(bounds[bounds.count - 1])), first <= last,
+                  let end = day(1, from: last)
+            else { return nil }
+            return DateInterval(start: first, end: end)
+        }
+    }
+
+    /// Seconds per workspace within `interval`, largest first, each split
+    /// by local calendar day.
+    static func summarize(_ spans: [TimeSpan], in interval: DateInterval, calendar: Calendar) -> [String: Any] {
+        var perDay: [String: [String: TimeInterval]] = [:]
+        for span in spans {
+            var cursor = max(span.start, interval.start)
+            let end = min(span.end, interval.end)
+            while cursor < end {
+                let midnight = calendar.date(byAdding: .day, value: 1, to: calendar.startOfDay(for: cursor)) ?? end
+                let stop = min(midnight, end)
+                perDay[span.workspace, default: [:]][dayKey(cursor, calendar: calendar), default: 0]
+                    += stop.timeIntervalSince(cursor)
+                cursor = stop
+            }
+        }
+        let workspaces = perDay
+            .map { workspace, days -> [String: Any] in
+                let seconds = Int(days.values.reduce(0, +).rounded())
+                return [
+                    "workspace": workspace, "seconds": seconds, "hours": (Double(seconds) / 36).rounded() / 100,
+                    "days": days.mapValues { Int($0.rounded()) },
+                ]
+            }
+            .sorted { ($0["seconds"] as? Int ?? 0) > ($1["seconds"] as? Int ?? 0) }
+        let total = workspaces.reduce(0) { $0 + ($1["seconds"] as? Int ?? 0) }
+        let lastDay = interval.end.addingTimeInterval(-1)
+        return [
+            "from": dayKey(interval.start, calendar: calendar), "to": dayKey(lastDay, calendar: calendar),
+            "total": total, "workspaces": workspaces,
+        ]
+    }
+
+    private static func dayKey(_ date: Date, calendar: Calendar) -> String {
+        let parts = calendar.dateComponents([.year, .month, .day], from: date)
+        return String(format: "%04d-%02d-%02d", parts.year ?? 0, parts.month ?? 0, parts.day ?? 0)
+    }
+
+    // MARK: - Storage
+
+    private func load(_ state: inout State) {
+        guard state.spans == nil else { return }
+        state.spans = file.load() ?? []
+    }
+
+    private func save(_ spans: [TimeSpan]) {
+        file.save(spans)
+    }
+}
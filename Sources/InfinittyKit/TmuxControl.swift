@@ -0,0 +1,396 @@
+import Foundation
+
+enum TmuxError: Error, Equatable, CustomStringConvertible {
+    case notInstalled
+    case launchFailed(String)
+    case failed(String)
+    case notFound(String)
+    case exited(String)
+
+    var description: String {
+        switch self {
+        case .notInstalled: return "tmux isn't installed"
+        case .launchFailed(let reason): return "could not start tmux: \(reason)"
+        case .failed(let message): return "tmux: \(message)"
+        case .notFound(let id): return "no tmux client \(id)"
+        case .exited(let reason): return "tmux exited: \(reason)"
+        }
+    }
+}
+
+/// What tmux says in control mode: a reply to one of our commands (the
+/// lines between `%begin` and `%end` or `%error`), or a notification.
+enum TmuxMessage: Equatable {
+    case reply(lines: [String], failed: Bool)
+    case output(pane: Int, bytes: [UInt8])
+    case windowAdd(window: Int)
+    case windowClose(window: Int)
+    case windowRenamed(window: Int, name: String)
+    case layoutChange(window: Int, layout: String)
+    case sessionChanged(session: Int, name: String)
+    case exit(reason: String?)
+    /// Notifications we don't act on (`%pane-mode-changed`, `%client-*`…).
+    case other(String)
+}
+
+/// Splits control-mode output into messages. Reply bodies can look like
+/// notifications (`list-panes` output starts with `%1`), so inside a block
+/// everything up to the matching `%end`/`%error` is body.
+struct TmuxControlStream {
+    private var buffer: [UInt8] = []
+    private var block: (number: String, lines: [String])?
+
+    mutating func feed(_ data: Data) -> [TmuxMessage] {
+        buffer.append(contentsOf: data)
+        var messages: [TmuxMessage] = []
+        while let newline = buffer.firstIndex(of: 0x0A) {
+            var line = Array(buffer[..<newline])
+            buffer.removeSubrange(...newline)
+            if line.last == 0x0D { line.removeLast() }
+            if let message = take(line) { messages.append(message) }
+        }
+        return messages
+    }
+
+    private mutating func take(_ line: [UInt8]) -> TmuxMessage? {
+        if let open = block {
+            let text = String(decoding: line, as: UTF8.self)
+            let fields = text.split(separator: " ")
+            if fields.count >= 3, fields[0] == "%end" || fields[0] == "%error", fields[2] == open.number {
+                block = nil
+                return .reply(lines: open.lines, failed: fields[0] == "%error")
+            }
+            block?.lines.append(text)
+            return nil
+        }
+        if line.starts(with: Array("%output ".utf8)) {
+            return Self.output(line)
+        }
+        let text = String(decoding: line, as: UTF8.self)
+        let fields = text.split(separator: " ", maxSplits: 2).map(String.init)
+        guard let kind = fields.first else { return nil }
+        func id(_ index: Int) -> Int? {
+            guard fields.count > index else { return nil }
+            return Int(fields[index].dropFirst())
+        }
+        switch kind {
+        case "%begin":
+            guard fields.count >= 3 else { return .other(text) }
+            block = (fields[2].split(separator: " ").first.map(String.init) ?? "", [])
+            return nil
+        case "%window-add":
+            return id(1).map { .windowAdd(window: $0) } ?? .other(text)
+        case "%window-close", "%unlinked-window-close":
+            return id(1).map { .windowClose(window: $0) } ?? .other(text)
+        case "%window-renamed":
+            return id(1).map { .windowRenamed(window: $0, name: fields.count > 2 ? fields[2] : "") } ?? .other(text)
+        case "%layout-change":
+            guard let window = id(1), fields.count > 2 else { return .other(text) }
+            return .layoutChange(window: window, layout: String(fields[2].prefix { $0 != " " }))
+        case "%session-changed":
+            return id(1).map { .sessionChanged(session: $0, name: fields.count > 2 ? fields[2] : "") } ?? .other(text)
+        case "%exit":
+            return .exit(reason: fields.count > 1 ? fields.dropFirst().joined(separator: " ") : nil)
+        default:
+            return .other(text)
+        }
+    }
+
+    /// `%output %<pane> <data>`, where tmux writes bytes below space and
+    /// backslashes as octal escapes (`\015`).
+    private static func output(_ line: [UInt8]) -> TmuxMessage {
+        let rest = line.dropFirst(8)
+        guard rest.first == UInt8(ascii: "%"), let space = rest.firstIndex(of: 0x20),
+              let pane = Int(String(decoding: rest[rest.index(after: rest.startIndex)..<space], as: UTF8.self))
+        else { return .other(String(decoding: line, as: UTF8.self)) }
+        var bytes: [UInt8] = []
+        var index = rest.index(after: space)
+        while index < rest.endIndex {
+            let byte = rest[index]
+            if byte == UInt8(ascii: "\\"), rest.distance(from: index, to: rest.endIndex) >= 4 {
+                let digits = rest[(index + 1)...(index + 3)]
+                if digits.allSatisfy({ (0x30...0x37).contains($0) }),
+                   let value = UInt8(String(decoding: digits, as: UTF8.self), radix: 8) {
+                    bytes.append(value)
+                    index += 4
+                    continue
+                }
+            }
+            bytes.append(byte)
+            index += 1
+        }
+        return .output(pane: pane, bytes: bytes)
+    }
+}
+
+/// A tmux window layout (`#{window_layout}`, as `%layout-change` reports
+/// it): `b25d,80x24,0,0{40x24,0,0,1,39x24,41,0,2}` is two panes side by
+/// side. `{…}` splits left to right, `[…]` top to bottom.
+indirect enum TmuxLayout: Equatable {
+    case pane(Int, width: Int, height: Int)
+    case split(sideBySide: Bool, [TmuxLayout], width: Int, height: Int)
+
+    /// Pane ids, left to right and top to bottom.
+    var panes: [Int] {
+        switch self {
+        case .pane(let id, _, _): return [id]
+        case .split(_, let children, _, _): return children.flatMap(\.panes)
+        }
+    }
+
+    var width: Int {
+        switch self {
+        case .pane(_, let width, _), .split(_, _, let width, _): return width
+        }
+    }
+
+    var height: Int {
+        switch self {
+        case .pane(_, _, let height), .split(_, _, _, let height): return height
+        }
+    }
+
+    /// Whether the split directly holding `pane` lays it out side by side;
+    /// nil when the pane isn't in this layout or is all of it.
+    func splitsSideBySide(around pane: Int) -> Bool? {
+        guard case .split(let sideBySide, let children, _, _) = self else { return nil }
+        for child in children {
+            if case .pane(pane, _, _) = child { return sideBySide }
+            if let inner = child.splitsSideBySide(around: pane) { return inner }
+        }
+        return nil
+    }
+
+    static func parse(_ text: String) -> TmuxLayout? {
+        var scanner = Array(text.utf8)[...]
+        // The leading checksum, when present.
+        if scanner.count > 5, scanner.prefix(4).allSatisfy({ Character(UnicodeScalar($0)).isHexDigit }),
+           scanner.dropFirst(4).first == UInt8(ascii: ",") {
+            scanner = scanner.dropFirst(5)
+        }
+        guard let layout = cell(&scanner), scanner.isEmpty else { return nil }
+        return layout
+    }
+
+    private static func cell(_ s: inout ArraySlice<UInt8>) -> TmuxLayout? {
+        guard let width = number(&s), eat(&s, "x"), let height = number(&s),
+              eat(&s, ","), number(&s) != nil, eat(&s, ","), number(&s) != nil
+        else { return nil }
+        if eat(&s, ",") {
+            return number(&s).map { .pane($0, width: width, height: height) }
+        }
+        let sideBySide: Bool
+        let close: Character
+        if eat(&s, "{") {
+            (sideBySide, close) = (true, "}")
+        } else if eat(&s, "[") {
+            (sideBySide, close) = (false, "]")
+        } else {
+            return nil
+        }
+        var children: [TmuxLayout] = []
+        repeat {
+            guard let child = cell(&s) else { return nil }
+            children.append(child)
+        } while eat(&s, ",")
+        guard eat(&s, close) else { return nil }
+        return .split(sideBySide: sideBySide, children, width: width, height: height)
+    }
+
+    private static func number(_ s: inout ArraySlice<UInt8>) -> Int? {
+        let digits = s.prefix { (0x30...0x39).contains($0) }
+        guard !digits.isEmpty else { return nil }
+        s = s.dropFirst(digits.count)
+        return Int(String(decoding: digits, as: UTF8.self))
+    }
+
+    private static func eat(_ s: inout ArraySlice<UInt8>, _ character: Character) -> Bool {
+        guard let byte = character.asciiValue, s.first == byte else { return false }
+        s = s.dropFirst()
+        return true
+    }
+}
+
+/// One `tmux -C attach` connection. Commands are answered in the order
+/// they were sent, so replies are matched to a FIFO of callbacks. Output
+/// goes straight to the PTY bound to its pane; every other message goes to
+/// `onMessage`, on the client's read queue.
+final class TmuxControlClient {
+    typealias Reply = (Result<[String], TmuxError>) -> Void
+
+    let id: String
+    /// `-L` socket name or `-S` path (one with a slash).
+    let socket: String?
+    let session: String?
+
+    var onMessage: ((TmuxControlClient, TmuxMessage) -> Void)?
+    /// The connection ended: tmux exited, the server went away or we
+    /// detached.
+    var onEnded: ((TmuxControlClient, String) -> Void)?
+
+    private struct State {
+        var replies: [Reply?] = []
+        var stream = TmuxControlStream()
+        /// Bound panes; output reaches one once its screen has been drawn.
+        var panes: [Int: (pty: PTY, live: Bool)] = [:]
+        var ended: String?
+    }
+
+    private let state = LockedState(State())
+    private let queue: DispatchQueue
+    private var process: Process?
+    private var input: FileHandle?
+
+    init(id: String, socket: String?, session: String?) {
+        self.id = id
+        self.socket = socket
+        self.session = session
+        queue = DispatchQueue(label: "infinitty.tmux.\(id)", qos: .userInitiated)
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id]
+        if let socket { object["socket"] = socket }
+        if let session { object["session"] = session }
+        return object
+    }
+
+    /// The arguments before the command: which server to talk to.
+    static func serverArguments(socket: String?) -> [String] {
+        guard let socket, !socket.isEmpty else { return [] }
+        return socket.contains("/") ? ["-S", (socket as NSString).expandingTildeInPath] : ["-L", socket]
+    }
+
+    func start() -> Result<Void, TmuxError> {
+        guard let tmux = ServiceMap.executable("tmux") else { return .failure(.notInstalled) }
+        let p = Process()
+        p.executableURL = URL(fileURLWithPath: tmux)
+        var arguments = Self.serverArguments(socket: socket) + ["-C", "attach-session"]
+        if let session, !session.isEmpty { arguments += ["-t", session] }
+        p.arguments = arguments
+        let stdin = Pipe()
+        let stdout = Pipe()
+        p.standardInput = stdin
+        p.standardOutput = stdout
+        p.standardError = stdout
+        p.terminationHandler = { [weak self] p in
+            self?.queue.async { self?.finish("status \(p.terminationStatus)") }
+        }
+        // tmux opens control mode with a reply to the attach itself.
+        state.withLock { $0.replies.append(nil) }
+        do {
+            try p.run()
+        } catch {
+            return .failure(.launchFailed(error.localizedDescription))
+        }
+        process = p
+        input = stdin.fileHandleForWriting
+        stdout.fileHandleForReading.readabilityHandler = { [weak self] handle in
+            let data = handle.availableData
+            guard let self else { return }
+            if data.isEmpty {
+                handle.readabilityHandler = nil
+                return
+            }
+            self.queue.async { self.ingest(data) }
+        }
+        return .success(())
+    }
+
+    /// Sends one command line; `reply` gets its output lines, or the error
+    /// tmux printed.
+    func send(_ command: String, reply: Reply? = nil) {
+        let sent = state.withLock { state -> Bool in
+            guard state.ended == nil, let input else { return false }
+            state.replies.append(reply)
+            // Under the lock, so the callback order is the write order.
+            try? input.write(contentsOf: Data((command + "\n").utf8))
+            return true
+        }
+        if !sent { reply?(.failure(.exited(state.snapshot.ended ?? "not attached"))) }
+    }
+
+    /// Types `bytes` into a tmux pane as they are, keys and all.
+    func sendKeys(_ bytes: [UInt8], to pane: Int) {
+        // send-keys takes a bounded argument list; long pastes go in parts.
+        for start in stride(from: 0, to: bytes.count, by: 256) {
+            let chunk = bytes[start..<min(start + 256, bytes.count)]
+            send("send-keys -t %\(pane) -H " + chunk.map { String(format: "%02x", $0) }.joined(separator: " "))
+        }
+    }
+
+    /// Makes `pty` the native end of tmux pane `pane`: keys typed into it
+    /// are sent to the pane, a resize resizes the pane and closing it kills
+    /// the pane. It starts with what the pane shows now, then gets the
+    /// pane's output from there on.
+    func bind(_ pty: PTY, to pane: Int) {
+        pty.attach(remote: PTY.Remote(
+            write: { [weak self] bytes in self?.sendKeys(bytes, to: pane) },
+            resize: { [weak self] cols, rows in self?.send("resize-pane -t %\(pane) -x \(cols) -y \(rows)") },
+            close: { [weak self] in self?.send("kill-pane -t %\(pane)") }))
+        state.withLock { $0.panes[pane] = (pty, false) }
+        var screen: [String] = []
+        send("capture-pane -p -e -t %\(pane)") { result in
+            if case .success(let lines) = result { screen = lines }
+        }
+        // Replies come in order on the read queue, after the capture's and
+        // before any output that followed it.
+        send("display-message -p -t %\(pane) '#{cursor_x} #{cursor_y}'") { [weak self] result in
+            let cursor = (try? result.get())?.first?.split(separator: " ").compactMap { Int($0) } ?? []
+            var bytes = Array("\u{1B}[H\u{1B}[2J".utf8) + Array(screen.joined(separator: "\r\n").utf8)
+            if cursor.count == 2 { bytes += Array("\u{1B}[\(cursor[1] + 1);\(cursor[0] + 1)H".utf8) }
+            guard self?.state.withLock({ $0.panes[pane]?.pty === pty }) == true else { return }
+            pty.deliver(bytes)
+            self?.state.withLock { $0.panes[pane]?.live = true }
+        }
+    }
+
+    /// The pane is gone from tmux; its native pane closes.
+    func unbind(pane: Int) {
+        state.withLock { $0.panes.removeValue(forKey: pane) }?.pty.disconnectRemote()
+    }
+
+    var boundPanes: [Int] { state.snapshot.panes.keys.sorted() }
+
+    /// Leaves the tmux session running, as a `prefix d` would.
+    func detach() {
+        send("detach-client")
+    }
+
+    func ingest(_ data: Data) {
+        let messages = state.withLock { $0.stream.feed(data) }
+        for message in messages {
+            if case .output(let pane, let bytes) = message {
+                state.withLock { $0.panes[pane] }.flatMap { $0.live ? $0.pty : nil }?.deliver(bytes)
+                continue
+            }
+            guard case .reply(let lines, let failed) = message else {
+                if case .exit(let reason) = message { finish(reason ?? "detached") }
+                onMessage?(self, message)
+                continue
+            }
+            let callback = state.withLock { state -> Reply? in
+                state.replies.isEmpty ? nil : state.replies.removeFirst()
+            }
+            callback?(failed ? .failure(.failed(lines.joined(separator: "\n"))) : .success(lines))
+        }
+    }
+
+    private func finish(_ reason: String) {
+        let (first, orphans, panes) = state.withLock { state -> (Bool, [Reply?], [PTY]) in
+            guard state.ended == nil else { return (false, [], []) }
+            state.ended = reason
+            defer {
+                state.replies.removeAll()
+                state.panes.removeAll()
+            }
+            return (true, state.replies, state.panes.values.map(\.pty))
+        }
+        guard first else { return }
+        for reply in orphans { reply?(.failure(.exited(reason))) }
+        panes.forEach { $0.disconnectRemote() }
+        try? input?.close()
+        if let process, process.isRunning { process.terminate() }
+        onEnded?(self, reason)
+    }
+}
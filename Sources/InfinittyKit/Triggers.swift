@@ -0,0 +1,180 @@
+import Foundation
+
+enum TriggerError: Error, Equatable, CustomStringConvertible {
+    case invalidPattern(String)
+    case unknownAction(String)
+    case missingCommand
+    case notFound(String)
+
+    var description: String {
+        switch self {
+        case .invalidPattern(let reason): return reason
+        case .unknownAction(let name): return "unknown action '\(name)' (use event, run, highlight, notify)"
+        case .missingCommand: return "the run action needs a command"
+        case .notFound(let id): return "no trigger \(id)"
+        }
+    }
+}
+
+/// What a trigger does with each match: publish a `trigger` event, run a
+/// shell command, paint the matched cells, or post a notification.
+enum TriggerAction: Equatable {
+    case event
+    case run(String)
+    case highlight
+    case notify
+
+    init?(name: String, command: String?) {
+        switch name {
+        case "event", "emit-event": self = .event
+        case "run", "run-command":
+            guard let command, !command.isEmpty else { return nil }
+            self = .run(command)
+        case "highlight": self = .highlight
+        case "notify": self = .notify
+        default: return nil
+        }
+    }
+
+    var name: String {
+        switch self {
+        case .event: return "event"
+        case .run: return "run"
+        case .highlight: return "highlight"
+        case .notify: return "notify"
+        }
+    }
+}
+
+/// A pattern watched for in output, in one pane or (with no pane) in all
+/// of them.
+struct Trigger: Equatable {
+    let id: String
+    let pattern: String
+    let action: TriggerAction
+    let pane: Int?
+    var fired = 0
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "pattern": pattern, "action": action.name, "fired": fired]
+        if case .run(let command) = action { object["command"] = command }
+        if let pane { object["pane"] = pane }
+        return object
+    }
+}
+
+/// One match on a finished output row: absolute line and cell columns, as
+/// search results give them, plus the regex's capture groups.
+struct TriggerMatch: Equatable {
+    let trigger: Trigger
+    let pane: Int
+    let line: Int
+    let start: Int
+    let end: Int
+    let text: String
+    let groups: [String]
+
+    var wire: [String: Any] {
+        [
+            "trigger": trigger.id, "action": trigger.action.name, "pane": pane, "line": line,
+            "start": start, "end": end, "text": text, "groups": groups,
+        ]
+    }
+}
+
+/// The app's output triggers. Panes scan each row once it is finished (the
+/// cursor has left it) on their PTY thread, so a pattern sees a line as it
+/// ends up on screen, escape sequences and progress-bar rewrites already
+/// applied. A match can't cross a soft wrap.
+final class TriggerEngine {
+    static let shared = TriggerEngine()
+    /// Matches reported per trigger per row; a flood of repeats is noise.
+    static let maxMatchesPerRow = 16
+
+    private struct Entry {
+        var trigger: Trigger
+        let regex: NSRegularExpression
+    }
+
+    private struct State {
+        var entries: [Entry] = []
+        var nextID = 1
+    }
+
+    private let state = LockedState(State())
+
+    var triggers: [Trigger] { state.snapshot.entries.map(\.trigger) }
+
+    func triggers(for pane: Int) -> [Trigger] {
+        triggers.filter { $0.pane == nil || $0.pane == pane }
+    }
+
+    func add(pattern: String, action: TriggerAction, pane: Int? = nil) -> Result<Trigger, TriggerError> {
+        guard !pattern.isEmpty else { return .failure(.invalidPattern("empty pattern")) }
+        let regex: NSRegularExpression
+        switch RegexScratchpad.compile(pattern, flags: "") {
+        case .success(let compiled): regex = compiled
+        case .failure(let error): return .failure(.invalidPattern(error.description))
+        }
+        return .success(state.withLock { state in
+            let trigger = Trigger(id: "trigger-\(state.nextID)", pattern: pattern, action: action, pane: pane)
+            state.nextID += 1
+            state.entries.append(Entry(trigger: trigger, regex: regex))
+            return trigger
+        })
+    }
+
+    func remove(_ id: String) -> Bool {
+        state.withLock { state in
+            let before = state.entries.count
+            state.entries.removeAll { $0.trigger.id == id }
+            return state.entries.count != before
+        }
+    }
+
+    /// Drops the triggers scoped to a closed pane.
+    func forget(pane: Int) {
+        state.withLock { $0.entries.removeAll { $0.trigger.pane == pane } }
+    }
+
+    func removeAll() {
+        state.withLock { $0.entries.removeAll() }
+    }
+
+    /// Every trigger match in one finished row of `pane`, given as its
+    /// characters one per cell.
+    func matches(pane: Int, line: Int, chars: [Character]) -> [TriggerMatch] {
+        let entries = state.snapshot.entries.filter { $0.trigger.pane == nil || $0.trigger.pane == pane }
+        guard !entries.isEmpty else { return [] }
+        // One UTF-16 unit per cell, so match ranges are columns.
+        var end = chars.count
+        while end > 0, chars[end - 1] == " " { end -= 1 }
+        let text = String(chars[0..<end].map { $0.utf16.count == 1 ? $0 : "\u{FFFD}" }) as NSString
+        var found: [TriggerMatch] = []
+        var fired: [String: Int] = [:]
+        for entry in entries {
+            let results = entry.regex.matches(in: text as String, range: NSRange(location: 0, length: text.length))
+            for result in results.prefix(Self.maxMatchesPerRow) where result.range.length > 0 {
+                let lo = result.range.location
+                let hi = lo + result.range.length - 1
+                let groups = (1..<max(result.numberOfRanges, 1)).map { index -> String in
+                    let range = result.range(at: index)
+                    guard range.location != NSNotFound, range.length > 0 else { return "" }
+                    return String(chars[range.location..<(range.location + range.length)])
+                }
+                found.append(TriggerMatch(
+                    trigger: entry.trigger, pane: pane, line: line, start: lo, end: hi,
+                    text: String(chars[lo...hi]), groups: groups))
+                fired[entry.trigger.id, default: 0] += 1
+            }
+        }
+        if !fired.isEmpty {
+            state.withLock { state in
+                for index in state.entries.indices {
+                    state.entries[index].trigger.fired += fired[state.entries[index].trigger.id] ?? 0
+                }
+            }
+        }
+        return found
+    }
+}
@@ -31,6 +31,8 @@ enum UtilityPanelKind: String, CaseIterable {
     case browser
     /// Agent-requested display surface (markdown doc, MCP-UI HTML, or URL).
     case surface
+    /// Workspace notes and todos for the focused terminal's repo.
+    case notes
 
     var title: String {
         switch self {
@@ -38,6 +40,7 @@ enum UtilityPanelKind: String, CaseIterable {
         case .chat: return "Chat"
         case .browser: return "Browser"
         case .surface: return "Surface"
+        case .notes: return "Notes"
         }
     }
 
@@ -47,6 +50,7 @@ enum UtilityPanelKind: String, CaseIterable {
         case .chat: return "bubble.left.and.bubble.right"
         case .browser: return "globe"
         case .surface: return "sparkles.rectangle.stack"
+        case .notes: return "note.text"
         }
     }
 
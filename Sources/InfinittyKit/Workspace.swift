@@ -0,0 +1,133 @@
+import Foundation
+
+/// One terminal pane of a saved workspace: where its shell was and which
+/// profile started it.
+struct WorkspacePane: Codable, Equatable {
+    var cwd: String?
+    var profile: String?
+    /// Its text size and line height when they differ from the config's.
+    var display: PaneDisplay?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = [:]
+        if let cwd { object["cwd"] = cwd }
+        if let profile { object["profile"] = profile }
+        if let display { object["display"] = display.wire }
+        return object
+    }
+}
+
+/// A browser pane of a saved tab: which browser it was, the page it had
+/// open, and where it sat — split off one of the tab's terminal panes.
+struct WorkspaceBrowser: Codable, Equatable {
+    let id: String
+    var url: String
+    /// Index into the tab's `panes` of the terminal it shared a split with.
+    var beside: Int
+    /// Side by side with that terminal rather than above or below it.
+    var vertical: Bool
+    /// Before the terminal (left or above) rather than after it.
+    var leading: Bool?
+    /// Its share of that split, 0–1.
+    var share: Double?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["id": id, "url": url, "beside": beside, "vertical": vertical]
+        if leading == true { object["leading"] = true }
+        if let share { object["share"] = share }
+        return object
+    }
+}
+
+/// A tab's split geometry and its panes in reading order, the order
+/// `applyLayout` fills the shape's slots in.
+struct WorkspaceTab: Codable, Equatable {
+    let shape: LayoutShape
+    let panes: [WorkspacePane]
+    /// Index into `panes` of the one that had focus.
+    var focused: Int?
+    /// Reopened once the terminals are in place, in this order.
+    var browsers: [WorkspaceBrowser]?
+
+    var isRestorable: Bool {
+        !panes.isEmpty && shape.paneCount == panes.count && panes.count <= LayoutShape.maxPanes
+    }
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["shape": shape.wire, "panes": panes.map(\.wire)]
+        if let focused { object["focused"] = focused }
+        if let browsers, !browsers.isEmpty { object["browsers"] = browsers.map(\.wire) }
+        return object
+    }
+}
+
+/// A window and its tabs, left to right.
+struct WorkspaceWindow: Codable, Equatable {
+    /// x, y, width and height in screen points.
+    var frame: [Double]?
+    let tabs: [WorkspaceTab]
+    var selectedTab: Int?
+
+    var wire: [String: Any] {
+        var object: [String: Any] = ["tabs": tabs.map(\.wire)]
+        if let frame { object["frame"] = frame }
+        if let selectedTab { object["selectedTab"] = selectedTab }
+        return object
+    }
+}
+
+/// Every terminal window, tab and split, and the browser panes beside
+/// them, as `restore-workspace` rebuilds them at the next launch. Files and
+/// Chat panes aren't kept; a tab without a terminal is left out.
+struct WorkspaceLayout: Codable, Equatable {
+    var windows: [WorkspaceWindow]
+
+    var paneCount: Int { windows.reduce(0) { $0 + $1.tabs.reduce(0) { $0 + $1.panes.count } } }
+
+    /// The windows with the tabs that can be rebuilt, dropping any whose
+    /// shape and panes disagree (a hand-edited file) and windows left empty.
+    /// Focus and selection indexes that no longer point anywhere are
+    /// cleared, and browsers beside a pane that isn't there are dropped.
+    var restorable: WorkspaceLayout {
+        WorkspaceLayout(windows: windows.compactMap { window in
+            let tabs = window.tabs.filter(\.isRestorable).map { tab -> WorkspaceTab in
+                var tab = tab
+                if let focused = tab.focused, !tab.panes.indices.contains(focused) { tab.focused = nil }
+                tab.browsers = tab.browsers?.filter { tab.panes.indices.contains($0.beside) }
+                return tab
+            }
+            guard !tabs.isEmpty else { return nil }
+            var kept = WorkspaceWindow(frame: window.frame, tabs: tabs, selectedTab: window.selectedTab)
+            if tabs.count != window.tabs.count || !tabs.indices.contains(window.selectedTab ?? 0) {
+                kept.selectedTab = nil
+            }
+            return kept
+        })
+    }
+
+    var wire: [String: Any] { ["windows": windows.map(\.wire), "panes": paneCount] }
+}
+
+/// The last saved workspace, as JSON under Application Support.
+final class WorkspaceStore {
+    static let shared = WorkspaceStore(url: WorkspaceStore.defaultURL)
+
+    static var defaultURL: URL { AppSupport.url("workspace.json") }
+
+    let url: URL
+    private let file: JSONFileStore<WorkspaceLayout>
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url)
+    }
+
+    func load() -> WorkspaceLayout? {
+        file.load()
+    }
+
+    @discardableResult
+    func save(_ layout: WorkspaceLayout) -> Bool {
+        file.save(layout)
+    }
+}
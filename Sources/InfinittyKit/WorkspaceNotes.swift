@@ -0,0 +1,445 @@
+import AppKit
+
+enum WorkspaceNotesError: Error, Equatable, CustomStringConvertible {
+    case emptyText
+    case noSuchTodo(String)
+
+    var description: String {
+        switch self {
+        case .emptyText: return "todo text is empty"
+        case .noSuchTodo(let id): return "no todo \(id)"
+        }
+    }
+}
+
+/// A checklist item kept for a workspace. Ids count up per workspace so
+/// they are short enough to type (`todo-toggle 3`).
+struct WorkspaceTodo: Codable, Equatable {
+    let id: String
+    var text: String
+    var done: Bool
+    let created: Date
+
+    var wire: [String: Any] {
+        ["id": id, "text": text, "done": done, "created": created.timeIntervalSince1970]
+    }
+}
+
+/// A TODO or FIXME comment found in the workspace's files.
+struct CodeComment: Equatable {
+    let path: String
+    let line: Int
+    let tag: String
+    let text: String
+
+    var wire: [String: Any] { ["path": path, "line": line, "tag": tag, "text": text] }
+}
+
+/// Free-form notes and a todo list per workspace (a repo root, or the
+/// directory when there is no repo), in Application Support. Changes post
+/// `didChangeNotification` on the main queue with the workspace in
+/// `userInfo[workspaceKey]`, which the Notes pane and the `notes` socket
+/// event follow.
+final class WorkspaceNotes {
+    static let shared = WorkspaceNotes(url: WorkspaceNotes.defaultURL)
+    static let didChangeNotification = Notification.Name("infinitty.workspaceNotes.didChange")
+    static let workspaceKey = "workspace"
+    static let commentPattern = "\\b(TODO|FIXME)\\b"
+
+    static var defaultURL: URL { AppSupport.url("workspace-notes.json") }
+
+    private struct Entry: Codable {
+        var notes = ""
+        var todos: [WorkspaceTodo] = []
+    }
+
+    let url: URL
+    private let file: JSONFileStore<[String: Entry]>
+    private let entries = LockedState<[String: Entry]?>(nil)
+
+    init(url: URL) {
+        self.url = url
+        file = JSONFileStore(url: url, dates: .secondsSince1970, formatting: [.sortedKeys])
+    }
+
+    static func workspace(for directory: String) -> String {
+        PetTipScanner.repoRoot(for: directory) ?? directory
+    }
+
+    func notes(for workspace: String) -> String {
+        read { $0[workspace]?.notes ?? "" }
+    }
+
+    func setNotes(_ text: String, for workspace: String) {
+        update(workspace) { $0.notes = text }
+    }
+
+    func todos(for workspace: String) -> [WorkspaceTodo] {
+        read { $0[workspace]?.todos ?? [] }
+    }
+
+    func addTodo(
+        _ text: String, to workspace: String, now: Date = Date()
+    ) -> Result<WorkspaceTodo, WorkspaceNotesError> {
+        let text = text.trimmingCharacters(in: .whitespacesAndNewlines)
+        guard !text.isEmpty else { return .failure(.emptyText) }
+        return .success(update(workspace) { entry -> WorkspaceTodo in
+            let next = (entry.todos.compactMap { Int($0.id) }.max() ?? 0) + 1
+            let todo = WorkspaceTodo(id: String(next), text: text, done: false, created: now)
+            entry.todos.append(todo)
+            return todo
+        })
+    }
+
+    /// Flips the todo, or sets it when `done` is given.
+    func toggleTodo(
+        _ id: String, in workspace: String, done: Bool? = nil
+    ) -> Result<WorkspaceTodo, WorkspaceNotesError> {
+        let toggled = update(workspace) { entry -> WorkspaceTodo? in
+            guard let index = entry.todos.firstIndex(where: { $0.id == id }) else { return nil }
+            entry.todos[index].done = done ?? !entry.todos[index].done
+            return entry.todos[index]
+        }
+        return toggled.map { .success($0) } ?? .failure(.noSuchTodo(id))
+    }
+
+    func removeTodo(_ id: String, in workspace: String) -> Result<WorkspaceTodo, WorkspaceNotesError> {
+        let removed = update(workspace) { entry -> WorkspaceTodo? in
+            guard let index = entry.todos.firstIndex(where: { $0.id == id }) else { return nil }
+            return entry.todos.remove(at: index)
+        }
+        return removed.map { .success($0) } ?? .failure(.noSuchTodo(id))
+    }
+
+    func wire(for workspace: String) -> [String: Any] {
+        read { entries in
+            let entry = entries[workspace] ?? Entry()
+            return ["workspace": workspace, "notes": entry.notes, "todos": entry.todos.map(\.wire)]
+        }
+    }
+
+    // MARK: - comments
+
+    /// TODO/FIXME comments under `root` through the code search (rg when
+    /// installed). Blocking; `limit` bounds huge or comment-heavy trees.
+    static func scanComments(
+        in root: String, limit: Int = 500, cancellation: CancellationToken? = nil
+    ) -> [CodeComment] {
+        CodeSearch.grep(pattern: commentPattern, root: root, limit: limit, cancellation: cancellation)
+            .compactMap { match in
+                parseComment(match.text).map {
+                    CodeComment(path: match.path, line: match.line, tag: $0.tag, text: $0.text)
+                }
+            }
+    }
+
+    /// The tag and what follows it on a source line, minus an `(owner)`,
+    /// a colon and any closing comment marker.
+    static func parseComment(_ line: String) -> (tag: String, text: String)? {
+        guard let regex = try? NSRegularExpression(pattern: commentPattern + "(?:\\([^)]*\\))?:?(.*)$"),
+              let match = regex.firstMatch(in: line, range: NSRange(line.startIndex..., in: line)),
+              let tag = Range(match.range(at: 1), in: line),
+              let rest = Range(match.range(at: 2), in: line)
+        else { return nil }
+        var text = line[rest].trimmingCharacters(in: .whitespaces)
+        for marker in ["*/", "-->", "#}", "%>"] where text.hasSuffix(marker) {
+            text = String(text.dropLast(marker.count)).trimmingCharacters(in: .whitespaces)
+        }
+        return (String(line[tag]), text)
+    }
+
+    // MARK: - storage
+
+    private func read<R>(_ body: ([String: Entry]) -> R) -> R {
+        entries.withLock { entries in
+            load(&entries)
+            return body(entries ?? [:])
+        }
+    }
+
+    @discardableResult
+    private func update<R>(_ workspace: String, _ body: (inout Entry) -> R) -> R {
+        let (result, snapshot) = entries.withLock { entries -> (R, [String: Entry]) in
+            load(&entries)
+            var all = entries ?? [:]
+            var entry = all[workspace] ?? Entry()
+            let result = body(&entry)
+            all[workspace] = entry.notes.isEmpty && entry.todos.isEmpty ? nil : entry
+            entries = all
+            return (result, all)
+        }
+        save(snapshot)
+        DispatchQueue.main.async {
+            NotificationCenter.default.post(
+                name: Self.didChangeNotification, object: self, userInfo: [Self.workspaceKey: workspace])
+        }
+        return result
+    }
+
+    private func load(_ entries: inout [String: Entry]?) {
+        guard entries == nil else { return }
+        entries = file.load() ?? [:]
+    }
+
+    private func save(_ entries: [String: Entry]) {
+        file.save(entries)
+    }
+}
+
+// MARK: - Notes pane
+
+/// The Notes utility pane: the tracked terminal's workspace notes above its
+/// todo list, plus the project's TODO/FIXME comments on request. Follows
+/// the session's cwd the way the Files pane does.
+final class WorkspaceNotesViewController: NSViewController, NSTextViewDelegate {
+    private let store: WorkspaceNotes
+    private weak var session: TerminalSession?
+    private var cwdObserver: NSObjectProtocol?
+    private var storeObserver: NSObjectProtocol?
+    private(set) var workspace: String?
+
+    private let pathLabel = NSTextField(labelWithString: "")
+    private let notesView = NSTextView()
+    private let notesScroll = NSScrollView()
+    private let addField = NSTextField()
+    private let scanButton = NSButton()
+    private let todoStack = NSStackView()
+    private let todoScroll = NSScrollView()
+    private var comments: [CodeComment] = []
+    private var scan: CancellationToken?
+    private var pendingSave: DispatchWorkItem?
+
+    init(store: WorkspaceNotes = .shared) {
+        self.store = store
+        super.init(nibName: nil, bundle: nil)
+    }
+
+    required init?(coder: NSCoder) { fatalError("init(coder:) is not supported") }
+
+    deinit {
+        if let cwdObserver { NotificationCenter.default.removeObserver(cwdObserver) }
+        if let storeObserver { NotificationCenter.default.removeObserver(storeObserver) }
+        scan?.cancel()
+    }
+
+    override func loadView() {
+        let container = NSView()
+
+        pathLabel.font = .systemFont(ofSize: NSFont.smallSystemFontSize, weight: .medium)
+        pathLabel.textColor = .secondaryLabelColor
+        pathLabel.lineBreakMode = .byTruncatingHead
+
+        notesView.isRichText = false
+        notesView.font = .systemFont(ofSize: NSFont.systemFontSize)
+        notesView.textColor = .labelColor
+        notesView.drawsBackground = false
+        notesView.isAutomaticQuoteSubstitutionEnabled = false
+        notesView.textContainerInset = NSSize(width: 4, height: 6)
+        notesView.isVerticallyResizable = true
+        notesView.autoresizingMask = [.width]
+        notesView.textContainer?.widthTracksTextView = true
+        notesView.delegate = self
+        notesScroll.documentView = notesView
+        notesScroll.hasVerticalScroller = true
+        notesScroll.drawsBackground = false
+        notesScroll.wantsLayer = true
+        notesScroll.layer?.cornerRadius = 6
+        notesScroll.layer?.borderWidth = 1
+        notesScroll.layer?.borderColor = CodePalette.glassBorder.cgColor
+        notesScroll.layer?.backgroundColor = CodePalette.glassFill.cgColor
+
+        addField.placeholderString = "Add a todo"
+        addField.font = .systemFont(ofSize: NSFont.systemFontSize)
+        addField.isBezeled = false
+        addField.drawsBackground = true
+        addField.backgroundColor = CodePalette.glassFill
+        addField.focusRingType = .none
+        addField.target = self
+        addField.action = #selector(addTodo(_:))
+
+        scanButton.title = "TODO/FIXME"
+        scanButton.bezelStyle = .inline
+        scanButton.font = .systemFont(ofSize: NSFont.smallSystemFontSize)
+        scanButton.toolTip = "List TODO and FIXME comments in this workspace"
+        scanButton.target = self
+        scanButton.action = #selector(scanComments(_:))
+
+        todoStack.orientation = .vertical
+        todoStack.alignment = .leading
+        todoStack.spacing = 4
+        todoStack.edgeInsets = NSEdgeInsets(top: 4, left: 0, bottom: 8, right: 0)
+        todoStack.translatesAutoresizingMaskIntoConstraints = false
+        let document = FlippedView()
+        document.translatesAutoresizingMaskIntoConstraints = false
+        document.addSubview(todoStack)
+        todoScroll.documentView = document
+        todoScroll.hasVerticalScroller = true
+        todoScroll.drawsBackground = false
+
+        let addRow = NSStackView(views: [addField, scanButton])
+        addRow.orientation = .horizontal
+        addRow.spacing = 6
+        for view in [pathLabel, notesScroll, addRow, todoScroll] as [NSView] {
+            view.translatesAutoresizingMaskIntoConstraints = false
+            container.addSubview(view)
+        }
+        NSLayoutConstraint.activate([
+            pathLabel.topAnchor.constraint(equalTo: container.topAnchor, constant: 6),
+            pathLabel.leadingAnchor.constraint(equalTo: container.leadingAnchor, constant: 8),
+            pathLabel.trailingAnchor.constraint(equalTo: container.trailingAnchor, constant: -8),
+
+            notesScroll.topAnchor.constraint(equalTo: pathLabel.bottomAnchor, constant: 6),
+            notesScroll.leadingAnchor.constraint(equalTo: container.leadingAnchor, constant: 8),
+            notesScroll.trailingAnchor.constraint(equalTo: container.trailingAnchor, constant: -8),
+            notesScroll.heightAnchor.constraint(equalTo: container.heightAnchor, multiplier: 0.35),
+
+            addRow.topAnchor.constraint(equalTo: notesScroll.bottomAnchor, constant: 8),
+            addRow.leadingAnchor.constraint(equalTo: container.leadingAnchor, constant: 8),
+            addRow.trailingAnchor.constraint(equalTo: container.trailingAnchor, constant: -8),
+            addField.heightAnchor.constraint(equalToConstant: 24),
+
+            todoScroll.topAnchor.constraint(equalTo: addRow.bottomAnchor, constant: 4),
+            todoScroll.leadingAnchor.constraint(equalTo: container.leadingAnchor, constant: 8),
+            todoScroll.trailingAnchor.constraint(equalTo: container.trailingAnchor, constant: -8),
+            todoScroll.bottomAnchor.constraint(equalTo: container.bottomAnchor),
+
+            document.widthAnchor.constraint(equalTo: todoScroll.contentView.widthAnchor),
+            todoStack.topAnchor.constraint(equalTo: document.topAnchor),
+            todoStack.leadingAnchor.constraint(equalTo: document.leadingAnchor),
+            todoStack.trailingAnchor.constraint(equalTo: document.trailingAnchor),
+            todoStack.bottomAnchor.constraint(equalTo: document.bottomAnchor),
+        ])
+        view = container
+
+        storeObserver = NotificationCenter.default.addObserver(
+            forName: WorkspaceNotes.didChangeNotification, object: store, queue: .main
+        ) { [weak self] note in
+            guard let self, let workspace = note.userInfo?[WorkspaceNotes.workspaceKey] as? String,
+                  workspace == self.workspace else { return }
+            self.reload(notes: false)
+        }
+        reload(notes: true)
+    }
+
+    override func viewWillDisappear() {
+        super.viewWillDisappear()
+        flushNotes()
+    }
+
+    /// Show `session`'s workspace and follow its cwd changes.
+    func track(session: TerminalSession) {
+        guard session !== self.session else { return }
+        if let cwdObserver { NotificationCenter.default.removeObserver(cwdObserver) }
+        self.session = session
+        let tracker = session.processTracker
+        cwdObserver = NotificationCenter.default.addObserver(
+            forName: ForegroundProcessTracker.cwdDidChangeNotification,
+            object: nil, queue: .main
+        ) { [weak self, weak session, weak tracker] note in
+            guard let self, let sender = note.object as AnyObject?,
+                  sender === session || sender === tracker,
+                  let path = note.userInfo?[ForegroundProcessTracker.cwdKey] as? String
+            else { return }
+            self.show(directory: path)
+        }
+        show(directory: session.currentDirectory())
+    }
+
+    private func show(directory: String?) {
+        let workspace = WorkspaceNotes.workspace(for: directory ?? NSHomeDirectory())
+        guard workspace != self.workspace else { return }
+        flushNotes()
+        self.workspace = workspace
+        scan?.cancel()
+        scanButton.isEnabled = true
+        comments = []
+        guard isViewLoaded else { return }
+        reload(notes: true)
+    }
+
+    /// Rebuilds the todo rows; `notes` also replaces the text, which is left
+    /// alone for the store's echo of the user's own typing.
+    private func reload(notes: Bool) {
+        guard let workspace else {
+            pathLabel.stringValue = ""
+            return
+        }
+        pathLabel.stringValue = (workspace as NSString).abbreviatingWithTildeInPath
+        if notes { notesView.string = store.notes(for: workspace) }
+        todoStack.arrangedSubviews.forEach { $0.removeFromSuperview() }
+        for todo in store.todos(for: workspace) {
+            let box = NSButton(checkboxWithTitle: todo.text, target: self, action: #selector(toggleTodo(_:)))
+            box.identifier = NSUserInterfaceItemIdentifier(todo.id)
+            box.state = todo.done ? .on : .off
+            box.font = .systemFont(ofSize: NSFont.systemFontSize)
+            box.menu = rowMenu(for: todo)
+            todoStack.addArrangedSubview(box)
+        }
+        for comment in comments {
+            let label = NSTextField(labelWithString: "\(comment.tag) \(comment.text)")
+            label.font = .systemFont(ofSize: NSFont.smallSystemFontSize)
+            label.textColor = .secondaryLabelColor
+            label.lineBreakMode = .byTruncatingTail
+            label.toolTip = "\(comment.path):\(comment.line)"
+            todoStack.addArrangedSubview(label)
+        }
+    }
+
+    private func rowMenu(for todo: WorkspaceTodo) -> NSMenu {
+        let menu = NSMenu()
+        let delete = menu.addItem(withTitle: "Delete", action: #selector(deleteTodo(_:)), keyEquivalent: "")
+        delete.target = self
+        delete.representedObject = todo.id
+        return menu
+    }
+
+    func textDidChange(_ notification: Notification) {
+        pendingSave?.cancel()
+        let work = DispatchWorkItem { [weak self] in self?.flushNotes() }
+        pendingSave = work
+        DispatchQueue.main.asyncAfter(deadline: .now() + 0.5, execute: work)
+    }
+
+    private func flushNotes() {
+        pendingSave?.cancel()
+        pendingSave = nil
+        guard let workspace, isViewLoaded, notesView.string != store.notes(for: workspace) else { return }
+        store.setNotes(notesView.string, for: workspace)
+    }
+
+    @objc private func addTodo(_ sender: Any?) {
+        guard let workspace, case .success = store.addTodo(addField.stringValue, to: workspace) else { return }
+        addField.stringValue = ""
+    }
+
+    @objc private func toggleTodo(_ sender: NSButton) {
+        guard let workspace, let id = sender.identifier?.rawValue else { return }
+        _ = store.toggleTodo(id, in: workspace, done: sender.state == .on)
+    }
+
+    @objc private func deleteTodo(_ sender: NSMenuItem) {
+        guard let workspace, let id = sender.representedObject as? String else { return }
+        _ = store.removeTodo(id, in: workspace)
+    }
+
+    @objc private func scanComments(_ sender: Any?) {
+        guard let workspace else { return }
+        scan?.cancel()
+        let token = CancellationToken()
+        scan = token
+        scanButton.isEnabled = false
+        DispatchQueue.global(qos: .userInitiated).async { [weak self] in
+            let found = WorkspaceNotes.scanComments(in: workspace, cancellation: token)
+            DispatchQueue.main.async {
+                guard let self, !token.isCancelled else { return }
+                self.scanButton.isEnabled = true
+                self.comments = found
+                self.reload(notes: false)
+            }
+        }
+    }
+}
+
+private final class FlippedView: NSView {
+    override var isFlipped: Bool { true }
+}
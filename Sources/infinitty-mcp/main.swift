@@ -136,21 +136,25 @@ final class EventBuffer {
 
     /// Matching events with seq > since; blocks until `deadline` for the
     /// first match when none are pending.
-    func collect(since: Int, event: String?, pane: Int?, deadline: Date) -> [Entry] {
+    func collect(since: Int, event: String?, pane: Int?, tab: String?, deadline: Date) -> [Entry] {
         condition.lock()
         defer { condition.unlock() }
         while true {
             let matches = entries.filter {
-                $0.seq > since && Self.matches($0.object, event: event, pane: pane)
+                $0.seq > since && Self.matches($0.object, event: event, pane: pane, tab: tab)
             }
             if !matches.isEmpty || Date() >= deadline { return matches }
             condition.wait(until: min(deadline, Date().addingTimeInterval(1)))
         }
     }
 
-    private static func matches(_ object: [String: Any], event: String?, pane: Int?) -> Bool {
+    private static func matches(
+        _ object: [String: Any], event: String?, pane: Int?, tab: String?
+    ) -> Bool {
         if let event, !((object["event"] as? String) ?? "").contains(event) { return false }
         if let pane, (object["pane"] as? Int) != pane { return false }
+        // Untargeted events are app-wide and pass any tab filter.
+        if let tab, let eventTab = object["tab"] as? String, eventTab != tab { return false }
         return true
     }
 }
@@ -213,6 +217,11 @@ private func pumpEventsOnce() {
 /// app-control protocol's framing.
 private let maximumBrowserRequestBytes = 48_000
 
+/// The terminal pane whose shell started this server, if any. Browser calls
+/// default their anchorPane to it, so they keep acting for the agent's own
+/// tab when infinitty isn't the frontmost app.
+let ownPane = ProcessInfo.processInfo.environment["INFINITTY_PANE"].flatMap { Int($0) }
+
 func browserCall(
     _ operation: String,
     arguments: [String: Any] = [:],
@@ -221,6 +230,19 @@ func browserCall(
     var payload = arguments
     payload["v"] = 1
     payload["op"] = operation
+    guard payload["anchorPane"] == nil, let ownPane else {
+        return browserRequest(payload, timeout: timeout)
+    }
+    payload["anchorPane"] = ownPane
+    let answer = browserRequest(payload, timeout: timeout)
+    // A shell the daemon kept through a restart carries the last run's
+    // pane id; the app's own default owner applies then.
+    guard answer.contains("\"unknown_pane\"") else { return answer }
+    payload["anchorPane"] = nil
+    return browserRequest(payload, timeout: timeout)
+}
+
+private func browserRequest(_ payload: [String: Any], timeout: Int32) -> String {
     guard JSONSerialization.isValidJSONObject(payload),
           let data = try? JSONSerialization.data(withJSONObject: payload) else {
         return "error: could not encode browser request"
@@ -259,6 +281,11 @@ let browserIDProperty: [String: Any] = [
         "type": "string",
         "description": "Browser id returned by infinitty_browser_open or infinitty_browser_list",
     ],
+    "anchorPane": [
+        "type": "integer",
+        "description": "Terminal pane id; the call is refused unless the browser lives in that "
+            + "pane's tab (default: the pane this server runs in, else the frontmost window's tab)",
+    ],
 ]
 
 let browserSnapshotProperty = browserIDProperty.merging([
@@ -268,6 +295,28 @@ let browserSnapshotProperty = browserIDProperty.merging([
     ],
 ] as [String: Any]) { a, _ in a }
 
+let fileTransferSchema: [String: Any] = [
+    "type": "object",
+    "properties": [
+        "from": ["type": "string", "description": "Absolute source path"],
+        "to": ["type": "string", "description": "Absolute destination path"],
+        "conflict": [
+            "type": "string", "enum": ["skip", "overwrite", "rename"],
+            "description": "What to do with entries that already exist (default skip)",
+        ] as [String: Any],
+    ],
+    "required": ["from", "to"],
+]
+
+/// fs-* commands take a JSON argument so paths can hold spaces.
+func fileOperation(_ command: String, _ args: [String: Any], keys: [String]) -> String {
+    let payload = args.filter { keys.contains($0.key) }
+    guard let data = try? JSONSerialization.data(withJSONObject: payload) else {
+        return "error: could not encode \(command) request"
+    }
+    return infinittyRequest("\(command) \(String(decoding: data, as: UTF8.self))")
+}
+
 let tools: [Tool] = [
     Tool(
         name: "infinitty_list_panes",
@@ -299,13 +348,13 @@ let tools: [Tool] = [
     ),
     Tool(
         name: "infinitty_sidebar_tab",
-        description: "Open or focus a Files/Changes pane or the independent Chat pane.",
+        description: "Open or focus a Files/Changes pane or the independent Chat or Notes pane.",
         schema: [
             "type": "object",
             "properties": [
                 "tab": [
                     "type": "string",
-                    "enum": ["files", "changes", "chat"],
+                    "enum": ["files", "changes", "chat", "notes"],
                     "description": "Which panel content to show",
                 ] as [String: Any],
             ],
@@ -382,6 +431,29 @@ let tools: [Tool] = [
             infinittyRequest("history \(paneArg(args)) \(args["lines"] as? Int ?? 100)")
         }
     ),
+    Tool(
+        name: "infinitty_search_scrollback",
+        description: "Search a pane's whole scrollback and screen for text (case-insensitive unless "
+            + "caseSensitive) or a regex. Returns {matches: [{line, column, length, text}], truncated, "
+            + "firstLine, lastLine}; lines are absolute, columns are cells.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "query": ["type": "string"],
+                "regex": ["type": "boolean", "description": "Treat query as an ICU regular expression"],
+                "caseSensitive": ["type": "boolean"],
+                "limit": ["type": "integer", "description": "Stop after this many matches (default 1000)"],
+            ]) { a, _ in a },
+            "required": ["pane", "query"],
+        ],
+        invoke: { args in
+            let request = args.filter { ["query", "regex", "caseSensitive", "limit"].contains($0.key) }
+            guard let data = try? JSONSerialization.data(withJSONObject: request) else {
+                return "error: could not encode search request"
+            }
+            return infinittyRequest("search \(paneArg(args)) \(String(decoding: data, as: UTF8.self))")
+        }
+    ),
     Tool(
         name: "infinitty_send",
         description: "Type text into a pane. Set submit=false to type without pressing return "
@@ -399,6 +471,135 @@ let tools: [Tool] = [
             return infinittyRequest("\(cmd) \(paneArg(args)) \(args["text"] as? String ?? "")")
         }
     ),
+    Tool(
+        name: "infinitty_paste",
+        description: "Paste text into a pane the way the clipboard would: control characters stripped, "
+            + "bracketed paste when the program asked for it. A paste with line breaks into a program "
+            + "without bracketed paste, using sudo, curl | sh or rm -rf, or hiding invisible characters "
+            + "(direction overrides, zero-width spaces) comes back unsent with "
+            + "needsConfirmation and its concerns; check with the user, then repeat with confirm=true.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "text": ["type": "string"],
+                "confirm": ["type": "boolean", "description": "Paste even with concerns"],
+            ]) { a, _ in a },
+            "required": ["pane", "text"],
+        ],
+        invoke: { args in
+            let request = args.filter { ["text", "confirm"].contains($0.key) }
+            guard let data = try? JSONSerialization.data(withJSONObject: request) else {
+                return "error: could not encode paste request"
+            }
+            return infinittyRequest("paste \(paneArg(args)) \(String(decoding: data, as: UTF8.self))")
+        }
+    ),
+    Tool(
+        name: "infinitty_clipboard_write",
+        description: "Put text on the clipboard, or (with pane and no text) copy that pane's selection. "
+            + "It is cleaned like any copy from a pane: transforms trim (trailing line breaks), strip-ansi "
+            + "(escape sequences) and strip-prompts ($ , % , ❯ at line starts); default the user's setting.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "text": ["type": "string"],
+                "pane": ["type": "integer", "description": "Copy this pane's selection instead of text"],
+                "transforms": [
+                    "type": "array",
+                    "items": ["type": "string", "enum": ["trim", "strip-ansi", "strip-prompts", "none"]],
+                ],
+            ],
+        ],
+        invoke: { args in
+            if args["text"] == nil, args["pane"] != nil { return infinittyRequest("clipboard-copy \(paneArg(args))") }
+            let request = args.filter { ["text", "transforms"].contains($0.key) }
+            guard let data = try? JSONSerialization.data(withJSONObject: request) else {
+                return "error: could not encode clipboard request"
+            }
+            return infinittyRequest("clipboard-write \(String(decoding: data, as: UTF8.self))")
+        }
+    ),
+    Tool(
+        name: "infinitty_pane_display",
+        description: "Read or set one pane's text size (6-72 points) and line spacing (0.7-3) without touching "
+            + "other panes. Pass null to return one to the user's config. Returns {pane, fontSize, lineSpacing, "
+            + "overrides}.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "fontSize": ["type": ["number", "null"]],
+                "lineSpacing": ["type": ["number", "null"]],
+            ]) { a, _ in a },
+            "required": ["pane"],
+        ],
+        invoke: { args in
+            let settings = args.filter { ["fontSize", "lineSpacing"].contains($0.key) }
+            guard !settings.isEmpty else { return infinittyRequest("pane-display \(paneArg(args))") }
+            guard let data = try? JSONSerialization.data(withJSONObject: settings) else {
+                return "error: could not encode pane-display request"
+            }
+            return infinittyRequest("pane-display \(paneArg(args)) \(String(decoding: data, as: UTF8.self))")
+        }
+    ),
+    Tool(
+        name: "infinitty_lock_input",
+        description: "Make a pane read-only (e.g. one tailing production logs) so nothing typed, pasted or "
+            + "sent reaches it, or writable again. Without locked, toggles. Returns {pane, locked}.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "locked": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            let state = (args["locked"] as? Bool).map { $0 ? " on" : " off" } ?? ""
+            return infinittyRequest("pane-lock \(paneArg(args))\(state)")
+        }
+    ),
+    Tool(
+        name: "infinitty_process_tree",
+        description: "List a pane's shell and every process under it: pid, ppid, pgid, name, path, depth, "
+            + "and whether it is in the terminal's foreground job.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("pane-processes \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_signal",
+        description: "Send a signal to a process in a pane, for a program that ignores Ctrl-C. Without pid "
+            + "it goes to the foreground job's process group; a pid must be one from infinitty_process_tree. "
+            + "Returns {pane, signal, pids}.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "signal": ["type": "string", "enum": ["int", "term", "kill", "hup", "quit", "stop", "cont"]],
+                "pid": ["type": "integer"],
+            ]) { a, _ in a },
+            "required": ["pane", "signal"],
+        ],
+        invoke: { args in
+            let pid = (args["pid"] as? Int).map { " \($0)" } ?? ""
+            return infinittyRequest("pane-signal \(paneArg(args)) \(args["signal"] as? String ?? "")\(pid)")
+        }
+    ),
+    Tool(
+        name: "infinitty_idle_panes",
+        description: "See how long each pane has gone without input or output against the idle-timeout "
+            + "policy (action list, the default), or hibernate a pane (end its shell, keep its scrollback) "
+            + "or resume one with a new shell. Only panes whose shell infinitty spawned itself can hibernate.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "action": ["type": "string", "enum": ["list", "hibernate", "resume"]],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            switch args["action"] as? String ?? "list" {
+            case "hibernate": return infinittyRequest("pane-hibernate \(paneArg(args))")
+            case "resume": return infinittyRequest("pane-resume \(paneArg(args))")
+            default: return infinittyRequest("idle-panes")
+            }
+        }
+    ),
     Tool(
         name: "infinitty_surface",
         description: "Open a display surface in infinitty: rendered markdown, raw HTML "
@@ -530,192 +731,2206 @@ let tools: [Tool] = [
         invoke: { args in infinittyRequest("exit-code \(paneArg(args))") }
     ),
     Tool(
-        name: "infinitty_new_tab",
-        description: "Open a new infinitty tab. Returns the new pane id.",
+        name: "infinitty_cwd",
+        description: "Working directory of a specific pane (the running tool's cwd, or the "
+            + "shell's as reported via OSC 7). Each pane has its own.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("cwd \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_copy_mode",
+        description: "Keyboard-style selection over a pane's scrollback (like tmux copy-mode). action: "
+            + "enter, exit, move (motion, count), select (mode: character, word, line, block, none), "
+            + "jump (line, column), search / search-back (text), copy. Returns {line, column, firstLine, "
+            + "lastLine, viewOffset, selection?, text?}; copy returns {text} and leaves copy mode.",
         schema: [
             "type": "object",
-            "properties": [
-                "cwd": ["type": "string", "description": "Shell starting directory (absolute path)"],
-            ],
+            "properties": paneProperty.merging([
+                "action": [
+                    "type": "string",
+                    "enum": ["enter", "exit", "move", "select", "jump", "search", "search-back", "copy"],
+                ],
+                "motion": [
+                    "type": "string",
+                    "enum": ["left", "right", "up", "down", "word-next", "word-prev", "line-start", "line-end",
+                             "page-up", "page-down", "top", "bottom"],
+                ],
+                "count": ["type": "integer"],
+                "mode": ["type": "string", "enum": ["character", "word", "line", "block", "none"]],
+                "line": ["type": "integer", "description": "Absolute line, as search results number them"],
+                "column": ["type": "integer"],
+                "text": ["type": "string"],
+            ]) { a, _ in a },
+            "required": ["pane", "action"],
         ],
         invoke: { args in
-            let cwd = args["cwd"] as? String ?? ""
-            return infinittyRequest(cwd.isEmpty ? "new-tab" : "new-tab \(cwd)")
+            let action = args["action"] as? String ?? "enter"
+            var words = ["copy-mode", paneArg(args), action]
+            switch action {
+            case "move": words += [args["motion"] as? String ?? "", String(args["count"] as? Int ?? 1)]
+            case "select": words.append(args["mode"] as? String ?? "character")
+            case "jump": words += [String(args["line"] as? Int ?? 0), String(args["column"] as? Int ?? 0)]
+            case "search", "search-back": words.append(args["text"] as? String ?? "")
+            default: break
+            }
+            return infinittyRequest(words.joined(separator: " "))
         }
     ),
     Tool(
-        name: "infinitty_new_window",
-        description: "Open a new infinitty window. Returns the new pane id.",
+        name: "infinitty_scrollback_extract",
+        description: "Pull a rectangle or a column out of a pane's scrollback, e.g. the IDs in tabular "
+            + "output. Reads the pane's selection, or absolute lines from...to. mode block takes cells "
+            + "columns [lo, hi] (default the block selection's); mode column takes field n of each line, "
+            + "split on whitespace or separator (1-based, negative counts from the end). "
+            + "Returns {firstLine, lastLine, values, text}.",
         schema: [
             "type": "object",
-            "properties": [
-                "cwd": ["type": "string", "description": "Shell starting directory (absolute path)"],
-            ],
+            "properties": paneProperty.merging([
+                "mode": ["type": "string", "enum": ["block", "column"]],
+                "from": ["type": "integer", "description": "First absolute line, as search results number them"],
+                "to": ["type": "integer"],
+                "columns": ["type": "array", "items": ["type": "integer"], "description": "[lo, hi] cell columns"],
+                "column": ["type": "integer", "description": "Field number for mode column"],
+                "separator": ["type": "string"],
+            ]) { a, _ in a },
+            "required": ["pane"],
         ],
         invoke: { args in
-            let cwd = args["cwd"] as? String ?? ""
-            return infinittyRequest(cwd.isEmpty ? "new-window" : "new-window \(cwd)")
+            fileOperation("scrollback-extract \(paneArg(args))", args, keys: [
+                "mode", "from", "to", "columns", "column", "separator",
+            ])
         }
     ),
     Tool(
-        name: "infinitty_split",
-        description: "Split a pane. Returns the new pane id.",
+        name: "infinitty_links",
+        description: "URLs, file paths (with :line:column split into path, fileLine, fileColumn) and git "
+            + "hashes in a pane's last n rows, with absolute line and cell columns. Set detect to true or "
+            + "false to turn detected-links events for new output on or off as well.",
         schema: [
             "type": "object",
             "properties": paneProperty.merging([
-                "direction": [
-                    "type": "string", "enum": ["right", "left", "down", "up"],
-                ],
+                "lines": ["type": "integer", "description": "Rows to scan (default one screen)"],
+                "detect": ["type": "boolean"],
             ]) { a, _ in a },
-            "required": ["pane", "direction"],
+            "required": ["pane"],
         ],
         invoke: { args in
-            infinittyRequest("split \(paneArg(args)) \(args["direction"] as? String ?? "right")")
+            if let detect = args["detect"] as? Bool {
+                let reply = infinittyRequest("detect-links \(detect ? "on" : "off")")
+                guard !reply.hasPrefix("error") else { return reply }
+            }
+            let lines = (args["lines"] as? Int).map { " \($0)" } ?? ""
+            return infinittyRequest("links \(paneArg(args))\(lines)")
         }
     ),
     Tool(
-        name: "infinitty_focus",
-        description: "Raise and focus a pane.",
-        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
-        invoke: { args in infinittyRequest("focus \(paneArg(args))") }
+        name: "infinitty_analyze_command",
+        description: "Check a command line before running it: whether it deletes or chmods recursively, "
+            + "force-pushes, drops tables or writes to a disk, and if so every path operand with its globs "
+            + "expanded from the pane's (default the focused pane's) directory or dir and the files and "
+            + "folders it covers. Runs nothing. The same check holds Enter in a pane when "
+            + "confirm-dangerous-commands is on.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "command": ["type": "string"],
+                "dir": ["type": "string", "description": "Directory to expand paths from, instead of a pane's"],
+            ]) { a, _ in a },
+            "required": ["command"],
+        ],
+        invoke: { args in fileOperation("analyze-command", args, keys: ["command", "pane", "dir"]) }
     ),
     Tool(
-        name: "infinitty_close",
-        description: "Close a pane (terminates its shell).",
-        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
-        invoke: { args in infinittyRequest("close \(paneArg(args))") }
+        name: "infinitty_expand_globs",
+        description: "The files a shell pattern would match, without running anything: brace expansion "
+            + "({a,b}, {1..5}, {a..e}) as bash does it, then ~ and * ? [...] globs, from a pane's (default the "
+            + "focused pane's) directory or dir. Returns {alternatives, matches (absolute), count, truncated}. "
+            + "A backslash escapes, as in the shell.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "pattern": ["type": "string"],
+                "dir": ["type": "string", "description": "Directory to expand from, instead of a pane's"],
+                "limit": ["type": "integer", "description": "Most paths to return (default 10000)"],
+            ]) { a, _ in a },
+            "required": ["pattern"],
+        ],
+        invoke: { args in fileOperation("expand-globs", args, keys: ["pattern", "pane", "dir", "limit"]) }
     ),
     Tool(
-        name: "infinitty_events",
-        description: "Read live infinitty events so agents can react to terminal state changes. "
-            + "Event types: pane-opened, pane-closed, title, marker, process, and browser-*. "
-            + "marker events are OSC 133 shell round trips (kind C = command started, "
-            + "kind D = command finished with its exit code). process events fire when a pane's "
-            + "foreground process changes — e.g. an agent CLI like claude or codex starts, or the "
-            + "pane returns to the shell prompt (empty name). Returns {latestSeq, events}; pass "
-            + "sinceSeq from the previous response to read only newer events. Set waitSeconds to "
-            + "long-poll: the call blocks until a matching event arrives or the wait expires.",
+        name: "infinitty_hyperlinks",
+        description: "Links programs printed with OSC 8 escape sequences in a pane's last n rows, whose "
+            + "visible text may not be the URL: {line, start, end, url, id?} per run of cells. Set scheme "
+            + "and action to change what cmd-clicking links of that scheme does until quit (open, ask, "
+            + "deny, or event: open nothing and publish link-activated for you to handle; null restores "
+            + "the config's link-schemes), or pass url to ask what clicking it would do.",
         schema: [
             "type": "object",
-            "properties": [
-                "sinceSeq": [
-                    "type": "integer",
-                    "description": "Only events after this sequence number (from the previous "
-                        + "response's latestSeq). Omit to get the most recent events.",
-                ] as [String: Any],
-                "waitSeconds": [
-                    "type": "integer", "minimum": 0, "maximum": 120,
-                    "description": "Block up to this many seconds for the first matching event "
-                        + "(default 0 = return immediately)",
-                ] as [String: Any],
-                "event": [
-                    "type": "string",
-                    "description": "Only events whose type contains this substring, "
-                        + "e.g. \"marker\" or \"process\"",
-                ] as [String: Any],
-                "pane": [
-                    "type": "integer",
-                    "description": "Only events for this pane id",
-                ] as [String: Any],
-            ],
+            "properties": paneProperty.merging([
+                "lines": ["type": "integer", "description": "Rows to scan (default one screen)"],
+                "scheme": ["type": "string"],
+                "action": ["type": ["string", "null"], "description": "open, ask, deny or event"],
+                "url": ["type": "string"],
+            ]) { a, _ in a },
         ],
         invoke: { args in
-            let since = args["sinceSeq"] as? Int ?? max(eventBuffer.latestSeq - 20, 0)
-            let wait = min(max(args["waitSeconds"] as? Int ?? 0, 0), 120)
-            let entries = eventBuffer.collect(
-                since: since,
-                event: args["event"] as? String,
-                pane: args["pane"] as? Int,
-                deadline: Date().addingTimeInterval(TimeInterval(wait)))
-            let events = entries.map { entry -> [String: Any] in
-                var object = entry.object
-                object["seq"] = entry.seq
-                return object
+            if args["scheme"] != nil || args["url"] != nil {
+                var request: [String: Any] = [:]
+                for key in ["scheme", "action", "url"] { if let value = args[key] { request[key] = value } }
+                guard let data = try? JSONSerialization.data(withJSONObject: request) else {
+                    return "error: could not encode link-policy request"
+                }
+                return infinittyRequest("link-policy \(String(decoding: data, as: UTF8.self))")
             }
-            let payload: [String: Any] = ["latestSeq": eventBuffer.latestSeq, "events": events]
-            guard let data = try? JSONSerialization.data(withJSONObject: payload) else {
-                return "error: could not encode events"
-            }
-            return String(decoding: data, as: UTF8.self)
+            let lines = (args["lines"] as? Int).map { " \($0)" } ?? ""
+            return infinittyRequest("hyperlinks \(paneArg(args))\(lines)")
         }
     ),
     Tool(
-        name: "infinitty_activity",
-        description: "Show a short status message in infinitty's notch live-activity widget.",
+        name: "infinitty_smart_select",
+        description: "Select the token at a cell of a pane's scrollback as a double-click would: a URL, "
+            + "path, UUID, IP address, git hash, JSON object (from one of its braces) or word. Returns "
+            + "{kind, line, start, end, text}.",
         schema: [
             "type": "object",
-            "properties": ["text": ["type": "string"]],
-            "required": ["text"],
+            "properties": paneProperty.merging([
+                "line": ["type": "integer", "description": "Absolute line, as search results number them"],
+                "column": ["type": "integer"],
+            ]) { a, _ in a },
+            "required": ["pane", "line", "column"],
         ],
-        invoke: { args in infinittyRequest("activity \(args["text"] as? String ?? "")") }
+        invoke: { args in
+            infinittyRequest(
+                "smart-select \(paneArg(args)) \(args["line"] as? Int ?? 0) \(args["column"] as? Int ?? 0)")
+        }
     ),
     Tool(
-        name: "infinitty_browser_open",
-        description: "Open a native browser pane, or focus an existing one. By default this "
-            + "focuses the most recently opened browser pane in the target tab (creating one "
-            + "only if none exists); pass newPane=true to add another instance, or browserId "
-            + "to target a specific instance. Optionally navigate it to a URL. "
-            + "Use its browserId with the other infinitty_browser_* tools.",
+        name: "infinitty_fold_command",
+        description: "Fold a finished command's output in a pane to one summary row of scrollback, or "
+            + "unfold it. Commands are named by the absolute line their output began on, as command "
+            + "events give it; without a line the latest command is used. With list true, returns the "
+            + "foldable commands instead: [{line, lastLine, lines, exitCode, folded, summary, command?}].",
         schema: [
             "type": "object",
-            "properties": [
-                "url": ["type": "string", "description": "Optional URL to load"],
-                "anchorPane": [
-                    "type": "integer",
-                    "description": "Terminal pane whose tab should host the browser (default: key tab)",
-                ],
-                "browserId": [
-                    "type": "string",
-                    "description": "Focus this existing browser instance instead of the most recent one",
-                ],
-                "newPane": [
-                    "type": "boolean",
-                    "description": "Create an additional browser pane even if one already exists",
-                ],
-            ],
+            "properties": paneProperty.merging([
+                "line": ["type": "integer", "description": "Absolute line the command's output began on"],
+                "folded": ["type": "boolean", "description": "Omit to toggle"],
+                "list": ["type": "boolean"],
+            ]) { a, _ in a },
+            "required": ["pane"],
         ],
-        invoke: { args in browserCall("open", arguments: args) }
-    ),
-    Tool(
-        name: "infinitty_browser_list",
-        description: "List all browser pane instances across every window, with each instance's "
-            + "browserId, current URL, title, loading state, and viewport mode.",
-        schema: ["type": "object", "properties": [:]],
-        invoke: { _ in browserCall("list") }
+        invoke: { args in
+            if args["list"] as? Bool == true { return infinittyRequest("fold \(paneArg(args))") }
+            let line = (args["line"] as? Int).map(String.init) ?? "last"
+            let state = (args["folded"] as? Bool).map { $0 ? "on" : "off" } ?? "toggle"
+            return infinittyRequest("fold \(paneArg(args)) \(line) \(state)")
+        }
     ),
     Tool(
-        name: "infinitty_browser_navigate",
-        description: "Navigate a browser panel to a URL and wait for the navigation result.",
+        name: "infinitty_diff_command_outputs",
+        description: "Diff the output of two finished commands, e.g. two runs of kubectl get pods. Commands "
+            + "are named by pane and the absolute line their output began on (as infinitty_fold_command "
+            + "lists them). Without lines, compares a pane's latest command with the previous run of the "
+            + "same command. Returns {a, b, identical, added, removed, lines: [{kind, old?, new?, text}]} "
+            + "as unified hunks, kind being hunk, context, add or del.",
         schema: [
             "type": "object",
-            "properties": browserIDProperty.merging([
-                "url": ["type": "string", "description": "Absolute URL, or a host/search-like URL"],
+            "properties": paneProperty.merging([
+                "line_a": ["type": "integer", "description": "Output line of the earlier command"],
+                "line_b": ["type": "integer", "description": "Output line of the later command"],
+                "pane_b": ["type": "integer", "description": "Pane of the later command (default: pane)"],
+                "context": ["type": "integer", "description": "Unchanged lines around each change (default 3)"],
             ]) { a, _ in a },
-            "required": ["browserId", "url"],
+            "required": ["pane"],
         ],
-        invoke: { args in browserCall("navigate", arguments: args) }
+        invoke: { args in
+            guard let pane = args["pane"] as? Int else { return "error: pane is required" }
+            guard args["line_a"] != nil || args["line_b"] != nil || args["pane_b"] != nil || args["context"] != nil
+            else { return infinittyRequest("output-diff \(pane)") }
+            var a: [String: Any] = ["pane": pane]
+            var b: [String: Any] = ["pane": args["pane_b"] as? Int ?? pane]
+            a["line"] = args["line_a"] as? Int
+            b["line"] = args["line_b"] as? Int
+            let request: [String: Any] = ["a": a, "b": b, "context": args["context"] as? Int ?? 3]
+            guard let data = try? JSONSerialization.data(withJSONObject: request) else {
+                return "error: could not encode output-diff request"
+            }
+            return infinittyRequest("output-diff \(String(decoding: data, as: UTF8.self))")
+        }
     ),
     Tool(
-        name: "infinitty_browser_snapshot",
-        description: "Return a compact DOM-first snapshot of visible interactive elements. "
-            + "Use the returned ref values for click, type, or press; take a new snapshot after navigation.",
+        name: "infinitty_scrollback_load",
+        description: "Read lines of a pane's history as text by absolute line number, including lines "
+            + "past the in-memory scrollback limit when scrollback-spill keeps them on disk. Returns "
+            + "{firstLine, lines, spilling}; firstLine is later than asked when older lines are gone.",
         schema: [
             "type": "object",
-            "properties": browserIDProperty.merging([
-                "maxNodes": [
-                    "type": "integer",
-                    "minimum": 1,
-                    "maximum": 250,
-                    "description": "Maximum interactive elements to return (default 80)",
-                ],
+            "properties": paneProperty.merging([
+                "line": ["type": "integer", "description": "First absolute line to read"],
+                "count": ["type": "integer", "description": "Lines to read (default 1000, max 10000)"],
             ]) { a, _ in a },
-            "required": ["browserId"],
+            "required": ["pane", "line"],
         ],
-        invoke: { args in browserCall("snapshot", arguments: args) }
+        invoke: { args in
+            let count = (args["count"] as? Int).map { " \($0)" } ?? ""
+            return infinittyRequest("scrollback-load \(paneArg(args)) \(args["line"] as? Int ?? 0)\(count)")
+        }
     ),
     Tool(
-        name: "infinitty_browser_click",
-        description: "Click an interactive element identified by a fresh browser snapshot ref.",
+        name: "infinitty_scrollback_usage",
+        description: "How much memory each pane's scrollback holds against the scrollback-budget setting: "
+            + "rows and bytes in memory, the rows memory may hold, whether the budget capped the pane, and "
+            + "how many older rows are only on disk (still found by search and scrollback_load).",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("scrollback-usage") }
+    ),
+    Tool(
+        name: "infinitty_rerun_command",
+        description: "Run a pane's earlier command again, found by the absolute line its output began on "
+            + "(as command events give it), or its latest command, or with failed true its latest "
+            + "failing one. With edit true the command is only typed at the prompt. Returns {command, "
+            + "exitCode, line?, source, edit}; the run's result arrives as command events.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "line": ["type": "integer", "description": "Absolute line the command's output began on"],
+                "failed": ["type": "boolean"],
+                "edit": ["type": "boolean"],
+            ]) { a, _ in a },
+            "required": ["pane"],
+        ],
+        invoke: { args in
+            let target = (args["line"] as? Int).map(String.init)
+                ?? (args["failed"] as? Bool == true ? "last-failed" : "last")
+            let edit = args["edit"] as? Bool == true ? " edit" : ""
+            return infinittyRequest("rerun \(paneArg(args)) \(target)\(edit)")
+        }
+    ),
+    Tool(
+        name: "infinitty_pane_title",
+        description: "A pane's title as its header shows it, and where that came from: source override, "
+            + "agent, reported (set by the program with OSC 0/2), directory (the cwd's name) or default. "
+            + "Also the OSC icon name, foreground process and cwd when known.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("pane-title \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_pane_info",
+        description: "A pane's working directory and where it came from (process: a foreground tool's cwd, "
+            + "osc7: the shell's report, probe: the shell's cwd, launch), its shell pid, whether a command "
+            + "is running, and the foreground program (pid, name, displayName, path) when it isn't the shell.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("pane-info \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_progress",
+        description: "How far a pane's running command has got: percent (0-100), state (normal, error, paused, "
+            + "indeterminate, or none when nothing shows progress) and source (osc: the program's OSC 9;4 "
+            + "report; output: a percentage or progress bar in its output, e.g. cargo, pip, docker, curl). "
+            + "Without a pane, every pane that shows progress.",
+        schema: ["type": "object", "properties": paneProperty],
+        invoke: { args in infinittyRequest(args["pane"] == nil ? "progress" : "progress \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_secure_input",
+        description: "Password prompts (sudo, ssh, gpg, read -s): status reports whether a pane is asking "
+            + "for one and which Keychain entries would fill it (without a pane, every pane that is); fill "
+            + "types an entry and Return at the prompt, and only at one. The secret never passes through "
+            + "this tool.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "action": ["type": "string", "enum": ["status", "fill"]],
+                "account": ["type": "string", "description": "Keychain entry to fill (default: first offered)"],
+            ]) { a, _ in a },
+            "required": ["action"],
+        ],
+        invoke: { args in
+            guard args["action"] as? String == "fill" else {
+                return infinittyRequest(args["pane"] == nil ? "secure-input" : "secure-input \(paneArg(args))")
+            }
+            let account = (args["account"] as? String).map { " \($0)" } ?? ""
+            return infinittyRequest("secure-fill \(paneArg(args))\(account)")
+        }
+    ),
+    Tool(
+        name: "infinitty_new_tab",
+        description: "Open a new infinitty tab. Returns the new pane id.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "cwd": ["type": "string", "description": "Shell starting directory (absolute path)"],
+                "profile": ["type": "string", "description": "Saved profile to start (infinitty_profiles)"],
+            ],
+        ],
+        invoke: { args in
+            if args["profile"] != nil { return fileOperation("new-tab", args, keys: ["cwd", "profile"]) }
+            let cwd = args["cwd"] as? String ?? ""
+            return infinittyRequest(cwd.isEmpty ? "new-tab" : "new-tab \(cwd)")
+        }
+    ),
+    Tool(
+        name: "infinitty_new_window",
+        description: "Open a new infinitty window. Returns the new pane id.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "cwd": ["type": "string", "description": "Shell starting directory (absolute path)"],
+                "profile": ["type": "string", "description": "Saved profile to start (infinitty_profiles)"],
+            ],
+        ],
+        invoke: { args in
+            if args["profile"] != nil { return fileOperation("new-window", args, keys: ["cwd", "profile"]) }
+            let cwd = args["cwd"] as? String ?? ""
+            return infinittyRequest(cwd.isEmpty ? "new-window" : "new-window \(cwd)")
+        }
+    ),
+    Tool(
+        name: "infinitty_split",
+        description: "Split a pane. Returns the new pane id.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "direction": [
+                    "type": "string", "enum": ["right", "left", "down", "up"],
+                ],
+            ]) { a, _ in a },
+            "required": ["pane", "direction"],
+        ],
+        invoke: { args in
+            infinittyRequest("split \(paneArg(args)) \(args["direction"] as? String ?? "right")")
+        }
+    ),
+    Tool(
+        name: "infinitty_duplicate_pane",
+        description: "Open another shell exactly where a pane is: a split beside it in the same live "
+            + "directory, with the same profile and environment. replay=true runs the pane's last command "
+            + "again in the copy. Returns the new pane id.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "direction": ["type": "string", "enum": ["right", "down"]],
+                "replay": ["type": "boolean"],
+            ]) { a, _ in a },
+            "required": ["pane"],
+        ],
+        invoke: { args in
+            let replay = args["replay"] as? Bool == true ? " replay" : ""
+            return infinittyRequest(
+                "pane-duplicate \(paneArg(args)) \(args["direction"] as? String ?? "right")\(replay)")
+        }
+    ),
+    Tool(
+        name: "infinitty_reveal",
+        description: "Show a file or folder a pane printed: selected in the Files pane's tree (in=files, "
+            + "the default) or in Finder (in=finder). Relative paths are read from the pane's directory and a "
+            + "trailing :line:column is ignored.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "path": ["type": "string"],
+                "in": ["type": "string", "enum": ["files", "finder"]],
+            ]) { a, _ in a },
+            "required": ["pane", "path"],
+        ],
+        invoke: { args in
+            let command = args["in"] as? String == "finder" ? "reveal-in-finder" : "reveal-in-explorer"
+            return infinittyRequest("\(command) \(paneArg(args)) \(args["path"] as? String ?? "")")
+        }
+    ),
+    Tool(
+        name: "infinitty_focus",
+        description: "Raise and focus a pane.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("focus \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_toggle_zoom",
+        description: "Zoom a pane to fill its tab (siblings, browser panes included, are hidden until "
+            + "restored) or restore the tab. Toggles unless zoomed is given. Emits one layout-changed event.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "pane": ["type": "integer", "description": "Terminal pane id"],
+                "browserId": ["type": "string", "description": "Browser pane id, instead of pane"],
+                "zoomed": ["type": "boolean"],
+            ],
+        ],
+        invoke: { args in
+            guard let target = (args["pane"] as? Int).map(String.init) ?? args["browserId"] as? String else {
+                return "error: pane or browserId is required"
+            }
+            let mode = (args["zoomed"] as? Bool).map { $0 ? " on" : " off" } ?? ""
+            return infinittyRequest("zoom \(target)\(mode)")
+        }
+    ),
+    Tool(
+        name: "infinitty_arrange_pane",
+        description: "Rearrange panes within a tab. swap exchanges pane (or browserId) with other, default the "
+            + "next pane in reading order; new-tab moves a terminal pane into a tab of its own; equalize evens "
+            + "out every split in pane's tab (default the focused tab); resize gives the pane percent (10-90) of "
+            + "its split, its siblings sharing the rest. Each emits a layout-changed event.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["swap", "new-tab", "equalize", "resize"]],
+                "pane": ["type": "integer", "description": "Terminal pane id"],
+                "browserId": ["type": "string", "description": "Browser pane id, instead of pane"],
+                "other": ["type": "string", "description": "Pane id or browser id to swap with"],
+                "percent": ["type": "integer"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let target = (args["pane"] as? Int).map(String.init) ?? args["browserId"] as? String
+            switch args["action"] as? String ?? "" {
+            case "equalize":
+                return infinittyRequest("pane-equalize \(target ?? "")")
+            case "new-tab":
+                guard let pane = args["pane"] as? Int else { return "error: pane is required" }
+                return infinittyRequest("pane-to-tab \(pane)")
+            case "swap":
+                guard let target else { return "error: pane or browserId is required" }
+                let other = (args["other"] as? String).map { " \($0)" } ?? ""
+                return infinittyRequest("pane-swap \(target)\(other)")
+            case "resize":
+                guard let target, let percent = args["percent"] as? Int else {
+                    return "error: pane (or browserId) and percent are required"
+                }
+                return infinittyRequest("pane-resize \(target) \(percent)")
+            default:
+                return "error: action is swap, new-tab, equalize or resize"
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_close",
+        description: "Close a pane (terminates its shell).",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("close \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_detach",
+        description: "Take a pane out of its window without ending its shell; output keeps going "
+            + "to its scrollback and the pane tools (send, screen, history) still work on it.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("detach \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_attach",
+        description: "Put a detached pane back, split beside target in direction, or as a new tab "
+            + "when no target is given.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "target": ["type": "integer", "description": "Pane to split beside"],
+                "direction": ["type": "string", "enum": ["right", "left", "down", "up"]],
+            ]) { a, _ in a },
+            "required": ["pane"],
+        ],
+        invoke: { args in
+            guard let target = args["target"] as? Int else { return infinittyRequest("attach \(paneArg(args))") }
+            return infinittyRequest(
+                "attach \(paneArg(args)) \(target) \(args["direction"] as? String ?? "right")")
+        }
+    ),
+    Tool(
+        name: "infinitty_detached",
+        description: "List detached panes (id, title, cwd, detachedAt) waiting to be attached.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("detached") }
+    ),
+    Tool(
+        name: "infinitty_new_detached",
+        description: "Start a shell with no window. Returns its pane id; drive it with the pane "
+            + "tools and show it with infinitty_attach.",
+        schema: [
+            "type": "object",
+            "properties": ["cwd": ["type": "string", "description": "Starting directory"]],
+        ],
+        invoke: { args in
+            let cwd = args["cwd"] as? String ?? ""
+            return infinittyRequest(cwd.isEmpty ? "new-detached" : "new-detached \(cwd)")
+        }
+    ),
+    Tool(
+        name: "infinitty_layout_presets",
+        description: "List named split layouts (built-in and saved) with their shapes and pane counts.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("layouts") }
+    ),
+    Tool(
+        name: "infinitty_layout_preset_save",
+        description: "Save a tab's split geometry (rows, columns and proportions, not the panes) as a "
+            + "named layout. Captures the tab of pane (default: the focused tab) unless shape is given: "
+            + "\"pane\" or {\"columns\"|\"rows\": [shape…], \"sizes\": [fractions]}.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "name": ["type": "string"],
+                "pane": ["type": "integer", "description": "A pane in the tab to capture"],
+                "shape": ["description": "Layout shape to save instead of capturing one"],
+            ],
+            "required": ["name"],
+        ],
+        invoke: { args in fileOperation("layout-save", args, keys: ["name", "pane", "shape"]) }
+    ),
+    Tool(
+        name: "infinitty_layout_preset_apply",
+        description: "Rearrange a tab's panes into a named layout or literal shape. Existing panes fill "
+            + "the slots in reading order, empty slots get new shells, extra panes stack in the last slot.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "name": ["type": "string", "description": "Preset name, e.g. three-columns-wide-bottom"],
+                "shape": ["description": "Layout shape, instead of a name"],
+                "pane": ["type": "integer", "description": "A pane in the tab to rearrange"],
+            ],
+        ],
+        invoke: { args in fileOperation("layout-apply", args, keys: ["name", "shape", "pane"]) }
+    ),
+    Tool(
+        name: "infinitty_workspace",
+        description: "The saved workspace: every window, tab and split with each pane's directory and profile, "
+            + "and the browser panes beside them. "
+            + "action show (default) reads the open one, save writes it now, restore reopens the saved one "
+            + "beside the open windows.",
+        schema: [
+            "type": "object",
+            "properties": ["action": ["type": "string", "enum": ["show", "save", "restore"]]],
+        ],
+        invoke: { args in
+            switch args["action"] as? String {
+            case "save": return infinittyRequest("workspace-save")
+            case "restore": return infinittyRequest("workspace-restore")
+            default: return infinittyRequest("workspace")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_git_status",
+        description: "Branch and changed files of the git repo holding a pane's directory (default the focused "
+            + "pane) or dir. Served from a cache the app keeps current with a file watcher, so it is cheap to "
+            + "call; subscribe to git-status-changed instead of polling. Returns {repo, branch, changes: "
+            + "[{path, status, staged}], upstream?, ahead?, behind?, error?}; counts are as of the last fetch.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            if let dir = args["dir"] as? String { return infinittyRequest("git-status \(dir)") }
+            return infinittyRequest(args["pane"] == nil ? "git-status" : "git-status \(paneArg(args))")
+        }
+    ),
+    Tool(
+        name: "infinitty_git_sync",
+        description: "Fetch (default), pull or push the git repo holding a pane's directory (default the focused "
+            + "pane) or dir, then return its status with upstream, ahead and behind counts. remote picks what "
+            + "to fetch from or push to; for pull, rebase true rebases, false merges, and absent follows "
+            + "pull.rebase. A push sends the current branch (or branch, a refspec), setting the upstream when "
+            + "it has none unless setUpstream is false; forceWithLease overwrites only what the last fetch "
+            + "saw, tags pushes every tag. An HTTPS remote that wants credentials asks the user (or "
+            + "infinitty_git_credentials) and waits up to 90 seconds; an ssh one that wants a passphrase "
+            + "returns git's error.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "action": ["type": "string", "enum": ["fetch", "pull", "push"]],
+                "pull": ["type": "boolean", "description": "Same as action pull"],
+                "remote": ["type": "string"],
+                "rebase": ["type": "boolean"],
+                "branch": ["type": "string"],
+                "forceWithLease": ["type": "boolean"],
+                "setUpstream": ["type": "boolean"],
+                "tags": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            switch args["action"] as? String ?? (args["pull"] as? Bool == true ? "pull" : "fetch") {
+            case "pull":
+                return fileOperation("git-pull", args, keys: ["pane", "dir", "rebase"])
+            case "push":
+                return fileOperation(
+                    "git-push", args,
+                    keys: ["pane", "dir", "remote", "branch", "forceWithLease", "setUpstream", "tags"])
+            default:
+                return fileOperation("git-fetch", args, keys: ["pane", "dir", "remote"])
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_git_credentials",
+        description: "Credential prompts from git commands infinitty is running (a fetch, pull or push to an "
+            + "HTTPS remote): list the waiting ones [{id, prompt, url?, username?, secret}], answer one (or "
+            + "cancel it, which fails that git command), or forget the answers remembered in the Keychain "
+            + "for url (default all). remember keeps the answer for the same prompt next time.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["list", "answer", "cancel", "forget"]],
+                "id": ["type": "string"],
+                "answer": ["type": "string"],
+                "remember": ["type": "boolean"],
+                "url": ["type": "string", "description": "For forget: https://host"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            switch args["action"] as? String {
+            case "answer": return fileOperation("git-credential-answer", args, keys: ["id", "answer", "remember"])
+            case "cancel":
+                let request = args.merging(["cancel": true]) { _, new in new }
+                return fileOperation("git-credential-answer", request, keys: ["id", "cancel"])
+            case "forget": return infinittyRequest("git-credential-forget \(args["url"] as? String ?? "")")
+            default: return infinittyRequest("git-credentials")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_git_remotes",
+        description: "List, add or remove the remotes of the git repo holding a pane's directory (default the "
+            + "focused pane) or dir. Returns {repo, remotes: [{name, fetch, push}]} after the change; removing "
+            + "one also drops its remote-tracking branches.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "action": ["type": "string", "enum": ["list", "add", "remove"]],
+                "name": ["type": "string"],
+                "url": ["type": "string"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            switch args["action"] as? String {
+            case "add": return fileOperation("git-remote-add", args, keys: ["pane", "dir", "name", "url"])
+            case "remove": return fileOperation("git-remote-remove", args, keys: ["pane", "dir", "name"])
+            default:
+                if let dir = args["dir"] as? String { return infinittyRequest("git-remotes \(dir)") }
+                return infinittyRequest(args["pane"] == nil ? "git-remotes" : "git-remotes \(paneArg(args))")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_search_index",
+        description: "Lines containing query in the workspace (repo root, or the directory outside git) of a "
+            + "pane's directory (default the focused pane) or dir: {root, indexed, matches: [{path, line, text}]}, "
+            + "paths relative to root, ASCII case ignored. Answered from a persistent trigram index; indexed "
+            + "false means it is still building and grep answered. Without query, returns the index's status "
+            + "(state, files, bytes, truncated, caps); rebuild true starts it over.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory in the workspace, instead of a pane"],
+                "query": ["type": "string"],
+                "limit": ["type": "integer", "description": "Most lines to return (default 200)"],
+                "rebuild": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            if args["query"] != nil {
+                return fileOperation("index-search", args, keys: ["pane", "dir", "query", "limit"])
+            }
+            let command = args["rebuild"] as? Bool == true ? "index-rebuild" : "index-status"
+            if let dir = args["dir"] as? String { return infinittyRequest("\(command) \(dir)") }
+            return infinittyRequest(args["pane"] == nil ? command : "\(command) \(paneArg(args))")
+        }
+    ),
+    Tool(
+        name: "infinitty_git_branch",
+        description: "Branches of the git repo holding a pane's directory (default the focused pane) or dir. "
+            + "action list (default) returns {current, local, remote}; create makes name at from (default "
+            + "HEAD; checkout true switches to it); delete removes name (force true even if unmerged); rename "
+            + "renames from (default the current branch) to to; checkout switches to branch, and a remote "
+            + "branch such as origin/topic gets a local topic tracking it. Changes return the new status.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "action": ["type": "string", "enum": ["list", "create", "delete", "rename", "checkout"]],
+                "name": ["type": "string"],
+                "from": ["type": "string"],
+                "to": ["type": "string"],
+                "branch": ["type": "string"],
+                "checkout": ["type": "boolean"],
+                "force": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            switch args["action"] as? String ?? "list" {
+            case "create":
+                return fileOperation("git-branch-create", args, keys: ["pane", "dir", "name", "from", "checkout"])
+            case "delete":
+                return fileOperation("git-branch-delete", args, keys: ["pane", "dir", "name", "force"])
+            case "rename":
+                return fileOperation("git-branch-rename", args, keys: ["pane", "dir", "from", "to"])
+            case "checkout":
+                return fileOperation("git-checkout", args, keys: ["pane", "dir", "branch"])
+            default:
+                if let dir = args["dir"] as? String { return infinittyRequest("git-branches \(dir)") }
+                return infinittyRequest(args["pane"] == nil ? "git-branches" : "git-branches \(paneArg(args))")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_git_merge",
+        description: "Merge or rebase in the git repo holding a pane's directory (default the focused pane) or "
+            + "dir. action merge merges branch into the current branch; rebase rebases it onto onto; abort and "
+            + "continue act on the merge or rebase in progress (continue needs every conflict resolved). "
+            + "conflicts returns each conflicted file (or just path) with its base, ours and theirs text; "
+            + "resolve writes content to file and stages it. Stopping on conflicts is not an error: the reply "
+            + "has operation and the conflicted paths.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "action": ["type": "string", "enum": ["merge", "rebase", "abort", "continue", "conflicts", "resolve"]],
+                "branch": ["type": "string"],
+                "onto": ["type": "string"],
+                "path": ["type": "string"],
+                "file": ["type": "string"],
+                "content": ["type": "string", "description": "The resolved file contents"],
+            ]) { a, _ in a },
+            "required": ["action"],
+        ],
+        invoke: { args in
+            switch args["action"] as? String ?? "" {
+            case "merge":
+                return fileOperation("git-merge", args, keys: ["pane", "dir", "branch"])
+            case "rebase":
+                return fileOperation("git-rebase", args, keys: ["pane", "dir", "onto"])
+            case "abort", "continue":
+                // Whichever is in progress; git-conflicts names it.
+                let state = fileOperation("git-conflicts", args, keys: ["pane", "dir"])
+                let operation = state.contains("\"operation\":\"rebase\"") ? "rebase" : "merge"
+                return fileOperation("git-\(operation)-\(args["action"] as? String ?? "")", args, keys: ["pane", "dir"])
+            case "conflicts":
+                return fileOperation("git-conflicts", args, keys: ["pane", "dir", "path"])
+            case "resolve":
+                return fileOperation("git-resolve-conflict", args, keys: ["pane", "dir", "file", "content"])
+            default:
+                return "error: action is merge, rebase, abort, continue, conflicts or resolve"
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_git_commit",
+        description: "Commit what's staged in the git repo holding a pane's directory (default the focused pane) "
+            + "or dir. amend rewrites the last commit (message optional, kept when omitted). sign true signs "
+            + "with the configured GPG or SSH key (signingKey picks another), false skips it, omitted follows "
+            + "commit.gpgsign. author and committer take \"Name <email>\". Returns the new status and commit "
+            + "hash; a signature that couldn't be made errors with \"signing failed\".",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "message": ["type": "string"],
+                "amend": ["type": "boolean"],
+                "sign": ["type": "boolean"],
+                "signingKey": ["type": "string"],
+                "author": ["type": "string"],
+                "committer": ["type": "string"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            fileOperation(
+                "git-commit", args,
+                keys: ["pane", "dir", "message", "amend", "sign", "signingKey", "author", "committer"])
+        }
+    ),
+    Tool(
+        name: "infinitty_git_discard",
+        description: "Throw away work in the git repo holding a pane's directory (default the focused pane) or dir. "
+            + "With file, discards that file's unstaged changes. Otherwise lists the untracked files and folders "
+            + "under paths (default the whole repo) that a clean would delete; pass dryRun false to delete them. "
+            + "Show the list to the user before deleting: neither can be undone.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "file": ["type": "string"],
+                "paths": ["type": "array", "items": ["type": "string"]],
+                "dryRun": ["type": "boolean", "description": "Default true: only list what would be deleted"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            if args["file"] != nil { return fileOperation("git-discard", args, keys: ["pane", "dir", "file"]) }
+            return fileOperation("git-clean", args, keys: ["pane", "dir", "paths", "dryRun"])
+        }
+    ),
+    Tool(
+        name: "infinitty_git_diff",
+        description: "What changed in one file of a pane's (default the focused pane's) repo or dir's, as hunks: "
+            + "{header, oldStart, oldLines, newStart, newLines, added, removed, lines: [{kind (context, add, "
+            + "del), old?, new?, text}]}. staged diffs the index against HEAD, otherwise the work tree against "
+            + "the index; an untracked file is all additions. Without file, returns per-file added/removed "
+            + "counts for both.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "file": ["type": "string", "description": "Repo-relative or absolute path"],
+                "staged": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            guard args["file"] != nil else {
+                if let dir = args["dir"] as? String { return infinittyRequest("git-diff-stats \(dir)") }
+                return infinittyRequest(args["pane"] == nil ? "git-diff-stats" : "git-diff-stats \(paneArg(args))")
+            }
+            return fileOperation("git-diff", args, keys: ["pane", "dir", "file", "staged"])
+        }
+    ),
+    Tool(
+        name: "infinitty_git_log",
+        description: "History of a pane's (default the focused pane's) repo or dir's, newest first: {commits: "
+            + "[{hash, parents, author, email, date (unix seconds), subject, refs}], nextSkip} — pass nextSkip "
+            + "as skip for the next page. ref is a branch, tag or \"all\"; path limits to commits touching it; "
+            + "graph adds each commit's lane layout. With hash, returns that commit's body and changed files.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "hash": ["type": "string", "description": "Show this commit instead of listing"],
+                "limit": ["type": "integer", "description": "Commits per page, default 50"],
+                "skip": ["type": "integer"],
+                "ref": ["type": "string"],
+                "path": ["type": "string"],
+                "graph": ["type": "boolean"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            if args["hash"] != nil { return fileOperation("git-show", args, keys: ["pane", "dir", "hash"]) }
+            return fileOperation("git-log", args, keys: ["pane", "dir", "limit", "skip", "ref", "path", "graph"])
+        }
+    ),
+    Tool(
+        name: "infinitty_git_blame",
+        description: "Who last changed each line of file in a pane's (default the focused pane's) repo or dir's: "
+            + "{lines: [{line, hash, author, email, date (unix seconds), summary, text, committed}]}. from and "
+            + "to (1-based, inclusive) limit it to a range; rev blames the file as of that commit instead of "
+            + "the worktree. Lines changed since the last commit have committed false.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "file": ["type": "string", "description": "Path relative to the repo root"],
+                "from": ["type": "integer"],
+                "to": ["type": "integer"],
+                "rev": ["type": "string"],
+            ]) { a, _ in a },
+            "required": ["file"],
+        ],
+        invoke: { args in
+            fileOperation("git-blame", args, keys: ["pane", "dir", "file", "from", "to", "rev"])
+        }
+    ),
+    Tool(
+        name: "infinitty_git_stage_partial",
+        description: "Stage part of a file's changes, like git add -p: one hunk of infinitty_git_diff's output "
+            + "(hunk: its index or header), or single lines (old: [[from, to]] ranges of removed lines by old "
+            + "line number, new: ranges of added lines by new line number). unstage takes them back out of the "
+            + "staged diff instead. Returns {repo, file, unstaged, remainingHunks}.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "dir": ["type": "string", "description": "A directory inside the repo, instead of a pane"],
+                "file": ["type": "string"],
+                "hunk": ["description": "Hunk index or its @@ header"],
+                "old": ["type": "array", "items": ["type": "array", "items": ["type": "integer"]]],
+                "new": ["type": "array", "items": ["type": "array", "items": ["type": "integer"]]],
+                "unstage": ["type": "boolean"],
+            ]) { a, _ in a },
+            "required": ["file"],
+        ],
+        invoke: { args in
+            let command = args["hunk"] != nil ? "git-stage-hunk" : "git-stage-lines"
+            return fileOperation(command, args, keys: ["pane", "dir", "file", "hunk", "old", "new", "unstage"])
+        }
+    ),
+    Tool(
+        name: "infinitty_broadcast_group",
+        description: "Manage input broadcast groups: panes whose typed keystrokes are mirrored to "
+            + "each other. action create (panes), add / remove (group, panes), delete (group) or list. "
+            + "Returns the group's {id, panes}, or every group for list.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["create", "add", "remove", "delete", "list"]],
+                "group": ["type": "string", "description": "Group id from create, e.g. group-1"],
+                "panes": ["type": "array", "items": ["type": "integer"]],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let action = args["action"] as? String ?? ""
+            let panes = (args["panes"] as? [Int] ?? []).map(String.init)
+            let group = args["group"] as? String ?? ""
+            switch action {
+            case "list": return infinittyRequest("broadcast-groups")
+            case "create": return infinittyRequest((["broadcast-create"] + panes).joined(separator: " "))
+            case "add", "remove", "delete":
+                return infinittyRequest((["broadcast-\(action)", group] + panes).joined(separator: " "))
+            default: return "error: action must be create, add, remove, delete or list"
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_broadcast_send",
+        description: "Write text to every pane in a broadcast group, e.g. the same command on several "
+            + "servers. submit (default true) presses return after it. Returns {group, panes}.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "group": ["type": "string"],
+                "text": ["type": "string"],
+                "submit": ["type": "boolean"],
+            ],
+            "required": ["group", "text"],
+        ],
+        invoke: { args in
+            let command = args["submit"] as? Bool == false ? "broadcast-send" : "broadcast-send-line"
+            return infinittyRequest("\(command) \(args["group"] as? String ?? "") \(args["text"] as? String ?? "")")
+        }
+    ),
+    Tool(
+        name: "infinitty_start_recording",
+        description: "Record a pane's output with timestamps to an asciicast v2 file (playable with "
+            + "asciinema) until infinitty_stop_recording or the shell exits. Returns {path, events, "
+            + "startedAt, recording}.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "path": ["type": "string", "description": "Where to write the .cast file (default: "
+                    + "Application Support/infinitty/recordings)"],
+            ]) { a, _ in a },
+            "required": ["pane"],
+        ],
+        invoke: { args in
+            let path = args["path"] as? String ?? ""
+            return infinittyRequest(
+                path.isEmpty ? "record-start \(paneArg(args))" : "record-start \(paneArg(args)) \(path)")
+        }
+    ),
+    Tool(
+        name: "infinitty_stop_recording",
+        description: "Stop recording a pane and close its file. Returns {path, events, duration}.",
+        schema: ["type": "object", "properties": paneProperty, "required": ["pane"]],
+        invoke: { args in infinittyRequest("record-stop \(paneArg(args))") }
+    ),
+    Tool(
+        name: "infinitty_replay_recording",
+        description: "Play an asciicast v2 file back as a background task: each event arrives as a "
+            + "replay-frame event (task, time, kind, data) via infinitty_events at its recorded time. "
+            + "Returns the task snapshot; cancel it with task-cancel.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "path": ["type": "string"],
+                "pane": ["type": "integer", "description": "Also draw the output into this pane"],
+                "speed": ["type": "number", "description": "Playback speed multiplier (default 1)"],
+                "maxIdle": ["type": "number", "description": "Cap pauses at this many seconds"],
+            ],
+            "required": ["path"],
+        ],
+        invoke: { args in fileOperation("replay", args, keys: ["path", "pane", "speed", "maxIdle"]) }
+    ),
+    Tool(
+        name: "infinitty_add_trigger",
+        description: "Watch terminal output for a regex and act on every match. action event (default) "
+            + "emits a trigger event (trigger, pane, line, start, end, text, groups) via infinitty_events; "
+            + "run starts command with sh -c, the match in $INFINITTY_TRIGGER_MATCH and groups in "
+            + "$INFINITTY_TRIGGER_GROUP_1…; highlight paints the match; notify posts a macOS notification. "
+            + "Omit pane to watch every pane. Returns the trigger with its id.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "pattern": ["type": "string", "description": "ICU regular expression, matched per output row"],
+                "action": ["type": "string", "enum": ["event", "run", "highlight", "notify"]],
+                "command": ["type": "string", "description": "Shell command for action run"],
+                "pane": ["type": "integer"],
+            ],
+            "required": ["pattern"],
+        ],
+        invoke: { args in fileOperation("trigger-add", args, keys: ["pattern", "action", "command", "pane"]) }
+    ),
+    Tool(
+        name: "infinitty_remove_trigger",
+        description: "Remove an output trigger by id, or list the triggers (with how often each fired) "
+            + "when no id is given.",
+        schema: ["type": "object", "properties": ["id": ["type": "string"]]],
+        invoke: { args in
+            guard let id = args["id"] as? String else { return infinittyRequest("triggers") }
+            return infinittyRequest("trigger-remove \(id)")
+        }
+    ),
+    Tool(
+        name: "infinitty_events",
+        description: "Read live infinitty events so agents can react to terminal state changes. "
+            + "Event types: pane-opened, pane-closed, layout-changed, title, marker, command-started, "
+            + "command-finished, process, cwd (pane, cwd, source), task-progress, "
+            + "subsystem-ready (deferred launch work finished), surface-closed, ui, "
+            + "log-lines, log-ended, replay-frame, trigger, detected-links, output-flood (a pane started "
+            + "or stopped printing faster than ~4 MB/s; the end carries bytes and seconds), watch (a watched "
+            + "command's output changed: changed and removed lines), progress (pane, state, percent; state none "
+            + "once the command finishes), secure-input (pane, active, prompt: a pane started or stopped "
+            + "asking for a password), input-lock (pane, locked), pane-idle (pane, action, idleSeconds, "
+            + "snapshot: the idle policy acted on a pane), pane-resumed (pane), paste-held (pane, lines, "
+            + "concerns, hidden: a paste is waiting for the user to confirm it), command-held (pane, command, "
+            + "risks, targets, affected: Enter on a risky command is waiting for a yes), git-status-changed (repo, "
+            + "branch, changes: a repo read by infinitty_git_status changed on disk), link-activated (pane, "
+            + "url, action: a link was cmd-clicked and opened, asked about, denied or left to you), and "
+            + "browser-*. "
+            + "Events raised in a window carry its tab id as \"tab\". "
+            + "marker events are OSC 133 shell round trips (kind C = command started, "
+            + "kind D = command finished with its exit code); command-started and command-finished carry "
+            + "the command line, and the latter its exitCode and duration in seconds. process events "
+            + "fire when a pane's foreground process changes — e.g. an agent CLI like claude or codex starts, or the "
+            + "pane returns to the shell prompt (empty name). Returns {latestSeq, events}; pass "
+            + "sinceSeq from the previous response to read only newer events. Set waitSeconds to "
+            + "long-poll: the call blocks until a matching event arrives or the wait expires.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "sinceSeq": [
+                    "type": "integer",
+                    "description": "Only events after this sequence number (from the previous "
+                        + "response's latestSeq). Omit to get the most recent events.",
+                ] as [String: Any],
+                "waitSeconds": [
+                    "type": "integer", "minimum": 0, "maximum": 120,
+                    "description": "Block up to this many seconds for the first matching event "
+                        + "(default 0 = return immediately)",
+                ] as [String: Any],
+                "event": [
+                    "type": "string",
+                    "description": "Only events whose type contains this substring, "
+                        + "e.g. \"marker\" or \"process\"",
+                ] as [String: Any],
+                "pane": [
+                    "type": "integer",
+                    "description": "Only events for this pane id",
+                ] as [String: Any],
+                "tab": [
+                    "type": "string",
+                    "description": "Only events from this tab id (the \"tab\" field of "
+                        + "infinitty_list_panes), plus app-wide events",
+                ] as [String: Any],
+            ],
+        ],
+        invoke: { args in
+            let since = args["sinceSeq"] as? Int ?? max(eventBuffer.latestSeq - 20, 0)
+            let wait = min(max(args["waitSeconds"] as? Int ?? 0, 0), 120)
+            let entries = eventBuffer.collect(
+                since: since,
+                event: args["event"] as? String,
+                pane: args["pane"] as? Int,
+                tab: args["tab"] as? String,
+                deadline: Date().addingTimeInterval(TimeInterval(wait)))
+            let events = entries.map { entry -> [String: Any] in
+                var object = entry.object
+                object["seq"] = entry.seq
+                return object
+            }
+            let payload: [String: Any] = ["latestSeq": eventBuffer.latestSeq, "events": events]
+            guard let data = try? JSONSerialization.data(withJSONObject: payload) else {
+                return "error: could not encode events"
+            }
+            return String(decoding: data, as: UTF8.self)
+        }
+    ),
+    Tool(
+        name: "infinitty_fs_copy",
+        description: "Copy a file or directory tree (symlinks kept as links, permissions and "
+            + "timestamps preserved). `to` is the exact destination path; existing directories "
+            + "merge. Returns the task snapshot whose result lists entries, bytes, skipped "
+            + "conflicts, warnings, and per-entry failures.",
+        schema: fileTransferSchema,
+        invoke: { args in fileOperation("fs-copy", args, keys: ["from", "to", "conflict"]) }
+    ),
+    Tool(
+        name: "infinitty_fs_move",
+        description: "Move a file or directory (rename on the same volume, copy + delete across "
+            + "volumes). Returns the same structured summary as infinitty_fs_copy.",
+        schema: fileTransferSchema,
+        invoke: { args in fileOperation("fs-move", args, keys: ["from", "to", "conflict"]) }
+    ),
+    Tool(
+        name: "infinitty_fs_delete",
+        description: "Permanently delete a file or directory tree (symlinks are removed, not "
+            + "followed). Returns entries and bytes removed plus per-entry failures.",
+        schema: [
+            "type": "object",
+            "properties": ["path": ["type": "string", "description": "Absolute path"]],
+            "required": ["path"],
+        ],
+        invoke: { args in fileOperation("fs-delete", args, keys: ["path"]) }
+    ),
+    Tool(
+        name: "infinitty_fs_read",
+        description: "Read a text file's contents, capped at `limit` bytes (default 65536, max "
+            + "200000); larger files come back with truncated=true instead of being read whole. "
+            + "Binary files are refused.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "path": ["type": "string", "description": "Absolute path"],
+                "limit": ["type": "integer", "description": "Maximum bytes to return"],
+            ],
+            "required": ["path"],
+        ],
+        invoke: { args in fileOperation("fs-read", args, keys: ["path", "limit"]) }
+    ),
+    Tool(
+        name: "infinitty_fs_hash",
+        description: "SHA-256 of a file, streamed in chunks so very large files are fine. "
+            + "Returns the task snapshot; result.sha256 holds the hex digest.",
+        schema: [
+            "type": "object",
+            "properties": ["path": ["type": "string", "description": "Absolute path"]],
+            "required": ["path"],
+        ],
+        invoke: { args in fileOperation("fs-hash", args, keys: ["path"]) }
+    ),
+    Tool(
+        name: "infinitty_format",
+        description: "Pretty-print JSON or YAML, keeping key order, and optionally apply a "
+            + "jq-style path (.a.b, .items[0], .items[-1], .items[1:3], .items[].name). "
+            + "Pass text, or pane to inspect that pane's last command output (needs OSC 133). "
+            + "output converts between json and yaml.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "text": ["type": "string", "description": "JSON or YAML to format"],
+                "input": [
+                    "type": "string", "enum": ["json", "yaml"],
+                    "description": "Input format (default: detect)",
+                ] as [String: Any],
+                "output": [
+                    "type": "string", "enum": ["json", "yaml"],
+                    "description": "Output format (default: same as input)",
+                ] as [String: Any],
+                "query": ["type": "string", "description": "jq-style path to extract"],
+            ]) { a, _ in a },
+        ],
+        invoke: { args in
+            fileOperation("format", args, keys: ["text", "pane", "input", "output", "query"])
+        }
+    ),
+    Tool(
+        name: "infinitty_log_open",
+        description: "Follow a log file (like tail -F, surviving rotation) or a shell command's "
+            + "output. filter is a regex and level a minimum severity (trace|debug|info|warn|"
+            + "error|fatal), both applied in the app; levels come from JSON level fields, "
+            + "level= pairs, or WARN-style words. Returns {id}; read lines with "
+            + "infinitty_log_read or watch log-lines events via infinitty_events.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "path": ["type": "string", "description": "Absolute path of a file to follow"],
+                "command": ["type": "string", "description": "Shell command whose output to follow"],
+                "cwd": ["type": "string", "description": "Working directory for command"],
+                "filter": ["type": "string", "description": "Only lines matching this regex"],
+                "level": ["type": "string", "description": "Only lines at or above this level"],
+            ],
+        ],
+        invoke: { args in
+            fileOperation("log-open", args, keys: ["path", "command", "cwd", "filter", "level"])
+        }
+    ),
+    Tool(
+        name: "infinitty_log_read",
+        description: "Read buffered lines of a log session after sequence number `since`, each "
+            + "with its level and the UTF-16 ranges the filter matched. filter/level here "
+            + "override the session's for this read. Pass latestSeq back as since to continue.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "id": ["type": "string", "description": "Log session id, e.g. log-1"],
+                "since": ["type": "integer", "description": "Only lines after this seq (default 0)"],
+                "limit": ["type": "integer", "description": "Maximum lines (default 200, max 1000)"],
+                "filter": ["type": "string", "description": "Regex overriding the session filter"],
+                "level": ["type": "string", "description": "Minimum level overriding the session's"],
+            ],
+            "required": ["id"],
+        ],
+        invoke: { args in
+            fileOperation("log-read", args, keys: ["id", "since", "limit", "filter", "level"])
+        }
+    ),
+    Tool(
+        name: "infinitty_log_control",
+        description: "Pause a log session (stop reading; nothing is lost), resume following it, "
+            + "or close it.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "id": ["type": "string", "description": "Log session id, e.g. log-1"],
+                "action": [
+                    "type": "string", "enum": ["pause", "follow", "close"],
+                    "description": "What to do with the session",
+                ] as [String: Any],
+            ],
+            "required": ["id", "action"],
+        ],
+        invoke: { args in
+            guard let id = args["id"] as? String,
+                  let action = args["action"] as? String,
+                  ["pause", "follow", "close"].contains(action)
+            else { return "error: id and action (pause|follow|close) are required" }
+            return infinittyRequest("log-\(action) \(id)")
+        }
+    ),
+    Tool(
+        name: "infinitty_share_join",
+        description: "Join a terminal someone is sharing from their infinitty (share-start gave them the "
+            + "join code) through the configured share-relay, end-to-end encrypted. Returns {id, mode, "
+            + "cols, rows, screen}; mode stays ro until the host grants this guest input.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "code": ["type": "string", "description": "Join code from the host"],
+                "name": ["type": "string", "description": "Name the host sees (default: your account name)"],
+            ],
+            "required": ["code"],
+        ],
+        invoke: { args in
+            guard let code = args["code"] as? String, !code.isEmpty else { return "error: code is required" }
+            let name = (args["name"] as? String).map { " " + $0 } ?? ""
+            return infinittyRequest("share-join \(code)\(name)")
+        }
+    ),
+    Tool(
+        name: "infinitty_share_read",
+        description: "The shared terminal's current screen as the host sees it, with this guest's mode "
+            + "(ro|rw) and ended once the share is over.",
+        schema: [
+            "type": "object",
+            "properties": ["id": ["type": "string", "description": "Joined share id, e.g. share-1"]],
+            "required": ["id"],
+        ],
+        invoke: { args in
+            guard let id = args["id"] as? String else { return "error: id is required" }
+            return infinittyRequest("share-read \(id)")
+        }
+    ),
+    Tool(
+        name: "infinitty_share_send",
+        description: "Type into the shared terminal. Refused until the host grants this guest read-write "
+            + "(share-mode); enter presses Return after the text.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "id": ["type": "string", "description": "Joined share id, e.g. share-1"],
+                "text": ["type": "string"],
+                "enter": ["type": "boolean", "description": "Press Return afterwards"],
+            ],
+            "required": ["id", "text"],
+        ],
+        invoke: { args in
+            guard let id = args["id"] as? String, let text = args["text"] as? String else {
+                return "error: id and text are required"
+            }
+            let cmd = args["enter"] as? Bool == true ? "share-send-line" : "share-send"
+            return infinittyRequest("\(cmd) \(id) \(text)")
+        }
+    ),
+    Tool(
+        name: "infinitty_share_leave",
+        description: "Leave a joined share; the host sees this guest go.",
+        schema: [
+            "type": "object",
+            "properties": ["id": ["type": "string", "description": "Joined share id, e.g. share-1"]],
+            "required": ["id"],
+        ],
+        invoke: { args in
+            guard let id = args["id"] as? String else { return "error: id is required" }
+            return infinittyRequest("share-leave \(id)")
+        }
+    ),
+    Tool(
+        name: "infinitty_history_stats",
+        description: "Usage analytics over infinitty's local command history (commands that "
+            + "finished under OSC 133 shell integration): total and failure rate, commands per "
+            + "day and per hour of day, top programs and busiest directories with their "
+            + "failure rates. Computed on this machine.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "range": [
+                    "type": "string",
+                    "description": "today, all, or a number of days like 7d (default 30d)",
+                ],
+            ],
+        ],
+        invoke: { args in
+            infinittyRequest("history-stats \((args["range"] as? String) ?? "30d")")
+        }
+    ),
+    Tool(
+        name: "infinitty_history_search",
+        description: "Fuzzy search over the commands every infinitty pane has run (logged through OSC 133), "
+            + "best match first, newest first without a query. Each hit has the command, exitCode, "
+            + "duration, cwd and pane. Filters: cwd (that directory or below), pane, failed (true for "
+            + "failures only, false for successes), since (today or 7d), limit.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "query": ["type": "string"],
+                "cwd": ["type": "string"],
+                "pane": ["type": "integer"],
+                "failed": ["type": "boolean"],
+                "since": ["type": "string", "description": "today or a number of days like 7d"],
+                "limit": ["type": "integer", "description": "1-1000 (default 50)"],
+                "unique": ["type": "boolean", "description": "Each command once (default true)"],
+            ],
+        ],
+        invoke: { args in
+            fileOperation(
+                "history-search", args, keys: ["query", "cwd", "pane", "failed", "since", "limit", "unique"])
+        }
+    ),
+    Tool(
+        name: "infinitty_suggest_correction",
+        description: "What a failed or mistyped command line probably meant, worked out locally and at "
+            + "once: for a program that isn't on PATH, an alias or in the history, the same line with a "
+            + "near miss swapped in and any Homebrew install that provides it; otherwise the line with a "
+            + "mistyped subcommand fixed from ones that have worked before (git psuh -> git push). "
+            + "Returns {command, program, suggestions: [{kind, command}]}, best first.",
+        schema: [
+            "type": "object",
+            "properties": ["command": ["type": "string"]],
+            "required": ["command"],
+        ],
+        invoke: { args in
+            infinittyRequest("suggest-correction \(args["command"] as? String ?? "")")
+        }
+    ),
+    Tool(
+        name: "infinitty_packages",
+        description: "List system packages from Homebrew (or MacPorts when Homebrew is absent): "
+            + "outdated ones with installed and latest versions, or everything installed. "
+            + "Read-only; upgrades are started by the user from infinitty.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "which": [
+                    "type": "string",
+                    "enum": ["outdated", "installed"],
+                    "description": "outdated (default) or installed",
+                ],
+            ],
+        ],
+        invoke: { args in
+            infinittyRequest("packages \((args["which"] as? String) ?? "outdated")")
+        }
+    ),
+    Tool(
+        name: "infinitty_cloud_profiles",
+        description: "List cloud CLI identities configured on this machine: AWS profiles, gcloud "
+            + "configurations and Azure subscriptions, with region/project/tenant, which one is "
+            + "the CLI default, and whether the name looks like production. No secrets.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("cloud-profiles") }
+    ),
+    Tool(
+        name: "infinitty_cloud_set_profile",
+        description: "Select a cloud profile for new commands in a pane by exporting its "
+            + "environment variable (AWS_PROFILE, CLOUDSDK_ACTIVE_CONFIG_NAME, or "
+            + "AZURE_SUBSCRIPTION_ID) into the pane's shell; omit profile to unset. The pane "
+            + "header shows the active profile. Fails while a program is in the foreground.",
+        schema: [
+            "type": "object",
+            "properties": paneProperty.merging([
+                "provider": ["type": "string", "enum": ["aws", "gcp", "azure"]],
+                "profile": [
+                    "type": "string",
+                    "description": "Profile, configuration, or subscription name or id "
+                        + "(from infinitty_cloud_profiles)",
+                ],
+            ]) { a, _ in a },
+            "required": ["pane", "provider"],
+        ],
+        invoke: { args in
+            guard let provider = args["provider"] as? String else { return "error: provider is required" }
+            let profile = (args["profile"] as? String) ?? ""
+            return infinittyRequest("cloud-set-profile \(paneArg(args)) \(provider) \(profile)")
+        }
+    ),
+    Tool(
+        name: "infinitty_ssh_keys",
+        description: "Audit SSH keys: every key in ~/.ssh and the ssh-agent with fingerprint, type, "
+            + "bits, comment, whether the private key is passphrase-protected or has permissions "
+            + "that are too open, and whether the agent holds it. No key material is returned.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("ssh-keys") }
+    ),
+    Tool(
+        name: "infinitty_ssh_keygen",
+        description: "Create an SSH key pair with ssh-keygen. Refuses to overwrite an existing key.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "type": ["type": "string", "enum": ["ed25519", "ecdsa", "rsa"]],
+                "bits": ["type": "integer", "description": "rsa: 3072 or 4096; ecdsa: 256, 384 or 521"],
+                "path": ["type": "string", "description": "Private key path (default ~/.ssh/id_<type>)"],
+                "comment": ["type": "string"],
+                "passphrase": ["type": "string", "description": "Empty for none"],
+            ],
+        ],
+        invoke: { args in
+            fileOperation("ssh-keygen", args, keys: ["type", "bits", "path", "comment", "passphrase"])
+        }
+    ),
+    Tool(
+        name: "infinitty_ssh_copy_id",
+        description: "Install a public key on a remote host by opening an infinitty tab that runs "
+            + "ssh-copy-id; the user answers the password prompt there. Returns the pane id.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "host": ["type": "string", "description": "user@host"],
+                "key": ["type": "string", "description": "Public key path (default ~/.ssh/id_ed25519.pub)"],
+                "port": ["type": "integer"],
+            ],
+            "required": ["host"],
+        ],
+        invoke: { args in fileOperation("ssh-copy-id", args, keys: ["host", "key", "port"]) }
+    ),
+    Tool(
+        name: "infinitty_net_dns",
+        description: "Look up DNS records with dig and return them parsed: status plus answers "
+            + "with name, type, TTL and value.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "host": ["type": "string", "description": "Name, or an address for PTR"],
+                "type": [
+                    "type": "string", "enum": ["A", "AAAA", "CNAME", "MX", "NS", "TXT", "SOA", "PTR", "SRV", "CAA"],
+                ],
+                "server": ["type": "string", "description": "Resolver to ask instead of the system's"],
+            ],
+            "required": ["host"],
+        ],
+        invoke: { args in
+            let host = args["host"] as? String ?? ""
+            let server = (args["server"] as? String).map { " @" + $0 } ?? ""
+            return infinittyRequest("net-dns \(host) \(args["type"] as? String ?? "A")\(server)")
+        }
+    ),
+    Tool(
+        name: "infinitty_net_ping",
+        description: "Ping a host as an infinitty background task. Returns the task at once; each "
+            + "reply streams as a net-ping event via infinitty_events, and the task result "
+            + "(infinitty_tasks) has loss and min/avg/max/stddev round trips in ms.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "host": ["type": "string"],
+                "count": ["type": "integer", "description": "Echoes to send, 1-100 (default 5)"],
+            ],
+            "required": ["host"],
+        ],
+        invoke: { args in
+            infinittyRequest("net-ping \(args["host"] as? String ?? "") \(args["count"] as? Int ?? 5)")
+        }
+    ),
+    Tool(
+        name: "infinitty_net_traceroute",
+        description: "Trace the route to a host as an infinitty background task. Each hop streams "
+            + "as a net-traceroute event; the task result lists every hop's addresses and "
+            + "per-probe round trips.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "host": ["type": "string"],
+                "maxHops": ["type": "integer", "description": "1-64 (default 30)"],
+            ],
+            "required": ["host"],
+        ],
+        invoke: { args in
+            infinittyRequest("net-traceroute \(args["host"] as? String ?? "") \(args["maxHops"] as? Int ?? 30)")
+        }
+    ),
+    Tool(
+        name: "infinitty_net_wol",
+        description: "Send a Wake-on-LAN magic packet to a MAC address, or to a watched host by name "
+            + "when infinitty_host_watch saved its MAC.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "mac": ["type": "string", "description": "aa:bb:cc:dd:ee:ff, or a watched host"],
+                "broadcast": ["type": "string", "description": "Broadcast address (default 255.255.255.255)"],
+            ],
+            "required": ["mac"],
+        ],
+        invoke: { args in
+            let broadcast = (args["broadcast"] as? String).map { " " + $0 } ?? ""
+            return infinittyRequest("net-wol \(args["mac"] as? String ?? "")\(broadcast)")
+        }
+    ),
+    Tool(
+        name: "infinitty_hosts",
+        description: "List hosts infinitty is monitoring, with whether each is up, its last round trip "
+            + "and since when. Changes arrive as host-status events via infinitty_events.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("hosts") }
+    ),
+    Tool(
+        name: "infinitty_host_watch",
+        description: "Start (or update) monitoring a host: infinitty pings it every interval seconds and "
+            + "emits host-status events when it goes up or down.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "host": ["type": "string"],
+                "name": ["type": "string"],
+                "mac": ["type": "string", "description": "Saved for infinitty_net_wol"],
+                "interval": ["type": "integer", "description": "Seconds between pings, 5-3600 (default 30)"],
+            ],
+            "required": ["host"],
+        ],
+        invoke: { args in fileOperation("host-watch", args, keys: ["host", "name", "mac", "interval"]) }
+    ),
+    Tool(
+        name: "infinitty_host_unwatch",
+        description: "Stop monitoring a host added with infinitty_host_watch.",
+        schema: [
+            "type": "object",
+            "properties": ["host": ["type": "string"]],
+            "required": ["host"],
+        ],
+        invoke: { args in infinittyRequest("host-unwatch \(args["host"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_text_util",
+        description: "Encode, decode or hash text: base64-encode, base64-decode, url-encode, url-decode, "
+            + "jwt-decode (header and payload, unverified), jwt-verify (needs key: HMAC secret or PEM public "
+            + "key), or md5/sha1/sha256/sha384/sha512/blake3. Input is text, a pane's selection, or for "
+            + "hashes a file path.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "op": [
+                    "type": "string",
+                    "enum": [
+                        "base64-encode", "base64-decode", "url-encode", "url-decode", "jwt-decode", "jwt-verify",
+                        "md5", "sha1", "sha256", "sha384", "sha512", "blake3",
+                    ],
+                ],
+                "text": ["type": "string"],
+                "pane": ["type": "integer", "description": "Use this pane's selected text"],
+                "path": ["type": "string", "description": "File to hash"],
+                "key": ["type": "string", "description": "For jwt-verify"],
+            ],
+            "required": ["op"],
+        ],
+        invoke: { args in fileOperation("text-util", args, keys: ["op", "text", "pane", "path", "key"]) }
+    ),
+    Tool(
+        name: "infinitty_regex_test",
+        description: "Test a regular expression (ICU syntax, as infinitty's log filters use) against sample "
+            + "text: every match with numbered and named groups as UTF-16 offsets, an optional $1-style "
+            + "replacement preview, or for an invalid pattern the error and its offset.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "pattern": ["type": "string"],
+                "flags": ["type": "string", "description": "Any of i, m, s, x, w"],
+                "sample": ["type": "string"],
+                "replace": ["type": "string", "description": "Replacement template, e.g. $2-$1"],
+            ],
+            "required": ["pattern", "sample"],
+        ],
+        invoke: { args in fileOperation("regex-test", args, keys: ["pattern", "flags", "sample", "replace"]) }
+    ),
+    Tool(
+        name: "infinitty_regex_patterns",
+        description: "List the regex tester's pattern library: built-ins (dates, UUIDs, versions, "
+            + "addresses) and saved patterns.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("regex-patterns") }
+    ),
+    Tool(
+        name: "infinitty_regex_save",
+        description: "Save a pattern to the regex tester's library (replacing one of the same name). "
+            + "The pattern must compile.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "name": ["type": "string"],
+                "pattern": ["type": "string"],
+                "flags": ["type": "string"],
+                "sample": ["type": "string"],
+            ],
+            "required": ["name", "pattern"],
+        ],
+        invoke: { args in fileOperation("regex-save", args, keys: ["name", "pattern", "flags", "sample"]) }
+    ),
+    Tool(
+        name: "infinitty_eval",
+        description: "Evaluate an expression with the palette calculator: arithmetic (+ - * / ^ mod, %, "
+            + "sqrt/log/sin…), hex and binary, unit conversions like \"1536 MiB in GB\" or \"72 F to C\", "
+            + "and currencies (\"20 usd in eur\") at cached ECB reference rates.",
+        schema: [
+            "type": "object",
+            "properties": ["expression": ["type": "string"]],
+            "required": ["expression"],
+        ],
+        invoke: { args in infinittyRequest("eval \(args["expression"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_speech_start",
+        description: "Start dictation through the Mac's microphone (on-device speech recognition where "
+            + "the language allows). Transcripts stream as speech events via infinitty_events; the first "
+            + "use asks the user for microphone and speech access.",
+        schema: [
+            "type": "object",
+            "properties": ["locale": ["type": "string", "description": "BCP 47, e.g. en-US; system default"]],
+        ],
+        invoke: { args in infinittyRequest("speech-start \(args["locale"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_speech_stop",
+        description: "Stop dictation and return the transcript so far as {text}.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("speech-stop") }
+    ),
+    Tool(
+        name: "infinitty_dnd",
+        description: "Read or set infinitty's Do Not Disturb: auto follows macOS Focus, on/off force it. "
+            + "Returns {active, mode, focus, focusName}. Check it before notifying the user.",
+        schema: [
+            "type": "object",
+            "properties": ["mode": ["type": "string", "enum": ["auto", "on", "off"]]],
+        ],
+        invoke: { args in infinittyRequest("dnd \(args["mode"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_long_command_notifications",
+        description: "Read or change when infinitty notifies about finished commands: a macOS "
+            + "notification is posted when a command that ran at least threshold seconds finishes "
+            + "while its window is in the background. Returns {enabled, threshold}.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "enabled": ["type": "boolean"],
+                "threshold": ["type": "number", "description": "Minimum runtime in seconds"],
+            ],
+        ],
+        invoke: { args in
+            var words: [String] = []
+            if let enabled = args["enabled"] as? Bool { words.append(enabled ? "on" : "off") }
+            if let threshold = args["threshold"] as? Double { words.append(String(threshold)) }
+            return infinittyRequest((["notify-long-commands"] + words).joined(separator: " "))
+        }
+    ),
+    Tool(
+        name: "infinitty_timetrack_report",
+        description: "Active time per workspace (git repo or directory) tracked by infinitty, with a "
+            + "per-day breakdown in seconds. Needs time-tracking = true in the config.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "range": [
+                    "type": "string",
+                    "description": "today (default), yesterday, week, last-week, month, last-month, "
+                        + "7d, YYYY-MM-DD or YYYY-MM-DD..YYYY-MM-DD",
+                ],
+            ],
+        ],
+        invoke: { args in infinittyRequest("timetrack-report \(args["range"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_pomodoro",
+        description: "Start a pomodoro for the focused pane's workspace (minutes), stop it (\"stop\"), "
+            + "or report the running one (no argument). A pomodoro event fires when it ends.",
+        schema: [
+            "type": "object",
+            "properties": ["action": ["type": "string", "description": "Minutes, e.g. 25, or stop"]],
+        ],
+        invoke: { args in
+            let action = args["action"].map { "\($0)" } ?? ""
+            return infinittyRequest("pomodoro \(action)")
+        }
+    ),
+    Tool(
+        name: "infinitty_notes_get",
+        description: "Read the notes and todos infinitty keeps for a workspace (the repo containing dir, "
+            + "default the focused pane's directory).",
+        schema: [
+            "type": "object",
+            "properties": ["dir": ["type": "string", "description": "Directory inside the workspace"]],
+        ],
+        invoke: { args in fileOperation("notes-get", args, keys: ["dir"]) }
+    ),
+    Tool(
+        name: "infinitty_notes_set",
+        description: "Replace a workspace's free-form notes (shown in infinitty's Notes pane).",
+        schema: [
+            "type": "object",
+            "properties": [
+                "text": ["type": "string"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["text"],
+        ],
+        invoke: { args in fileOperation("notes-set", args, keys: ["text", "dir"]) }
+    ),
+    Tool(
+        name: "infinitty_todos_add",
+        description: "Add a todo to a workspace's list; returns it with its id.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "text": ["type": "string"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["text"],
+        ],
+        invoke: { args in fileOperation("todos-add", args, keys: ["text", "dir"]) }
+    ),
+    Tool(
+        name: "infinitty_todos_toggle",
+        description: "Check or uncheck a workspace todo by id; without done it flips.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "id": ["type": "string"],
+                "done": ["type": "boolean"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["id"],
+        ],
+        invoke: { args in fileOperation("todos-toggle", args, keys: ["id", "done", "dir"]) }
+    ),
+    Tool(
+        name: "infinitty_todos_list",
+        description: "List a workspace's todos; with scan, also the TODO/FIXME comments in its files "
+            + "(path, line, tag, text).",
+        schema: [
+            "type": "object",
+            "properties": [
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+                "scan": ["type": "boolean"],
+            ],
+        ],
+        invoke: { args in fileOperation("todos-list", args, keys: ["dir", "scan"]) }
+    ),
+    Tool(
+        name: "infinitty_pinned_commands",
+        description: "Manage a workspace's pinned commands, which head infinitty's palette and can be "
+            + "bound to hotkeys. list returns them in order; add, edit, remove and move change them; "
+            + "run types one into a pane and presses Return.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["list", "add", "edit", "remove", "move", "run"]],
+                "id": ["type": "string", "description": "The pin (edit, remove, move, run)"],
+                "command": ["type": "string"],
+                "title": ["type": "string", "description": "Palette label; empty clears it"],
+                "hotkey": ["type": "string", "description": "e.g. cmd+shift+t; empty clears it"],
+                "index": ["type": "integer", "description": "New position (move)"],
+                "pane": ["type": "integer", "description": "Pane to run in (default: focused)"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let action = args["action"] as? String ?? "list"
+            return fileOperation(
+                "pins-\(action)", args, keys: ["id", "command", "title", "hotkey", "index", "pane", "dir"])
+        }
+    ),
+    Tool(
+        name: "infinitty_startup_commands",
+        description: "Read or set the commands every new pane in a workspace runs once its shell is up, "
+            + "after its profile's startup lines. get also reports a pane's own run: each line's source "
+            + "and exit code (with shell integration), and how many failed.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["get", "set"]],
+                "commands": [
+                    "type": "array", "items": ["type": "string"],
+                    "description": "The workspace's lines, in order (set); [] removes them",
+                ],
+                "pane": ["type": "integer", "description": "Pane whose workspace and run to use (default: focused)"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let command = args["action"] as? String == "set" ? "startup-commands-set" : "startup-commands"
+            return fileOperation(command, args, keys: ["commands", "pane", "dir"])
+        }
+    ),
+    Tool(
+        name: "infinitty_macros",
+        description: "Record and replay keyboard macros: what is typed into a pane, with its timing, for "
+            + "interactive sequences that can't be scripted. record_start begins capturing a pane's input, "
+            + "record_stop saves it under its name, play types a macro into a pane, list and remove "
+            + "manage the saved ones.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": [
+                    "type": "string", "enum": ["list", "record_start", "record_stop", "play", "remove"],
+                ],
+                "name": ["type": "string", "description": "The macro (all but list)"],
+                "pane": ["type": "integer", "description": "Pane to record or play in (default: focused)"],
+                "speed": ["type": "number", "description": "Playback speed multiplier (default 1)"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let command: String
+            switch args["action"] as? String ?? "list" {
+            case "record_start": command = "macro-record-start"
+            case "record_stop": command = "macro-record-stop"
+            case "play": command = "macro-play"
+            case "remove": command = "macro-remove"
+            default: command = "macros"
+            }
+            return fileOperation(command, args, keys: ["name", "pane", "speed"])
+        }
+    ),
+    Tool(
+        name: "infinitty_browser_bookmarks",
+        description: "Manage a workspace's browser bookmarks (docs, dashboards, local servers). list returns "
+            + "them in order with their folders; add, edit, remove and move change them (remove with folder "
+            + "drops that folder and its contents); folder-rename renames a folder from to to; open focuses "
+            + "a browser pane already on the page or opens a new one there.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": [
+                    "type": "string", "enum": ["list", "add", "edit", "remove", "move", "folder-rename", "open"],
+                ],
+                "id": ["type": "string", "description": "The bookmark (edit, remove, move, open)"],
+                "url": ["type": "string"],
+                "title": ["type": "string", "description": "Menu label; empty clears it"],
+                "folder": ["type": "string", "description": "Slash-separated, e.g. Docs/API; empty clears it"],
+                "from": ["type": "string"],
+                "to": ["type": "string"],
+                "index": ["type": "integer", "description": "New position (move)"],
+                "dir": ["type": "string", "description": "Directory inside the workspace"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let action = args["action"] as? String ?? "list"
+            return fileOperation(
+                "bookmarks-\(action)", args, keys: ["id", "url", "title", "folder", "from", "to", "index", "dir"])
+        }
+    ),
+    Tool(
+        name: "infinitty_project_automation",
+        description: "Show the .infinitty.toml that applies at a directory (env, run commands, layout) "
+            + "and whether the user trusted it (trusted, ignored, or ask). Trusting is left to the user; "
+            + "infinitty then applies it when a pane's shell enters the project.",
+        schema: [
+            "type": "object",
+            "properties": ["dir": ["type": "string", "description": "Directory (default: the focused pane's)"]],
+        ],
+        invoke: { args in infinittyRequest("project-automation \(args["dir"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_watch_command",
+        description: "Re-run a shell command on an interval or when files change, like watch or entr. "
+            + "start returns the watch; each run whose output or exit code changed raises a watch event "
+            + "(infinitty_events) with only the changed and removed lines. output returns the last run "
+            + "with changed lines marked; list and stop manage watches.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["start", "stop", "list", "output"]],
+                "id": ["type": "string", "description": "The watch (stop, output)"],
+                "command": ["type": "string", "description": "Shell command to run (start)"],
+                "interval": ["type": "number", "description": "Seconds between runs (default 2 without paths)"],
+                "paths": [
+                    "type": "array", "items": ["type": "string"],
+                    "description": "Files or directories whose changes trigger a run",
+                ],
+                "pane": ["type": "integer", "description": "Pane whose directory to run in; stops with it"],
+                "dir": ["type": "string", "description": "Working directory for a watch without a pane"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let id = args["id"] as? String ?? ""
+            switch args["action"] as? String ?? "list" {
+            case "start":
+                return fileOperation("watch-start", args, keys: ["command", "interval", "paths", "pane", "dir"])
+            case "stop": return infinittyRequest("watch-stop \(id)")
+            case "output": return infinittyRequest("watch-output \(id)")
+            default: return infinittyRequest("watches")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_tmux",
+        description: "Attach infinitty to a running tmux server in control mode, showing each tmux window "
+            + "as a tab of native panes kept in sync both ways; list or detach attachments, or run any "
+            + "tmux command on one and get its output lines.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["attach", "detach", "list", "command"]],
+                "id": ["type": "string", "description": "The attachment, e.g. tmux-1 (detach, command)"],
+                "session": ["type": "string", "description": "tmux session to attach (default: the latest)"],
+                "socket": ["type": "string", "description": "tmux -L socket name or -S socket path"],
+                "command": ["type": "string", "description": "tmux command line (command)"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            let id = args["id"] as? String ?? ""
+            switch args["action"] as? String ?? "list" {
+            case "attach": return fileOperation("tmux-attach", args, keys: ["session", "socket"])
+            case "detach": return infinittyRequest("tmux-detach \(id)")
+            case "command": return infinittyRequest("tmux-command \(id) \(args["command"] as? String ?? "")")
+            default: return infinittyRequest("tmux-clients")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_serial",
+        description: "List serial devices (/dev/cu.*, with the pane that has each open) or open one as a "
+            + "new infinitty tab in place of a shell, like screen or minicom. The returned pane works with "
+            + "every pane tool: send input, read the screen, wait for output.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["list", "open"]],
+                "port": ["type": "string", "description": "Device path or name, e.g. usbserial-1410 (open)"],
+                "baud": ["type": "integer", "description": "Baud rate (default 115200)"],
+                "dataBits": ["type": "integer", "description": "5-8 (default 8)"],
+                "parity": ["type": "string", "enum": ["none", "even", "odd"]],
+                "stopBits": ["type": "integer", "description": "1 or 2 (default 1)"],
+                "flowControl": ["type": "string", "enum": ["none", "hardware", "software"]],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            guard args["action"] as? String == "open" else { return infinittyRequest("serial-ports") }
+            return fileOperation(
+                "serial-open", args, keys: ["port", "baud", "dataBits", "parity", "stopBits", "flowControl"])
+        }
+    ),
+    Tool(
+        name: "infinitty_daemon",
+        description: "Shells that survive quitting infinitty: status lists what the PTY daemon holds and "
+            + "whether new shells go there; enable/disable sets that; attach reopens a detached shell "
+            + "(or all of them) as tabs, with its recent output replayed.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["status", "enable", "disable", "attach"]],
+                "id": ["type": "integer", "description": "Daemon shell id from status (attach; default all)"],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            switch args["action"] as? String {
+            case "enable": return infinittyRequest("daemon-enable on")
+            case "disable": return infinittyRequest("daemon-enable off")
+            case "attach": return infinittyRequest("daemon-attach \((args["id"] as? Int).map(String.init) ?? "")")
+            default: return infinittyRequest("daemon-status")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_profiles",
+        description: "Shell profiles for new panes: list them; save one (shell path, args, env vars that may "
+            + "use $VAR, startup commands, cwd, where its new tabs and splits start; no args means a login "
+            + "shell); remove one. Open a pane with one through infinitty_new_tab's profile.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "action": ["type": "string", "enum": ["list", "save", "remove"]],
+                "name": ["type": "string"],
+                "shell": ["type": "string", "description": "Absolute path (default $SHELL)"],
+                "args": ["type": "array", "items": ["type": "string"]],
+                "env": ["type": "object", "additionalProperties": ["type": "string"]],
+                "startup": ["type": "array", "items": ["type": "string"]],
+                "cwd": ["type": "string"],
+                "newPaneDirectory": [
+                    "type": "string", "enum": ["home", "inherit", "workspace"],
+                    "description": "Overrides new-pane-directory: home (or cwd), the source pane's "
+                        + "directory, or its repo root",
+                ],
+            ],
+            "required": ["action"],
+        ],
+        invoke: { args in
+            switch args["action"] as? String {
+            case "save":
+                return fileOperation(
+                    "profile-save", args, keys: ["name", "shell", "args", "env", "startup", "cwd", "newPaneDirectory"])
+            case "remove":
+                return infinittyRequest("profile-remove \(args["name"] as? String ?? "")")
+            default:
+                return infinittyRequest("profiles")
+            }
+        }
+    ),
+    Tool(
+        name: "infinitty_project_dependencies",
+        description: "Scan a project's dependencies (Cargo, npm, pip/pyproject, Go modules, Bundler) "
+            + "as an infinitty background task. Returns the task at once; its result "
+            + "(infinitty_tasks) lists each dependency's declared requirement, resolved version, "
+            + "license and OSV advisories, with a summary of vulnerable and copyleft packages.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "dir": ["type": "string", "description": "Project directory (default: the focused pane's)"],
+                "offline": ["type": "boolean", "description": "Skip the OSV vulnerability lookup"],
+            ],
+        ],
+        invoke: { args in fileOperation("deps", args, keys: ["dir", "offline"]) }
+    ),
+    Tool(
+        name: "infinitty_service_map",
+        description: "What's reachable where: local TCP listeners (lsof), published Docker ports and "
+            + "Kubernetes services in one list, each with source, name, host/port and url when "
+            + "reachable from this Mac, or a kubectl port-forward command for ClusterIP services. "
+            + "sources says which tools answered.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("services") }
+    ),
+    Tool(
+        name: "infinitty_service_open",
+        description: "Open a service endpoint from infinitty_service_map in an infinitty Browser pane.",
+        schema: [
+            "type": "object",
+            "properties": ["endpoint": ["type": "string", "description": "Endpoint id or host port"]],
+            "required": ["endpoint"],
+        ],
+        invoke: { args in
+            let endpoint = (args["endpoint"] as? String) ?? (args["endpoint"] as? Int).map(String.init) ?? ""
+            return infinittyRequest("service-open \(endpoint)")
+        }
+    ),
+    Tool(
+        name: "infinitty_tasks",
+        description: "List infinitty background tasks (file copies, searches, clones, ...) with "
+            + "id, kind, state (running|succeeded|failed|cancelled), completed/total progress, "
+            + "and the result or error once finished. Live updates arrive as task-progress "
+            + "events via infinitty_events.",
+        schema: ["type": "object", "properties": [:]],
+        invoke: { _ in infinittyRequest("tasks") }
+    ),
+    Tool(
+        name: "infinitty_task_cancel",
+        description: "Cancel a running infinitty background task by id (from infinitty_tasks).",
+        schema: [
+            "type": "object",
+            "properties": ["task": ["type": "string", "description": "Task id, e.g. task-3"]],
+            "required": ["task"],
+        ],
+        invoke: { args in infinittyRequest("task-cancel \(args["task"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_cancel_operation",
+        description: "Cancel in-flight infinitty work by request id: a background task id "
+            + "(task-3) or the requestId a browser request was sent with. Work stops "
+            + "promptly instead of finishing unseen.",
+        schema: [
+            "type": "object",
+            "properties": ["requestId": ["type": "string", "description": "Task id or requestId"]],
+            "required": ["requestId"],
+        ],
+        invoke: { args in
+            infinittyRequest("cancel-operation \(args["requestId"] as? String ?? "")")
+        }
+    ),
+    Tool(
+        name: "infinitty_activity",
+        description: "Show a short status message in infinitty's notch live-activity widget.",
+        schema: [
+            "type": "object",
+            "properties": ["text": ["type": "string"]],
+            "required": ["text"],
+        ],
+        invoke: { args in infinittyRequest("activity \(args["text"] as? String ?? "")") }
+    ),
+    Tool(
+        name: "infinitty_browser_open",
+        description: "Open a native browser pane, or focus an existing one. By default this "
+            + "focuses the most recently opened browser pane in the target tab (creating one "
+            + "only if none exists); pass newPane=true to add another instance, or browserId "
+            + "to target a specific instance. Optionally navigate it to a URL. "
+            + "Use its browserId with the other infinitty_browser_* tools.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "url": ["type": "string", "description": "Optional URL to load"],
+                "anchorPane": [
+                    "type": "integer",
+                    "description": "Terminal pane whose tab should host the browser "
+                        + "(default: this server's pane, else the frontmost tab)",
+                ],
+                "browserId": [
+                    "type": "string",
+                    "description": "Focus this existing browser instance instead of the most recent one",
+                ],
+                "newPane": [
+                    "type": "boolean",
+                    "description": "Create an additional browser pane even if one already exists",
+                ],
+            ],
+        ],
+        invoke: { args in browserCall("open", arguments: args) }
+    ),
+    Tool(
+        name: "infinitty_browser_list",
+        description: "List browser pane instances with each instance's browserId, owning tab, "
+            + "current URL, title, loading state, and viewport mode. Only the browsers in one tab: "
+            + "anchorPane's, else the pane this server runs in, else the frontmost window's.",
+        schema: [
+            "type": "object",
+            "properties": [
+                "anchorPane": ["type": "integer", "description": "List the browsers in this pane's tab"],
+            ],
+        ],
+        invoke: { args in browserCall("list", arguments: args) }
+    ),
+    Tool(
+        name: "infinitty_browser_navigate",
+        description: "Navigate a browser panel to a URL and wait for the navigation result.",
+        schema: [
+            "type": "object",
+            "properties": browserIDProperty.merging([
+                "url": ["type": "string", "description": "Absolute URL, or a host/search-like URL"],
+            ]) { a, _ in a },
+            "required": ["browserId", "url"],
+        ],
+        invoke: { args in browserCall("navigate", arguments: args) }
+    ),
+    Tool(
+        name: "infinitty_browser_snapshot",
+        description: "Return a compact DOM-first snapshot of visible interactive elements. "
+            + "Use the returned ref values for click, type, or press; take a new snapshot after navigation.",
+        schema: [
+            "type": "object",
+            "properties": browserIDProperty.merging([
+                "maxNodes": [
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": 250,
+                    "description": "Maximum interactive elements to return (default 80)",
+                ],
+            ]) { a, _ in a },
+            "required": ["browserId"],
+        ],
+        invoke: { args in browserCall("snapshot", arguments: args) }
+    ),
+    Tool(
+        name: "infinitty_browser_click",
+        description: "Click an interactive element identified by a fresh browser snapshot ref.",
         schema: [
             "type": "object",
             "properties": browserSnapshotProperty.merging([
@@ -769,6 +2984,31 @@ let tools: [Tool] = [
         ],
         invoke: { args in browserCall("scroll", arguments: args) }
     ),
+    Tool(
+        name: "infinitty_browser_network_log",
+        description: "Requests a browser panel's page made while capture is on: its own loads (kind document) "
+            + "and fetch and XHR calls, each {id, kind, method, url, status, started, duration (ms), error?}. "
+            + "Pass capture true or false to switch logging on or off first (off by default); after returns "
+            + "only entries newer than that id (pass back next), and clear empties the log after reading.",
+        schema: [
+            "type": "object",
+            "properties": browserIDProperty.merging([
+                "capture": ["type": "boolean", "description": "Turn request logging on or off first"],
+                "after": ["type": "integer", "description": "Last entry id already seen"],
+                "clear": ["type": "boolean"],
+            ]) { a, _ in a },
+            "required": ["browserId"],
+        ],
+        invoke: { args in
+            if let capture = args["capture"] as? Bool {
+                var request = args.filter { ["browserId", "anchorPane"].contains($0.key) }
+                request["enabled"] = capture
+                let switched = browserCall("network-capture", arguments: request)
+                if isToolError(switched) { return switched }
+            }
+            return browserCall("network-log", arguments: args.filter { $0.key != "capture" })
+        }
+    ),
     Tool(
         name: "infinitty_browser_screenshot",
         description: "Capture the visible browser panel and return the local artifact path.",
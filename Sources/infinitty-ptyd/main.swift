@@ -0,0 +1,317 @@
+import CPty
+import Darwin
+import Foundation
+
+// infinitty-ptyd: keeps infinitty's shells alive across app restarts. The
+// app starts it when `daemon = true`; it forks each shell onto a pty it
+// owns and streams it to whichever app connection is attached, buffering
+// recent output while none is. It exits once its last shell has.
+//
+// Socket: /tmp/infinitty-ptyd-<uid>/ptyd.sock, in a directory only the user
+// can enter. A connection opens with one JSON line and gets one JSON line
+// back:
+//   {"op": "list"}                          -> {"pid", "sessions": [{id, pid, attached, started}]}
+//   {"op": "spawn", "cols", "rows", "cwd"?, "socket"?} -> {"id", "pid"}
+//     with "path", "argv" and "env" (["KEY=value"]) it runs exactly that
+//     instead of the login shell
+//   {"op": "attach", "id"}                  -> {"id", "pid"} or {"error"}
+// After spawn or attach the connection carries frames: a kind byte, a
+// big-endian UInt32 length, the payload. The app sends i (input), r
+// (resize: cols and rows as big-endian UInt16s) and k (hang up the shell);
+// the daemon sends o (output, starting with what was buffered) and x (the
+// shell exited; its wait status as a big-endian Int32).
+
+signal(SIGPIPE, SIG_IGN)
+signal(SIGHUP, SIG_IGN)
+// Out of the app's session, so quitting it doesn't take the shells along.
+setsid()
+
+let socketDirectory = "/tmp/infinitty-ptyd-\(getuid())"
+let socketPath = "\(socketDirectory)/ptyd.sock"
+/// Output kept for the next attach while no app is listening.
+let replayLimit = 512 * 1024
+/// How long the daemon outlives its last shell, for an app mid-relaunch.
+let idleExit: TimeInterval = 30
+
+final class Shell {
+    let id: Int
+    let pid: pid_t
+    let master: Int32
+    let started = Date()
+    let lock = NSLock()
+    var replay: [UInt8] = []
+    var client: Int32 = -1
+
+    init(id: Int, pid: pid_t, master: Int32) {
+        self.id = id
+        self.pid = pid
+        self.master = master
+    }
+}
+
+let lock = NSLock()
+var shells: [Int: Shell] = [:]
+var nextID = 1
+var lastActive = Date()
+
+func frame(_ kind: UInt8, _ payload: [UInt8]) -> [UInt8] {
+    let length = UInt32(payload.count)
+    return [kind, UInt8(length >> 24), UInt8(length >> 16 & 0xFF), UInt8(length >> 8 & 0xFF), UInt8(length & 0xFF)]
+        + payload
+}
+
+@discardableResult
+func writeAll(_ fd: Int32, _ bytes: [UInt8]) -> Bool {
+    bytes.withUnsafeBufferPointer { buffer -> Bool in
+        guard let base = buffer.baseAddress else { return true }
+        var offset = 0
+        while offset < buffer.count {
+            let written = write(fd, base + offset, buffer.count - offset)
+            if written > 0 {
+                offset += written
+            } else if written < 0, errno == EINTR {
+                continue
+            } else {
+                return false
+            }
+        }
+        return true
+    }
+}
+
+func readExactly(_ fd: Int32, _ count: Int) -> [UInt8]? {
+    var bytes = [UInt8](repeating: 0, count: count)
+    var offset = 0
+    while offset < count {
+        let n = bytes.withUnsafeMutableBufferPointer { read(fd, $0.baseAddress! + offset, count - offset) }
+        if n > 0 {
+            offset += n
+        } else if n < 0, errno == EINTR {
+            continue
+        } else {
+            return nil
+        }
+    }
+    return bytes
+}
+
+func reply(_ fd: Int32, _ object: [String: Any]) {
+    let data = (try? JSONSerialization.data(withJSONObject: object)) ?? Data("{}".utf8)
+    writeAll(fd, Array(data) + [0x0A])
+}
+
+/// Copies the shell's output to its client, or into the replay buffer for
+/// the next one, until the shell exits.
+func pump(_ shell: Shell) {
+    var buffer = [UInt8](repeating: 0, count: 1 << 16)
+    while true {
+        let n = read(shell.master, &buffer, buffer.count)
+        if n < 0, errno == EINTR { continue }
+        guard n > 0 else { break }
+        let chunk = Array(buffer[..<n])
+        shell.lock.lock()
+        shell.replay += chunk
+        if shell.replay.count > replayLimit { shell.replay.removeFirst(shell.replay.count - replayLimit) }
+        if shell.client >= 0, !writeAll(shell.client, frame(UInt8(ascii: "o"), chunk)) {
+            // A client that can't keep up is dropped; it reattaches.
+            shutdown(shell.client, SHUT_RDWR)
+            shell.client = -1
+        }
+        shell.lock.unlock()
+    }
+    var status: Int32 = 0
+    waitpid(shell.pid, &status, 0)
+    let code = UInt32(bitPattern: status)
+    shell.lock.lock()
+    if shell.client >= 0 {
+        writeAll(shell.client, frame(UInt8(ascii: "x"), [24, 16, 8, 0].map { UInt8(code >> $0 & 0xFF) }))
+        shutdown(shell.client, SHUT_RDWR)
+    }
+    shell.lock.unlock()
+    close(shell.master)
+    lock.lock()
+    shells.removeValue(forKey: shell.id)
+    lastActive = Date()
+    lock.unlock()
+}
+
+/// Makes `fd` the shell's client, replays what it missed, then carries
+/// its input until it goes away or another attach takes over.
+func serve(_ fd: Int32, for shell: Shell) {
+    shell.lock.lock()
+    if shell.client >= 0 { shutdown(shell.client, SHUT_RDWR) }
+    shell.client = fd
+    reply(fd, ["id": shell.id, "pid": Int(shell.pid)])
+    if !shell.replay.isEmpty { writeAll(fd, frame(UInt8(ascii: "o"), shell.replay)) }
+    shell.lock.unlock()
+    while let header = readExactly(fd, 5) {
+        let length = Int(header[1]) << 24 | Int(header[2]) << 16 | Int(header[3]) << 8 | Int(header[4])
+        guard length <= 1 << 20, let payload = readExactly(fd, length) else { break }
+        switch header[0] {
+        case UInt8(ascii: "i"):
+            writeAll(shell.master, payload)
+        case UInt8(ascii: "r") where payload.count == 4:
+            let cols = UInt16(payload[0]) << 8 | UInt16(payload[1])
+            let rows = UInt16(payload[2]) << 8 | UInt16(payload[3])
+            _ = cpty_set_winsize(shell.master, rows, cols, 0, 0)
+        case UInt8(ascii: "k"):
+            kill(shell.pid, SIGHUP)
+        default:
+            break
+        }
+    }
+    shell.lock.lock()
+    if shell.client == fd { shell.client = -1 }
+    shell.lock.unlock()
+}
+
+func handle(_ fd: Int32) {
+    var timeout = timeval(tv_sec: 5, tv_usec: 0)
+    setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &timeout, socklen_t(MemoryLayout<timeval>.size))
+    setsockopt(fd, SOL_SOCKET, SO_SNDTIMEO, &timeout, socklen_t(MemoryLayout<timeval>.size))
+    var line: [UInt8] = []
+    // Spawn requests carry the shell's whole environment.
+    while line.count < 1 << 20, let byte = readExactly(fd, 1), byte[0] != 0x0A { line += byte }
+    guard let request = (try? JSONSerialization.jsonObject(with: Data(line))) as? [String: Any] else { return }
+    switch request["op"] as? String {
+    case "list":
+        lock.lock()
+        let all = shells.values.sorted { $0.id < $1.id }
+        lock.unlock()
+        reply(fd, ["pid": Int(getpid()), "sessions": all.map { shell -> [String: Any] in
+            shell.lock.lock()
+            defer { shell.lock.unlock() }
+            return [
+                "id": shell.id, "pid": Int(shell.pid), "attached": shell.client >= 0,
+                "started": shell.started.timeIntervalSince1970,
+            ]
+        }])
+    case "spawn":
+        var size = winsize(
+            ws_row: UInt16(clamping: request["rows"] as? Int ?? 32),
+            ws_col: UInt16(clamping: request["cols"] as? Int ?? 120),
+            ws_xpixel: 0, ws_ypixel: 0)
+        var master: Int32 = -1
+        let cwd = request["cwd"] as? String
+        let pid: pid_t
+        if let path = request["path"] as? String, let argv = request["argv"] as? [String], !argv.isEmpty,
+           let env = request["env"] as? [String] {
+            let cArgv = argv.map { strdup($0) } + [nil]
+            let cEnv = env.map { strdup($0) } + [nil]
+            pid = cpty_spawn(&master, &size, path, cArgv, cEnv, cwd)
+            (cArgv + cEnv).forEach { free($0) }
+        } else {
+            pid = cpty_spawn_shell(&master, &size, request["socket"] as? String, cwd)
+        }
+        guard pid > 0, master >= 0 else { return reply(fd, ["error": "could not spawn a shell"]) }
+        _ = fcntl(master, F_SETFD, FD_CLOEXEC)
+        lock.lock()
+        let shell = Shell(id: nextID, pid: pid, master: master)
+        nextID += 1
+        shells[shell.id] = shell
+        lock.unlock()
+        Thread.detachNewThread { pump(shell) }
+        clearTimeouts(fd)
+        serve(fd, for: shell)
+    case "attach":
+        lock.lock()
+        let shell = (request["id"] as? Int).flatMap { shells[$0] }
+        lock.unlock()
+        guard let shell else { return reply(fd, ["error": "no session \(request["id"] ?? "")"]) }
+        clearTimeouts(fd)
+        serve(fd, for: shell)
+    default:
+        reply(fd, ["error": "unknown op"])
+    }
+}
+
+/// A stream's reads wait as long as the user does; writes get a few
+/// seconds before a stuck app is dropped.
+func clearTimeouts(_ fd: Int32) {
+    var none = timeval(tv_sec: 0, tv_usec: 0)
+    setsockopt(fd, SOL_SOCKET, SO_RCVTIMEO, &none, socklen_t(MemoryLayout<timeval>.size))
+}
+
+/// Creates `path` 0700, or accepts it if it already is that and ours — a
+/// directory another user made first must not get to hold our socket.
+func ownPrivateDirectory(_ path: String) -> Bool {
+    if mkdir(path, 0o700) != 0, errno != EEXIST { return false }
+    var info = stat()
+    guard lstat(path, &info) == 0 else { return false }
+    return (info.st_mode & S_IFMT) == S_IFDIR && info.st_uid == getuid() && info.st_mode & 0o077 == 0
+}
+
+func listen(at path: String) -> Int32 {
+    let fd = socket(AF_UNIX, SOCK_STREAM, 0)
+    guard fd >= 0 else { return -1 }
+    var addr = sockaddr_un()
+    addr.sun_family = sa_family_t(AF_UNIX)
+    let capacity = MemoryLayout.size(ofValue: addr.sun_path)
+    let ok = withUnsafeMutablePointer(to: &addr.sun_path) { tuple -> Bool in
+        tuple.withMemoryRebound(to: CChar.self, capacity: capacity) { dst in
+            let bytes = Array(path.utf8)
+            guard bytes.count < capacity else { return false }
+            for (i, b) in bytes.enumerated() { dst[i] = CChar(bitPattern: b) }
+            dst[bytes.count] = 0
+            return true
+        }
+    }
+    guard ok else {
+        close(fd)
+        return -1
+    }
+    // umask around bind: the socket hands out shells, so it's never
+    // world-usable — but the shells forked later keep the user's umask.
+    let umaskBefore = umask(0o077)
+    unlink(path)
+    let len = socklen_t(MemoryLayout<sockaddr_un>.size)
+    let bound = withUnsafePointer(to: &addr) {
+        $0.withMemoryRebound(to: sockaddr.self, capacity: 1) { bind(fd, $0, len) }
+    }
+    umask(umaskBefore)
+    guard bound == 0, Darwin.listen(fd, 16) == 0 else {
+        close(fd)
+        return -1
+    }
+    _ = fcntl(fd, F_SETFD, FD_CLOEXEC)
+    return fd
+}
+
+guard ownPrivateDirectory(socketDirectory) else {
+    FileHandle.standardError.write(Data("infinitty-ptyd: \(socketDirectory) isn't a private directory of ours\n".utf8))
+    exit(1)
+}
+let listener = listen(at: socketPath)
+guard listener >= 0 else {
+    FileHandle.standardError.write(Data("infinitty-ptyd: cannot listen at \(socketPath)\n".utf8))
+    exit(1)
+}
+
+Thread.detachNewThread {
+    while true {
+        Thread.sleep(forTimeInterval: 5)
+        lock.lock()
+        let idle = shells.isEmpty && Date().timeIntervalSince(lastActive) > idleExit
+        lock.unlock()
+        if idle {
+            unlink(socketPath)
+            exit(0)
+        }
+    }
+}
+
+while true {
+    let client = accept(listener, nil, nil)
+    if client < 0 {
+        if errno == EINTR { continue }
+        break
+    }
+    _ = fcntl(client, F_SETFD, FD_CLOEXEC)
+    lock.lock()
+    lastActive = Date()
+    lock.unlock()
+    Thread.detachNewThread {
+        handle(client)
+        close(client)
+    }
+}
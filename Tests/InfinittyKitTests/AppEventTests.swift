@@ -0,0 +1,90 @@
+import XCTest
+@testable import InfinittyKit
+
+final class AppEventTests: XCTestCase {
+
+    /// Payloads keep the wire names existing subscribers parse, and a
+    /// targeted event carries its tab id.
+    func testPayloadEncodesNameFieldsAndTab() {
+        let marker = AppEvent.marker(pane: 3, kind: UInt8(ascii: "D"), exit: 1).payload(tab: "tab-2")
+        XCTAssertEqual(marker["event"] as? String, "marker")
+        XCTAssertEqual(marker["pane"] as? Int, 3)
+        XCTAssertEqual(marker["kind"] as? String, "D")
+        XCTAssertEqual(marker["exit"] as? Int, 1)
+        XCTAssertEqual(marker["tab"] as? String, "tab-2")
+
+        XCTAssertNil(AppEvent.paneOpened(pane: 1).payload()["tab"])
+        XCTAssertEqual(AppEvent.paneDetached(pane: 4).payload()["event"] as? String, "pane-detached")
+        XCTAssertEqual(AppEvent.paneAttached(pane: 4).payload()["pane"] as? Int, 4)
+        let zoom = AppEvent.layoutChanged(reason: "zoom", pane: nil, surface: "browser-2").payload()
+        XCTAssertEqual(zoom["event"] as? String, "layout-changed")
+        XCTAssertEqual(zoom["reason"] as? String, "zoom")
+        XCTAssertEqual(zoom["surface"] as? String, "browser-2")
+        XCTAssertNil(zoom["pane"])
+        let finished = AppEvent.command(
+            pane: 2, .finished(command: "make", exitCode: 1, duration: 1.5, line: 40)).payload()
+        XCTAssertEqual(finished["event"] as? String, "command-finished")
+        XCTAssertEqual(finished["exitCode"] as? Int, 1)
+        XCTAssertEqual(finished["duration"] as? Double, 1.5)
+        XCTAssertEqual(AppEvent.command(pane: 2, .started(command: nil, line: 3)).name, "command-started")
+        let frame = AppEvent.replayFrame(task: "task-2", time: 1.5, kind: "o", data: "hi").payload()
+        XCTAssertEqual(frame["event"] as? String, "replay-frame")
+        XCTAssertEqual(frame["time"] as? Double, 1.5)
+        XCTAssertEqual(frame["data"] as? String, "hi")
+        let trigger = Trigger(id: "trigger-1", pattern: "ERROR", action: .event, pane: nil)
+        let matched = AppEvent.trigger(TriggerMatch(
+            trigger: trigger, pane: 4, line: 12, start: 0, end: 4, text: "ERROR", groups: [])).payload()
+        XCTAssertEqual(matched["event"] as? String, "trigger")
+        XCTAssertEqual(matched["trigger"] as? String, "trigger-1")
+        XCTAssertEqual(matched["line"] as? Int, 12)
+        let link = DetectedLink(kind: .url, line: 3, columns: 0...9, text: "https://a")
+        let links = AppEvent.detectedLinks(pane: 4, links: [link]).payload()
+        XCTAssertEqual(links["event"] as? String, "detected-links")
+        XCTAssertEqual((links["links"] as? [[String: Any]])?.first?["kind"] as? String, "url")
+        let cwd = AppEvent.directory(pane: 5, cwd: "/src/api", source: "osc7").payload()
+        XCTAssertEqual(cwd["event"] as? String, "cwd")
+        XCTAssertEqual(cwd["cwd"] as? String, "/src/api")
+        XCTAssertEqual(cwd["source"] as? String, "osc7")
+        let ready = AppEvent.subsystemReady(.notch, error: nil).payload()
+        XCTAssertEqual(ready["subsystem"] as? String, "notch")
+        XCTAssertNil(ready["error"])
+    }
+
+    /// Browser pane events gain the pane's browserId; load state shares one
+    /// event name across start and failure.
+    func testBrowserEventsAreWrappedWithTheirBrowserID() {
+        let failed = AppEvent.browser(browserId: "browser-1", .loadFailed(error: "offline")).payload()
+        XCTAssertEqual(failed["event"] as? String, "browser-load-state")
+        XCTAssertEqual(failed["browserId"] as? String, "browser-1")
+        XCTAssertEqual(failed["loading"] as? Bool, false)
+        XCTAssertEqual(failed["error"] as? String, "offline")
+        XCTAssertEqual(BrowserPaneEvent.loadStarted.name, "browser-load-state")
+        XCTAssertEqual(BrowserPaneEvent.inspectorArmed(false).name, "browser-inspector-disarmed")
+    }
+
+    func testTaskProgressRenamesIDToTask() {
+        let payload = AppEvent.taskProgress(["id": "task-4", "state": "running"]).payload()
+        XCTAssertEqual(payload["task"] as? String, "task-4")
+        XCTAssertNil(payload["id"])
+        XCTAssertEqual(payload["event"] as? String, "task-progress")
+    }
+
+    func testOutputFloodPayloads() {
+        let started = AppEvent.outputFlood(pane: 3, .started).payload()
+        XCTAssertEqual(started["event"] as? String, "output-flood")
+        XCTAssertEqual(started["flooding"] as? Bool, true)
+        let ended = AppEvent.outputFlood(pane: 3, .ended(bytes: 4096, duration: 2)).payload()
+        XCTAssertEqual(ended["flooding"] as? Bool, false)
+        XCTAssertEqual(ended["bytes"] as? Int, 4096)
+        XCTAssertEqual(ended["seconds"] as? Double, 2)
+    }
+
+    /// A tab-filtered subscriber gets its own tab's events and app-wide
+    /// ones, never another window's.
+    func testTabFiltering() {
+        XCTAssertTrue(AppEvent.delivers(tab: "tab-1", to: nil))
+        XCTAssertTrue(AppEvent.delivers(tab: nil, to: "tab-1"))
+        XCTAssertTrue(AppEvent.delivers(tab: "tab-1", to: "tab-1"))
+        XCTAssertFalse(AppEvent.delivers(tab: "tab-2", to: "tab-1"))
+    }
+}
@@ -0,0 +1,22 @@
+import XCTest
+@testable import InfinittyKit
+
+final class AppSupportTests: XCTestCase {
+    /// A store's file round-trips with its date format, creating its folder
+    /// and taking the permissions asked for.
+    func testJSONFileStoreRoundTrips() throws {
+        let dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: dir) }
+        let file = JSONFileStore<[String: Date]>(
+            url: dir.appendingPathComponent("nested/store.json"), dates: .secondsSince1970, permissions: 0o600)
+        XCTAssertNil(file.load())
+
+        let value = ["a": Date(timeIntervalSince1970: 1_700_000_000)]
+        XCTAssertTrue(file.save(value))
+        XCTAssertEqual(file.load(), value)
+        let saved = try XCTUnwrap(JSONSerialization.jsonObject(with: Data(contentsOf: file.url)) as? [String: Any])
+        XCTAssertEqual(saved["a"] as? Double, 1_700_000_000)
+        let attributes = try FileManager.default.attributesOfItem(atPath: file.url.path)
+        XCTAssertEqual((attributes[.posixPermissions] as? NSNumber)?.intValue, 0o600)
+    }
+}
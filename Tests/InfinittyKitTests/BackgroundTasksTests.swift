@@ -0,0 +1,76 @@
+import XCTest
+@testable import InfinittyKit
+
+final class BackgroundTasksTests: XCTestCase {
+
+    /// A finished task reports its result, and subscribers see the running
+    /// and terminal states as task-progress events.
+    func testTaskSucceedsAndEmitsProgressEvents() {
+        let registry = BackgroundTaskRegistry()
+        let finished = expectation(description: "finished")
+        var states: [String] = []
+        let lock = NSLock()
+        registry.onEvent = { appEvent in
+            let event = appEvent.payload()
+            XCTAssertEqual(event["event"] as? String, "task-progress")
+            lock.lock()
+            states.append(event["state"] as? String ?? "")
+            lock.unlock()
+            if event["state"] as? String == "succeeded" { finished.fulfill() }
+        }
+        let task = registry.start(kind: "copy", title: "a → b") { task in
+            task.report(completed: 1, total: 1)
+            return ["entries": 1]
+        }
+        wait(for: [finished], timeout: 2)
+        XCTAssertEqual(task.currentState, .succeeded)
+        XCTAssertEqual(states.first, "running")
+        let listed = registry.list().first { $0["id"] as? String == task.id }
+        XCTAssertEqual((listed?["result"] as? [String: Any])?["entries"] as? Int, 1)
+        XCTAssertEqual(listed?["total"] as? Int64, 1)
+    }
+
+    /// Cancellation is cooperative: the work sees `isCancelled` and the task
+    /// ends cancelled rather than succeeded. Finished tasks can't be cancelled.
+    func testCancelStopsCooperativeWork() {
+        let registry = BackgroundTaskRegistry()
+        let started = DispatchSemaphore(value: 0)
+        let done = expectation(description: "done")
+        registry.onEvent = { appEvent in
+            let event = appEvent.payload()
+            if event["state"] as? String == "cancelled" { done.fulfill() }
+        }
+        let task = registry.start(kind: "search", title: "loop") { task in
+            started.signal()
+            while !task.isCancelled { usleep(1000) }
+            return nil
+        }
+        XCTAssertEqual(started.wait(timeout: .now() + 2), .success)
+        XCTAssertTrue(registry.cancel(task.id))
+        wait(for: [done], timeout: 2)
+        XCTAssertEqual(task.currentState, .cancelled)
+        XCTAssertFalse(registry.cancel(task.id))
+        XCTAssertFalse(registry.cancel("task-999"))
+    }
+
+    func testThrowingWorkMarksTaskFailed() {
+        struct Boom: Error, CustomStringConvertible { var description: String { "boom" } }
+        let registry = BackgroundTaskRegistry()
+        let done = expectation(description: "done")
+        registry.onEvent = { appEvent in
+            let event = appEvent.payload()
+            if event["state"] as? String == "failed" { done.fulfill() }
+        }
+        let task = registry.start(kind: "clone", title: "x") { _ in throw Boom() }
+        wait(for: [done], timeout: 2)
+        XCTAssertEqual(task.snapshot()["error"] as? String, "boom")
+    }
+
+    func testIDsAreUniqueAndSequential() {
+        let registry = BackgroundTaskRegistry()
+        let a = registry.start(kind: "k", title: "a") { _ in nil }
+        let b = registry.start(kind: "k", title: "b") { _ in nil }
+        XCTAssertEqual(a.id, "task-1")
+        XCTAssertEqual(b.id, "task-2")
+    }
+}
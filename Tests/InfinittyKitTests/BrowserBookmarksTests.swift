@@ -0,0 +1,57 @@
+import XCTest
+@testable import InfinittyKit
+
+final class BrowserBookmarksTests: XCTestCase {
+    private var url: URL!
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("bookmarks-\(UUID().uuidString).json")
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    /// Bookmarks keep their order per workspace and survive a reload;
+    /// addresses without a scheme get https.
+    func testBookmarksPersistInOrderPerWorkspace() throws {
+        let store = BrowserBookmarks(url: url)
+        let docs = try store.add(" docs.rs/tokio ", folder: " Docs / ", to: "/src/a").get()
+        XCTAssertEqual(docs.url, "https://docs.rs/tokio")
+        XCTAssertEqual(docs.folder, "Docs")
+        XCTAssertEqual(docs.label, "docs.rs/tokio")
+        XCTAssertEqual(try store.add("http://localhost:3000", title: "App", to: "/src/a").get().id, "2")
+        XCTAssertEqual(try store.add("https://grafana.example.com", to: "/src/b").get().id, "1")
+        let ftp = "ftp://files.example.com"
+        XCTAssertEqual(store.add(ftp, to: "/src/a"), .failure(.invalidURL(ftp)))
+        XCTAssertEqual(store.add("  ", to: "/src/a"), .failure(.invalidURL("  ")))
+
+        XCTAssertEqual(try store.move("2", to: 0, in: "/src/a").get().map(\.id), ["2", "1"])
+        XCTAssertNil(try store.edit("2", in: "/src/a", title: "").get().title)
+        XCTAssertEqual(store.edit("9", in: "/src/a", title: "x"), .failure(.notFound("9")))
+
+        let reloaded = BrowserBookmarks(url: url)
+        XCTAssertEqual(reloaded.bookmarks(for: "/src/a").map(\.url), ["http://localhost:3000", "https://docs.rs/tokio"])
+        XCTAssertEqual(try reloaded.remove("1", in: "/src/b").get().url, "https://grafana.example.com")
+        let saved = try XCTUnwrap(JSONSerialization.jsonObject(with: Data(contentsOf: url)) as? [String: Any])
+        XCTAssertEqual(saved.keys.sorted(), ["/src/a"])
+    }
+
+    /// Renaming a folder carries its subfolders; removing one takes
+    /// everything under it and nothing beside it.
+    func testFoldersRenameAndRemoveWithSubfolders() throws {
+        let store = BrowserBookmarks(url: url)
+        _ = try store.add("https://a.example.com", folder: "Docs", to: "/src/a").get()
+        _ = try store.add("https://b.example.com", folder: "Docs/API", to: "/src/a").get()
+        _ = try store.add("https://c.example.com", folder: "Docsite", to: "/src/a").get()
+        XCTAssertEqual(store.folders(for: "/src/a"), ["Docs", "Docs/API", "Docsite"])
+
+        let renamed = try store.renameFolder("Docs", to: "Reference", in: "/src/a").get()
+        XCTAssertEqual(renamed.map(\.folder), ["Reference", "Reference/API", "Docsite"])
+        XCTAssertEqual(store.renameFolder("Docs", to: "x", in: "/src/a"), .failure(.folderNotFound("Docs")))
+        XCTAssertNil(try store.renameFolder("Reference/API", to: "", in: "/src/a").get()[1].folder)
+
+        XCTAssertEqual(try store.removeFolder("Reference", in: "/src/a").get().map(\.id), ["1"])
+        XCTAssertEqual(store.bookmarks(for: "/src/a").map(\.id), ["2", "3"])
+    }
+}
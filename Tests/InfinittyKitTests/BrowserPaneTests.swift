@@ -194,6 +194,25 @@ final class BrowserPaneTests: XCTestCase {
         XCTAssertEqual(controller.view.subviews.filter { $0 is NSVisualEffectView }.count, 2)
     }
 
+    /// Until its first page has painted, the web view is see-through over
+    /// the theme's backdrop instead of opaque white.
+    func testWebViewStartsTransparentOverTheBackdrop() throws {
+        _ = NSApplication.shared
+        let controller = BrowserPaneController(dataStore: .nonPersistent())
+        let backdrop = NSColor(srgbRed: 0.1, green: 0.1, blue: 0.12, alpha: 1)
+        controller.backdrop = backdrop
+        controller.loadViewIfNeeded()
+
+        let webView = try XCTUnwrap(controller.view.subviews.compactMap { $0 as? WKWebView }.first)
+        XCTAssertEqual(webView.value(forKey: "drawsBackground") as? Bool, false)
+        XCTAssertEqual(webView.underPageBackgroundColor, backdrop)
+        XCTAssertEqual(controller.view.layer?.backgroundColor, backdrop.cgColor)
+
+        let lighter = NSColor(srgbRed: 0.9, green: 0.9, blue: 0.9, alpha: 1)
+        controller.backdrop = lighter
+        XCTAssertEqual(webView.underPageBackgroundColor, lighter)
+    }
+
     func testInspectorStateCommandUsesTopLevelReturnAndNamedArguments() {
         let script = BrowserPaneController.inspectorStateScript
         XCTAssertTrue(script.contains("return true"))
@@ -234,4 +253,148 @@ final class BrowserPaneTests: XCTestCase {
         XCTAssertTrue(script.contains("return true"))
         XCTAssertFalse(script.contains("innerHTML"))
     }
+
+    func testNetworkLogRecordsPageMessagesAndPages() {
+        var log = BrowserNetworkLog()
+        let fetched = log.record(message: [
+            "kind": "fetch", "method": "post", "url": "https://example.com/api", "status": 201,
+            "started": 1_000.5, "duration": 12.25,
+        ])
+        XCTAssertEqual(fetched, BrowserNetworkEntry(
+            id: 1, kind: "fetch", method: "POST", url: "https://example.com/api", status: 201, started: 1_000.5,
+            duration: 12.25, error: nil))
+        XCTAssertNil(log.record(message: ["kind": "document", "url": "https://example.com/"]))
+        XCTAssertNil(log.record(message: ["kind": "xhr"]))
+        XCTAssertNil(log.record(message: "not a dictionary"))
+        let failed = log.record(message: ["kind": "xhr", "url": "https://example.com/x", "error": "request failed"])
+        XCTAssertEqual(failed?.status, 0)
+        XCTAssertEqual(failed?.method, "GET")
+        XCTAssertEqual(log.entries(after: 1).map(\.id), [2])
+
+        log.clear()
+        XCTAssertTrue(log.entries.isEmpty)
+        let next = log.record(
+            kind: "document", method: "GET", url: "https://example.com/", status: 200, started: 0, duration: 3)
+        XCTAssertEqual(next.id, 3)
+        for n in 0..<BrowserNetworkLog.maxEntries {
+            log.record(
+                kind: "fetch", method: "GET", url: "https://example.com/\(n)", status: 200, started: 0, duration: 1)
+        }
+        XCTAssertEqual(log.entries.count, BrowserNetworkLog.maxEntries)
+        XCTAssertEqual(log.dropped, 1)
+        XCTAssertEqual(log.entries.first?.id, 4)
+        XCTAssertEqual(log.lastID, 3 + BrowserNetworkLog.maxEntries)
+
+        let path = String(repeating: "a", count: 4_000)
+        let long = log.record(
+            kind: "fetch", method: "GET", url: "https://example.com/" + path, status: 200, started: 0, duration: 1)
+        XCTAssertTrue(long.url.hasSuffix("…"))
+        XCTAssertLessThanOrEqual(long.url.utf8.count, BrowserNetworkLog.maxURLBytes + 3)
+    }
+
+    func testNetworkCaptureIsOffUntilAskedFor() {
+        _ = NSApplication.shared
+        let controller = BrowserPaneController(dataStore: .nonPersistent())
+        controller.loadViewIfNeeded()
+        XCTAssertEqual(controller.controlState()["networkCapture"] as? Bool, false)
+        controller.setNetworkCapture(true)
+        XCTAssertTrue(controller.capturesNetwork)
+        XCTAssertEqual(controller.controlState()["networkCapture"] as? Bool, true)
+        controller.setNetworkCapture(false)
+        XCTAssertFalse(controller.capturesNetwork)
+    }
+
+    /// Generated ids skip every id already live or persisted, so a new pane
+    /// can never take over another pane's automation target.
+    func testBrowserIDGenerationSkipsTakenIDs() {
+        var draws = ["AAAAAAAA-0000", "AAAAAAAA-1111", "BBBBBBBB-2222"].makeIterator()
+        let id = BrowserPaneController.makeBrowserID(avoiding: ["browser-aaaaaaaa"]) {
+            draws.next()!
+        }
+        XCTAssertEqual(id, "browser-bbbbbbbb")
+    }
+
+    private final class Window {}
+
+    private func owner(
+        anchorPane: Int? = nil, panes: [Int: Window] = [:], key: Window? = nil,
+        ordered: [Window] = [], main: [Window] = []
+    ) -> Result<Window?, BrowserControlOwnership.Failure> {
+        BrowserControlOwnership.owner(
+            anchorPane: anchorPane, windowOfPane: { panes[$0] }, keyWindow: key,
+            orderedWindows: ordered, isMainWindow: { window in main.contains { $0 === window } })
+    }
+
+    /// anchorPane picks the tab; without it the key main window does, and
+    /// with infinitty in the background its frontmost main window.
+    func testBrowserOwnerFallsBackToTheFrontmostMainWindow() throws {
+        let first = Window(), second = Window(), quick = Window()
+        XCTAssertTrue(try owner(anchorPane: 3, panes: [3: second], key: first, main: [first, second]).get() === second)
+        XCTAssertTrue(try owner(key: first, ordered: [second, first], main: [first, second]).get() === first)
+        XCTAssertTrue(try owner(key: quick, ordered: [quick, second], main: [first, second]).get() === second)
+        XCTAssertTrue(try owner(ordered: [quick, second, first], main: [first, second]).get() === second)
+        XCTAssertNil(try owner(ordered: [quick], main: [first]).get())
+        XCTAssertEqual(owner(anchorPane: 9, panes: [3: first], key: first, main: [first]).failure, .unknownPane)
+    }
+
+    /// A browser is driven only for the tab that hosts it.
+    func testBrowserOwnershipRefusesOtherTabsAndMissingOwner() {
+        let mine = Window(), theirs = Window()
+        XCTAssertNil(BrowserControlOwnership.check("browser-a", in: mine, owner: mine).failure)
+        let foreign = BrowserControlOwnership.check("browser-b", in: theirs, owner: mine).failure
+        XCTAssertEqual(foreign, .notOwned(browserID: "browser-b"))
+        XCTAssertEqual(foreign?.code, "browser_not_owned")
+        let ownerless = BrowserControlOwnership.check("browser-a", in: mine, owner: nil as Window?).failure
+        XCTAssertEqual(ownerless, .noOwner)
+        XCTAssertEqual(ownerless?.code, "no_owner")
+    }
+
+    private func sessionStore() -> BrowserPaneSessionStore {
+        let suite = "infinitty-browser-sessions-\(UUID().uuidString)"
+        let defaults = UserDefaults(suiteName: suite)!
+        addTeardownBlock { defaults.removePersistentDomain(forName: suite) }
+        return BrowserPaneSessionStore(defaults: defaults)
+    }
+
+    private func sessionRecord(_ id: String, url: String, age: TimeInterval = 0) -> BrowserPaneSessionStore.Record {
+        BrowserPaneSessionStore.Record(
+            browserID: id, url: url, title: id, viewport: .mobile,
+            updatedAt: Date().timeIntervalSince1970 - age)
+    }
+
+    /// Records are looked up by the browser they belong to, newest
+    /// location winning, and orphans stay until pruned.
+    func testBrowserSessionStoreKeepsEachPanesLatestLocation() {
+        let store = sessionStore()
+        store.save(sessionRecord("browser-old", url: "https://old.example", age: 60))
+        store.save(sessionRecord("browser-new", url: "https://new.example", age: 10))
+        store.save(sessionRecord("browser-new", url: "https://newer.example"))
+
+        XCTAssertEqual(store.record("browser-new")?.url, "https://newer.example")
+        XCTAssertEqual(store.record("browser-new")?.viewport, .mobile)
+        XCTAssertEqual(store.record("browser-old")?.url, "https://old.example")
+        XCTAssertNil(store.record("browser-gone"))
+        XCTAssertEqual(store.reconcile(live: ["browser-new"]).map(\.browserID), ["browser-old"])
+        XCTAssertEqual(store.records().map(\.browserID), ["browser-new", "browser-old"])
+    }
+
+    func testBrowserSessionStorePrunesStaleAndUnsafeRecords() {
+        let store = sessionStore()
+        store.save(sessionRecord("browser-stale", url: "https://stale.example",
+                                 age: BrowserPaneSessionStore.maxAge + 1))
+        store.save(sessionRecord("browser-file", url: "file:///etc/passwd"))
+        store.save(sessionRecord("browser-ok", url: "https://ok.example"))
+
+        XCTAssertEqual(store.reconcile(live: []).map(\.browserID), ["browser-ok"])
+        XCTAssertEqual(store.records().map(\.browserID), ["browser-ok"])
+        store.remove("browser-ok")
+        XCTAssertTrue(store.records().isEmpty)
+    }
+}
+
+private extension Result {
+    var failure: Failure? {
+        if case .failure(let error) = self { return error }
+        return nil
+    }
 }
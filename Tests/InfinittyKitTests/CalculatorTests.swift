@@ -0,0 +1,64 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CalculatorTests: XCTestCase {
+
+    private func text(_ expression: String, rates: [String: Double] = [:]) -> String? {
+        try? Calculator.evaluate(expression, rates: rates).get().text
+    }
+
+    /// Precedence, right-associative powers, percentages and the literal
+    /// forms a terminal user pastes.
+    func testArithmetic() {
+        XCTAssertEqual(text("2 + 3 * 4"), "14")
+        XCTAssertEqual(text("-2^2"), "-4")
+        XCTAssertEqual(text("2^3^2"), "512")
+        XCTAssertEqual(text("(1 + 2) × 3 mod 4"), "1")
+        XCTAssertEqual(text("15% of 80"), "12")
+        XCTAssertEqual(text("0xff + 0b101"), "260")
+        XCTAssertEqual(text("1,234.5 * 2"), "2469")
+        XCTAssertEqual(text("sqrt(2)"), "1.414213562")
+        XCTAssertEqual(text("255 in hex"), "0xff")
+        XCTAssertEqual(Calculator.evaluate("1/0", rates: [:]), .failure(.domain("division by zero")))
+        XCTAssertEqual(Calculator.evaluate("2 +", rates: [:]), .failure(.syntax("expression ends early")))
+    }
+
+    func testUnitConversions() {
+        XCTAssertEqual(text("1536 MiB in GB"), "1.610612736 GB")
+        XCTAssertEqual(text("100 km/h in mph"), "62.13711922 mph")
+        XCTAssertEqual(text("100 Mbit/s to MB/s"), "12.5 MB/s")
+        XCTAssertEqual(text("212 °F in C"), "100 °C")
+        XCTAssertEqual(text("5 feet as m"), "1.524 m")
+        XCTAssertEqual(text("1 GB + 512 MB"), "1.512 GB")
+        XCTAssertEqual(text("1 GiB / 1 GB"), "1.073741824")
+    }
+
+    func testIncompatibleUnits() {
+        XCTAssertEqual(Calculator.evaluate("3 GB in kg", rates: [:]), .failure(.cannotConvert("GB", "kg")))
+        XCTAssertEqual(Calculator.evaluate("2 GB * 3 GB", rates: [:]), .failure(.incompatible("GB", "GB")))
+        XCTAssertEqual(Calculator.evaluate("5 parsecs", rates: [:]), .failure(.unknownUnit("parsecs")))
+    }
+
+    /// Currencies convert through the common base; a known code with no
+    /// cached rate is reported as such rather than as an unknown unit.
+    func testCurrency() throws {
+        let rates = ["EUR": 1, "USD": 1.08, "GBP": 0.85]
+        let result = try Calculator.evaluate("54 usd in eur", rates: rates).get()
+        XCTAssertEqual(result.text, "50.00 EUR")
+        XCTAssertTrue(result.usesCurrency)
+        XCTAssertEqual(text("$10 in gbp", rates: rates), "7.87 GBP")
+        XCTAssertEqual(Calculator.evaluate("10 chf in eur", rates: rates), .failure(.noRate("CHF")))
+    }
+
+    func testParseECB() throws {
+        let xml = """
+            <gesmes:Envelope><Cube><Cube time='2026-10-15'>
+            <Cube currency='USD' rate='1.0812'/><Cube currency='JPY' rate='162.43'/>
+            </Cube></Cube></gesmes:Envelope>
+            """
+        let table = try XCTUnwrap(CurrencyRates.parseECB(xml, fetched: Date(timeIntervalSince1970: 0)))
+        XCTAssertEqual(table.date, "2026-10-15")
+        XCTAssertEqual(table.rates, ["EUR": 1, "USD": 1.0812, "JPY": 162.43])
+        XCTAssertNil(CurrencyRates.parseECB("<html>maintenance</html>", fetched: Date()))
+    }
+}
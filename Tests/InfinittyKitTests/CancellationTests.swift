@@ -0,0 +1,50 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CancellationTests: XCTestCase {
+
+    /// Handlers run exactly once; one added after cancellation runs at once.
+    func testHandlersRunOnceAndLateHandlersRunImmediately() {
+        let token = CancellationToken()
+        var calls: [String] = []
+        token.onCancel { calls.append("early") }
+        let removed = token.onCancel { calls.append("removed") }
+        token.removeHandler(removed)
+        token.cancel()
+        token.cancel()
+        XCTAssertTrue(token.isCancelled)
+        token.onCancel { calls.append("late") }
+        XCTAssertEqual(calls, ["early", "late"])
+    }
+
+    func testRegistryCancelsByIDOnlyWhileRegistered() {
+        let registry = CancellationRegistry()
+        let token = CancellationToken()
+        registry.register("req-1", token)
+        XCTAssertEqual(registry.activeIDs, ["req-1"])
+        XCTAssertTrue(registry.cancel("req-1"))
+        XCTAssertTrue(token.isCancelled)
+        XCTAssertFalse(registry.cancel("req-1"))
+
+        let finished = CancellationToken()
+        registry.register("req-2", finished)
+        registry.unregister("req-2")
+        XCTAssertFalse(registry.cancel("req-2"))
+        XCTAssertFalse(finished.isCancelled)
+    }
+
+    /// Background tasks are reachable through the shared registry by task id.
+    func testBackgroundTaskIsCancellableByTaskID() {
+        let tasks = BackgroundTaskRegistry()
+        let started = DispatchSemaphore(value: 0)
+        let task = tasks.start(kind: "search", title: "loop") { task in
+            started.signal()
+            while !task.isCancelled { usleep(1000) }
+            return nil
+        }
+        XCTAssertEqual(started.wait(timeout: .now() + 2), .success)
+        XCTAssertTrue(CancellationRegistry.shared.cancel(task.id))
+        XCTAssertTrue(task.wait(timeout: 2))
+        XCTAssertEqual(task.currentState, .cancelled)
+    }
+}
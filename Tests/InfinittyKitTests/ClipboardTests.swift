@@ -0,0 +1,22 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ClipboardTests: XCTestCase {
+    func testClean() {
+        let copied = "\u{1B}[32m$\u{1B}[0m ls -la\n$ \n% make test\n\u{1B}]0;title\u{07}❯ git push\n\n"
+        XCTAssertEqual(Clipboard.clean(copied, CopyTransform.defaults), "$ ls -la\n$ \n% make test\n❯ git push")
+        XCTAssertEqual(Clipboard.clean(copied, Set(CopyTransform.allCases)), "ls -la\nmake test\ngit push")
+        XCTAssertEqual(Clipboard.clean(copied, []), copied)
+        // Only a marker followed by a command is a prompt.
+        XCTAssertEqual(Clipboard.clean("costs $5\n  $HOME is set\n", [.stripPrompts]), "costs $5\n  $HOME is set\n")
+        XCTAssertEqual(Clipboard.clean("echo hi\r\n", [.trim]), "echo hi")
+    }
+
+    func testParse() {
+        XCTAssertEqual(CopyTransform.parse("trim, strip-ansi"), [.trim, .stripANSI])
+        XCTAssertEqual(CopyTransform.parse("strip-prompts trim"), [.stripPrompts, .trim])
+        XCTAssertEqual(CopyTransform.parse("all"), Set(CopyTransform.allCases))
+        XCTAssertEqual(CopyTransform.parse("none"), [])
+        XCTAssertNil(CopyTransform.parse("trim, shout"))
+    }
+}
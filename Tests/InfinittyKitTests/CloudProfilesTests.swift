@@ -0,0 +1,67 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CloudProfilesTests: XCTestCase {
+
+    /// Config `[profile x]` and credentials `[x]` sections merge; SSO
+    /// session sections are not profiles; an inherited AWS_PROFILE is the
+    /// default.
+    func testAWSProfiles() {
+        let config = """
+            [default]
+            region = us-east-1
+            [profile prod-admin]
+            region = eu-west-1
+            sso_session = corp
+            [sso-session corp]
+            sso_region = us-east-1
+            """
+        let credentials = "[default]\naws_access_key_id = AKIA\n[ci]\naws_access_key_id = AKIB\n"
+        let profiles = CloudProfiles.aws(config: config, credentials: credentials, selected: "ci")
+        XCTAssertEqual(profiles.map(\.name), ["default", "prod-admin", "ci"])
+        XCTAssertEqual(profiles.map(\.detail), ["us-east-1", "eu-west-1", nil])
+        XCTAssertEqual(profiles.filter(\.isDefault).map(\.name), ["ci"])
+        XCTAssertTrue(profiles[1].looksLikeProduction)
+        XCTAssertFalse(profiles[0].looksLikeProduction)
+    }
+
+    func testGCPConfigurations() {
+        let profiles = CloudProfiles.gcp(
+            configurations: [("default", "[core]\nproject = sandbox-1\n"), ("live", "[core]\nproject = shop\n")],
+            active: "live")
+        XCTAssertEqual(profiles.map(\.detail), ["sandbox-1", "shop"])
+        XCTAssertEqual(profiles.filter(\.isDefault).map(\.name), ["live"])
+    }
+
+    /// `az login` writes the profile with a byte order mark; subscriptions
+    /// are selected by id.
+    func testAzureSubscriptionsSkipBOM() throws {
+        let json = #"{"subscriptions":[{"id":"1111","name":"Production","isDefault":true,"tenantId":"t"}]}"#
+        let profiles = CloudProfiles.azureSubscriptions(Data([0xEF, 0xBB, 0xBF]) + Data(json.utf8))
+        let profile = try XCTUnwrap(profiles.first)
+        XCTAssertEqual(profile.name, "Production")
+        XCTAssertEqual(profile.value, "1111")
+        XCTAssertTrue(profile.isDefault)
+        XCTAssertEqual(
+            CloudProfiles.exportCommand(.azure, profile),
+            " export AZURE_SUBSCRIPTION_ID='1111' ARM_SUBSCRIPTION_ID='1111'")
+    }
+
+    func testExportAndUnset() {
+        let profile = CloudProfile(provider: .aws, name: "o'brien", value: "o'brien")
+        XCTAssertEqual(CloudProfiles.exportCommand(.aws, profile), #" export AWS_PROFILE='o'\''brien'"#)
+        XCTAssertEqual(CloudProfiles.exportCommand(.gcp, nil), " unset CLOUDSDK_ACTIVE_CONFIG_NAME")
+    }
+
+    /// The pane header badge lists each provider and hides when cleared.
+    func testPaneHeaderBadge() {
+        let header = PaneHeaderView(frame: NSRect(x: 0, y: 0, width: 400, height: PaneHeaderView.height))
+        header.setCloudProfiles([
+            CloudProfile(provider: .aws, name: "prod", value: "prod"),
+            CloudProfile(provider: .gcp, name: "web", value: "web"),
+        ])
+        XCTAssertEqual(header.cloudBadgeForTesting, "aws:prod · gcp:web")
+        header.setCloudProfiles([])
+        XCTAssertNil(header.cloudBadgeForTesting)
+    }
+}
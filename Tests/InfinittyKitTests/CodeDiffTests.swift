@@ -50,6 +50,55 @@ final class CodeDiffTests: XCTestCase {
             kind: .del, oldLine: 2, newLine: nil, text: "old line")))
     }
 
+    func testHunksCarryRangesAndCounts() {
+        let hunks = CodeDiff.hunks(sample)
+        XCTAssertEqual(hunks.count, 2)
+        XCTAssertEqual(hunks[0].header, "@@ -1,4 +1,4 @@")
+        XCTAssertEqual([hunks[0].oldStart, hunks[0].oldCount, hunks[0].newStart, hunks[0].newCount], [1, 4, 1, 4])
+        XCTAssertEqual([hunks[0].added, hunks[0].removed], [1, 1])
+        XCTAssertEqual([hunks[1].oldStart, hunks[1].oldCount, hunks[1].newStart, hunks[1].newCount], [10, 1, 10, 2])
+        XCTAssertEqual(hunks[1].lines.last?.text, "added")
+        XCTAssertTrue(CodeDiff.hunks("Binary files /dev/null and b/logo.png differ\n").isEmpty)
+    }
+
+    /// Staging keeps unpicked deletions as context and drops unpicked
+    /// additions; later hunks' new-side starts move by what was left out.
+    func testPartialPatchPicksHunksAndLines() {
+        let header = "diff --git a/Sources/App.swift b/Sources/App.swift\nindex 1111111..2222222 100644\n"
+            + "--- a/Sources/App.swift\n+++ b/Sources/App.swift\n"
+        XCTAssertEqual(CodeDiff.partialPatch(sample) { hunk, _ in hunk == 1 },
+                       header + "@@ -10,1 +10,2 @@\n ctx\n+added\n")
+        XCTAssertEqual(CodeDiff.partialPatch(sample) { _, line in line.kind == .add },
+                       header + "@@ -1,4 +1,5 @@\n line one\n old line\n+new line\n line three\n line four\n"
+                        + "@@ -10,1 +11,2 @@\n ctx\n+added\n")
+        XCTAssertEqual(CodeDiff.partialPatch(sample, reverse: true) { _, line in line.kind == .del },
+                       header + "@@ -1,5 +1,4 @@\n line one\n-old line\n new line\n line three\n line four\n")
+        XCTAssertNil(CodeDiff.partialPatch(sample) { _, _ in false })
+    }
+
+    func testHunkAtRowCountsWithinEachHalf() {
+        let headers: [String?] = ["@@ -1 +1 @@", nil, "@@ -9 +9 @@", nil, "--- unstaged ---", "@@ -1 +1 @@", nil]
+        XCTAssertEqual(CodeDiff.hunk(at: 1, headers: headers, stagedFirst: true)?.index, 0)
+        XCTAssertEqual(CodeDiff.hunk(at: 3, headers: headers, stagedFirst: true)?.index, 1)
+        XCTAssertEqual(CodeDiff.hunk(at: 3, headers: headers, stagedFirst: true)?.staged, true)
+        XCTAssertNil(CodeDiff.hunk(at: 4, headers: headers, stagedFirst: true))
+        XCTAssertEqual(CodeDiff.hunk(at: 6, headers: headers, stagedFirst: true)?.index, 0)
+        XCTAssertEqual(CodeDiff.hunk(at: 6, headers: headers, stagedFirst: true)?.staged, false)
+        XCTAssertNil(CodeDiff.hunk(at: 7, headers: headers, stagedFirst: true))
+    }
+
+    /// Renames report the new path, braces and all; binaries have no counts.
+    func testParseNumstat() {
+        let stats = CodeDiff.parseNumstat("3\t1\tSources/App.swift\n-\t-\tlogo.png\n"
+            + "0\t0\tdocs/{old.md => new.md}\n2\t0\ta.txt => b.txt\n")
+        XCTAssertEqual(stats, [
+            DiffStat(path: "Sources/App.swift", added: 3, removed: 1, isBinary: false),
+            DiffStat(path: "logo.png", added: 0, removed: 0, isBinary: true),
+            DiffStat(path: "docs/new.md", added: 0, removed: 0, isBinary: false),
+            DiffStat(path: "b.txt", added: 2, removed: 0, isBinary: false),
+        ])
+    }
+
     func testParseHunkHeaderDefaultsAndRejects() {
         XCTAssertEqual(CodeDiff.parseHunkHeader("@@ -5 +9,2 @@")?.old, 5)
         XCTAssertEqual(CodeDiff.parseHunkHeader("@@ -5 +9,2 @@")?.new, 9)
@@ -76,6 +125,25 @@ final class CodeDiffTests: XCTestCase {
         XCTAssertEqual(last.newKind, .add)
     }
 
+    /// Comparing two texts gives the rows parsing git's diff of them would.
+    func testCompareMatchesUnifiedDiff() {
+        let old = ["one", "two", "three", "four", "five", "six", "seven", "eight"]
+        let new = ["one", "TWO", "three", "four", "five", "six", "seven", "eight", "nine"]
+        let unified = """
+        @@ -1,3 +1,3 @@
+         one
+        -two
+        +TWO
+         three
+        @@ -8,1 +8,2 @@
+         eight
+        +nine
+        """
+        XCTAssertEqual(CodeDiff.compare(old, new, context: 1), CodeDiff.parse(unified))
+        XCTAssertEqual(CodeDiff.compare([], ["x"]).first?.text, "@@ -0,0 +1,1 @@")
+        XCTAssertEqual(CodeDiff.compare(old, old), [])
+    }
+
     func testSplitRowsPadTheShorterSide() {
         let diff = """
         @@ -1,3 +1,1 @@
@@ -35,6 +35,33 @@ final class CodeGitTests: XCTestCase {
         XCTAssertNil(branch)
     }
 
+    func testParseTracking() {
+        XCTAssertEqual(
+            CodeGit.parseTracking("## main...origin/main [ahead 1, behind 12]\n M a\n"),
+            CodeGit.Tracking(upstream: "origin/main", ahead: 1, behind: 12))
+        XCTAssertEqual(
+            CodeGit.parseTracking("## feature/x...upstream/feature/x\n"),
+            CodeGit.Tracking(upstream: "upstream/feature/x"))
+        XCTAssertEqual(
+            CodeGit.parseTracking("## topic...origin/topic [gone]\n"),
+            CodeGit.Tracking(upstream: "origin/topic", gone: true))
+        XCTAssertNil(CodeGit.parseTracking("## main\n"))
+        XCTAssertNil(CodeGit.parseTracking("## HEAD (no branch)\n"))
+    }
+
+    func testBranchNamesAndRemoteParsing() {
+        XCTAssertTrue(CodeGit.isValidBranchName("feature/x-1"))
+        for name in ["", "-f", "a..b", "a b", "a:b", "x.lock", "a/.b", "a/", "@", "a@{1}", "a//b", "a."] {
+            XCTAssertFalse(CodeGit.isValidBranchName(name), name)
+        }
+        XCTAssertEqual(
+            CodeGit.parseRemoteBranches("origin/HEAD\trefs/remotes/origin/main\norigin/main\t\nup/feature/x\t\n"),
+            ["origin/main", "up/feature/x"])
+        XCTAssertEqual(CodeGit.localName(ofRemote: "origin/feature/x"), "feature/x")
+        XCTAssertNil(CodeGit.localName(ofRemote: "main"))
+        XCTAssertNil(CodeGit.localName(ofRemote: "origin/"))
+    }
+
     func testChangeLabels() {
         XCTAssertEqual(CodeChange(x: "M", y: " ", path: "a").label, "M")
         XCTAssertEqual(CodeChange(x: " ", y: "M", path: "a").label, "M")
@@ -173,6 +200,460 @@ final class CodeGitTests: XCTestCase {
         XCTAssertTrue(git(in: dir, "log", "--oneline", "-1").contains("second commit"))
     }
 
+    /// Repeated reads share one probe until something invalidates the repo;
+    /// failed probes are retried rather than cached.
+    /// Each side of a file's diff on its own, untracked files as all
+    /// additions, and numstat counts for the badge.
+    func testFileDiffSidesAndStats() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "one\ntwo\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        try "new\n".write(toFile: dir + "/b.txt", atomically: true, encoding: .utf8)
+
+        let unstaged = CodeGit.fileDiff(in: dir, path: "a.txt", staged: false)
+        XCTAssertNil(unstaged.error)
+        XCTAssertEqual(CodeDiff.hunks(unstaged.text).map(\.added), [1])
+        XCTAssertEqual(CodeGit.fileDiff(in: dir, path: "a.txt", staged: true).text, "")
+        let untracked = CodeDiff.hunks(CodeGit.fileDiff(in: dir, path: "b.txt", staged: false).text)
+        XCTAssertEqual(untracked.first?.lines, [DiffLine(kind: .add, oldLine: nil, newLine: 1, text: "new")])
+
+        XCTAssertNil(CodeGit.stage(in: dir, path: "a.txt"))
+        XCTAssertEqual(CodeGit.fileDiff(in: dir, path: "a.txt", staged: false).text, "")
+        XCTAssertEqual(CodeDiff.hunks(CodeGit.fileDiff(in: dir, path: "a.txt", staged: true).text).count, 1)
+        XCTAssertEqual(CodeGit.diffStats(in: dir, staged: true).stats,
+                       [DiffStat(path: "a.txt", added: 1, removed: 0, isBinary: false)])
+        XCTAssertEqual(CodeGit.diffStats(in: dir, staged: false).stats, [])
+    }
+
+    /// One hunk staged, then one line of the other unstaged again, the way
+    /// the Changes page and `git-stage-hunk` / `git-stage-lines` do it.
+    func testStageHunkAndUnstageLine() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        let lines = (1...20).map { "line \($0)" }
+        try (lines.joined(separator: "\n") + "\n").write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "twenty lines")
+        var edited = lines
+        edited[1] = "line two"
+        edited[17] = "line eighteen"
+        try (edited.joined(separator: "\n") + "\n").write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+
+        let diff = CodeGit.fileDiff(in: dir, path: "a.txt", staged: false).text
+        XCTAssertEqual(CodeDiff.hunks(diff).count, 2)
+        let patch = try XCTUnwrap(CodeDiff.partialPatch(diff) { hunk, _ in hunk == 1 })
+        XCTAssertNil(CodeGit.applyToIndex(in: dir, patch: patch, reverse: false))
+        XCTAssertEqual(git(in: dir, "diff", "--cached", "--numstat"), "1\t1\ta.txt\n")
+        XCTAssertEqual(CodeDiff.hunks(CodeGit.fileDiff(in: dir, path: "a.txt", staged: false).text).count, 1)
+
+        let staged = CodeGit.fileDiff(in: dir, path: "a.txt", staged: true).text
+        let unstage = try XCTUnwrap(CodeDiff.partialPatch(staged, reverse: true) { _, line in line.kind == .del })
+        XCTAssertNil(CodeGit.applyToIndex(in: dir, patch: unstage, reverse: true))
+        XCTAssertEqual(git(in: dir, "diff", "--cached", "--numstat"), "1\t0\ta.txt\n")
+    }
+
+    func testLogPagesAndShow() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "one\ntwo\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "second", "-m", "with a body")
+
+        let page = CodeGit.log(in: dir, limit: 1)
+        XCTAssertNil(page.error)
+        XCTAssertEqual(page.commits.map(\.subject), ["second"])
+        XCTAssertTrue(page.commits[0].refs.contains { $0.hasPrefix("HEAD -> ") })
+        let rest = CodeGit.log(in: dir, limit: 10, skip: 1).commits
+        XCTAssertEqual(rest.map(\.subject), ["init"])
+        XCTAssertEqual(page.commits[0].parents, [rest[0].hash])
+
+        let shown = CodeGit.show(in: dir, hash: page.commits[0].hash)
+        XCTAssertEqual(shown.commit, page.commits[0])
+        XCTAssertEqual(shown.body, "with a body")
+        XCTAssertEqual(shown.files, [DiffStat(path: "a.txt", added: 1, removed: 0, isBinary: false)])
+        XCTAssertNil(CodeGit.show(in: dir, hash: "0000000").commit)
+    }
+
+    func testFetchAndPullTrackUpstream() throws {
+        let origin = try makeLiveRepo()
+        let clone = origin + "-clone"
+        defer { [origin, clone].forEach { try? FileManager.default.removeItem(atPath: $0) } }
+        git(in: origin, "clone", "-q", origin, clone)
+        git(in: clone, "config", "user.email", "test@example.com")
+        git(in: clone, "config", "user.name", "Test")
+        let branch = try XCTUnwrap(CodeGit.status(in: clone).branch)
+        XCTAssertEqual(CodeGit.status(in: clone).tracking, CodeGit.Tracking(upstream: "origin/\(branch)"))
+
+        try "one\ntwo\n".write(toFile: origin + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: origin, "commit", "-qam", "upstream")
+        try "local\n".write(toFile: clone + "/b.txt", atomically: true, encoding: .utf8)
+        git(in: clone, "add", "b.txt")
+        git(in: clone, "commit", "-qm", "local")
+        XCTAssertNil(CodeGit.fetch(in: clone))
+        XCTAssertEqual(
+            CodeGit.status(in: clone).tracking, CodeGit.Tracking(upstream: "origin/\(branch)", ahead: 1, behind: 1))
+
+        XCTAssertNil(CodeGit.pull(in: clone, rebase: true).error)
+        XCTAssertEqual(CodeGit.status(in: clone).tracking, CodeGit.Tracking(upstream: "origin/\(branch)", ahead: 1))
+        XCTAssertEqual(try String(contentsOfFile: clone + "/a.txt", encoding: .utf8), "one\ntwo\n")
+        XCTAssertNotNil(CodeGit.fetch(in: clone, remote: "nowhere"))
+    }
+
+    func testParseRemotes() {
+        let output = "origin\t../up.git (fetch)\norigin\t../up.git (push)\n"
+            + "fork\thttps://example.com/x.git (fetch)\nfork\tgit@example.com:x.git (push)\n"
+        XCTAssertEqual(CodeGit.parseRemotes(output), [
+            CodeGit.Remote(name: "origin", fetchURL: "../up.git", pushURL: "../up.git"),
+            CodeGit.Remote(name: "fork", fetchURL: "https://example.com/x.git", pushURL: "git@example.com:x.git"),
+        ])
+        XCTAssertEqual(CodeGit.parseRemotes(""), [])
+    }
+
+    /// A new branch publishes with its upstream set; a rewritten one needs
+    /// the lease.
+    func testRemotesAndPush() throws {
+        let origin = try makeLiveRepo()
+        let clone = origin + "-clone"
+        defer { [origin, clone].forEach { try? FileManager.default.removeItem(atPath: $0) } }
+        git(in: origin, "clone", "-q", origin, clone)
+        git(in: clone, "config", "user.email", "test@example.com")
+        git(in: clone, "config", "user.name", "Test")
+        XCTAssertEqual(CodeGit.remotes(in: clone).map(\.name), ["origin"])
+        XCTAssertNil(CodeGit.addRemote(in: clone, name: "spare", url: origin))
+        XCTAssertNotNil(CodeGit.addRemote(in: clone, name: "spare", url: origin))
+        XCTAssertNotNil(CodeGit.addRemote(in: clone, name: "-x", url: origin))
+        XCTAssertEqual(CodeGit.remotes(in: clone).map(\.name), ["origin", "spare"])
+        XCTAssertNil(CodeGit.removeRemote(in: clone, name: "spare"))
+        XCTAssertNotNil(CodeGit.removeRemote(in: clone, name: "spare"))
+
+        XCTAssertNil(CodeGit.createBranch(in: clone, name: "work", checkout: true))
+        try "work\n".write(toFile: clone + "/w.txt", atomically: true, encoding: .utf8)
+        git(in: clone, "add", "w.txt")
+        git(in: clone, "commit", "-qm", "work")
+        XCTAssertNil(CodeGit.push(in: clone).error)
+        XCTAssertEqual(CodeGit.status(in: clone).tracking, CodeGit.Tracking(upstream: "origin/work"))
+        XCTAssertEqual(git(in: origin, "rev-parse", "work"), git(in: clone, "rev-parse", "HEAD"))
+
+        git(in: clone, "commit", "-q", "--amend", "-m", "reworded")
+        XCTAssertNotNil(CodeGit.push(in: clone).error)
+        XCTAssertNil(CodeGit.push(in: clone, options: CodeGit.PushOptions(forceWithLease: true)).error)
+        XCTAssertEqual(git(in: origin, "rev-parse", "work"), git(in: clone, "rev-parse", "HEAD"))
+
+        git(in: clone, "tag", "v1")
+        XCTAssertNil(CodeGit.push(in: clone, options: CodeGit.PushOptions(tags: true)).error)
+        XCTAssertEqual(git(in: origin, "tag"), "v1\n")
+        XCTAssertNotNil(CodeGit.push(in: clone, options: CodeGit.PushOptions(remote: "--all")).error)
+    }
+
+    /// Create, rename and delete act on locals; a remote branch checks out
+    /// as a local one tracking it.
+    func testBranchLifecycleAndRemoteCheckout() throws {
+        let origin = try makeLiveRepo()
+        let clone = origin + "-clone"
+        defer { [origin, clone].forEach { try? FileManager.default.removeItem(atPath: $0) } }
+        git(in: origin, "branch", "topic")
+        git(in: origin, "clone", "-q", origin, clone)
+        git(in: clone, "config", "user.email", "test@example.com")
+        git(in: clone, "config", "user.name", "Test")
+        let main = try XCTUnwrap(CodeGit.status(in: clone).branch)
+        XCTAssertEqual(Set(CodeGit.remoteBranches(in: clone)), ["origin/\(main)", "origin/topic"])
+
+        XCTAssertNil(CodeGit.createBranch(in: clone, name: "work", checkout: true))
+        XCTAssertEqual(CodeGit.status(in: clone).branch, "work")
+        XCTAssertNotNil(CodeGit.createBranch(in: clone, name: "work"))
+        XCTAssertNotNil(CodeGit.createBranch(in: clone, name: "-D"))
+        XCTAssertNil(CodeGit.renameBranch(in: clone, from: "work", to: "renamed"))
+        XCTAssertEqual(CodeGit.status(in: clone).branch, "renamed")
+
+        try "more\n".write(toFile: clone + "/c.txt", atomically: true, encoding: .utf8)
+        git(in: clone, "add", "c.txt")
+        git(in: clone, "commit", "-qm", "unmerged")
+        XCTAssertNil(CodeGit.checkout(in: clone, branch: main))
+        XCTAssertNotNil(CodeGit.deleteBranch(in: clone, name: "renamed"))
+        XCTAssertNil(CodeGit.deleteBranch(in: clone, name: "renamed", force: true))
+        XCTAssertFalse(CodeGit.branches(in: clone).contains("renamed"))
+
+        let checkedOut = CodeGit.checkoutRemote(in: clone, remoteBranch: "origin/topic")
+        XCTAssertNil(checkedOut.error)
+        XCTAssertEqual(checkedOut.branch, "topic")
+        XCTAssertEqual(CodeGit.status(in: clone).tracking, CodeGit.Tracking(upstream: "origin/topic"))
+        XCTAssertEqual(CodeGit.checkoutRemote(in: clone, remoteBranch: "origin/topic").branch, "topic")
+    }
+
+    func testParseBlameReusesCommitDetails() {
+        let hash = String(repeating: "a", count: 40)
+        let zero = String(repeating: "0", count: 40)
+        let output = """
+            \(hash) 1 1 2
+            author Ann
+            author-mail <ann@example.com>
+            author-time 1700000000
+            summary First
+            filename a.txt
+            \tone
+            \(hash) 2 2
+            \ttwo
+            \(zero) 3 3 1
+            author Not Committed Yet
+            author-mail <not.committed.yet>
+            author-time 1700000100
+            summary Version of a.txt from a.txt
+            filename a.txt
+            \t\tindented
+
+            """
+        let lines = CodeGit.parseBlame(output)
+        XCTAssertEqual(lines.map(\.line), [1, 2, 3])
+        XCTAssertEqual(lines.map(\.text), ["one", "two", "\tindented"])
+        XCTAssertEqual(lines[1], BlameLine(
+            line: 2, hash: hash, author: "Ann", email: "ann@example.com", date: 1_700_000_000,
+            summary: "First", text: "two"))
+        XCTAssertTrue(lines[0].isCommitted)
+        XCTAssertFalse(lines[2].isCommitted)
+    }
+
+    func testLiveBlameRangeAndRev() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "one\ntwo\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "second")
+        try "one\ntwo\nthree\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+
+        let blame = CodeGit.blame(in: dir, path: "a.txt")
+        XCTAssertNil(blame.error)
+        XCTAssertEqual(blame.lines.map(\.summary), ["init", "second", "Version of a.txt from a.txt"])
+        XCTAssertEqual(blame.lines.map(\.isCommitted), [true, true, false])
+        XCTAssertEqual(CodeGit.blame(in: dir, path: "a.txt", lines: 2...3).lines.map(\.line), [2, 3])
+        XCTAssertEqual(CodeGit.blame(in: dir, path: "a.txt", rev: "HEAD~1").lines.map(\.text), ["one"])
+        XCTAssertNotNil(CodeGit.blame(in: dir, path: "missing.txt").error)
+    }
+
+    func testParseIdentityAndSigningErrors() {
+        XCTAssertEqual(CodeGit.parseIdentity(" Ada Lovelace <ada@example.com> ")?.name, "Ada Lovelace")
+        XCTAssertEqual(CodeGit.parseIdentity("Ada <ada@example.com>")?.email, "ada@example.com")
+        XCTAssertNil(CodeGit.parseIdentity("ada@example.com"))
+        XCTAssertNil(CodeGit.parseIdentity("<ada@example.com>"))
+        XCTAssertNil(CodeGit.parseIdentity("Ada <>"))
+
+        let gpg = "error: gpg failed to sign the data\nfatal: failed to write commit object"
+        XCTAssertTrue(CodeGit.isSigningError(gpg))
+        XCTAssertTrue(CodeGit.isSigningError("error: Couldn't load public key /k: No such file or directory?"))
+        XCTAssertFalse(CodeGit.isSigningError("nothing added to commit but untracked files present"))
+    }
+
+    /// Author and committer overrides land in the commit, amend rewrites
+    /// it in place (keeping the message when none is given), and a key
+    /// that can't sign fails as a signing error without committing.
+    func testCommitAmendAuthorAndSigning() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "two\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "add", "a.txt")
+        var options = CodeGit.CommitOptions(sign: false)
+        options.author = "Ada <ada@example.com>"
+        options.committer = "Bot <bot@example.com>"
+        XCTAssertNil(CodeGit.commit(in: dir, message: "second", options: options))
+        XCTAssertEqual(git(in: dir, "log", "-1", "--format=%an <%ae>|%cn|%s"), "Ada <ada@example.com>|Bot|second\n")
+        let head = CodeGit.head(in: dir)
+
+        try "three\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "add", "a.txt")
+        XCTAssertNil(CodeGit.commit(in: dir, message: "", options: CodeGit.CommitOptions(amend: true)))
+        XCTAssertEqual(git(in: dir, "log", "--format=%an|%s"), "Ada|second\nTest|init\n")
+        XCTAssertNotEqual(CodeGit.head(in: dir), head)
+        XCTAssertNotNil(CodeGit.commit(in: dir, message: "x", options: CodeGit.CommitOptions(author: "nobody")))
+
+        try "four\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "add", "a.txt")
+        git(in: dir, "config", "gpg.format", "ssh")
+        let unsigned = CodeGit.head(in: dir)
+        let error = CodeGit.commit(
+            in: dir, message: "signed", options: CodeGit.CommitOptions(sign: true, signingKey: dir + "/missing-key"))
+        XCTAssertTrue(CodeGit.isSigningError(try XCTUnwrap(error)))
+        XCTAssertEqual(CodeGit.head(in: dir), unsigned)
+    }
+
+    func testParseClean() {
+        let output = "Would remove build/\nWould remove \"a\\tb.txt\"\nRemoving notes.md\nwarning: skipped\n"
+        XCTAssertEqual(CodeGit.parseClean(output), ["build/", "a\\tb.txt", "notes.md"])
+        XCTAssertEqual(CodeGit.parseClean(""), [])
+    }
+
+    /// Discard puts a file back; a dry-run clean lists untracked files and
+    /// folders without touching them, and a real one deletes just those.
+    func testDiscardAndClean() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "changed\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        XCTAssertNil(CodeGit.discard(in: dir, path: "a.txt"))
+        XCTAssertEqual(try String(contentsOfFile: dir + "/a.txt", encoding: .utf8), "one\n")
+        XCTAssertNotNil(CodeGit.discard(in: dir, path: "missing.txt"))
+
+        try FileManager.default.createDirectory(atPath: dir + "/build", withIntermediateDirectories: true)
+        try "x".write(toFile: dir + "/build/out.o", atomically: true, encoding: .utf8)
+        try "x".write(toFile: dir + "/new.txt", atomically: true, encoding: .utf8)
+        let preview = CodeGit.clean(in: dir, dryRun: true)
+        XCTAssertNil(preview.error)
+        XCTAssertEqual(preview.paths, ["build/", "new.txt"])
+        XCTAssertTrue(FileManager.default.fileExists(atPath: dir + "/new.txt"))
+
+        XCTAssertEqual(CodeGit.clean(in: dir, paths: ["new.txt"], dryRun: false).paths, ["new.txt"])
+        XCTAssertFalse(FileManager.default.fileExists(atPath: dir + "/new.txt"))
+        XCTAssertTrue(FileManager.default.fileExists(atPath: dir + "/build/out.o"))
+        XCTAssertTrue(FileManager.default.fileExists(atPath: dir + "/a.txt"))
+    }
+
+    func testParseUnmergedGroupsStages() {
+        let output = "100644 aaa 1\tb.txt\0100644 bbb 2\tb.txt\0100644 ccc 3\tb.txt\0"
+            + "100644 ddd 2\ta b.txt\0100644 eee 3\ta b.txt\0"
+        let entries = CodeGit.parseUnmerged(output)
+        XCTAssertEqual(entries.map(\.path), ["a b.txt", "b.txt"])
+        XCTAssertEqual(entries[1].stages, [1: "aaa", 2: "bbb", 3: "ccc"])
+        XCTAssertEqual(CodeGit.conflictKind(stages: Set(entries[0].stages.keys)), "both-added")
+        XCTAssertEqual(CodeGit.conflictKind(stages: [1, 2, 3]), "both-modified")
+        XCTAssertEqual(CodeGit.conflictKind(stages: [1, 3]), "deleted-by-us")
+        XCTAssertEqual(CodeGit.conflictKind(stages: [1, 2]), "deleted-by-them")
+    }
+
+    /// A conflicting merge stops with all three versions readable and
+    /// finishes once resolved; a rebase stops the same way (sides swapped)
+    /// and aborts back to the branch.
+    func testMergeConflictResolveAndRebaseAbort() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        let main = try XCTUnwrap(CodeGit.status(in: dir).branch)
+        git(in: dir, "checkout", "-qb", "topic")
+        try "theirs\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "topic")
+        git(in: dir, "checkout", "-q", main)
+        try "ours\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "main")
+        XCTAssertNil(CodeGit.operation(in: dir))
+
+        XCTAssertTrue(CodeGit.merge(in: dir, branch: "topic").error?.contains("CONFLICT") == true)
+        XCTAssertEqual(CodeGit.operation(in: dir), .merge)
+        XCTAssertEqual(CodeGit.conflicts(in: dir).conflicts, [
+            CodeGit.Conflict(path: "a.txt", base: "one\n", ours: "ours\n", theirs: "theirs\n", kind: "both-modified"),
+        ])
+        XCTAssertNotNil(CodeGit.resume(in: dir, operation: .merge).error)
+        XCTAssertNotNil(CodeGit.resolveConflict(in: dir, path: "../a.txt", content: ""))
+        XCTAssertNil(CodeGit.resolveConflict(in: dir, path: "a.txt", content: "both\n"))
+        XCTAssertEqual(CodeGit.conflicts(in: dir).conflicts, [])
+        XCTAssertNotNil(CodeGit.resolveConflict(in: dir, path: "a.txt", content: "again\n"))
+        XCTAssertNil(CodeGit.resume(in: dir, operation: .merge).error)
+        XCTAssertNil(CodeGit.operation(in: dir))
+        XCTAssertEqual(try String(contentsOfFile: dir + "/a.txt", encoding: .utf8), "both\n")
+
+        git(in: dir, "checkout", "-qb", "side", "topic")
+        try "side\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        git(in: dir, "commit", "-qam", "side")
+        XCTAssertNotNil(CodeGit.rebase(in: dir, onto: main).error)
+        XCTAssertEqual(CodeGit.operation(in: dir), .rebase)
+        let conflict = try XCTUnwrap(CodeGit.conflicts(in: dir, path: "a.txt").conflicts.first)
+        XCTAssertEqual([conflict.base, conflict.ours, conflict.theirs], ["theirs\n", "both\n", "side\n"])
+        XCTAssertNil(CodeGit.abort(in: dir, operation: .rebase))
+        XCTAssertNil(CodeGit.operation(in: dir))
+        XCTAssertEqual(CodeGit.status(in: dir).branch, "side")
+        XCTAssertNotNil(CodeGit.merge(in: dir, branch: "--abort").error)
+    }
+
+    func testStatusCacheReusesProbeUntilInvalidated() {
+        var probes = 0
+        var fail = false
+        let cache = GitStatusCache(watchesFileSystem: false) { _ in
+            probes += 1
+            return fail
+                ? CodeGit.RepoStatus(branch: nil, changes: [], error: "boom")
+                : CodeGit.RepoStatus(branch: "main", changes: [], error: nil)
+        }
+        XCTAssertEqual(cache.status(in: "/repo").branch, "main")
+        XCTAssertEqual(cache.status(in: "/repo").branch, "main")
+        XCTAssertEqual(probes, 1)
+
+        cache.invalidate("/repo")
+        fail = true
+        XCTAssertEqual(cache.status(in: "/repo").error, "boom")
+        XCTAssertEqual(cache.status(in: "/repo").error, "boom")
+        XCTAssertEqual(probes, 3)
+    }
+
+    /// A burst of invalidations of a repo someone read posts once, after
+    /// it settles, with the re-read status; repos never read stay quiet.
+    func testStatusCachePostsChangeOncePerBurst() {
+        let cache = GitStatusCache(watchesFileSystem: false, settle: 0.05) { _ in
+            CodeGit.RepoStatus(branch: "main", changes: [CodeChange(x: "?", y: "?", path: "new.txt")], error: nil)
+        }
+        let posted = LockedState<[String]>([])
+        let settled = expectation(description: "posted")
+        let observer = NotificationCenter.default.addObserver(
+            forName: GitStatusCache.didChangeNotification, object: cache, queue: nil
+        ) { note in
+            guard let repo = note.userInfo?[GitStatusCache.repoKey] as? String,
+                  let status = note.userInfo?[GitStatusCache.statusKey] as? CodeGit.RepoStatus
+            else { return }
+            XCTAssertEqual(status.branch, "main")
+            posted.withLock { $0.append(repo) }
+            settled.fulfill()
+        }
+        defer { NotificationCenter.default.removeObserver(observer) }
+
+        cache.invalidate("/never")
+        _ = cache.status(in: "/repo")
+        cache.invalidate("/repo")
+        cache.invalidate("/repo")
+        cache.invalidate("/repo")
+        wait(for: [settled], timeout: 5)
+        Thread.sleep(forTimeInterval: 0.2)
+        XCTAssertEqual(posted.snapshot, ["/repo"])
+
+        let wire = cache.status(in: "/repo").wire
+        XCTAssertEqual(wire["branch"] as? String, "main")
+        XCTAssertEqual((wire["changes"] as? [[String: Any]])?.first?["status"] as? String, "??")
+        XCTAssertNil(wire["error"])
+    }
+
+    /// A repo's watcher lives while some pane is in it or below it; once
+    /// none is, the stream and the cached status both go.
+    func testStatusCacheReleasesWatchersNoPaneNeeds() throws {
+        let root = FileManager.default.temporaryDirectory.appendingPathComponent("watch-\(UUID().uuidString)").path
+        let (kept, dropped) = (root + "/kept", root + "/dropped")
+        try FileManager.default.createDirectory(atPath: kept + "/src", withIntermediateDirectories: true)
+        try FileManager.default.createDirectory(atPath: dropped, withIntermediateDirectories: true)
+        defer { try? FileManager.default.removeItem(atPath: root) }
+        var probes = 0
+        let cache = GitStatusCache(watchesFileSystem: true) { _ in
+            probes += 1
+            return CodeGit.RepoStatus(branch: "main", changes: [], error: nil)
+        }
+        _ = cache.status(in: kept)
+        _ = cache.status(in: dropped)
+        XCTAssertEqual(cache.watchedRepos, [dropped, kept])
+
+        cache.release(keeping: [kept + "/src", root + "/dropped-not"])
+        XCTAssertEqual(cache.watchedRepos, [kept])
+        _ = cache.status(in: kept)
+        XCTAssertEqual(probes, 2)
+        _ = cache.status(in: dropped)
+        XCTAssertEqual(probes, 3)
+        XCTAssertEqual(cache.watchedRepos, [dropped, kept])
+
+        cache.release(keeping: [])
+        XCTAssertEqual(cache.watchedRepos, [])
+    }
+
+    /// Our own mutations drop the shared cache entry, so the Changes page
+    /// sees a stage immediately instead of the pre-stage snapshot.
+    func testMutationInvalidatesSharedStatusCache() throws {
+        let dir = try makeLiveRepo()
+        defer { try? FileManager.default.removeItem(atPath: dir) }
+        try "two\n".write(toFile: dir + "/a.txt", atomically: true, encoding: .utf8)
+        GitStatusCache.shared.invalidate(dir)
+        XCTAssertEqual(GitStatusCache.shared.status(in: dir).changes,
+                       [CodeChange(x: " ", y: "M", path: "a.txt")])
+
+        XCTAssertNil(CodeGit.stage(in: dir, path: "a.txt"))
+        XCTAssertEqual(GitStatusCache.shared.status(in: dir).changes,
+                       [CodeChange(x: "M", y: " ", path: "a.txt")])
+    }
+
     /// A fresh repo in a temp dir with one committed file ("a.txt").
     private func makeLiveRepo() throws -> String {
         let dir = NSTemporaryDirectory() + "/infinitty-git-\(UUID().uuidString)"
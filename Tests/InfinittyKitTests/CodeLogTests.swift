@@ -0,0 +1,33 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CodeLogTests: XCTestCase {
+    func testParseLogRecords() {
+        let output = "aaa\u{1F}bbb ccc\u{1F}Ada\u{1F}ada@example.com\u{1F}1700000000\u{1F}HEAD -> main, tag: v1"
+            + "\u{1F}Merge branch 'x'\u{1E}\nbbb\u{1F}\u{1F}Bo\u{1F}bo@example.com\u{1F}1600000000\u{1F}\u{1F}"
+            + "first: with | pipes\u{1E}\n"
+        XCTAssertEqual(CodeGit.parseLog(output), [
+            CodeCommit(hash: "aaa", parents: ["bbb", "ccc"], author: "Ada", email: "ada@example.com",
+                       date: 1_700_000_000, subject: "Merge branch 'x'", refs: ["HEAD -> main", "tag: v1"]),
+            CodeCommit(hash: "bbb", parents: [], author: "Bo", email: "bo@example.com",
+                       date: 1_600_000_000, subject: "first: with | pipes", refs: []),
+        ])
+    }
+
+    /// A merge opens a lane for its second parent, which joins the first
+    /// parent's lane again at the fork point.
+    func testGraphLanesForMerge() {
+        func commit(_ hash: String, _ parents: [String]) -> CodeCommit {
+            CodeCommit(hash: hash, parents: parents, author: "", email: "", date: 0, subject: hash, refs: [])
+        }
+        let rows = CommitGraph.rows([
+            commit("M", ["A", "B"]), commit("B", ["A"]), commit("A", ["R"]), commit("R", []),
+        ])
+        XCTAssertEqual(rows, [
+            CommitGraphRow(column: 0, joining: [], parents: [0, 1], passing: []),
+            CommitGraphRow(column: 1, joining: [], parents: [1], passing: [0]),
+            CommitGraphRow(column: 0, joining: [1], parents: [0], passing: []),
+            CommitGraphRow(column: 0, joining: [], parents: [], passing: []),
+        ])
+    }
+}
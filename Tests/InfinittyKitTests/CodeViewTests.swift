@@ -62,6 +62,25 @@ final class CodeViewTests: XCTestCase {
         XCTAssertEqual(controller.cellTextForTesting(row: 1), "Package.swift")
     }
 
+    /// Reveal expands the folders above a path, finds files made after the
+    /// folder was listed, and re-roots for a path outside the tree.
+    func testRevealSelectsPath() throws {
+        let (controller, _) = mountedController()
+        controller.reRootForTesting(tempDir)
+        XCTAssertEqual(controller.topLevelRowCountForTesting, 2)
+        try "print(1)\n".write(toFile: tempDir + "/Sources/main.swift", atomically: true, encoding: .utf8)
+        XCTAssertTrue(controller.reveal(path: tempDir + "/Sources/main.swift"))
+        XCTAssertEqual(controller.selectedPathForTesting?.hasSuffix("/Sources/main.swift"), true)
+        XCTAssertEqual(controller.topLevelRowCountForTesting, 3)
+        XCTAssertFalse(controller.reveal(path: tempDir + "/Sources/missing.swift"))
+
+        let outside = NSTemporaryDirectory() + "infinitty-reveal-\(UUID().uuidString).txt"
+        try "x".write(toFile: outside, atomically: true, encoding: .utf8)
+        defer { try? FileManager.default.removeItem(atPath: outside) }
+        XCTAssertTrue(controller.reveal(path: outside))
+        XCTAssertEqual(controller.headerTextForTesting, (outside as NSString).deletingLastPathComponent)
+    }
+
     func testPreviewLoadsSelectedFile() {
         let (controller, _) = mountedController()
         controller.reRootForTesting(tempDir)
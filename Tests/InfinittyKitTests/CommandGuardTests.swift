@@ -0,0 +1,61 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CommandGuardTests: XCTestCase {
+    func testRisksByCommand() {
+        XCTAssertEqual(CommandGuard.risks(in: "rm -rf build"), [.recursiveDelete])
+        XCTAssertEqual(CommandGuard.risks(in: "sudo -n rm --recursive /tmp/x"), [.recursiveDelete])
+        XCTAssertEqual(CommandGuard.risks(in: "find . -name '*.o' -delete"), [.recursiveDelete])
+        XCTAssertEqual(CommandGuard.risks(in: "make && chmod -R 777 ."), [.recursivePermissions])
+        XCTAssertEqual(CommandGuard.risks(in: "git -C repo push -f origin main"), [.forcePush])
+        XCTAssertEqual(CommandGuard.risks(in: "git push origin +main"), [.forcePush])
+        XCTAssertEqual(CommandGuard.risks(in: "git push --force-with-lease"), [.forcePush])
+        XCTAssertEqual(CommandGuard.risks(in: "psql -c 'drop table users'"), [.dropTable])
+        XCTAssertEqual(CommandGuard.risks(in: "dd if=x.img of=/dev/disk4 bs=1m"), [.diskWrite])
+        XCTAssertEqual(CommandGuard.risks(in: "rm -rf a; git push --force"), [.recursiveDelete, .forcePush])
+
+        XCTAssertEqual(CommandGuard.risks(in: "rm file.txt"), [])
+        XCTAssertEqual(CommandGuard.risks(in: "echo 'rm -rf /'"), [])
+        XCTAssertEqual(CommandGuard.risks(in: "git push origin main"), [])
+        XCTAssertEqual(CommandGuard.risks(in: "ls # rm -rf /"), [])
+        XCTAssertEqual(CommandGuard.risks(in: "chmod 644 a"), [])
+    }
+
+    func testWordsFollowShellQuoting() {
+        let segments = CommandGuard.words(#"FOO=1 rm -r "a b" c\ d 'e*' f* | wc; echo "x\y""#)
+        XCTAssertEqual(segments.count, 3)
+        XCTAssertEqual(segments[0].map(\.text), ["FOO=1", "rm", "-r", "a b", "c d", "e*", "f*"])
+        XCTAssertNil(segments[0][5].pattern)
+        XCTAssertEqual(segments[0][6].pattern, "f*")
+        XCTAssertEqual(segments[2].map(\.text), ["echo", #"x\y"#])
+        XCTAssertEqual(CommandGuard.words("ls ~/src")[0][1].text, NSHomeDirectory() + "/src")
+        XCTAssertEqual(CommandGuard.words("ls '~/src'")[0][1].text, "~/src")
+        XCTAssertEqual(CommandGuard.words("rm -r {a,'b,c'}")[0][2].pattern, #"{a,b\,c}"#)
+    }
+
+    /// Globs expand from the directory, quoted ones match literally, and
+    /// everything under a folder counts.
+    func testAnalyzeExpandsAndCounts() throws {
+        let dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: dir) }
+        try FileManager.default.createDirectory(
+            at: dir.appendingPathComponent("build/sub"), withIntermediateDirectories: true)
+        for path in ["build/a.o", "build/sub/b.o", "x.log", "y.log", "*.log"] {
+            try Data().write(to: dir.appendingPathComponent(path))
+        }
+
+        let analysis = try XCTUnwrap(CommandGuard.analyze("rm -rf build *.log '*.log' missing", in: dir.path))
+        XCTAssertEqual(analysis.risks, [.recursiveDelete])
+        XCTAssertEqual(analysis.targets.map(\.argument), ["build", "*.log", "*.log", "missing"])
+        XCTAssertEqual(analysis.targets.map(\.count), [4, 3, 1, 0])
+        XCTAssertEqual(analysis.targets[1].matches.count, 3)
+        XCTAssertEqual(analysis.targets[2].matches, [dir.appendingPathComponent("*.log").path])
+        XCTAssertEqual(analysis.affected, 8)
+        XCTAssertFalse(analysis.truncated)
+
+        let capped = try XCTUnwrap(CommandGuard.analyze("rm -r build", in: dir.path, limit: 2))
+        XCTAssertEqual(capped.affected, 2)
+        XCTAssertTrue(capped.truncated)
+        XCTAssertNil(CommandGuard.analyze("ls build", in: dir.path))
+    }
+}
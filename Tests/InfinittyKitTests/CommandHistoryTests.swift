@@ -0,0 +1,96 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CommandHistoryTests: XCTestCase {
+
+    private let utc: Calendar = {
+        var calendar = Calendar(identifier: .gregorian)
+        calendar.timeZone = TimeZone(identifier: "UTC")!
+        return calendar
+    }()
+
+    private func record(
+        _ command: String, in directory: String = "/repo", exit: Int = 0, at time: TimeInterval
+    ) -> CommandRecord {
+        CommandRecord(
+            command: command, directory: directory, exitCode: exit,
+            started: Date(timeIntervalSince1970: time), duration: 1)
+    }
+
+    /// Wrappers, assignments and paths are peeled off to find the program.
+    func testProgramName() {
+        XCTAssertEqual(record("git push -f", at: 0).program, "git")
+        XCTAssertEqual(record("sudo -E /usr/bin/make install", at: 0).program, "make")
+        XCTAssertEqual(record("RUST_LOG=debug cargo run", at: 0).program, "cargo")
+    }
+
+    /// Counts per day and hour, top programs and directories with failure
+    /// rates, restricted to the requested range.
+    func testStats() throws {
+        let day = 86_400.0
+        let records = [
+            record("git status", at: 10 * day + 3_600),
+            record("git push", exit: 1, at: 10 * day + 7_200),
+            record("make", in: "/other", exit: 2, at: 11 * day + 3_600),
+            record("ls", at: 2 * day),
+        ]
+        let now = Date(timeIntervalSince1970: 11 * day + 80_000)
+        let range = try XCTUnwrap(HistoryStats.range("2d", now: now, calendar: utc))
+        let stats = HistoryStats(records, range: range, calendar: utc)
+
+        XCTAssertEqual(stats.total, 3)
+        XCTAssertEqual(stats.failures, 2)
+        XCTAssertEqual(stats.days.map(\.key), ["1970-01-11", "1970-01-12"])
+        XCTAssertEqual(stats.days.map(\.count), [2, 1])
+        XCTAssertEqual(stats.hours[1], 2)
+        XCTAssertEqual(stats.topCommands.map(\.key), ["git", "make"])
+        XCTAssertEqual(stats.directories.map(\.key), ["/repo", "/other"])
+        let top = try XCTUnwrap((stats.wire["topCommands"] as? [[String: Any]])?.first)
+        XCTAssertEqual(top["failureRate"] as? Double, 0.5)
+
+        XCTAssertEqual(HistoryStats(records, range: try XCTUnwrap(HistoryStats.range("all")), calendar: utc).total, 4)
+        XCTAssertNil(HistoryStats.range("forever"))
+    }
+
+    /// Filters narrow the hits; exact substrings outrank scattered matches,
+    /// and repeats collapse to their latest run.
+    func testSearch() throws {
+        var records = [
+            record("docker build -t api .", at: 1),
+            record("cd ~/src/docker", in: "/home", at: 2),
+            record("dd bs=1m if=disk.img", exit: 1, at: 3),
+            record("docker build -t api .", in: "/repo/web", at: 4),
+        ]
+        records[3].pane = 7
+        let hits = HistoryQuery(text: "dock bld").search(records)
+        XCTAssertEqual(hits.map(\.record.command), ["docker build -t api ."])
+        XCTAssertEqual(hits.first?.record.started, Date(timeIntervalSince1970: 4))
+
+        XCTAssertEqual(HistoryQuery(text: "docker").search(records).map(\.record.command),
+                       ["docker build -t api .", "cd ~/src/docker"])
+        XCTAssertEqual(HistoryQuery(unique: false).search(records).map(\.record.started.timeIntervalSince1970),
+                       [4, 3, 2, 1])
+        XCTAssertEqual(HistoryQuery(failed: true).search(records).map(\.record.command), ["dd bs=1m if=disk.img"])
+        XCTAssertEqual(HistoryQuery(directory: "/repo").search(records).count, 2)
+        XCTAssertEqual(HistoryQuery(pane: 7).search(records).count, 1)
+        XCTAssertNil(HistoryQuery.score("xyz", in: "docker"))
+
+        let parsed = try HistoryQuery.parse(#"{"query":"git","failed":false,"limit":5,"cwd":"/repo"}"#).get()
+        XCTAssertEqual(parsed, HistoryQuery(text: "git", directory: "/repo", failed: false, limit: 5))
+        XCTAssertEqual(try HistoryQuery.parse("  git push ").get().text, "git push")
+        XCTAssertEqual(HistoryQuery.parse(#"{"limit":0}"#), .failure(.invalid("limit must be 1-1000")))
+    }
+
+    /// Records survive the file round trip in order.
+    func testStoreRoundTrip() {
+        let url = FileManager.default.temporaryDirectory
+            .appendingPathComponent("infinitty-history-\(UUID().uuidString)")
+            .appendingPathComponent("history.jsonl")
+        defer { try? FileManager.default.removeItem(at: url.deletingLastPathComponent()) }
+        let history = CommandHistory(url: url)
+        history.record(record("one", at: 1))
+        history.record(record("two", exit: 1, at: 2))
+        XCTAssertEqual(history.records().map(\.command), ["one", "two"])
+        XCTAssertEqual(history.records().last?.exitCode, 1)
+    }
+}
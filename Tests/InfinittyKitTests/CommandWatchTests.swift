@@ -0,0 +1,50 @@
+import XCTest
+@testable import InfinittyKit
+
+final class CommandWatchTests: XCTestCase {
+    /// New and altered lines come back at their place in the new output;
+    /// lines that went away at their place in the old one.
+    func testOutputDiff() {
+        let old = ["M a.swift", "?? b.txt", "M c.swift"]
+        let new = ["M a.swift", "A b.txt", "M c.swift", "?? d.txt"]
+        let diff = OutputDiff.between(old, new)
+        XCTAssertEqual(diff.changed, [.init(line: 1, text: "A b.txt"), .init(line: 3, text: "?? d.txt")])
+        XCTAssertEqual(diff.removed, [.init(line: 1, text: "?? b.txt")])
+        XCTAssertTrue(OutputDiff.between(new, new).isEmpty)
+        XCTAssertEqual(OutputDiff.between([], ["x"]).changed, [.init(line: 0, text: "x")])
+    }
+
+    /// The first run reports every line; a rerun with the same output and
+    /// status reports nothing, and a stopped watch is gone.
+    func testWatchReportsOnlyChanges() throws {
+        let watcher = CommandWatcher()
+        XCTAssertEqual(watcher.start(command: " ", directory: nil).map(\.id), .failure(.emptyCommand))
+        XCTAssertEqual(
+            watcher.start(command: "date", directory: nil, interval: 0.1).map(\.id), .failure(.invalidInterval))
+
+        let first = expectation(description: "first run")
+        var changes: [CommandWatchChange] = []
+        watcher.onChange = { change in
+            changes.append(change)
+            first.fulfill()
+        }
+        let watch = try watcher.start(command: "printf 'one\\ntwo\\n'; exit 3", directory: "/tmp", interval: 0.5).get()
+        XCTAssertEqual(watch.id, "watch-1")
+        XCTAssertEqual(watcher.list().map(\.id), ["watch-1"])
+        wait(for: [first], timeout: 5)
+        XCTAssertEqual(changes.first?.diff.changed.map(\.text), ["one", "two"])
+        XCTAssertEqual(changes.first?.watch.exitCode, 3)
+
+        let rerun = expectation(description: "unchanged rerun")
+        DispatchQueue.global().asyncAfter(deadline: .now() + 1.2) { rerun.fulfill() }
+        wait(for: [rerun], timeout: 5)
+        XCTAssertGreaterThan(watcher.watch(watch.id)?.runs ?? 0, 1)
+        XCTAssertEqual(changes.count, 1)
+        let output = try XCTUnwrap(watcher.output(watch.id)?["lines"] as? [[String: Any]])
+        XCTAssertEqual(output.compactMap { $0["changed"] as? Bool }, [true, true])
+
+        XCTAssertTrue(watcher.stop(watch.id))
+        XCTAssertFalse(watcher.stop(watch.id))
+        XCTAssertTrue(watcher.list().isEmpty)
+    }
+}
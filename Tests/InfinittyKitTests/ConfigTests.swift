@@ -17,6 +17,37 @@ final class ConfigTests: XCTestCase {
         XCTAssertNil(reparsed.pet)
     }
 
+    func testProcessTimeoutParsesAndSurvivesSerialization() {
+        var config = AppConfig()
+        config.apply(fileContents: "process-timeout = 90")
+        XCTAssertEqual(config.processTimeout, 90)
+
+        var reparsed = AppConfig()
+        reparsed.apply(fileContents: config.serialize())
+        XCTAssertEqual(reparsed.processTimeout, 90)
+    }
+
+    func testScrollbackLinesParseAndSurviveSerialization() {
+        var config = AppConfig()
+        config.apply(fileContents: "scrollback-lines = 100_000")
+        XCTAssertEqual(config.scrollbackLines, 100_000)
+
+        var reparsed = AppConfig()
+        reparsed.apply(fileContents: config.serialize())
+        XCTAssertEqual(reparsed.scrollbackLines, 100_000)
+    }
+
+    func testRestoreWorkspaceOptOutSurvivesSerialization() {
+        XCTAssertTrue(AppConfig().restoreWorkspace)
+        var config = AppConfig()
+        config.apply(fileContents: "restore-workspace = false")
+        XCTAssertFalse(config.restoreWorkspace)
+
+        var reparsed = AppConfig()
+        reparsed.apply(fileContents: config.serialize())
+        XCTAssertFalse(reparsed.restoreWorkspace)
+    }
+
     func testParsePaletteEntry() {
         XCTAssertEqual(AppConfig.parsePaletteEntry("4=#61AFEF")?.index, 4)
         XCTAssertEqual(AppConfig.parsePaletteEntry("4=#61AFEF")?.color, 0x61AFEF)
@@ -56,6 +87,26 @@ final class ConfigTests: XCTestCase {
         XCTAssertEqual(theme.palette[1], Theme.dark.palette[1])
     }
 
+    /// Dark blue text on the default background is lifted to the ratio;
+    /// colours already past it, and backgrounds, are left alone.
+    func testMinimumContrastLiftsForegroundPalette() {
+        var config = AppConfig()
+        config.apply(fileContents: "minimum-contrast = 4.5\npalette = 4=#000080")
+        XCTAssertEqual(config.minimumContrast, 4.5)
+        let theme = Theme.dark.applying(config)
+        let code = ColorCode.indexed(4)
+        let text = theme.resolve(code, isFG: true, flags: 0)
+        XCTAssertGreaterThanOrEqual(ColorContrast.ratio(text, theme.background), 4.5)
+        XCTAssertLessThan(ColorContrast.ratio(text, theme.background), 4.7)
+        XCTAssertEqual(theme.resolve(code, isFG: false, flags: 0), Theme.rgba(0x000080))
+        XCTAssertEqual(theme.resolve(ColorCode.indexed(15), isFG: true, flags: 0), Theme.rgba(0xFFFFFF))
+        XCTAssertNil(Theme.dark.applying(AppConfig()).foregroundPalette)
+
+        var reparsed = AppConfig()
+        reparsed.apply(fileContents: config.serialize())
+        XCTAssertEqual(reparsed.minimumContrast, 4.5)
+    }
+
     func testSerializeRoundTripsPalette() {
         var config = AppConfig()
         config.palette = [4: 0x61AFEF, 1: 0xE06C75]
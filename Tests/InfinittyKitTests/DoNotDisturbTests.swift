@@ -0,0 +1,47 @@
+import XCTest
+@testable import InfinittyKit
+
+final class DoNotDisturbTests: XCTestCase {
+
+    func testParseFocusDatabase() {
+        let assertions = Data("""
+            {"data":[{"storeAssertionRecords":[{"assertionDetails":
+              {"assertionDetailsModeIdentifier":"com.apple.focus.work"}}]}]}
+            """.utf8)
+        let configurations = Data("""
+            {"data":[{"modeConfigurations":{"com.apple.focus.work":{"mode":{"name":"Work"}}}}]}
+            """.utf8)
+        XCTAssertEqual(
+            FocusStatus.parse(assertions: assertions, configurations: configurations),
+            FocusStatus(isFocused: true, name: "Work"))
+        XCTAssertEqual(
+            FocusStatus.parse(assertions: assertions, configurations: nil), FocusStatus(isFocused: true, name: "Focus"))
+        XCTAssertEqual(
+            FocusStatus.parse(assertions: Data(#"{"data":[{}]}"#.utf8), configurations: nil),
+            FocusStatus(isFocused: false))
+        XCTAssertNil(FocusStatus.parse(assertions: Data("nope".utf8), configurations: nil))
+    }
+
+    /// `auto` follows Focus; `on` and `off` ignore it. Changes are reported
+    /// once each.
+    func testModes() {
+        var focus = FocusStatus(isFocused: false)
+        let dnd = DoNotDisturb(readFocus: { focus })
+        var changes: [Bool] = []
+        dnd.onChange = { changes.append($0.isActive) }
+
+        dnd.setMode(.auto)
+        XCTAssertFalse(dnd.isActive)
+        focus = FocusStatus(isFocused: true, name: "Presenting")
+        dnd.refresh()
+        dnd.refresh()
+        XCTAssertTrue(dnd.isActive)
+        XCTAssertEqual(dnd.snapshot.wire["focusName"] as? String, "Presenting")
+
+        dnd.setMode(.off)
+        XCTAssertFalse(dnd.isActive)
+        dnd.setMode(.on)
+        XCTAssertTrue(dnd.isActive)
+        XCTAssertEqual(changes, [true, false, true])
+    }
+}
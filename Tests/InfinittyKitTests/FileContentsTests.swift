@@ -0,0 +1,70 @@
+import XCTest
+@testable import InfinittyKit
+
+final class FileContentsTests: XCTestCase {
+    private var dir: URL!
+
+    override func setUpWithError() throws {
+        dir = FileManager.default.temporaryDirectory
+            .appendingPathComponent("infinitty-contents-\(UUID().uuidString)")
+        try FileManager.default.createDirectory(at: dir, withIntermediateDirectories: true)
+    }
+
+    override func tearDownWithError() throws {
+        try? FileManager.default.removeItem(at: dir)
+    }
+
+    private func write(_ data: Data, _ name: String) throws -> URL {
+        let url = dir.appendingPathComponent(name)
+        try data.write(to: url)
+        return url
+    }
+
+    func testTextWithinLimit() throws {
+        let url = try write(Data("hello\n".utf8), "a.txt")
+        XCTAssertEqual(
+            try FileContents.text(of: url, limit: 100).get(),
+            FileText(text: "hello\n", size: 6, truncated: false))
+    }
+
+    /// Over the limit is an error unless truncation is asked for, and a cut
+    /// never splits a multi-byte character.
+    func testOversizedFileIsRefusedOrTruncatedOnACharacterBoundary() throws {
+        let url = try write(Data("ab€cd".utf8), "euro.txt") // € is 3 bytes
+        XCTAssertEqual(
+            FileContents.text(of: url, limit: 3).failure,
+            .tooLarge(size: 7, limit: 3))
+        let cut = try FileContents.text(of: url, limit: 4, truncate: true).get()
+        XCTAssertEqual(cut.text, "ab")
+        XCTAssertTrue(cut.truncated)
+        XCTAssertEqual(cut.size, 7)
+    }
+
+    func testBinaryAndDirectoriesAreRefused() throws {
+        let url = try write(Data([0x50, 0x4B, 0x00, 0x03]), "a.zip")
+        XCTAssertEqual(FileContents.text(of: url, limit: 100).failure, .binary)
+        XCTAssertEqual(FileContents.text(of: dir, limit: 100).failure, .notRegularFile)
+    }
+
+    /// Chunked hashing matches the one-shot digest and reports progress per
+    /// chunk.
+    func testSHA256StreamsInChunks() throws {
+        let url = try write(Data("abc".utf8), "abc")
+        var seen: [Int64] = []
+        let digest = try FileContents.sha256(of: url, chunkSize: 2, progress: { seen.append($0) }).get()
+        XCTAssertEqual(digest, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        XCTAssertEqual(seen, [2, 3])
+
+        let empty = try write(Data(), "empty")
+        XCTAssertEqual(
+            try FileContents.sha256(of: empty).get(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+    }
+}
+
+private extension Result {
+    var failure: Failure? {
+        if case .failure(let error) = self { return error }
+        return nil
+    }
+}
@@ -0,0 +1,150 @@
+import XCTest
+@testable import InfinittyKit
+
+final class FileOperationsTests: XCTestCase {
+    private var root: String!
+    private let fm = FileManager.default
+
+    override func setUpWithError() throws {
+        root = (NSTemporaryDirectory() as NSString)
+            .appendingPathComponent("infinitty-fileops-\(UUID().uuidString)")
+        try fm.createDirectory(atPath: root + "/src/nested", withIntermediateDirectories: true)
+        try "alpha\n".write(toFile: root + "/src/a.txt", atomically: true, encoding: .utf8)
+        try "beta\n".write(toFile: root + "/src/nested/b.txt", atomically: true, encoding: .utf8)
+    }
+
+    override func tearDownWithError() throws {
+        try? fm.removeItem(atPath: root)
+    }
+
+    /// Symlinks stay links (even dangling ones) and mode/mtime survive.
+    func testCopyPreservesSymlinksAndMetadata() throws {
+        try fm.createSymbolicLink(atPath: root + "/src/link", withDestinationPath: "a.txt")
+        try fm.createSymbolicLink(atPath: root + "/src/dangling", withDestinationPath: "missing")
+        let stamp = Date(timeIntervalSince1970: 1_600_000_000)
+        try fm.setAttributes(
+            [.posixPermissions: 0o640, .modificationDate: stamp],
+            ofItemAtPath: root + "/src/a.txt")
+
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src"), to: URL(fileURLWithPath: root + "/dst"))
+        XCTAssertTrue(result.succeeded, "\(result.failures)")
+        XCTAssertEqual(result.entries, 6) // dst, a.txt, link, dangling, nested, b.txt
+        XCTAssertEqual(result.bytes, 11)
+
+        XCTAssertEqual(try fm.destinationOfSymbolicLink(atPath: root + "/dst/link"), "a.txt")
+        XCTAssertEqual(try fm.destinationOfSymbolicLink(atPath: root + "/dst/dangling"), "missing")
+        let attrs = try fm.attributesOfItem(atPath: root + "/dst/a.txt")
+        XCTAssertEqual((attrs[.posixPermissions] as? NSNumber)?.intValue, 0o640)
+        XCTAssertEqual(attrs[.modificationDate] as? Date, stamp)
+        XCTAssertEqual(try String(contentsOfFile: root + "/dst/nested/b.txt"), "beta\n")
+    }
+
+    func testSkipPolicyLeavesExistingFilesAndMergesDirectories() throws {
+        try fm.createDirectory(atPath: root + "/dst", withIntermediateDirectories: true)
+        try "keep\n".write(toFile: root + "/dst/a.txt", atomically: true, encoding: .utf8)
+
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src"), to: URL(fileURLWithPath: root + "/dst"),
+            conflict: .skip)
+        XCTAssertTrue(result.succeeded)
+        XCTAssertEqual(result.skipped, [root + "/dst/a.txt"])
+        XCTAssertEqual(try String(contentsOfFile: root + "/dst/a.txt"), "keep\n")
+        XCTAssertTrue(fm.fileExists(atPath: root + "/dst/nested/b.txt"))
+    }
+
+    func testOverwritePolicyReplacesExistingEntries() throws {
+        try fm.createDirectory(atPath: root + "/dst", withIntermediateDirectories: true)
+        try "old\n".write(toFile: root + "/dst/a.txt", atomically: true, encoding: .utf8)
+
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src"), to: URL(fileURLWithPath: root + "/dst"),
+            conflict: .overwrite)
+        XCTAssertTrue(result.succeeded, "\(result.failures)")
+        XCTAssertEqual(try String(contentsOfFile: root + "/dst/a.txt"), "alpha\n")
+    }
+
+    func testRenamePolicyCopiesAlongside() throws {
+        try fm.createDirectory(atPath: root + "/out", withIntermediateDirectories: true)
+        try "other\n".write(toFile: root + "/out/a.txt", atomically: true, encoding: .utf8)
+
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src/a.txt"), to: URL(fileURLWithPath: root + "/out/a.txt"),
+            conflict: .rename)
+        XCTAssertTrue(result.succeeded, "\(result.failures)")
+        XCTAssertEqual(try String(contentsOfFile: root + "/out/a.txt"), "other\n")
+        XCTAssertEqual(try String(contentsOfFile: root + "/out/a 2.txt"), "alpha\n")
+    }
+
+    /// An unreadable entry is reported; its siblings are still copied.
+    func testPerEntryFailuresDoNotAbortTheTree() throws {
+        try "secret\n".write(toFile: root + "/src/locked.txt", atomically: true, encoding: .utf8)
+        try fm.setAttributes([.posixPermissions: 0], ofItemAtPath: root + "/src/locked.txt")
+        defer { try? fm.setAttributes([.posixPermissions: 0o644], ofItemAtPath: root + "/src/locked.txt") }
+        try XCTSkipIf(fm.isReadableFile(atPath: root + "/src/locked.txt"), "running as root")
+
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src"), to: URL(fileURLWithPath: root + "/dst"))
+        XCTAssertEqual(result.failures.map(\.path), [root + "/src/locked.txt"])
+        XCTAssertTrue(fm.fileExists(atPath: root + "/dst/a.txt"))
+        XCTAssertTrue(fm.fileExists(atPath: root + "/dst/nested/b.txt"))
+    }
+
+    func testCopyIntoItselfIsRejected() {
+        let result = FileOperations.copy(
+            URL(fileURLWithPath: root + "/src"), to: URL(fileURLWithPath: root + "/src/nested/again"))
+        XCTAssertEqual(result.failures.count, 1)
+        XCTAssertFalse(fm.fileExists(atPath: root + "/src/nested/again"))
+    }
+
+    func testMoveRenamesWithinVolumeAndHonoursConflicts() throws {
+        var result = FileOperations.move(
+            URL(fileURLWithPath: root + "/src/a.txt"), to: URL(fileURLWithPath: root + "/src/moved.txt"))
+        XCTAssertTrue(result.succeeded)
+        XCTAssertEqual(result.entries, 1)
+        XCTAssertFalse(fm.fileExists(atPath: root + "/src/a.txt"))
+
+        result = FileOperations.move(
+            URL(fileURLWithPath: root + "/src/nested/b.txt"),
+            to: URL(fileURLWithPath: root + "/src/moved.txt"), conflict: .skip)
+        XCTAssertEqual(result.skipped, [root + "/src/moved.txt"])
+        XCTAssertTrue(fm.fileExists(atPath: root + "/src/nested/b.txt"))
+
+        result = FileOperations.move(
+            URL(fileURLWithPath: root + "/src/nested/b.txt"),
+            to: URL(fileURLWithPath: root + "/src/moved.txt"), conflict: .rename)
+        XCTAssertTrue(result.succeeded)
+        XCTAssertEqual(result.warnings.count, 1)
+        XCTAssertEqual(try String(contentsOfFile: root + "/src/moved 2.txt"), "beta\n")
+    }
+
+    func testDeleteCountsEntriesAndBytesWithoutFollowingLinks() throws {
+        try fm.createDirectory(atPath: root + "/keep", withIntermediateDirectories: true)
+        try "kept\n".write(toFile: root + "/keep/k.txt", atomically: true, encoding: .utf8)
+        try fm.createSymbolicLink(atPath: root + "/src/out", withDestinationPath: root + "/keep")
+
+        let result = FileOperations.delete(URL(fileURLWithPath: root + "/src"))
+        XCTAssertTrue(result.succeeded, "\(result.failures)")
+        XCTAssertEqual(result.entries, 5) // src, a.txt, out, nested, b.txt
+        XCTAssertEqual(result.bytes, 11)
+        XCTAssertFalse(fm.fileExists(atPath: root + "/src"))
+        XCTAssertTrue(fm.fileExists(atPath: root + "/keep/k.txt"))
+    }
+
+    func testResultEncodesFailuresForTheSocket() {
+        var result = FileOperationResult()
+        result.entries = 2
+        result.failures = [FileOperationFailure(path: "/x", message: "Permission denied")]
+        let json = result.jsonObject
+        XCTAssertEqual(json["entries"] as? Int, 2)
+        XCTAssertEqual((json["failures"] as? [[String: String]])?.first?["path"], "/x")
+        XCTAssertTrue(JSONSerialization.isValidJSONObject(json))
+    }
+
+    func testUniqueDestinationNaming() {
+        let taken: Set<String> = ["/d/a 2.txt"]
+        XCTAssertEqual(FileOperations.uniqueDestination(for: "/d/a.txt") { taken.contains($0) }, "/d/a 3.txt")
+        XCTAssertEqual(FileOperations.uniqueDestination(for: "/d/folder") { _ in false }, "/d/folder 2")
+        XCTAssertEqual(FileOperations.uniqueDestination(for: "/d/.env") { _ in false }, "/d/.env 2")
+    }
+}
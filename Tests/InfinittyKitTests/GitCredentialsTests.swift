@@ -0,0 +1,62 @@
+import XCTest
+@testable import InfinittyKit
+
+final class GitCredentialsTests: XCTestCase {
+    func testPromptParsing() {
+        let password = GitCredentialPrompt(id: "1", prompt: "Password for 'https://me%40work@git.example.com:8443': ")
+        XCTAssertEqual(password.url, "https://git.example.com:8443")
+        XCTAssertEqual(password.username, "me@work")
+        XCTAssertTrue(password.isSecret)
+
+        let username = GitCredentialPrompt(id: "2", prompt: "Username for 'https://github.com': ")
+        XCTAssertEqual(username.url, "https://github.com")
+        XCTAssertNil(username.username)
+        XCTAssertFalse(username.isSecret)
+        XCTAssertEqual(username.wire["secret"] as? Bool, false)
+
+        let bare = GitCredentialPrompt(id: "3", prompt: "Enter passphrase: ")
+        XCTAssertNil(bare.url)
+        XCTAssertTrue(bare.isSecret)
+
+        XCTAssertTrue(GitAskpass.isAuthenticationFailure(
+            "remote: Invalid username or password.\nfatal: Authentication failed for 'https://github.com/x/y.git/'"))
+        XCTAssertFalse(GitAskpass.isAuthenticationFailure("fatal: repository 'https://github.com/x/y.git/' not found"))
+    }
+
+    /// Only a command the app is running gets asked for; its prompt waits
+    /// for an answer and then goes away.
+    func testAskWaitsForAnAnswerWithinASession() throws {
+        let dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: dir) }
+        let askpass = GitAskpass(scriptURL: dir.appendingPathComponent("git-askpass.sh"))
+
+        XCTAssertTrue(askpass.session { environment in
+            XCTAssertNil(environment["GIT_ASKPASS"])
+            return (0, "", "")
+        }.status == 0)
+
+        var resolved: [(String, Bool)] = []
+        askpass.onResolved = { prompt, answered in resolved.append((prompt.id, answered)) }
+        askpass.presenter = { prompt in
+            DispatchQueue.global().async {
+                XCTAssertEqual(askpass.pending.map(\.id), [prompt.id])
+                askpass.answer(prompt.id, with: prompt.isSecret ? "s3cret" : nil)
+            }
+        }
+        let prompt = "Password for 'https://me@example.invalid/\(UUID().uuidString)': "
+        _ = askpass.session { environment in
+            let token = environment["INFINITTY_ASKPASS_TOKEN"] ?? ""
+            XCTAssertFalse(token.isEmpty)
+            XCTAssertEqual(environment["GIT_ASKPASS"], dir.appendingPathComponent("git-askpass.sh").path)
+            XCTAssertEqual(askpass.ask(token: token, prompt: prompt), "s3cret")
+            XCTAssertNil(askpass.ask(token: token, prompt: "Username for 'https://example.invalid': "))
+            return (0, "", "")
+        }
+        XCTAssertEqual(resolved.map(\.1), [true, false])
+        XCTAssertEqual(askpass.pending, [])
+        XCTAssertNil(askpass.ask(token: "finished", prompt: prompt))
+        XCTAssertFalse(askpass.answer("1", with: "late"))
+        let script = try String(contentsOf: dir.appendingPathComponent("git-askpass.sh"), encoding: .utf8)
+        XCTAssertEqual(script, GitAskpass.script)
+    }
+}
@@ -0,0 +1,52 @@
+import XCTest
+@testable import InfinittyKit
+
+final class GlobExpansionTests: XCTestCase {
+    func testBraceExpansionFollowsBash() {
+        XCTAssertEqual(GlobExpansion.braces("a{b,c}d"), ["abd", "acd"])
+        XCTAssertEqual(GlobExpansion.braces("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"])
+        XCTAssertEqual(GlobExpansion.braces("x{a,{b,c}}"), ["xa", "xb", "xc"])
+        XCTAssertEqual(GlobExpansion.braces("f{1..3}"), ["f1", "f2", "f3"])
+        XCTAssertEqual(GlobExpansion.braces("{08..10}"), ["08", "09", "10"])
+        XCTAssertEqual(GlobExpansion.braces("{5..1..2}"), ["5", "3", "1"])
+        XCTAssertEqual(GlobExpansion.braces("{a..c}"), ["a", "b", "c"])
+        XCTAssertEqual(GlobExpansion.braces("{a}"), ["{a}"])
+        XCTAssertEqual(GlobExpansion.braces(#"\{a,b}"#), [#"\{a,b}"#])
+        XCTAssertEqual(GlobExpansion.braces("{a,b"), ["{a,b"])
+        XCTAssertEqual(GlobExpansion.braces("{1..2000}").count, GlobExpansion.maxAlternatives)
+    }
+
+    /// Braces, then globs per alternative, each sorted; duplicates go and
+    /// an escaped wildcard matches itself.
+    func testExpandMatchesFiles() throws {
+        let dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: dir) }
+        for sub in ["app", "lib"] {
+            try FileManager.default.createDirectory(
+                at: dir.appendingPathComponent(sub), withIntermediateDirectories: true)
+        }
+        for path in ["app/b.ts", "app/a.ts", "lib/c.ts", "lib/c.js", "*.md", "x.md"] {
+            try Data().write(to: dir.appendingPathComponent(path))
+        }
+        func relative(_ result: GlobExpansion.Result) -> [String] {
+            result.matches.map { String($0.dropFirst(dir.path.count + 1)) }
+        }
+
+        let result = GlobExpansion.expand("{app,lib}/*.ts", in: dir.path)
+        XCTAssertEqual(result.alternatives, ["app/*.ts", "lib/*.ts"])
+        XCTAssertEqual(relative(result), ["app/a.ts", "app/b.ts", "lib/c.ts"])
+        XCTAssertFalse(result.truncated)
+
+        XCTAssertEqual(relative(GlobExpansion.expand("{*,lib/c}.ts", in: dir.path)), ["lib/c.ts"])
+        XCTAssertEqual(relative(GlobExpansion.expand("lib/c.{ts,js,ts}", in: dir.path)), ["lib/c.ts", "lib/c.js"])
+        XCTAssertEqual(relative(GlobExpansion.expand(#"\*.md"#, in: dir.path)), ["*.md"])
+        XCTAssertEqual(relative(GlobExpansion.expand("*.md", in: dir.path)), ["*.md", "x.md"])
+        XCTAssertEqual(relative(GlobExpansion.expand("missing", in: dir.path)), [])
+
+        let capped = GlobExpansion.expand("*/*", in: dir.path, limit: 2)
+        XCTAssertEqual(capped.matches.count, 2)
+        XCTAssertTrue(capped.truncated)
+        XCTAssertEqual(
+            TerminalView.globTooltip(capped, directory: dir.path), "2+ matches\napp/a.ts\napp/b.ts")
+    }
+}
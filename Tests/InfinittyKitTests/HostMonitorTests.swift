@@ -0,0 +1,61 @@
+import XCTest
+@testable import InfinittyKit
+
+final class HostMonitorTests: XCTestCase {
+
+    func testParseMAC() {
+        let expected: [UInt8] = [0x00, 0x11, 0x32, 0xAA, 0xBB, 0xCC]
+        XCTAssertEqual(WakeOnLAN.parseMAC("00:11:32:aa:bb:cc"), expected)
+        XCTAssertEqual(WakeOnLAN.parseMAC("00-11-32-AA-BB-CC"), expected)
+        XCTAssertEqual(WakeOnLAN.parseMAC("001132aabbcc"), expected)
+        XCTAssertNil(WakeOnLAN.parseMAC("00:11:32:aa:bb"))
+        XCTAssertNil(WakeOnLAN.parseMAC("nas.local"))
+    }
+
+    /// Six 0xFF bytes, then the MAC sixteen times: 102 bytes.
+    func testMagicPacket() {
+        let mac: [UInt8] = [1, 2, 3, 4, 5, 6]
+        let packet = WakeOnLAN.magicPacket(mac)
+        XCTAssertEqual(packet.count, 102)
+        XCTAssertEqual(Array(packet.prefix(6)), [UInt8](repeating: 0xFF, count: 6))
+        XCTAssertEqual(Array(packet.suffix(6)), mac)
+    }
+
+    /// The first probe decides either way; after that one lost ping is
+    /// tolerated and the second marks the host down.
+    func testStatusTransitions() {
+        var status = HostStatus(watched: WatchedHost(host: "nas.local", interval: 30))
+        let start = Date(timeIntervalSince1970: 1000)
+        XCTAssertTrue(status.record(rtt: 2.5, at: start))
+        XCTAssertEqual(status.up, true)
+        XCTAssertFalse(status.record(rtt: 3, at: start + 30))
+
+        XCTAssertFalse(status.record(rtt: nil, at: start + 60))
+        XCTAssertEqual(status.up, true)
+        XCTAssertTrue(status.record(rtt: nil, at: start + 90))
+        XCTAssertEqual(status.up, false)
+        XCTAssertEqual(status.since, start + 90)
+        XCTAssertTrue(status.record(rtt: 1, at: start + 120))
+
+        var unreachable = HostStatus(watched: WatchedHost(host: "10.9.9.9", interval: 30))
+        XCTAssertTrue(unreachable.record(rtt: nil, at: start))
+        XCTAssertEqual(unreachable.up, false)
+    }
+
+    /// The watch list is saved and picked up by a fresh monitor.
+    func testWatchListPersists() throws {
+        let url = FileManager.default.temporaryDirectory
+            .appendingPathComponent("hosts-\(UUID().uuidString).json")
+        defer { try? FileManager.default.removeItem(at: url) }
+        let monitor = HostMonitor(url: url)
+        monitor.watch(WatchedHost(host: "127.0.0.1", name: "loopback", mac: "00:11:32:aa:bb:cc", interval: 3600))
+        defer { _ = monitor.unwatch("127.0.0.1") }
+
+        let reloaded = HostMonitor(url: url)
+        reloaded.start()
+        defer { _ = reloaded.unwatch("127.0.0.1") }
+        XCTAssertEqual(reloaded.watched("127.0.0.1")?.name, "loopback")
+        XCTAssertEqual(reloaded.list().map(\.watched.mac), ["00:11:32:aa:bb:cc"])
+        XCTAssertFalse(reloaded.unwatch("192.0.2.1"))
+    }
+}
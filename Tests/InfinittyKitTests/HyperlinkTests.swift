@@ -0,0 +1,45 @@
+import XCTest
+@testable import InfinittyKit
+
+final class HyperlinkTests: XCTestCase {
+    /// Web, mail and file links open; unknown schemes ask; `link-schemes`
+    /// adds to the defaults rather than replacing them.
+    func testPolicyBySchemeAndConfig() throws {
+        let policy = LinkPolicy()
+        XCTAssertEqual(policy.action(for: try XCTUnwrap(URL(string: "HTTPS://example.com"))), .open)
+        XCTAssertEqual(policy.action(for: try XCTUnwrap(URL(string: "mailto:me@example.com"))), .open)
+        XCTAssertEqual(policy.action(for: try XCTUnwrap(URL(string: "x-man-page://ls"))), .ask)
+        XCTAssertEqual(policy.action(for: try XCTUnwrap(URL(string: "relative/path"))), .deny)
+
+        var config = AppConfig()
+        config.apply(fileContents: "link-schemes = vscode:open, HTTPS:ask x-man-page:deny")
+        XCTAssertEqual(config.linkPolicy.action(for: try XCTUnwrap(URL(string: "vscode://file/tmp/a"))), .open)
+        XCTAssertEqual(config.linkPolicy.action(for: try XCTUnwrap(URL(string: "https://example.com"))), .ask)
+        XCTAssertEqual(config.linkPolicy.action(for: try XCTUnwrap(URL(string: "x-man-page://ls"))), .deny)
+        XCTAssertEqual(config.linkPolicy.rules["mailto"], .open)
+
+        config.apply(fileContents: "link-schemes = vscode:launch")
+        XCTAssertEqual(config.linkPolicy.rules["vscode"], .open)
+        XCTAssertNil(LinkPolicy.parse("1abc:open"))
+        XCTAssertNil(LinkPolicy.scheme("java script"))
+    }
+
+    /// A file link that would launch something asks even though file
+    /// links open.
+    func testFileLinksToProgramsAsk() throws {
+        let policy = LinkPolicy()
+        let dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        try FileManager.default.createDirectory(at: dir, withIntermediateDirectories: true)
+        defer { try? FileManager.default.removeItem(at: dir) }
+        let script = dir.appendingPathComponent("run")
+        let notes = dir.appendingPathComponent("notes.txt")
+        try Data("#!/bin/sh\n".utf8).write(to: script)
+        try Data("hi\n".utf8).write(to: notes)
+        try FileManager.default.setAttributes([.posixPermissions: 0o755], ofItemAtPath: script.path)
+
+        XCTAssertEqual(policy.action(for: notes), .open)
+        XCTAssertEqual(policy.action(for: script), .ask)
+        XCTAssertEqual(policy.action(for: URL(fileURLWithPath: "/Applications/Calculator.app")), .ask)
+        XCTAssertEqual(policy.action(for: dir), .open)
+    }
+}
@@ -0,0 +1,39 @@
+import XCTest
+@testable import InfinittyKit
+
+final class IdlePanesTests: XCTestCase {
+    func testDurations() {
+        XCTAssertEqual(IdlePolicy.duration("8h"), 8 * 3600)
+        XCTAssertEqual(IdlePolicy.duration(" 90m "), 90 * 60)
+        XCTAssertEqual(IdlePolicy.duration("2d"), 2 * 86_400)
+        XCTAssertEqual(IdlePolicy.duration("3600s"), 3600)
+        XCTAssertEqual(IdlePolicy.duration("1.5"), 1.5 * 3600)
+        XCTAssertEqual(IdlePolicy.duration("off"), 0)
+        XCTAssertNil(IdlePolicy.duration("soon"))
+        XCTAssertNil(IdlePolicy.duration("-1h"))
+    }
+
+    /// Due once the timeout has passed at the prompt, and only once for
+    /// the same stretch of idleness.
+    func testDue() {
+        let policy = IdlePolicy(timeout: 3600, action: .hibernate)
+        let last = Date(timeIntervalSince1970: 1_000_000)
+        let later = last.addingTimeInterval(3601)
+        XCTAssertFalse(policy.isDue(lastActivity: last, atPrompt: true, handled: nil, now: last.addingTimeInterval(60)))
+        XCTAssertTrue(policy.isDue(lastActivity: last, atPrompt: true, handled: nil, now: later))
+        XCTAssertFalse(policy.isDue(lastActivity: last, atPrompt: false, handled: nil, now: later))
+        XCTAssertFalse(policy.isDue(lastActivity: last, atPrompt: true, handled: last, now: later))
+        XCTAssertFalse(IdlePolicy().isDue(lastActivity: last, atPrompt: true, handled: nil, now: later))
+    }
+
+    func testSnapshotIsOwnerOnly() throws {
+        let directory = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: directory) }
+        let date = Date(timeIntervalSince1970: 0)
+        let url = try XCTUnwrap(IdleSnapshots.save("<html></html>", pane: 7, at: date, in: directory))
+        XCTAssertTrue(url.lastPathComponent.hasPrefix("pane-7-") && url.pathExtension == "html")
+        XCTAssertEqual(try String(contentsOf: url, encoding: .utf8), "<html></html>")
+        let attributes = try FileManager.default.attributesOfItem(atPath: url.path)
+        XCTAssertEqual((attributes[.posixPermissions] as? NSNumber)?.intValue, 0o600)
+    }
+}
@@ -0,0 +1,35 @@
+import XCTest
+@testable import InfinittyKit
+
+final class InputBroadcastTests: XCTestCase {
+
+    func testGroupsTrackMembersAndPeers() throws {
+        var groups = InputBroadcastGroups()
+        XCTAssertEqual(groups.create([]), .failure(.noPanes))
+        let servers = try groups.create([1, 2, 2, 3]).get()
+        XCTAssertEqual(servers, InputBroadcastGroup(id: "group-1", panes: [1, 2, 3]))
+        let pair = try groups.create([3, 4]).get()
+        XCTAssertEqual(pair.id, "group-2")
+
+        XCTAssertEqual(groups.peers(of: 3), [1, 2, 4])
+        XCTAssertEqual(groups.peers(of: 1), [2, 3])
+        XCTAssertEqual(groups.peers(of: 9), [])
+
+        XCTAssertEqual(try groups.add([5, 1], to: "group-2").get().panes, [3, 4, 5, 1])
+        XCTAssertEqual(groups.add([6], to: "group-9"), .failure(.notFound("group-9")))
+        XCTAssertEqual(try groups.remove([1, 2], from: "group-1").get().panes, [3])
+    }
+
+    /// A closed pane leaves every group; groups it was the last member of
+    /// are dropped.
+    func testForgettingAPaneDropsEmptiedGroups() throws {
+        var groups = InputBroadcastGroups()
+        _ = try groups.create([1]).get()
+        _ = try groups.create([1, 2]).get()
+        groups.forget(pane: 1)
+        XCTAssertEqual(groups.groups, [InputBroadcastGroup(id: "group-2", panes: [2])])
+        XCTAssertTrue(try groups.remove([2], from: "group-2").get().panes.isEmpty)
+        XCTAssertTrue(groups.groups.isEmpty)
+        XCTAssertFalse(groups.delete("group-2"))
+    }
+}
@@ -0,0 +1,53 @@
+import XCTest
+@testable import InfinittyKit
+
+final class KeyboardMacrosTests: XCTestCase {
+    private var url: URL!
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("macros-\(UUID().uuidString).json")
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    /// Each write keeps the pause before it, the first none; playback
+    /// divides the pauses by its speed.
+    func testRecordingKeepsTimingAndScalesOnPlayback() throws {
+        var recorder = MacroRecorder(name: "gdb", pane: 4)
+        XCTAssertEqual(recorder.macro(), .failure(.nothingRecorded("gdb")))
+        recorder.record(Array("break main\r".utf8), at: 100)
+        recorder.record([], at: 100.5)
+        recorder.record(Array("run\r".utf8), at: 101)
+        recorder.record([0x1B, 0x5B, 0x41], at: 104)
+
+        let macro = try recorder.macro().get()
+        XCTAssertEqual(macro.steps.map(\.delay), [0, 1, 3])
+        XCTAssertEqual(macro.duration, 4)
+        XCTAssertEqual(macro.wire["bytes"] as? Int, 18)
+        let schedule = macro.schedule(speed: 2)
+        XCTAssertEqual(schedule.map(\.at), [0, 0.5, 2])
+        XCTAssertEqual(schedule.last?.bytes, [0x1B, 0x5B, 0x41])
+    }
+
+    /// Macros are kept by name, a new recording replaces an old one, and
+    /// they survive a reload.
+    func testMacrosPersistByName() throws {
+        let store = KeyboardMacros(url: url)
+        let created = Date(timeIntervalSince1970: 1_700_000_000)
+        let step = KeyboardMacro.Step(delay: 0, bytes: Data("y\r".utf8))
+        store.save(KeyboardMacro(name: "yes", steps: [step], created: created))
+        store.save(KeyboardMacro(name: "Deploy", steps: [step, step], created: created))
+        store.save(KeyboardMacro(name: "yes", steps: [step, step, step], created: created))
+
+        let reloaded = KeyboardMacros(url: url)
+        XCTAssertEqual(reloaded.macros.map(\.name), ["Deploy", "yes"])
+        XCTAssertEqual(try reloaded.macro(named: "yes").get().steps.count, 3)
+        XCTAssertEqual(try reloaded.macro(named: "yes").get().created, created)
+        XCTAssertEqual(reloaded.remove(named: "nope"), .failure(.notFound("nope")))
+        XCTAssertEqual(try reloaded.remove(named: "Deploy").get().steps.count, 2)
+        XCTAssertEqual(KeyboardMacros(url: url).macros.map(\.name), ["yes"])
+        XCTAssertEqual(KeyboardMacros.name("  "), .failure(.emptyName))
+    }
+}
@@ -0,0 +1,33 @@
+import XCTest
+@testable import InfinittyKit
+
+final class LaunchReadinessTests: XCTestCase {
+
+    /// Deferred work reports once, as a background task, with any failure
+    /// carried into the snapshot.
+    func testBackgroundWorkReportsReadinessOnce() {
+        struct Boom: Error, CustomStringConvertible { var description: String { "no CLI" } }
+        let readiness = LaunchReadiness(registry: BackgroundTaskRegistry())
+        let ready = expectation(description: "ready")
+        ready.expectedFulfillmentCount = 2
+        var reported: [String] = []
+        let lock = NSLock()
+        readiness.onReady = { subsystem, _ in
+            lock.lock()
+            reported.append(subsystem.rawValue)
+            lock.unlock()
+            ready.fulfill()
+        }
+        readiness.runInBackground(.socketSweep) {}
+        readiness.runInBackground(.mcpRegistration) { throw Boom() }
+        wait(for: [ready], timeout: 2)
+
+        readiness.markReady(.socketSweep)
+        XCTAssertEqual(reported.sorted(), ["mcp-registration", "socket-sweep"])
+        let snapshot = readiness.snapshot()
+        XCTAssertEqual((snapshot["socket-sweep"] as? [String: Any])?["ready"] as? Bool, true)
+        XCTAssertEqual((snapshot["mcp-registration"] as? [String: Any])?["error"] as? String, "no CLI")
+        XCTAssertEqual((snapshot["notch"] as? [String: Any])?["ready"] as? Bool, false)
+        XCTAssertFalse(readiness.isReady(.notch))
+    }
+}
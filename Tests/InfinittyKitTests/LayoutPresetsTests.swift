@@ -0,0 +1,140 @@
+import AppKit
+import XCTest
+@testable import InfinittyKit
+
+final class LayoutPresetsTests: XCTestCase {
+    private var url: URL!
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("layouts-\(UUID().uuidString).json")
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    func testShapeJSONRoundTrip() throws {
+        let json = Data(#"{"rows": [{"columns": ["pane", "pane", "pane"]}, "pane"], "sizes": [0.7, 0.3]}"#.utf8)
+        let shape = try JSONDecoder().decode(LayoutShape.self, from: json)
+        XCTAssertEqual(shape, .split(vertical: false, children: [
+            .split(vertical: true, children: [.pane, .pane, .pane], sizes: []),
+            .pane,
+        ], sizes: [0.7, 0.3]))
+        XCTAssertEqual(shape.paneCount, 4)
+        XCTAssertEqual(LayoutShape(wire: shape.wire), shape)
+        XCTAssertNil(LayoutShape(wire: "window"))
+        XCTAssertNil(LayoutShape(wire: ["columns": "pane"]))
+    }
+
+    /// Missing or mismatched sizes become even shares and a split with one
+    /// child is just that child.
+    func testNormalized() {
+        let shape = LayoutShape.split(vertical: true, children: [
+            .split(vertical: false, children: [.pane], sizes: [5]),
+            .split(vertical: false, children: [.pane, .pane], sizes: [1]),
+        ], sizes: [3, 1])
+        XCTAssertEqual(shape.normalized, .split(vertical: true, children: [
+            .pane,
+            .split(vertical: false, children: [.pane, .pane], sizes: [0.5, 0.5]),
+        ], sizes: [0.75, 0.25]))
+        XCTAssertEqual(LayoutShape.dividerRatios(for: [0.25, 0.25, 0.5]), [0.25, 0.5])
+    }
+
+    /// A resize preset gives one child its share, clamped, and evens out the
+    /// rest; the divider ratios follow.
+    func testSizesGivingOneChildAShare() {
+        XCTAssertEqual(LayoutShape.sizes(count: 2, giving: 0, share: 0.75), [0.75, 0.25])
+        XCTAssertEqual(LayoutShape.sizes(count: 3, giving: 2, share: 0.5), [0.25, 0.25, 0.5])
+        let clamped = LayoutShape.sizes(count: 2, giving: 1, share: 1)
+        XCTAssertEqual(clamped[1], 0.9)
+        XCTAssertEqual(clamped[0], 0.1, accuracy: 1e-9)
+        XCTAssertEqual(LayoutShape.sizes(count: 1, giving: 0, share: 0.5), [1])
+        XCTAssertEqual(LayoutShape.sizes(count: 2, giving: 5, share: 0.5), [])
+        XCTAssertEqual(LayoutShape.dividerRatios(for: LayoutShape.sizes(count: 2, giving: 1, share: 0.25)), [0.75])
+    }
+
+    func testCaptureReadsSplitProportions() {
+        let split = NSSplitView(frame: NSRect(x: 0, y: 0, width: 400, height: 300))
+        split.isVertical = true
+        let panes = (0..<2).map { _ in
+            UtilityPaneView(kind: .browser, contentView: NSView(frame: .zero), background: .windowBackgroundColor)
+        }
+        panes.forEach(split.addArrangedSubview)
+        panes[0].frame = NSRect(x: 0, y: 0, width: 300, height: 300)
+        panes[1].frame = NSRect(x: 300, y: 0, width: 100, height: 300)
+        let container = NSView(frame: split.frame)
+        container.addSubview(split)
+        XCTAssertEqual(
+            LayoutShape.capture(container), .split(vertical: true, children: [.pane, .pane], sizes: [0.75, 0.25]))
+
+        // Leaving a pane out folds its split away; the kept one is listed.
+        var leaves: [NSView] = []
+        XCTAssertEqual(LayoutShape.capture(container, leaves: &leaves) { $0 === panes[1] }, .pane)
+        XCTAssertEqual(leaves, [panes[1]])
+    }
+
+    func testLibrarySavesAndDeletes() throws {
+        let library = LayoutPresetLibrary(url: url)
+        let shape = LayoutShape.split(vertical: true, children: [.pane, .pane], sizes: [2, 1])
+        let saved = try library.save(name: " review ", shape: shape).get()
+        XCTAssertEqual(saved.name, "review")
+        XCTAssertEqual(saved.shape, .split(vertical: true, children: [.pane, .pane], sizes: [2 / 3.0, 1 / 3.0]))
+        XCTAssertEqual(library.save(name: "grid", shape: shape), .failure(.invalidName))
+        let tooMany = LayoutShape.split(
+            vertical: true, children: Array(repeating: .pane, count: LayoutShape.maxPanes + 1), sizes: [])
+        XCTAssertEqual(library.save(name: "wall", shape: tooMany), .failure(.invalidShape))
+
+        let reloaded = LayoutPresetLibrary(url: url)
+        XCTAssertEqual(reloaded.all().count, LayoutPresetLibrary.builtIns.count + 1)
+        XCTAssertEqual(reloaded.preset(named: "review"), saved)
+        XCTAssertEqual(reloaded.preset(named: "three-columns-wide-bottom")?.shape.paneCount, 4)
+        XCTAssertTrue(reloaded.delete("review"))
+        XCTAssertFalse(reloaded.delete("grid"))
+        XCTAssertNil(LayoutPresetLibrary(url: url).preset(named: "review"))
+    }
+
+    /// A pane named twice keeps its last percent in its first place; any
+    /// bad entry rejects the whole batch.
+    func testResizeBatchKeepsLastSizePerPane() throws {
+        let batch = try XCTUnwrap(PaneResizeBatch.parse(
+            #"[{"pane": 2, "percent": 30}, {"pane": "browser-1a2b3c4d", "percent": 40}, {"pane": 2, "percent": 67}]"#))
+        XCTAssertEqual(batch.map { $0.pane }, ["2", "browser-1a2b3c4d"])
+        XCTAssertEqual(batch.map { $0.share }, [0.67, 0.4])
+        XCTAssertNil(PaneResizeBatch.parse(#"[{"pane": 2, "percent": 95}]"#))
+        XCTAssertNil(PaneResizeBatch.parse(#"[{"percent": 50}]"#))
+        XCTAssertNil(PaneResizeBatch.parse("[]"))
+    }
+
+    /// The divider animation ends on its target and never writes a split
+    /// the position it already has.
+    func testDividerAnimationLandsWithoutRepeatingPositions() {
+        let split = RecordingSplitView(frame: NSRect(x: 0, y: 0, width: 300, height: 100))
+        split.isVertical = true
+        (0..<2).forEach { _ in split.addArrangedSubview(NSView(frame: .zero)) }
+        let container = NSView(frame: split.frame)
+        container.addSubview(split)
+        split.setPosition(100, ofDividerAt: 0)
+        split.positions.removeAll()
+
+        let done = expectation(description: "finished")
+        let animation = PaneDividerAnimation(
+            keyframes: [PaneDividerKeyframe(split: split, start: [100], end: [200])], duration: 0.05
+        ) { done.fulfill() }
+        animation.start()
+        wait(for: [done], timeout: 2)
+
+        XCTAssertEqual(split.positions.first, 100)
+        XCTAssertEqual(split.positions.last, 200)
+        XCTAssertEqual(split.arrangedSubviews[0].frame.maxX, 200, accuracy: 1)
+        XCTAssertFalse(zip(split.positions, split.positions.dropFirst()).contains { $0 == $1 })
+    }
+}
+
+private final class RecordingSplitView: NSSplitView {
+    var positions: [CGFloat] = []
+
+    override func setPosition(_ position: CGFloat, ofDividerAt dividerIndex: Int) {
+        positions.append(position)
+        super.setPosition(position, ofDividerAt: dividerIndex)
+    }
+}
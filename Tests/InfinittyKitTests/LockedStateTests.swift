@@ -0,0 +1,31 @@
+import XCTest
+@testable import InfinittyKit
+
+final class LockedStateTests: XCTestCase {
+
+    func testConcurrentMutationsAreSerialized() {
+        let counter = LockedState(0)
+        DispatchQueue.concurrentPerform(iterations: 1000) { _ in
+            counter.withLock { $0 += 1 }
+        }
+        XCTAssertEqual(counter.snapshot, 1000)
+    }
+
+    /// A body that throws part-way keeps its earlier writes and leaves the
+    /// lock free for the next caller.
+    func testThrowingBodyReleasesTheLock() {
+        struct Boom: Error {}
+        let state = LockedState([String]())
+        XCTAssertThrowsError(try state.withLock { list in
+            list.append("before")
+            throw Boom()
+        })
+        let released = expectation(description: "released")
+        DispatchQueue.global().async {
+            state.withLock { $0.append("after") }
+            released.fulfill()
+        }
+        wait(for: [released], timeout: 2)
+        XCTAssertEqual(state.snapshot, ["before", "after"])
+    }
+}
@@ -0,0 +1,92 @@
+import XCTest
+@testable import InfinittyKit
+
+final class LogViewerTests: XCTestCase {
+
+    /// JSON, logfmt and plain-text spellings all resolve to one level.
+    func testLevelDetection() {
+        XCTAssertEqual(LogLevel.detect(in: #"{"level":"warning","msg":"slow"}"#), .warn)
+        XCTAssertEqual(LogLevel.detect(in: #"{"level":50,"msg":"boom"}"#), .error)
+        XCTAssertEqual(LogLevel.detect(in: "ts=1 level=debug msg=hi"), .debug)
+        XCTAssertEqual(LogLevel.detect(in: "2024-01-01 12:00:00 ERROR db: refused"), .error)
+        XCTAssertEqual(LogLevel.detect(in: "[info] listening on :8080"), .info)
+        XCTAssertNil(LogLevel.detect(in: "an error occurred in lowercase prose"))
+        XCTAssertNil(LogLevel.detect(in: "INFORMATIONAL banner"))
+    }
+
+    /// Lines split on newlines across chunks, a trailing CR is dropped and
+    /// an unlevelled continuation line inherits the level above it.
+    func testIngestSplitsLinesAndCarriesLevelToContinuations() {
+        let session = LogSession(id: "log-t", source: .file("/dev/null"), filter: LogFilter())
+        session.ingest(Data("ERROR failed\r\n  at mai".utf8))
+        session.ingest(Data("n.swift:3\nINFO ok\n".utf8))
+        let lines = session.read(since: 0, limit: 10, filter: LogFilter())["lines"] as? [[String: Any]]
+        XCTAssertEqual(lines?.map { $0["text"] as? String }, ["ERROR failed", "  at main.swift:3", "INFO ok"])
+        XCTAssertEqual(lines?.map { $0["level"] as? String }, ["error", "error", "info"])
+    }
+
+    /// Regex and level filters are applied in the backend; matches come back
+    /// as UTF-16 highlight ranges and `latestSeq` resumes after the last
+    /// line scanned, not just the last one returned.
+    func testReadFiltersAndHighlights() throws {
+        let session = LogSession(id: "log-t", source: .file("/dev/null"), filter: LogFilter())
+        session.ingest(Data("INFO é timeout\nWARN timeout again\nERROR other\n".utf8))
+        let filter = try LogFilter.parse(pattern: "timeout", level: "warn").get()
+        let result = session.read(since: 0, limit: 10, filter: filter)
+        let lines = result["lines"] as? [[String: Any]]
+        XCTAssertEqual(lines?.count, 1)
+        XCTAssertEqual(lines?.first?["text"] as? String, "WARN timeout again")
+        XCTAssertEqual(lines?.first?["highlights"] as? [[Int]], [[5, 7]])
+        XCTAssertEqual(result["latestSeq"] as? Int, 3)
+
+        let limited = session.read(since: 0, limit: 1, filter: LogFilter())
+        XCTAssertEqual(limited["latestSeq"] as? Int, 1)
+        XCTAssertEqual(limited["more"] as? Bool, true)
+
+        XCTAssertEqual(LogFilter.parse(pattern: "(", level: nil).failure, .badFilter("invalid regex: ("))
+        XCTAssertEqual(LogFilter.parse(pattern: nil, level: "loud").failure, .badFilter("unknown level: loud"))
+    }
+
+    /// A followed file shows appended lines, and pausing holds them back
+    /// until following resumes.
+    func testFollowsAppendedFileLinesAcrossPause() throws {
+        let url = FileManager.default.temporaryDirectory
+            .appendingPathComponent("infinitty-log-\(UUID().uuidString).log")
+        try Data("first\n".utf8).write(to: url)
+        defer { try? FileManager.default.removeItem(at: url) }
+
+        let session = LogSession(id: "log-t", source: .file(url.path), filter: LogFilter())
+        let seen = LockedState([String]())
+        let first = expectation(description: "first")
+        let second = expectation(description: "second")
+        session.onLines = { _, lines in
+            for line in lines {
+                seen.withLock { $0.append(line.text) }
+                if line.text == "first" { first.fulfill() }
+                if line.text == "second" { second.fulfill() }
+            }
+        }
+        try session.start()
+        defer { session.close() }
+        wait(for: [first], timeout: 2)
+
+        session.setPaused(true)
+        let handle = try FileHandle(forWritingTo: url)
+        try handle.seekToEnd()
+        try handle.write(contentsOf: Data("second\n".utf8))
+        try handle.close()
+        Thread.sleep(forTimeInterval: LogSession.pollInterval * 3)
+        XCTAssertEqual(seen.snapshot, ["first"])
+
+        session.setPaused(false)
+        wait(for: [second], timeout: 2)
+        XCTAssertEqual(seen.snapshot, ["first", "second"])
+    }
+}
+
+private extension Result {
+    var failure: Failure? {
+        if case .failure(let error) = self { return error }
+        return nil
+    }
+}
@@ -0,0 +1,109 @@
+import XCTest
+@testable import InfinittyKit
+
+final class MissingCommandTests: XCTestCase {
+
+    /// Only exit 127 with the shell's own message counts, and typed paths
+    /// are never second-guessed.
+    func testDetectsNotFound() {
+        XCTAssertEqual(
+            MissingCommand.program(in: "gti status", exitCode: 127, output: "zsh: command not found: gti"), "gti")
+        XCTAssertEqual(
+            MissingCommand.program(in: "sudo lss -la", exitCode: 127, output: "sudo: lss: command not found"),
+            "lss")
+        XCTAssertEqual(
+            MissingCommand.program(in: "gti", exitCode: 127, output: "fish: Unknown command: gti"), "gti")
+        XCTAssertNil(MissingCommand.program(in: "gti", exitCode: 1, output: "zsh: command not found: gti"))
+        XCTAssertNil(MissingCommand.program(in: "make", exitCode: 127, output: "make: *** [all] Error 127"))
+        XCTAssertNil(
+            MissingCommand.program(in: "./buld", exitCode: 127, output: "zsh: no such file: ./buld not found"))
+    }
+
+    func testEditDistanceCountsTranspositionsOnce() {
+        XCTAssertEqual(MissingCommand.editDistance("gti", "git"), 1)
+        XCTAssertEqual(MissingCommand.editDistance("dokcer", "docker"), 1)
+        XCTAssertEqual(MissingCommand.editDistance("kubctl", "kubectl"), 1)
+        XCTAssertEqual(MissingCommand.editDistance("", "ls"), 2)
+    }
+
+    /// Nearer names first, then the ones used more; short names get less
+    /// slack so `ls` does not suggest every two-letter tool.
+    func testCorrectionsRankByDistanceThenUse() {
+        let known = ["git": 40, "gist": 2, "gitk": 5, "go": 9, "gt": 3]
+        XCTAssertEqual(MissingCommand.corrections(for: "gti", among: known), ["git", "gt"])
+        XCTAssertEqual(MissingCommand.corrections(for: "gitt", among: known), ["git", "gitk", "gist"])
+    }
+
+    /// Corrections rewrite only the program word; installs follow.
+    func testSuggestionsRewriteTheCommandLine() {
+        let suggestions = MissingCommand.suggestions(
+            for: "gti", in: "GIT_TRACE=1 gti status gti", known: ["git": 3], formulae: ["gti"])
+        XCTAssertEqual(suggestions, [
+            CommandSuggestion(kind: .didYouMean, command: "GIT_TRACE=1 git status gti"),
+            CommandSuggestion(kind: .install, command: "brew install gti"),
+        ])
+        XCTAssertEqual(AppEvent.paneSuggestion(
+            pane: 2, command: "gti", program: "gti", suggestions: suggestions
+        ).payload()["event"] as? String, "pane-suggestion")
+    }
+
+    /// A known program with a subcommand it has never run successfully
+    /// gets the nearest one that has; an unknown program goes the
+    /// not-found way, installs included.
+    func testSuggestCorrectionFixesSubcommandsFromHistory() {
+        func record(_ command: String, exit: Int = 0) -> CommandRecord {
+            CommandRecord(command: command, directory: nil, exitCode: exit, started: Date(), duration: 1)
+        }
+        let history = [
+            record("git push origin main"), record("git push"), record("git pull"), record("git status"),
+            record("git psuh", exit: 1), record("kubectl get pods"),
+        ]
+        XCTAssertEqual(
+            MissingCommand.subcommandCorrections(in: "git psuh -f origin", history: history),
+            ["git push -f origin"])
+        XCTAssertEqual(MissingCommand.subcommandCorrections(in: "git pull --rebase", history: history), [])
+        XCTAssertEqual(MissingCommand.subcommandCorrections(in: "git --version", history: history), [])
+
+        let known = MissingCommand.knownPrograms(history: history, directories: [], aliases: ["ll", "git"])
+        XCTAssertEqual(known["ll"], 0)
+        XCTAssertEqual(known["git"], 4)
+        XCTAssertEqual(
+            MissingCommand.suggestCorrection(for: "sudo git stauts", known: known, history: history) { _ in [] },
+            [CommandSuggestion(kind: .didYouMean, command: "sudo git status")])
+        XCTAssertEqual(
+            MissingCommand.suggestCorrection(for: "kubctl get pods", known: known, history: history) { ["kubectl"] },
+            [
+                CommandSuggestion(kind: .didYouMean, command: "kubectl get pods"),
+                CommandSuggestion(kind: .install, command: "brew install kubectl"),
+            ])
+    }
+
+    /// bash/zsh `alias name=…` with or without flags, fish `alias` and
+    /// `abbr`; other lines and quoted junk are skipped.
+    func testAliasesFromStartupFiles() {
+        let rc = """
+        alias ll='ls -la'
+          alias -g G='| grep'
+        alias gst="git status"
+        abbr -a gco git checkout
+        alias serve 'python3 -m http.server'
+        export ALIAS=1
+        alias
+        alias ll='ls -l'
+        """
+        XCTAssertEqual(MissingCommand.aliases(in: rc), ["ll", "G", "gst", "gco", "serve"])
+    }
+
+    /// Both Homebrew index formats: the executables database and the
+    /// signed API cache's formula names.
+    func testHomebrewIndexParsers() throws {
+        let executables = HomebrewIndex.parseExecutables(
+            "ripgrep(14.1.0):rg\nmoreutils:sponge ts parallel\nparallel(2024):parallel sem\n")
+        XCTAssertEqual(executables["rg"], ["ripgrep"])
+        XCTAssertEqual(executables["parallel"], ["moreutils", "parallel"])
+
+        let payload = #"[{"name":"wget"},{"name":"jq"}]"#
+        let cache = try JSONSerialization.data(withJSONObject: ["payload": payload, "signatures": []] as [String: Any])
+        XCTAssertEqual(HomebrewIndex.parseFormulaJSON(cache), ["wget", "jq"])
+    }
+}
@@ -414,6 +414,28 @@ final class NavigationTests: XCTestCase {
         XCTAssertTrue(created)
     }
 
+    func testTabCommandPaletteOffersSelectionTransforms() {
+        let palette = TabCommandPaletteViewController(titles: ["fish"], selectedIndex: 0)
+        _ = palette.view
+        palette.setQueryForTesting("base64 decode")
+        XCTAssertEqual(palette.filteredTitlesForTesting, ["Base64 decode selection"])
+
+        var transform: TextTransform?
+        palette.onTransformSelection = { transform = $0 }
+        palette.performFirstResultForTesting()
+        XCTAssertEqual(transform, .base64Decode)
+    }
+
+    /// Expressions are answered inline; words with digits in them are not.
+    func testTabCommandPaletteAnswersExpressions() {
+        let palette = TabCommandPaletteViewController(titles: ["fish"], selectedIndex: 0)
+        _ = palette.view
+        palette.setQueryForTesting("1536 MiB in GB")
+        XCTAssertEqual(palette.filteredTitlesForTesting, ["= 1.610612736 GB"])
+        palette.setQueryForTesting("base64")
+        XCTAssertFalse(palette.filteredTitlesForTesting.contains { $0.hasPrefix("=") })
+    }
+
     /// The chrome hides the strip for a single tab (matching macOS) and shows
     /// it once there are multiple.
     func testChromeHidesStripForSingleTab() {
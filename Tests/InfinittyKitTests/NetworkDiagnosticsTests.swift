@@ -0,0 +1,92 @@
+import XCTest
+@testable import InfinittyKit
+
+final class NetworkDiagnosticsTests: XCTestCase {
+
+    /// Anything that could pass for an option never reaches the tools.
+    func testHostValidation() {
+        XCTAssertTrue(NetworkDiagnostics.isValidHost("example.com"))
+        XCTAssertTrue(NetworkDiagnostics.isValidHost("2606:4700::1111"))
+        XCTAssertFalse(NetworkDiagnostics.isValidHost("-c 1000 example.com"))
+        XCTAssertFalse(NetworkDiagnostics.isValidHost("example.com;reboot"))
+        XCTAssertFalse(NetworkDiagnostics.isValidHost(""))
+    }
+
+    /// Reverse lookups of an address use `-x`; names keep the type.
+    func testDigArguments() {
+        XCTAssertEqual(
+            NetworkDiagnostics.digArguments(host: "1.1.1.1", type: .ptr, server: nil).suffix(2),
+            ["-x", "1.1.1.1"])
+        XCTAssertEqual(
+            NetworkDiagnostics.digArguments(host: "example.com", type: .mx, server: "8.8.8.8").suffix(3),
+            ["@8.8.8.8", "example.com", "MX"])
+    }
+
+    func testParseDig() {
+        let output = """
+            ;; Got answer:
+            ;; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 4242
+            ;; flags: qr rd ra; QUERY: 1, ANSWER: 2, AUTHORITY: 0, ADDITIONAL: 1
+
+            ;; ANSWER SECTION:
+            example.com.\t\t300\tIN\tMX\t10 mail.example.com.
+            example.com.\t\t300\tIN\tTXT\t"v=spf1 -all"
+            """
+        let parsed = NetworkDiagnostics.parseDig(output)
+        XCTAssertEqual(parsed.status, "NOERROR")
+        XCTAssertEqual(parsed.answers, [
+            DNSAnswer(name: "example.com.", type: "MX", ttl: 300, value: "10 mail.example.com."),
+            DNSAnswer(name: "example.com.", type: "TXT", ttl: 300, value: "\"v=spf1 -all\""),
+        ])
+        let missing = NetworkDiagnostics.parseDig(";; ->>HEADER<<- opcode: QUERY, status: NXDOMAIN, id: 1")
+        XCTAssertEqual(missing.status, "NXDOMAIN")
+    }
+
+    /// Replies, timeouts and the resolved address; summary lines are not
+    /// replies.
+    func testParsePing() {
+        XCTAssertEqual(
+            NetworkDiagnostics.parsePingLine("64 bytes from 1.1.1.1: icmp_seq=3 ttl=57 time=12.345 ms"),
+            PingReply(sequence: 3, rtt: 12.345, ttl: 57))
+        XCTAssertEqual(
+            NetworkDiagnostics.parsePingLine("Request timeout for icmp_seq 4"), PingReply(sequence: 4, rtt: nil))
+        XCTAssertNil(NetworkDiagnostics.parsePingLine("5 packets transmitted, 4 packets received, 20.0% packet loss"))
+        XCTAssertEqual(
+            NetworkDiagnostics.parsePingAddress("PING example.com (93.184.216.34): 56 data bytes"), "93.184.216.34")
+    }
+
+    func testPingSummary() {
+        let replies = [
+            PingReply(sequence: 0, rtt: 10), PingReply(sequence: 1, rtt: nil), PingReply(sequence: 2, rtt: 20),
+        ]
+        let summary = NetworkDiagnostics.pingSummary(host: "h", address: "10.0.0.1", sent: 4, replies: replies)
+        XCTAssertEqual(summary["received"] as? Int, 2)
+        XCTAssertEqual(summary["loss"] as? Double, 0.5)
+        XCTAssertEqual(summary["min"] as? Double, 10)
+        XCTAssertEqual(summary["avg"] as? Double, 15)
+        XCTAssertEqual(summary["max"] as? Double, 20)
+        XCTAssertEqual(summary["stddev"] as? Double, 5)
+        XCTAssertNil(NetworkDiagnostics.pingSummary(host: "h", address: nil, sent: 2, replies: [])["avg"])
+    }
+
+    /// Probes answered by different routers, lost probes and `!H`
+    /// annotations.
+    func testParseTraceroute() throws {
+        let hop = try XCTUnwrap(NetworkDiagnostics.parseTracerouteLine(
+            " 3  10.0.0.1  5.120 ms  10.0.0.2  6.010 ms *"))
+        XCTAssertEqual(hop.hop, 3)
+        XCTAssertEqual(hop.probes, [
+            .init(address: "10.0.0.1", rtt: 5.12), .init(address: "10.0.0.2", rtt: 6.01), .init(),
+        ])
+        XCTAssertEqual(hop.wire["addresses"] as? [String], ["10.0.0.1", "10.0.0.2"])
+
+        let unreachable = try XCTUnwrap(NetworkDiagnostics.parseTracerouteLine(
+            "12  192.0.2.1  40.1 ms !H  40.2 ms !H  39.9 ms !H"))
+        XCTAssertEqual(unreachable.probes.compactMap(\.rtt), [40.1, 40.2, 39.9])
+        XCTAssertEqual(Set(unreachable.probes.compactMap(\.address)), ["192.0.2.1"])
+
+        XCTAssertEqual(NetworkDiagnostics.parseTracerouteLine("14  * * *")?.probes.count, 3)
+        XCTAssertNil(NetworkDiagnostics.parseTracerouteLine(
+            "traceroute to example.com (93.184.216.34), 30 hops max, 52 byte packets"))
+    }
+}
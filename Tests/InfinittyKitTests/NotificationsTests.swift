@@ -0,0 +1,28 @@
+import XCTest
+@testable import InfinittyKit
+
+final class NotificationsTests: XCTestCase {
+
+    /// Only long commands whose window is in the background notify, and
+    /// never under Do Not Disturb.
+    func testLongCommandPolicy() {
+        let policy = LongCommandPolicy(enabled: true, threshold: 10)
+        XCTAssertTrue(policy.shouldNotify(duration: 12, windowFocused: false, quiet: false))
+        XCTAssertTrue(policy.shouldNotify(duration: 10, windowFocused: false, quiet: false))
+        XCTAssertFalse(policy.shouldNotify(duration: 9.5, windowFocused: false, quiet: false))
+        XCTAssertFalse(policy.shouldNotify(duration: 60, windowFocused: true, quiet: false))
+        XCTAssertFalse(policy.shouldNotify(duration: 60, windowFocused: false, quiet: true))
+        XCTAssertFalse(LongCommandPolicy(enabled: false).shouldNotify(duration: 60, windowFocused: false, quiet: false))
+    }
+
+    func testNotificationContent() {
+        let done = CommandNotifier.content(command: "make test", exitCode: 0, duration: 125)
+        XCTAssertEqual(done.title, "Command finished")
+        XCTAssertTrue(done.body.hasPrefix("make test — "))
+        let failed = CommandNotifier.content(command: nil, exitCode: 2, duration: 30)
+        XCTAssertEqual(failed.title, "Command failed (exit 2)")
+        XCTAssertTrue(failed.body.hasPrefix("after "))
+        let long = CommandNotifier.content(command: String(repeating: "x", count: 200), exitCode: 0, duration: 30)
+        XCTAssertTrue(long.body.hasPrefix(String(repeating: "x", count: 79) + "…"))
+    }
+}
@@ -0,0 +1,56 @@
+import AppKit
+import XCTest
+@testable import InfinittyKit
+
+final class OutputProgressTests: XCTestCase {
+    func testPercentInLine() throws {
+        XCTAssertEqual(ProgressDetector.percent(in: "######################                   45.2%"), 45.2)
+        XCTAssertEqual(ProgressDetector.percent(in: "Receiving objects:  73% (1460/2000), 1.2 MiB"), 73)
+        XCTAssertEqual(ProgressDetector.percent(in: "    Building [=======>      ] 120/300: serde"), 40)
+        XCTAssertEqual(ProgressDetector.percent(in: "a1b2: Downloading [=====>     ]  12MB/48MB"), 25)
+        XCTAssertEqual(
+            try XCTUnwrap(ProgressDetector.percent(in: "   ━━━━━━━━━━╺━━━━━━━━━ 512.0 kB/2.0 MB 1.1 MB/s eta 0:00:02")),
+            25.6, accuracy: 0.01)
+        XCTAssertEqual(ProgressDetector.percent(in: "━━━━━━━━━━━━━━━━━━━ 2.5/10.0 MB"), 25)
+
+        // A count without a bar, or a number past 100%, isn't progress.
+        XCTAssertNil(ProgressDetector.percent(in: "Executed 3/4 tests on 10/16"))
+        XCTAssertNil(ProgressDetector.percent(in: "load went up 250%"))
+        XCTAssertNil(ProgressDetector.percent(in: "[=====>     ] 48MB/12MB"))
+    }
+
+    /// Carriage-return redraws count as they complete; an OSC 9;4 report
+    /// wins until it's removed, and a reset forgets both.
+    func testDetectorFollowsRedrawsAndReports() {
+        let detector = ProgressDetector()
+        XCTAssertFalse(detector.ingest(Data("\r 10%".utf8)))
+        XCTAssertTrue(detector.ingest(Data("\r\u{1B}[32m 20%\u{1B}[0m\r 3".utf8)))
+        XCTAssertEqual(detector.current, PaneProgress(percent: 20, state: .normal, source: .output))
+        XCTAssertFalse(detector.ingest(Data("building...\n".utf8)))
+
+        XCTAssertTrue(detector.ingest(Data("\u{1B}]9;4;1;60\u{07}".utf8)))
+        XCTAssertEqual(detector.current, PaneProgress(percent: 60, state: .normal, source: .osc))
+        XCTAssertFalse(detector.ingest(Data("\r 90%\n\u{1B}]9;4;2".utf8)))
+        XCTAssertTrue(detector.ingest(Data("\u{1B}\\".utf8)))
+        XCTAssertEqual(detector.current, PaneProgress(percent: 60, state: .error, source: .osc))
+        XCTAssertTrue(detector.ingest(Data("\u{1B}]9;4;0\u{07}".utf8)))
+        XCTAssertEqual(detector.current?.percent, 90)
+
+        XCTAssertTrue(detector.reset())
+        XCTAssertNil(detector.current)
+        XCTAssertFalse(detector.reset())
+    }
+
+    func testHeaderBarFollowsProgress() {
+        let header = PaneHeaderView(frame: NSRect(x: 0, y: 0, width: 400, height: 28))
+        XCTAssertNil(header.progressWidthForTesting)
+        header.setProgress(PaneProgress(percent: 25, state: .normal, source: .output))
+        header.layoutSubtreeIfNeeded()
+        XCTAssertEqual(header.progressWidthForTesting, 100)
+        header.setProgress(PaneProgress(percent: nil, state: .indeterminate, source: .osc))
+        header.layoutSubtreeIfNeeded()
+        XCTAssertEqual(header.progressWidthForTesting, 400)
+        header.setProgress(nil)
+        XCTAssertNil(header.progressWidthForTesting)
+    }
+}
@@ -0,0 +1,36 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PTYDaemonTests: XCTestCase {
+    /// Frames split anywhere across reads come out whole and in order.
+    func testFramesRoundTripAcrossPartialReads() {
+        let frames = [
+            PTYDaemonFrame(kind: .output, payload: Array("hello\r\n".utf8)),
+            PTYDaemonFrame(kind: .exit, payload: [0, 0, 0, 0]),
+            PTYDaemonFrame(kind: .hangUp),
+        ]
+        let stream = frames.flatMap(\.encoded)
+        XCTAssertEqual(Array(stream[0..<5]), [0x6F, 0, 0, 0, 7])
+        for split in 0...stream.count {
+            var reader = PTYDaemonFrame.Reader()
+            let decoded = reader.feed(Array(stream[..<split])) + reader.feed(Array(stream[split...]))
+            XCTAssertEqual(decoded, frames, "split at \(split)")
+        }
+
+        XCTAssertEqual(PTYDaemonFrame.resize(cols: 300, rows: 70_000).payload, [0x01, 0x2C, 0xFF, 0xFF])
+        var reader = PTYDaemonFrame.Reader()
+        XCTAssertEqual(reader.feed([0x3F, 0, 0, 0, 1, 0x21] + PTYDaemonFrame(kind: .input).encoded),
+                       [PTYDaemonFrame(kind: .input)])
+    }
+
+    func testSessionListing() {
+        let shell = PTYDaemonSession(["id": 3, "pid": 4242, "attached": true, "started": 1_700_000_000.5])
+        XCTAssertEqual(shell?.id, 3)
+        XCTAssertEqual(shell?.pid, 4242)
+        XCTAssertEqual(shell?.attached, true)
+        XCTAssertEqual(shell?.started, Date(timeIntervalSince1970: 1_700_000_000.5))
+        XCTAssertEqual(shell?.wire["pid"] as? Int, 4242)
+        XCTAssertEqual(PTYDaemonSession(["id": 1, "pid": 2])?.attached, false)
+        XCTAssertNil(PTYDaemonSession(["id": 1]))
+    }
+}
@@ -0,0 +1,36 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PTYTests: XCTestCase {
+    /// A flood starts on a window over the rate, survives a dip that stays
+    /// above a quarter of it, and ends with the bytes it carried.
+    func testFloodMeterEntersAndLeavesFastForward() {
+        var meter = OutputFloodMeter()
+        let chunk = Int(OutputFloodMeter.floodRate * OutputFloodMeter.window)
+        XCTAssertNil(meter.record(512, at: 0))
+        XCTAssertNil(meter.record(512, at: 0.3)) // a quiet window
+        XCTAssertNil(meter.record(chunk / 2, at: 0.4))
+        XCTAssertEqual(meter.record(chunk, at: 0.56), .started)
+        XCTAssertTrue(meter.isFlooding)
+
+        XCTAssertNil(meter.record(chunk / 2, at: 0.82)) // half the rate: still flooding
+        XCTAssertTrue(meter.isFlooding)
+        XCTAssertEqual(meter.record(0, at: 1.1), .ended(bytes: chunk / 2, duration: 1.1 - 0.3))
+        XCTAssertFalse(meter.isFlooding)
+    }
+
+    /// A locked pane drops input but still answers the program's queries.
+    func testInputLockDropsWritesButNotReplies() {
+        let pty = PTY()
+        let sent = LockedState([[UInt8]]())
+        pty.attach(remote: PTY.Remote(
+            write: { bytes in sent.withLock { $0.append(bytes) } }, resize: { _, _ in }, close: {}))
+        pty.write(Array("ls\r".utf8))
+        pty.isInputLocked = true
+        pty.write(Array("rm -rf\r".utf8))
+        pty.reply(Array("\u{1B}[0n".utf8))
+        pty.isInputLocked = false
+        pty.write([0x03])
+        XCTAssertEqual(sent.snapshot, [Array("ls\r".utf8), Array("\u{1B}[0n".utf8), [0x03]])
+    }
+}
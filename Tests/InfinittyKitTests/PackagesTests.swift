@@ -0,0 +1,80 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PackagesTests: XCTestCase {
+
+    /// Known install locations win over PATH, and Homebrew over MacPorts.
+    func testDetectPrefersHomebrew() throws {
+        let installed: Set<String> = ["/opt/local/bin/port", "/custom/bin/brew"]
+        let found = try XCTUnwrap(PackageManager.detect(
+            environment: ["PATH": "/usr/bin:/custom/bin"], isExecutable: installed.contains))
+        XCTAssertEqual(found.manager, .homebrew)
+        XCTAssertEqual(found.path, "/custom/bin/brew")
+
+        let portOnly = try XCTUnwrap(PackageManager.detect(
+            environment: [:], isExecutable: { $0 == "/opt/local/bin/port" }))
+        XCTAssertEqual(portOnly.manager, .macports)
+        XCTAssertNil(PackageManager.detect(environment: ["PATH": "/usr/bin"], isExecutable: { _ in false }))
+    }
+
+    /// `brew list --versions` reports the last (linked) version.
+    func testBrewInstalled() {
+        let packages = PackageListing.brewInstalled("git 2.44.0\nnode 20.1.0 21.7.1\n\n", kind: .formula)
+        XCTAssertEqual(packages.map(\.name), ["git", "node"])
+        XCTAssertEqual(packages.map(\.installed), ["2.44.0", "21.7.1"])
+    }
+
+    /// Formulae list installed versions as arrays; casks may use a string.
+    func testBrewOutdated() throws {
+        let json = """
+            {"formulae": [{"name": "git", "installed_versions": ["2.43.0"], "current_version": "2.44.0"}],
+             "casks": [{"name": "firefox", "installed_versions": "123.0", "current_version": "124.0"}]}
+            """
+        let packages = try XCTUnwrap(PackageListing.brewOutdated(Data(json.utf8)))
+        XCTAssertEqual(packages, [
+            InstalledPackage(name: "git", kind: .formula, installed: "2.43.0", latest: "2.44.0"),
+            InstalledPackage(name: "firefox", kind: .cask, installed: "123.0", latest: "124.0"),
+        ])
+        XCTAssertNil(PackageListing.brewOutdated(Data("Error: no".utf8)))
+    }
+
+    func testPortListings() {
+        XCTAssertEqual(
+            PackageListing.portOutdated("curl                           8.5.0_0 < 8.6.0_0\nnoise\n"),
+            [InstalledPackage(name: "curl", kind: .port, installed: "8.5.0_0", latest: "8.6.0_0")])
+        XCTAssertEqual(
+            PackageListing.portInstalled("  zlib @1.3.1_0 (active)\n").map(\.installed), ["1.3.1_0"])
+    }
+
+    /// Names are checked before reaching a shell; paths are quoted only
+    /// when they need it.
+    func testUpgradeCommand() {
+        XCTAssertEqual(
+            PackageManager.homebrew.upgradeCommand(path: "/opt/homebrew/bin/brew", packages: []),
+            "/opt/homebrew/bin/brew upgrade")
+        XCTAssertEqual(
+            PackageManager.homebrew.upgradeCommand(path: "/My Tools/brew", packages: ["git", "node@20"]),
+            "'/My Tools/brew' upgrade git node@20")
+        XCTAssertEqual(
+            PackageManager.macports.upgradeCommand(path: "/opt/local/bin/port", packages: []),
+            "sudo /opt/local/bin/port upgrade outdated")
+        XCTAssertTrue(PackageManager.isValidName("homebrew/cask/firefox"))
+        XCTAssertFalse(PackageManager.isValidName("--force"))
+        XCTAssertFalse(PackageManager.isValidName("git;rm"))
+    }
+
+    /// Colored step headers split across reads count once each; the
+    /// summary line and anything not asked for do not.
+    func testUpgradeProgress() {
+        let progress = PackageUpgradeProgress(manager: .homebrew, expected: [])
+        let summary = "\u{1B}[34m==>\u{1B}[0m \u{1B}[1mUpgrading 2 outdated packages:\u{1B}[0m\n"
+        XCTAssertEqual(progress.ingest(Data(summary.utf8)), [])
+        XCTAssertEqual(progress.ingest(Data("==> Upgrading git\r\n==> Upgr".utf8)), ["git"])
+        XCTAssertEqual(progress.ingest(Data("ading node\n==> Upgrading git\n".utf8)), ["node"])
+        XCTAssertEqual(progress.started, ["git", "node"])
+
+        let only = PackageUpgradeProgress(manager: .macports, expected: ["curl"])
+        let fetched = "--->  Fetching archive for zlib\n--->  Fetching archive for curl\n"
+        XCTAssertEqual(only.ingest(Data(fetched.utf8)), ["curl"])
+    }
+}
@@ -0,0 +1,34 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PaneDisplayTests: XCTestCase {
+    /// Overrides sit over the config and step back to following it once
+    /// they land on its size again.
+    func testAppliedAndStepped() {
+        var config = AppConfig()
+        config.fontSize = 13
+        config.lineSpacing = 1
+
+        let bigger = PaneDisplay().stepped(2, from: config)
+        XCTAssertEqual(bigger, PaneDisplay(fontSize: 15))
+        XCTAssertEqual(bigger.applied(to: config).fontSize, 15)
+        XCTAssertEqual(bigger.applied(to: config).lineSpacing, 1)
+        XCTAssertTrue(bigger.stepped(-2, from: config).isDefault)
+        XCTAssertEqual(PaneDisplay(fontSize: 72).stepped(1, from: config).fontSize, 72)
+        XCTAssertEqual(PaneDisplay(fontSize: 200).applied(to: config).fontSize, 72)
+    }
+
+    func testUpdatedAndWire() {
+        var config = AppConfig()
+        config.fontSize = 13
+        let display = PaneDisplay(fontSize: 16, lineSpacing: 1.4)
+        XCTAssertEqual(display.updated(with: ["lineSpacing": NSNull()]), PaneDisplay(fontSize: 16))
+        XCTAssertEqual(display.updated(with: ["fontSize": 20]), PaneDisplay(fontSize: 20, lineSpacing: 1.4))
+        XCTAssertNil(display.updated(with: ["fontSize": 3]))
+        XCTAssertNil(display.updated(with: ["lineSpacing": "tall"]))
+
+        let wire = PaneDisplay(lineSpacing: 1.4).wire(over: config)
+        XCTAssertEqual(wire["fontSize"] as? Double, 13)
+        XCTAssertEqual(wire["overrides"] as? [String: Double], ["lineSpacing": 1.4])
+    }
+}
@@ -0,0 +1,53 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PaneSnapshotTests: XCTestCase {
+
+    private func row(_ text: String, fg: UInt32 = ColorCode.defaultFG, flags: UInt16 = 0) -> [Cell] {
+        text.unicodeScalars.map { Cell(glyph: $0.value, fg: fg, bg: ColorCode.defaultBG, flags: flags) }
+    }
+
+    /// Styled runs become one span each, plain text stays bare and is
+    /// escaped, and trailing blank rows are dropped.
+    func testHTMLMergesRunsAndEscapes() {
+        let rows = [
+            row("a<b "),
+            row("ok", fg: ColorCode.indexed(1), flags: CellFlags.bold) + row(" done"),
+            row("   "),
+        ]
+        let html = PaneSnapshot.html(rows: rows, theme: .dark, title: "build & test")
+        XCTAssertTrue(html.contains("<title>build &amp; test</title>"))
+        // Bold brightens palette red (1) to bright red (9), as on screen.
+        XCTAssertTrue(html.contains(
+            "<pre>a&lt;b\n<span style=\"color:#e9737e;font-weight:bold\">ok</span> done</pre>"))
+    }
+
+    /// Inverse video swaps the default colors into explicit ones.
+    func testInverseUsesThemeColors() {
+        let html = PaneSnapshot.html(rows: [row("x", flags: CellFlags.inverse)], theme: .dark, title: "t")
+        XCTAssertTrue(html.contains("<span style=\"color:#0f1216;background:#d7dae0\">x</span>"))
+    }
+
+    /// Published pages are served at their own path only.
+    func testServerServesPublishedPage() throws {
+        let server = PaneSnapshotServer()
+        defer { server.stop() }
+        let url = try server.publish("<p>hi</p>").get()
+        XCTAssertTrue(url.hasPrefix("http://127.0.0.1:"))
+
+        func fetch(_ string: String) throws -> (Int, String) {
+            let done = expectation(description: string)
+            var result = (0, "")
+            URLSession.shared.dataTask(with: try XCTUnwrap(URL(string: string))) { data, response, _ in
+                result = ((response as? HTTPURLResponse)?.statusCode ?? 0,
+                          String(decoding: data ?? Data(), as: UTF8.self))
+                done.fulfill()
+            }.resume()
+            wait(for: [done], timeout: 5)
+            return result
+        }
+        XCTAssertEqual(try fetch(url).0, 200)
+        XCTAssertEqual(try fetch(url).1, "<p>hi</p>")
+        XCTAssertEqual(try fetch(url.replacingOccurrences(of: ".html", with: "0.html")).0, 404)
+    }
+}
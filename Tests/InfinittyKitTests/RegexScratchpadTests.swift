@@ -0,0 +1,66 @@
+import XCTest
+@testable import InfinittyKit
+
+final class RegexScratchpadTests: XCTestCase {
+
+    /// Groups that did not take part come back as null; offsets are UTF-16.
+    func testMatchesAndGroups() throws {
+        let result = try RegexScratchpad.test(
+            pattern: #"(?<key>\w+)=(\d+)?"#, flags: "", sample: "é=1 b=", template: "$2:$1").get()
+        XCTAssertEqual(result["count"] as? Int, 2)
+        XCTAssertEqual(result["groupNames"] as? [String], ["key"])
+        let matches = try XCTUnwrap(result["matches"] as? [[String: Any]])
+        XCTAssertEqual(matches.map { $0["text"] as? String }, ["é=1", "b="])
+        XCTAssertEqual(matches[1]["start"] as? Int, 4)
+
+        let groups = try XCTUnwrap(matches[1]["groups"] as? [Any])
+        XCTAssertEqual((groups[0] as? [String: Any])?["name"] as? String, "key")
+        XCTAssertTrue(groups[1] is NSNull)
+        XCTAssertEqual(result["replaced"] as? String, "1:é :b")
+    }
+
+    func testFlags() throws {
+        let lines = try RegexScratchpad.test(pattern: "^b$", flags: "mi", sample: "a\nB\nc").get()
+        XCTAssertEqual(lines["count"] as? Int, 1)
+        guard case .failure(let error) = RegexScratchpad.options("iq") else { return XCTFail("accepted q") }
+        XCTAssertEqual(error, .unknownFlag("q"))
+    }
+
+    /// Unbalanced groups and classes, stray quantifiers and a trailing
+    /// backslash are located; escaped and bracketed look-alikes are not.
+    func testErrorOffsets() {
+        XCTAssertEqual(RegexScratchpad.errorOffset(in: "a(b|c"), 1)
+        XCTAssertEqual(RegexScratchpad.errorOffset(in: "ab)c"), 2)
+        XCTAssertEqual(RegexScratchpad.errorOffset(in: "x[a-z"), 1)
+        XCTAssertEqual(RegexScratchpad.errorOffset(in: "a|*b"), 2)
+        XCTAssertEqual(RegexScratchpad.errorOffset(in: #"abc\"#), 3)
+        XCTAssertNil(RegexScratchpad.errorOffset(in: #"\(?[(]+(?:x*?)\\"#))
+
+        guard case .failure(let error) = RegexScratchpad.compile("a(b", flags: "") else {
+            return XCTFail("compiled")
+        }
+        XCTAssertEqual(error, .invalidPattern("unclosed group", offset: 1))
+    }
+
+    func testGroupNamesSkipLookbehinds() {
+        XCTAssertEqual(
+            RegexScratchpad.groupNames(in: #"(?<=\$)(\d+)(?<cents>\.\d\d)?(?<!x)"#), [2: "cents"])
+    }
+
+    /// Saved patterns persist, must compile, and can't shadow built-ins.
+    func testLibrary() throws {
+        let url = FileManager.default.temporaryDirectory.appendingPathComponent("regex-\(UUID().uuidString).json")
+        defer { try? FileManager.default.removeItem(at: url) }
+        let library = RegexLibrary(url: url)
+        XCTAssertNoThrow(try library.save(SavedPattern(name: "port", pattern: #":(\d+)"#)).get())
+        XCTAssertEqual(
+            library.save(SavedPattern(name: "broken", pattern: "(")),
+            .failure(.invalidPattern("unclosed group", offset: 0)))
+        XCTAssertEqual(library.save(SavedPattern(name: "UUID", pattern: "x")), .failure(.invalidName))
+
+        let reloaded = RegexLibrary(url: url)
+        XCTAssertEqual(reloaded.all().filter { !$0.builtIn }.map(\.name), ["port"])
+        XCTAssertTrue(reloaded.delete("port"))
+        XCTAssertFalse(reloaded.delete("UUID"))
+    }
+}
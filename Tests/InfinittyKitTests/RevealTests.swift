@@ -0,0 +1,31 @@
+import XCTest
+@testable import InfinittyKit
+
+final class RevealTests: XCTestCase {
+    /// Paths come in as a pane printed them: relative, with `~`, or with a
+    /// compiler's `:line:column` on the end.
+    func testResolve() {
+        let files: Set<String> = [
+            "/work/app", "/work/app/src/main.rs", "/work/app/notes:v2.txt", NSHomeDirectory() + "/.zshrc",
+        ]
+        func resolve(_ text: String, cwd: String? = "/work/app") -> String? {
+            RevealPath.resolve(text, cwd: cwd) { files.contains($0) }
+        }
+        XCTAssertEqual(resolve("src/main.rs"), "/work/app/src/main.rs")
+        XCTAssertEqual(resolve("./src/../src/main.rs:42:7"), "/work/app/src/main.rs")
+        XCTAssertEqual(resolve("/work/app/src/main.rs:42"), "/work/app/src/main.rs")
+        XCTAssertEqual(resolve("notes:v2.txt"), "/work/app/notes:v2.txt")
+        XCTAssertEqual(resolve("."), "/work/app")
+        XCTAssertEqual(resolve("~/.zshrc"), NSHomeDirectory() + "/.zshrc")
+        XCTAssertNil(resolve("src/lib.rs"))
+        XCTAssertNil(resolve("src/main.rs", cwd: nil))
+        XCTAssertNil(resolve("  "))
+    }
+
+    func testComponents() {
+        XCTAssertEqual(RevealPath.components(of: "/work/app/src/main.rs", under: "/work/app"), ["src", "main.rs"])
+        XCTAssertEqual(RevealPath.components(of: "/work/app", under: "/work/app/"), [])
+        XCTAssertNil(RevealPath.components(of: "/work/application", under: "/work/app"))
+        XCTAssertNil(RevealPath.components(of: "/work", under: "/work/app"))
+    }
+}
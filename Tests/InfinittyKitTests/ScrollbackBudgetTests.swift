@@ -0,0 +1,27 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ScrollbackBudgetTests: XCTestCase {
+    func testSizes() {
+        XCTAssertEqual(ScrollbackBudget.size("512MB"), 512 << 20)
+        XCTAssertEqual(ScrollbackBudget.size("2g"), 2 << 30)
+        XCTAssertEqual(ScrollbackBudget.size("1.5 GB"), 3 << 29)
+        XCTAssertEqual(ScrollbackBudget.size("300k"), 300 << 10)
+        XCTAssertEqual(ScrollbackBudget.size("4096"), 4096)
+        XCTAssertEqual(ScrollbackBudget.size("off"), 0)
+        XCTAssertNil(ScrollbackBudget.size("lots"))
+    }
+
+    /// Panes under an even share keep everything; the rest split what is
+    /// left evenly.
+    func testCapsShareWhatIsLeft() {
+        XCTAssertNil(ScrollbackBudget.caps(usage: [1: 40, 2: 50], budget: 100))
+        XCTAssertNil(ScrollbackBudget.caps(usage: [1: 400], budget: 0))
+        XCTAssertEqual(ScrollbackBudget.caps(usage: [1: 10, 2: 200, 3: 90], budget: 100), [2: 45, 3: 45])
+        XCTAssertEqual(ScrollbackBudget.caps(usage: [1: 60, 2: 60], budget: 100), [1: 50, 2: 50])
+
+        XCTAssertEqual(ScrollbackBudget.lines(forCap: 320_000, bytes: 640_000, lines: 2000), 1000)
+        XCTAssertEqual(ScrollbackBudget.lines(forCap: 3_200_000, bytes: 6_400_000, lines: 20_000), 10_000)
+        XCTAssertEqual(ScrollbackBudget.lines(forCap: 10, bytes: 6_400_000, lines: 20_000), 1000)
+    }
+}
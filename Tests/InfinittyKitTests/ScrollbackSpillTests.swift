@@ -0,0 +1,39 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ScrollbackSpillTests: XCTestCase {
+
+    private func makeSpill() -> ScrollbackSpill {
+        let directory = FileManager.default.temporaryDirectory
+            .appendingPathComponent("spill-\(UUID().uuidString)", isDirectory: true)
+        addTeardownBlock { try? FileManager.default.removeItem(at: directory) }
+        return ScrollbackSpill(directory: directory)
+    }
+
+    /// Reads span written chunks and the lines still pending in memory.
+    func testReadsAcrossChunksAndPendingLines() {
+        let spill = makeSpill()
+        let total = ScrollbackSpill.chunkLines * 2 + 10
+        for line in 100..<(100 + total) { spill.append(line: line, text: "line \(line)") }
+        XCTAssertEqual(spill.lines, 100..<(100 + total))
+
+        let edge = 100 + ScrollbackSpill.chunkLines - 2
+        let read = spill.read(from: edge, count: 4)
+        XCTAssertEqual(read.firstLine, edge)
+        XCTAssertEqual(read.lines, (edge..<(edge + 4)).map { "line \($0)" })
+        let tail = spill.read(from: 100 + total - 12, count: 50)
+        XCTAssertEqual(tail.lines, ((100 + total - 12)..<(100 + total)).map { "line \($0)" })
+        XCTAssertEqual(spill.read(from: 0, count: 101).lines, ["line 100"])
+    }
+
+    /// A jump in line numbers (scrollback was cleared) starts over.
+    func testGapStartsOver() {
+        let spill = makeSpill()
+        for line in 0..<5 { spill.append(line: line, text: "\(line)") }
+        spill.append(line: 40, text: "40")
+        XCTAssertEqual(spill.lines, 40..<41)
+        XCTAssertEqual(spill.read(from: 0, count: 100).lines, ["40"])
+        spill.removeAll()
+        XCTAssertTrue(spill.lines.isEmpty)
+    }
+}
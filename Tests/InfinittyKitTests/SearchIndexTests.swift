@@ -0,0 +1,123 @@
+import XCTest
+@testable import InfinittyKit
+
+final class SearchIndexTests: XCTestCase {
+    private var dir: URL!
+
+    override func setUpWithError() throws {
+        dir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        try FileManager.default.createDirectory(
+            at: dir.appendingPathComponent("src"), withIntermediateDirectories: true)
+    }
+
+    override func tearDownWithError() throws {
+        try? FileManager.default.removeItem(at: dir)
+    }
+
+    private func write(_ text: String, to path: String) throws {
+        try Data(text.utf8).write(to: dir.appendingPathComponent(path))
+    }
+
+    func testTrigramsFoldASCIICase() {
+        let upper = SearchIndex.trigrams(of: Array("ABCd".utf8))
+        XCTAssertEqual(upper, SearchIndex.trigrams(of: Array("abcD".utf8)))
+        XCTAssertEqual(upper.count, 2)
+        XCTAssertTrue(SearchIndex.trigrams(of: Array("ab".utf8)).isEmpty)
+
+        let file = IndexedFile(modified: 0, size: 4, trigrams: SearchIndex.packed(upper))
+        XCTAssertEqual(file.trigrams.count, 8)
+        XCTAssertTrue(upper.allSatisfy(file.contains))
+        XCTAssertFalse(file.contains(SearchIndex.trigrams(of: Array("xyz".utf8)).first ?? 0))
+    }
+
+    /// Only files holding every trigram of the query are read; the lines
+    /// that really contain it come back, paths relative to the root.
+    func testSearchFindsLinesAndSkipsBinaries() throws {
+        try write("let total = 1\nprint(Total)\n", to: "src/a.swift")
+        try write("nothing here\n", to: "src/b.swift")
+        try Data([0x74, 0x6f, 0x74, 0x61, 0x6c, 0x00, 0x01]).write(to: dir.appendingPathComponent("blob.bin"))
+        try write("total\n", to: "notes.md")
+        let index = SearchIndex(root: dir.path, limits: SearchIndexLimits(), storeURL: nil, watches: false)
+        index.waitForIdle()
+
+        let status = index.status
+        XCTAssertEqual(status.phase, .ready)
+        XCTAssertEqual(status.files, 3)
+        XCTAssertEqual(status.skipped, 1)
+        XCTAssertFalse(status.truncated)
+
+        let matches = try XCTUnwrap(index.search("TOTAL"))
+        XCTAssertEqual(matches.map(\.path), ["notes.md", "src/a.swift", "src/a.swift"])
+        XCTAssertEqual(matches.map(\.line), [1, 1, 2])
+        XCTAssertEqual(try XCTUnwrap(index.search("total", under: "src/")).count, 2)
+        XCTAssertEqual(try XCTUnwrap(index.search("total", limit: 1)).count, 1)
+        XCTAssertEqual(try XCTUnwrap(index.search("absent")), [])
+    }
+
+    func testCapsTruncate() throws {
+        for n in 0..<4 { try write("line \(n)\n", to: "src/\(n).txt") }
+        var limits = SearchIndexLimits()
+        limits.maxFiles = 2
+        let index = SearchIndex(root: dir.path, limits: limits, storeURL: nil, watches: false)
+        index.waitForIdle()
+        XCTAssertEqual(index.status.files, 2)
+        XCTAssertTrue(index.status.truncated)
+
+        limits.maxFiles = 10
+        limits.maxBytes = 14
+        index.setLimits(limits)
+        index.waitForIdle()
+        XCTAssertEqual(index.status.files, 2)
+        XCTAssertEqual(index.status.bytes, 14)
+        XCTAssertTrue(index.status.truncated)
+
+        index.setLimits(SearchIndexLimits())
+        index.waitForIdle()
+        XCTAssertEqual(index.status.files, 4)
+        XCTAssertFalse(index.status.truncated)
+    }
+
+    /// A saved index is picked up by the next one for the same root, and a
+    /// file changed since is read again.
+    func testSavedIndexReloadsAndRefreshes() throws {
+        let storeDir = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        defer { try? FileManager.default.removeItem(at: storeDir) }
+        let store = storeDir.appendingPathComponent("index.plist")
+        try write("alpha\n", to: "src/a.txt")
+        let first = SearchIndex(root: dir.path, limits: SearchIndexLimits(), storeURL: store, watches: false)
+        first.waitForIdle()
+        XCTAssertTrue(FileManager.default.fileExists(atPath: store.path))
+
+        try write("alpha beta\n", to: "src/a.txt")
+        let second = SearchIndex(root: dir.path, limits: SearchIndexLimits(), storeURL: store, watches: false)
+        second.waitForIdle()
+        XCTAssertEqual(try XCTUnwrap(second.search("beta")).map(\.path), ["src/a.txt"])
+
+        second.rebuild()
+        second.waitForIdle()
+        XCTAssertEqual(second.status.files, 1)
+    }
+
+    func testConfigCaps() {
+        var config = AppConfig()
+        config.apply(fileContents: "search-index-max = 2G\nsearch-index-max-files = 10_000")
+        XCTAssertEqual(config.searchIndexLimits.maxBytes, 2 << 30)
+        XCTAssertEqual(config.searchIndexLimits.maxFiles, 10_000)
+        config.apply(fileContents: "search-index-max = 0\nsearch-index-max-files = many")
+        XCTAssertEqual(config.searchIndexLimits.maxBytes, 2 << 30)
+        XCTAssertEqual(config.searchIndexLimits.maxFiles, 10_000)
+    }
+
+    func testIndexesShareOnePerRootAndEvict() throws {
+        let indexes = SearchIndexes(directory: nil)
+        let index = indexes.index(for: dir.path)
+        XCTAssertTrue(index === indexes.index(for: dir.path))
+        for n in 0..<SearchIndexes.maxLive {
+            let other = dir.appendingPathComponent("src/\(n)")
+            try FileManager.default.createDirectory(at: other, withIntermediateDirectories: true)
+            _ = indexes.index(for: other.path)
+        }
+        XCTAssertEqual(indexes.all.count, SearchIndexes.maxLive)
+        XCTAssertFalse(indexes.all.contains { $0 === index })
+    }
+}
@@ -0,0 +1,61 @@
+import AppKit
+import XCTest
+@testable import InfinittyKit
+
+final class SecureInputTests: XCTestCase {
+    func testPromptLines() {
+        for line in [
+            "Password:", "[sudo] password for ada:", "Enter passphrase for key '/Users/ada/.ssh/id_ed25519':",
+            "Enter PIN for 'YubiKey':", "ada@db.example.com's password:", "Verification code:",
+        ] {
+            XCTAssertTrue(SecureInputDetector.looksLikePrompt(line), line)
+        }
+        for line in ["", "password reset email sent", "ada@host ~ %", "Passwords: 3 weak, 1 reused, more below"] {
+            XCTAssertFalse(SecureInputDetector.looksLikePrompt(line), line)
+        }
+    }
+
+    /// Echo off on a line-buffered tty is enough; a raw or remote one needs
+    /// the prompt, and output after Return ends it.
+    func testDetectorFollowsEchoAndPrompts() {
+        let detector = SecureInputDetector()
+        let reading = TTYEcho(echo: false, canonical: true)
+        let raw = TTYEcho(echo: false, canonical: false)
+        let cooked = TTYEcho(echo: true, canonical: true)
+
+        XCTAssertFalse(detector.ingest(Data("building\r\n".utf8), echo: cooked))
+        XCTAssertTrue(detector.ingest(Data("[sudo] password for ada: ".utf8), echo: reading))
+        XCTAssertEqual(detector.current, SecurePrompt(prompt: "[sudo] password for ada:", source: .echo))
+        XCTAssertFalse(detector.ingest(Data("\r\nSorry, try again.\r\n[sudo] password for ada: ".utf8), echo: reading))
+        XCTAssertTrue(detector.ingest(Data("\r\n".utf8), echo: cooked))
+        XCTAssertNil(detector.current)
+
+        XCTAssertFalse(detector.ingest(Data("ada@host ~ % ".utf8), echo: raw))
+        XCTAssertTrue(detector.ingest(Data("\r\n\u{1B}[1mada@db's password:\u{1B}[0m ".utf8), echo: nil))
+        XCTAssertEqual(detector.current?.source, .prompt)
+        XCTAssertTrue(detector.ingest(Data("\r\nWelcome\r\n".utf8), echo: nil))
+        XCTAssertFalse(detector.isActive)
+
+        XCTAssertTrue(detector.ingest(Data("Password:".utf8), echo: raw))
+        XCTAssertTrue(detector.reset())
+        XCTAssertFalse(detector.reset())
+    }
+
+    func testEntriesMatchTheirPrompts() {
+        let sudo = PromptPasswords.Entry(account: "sudo", pattern: #"^\[sudo\] password"#)
+        XCTAssertTrue(sudo.matches("[sudo] password for ada:"))
+        XCTAssertFalse(sudo.matches("Enter passphrase for key 'id_ed25519':"))
+        XCTAssertTrue(PromptPasswords.Entry(account: "any", pattern: nil).matches("Password:"))
+    }
+
+    func testHeaderShowsLock() {
+        let header = PaneHeaderView(frame: NSRect(x: 0, y: 0, width: 400, height: 28))
+        XCTAssertNil(header.secureTooltipForTesting)
+        header.setSecureInput(SecurePrompt(prompt: "Password:", source: .echo))
+        XCTAssertEqual(header.secureTooltipForTesting, "Secure input: Password:")
+        header.layoutSubtreeIfNeeded()
+        XCTAssertLessThanOrEqual(header.titleFrameForTesting.maxX, header.secureAnchorView.frame.minX)
+        header.setSecureInput(nil)
+        XCTAssertNil(header.secureTooltipForTesting)
+    }
+}
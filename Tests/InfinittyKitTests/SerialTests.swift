@@ -0,0 +1,43 @@
+import XCTest
+@testable import InfinittyKit
+
+final class SerialTests: XCTestCase {
+    func testSettingsParsing() throws {
+        let defaults = try SerialSettings.parse(["port": "usbserial-1410"]).get()
+        XCTAssertEqual(defaults.port, "/dev/cu.usbserial-1410")
+        XCTAssertEqual(defaults.summary, "115200 8N1")
+
+        let custom = try SerialSettings.parse([
+            "port": "/dev/tty.usbmodem1", "baud": 9600, "dataBits": 7, "parity": "Even", "stopBits": 2,
+            "flowControl": "hardware",
+        ]).get()
+        XCTAssertEqual(custom.port, "/dev/tty.usbmodem1")
+        XCTAssertEqual(custom.summary, "9600 7E2")
+        XCTAssertEqual(custom.flowControl, .hardware)
+
+        XCTAssertEqual(SerialSettings.parse([:]).map(\.port), .failure(.invalidSetting("port is required")))
+        for bad: [String: Any] in [["baud": 0], ["dataBits": 9], ["parity": "mark"], ["stopBits": 3]] {
+            let object = bad.merging(["port": "x"]) { a, _ in a }
+            guard case .failure(.invalidSetting) = SerialSettings.parse(object) else {
+                return XCTFail("\(bad) should be rejected")
+            }
+        }
+    }
+
+    /// Only call-out nodes are listed, and names map back to their paths.
+    func testPortsAndNames() throws {
+        let directory = FileManager.default.temporaryDirectory.appendingPathComponent(UUID().uuidString)
+        try FileManager.default.createDirectory(at: directory, withIntermediateDirectories: true)
+        defer { try? FileManager.default.removeItem(at: directory) }
+        for name in ["cu.usbserial-2", "tty.usbserial-2", "cu.usbserial-10", "null"] {
+            FileManager.default.createFile(atPath: directory.appendingPathComponent(name).path, contents: nil)
+        }
+        let ports = SerialPort.list(in: directory.path)
+        XCTAssertEqual(ports.map(SerialPort.name(of:)), ["usbserial-2", "usbserial-10"])
+        XCTAssertEqual(SerialPort.path(for: "usbserial-2", in: directory.path), ports.first)
+        XCTAssertEqual(SerialPort.path(for: "tty.usbserial-2"), "/dev/tty.usbserial-2")
+        XCTAssertEqual(
+            SerialConnection.open(SerialSettings(port: "nothing-here")).map(\.settings),
+            .failure(.noSuchPort("/dev/cu.nothing-here")))
+    }
+}
@@ -0,0 +1,94 @@
+import XCTest
+@testable import InfinittyKit
+
+final class ServiceMapTests: XCTestCase {
+    /// IPv4 and IPv6 sockets of one process collapse; a specific bind
+    /// address becomes the host.
+    func testParseLsof() {
+        let output = """
+            p812
+            cnode
+            f23
+            n127.0.0.1:5173
+            f24
+            n[::1]:5173
+            p90
+            cpostgres
+            f7
+            n*:5432
+            f8
+            n192.168.1.20:9000
+            """
+        let endpoints = ServiceMap.parseLsof(output)
+        XCTAssertEqual(endpoints.map(\.name), ["node", "postgres", "postgres"])
+        XCTAssertEqual(endpoints.map(\.port), [5173, 5432, 9000])
+        XCTAssertEqual(endpoints.map(\.url), [
+            "http://localhost:5173", "http://localhost:5432", "http://192.168.1.20:9000",
+        ])
+        XCTAssertEqual(endpoints[0].pid, 812)
+        XCTAssertEqual(endpoints[0].id, "local:node:5173/-")
+    }
+
+    func testParseDockerPorts() {
+        let mappings = ServiceMap.parseDockerPorts(
+            "0.0.0.0:8000-8001->80-81/tcp, :::8000->80/tcp, 6379/tcp, 53/udp")
+        XCTAssertEqual(mappings, [
+            .init(host: "0.0.0.0", hostPort: 8000, containerPort: 80, proto: "tcp"),
+            .init(host: "0.0.0.0", hostPort: 8001, containerPort: 81, proto: "tcp"),
+            .init(host: "::", hostPort: 8000, containerPort: 80, proto: "tcp"),
+            .init(host: nil, hostPort: nil, containerPort: 6379, proto: "tcp"),
+            .init(host: nil, hostPort: nil, containerPort: 53, proto: "udp"),
+        ])
+    }
+
+    func testParseDockerPS() {
+        let output = #"""
+            {"ID":"a1","Image":"postgres:16","Names":"db","Ports":"0.0.0.0:5432->5432/tcp, :::5432->5432/tcp"}
+            {"ID":"b2","Image":"redis:7","Names":"cache","Ports":"6379/tcp"}
+            """#
+        let endpoints = ServiceMap.parseDockerPS(output)
+        XCTAssertEqual(endpoints.map(\.name), ["db", "cache"])
+        XCTAssertEqual(endpoints.map(\.port), [5432, nil])
+        XCTAssertEqual(endpoints.map(\.targetPort), [5432, 6379])
+        XCTAssertEqual(endpoints[0].detail, "postgres:16")
+        XCTAssertNil(endpoints[1].url)
+    }
+
+    func testParseKubernetesServices() throws {
+        let json = Data(#"""
+            {"items": [
+              {"metadata": {"name": "web", "namespace": "shop"},
+               "spec": {"type": "LoadBalancer", "ports": [{"port": 80, "nodePort": 31000, "protocol": "TCP"}]},
+               "status": {"loadBalancer": {"ingress": [{"hostname": "localhost"}]}}},
+              {"metadata": {"name": "api", "namespace": "shop"},
+               "spec": {"type": "NodePort", "ports": [{"port": 8080, "nodePort": 30080}]}},
+              {"metadata": {"name": "db", "namespace": "shop"},
+               "spec": {"type": "ClusterIP", "ports": [{"port": 5432}]}}
+            ]}
+            """#.utf8)
+        let endpoints = try XCTUnwrap(ServiceMap.parseKubernetesServices(json))
+        XCTAssertEqual(endpoints.map(\.name), ["shop/web", "shop/api", "shop/db"])
+        XCTAssertEqual(endpoints.map(\.url), ["http://localhost:80", "http://localhost:30080", nil])
+        XCTAssertEqual(endpoints[2].forward, "kubectl port-forward -n shop svc/db 5432:5432")
+        XCTAssertNil(ServiceMap.parseKubernetesServices(Data("error".utf8)))
+    }
+
+    /// A runtime's port proxy is hidden behind the container it forwards
+    /// to; other listeners on the same port stay.
+    func testMergeDropsRuntimeProxies() {
+        let proxy = ServiceEndpoint(source: .local, name: "com.docke", port: 5432, pid: 1)
+        let node = ServiceEndpoint(source: .local, name: "node", port: 3000, pid: 2)
+        let db = ServiceEndpoint(source: .docker, name: "db", port: 5432, targetPort: 5432)
+        let merged = ServiceMap.merge(local: [proxy, node], docker: [db], kubernetes: [])
+        XCTAssertEqual(merged, [db, node])
+    }
+
+    func testExecutablePrefersKnownLocations() {
+        let environment = ["PATH": "/usr/bin:/custom/bin", "HOME": "/Users/me"]
+        let installed: Set<String> = ["/custom/bin/kubectl", "/Users/me/.orbstack/bin/kubectl"]
+        XCTAssertEqual(
+            ServiceMap.executable("kubectl", environment: environment, isExecutable: installed.contains),
+            "/Users/me/.orbstack/bin/kubectl")
+        XCTAssertNil(ServiceMap.executable("docker", environment: environment, isExecutable: installed.contains))
+    }
+}
@@ -0,0 +1,79 @@
+import XCTest
+@testable import InfinittyKit
+
+final class SessionRecordingTests: XCTestCase {
+
+    func testParsesHeaderAndEvents() throws {
+        let text = """
+        {"version": 2, "width": 80, "height": 24, "timestamp": 1700000000, "title": "demo"}
+        [0.5, "o", "hello "]
+        [1.25, "r", "100x30"]
+        [2.0, "o", "world\\r\\n"]
+        """
+        let cast = try Asciicast.parse(text).get()
+        XCTAssertEqual(cast.width, 80)
+        XCTAssertEqual(cast.height, 24)
+        XCTAssertEqual(cast.title, "demo")
+        XCTAssertEqual(cast.events.count, 3)
+        XCTAssertEqual(cast.events[1], AsciicastEvent(time: 1.25, kind: "r", data: "100x30"))
+        XCTAssertEqual(cast.events[2].data, "world\r\n")
+        XCTAssertEqual(cast.duration, 2.0)
+
+        XCTAssertEqual(
+            Asciicast.parse("{\"version\": 1, \"width\": 80, \"height\": 24}"),
+            .failure(.invalidCast("version is not 2")))
+        XCTAssertEqual(
+            Asciicast.parse("{\"version\": 2, \"width\": 80, \"height\": 24}\n[1, \"o\"]"),
+            .failure(.invalidCast("line 2 is not [time, kind, data]")))
+    }
+
+    /// Speed divides every gap; maxIdle trims only the long ones.
+    func testScheduleScalesAndCapsPauses() {
+        let events = [0.5, 1.0, 11.0, 11.5].map { AsciicastEvent(time: $0, kind: "o", data: "x") }
+        XCTAssertEqual(Asciicast.schedule(events), [0.5, 1.0, 11.0, 11.5])
+        XCTAssertEqual(Asciicast.schedule(events, speed: 2), [0.25, 0.5, 5.5, 5.75])
+        XCTAssertEqual(Asciicast.schedule(events, maxIdle: 2), [0.5, 1.0, 3.0, 3.5])
+    }
+
+    /// The file is a header plus one line per event, a resize is logged
+    /// before the output that follows it, and a UTF-8 sequence split across
+    /// reads is written as one event once it is whole.
+    func testRecorderWritesAsciicast() throws {
+        let path = NSTemporaryDirectory() + "infinitty-\(UUID().uuidString).cast"
+        defer { try? FileManager.default.removeItem(atPath: path) }
+        let start = Date(timeIntervalSince1970: 1_700_000_000)
+        let recorder = try AsciicastRecorder(
+            path: path, cols: 80, rows: 24, title: "demo",
+            environment: ["SHELL": "/bin/zsh", "TERM": "xterm-256color", "SECRET": "x"], now: start)
+        let euro = Array("€".utf8)
+        recorder.record(Data("$ ls\r\n".utf8), cols: 80, rows: 24, at: start + 0.5)
+        recorder.record(Data(euro.prefix(2)), cols: 100, rows: 30, at: start + 1)
+        recorder.record(Data(euro.suffix(1)), cols: 100, rows: 30, at: start + 1.5)
+        let summary = recorder.stop()
+        XCTAssertEqual(summary["events"] as? Int, 3)
+        XCTAssertEqual(summary["duration"] as? Double, 1.5)
+        recorder.record(Data("late".utf8), cols: 100, rows: 30, at: start + 2)
+
+        let cast = try Asciicast.parse(String(contentsOfFile: path, encoding: .utf8)).get()
+        XCTAssertEqual(cast.width, 80)
+        XCTAssertEqual(cast.timestamp, 1_700_000_000)
+        XCTAssertEqual(cast.events, [
+            AsciicastEvent(time: 0.5, kind: "o", data: "$ ls\r\n"),
+            AsciicastEvent(time: 1, kind: "r", data: "100x30"),
+            AsciicastEvent(time: 1.5, kind: "o", data: "€"),
+        ])
+        let header = try String(contentsOfFile: path, encoding: .utf8).split(separator: "\n")[0]
+        let env = try XCTUnwrap(
+            JSONSerialization.jsonObject(with: Data(header.utf8)) as? [String: Any])["env"] as? [String: String]
+        XCTAssertEqual(env, ["SHELL": "/bin/zsh", "TERM": "xterm-256color"])
+    }
+
+    func testCompleteUTF8Length() {
+        let euro = Data("a€".utf8)
+        XCTAssertEqual(AsciicastRecorder.completeUTF8Length(euro), 4)
+        XCTAssertEqual(AsciicastRecorder.completeUTF8Length(euro.prefix(3)), 1)
+        XCTAssertEqual(AsciicastRecorder.completeUTF8Length(euro.prefix(2)), 1)
+        XCTAssertEqual(AsciicastRecorder.completeUTF8Length(Data("plain".utf8)), 5)
+        XCTAssertEqual(AsciicastRecorder.completeUTF8Length(Data()), 0)
+    }
+}
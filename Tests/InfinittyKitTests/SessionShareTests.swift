@@ -0,0 +1,248 @@
+import CryptoKit
+import XCTest
+@testable import InfinittyKit
+
+final class SessionShareTests: XCTestCase {
+
+    /// Codes survive the text round trip, tolerate pasted whitespace, and
+    /// the room id the relay sees doesn't contain the secret.
+    func testCodeRoundTripsAndHidesSecret() throws {
+        let code = ShareCode.generate()
+        XCTAssertEqual(code.string.count, 43)
+        XCTAssertEqual(ShareCode(" \(code.string)\n"), code)
+        XCTAssertNil(ShareCode("too-short"))
+        XCTAssertNotEqual(code.room, ShareCode.generate().room)
+        XCTAssertFalse(code.room.contains(code.string))
+    }
+
+    /// Frames open only with the same code, never for their own sender,
+    /// and only once.
+    func testChannelRejectsWrongKeyEchoAndReplay() throws {
+        let code = ShareCode.generate()
+        let host = ShareChannel(code: code, sender: ShareChannel.hostID)
+        let guest = ShareChannel(code: code, sender: "g1")
+        let frame = try XCTUnwrap(guest.seal(.input(Data("ls\r".utf8))))
+
+        XCTAssertNil(ShareChannel(code: ShareCode.generate(), sender: "host").open(frame))
+        XCTAssertNil(guest.open(frame))
+        XCTAssertEqual(host.open(frame)?.message, .input(Data("ls\r".utf8)))
+        XCTAssertNil(host.open(frame))
+    }
+
+    /// A joining guest gets a snapshot and then live output; its typing is
+    /// dropped until the host grants it read-write, and presence follows
+    /// hello / bye.
+    func testHostStreamsAndGatesGuestInput() {
+        let relay = MemoryRelay()
+        let code = ShareCode.generate()
+        let host = SessionShareHost(
+            pane: 1, code: code, relay: "wss://relay.test", transport: relay.connect(host: true))
+        host.screen = { (80, 24, "$ ") }
+        let typed = LockedState([Data]())
+        let presence = LockedState([String]())
+        host.onInput = { data in typed.withLock { $0.append(data) } }
+        host.onPresence = { participant, joined in
+            presence.withLock { $0.append("\(participant.name) \(joined ? "joined" : "left")") }
+        }
+        host.start()
+        defer { host.stop() }
+
+        let guest = SessionShareGuest(code: code, name: "sam", transport: relay.connect(host: false))
+        let screens = LockedState([String]())
+        let output = LockedState(Data())
+        guest.onSnapshot = { _, _, text in screens.withLock { $0.append(text) } }
+        guest.onOutput = { data in output.withLock { $0.append(data) } }
+        guest.start()
+
+        XCTAssertEqual(screens.snapshot, ["$ "])
+        XCTAssertEqual(host.participants.map(\.name), ["sam"])
+        host.publishOutput(Data("hello\r\n".utf8))
+        XCTAssertEqual(output.snapshot, Data("hello\r\n".utf8))
+
+        XCTAssertFalse(guest.send(input: Data("x".utf8)))
+        XCTAssertFalse(host.setReadWrite(true, for: "nobody"))
+        XCTAssertTrue(host.setReadWrite(true, for: guest.participant))
+        XCTAssertTrue(guest.readWrite)
+        XCTAssertTrue(guest.send(input: Data("y".utf8)))
+        XCTAssertEqual(typed.snapshot, [Data("y".utf8)])
+
+        guest.leave()
+        XCTAssertEqual(presence.snapshot, ["sam joined", "sam left"])
+        XCTAssertTrue(host.participants.isEmpty)
+    }
+
+    /// Read-write is granted per guest: the other guest stays read-only and
+    /// its input is dropped even if it sends anyway.
+    func testReadWriteIsGrantedPerParticipant() {
+        let relay = MemoryRelay()
+        let code = ShareCode.generate()
+        let host = SessionShareHost(
+            pane: 1, code: code, relay: "wss://relay.test", transport: relay.connect(host: true))
+        let typed = LockedState([Data]())
+        host.onInput = { data in typed.withLock { $0.append(data) } }
+        host.start()
+        defer { host.stop() }
+        let sam = SessionShareGuest(code: code, name: "sam", transport: relay.connect(host: false))
+        let kim = SessionShareGuest(code: code, name: "kim", transport: relay.connect(host: false))
+        sam.start()
+        kim.start()
+
+        host.setReadWrite(true, for: sam.participant)
+        XCTAssertTrue(sam.readWrite)
+        XCTAssertFalse(kim.readWrite)
+        XCTAssertEqual(host.participants.map(\.readWrite), [true, false])
+
+        // kim's own channel refuses to send, so seal past its hello by hand.
+        let forged = ShareChannel(code: code, sender: kim.participant)
+        _ = forged.seal(.hello(name: "kim"))
+        XCTAssertFalse(kim.send(input: Data("rm".utf8)))
+        relay.endpoints.last?.send(forged.seal(.input(Data("rm".utf8)))!)
+        XCTAssertTrue(sam.send(input: Data("ls".utf8)))
+        XCTAssertEqual(typed.snapshot, [Data("ls".utf8)])
+    }
+
+    /// A read-only guest that has seen another's id can't type as it: not
+    /// under the code's key, not by joining with that id, and not under its
+    /// own agreed key. It never sees the other's grant, and the real guest
+    /// still types afterwards.
+    func testGuestCannotSpeakForAnother() throws {
+        let relay = MemoryRelay()
+        let code = ShareCode.generate()
+        let host = SessionShareHost(
+            pane: 1, code: code, relay: "wss://relay.test", transport: relay.connect(host: true))
+        host.screen = { (80, 24, "$ ") }
+        let typed = LockedState([Data]())
+        host.onInput = { data in typed.withLock { $0.append(data) } }
+        host.start()
+        defer { host.stop() }
+        let sam = SessionShareGuest(code: code, name: "sam", transport: relay.connect(host: false))
+        sam.start()
+
+        let mallory = Curve25519.KeyAgreement.PrivateKey()
+        let malloryID = ShareChannel.participantID(mallory.publicKey)
+        let room = ShareChannel(code: code, sender: malloryID)
+        let seen = LockedState([ShareMessage]())
+        let endpoint = relay.connect(host: false)
+        endpoint.onReceive = { frame in
+            if let message = room.open(frame)?.message { seen.withLock { $0.append(message) } }
+        }
+        endpoint.send(try XCTUnwrap(room.seal(.join(name: "mallory", key: mallory.publicKey.rawRepresentation))))
+        XCTAssertEqual(host.participants.map(\.name), ["sam", "mallory"])
+        let hostKey = try XCTUnwrap(seen.snapshot.lazy.compactMap { message -> Data? in
+            guard case .welcome(malloryID, let key) = message else { return nil }
+            return key
+        }.first)
+        let agreed = try XCTUnwrap(code.participantKey(
+            mallory, try Curve25519.KeyAgreement.PublicKey(rawRepresentation: hostKey)))
+
+        host.setReadWrite(true, for: sam.participant)
+        XCTAssertTrue(sam.readWrite)
+        XCTAssertFalse(seen.snapshot.contains { message in
+            switch message {
+            case .mode, .snapshot: return true
+            default: return false
+            }
+        })
+
+        let spoofed = ShareChannel(code: code, sender: sam.participant)
+        let impostor = ShareChannel(key: agreed, sender: sam.participant)
+        // Seqs well ahead of sam's, which used to break its counter too.
+        for _ in 0..<5 {
+            _ = spoofed.seal(.hello(name: "sam"))
+            _ = impostor.seal(.hello(name: "sam"))
+        }
+        endpoint.send(try XCTUnwrap(spoofed.seal(.input(Data("rm".utf8)))))
+        endpoint.send(try XCTUnwrap(spoofed.seal(.join(name: "sam", key: mallory.publicKey.rawRepresentation))))
+        endpoint.send(try XCTUnwrap(impostor.seal(.input(Data("rm".utf8)))))
+        XCTAssertTrue(typed.snapshot.isEmpty)
+        XCTAssertTrue(sam.send(input: Data("ls".utf8)))
+        XCTAssertEqual(typed.snapshot, [Data("ls".utf8)])
+    }
+
+    /// A joined share plays the host's stream into a terminal, so reading
+    /// it gives the host's screen, and it can't type until granted.
+    func testJoinedShareShowsHostScreen() {
+        let relay = MemoryRelay()
+        let code = ShareCode.generate()
+        let host = SessionShareHost(
+            pane: 1, code: code, relay: "wss://relay.test", transport: relay.connect(host: true))
+        host.screen = { (40, 5, "$ make\nok") }
+        let typed = LockedState([Data]())
+        host.onInput = { data in typed.withLock { $0.append(data) } }
+        host.start()
+        defer { host.stop() }
+
+        let guest = SessionShareGuest(code: code, name: "sam", transport: relay.connect(host: false))
+        let share = JoinedShare(id: "share-1", relay: "wss://relay.test", guest: guest)
+        guest.start()
+        XCTAssertEqual(share.wire["cols"] as? Int, 40)
+        host.publishOutput(Data("\r\n$ done".utf8))
+        let screen = share.wire["screen"] as? String ?? ""
+        XCTAssertTrue(screen.hasPrefix("$ make\nok\n$ done"), screen)
+
+        guard case .failure(.readOnly("share-1")) = share.send("x") else { return XCTFail("sent while read-only") }
+        host.setReadWrite(true, for: guest.participant)
+        XCTAssertEqual(share.wire["mode"] as? String, "rw")
+        guard case .success = share.send("y") else { return XCTFail("refused once granted") }
+        XCTAssertEqual(typed.snapshot, [Data("y".utf8)])
+    }
+
+    /// Guests that stop sending heartbeats are dropped.
+    func testSilentParticipantsExpire() {
+        let relay = MemoryRelay()
+        let code = ShareCode.generate()
+        let host = SessionShareHost(
+            pane: 1, code: code, relay: "wss://relay.test", transport: relay.connect(host: true))
+        let left = expectation(description: "left")
+        host.onPresence = { _, joined in if !joined { left.fulfill() } }
+        host.start()
+        defer { host.stop() }
+        let guest = SessionShareGuest(code: code, name: "sam", transport: relay.connect(host: false))
+        guest.start()
+
+        host.expireSilent(now: Date().addingTimeInterval(SessionShareHost.participantTimeout + 1))
+        wait(for: [left], timeout: 1)
+        XCTAssertTrue(host.participants.isEmpty)
+    }
+
+    func testRelayURL() {
+        XCTAssertEqual(
+            WebSocketShareTransport.url(relay: "wss://r.example/share", room: "abc", role: "guest")?.absoluteString,
+            "wss://r.example/share/abc?role=guest")
+        XCTAssertNil(WebSocketShareTransport.url(relay: "https://r.example", room: "abc", role: "host"))
+    }
+}
+
+/// Forwards frames synchronously the way the real relay does: host frames
+/// to every guest, guest frames to the host.
+private final class MemoryRelay {
+    final class Endpoint: ShareTransport {
+        var onReceive: ((Data) -> Void)?
+        var onClose: ((String) -> Void)?
+        let isHost: Bool
+        weak var relay: MemoryRelay?
+
+        init(isHost: Bool, relay: MemoryRelay) {
+            self.isHost = isHost
+            self.relay = relay
+        }
+
+        func connect() {}
+        func send(_ frame: Data) { relay?.forward(frame, from: self) }
+        func close() { relay?.endpoints.removeAll { $0 === self } }
+    }
+
+    var endpoints: [Endpoint] = []
+
+    func connect(host: Bool) -> Endpoint {
+        let endpoint = Endpoint(isHost: host, relay: self)
+        endpoints.append(endpoint)
+        return endpoint
+    }
+
+    func forward(_ frame: Data, from sender: Endpoint) {
+        for endpoint in endpoints where endpoint !== sender && endpoint.isHost != sender.isHost {
+            endpoint.onReceive?(frame)
+        }
+    }
+}
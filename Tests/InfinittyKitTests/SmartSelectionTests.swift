@@ -0,0 +1,53 @@
+import XCTest
+@testable import InfinittyKit
+
+final class SmartSelectionTests: XCTestCase {
+
+    private func token(_ line: String, at column: Int) -> (SmartToken.Kind, String)? {
+        let chars = Array(line)
+        guard let (kind, columns) = SmartToken.find(in: chars, at: column) else { return nil }
+        return (kind, String(chars[columns]))
+    }
+
+    func testFindsTokensUnderTheClick() {
+        XCTAssertEqual(token("see https://example.com/a?b=1). ok", at: 6)?.1, "https://example.com/a?b=1")
+        XCTAssertEqual(token("see https://example.com/a?b=1). ok", at: 6)?.0, .url)
+        XCTAssertEqual(token("error at Sources/App.swift:12:3: boom", at: 17)?.1, "Sources/App.swift:12:3")
+        XCTAssertEqual(token("cd ~/src/infinitty && ls", at: 8)?.1, "~/src/infinitty")
+        XCTAssertEqual(token("id 550e8400-e29b-41d4-a716-446655440000 ok", at: 10)?.0, .uuid)
+        XCTAssertEqual(token("connect 192.168.1.20:5432 refused", at: 10)?.1, "192.168.1.20:5432")
+        XCTAssertEqual(token("ping fe80::1 now", at: 7)?.1, "fe80::1")
+        XCTAssertEqual(token("commit 3f2a9c1 fix", at: 9)?.0, .gitHash)
+    }
+
+    /// Numbers, clock times and words are left to plain word selection.
+    func testLeavesPlainWordsAlone() {
+        XCTAssertNil(token("took 1234567 ms", at: 7))
+        XCTAssertNil(token("at 12:30:45 today", at: 5))
+        XCTAssertNil(token("just words here", at: 6))
+    }
+
+    /// A brace selects its object when the span parses as JSON, skipping
+    /// braces inside strings.
+    func testBracesSelectJSONObjects() {
+        let line = #"log {"a": {"b": "}"}, "c": [1]} end"#
+        XCTAssertEqual(token(line, at: 4)?.0, .json)
+        XCTAssertEqual(token(line, at: 30)?.1, #"{"a": {"b": "}"}, "c": [1]}"#)
+        XCTAssertEqual(token(line, at: 10)?.1, #"{"b": "}"}"#)
+        XCTAssertNil(token("echo ${HOME}", at: 6))
+    }
+
+    /// Link detection reports each URL, path and hash once, with a path's
+    /// position split off.
+    func testLinksInARow() {
+        let links = SmartToken.links(in: Array("see https://x.io/a, src/App.swift:12:3 and 3f2a9c1 in /tmp"), line: 5)
+        XCTAssertEqual(links.map(\.kind), [.url, .path, .gitHash, .path])
+        XCTAssertEqual(links.map(\.start), [4, 20, 43, 54])
+        XCTAssertEqual(links[0].text, "https://x.io/a")
+        XCTAssertEqual(links[1].path, "src/App.swift")
+        XCTAssertEqual([links[1].fileLine, links[1].fileColumn], [12, 3])
+        XCTAssertEqual(links[1].end, 37)
+        XCTAssertNil(links[3].fileLine)
+        XCTAssertEqual(links[3].wire["line"] as? Int, 5)
+    }
+}
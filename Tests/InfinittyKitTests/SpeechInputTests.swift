@@ -0,0 +1,20 @@
+import XCTest
+@testable import InfinittyKit
+
+final class SpeechInputTests: XCTestCase {
+
+    /// Dictation follows what was already typed instead of replacing it.
+    func testComposedKeepsTheDraft() {
+        XCTAssertEqual(SpeechInput.composed(draft: "", transcript: "why is the build red"), "why is the build red")
+        XCTAssertEqual(SpeechInput.composed(draft: "in ~/app ", transcript: "run the tests"), "in ~/app run the tests")
+        XCTAssertEqual(SpeechInput.composed(draft: "explain", transcript: ""), "explain")
+    }
+
+    func testSpeechEventPayload() {
+        let payload = AppEvent.speech(SpeechTranscript(text: "open the logs", isFinal: true)).payload()
+        XCTAssertEqual(payload["event"] as? String, "speech")
+        XCTAssertEqual(payload["text"] as? String, "open the logs")
+        XCTAssertEqual(payload["final"] as? Bool, true)
+        XCTAssertFalse(SpeechInput.shared.isListening)
+    }
+}
@@ -0,0 +1,51 @@
+import XCTest
+@testable import InfinittyKit
+
+final class StartupCommandsTests: XCTestCase {
+    private var url: URL!
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("startup-\(UUID().uuidString).json")
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    /// Lines are split and trimmed, blank ones dropped, and an emptied
+    /// workspace leaves the file.
+    func testWorkspaceCommandsPersist() throws {
+        let store = WorkspaceStartup(url: url)
+        XCTAssertEqual(store.setCommands([" nvm use ", "", "git fetch\nmake deps"], for: "/src/a"),
+                       ["nvm use", "git fetch", "make deps"])
+        store.setCommands(["docker compose up -d"], for: "/src/b")
+        XCTAssertEqual(WorkspaceStartup(url: url).commands(for: "/src/a"), ["nvm use", "git fetch", "make deps"])
+
+        store.setCommands([], for: "/src/b")
+        XCTAssertEqual(store.commands(for: "/src/b"), [])
+        let saved = try XCTUnwrap(JSONSerialization.jsonObject(with: Data(contentsOf: url)) as? [String: Any])
+        XCTAssertEqual(saved.keys.sorted(), ["/src/a"])
+    }
+
+    /// The profile's lines come first; finished commands are matched to
+    /// them in order, and ones the user typed are left alone.
+    func testRunTracksExitCodesInOrder() {
+        var run = StartupRun(profile: ["nvm use"], workspace: ["make deps", "git fetch"])
+        XCTAssertEqual(run.input, Array("nvm use\rmake deps\rgit fetch\r".utf8))
+        XCTAssertTrue(run.marker.contains("3 startup commands"))
+
+        XCTAssertEqual(run.finished(command: "nvm use", exitCode: 0)?.source, .profile)
+        XCTAssertNil(run.finished(command: "ls", exitCode: 1))
+        let failed = run.finished(command: nil, exitCode: 2)
+        XCTAssertEqual(failed?.command, "make deps")
+        XCTAssertEqual(failed?.source, .workspace)
+        XCTAssertEqual(failed?.failed, true)
+        XCTAssertEqual(run.pending.map(\.command), ["git fetch"])
+
+        XCTAssertFalse(run.finished(command: "git fetch ", exitCode: 0)?.failed ?? true)
+        XCTAssertTrue(run.isFinished)
+        XCTAssertNil(run.finished(command: nil, exitCode: 1))
+        XCTAssertEqual(run.wire["failed"] as? Int, 1)
+        XCTAssertTrue(StartupRun(profile: [], workspace: []).isEmpty)
+    }
+}
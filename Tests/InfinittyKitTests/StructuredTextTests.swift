@@ -0,0 +1,103 @@
+import XCTest
+@testable import InfinittyKit
+
+final class StructuredTextTests: XCTestCase {
+
+    /// Pretty JSON keeps source key order and number spelling.
+    func testJSONPrettyPrintKeepsOrderAndNumbers() throws {
+        let formatted = try StructuredText.format(#"{"b":1.10,"a":[true,null,"x"],"e":{}}"#).get()
+        XCTAssertEqual(formatted, """
+            {
+              "b": 1.10,
+              "a": [
+                true,
+                null,
+                "x"
+              ],
+              "e": {}
+            }
+            """)
+    }
+
+    /// Block mappings, both sequence indent styles, comments and `|` text
+    /// all read into the same values JSON would give.
+    func testYAMLReadsCommonBlockStyles() throws {
+        let yaml = """
+            # service
+            name: api
+            zip: 007
+            ports:
+              - 80
+              - "443"
+            env:
+            - key: MODE
+              value: prod # trailing comment
+            note: |
+              line one
+              line two
+            empty:
+            """
+        func query(_ path: String) throws -> String {
+            try StructuredText.format(yaml, output: .json, query: path).get()
+        }
+        XCTAssertEqual(try query(".name"), #""api""#)
+        XCTAssertEqual(try query(".zip"), #""007""#)
+        XCTAssertEqual(try query(".ports[]"), "80\n\"443\"")
+        XCTAssertEqual(try query(".env[0].value"), #""prod""#)
+        XCTAssertEqual(try query(".note"), #""line one\nline two\n""#)
+        XCTAssertEqual(try query(".empty"), "null")
+    }
+
+    /// JSON renders as YAML that quotes strings which would otherwise read
+    /// back as something else, and the YAML parses back to the same value.
+    func testYAMLOutputRoundTrips() throws {
+        let json = #"{"name":"a: b","items":[{"id":1,"tags":["x","y"]},{"id":2,"tags":[]}],"on":"true","n":null}"#
+        let yaml = try StructuredText.format(json, output: .yaml).get()
+        XCTAssertEqual(yaml, """
+            name: "a: b"
+            items:
+              - id: 1
+                tags:
+                  - x
+                  - y
+              - id: 2
+                tags: []
+            on: "true"
+            n: null
+            """)
+        XCTAssertEqual(
+            try StructuredText.parse(yaml, as: .yaml).get().value,
+            try StructuredText.parse(json, as: .json).get().value)
+    }
+
+    /// jq path steps: indexes from either end, slices, missing keys as null,
+    /// and errors for indexing the wrong kind of value.
+    func testQueries() throws {
+        let json = #"{"a":[1,2,3]}"#
+        XCTAssertEqual(try StructuredText.format(json, query: ".a[-1]").get(), "3")
+        XCTAssertEqual(try StructuredText.format(json, query: ".a[1:]").get(), "[\n  2,\n  3\n]")
+        XCTAssertEqual(try StructuredText.format(json, query: ".missing").get(), "null")
+        XCTAssertEqual(
+            StructuredText.format(json, query: ".a.b").failure,
+            .query(#"cannot apply key "b" to an array"#))
+        XCTAssertEqual(
+            StructuredText.format(json, query: "a.b").failure,
+            .badQuery("a path starts with '.'"))
+    }
+
+    /// Detection falls back to YAML, but text that looks like JSON reports
+    /// the JSON error.
+    func testFormatDetection() throws {
+        XCTAssertEqual(try StructuredText.format("- a\n- b").get(), "- a\n- b")
+        XCTAssertEqual(
+            StructuredText.format(#"{"a": 1,,}"#).failure,
+            .syntax("expected a quoted key", line: 1))
+    }
+}
+
+private extension Result {
+    var failure: Failure? {
+        if case .failure(let error) = self { return error }
+        return nil
+    }
+}
@@ -175,6 +175,176 @@ final class TerminalTests: XCTestCase {
         XCTAssertTrue(history.contains("line5"))
     }
 
+    /// Lines are absolute, so they stay put as old rows fall out of a
+    /// small ring; columns count cells past wide glyphs.
+    func testSearchScrollback() throws {
+        let t = Terminal(cols: 40, rows: 5, scrollback: 10)
+        for i in 0..<30 { feed(t, "line \(i)\r\n") }
+        let literal = try t.searchScrollback("LINE 1", regex: false).get()
+        XCTAssertEqual(literal.firstLine, 16)
+        XCTAssertEqual(literal.matches.map(\.line), [16, 17, 18, 19])
+        XCTAssertEqual(literal.matches.first?.text, "line 16")
+        XCTAssertTrue(try t.searchScrollback("LINE 1", regex: false, caseSensitive: true).get().matches.isEmpty)
+
+        feed(t, "日本 error: disk full\r\n")
+        let regex = try t.searchScrollback(#"err(or)?: \w+"#, regex: true).get()
+        XCTAssertEqual(regex.matches.map(\.column), [5])
+        XCTAssertEqual(regex.matches.map(\.length), [11])
+
+        let capped = try t.searchScrollback("line", regex: false, limit: 3).get()
+        XCTAssertEqual(capped.matches.count, 3)
+        XCTAssertTrue(capped.truncated)
+        if case .success = t.searchScrollback("(", regex: true) { XCTFail("an unbalanced group compiled") }
+    }
+
+    /// Copy mode walks scrollback with the viewport following, selects by
+    /// word and by block, and searches in both directions.
+    func testCopyModeMovesSelectsAndSearches() throws {
+        let t = Terminal(cols: 20, rows: 3, scrollback: 10)
+        feed(t, "alpha beta\r\ngamma delta\r\nepsilon\r\nzeta eta\r\n")
+        XCTAssertEqual(t.copyModeMove(.up), .failure(.inactive))
+        let start = t.copyModeEnter()
+        XCTAssertEqual(start.line, 4)
+        XCTAssertEqual(start.firstLine, 0)
+
+        XCTAssertEqual(try t.copyModeMove(.up, count: 3).get().viewOffset, 1)
+        XCTAssertEqual(try t.copyModeMove(.wordNext).get().column, 6)
+        XCTAssertEqual(try t.copyModeSelect(.word).get().text, "delta")
+
+        _ = t.copyModeSelect(nil)
+        _ = t.copyModeJump(line: 0, column: 6)
+        _ = t.copyModeSelect(.block)
+        _ = t.copyModeMove(.down)
+        let block = try t.copyModeMove(.right, count: 2).get()
+        XCTAssertEqual(block.selection, .block)
+        XCTAssertEqual(block.text, "bet\ndel")
+
+        _ = t.copyModeSelect(nil)
+        let found = try t.copyModeSearch("eta").get()
+        XCTAssertEqual([found.line, found.column], [3, 1])
+        let back = try t.copyModeSearch("eta", backward: true).get()
+        XCTAssertEqual([back.line, back.column], [0, 7])
+        XCTAssertEqual(try t.copyModeMove(.wordPrevious).get().column, 6)
+        XCTAssertEqual(try t.copyModeMove(.lineEnd).get().column, 9)
+        XCTAssertEqual(t.copyModeSearch("nowhere"), .failure(.noMatch))
+
+        t.copyModeExit()
+        XCTAssertNil(t.copyModeStatus)
+        XCTAssertNil(t.selectedText())
+    }
+
+    /// Columns come out of tabular output by field or by cell rectangle,
+    /// from explicit lines or from a block selection.
+    func testScrollbackExtractsBlocksAndFields() throws {
+        let t = Terminal(cols: 30, rows: 4, scrollback: 10)
+        feed(t, "ID    NAME   STATUS\r\nab12  web    up\r\ncd34  db     down\r\n")
+        XCTAssertEqual(t.scrollbackExtract(mode: .block(nil)), .failure(.noSelection))
+        XCTAssertEqual(try t.scrollbackExtract(lines: 1...2, mode: .field(1, separator: nil)).get().values,
+                       ["ab12", "cd34"])
+        let last = try t.scrollbackExtract(lines: 0...3, mode: .field(-1, separator: nil)).get()
+        XCTAssertEqual(last.values, ["STATUS", "up", "down"])
+        XCTAssertEqual([last.firstLine, last.lastLine], [0, 3])
+        XCTAssertEqual(try t.scrollbackExtract(lines: 0...2, mode: .block(6...9)).get().values,
+                       ["NAME", "web", "db"])
+        XCTAssertEqual(t.scrollbackExtract(lines: 100...200, mode: .block(nil)), .failure(.outOfRange))
+        XCTAssertEqual(t.scrollbackExtract(lines: 0...1, mode: .field(0, separator: nil)), .failure(.invalidField))
+
+        _ = t.copyModeEnter()
+        _ = t.copyModeJump(line: 1, column: 6)
+        _ = t.copyModeSelect(.block)
+        _ = t.copyModeMove(.down)
+        _ = t.copyModeMove(.right, count: 2)
+        XCTAssertEqual(try t.scrollbackExtract(mode: .block(nil)).get().text, "web\ndb")
+
+        XCTAssertEqual(ScrollbackExtractMode.field(2, of: "a, b ,c", separator: ","), "b")
+        XCTAssertNil(ScrollbackExtractMode.field(4, of: "a, b ,c", separator: ","))
+    }
+
+    /// Double-click (and `smart-select`) takes the whole token under the
+    /// cell, or the word when there is nothing smarter.
+    func testDoubleClickSelectsSmartTokens() {
+        let t = Terminal(cols: 40, rows: 3, scrollback: 10)
+        feed(t, "see src/main.rs:4 and 0a1b2c3d\r\n")
+        let path = t.smartSelect(line: 0, column: 6)
+        XCTAssertEqual(path, SmartToken(kind: .path, line: 0, start: 4, end: 16, text: "src/main.rs:4"))
+        XCTAssertEqual(t.selectedText(), "src/main.rs:4")
+        t.selectionBegin(viewRow: 0, col: 25, mode: .word)
+        XCTAssertEqual(t.selectedText(), "0a1b2c3d")
+        XCTAssertEqual(t.smartSelect(line: 0, column: 19)?.kind, .word)
+        XCTAssertNil(t.smartSelect(line: 0, column: 3))
+    }
+
+    /// Rows are handed to triggers once the cursor leaves them, never from
+    /// the alternate screen.
+    /// With a spill, lines pushed out of scrollback read back from disk;
+    /// without one they are gone.
+    func testScrollbackLinesReadSpilledHistory() {
+        let t = Terminal(cols: 20, rows: 2, scrollback: 5)
+        feed(t, (0..<30).map { "row \($0)" }.joined(separator: "\r\n"))
+        let kept = t.scrollbackLines(from: 0, count: 100)
+        XCTAssertEqual(kept.firstLine, 23)
+        XCTAssertEqual(kept.lines.first, "row 23")
+
+        let directory = FileManager.default.temporaryDirectory
+            .appendingPathComponent("spill-\(UUID().uuidString)", isDirectory: true)
+        defer { try? FileManager.default.removeItem(at: directory) }
+        t.spillScrollback(to: ScrollbackSpill(directory: directory))
+        feed(t, (30..<60).map { "\r\nrow \($0)" }.joined())
+        let loaded = t.scrollbackLines(from: 0, count: 100)
+        XCTAssertEqual(loaded.firstLine, 23)
+        XCTAssertEqual(loaded.lines, (23..<60).map { "row \($0)" })
+        XCTAssertEqual(t.scrollbackLines(from: 40, count: 2).lines, ["row 40", "row 41"])
+    }
+
+    /// A budget cap pushes the oldest rows out to the spill, where search
+    /// and exports still find them; lifting it lets scrollback grow again.
+    func testCappedScrollbackSpills() throws {
+        let t = Terminal(cols: 20, rows: 2, scrollback: 50)
+        let directory = FileManager.default.temporaryDirectory
+            .appendingPathComponent("spill-\(UUID().uuidString)", isDirectory: true)
+        defer { try? FileManager.default.removeItem(at: directory) }
+        t.spillScrollback(to: ScrollbackSpill(directory: directory))
+        feed(t, (0..<30).map { "row \($0)" }.joined(separator: "\r\n"))
+        XCTAssertEqual(t.scrollbackUsage.lines, 28)
+        XCTAssertEqual(t.scrollbackUsage.bytes, 28 * 20 * MemoryLayout<Cell>.stride)
+
+        t.capScrollback(lines: 10)
+        let usage = t.scrollbackUsage
+        XCTAssertEqual([usage.lines, usage.capacity, usage.spilled], [10, 10, 18])
+        XCTAssertEqual(t.scrollbackLines(from: 0, count: 100).lines, (0..<30).map { "row \($0)" })
+        let search = try t.searchScrollback("row 5", regex: false).get()
+        XCTAssertEqual(search.firstLine, 0)
+        XCTAssertEqual(search.matches.map(\.line), [5])
+        XCTAssertEqual(search.matches.first?.length, 5)
+        let rows = t.historyRows(lines: 100)
+        XCTAssertEqual(rows.count, 30)
+        XCTAssertEqual(rows.first?.first?.glyph, UInt32(UInt8(ascii: "r")))
+
+        t.capScrollback(lines: nil)
+        feed(t, (30..<40).map { "\r\nrow \($0)" }.joined())
+        XCTAssertEqual(t.scrollbackUsage.lines, 20)
+        XCTAssertEqual(t.scrollbackUsage.spilled, 18)
+    }
+
+    func testFinishedRowsAndHighlight() {
+        let t = Terminal(cols: 20, rows: 4, scrollback: 10)
+        XCTAssertEqual(t.finishedRows(after: nil).next, 0)
+        feed(t, "one\r\ntwo\r\nthr")
+        let (rows, next) = t.finishedRows(after: 0)
+        XCTAssertEqual(rows.map { $0.line }, [0, 1])
+        XCTAssertEqual(String(rows[1].chars).trimmingCharacters(in: .whitespaces), "two")
+        XCTAssertEqual(next, 2)
+        feed(t, "ee\r\n")
+        XCTAssertEqual(t.finishedRows(after: next).rows.map { $0.line }, [2])
+        feed(t, "\u{1B}[?1049h")
+        XCTAssertNil(t.finishedRows(after: 3).next)
+        feed(t, "\u{1B}[?1049l")
+
+        t.highlight(line: 1, lo: 0, hi: 1)
+        XCTAssertEqual(cell(t, 1, 1).bg, ColorCode.indexed(11))
+        XCTAssertEqual(cell(t, 2, 1).bg, ColorCode.defaultBG)
+    }
+
     func testAltScreenRoundTrip() {
         let t = makeTerminal()
         feed(t, "main\r\n")
@@ -200,4 +370,147 @@ final class TerminalTests: XCTestCase {
         t.userDidInput()
         XCTAssertNil(t.selectedText())
     }
+
+    // MARK: OSC 7 working directory
+
+    /// The shell's cwd report is percent-decoded and delivered once per
+    /// batch; reports from another host (ssh) are ignored.
+    func testOSC7ReportsLocalDirectory() {
+        let t = makeTerminal()
+        var reported: [String] = []
+        t.onDirectory = { reported.append($0) }
+        feed(t, "\u{1B}]7;file://localhost/Users/me/My%20Project\u{07}")
+        feed(t, "\u{1B}]7;file://build-box.example.com/srv/app\u{1B}\\")
+        XCTAssertEqual(reported, ["/Users/me/My Project"])
+    }
+
+    /// OSC 2 sets only the title, OSC 1 only the icon name, OSC 0 both.
+    func testOSCTitleAndIconName() {
+        let t = makeTerminal()
+        var titles: [String] = []
+        var icons: [String] = []
+        t.onTitle = { titles.append($0) }
+        t.onIconName = { icons.append($0) }
+        feed(t, "\u{1B}]2;vim notes.md\u{07}")
+        feed(t, "\u{1B}]1;vim\u{07}")
+        feed(t, "\u{1B}]0;~/src\u{1B}\\")
+        XCTAssertEqual(titles, ["vim notes.md", "~/src"])
+        XCTAssertEqual(icons, ["vim", "~/src"])
+    }
+
+    // MARK: OSC 8 hyperlinks
+
+    /// A link covers the cells printed while it's open, survives SGR 0,
+    /// and an empty URI closes it; the same URI and id reuse one number.
+    func testOSC8HyperlinkRanges() throws {
+        let t = makeTerminal()
+        let open = "\u{1B}]8;id=a1;https://example.com/docs?a=1;b"
+        feed(t, "see \(open)\u{1B}\\the \u{1B}[1mdocs\u{1B}[0m\u{1B}]8;;\u{1B}\\.")
+        feed(t, "\r\n\u{1B}]8;;file:///tmp/x.txt\u{07}x\u{1B}]8;;\u{07} \(open)\u{07}again\u{1B}]8;;\u{07}")
+
+        let first = try XCTUnwrap(t.hyperlink(viewRow: 0, col: 6))
+        XCTAssertEqual(first.link, TerminalHyperlink(url: "https://example.com/docs?a=1;b", id: "a1"))
+        XCTAssertEqual(first.lo, 4)
+        XCTAssertEqual(first.hi, 11)
+        XCTAssertNil(t.hyperlink(viewRow: 0, col: 12))
+        XCTAssertNil(t.hyperlink(viewRow: 0, col: 0))
+        XCTAssertEqual(cell(t, 4, 0).link, cell(t, 2, 1).link)
+
+        let docs = first.link
+        XCTAssertEqual(t.hyperlinkRanges(10), [
+            HyperlinkRange(line: 0, start: 4, end: 11, link: docs),
+            HyperlinkRange(line: 1, start: 0, end: 0, link: TerminalHyperlink(url: "file:///tmp/x.txt", id: nil)),
+            HyperlinkRange(line: 1, start: 2, end: 6, link: docs),
+        ])
+    }
+
+    func testDirectoryTitleName() {
+        XCTAssertEqual(TerminalSession.titleName(forDirectory: "/Users/me/src/api/", home: "/Users/me"), "api")
+        XCTAssertEqual(TerminalSession.titleName(forDirectory: "/Users/me", home: "/Users/me"), "~")
+        XCTAssertEqual(TerminalSession.titleName(forDirectory: "/", home: "/Users/me"), "/")
+        XCTAssertNil(TerminalSession.titleName(forDirectory: nil))
+    }
+
+    /// C and D markers become timed command events; the command line is
+    /// what followed the B, or OSC 633 E's exact text, and a D with no C
+    /// (an empty prompt) is not a command.
+    func testShellIntegrationCommandEvents() throws {
+        let t = makeTerminal()
+        var events: [CommandEvent] = []
+        t.onCommand = { events.append($0) }
+        feed(t, "\u{1B}]133;A\u{07}$ \u{1B}]133;B\u{07}make test\r\n\u{1B}]133;C\u{07}ok\r\n")
+        feed(t, "\u{1B}]133;D;2;aid=7\u{07}")
+        feed(t, "\u{1B}]133;A\u{07}$ \u{1B}]133;B\u{07}\r\n\u{1B}]133;D;0\u{07}")
+        feed(t, "\u{1B}]633;A\u{07}$ \u{1B}]633;B\u{07}echo a;b\r\n")
+        feed(t, "\u{1B}]633;E;echo a\\x3bb\u{07}\u{1B}]633;C\u{07}\u{1B}]633;D;0\u{07}")
+
+        XCTAssertEqual(events.count, 4)
+        XCTAssertEqual(events[0], .started(command: "make test", line: 1))
+        guard case .finished(let command, let exitCode, let duration, let line) = events[1] else {
+            return XCTFail("expected a finished event, got \(events[1])")
+        }
+        XCTAssertEqual(command, "make test")
+        XCTAssertEqual(exitCode, 2)
+        XCTAssertEqual(line, 1)
+        XCTAssertGreaterThanOrEqual(duration, 0)
+        XCTAssertEqual(events[2], .started(command: "echo a;b", line: 4))
+        XCTAssertEqual(t.finishedCommands().map(\.command), ["make test", "echo a;b"])
+        XCTAssertEqual(t.finishedCommands().map(\.lineCount), [1, 0])
+        XCTAssertEqual(t.commandOutputBlocks().map(\.line), [1])
+        XCTAssertFalse(t.isRunningCommand)
+        XCTAssertEqual(Terminal.unescapeOSC633(Array("a\\\\b\\x0a".utf8)), "a\\b\n")
+    }
+
+    /// A folded command's scrolled-off output shows as one summary row;
+    /// unfolding keeps the same line at the top of the viewport.
+    func testFoldingCommandOutput() throws {
+        let t = Terminal(cols: 30, rows: 4, scrollback: 100)
+        func viewText() -> [String] {
+            var snap = TermSnapshot()
+            t.copySnapshot(into: &snap)
+            return (0..<snap.rows).map { r in
+                String(String.UnicodeScalarView((0..<snap.cols).compactMap { c in
+                    let glyph = snap.cells[r * snap.cols + c].glyph
+                    return Unicode.Scalar(glyph == 0 ? 32 : glyph)
+                })).trimmingCharacters(in: .whitespaces)
+            }
+        }
+        feed(t, "\u{1B}]133;A\u{07}$ \u{1B}]133;B\u{07}seq 1 20\r\n\u{1B}]133;C\u{07}")
+        feed(t, (1...20).map { "\($0)\r\n" }.joined())
+        feed(t, "\u{1B}]133;D;0\u{07}$ ")
+        let blocks = t.commandOutputBlocks()
+        XCTAssertEqual(blocks.map(\.line), [1])
+        XCTAssertEqual(blocks.first?.lineCount, 20)
+        XCTAssertEqual(t.foldCommand(line: 5, folded: true), .failure(.notFound(5)))
+
+        let folded = try t.foldCommand(line: nil, folded: nil).get()
+        XCTAssertTrue(folded.folded)
+        t.scrollViewport(by: 100)
+        XCTAssertEqual(viewText(), ["$ seq 1 20", "▸ seq 1 20 — 20 lines folded", "18", "19"])
+
+        XCTAssertFalse(try t.foldCommand(line: 1, folded: false).get().folded)
+        XCTAssertEqual(viewText(), ["$ seq 1 20", "1", "2", "3"])
+    }
+
+    func testCommandOutputByLine() throws {
+        let t = Terminal(cols: 30, rows: 4, scrollback: 100)
+        feed(t, "\u{1B}]133;A\u{07}$ \u{1B}]133;B\u{07}seq 1 3\r\n\u{1B}]133;C\u{07}1\r\n2\r\n3\r\n")
+        feed(t, "\u{1B}]133;D;0\u{07}\u{1B}]133;A\u{07}$ \u{1B}]133;B\u{07}seq 2 4\r\n\u{1B}]133;C\u{07}")
+        feed(t, "2\r\n3\r\n4\r\n\u{1B}]133;D;0\u{07}$ ")
+        let blocks = t.commandOutputBlocks()
+        XCTAssertEqual(blocks.count, 2)
+        XCTAssertEqual(try t.commandOutput(line: blocks[0].line).get().lines, ["1", "2", "3"])
+        let latest = try t.commandOutput(line: nil).get()
+        XCTAssertEqual(latest.block, blocks[1])
+        XCTAssertEqual(latest.lines, ["2", "3", "4"])
+        XCTAssertThrowsError(try t.commandOutput(line: 3).get())
+    }
+
+    func testOSC7Parsing() {
+        let hosts: Set<String> = ["localhost", "mac"]
+        XCTAssertEqual(Terminal.directory(fromOSC7: "file:///tmp/x", localHosts: hosts), "/tmp/x")
+        XCTAssertEqual(Terminal.directory(fromOSC7: "file://MAC/tmp", localHosts: hosts), "/tmp")
+        XCTAssertNil(Terminal.directory(fromOSC7: "file://other/tmp", localHosts: hosts))
+        XCTAssertNil(Terminal.directory(fromOSC7: "https://mac/tmp", localHosts: hosts))
+    }
 }
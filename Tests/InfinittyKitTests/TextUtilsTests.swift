@@ -0,0 +1,79 @@
+import XCTest
+@testable import InfinittyKit
+
+final class TextUtilsTests: XCTestCase {
+
+    private let token = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9."
+        + "eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiaWF0IjoxNTE2MjM5MDIyfQ."
+        + "SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c"
+
+    /// URL-safe alphabet, missing padding and wrapped lines all decode.
+    func testBase64() {
+        XCTAssertEqual(TextUtils.apply(.base64Encode, to: "hello"), .success("aGVsbG8="))
+        XCTAssertEqual(TextUtils.apply(.base64Decode, to: "aGVs\nbG8"), .success("hello"))
+        XCTAssertEqual(TextUtils.base64Decode("-_8gaGk_"), Data([0xFB, 0xFF, 0x20, 0x68, 0x69, 0x3F]))
+        XCTAssertEqual(
+            TextUtils.apply(.base64Decode, to: "-_8gaGk_"),
+            .failure(.invalidInput("decodes to 6 bytes of binary data")))
+    }
+
+    func testURLEncoding() {
+        XCTAssertEqual(TextUtils.apply(.urlEncode, to: "a b&c=d/é~"), .success("a%20b%26c%3Dd%2F%C3%A9~"))
+        XCTAssertEqual(TextUtils.apply(.urlDecode, to: "a+b%26c%3Dd"), .success("a b&c=d"))
+        XCTAssertEqual(TextUtils.apply(.urlDecode, to: "100%"), .failure(.invalidInput("malformed percent-encoding")))
+    }
+
+    func testHashes() {
+        XCTAssertEqual(TextUtils.apply(.md5, to: "abc"), .success("900150983cd24fb0d6963f7d28e17f72"))
+        XCTAssertEqual(TextUtils.apply(.sha1, to: "abc"), .success("a9993e364706816aba3e25717850c26c9cd0d89d"))
+        XCTAssertEqual(
+            TextUtils.apply(.sha256, to: "abc"),
+            .success("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"))
+    }
+
+    /// Published vectors (input bytes `i % 251`) across the chunk and tree
+    /// boundaries, fed whole and in odd-sized pieces.
+    func testBLAKE3() {
+        XCTAssertEqual(
+            TextUtils.hex(BLAKE3.hash(data: [UInt8]())),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262")
+        XCTAssertEqual(
+            TextUtils.apply(.blake3, to: "abc"),
+            .success("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"))
+        let expected = [
+            1024: "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+            1025: "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+            3073: "7124b49501012f81cc7f11ca069ec9226cecb8a2c850cfe644e327d22d3e1cd3",
+        ]
+        for (length, digest) in expected {
+            let input = (0..<length).map { UInt8($0 % 251) }
+            XCTAssertEqual(TextUtils.hex(BLAKE3.hash(data: input)), digest, "length \(length)")
+            var hasher = BLAKE3()
+            stride(from: 0, to: length, by: 100).forEach { hasher.update(data: input[$0..<min($0 + 100, length)]) }
+            XCTAssertEqual(TextUtils.hex(hasher.finalize()), digest, "length \(length) in pieces")
+        }
+    }
+
+    func testHashFile() throws {
+        let url = FileManager.default.temporaryDirectory.appendingPathComponent("hash-\(UUID().uuidString)")
+        try Data("abc".utf8).write(to: url)
+        defer { try? FileManager.default.removeItem(at: url) }
+        XCTAssertEqual(
+            TextUtils.hashFile(url, algorithm: .sha256, chunkSize: 2),
+            .success("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"))
+    }
+
+    func testDecodeJWT() throws {
+        let jwt = try TextUtils.decodeJWT("Bearer " + token).get()
+        XCTAssertEqual(jwt.algorithm, "HS256")
+        XCTAssertEqual(jwt.payload["name"] as? String, "John Doe")
+        XCTAssertNil(jwt.wire(now: Date())["expired"])
+        guard case .failure(let error) = TextUtils.decodeJWT("not.a-token") else { return XCTFail("decoded") }
+        XCTAssertEqual(error, .invalidInput("a JWT has three dot-separated parts, not 2"))
+    }
+
+    func testVerifyJWT() {
+        XCTAssertEqual(TextUtils.verifyJWT(token, key: "your-256-bit-secret"), .success(true))
+        XCTAssertEqual(TextUtils.verifyJWT(token, key: "wrong"), .success(false))
+    }
+}
@@ -0,0 +1,96 @@
+import XCTest
+@testable import InfinittyKit
+
+final class TimeTrackerTests: XCTestCase {
+    private var url: URL!
+    private var calendar = Calendar(identifier: .gregorian)
+
+    override func setUp() {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("time-\(UUID().uuidString).json")
+        calendar.timeZone = TimeZone(identifier: "UTC")!
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+    }
+
+    private func date(_ day: Int, _ hour: Int, _ minute: Int = 0) -> Date {
+        calendar.date(from: DateComponents(year: 2026, month: 10, day: day, hour: hour, minute: minute))!
+    }
+
+    /// Consecutive ticks extend one span; a switch backdates the new span to
+    /// the previous tick; a background tick breaks the chain.
+    func testTicksBecomeSpans() {
+        let tracker = TimeTracker(url: url)
+        let start = date(15, 9)
+        tracker.record(workspace: "/src/a", idle: 0, now: start)
+        tracker.record(workspace: "/src/a", idle: 0, now: start + 30)
+        tracker.record(workspace: "/src/b", idle: 0, now: start + 60)
+        tracker.record(workspace: nil, idle: 0, now: start + 90)
+        tracker.record(workspace: "/src/b", idle: 0, now: start + 600)
+
+        let expected = [
+            TimeSpan(workspace: "/src/a", start: start, end: start + 30),
+            TimeSpan(workspace: "/src/b", start: start + 30, end: start + 60),
+            TimeSpan(workspace: "/src/b", start: start + 600, end: start + 600),
+        ]
+        XCTAssertEqual(tracker.spans(), expected)
+        XCTAssertEqual(TimeTracker(url: url).spans(), expected)
+    }
+
+    func testIdleStopsTheClockUnlessACommandRuns() {
+        let tracker = TimeTracker(url: url)
+        tracker.idleThreshold = 300
+        let start = date(15, 9)
+        tracker.record(workspace: "/src/a", idle: 0, now: start)
+        tracker.record(workspace: "/src/a", idle: 400, now: start + 30)
+        XCTAssertEqual(tracker.spans().last?.end, start)
+
+        tracker.noteCommand(in: "/src/a", at: start + 40)
+        tracker.record(workspace: "/src/a", idle: 450, now: start + 60)
+        tracker.record(workspace: "/src/a", idle: 480, now: start + 90)
+        XCTAssertEqual(tracker.spans().last, TimeSpan(workspace: "/src/a", start: start + 60, end: start + 90))
+
+        tracker.idleThreshold = 0
+        tracker.record(workspace: "/src/a", idle: 10_000, now: start + 120)
+        XCTAssertEqual(tracker.spans().last?.end, start + 120)
+    }
+
+    func testRanges() {
+        let now = date(15, 12)
+        func range(_ text: String) -> DateInterval? {
+            TimeTracker.interval(for: text, now: now, calendar: calendar)
+        }
+        XCTAssertEqual(range("today"), DateInterval(start: date(15, 0), end: date(16, 0)))
+        XCTAssertEqual(range("yesterday"), DateInterval(start: date(14, 0), end: date(15, 0)))
+        XCTAssertEqual(range("7d"), DateInterval(start: date(9, 0), end: date(16, 0)))
+        XCTAssertEqual(range("2026-10-01..2026-10-03"), DateInterval(start: date(1, 0), end: date(4, 0)))
+        XCTAssertEqual(range("last-month")?.start, calendar.date(from: DateComponents(year: 2026, month: 9, day: 1)))
+        XCTAssertEqual(range("last-month")?.end, date(1, 0))
+        XCTAssertNil(range("2026-10-03..2026-10-01"))
+        XCTAssertNil(range("fortnight"))
+    }
+
+    /// Spans are clipped to the range and split at midnight.
+    func testReportSplitsByDay() throws {
+        let spans = [
+            TimeSpan(workspace: "/src/a", start: date(14, 23, 30), end: date(15, 0, 30)),
+            TimeSpan(workspace: "/src/b", start: date(15, 10), end: date(15, 10, 10)),
+        ]
+        let interval = try XCTUnwrap(
+            TimeTracker.interval(for: "2026-10-14..2026-10-15", now: date(15, 12), calendar: calendar))
+        let report = TimeTracker.summarize(spans, in: interval, calendar: calendar)
+        XCTAssertEqual(report["from"] as? String, "2026-10-14")
+        XCTAssertEqual(report["to"] as? String, "2026-10-15")
+        XCTAssertEqual(report["total"] as? Int, 4200)
+        let workspaces = try XCTUnwrap(report["workspaces"] as? [[String: Any]])
+        XCTAssertEqual(workspaces.map { $0["workspace"] as? String }, ["/src/a", "/src/b"])
+        XCTAssertEqual(workspaces[0]["hours"] as? Double, 1)
+        XCTAssertEqual(workspaces[0]["days"] as? [String: Int], ["2026-10-14": 1800, "2026-10-15": 1800])
+
+        let today = try XCTUnwrap(TimeTracker.interval(for: "today", now: date(15, 12), calendar: calendar))
+        XCTAssertEqual(TimeTracker.summarize(spans, in: today, calendar: calendar)["total"] as? Int, 2400)
+        XCTAssertEqual(
+            TimeTracker(url: url).report(range: "soon").map { $0.count }, .failure(.invalidRange("soon")))
+    }
+}
@@ -0,0 +1,58 @@
+import XCTest
+@testable import InfinittyKit
+
+final class TmuxControlTests: XCTestCase {
+    /// Reply bodies are taken whole even when a line looks like a
+    /// notification, and output is unescaped back to raw bytes.
+    func testStreamSplitsRepliesAndNotifications() {
+        var stream = TmuxControlStream()
+        XCTAssertEqual(stream.feed(Data("%begin 1700000000 12 1\n%1 80x24\n%2 80x2".utf8)), [])
+        XCTAssertEqual(
+            stream.feed(Data("4\n%end 1700000000 12 1\n".utf8)),
+            [.reply(lines: ["%1 80x24", "%2 80x24"], failed: false)])
+
+        let messages = stream.feed(Data("""
+            %output %3 ls\\015\\012caf\u{e9} \\134o/
+            %begin 1700000001 13 1
+            unknown command: nope
+            %error 1700000001 13 1
+            %window-add @4
+            %layout-change @4 b25d,80x24,0,0,3 b25d,80x24,0,0,3 *
+            %window-renamed @4 build logs
+            %session-changed $1 work
+            %pane-mode-changed %3
+            %exit server exited
+
+            """.utf8))
+        XCTAssertEqual(messages, [
+            .output(pane: 3, bytes: Array("ls\r\ncafé \\o/".utf8)),
+            .reply(lines: ["unknown command: nope"], failed: true),
+            .windowAdd(window: 4),
+            .layoutChange(window: 4, layout: "b25d,80x24,0,0,3"),
+            .windowRenamed(window: 4, name: "build logs"),
+            .sessionChanged(session: 1, name: "work"),
+            .other("%pane-mode-changed %3"),
+            .exit(reason: "server exited"),
+        ])
+    }
+
+    func testLayoutParsing() throws {
+        let text = "b25d,160x48,0,0{80x48,0,0,1,79x48,81,0[79x24,81,0,2,79x23,81,25,5]}"
+        let layout = try XCTUnwrap(TmuxLayout.parse(text))
+        XCTAssertEqual(layout.panes, [1, 2, 5])
+        XCTAssertEqual(layout.width, 160)
+        XCTAssertEqual(layout.splitsSideBySide(around: 1), true)
+        XCTAssertEqual(layout.splitsSideBySide(around: 5), false)
+        XCTAssertNil(layout.splitsSideBySide(around: 9))
+
+        XCTAssertEqual(TmuxLayout.parse("8x24,0,0,7"), .pane(7, width: 8, height: 24))
+        XCTAssertNil(TmuxLayout.parse("b25d,80x24,0,0{40x24,0,0,1"))
+        XCTAssertNil(TmuxLayout.parse("nonsense"))
+    }
+
+    func testServerArguments() {
+        XCTAssertEqual(TmuxControlClient.serverArguments(socket: nil), [])
+        XCTAssertEqual(TmuxControlClient.serverArguments(socket: "work"), ["-L", "work"])
+        XCTAssertEqual(TmuxControlClient.serverArguments(socket: "/tmp/tmux.sock"), ["-S", "/tmp/tmux.sock"])
+    }
+}
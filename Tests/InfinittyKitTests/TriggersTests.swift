@@ -0,0 +1,43 @@
+import XCTest
+@testable import InfinittyKit
+
+final class TriggersTests: XCTestCase {
+
+    func testMatchesRowsByColumnWithGroups() throws {
+        let engine = TriggerEngine()
+        let listening = try engine.add(pattern: #"listening on :(\d+)"#, action: .event).get()
+        let errors = try engine.add(pattern: "ERROR", action: .highlight, pane: 2).get()
+        XCTAssertEqual([listening.id, errors.id], ["trigger-1", "trigger-2"])
+
+        let matches = engine.matches(pane: 1, line: 7, chars: Array("  listening on :8080   "))
+        XCTAssertEqual(matches.count, 1)
+        XCTAssertEqual([matches[0].line, matches[0].start, matches[0].end], [7, 2, 19])
+        XCTAssertEqual(matches[0].text, "listening on :8080")
+        XCTAssertEqual(matches[0].groups, ["8080"])
+
+        XCTAssertTrue(engine.matches(pane: 1, line: 8, chars: Array("ERROR")).isEmpty)
+        XCTAssertEqual(engine.matches(pane: 2, line: 9, chars: Array("ERROR x ERROR")).map(\.start), [0, 8])
+        // A character outside the BMP is still one cell.
+        XCTAssertEqual(engine.matches(pane: 2, line: 10, chars: Array("🚀 ERROR")).map(\.start), [2])
+        XCTAssertEqual(engine.triggers.map(\.fired), [1, 3])
+        XCTAssertEqual(engine.triggers(for: 1).map(\.id), ["trigger-1"])
+    }
+
+    /// Pane triggers go with their pane; bad patterns and actions are
+    /// refused up front.
+    func testManagingTriggers() {
+        let engine = TriggerEngine()
+        _ = engine.add(pattern: "a", action: .notify, pane: 3)
+        _ = engine.add(pattern: "b", action: .event)
+        engine.forget(pane: 3)
+        XCTAssertEqual(engine.triggers.map(\.pattern), ["b"])
+        XCTAssertTrue(engine.remove("trigger-2"))
+        XCTAssertFalse(engine.remove("trigger-2"))
+
+        XCTAssertEqual(engine.add(pattern: "", action: .event), .failure(.invalidPattern("empty pattern")))
+        if case .success = engine.add(pattern: "(", action: .event) { XCTFail("unbalanced group accepted") }
+        XCTAssertEqual(TriggerAction(name: "run-command", command: "say hi"), .run("say hi"))
+        XCTAssertNil(TriggerAction(name: "run", command: nil))
+        XCTAssertNil(TriggerAction(name: "beep", command: nil))
+    }
+}
@@ -0,0 +1,63 @@
+import XCTest
+@testable import InfinittyKit
+
+final class WorkspaceNotesTests: XCTestCase {
+    private var url: URL!
+    private var root: URL!
+
+    override func setUpWithError() throws {
+        url = FileManager.default.temporaryDirectory.appendingPathComponent("notes-\(UUID().uuidString).json")
+        root = FileManager.default.temporaryDirectory.appendingPathComponent("notes-root-\(UUID().uuidString)")
+        try FileManager.default.createDirectory(at: root, withIntermediateDirectories: true)
+    }
+
+    override func tearDown() {
+        try? FileManager.default.removeItem(at: url)
+        try? FileManager.default.removeItem(at: root)
+    }
+
+    /// Todos get short per-workspace ids, survive a reload, and a workspace
+    /// with nothing left in it is dropped from the file.
+    func testTodosAndNotesPersistPerWorkspace() throws {
+        let store = WorkspaceNotes(url: url)
+        XCTAssertEqual(try store.addTodo("  ship it ", to: "/src/a").get().id, "1")
+        XCTAssertEqual(try store.addTodo("write docs", to: "/src/a").get().id, "2")
+        XCTAssertEqual(try store.addTodo("other", to: "/src/b").get().id, "1")
+        XCTAssertEqual(store.addTodo("   ", to: "/src/a"), .failure(.emptyText))
+        store.setNotes("staging is flaky", for: "/src/a")
+
+        XCTAssertEqual(try store.toggleTodo("1", in: "/src/a").get().done, true)
+        XCTAssertEqual(try store.toggleTodo("1", in: "/src/a", done: true).get().done, true)
+        XCTAssertEqual(store.toggleTodo("9", in: "/src/a"), .failure(.noSuchTodo("9")))
+
+        let reloaded = WorkspaceNotes(url: url)
+        XCTAssertEqual(reloaded.notes(for: "/src/a"), "staging is flaky")
+        XCTAssertEqual(reloaded.todos(for: "/src/a").map(\.text), ["ship it", "write docs"])
+        XCTAssertEqual(reloaded.todos(for: "/src/a").map(\.done), [true, false])
+
+        _ = reloaded.removeTodo("1", in: "/src/b")
+        let saved = try XCTUnwrap(JSONSerialization.jsonObject(with: Data(contentsOf: url)) as? [String: Any])
+        XCTAssertEqual(saved.keys.sorted(), ["/src/a"])
+    }
+
+    func testParseComment() {
+        XCTAssertEqual(WorkspaceNotes.parseComment("// TODO: retry on 503")?.text, "retry on 503")
+        XCTAssertEqual(WorkspaceNotes.parseComment("/* FIXME(ana) leaks the fd */")?.tag, "FIXME")
+        XCTAssertEqual(WorkspaceNotes.parseComment("/* FIXME(ana) leaks the fd */")?.text, "leaks the fd")
+        XCTAssertEqual(WorkspaceNotes.parseComment("<!-- TODO translate -->")?.text, "translate")
+        XCTAssertNil(WorkspaceNotes.parseComment("let todoCount = TODOS.count"))
+    }
+
+    func testScanCommentsFindsTagsInFiles() throws {
+        try "fn main() {}\n// TODO: handle SIGTERM\n".write(
+            to: root.appendingPathComponent("main.rs"), atomically: true, encoding: .utf8)
+        try "# FIXME pin the version\n".write(
+            to: root.appendingPathComponent("setup.sh"), atomically: true, encoding: .utf8)
+
+        let comments = WorkspaceNotes.scanComments(in: root.path).sorted { $0.path < $1.path }
+        XCTAssertEqual(comments, [
+            CodeComment(path: "main.rs", line: 2, tag: "TODO", text: "handle SIGTERM"),
+            CodeComment(path: "setup.sh", line: 1, tag: "FIXME", text: "pin the version"),
+        ])
+    }
+}
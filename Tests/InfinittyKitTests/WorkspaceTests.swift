@@ -0,0 +1,66 @@
+import XCTest
+@testable import InfinittyKit
+
+final class WorkspaceTests: XCTestCase {
+    private let columns = LayoutShape.split(vertical: true, children: [.pane, .pane], sizes: [0.5, 0.5])
+
+    /// Tabs whose shape and panes disagree are dropped, and indexes that
+    /// pointed at them are cleared rather than landing on another tab.
+    func testRestorable() {
+        let good = WorkspaceTab(
+            shape: columns, panes: [WorkspacePane(cwd: "/src"), WorkspacePane(cwd: "/tmp", profile: "fish")],
+            focused: 5, browsers: [
+                WorkspaceBrowser(id: "browser-a", url: "https://a.example", beside: 1, vertical: true),
+                WorkspaceBrowser(id: "browser-b", url: "https://b.example", beside: 2, vertical: false),
+            ])
+        let bad = WorkspaceTab(shape: columns, panes: [WorkspacePane(cwd: "/src")])
+        let layout = WorkspaceLayout(windows: [
+            WorkspaceWindow(frame: [0, 0, 800, 600], tabs: [bad, good], selectedTab: 1),
+            WorkspaceWindow(tabs: [bad]),
+            WorkspaceWindow(tabs: [good, good], selectedTab: 1),
+        ])
+        let restorable = layout.restorable
+        XCTAssertEqual(restorable.windows.count, 2)
+        XCTAssertEqual(restorable.windows[0].tabs.map(\.panes.count), [2])
+        XCTAssertNil(restorable.windows[0].tabs[0].focused)
+        XCTAssertEqual(restorable.windows[0].tabs[0].browsers?.map(\.id), ["browser-a"])
+        XCTAssertNil(restorable.windows[0].selectedTab)
+        XCTAssertEqual(restorable.windows[0].frame, [0, 0, 800, 600])
+        XCTAssertEqual(restorable.windows[1].selectedTab, 1)
+        XCTAssertEqual(layout.paneCount, 8)
+    }
+
+    func testStoreRoundTrip() throws {
+        let url = FileManager.default.temporaryDirectory
+            .appendingPathComponent(UUID().uuidString).appendingPathComponent("workspace.json")
+        defer { try? FileManager.default.removeItem(at: url.deletingLastPathComponent()) }
+        let store = WorkspaceStore(url: url)
+        XCTAssertNil(store.load())
+        let layout = WorkspaceLayout(windows: [
+            WorkspaceWindow(
+                frame: [10, 20, 900, 700],
+                tabs: [
+                    WorkspaceTab(shape: .pane, panes: [WorkspacePane(cwd: "/src", profile: "work")]),
+                    WorkspaceTab(shape: columns, panes: [
+                        WorkspacePane(), WorkspacePane(cwd: "/tmp", display: PaneDisplay(fontSize: 18)),
+                    ], focused: 1, browsers: [
+                        WorkspaceBrowser(
+                            id: "browser-a", url: "https://a.example", beside: 0, vertical: true,
+                            leading: true, share: 0.4),
+                    ]),
+                ],
+                selectedTab: 1),
+        ])
+        XCTAssertTrue(store.save(layout))
+        XCTAssertEqual(WorkspaceStore(url: url).load(), layout)
+        let json = try XCTUnwrap(String(data: Data(contentsOf: url), encoding: .utf8))
+        XCTAssertTrue(json.contains("\"columns\""))
+        // Nor did tabs have browsers.
+        XCTAssertNil(try JSONDecoder().decode(
+            WorkspaceTab.self, from: Data(#"{"shape":"pane","panes":[{}]}"#.utf8)).browsers)
+        // A file saved before panes had their own sizes still loads.
+        XCTAssertEqual(
+            try JSONDecoder().decode(WorkspacePane.self, from: Data(#"{"cwd":"/src"}"#.utf8)),
+            WorkspacePane(cwd: "/src"))
+    }
+}
@@ -13,6 +13,7 @@ mkdir -p "$APP/Contents/MacOS" "$APP/Contents/Resources"
 
 cp "$BIN_DIR/infinitty" "$APP/Contents/MacOS/infinitty"
 cp "$BIN_DIR/infinitty-mcp" "$APP/Contents/MacOS/infinitty-mcp"
+cp "$BIN_DIR/infinitty-ptyd" "$APP/Contents/MacOS/infinitty-ptyd"
 cp assets/AppIcon.icns "$APP/Contents/Resources/"
 cp -R shell-integration "$APP/Contents/Resources/"
 cp -R Sources/InfinittyKit/Resources/Pets "$APP/Contents/Resources/"
@@ -36,6 +37,8 @@ cat > "$APP/Contents/Info.plist" <<PLIST
     <key>LSMinimumSystemVersion</key><string>14.0</string>
     <key>NSHighResolutionCapable</key><true/>
     <key>NSHumanReadableCopyright</key><string>© Jason Kneen</string>
+    <key>NSMicrophoneUsageDescription</key><string>Dictate prompts to the assistant.</string>
+    <key>NSSpeechRecognitionUsageDescription</key><string>Turns dictated prompts into text.</string>
     <key>CFBundleDocumentTypes</key>
     <array>
         <dict>